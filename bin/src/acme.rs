@@ -0,0 +1,562 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Obtaining and renewing the TLS certificates with ACME, [RFC 8555](https://www.rfc-editor.org/rfc/rfc8555)
+//!
+//! A certificate is obtained for the `endpoint_name` of each TLS certificate when it is missing
+//!  or about to expire, and written to its files, after which the listeners are reloaded. Until
+//!  the first certificate is obtained, a short-lived self-signed certificate is served.
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+
+use hickory_acme::{challenge_name, Dns01Challenge, Dns01Solver, InProcessAuthority};
+use hickory_server::{
+    authority::AuthorityObject,
+    config::{
+        acme::{AcmeChallenge, AcmeConfig},
+        dnssec::CertType,
+        Config,
+    },
+    proto::{
+        rr::{LowerName, Name},
+        rustls::tls_server::read_cert,
+    },
+    server::SniCertResolver,
+    store::in_memory::InMemoryAuthority,
+};
+use instant_acme::{
+    Account, AccountCredentials, AuthorizationStatus, ChallengeType, Identifier, NewAccount,
+    NewOrder, Order, OrderStatus,
+};
+use rcgen::{CertificateParams, CustomExtension, DistinguishedName};
+use rustls::{Certificate, PrivateKey};
+use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time};
+use tracing::{info, warn};
+
+/// The interval at which the expiration of the certificates is checked
+const RENEW_INTERVAL: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// The time after which failed renewals are retried
+const RETRY_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// The interval at which pending orders are polled
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The number of polls after which a pending order is given up
+const MAX_POLLS: u32 = 60;
+
+/// The TTL of the challenge zones and records
+const CHALLENGE_TTL: u32 = 60;
+
+/// A certificate obtained with ACME
+struct AcmeCertificate {
+    domain: String,
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    /// publishes the DNS-01 challenges in a zone served by this server
+    dns01: Option<(Arc<InMemoryAuthority>, Dns01Solver<InProcessAuthority>)>,
+}
+
+/// Obtains and renews the TLS certificates of the configuration with an ACME account
+pub(crate) struct AcmeCertificates {
+    directory_url: String,
+    contact: Vec<String>,
+    account_path: PathBuf,
+    challenge: AcmeChallenge,
+    renew_before: Duration,
+    certificates: Vec<AcmeCertificate>,
+}
+
+impl AcmeCertificates {
+    /// Creates the certificates from the `[acme]` section and the TLS certificates of the
+    ///  configuration, relative paths are relative to `zone_dir`
+    pub(crate) fn try_from_config(
+        acme_config: &AcmeConfig,
+        config: &Config,
+        zone_dir: &Path,
+    ) -> Result<Self, String> {
+        let challenge = acme_config.get_challenge();
+        let mut certificates = Vec::new();
+
+        for tls_cert_config in config
+            .get_tls_cert()
+            .into_iter()
+            .chain(config.get_tls_certs())
+        {
+            let path = tls_cert_config.get_path();
+            let domain = tls_cert_config
+                .get_endpoint_name()
+                .ok_or_else(|| format!("the tls certificate {path:?} has no endpoint_name"))?;
+            let key_path = tls_cert_config
+                .get_private_key()
+                .ok_or_else(|| format!("the tls certificate {path:?} has no private_key"))?;
+            if tls_cert_config.get_cert_type() != CertType::Pem {
+                return Err(format!("the tls certificate {path:?} is not in PEM format"));
+            }
+
+            let dns01 = match challenge {
+                AcmeChallenge::Dns01 => {
+                    let zone = Arc::new(challenge_zone(domain)?);
+                    let mut solver = Dns01Solver::new(
+                        InProcessAuthority::new(Arc::clone(&zone)),
+                        // the records are served by this process as soon as they are published
+                        Vec::new(),
+                    );
+                    solver.set_ttl(CHALLENGE_TTL);
+                    Some((zone, solver))
+                }
+                _ => None,
+            };
+
+            certificates.push(AcmeCertificate {
+                domain: domain.to_string(),
+                cert_path: zone_dir.join(path),
+                key_path: zone_dir.join(key_path),
+                dns01,
+            });
+        }
+
+        if certificates.is_empty() {
+            return Err("there is no tls_cert to obtain".to_string());
+        }
+
+        Ok(Self {
+            directory_url: acme_config.get_directory_url().to_string(),
+            contact: acme_config.get_contact().to_vec(),
+            account_path: zone_dir.join(acme_config.get_account_path()),
+            challenge,
+            renew_before: acme_config.get_renew_before(),
+            certificates,
+        })
+    }
+
+    /// The zones of the DNS-01 challenges, to be added to the `Catalog`
+    pub(crate) fn authorities(&self) -> Vec<(LowerName, Box<dyn AuthorityObject>)> {
+        self.certificates
+            .iter()
+            .filter_map(|certificate| certificate.dns01.as_ref())
+            .map(|(zone, _)| {
+                (
+                    zone.origin().clone(),
+                    Box::new(Arc::clone(zone)) as Box<dyn AuthorityObject>,
+                )
+            })
+            .collect()
+    }
+
+    /// Writes short-lived self-signed certificates for the missing files, so that the listeners
+    ///  can be started before the certificates are obtained
+    pub(crate) fn create_missing(&self) -> Result<(), String> {
+        for certificate in &self.certificates {
+            if certificate.cert_path.exists() && certificate.key_path.exists() {
+                continue;
+            }
+
+            info!(
+                "creating temporary self-signed certificate for {}",
+                certificate.domain
+            );
+            let mut params = CertificateParams::new(vec![certificate.domain.clone()]);
+            params.not_before = OffsetDateTime::now_utc();
+            params.not_after = params.not_before + time::Duration::DAY;
+            let cert = rcgen::Certificate::from_params(params)
+                .map_err(|e| format!("failed to create certificate: {e}"))?;
+            let cert_pem = cert
+                .serialize_pem()
+                .map_err(|e| format!("failed to create certificate: {e}"))?;
+
+            certificate.write(&cert_pem, &cert.serialize_private_key_pem())?;
+        }
+
+        Ok(())
+    }
+
+    /// Renews the certificates when they are about to expire, this never returns
+    pub(crate) async fn renew_every(self: Arc<Self>, cert_resolver: Arc<SniCertResolver>) {
+        loop {
+            let interval = match self.renew(&cert_resolver).await {
+                Ok(()) => RENEW_INTERVAL,
+                Err(e) => {
+                    warn!("failed to obtain certificates with acme: {e}");
+                    RETRY_INTERVAL
+                }
+            };
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Obtains the certificates which expire within `renew_before`, and reloads the listeners
+    async fn renew(&self, cert_resolver: &SniCertResolver) -> Result<(), String> {
+        let renew_at = OffsetDateTime::now_utc() + self.renew_before;
+        let due = self
+            .certificates
+            .iter()
+            .filter(|certificate| match expiration(&certificate.cert_path) {
+                Some(expiration) => expiration <= renew_at,
+                None => true,
+            })
+            .collect::<Vec<_>>();
+        if due.is_empty() {
+            return Ok(());
+        }
+
+        let account = self.account().await?;
+        let mut result = Ok(());
+        for certificate in due {
+            info!("obtaining certificate for {} with acme", certificate.domain);
+            match self.obtain(&account, certificate, cert_resolver).await {
+                Ok(()) => {
+                    info!("obtained certificate for {}", certificate.domain);
+                    cert_resolver.reload();
+                }
+                Err(e) => result = Err(format!("{}: {e}", certificate.domain)),
+            }
+        }
+
+        result
+    }
+
+    /// Restores the account from its credentials, or creates it
+    async fn account(&self) -> Result<Account, String> {
+        match fs::read(&self.account_path) {
+            Ok(json) => {
+                let credentials = serde_json::from_slice::<AccountCredentials>(&json)
+                    .map_err(|e| format!("bad acme account {:?}: {e}", self.account_path))?;
+                return Account::from_credentials(credentials)
+                    .await
+                    .map_err(|e| format!("failed to restore acme account: {e}"));
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("could not read {:?}: {e}", self.account_path)),
+        }
+
+        let contact = self.contact.iter().map(String::as_str).collect::<Vec<_>>();
+        let new_account = NewAccount {
+            contact: &contact,
+            terms_of_service_agreed: true,
+            only_return_existing: false,
+        };
+        let (account, credentials) = Account::create(&new_account, &self.directory_url, None)
+            .await
+            .map_err(|e| format!("failed to create acme account: {e}"))?;
+
+        let json = serde_json::to_vec(&credentials)
+            .map_err(|e| format!("failed to serialize acme account: {e}"))?;
+        replace_file(&self.account_path, &json)
+            .map_err(|e| format!("could not write {:?}: {e}", self.account_path))?;
+        info!("created acme account in {:?}", self.account_path);
+
+        Ok(account)
+    }
+
+    /// Orders the certificate, and writes it and its key once the challenges are validated
+    async fn obtain(
+        &self,
+        account: &Account,
+        certificate: &AcmeCertificate,
+        cert_resolver: &SniCertResolver,
+    ) -> Result<(), String> {
+        let identifiers = [Identifier::Dns(certificate.domain.clone())];
+        let mut order = account
+            .new_order(&NewOrder {
+                identifiers: &identifiers,
+            })
+            .await
+            .map_err(|e| format!("failed to create order: {e}"))?;
+
+        let mut dns01_challenges = Vec::new();
+        let result = self
+            .validate(
+                &mut order,
+                certificate,
+                cert_resolver,
+                &mut dns01_challenges,
+            )
+            .await;
+
+        // the challenges are not needed anymore, whether the validation succeeded or not
+        cert_resolver.remove_acme_challenge(&certificate.domain);
+        if let Some((_, solver)) = &certificate.dns01 {
+            for challenge in dns01_challenges {
+                if let Err(e) = solver.cleanup(challenge).await {
+                    warn!("failed to remove acme challenge: {e}");
+                }
+            }
+        }
+        result?;
+
+        let mut params = CertificateParams::new(vec![certificate.domain.clone()]);
+        params.distinguished_name = DistinguishedName::new();
+        let key = rcgen::Certificate::from_params(params)
+            .map_err(|e| format!("failed to create key: {e}"))?;
+        let csr = key
+            .serialize_request_der()
+            .map_err(|e| format!("failed to create certificate request: {e}"))?;
+        order
+            .finalize(&csr)
+            .await
+            .map_err(|e| format!("failed to finalize order: {e}"))?;
+
+        for _ in 0..MAX_POLLS {
+            let cert_chain = order
+                .certificate()
+                .await
+                .map_err(|e| format!("failed to download certificate: {e}"))?;
+            if let Some(cert_chain) = cert_chain {
+                return certificate.write(&cert_chain, &key.serialize_private_key_pem());
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+
+        Err("the certificate was not issued in time".to_string())
+    }
+
+    /// Presents the challenges of the pending authorizations, and waits for their validation
+    async fn validate(
+        &self,
+        order: &mut Order,
+        certificate: &AcmeCertificate,
+        cert_resolver: &SniCertResolver,
+        dns01_challenges: &mut Vec<Dns01Challenge>,
+    ) -> Result<(), String> {
+        let challenge_type = match self.challenge {
+            AcmeChallenge::TlsAlpn01 => ChallengeType::TlsAlpn01,
+            AcmeChallenge::Dns01 => ChallengeType::Dns01,
+            challenge => return Err(format!("unsupported challenge: {challenge:?}")),
+        };
+
+        let authorizations = order
+            .authorizations()
+            .await
+            .map_err(|e| format!("failed to get authorizations: {e}"))?;
+        for authorization in authorizations {
+            match authorization.status {
+                AuthorizationStatus::Pending => {}
+                AuthorizationStatus::Valid => continue,
+                status => return Err(format!("authorization is {status:?}")),
+            }
+
+            let Identifier::Dns(domain) = &authorization.identifier;
+            let challenge = authorization
+                .challenges
+                .iter()
+                .find(|challenge| challenge.r#type == challenge_type)
+                .ok_or_else(|| format!("no {challenge_type:?} challenge for {domain}"))?;
+            let key_authorization = order.key_authorization(challenge);
+
+            match &certificate.dns01 {
+                Some((_, solver)) => {
+                    let name = Name::from_str(domain).map_err(|e| format!("bad name: {e}"))?;
+                    let challenge = solver
+                        .present(&name, &key_authorization.dns_value())
+                        .await
+                        .map_err(|e| format!("failed to publish challenge: {e}"))?;
+                    dns01_challenges.push(challenge);
+                }
+                None => {
+                    let (cert, key) =
+                        tls_alpn_certificate(domain, key_authorization.digest().as_ref())?;
+                    cert_resolver.add_acme_challenge(domain, cert, &key)?;
+                }
+            }
+
+            order
+                .set_challenge_ready(&challenge.url)
+                .await
+                .map_err(|e| format!("failed to set challenge ready: {e}"))?;
+        }
+
+        for _ in 0..MAX_POLLS {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let state = order
+                .refresh()
+                .await
+                .map_err(|e| format!("failed to get order: {e}"))?;
+            match state.status {
+                OrderStatus::Ready | OrderStatus::Valid => return Ok(()),
+                OrderStatus::Invalid => {
+                    return Err(format!("validation failed: {:?}", state.error));
+                }
+                _ => {}
+            }
+        }
+
+        Err("the challenges were not validated in time".to_string())
+    }
+}
+
+impl AcmeCertificate {
+    /// Replaces the files of the certificate chain and of the key
+    fn write(&self, cert_chain: &str, key: &str) -> Result<(), String> {
+        replace_file(&self.key_path, key.as_bytes())
+            .map_err(|e| format!("could not write {:?}: {e}", self.key_path))?;
+        replace_file(&self.cert_path, cert_chain.as_bytes())
+            .map_err(|e| format!("could not write {:?}: {e}", self.cert_path))
+    }
+}
+
+/// The zone of the `_acme-challenge` records of the domain
+fn challenge_zone(domain: &str) -> Result<InMemoryAuthority, String> {
+    let domain = Name::from_str(domain).map_err(|e| format!("bad endpoint_name {domain}: {e}"))?;
+    let origin = challenge_name(&domain).map_err(|e| format!("bad endpoint_name {domain}: {e}"))?;
+
+    Ok(InMemoryAuthority::empty_primary(
+        origin,
+        InMemoryAuthority::serial_now(),
+        CHALLENGE_TTL,
+    ))
+}
+
+/// The self-signed certificate of a TLS-ALPN-01 challenge, [RFC 8737](https://www.rfc-editor.org/rfc/rfc8737#section-3)
+fn tls_alpn_certificate(domain: &str, digest: &[u8]) -> Result<(Certificate, PrivateKey), String> {
+    let mut params = CertificateParams::new(vec![domain.to_string()]);
+    params.custom_extensions = vec![CustomExtension::new_acme_identifier(digest)];
+    let cert = rcgen::Certificate::from_params(params)
+        .map_err(|e| format!("failed to create challenge certificate: {e}"))?;
+    let der = cert
+        .serialize_der()
+        .map_err(|e| format!("failed to create challenge certificate: {e}"))?;
+
+    Ok((
+        Certificate(der),
+        PrivateKey(cert.serialize_private_key_der()),
+    ))
+}
+
+/// Replaces the file at once, so that its contents are never partially written
+fn replace_file(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    // the keys and the account credentials are secret
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let mut file = options.open(&temporary)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&temporary, path)
+}
+
+/// Returns the end of the validity of the first certificate in the file, if it can be read
+fn expiration(path: &Path) -> Option<OffsetDateTime> {
+    let cert = read_cert(path).ok()?;
+    not_after(&cert.first()?.0)
+}
+
+/// Returns the `notAfter` time of the DER encoded certificate, [RFC 5280](https://www.rfc-editor.org/rfc/rfc5280#section-4.1)
+fn not_after(cert: &[u8]) -> Option<OffsetDateTime> {
+    let (_, cert, _) = der_next(cert)?;
+    let (_, tbs_cert, _) = der_next(cert)?;
+
+    // skip the optional version, the serial number, the signature algorithm and the issuer
+    let (tag, _, mut fields) = der_next(tbs_cert)?;
+    let skip = if tag == 0xa0 { 3 } else { 2 };
+    for _ in 0..skip {
+        fields = der_next(fields)?.2;
+    }
+
+    let (_, validity, _) = der_next(fields)?;
+    let (_, _, not_after) = der_next(validity)?;
+    let (tag, time, _) = der_next(not_after)?;
+    parse_time(tag, time)
+}
+
+/// Splits the first element of the DER encoding into its tag, its contents and the rest
+fn der_next(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, input) = input.split_first()?;
+    let (&len, mut input) = input.split_first()?;
+
+    let len = if len & 0x80 == 0 {
+        usize::from(len)
+    } else {
+        let octets = usize::from(len & 0x7f);
+        if octets == 0 || octets > 4 || input.len() < octets {
+            return None;
+        }
+        let (len, rest) = input.split_at(octets);
+        input = rest;
+        len.iter()
+            .fold(0, |len, octet| len << 8 | usize::from(*octet))
+    };
+
+    if input.len() < len {
+        return None;
+    }
+    let (contents, rest) = input.split_at(len);
+    Some((tag, contents, rest))
+}
+
+/// Parses a `UTCTime` or a `GeneralizedTime` in UTC
+fn parse_time(tag: u8, time: &[u8]) -> Option<OffsetDateTime> {
+    let time = std::str::from_utf8(time).ok()?.strip_suffix('Z')?;
+    let (year, time) = match tag {
+        // YYMMDDHHMMSSZ
+        0x17 => {
+            let year = time.get(..2)?.parse::<i32>().ok()?;
+            let year = if year < 50 { 2000 + year } else { 1900 + year };
+            (year, time.get(2..)?)
+        }
+        // YYYYMMDDHHMMSSZ
+        0x18 => (time.get(..4)?.parse::<i32>().ok()?, time.get(4..)?),
+        _ => return None,
+    };
+    if time.len() != 10 {
+        return None;
+    }
+
+    let field = |at: usize| time.get(at..at + 2)?.parse::<u8>().ok();
+    let date = Date::from_calendar_date(year, Month::try_from(field(0)?).ok()?, field(2)?).ok()?;
+    let time = Time::from_hms(field(4)?, field(6)?, field(8)?).ok()?;
+    Some(PrimitiveDateTime::new(date, time).assume_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::datetime;
+
+    use super::*;
+
+    #[test]
+    fn test_not_after() {
+        let mut params = CertificateParams::new(vec!["dot.example.com".to_string()]);
+        params.not_after = datetime!(2049-12-31 23:59:59 UTC);
+        let utc_time = rcgen::Certificate::from_params(params).unwrap();
+        assert_eq!(
+            not_after(&utc_time.serialize_der().unwrap()),
+            Some(datetime!(2049-12-31 23:59:59 UTC))
+        );
+
+        let mut params = CertificateParams::new(vec!["dot.example.com".to_string()]);
+        params.not_after = datetime!(2050-01-01 00:00:00 UTC);
+        let generalized_time = rcgen::Certificate::from_params(params).unwrap();
+        assert_eq!(
+            not_after(&generalized_time.serialize_der().unwrap()),
+            Some(datetime!(2050-01-01 00:00:00 UTC))
+        );
+
+        assert_eq!(not_after(b"\x30\x82\x01"), None);
+    }
+
+    #[test]
+    fn test_tls_alpn_certificate() {
+        let (cert, _) = tls_alpn_certificate("dot.example.com", &[0; 32]).unwrap();
+        // the acmeIdentifier extension, 1.3.6.1.5.5.7.1.31
+        let oid = [0x06, 0x08, 0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x01, 0x1f];
+        assert!(cert.0.windows(oid.len()).any(|window| window == oid));
+        assert!(not_after(&cert.0).is_some());
+    }
+}
@@ -16,7 +16,7 @@
 //!    -d, --debug             Turn on DEBUG messages (default is only INFO)
 //!    -h, --help              Show this message
 //!    -v, --version           Show the version of hickory-dns
-//!    -c FILE, --config=FILE  Path to configuration file, default is /etc/named.toml
+//!    -c FILE, --config=FILE  Path to configuration file, TOML or YAML, default is /etc/named.toml
 //!    -z DIR, --zonedir=DIR   Path to the root directory for all zone files, see also config toml
 //!    -p PORT, --port=PORT    Override the listening port
 //!    --tls-port=PORT         Override the listening port for TLS connections
@@ -36,19 +36,25 @@
 #![recursion_limit = "128"]
 #![allow(clippy::redundant_clone)]
 
+#[cfg(feature = "acme")]
+mod acme;
+
 use std::{
     env, fmt,
     net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs},
     path::{Path, PathBuf},
+    process,
     sync::Arc,
+    time::{Duration, Instant},
 };
 
 use clap::Parser;
 use time::OffsetDateTime;
-use tokio::{
-    net::{TcpListener, UdpSocket},
-    runtime,
-};
+#[cfg(any(feature = "dns-over-tls", feature = "dns-over-https"))]
+use tokio::net::TcpListener;
+#[cfg(any(feature = "dns-over-quic", feature = "dns-over-h3"))]
+use tokio::net::UdpSocket;
+use tokio::runtime;
 use tracing::{debug, error, info, warn, Event, Subscriber};
 use tracing_subscriber::{
     fmt::{format, FmtContext, FormatEvent, FormatFields, FormattedFields},
@@ -61,7 +67,19 @@ use hickory_client::rr::Name;
 #[cfg(feature = "dns-over-tls")]
 use hickory_server::config::dnssec::{self, TlsCertConfig};
 #[cfg(feature = "resolver")]
+use hickory_server::resolver::blocklist::SharedBlocklist;
+#[cfg(feature = "dns-over-https")]
+use hickory_server::server::HttpsAuth;
+#[cfg(feature = "dns-over-rustls")]
+use hickory_server::server::SniCertResolver;
+#[cfg(feature = "etcd")]
+use hickory_server::store::discovery::{DiscoveryAuthority, EtcdWatch};
+#[cfg(feature = "resolver")]
 use hickory_server::store::forwarder::ForwardAuthority;
+#[cfg(feature = "geoip")]
+use hickory_server::store::geo::GeoAuthority;
+#[cfg(feature = "resolver")]
+use hickory_server::store::in_memory::{AdditionalResolver, AnameFlattener};
 #[cfg(feature = "recursor")]
 use hickory_server::store::recursor::RecursiveAuthority;
 #[cfg(feature = "sqlite")]
@@ -69,15 +87,21 @@ use hickory_server::store::sqlite::{SqliteAuthority, SqliteConfig};
 use hickory_server::{
     authority::{AuthorityObject, Catalog, ZoneType},
     config::{Config, ZoneConfig},
-    server::ServerFuture,
+    server::{listen_fds, sd_notify, sd_watchdog_interval, InheritedSocket, ServerFuture},
     store::{
-        file::{FileAuthority, FileConfig},
+        dhcp::DhcpLeases,
+        file::{FileAuthority, FileConfig, Severity},
+        health::HealthAuthority,
+        in_memory::Rotation,
         StoreConfig,
     },
 };
 
 #[cfg(feature = "dnssec")]
-use {hickory_client::rr::rdata::key::KeyUsage, hickory_server::authority::DnssecAuthority};
+use {
+    hickory_client::rr::rdata::key::KeyUsage, hickory_server::authority::DnssecAuthority,
+    hickory_server::store::in_memory::InMemoryAuthority,
+};
 
 #[cfg(feature = "dnssec")]
 async fn load_keys<A, L>(
@@ -138,6 +162,68 @@ async fn load_keys<T>(
     Ok(())
 }
 
+/// Verifies the signatures of a freshly signed zone before it is served
+#[cfg(feature = "dnssec")]
+async fn verify_zone(
+    authority: &InMemoryAuthority,
+    zone_config: &ZoneConfig,
+) -> Result<(), String> {
+    if zone_config.is_dnssec_enabled() {
+        info!("verifying zone: {}", zone_config.get_zone()?);
+        authority
+            .verify_zone()
+            .await
+            .map_err(|e| format!("failed to verify zone signatures: {e}"))?;
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "dnssec"))]
+#[allow(clippy::unnecessary_wraps)]
+async fn verify_zone<T>(_authority: &T, _zone_config: &ZoneConfig) -> Result<(), String> {
+    Ok(())
+}
+
+/// Resolves the addresses outside of the zone for the additional section, when enabled for the zone
+#[cfg(feature = "resolver")]
+fn resolve_additionals<A>(authority: &mut A, zone_config: &ZoneConfig) -> Result<(), String>
+where
+    A: std::ops::DerefMut<Target = hickory_server::store::in_memory::InMemoryAuthority>,
+{
+    if let Some(config) = zone_config.get_additionals() {
+        authority.set_additional_resolver(AdditionalResolver::try_from_config(config)?);
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "resolver"))]
+#[allow(clippy::unnecessary_wraps)]
+fn resolve_additionals<T>(_authority: &mut T, _zone_config: &ZoneConfig) -> Result<(), String> {
+    Ok(())
+}
+
+/// Flattens the ANAME records of the zone in the background, when the zone has an ANAME resolver
+#[cfg(feature = "resolver")]
+fn flatten_anames<A>(authority: &Arc<A>, zone_config: &ZoneConfig) -> Result<(), String>
+where
+    A: std::ops::Deref<Target = hickory_server::store::in_memory::InMemoryAuthority>
+        + Send
+        + Sync
+        + 'static,
+{
+    if let Some(config) = zone_config.get_aname() {
+        let flattener = AnameFlattener::try_from_config(config)?;
+        tokio::spawn(flattener.run(Arc::clone(authority)));
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "resolver"))]
+#[allow(clippy::unnecessary_wraps)]
+fn flatten_anames<T>(_authority: &Arc<T>, _zone_config: &ZoneConfig) -> Result<(), String> {
+    Ok(())
+}
+
 #[cfg_attr(not(feature = "dnssec"), allow(unused_mut, unused))]
 #[warn(clippy::wildcard_enum_match_arm)] // make sure all cases are handled despite of non_exhaustive
 async fn load_zone(
@@ -145,12 +231,18 @@ async fn load_zone(
     zone_config: &ZoneConfig,
 ) -> Result<Box<dyn AuthorityObject>, String> {
     debug!("loading zone with config: {:#?}", zone_config);
+    let started = Instant::now();
 
     let zone_name: Name = zone_config.get_zone().expect("bad zone name");
     let zone_name_for_signer = zone_name.clone();
     let zone_path: Option<String> = zone_config.file.clone();
     let zone_type: ZoneType = zone_config.get_zone_type();
     let is_axfr_allowed = zone_config.is_axfr_allowed();
+    let rotation = Rotation::try_from_config(
+        &zone_name,
+        zone_config.get_rotation(),
+        zone_config.get_rotations(),
+    )?;
     #[allow(unused_variables)]
     let is_dnssec_enabled = zone_config.is_dnssec_enabled();
 
@@ -176,9 +268,22 @@ async fn load_zone(
             )
             .await?;
 
+            #[cfg(feature = "dnssec")]
+            authority.set_nx_proof_kind(zone_config.get_nx_proof_kind());
+            authority.set_any_query(zone_config.get_any_query());
+            authority.set_rotation(rotation);
+            resolve_additionals(&mut authority, zone_config)?;
+
             // load any keys for the Zone, if it is a dynamic update zone, then keys are required
             load_keys(&mut authority, zone_name_for_signer, zone_config).await?;
-            Box::new(Arc::new(authority)) as Box<dyn AuthorityObject>
+            authority
+                .verify_journal()
+                .await
+                .map_err(|e| format!("failed to verify journal: {e}"))?;
+            verify_zone(&authority, zone_config).await?;
+            let authority = Arc::new(authority);
+            flatten_anames(&authority, zone_config)?;
+            Box::new(authority) as Box<dyn AuthorityObject>
         }
         Some(StoreConfig::File(ref config)) => {
             if zone_path.is_some() {
@@ -193,13 +298,30 @@ async fn load_zone(
                 config,
             )?;
 
+            #[cfg(feature = "dnssec")]
+            authority.set_nx_proof_kind(zone_config.get_nx_proof_kind());
+            authority.set_any_query(zone_config.get_any_query());
+            authority.set_rotation(rotation);
+            resolve_additionals(&mut authority, zone_config)?;
+
             // load any keys for the Zone, if it is a dynamic update zone, then keys are required
             load_keys(&mut authority, zone_name_for_signer, zone_config).await?;
-            Box::new(Arc::new(authority)) as Box<dyn AuthorityObject>
+            verify_zone(&authority, zone_config).await?;
+            let authority = Arc::new(authority);
+            flatten_anames(&authority, zone_config)?;
+            Box::new(authority) as Box<dyn AuthorityObject>
         }
         #[cfg(feature = "resolver")]
         Some(StoreConfig::Forward(ref config)) => {
             let forwarder = ForwardAuthority::try_from_config(zone_name, zone_type, config)?;
+            let warm_up = forwarder.warm_up().await;
+            if !warm_up.resolved.is_empty() || !warm_up.failed.is_empty() {
+                info!(
+                    "warmed up forwarder cache: {} resolved, {} failed",
+                    warm_up.resolved.len(),
+                    warm_up.failed.len()
+                );
+            }
 
             Box::new(Arc::new(forwarder)) as Box<dyn AuthorityObject>
         }
@@ -211,6 +333,43 @@ async fn load_zone(
 
             Box::new(Arc::new(authority)) as Box<dyn AuthorityObject>
         }
+        #[cfg(feature = "etcd")]
+        Some(StoreConfig::Etcd(ref config)) => {
+            let authority = Arc::new(DiscoveryAuthority::new(zone_name, config.get_ttl()));
+            let watch = EtcdWatch::new(config.endpoint, config.prefix.clone());
+            info!(
+                "watching services of zone {} in etcd at {}",
+                authority.origin(),
+                config.endpoint
+            );
+            tokio::spawn(Arc::clone(&authority).watch(watch, config.get_retry_interval()));
+
+            Box::new(authority) as Box<dyn AuthorityObject>
+        }
+        #[cfg(feature = "geoip")]
+        Some(StoreConfig::Geo(ref config)) => {
+            let authority = GeoAuthority::try_from_config(
+                zone_name,
+                zone_type,
+                is_axfr_allowed,
+                Some(zone_dir),
+                config,
+            )?;
+
+            Box::new(Arc::new(authority)) as Box<dyn AuthorityObject>
+        }
+        Some(StoreConfig::Health(ref config)) => {
+            let authority = Arc::new(HealthAuthority::try_from_config(
+                zone_name,
+                zone_type,
+                is_axfr_allowed,
+                Some(zone_dir),
+                config,
+            )?);
+            tokio::spawn(Arc::clone(&authority).run_checks());
+
+            Box::new(authority) as Box<dyn AuthorityObject>
+        }
         #[cfg(feature = "sqlite")]
         None if zone_config.is_update_allowed() => {
             warn!(
@@ -239,9 +398,22 @@ async fn load_zone(
             )
             .await?;
 
+            #[cfg(feature = "dnssec")]
+            authority.set_nx_proof_kind(zone_config.get_nx_proof_kind());
+            authority.set_any_query(zone_config.get_any_query());
+            authority.set_rotation(rotation);
+            resolve_additionals(&mut authority, zone_config)?;
+
             // load any keys for the Zone, if it is a dynamic update zone, then keys are required
             load_keys(&mut authority, zone_name_for_signer, zone_config).await?;
-            Box::new(Arc::new(authority)) as Box<dyn AuthorityObject>
+            authority
+                .verify_journal()
+                .await
+                .map_err(|e| format!("failed to verify journal: {e}"))?;
+            verify_zone(&authority, zone_config).await?;
+            let authority = Arc::new(authority);
+            flatten_anames(&authority, zone_config)?;
+            Box::new(authority) as Box<dyn AuthorityObject>
         }
         None => {
             let config = FileConfig {
@@ -256,19 +428,56 @@ async fn load_zone(
                 &config,
             )?;
 
+            #[cfg(feature = "dnssec")]
+            authority.set_nx_proof_kind(zone_config.get_nx_proof_kind());
+            authority.set_any_query(zone_config.get_any_query());
+            authority.set_rotation(rotation);
+            resolve_additionals(&mut authority, zone_config)?;
+
             // load any keys for the Zone, if it is a dynamic update zone, then keys are required
             load_keys(&mut authority, zone_name_for_signer, zone_config).await?;
-            Box::new(Arc::new(authority)) as Box<dyn AuthorityObject>
+            verify_zone(&authority, zone_config).await?;
+            let authority = Arc::new(authority);
+            flatten_anames(&authority, zone_config)?;
+            Box::new(authority) as Box<dyn AuthorityObject>
         }
         Some(_) => {
             panic!("unrecognized authority type, check enabled features");
         }
     };
 
-    info!("zone successfully loaded: {}", zone_config.get_zone()?);
+    info!(
+        "zone successfully loaded: {} in {:?}",
+        zone_config.get_zone()?,
+        started.elapsed()
+    );
     Ok(authority)
 }
 
+/// Prints the problems of the configuration, and returns the exit status of `--check-config`
+fn check_config(config_path: &Path, config: &Config, zone_dir: &Path) -> i32 {
+    let problems = config.validate(zone_dir);
+    for problem in &problems {
+        println!("{}: {problem}", config_path.display());
+    }
+
+    let count = |severity| {
+        problems
+            .iter()
+            .filter(|problem| problem.severity == severity)
+            .count()
+    };
+    let errors = count(Severity::Error);
+    println!(
+        "{}: {} zones, {errors} errors, {} warnings",
+        config_path.display(),
+        config.get_zones().len(),
+        count(Severity::Warning)
+    );
+
+    i32::from(errors > 0)
+}
+
 /// Cli struct for all options managed with clap derive api.
 #[derive(Debug, Parser)]
 #[clap(name = "Hickory DNS named server", version, about)]
@@ -281,8 +490,8 @@ struct Cli {
     #[clap(short = 'd', long = "debug", conflicts_with = "quiet")]
     pub(crate) debug: bool,
 
-    /// Path to configuration file of named server,
-    /// by default `/etc/named.toml`
+    /// Path to configuration file of named server, in TOML or YAML (`.yaml` or `.yml`),
+    /// by default `/etc/named.toml`, the `HICKORY_*` environment variables override its keys
     #[clap(
         short = 'c',
         long = "config",
@@ -316,6 +525,16 @@ struct Cli {
     /// overrides any value in config file
     #[clap(long = "quic-port", value_name = "QUIC-PORT")]
     pub(crate) quic_port: Option<u16>,
+
+    /// Listening port for DNS over HTTP/3 queries,
+    /// overrides any value in config file
+    #[clap(long = "h3-port", value_name = "H3-PORT")]
+    pub(crate) h3_port: Option<u16>,
+
+    /// Check the configuration, with its zone, key and certificate files, and exit
+    ///  without binding any sockets, the exit status is 1 if there is any error
+    #[clap(long = "check-config")]
+    pub(crate) check_config: bool,
 }
 
 /// Main method for running the named server.
@@ -339,8 +558,17 @@ fn main() {
     let config = args.config.clone();
     let config_path = Path::new(&config);
     info!("loading configuration from: {:?}", config_path);
-    let config = Config::read_config(config_path)
-        .unwrap_or_else(|e| panic!("could not read config {}: {:?}", config_path.display(), e));
+    // the variables which are not unicode can not be keys of the configuration
+    let vars = env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)));
+    let mut config = match Config::read_config_with_env(config_path, vars) {
+        Ok(config) => config,
+        Err(e) if args.check_config => {
+            eprintln!("{}: error: {e}", config_path.display());
+            process::exit(1);
+        }
+        Err(e) => panic!("could not read config {}: {:?}", config_path.display(), e),
+    };
     let directory_config = config.get_directory().to_path_buf();
     let zonedir = args.zonedir.clone();
     let zone_dir: PathBuf = zonedir
@@ -348,25 +576,106 @@ fn main() {
         .map(PathBuf::from)
         .unwrap_or_else(|| directory_config.clone());
 
-    // TODO: allow for num threads configured...
+    // the ports given on the command line override the ones of the configuration
+    if let Some(port) = args.port {
+        config.set_listen_port(port);
+    }
+    if let Some(port) = args.tls_port {
+        config.set_tls_listen_port(port);
+    }
+    if let Some(port) = args.https_port {
+        config.set_https_listen_port(port);
+    }
+    if let Some(port) = args.quic_port {
+        config.set_quic_listen_port(port);
+    }
+    if let Some(port) = args.h3_port {
+        config.set_h3_listen_port(port);
+    }
+
+    if args.check_config {
+        process::exit(check_config(config_path, &config, &zone_dir));
+    }
+
+    let workers = config.get_workers();
     let mut runtime = runtime::Builder::new_multi_thread()
         .enable_all()
-        .worker_threads(4)
+        .worker_threads(workers.map_or(4, usize::from))
         .thread_name("hickory-server-runtime")
         .build()
         .expect("failed to initialize Tokio Runtime");
     let mut catalog: Catalog = Catalog::new();
-    // configure our server based on the config_path
-    for zone in config.get_zones() {
+    if let Some(server_id) = config.get_server_id() {
+        info!("identifying as {} in NSID and hostname.bind", server_id);
+        catalog.set_server_id(Some(server_id.to_string()));
+    }
+    catalog.set_request_timeout(config.get_request_timeout());
+    catalog.set_slow_query_threshold(config.get_slow_query_threshold());
+    // the names which are blocked, the lists are read again when they change
+    #[cfg(feature = "resolver")]
+    if let Some(blocklist_config) = config.get_blocklist() {
+        let blocklist = blocklist_config
+            .read(&zone_dir)
+            .unwrap_or_else(|e| panic!("could not read blocklist: {e}"));
+        let blocklist = Arc::new(blocklist);
+        if let Some(interval) = blocklist_config.get_reload_interval() {
+            runtime.spawn(reload_blocklist_every(Arc::clone(&blocklist), interval));
+        }
+        catalog.set_blocklist(Some(blocklist));
+    }
+    // configure our server based on the config_path, zones are loaded and signed in parallel
+    let started = Instant::now();
+    let config = Arc::new(config);
+    let zone_loads = (0..config.get_zones().len())
+        .map(|idx| {
+            let config = Arc::clone(&config);
+            let zone_dir = zone_dir.clone();
+            runtime.spawn(async move { load_zone(&zone_dir, &config.get_zones()[idx]).await })
+        })
+        .collect::<Vec<_>>();
+
+    for (zone, zone_load) in config.get_zones().iter().zip(zone_loads) {
         let zone_name = zone
             .get_zone()
             .unwrap_or_else(|_| panic!("bad zone name in {:?}", config_path));
 
-        match runtime.block_on(load_zone(&zone_dir, zone)) {
-            Ok(authority) => catalog.upsert(zone_name.into(), authority),
+        match runtime.block_on(zone_load) {
+            Ok(Ok(authority)) => catalog.upsert(zone_name.into(), authority),
+            Ok(Err(error)) => panic!("could not load zone {}: {}", zone_name, error),
             Err(error) => panic!("could not load zone {}: {}", zone_name, error),
         }
     }
+    info!(
+        "loaded {} zones in {:?}",
+        config.get_zones().len(),
+        started.elapsed()
+    );
+
+    // the zones maintained from DHCP lease events and lease files
+    let dhcp_leases = config.get_dhcp().map(|dhcp_config| {
+        let leases = DhcpLeases::try_from_config(dhcp_config)
+            .unwrap_or_else(|e| panic!("could not configure dhcp: {e}"));
+        for (origin, authority) in leases.authorities() {
+            info!("maintaining zone {} from dhcp leases", origin);
+            catalog.upsert(origin, authority);
+        }
+        Arc::new(leases)
+    });
+
+    // the certificates obtained with ACME, and the zones of their DNS-01 challenges
+    #[cfg(feature = "acme")]
+    let acme_certificates = config.get_acme().map(|acme_config| {
+        let certificates = acme::AcmeCertificates::try_from_config(acme_config, &config, &zone_dir)
+            .unwrap_or_else(|e| panic!("could not configure acme: {e}"));
+        for (origin, authority) in certificates.authorities() {
+            info!("serving acme challenges in zone {}", origin);
+            catalog.upsert(origin, authority);
+        }
+        certificates
+            .create_missing()
+            .unwrap_or_else(|e| panic!("could not create certificates: {e}"));
+        Arc::new(certificates)
+    });
 
     // TODO: support all the IPs asked to listen on...
     // TODO:, there should be the option to listen on any port, IP and protocol option...
@@ -395,43 +704,51 @@ fn main() {
     let allow_networks = config.get_allow_networks();
 
     // now, run the server, based on the config
-    #[cfg_attr(not(feature = "dns-over-tls"), allow(unused_mut))]
     let mut server = ServerFuture::with_access(catalog, deny_networks, allow_networks);
+    server.set_response_config(config.get_response_config());
+    server.set_accept_config(config.get_accept_config());
+    server.set_admission_config(config.get_admission_config());
+    server.set_proxy_protocol(config.get_tcp_proxy_protocol());
+
+    // the sockets passed by systemd, which stay open across restarts, replace the UDP and TCP
+    //  sockets of the configuration
+    let mut inherited_sockets = InheritedSockets::take();
+    let dns_sockets = inherited_sockets.take_dns();
+    let sockaddrs = if dns_sockets.is_empty() {
+        sockaddrs
+    } else {
+        let _guard = runtime.enter();
+        for socket in dns_sockets {
+            info!("registering inherited socket {:?}", socket);
+            server
+                .register_inherited_socket(socket, tcp_request_timeout)
+                .unwrap_or_else(|e| panic!("could not register inherited socket: {e}"));
+        }
+        Vec::new()
+    };
 
-    // load all the listeners
-    for udp_socket in &sockaddrs {
+    // load all the listeners, with one shard per worker if configured
+    let shards = workers.map_or(1, usize::from);
+    let udp_sockaddrs = register_xdp(&mut server, &config, &sockaddrs, &mut runtime);
+    for udp_socket in udp_sockaddrs {
         info!("binding UDP to {:?}", udp_socket);
-        let udp_socket = runtime
-            .block_on(UdpSocket::bind(udp_socket))
+        let _guard = runtime.enter();
+        let udp_addr = server
+            .register_sharded_socket(*udp_socket, shards)
             .unwrap_or_else(|err| panic!("could not bind to UDP socket {udp_socket}: {err}"));
 
-        info!(
-            "listening for UDP on {:?}",
-            udp_socket
-                .local_addr()
-                .expect("could not lookup local address")
-        );
-
-        let _guard = runtime.enter();
-        server.register_socket(udp_socket);
+        info!("listening for UDP on {:?} with {} shards", udp_addr, shards);
     }
 
     // and TCP as necessary
     for tcp_listener in &sockaddrs {
         info!("binding TCP to {:?}", tcp_listener);
-        let tcp_listener = runtime
-            .block_on(TcpListener::bind(tcp_listener))
+        let _guard = runtime.enter();
+        let tcp_addr = server
+            .register_sharded_listener(*tcp_listener, shards, tcp_request_timeout)
             .unwrap_or_else(|_| panic!("could not bind to tcp: {}", tcp_listener));
 
-        info!(
-            "listening for TCP on {:?}",
-            tcp_listener
-                .local_addr()
-                .expect("could not lookup local address")
-        );
-
-        let _guard = runtime.enter();
-        server.register_listener(tcp_listener, tcp_request_timeout);
+        info!("listening for TCP on {:?} with {} shards", tcp_addr, shards);
     }
 
     let tls_cert_config = config.get_tls_cert();
@@ -439,15 +756,31 @@ fn main() {
     // and TLS as necessary
     // TODO: we should add some more control from configs to enable/disable TLS/HTTPS/QUIC
     if let Some(_tls_cert_config) = tls_cert_config {
+        // load the certificates shared by all listeners
+        #[cfg(feature = "dns-over-rustls")]
+        let _cert_resolver =
+            config_cert_resolver(&config, _tls_cert_config, &zone_dir, &mut runtime);
+
+        // renew the certificates, which are then reloaded by the listeners
+        #[cfg(feature = "acme")]
+        if let Some(acme_certificates) = acme_certificates {
+            runtime.spawn(acme_certificates.renew_every(Arc::clone(&_cert_resolver)));
+        }
+
         // setup TLS listeners
         #[cfg(feature = "dns-over-tls")]
         config_tls(
             &args,
             &mut server,
             &config,
+            #[cfg(not(feature = "dns-over-rustls"))]
             _tls_cert_config,
+            #[cfg(feature = "dns-over-rustls")]
+            &_cert_resolver,
+            #[cfg(not(feature = "dns-over-rustls"))]
             &zone_dir,
             &listen_addrs,
+            &mut inherited_sockets,
             &mut runtime,
         );
 
@@ -458,8 +791,10 @@ fn main() {
             &mut server,
             &config,
             _tls_cert_config,
+            &_cert_resolver,
             &zone_dir,
             &listen_addrs,
+            &mut inherited_sockets,
             &mut runtime,
         );
 
@@ -470,21 +805,56 @@ fn main() {
             &mut server,
             &config,
             _tls_cert_config,
-            &zone_dir,
+            &_cert_resolver,
+            &listen_addrs,
+            &mut inherited_sockets,
+            &mut runtime,
+        );
+
+        // setup HTTP/3 listeners
+        #[cfg(feature = "dns-over-h3")]
+        config_h3(
+            &args,
+            &mut server,
+            &config,
+            _tls_cert_config,
+            &_cert_resolver,
             &listen_addrs,
+            &mut inherited_sockets,
             &mut runtime,
         );
     }
 
+    if let Some(dhcp_leases) = dhcp_leases {
+        config_dhcp(&config, &zone_dir, dhcp_leases, &mut runtime);
+    }
+
+    inherited_sockets.warn_unused();
+
     // config complete, starting!
     banner();
     info!("awaiting connections...");
+    notify_service_manager("READY=1");
+    if let Some(interval) = sd_watchdog_interval() {
+        info!("notifying the watchdog every {:?}", interval / 2);
+        runtime.spawn(notify_watchdog_every(interval / 2));
+    }
 
     // TODO: how to do threads? should we do a bunch of listener threads and then query threads?
     // Ideally the processing would be n-threads for receiving, which hand off to m-threads for
     //  request handling. It would generally be the case that n <= m.
     info!("Server starting up");
-    match runtime.block_on(server.block_until_done()) {
+    let result = runtime.block_on(async {
+        tokio::select! {
+            result = server.block_until_done() => return result,
+            _ = shutdown_signal() => info!("shutting down, answering the requests in flight"),
+        }
+        notify_service_manager("STOPPING=1");
+        server
+            .shutdown_gracefully_with_deadline(SHUTDOWN_DEADLINE)
+            .await
+    });
+    match result {
         Ok(()) => {
             // we're exiting for some reason...
             info!("Hickory DNS {} stopping", hickory_client::version());
@@ -502,18 +872,304 @@ fn main() {
     };
 }
 
+/// The time given to the requests in flight to be answered on shutdown
+const SHUTDOWN_DEADLINE: Duration = Duration::from_secs(10);
+
+/// Completes once the server is asked to stop, with SIGTERM or SIGINT
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut terminate =
+            signal(SignalKind::terminate()).expect("could not register for SIGTERM");
+        tokio::select! {
+            _ = terminate.recv() => (),
+            _ = tokio::signal::ctrl_c() => (),
+        }
+    }
+
+    #[cfg(not(unix))]
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        warn!("could not register for ctrl-c: {e}");
+        std::future::pending::<()>().await;
+    }
+}
+
+/// Sends the state to systemd, if the server is run as a `Type=notify` service
+fn notify_service_manager(state: &str) {
+    if let Err(e) = sd_notify(state) {
+        warn!("could not notify the service manager of {state}: {e}");
+    }
+}
+
+/// Keeps the watchdog of systemd from restarting the server, while the runtime is responsive
+async fn notify_watchdog_every(period: Duration) {
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        notify_service_manager("WATCHDOG=1");
+    }
+}
+
+/// Reads the lists of the blocklist again when they changed
+#[cfg(feature = "resolver")]
+async fn reload_blocklist_every(blocklist: Arc<SharedBlocklist>, period: Duration) {
+    let mut interval = tokio::time::interval(period);
+    // the lists were just read
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        let blocklist = Arc::clone(&blocklist);
+        // reading large lists takes a while, failures are logged and retried on the next change
+        let reloaded = tokio::task::spawn_blocking(move || blocklist.reload_if_changed()).await;
+        if let Ok(Ok(true)) = reloaded {
+            info!("reloaded blocklist");
+        }
+    }
+}
+
+/// The sockets passed by systemd with socket activation, by their `FileDescriptorName=`
+///
+/// The sockets named `tls`, `https`, `quic` and `h3` are used by those listeners, all the others
+///  replace the UDP and TCP sockets of the configuration.
+struct InheritedSockets(Vec<(String, InheritedSocket)>);
+
+impl InheritedSockets {
+    /// The names of the sockets which are not used for plain DNS
+    const LISTENER_NAMES: [&'static str; 4] = ["tls", "https", "quic", "h3"];
+
+    fn take() -> Self {
+        Self(listen_fds().unwrap_or_else(|e| panic!("could not take the inherited sockets: {e}")))
+    }
+
+    /// Takes the sockets for plain DNS over UDP and TCP
+    fn take_dns(&mut self) -> Vec<InheritedSocket> {
+        self.take_where(|name| !Self::LISTENER_NAMES.contains(&name))
+    }
+
+    /// Takes the TCP listeners with the name, which must be registered in the runtime
+    #[cfg(any(feature = "dns-over-tls", feature = "dns-over-https"))]
+    fn take_listeners(&mut self, name: &str) -> Vec<TcpListener> {
+        self.take_where(|n| n == name)
+            .into_iter()
+            .map(|socket| match socket {
+                InheritedSocket::Tcp(listener) => TcpListener::from_std(listener)
+                    .unwrap_or_else(|e| panic!("could not register {name} socket: {e}")),
+                socket => panic!("the inherited {name} socket is not a TCP listener: {socket:?}"),
+            })
+            .collect()
+    }
+
+    /// Takes the UDP sockets with the name, which must be registered in the runtime
+    #[cfg(any(feature = "dns-over-quic", feature = "dns-over-h3"))]
+    fn take_sockets(&mut self, name: &str) -> Vec<UdpSocket> {
+        self.take_where(|n| n == name)
+            .into_iter()
+            .map(|socket| match socket {
+                InheritedSocket::Udp(socket) => UdpSocket::from_std(socket)
+                    .unwrap_or_else(|e| panic!("could not register {name} socket: {e}")),
+                socket => panic!("the inherited {name} socket is not a UDP socket: {socket:?}"),
+            })
+            .collect()
+    }
+
+    fn take_where(&mut self, mut f: impl FnMut(&str) -> bool) -> Vec<InheritedSocket> {
+        let (taken, kept) = std::mem::take(&mut self.0)
+            .into_iter()
+            .partition(|(name, _)| f(name));
+        self.0 = kept;
+        taken.into_iter().map(|(_, socket)| socket).collect()
+    }
+
+    /// The sockets of the listeners which are not enabled stay unused, e.g. without `tls_cert`
+    fn warn_unused(self) {
+        for (name, socket) in self.0 {
+            warn!("the inherited {name} socket is not used: {socket:?}");
+        }
+    }
+}
+
+/// Returns the inherited TCP listeners with the name, or binds the addresses if there are none
+#[cfg(any(feature = "dns-over-tls", feature = "dns-over-https"))]
+fn tcp_listeners(
+    name: &str,
+    protocol: &str,
+    sockaddrs: &[SocketAddr],
+    inherited_sockets: &mut InheritedSockets,
+    runtime: &mut runtime::Runtime,
+) -> Vec<TcpListener> {
+    let listeners = {
+        let _guard = runtime.enter();
+        inherited_sockets.take_listeners(name)
+    };
+    if !listeners.is_empty() {
+        return listeners;
+    }
+
+    if sockaddrs.is_empty() {
+        warn!(
+            "a tls certificate was specified, but no {protocol} addresses configured to listen on"
+        );
+    }
+
+    sockaddrs
+        .iter()
+        .map(|sockaddr| {
+            info!("binding {protocol} to {:?}", sockaddr);
+            runtime
+                .block_on(TcpListener::bind(sockaddr))
+                .unwrap_or_else(|_| panic!("could not bind to {name}: {sockaddr}"))
+        })
+        .collect()
+}
+
+/// Returns the inherited UDP sockets with the name, or binds the addresses if there are none
+#[cfg(any(feature = "dns-over-quic", feature = "dns-over-h3"))]
+fn udp_sockets(
+    name: &str,
+    protocol: &str,
+    sockaddrs: &[SocketAddr],
+    inherited_sockets: &mut InheritedSockets,
+    runtime: &mut runtime::Runtime,
+) -> Vec<UdpSocket> {
+    let sockets = {
+        let _guard = runtime.enter();
+        inherited_sockets.take_sockets(name)
+    };
+    if !sockets.is_empty() {
+        return sockets;
+    }
+
+    if sockaddrs.is_empty() {
+        warn!(
+            "a tls certificate was specified, but no {protocol} addresses configured to listen on"
+        );
+    }
+
+    sockaddrs
+        .iter()
+        .map(|sockaddr| {
+            info!("binding {protocol} to {:?}", sockaddr);
+            runtime
+                .block_on(UdpSocket::bind(sockaddr))
+                .unwrap_or_else(|_| panic!("could not bind to {name}: {sockaddr}"))
+        })
+        .collect()
+}
+
+/// Registers the XDP sockets for the first address, if configured, and returns the addresses
+///  which are left for the UDP sockets
+#[cfg(all(feature = "xdp", target_os = "linux"))]
+fn register_xdp<'a>(
+    server: &mut ServerFuture<Catalog>,
+    config: &Config,
+    sockaddrs: &'a [SocketAddr],
+    runtime: &mut runtime::Runtime,
+) -> &'a [SocketAddr] {
+    let (Some(xdp_config), Some((udp_socket, rest))) = (config.get_xdp(), sockaddrs.split_first())
+    else {
+        return sockaddrs;
+    };
+
+    info!(
+        "binding XDP on {} to {:?}",
+        xdp_config.get_interface(),
+        udp_socket
+    );
+    let configs = xdp_config.to_socket_configs(udp_socket.port());
+    let _guard = runtime.enter();
+    let udp_addr = server
+        .register_xdp_sockets(&configs, *udp_socket)
+        .unwrap_or_else(|err| panic!("could not bind XDP sockets for {udp_socket}: {err}"));
+
+    info!(
+        "listening for UDP on {:?} with {} XDP sockets",
+        udp_addr,
+        configs.len()
+    );
+    rest
+}
+
+#[cfg(not(all(feature = "xdp", target_os = "linux")))]
+fn register_xdp<'a>(
+    _server: &mut ServerFuture<Catalog>,
+    config: &Config,
+    sockaddrs: &'a [SocketAddr],
+    _runtime: &mut runtime::Runtime,
+) -> &'a [SocketAddr] {
+    if config.get_xdp().is_some() {
+        warn!("xdp is configured, but hickory-dns was built without the xdp feature for linux");
+    }
+    sockaddrs
+}
+
+/// The interval at which TLS certificates are reloaded, if their files changed
+#[cfg(feature = "dns-over-rustls")]
+const CERT_RELOAD_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Loads the default `tls_cert` and the `tls_certs` selected by the SNI of clients, which are
+///  reloaded when their files change
+#[cfg(feature = "dns-over-rustls")]
+fn config_cert_resolver(
+    config: &Config,
+    tls_cert_config: &TlsCertConfig,
+    zone_dir: &Path,
+    runtime: &mut runtime::Runtime,
+) -> Arc<SniCertResolver> {
+    let mut cert_resolver = SniCertResolver::new();
+
+    let tls_cert_configs = std::iter::once(tls_cert_config).chain(config.get_tls_certs());
+    for (i, tls_cert_config) in tls_cert_configs.enumerate() {
+        let names: Vec<String> = tls_cert_config
+            .get_endpoint_name()
+            .map(str::to_string)
+            .into_iter()
+            .collect();
+        if names.is_empty() && i > 0 {
+            warn!(
+                "the tls certificate {:?} has no endpoint_name, it is never selected",
+                tls_cert_config.get_path()
+            );
+        }
+
+        info!(
+            "loading cert for DNS over TLS named {:?} from {:?}",
+            names,
+            tls_cert_config.get_path()
+        );
+        let files = std::iter::once(tls_cert_config.get_path())
+            .chain(tls_cert_config.get_private_key())
+            .map(|path| zone_dir.join(path))
+            .collect();
+        let load = {
+            let zone_dir = zone_dir.to_owned();
+            let tls_cert_config = tls_cert_config.clone();
+            Box::new(move || dnssec::load_cert(&zone_dir, &tls_cert_config))
+        };
+
+        cert_resolver
+            .add(names, files, load)
+            .expect("error loading tls certificate file");
+    }
+
+    let cert_resolver = Arc::new(cert_resolver);
+    runtime.spawn(Arc::clone(&cert_resolver).reload_every(CERT_RELOAD_INTERVAL));
+    cert_resolver
+}
+
 #[cfg(feature = "dns-over-tls")]
 fn config_tls(
     args: &Cli,
     server: &mut ServerFuture<Catalog>,
     config: &Config,
-    tls_cert_config: &TlsCertConfig,
-    zone_dir: &Path,
+    #[cfg(not(feature = "dns-over-rustls"))] tls_cert_config: &TlsCertConfig,
+    #[cfg(feature = "dns-over-rustls")] cert_resolver: &Arc<SniCertResolver>,
+    #[cfg(not(feature = "dns-over-rustls"))] zone_dir: &Path,
     listen_addrs: &[IpAddr],
+    inherited_sockets: &mut InheritedSockets,
     runtime: &mut runtime::Runtime,
 ) {
-    use futures_util::TryFutureExt;
-
     let tls_listen_port: u16 = args
         .tls_port
         .unwrap_or_else(|| config.get_tls_listen_port());
@@ -522,24 +1178,20 @@ fn config_tls(
         .flat_map(|x| (*x, tls_listen_port).to_socket_addrs().unwrap())
         .collect();
 
-    if tls_sockaddrs.is_empty() {
-        warn!("a tls certificate was specified, but no TLS addresses configured to listen on");
-    }
+    let tls_listeners = tcp_listeners("tls", "TLS", &tls_sockaddrs, inherited_sockets, runtime);
 
-    for tls_listener in &tls_sockaddrs {
-        info!(
-            "loading cert for DNS over TLS: {:?}",
-            tls_cert_config.get_path()
-        );
-
-        let tls_cert = dnssec::load_cert(zone_dir, tls_cert_config)
-            .expect("error loading tls certificate file");
+    server.set_proxy_protocol(config.get_tls_proxy_protocol());
+    for tls_listener in tls_listeners {
+        #[cfg(not(feature = "dns-over-rustls"))]
+        let tls_cert = {
+            info!(
+                "loading cert for DNS over TLS: {:?}",
+                tls_cert_config.get_path()
+            );
 
-        info!("binding TLS to {:?}", tls_listener);
-        let tls_listener = runtime.block_on(
-            TcpListener::bind(tls_listener)
-                .unwrap_or_else(|_| panic!("could not bind to tls: {}", tls_listener)),
-        );
+            dnssec::load_cert(zone_dir, tls_cert_config)
+                .expect("error loading tls certificate file")
+        };
 
         info!(
             "listening for TLS on {:?}",
@@ -549,9 +1201,16 @@ fn config_tls(
         );
 
         let _guard = runtime.enter();
-        server
-            .register_tls_listener(tls_listener, config.get_tcp_request_timeout(), tls_cert)
-            .expect("could not register TLS listener");
+        let timeout = config.get_tcp_request_timeout();
+        #[cfg(feature = "dns-over-rustls")]
+        let result = server.register_tls_listener_with_cert_resolver(
+            tls_listener,
+            timeout,
+            Arc::clone(cert_resolver) as _,
+        );
+        #[cfg(not(feature = "dns-over-rustls"))]
+        let result = server.register_tls_listener(tls_listener, timeout, tls_cert);
+        result.expect("could not register TLS listener");
     }
 }
 
@@ -561,12 +1220,12 @@ fn config_https(
     server: &mut ServerFuture<Catalog>,
     config: &Config,
     tls_cert_config: &TlsCertConfig,
+    cert_resolver: &Arc<SniCertResolver>,
     zone_dir: &Path,
     listen_addrs: &[IpAddr],
+    inherited_sockets: &mut InheritedSockets,
     runtime: &mut runtime::Runtime,
 ) {
-    use futures_util::TryFutureExt;
-
     let https_listen_port: u16 = args
         .https_port
         .unwrap_or_else(|| config.get_https_listen_port());
@@ -575,33 +1234,28 @@ fn config_https(
         .flat_map(|x| (*x, https_listen_port).to_socket_addrs().unwrap())
         .collect();
 
-    if https_sockaddrs.is_empty() {
-        warn!("a tls certificate was specified, but no HTTPS addresses configured to listen on");
-    }
-
-    for https_listener in &https_sockaddrs {
-        if let Some(endpoint_name) = tls_cert_config.get_endpoint_name() {
-            info!(
-                "loading cert for DNS over TLS named {} from {:?}",
-                endpoint_name,
-                tls_cert_config.get_path()
-            );
-        } else {
-            info!(
-                "loading cert for DNS over TLS from {:?}",
-                tls_cert_config.get_path()
-            );
-        }
-        // TODO: see about modifying native_tls to impl Clone for Pkcs12
-        let tls_cert = dnssec::load_cert(zone_dir, tls_cert_config)
-            .expect("error loading tls certificate file");
-
-        info!("binding HTTPS to {:?}", https_listener);
-        let https_listener = runtime.block_on(
-            TcpListener::bind(https_listener)
-                .unwrap_or_else(|_| panic!("could not bind to tls: {}", https_listener)),
-        );
+    let https_listeners = tcp_listeners(
+        "https",
+        "HTTPS",
+        &https_sockaddrs,
+        inherited_sockets,
+        runtime,
+    );
+
+    let https_auth = config.get_https_auth().map(|auth_config| {
+        info!("enabling authentication for HTTPS");
+        let auth = HttpsAuth::try_from_config(auth_config, Some(zone_dir))
+            .unwrap_or_else(|e| panic!("could not load https_auth: {e}"));
+        Arc::new(auth)
+    });
+
+    // requests may be for the name of any of the certificates
+    let endpoint_name = match config.get_tls_certs() {
+        [] => tls_cert_config.get_endpoint_name().map(|s| s.to_string()),
+        _ => None,
+    };
 
+    for https_listener in https_listeners {
         info!(
             "listening for HTTPS on {:?}",
             https_listener
@@ -611,11 +1265,12 @@ fn config_https(
 
         let _guard = runtime.enter();
         server
-            .register_https_listener(
+            .register_https_listener_with_cert_resolver(
                 https_listener,
                 config.get_tcp_request_timeout(),
-                tls_cert,
-                tls_cert_config.get_endpoint_name().map(|s| s.to_string()),
+                Arc::clone(cert_resolver) as _,
+                endpoint_name.clone(),
+                https_auth.clone(),
             )
             .expect("could not register HTTPS listener");
     }
@@ -627,12 +1282,11 @@ fn config_quic(
     server: &mut ServerFuture<Catalog>,
     config: &Config,
     tls_cert_config: &TlsCertConfig,
-    zone_dir: &Path,
+    cert_resolver: &Arc<SniCertResolver>,
     listen_addrs: &[IpAddr],
+    inherited_sockets: &mut InheritedSockets,
     runtime: &mut runtime::Runtime,
 ) {
-    use futures_util::TryFutureExt;
-
     let quic_listen_port: u16 = args
         .quic_port
         .unwrap_or_else(|| config.get_quic_listen_port());
@@ -641,33 +1295,9 @@ fn config_quic(
         .flat_map(|x| (*x, quic_listen_port).to_socket_addrs().unwrap())
         .collect();
 
-    if quic_sockaddrs.is_empty() {
-        warn!("a tls certificate was specified, but no QUIC addresses configured to listen on");
-    }
-
-    for quic_listener in &quic_sockaddrs {
-        if let Some(endpoint_name) = tls_cert_config.get_endpoint_name() {
-            info!(
-                "loading cert for DNS over QUIC named {} from {:?}",
-                endpoint_name,
-                tls_cert_config.get_path()
-            );
-        } else {
-            info!(
-                "loading cert for DNS over QUIC from {:?}",
-                tls_cert_config.get_path()
-            );
-        }
-        // TODO: see about modifying native_tls to impl Clone for Pkcs12
-        let tls_cert = dnssec::load_cert(zone_dir, tls_cert_config)
-            .expect("error loading tls certificate file");
-
-        info!("binding QUIC to {:?}", quic_listener);
-        let quic_listener = runtime.block_on(
-            UdpSocket::bind(quic_listener)
-                .unwrap_or_else(|_| panic!("could not bind to tls: {}", quic_listener)),
-        );
+    let quic_listeners = udp_sockets("quic", "QUIC", &quic_sockaddrs, inherited_sockets, runtime);
 
+    for quic_listener in quic_listeners {
         info!(
             "listening for QUIC on {:?}",
             quic_listener
@@ -677,16 +1307,119 @@ fn config_quic(
 
         let _guard = runtime.enter();
         server
-            .register_quic_listener(
+            .register_quic_listener_with_cert_resolver(
                 quic_listener,
                 config.get_tcp_request_timeout(),
-                tls_cert,
+                Arc::clone(cert_resolver) as _,
                 tls_cert_config.get_endpoint_name().map(|s| s.to_string()),
             )
             .expect("could not register QUIC listener");
     }
 }
 
+#[cfg(feature = "dns-over-h3")]
+fn config_h3(
+    args: &Cli,
+    server: &mut ServerFuture<Catalog>,
+    config: &Config,
+    tls_cert_config: &TlsCertConfig,
+    cert_resolver: &Arc<SniCertResolver>,
+    listen_addrs: &[IpAddr],
+    inherited_sockets: &mut InheritedSockets,
+    runtime: &mut runtime::Runtime,
+) {
+    let h3_listen_port: u16 = args.h3_port.unwrap_or_else(|| config.get_h3_listen_port());
+    let h3_sockaddrs: Vec<SocketAddr> = listen_addrs
+        .iter()
+        .flat_map(|x| (*x, h3_listen_port).to_socket_addrs().unwrap())
+        .collect();
+
+    let h3_listeners = udp_sockets("h3", "HTTP/3", &h3_sockaddrs, inherited_sockets, runtime);
+
+    for h3_listener in h3_listeners {
+        info!(
+            "listening for HTTP/3 on {:?}",
+            h3_listener
+                .local_addr()
+                .expect("could not lookup local address")
+        );
+
+        let _guard = runtime.enter();
+        server
+            .register_h3_listener_with_cert_resolver(
+                h3_listener,
+                config.get_tcp_request_timeout(),
+                Arc::clone(cert_resolver) as _,
+                tls_cert_config.get_endpoint_name().map(|s| s.to_string()),
+                config.get_h3_max_concurrent_streams(),
+            )
+            .expect("could not register HTTP/3 listener");
+    }
+}
+
+/// The interval at which the records of expired DHCP leases are removed
+const DHCP_EXPIRY_INTERVAL: Duration = Duration::from_secs(10);
+
+fn config_dhcp(
+    config: &Config,
+    zone_dir: &Path,
+    dhcp_leases: Arc<DhcpLeases>,
+    runtime: &mut runtime::Runtime,
+) {
+    let dhcp_config = config.get_dhcp().expect("dhcp is configured");
+    runtime.spawn(Arc::clone(&dhcp_leases).remove_expired_every(DHCP_EXPIRY_INTERVAL));
+
+    if let Some(lease_files) = dhcp_config.lease_files(zone_dir) {
+        for source in lease_files.sources() {
+            info!("reading dhcp leases from {:?}", source.path);
+        }
+        runtime.spawn(
+            Arc::clone(&dhcp_leases)
+                .watch_lease_files(lease_files, dhcp_config.get_reload_interval()),
+        );
+    }
+
+    let Some(socket_path) = dhcp_config.get_socket_path() else {
+        return;
+    };
+
+    #[cfg(unix)]
+    {
+        use tokio::net::UnixListener;
+
+        // a stale socket of an earlier run prevents binding
+        if socket_path.exists() {
+            std::fs::remove_file(socket_path).unwrap_or_else(|e| {
+                panic!(
+                    "could not remove dhcp socket {}: {e}",
+                    socket_path.display()
+                )
+            });
+        }
+
+        info!("binding dhcp lease events to {:?}", socket_path);
+        let _guard = runtime.enter();
+        let listener = UnixListener::bind(socket_path).unwrap_or_else(|e| {
+            panic!(
+                "could not bind to dhcp socket {}: {e}",
+                socket_path.display()
+            )
+        });
+
+        runtime.spawn(async move {
+            if let Err(e) = dhcp_leases.serve(listener).await {
+                error!("dhcp lease events failed: {}", e);
+            }
+        });
+    }
+
+    #[cfg(not(unix))]
+    warn!(
+        "dhcp lease events are only accepted on unix sockets, {} is not served",
+        socket_path.display()
+    );
+}
+
 fn banner() {
     #[cfg(feature = "ascii-art")]
     const HICKORY_DNS_LOGO: &str = include_str!("hickory-dns.ascii");
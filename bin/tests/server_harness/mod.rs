@@ -100,7 +100,8 @@ where
         .arg(&format!("--port={}", 0))
         .arg(&format!("--tls-port={}", 0))
         .arg(&format!("--https-port={}", 0))
-        .arg(&format!("--quic-port={}", 0));
+        .arg(&format!("--quic-port={}", 0))
+        .arg("--h3-port=0");
 
     println!("named cli options: {command:#?}");
 
@@ -154,7 +155,7 @@ where
 
     // Search strings for the ports used during testing
     let addr_regex = Regex::new(
-        r"listening for (UDP|TCP|TLS|HTTPS|QUIC) on ((?:(?:0\.0\.0\.0)|(?:\[::\])):\d+)",
+        r"listening for (UDP|TCP|TLS|HTTPS|QUIC|HTTP/3) on ((?:(?:0\.0\.0\.0)|(?:\[::\])):\d+)",
     )
     .unwrap();
 
@@ -185,6 +186,7 @@ where
                 "TLS" => socket_ports.put(Protocol::Tls, socket_addr),
                 "HTTPS" => socket_ports.put(Protocol::Https, socket_addr),
                 "QUIC" => socket_ports.put(Protocol::Quic, socket_addr),
+                "HTTP/3" => socket_ports.put(Protocol::H3, socket_addr),
                 _ => panic!("unsupported protocol: {proto}"),
             }
         } else if output.contains("awaiting connections...") {
@@ -0,0 +1,123 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Error types for the crate
+
+#![deny(missing_docs)]
+
+use std::{fmt, io, net::SocketAddr};
+
+use enum_as_inner::EnumAsInner;
+use thiserror::Error;
+
+use crate::{
+    client::error::ClientError,
+    proto::{error::ProtoError, op::ResponseCode, rr::Name},
+    resolver::error::ResolveError,
+};
+
+/// The error kind for errors that get returned in the crate
+#[derive(Debug, EnumAsInner, Error)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// An error with an arbitrary message, referenced as &'static str
+    #[error("{0}")]
+    Message(&'static str),
+
+    /// An error with an arbitrary message, stored as String
+    #[error("{0}")]
+    Msg(String),
+
+    /// An error got returned by the hickory-client crate
+    #[error("client error: {0}")]
+    Client(#[from] ClientError),
+
+    /// An error got returned by the hickory-proto crate
+    #[error("proto error: {0}")]
+    Proto(#[from] ProtoError),
+
+    /// An error got returned by the hickory-resolver crate
+    #[error("resolve error: {0}")]
+    Resolve(#[from] ResolveError),
+
+    /// The name server refused to change the challenge record
+    #[error("update of {name} rejected: {response_code}")]
+    Rejected {
+        /// Name of the challenge record
+        name: Name,
+        /// The response code of the name server
+        response_code: ResponseCode,
+    },
+
+    /// The challenge record was not served by all authoritative name servers in time
+    #[error("{name} not propagated to: {pending:?}")]
+    NotPropagated {
+        /// Name of the challenge record
+        name: Name,
+        /// The name servers which did not serve the record yet
+        pending: Vec<SocketAddr>,
+    },
+}
+
+/// The error type for errors that get returned in the crate
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub struct Error {
+    /// Kind of error that ocurred
+    pub kind: Box<ErrorKind>,
+}
+
+impl Error {
+    /// Get the kind of the error
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.kind, f)
+    }
+}
+
+impl<E> From<E> for Error
+where
+    E: Into<ErrorKind>,
+{
+    fn from(error: E) -> Self {
+        Self {
+            kind: Box::new(error.into()),
+        }
+    }
+}
+
+impl From<&'static str> for Error {
+    fn from(msg: &'static str) -> Self {
+        ErrorKind::Message(msg).into()
+    }
+}
+
+impl From<String> for Error {
+    fn from(msg: String) -> Self {
+        ErrorKind::Msg(msg).into()
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self {
+        match *e.kind() {
+            ErrorKind::NotPropagated { .. } => Self::new(io::ErrorKind::TimedOut, e),
+            _ => Self::new(io::ErrorKind::Other, e),
+        }
+    }
+}
+
+impl From<Error> for String {
+    fn from(e: Error) -> Self {
+        e.to_string()
+    }
+}
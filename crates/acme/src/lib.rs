@@ -0,0 +1,72 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Automation of DNS-01 challenges of ACME, [RFC 8555](https://www.rfc-editor.org/rfc/rfc8555#section-8.4)
+//!
+//! A [`Dns01Solver`] publishes the `_acme-challenge` TXT record of a domain with a
+//!  [`ChallengePublisher`], waits until all authoritative name servers of the zone serve it, and
+//!  removes it again once the ACME server validated the challenge.
+//!
+//! ```rust,no_run
+//! # async fn solve(client: hickory_client::client::AsyncClient) -> Result<(), hickory_acme::Error> {
+//! use std::str::FromStr;
+//!
+//! use hickory_acme::{authoritative_servers, Dns01Solver, DynamicUpdate};
+//! use hickory_acme::proto::rr::Name;
+//! use hickory_acme::resolver::config::{ResolverConfig, ResolverOpts};
+//! use hickory_acme::resolver::TokioAsyncResolver;
+//!
+//! let zone = Name::from_str("example.com.")?;
+//! let resolver = TokioAsyncResolver::tokio(ResolverConfig::default(), ResolverOpts::default());
+//! let servers = authoritative_servers(&zone, &resolver).await?;
+//!
+//! // the client is connected to the primary name server of the zone
+//! let solver = Dns01Solver::new(DynamicUpdate::new(client, zone), servers);
+//! let challenge = solver
+//!     .present(&Name::from_str("www.example.com.")?, "digest of the key authorization")
+//!     .await?;
+//!
+//! // ... let the ACME server validate the challenge
+//!
+//! solver.cleanup(challenge).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+#![warn(
+    clippy::default_trait_access,
+    clippy::dbg_macro,
+    clippy::print_stdout,
+    clippy::unimplemented,
+    missing_copy_implementations,
+    missing_docs,
+    non_snake_case,
+    non_upper_case_globals,
+    rust_2018_idioms,
+    unreachable_pub
+)]
+#![allow(clippy::single_component_path_imports)]
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+pub mod error;
+mod propagation;
+mod publisher;
+mod solver;
+
+pub use error::{Error, ErrorKind};
+pub use hickory_client as client;
+pub use hickory_proto as proto;
+pub use hickory_resolver as resolver;
+#[cfg(feature = "server")]
+#[cfg_attr(docsrs, doc(cfg(feature = "server")))]
+pub use hickory_server as server;
+pub use propagation::{authoritative_servers, wait_for_propagation, Propagation};
+#[cfg(feature = "server")]
+#[cfg_attr(docsrs, doc(cfg(feature = "server")))]
+pub use publisher::InProcessAuthority;
+pub use publisher::{ChallengePublisher, DynamicUpdate};
+pub use solver::{challenge_name, Dns01Challenge, Dns01Solver};
@@ -0,0 +1,145 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Waiting for the authoritative name servers to serve the challenge records
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use futures_util::future;
+use tokio::net::UdpSocket;
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+use crate::{
+    client::{client::AsyncClient, udp::UdpClientStream},
+    error::{Error, ErrorKind},
+    proto::{
+        error::ProtoError,
+        op::Query,
+        rr::{Name, Record},
+        xfer::{DnsHandle, DnsRequestOptions, FirstAnswer},
+    },
+    resolver::TokioAsyncResolver,
+};
+
+/// How long and how often the authoritative name servers are queried for the challenge records
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub struct Propagation {
+    /// Time after which to give up, when not all name servers serve the record
+    pub timeout: Duration,
+    /// Time between queries to the name servers which did not serve the record yet
+    pub interval: Duration,
+    /// Time to wait for the response of a name server
+    pub query_timeout: Duration,
+}
+
+impl Default for Propagation {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(120),
+            interval: Duration::from_secs(5),
+            query_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Looks up the addresses of the name servers of the zone
+///
+/// Name servers whose addresses can not be looked up are skipped, it is an error if no addresses
+///  are found at all.
+pub async fn authoritative_servers(
+    zone: &Name,
+    resolver: &TokioAsyncResolver,
+) -> Result<Vec<SocketAddr>, Error> {
+    let mut servers = Vec::new();
+    for name_server in resolver.ns_lookup(zone.clone()).await?.iter() {
+        match resolver.lookup_ip(name_server.0.clone()).await {
+            Ok(ips) => servers.extend(ips.iter().map(|ip| SocketAddr::new(ip, 53))),
+            Err(e) => warn!("failed to look up name server {}: {}", name_server, e),
+        }
+    }
+
+    if servers.is_empty() {
+        return Err(format!("no name server addresses found for {zone}").into());
+    }
+
+    Ok(servers)
+}
+
+/// Queries the name servers directly until all of them serve the record
+///
+/// The name servers are queried without recursion, so they must be authoritative for the zone of
+///  the record.
+pub async fn wait_for_propagation(
+    record: &Record,
+    servers: &[SocketAddr],
+    propagation: &Propagation,
+) -> Result<(), Error> {
+    let deadline = Instant::now() + propagation.timeout;
+    let mut pending = servers.to_vec();
+
+    loop {
+        let served = future::join_all(
+            pending
+                .iter()
+                .map(|server| serves(record, *server, propagation.query_timeout)),
+        )
+        .await;
+
+        let mut served = served.into_iter();
+        pending.retain(|server| match served.next() {
+            Some(Ok(true)) => {
+                debug!("{} serves {}", server, record);
+                false
+            }
+            Some(Ok(false)) => true,
+            Some(Err(e)) => {
+                debug!("querying {} for {} failed: {}", server, record.name(), e);
+                true
+            }
+            None => true,
+        });
+
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        if Instant::now() + propagation.interval > deadline {
+            return Err(ErrorKind::NotPropagated {
+                name: record.name().clone(),
+                pending,
+            }
+            .into());
+        }
+
+        tokio::time::sleep(propagation.interval).await;
+    }
+}
+
+/// Returns true if the name server answers with the record
+async fn serves(
+    record: &Record,
+    server: SocketAddr,
+    timeout: Duration,
+) -> Result<bool, ProtoError> {
+    let stream = UdpClientStream::<UdpSocket>::with_timeout(server, timeout);
+    let (client, bg) = AsyncClient::connect(stream).await?;
+    // the background ends when the client is dropped
+    tokio::spawn(bg);
+
+    let mut options = DnsRequestOptions::default();
+    options.recursion_desired = false;
+
+    let query = Query::query(record.name().clone(), record.record_type());
+    let response = client.lookup(query, options).first_answer().await?;
+
+    Ok(response.answers().iter().any(|answer| {
+        answer.record_type() == record.record_type() && answer.data() == record.data()
+    }))
+}
@@ -0,0 +1,140 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Publishing and removing the challenge records
+
+#[cfg(feature = "server")]
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tracing::debug;
+
+use crate::{
+    client::client::ClientHandle,
+    error::{Error, ErrorKind},
+    proto::{
+        op::ResponseCode,
+        rr::{Name, Record},
+        xfer::DnsResponse,
+    },
+};
+#[cfg(feature = "server")]
+use crate::{
+    proto::rr::{LowerName, RrKey},
+    server::store::in_memory::InMemoryAuthority,
+};
+
+/// Publishes and removes the TXT records of challenges
+///
+/// There may be several challenges for the same name at once, e.g. for a domain and its wildcard,
+///  so records are added to and removed from the existing RRset.
+#[async_trait]
+pub trait ChallengePublisher: Send + Sync {
+    /// Adds the record to the zone
+    async fn publish(&self, record: &Record) -> Result<(), Error>;
+
+    /// Removes the record from the zone, which succeeds if the record does not exist
+    async fn remove(&self, record: &Record) -> Result<(), Error>;
+}
+
+/// Publishes the records with dynamic DNS updates, [RFC 2136](https://tools.ietf.org/html/rfc2136)
+///
+/// The client must be connected to the primary name server of the zone, and sign the requests if
+///  the name server requires it, e.g. with SIG(0).
+#[derive(Clone)]
+pub struct DynamicUpdate<C: ClientHandle> {
+    client: C,
+    zone: Name,
+}
+
+impl<C: ClientHandle> DynamicUpdate<C> {
+    /// Creates a publisher updating `zone` through the client
+    pub fn new(client: C, zone: Name) -> Self {
+        Self { client, zone }
+    }
+}
+
+#[async_trait]
+impl<C: ClientHandle> ChallengePublisher for DynamicUpdate<C> {
+    async fn publish(&self, record: &Record) -> Result<(), Error> {
+        debug!("publishing {} in {} with update", record, self.zone);
+        let response = self
+            .client
+            .clone()
+            .append(record.clone(), self.zone.clone(), false)
+            .await?;
+        check_response(record, &response)
+    }
+
+    async fn remove(&self, record: &Record) -> Result<(), Error> {
+        debug!("removing {} from {} with update", record, self.zone);
+        let response = self
+            .client
+            .clone()
+            .delete_by_rdata(record.clone(), self.zone.clone())
+            .await?;
+        check_response(record, &response)
+    }
+}
+
+fn check_response(record: &Record, response: &DnsResponse) -> Result<(), Error> {
+    match response.response_code() {
+        ResponseCode::NoError => Ok(()),
+        response_code => Err(ErrorKind::Rejected {
+            name: record.name().clone(),
+            response_code,
+        }
+        .into()),
+    }
+}
+
+/// Publishes the records directly in an authority served by this process
+///
+/// The authority must be shared with the `Catalog`, e.g. as `Box::new(authority.clone())`. The
+///  zone is not re-signed, so this is not suitable for zones signed with DNSSEC.
+#[cfg(feature = "server")]
+#[cfg_attr(docsrs, doc(cfg(feature = "server")))]
+#[derive(Clone)]
+pub struct InProcessAuthority {
+    authority: Arc<InMemoryAuthority>,
+}
+
+#[cfg(feature = "server")]
+impl InProcessAuthority {
+    /// Creates a publisher changing the records of the authority
+    pub fn new(authority: Arc<InMemoryAuthority>) -> Self {
+        Self { authority }
+    }
+}
+
+#[cfg(feature = "server")]
+#[async_trait]
+impl ChallengePublisher for InProcessAuthority {
+    async fn publish(&self, record: &Record) -> Result<(), Error> {
+        debug!("publishing {} in authority", record);
+        let serial = self.authority.serial().await;
+        self.authority.upsert(record.clone(), serial).await;
+        Ok(())
+    }
+
+    async fn remove(&self, record: &Record) -> Result<(), Error> {
+        debug!("removing {} from authority", record);
+        let serial = self.authority.serial().await;
+        let key = RrKey::new(LowerName::new(record.name()), record.record_type());
+
+        let mut records = self.authority.records_mut().await;
+        if let Some(rrset) = records.get_mut(&key) {
+            let rrset = Arc::make_mut(rrset);
+            rrset.remove(record, serial);
+            if rrset.is_empty() {
+                records.remove(&key);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Solving DNS-01 challenges
+
+use std::net::SocketAddr;
+
+use tracing::{debug, warn};
+
+use crate::{
+    error::Error,
+    propagation::{wait_for_propagation, Propagation},
+    proto::{
+        error::ProtoError,
+        rr::{rdata::TXT, Name, RData, Record},
+    },
+    publisher::ChallengePublisher,
+};
+
+/// TTL of the challenge records by default, short as they are only needed during validation
+const DEFAULT_TTL: u32 = 60;
+
+/// Returns the name of the challenge record for the domain, `_acme-challenge.<domain>`
+///
+/// The challenges of wildcard domains are at the base domain, [RFC 8555, section 8.4](https://www.rfc-editor.org/rfc/rfc8555#section-8.4).
+pub fn challenge_name(domain: &Name) -> Result<Name, ProtoError> {
+    let domain = if domain.is_wildcard() {
+        domain.base_name()
+    } else {
+        domain.clone()
+    };
+
+    Name::from_ascii("_acme-challenge")?.append_domain(&domain)
+}
+
+/// A challenge record which was published, and must be cleaned up after the validation
+#[derive(Clone, Debug)]
+pub struct Dns01Challenge {
+    record: Record,
+}
+
+impl Dns01Challenge {
+    /// The published TXT record
+    pub fn record(&self) -> &Record {
+        &self.record
+    }
+}
+
+/// Publishes the records of DNS-01 challenges, and waits for their propagation
+pub struct Dns01Solver<P: ChallengePublisher> {
+    publisher: P,
+    servers: Vec<SocketAddr>,
+    ttl: u32,
+    propagation: Propagation,
+}
+
+impl<P: ChallengePublisher> Dns01Solver<P> {
+    /// Creates a solver publishing with `publisher`, and waiting for the propagation to `servers`
+    ///
+    /// # Arguments
+    ///
+    /// * `publisher` - publishes the records in the zone
+    /// * `servers` - the authoritative name servers of the zone, see [`authoritative_servers`]
+    ///
+    /// [`authoritative_servers`]: crate::authoritative_servers
+    pub fn new(publisher: P, servers: Vec<SocketAddr>) -> Self {
+        Self {
+            publisher,
+            servers,
+            ttl: DEFAULT_TTL,
+            propagation: Propagation::default(),
+        }
+    }
+
+    /// Sets the TTL of the challenge records, 60 seconds by default
+    pub fn set_ttl(&mut self, ttl: u32) -> &mut Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Sets how long and how often the name servers are queried for the records
+    pub fn set_propagation(&mut self, propagation: Propagation) -> &mut Self {
+        self.propagation = propagation;
+        self
+    }
+
+    /// Publishes the challenge record of the domain and waits until all name servers serve it
+    ///
+    /// The record is removed again if it does not propagate in time.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - the domain of the identifier to validate, which may be a wildcard
+    /// * `value` - the base64url encoded SHA-256 digest of the key authorization of the challenge
+    pub async fn present(&self, domain: &Name, value: &str) -> Result<Dns01Challenge, Error> {
+        let record = Record::from_rdata(
+            challenge_name(domain)?,
+            self.ttl,
+            RData::TXT(TXT::new(vec![value.to_string()])),
+        );
+
+        self.publisher.publish(&record).await?;
+        debug!("published {}, waiting for propagation", record);
+
+        if let Err(e) = wait_for_propagation(&record, &self.servers, &self.propagation).await {
+            if let Err(remove_error) = self.publisher.remove(&record).await {
+                warn!("failed to remove {}: {}", record, remove_error);
+            }
+            return Err(e);
+        }
+
+        Ok(Dns01Challenge { record })
+    }
+
+    /// Removes the challenge record, after the ACME server validated the challenge
+    pub async fn cleanup(&self, challenge: Dns01Challenge) -> Result<(), Error> {
+        self.publisher.remove(&challenge.record).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_challenge_name() {
+        assert_eq!(
+            challenge_name(&Name::from_str("www.example.com.").unwrap()).unwrap(),
+            Name::from_str("_acme-challenge.www.example.com.").unwrap()
+        );
+        assert_eq!(
+            challenge_name(&Name::from_str("*.example.com.").unwrap()).unwrap(),
+            Name::from_str("_acme-challenge.example.com.").unwrap()
+        );
+    }
+}
@@ -0,0 +1,25 @@
+//! Resolves a name with the async-std runtime, without Tokio
+//!
+//! ```text
+//! cargo run -p async-std-resolver --example lookup -- www.example.com.
+//! ```
+
+use async_std_resolver::config::{ResolverConfig, ResolverOpts};
+use async_std_resolver::resolver;
+
+#[async_std::main]
+async fn main() {
+    let name = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "www.example.com.".to_string());
+
+    let resolver = resolver(ResolverConfig::default(), ResolverOpts::default()).await;
+    match resolver.lookup_ip(name.as_str()).await {
+        Ok(response) => {
+            for ip in response.iter() {
+                println!("{name}: {ip}");
+            }
+        }
+        Err(e) => println!("failed to look up {name}: {e}"),
+    }
+}
@@ -96,8 +96,6 @@
 
 use hickory_resolver::AsyncResolver;
 
-use crate::runtime::AsyncStdConnectionProvider;
-
 mod net;
 mod runtime;
 #[cfg(test)]
@@ -109,6 +107,7 @@ pub use hickory_resolver::error::ResolveError;
 pub use hickory_resolver::lookup;
 pub use hickory_resolver::lookup_ip;
 pub use hickory_resolver::proto;
+pub use runtime::{AsyncStdConnectionProvider, AsyncStdRuntimeHandle, AsyncStdRuntimeProvider};
 
 /// An AsyncResolver used with async_std
 pub type AsyncStdResolver = AsyncResolver<AsyncStdConnectionProvider>;
@@ -20,33 +20,13 @@ use crate::proto::tcp::Connect;
 use crate::proto::udp::UdpSocket;
 use crate::time::AsyncStdTime;
 
-/// The async_std runtime.
+/// The async_std runtime, for the connections, timers and background tasks of the resolver.
 ///
-/// The runtime provides an I/O [driver], task scheduler, [timer], and blocking
-/// pool, necessary for running asynchronous tasks.
+/// This can be combined with a custom `ConnectionProvider`, e.g. with
+/// `GenericConnector<AsyncStdRuntimeProvider>`, the [`AsyncStdConnectionProvider`] is the one used
+/// by the [`AsyncStdResolver`].
 ///
-/// Instances of `AsyncStdRuntime` can be created using [`new`]. However, most
-/// users will use the `#[async_std::main]` annotation on their entry point instead.
-///
-/// See [module level][mod] documentation for more details.
-///
-/// # Shutdown
-///
-/// Shutting down the runtime is done by dropping the value. The current thread
-/// will block until the shut down operation has completed.
-///
-/// * Drain any scheduled work queues.
-/// * Drop any futures that have not yet completed.
-/// * Drop the reactor.
-///
-/// Once the reactor has dropped, any outstanding I/O resources bound to
-/// that reactor will no longer function. Calling any method on them will
-/// result in an error.
-///
-/// [driver]: crate::io::driver
-/// [timer]: crate::time
-/// [mod]: index.html
-/// [`new`]: #method.new
+/// [`AsyncStdResolver`]: crate::AsyncStdResolver
 #[derive(Clone, Copy, Default)]
 pub struct AsyncStdRuntimeProvider;
 
@@ -60,6 +40,7 @@ impl Executor for AsyncStdRuntimeProvider {
     }
 }
 
+/// A handle to the async_std runtime, which spawns the background tasks of the connections
 #[derive(Clone, Copy)]
 pub struct AsyncStdRuntimeHandle;
 impl Spawn for AsyncStdRuntimeHandle {
@@ -97,6 +78,9 @@ impl RuntimeProvider for AsyncStdRuntimeProvider {
     }
 }
 
+/// The ConnectionProvider of the [`AsyncStdResolver`], with the connections on async_std
+///
+/// [`AsyncStdResolver`]: crate::AsyncStdResolver
 #[derive(Clone, Default)]
 pub struct AsyncStdConnectionProvider {
     runtime_provider: AsyncStdRuntimeProvider,
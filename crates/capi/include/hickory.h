@@ -0,0 +1,106 @@
+/*
+ * Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * https://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+
+/*
+ * C API of the Hickory DNS Resolver
+ *
+ * All objects which are returned are owned by the caller, and released with their _free function.
+ * Strings are NUL terminated UTF-8, and are borrowed from the object they belong to.
+ */
+
+#ifndef HICKORY_H
+#define HICKORY_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* the lookup succeeded */
+#define HICKORY_OK 0
+/* an argument is null or not valid */
+#define HICKORY_ERR_INVALID_ARGUMENT -1
+/* the name has no records of the type */
+#define HICKORY_ERR_NO_RECORDS -2
+/* the name servers did not respond in time */
+#define HICKORY_ERR_TIMEOUT -3
+/* the request was canceled */
+#define HICKORY_ERR_CANCELED -4
+/* the lookup failed for another reason */
+#define HICKORY_ERR_RESOLVE -5
+/* a blocking function was called from a callback, which would deadlock */
+#define HICKORY_ERR_IN_CALLBACK -6
+
+typedef struct HickoryResolver HickoryResolver;
+typedef struct HickoryResult HickoryResult;
+typedef struct HickoryRequest HickoryRequest;
+
+typedef struct HickoryConfig {
+    /* addresses of the name servers, "ip" or "ip:port", or NULL for the system configuration */
+    const char *const *name_servers;
+    /* the number of name servers */
+    size_t name_servers_len;
+    /* the timeout of each request in milliseconds, or 0 for the default */
+    uint32_t timeout_ms;
+    /* the number of attempts of each request, or 0 for the default */
+    uint32_t attempts;
+} HickoryConfig;
+
+/*
+ * The callback of a lookup in the background, called exactly once from a thread of the resolver.
+ * The result is NULL unless the status is HICKORY_OK, it is owned by the callback.
+ */
+typedef void (*HickoryCallback)(int status, HickoryResult *result, void *user_data);
+
+/* creates a resolver, the configuration may be NULL for the system configuration */
+int hickory_resolver_new(const HickoryConfig *config, HickoryResolver **out);
+/* frees the resolver, lookups which did not complete are dropped without calling their callback */
+void hickory_resolver_free(HickoryResolver *resolver);
+
+/* blocking lookups, which must not be called from a callback */
+int hickory_resolve_ip(const HickoryResolver *resolver, const char *name, HickoryResult **out);
+int hickory_resolve_txt(const HickoryResolver *resolver, const char *name, HickoryResult **out);
+int hickory_resolve_srv(const HickoryResolver *resolver, const char *name, HickoryResult **out);
+
+/*
+ * lookups in the background, return NULL if an argument is not valid
+ *
+ * The request must be released with hickory_request_cancel or hickory_request_free.
+ */
+HickoryRequest *hickory_resolve_ip_async(const HickoryResolver *resolver, const char *name,
+                                         HickoryCallback callback, void *user_data);
+HickoryRequest *hickory_resolve_txt_async(const HickoryResolver *resolver, const char *name,
+                                          HickoryCallback callback, void *user_data);
+HickoryRequest *hickory_resolve_srv_async(const HickoryResolver *resolver, const char *name,
+                                          HickoryCallback callback, void *user_data);
+
+/* cancels and frees the request, the callback gets HICKORY_ERR_CANCELED unless it was called */
+void hickory_request_cancel(HickoryRequest *request);
+/* frees the request without canceling it, the callback is still called */
+void hickory_request_free(HickoryRequest *request);
+
+/* the number of records of the result */
+size_t hickory_result_len(const HickoryResult *result);
+/* the record at the index in presentation format, or NULL if the index is out of range */
+const char *hickory_result_get(const HickoryResult *result, size_t index);
+/* the fields of the SRV record at the index, any of the outputs may be NULL */
+int hickory_result_srv(const HickoryResult *result, size_t index, uint16_t *priority,
+                       uint16_t *weight, uint16_t *port, const char **target);
+void hickory_result_free(HickoryResult *result);
+
+/* a static description of the status */
+const char *hickory_status_str(int status);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* HICKORY_H */
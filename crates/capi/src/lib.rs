@@ -0,0 +1,728 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! C API of the Hickory DNS Resolver, see `include/hickory.h` for the declarations
+//!
+//! A [`HickoryResolver`] owns a Tokio runtime, on which the lookups are run. Each lookup is either
+//!  blocking, e.g. [`hickory_resolve_ip`], or runs in the background and passes the result to a
+//!  callback, e.g. [`hickory_resolve_ip_async`].
+//!
+//! All objects which are returned are owned by the caller, and released with their `_free`
+//!  function. Strings are NUL terminated UTF-8, and are borrowed from the object they belong to.
+
+#![warn(
+    clippy::default_trait_access,
+    clippy::dbg_macro,
+    clippy::print_stdout,
+    clippy::unimplemented,
+    missing_copy_implementations,
+    missing_docs,
+    non_snake_case,
+    non_upper_case_globals,
+    rust_2018_idioms,
+    unreachable_pub
+)]
+#![allow(clippy::missing_safety_doc)]
+
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::net::SocketAddr;
+use std::ptr;
+use std::time::Duration;
+
+use hickory_resolver::config::{NameServerConfig, Protocol, ResolverConfig, ResolverOpts};
+use hickory_resolver::error::ResolveError;
+use hickory_resolver::proto::error::ProtoErrorKind;
+use hickory_resolver::TokioAsyncResolver;
+use tokio::runtime::{self, Runtime};
+use tokio::sync::oneshot;
+
+/// The lookup succeeded
+pub const HICKORY_OK: c_int = 0;
+/// An argument is null or not valid
+pub const HICKORY_ERR_INVALID_ARGUMENT: c_int = -1;
+/// The name has no records of the type
+pub const HICKORY_ERR_NO_RECORDS: c_int = -2;
+/// The name servers did not respond in time
+pub const HICKORY_ERR_TIMEOUT: c_int = -3;
+/// The request was canceled
+pub const HICKORY_ERR_CANCELED: c_int = -4;
+/// The lookup failed for another reason
+pub const HICKORY_ERR_RESOLVE: c_int = -5;
+/// A blocking function was called from a callback, which would deadlock
+pub const HICKORY_ERR_IN_CALLBACK: c_int = -6;
+
+/// The configuration of a resolver
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct HickoryConfig {
+    /// Addresses of the name servers, `ip` or `ip:port`, or null for the system configuration
+    pub name_servers: *const *const c_char,
+    /// The number of name servers
+    pub name_servers_len: usize,
+    /// The timeout of each request in milliseconds, or 0 for the default
+    pub timeout_ms: u32,
+    /// The number of attempts of each request, or 0 for the default
+    pub attempts: u32,
+}
+
+/// A resolver, with the runtime of its lookups
+pub struct HickoryResolver {
+    runtime: Runtime,
+    resolver: TokioAsyncResolver,
+}
+
+/// The records of a lookup
+#[derive(Debug)]
+pub struct HickoryResult {
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    /// The record in presentation format, e.g. the address
+    text: CString,
+    srv: Option<Srv>,
+}
+
+#[derive(Debug)]
+struct Srv {
+    priority: u16,
+    weight: u16,
+    port: u16,
+    target: CString,
+}
+
+/// A lookup running in the background
+#[derive(Debug)]
+pub struct HickoryRequest {
+    cancel: oneshot::Sender<()>,
+}
+
+/// The callback of a lookup in the background, called exactly once from a thread of the runtime
+///
+/// The result is null unless the status is `HICKORY_OK`, it is owned by the callback.
+pub type HickoryCallback =
+    Option<unsafe extern "C" fn(status: c_int, result: *mut HickoryResult, user_data: *mut c_void)>;
+
+#[derive(Clone, Copy, Debug)]
+enum Kind {
+    Ip,
+    Txt,
+    Srv,
+}
+
+/// Creates a resolver, the configuration may be null for the system configuration
+///
+/// Returns the status, and on success the resolver in `out`.
+#[no_mangle]
+pub unsafe extern "C" fn hickory_resolver_new(
+    config: *const HickoryConfig,
+    out: *mut *mut HickoryResolver,
+) -> c_int {
+    if out.is_null() {
+        return HICKORY_ERR_INVALID_ARGUMENT;
+    }
+    *out = ptr::null_mut();
+
+    let (resolver_config, mut options) = match config.as_ref() {
+        Some(config) if !config.name_servers.is_null() => {
+            match name_servers(config.name_servers, config.name_servers_len) {
+                Some(resolver_config) => (resolver_config, ResolverOpts::default()),
+                None => return HICKORY_ERR_INVALID_ARGUMENT,
+            }
+        }
+        _ => match hickory_resolver::system_conf::read_system_conf() {
+            Ok(conf) => conf,
+            Err(_) => return HICKORY_ERR_RESOLVE,
+        },
+    };
+    if let Some(config) = config.as_ref() {
+        if config.timeout_ms > 0 {
+            options.timeout = Duration::from_millis(config.timeout_ms.into());
+        }
+        if config.attempts > 0 {
+            options.attempts = config.attempts as usize;
+        }
+    }
+
+    let runtime = match runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .thread_name("hickory-capi")
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime,
+        Err(_) => return HICKORY_ERR_RESOLVE,
+    };
+
+    let resolver = {
+        let _guard = runtime.enter();
+        TokioAsyncResolver::tokio(resolver_config, options)
+    };
+    *out = Box::into_raw(Box::new(HickoryResolver { runtime, resolver }));
+    HICKORY_OK
+}
+
+/// Parses the addresses of the name servers, the port defaults to 53
+unsafe fn name_servers(addrs: *const *const c_char, len: usize) -> Option<ResolverConfig> {
+    let mut config = ResolverConfig::new();
+    for i in 0..len {
+        let addr = c_str(*addrs.add(i))?;
+        let socket_addr = addr
+            .parse::<SocketAddr>()
+            .ok()
+            .or_else(|| Some(SocketAddr::new(addr.parse().ok()?, 53)))?;
+
+        config.add_name_server(NameServerConfig::new(socket_addr, Protocol::Udp));
+        config.add_name_server(NameServerConfig::new(socket_addr, Protocol::Tcp));
+    }
+
+    Some(config)
+}
+
+/// Frees the resolver, lookups in the background which did not complete are dropped without
+///  calling their callback
+#[no_mangle]
+pub unsafe extern "C" fn hickory_resolver_free(resolver: *mut HickoryResolver) {
+    if resolver.is_null() {
+        return;
+    }
+
+    // this may be called from a callback, i.e. from the runtime itself
+    let resolver = Box::from_raw(resolver);
+    resolver.runtime.shutdown_background();
+}
+
+/// Looks up the IPv4 and IPv6 addresses of the name, blocking until the result
+#[no_mangle]
+pub unsafe extern "C" fn hickory_resolve_ip(
+    resolver: *const HickoryResolver,
+    name: *const c_char,
+    out: *mut *mut HickoryResult,
+) -> c_int {
+    resolve(resolver, name, Kind::Ip, out)
+}
+
+/// Looks up the TXT records of the name, blocking until the result
+#[no_mangle]
+pub unsafe extern "C" fn hickory_resolve_txt(
+    resolver: *const HickoryResolver,
+    name: *const c_char,
+    out: *mut *mut HickoryResult,
+) -> c_int {
+    resolve(resolver, name, Kind::Txt, out)
+}
+
+/// Looks up the SRV records of the name, blocking until the result
+#[no_mangle]
+pub unsafe extern "C" fn hickory_resolve_srv(
+    resolver: *const HickoryResolver,
+    name: *const c_char,
+    out: *mut *mut HickoryResult,
+) -> c_int {
+    resolve(resolver, name, Kind::Srv, out)
+}
+
+unsafe fn resolve(
+    resolver: *const HickoryResolver,
+    name: *const c_char,
+    kind: Kind,
+    out: *mut *mut HickoryResult,
+) -> c_int {
+    if out.is_null() {
+        return HICKORY_ERR_INVALID_ARGUMENT;
+    }
+    *out = ptr::null_mut();
+
+    let (Some(resolver), Some(name)) = (resolver.as_ref(), c_str(name)) else {
+        return HICKORY_ERR_INVALID_ARGUMENT;
+    };
+    if runtime::Handle::try_current().is_ok() {
+        return HICKORY_ERR_IN_CALLBACK;
+    }
+
+    match resolver
+        .runtime
+        .block_on(lookup(resolver.resolver.clone(), name.to_string(), kind))
+    {
+        Ok(result) => {
+            *out = Box::into_raw(Box::new(result));
+            HICKORY_OK
+        }
+        Err(status) => status,
+    }
+}
+
+/// Looks up the IPv4 and IPv6 addresses of the name in the background
+///
+/// Returns the request, or null if an argument is not valid. The request must be released with
+///  `hickory_request_cancel` or `hickory_request_free`.
+#[no_mangle]
+pub unsafe extern "C" fn hickory_resolve_ip_async(
+    resolver: *const HickoryResolver,
+    name: *const c_char,
+    callback: HickoryCallback,
+    user_data: *mut c_void,
+) -> *mut HickoryRequest {
+    spawn(resolver, name, Kind::Ip, callback, user_data)
+}
+
+/// Looks up the TXT records of the name in the background, see `hickory_resolve_ip_async`
+#[no_mangle]
+pub unsafe extern "C" fn hickory_resolve_txt_async(
+    resolver: *const HickoryResolver,
+    name: *const c_char,
+    callback: HickoryCallback,
+    user_data: *mut c_void,
+) -> *mut HickoryRequest {
+    spawn(resolver, name, Kind::Txt, callback, user_data)
+}
+
+/// Looks up the SRV records of the name in the background, see `hickory_resolve_ip_async`
+#[no_mangle]
+pub unsafe extern "C" fn hickory_resolve_srv_async(
+    resolver: *const HickoryResolver,
+    name: *const c_char,
+    callback: HickoryCallback,
+    user_data: *mut c_void,
+) -> *mut HickoryRequest {
+    spawn(resolver, name, Kind::Srv, callback, user_data)
+}
+
+/// The callback with its data, which the caller promised to be usable from other threads
+struct Callback {
+    callback: unsafe extern "C" fn(c_int, *mut HickoryResult, *mut c_void),
+    user_data: *mut c_void,
+}
+
+unsafe impl Send for Callback {}
+
+impl Callback {
+    fn call(self, result: Result<HickoryResult, c_int>) {
+        let (status, result) = match result {
+            Ok(result) => (HICKORY_OK, Box::into_raw(Box::new(result))),
+            Err(status) => (status, ptr::null_mut()),
+        };
+
+        // SAFETY: the callback and its data are valid until it is called
+        unsafe { (self.callback)(status, result, self.user_data) }
+    }
+}
+
+unsafe fn spawn(
+    resolver: *const HickoryResolver,
+    name: *const c_char,
+    kind: Kind,
+    callback: HickoryCallback,
+    user_data: *mut c_void,
+) -> *mut HickoryRequest {
+    let (Some(resolver), Some(name), Some(callback)) = (resolver.as_ref(), c_str(name), callback)
+    else {
+        return ptr::null_mut();
+    };
+
+    let callback = Callback {
+        callback,
+        user_data,
+    };
+    let lookup = lookup(resolver.resolver.clone(), name.to_string(), kind);
+    let (cancel, canceled) = oneshot::channel();
+    resolver.runtime.spawn(async move {
+        let result = tokio::select! {
+            result = lookup => result,
+            // a request which is freed without canceling continues
+            Ok(()) = canceled => Err(HICKORY_ERR_CANCELED),
+        };
+        callback.call(result);
+    });
+
+    Box::into_raw(Box::new(HickoryRequest { cancel }))
+}
+
+/// Cancels the request and frees it, the callback is called with `HICKORY_ERR_CANCELED` unless it
+///  was called already
+#[no_mangle]
+pub unsafe extern "C" fn hickory_request_cancel(request: *mut HickoryRequest) {
+    if request.is_null() {
+        return;
+    }
+
+    let _ = Box::from_raw(request).cancel.send(());
+}
+
+/// Frees the request without canceling it, the callback is still called
+#[no_mangle]
+pub unsafe extern "C" fn hickory_request_free(request: *mut HickoryRequest) {
+    if !request.is_null() {
+        drop(Box::from_raw(request));
+    }
+}
+
+async fn lookup(
+    resolver: TokioAsyncResolver,
+    name: String,
+    kind: Kind,
+) -> Result<HickoryResult, c_int> {
+    let entries = match kind {
+        Kind::Ip => resolver
+            .lookup_ip(name)
+            .await
+            .map_err(status)?
+            .iter()
+            .map(|ip| Entry::text(ip.to_string()))
+            .collect(),
+        Kind::Txt => resolver
+            .txt_lookup(name)
+            .await
+            .map_err(status)?
+            .iter()
+            .map(|txt| Entry::text(txt.to_string()))
+            .collect(),
+        Kind::Srv => resolver
+            .srv_lookup(name)
+            .await
+            .map_err(status)?
+            .iter()
+            .map(|srv| Entry {
+                text: c_string(srv.to_string()),
+                srv: Some(Srv {
+                    priority: srv.priority(),
+                    weight: srv.weight(),
+                    port: srv.port(),
+                    target: c_string(srv.target().to_string()),
+                }),
+            })
+            .collect(),
+    };
+
+    Ok(HickoryResult { entries })
+}
+
+impl Entry {
+    fn text(text: String) -> Self {
+        Self {
+            text: c_string(text),
+            srv: None,
+        }
+    }
+}
+
+fn status(error: ResolveError) -> c_int {
+    match error.proto().map(|proto| proto.kind()) {
+        Some(ProtoErrorKind::NoRecordsFound { .. }) => HICKORY_ERR_NO_RECORDS,
+        Some(ProtoErrorKind::Timeout) => HICKORY_ERR_TIMEOUT,
+        _ => HICKORY_ERR_RESOLVE,
+    }
+}
+
+/// Returns the number of records of the result
+#[no_mangle]
+pub unsafe extern "C" fn hickory_result_len(result: *const HickoryResult) -> usize {
+    result.as_ref().map_or(0, |result| result.entries.len())
+}
+
+/// Returns the record at the index in presentation format, or null if the index is out of range
+///
+/// The string is valid until the result is freed.
+#[no_mangle]
+pub unsafe extern "C" fn hickory_result_get(
+    result: *const HickoryResult,
+    index: usize,
+) -> *const c_char {
+    match result.as_ref().and_then(|result| result.entries.get(index)) {
+        Some(entry) => entry.text.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Returns the fields of the SRV record at the index, the target is valid until the result is freed
+///
+/// Returns `HICKORY_ERR_INVALID_ARGUMENT` if the index is out of range, or the record is not SRV.
+#[no_mangle]
+pub unsafe extern "C" fn hickory_result_srv(
+    result: *const HickoryResult,
+    index: usize,
+    priority: *mut u16,
+    weight: *mut u16,
+    port: *mut u16,
+    target: *mut *const c_char,
+) -> c_int {
+    let Some(srv) = result
+        .as_ref()
+        .and_then(|result| result.entries.get(index))
+        .and_then(|entry| entry.srv.as_ref())
+    else {
+        return HICKORY_ERR_INVALID_ARGUMENT;
+    };
+
+    if let Some(priority) = priority.as_mut() {
+        *priority = srv.priority;
+    }
+    if let Some(weight) = weight.as_mut() {
+        *weight = srv.weight;
+    }
+    if let Some(port) = port.as_mut() {
+        *port = srv.port;
+    }
+    if let Some(target) = target.as_mut() {
+        *target = srv.target.as_ptr();
+    }
+    HICKORY_OK
+}
+
+/// Frees the result
+#[no_mangle]
+pub unsafe extern "C" fn hickory_result_free(result: *mut HickoryResult) {
+    if !result.is_null() {
+        drop(Box::from_raw(result));
+    }
+}
+
+/// Returns a static description of the status
+#[no_mangle]
+pub extern "C" fn hickory_status_str(status: c_int) -> *const c_char {
+    let description: &'static [u8] = match status {
+        HICKORY_OK => b"ok\0",
+        HICKORY_ERR_INVALID_ARGUMENT => b"invalid argument\0",
+        HICKORY_ERR_NO_RECORDS => b"no records found\0",
+        HICKORY_ERR_TIMEOUT => b"request timed out\0",
+        HICKORY_ERR_CANCELED => b"request canceled\0",
+        HICKORY_ERR_RESOLVE => b"resolve error\0",
+        HICKORY_ERR_IN_CALLBACK => b"blocking call from a callback\0",
+        _ => b"unknown status\0",
+    };
+    description.as_ptr().cast()
+}
+
+unsafe fn c_str<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        return None;
+    }
+
+    CStr::from_ptr(s).to_str().ok()
+}
+
+/// Records never contain NUL in presentation format, but the TXT strings may
+fn c_string(s: String) -> CString {
+    CString::new(s).unwrap_or_else(|e| {
+        let nul = e.nul_position();
+        let mut bytes = e.into_vec();
+        bytes.truncate(nul);
+        CString::new(bytes).expect("truncated before the first NUL")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, UdpSocket};
+    use std::str::FromStr;
+    use std::sync::mpsc;
+    use std::thread;
+
+    use hickory_proto::op::{Message, MessageType};
+    use hickory_proto::rr::rdata::{A, SRV, TXT};
+    use hickory_proto::rr::{Name, RData, Record, RecordType};
+
+    use super::*;
+
+    /// Answers queries for `www.example.com.`, until the socket is closed
+    fn serve() -> CString {
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = socket.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut buffer = [0; 512];
+            while let Ok((len, src)) = socket.recv_from(&mut buffer) {
+                let query = Message::from_vec(&buffer[..len]).unwrap();
+                let question = query.queries()[0].clone();
+                let name = question.name().clone();
+
+                let mut response = Message::new();
+                response
+                    .set_id(query.id())
+                    .set_message_type(MessageType::Response)
+                    .add_query(question.clone());
+                let rdata = match question.query_type() {
+                    RecordType::A => Some(RData::A(A::new(10, 0, 0, 1))),
+                    RecordType::TXT => Some(RData::TXT(TXT::new(vec!["hello".to_string()]))),
+                    RecordType::SRV => Some(RData::SRV(SRV::new(
+                        1,
+                        2,
+                        443,
+                        Name::from_str("host.example.com.").unwrap(),
+                    ))),
+                    _ => None,
+                };
+                if let Some(rdata) = rdata {
+                    response.add_answer(Record::from_rdata(name, 60, rdata));
+                }
+                socket.send_to(&response.to_vec().unwrap(), src).unwrap();
+            }
+        });
+
+        CString::new(addr.to_string()).unwrap()
+    }
+
+    unsafe fn resolver(name_server: &CString) -> *mut HickoryResolver {
+        let name_servers = [name_server.as_ptr()];
+        let config = HickoryConfig {
+            name_servers: name_servers.as_ptr(),
+            name_servers_len: 1,
+            timeout_ms: 500,
+            attempts: 1,
+        };
+
+        let mut resolver = ptr::null_mut();
+        assert_eq!(hickory_resolver_new(&config, &mut resolver), HICKORY_OK);
+        resolver
+    }
+
+    unsafe fn entry(result: *const HickoryResult, index: usize) -> &'static str {
+        CStr::from_ptr(hickory_result_get(result, index))
+            .to_str()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_resolve_blocking() {
+        let name_server = serve();
+        let name = CString::new("www.example.com.").unwrap();
+
+        unsafe {
+            let resolver = resolver(&name_server);
+
+            let mut result = ptr::null_mut();
+            assert_eq!(
+                hickory_resolve_ip(resolver, name.as_ptr(), &mut result),
+                HICKORY_OK
+            );
+            assert_eq!(hickory_result_len(result), 1);
+            assert_eq!(entry(result, 0), "10.0.0.1");
+            assert!(hickory_result_get(result, 1).is_null());
+            hickory_result_free(result);
+
+            assert_eq!(
+                hickory_resolve_txt(resolver, name.as_ptr(), &mut result),
+                HICKORY_OK
+            );
+            assert_eq!(entry(result, 0), "hello");
+            hickory_result_free(result);
+
+            assert_eq!(
+                hickory_resolve_srv(resolver, name.as_ptr(), &mut result),
+                HICKORY_OK
+            );
+            let (mut priority, mut weight, mut port, mut target) = (0, 0, 0, ptr::null());
+            assert_eq!(
+                hickory_result_srv(
+                    result,
+                    0,
+                    &mut priority,
+                    &mut weight,
+                    &mut port,
+                    &mut target
+                ),
+                HICKORY_OK
+            );
+            assert_eq!((priority, weight, port), (1, 2, 443));
+            assert_eq!(
+                CStr::from_ptr(target).to_str().unwrap(),
+                "host.example.com."
+            );
+            hickory_result_free(result);
+
+            hickory_resolver_free(resolver);
+        }
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        let name_server = CString::new("not an address").unwrap();
+        let name_servers = [name_server.as_ptr()];
+        let config = HickoryConfig {
+            name_servers: name_servers.as_ptr(),
+            name_servers_len: 1,
+            timeout_ms: 0,
+            attempts: 0,
+        };
+
+        unsafe {
+            let mut resolver = ptr::null_mut();
+            assert_eq!(
+                hickory_resolver_new(&config, &mut resolver),
+                HICKORY_ERR_INVALID_ARGUMENT
+            );
+            assert!(resolver.is_null());
+
+            let mut result = ptr::null_mut();
+            assert_eq!(
+                hickory_resolve_ip(ptr::null(), ptr::null(), &mut result),
+                HICKORY_ERR_INVALID_ARGUMENT
+            );
+            assert!(
+                hickory_resolve_ip_async(ptr::null(), ptr::null(), None, ptr::null_mut()).is_null()
+            );
+        }
+    }
+
+    unsafe extern "C" fn send_result(
+        status: c_int,
+        result: *mut HickoryResult,
+        user_data: *mut c_void,
+    ) {
+        let sender = Box::from_raw(user_data.cast::<mpsc::Sender<(c_int, Option<String>)>>());
+        let text = (!result.is_null()).then(|| entry(result, 0).to_string());
+        hickory_result_free(result);
+        sender.send((status, text)).unwrap();
+    }
+
+    fn user_data() -> (*mut c_void, mpsc::Receiver<(c_int, Option<String>)>) {
+        let (sender, receiver) = mpsc::channel();
+        (Box::into_raw(Box::new(sender)).cast(), receiver)
+    }
+
+    #[test]
+    fn test_resolve_async() {
+        let name_server = serve();
+        let name = CString::new("www.example.com.").unwrap();
+
+        unsafe {
+            let resolver = resolver(&name_server);
+
+            let (data, receiver) = user_data();
+            let request =
+                hickory_resolve_txt_async(resolver, name.as_ptr(), Some(send_result), data);
+            assert!(!request.is_null());
+            hickory_request_free(request);
+            assert_eq!(
+                receiver.recv().unwrap(),
+                (HICKORY_OK, Some("hello".to_string()))
+            );
+
+            hickory_resolver_free(resolver);
+        }
+    }
+
+    #[test]
+    fn test_cancel() {
+        // a name server which never responds
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let name_server = CString::new(socket.local_addr().unwrap().to_string()).unwrap();
+        let name = CString::new("www.example.com.").unwrap();
+
+        unsafe {
+            let resolver = resolver(&name_server);
+
+            let (data, receiver) = user_data();
+            let request =
+                hickory_resolve_ip_async(resolver, name.as_ptr(), Some(send_result), data);
+            hickory_request_cancel(request);
+            assert_eq!(receiver.recv().unwrap(), (HICKORY_ERR_CANCELED, None));
+
+            hickory_resolver_free(resolver);
+        }
+    }
+}
@@ -30,7 +30,7 @@ use crate::{
         xfer::{
             BufDnsStreamHandle, DnsClientStream, DnsExchange, DnsExchangeBackground,
             DnsExchangeSend, DnsHandle, DnsMultiplexer, DnsRequest, DnsRequestOptions,
-            DnsRequestSender, DnsResponse,
+            DnsRequestSender, DnsResponse, RetryDnsHandle, RetryPolicy,
         },
         TokioTime,
     },
@@ -141,6 +141,13 @@ impl AsyncClient {
     pub fn disable_edns(&mut self) {
         self.use_edns = false;
     }
+
+    /// Returns a handle which reattempts failed requests as specified by the policy
+    ///
+    /// The client has a single connection, retries on a different server do not apply.
+    pub fn with_retry_policy(self, policy: RetryPolicy) -> RetryDnsHandle<Self> {
+        RetryDnsHandle::with_policy(self, policy)
+    }
 }
 
 impl DnsHandle for AsyncClient {
@@ -590,7 +597,7 @@ pub trait ClientHandle: 'static + Clone + DnsHandle + Send {
     ///
     /// # Arguments
     /// * `zone_origin` - the zone name to update, i.e. SOA name
-    /// * `last_soa` - the last SOA known, if any. If provided, name must match `zone_origin`
+    /// * `last_soa` - the last SOA known for `zone_origin`, if any
 
     fn zone_transfer(
         &mut self,
@@ -743,10 +750,7 @@ impl<R> ClientStreamXfrState<R> {
                             Ok(())
                         } else {
                             // invalid answer : trailing records
-                            Err(ClientErrorKind::Message(
-                                "invalid zone transfer, contains trailing records",
-                            )
-                            .into())
+                            Err(ZoneTransferError::TrailingRecords.into())
                         }
                     } else if maybe_incr {
                         *self = Ixfr {
@@ -757,10 +761,7 @@ impl<R> ClientStreamXfrState<R> {
                         self.process(&answers[1..])
                     } else {
                         *self = Ended;
-                        Err(ClientErrorKind::Message(
-                            "invalid zone transfer, expected AXFR, got IXFR",
-                        )
-                        .into())
+                        Err(ZoneTransferError::UnexpectedIxfr.into())
                     }
                 } else {
                     // standard AXFR
@@ -791,18 +792,12 @@ impl<R> ClientStreamXfrState<R> {
                         *self = Ended;
                         match answers.last().map(|r| r.record_type()) {
                             Some(RecordType::SOA) => Ok(()),
-                            _ => Err(ClientErrorKind::Message(
-                                "invalid zone transfer, contains trailing records",
-                            )
-                            .into()),
+                            _ => Err(ZoneTransferError::TrailingRecords.into()),
                         }
                     }
                     _ => {
                         *self = Ended;
-                        Err(ClientErrorKind::Message(
-                            "invalid zone transfer, contains trailing records",
-                        )
-                        .into())
+                        Err(ZoneTransferError::TrailingRecords.into())
                     }
                 }
             }
@@ -0,0 +1,482 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! A client on the standard sockets, which blocks the calling thread
+
+use std::io::{self, Read, Write};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, UdpSocket};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use tracing::debug;
+
+use crate::client::Signer;
+use crate::error::*;
+use crate::op::{DnsResponse, Edns, Message, MessageFinalizer, MessageType, OpCode, Query};
+use crate::proto::op::update_message;
+use crate::rr::{DNSClass, Name, Record, RecordSet, RecordType};
+
+/// The largest response which is received over UDP, as advertised with EDNS
+const MAX_RECEIVE_BUFFER_SIZE: usize = update_message::MAX_PAYLOAD_LEN as usize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Transport {
+    Udp,
+    Tcp,
+}
+
+/// A DNS client which sends each request on a new standard socket, and blocks until the response
+///
+/// Unlike the [`SyncClient`](super::SyncClient), this needs no async runtime, which suits command
+///  line tools and scripts. UDP responses which are truncated are retried over TCP. Zone transfers
+///  are not supported, they need the streaming [`AsyncClient`](super::AsyncClient).
+///
+/// ```no_run
+/// use hickory_client::client::BlockingClient;
+/// use hickory_client::rr::{DNSClass, Name, RecordType};
+///
+/// let client = BlockingClient::udp(([8, 8, 8, 8], 53).into());
+/// let response = client
+///     .query(&Name::from_ascii("www.example.com.").unwrap(), DNSClass::IN, RecordType::A)
+///     .unwrap();
+/// println!("{:?}", response.answers());
+/// ```
+#[derive(Clone)]
+pub struct BlockingClient {
+    name_server: SocketAddr,
+    transport: Transport,
+    timeout: Duration,
+    use_edns: bool,
+    signer: Option<Arc<Signer>>,
+}
+
+impl BlockingClient {
+    /// Creates a client which sends requests over UDP
+    pub fn udp(name_server: SocketAddr) -> Self {
+        Self::new(name_server, Transport::Udp)
+    }
+
+    /// Creates a client which sends requests over TCP
+    pub fn tcp(name_server: SocketAddr) -> Self {
+        Self::new(name_server, Transport::Tcp)
+    }
+
+    fn new(name_server: SocketAddr, transport: Transport) -> Self {
+        Self {
+            name_server,
+            transport,
+            timeout: Duration::from_secs(5),
+            use_edns: true,
+            signer: None,
+        }
+    }
+
+    /// Sets the timeout of each request, defaults to 5 seconds
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets whether EDNS is used, defaults to true
+    pub fn with_edns(mut self, use_edns: bool) -> Self {
+        self.use_edns = use_edns;
+        self
+    }
+
+    /// Sets the signer of updates and notifications
+    pub fn with_signer(mut self, signer: Signer) -> Self {
+        self.signer = Some(Arc::new(signer));
+        self
+    }
+
+    /// Returns the address of the name server
+    pub fn name_server(&self) -> SocketAddr {
+        self.name_server
+    }
+
+    /// A *classic* DNS query, i.e. does not perform any DNSSEC operations
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the label to lookup
+    /// * `query_class` - most likely this should always be DNSClass::IN
+    /// * `query_type` - record type to lookup
+    pub fn query(
+        &self,
+        name: &Name,
+        query_class: DNSClass,
+        query_type: RecordType,
+    ) -> ClientResult<DnsResponse> {
+        let mut query = Query::query(name.clone(), query_type);
+        query.set_query_class(query_class);
+
+        let mut message = Message::new();
+        message
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Query)
+            .set_recursion_desired(true)
+            .add_query(query);
+        self.add_edns(&mut message);
+
+        self.send(message)
+    }
+
+    /// Sends a NOTIFY message to the remote system, see [RFC 1996](https://tools.ietf.org/html/rfc1996)
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the label which is being notified
+    /// * `query_class` - most likely this should always be DNSClass::IN
+    /// * `query_type` - record type which has been updated
+    /// * `rrset` - the new version of the record(s) being notified
+    pub fn notify<R>(
+        &self,
+        name: Name,
+        query_class: DNSClass,
+        query_type: RecordType,
+        rrset: Option<R>,
+    ) -> ClientResult<DnsResponse>
+    where
+        R: Into<RecordSet>,
+    {
+        let mut query = Query::query(name, query_type);
+        query.set_query_class(query_class);
+
+        let mut message = Message::new();
+        message
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Notify)
+            .add_query(query);
+        self.add_edns(&mut message);
+
+        // add the notify message, see https://tools.ietf.org/html/rfc1996, section 3.7
+        if let Some(rrset) = rrset {
+            message.add_answers(rrset.into());
+        }
+
+        self.send(message)
+    }
+
+    /// Sends a record to create on the server, this will fail if the record exists
+    ///
+    /// See [`Client::create`](super::Client::create).
+    pub fn create<R>(&self, rrset: R, zone_origin: Name) -> ClientResult<DnsResponse>
+    where
+        R: Into<RecordSet>,
+    {
+        self.send(update_message::create(
+            rrset.into(),
+            zone_origin,
+            self.use_edns,
+        ))
+    }
+
+    /// Appends a record to an existing rrset, optionally require the rrset to exist
+    ///
+    /// See [`Client::append`](super::Client::append).
+    pub fn append<R>(
+        &self,
+        rrset: R,
+        zone_origin: Name,
+        must_exist: bool,
+    ) -> ClientResult<DnsResponse>
+    where
+        R: Into<RecordSet>,
+    {
+        self.send(update_message::append(
+            rrset.into(),
+            zone_origin,
+            must_exist,
+            self.use_edns,
+        ))
+    }
+
+    /// Compares and if it matches, swaps it for the new value
+    ///
+    /// See [`Client::compare_and_swap`](super::Client::compare_and_swap).
+    pub fn compare_and_swap<CR, NR>(
+        &self,
+        current: CR,
+        new: NR,
+        zone_origin: Name,
+    ) -> ClientResult<DnsResponse>
+    where
+        CR: Into<RecordSet>,
+        NR: Into<RecordSet>,
+    {
+        self.send(update_message::compare_and_swap(
+            current.into(),
+            new.into(),
+            zone_origin,
+            self.use_edns,
+        ))
+    }
+
+    /// Deletes a record (by rdata) from an rrset
+    ///
+    /// See [`Client::delete_by_rdata`](super::Client::delete_by_rdata).
+    pub fn delete_by_rdata<R>(&self, record: R, zone_origin: Name) -> ClientResult<DnsResponse>
+    where
+        R: Into<RecordSet>,
+    {
+        self.send(update_message::delete_by_rdata(
+            record.into(),
+            zone_origin,
+            self.use_edns,
+        ))
+    }
+
+    /// Deletes an entire rrset
+    ///
+    /// See [`Client::delete_rrset`](super::Client::delete_rrset).
+    pub fn delete_rrset(&self, record: Record, zone_origin: Name) -> ClientResult<DnsResponse> {
+        self.send(update_message::delete_rrset(
+            record,
+            zone_origin,
+            self.use_edns,
+        ))
+    }
+
+    /// Deletes all records at the specified name
+    ///
+    /// See [`Client::delete_all`](super::Client::delete_all).
+    pub fn delete_all(
+        &self,
+        name_of_records: Name,
+        zone_origin: Name,
+        dns_class: DNSClass,
+    ) -> ClientResult<DnsResponse> {
+        assert!(zone_origin.zone_of(&name_of_records));
+        self.send(update_message::delete_all(
+            name_of_records,
+            zone_origin,
+            dns_class,
+            self.use_edns,
+        ))
+    }
+
+    /// Sends the message, with a new id, and returns the response
+    ///
+    /// The message is signed, if there is a signer and the message requires it.
+    pub fn send(&self, mut message: Message) -> ClientResult<DnsResponse> {
+        message.set_id(rand::random());
+
+        let mut verifier = None;
+        if let Some(signer) = &self.signer {
+            if signer.should_finalize_message(&message) {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_err(|_| ClientError::from("current time is before the Unix epoch"))?;
+                verifier = message.finalize(signer.as_ref(), now.as_secs() as u32)?;
+            }
+        }
+
+        let request = message.to_vec()?;
+        let mut buffer = match self.transport {
+            Transport::Udp => self.exchange_udp(message.id(), &request)?,
+            Transport::Tcp => self.exchange_tcp(message.id(), &request)?,
+        };
+
+        let mut response = Message::from_vec(&buffer)?;
+        if self.transport == Transport::Udp && response.truncated() {
+            debug!(
+                "response truncated, retrying over tcp: {}",
+                self.name_server
+            );
+            buffer = self.exchange_tcp(message.id(), &request)?;
+            response = Message::from_vec(&buffer)?;
+        }
+
+        match verifier {
+            Some(mut verifier) => Ok(verifier(&buffer)?),
+            None => Ok(DnsResponse::new(response, buffer)),
+        }
+    }
+
+    fn add_edns(&self, message: &mut Message) {
+        if self.use_edns {
+            message
+                .extensions_mut()
+                .get_or_insert_with(Edns::new)
+                .set_max_payload(update_message::MAX_PAYLOAD_LEN)
+                .set_version(0);
+        }
+    }
+
+    /// Sends the request, and receives until the response with the id from the name server
+    fn exchange_udp(&self, id: u16, request: &[u8]) -> ClientResult<Vec<u8>> {
+        let bind_addr: SocketAddr = match self.name_server {
+            SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+        };
+        let socket = UdpSocket::bind(bind_addr)?;
+        socket.connect(self.name_server)?;
+        socket.send(request)?;
+
+        let deadline = Instant::now() + self.timeout;
+        let mut buffer = vec![0; MAX_RECEIVE_BUFFER_SIZE];
+        loop {
+            socket.set_read_timeout(Some(remaining(deadline)?))?;
+            let len = socket.recv(&mut buffer).map_err(timeout_error)?;
+
+            // responses to earlier requests, or spoofed ones, are dropped
+            if len >= 2 && u16::from_be_bytes([buffer[0], buffer[1]]) == id {
+                buffer.truncate(len);
+                return Ok(buffer);
+            }
+            debug!(
+                "dropped response with unexpected id from {}",
+                self.name_server
+            );
+        }
+    }
+
+    /// Sends the request with its length, and receives the response with its length
+    fn exchange_tcp(&self, id: u16, request: &[u8]) -> ClientResult<Vec<u8>> {
+        let deadline = Instant::now() + self.timeout;
+        let mut stream =
+            TcpStream::connect_timeout(&self.name_server, self.timeout).map_err(timeout_error)?;
+        stream.set_nodelay(true)?;
+
+        let len = u16::try_from(request.len())
+            .map_err(|_| ClientError::from("request is too large for tcp"))?;
+        stream.set_write_timeout(Some(remaining(deadline)?))?;
+        stream.write_all(&len.to_be_bytes())?;
+        stream.write_all(request)?;
+
+        loop {
+            let mut len = [0; 2];
+            stream.set_read_timeout(Some(remaining(deadline)?))?;
+            stream.read_exact(&mut len).map_err(timeout_error)?;
+            let mut buffer = vec![0; u16::from_be_bytes(len) as usize];
+            stream.read_exact(&mut buffer).map_err(timeout_error)?;
+
+            if buffer.len() >= 2 && u16::from_be_bytes([buffer[0], buffer[1]]) == id {
+                return Ok(buffer);
+            }
+            debug!(
+                "dropped response with unexpected id from {}",
+                self.name_server
+            );
+        }
+    }
+}
+
+/// Returns the time until the deadline, or the timeout error once it passed
+fn remaining(deadline: Instant) -> ClientResult<Duration> {
+    match deadline.checked_duration_since(Instant::now()) {
+        Some(remaining) if !remaining.is_zero() => Ok(remaining),
+        _ => Err(ClientErrorKind::Timeout.into()),
+    }
+}
+
+/// The timeouts of the sockets are reported as `WouldBlock` or `TimedOut`, depending on the platform
+fn timeout_error(error: io::Error) -> ClientError {
+    match error.kind() {
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => ClientErrorKind::Timeout.into(),
+        _ => error.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::op::ResponseCode;
+
+    /// Answers a single query over UDP, with the response modified by the function
+    fn serve_udp(respond: impl FnOnce(&mut Message) + Send + 'static) -> SocketAddr {
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = socket.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut buffer = [0; 512];
+            let (len, src) = socket.recv_from(&mut buffer).unwrap();
+            let mut message = Message::from_vec(&buffer[..len]).unwrap();
+            message.set_message_type(MessageType::Response);
+            respond(&mut message);
+            socket.send_to(&message.to_vec().unwrap(), src).unwrap();
+        });
+        addr
+    }
+
+    #[test]
+    fn test_udp_query() {
+        let addr = serve_udp(|message| {
+            message.set_response_code(ResponseCode::NXDomain);
+        });
+
+        let client = BlockingClient::udp(addr);
+        let response = client
+            .query(
+                &Name::from_ascii("www.example.com.").unwrap(),
+                DNSClass::IN,
+                RecordType::A,
+            )
+            .unwrap();
+        assert_eq!(response.response_code(), ResponseCode::NXDomain);
+        assert_eq!(response.queries()[0].query_type(), RecordType::A);
+        assert!(response.extensions().is_some());
+    }
+
+    #[test]
+    fn test_udp_timeout() {
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+
+        let client = BlockingClient::udp(socket.local_addr().unwrap())
+            .with_timeout(Duration::from_millis(50));
+        let error = client
+            .query(&Name::root(), DNSClass::IN, RecordType::NS)
+            .unwrap_err();
+        assert!(matches!(error.kind(), ClientErrorKind::Timeout), "{error}");
+    }
+
+    #[test]
+    fn test_truncated_retries_tcp() {
+        let listener = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let udp = UdpSocket::bind(addr).unwrap();
+
+        thread::spawn(move || {
+            let mut buffer = [0; 512];
+            let (len, src) = udp.recv_from(&mut buffer).unwrap();
+            let mut message = Message::from_vec(&buffer[..len]).unwrap();
+            message
+                .set_message_type(MessageType::Response)
+                .set_truncated(true);
+            udp.send_to(&message.to_vec().unwrap(), src).unwrap();
+        });
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut len = [0; 2];
+            stream.read_exact(&mut len).unwrap();
+            let mut buffer = vec![0; u16::from_be_bytes(len) as usize];
+            stream.read_exact(&mut buffer).unwrap();
+
+            let mut message = Message::from_vec(&buffer).unwrap();
+            message
+                .set_message_type(MessageType::Response)
+                .set_response_code(ResponseCode::Refused);
+            let response = message.to_vec().unwrap();
+            stream
+                .write_all(&(response.len() as u16).to_be_bytes())
+                .unwrap();
+            stream.write_all(&response).unwrap();
+        });
+
+        let client = BlockingClient::udp(addr);
+        let response = client
+            .query(
+                &Name::from_ascii("example.com.").unwrap(),
+                DNSClass::IN,
+                RecordType::TXT,
+            )
+            .unwrap();
+        assert!(!response.truncated());
+        assert_eq!(response.response_code(), ResponseCode::Refused);
+    }
+}
@@ -417,7 +417,7 @@ pub trait Client {
     ///
     /// # Arguments
     /// * `zone_origin` - the zone name to update, i.e. SOA name
-    /// * `last_soa` - the last SOA known, if any. If provided, name must match `zone_origin`
+    /// * `last_soa` - the last SOA known for `zone_origin`, if any
     fn zone_transfer(
         &self,
         name: &Name,
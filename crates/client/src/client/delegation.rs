@@ -0,0 +1,799 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Health checks of the delegation of a zone
+//!
+//! The parent name servers are queried for the delegation (NS, glue and DS records), then each
+//!  delegated name server is queried for the SOA, NS and DNSKEY records of the zone, and the
+//!  answers are compared with each other.
+//!
+//! ```rust,no_run
+//! # async fn check() -> Result<(), Box<dyn std::error::Error>> {
+//! use std::str::FromStr;
+//!
+//! use hickory_client::client::DelegationChecker;
+//! use hickory_client::rr::Name;
+//!
+//! let zone = Name::from_str("example.com.")?;
+//! // the name servers of `com.`
+//! let parents = ["192.5.6.30:53".parse()?, "192.33.14.30:53".parse()?];
+//!
+//! let report = DelegationChecker::new().check(&zone, &parents).await;
+//! for finding in report.findings() {
+//!     println!("{finding}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::future::{self, BoxFuture};
+use tokio::net::{TcpStream as TokioTcpStream, UdpSocket};
+use tracing::debug;
+
+#[cfg(feature = "dnssec")]
+use crate::proto::rr::{
+    dnssec::{
+        rdata::{DNSSECRData, DNSKEY, DS},
+        Verifier,
+    },
+    DNSClass,
+};
+use crate::{
+    client::AsyncClient,
+    error::ClientError,
+    op::{Edns, Message, MessageType, OpCode, Query, ResponseCode},
+    proto::{
+        iocompat::AsyncIoTokioAsStd,
+        xfer::{DnsHandle, DnsRequest, DnsRequestOptions, DnsResponse, FirstAnswer},
+    },
+    rr::{Name, RData, Record, RecordType},
+    tcp::TcpClientStream,
+    udp::UdpClientStream,
+};
+
+/// Maximum payload advertised with EDNS, large enough for most DNSKEY sets
+const MAX_PAYLOAD_LEN: u16 = 4096;
+
+/// Looks up the addresses of a name server, for name servers without glue in the parent
+pub type AddressLookup = Arc<dyn Fn(Name) -> BoxFuture<'static, Vec<IpAddr>> + Send + Sync>;
+
+/// Checks that the delegation of a zone is consistent
+///
+/// A delegation is healthy when all parents agree on it, every delegated name server answers
+///  authoritatively with the same SOA serial and the same NS set as the parent, and, if the parent
+///  has DS records, every name server serves a DNSKEY set matching them and signed by such a key.
+#[derive(Clone)]
+pub struct DelegationChecker {
+    port: u16,
+    timeout: Duration,
+    address_lookup: Option<AddressLookup>,
+}
+
+impl DelegationChecker {
+    /// Creates a checker querying port 53, with a timeout of 5 seconds per query
+    pub fn new() -> Self {
+        Self {
+            port: 53,
+            timeout: Duration::from_secs(5),
+            address_lookup: None,
+        }
+    }
+
+    /// Sets the port on which the delegated name servers are queried
+    pub fn set_port(&mut self, port: u16) -> &mut Self {
+        self.port = port;
+        self
+    }
+
+    /// Sets the time to wait for each response
+    pub fn set_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets the lookup for the addresses of name servers without glue
+    ///
+    /// Without a lookup such name servers are reported with [`Finding::NoAddress`].
+    pub fn set_address_lookup(&mut self, address_lookup: AddressLookup) -> &mut Self {
+        self.address_lookup = Some(address_lookup);
+        self
+    }
+
+    /// Checks the delegation of `zone` from the name servers of its parent
+    ///
+    /// # Arguments
+    ///
+    /// * `zone` - the zone whose delegation to check
+    /// * `parent_servers` - the addresses of the authoritative name servers of the parent zone
+    pub async fn check(&self, zone: &Name, parent_servers: &[SocketAddr]) -> DelegationReport {
+        let mut findings = Vec::new();
+        let delegation = self
+            .query_parents(zone, parent_servers, &mut findings)
+            .await;
+
+        let mut name_servers = Vec::new();
+        for name in &delegation.name_servers {
+            let addresses = match delegation.glue.get(name) {
+                Some(addresses) => addresses.clone(),
+                None => match self.address_lookup {
+                    Some(ref lookup) => lookup(name.clone()).await,
+                    None => Vec::new(),
+                },
+            };
+
+            if addresses.is_empty() {
+                findings.push(Finding::NoAddress { name: name.clone() });
+                continue;
+            }
+
+            let queries = addresses
+                .into_iter()
+                .map(|ip| self.query_child(zone, name.clone(), SocketAddr::new(ip, self.port)));
+            for result in future::join_all(queries).await {
+                match result {
+                    Ok(report) => name_servers.push(report),
+                    Err(finding) => findings.push(finding),
+                }
+            }
+        }
+
+        findings.extend(analyze(
+            &delegation.name_servers,
+            #[cfg(feature = "dnssec")]
+            zone,
+            #[cfg(feature = "dnssec")]
+            &delegation.ds,
+            &name_servers,
+        ));
+
+        DelegationReport {
+            zone: zone.clone(),
+            delegation: delegation.name_servers,
+            #[cfg(feature = "dnssec")]
+            ds: delegation.ds,
+            name_servers,
+            findings,
+        }
+    }
+
+    /// Queries the parents for the delegation, the first parent which answers is authoritative
+    async fn query_parents(
+        &self,
+        zone: &Name,
+        parent_servers: &[SocketAddr],
+        findings: &mut Vec<Finding>,
+    ) -> Delegation {
+        let mut delegation: Option<Delegation> = None;
+        for &addr in parent_servers {
+            let response = match self.query(addr, zone, RecordType::NS).await {
+                Ok(response) => response,
+                Err(e) => {
+                    debug!("querying parent {} for {} failed: {}", addr, zone, e);
+                    findings.push(Finding::ParentUnreachable {
+                        addr,
+                        error: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+
+            let name_servers = name_servers(zone, response.answers().iter())
+                .chain(name_servers(zone, response.name_servers().iter()))
+                .collect::<Vec<_>>();
+            let name_servers = sorted(name_servers);
+
+            match delegation {
+                Some(ref delegation) if delegation.name_servers != name_servers => {
+                    findings.push(Finding::ParentsDisagree { addr, name_servers });
+                }
+                Some(_) => (),
+                None => {
+                    let mut glue = BTreeMap::<Name, Vec<IpAddr>>::new();
+                    for record in response.additionals() {
+                        if let Some(ip) = record.data().and_then(RData::ip_addr) {
+                            if name_servers.contains(record.name()) {
+                                glue.entry(record.name().clone()).or_default().push(ip);
+                            }
+                        }
+                    }
+
+                    delegation = Some(Delegation {
+                        #[cfg(feature = "dnssec")]
+                        ds: self.query_ds(addr, zone).await,
+                        name_servers,
+                        glue,
+                    });
+                }
+            }
+        }
+
+        match delegation {
+            Some(delegation) => {
+                if delegation.name_servers.is_empty() {
+                    findings.push(Finding::NotDelegated);
+                }
+                delegation
+            }
+            None => Delegation::default(),
+        }
+    }
+
+    /// Queries the parent for the DS records of the zone, which are missing if the query fails
+    #[cfg(feature = "dnssec")]
+    async fn query_ds(&self, addr: SocketAddr, zone: &Name) -> Vec<DS> {
+        match self.query(addr, zone, RecordType::DS).await {
+            Ok(response) => response
+                .answers()
+                .iter()
+                .filter(|record| record.name() == zone)
+                .filter_map(|record| match record.data() {
+                    Some(RData::DNSSEC(DNSSECRData::DS(ds))) => Some(ds.clone()),
+                    _ => None,
+                })
+                .collect(),
+            Err(e) => {
+                debug!("querying parent {} for DS of {} failed: {}", addr, zone, e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Queries a delegated name server, an error is the finding why it can not be checked
+    async fn query_child(
+        &self,
+        zone: &Name,
+        name: Name,
+        addr: SocketAddr,
+    ) -> Result<NameServerReport, Finding> {
+        let unreachable = |e: ClientError| Finding::Unreachable {
+            name: name.clone(),
+            addr,
+            error: e.to_string(),
+        };
+
+        let response = self
+            .query(addr, zone, RecordType::SOA)
+            .await
+            .map_err(unreachable)?;
+        let serial = response
+            .answers()
+            .iter()
+            .find_map(|record| match record.data() {
+                Some(RData::SOA(soa)) if record.name() == zone => Some(soa.serial()),
+                _ => None,
+            });
+        let serial = match serial {
+            Some(serial) if response.authoritative() => serial,
+            _ => {
+                return Err(Finding::Lame {
+                    name,
+                    addr,
+                    response_code: response.response_code(),
+                })
+            }
+        };
+
+        let response = self
+            .query(addr, zone, RecordType::NS)
+            .await
+            .map_err(unreachable)?;
+        let name_servers = sorted(name_servers(zone, response.answers().iter()).collect());
+
+        #[cfg(feature = "dnssec")]
+        let (dnskeys, signing_keys) = {
+            let response = self
+                .query(addr, zone, RecordType::DNSKEY)
+                .await
+                .map_err(unreachable)?;
+            dnskeys(zone, response.answers())
+        };
+
+        Ok(NameServerReport {
+            name,
+            addr,
+            serial,
+            name_servers,
+            #[cfg(feature = "dnssec")]
+            dnskeys,
+            #[cfg(feature = "dnssec")]
+            signing_keys,
+        })
+    }
+
+    /// Queries the name server without recursion, retrying over TCP if the response is truncated
+    async fn query(
+        &self,
+        addr: SocketAddr,
+        name: &Name,
+        record_type: RecordType,
+    ) -> Result<DnsResponse, ClientError> {
+        let stream = UdpClientStream::<UdpSocket>::with_timeout(addr, self.timeout);
+        let (client, bg) = AsyncClient::connect(stream).await?;
+        // the background ends when the client is dropped
+        tokio::spawn(bg);
+
+        let response = client
+            .send(request(name, record_type))
+            .first_answer()
+            .await?;
+        if !response.truncated() {
+            return Ok(response);
+        }
+
+        debug!("response of {} is truncated, retrying over TCP", addr);
+        let (stream, sender) =
+            TcpClientStream::<AsyncIoTokioAsStd<TokioTcpStream>>::with_timeout(addr, self.timeout);
+        let (client, bg) = AsyncClient::with_timeout(stream, sender, self.timeout, None).await?;
+        tokio::spawn(bg);
+
+        Ok(client
+            .send(request(name, record_type))
+            .first_answer()
+            .await?)
+    }
+}
+
+impl Default for DelegationChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for DelegationChecker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DelegationChecker")
+            .field("port", &self.port)
+            .field("timeout", &self.timeout)
+            .field("address_lookup", &self.address_lookup.is_some())
+            .finish()
+    }
+}
+
+/// The delegation as served by the parent
+#[derive(Default)]
+struct Delegation {
+    name_servers: Vec<Name>,
+    glue: BTreeMap<Name, Vec<IpAddr>>,
+    #[cfg(feature = "dnssec")]
+    ds: Vec<DS>,
+}
+
+/// The result of checking the delegation of a zone
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct DelegationReport {
+    /// The checked zone
+    pub zone: Name,
+    /// The name servers of the zone according to the parent
+    pub delegation: Vec<Name>,
+    /// The DS records of the zone in the parent
+    #[cfg(feature = "dnssec")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "dnssec")))]
+    pub ds: Vec<DS>,
+    /// The delegated name servers which answered authoritatively, one per address
+    pub name_servers: Vec<NameServerReport>,
+    /// The problems found with the delegation
+    pub findings: Vec<Finding>,
+}
+
+impl DelegationReport {
+    /// Returns true if no problems were found
+    pub fn is_healthy(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    /// The problems found with the delegation
+    pub fn findings(&self) -> &[Finding] {
+        &self.findings
+    }
+}
+
+/// The answers of a delegated name server
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct NameServerReport {
+    /// The name of the name server
+    pub name: Name,
+    /// The queried address of the name server
+    pub addr: SocketAddr,
+    /// The serial of the SOA record of the zone
+    pub serial: u32,
+    /// The NS records of the zone
+    pub name_servers: Vec<Name>,
+    /// The DNSKEY records of the zone
+    #[cfg(feature = "dnssec")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "dnssec")))]
+    pub dnskeys: Vec<DNSKEY>,
+    /// The keys with a valid signature over the DNSKEY records
+    #[cfg(feature = "dnssec")]
+    signing_keys: Vec<DNSKEY>,
+}
+
+/// A problem with the delegation of a zone
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Finding {
+    /// A parent name server could not be queried
+    ParentUnreachable {
+        /// The address of the parent name server
+        addr: SocketAddr,
+        /// The error of the query
+        error: String,
+    },
+    /// A parent name server delegates to other name servers than the first parent
+    ParentsDisagree {
+        /// The address of the parent name server
+        addr: SocketAddr,
+        /// The name servers it delegates to
+        name_servers: Vec<Name>,
+    },
+    /// The parent does not delegate the zone
+    NotDelegated,
+    /// A name server has no glue, and its addresses could not be looked up
+    NoAddress {
+        /// The name of the name server
+        name: Name,
+    },
+    /// A name server could not be queried
+    Unreachable {
+        /// The name of the name server
+        name: Name,
+        /// The queried address
+        addr: SocketAddr,
+        /// The error of the query
+        error: String,
+    },
+    /// A name server does not answer authoritatively for the zone
+    Lame {
+        /// The name of the name server
+        name: Name,
+        /// The queried address
+        addr: SocketAddr,
+        /// The response code of its answer
+        response_code: ResponseCode,
+    },
+    /// The name servers serve different versions of the zone
+    SerialMismatch {
+        /// The serial served at each address
+        serials: Vec<(SocketAddr, u32)>,
+    },
+    /// A name server serves other NS records than the delegation in the parent
+    NameServersMismatch {
+        /// The address of the name server
+        addr: SocketAddr,
+        /// The NS records it serves
+        name_servers: Vec<Name>,
+    },
+    /// None of the DNSKEY records of a name server matches a DS record of the parent
+    #[cfg(feature = "dnssec")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "dnssec")))]
+    NoKeyForDs {
+        /// The address of the name server
+        addr: SocketAddr,
+    },
+    /// The DNSKEY records of a name server are not signed by a key matching a DS record
+    #[cfg(feature = "dnssec")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "dnssec")))]
+    KeysNotSigned {
+        /// The address of the name server
+        addr: SocketAddr,
+    },
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ParentUnreachable { addr, error } => {
+                write!(f, "parent {addr} is unreachable: {error}")
+            }
+            Self::ParentsDisagree { addr, name_servers } => {
+                write!(f, "parent {addr} delegates to other name servers: ")?;
+                write_names(f, name_servers)
+            }
+            Self::NotDelegated => write!(f, "the zone is not delegated"),
+            Self::NoAddress { name } => write!(f, "no address for name server {name}"),
+            Self::Unreachable { name, addr, error } => {
+                write!(f, "name server {name} at {addr} is unreachable: {error}")
+            }
+            Self::Lame {
+                name,
+                addr,
+                response_code,
+            } => write!(
+                f,
+                "name server {name} at {addr} is lame, it answered with {response_code}"
+            ),
+            Self::SerialMismatch { serials } => {
+                write!(f, "the SOA serials differ:")?;
+                for (addr, serial) in serials {
+                    write!(f, " {serial} at {addr}")?;
+                }
+                Ok(())
+            }
+            Self::NameServersMismatch { addr, name_servers } => {
+                write!(f, "name server at {addr} serves other NS records: ")?;
+                write_names(f, name_servers)
+            }
+            #[cfg(feature = "dnssec")]
+            Self::NoKeyForDs { addr } => {
+                write!(f, "name server at {addr} serves no DNSKEY matching a DS")
+            }
+            #[cfg(feature = "dnssec")]
+            Self::KeysNotSigned { addr } => write!(
+                f,
+                "name server at {addr} serves DNSKEYs not signed by a key matching a DS"
+            ),
+        }
+    }
+}
+
+fn write_names(f: &mut fmt::Formatter<'_>, names: &[Name]) -> fmt::Result {
+    for (i, name) in names.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{name}")?;
+    }
+    Ok(())
+}
+
+/// Builds a non-recursive query, which requests DNSSEC records if supported
+fn request(name: &Name, record_type: RecordType) -> DnsRequest {
+    let mut message = Message::new();
+    message
+        .set_id(rand::random())
+        .set_message_type(MessageType::Query)
+        .set_op_code(OpCode::Query)
+        .set_recursion_desired(false)
+        .add_query(Query::query(name.clone(), record_type));
+
+    let mut edns = Edns::new();
+    edns.set_max_payload(MAX_PAYLOAD_LEN);
+    #[cfg(feature = "dnssec")]
+    edns.set_dnssec_ok(true);
+    message.set_edns(edns);
+
+    let mut options = DnsRequestOptions::default();
+    options.recursion_desired = false;
+    DnsRequest::new(message, options)
+}
+
+/// Returns the targets of the NS records of the zone
+fn name_servers<'r>(
+    zone: &'r Name,
+    records: impl Iterator<Item = &'r Record> + 'r,
+) -> impl Iterator<Item = Name> + 'r {
+    records.filter_map(move |record| match record.data() {
+        Some(RData::NS(ns)) if record.name() == zone => Some(ns.0.clone()),
+        _ => None,
+    })
+}
+
+fn sorted(mut names: Vec<Name>) -> Vec<Name> {
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Returns the DNSKEY records of the zone, and the keys with a valid signature over them
+#[cfg(feature = "dnssec")]
+fn dnskeys(zone: &Name, answers: &[Record]) -> (Vec<DNSKEY>, Vec<DNSKEY>) {
+    let records = answers
+        .iter()
+        .filter(|record| record.name() == zone && record.record_type() == RecordType::DNSKEY)
+        .collect::<Vec<_>>();
+    let keys = records
+        .iter()
+        .filter_map(|record| match record.data() {
+            Some(RData::DNSSEC(DNSSECRData::DNSKEY(key))) => Some(key.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs() as u32);
+    let signatures = answers
+        .iter()
+        .filter_map(|record| match record.data() {
+            Some(RData::DNSSEC(DNSSECRData::RRSIG(rrsig))) => Some(rrsig),
+            _ => None,
+        })
+        .filter(|rrsig| {
+            rrsig.type_covered() == RecordType::DNSKEY
+                && rrsig.sig_inception() <= now
+                && now <= rrsig.sig_expiration()
+        })
+        .collect::<Vec<_>>();
+
+    let signing_keys = keys
+        .iter()
+        .filter(|key| {
+            let key_tag = key.calculate_key_tag().ok();
+            signatures.iter().any(|rrsig| {
+                Some(rrsig.key_tag()) == key_tag
+                    && rrsig.algorithm() == key.algorithm()
+                    && key
+                        .verify_rrsig(zone, DNSClass::IN, rrsig, &records)
+                        .is_ok()
+            })
+        })
+        .cloned()
+        .collect();
+
+    (keys, signing_keys)
+}
+
+/// Compares the answers of the name servers with each other and with the delegation
+fn analyze(
+    delegation: &[Name],
+    #[cfg(feature = "dnssec")] zone: &Name,
+    #[cfg(feature = "dnssec")] ds: &[DS],
+    name_servers: &[NameServerReport],
+) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    let serials = name_servers
+        .iter()
+        .map(|name_server| (name_server.addr, name_server.serial))
+        .collect::<Vec<_>>();
+    if serials.windows(2).any(|pair| pair[0].1 != pair[1].1) {
+        findings.push(Finding::SerialMismatch { serials });
+    }
+
+    for name_server in name_servers {
+        if !delegation.is_empty() && name_server.name_servers != delegation {
+            findings.push(Finding::NameServersMismatch {
+                addr: name_server.addr,
+                name_servers: name_server.name_servers.clone(),
+            });
+        }
+
+        #[cfg(feature = "dnssec")]
+        if !ds.is_empty() {
+            let covered = |key: &&DNSKEY| ds.iter().any(|ds| ds.covers(zone, key).unwrap_or(false));
+            if !name_server.dnskeys.iter().any(|key| covered(&key)) {
+                findings.push(Finding::NoKeyForDs {
+                    addr: name_server.addr,
+                });
+            } else if !name_server.signing_keys.iter().any(|key| covered(&key)) {
+                findings.push(Finding::KeysNotSigned {
+                    addr: name_server.addr,
+                });
+            }
+        }
+    }
+
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn name(name: &str) -> Name {
+        Name::from_str(name).unwrap()
+    }
+
+    fn report(addr: &str, serial: u32, name_servers: &[&str]) -> NameServerReport {
+        NameServerReport {
+            name: name("ns1.example.com."),
+            addr: addr.parse().unwrap(),
+            serial,
+            name_servers: sorted(name_servers.iter().map(|n| name(n)).collect()),
+            #[cfg(feature = "dnssec")]
+            dnskeys: Vec::new(),
+            #[cfg(feature = "dnssec")]
+            signing_keys: Vec::new(),
+        }
+    }
+
+    fn analyze(delegation: &[&str], name_servers: &[NameServerReport]) -> Vec<Finding> {
+        let delegation = sorted(delegation.iter().map(|n| name(n)).collect());
+        super::analyze(
+            &delegation,
+            #[cfg(feature = "dnssec")]
+            &name("example.com."),
+            #[cfg(feature = "dnssec")]
+            &[],
+            name_servers,
+        )
+    }
+
+    #[test]
+    fn test_consistent() {
+        let findings = analyze(
+            &["ns1.example.com.", "ns2.example.com."],
+            &[
+                report("127.0.0.1:53", 1, &["ns2.example.com.", "ns1.example.com."]),
+                report("127.0.0.2:53", 1, &["NS1.example.com.", "ns2.example.com."]),
+            ],
+        );
+        assert!(findings.is_empty(), "{findings:?}");
+    }
+
+    #[test]
+    fn test_serial_mismatch() {
+        let findings = analyze(
+            &["ns1.example.com."],
+            &[
+                report("127.0.0.1:53", 1, &["ns1.example.com."]),
+                report("127.0.0.2:53", 2, &["ns1.example.com."]),
+            ],
+        );
+        assert_eq!(
+            findings,
+            vec![Finding::SerialMismatch {
+                serials: vec![
+                    ("127.0.0.1:53".parse().unwrap(), 1),
+                    ("127.0.0.2:53".parse().unwrap(), 2)
+                ]
+            }]
+        );
+    }
+
+    #[test]
+    fn test_name_servers_mismatch() {
+        let findings = analyze(
+            &["ns1.example.com.", "ns2.example.com."],
+            &[report("127.0.0.1:53", 1, &["ns1.example.com."])],
+        );
+        assert_eq!(
+            findings,
+            vec![Finding::NameServersMismatch {
+                addr: "127.0.0.1:53".parse().unwrap(),
+                name_servers: vec![name("ns1.example.com.")],
+            }]
+        );
+    }
+
+    #[cfg(feature = "dnssec-ring")]
+    #[test]
+    fn test_dnssec_chain() {
+        use crate::proto::rr::dnssec::{Algorithm, DigestType};
+
+        let zone = name("example.com.");
+        let key = DNSKEY::new(true, true, false, Algorithm::ED25519, vec![1; 32]);
+        let other_key = DNSKEY::new(true, false, false, Algorithm::ED25519, vec![0; 32]);
+        let digest = key.to_digest(&zone, DigestType::SHA256).unwrap();
+        let ds = [DS::new(
+            key.calculate_key_tag().unwrap(),
+            Algorithm::ED25519,
+            DigestType::SHA256,
+            digest.as_ref().to_vec(),
+        )];
+
+        let mut signed = report("127.0.0.1:53", 1, &[]);
+        signed.dnskeys = vec![key.clone(), other_key.clone()];
+        signed.signing_keys = vec![key];
+        let mut unsigned = report("127.0.0.2:53", 1, &[]);
+        unsigned.dnskeys = signed.dnskeys.clone();
+        let mut unmatched = report("127.0.0.3:53", 1, &[]);
+        unmatched.dnskeys = vec![other_key];
+
+        let findings = super::analyze(&[], &zone, &ds, &[signed, unsigned, unmatched]);
+        assert_eq!(
+            findings,
+            vec![
+                Finding::KeysNotSigned {
+                    addr: "127.0.0.2:53".parse().unwrap()
+                },
+                Finding::NoKeyForDs {
+                    addr: "127.0.0.3:53".parse().unwrap()
+                },
+            ]
+        );
+    }
+}
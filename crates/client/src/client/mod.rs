@@ -20,17 +20,21 @@ pub(crate) mod async_client;
 #[cfg(feature = "dnssec")]
 #[cfg_attr(docsrs, doc(cfg(feature = "dnssec")))]
 pub(crate) mod async_secure_client;
+mod blocking_client;
 #[allow(clippy::module_inception)]
 mod client;
 pub mod client_connection;
+mod delegation;
 mod memoize_client_handle;
 mod rc_stream;
+mod zone_transfer;
 
 #[allow(deprecated)]
 pub use self::async_client::{AsyncClient, ClientFuture, ClientHandle, ClientStreamingResponse};
 #[cfg(feature = "dnssec")]
 #[cfg_attr(docsrs, doc(cfg(feature = "dnssec")))]
 pub use self::async_secure_client::{AsyncDnssecClient, AsyncSecureClientBuilder};
+pub use self::blocking_client::BlockingClient;
 #[cfg(feature = "dnssec")]
 #[cfg_attr(docsrs, doc(cfg(feature = "dnssec")))]
 pub use self::client::SyncDnssecClient;
@@ -38,4 +42,8 @@ pub use self::client::SyncDnssecClient;
 pub use self::client::{BlockingStream, Client, SyncClient};
 pub use self::client_connection::ClientConnection;
 pub use self::client_connection::Signer;
+pub use self::delegation::{
+    AddressLookup, DelegationChecker, DelegationReport, Finding, NameServerReport,
+};
 pub use self::memoize_client_handle::MemoizeClientHandle;
+pub use self::zone_transfer::{ZoneDelta, ZoneTransferClient, ZoneTransferLimits};
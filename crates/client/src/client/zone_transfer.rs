@@ -0,0 +1,706 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Mirroring of zones with AXFR and IXFR
+
+use std::collections::BTreeMap;
+
+use futures_util::stream::StreamExt;
+use tracing::debug;
+
+use crate::{
+    client::ClientHandle,
+    error::*,
+    op::ResponseCode,
+    proto::rr::{LowerName, RrKey},
+    rr::{rdata::SOA, Name, RData, Record, RecordSet, RecordType},
+};
+
+/// A change to the mirrored zone, received in a zone transfer
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ZoneDelta {
+    /// The zone was replaced with the records of a full transfer (AXFR)
+    Full {
+        /// The serial of the zone after the transfer
+        serial: u32,
+        /// All records of the zone, including the SOA record
+        records: Vec<Record>,
+    },
+    /// The records of the zone changed from one serial to the next (IXFR)
+    Incremental {
+        /// The serial of the zone the changes apply to
+        from_serial: u32,
+        /// The serial of the zone after the changes
+        to_serial: u32,
+        /// The records which were removed, starting with the old SOA record
+        deleted: Vec<Record>,
+        /// The records which were added, starting with the new SOA record
+        added: Vec<Record>,
+    },
+}
+
+/// Limits of the zone transfers of a `ZoneTransferClient`, which protect against primaries
+///  sending an unbounded amount of data
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ZoneTransferLimits {
+    /// The maximum number of records of a transfer, `None` for no limit
+    pub max_records: Option<usize>,
+    /// The maximum size of the messages of a transfer in bytes, `None` for no limit
+    pub max_bytes: Option<usize>,
+}
+
+impl ZoneDelta {
+    /// Returns the serial of the zone once this change is applied
+    pub fn serial(&self) -> u32 {
+        match self {
+            Self::Full { serial, .. } => *serial,
+            Self::Incremental { to_serial, .. } => *to_serial,
+        }
+    }
+}
+
+/// A client keeping a copy of a zone up to date with zone transfers
+///
+/// The first transfer is a full transfer (AXFR), all further transfers request only the changes
+///  since the last serial (IXFR). The server may still answer these with a full transfer. The
+///  changes are only applied once the transfer completed and was validated, so a failed transfer
+///  leaves the copy of the zone at the last serial. Transfers containing records outside of the
+///  zone, misplaced SOA records, or serials which do not line up are rejected with a
+///  `ZoneTransferError`.
+///
+/// This works with any `ClientHandle`, so TSIG is configured by creating the `AsyncClient` with a
+///  `Signer::TSIG`, and transfers over TLS by creating it with a `TlsClientStream`.
+///
+/// ```no_run
+/// use std::str::FromStr;
+/// use tokio::net::TcpStream as TokioTcpStream;
+/// use hickory_client::client::{AsyncClient, ZoneTransferClient};
+/// use hickory_client::proto::iocompat::AsyncIoTokioAsStd;
+/// use hickory_client::rr::Name;
+/// use hickory_client::tcp::TcpClientStream;
+///
+/// # async fn mirror() {
+/// let (stream, sender) =
+///     TcpClientStream::<AsyncIoTokioAsStd<TokioTcpStream>>::new(([192, 0, 2, 1], 53).into());
+/// let (client, bg) = AsyncClient::new(stream, sender, None).await.unwrap();
+/// tokio::spawn(bg);
+///
+/// let mut zone = ZoneTransferClient::new(client, Name::from_str("example.com.").unwrap());
+/// zone.transfer_with(|delta| println!("zone now at serial {}", delta.serial()))
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+pub struct ZoneTransferClient<C: ClientHandle> {
+    client: C,
+    origin: Name,
+    soa: Option<SOA>,
+    records: BTreeMap<RrKey, RecordSet>,
+    limits: ZoneTransferLimits,
+}
+
+impl<C: ClientHandle> ZoneTransferClient<C> {
+    /// Creates a new client for the zone, without any records
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - the client used for the transfers, connected to the primary server
+    /// * `origin` - the name of the zone
+    pub fn new(client: C, origin: Name) -> Self {
+        Self {
+            client,
+            origin,
+            soa: None,
+            records: BTreeMap::new(),
+            limits: ZoneTransferLimits::default(),
+        }
+    }
+
+    /// Sets the limits of the transfers, by default there are none
+    pub fn set_limits(&mut self, limits: ZoneTransferLimits) {
+        self.limits = limits;
+    }
+
+    /// Creates a new client for a previously transferred copy of the zone
+    ///
+    /// This allows restoring a backup of the zone, so that only the changes since the serial of
+    ///  the backup are transferred.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - the client used for the transfers, connected to the primary server
+    /// * `origin` - the name of the zone
+    /// * `records` - all records of the zone, including the SOA record of the origin
+    pub fn with_records(
+        client: C,
+        origin: Name,
+        records: impl IntoIterator<Item = Record>,
+    ) -> ClientResult<Self> {
+        let records = records.into_iter().collect::<Vec<_>>();
+        let serial = records
+            .iter()
+            .filter(|record| record.name() == &origin)
+            .find_map(soa_serial)
+            .ok_or(ClientErrorKind::Message(
+                "zone records do not contain an SOA record",
+            ))?;
+
+        let mut zone = Self::new(client, origin);
+        zone.apply(&ZoneDelta::Full { serial, records });
+        Ok(zone)
+    }
+
+    /// Returns the name of the zone
+    pub fn origin(&self) -> &Name {
+        &self.origin
+    }
+
+    /// Returns the serial of the copy of the zone, `None` before the first transfer
+    pub fn serial(&self) -> Option<u32> {
+        self.soa.as_ref().map(SOA::serial)
+    }
+
+    /// Returns the records of the copy of the zone
+    pub fn records(&self) -> &BTreeMap<RrKey, RecordSet> {
+        &self.records
+    }
+
+    /// Consumes the client, returning the records of the copy of the zone
+    pub fn into_records(self) -> BTreeMap<RrKey, RecordSet> {
+        self.records
+    }
+
+    /// Transfers the changes to the zone since the last transfer
+    ///
+    /// # Return
+    ///
+    /// The changes applied to the copy of the zone, empty if it was already up to date
+    pub async fn transfer(&mut self) -> ClientResult<Vec<ZoneDelta>> {
+        let mut deltas = Vec::new();
+        self.transfer_with(|delta| deltas.push(delta.clone()))
+            .await?;
+        Ok(deltas)
+    }
+
+    /// Transfers the changes to the zone since the last transfer, calling `on_delta` for every
+    ///  change after it was applied
+    ///
+    /// # Return
+    ///
+    /// The serial of the copy of the zone after the transfer
+    pub async fn transfer_with<F>(&mut self, mut on_delta: F) -> ClientResult<u32>
+    where
+        F: FnMut(&ZoneDelta),
+    {
+        let records = self.receive().await?;
+        let deltas = parse(records, &self.origin, self.serial())?;
+
+        for delta in &deltas {
+            self.apply(delta);
+            on_delta(delta);
+        }
+
+        self.serial()
+            .ok_or_else(|| ZoneTransferError::MissingSoa.into())
+    }
+
+    /// Sends the transfer request, returning all records of the transfer
+    async fn receive(&mut self) -> ClientResult<Vec<Record>> {
+        let current = self.serial();
+        debug!(
+            "requesting transfer of {} since serial {:?}",
+            self.origin, current
+        );
+
+        let mut responses = self
+            .client
+            .zone_transfer(self.origin.clone(), self.soa.clone());
+        let mut records = Vec::new();
+        let mut size = 0;
+        while let Some(response) = responses.next().await {
+            let response = response?;
+            if response.response_code() != ResponseCode::NoError {
+                return Err(ZoneTransferError::Rejected(response.response_code()).into());
+            }
+
+            size += response.as_buffer().len();
+            if let Some(max_bytes) = self.limits.max_bytes.filter(|max| size > *max) {
+                return Err(ZoneTransferError::TooLarge(max_bytes).into());
+            }
+
+            if let Some(record) = response
+                .answers()
+                .iter()
+                .find(|record| !self.origin.zone_of(record.name()))
+            {
+                return Err(ZoneTransferError::OutOfZone(record.name().clone()).into());
+            }
+
+            records.extend(response.answers().iter().cloned());
+            if let Some(max_records) = self.limits.max_records.filter(|max| records.len() > *max) {
+                return Err(ZoneTransferError::TooManyRecords(max_records).into());
+            }
+
+            // a single SOA record with the current serial signals the zone is up to date
+            if let [soa] = records.as_slice() {
+                if current.is_some() && soa_serial(soa) == current {
+                    break;
+                }
+            }
+        }
+
+        Ok(records)
+    }
+
+    /// Applies the change to the copy of the zone
+    fn apply(&mut self, delta: &ZoneDelta) {
+        match delta {
+            ZoneDelta::Full { serial, records } => {
+                self.records.clear();
+                for record in records {
+                    self.insert(record.clone(), *serial);
+                }
+            }
+            ZoneDelta::Incremental {
+                to_serial,
+                deleted,
+                added,
+                ..
+            } => {
+                for record in deleted {
+                    self.remove(record);
+                }
+                for record in added {
+                    self.insert(record.clone(), *to_serial);
+                }
+            }
+        }
+    }
+
+    fn insert(&mut self, record: Record, serial: u32) {
+        let key = RrKey::new(LowerName::from(record.name()), record.record_type());
+
+        // the SOA always replaces the previous one, which is never deleted
+        if let Some(RData::SOA(soa)) = record.data() {
+            if record.name() == &self.origin {
+                self.soa = Some(soa.clone());
+            }
+            self.records.remove(&key);
+        }
+
+        self.records
+            .entry(key)
+            .or_insert_with(|| RecordSet::new(record.name(), record.record_type(), serial))
+            .insert(record, serial);
+    }
+
+    fn remove(&mut self, record: &Record) {
+        if record.record_type() == RecordType::SOA {
+            return;
+        }
+
+        let key = RrKey::new(LowerName::from(record.name()), record.record_type());
+        let Some(rrset) = self.records.get_mut(&key) else {
+            return;
+        };
+
+        // RecordSet::remove keeps the last NS record, but the transfer may replace all of them
+        let last = rrset.records_without_rrsigs().count() <= 1
+            && rrset
+                .records_without_rrsigs()
+                .any(|r| r.data() == record.data());
+        if last {
+            self.records.remove(&key);
+        } else {
+            rrset.remove(record, rrset.serial());
+        }
+    }
+}
+
+fn soa_serial(record: &Record) -> Option<u32> {
+    record.data().and_then(RData::as_soa).map(SOA::serial)
+}
+
+/// Splits the records of a transfer into the changes to the zone
+///
+/// See [RFC 1995, section 4](https://datatracker.ietf.org/doc/html/rfc1995#section-4) for the
+///  format of IXFR responses, which may also be in the format of an AXFR response.
+///
+/// The SOA records must be at the origin of the zone, and a full transfer only contains the SOA
+///  records at its start and end.
+fn parse(
+    mut records: Vec<Record>,
+    origin: &Name,
+    current: Option<u32>,
+) -> Result<Vec<ZoneDelta>, ZoneTransferError> {
+    if let Some(soa) = records
+        .iter()
+        .find(|r| r.record_type() == RecordType::SOA && r.name() != origin)
+    {
+        return Err(ZoneTransferError::UnexpectedSoa(soa.name().clone()));
+    }
+
+    let serial = records
+        .first()
+        .and_then(soa_serial)
+        .ok_or(ZoneTransferError::MissingSoa)?;
+
+    // the zone is up to date
+    if records.len() == 1 && current == Some(serial) {
+        return Ok(vec![]);
+    }
+
+    if records.len() < 2 || records.last().and_then(soa_serial) != Some(serial) {
+        return Err(ZoneTransferError::MissingEndSoa(serial));
+    }
+
+    // an incremental transfer has the old SOA as the second record, a full transfer of a zone
+    //  only containing the SOA record has the same SOA again
+    let incremental =
+        current.is_some() && records.len() > 2 && records[1].record_type() == RecordType::SOA;
+    if !incremental {
+        records.pop();
+        if let Some(soa) = records[1..]
+            .iter()
+            .find(|r| r.record_type() == RecordType::SOA)
+        {
+            return Err(ZoneTransferError::UnexpectedSoa(soa.name().clone()));
+        }
+
+        // serials are compared with the sequence space arithmetic of RFC 1982
+        if let Some(current) = current.filter(|current| (serial.wrapping_sub(*current) as i32) < 0)
+        {
+            return Err(ZoneTransferError::OlderSerial { serial, current });
+        }
+
+        return Ok(vec![ZoneDelta::Full { serial, records }]);
+    }
+
+    let mut deltas = Vec::new();
+    let mut expected = current;
+    let mut changes = records[1..records.len() - 1].iter().peekable();
+    while let Some(old_soa) = changes.next() {
+        let from_serial = soa_serial(old_soa).ok_or(ZoneTransferError::MissingSoa)?;
+        if Some(from_serial) != expected {
+            return Err(ZoneTransferError::SerialMismatch {
+                from: from_serial,
+                expected,
+            });
+        }
+
+        let mut deleted = vec![old_soa.clone()];
+        while let Some(record) = changes.next_if(|r| r.record_type() != RecordType::SOA) {
+            deleted.push(record.clone());
+        }
+
+        let new_soa = changes.next().ok_or(ZoneTransferError::MissingNewSoa)?;
+        let to_serial = soa_serial(new_soa).expect("only SOA records left");
+
+        let mut added = vec![new_soa.clone()];
+        while let Some(record) = changes.next_if(|r| r.record_type() != RecordType::SOA) {
+            added.push(record.clone());
+        }
+
+        deltas.push(ZoneDelta::Incremental {
+            from_serial,
+            to_serial,
+            deleted,
+            added,
+        });
+        expected = Some(to_serial);
+    }
+
+    if expected != Some(serial) {
+        return Err(ZoneTransferError::Incomplete(serial));
+    }
+
+    Ok(deltas)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        pin::Pin,
+        str::FromStr,
+        sync::{Arc, Mutex},
+    };
+
+    use futures::{executor::block_on, stream, Stream};
+    use hickory_proto::{
+        error::ProtoError,
+        xfer::{DnsHandle, DnsRequest, DnsResponse},
+    };
+
+    use super::*;
+    use crate::{
+        op::Message,
+        rr::rdata::{A, NS},
+    };
+
+    /// Answers each transfer with the next list of messages
+    #[derive(Clone, Default)]
+    struct TestClient {
+        transfers: Arc<Mutex<Vec<Vec<Message>>>>,
+        requests: Arc<Mutex<Vec<RecordType>>>,
+    }
+
+    impl DnsHandle for TestClient {
+        type Response = Pin<Box<dyn Stream<Item = Result<DnsResponse, ProtoError>> + Send>>;
+
+        fn send<R: Into<DnsRequest> + Send + 'static>(&self, request: R) -> Self::Response {
+            let request = request.into();
+            self.requests
+                .lock()
+                .unwrap()
+                .push(request.queries()[0].query_type());
+
+            let messages = self.transfers.lock().unwrap().remove(0);
+            Box::pin(stream::iter(
+                messages
+                    .into_iter()
+                    .map(|message| Ok(DnsResponse::from_message(message).unwrap())),
+            ))
+        }
+    }
+
+    impl TestClient {
+        fn push(&self, transfer: Vec<Vec<Record>>) {
+            let messages = transfer
+                .into_iter()
+                .map(|records| {
+                    let mut message = Message::new();
+                    message.add_answers(records);
+                    message
+                })
+                .collect();
+            self.transfers.lock().unwrap().push(messages);
+        }
+
+        fn last_request(&self) -> RecordType {
+            *self.requests.lock().unwrap().last().unwrap()
+        }
+    }
+
+    fn origin() -> Name {
+        Name::from_str("example.com.").unwrap()
+    }
+
+    fn soa(serial: u32) -> Record {
+        Record::from_rdata(
+            origin(),
+            3600,
+            RData::SOA(SOA::new(
+                Name::from_str("ns.example.com.").unwrap(),
+                Name::from_str("hostmaster.example.com.").unwrap(),
+                serial,
+                3600,
+                600,
+                86400,
+                300,
+            )),
+        )
+    }
+
+    fn a(ip: u8) -> Record {
+        Record::from_rdata(
+            Name::from_str("www.example.com.").unwrap(),
+            300,
+            RData::A(A::new(192, 0, 2, ip)),
+        )
+    }
+
+    fn ns() -> Record {
+        Record::from_rdata(
+            origin(),
+            300,
+            RData::NS(NS(Name::from_str("ns.example.com.").unwrap())),
+        )
+    }
+
+    fn addresses(zone: &ZoneTransferClient<TestClient>) -> Vec<RData> {
+        let key = RrKey::new(
+            LowerName::from_str("www.example.com.").unwrap(),
+            RecordType::A,
+        );
+        zone.records()
+            .get(&key)
+            .map(|rrset| {
+                rrset
+                    .records_without_rrsigs()
+                    .filter_map(Record::data)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_axfr_then_ixfr() {
+        let client = TestClient::default();
+        let mut zone = ZoneTransferClient::new(client.clone(), origin());
+
+        // the first transfer is a full transfer, split over multiple messages
+        client.push(vec![vec![soa(1), ns(), a(1)], vec![soa(1)]]);
+        let deltas = block_on(zone.transfer()).unwrap();
+        assert_eq!(client.last_request(), RecordType::AXFR);
+        assert_eq!(
+            deltas,
+            vec![ZoneDelta::Full {
+                serial: 1,
+                records: vec![soa(1), ns(), a(1)],
+            }]
+        );
+        assert_eq!(zone.serial(), Some(1));
+        assert_eq!(zone.records().len(), 3);
+        assert_eq!(addresses(&zone), vec![RData::A(A::new(192, 0, 2, 1))]);
+
+        // further transfers are incremental, with multiple changes
+        client.push(vec![vec![
+            soa(3),
+            soa(1),
+            a(1),
+            soa(2),
+            a(2),
+            soa(2),
+            soa(3),
+            a(3),
+            soa(3),
+        ]]);
+        let mut serials = Vec::new();
+        let serial = block_on(zone.transfer_with(|delta| serials.push(delta.serial()))).unwrap();
+        assert_eq!(client.last_request(), RecordType::IXFR);
+        assert_eq!(serial, 3);
+        assert_eq!(serials, vec![2, 3]);
+        assert_eq!(
+            addresses(&zone),
+            vec![
+                RData::A(A::new(192, 0, 2, 2)),
+                RData::A(A::new(192, 0, 2, 3))
+            ]
+        );
+
+        // a single SOA record means the zone is up to date
+        client.push(vec![vec![soa(3)]]);
+        assert!(block_on(zone.transfer()).unwrap().is_empty());
+        assert_eq!(zone.serial(), Some(3));
+
+        // the server may answer an incremental transfer with a full transfer
+        client.push(vec![vec![soa(4), ns(), a(4), soa(4)]]);
+        let deltas = block_on(zone.transfer()).unwrap();
+        assert!(matches!(deltas[..], [ZoneDelta::Full { serial: 4, .. }]));
+        assert_eq!(addresses(&zone), vec![RData::A(A::new(192, 0, 2, 4))]);
+    }
+
+    #[test]
+    fn test_invalid_transfer() {
+        let client = TestClient::default();
+        let mut zone =
+            ZoneTransferClient::with_records(client.clone(), origin(), vec![soa(1), ns(), a(1)])
+                .unwrap();
+        assert_eq!(zone.serial(), Some(1));
+
+        // changes which do not apply to the current serial are rejected
+        client.push(vec![vec![soa(3), soa(2), a(1), soa(3), a(3), soa(3)]]);
+        assert!(block_on(zone.transfer()).is_err());
+
+        // as are incomplete transfers
+        client.push(vec![vec![soa(2), soa(1), a(1), soa(2)]]);
+        assert!(block_on(zone.transfer()).is_err());
+
+        // and refused transfers
+        let mut refused = Message::new();
+        refused.set_response_code(ResponseCode::Refused);
+        client.transfers.lock().unwrap().push(vec![refused]);
+        assert!(block_on(zone.transfer()).is_err());
+
+        // none of which changed the zone
+        assert_eq!(zone.serial(), Some(1));
+        assert_eq!(addresses(&zone), vec![RData::A(A::new(192, 0, 2, 1))]);
+    }
+
+    fn transfer_error(zone: &mut ZoneTransferClient<TestClient>) -> ZoneTransferError {
+        match block_on(zone.transfer()).unwrap_err().kind() {
+            ClientErrorKind::ZoneTransfer(e) => e.clone(),
+            e => panic!("unexpected error: {e}"),
+        }
+    }
+
+    #[test]
+    fn test_transfer_validation() {
+        let client = TestClient::default();
+        let mut zone =
+            ZoneTransferClient::with_records(client.clone(), origin(), vec![soa(2), ns(), a(1)])
+                .unwrap();
+
+        let other = Record::from_rdata(
+            Name::from_str("www.example.net.").unwrap(),
+            300,
+            RData::A(A::new(192, 0, 2, 9)),
+        );
+        client.push(vec![vec![soa(3), other, soa(3)]]);
+        assert_eq!(
+            transfer_error(&mut zone),
+            ZoneTransferError::OutOfZone(Name::from_str("www.example.net.").unwrap())
+        );
+
+        // a full transfer only has the SOA records at its start and end
+        client.push(vec![vec![soa(3), a(3), soa(4), a(4), soa(3)]]);
+        assert_eq!(
+            transfer_error(&mut zone),
+            ZoneTransferError::TrailingRecords
+        );
+
+        // which are at the origin of the zone
+        let mut other = soa(3);
+        other.set_name(Name::from_str("sub.example.com.").unwrap());
+        assert_eq!(
+            parse(
+                vec![soa(3), soa(2), other, soa(3), soa(3)],
+                &origin(),
+                Some(2)
+            ),
+            Err(ZoneTransferError::UnexpectedSoa(
+                Name::from_str("sub.example.com.").unwrap()
+            ))
+        );
+
+        client.push(vec![vec![soa(1), ns(), a(1), soa(1)]]);
+        assert_eq!(
+            transfer_error(&mut zone),
+            ZoneTransferError::OlderSerial {
+                serial: 1,
+                current: 2
+            }
+        );
+
+        client.push(vec![vec![soa(4), soa(2), a(1), soa(3), a(3), soa(4)]]);
+        assert_eq!(transfer_error(&mut zone), ZoneTransferError::Incomplete(4));
+
+        // the limits are checked as the messages are received
+        zone.set_limits(ZoneTransferLimits {
+            max_records: Some(3),
+            ..ZoneTransferLimits::default()
+        });
+        client.push(vec![vec![soa(3), ns()], vec![a(3), soa(3)]]);
+        assert_eq!(
+            transfer_error(&mut zone),
+            ZoneTransferError::TooManyRecords(3)
+        );
+
+        zone.set_limits(ZoneTransferLimits {
+            max_bytes: Some(64),
+            ..ZoneTransferLimits::default()
+        });
+        client.push(vec![vec![soa(3), ns(), a(3), soa(3)]]);
+        assert_eq!(transfer_error(&mut zone), ZoneTransferError::TooLarge(64));
+
+        assert_eq!(zone.serial(), Some(2));
+        assert_eq!(addresses(&zone), vec![RData::A(A::new(192, 0, 2, 1))]);
+    }
+}
@@ -15,6 +15,8 @@ use thiserror::Error;
 #[cfg(feature = "backtrace")]
 use crate::proto::{trace, ExtBacktrace};
 use hickory_proto::error::{DnsSecError, DnsSecErrorKind, ProtoError, ProtoErrorKind};
+use hickory_proto::op::ResponseCode;
+use hickory_proto::rr::Name;
 
 /// An alias for results returned by functions of this crate
 pub type Result<T> = ::std::result::Result<T, Error>;
@@ -51,6 +53,77 @@ pub enum ErrorKind {
     /// A request timed out
     #[error("request timed out")]
     Timeout,
+
+    /// A zone transfer was rejected or failed validation
+    #[error("invalid zone transfer: {0}")]
+    ZoneTransfer(#[from] ZoneTransferError),
+}
+
+/// The reason a zone transfer was not applied, see `ZoneTransferClient`
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ZoneTransferError {
+    /// The server did not answer with `NoError`
+    #[error("the server answered with {0}")]
+    Rejected(ResponseCode),
+
+    /// The first record is not the SOA record of the zone
+    #[error("does not start with an SOA record")]
+    MissingSoa,
+
+    /// The last record is not the SOA record the transfer started with
+    #[error("does not end with the SOA record of serial {0}")]
+    MissingEndSoa(u32),
+
+    /// A record is not in the zone
+    #[error("record {0} is outside of the zone")]
+    OutOfZone(Name),
+
+    /// An SOA record is in a position where it is not allowed, or not at the origin of the zone
+    #[error("unexpected SOA record of {0}")]
+    UnexpectedSoa(Name),
+
+    /// The serial of a full transfer is older than the serial of the copy of the zone
+    #[error("serial {serial} is older than the current serial {current}")]
+    OlderSerial {
+        /// The serial of the transfer
+        serial: u32,
+        /// The serial of the copy of the zone
+        current: u32,
+    },
+
+    /// A change of an incremental transfer does not apply to the serial reached so far
+    #[error("change from serial {from} does not apply to serial {expected:?}")]
+    SerialMismatch {
+        /// The serial the change applies to
+        from: u32,
+        /// The serial of the zone before the change
+        expected: Option<u32>,
+    },
+
+    /// A change of an incremental transfer has no SOA record for the new serial
+    #[error("change is missing the new SOA record")]
+    MissingNewSoa,
+
+    /// The changes of an incremental transfer do not reach the serial of the transfer
+    #[error("changes do not end at serial {0}")]
+    Incomplete(u32),
+
+    /// Records follow the SOA record which ended the transfer
+    #[error("contains trailing records")]
+    TrailingRecords,
+
+    /// The server answered a full transfer request with an incremental transfer
+    #[error("expected AXFR, got IXFR")]
+    UnexpectedIxfr,
+
+    /// The transfer contains more records than allowed by `ZoneTransferLimits::max_records`
+    #[error("exceeds the limit of {0} records")]
+    TooManyRecords(usize),
+
+    /// The transfer is larger than allowed by `ZoneTransferLimits::max_bytes`
+    #[error("exceeds the limit of {0} bytes")]
+    TooLarge(usize),
 }
 
 impl Clone for ErrorKind {
@@ -65,6 +138,7 @@ impl Clone for ErrorKind {
             Proto(proto) => Proto(proto.clone()),
             SendError(e) => SendError(e.clone()),
             Timeout => Timeout,
+            ZoneTransfer(e) => ZoneTransfer(e.clone()),
         }
     }
 }
@@ -138,6 +212,12 @@ impl From<DnsSecError> for Error {
     }
 }
 
+impl From<ZoneTransferError> for Error {
+    fn from(e: ZoneTransferError) -> Self {
+        ErrorKind::from(e).into()
+    }
+}
+
 impl From<io::Error> for Error {
     fn from(e: io::Error) -> Self {
         match e.kind() {
@@ -22,3 +22,4 @@ mod client_error;
 pub use self::client_error::Error as ClientError;
 pub use self::client_error::ErrorKind as ClientErrorKind;
 pub use self::client_error::Result as ClientResult;
+pub use self::client_error::ZoneTransferError;
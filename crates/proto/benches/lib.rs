@@ -5,7 +5,9 @@ extern crate test;
 
 use hickory_proto::op::{Header, Message, MessageType, OpCode, ResponseCode};
 use hickory_proto::rr::Record;
-use hickory_proto::serialize::binary::{BinDecodable, BinDecoder, BinEncodable, BinEncoder};
+use hickory_proto::serialize::binary::{
+    BinDecodable, BinDecoder, BinEncodable, BinEncoder, EncodeBuffer,
+};
 
 use test::Bencher;
 
@@ -70,6 +72,21 @@ fn bench_emit_message(b: &mut Bencher) {
     })
 }
 
+#[bench]
+fn bench_emit_message_reused_buffer(b: &mut Bencher) {
+    let mut message = Message::new();
+    message
+        .set_id(10)
+        .set_message_type(MessageType::Response)
+        .set_response_code(ResponseCode::ServFail);
+    message.add_answer(Record::new());
+    message.add_name_server(Record::new());
+    message.add_additional(Record::new());
+
+    let mut buffer = EncodeBuffer::new();
+    b.iter(|| message.emit(&mut buffer.encoder()))
+}
+
 #[bench]
 fn bench_emit_message_no_reservation(b: &mut Bencher) {
     let mut message = Message::new();
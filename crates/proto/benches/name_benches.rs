@@ -158,3 +158,41 @@ fn name_no_lower_long(b: &mut Bencher) {
         assert_eq!(lower.num_labels(), 3);
     });
 }
+
+#[bench]
+fn name_hash_long(b: &mut Bencher) {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    let name1 = Name::from_ascii("a.crazy.really.long.EXAMPLE.com").unwrap();
+
+    b.iter(|| {
+        let mut hasher = DefaultHasher::new();
+        name1.hash(&mut hasher);
+        hasher.finish()
+    });
+}
+
+#[bench]
+fn lower_name_hash_long(b: &mut Bencher) {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    let name1 = LowerName::from(Name::from_ascii("a.crazy.really.long.EXAMPLE.com").unwrap());
+
+    b.iter(|| {
+        let mut hasher = DefaultHasher::new();
+        name1.hash(&mut hasher);
+        hasher.finish()
+    });
+}
+
+#[bench]
+fn lower_name_eq_long(b: &mut Bencher) {
+    let name1 = LowerName::from(Name::from_ascii("a.crazy.really.long.example.com").unwrap());
+    let name2 = LowerName::from(Name::from_ascii("a.crazy.really.long.EXAMPLE.com").unwrap());
+
+    b.iter(|| {
+        assert_eq!(name1, name2);
+    });
+}
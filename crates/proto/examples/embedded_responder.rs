@@ -0,0 +1,101 @@
+//! Answers A, PTR and SRV queries from a static table, without allocating per query
+//!
+//! The table is in read-only data and the messages are in fixed buffers, as on a device without a
+//!  heap. A standard UDP socket stands in for the network stack of the device, with smoltcp the
+//!  `SmoltcpUdpSocket` of the `smoltcp` feature receives and sends the messages.
+//!
+//! ```text
+//! cargo run --example embedded_responder --features embedded -- 127.0.0.1:5353
+//! dig @127.0.0.1 -p 5353 _http._tcp.device.local. SRV
+//! ```
+
+use std::net::{Ipv4Addr, UdpSocket};
+
+use hickory_proto::embedded::{Query, Response, WireError};
+use hickory_proto::op::ResponseCode;
+use hickory_proto::rr::{RecordType, StaticName};
+use hickory_proto::static_name;
+
+/// The largest message without EDNS
+const MAX_MESSAGE_SIZE: usize = 512;
+const TTL: u32 = 120;
+
+const HOST: StaticName = static_name!("device.local.");
+const ADDRESS: Ipv4Addr = Ipv4Addr::new(192, 168, 0, 10);
+
+enum Answer {
+    A(Ipv4Addr),
+    Ptr(StaticName),
+    Srv { port: u16, target: StaticName },
+}
+
+static RECORDS: [(StaticName, Answer); 4] = [
+    (HOST, Answer::A(ADDRESS)),
+    (
+        static_name!("10.0.168.192.in-addr.arpa."),
+        Answer::Ptr(HOST),
+    ),
+    (
+        static_name!("_http._tcp.local."),
+        Answer::Ptr(static_name!("_http._tcp.device.local.")),
+    ),
+    (
+        static_name!("_http._tcp.device.local."),
+        Answer::Srv {
+            port: 80,
+            target: HOST,
+        },
+    ),
+];
+
+/// Writes the response to the query into the buffer, and returns its length
+fn respond(query: &[u8], buffer: &mut [u8]) -> Result<usize, WireError> {
+    let query = Query::parse(query)?;
+    let mut response = Response::new(buffer, &query)?;
+
+    let mut known_name = false;
+    for (name, answer) in &RECORDS {
+        if !query.name_eq(name) {
+            continue;
+        }
+        known_name = true;
+
+        match (query.query_type(), answer) {
+            (RecordType::A | RecordType::ANY, Answer::A(ip)) => response.answer_a(TTL, *ip)?,
+            (RecordType::PTR | RecordType::ANY, Answer::Ptr(target)) => {
+                response.answer_ptr(TTL, *target)?
+            }
+            (RecordType::SRV | RecordType::ANY, Answer::Srv { port, target }) => {
+                response.answer_srv(TTL, 0, 0, *port, *target)?;
+                response.answer_a_for(*target, TTL, ADDRESS)?;
+            }
+            _ => (),
+        }
+    }
+
+    if !known_name {
+        response.set_response_code(ResponseCode::NXDomain);
+    }
+
+    Ok(response.finish().len())
+}
+
+fn main() -> std::io::Result<()> {
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:5353".to_string());
+    let socket = UdpSocket::bind(addr)?;
+    println!("listening on {}", socket.local_addr()?);
+
+    let mut query = [0; MAX_MESSAGE_SIZE];
+    let mut response = [0; MAX_MESSAGE_SIZE];
+    loop {
+        let (len, src) = socket.recv_from(&mut query)?;
+        match respond(&query[..len], &mut response) {
+            Ok(len) => {
+                socket.send_to(&response[..len], src)?;
+            }
+            Err(e) => println!("dropped message from {src}: {e}"),
+        }
+    }
+}
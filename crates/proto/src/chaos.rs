@@ -0,0 +1,615 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Fault injection around the sockets and streams of the transports, for testing
+//!
+//! [`ChaosUdpSocket`] wraps any [`DnsUdpSocket`] and [`ChaosStream`] any [`DnsTcpStream`], the
+//!  faults of a [`ChaosConfig`] are then injected into what they receive. The faults are drawn
+//!  from a generator with the seed of the configuration, the same seed injects the same faults
+//!  into the same traffic.
+//!
+//! The wrappers are passed wherever the transports take a socket or stream, e.g. to
+//!  `UdpStream::with_bound`, or from the `bind_udp` and `connect_tcp` of the `RuntimeProvider` of
+//!  a resolver:
+//!
+//! ```rust,no_run
+//! # #[cfg(feature = "tokio-runtime")]
+//! # async fn chaos() -> std::io::Result<()> {
+//! use std::time::Duration;
+//!
+//! use hickory_proto::chaos::{ChaosConfig, ChaosUdpSocket, Latency};
+//! use hickory_proto::udp::{UdpSocket, UdpStream};
+//!
+//! let config = ChaosConfig {
+//!     loss: 0.2,
+//!     latency: Latency::Uniform {
+//!         min: Duration::from_millis(10),
+//!         max: Duration::from_millis(200),
+//!     },
+//!     seed: 42,
+//!     ..ChaosConfig::default()
+//! };
+//!
+//! let socket = <tokio::net::UdpSocket as UdpSocket>::bind("127.0.0.1:0".parse().unwrap()).await?;
+//! let (stream, handle) =
+//!     UdpStream::with_bound(ChaosUdpSocket::new(socket, config), "192.0.2.53:53".parse().unwrap());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_util::ready;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::tcp::DnsTcpStream;
+use crate::udp::{DnsUdpSocket, MAX_RECEIVE_BUFFER_SIZE};
+use crate::Time;
+
+type Delay = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// The distribution of the latency added to each received packet or read
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub enum Latency {
+    /// No latency is added
+    #[default]
+    None,
+    /// The same latency is added to each packet or read
+    Fixed(Duration),
+    /// A latency uniformly distributed between the bounds, inclusive
+    Uniform {
+        /// the shortest latency
+        min: Duration,
+        /// the longest latency
+        max: Duration,
+    },
+    /// A normally distributed latency, the negative samples add no latency
+    Normal {
+        /// the mean of the latencies
+        mean: Duration,
+        /// the standard deviation of the latencies
+        std_dev: Duration,
+    },
+}
+
+impl Latency {
+    fn sample(&self, rng: &mut StdRng) -> Duration {
+        match *self {
+            Self::None => Duration::ZERO,
+            Self::Fixed(latency) => latency,
+            Self::Uniform { min, max } if max <= min => min,
+            Self::Uniform { min, max } => rng.gen_range(min..=max),
+            Self::Normal { mean, std_dev } => {
+                // the Box-Muller transform, of `u1` in `(0, 1]`
+                let u1 = 1.0 - rng.gen::<f64>();
+                let u2 = rng.gen::<f64>();
+                let z = (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos();
+                let latency = mean.as_secs_f64() + z * std_dev.as_secs_f64();
+                Duration::from_secs_f64(latency.max(0.0))
+            }
+        }
+    }
+}
+
+/// The faults injected into the received packets, or the reads of a stream
+///
+/// Each probability is in `[0, 1]`, the default configuration injects no faults.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ChaosConfig {
+    /// The probability of dropping a packet, or of resetting a stream on a read
+    pub loss: f64,
+    /// The probability of receiving a packet twice, each copy has its own latency
+    pub duplication: f64,
+    /// The probability of holding a packet back until the next one was received
+    ///
+    /// A packet held back while no other packet is received is not delivered, as if it was lost.
+    pub reordering: f64,
+    /// The probability of cutting a packet short, or of ending a stream in the middle of a read
+    pub truncation: f64,
+    /// The probability of replacing a random byte of a packet or read with another value
+    pub corruption: f64,
+    /// The latency added to each packet or read
+    pub latency: Latency,
+    /// The seed of the generator of the faults
+    pub seed: u64,
+}
+
+/// The faults of a configuration, with their generator
+#[derive(Debug)]
+struct Faults {
+    config: ChaosConfig,
+    rng: StdRng,
+}
+
+impl Faults {
+    fn new(config: ChaosConfig) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(config.seed),
+            config,
+        }
+    }
+
+    fn happens(&mut self, probability: f64) -> bool {
+        probability > 0.0 && self.rng.gen::<f64>() < probability
+    }
+
+    fn latency(&mut self) -> Duration {
+        self.config.latency.sample(&mut self.rng)
+    }
+
+    /// Replaces a random byte of the data with another value
+    fn corrupt(&mut self, data: &mut [u8]) {
+        if !data.is_empty() && self.happens(self.config.corruption) {
+            let index = self.rng.gen_range(0..data.len());
+            data[index] ^= self.rng.gen_range(1..=u8::MAX);
+        }
+    }
+}
+
+/// The packets received by a `ChaosUdpSocket`, which are not yet delivered
+struct Inbound {
+    faults: Faults,
+    ready: VecDeque<(Vec<u8>, SocketAddr)>,
+    delayed: Vec<(Delay, Vec<u8>, SocketAddr)>,
+    held: Option<(Vec<u8>, SocketAddr)>,
+}
+
+impl Inbound {
+    /// Injects the faults into a received packet
+    fn receive<T: Time>(&mut self, mut packet: Vec<u8>, from: SocketAddr) {
+        if self.faults.happens(self.faults.config.loss) {
+            return;
+        }
+
+        if !packet.is_empty() && self.faults.happens(self.faults.config.truncation) {
+            let len = self.faults.rng.gen_range(0..packet.len());
+            packet.truncate(len);
+        }
+        self.faults.corrupt(&mut packet);
+
+        let copies = if self.faults.happens(self.faults.config.duplication) {
+            2
+        } else {
+            1
+        };
+        for _ in 0..copies {
+            let latency = self.faults.latency();
+            if latency.is_zero() {
+                self.deliver(packet.clone(), from);
+            } else {
+                self.delayed
+                    .push((T::delay_for(latency), packet.clone(), from));
+            }
+        }
+    }
+
+    /// Makes the packet ready, unless it is held back
+    fn deliver(&mut self, packet: Vec<u8>, from: SocketAddr) {
+        if self.held.is_none() && self.faults.happens(self.faults.config.reordering) {
+            self.held = Some((packet, from));
+            return;
+        }
+
+        self.ready.push_back((packet, from));
+        if let Some(held) = self.held.take() {
+            self.ready.push_back(held);
+        }
+    }
+
+    /// Delivers the packets whose latency elapsed
+    fn poll_delayed(&mut self, cx: &mut Context<'_>) {
+        let mut index = 0;
+        while index < self.delayed.len() {
+            if self.delayed[index].0.as_mut().poll(cx).is_ready() {
+                let (_, packet, from) = self.delayed.remove(index);
+                self.deliver(packet, from);
+            } else {
+                index += 1;
+            }
+        }
+    }
+}
+
+/// A UDP socket which injects faults into the packets it receives
+///
+/// All of the faults of the `ChaosConfig` are injected, the packets are sent unchanged. The
+///  socket of each end is wrapped to inject the faults in both directions.
+pub struct ChaosUdpSocket<S> {
+    socket: S,
+    inbound: Mutex<Inbound>,
+}
+
+impl<S: DnsUdpSocket> ChaosUdpSocket<S> {
+    /// Wraps the socket, injecting the faults of the configuration
+    pub fn new(socket: S, config: ChaosConfig) -> Self {
+        Self {
+            socket,
+            inbound: Mutex::new(Inbound {
+                faults: Faults::new(config),
+                ready: VecDeque::new(),
+                delayed: Vec::new(),
+                held: None,
+            }),
+        }
+    }
+
+    /// The wrapped socket
+    pub fn get_ref(&self) -> &S {
+        &self.socket
+    }
+
+    /// Returns the wrapped socket
+    pub fn into_inner(self) -> S {
+        self.socket
+    }
+}
+
+#[async_trait]
+impl<S: DnsUdpSocket> DnsUdpSocket for ChaosUdpSocket<S> {
+    type Time = S::Time;
+
+    fn poll_recv_from(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<(usize, SocketAddr)>> {
+        let mut inbound = self.inbound.lock().expect("chaos socket poisoned");
+        loop {
+            inbound.poll_delayed(cx);
+            if let Some((packet, from)) = inbound.ready.pop_front() {
+                let len = packet.len().min(buf.len());
+                buf[..len].copy_from_slice(&packet[..len]);
+                return Poll::Ready(Ok((len, from)));
+            }
+
+            let mut packet = vec![0; MAX_RECEIVE_BUFFER_SIZE.max(buf.len())];
+            let (len, from) = ready!(self.socket.poll_recv_from(cx, &mut packet))?;
+            packet.truncate(len);
+            inbound.receive::<S::Time>(packet, from);
+        }
+    }
+
+    fn poll_send_to(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        target: SocketAddr,
+    ) -> Poll<io::Result<usize>> {
+        self.socket.poll_send_to(cx, buf, target)
+    }
+
+    fn bind_device(&self, interface: &str) -> io::Result<()> {
+        self.socket.bind_device(interface)
+    }
+}
+
+/// A stream which injects faults into its reads
+///
+/// The bytes of a stream are neither lost, duplicated nor reordered, a lost read resets the
+///  stream instead, and a truncated read ends it. The latency is added before each read.
+pub struct ChaosStream<S> {
+    stream: S,
+    faults: Faults,
+    // the delays are not `Sync`, only the polls of the stream access them
+    delay: Mutex<Option<Delay>>,
+    delayed: bool,
+    ended: bool,
+}
+
+impl<S: DnsTcpStream> ChaosStream<S> {
+    /// Wraps the stream, injecting the faults of the configuration
+    pub fn new(stream: S, config: ChaosConfig) -> Self {
+        Self {
+            stream,
+            faults: Faults::new(config),
+            delay: Mutex::new(None),
+            delayed: false,
+            ended: false,
+        }
+    }
+
+    /// The wrapped stream
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Returns the wrapped stream
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S: DnsTcpStream> DnsTcpStream for ChaosStream<S> {
+    type Time = S::Time;
+}
+
+impl<S: DnsTcpStream> AsyncRead for ChaosStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.ended {
+            return Poll::Ready(Ok(0));
+        }
+
+        if !this.delayed {
+            let delay = this.delay.get_mut().expect("chaos stream poisoned");
+            if delay.is_none() {
+                let latency = this.faults.latency();
+                if !latency.is_zero() {
+                    *delay = Some(S::Time::delay_for(latency));
+                }
+            }
+            if let Some(sleep) = delay {
+                ready!(sleep.as_mut().poll(cx));
+                *delay = None;
+            }
+            this.delayed = true;
+        }
+
+        let len = ready!(Pin::new(&mut this.stream).poll_read(cx, buf))?;
+        this.delayed = false;
+        if len == 0 {
+            return Poll::Ready(Ok(0));
+        }
+
+        if this.faults.happens(this.faults.config.loss) {
+            this.ended = true;
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::ConnectionReset,
+                "chaos: stream reset",
+            )));
+        }
+
+        let len = if this.faults.happens(this.faults.config.truncation) {
+            this.ended = true;
+            this.faults.rng.gen_range(0..len)
+        } else {
+            len
+        };
+        this.faults.corrupt(&mut buf[..len]);
+
+        Poll::Ready(Ok(len))
+    }
+}
+
+impl<S: DnsTcpStream> AsyncWrite for ChaosStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_close(cx)
+    }
+}
+
+#[cfg(all(test, feature = "tokio-runtime"))]
+mod tests {
+    use std::net::Ipv4Addr;
+    use std::time::Instant;
+
+    use futures_util::future::poll_fn;
+    use tokio::net::UdpSocket;
+
+    use super::*;
+    use crate::iocompat::AsyncIoTokioAsStd;
+
+    /// Sends the packets `0..count` to a wrapped socket, and returns those received in time
+    async fn receive(config: ChaosConfig, count: u8) -> Vec<Vec<u8>> {
+        let sender = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let receiver = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let addr = receiver.local_addr().unwrap();
+        let receiver = ChaosUdpSocket::new(receiver, config);
+
+        for packet in 0..count {
+            sender.send_to(&[packet, 0, 0, 0], addr).await.unwrap();
+        }
+
+        let mut received = Vec::new();
+        let mut buf = [0_u8; 512];
+        while let Ok(Ok((len, _))) =
+            tokio::time::timeout(Duration::from_millis(200), receiver.recv_from(&mut buf)).await
+        {
+            received.push(buf[..len].to_vec());
+        }
+        received
+    }
+
+    #[tokio::test]
+    async fn test_no_faults() {
+        let received = receive(ChaosConfig::default(), 16).await;
+        assert_eq!(
+            received,
+            (0..16)
+                .map(|packet| vec![packet, 0, 0, 0])
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deterministic_loss() {
+        let config = ChaosConfig {
+            loss: 0.5,
+            seed: 7,
+            ..ChaosConfig::default()
+        };
+
+        let first = receive(config, 32).await;
+        assert!(!first.is_empty());
+        assert!(first.len() < 32);
+        assert_eq!(receive(config, 32).await, first);
+    }
+
+    #[tokio::test]
+    async fn test_duplication_and_reordering() {
+        let duplicated = receive(
+            ChaosConfig {
+                duplication: 1.0,
+                ..ChaosConfig::default()
+            },
+            4,
+        )
+        .await;
+        assert_eq!(duplicated.len(), 8);
+        assert_eq!(duplicated[0], duplicated[1]);
+
+        let reordered = receive(
+            ChaosConfig {
+                reordering: 1.0,
+                ..ChaosConfig::default()
+            },
+            4,
+        )
+        .await;
+        // each packet is held back until the next one, the last one is never delivered
+        assert_eq!(
+            reordered.iter().map(|packet| packet[0]).collect::<Vec<_>>(),
+            vec![1, 0, 3, 2]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_truncation_and_corruption() {
+        let truncated = receive(
+            ChaosConfig {
+                truncation: 1.0,
+                ..ChaosConfig::default()
+            },
+            8,
+        )
+        .await;
+        assert!(truncated.iter().all(|packet| packet.len() < 4));
+
+        let corrupted = receive(
+            ChaosConfig {
+                corruption: 1.0,
+                ..ChaosConfig::default()
+            },
+            8,
+        )
+        .await;
+        assert_eq!(corrupted.len(), 8);
+        for (packet, corrupted) in corrupted.iter().enumerate() {
+            let differences = [packet as u8, 0, 0, 0]
+                .iter()
+                .zip(corrupted)
+                .filter(|(sent, received)| sent != received)
+                .count();
+            assert_eq!(differences, 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_latency() {
+        let started = Instant::now();
+        let received = receive(
+            ChaosConfig {
+                latency: Latency::Fixed(Duration::from_millis(100)),
+                ..ChaosConfig::default()
+            },
+            2,
+        )
+        .await;
+        assert_eq!(received.len(), 2);
+        assert!(started.elapsed() >= Duration::from_millis(100));
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let uniform = Latency::Uniform {
+            min: Duration::from_millis(10),
+            max: Duration::from_millis(20),
+        };
+        let normal = Latency::Normal {
+            mean: Duration::from_millis(10),
+            std_dev: Duration::from_millis(50),
+        };
+        for _ in 0..100 {
+            let latency = uniform.sample(&mut rng);
+            assert!(latency >= Duration::from_millis(10) && latency <= Duration::from_millis(20));
+            normal.sample(&mut rng);
+        }
+    }
+
+    /// Reads from a wrapped stream of the bytes
+    async fn read_all(bytes: &'static [u8], config: ChaosConfig) -> io::Result<Vec<u8>> {
+        let (client, mut server) = tokio::io::duplex(64);
+        tokio::io::AsyncWriteExt::write_all(&mut server, bytes)
+            .await
+            .unwrap();
+        drop(server);
+
+        let mut stream = ChaosStream::new(AsyncIoTokioAsStd(client), config);
+        let mut read = Vec::new();
+        let mut buf = [0_u8; 64];
+        loop {
+            let len = poll_fn(|cx| Pin::new(&mut stream).poll_read(cx, &mut buf)).await?;
+            if len == 0 {
+                return Ok(read);
+            }
+            read.extend_from_slice(&buf[..len]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream() {
+        let read = read_all(
+            b"hickory",
+            ChaosConfig {
+                corruption: 1.0,
+                latency: Latency::Fixed(Duration::from_millis(10)),
+                ..ChaosConfig::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(read.len(), 7);
+        assert_ne!(read, b"hickory");
+
+        let error = read_all(
+            b"hickory",
+            ChaosConfig {
+                loss: 1.0,
+                ..ChaosConfig::default()
+            },
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::ConnectionReset);
+
+        let read = read_all(
+            b"hickory",
+            ChaosConfig {
+                truncation: 1.0,
+                ..ChaosConfig::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert!(read.len() < 7);
+    }
+}
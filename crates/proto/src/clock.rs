@@ -0,0 +1,128 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! The clocks used for deadlines and caching, which work on all targets
+//!
+//! On `wasm32-unknown-unknown` the clocks of the standard library panic, the clocks of the
+//!  JavaScript host are used there instead. All other targets, including `wasm32-wasi`, use the
+//!  standard library.
+//!
+//! Components reading the time take a [`Clock`], by default the [`SystemClock`]. Targets without
+//!  a real time clock supply their own, and tests use a [`ManualClock`] to expire cached records
+//!  or signatures without waiting.
+
+use std::fmt::Debug;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::error::ProtoResult;
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub use web_time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// A source of the current time
+pub trait Clock: Debug + Send + Sync + 'static {
+    /// The monotonic time, for deadlines and the expiry of cached records
+    fn instant(&self) -> Instant;
+
+    /// The wall clock time, for signing messages and the validity of signatures
+    fn system_time(&self) -> SystemTime;
+
+    /// The wall clock time in seconds since the Unix epoch, as in RRSIG and SIG records
+    ///
+    /// The seconds are truncated to 32 bits, these fields use serial number arithmetic.
+    fn unix_time(&self) -> ProtoResult<u32> {
+        match self.system_time().duration_since(UNIX_EPOCH) {
+            Ok(now) => Ok(now.as_secs() as u32),
+            Err(_) => Err("Current time is before the Unix epoch.".into()),
+        }
+    }
+}
+
+/// The clocks of the standard library, or of the JavaScript host on `wasm32-unknown-unknown`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock which only advances when told to, for deterministic tests
+#[derive(Debug)]
+pub struct ManualClock {
+    instant: Instant,
+    system_time: SystemTime,
+    elapsed: Mutex<Duration>,
+}
+
+impl ManualClock {
+    /// Creates a clock showing the given wall clock time
+    ///
+    /// The monotonic time starts at the current instant, as instants can't be constructed.
+    pub fn new(system_time: SystemTime) -> Self {
+        Self {
+            instant: Instant::now(),
+            system_time,
+            elapsed: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    /// Creates a clock showing the given seconds since the Unix epoch
+    pub fn from_unix_time(secs: u64) -> Self {
+        Self::new(UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    /// Advances both the monotonic and the wall clock time
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().expect("clock lock poisoned") += duration;
+    }
+
+    fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().expect("clock lock poisoned")
+    }
+}
+
+impl Clock for ManualClock {
+    fn instant(&self) -> Instant {
+        self.instant + self.elapsed()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        self.system_time + self.elapsed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manual_clock() {
+        let clock = ManualClock::from_unix_time(1_700_000_000);
+        let start = clock.instant();
+        assert_eq!(clock.unix_time().unwrap(), 1_700_000_000);
+
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(clock.instant() - start, Duration::from_secs(90));
+        assert_eq!(clock.unix_time().unwrap(), 1_700_000_090);
+    }
+
+    #[test]
+    fn test_unix_time_truncates() {
+        let clock = ManualClock::from_unix_time(u64::from(u32::MAX) + 5);
+        assert_eq!(clock.unix_time().unwrap(), 4);
+    }
+}
@@ -0,0 +1,461 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Allocation free queries and responses in fixed buffers, for responders on embedded devices
+//!
+//! [`Query`] borrows the received datagram, [`Response`] writes into a buffer of the caller, the
+//!  size of the buffer bounds the size of the response. Owner and target names are
+//!  [`StaticName`]s, so a table of records can be kept in flash. This is enough for a DNS or mDNS
+//!  responder answering A, AAAA, PTR, SRV and TXT queries on any datagram socket, see the
+//!  `embedded_responder` example. Only `core` is used, the sockets of smoltcp are adapted in
+//!  `smoltcp_socket` with the `smoltcp` feature.
+
+use core::fmt;
+use core::net::{Ipv4Addr, Ipv6Addr};
+
+use crate::op::{OpCode, ResponseCode};
+use crate::rr::{DNSClass, RecordType, StaticName};
+
+/// The size of the header of a message
+const HEADER_LEN: usize = 12;
+/// Compression pointer to the name of the question, which directly follows the header
+const QUESTION_NAME_POINTER: [u8; 2] = [0xC0, HEADER_LEN as u8];
+/// The top bit of the class of a question, requesting a unicast response in mDNS, RFC 6762
+const UNICAST_RESPONSE: u16 = 0x8000;
+
+/// Errors of parsing and writing messages in fixed buffers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WireError {
+    /// The message ends before the data which is read
+    Truncated,
+    /// The buffer of the response is too small for the data which is written
+    BufferTooSmall,
+    /// The message is not a standard query with a single question
+    NotAQuery,
+    /// The name of the question is not valid
+    InvalidName,
+}
+
+impl fmt::Display for WireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated => f.write_str("message is truncated"),
+            Self::BufferTooSmall => f.write_str("buffer is too small for the response"),
+            Self::NotAQuery => {
+                f.write_str("message is not a standard query with a single question")
+            }
+            Self::InvalidName => f.write_str("name of the question is not valid"),
+        }
+    }
+}
+
+impl core::error::Error for WireError {}
+
+/// A standard query with a single question, borrowed from the received message
+#[derive(Clone, Copy, Debug)]
+pub struct Query<'a> {
+    id: u16,
+    recursion_desired: bool,
+    /// The question, i.e. the name in wire format followed by the type and class
+    question: &'a [u8],
+    query_type: RecordType,
+    query_class: DNSClass,
+    unicast_response: bool,
+}
+
+impl<'a> Query<'a> {
+    /// Parses the header and the question of the message
+    ///
+    /// Only the first question is read, any records which follow it are ignored. The name of the
+    ///  question must not be compressed, which it never is in the first question.
+    pub fn parse(message: &'a [u8]) -> Result<Self, WireError> {
+        if message.len() < HEADER_LEN {
+            return Err(WireError::Truncated);
+        }
+
+        let flags = message[2];
+        let is_response = flags & 0x80 != 0;
+        let op_code = (flags >> 3) & 0x0F;
+        let query_count = u16::from_be_bytes([message[4], message[5]]);
+        if is_response || op_code != u8::from(OpCode::Query) || query_count != 1 {
+            return Err(WireError::NotAQuery);
+        }
+
+        let mut pos = HEADER_LEN;
+        loop {
+            let len = *message.get(pos).ok_or(WireError::Truncated)? as usize;
+            pos += 1;
+            if len == 0 {
+                break;
+            }
+            // compression pointers and the extended label types are not valid here
+            if len > 63 {
+                return Err(WireError::InvalidName);
+            }
+            pos += len;
+        }
+        if pos - HEADER_LEN > 255 {
+            return Err(WireError::InvalidName);
+        }
+
+        let fields = message.get(pos..pos + 4).ok_or(WireError::Truncated)?;
+        let query_type = u16::from_be_bytes([fields[0], fields[1]]);
+        let query_class = u16::from_be_bytes([fields[2], fields[3]]);
+
+        Ok(Self {
+            id: u16::from_be_bytes([message[0], message[1]]),
+            recursion_desired: flags & 0x01 != 0,
+            question: &message[HEADER_LEN..pos + 4],
+            query_type: RecordType::from(query_type),
+            query_class: DNSClass::from(query_class & !UNICAST_RESPONSE),
+            unicast_response: query_class & UNICAST_RESPONSE != 0,
+        })
+    }
+
+    /// The id of the message
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    /// Whether recursion is desired
+    pub fn recursion_desired(&self) -> bool {
+        self.recursion_desired
+    }
+
+    /// The name of the question in wire format
+    pub fn name(&self) -> &'a [u8] {
+        &self.question[..self.question.len() - 4]
+    }
+
+    /// Case insensitive comparison of the name of the question
+    pub fn name_eq(&self, name: &StaticName) -> bool {
+        self.name().eq_ignore_ascii_case(name.as_wire())
+    }
+
+    /// The type of the question
+    pub fn query_type(&self) -> RecordType {
+        self.query_type
+    }
+
+    /// The class of the question, without the unicast response bit of mDNS
+    pub fn query_class(&self) -> DNSClass {
+        self.query_class
+    }
+
+    /// Whether a unicast response is requested, only used in mDNS
+    pub fn unicast_response(&self) -> bool {
+        self.unicast_response
+    }
+}
+
+/// A response to a [`Query`], written into a fixed buffer
+///
+/// The question is copied from the query, and answers are appended. Answers for the name of the
+///  question are written with a compression pointer to it.
+#[derive(Debug)]
+pub struct Response<'b> {
+    buffer: &'b mut [u8],
+    len: usize,
+    answer_count: u16,
+}
+
+impl<'b> Response<'b> {
+    /// Starts an authoritative response to the query, with the `NoError` response code
+    pub fn new(buffer: &'b mut [u8], query: &Query<'_>) -> Result<Self, WireError> {
+        let mut this = Self {
+            buffer,
+            len: 0,
+            answer_count: 0,
+        };
+
+        let flags = 0x80 | 0x04 | u8::from(query.recursion_desired);
+        let [id_high, id_low] = query.id.to_be_bytes();
+        this.write(&[id_high, id_low, flags, 0, 0, 1, 0, 0, 0, 0, 0, 0])?;
+        this.write(query.question)?;
+        Ok(this)
+    }
+
+    /// Sets the response code, only the codes of the header are supported
+    pub fn set_response_code(&mut self, response_code: ResponseCode) {
+        self.buffer[3] = (self.buffer[3] & 0xF0) | (response_code.low() & 0x0F);
+    }
+
+    /// Appends an A record for the name of the question
+    pub fn answer_a(&mut self, ttl: u32, ip: Ipv4Addr) -> Result<(), WireError> {
+        self.answer(None, RecordType::A, ttl, &[&ip.octets()])
+    }
+
+    /// Appends an AAAA record for the name of the question
+    pub fn answer_aaaa(&mut self, ttl: u32, ip: Ipv6Addr) -> Result<(), WireError> {
+        self.answer(None, RecordType::AAAA, ttl, &[&ip.octets()])
+    }
+
+    /// Appends a PTR record for the name of the question
+    pub fn answer_ptr(&mut self, ttl: u32, target: StaticName) -> Result<(), WireError> {
+        self.answer(None, RecordType::PTR, ttl, &[target.as_wire()])
+    }
+
+    /// Appends an SRV record for the name of the question
+    pub fn answer_srv(
+        &mut self,
+        ttl: u32,
+        priority: u16,
+        weight: u16,
+        port: u16,
+        target: StaticName,
+    ) -> Result<(), WireError> {
+        let [priority_high, priority_low] = priority.to_be_bytes();
+        let [weight_high, weight_low] = weight.to_be_bytes();
+        let [port_high, port_low] = port.to_be_bytes();
+        let fields = [
+            priority_high,
+            priority_low,
+            weight_high,
+            weight_low,
+            port_high,
+            port_low,
+        ];
+        self.answer(None, RecordType::SRV, ttl, &[&fields, target.as_wire()])
+    }
+
+    /// Appends a TXT record with a single string for the name of the question
+    pub fn answer_txt(&mut self, ttl: u32, txt: &[u8]) -> Result<(), WireError> {
+        let len = u8::try_from(txt.len()).map_err(|_| WireError::BufferTooSmall)?;
+        self.answer(None, RecordType::TXT, ttl, &[&[len], txt])
+    }
+
+    /// Appends an A record for another name, e.g. the target of an SRV record
+    pub fn answer_a_for(
+        &mut self,
+        name: StaticName,
+        ttl: u32,
+        ip: Ipv4Addr,
+    ) -> Result<(), WireError> {
+        self.answer(Some(name), RecordType::A, ttl, &[&ip.octets()])
+    }
+
+    /// Appends a record with the record data in parts, the name of the question is the default owner
+    fn answer(
+        &mut self,
+        name: Option<StaticName>,
+        record_type: RecordType,
+        ttl: u32,
+        rdata: &[&[u8]],
+    ) -> Result<(), WireError> {
+        if self.answer_count == u16::MAX {
+            return Err(WireError::BufferTooSmall);
+        }
+
+        // nothing is kept of an answer which does not fit
+        let start = self.len;
+        let result = self.write_answer(name, record_type, ttl, rdata);
+        if result.is_err() {
+            self.len = start;
+            return result;
+        }
+
+        self.answer_count += 1;
+        self.buffer[6..8].copy_from_slice(&self.answer_count.to_be_bytes());
+        Ok(())
+    }
+
+    fn write_answer(
+        &mut self,
+        name: Option<StaticName>,
+        record_type: RecordType,
+        ttl: u32,
+        rdata: &[&[u8]],
+    ) -> Result<(), WireError> {
+        match name {
+            Some(name) => self.write(name.as_wire())?,
+            None => self.write(&QUESTION_NAME_POINTER)?,
+        }
+
+        let rdata_len: usize = rdata.iter().map(|part| part.len()).sum();
+        let rdata_len = u16::try_from(rdata_len).map_err(|_| WireError::BufferTooSmall)?;
+        self.write(&u16::from(record_type).to_be_bytes())?;
+        self.write(&u16::from(DNSClass::IN).to_be_bytes())?;
+        self.write(&ttl.to_be_bytes())?;
+        self.write(&rdata_len.to_be_bytes())?;
+        for part in rdata {
+            self.write(part)?;
+        }
+
+        Ok(())
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), WireError> {
+        let end = self.len + data.len();
+        self.buffer
+            .get_mut(self.len..end)
+            .ok_or(WireError::BufferTooSmall)?
+            .copy_from_slice(data);
+        self.len = end;
+        Ok(())
+    }
+
+    /// The number of answers
+    pub fn answer_count(&self) -> u16 {
+        self.answer_count
+    }
+
+    /// Finishes the response, and returns the message
+    pub fn finish(self) -> &'b [u8] {
+        &self.buffer[..self.len]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::op::{Message, MessageType, Query as OpQuery};
+    use crate::rr::{Name, RData};
+    use crate::serialize::binary::BinEncodable;
+    use crate::static_name;
+
+    fn query(name: &str, query_type: RecordType) -> Vec<u8> {
+        let mut message = Message::new();
+        message
+            .set_id(42)
+            .set_recursion_desired(true)
+            .add_query(OpQuery::query(Name::from_ascii(name).unwrap(), query_type));
+        message.to_bytes().unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let bytes = query("Device.Local.", RecordType::A);
+        let query = Query::parse(&bytes).unwrap();
+
+        assert_eq!(query.id(), 42);
+        assert!(query.recursion_desired());
+        assert_eq!(query.query_type(), RecordType::A);
+        assert_eq!(query.query_class(), DNSClass::IN);
+        assert!(!query.unicast_response());
+        assert!(query.name_eq(&static_name!("device.local.")));
+        assert!(!query.name_eq(&static_name!("local.")));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let bytes = query("device.local.", RecordType::A);
+        assert_eq!(
+            Query::parse(&bytes[..bytes.len() - 1]).unwrap_err(),
+            WireError::Truncated
+        );
+
+        let mut response = bytes.clone();
+        response[2] |= 0x80;
+        assert_eq!(Query::parse(&response).unwrap_err(), WireError::NotAQuery);
+
+        let mut notify = bytes.clone();
+        notify[2] |= u8::from(OpCode::Notify) << 3;
+        assert_eq!(Query::parse(&notify).unwrap_err(), WireError::NotAQuery);
+
+        let mut compressed = bytes;
+        compressed[HEADER_LEN] = 0xC0;
+        assert_eq!(
+            Query::parse(&compressed).unwrap_err(),
+            WireError::InvalidName
+        );
+    }
+
+    #[test]
+    fn test_unicast_response() {
+        let mut bytes = query("device.local.", RecordType::A);
+        let class = bytes.len() - 2;
+        bytes[class] |= 0x80;
+
+        let query = Query::parse(&bytes).unwrap();
+        assert!(query.unicast_response());
+        assert_eq!(query.query_class(), DNSClass::IN);
+    }
+
+    #[test]
+    fn test_response() {
+        let bytes = query("_http._tcp.device.local.", RecordType::SRV);
+        let query = Query::parse(&bytes).unwrap();
+
+        let mut buffer = [0; 512];
+        let mut response = Response::new(&mut buffer, &query).unwrap();
+        response
+            .answer_srv(120, 0, 0, 80, static_name!("device.local."))
+            .unwrap();
+        response
+            .answer_a_for(
+                static_name!("device.local."),
+                120,
+                Ipv4Addr::new(10, 0, 0, 1),
+            )
+            .unwrap();
+        response.answer_txt(120, b"path=/").unwrap();
+        assert_eq!(response.answer_count(), 3);
+
+        let message = Message::from_vec(response.finish()).unwrap();
+        assert_eq!(message.id(), 42);
+        assert_eq!(message.message_type(), MessageType::Response);
+        assert!(message.authoritative());
+        assert!(message.recursion_desired());
+        assert_eq!(message.queries(), query_of(&bytes));
+
+        let answers = message.answers();
+        assert_eq!(answers.len(), 3);
+        assert_eq!(
+            answers[0].name(),
+            &Name::from_ascii("_http._tcp.device.local.").unwrap()
+        );
+        match answers[0].data() {
+            Some(RData::SRV(srv)) => {
+                assert_eq!(srv.port(), 80);
+                assert_eq!(srv.target(), &Name::from_ascii("device.local.").unwrap());
+            }
+            data => panic!("unexpected record data: {data:?}"),
+        }
+        assert_eq!(
+            answers[1].data(),
+            Some(&RData::A(Ipv4Addr::new(10, 0, 0, 1).into()))
+        );
+        assert_eq!(answers[1].ttl(), 120);
+        assert_eq!(answers[2].data().unwrap().record_type(), RecordType::TXT);
+    }
+
+    fn query_of(bytes: &[u8]) -> Vec<OpQuery> {
+        Message::from_vec(bytes).unwrap().queries().to_vec()
+    }
+
+    #[test]
+    fn test_response_code() {
+        let bytes = query("unknown.local.", RecordType::A);
+        let query = Query::parse(&bytes).unwrap();
+
+        let mut buffer = [0; 512];
+        let mut response = Response::new(&mut buffer, &query).unwrap();
+        response.set_response_code(ResponseCode::NXDomain);
+
+        let message = Message::from_vec(response.finish()).unwrap();
+        assert_eq!(message.response_code(), ResponseCode::NXDomain);
+        assert!(message.answers().is_empty());
+    }
+
+    #[test]
+    fn test_buffer_too_small() {
+        let bytes = query("device.local.", RecordType::A);
+        let query = Query::parse(&bytes).unwrap();
+
+        let mut buffer = [0; 50];
+        let mut response = Response::new(&mut buffer, &query).unwrap();
+        assert_eq!(
+            response.answer_aaaa(120, Ipv6Addr::LOCALHOST).unwrap_err(),
+            WireError::BufferTooSmall
+        );
+        response.answer_a(120, Ipv4Addr::LOCALHOST).unwrap();
+
+        let message = Message::from_vec(response.finish()).unwrap();
+        assert_eq!(message.answers().len(), 1);
+    }
+}
@@ -0,0 +1,228 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Self-test of the randomness of query ids and source ports
+//!
+//! An off-path attacker spoofing responses has to guess the query id and the source port of a
+//!  query, see [RFC 5452](https://tools.ietf.org/html/rfc5452). `entropy_report` checks the
+//!  sources of both as they are used at runtime, so that integrators can verify that queries
+//!  are not predictable on their target.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::net::SocketAddr;
+
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+/// The number of query ids drawn to check that they do not repeat
+const QUERY_ID_SAMPLES: usize = 64;
+
+/// The minimum number of distinct query ids of the samples
+///
+/// 64 uniformly random ids of 16 bits have less than a 4% chance of a single collision, more
+///  than 4 repeated ids only happen for a generator which is broken.
+const QUERY_ID_MIN_DISTINCT: usize = QUERY_ID_SAMPLES - 4;
+
+/// The source of randomness of the query ids
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RngSource {
+    /// The thread local generator, seeded from the operating system
+    OsSeeded,
+    /// The operating system did not provide randomness, the generator can not be seeded
+    Unseeded,
+}
+
+/// How the source ports of queries over UDP are chosen
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PortRandomization {
+    /// A random port of 1024-65535 for each socket
+    Random,
+    /// The port of the bind address is used for all queries
+    Fixed(u16),
+}
+
+/// A problem found by `entropy_report`
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EntropyIssue {
+    /// The operating system did not provide randomness
+    OsRngUnavailable(String),
+    /// The sampled query ids repeated more often than for random ids
+    RepeatedQueryIds {
+        /// The number of distinct ids
+        distinct: usize,
+        /// The number of sampled ids
+        sampled: usize,
+    },
+    /// The bind address fixes the source port
+    FixedSourcePort(u16),
+}
+
+impl fmt::Display for EntropyIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OsRngUnavailable(err) => {
+                write!(f, "the operating system random source failed: {err}")
+            }
+            Self::RepeatedQueryIds { distinct, sampled } => write!(
+                f,
+                "only {distinct} of {sampled} sampled query ids are distinct"
+            ),
+            Self::FixedSourcePort(port) => write!(
+                f,
+                "the source port is fixed to {port}, only the query id is random"
+            ),
+        }
+    }
+}
+
+/// The risk of spoofed responses being accepted, ordered from lowest to highest
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Risk {
+    /// Query ids and source ports are random
+    Low,
+    /// Query ids are random, but the source port is not
+    Medium,
+    /// Query ids can be predicted
+    High,
+}
+
+/// The result of `entropy_report`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EntropyReport {
+    rng: RngSource,
+    ports: PortRandomization,
+    issues: Vec<EntropyIssue>,
+}
+
+impl EntropyReport {
+    /// The source of randomness of the query ids
+    pub fn rng(&self) -> RngSource {
+        self.rng
+    }
+
+    /// How the source ports are chosen
+    pub fn ports(&self) -> PortRandomization {
+        self.ports
+    }
+
+    /// The problems which were found, empty if there are none
+    pub fn issues(&self) -> &[EntropyIssue] {
+        &self.issues
+    }
+
+    /// The overall risk, the highest of all issues
+    pub fn risk(&self) -> Risk {
+        self.issues
+            .iter()
+            .map(|issue| match issue {
+                EntropyIssue::OsRngUnavailable(_) | EntropyIssue::RepeatedQueryIds { .. } => {
+                    Risk::High
+                }
+                EntropyIssue::FixedSourcePort(_) => Risk::Medium,
+            })
+            .max()
+            .unwrap_or(Risk::Low)
+    }
+}
+
+impl fmt::Display for EntropyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "risk {:?}", self.risk())?;
+        for issue in &self.issues {
+            write!(f, "; {issue}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks the randomness of the query ids and source ports
+///
+/// The operating system source is tested, then query ids are drawn from the same generator as for
+///  queries and checked to not repeat. This is a sanity check for broken or unseeded generators,
+///  not a statistical test of the generator.
+///
+/// # Arguments
+///
+/// * `bind_addr` - the bind address of the name servers, as passed to `UdpClientStream`, `None`
+///   or port 0 randomize the port
+pub fn entropy_report(bind_addr: Option<SocketAddr>) -> EntropyReport {
+    let mut issues = Vec::new();
+
+    // the thread local generator panics on first use if it can't be seeded, so check the source
+    //  before drawing any ids from it
+    let rng = match OsRng.try_fill_bytes(&mut [0_u8; 16]) {
+        Ok(()) => {
+            let ids = (0..QUERY_ID_SAMPLES)
+                .map(|_| rand::random::<u16>())
+                .collect::<HashSet<_>>();
+            if ids.len() < QUERY_ID_MIN_DISTINCT {
+                issues.push(EntropyIssue::RepeatedQueryIds {
+                    distinct: ids.len(),
+                    sampled: QUERY_ID_SAMPLES,
+                });
+            }
+            RngSource::OsSeeded
+        }
+        Err(err) => {
+            issues.push(EntropyIssue::OsRngUnavailable(err.to_string()));
+            RngSource::Unseeded
+        }
+    };
+
+    let ports = match bind_addr.map(|addr| addr.port()) {
+        None | Some(0) => PortRandomization::Random,
+        Some(port) => {
+            issues.push(EntropyIssue::FixedSourcePort(port));
+            PortRandomization::Fixed(port)
+        }
+    };
+
+    EntropyReport { rng, ports, issues }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_ports() {
+        let report = entropy_report(None);
+        assert_eq!(report.rng(), RngSource::OsSeeded);
+        assert_eq!(report.ports(), PortRandomization::Random);
+        assert!(report.issues().is_empty());
+        assert_eq!(report.risk(), Risk::Low);
+
+        let report = entropy_report(Some("0.0.0.0:0".parse().unwrap()));
+        assert_eq!(report.ports(), PortRandomization::Random);
+        assert_eq!(report.risk(), Risk::Low);
+    }
+
+    #[test]
+    fn test_fixed_port() {
+        let report = entropy_report(Some("0.0.0.0:5300".parse().unwrap()));
+        assert_eq!(report.ports(), PortRandomization::Fixed(5300));
+        assert_eq!(report.issues(), &[EntropyIssue::FixedSourcePort(5300)]);
+        assert_eq!(report.risk(), Risk::Medium);
+    }
+
+    #[test]
+    fn test_risk_is_highest_issue() {
+        let report = EntropyReport {
+            rng: RngSource::Unseeded,
+            ports: PortRandomization::Fixed(53),
+            issues: vec![
+                EntropyIssue::FixedSourcePort(53),
+                EntropyIssue::OsRngUnavailable("unsupported".to_string()),
+            ],
+        };
+        assert_eq!(report.risk(), Risk::High);
+    }
+}
@@ -10,8 +10,10 @@
 #![deny(missing_docs)]
 
 use std::cmp::Ordering;
+use std::net::SocketAddr;
 use std::{fmt, io, sync};
 
+use crate::logging::debug;
 #[cfg(feature = "backtrace")]
 #[cfg_attr(docsrs, doc(cfg(feature = "backtrace")))]
 pub use backtrace::Backtrace as ExtBacktrace;
@@ -19,7 +21,6 @@ use enum_as_inner::EnumAsInner;
 #[cfg(feature = "backtrace")]
 use once_cell::sync::Lazy;
 use thiserror::Error;
-use tracing::debug;
 
 use crate::op::{Header, Query, ResponseCode};
 
@@ -88,6 +89,12 @@ pub enum ProtoErrorKind {
         len: usize,
     },
 
+    /// A different response for the same query was received over UDP, one of them may be spoofed
+    ///
+    /// The query should be confirmed over a connection which can not be spoofed as easily, e.g. TCP.
+    #[error("conflicting responses received from {0}")]
+    ConflictingResponses(SocketAddr),
+
     /// Overlapping labels
     #[error("overlapping labels name {label} other {other}")]
     LabelOverlapsWithOther {
@@ -374,16 +381,18 @@ impl ProtoError {
         matches!(*self.kind, ProtoErrorKind::Busy)
     }
 
+    /// Returns true if this is a ProtoErrorKind::ConflictingResponses
+    #[inline]
+    pub fn is_conflicting_responses(&self) -> bool {
+        matches!(*self.kind, ProtoErrorKind::ConflictingResponses(_))
+    }
+
     /// Returns true if this error represents NoConnections
     #[inline]
     pub fn is_no_connections(&self) -> bool {
         matches!(*self.kind, ProtoErrorKind::NoConnections)
     }
 
-    pub(crate) fn as_dyn(&self) -> &(dyn std::error::Error + 'static) {
-        self
-    }
-
     /// A conversion to determine if the response is an error
     pub fn from_response(response: DnsResponse, trust_nx: bool) -> Result<DnsResponse, Self> {
         use ResponseCode::*;
@@ -596,6 +605,7 @@ impl Clone for ProtoErrorKind {
             Busy => Busy,
             Canceled(ref c) => Canceled(*c),
             CharacterDataTooLong { max, len } => CharacterDataTooLong { max, len },
+            ConflictingResponses(addr) => ConflictingResponses(addr),
             LabelOverlapsWithOther { label, other } => LabelOverlapsWithOther { label, other },
             DnsKeyProtocolNot3(protocol) => DnsKeyProtocolNot3(protocol),
             DomainNameTooLong(len) => DomainNameTooLong(len),
@@ -0,0 +1,778 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Structured mutations of messages, and `Arbitrary` impls of the core types, for fuzzing
+//!
+//! A fuzzer of raw bytes rarely gets past the header and the first name of a message. The
+//!  [`Arbitrary`] impls generate valid messages from the input of the fuzzer instead, and the
+//!  [`MessageMutator`] breaks an encoded message at one precise place, e.g. a label length or a
+//!  compression pointer, so that the decoders of the consumers see all of the message up to it.
+//!
+//! ```
+//! use hickory_proto::fuzzing::{HeaderFlag, MessageMutator, Mutation};
+//! use hickory_proto::op::{Message, Query};
+//! use hickory_proto::rr::{Name, RecordType};
+//!
+//! let mut message = Message::new();
+//! message.add_query(Query::query(
+//!     Name::from_ascii("www.example.com.").unwrap(),
+//!     RecordType::A,
+//! ));
+//!
+//! let mutator = MessageMutator::new(&message).unwrap();
+//! let looped = mutator
+//!     .mutate(Mutation::CompressionLoop { name: 0 })
+//!     .unwrap();
+//! assert!(Message::from_vec(&looped).is_err());
+//!
+//! let flipped = mutator
+//!     .mutate(Mutation::FlipFlag(HeaderFlag::RecursionDesired))
+//!     .unwrap();
+//! assert!(Message::from_vec(&flipped).unwrap().recursion_desired());
+//! ```
+
+use std::ops::Range;
+
+use arbitrary::{Arbitrary, Unstructured};
+
+use crate::error::{ProtoError, ProtoResult};
+use crate::op::{Edns, Header, Message, MessageType, OpCode, Query, ResponseCode};
+use crate::rr::domain::Label;
+use crate::rr::rdata::{A, AAAA, ANAME, CNAME, MX, NS, NULL, PTR, SOA, SRV, TXT};
+use crate::rr::{DNSClass, Name, RData, Record, RecordType};
+use crate::serialize::binary::BinEncodable;
+
+/// The most labels of an arbitrary name, which keeps it below 255 bytes
+const MAX_LABELS: u8 = 3;
+
+/// The most queries of an arbitrary message
+const MAX_QUERIES: u8 = 2;
+
+/// The most records of each section of an arbitrary message
+const MAX_RECORDS: u8 = 4;
+
+/// The offset of the pointers to the first 16KiB of a message
+const POINTER: u16 = 0xC000;
+
+/// A flag of the header of a message
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeaderFlag {
+    /// QR, the message is a response
+    Response,
+    /// AA, the answer is authoritative
+    Authoritative,
+    /// TC, the message is truncated
+    Truncated,
+    /// RD, recursion is desired
+    RecursionDesired,
+    /// RA, recursion is available
+    RecursionAvailable,
+    /// AD, the data is authentic
+    AuthenticData,
+    /// CD, checking is disabled
+    CheckingDisabled,
+}
+
+impl HeaderFlag {
+    /// All of the flags
+    pub const ALL: [Self; 7] = [
+        Self::Response,
+        Self::Authoritative,
+        Self::Truncated,
+        Self::RecursionDesired,
+        Self::RecursionAvailable,
+        Self::AuthenticData,
+        Self::CheckingDisabled,
+    ];
+
+    /// The offset of the byte of the flag in the message, and its bit
+    fn bit(self) -> (usize, u8) {
+        match self {
+            Self::Response => (2, 0x80),
+            Self::Authoritative => (2, 0x04),
+            Self::Truncated => (2, 0x02),
+            Self::RecursionDesired => (2, 0x01),
+            Self::RecursionAvailable => (3, 0x80),
+            Self::AuthenticData => (3, 0x20),
+            Self::CheckingDisabled => (3, 0x10),
+        }
+    }
+}
+
+impl<'a> Arbitrary<'a> for HeaderFlag {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        u.choose(&Self::ALL).copied()
+    }
+}
+
+/// A mutation of an encoded message, see [`MessageMutator::mutate`]
+///
+/// The labels, names and records are numbered in the order of the message, the names and the
+///  records of the record data included.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mutation {
+    /// Flips the flag in the header
+    FlipFlag(HeaderFlag),
+    /// Replaces the length of the label, e.g. by a length beyond the end of the message, or by
+    ///  one of the reserved label types above 63
+    LabelLength {
+        /// The number of the label
+        label: usize,
+        /// The new length
+        length: u8,
+    },
+    /// Ends the name with a compression pointer to its start, which loops forever
+    CompressionLoop {
+        /// The number of the name
+        name: usize,
+    },
+    /// Truncates the record data of the record, its length is updated to the truncated length
+    TruncateRData {
+        /// The number of the record
+        record: usize,
+        /// The length of the data to keep
+        len: u16,
+    },
+}
+
+/// The end of a name, its root label or a compression pointer
+#[derive(Clone, Copy, Debug)]
+struct NameLayout {
+    start: usize,
+    tail: usize,
+    pointer: bool,
+}
+
+/// Mutates an encoded message at the labels, names and records found in it
+///
+/// Changes of the length of the message, by `Mutation::CompressionLoop` and
+///  `Mutation::TruncateRData`, keep the rest of it valid: the lengths of the record data around
+///  the change and the compression pointers to the names after it are updated.
+#[derive(Clone, Debug)]
+pub struct MessageMutator {
+    bytes: Vec<u8>,
+    labels: Vec<usize>,
+    names: Vec<NameLayout>,
+    pointers: Vec<usize>,
+    rdatas: Vec<Range<usize>>,
+}
+
+impl MessageMutator {
+    /// Creates the mutator of the encoded message
+    pub fn new(message: &Message) -> ProtoResult<Self> {
+        Self::from_vec(message.to_bytes()?)
+    }
+
+    /// Creates the mutator of the bytes of a valid message
+    pub fn from_vec(bytes: Vec<u8>) -> ProtoResult<Self> {
+        // the message is walked without further checks of its contents
+        Message::from_vec(&bytes)?;
+
+        let mut mutator = Self {
+            bytes,
+            labels: Vec::new(),
+            names: Vec::new(),
+            pointers: Vec::new(),
+            rdatas: Vec::new(),
+        };
+        mutator.walk()?;
+        Ok(mutator)
+    }
+
+    /// The encoded message
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// The number of uncompressed labels in the message
+    pub fn label_count(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// The number of names in the message
+    pub fn name_count(&self) -> usize {
+        self.names.len()
+    }
+
+    /// The number of records in the message, the EDNS and signature records included
+    pub fn record_count(&self) -> usize {
+        self.rdatas.len()
+    }
+
+    /// Returns the message with the mutation, or an error if its label, name or record is not
+    ///  in the message
+    pub fn mutate(&self, mutation: Mutation) -> ProtoResult<Vec<u8>> {
+        let mut bytes = self.bytes.clone();
+
+        match mutation {
+            Mutation::FlipFlag(flag) => {
+                let (offset, bit) = flag.bit();
+                bytes[offset] ^= bit;
+            }
+            Mutation::LabelLength { label, length } => {
+                let offset = self
+                    .labels
+                    .get(label)
+                    .ok_or_else(|| ProtoError::from(format!("no label {label} in the message")))?;
+                bytes[*offset] = length;
+            }
+            Mutation::CompressionLoop { name } => {
+                let name = self
+                    .names
+                    .get(name)
+                    .ok_or_else(|| ProtoError::from(format!("no name {name} in the message")))?;
+                let start = u16::try_from(name.start)
+                    .ok()
+                    .filter(|start| start & POINTER == 0)
+                    .ok_or_else(|| {
+                        ProtoError::from(format!(
+                            "name at {} is out of the reach of pointers",
+                            name.start
+                        ))
+                    })?;
+
+                let tail = if name.pointer { 2 } else { 1 };
+                self.splice(
+                    &mut bytes,
+                    name.tail..name.tail + tail,
+                    &(POINTER | start).to_be_bytes(),
+                );
+            }
+            Mutation::TruncateRData { record, len } => {
+                let rdata = self.rdatas.get(record).ok_or_else(|| {
+                    ProtoError::from(format!("no record {record} in the message"))
+                })?;
+                let len = usize::from(len).min(rdata.len());
+                self.splice(&mut bytes, rdata.start + len..rdata.end, &[]);
+            }
+        }
+
+        Ok(bytes)
+    }
+
+    /// Generates a mutation of a label, name or record of the message
+    pub fn arbitrary_mutation(&self, u: &mut Unstructured<'_>) -> arbitrary::Result<Mutation> {
+        let mutation = match u.int_in_range(0..=3)? {
+            1 if !self.labels.is_empty() => Mutation::LabelLength {
+                label: u.int_in_range(0..=self.labels.len() - 1)?,
+                length: u.arbitrary()?,
+            },
+            2 if !self.names.is_empty() => Mutation::CompressionLoop {
+                name: u.int_in_range(0..=self.names.len() - 1)?,
+            },
+            3 if !self.rdatas.is_empty() => {
+                let record = u.int_in_range(0..=self.rdatas.len() - 1)?;
+                let len = u16::try_from(self.rdatas[record].len()).unwrap_or(u16::MAX);
+                Mutation::TruncateRData {
+                    record,
+                    len: u.int_in_range(0..=len)?,
+                }
+            }
+            _ => Mutation::FlipFlag(u.arbitrary()?),
+        };
+
+        Ok(mutation)
+    }
+
+    /// Replaces the range of the message, and updates the lengths and pointers after it
+    fn splice(&self, bytes: &mut Vec<u8>, range: Range<usize>, replacement: &[u8]) {
+        let resize = |offset: usize| offset + replacement.len() - range.len();
+
+        for rdata in &self.rdatas {
+            if rdata.start <= range.start && range.end <= rdata.end {
+                let len = u16::try_from(resize(rdata.len())).unwrap_or(u16::MAX);
+                bytes[rdata.start - 2..rdata.start].copy_from_slice(&len.to_be_bytes());
+            }
+        }
+
+        for &pointer in self
+            .pointers
+            .iter()
+            .filter(|pointer| **pointer >= range.end)
+        {
+            let target = usize::from(read_u16(bytes, pointer) & !POINTER);
+            if target < range.end {
+                continue;
+            }
+
+            if let Some(target) = u16::try_from(resize(target))
+                .ok()
+                .filter(|target| target & POINTER == 0)
+            {
+                bytes[pointer..pointer + 2].copy_from_slice(&(POINTER | target).to_be_bytes());
+            }
+        }
+
+        bytes.splice(range, replacement.iter().copied());
+    }
+
+    /// Finds the labels, names, pointers and record data of the message
+    fn walk(&mut self) -> ProtoResult<()> {
+        let queries = read_u16(&self.bytes, 4);
+        let records = [6, 8, 10]
+            .iter()
+            .map(|offset| usize::from(read_u16(&self.bytes, *offset)))
+            .sum::<usize>();
+
+        let mut offset = 12;
+        for _ in 0..queries {
+            // the type and class
+            offset = self.walk_name(offset)? + 4;
+        }
+
+        for _ in 0..records {
+            offset = self.walk_name(offset)?;
+            let record_type = RecordType::from(read_u16(&self.bytes, offset));
+            // the type, class and TTL
+            offset += 8;
+            let len = usize::from(read_u16(&self.bytes, offset));
+            offset += 2;
+
+            let rdata = offset..offset + len;
+            match record_type {
+                RecordType::ANAME | RecordType::CNAME | RecordType::NS | RecordType::PTR => {
+                    self.walk_name(rdata.start)?;
+                }
+                RecordType::MX => {
+                    self.walk_name(rdata.start + 2)?;
+                }
+                RecordType::SRV => {
+                    self.walk_name(rdata.start + 6)?;
+                }
+                RecordType::SOA => {
+                    let rname = self.walk_name(rdata.start)?;
+                    self.walk_name(rname)?;
+                }
+                _ => (),
+            }
+
+            offset = rdata.end;
+            self.rdatas.push(rdata);
+        }
+
+        Ok(())
+    }
+
+    /// Finds the labels and the end of the name, and returns the offset after it
+    fn walk_name(&mut self, mut offset: usize) -> ProtoResult<usize> {
+        let start = offset;
+        loop {
+            let len = *self
+                .bytes
+                .get(offset)
+                .ok_or_else(|| ProtoError::from(format!("name at {start} is truncated")))?;
+
+            if len == 0 || len & 0xC0 == 0xC0 {
+                let pointer = len != 0;
+                if pointer {
+                    self.pointers.push(offset);
+                }
+                self.names.push(NameLayout {
+                    start,
+                    tail: offset,
+                    pointer,
+                });
+
+                return Ok(offset + if pointer { 2 } else { 1 });
+            }
+
+            self.labels.push(offset);
+            offset += 1 + usize::from(len);
+        }
+    }
+}
+
+/// Reads the big endian u16 at the offset of a message which was decoded before
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+/// Maps the errors of invalid values to the errors of unfit input of the fuzzer
+fn incorrect_format(_: ProtoError) -> arbitrary::Error {
+    arbitrary::Error::IncorrectFormat
+}
+
+impl<'a> Arbitrary<'a> for Name {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut name = Self::root();
+        for _ in 0..u.int_in_range(0..=MAX_LABELS)? {
+            let len = u.int_in_range(1..=63)?;
+            let label = Label::from_raw_bytes(u.bytes(len)?).map_err(incorrect_format)?;
+            name = name.append_label(label).map_err(incorrect_format)?;
+        }
+
+        Ok(name)
+    }
+}
+
+impl<'a> Arbitrary<'a> for RecordType {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        const COMMON: [RecordType; 8] = [
+            RecordType::A,
+            RecordType::AAAA,
+            RecordType::CNAME,
+            RecordType::MX,
+            RecordType::NS,
+            RecordType::SOA,
+            RecordType::SRV,
+            RecordType::TXT,
+        ];
+
+        // the common types, or any other
+        match u.int_in_range(0..=COMMON.len())? {
+            i if i < COMMON.len() => Ok(COMMON[i]),
+            _ => Ok(Self::from(u.arbitrary::<u16>()?)),
+        }
+    }
+}
+
+impl<'a> Arbitrary<'a> for DNSClass {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        const KNOWN: [DNSClass; 5] = [
+            DNSClass::IN,
+            DNSClass::CH,
+            DNSClass::HS,
+            DNSClass::NONE,
+            DNSClass::ANY,
+        ];
+
+        match u.int_in_range(0..=KNOWN.len())? {
+            i if i < KNOWN.len() => Ok(KNOWN[i]),
+            _ => Ok(Self::from(u.arbitrary::<u16>()?)),
+        }
+    }
+}
+
+impl<'a> Arbitrary<'a> for MessageType {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        u.choose(&[Self::Query, Self::Response]).copied()
+    }
+}
+
+impl<'a> Arbitrary<'a> for OpCode {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        u.choose(&[
+            Self::Query,
+            Self::Status,
+            Self::Notify,
+            Self::Update,
+            Self::Dso,
+        ])
+        .copied()
+    }
+}
+
+impl<'a> Arbitrary<'a> for ResponseCode {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        // the 4 bits of the header, the high bits are in the EDNS record
+        Ok(Self::from_low(u.arbitrary()?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Header {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        // the counts are set when the message is encoded
+        let mut header = Self::new();
+        header
+            .set_id(u.arbitrary()?)
+            .set_message_type(u.arbitrary()?)
+            .set_op_code(u.arbitrary()?)
+            .set_authoritative(u.arbitrary()?)
+            .set_truncated(u.arbitrary()?)
+            .set_recursion_desired(u.arbitrary()?)
+            .set_recursion_available(u.arbitrary()?)
+            .set_authentic_data(u.arbitrary()?)
+            .set_checking_disabled(u.arbitrary()?)
+            .set_response_code(u.arbitrary()?);
+
+        Ok(header)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Query {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut query = Self::query(u.arbitrary()?, u.arbitrary()?);
+        query.set_query_class(u.arbitrary()?);
+
+        Ok(query)
+    }
+}
+
+impl<'a> Arbitrary<'a> for RData {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let rdata = match u.int_in_range(0..=10)? {
+            0 => Self::A(A(u.arbitrary::<[u8; 4]>()?.into())),
+            1 => Self::AAAA(AAAA(u.arbitrary::<[u8; 16]>()?.into())),
+            2 => Self::ANAME(ANAME(u.arbitrary()?)),
+            3 => Self::CNAME(CNAME(u.arbitrary()?)),
+            4 => Self::MX(MX::new(u.arbitrary()?, u.arbitrary()?)),
+            5 => Self::NS(NS(u.arbitrary()?)),
+            6 => Self::PTR(PTR(u.arbitrary()?)),
+            7 => Self::SOA(SOA::new(
+                u.arbitrary()?,
+                u.arbitrary()?,
+                u.arbitrary()?,
+                u.arbitrary()?,
+                u.arbitrary()?,
+                u.arbitrary()?,
+                u.arbitrary()?,
+            )),
+            8 => Self::SRV(SRV::new(
+                u.arbitrary()?,
+                u.arbitrary()?,
+                u.arbitrary()?,
+                u.arbitrary()?,
+            )),
+            9 => {
+                // the strings of the data are limited to 255 bytes, these are ASCII
+                let mut strings = Vec::new();
+                for _ in 0..u.int_in_range(1..=3)? {
+                    let len = u.int_in_range(0..=255)?;
+                    let string = u.bytes(len)?.iter().map(|b| char::from(b & 0x7F));
+                    strings.push(string.collect());
+                }
+                Self::TXT(TXT::new(strings))
+            }
+            _ => {
+                let len = u.int_in_range(1..=512)?;
+                Self::NULL(NULL::with(u.bytes(len)?.to_vec()))
+            }
+        };
+
+        Ok(rdata)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Record {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut record = Self::from_rdata(u.arbitrary()?, u.arbitrary()?, u.arbitrary()?);
+        record.set_dns_class(u.arbitrary()?);
+
+        Ok(record)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Edns {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut edns = Self::new();
+        edns.set_max_payload(u.int_in_range(512..=u16::MAX)?)
+            .set_dnssec_ok(u.arbitrary()?);
+
+        Ok(edns)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Message {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut message = Self::new();
+        message.set_header(u.arbitrary()?);
+
+        for _ in 0..u.int_in_range(0..=MAX_QUERIES)? {
+            message.add_query(u.arbitrary()?);
+        }
+        for _ in 0..u.int_in_range(0..=MAX_RECORDS)? {
+            message.add_answer(u.arbitrary()?);
+        }
+        for _ in 0..u.int_in_range(0..=MAX_RECORDS)? {
+            message.add_name_server(u.arbitrary()?);
+        }
+        for _ in 0..u.int_in_range(0..=MAX_RECORDS)? {
+            message.add_additional(u.arbitrary()?);
+        }
+        if u.arbitrary()? {
+            message.set_edns(u.arbitrary()?);
+        }
+
+        Ok(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{RngCore, SeedableRng};
+
+    use super::*;
+    use crate::serialize::binary::{BinDecodable, BinDecoder};
+
+    fn name(name: &str) -> Name {
+        Name::from_ascii(name).unwrap()
+    }
+
+    fn mail() -> Record {
+        Record::from_rdata(
+            name("mail.example.com."),
+            300,
+            RData::A(A::new(192, 0, 2, 25)),
+        )
+    }
+
+    /// A response with compressed names in the answers and in their record data, and a name of
+    ///  another zone
+    fn response() -> Message {
+        let mut message = Message::new();
+        message
+            .set_message_type(MessageType::Response)
+            .add_query(Query::query(name("example.com."), RecordType::MX))
+            .add_answer(Record::from_rdata(
+                name("www.example.org."),
+                300,
+                RData::A(A::new(192, 0, 2, 80)),
+            ))
+            .add_answer(Record::from_rdata(
+                name("example.com."),
+                300,
+                RData::MX(MX::new(10, name("mail.example.com."))),
+            ))
+            .add_additional(mail());
+        message
+    }
+
+    /// Decodes the record at the offset of the message
+    fn read_record(bytes: &[u8], offset: usize) -> ProtoResult<Record> {
+        let mut decoder = BinDecoder::new(bytes);
+        decoder.read_slice(offset)?;
+        Record::read(&mut decoder)
+    }
+
+    #[test]
+    fn test_layout() {
+        let mutator = MessageMutator::new(&response()).unwrap();
+
+        // the labels of the query and of the other zone, and the first label of the exchange
+        assert_eq!(mutator.label_count(), 6);
+        // the query, the owners of the records and the exchange
+        assert_eq!(mutator.name_count(), 5);
+        assert_eq!(mutator.record_count(), 3);
+        assert_eq!(
+            read_record(mutator.bytes(), mutator.names[4].start).unwrap(),
+            mail()
+        );
+    }
+
+    #[test]
+    fn test_flip_flag() {
+        let mutator = MessageMutator::new(&response()).unwrap();
+
+        for flag in HeaderFlag::ALL {
+            let mutated = mutator.mutate(Mutation::FlipFlag(flag)).unwrap();
+            assert_ne!(mutated, mutator.bytes());
+
+            // flipping it twice restores the message
+            let restored = MessageMutator::from_vec(mutated)
+                .unwrap()
+                .mutate(Mutation::FlipFlag(flag))
+                .unwrap();
+            assert_eq!(restored, mutator.bytes());
+        }
+    }
+
+    #[test]
+    fn test_label_length() {
+        let mutator = MessageMutator::new(&response()).unwrap();
+
+        // a reserved label type
+        let mutated = mutator
+            .mutate(Mutation::LabelLength {
+                label: 0,
+                length: 0x40,
+            })
+            .unwrap();
+        assert!(Message::from_vec(&mutated).is_err());
+
+        // beyond the end of the message
+        let mutated = mutator
+            .mutate(Mutation::LabelLength {
+                label: 5,
+                length: 63,
+            })
+            .unwrap();
+        assert!(Message::from_vec(&mutated).is_err());
+
+        assert!(mutator
+            .mutate(Mutation::LabelLength {
+                label: 6,
+                length: 1,
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn test_compression_loop() {
+        let mutator = MessageMutator::new(&response()).unwrap();
+
+        for name in 0..mutator.name_count() {
+            let mutated = mutator.mutate(Mutation::CompressionLoop { name }).unwrap();
+            assert!(Message::from_vec(&mutated).is_err(), "name {name}");
+        }
+    }
+
+    #[test]
+    fn test_compression_loop_moves_pointers() {
+        let mutator = MessageMutator::new(&response()).unwrap();
+
+        // the root label of the other zone is replaced by a pointer, one byte longer
+        let mutated = mutator
+            .mutate(Mutation::CompressionLoop { name: 1 })
+            .unwrap();
+        assert_eq!(mutated.len(), mutator.bytes().len() + 1);
+
+        // the pointer of the additional to the exchange is moved with it
+        assert_eq!(
+            read_record(&mutated, mutator.names[4].start + 1).unwrap(),
+            mail()
+        );
+    }
+
+    #[test]
+    fn test_truncate_rdata() {
+        let mutator = MessageMutator::new(&response()).unwrap();
+
+        let mutated = mutator
+            .mutate(Mutation::TruncateRData { record: 0, len: 2 })
+            .unwrap();
+        assert_eq!(mutated.len(), mutator.bytes().len() - 2);
+        assert!(Message::from_vec(&mutated).is_err());
+
+        // the pointer of the additional to the exchange is moved with it
+        assert_eq!(
+            read_record(&mutated, mutator.names[4].start - 2).unwrap(),
+            mail()
+        );
+
+        // the full length keeps the message
+        let mutated = mutator
+            .mutate(Mutation::TruncateRData {
+                record: 2,
+                len: u16::MAX,
+            })
+            .unwrap();
+        assert_eq!(mutated, mutator.bytes());
+    }
+
+    #[test]
+    fn test_arbitrary_messages() {
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        let mut data = vec![0_u8; 8192];
+
+        for _ in 0..256 {
+            rng.fill_bytes(&mut data);
+            let mut u = Unstructured::new(&data);
+            let message = Message::arbitrary(&mut u).unwrap();
+
+            // the messages are valid, and each mutation is in range
+            let mutator = MessageMutator::new(&message).unwrap();
+            let decoded = Message::from_vec(mutator.bytes()).unwrap();
+            assert_eq!(decoded.queries(), message.queries());
+            assert_eq!(decoded.answers().len(), message.answers().len());
+
+            let mutation = mutator.arbitrary_mutation(&mut u).unwrap();
+            let mutated = mutator.mutate(mutation).unwrap();
+            let _ = Message::from_vec(&mutated);
+        }
+    }
+}
@@ -15,19 +15,21 @@ use std::str::FromStr;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 
+use crate::logging::{debug, warn};
 use bytes::{Buf, Bytes, BytesMut};
 use futures_util::future::{FutureExt, TryFutureExt};
 use futures_util::ready;
 use futures_util::stream::Stream;
 use h2::client::{Connection, SendRequest};
-use http::header::{self, CONTENT_LENGTH};
+use http::header::{HeaderName, HeaderValue, CONTENT_LENGTH};
+use http::{HeaderMap, Request, Response};
 use rustls::ClientConfig;
 use tokio_rustls::{
     client::TlsStream as TokioTlsClientStream, Connect as TokioTlsConnect, TlsConnector,
 };
-use tracing::{debug, warn};
 
-use crate::error::ProtoError;
+use crate::error::{ProtoError, ProtoResult};
+use crate::h2::http1_client::Http1Connection;
 use crate::http::Version;
 use crate::iocompat::AsyncIoStdAsTokio;
 use crate::op::Message;
@@ -35,6 +37,7 @@ use crate::tcp::{Connect, DnsTcpStream};
 use crate::xfer::{DnsRequest, DnsRequestSender, DnsResponse, DnsResponseStream};
 
 const ALPN_H2: &[u8] = b"h2";
+const ALPN_HTTP1: &[u8] = b"http/1.1";
 
 /// A DNS client connection for DNS-over-HTTPS
 #[derive(Clone)]
@@ -43,10 +46,36 @@ pub struct HttpsClientStream {
     // Corresponds to the dns-name of the HTTPS server
     name_server_name: Arc<str>,
     name_server: SocketAddr,
-    h2: SendRequest<Bytes>,
+    connection: HttpConnection,
+    options: HttpOptions,
     is_shutdown: bool,
 }
 
+/// The HTTP connection of a [`HttpsClientStream`], negotiated with ALPN
+#[derive(Clone)]
+enum HttpConnection {
+    H2(SendRequest<Bytes>),
+    Http1(Http1Connection),
+}
+
+/// The options of the requests of a [`HttpsClientStream`]
+#[derive(Clone)]
+struct HttpOptions {
+    query_path: Arc<str>,
+    headers: Arc<HeaderMap>,
+    http1_fallback: bool,
+}
+
+impl Default for HttpOptions {
+    fn default() -> Self {
+        Self {
+            query_path: Arc::from(crate::http::DNS_QUERY_PATH),
+            headers: Arc::default(),
+            http1_fallback: false,
+        }
+    }
+}
+
 impl Display for HttpsClientStream {
     fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         write!(
@@ -59,10 +88,61 @@ impl Display for HttpsClientStream {
 
 impl HttpsClientStream {
     async fn inner_send(
-        h2: SendRequest<Bytes>,
+        connection: HttpConnection,
         message: Bytes,
         name_server_name: Arc<str>,
+        options: HttpOptions,
     ) -> Result<DnsResponse, ProtoError> {
+        // build up the http request
+        let version = match connection {
+            HttpConnection::H2(_) => Version::Http2,
+            HttpConnection::Http1(_) => Version::Http11,
+        };
+        let request = crate::http::request::new(
+            version,
+            &name_server_name,
+            &options.query_path,
+            message.remaining(),
+        );
+
+        let mut request =
+            request.map_err(|err| ProtoError::from(format!("bad http request: {err}")))?;
+        request
+            .headers_mut()
+            .extend(options.headers.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+        debug!("request: {:#?}", request);
+
+        let (response, response_bytes) = match connection {
+            HttpConnection::H2(h2) => Self::send_h2(h2, request, message).await?,
+            HttpConnection::Http1(http1) => http1.send(request, message).await?,
+        };
+
+        // Was it a successful request?
+        if !response.status().is_success() {
+            let error_string = String::from_utf8_lossy(response_bytes.as_ref());
+
+            // TODO: make explicit error type
+            return Err(ProtoError::from(format!(
+                "http unsuccessful code: {}, message: {}",
+                response.status(),
+                error_string
+            )));
+        }
+
+        crate::http::response::verify_content_type(response.headers())
+            .map_err(|e| ProtoError::from(format!("bad headers received: {e}")))?;
+
+        // and finally convert the bytes into a DNS message
+        let message = Message::from_vec(&response_bytes)?;
+        Ok(DnsResponse::new(message, response_bytes.to_vec()))
+    }
+
+    async fn send_h2(
+        h2: SendRequest<Bytes>,
+        request: Request<()>,
+        message: Bytes,
+    ) -> Result<(Response<()>, Bytes), ProtoError> {
         let mut h2 = match h2.ready().await {
             Ok(h2) => h2,
             Err(err) => {
@@ -71,15 +151,6 @@ impl HttpsClientStream {
             }
         };
 
-        // build up the http request
-        let request =
-            crate::http::request::new(Version::Http2, &name_server_name, message.remaining());
-
-        let request =
-            request.map_err(|err| ProtoError::from(format!("bad http request: {err}")))?;
-
-        debug!("request: {:#?}", request);
-
         // Send the request
         let (response_future, mut send_stream) = h2
             .send_request(request, false)
@@ -89,15 +160,16 @@ impl HttpsClientStream {
             .send_data(message, true)
             .map_err(|e| ProtoError::from(format!("h2 send_data error: {e}")))?;
 
-        let mut response_stream = response_future
+        let response_stream = response_future
             .await
             .map_err(|err| ProtoError::from(format!("received a stream error: {err}")))?;
 
         debug!("got response: {:#?}", response_stream);
+        let (parts, mut body) = response_stream.into_parts();
 
         // get the length of packet
-        let content_length = response_stream
-            .headers()
+        let content_length = parts
+            .headers
             .get(CONTENT_LENGTH)
             .map(|v| v.to_str())
             .transpose()
@@ -112,7 +184,7 @@ impl HttpsClientStream {
         let mut response_bytes =
             BytesMut::with_capacity(content_length.unwrap_or(512).clamp(512, 4096));
 
-        while let Some(partial_bytes) = response_stream.body_mut().data().await {
+        while let Some(partial_bytes) = body.data().await {
             let partial_bytes =
                 partial_bytes.map_err(|e| ProtoError::from(format!("bad http request: {e}")))?;
 
@@ -139,44 +211,7 @@ impl HttpsClientStream {
             }
         }
 
-        // Was it a successful request?
-        if !response_stream.status().is_success() {
-            let error_string = String::from_utf8_lossy(response_bytes.as_ref());
-
-            // TODO: make explicit error type
-            return Err(ProtoError::from(format!(
-                "http unsuccessful code: {}, message: {}",
-                response_stream.status(),
-                error_string
-            )));
-        } else {
-            // verify content type
-            {
-                // in the case that the ContentType is not specified, we assume it's the standard DNS format
-                let content_type = response_stream
-                    .headers()
-                    .get(header::CONTENT_TYPE)
-                    .map(|h| {
-                        h.to_str().map_err(|err| {
-                            // TODO: make explicit error type
-                            ProtoError::from(format!("ContentType header not a string: {err}"))
-                        })
-                    })
-                    .unwrap_or(Ok(crate::http::MIME_APPLICATION_DNS))?;
-
-                if content_type != crate::http::MIME_APPLICATION_DNS {
-                    return Err(ProtoError::from(format!(
-                        "ContentType unsupported (must be '{}'): '{}'",
-                        crate::http::MIME_APPLICATION_DNS,
-                        content_type
-                    )));
-                }
-            }
-        };
-
-        // and finally convert the bytes into a DNS message
-        let message = Message::from_vec(&response_bytes)?;
-        Ok(DnsResponse::new(message, response_bytes.to_vec()))
+        Ok((Response::from_parts(parts, ()), response_bytes.freeze()))
     }
 }
 
@@ -242,9 +277,10 @@ impl DnsRequestSender for HttpsClientStream {
         };
 
         Box::pin(Self::inner_send(
-            self.h2.clone(),
+            self.connection.clone(),
             Bytes::from(bytes),
             Arc::clone(&self.name_server_name),
+            self.options.clone(),
         ))
         .into()
     }
@@ -267,12 +303,18 @@ impl Stream for HttpsClientStream {
         }
 
         // just checking if the connection is ok
-        match self.h2.poll_ready(cx) {
-            Poll::Ready(Ok(())) => Poll::Ready(Some(Ok(()))),
-            Poll::Pending => Poll::Pending,
-            Poll::Ready(Err(e)) => Poll::Ready(Some(Err(ProtoError::from(format!(
-                "h2 stream errored: {e}",
-            ))))),
+        match self.connection {
+            HttpConnection::H2(ref mut h2) => match h2.poll_ready(cx) {
+                Poll::Ready(Ok(())) => Poll::Ready(Some(Ok(()))),
+                Poll::Pending => Poll::Pending,
+                Poll::Ready(Err(e)) => Poll::Ready(Some(Err(ProtoError::from(format!(
+                    "h2 stream errored: {e}",
+                ))))),
+            },
+            HttpConnection::Http1(ref http1) if http1.is_closed() => {
+                Poll::Ready(Some(Err(ProtoError::from("http/1.1 connection closed"))))
+            }
+            HttpConnection::Http1(_) => Poll::Ready(Some(Ok(()))),
         }
     }
 }
@@ -282,6 +324,7 @@ impl Stream for HttpsClientStream {
 pub struct HttpsClientStreamBuilder {
     client_config: Arc<ClientConfig>,
     bind_addr: Option<SocketAddr>,
+    options: HttpOptions,
 }
 
 impl HttpsClientStreamBuilder {
@@ -290,6 +333,7 @@ impl HttpsClientStreamBuilder {
         Self {
             client_config,
             bind_addr: None,
+            options: HttpOptions::default(),
         }
     }
 
@@ -298,6 +342,36 @@ impl HttpsClientStreamBuilder {
         self.bind_addr = Some(bind_addr);
     }
 
+    /// Sets the path of the queries from a URI template, `/dns-query` by default
+    ///
+    /// The template is e.g. the `dohpath` of a SVCB record, like `/dns-query{?dns}`, see
+    ///  [`crate::http::request::query_path`].
+    pub fn query_path(&mut self, template: &str) -> ProtoResult<()> {
+        let query_path = crate::http::request::query_path(template)
+            .map_err(|e| ProtoError::from(format!("bad query path: {e}")))?;
+        self.options.query_path = Arc::from(query_path);
+        Ok(())
+    }
+
+    /// Adds a header to all requests, e.g. `Authorization` with a token of the server
+    pub fn header(&mut self, name: &str, value: &str) -> ProtoResult<()> {
+        let name = HeaderName::from_str(name)
+            .map_err(|e| ProtoError::from(format!("bad header name: {e}")))?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|e| ProtoError::from(format!("bad header value: {e}")))?;
+        Arc::make_mut(&mut self.options.headers).append(name, value);
+        Ok(())
+    }
+
+    /// Speak HTTP/1.1 with servers which do not negotiate HTTP/2, disabled by default
+    ///
+    /// HTTP/1.1 is offered along with HTTP/2 in ALPN, unless the `ClientConfig` has its own ALPN
+    ///  protocols. Servers and middleboxes which do not negotiate a protocol are spoken to with
+    ///  HTTP/1.1 as well. Queries over HTTP/1.1 are sent one at a time.
+    pub fn http1_fallback(&mut self, http1_fallback: bool) {
+        self.options.http1_fallback = http1_fallback;
+    }
+
     /// Creates a new HttpsStream to the specified name_server
     ///
     /// # Arguments
@@ -305,53 +379,52 @@ impl HttpsClientStreamBuilder {
     /// * `name_server` - IP and Port for the remote DNS resolver
     /// * `dns_name` - The DNS name, Subject Public Key Info (SPKI) name, as associated to a certificate
     pub fn build<S: Connect>(
-        mut self,
+        self,
         name_server: SocketAddr,
         dns_name: String,
     ) -> HttpsClientConnect<S> {
-        // ensure the ALPN protocol is set correctly
-        if self.client_config.alpn_protocols.is_empty() {
-            let mut client_config = (*self.client_config).clone();
-            client_config.alpn_protocols = vec![ALPN_H2.to_vec()];
-
-            self.client_config = Arc::new(client_config);
-        }
-
-        let tls = TlsConfig {
-            client_config: self.client_config,
-            dns_name: Arc::from(dns_name),
-        };
-
         let connect = S::connect_with_bind(name_server, self.bind_addr);
-
-        HttpsClientConnect::<S>(HttpsClientConnectState::TcpConnecting {
-            connect,
-            name_server,
-            tls: Some(tls),
-        })
+        self.build_from_future(connect, name_server, dns_name)
     }
 
     /// Creates a new HttpsStream with existing connection
     pub fn build_with_future<S, F>(
         future: F,
-        mut client_config: Arc<ClientConfig>,
+        client_config: Arc<ClientConfig>,
         name_server: SocketAddr,
         dns_name: String,
     ) -> HttpsClientConnect<S>
     where
         S: DnsTcpStream,
         F: Future<Output = std::io::Result<S>> + Send + Unpin + 'static,
+    {
+        Self::with_client_config(client_config).build_from_future(future, name_server, dns_name)
+    }
+
+    /// Creates a new HttpsStream with existing connection, and the options of this builder
+    pub fn build_from_future<S, F>(
+        mut self,
+        future: F,
+        name_server: SocketAddr,
+        dns_name: String,
+    ) -> HttpsClientConnect<S>
+    where
+        S: DnsTcpStream,
+        F: Future<Output = std::io::Result<S>> + Send + 'static,
     {
         // ensure the ALPN protocol is set correctly
-        if client_config.alpn_protocols.is_empty() {
-            let mut client_cfg = (*client_config).clone();
-            client_cfg.alpn_protocols = vec![ALPN_H2.to_vec()];
+        if self.client_config.alpn_protocols.is_empty() {
+            let mut client_config = (*self.client_config).clone();
+            client_config.alpn_protocols = vec![ALPN_H2.to_vec()];
+            if self.options.http1_fallback {
+                client_config.alpn_protocols.push(ALPN_HTTP1.to_vec());
+            }
 
-            client_config = Arc::new(client_cfg);
+            self.client_config = Arc::new(client_config);
         }
 
         let tls = TlsConfig {
-            client_config,
+            client_config: self.client_config,
             dns_name: Arc::from(dns_name),
         };
 
@@ -359,6 +432,7 @@ impl HttpsClientStreamBuilder {
             connect: Box::pin(future),
             name_server,
             tls: Some(tls),
+            options: self.options,
         })
     }
 }
@@ -394,12 +468,14 @@ where
         connect: Pin<Box<dyn Future<Output = io::Result<S>> + Send>>,
         name_server: SocketAddr,
         tls: Option<TlsConfig>,
+        options: HttpOptions,
     },
     TlsConnecting {
         // TODO: also abstract away Tokio TLS in RuntimeProvider.
         tls: TokioTlsConnect<AsyncIoStdAsTokio<S>>,
         name_server_name: Arc<str>,
         name_server: SocketAddr,
+        options: HttpOptions,
     },
     H2Handshake {
         handshake: Pin<
@@ -417,6 +493,7 @@ where
         >,
         name_server_name: Arc<str>,
         name_server: SocketAddr,
+        options: HttpOptions,
     },
     Connected(Option<HttpsClientStream>),
     Errored(Option<ProtoError>),
@@ -435,6 +512,7 @@ where
                     ref mut connect,
                     name_server,
                     ref mut tls,
+                    ref options,
                 } => {
                     let tcp = ready!(connect.poll_unpin(cx))?;
 
@@ -452,6 +530,7 @@ where
                                 name_server_name,
                                 name_server,
                                 tls,
+                                options: options.clone(),
                             }
                         }
                         Err(_) => Self::Errored(Some(ProtoError::from(format!(
@@ -464,9 +543,25 @@ where
                     ref name_server_name,
                     name_server,
                     ref mut tls,
+                    ref options,
                 } => {
                     let tls = ready!(tls.poll_unpin(cx))?;
                     debug!("tls connection established to: {}", name_server);
+
+                    // servers and middleboxes without HTTP/2 do not negotiate h2
+                    let alpn = tls.get_ref().1.alpn_protocol();
+                    if options.http1_fallback && alpn != Some(ALPN_H2) {
+                        debug!("http/1.1 connection established to: {}", name_server);
+                        *self.as_mut().deref_mut() = Self::Connected(Some(HttpsClientStream {
+                            name_server_name: Arc::clone(name_server_name),
+                            name_server,
+                            connection: HttpConnection::Http1(Http1Connection::new(tls)),
+                            options: options.clone(),
+                            is_shutdown: false,
+                        }));
+                        continue;
+                    }
+
                     let mut handshake = h2::client::Builder::new();
                     handshake.enable_push(false);
 
@@ -475,12 +570,14 @@ where
                         name_server_name: Arc::clone(name_server_name),
                         name_server,
                         handshake: Box::pin(handshake),
+                        options: options.clone(),
                     }
                 }
                 Self::H2Handshake {
                     ref name_server_name,
                     name_server,
                     ref mut handshake,
+                    ref options,
                 } => {
                     let (send_request, connection) = ready!(handshake
                         .poll_unpin(cx)
@@ -497,7 +594,8 @@ where
                     Self::Connected(Some(HttpsClientStream {
                         name_server_name: Arc::clone(name_server_name),
                         name_server,
-                        h2: send_request,
+                        connection: HttpConnection::H2(send_request),
+                        options: options.clone(),
                         is_shutdown: false,
                     }))
                 }
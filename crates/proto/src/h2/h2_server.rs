@@ -11,12 +11,12 @@ use std::fmt::Debug;
 use std::str::FromStr;
 use std::sync::Arc;
 
+use crate::logging::debug;
 use bytes::{Bytes, BytesMut};
 use futures_util::stream::{Stream, StreamExt};
 use h2;
 use http::header::CONTENT_LENGTH;
 use http::{Method, Request};
-use tracing::debug;
 
 use crate::h2::HttpsError;
 use crate::http::Version;
@@ -35,7 +35,12 @@ where
     debug!("Received request: {:#?}", request);
 
     let this_server_name = this_server_name.as_deref();
-    match crate::http::request::verify(Version::Http2, this_server_name, &request) {
+    match crate::http::request::verify(
+        Version::Http2,
+        this_server_name,
+        crate::http::DNS_QUERY_PATH,
+        &request,
+    ) {
         Ok(_) => (),
         Err(err) => return Err(err),
     }
@@ -124,7 +129,13 @@ mod tests {
         let msg_bytes = message.to_vec().unwrap();
         let len = msg_bytes.len();
         let stream = TestBytesStream(vec![Ok(Bytes::from(msg_bytes))]);
-        let request = request::new(Version::Http2, "ns.example.com", len).unwrap();
+        let request = request::new(
+            Version::Http2,
+            "ns.example.com",
+            crate::http::DNS_QUERY_PATH,
+            len,
+        )
+        .unwrap();
         let request = request.map(|()| stream);
 
         let from_post = message_from(Some(Arc::from("ns.example.com")), request);
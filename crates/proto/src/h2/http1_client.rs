@@ -0,0 +1,356 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! A minimal HTTP/1.1 client for DNS over HTTPS with servers and middleboxes without HTTP/2
+
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use bytes::{Bytes, BytesMut};
+use futures_util::lock::Mutex;
+use http::header::{HeaderName, HeaderValue, CONNECTION, CONTENT_LENGTH, HOST, TRANSFER_ENCODING};
+use http::{Request, Response, StatusCode};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::error::ProtoError;
+
+/// The maximum size of the status line and the headers of a response
+const MAX_HEAD_LEN: usize = 16 * 1024;
+
+/// The maximum size of the body of a response, a DNS message is at most 64 KiB
+const MAX_BODY_LEN: usize = u16::MAX as usize;
+
+pub(crate) trait Io: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin> Io for T {}
+
+/// A connection with one request at a time, as HTTP/1.1 has no multiplexing
+#[derive(Clone)]
+pub(crate) struct Http1Connection {
+    io: Arc<Mutex<Option<Buffered>>>,
+    closed: Arc<AtomicBool>,
+}
+
+struct Buffered {
+    io: Box<dyn Io>,
+    buf: BytesMut,
+}
+
+impl Http1Connection {
+    pub(crate) fn new(io: impl Io + 'static) -> Self {
+        Self {
+            io: Arc::new(Mutex::new(Some(Buffered {
+                io: Box::new(io),
+                buf: BytesMut::new(),
+            }))),
+            closed: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Returns true if the server closed the connection, or it failed
+    pub(crate) fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire)
+    }
+
+    /// Sends the request with the body, and returns the response with its body
+    pub(crate) async fn send(
+        &self,
+        request: Request<()>,
+        body: Bytes,
+    ) -> Result<(Response<()>, Bytes), ProtoError> {
+        let mut guard = self.io.lock().await;
+        let Some(io) = guard.as_mut() else {
+            return Err(ProtoError::from("http/1.1 connection closed"));
+        };
+
+        let result = io.exchange(&request, &body).await;
+        if !matches!(result, Ok((_, _, true))) {
+            *guard = None;
+            self.closed.store(true, Ordering::Release);
+        }
+
+        result.map(|(response, body, _)| (response, body))
+    }
+}
+
+impl Buffered {
+    /// Returns the response, its body, and whether the connection can be reused
+    async fn exchange(
+        &mut self,
+        request: &Request<()>,
+        body: &[u8],
+    ) -> Result<(Response<()>, Bytes, bool), ProtoError> {
+        self.io.write_all(&encode_head(request)?).await?;
+        self.io.write_all(body).await?;
+        self.io.flush().await?;
+
+        let head = self.read_head().await?;
+        let response = parse_head(&head)?;
+
+        let keep_alive = !response.headers().get(CONNECTION).map_or(false, |value| {
+            value.as_bytes().eq_ignore_ascii_case(b"close")
+        });
+
+        let chunked = response
+            .headers()
+            .get(TRANSFER_ENCODING)
+            .map_or(false, |value| {
+                value.as_bytes().to_ascii_lowercase().ends_with(b"chunked")
+            });
+
+        let content_length = response
+            .headers()
+            .get(CONTENT_LENGTH)
+            .map(|value| value.to_str().map(usize::from_str))
+            .transpose()
+            .map_err(|e| ProtoError::from(format!("bad headers received: {e}")))?
+            .transpose()
+            .map_err(|e| ProtoError::from(format!("bad headers received: {e}")))?;
+
+        let (body, keep_alive) = if response.status() == StatusCode::NO_CONTENT
+            || response.status().is_informational()
+        {
+            (Bytes::new(), keep_alive)
+        } else if chunked {
+            (self.read_chunked().await?, keep_alive)
+        } else if let Some(len) = content_length {
+            (self.read_exact(len).await?, keep_alive)
+        } else {
+            // the body is delimited by the end of the connection
+            (self.read_to_end().await?, false)
+        };
+
+        Ok((response, body, keep_alive))
+    }
+
+    /// Reads more bytes into the buffer, returns an error at the end of the stream
+    async fn fill(&mut self) -> Result<(), ProtoError> {
+        if self.io.read_buf(&mut self.buf).await? == 0 {
+            return Err(ProtoError::from("http/1.1 connection closed by the server"));
+        }
+        Ok(())
+    }
+
+    async fn read_head(&mut self) -> Result<BytesMut, ProtoError> {
+        loop {
+            if let Some(end) = find(&self.buf, b"\r\n\r\n") {
+                return Ok(self.buf.split_to(end + 4));
+            }
+            if self.buf.len() > MAX_HEAD_LEN {
+                return Err(ProtoError::from("http/1.1 response headers too large"));
+            }
+            self.fill().await?;
+        }
+    }
+
+    async fn read_line(&mut self) -> Result<BytesMut, ProtoError> {
+        loop {
+            if let Some(end) = find(&self.buf, b"\r\n") {
+                let mut line = self.buf.split_to(end + 2);
+                line.truncate(end);
+                return Ok(line);
+            }
+            if self.buf.len() > MAX_HEAD_LEN {
+                return Err(ProtoError::from("http/1.1 chunk header too large"));
+            }
+            self.fill().await?;
+        }
+    }
+
+    async fn read_exact(&mut self, len: usize) -> Result<Bytes, ProtoError> {
+        if len > MAX_BODY_LEN {
+            return Err(ProtoError::from(format!(
+                "http/1.1 response body too large: {len}"
+            )));
+        }
+        while self.buf.len() < len {
+            self.fill().await?;
+        }
+        Ok(self.buf.split_to(len).freeze())
+    }
+
+    async fn read_chunked(&mut self) -> Result<Bytes, ProtoError> {
+        let mut body = BytesMut::new();
+        loop {
+            let line = self.read_line().await?;
+            let size = std::str::from_utf8(&line)
+                .ok()
+                .and_then(|line| line.split(';').next())
+                .and_then(|size| usize::from_str_radix(size.trim(), 16).ok())
+                .ok_or_else(|| ProtoError::from("bad http/1.1 chunk size"))?;
+
+            if size == 0 {
+                // skip the trailers
+                while !self.read_line().await?.is_empty() {}
+                return Ok(body.freeze());
+            }
+
+            if size > MAX_BODY_LEN - body.len() {
+                return Err(ProtoError::from("http/1.1 response body too large"));
+            }
+            body.extend_from_slice(&self.read_exact(size).await?);
+            if !self.read_line().await?.is_empty() {
+                return Err(ProtoError::from("bad http/1.1 chunk"));
+            }
+        }
+    }
+
+    async fn read_to_end(&mut self) -> Result<Bytes, ProtoError> {
+        while self.io.read_buf(&mut self.buf).await? > 0 {
+            if self.buf.len() > MAX_BODY_LEN {
+                return Err(ProtoError::from("http/1.1 response body too large"));
+            }
+        }
+        Ok(self.buf.split().freeze())
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn trim(mut bytes: &[u8]) -> &[u8] {
+    while let [b' ' | b'\t', rest @ ..] = bytes {
+        bytes = rest;
+    }
+    while let [rest @ .., b' ' | b'\t'] = bytes {
+        bytes = rest;
+    }
+    bytes
+}
+
+/// Encodes the request line and the headers of the request
+fn encode_head(request: &Request<()>) -> Result<Vec<u8>, ProtoError> {
+    let uri = request.uri();
+    let path = uri.path_and_query().map_or("/", |path| path.as_str());
+    let host = uri
+        .authority()
+        .ok_or_else(|| ProtoError::from("no authority in the request"))?;
+
+    let mut head = format!("{} {path} HTTP/1.1\r\n", request.method()).into_bytes();
+    if !request.headers().contains_key(HOST) {
+        head.extend_from_slice(format!("host: {host}\r\n").as_bytes());
+    }
+    for (name, value) in request.headers() {
+        head.extend_from_slice(name.as_str().as_bytes());
+        head.extend_from_slice(b": ");
+        head.extend_from_slice(value.as_bytes());
+        head.extend_from_slice(b"\r\n");
+    }
+    head.extend_from_slice(b"\r\n");
+
+    Ok(head)
+}
+
+/// Parses the status line and the headers of a response
+fn parse_head(mut head: &[u8]) -> Result<Response<()>, ProtoError> {
+    let mut lines = std::iter::from_fn(|| {
+        let end = find(head, b"\r\n")?;
+        let line = &head[..end];
+        head = &head[end + 2..];
+        Some(line)
+    });
+
+    let status_line = lines.next().unwrap_or_default();
+    let status = std::str::from_utf8(status_line)
+        .ok()
+        .filter(|line| line.starts_with("HTTP/1."))
+        .and_then(|line| line.split(' ').nth(1))
+        .and_then(|status| StatusCode::from_str(status).ok())
+        .ok_or_else(|| {
+            ProtoError::from(format!(
+                "bad http/1.1 status line: {}",
+                String::from_utf8_lossy(status_line)
+            ))
+        })?;
+
+    let mut response = Response::builder().status(status);
+    for line in lines.take_while(|line| !line.is_empty()) {
+        let colon = line
+            .iter()
+            .position(|b| *b == b':')
+            .ok_or_else(|| ProtoError::from("bad http/1.1 header"))?;
+        let name = HeaderName::from_bytes(&line[..colon])
+            .map_err(|e| ProtoError::from(format!("bad http/1.1 header: {e}")))?;
+        let value = HeaderValue::from_bytes(trim(&line[colon + 1..]))
+            .map_err(|e| ProtoError::from(format!("bad http/1.1 header: {e}")))?;
+        response = response.header(name, value);
+    }
+
+    response
+        .body(())
+        .map_err(|e| ProtoError::from(format!("bad http/1.1 response: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use http::header::CONTENT_TYPE;
+    use tokio::io::duplex;
+
+    use super::*;
+
+    async fn exchange(
+        response: &'static [u8],
+    ) -> (Result<(Response<()>, Bytes), ProtoError>, Vec<u8>, bool) {
+        let (client, mut server) = duplex(4096);
+        let connection = Http1Connection::new(client);
+
+        let server = tokio::spawn(async move {
+            let mut request = vec![0; 4096];
+            let len = server.read(&mut request).await.unwrap();
+            request.truncate(len);
+            server.write_all(response).await.unwrap();
+            request
+        });
+
+        let request = Request::post("https://dns.example.com/dns-query")
+            .header(CONTENT_TYPE, "application/dns-message")
+            .body(())
+            .unwrap();
+        let result = connection.send(request, Bytes::from_static(b"query")).await;
+        (result, server.await.unwrap(), connection.is_closed())
+    }
+
+    #[tokio::test]
+    async fn test_content_length() {
+        let (result, request, closed) = exchange(
+            b"HTTP/1.1 200 OK\r\ncontent-type: application/dns-message\r\ncontent-length: 5\r\n\r\nhello",
+        )
+        .await;
+
+        let request = String::from_utf8(request).unwrap();
+        assert!(request.starts_with("POST /dns-query HTTP/1.1\r\nhost: dns.example.com\r\n"));
+        assert!(request.ends_with("\r\n\r\nquery"));
+
+        let (response, body) = result.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/dns-message");
+        assert_eq!(body, "hello");
+        assert!(!closed);
+    }
+
+    #[tokio::test]
+    async fn test_chunked() {
+        let (result, _, closed) = exchange(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n3\r\nhel\r\n2;ext=1\r\nlo\r\n0\r\n\r\n",
+        )
+        .await;
+
+        assert_eq!(result.unwrap().1, "hello");
+        assert!(closed);
+    }
+
+    #[tokio::test]
+    async fn test_bad_response() {
+        let (result, _, closed) = exchange(b"SSH-2.0-OpenSSH\r\n\r\n").await;
+        assert!(result.is_err());
+        assert!(closed);
+    }
+}
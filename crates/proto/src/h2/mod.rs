@@ -9,6 +9,7 @@
 
 mod h2_client_stream;
 pub mod h2_server;
+mod http1_client;
 
 pub use crate::http::error::{Error as HttpsError, Result as HttpsResult};
 
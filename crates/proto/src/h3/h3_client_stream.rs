@@ -13,15 +13,15 @@ use std::str::FromStr;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 
+use crate::logging::debug;
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 use futures_util::future::FutureExt;
 use futures_util::stream::Stream;
 use h3::client::{Connection, SendRequest};
 use h3_quinn::OpenStreams;
-use http::header::{self, CONTENT_LENGTH};
+use http::header::CONTENT_LENGTH;
 use quinn::{ClientConfig, Endpoint, EndpointConfig, TransportConfig};
 use rustls::ClientConfig as TlsClientConfig;
-use tracing::debug;
 
 use crate::error::ProtoError;
 use crate::http::Version;
@@ -66,8 +66,12 @@ impl H3ClientStream {
         name_server_name: Arc<str>,
     ) -> Result<DnsResponse, ProtoError> {
         // build up the http request
-        let request =
-            crate::http::request::new(Version::Http3, &name_server_name, message.remaining());
+        let request = crate::http::request::new(
+            Version::Http3,
+            &name_server_name,
+            crate::http::DNS_QUERY_PATH,
+            message.remaining(),
+        );
 
         let request =
             request.map_err(|err| ProtoError::from(format!("bad http request: {err}")))?;
@@ -152,30 +156,10 @@ impl H3ClientStream {
                 response.status(),
                 error_string
             )));
-        } else {
-            // verify content type
-            {
-                // in the case that the ContentType is not specified, we assume it's the standard DNS format
-                let content_type = response
-                    .headers()
-                    .get(header::CONTENT_TYPE)
-                    .map(|h| {
-                        h.to_str().map_err(|err| {
-                            // TODO: make explicit error type
-                            ProtoError::from(format!("ContentType header not a string: {err}"))
-                        })
-                    })
-                    .unwrap_or(Ok(crate::http::MIME_APPLICATION_DNS))?;
-
-                if content_type != crate::http::MIME_APPLICATION_DNS {
-                    return Err(ProtoError::from(format!(
-                        "ContentType unsupported (must be '{}'): '{}'",
-                        crate::http::MIME_APPLICATION_DNS,
-                        content_type
-                    )));
-                }
-            }
-        };
+        }
+
+        crate::http::response::verify_content_type(response.headers())
+            .map_err(|e| ProtoError::from(format!("bad headers received: {e}")))?;
 
         // and finally convert the bytes into a DNS message
         let message = Message::from_vec(&response_bytes)?;
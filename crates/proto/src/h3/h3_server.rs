@@ -14,12 +14,29 @@ use h3::server::{Connection, RequestStream};
 use h3_quinn::{BidiStream, Endpoint};
 use http::Request;
 use quinn::{EndpointConfig, ServerConfig};
-use rustls::{server::ServerConfig as TlsServerConfig, version::TLS13, Certificate, PrivateKey};
+use rustls::{
+    server::{ResolvesServerCert, ServerConfig as TlsServerConfig, WantsServerCert},
+    version::TLS13,
+    Certificate, ConfigBuilder, PrivateKey,
+};
 
 use crate::{error::ProtoError, udp::UdpSocket};
 
 use super::ALPN_H3;
 
+/// The number of requests a client may send at a time on each connection, by default
+pub const DEFAULT_MAX_CONCURRENT_STREAMS: u32 = 100;
+
+/// The TLS 1.3 configuration of servers, without the certificate
+fn tls_config() -> ConfigBuilder<TlsServerConfig, WantsServerCert> {
+    TlsServerConfig::builder()
+        .with_safe_default_cipher_suites()
+        .with_safe_default_kx_groups()
+        .with_protocol_versions(&[&TLS13])
+        .expect("TLS1.3 not supported")
+        .with_no_client_auth()
+}
+
 /// A DNS-over-HTTP/3 Server, see H3ClientStream for the client counterpart
 pub struct H3Server {
     endpoint: Endpoint,
@@ -43,18 +60,47 @@ impl H3Server {
         cert: Vec<Certificate>,
         key: PrivateKey,
     ) -> Result<Self, ProtoError> {
-        let mut config = TlsServerConfig::builder()
-            .with_safe_default_cipher_suites()
-            .with_safe_default_kx_groups()
-            .with_protocol_versions(&[&TLS13])
-            .expect("TLS1.3 not supported")
-            .with_no_client_auth()
-            .with_single_cert(cert, key)?;
+        Self::with_socket_and_max_concurrent_streams(
+            socket,
+            cert,
+            key,
+            DEFAULT_MAX_CONCURRENT_STREAMS,
+        )
+    }
+
+    /// Construct the new server with an existing socket, which accepts up to
+    ///  `max_concurrent_streams` requests at a time on each connection
+    pub fn with_socket_and_max_concurrent_streams(
+        socket: tokio::net::UdpSocket,
+        cert: Vec<Certificate>,
+        key: PrivateKey,
+        max_concurrent_streams: u32,
+    ) -> Result<Self, ProtoError> {
+        let config = tls_config().with_single_cert(cert, key)?;
+        Self::with_socket_and_tls_config(socket, config, max_concurrent_streams)
+    }
+
+    /// Construct the new server with an existing socket, which selects the certificate with the
+    ///  resolver, e.g. by the SNI of the client, and accepts up to `max_concurrent_streams`
+    ///  requests at a time on each connection
+    pub fn with_socket_and_cert_resolver(
+        socket: tokio::net::UdpSocket,
+        resolver: Arc<dyn ResolvesServerCert>,
+        max_concurrent_streams: u32,
+    ) -> Result<Self, ProtoError> {
+        let config = tls_config().with_cert_resolver(resolver);
+        Self::with_socket_and_tls_config(socket, config, max_concurrent_streams)
+    }
 
+    fn with_socket_and_tls_config(
+        socket: tokio::net::UdpSocket,
+        mut config: TlsServerConfig,
+        max_concurrent_streams: u32,
+    ) -> Result<Self, ProtoError> {
         config.alpn_protocols = vec![ALPN_H3.to_vec()];
 
         let mut server_config = ServerConfig::with_crypto(Arc::new(config));
-        server_config.transport = Arc::new(super::transport());
+        server_config.transport = Arc::new(super::server_transport(max_concurrent_streams));
 
         let socket = socket.into_std()?;
 
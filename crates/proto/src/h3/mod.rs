@@ -37,3 +37,11 @@ fn transport() -> TransportConfig {
 
     transport_config
 }
+
+/// Returns the endpoint configuration of servers, which accept up to `max_concurrent_streams`
+///  requests at a time on each connection
+fn server_transport(max_concurrent_streams: u32) -> TransportConfig {
+    let mut transport_config = transport();
+    transport_config.max_concurrent_bidi_streams(VarInt::from_u32(max_concurrent_streams));
+    transport_config
+}
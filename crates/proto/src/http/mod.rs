@@ -17,6 +17,9 @@ pub mod response;
 /// Represents a version of the HTTP spec.
 #[derive(Clone, Copy, Debug)]
 pub enum Version {
+    /// HTTP/1.1 for DoH, with servers and middleboxes without HTTP/2
+    #[cfg(feature = "dns-over-https")]
+    Http11,
     /// HTTP/2 for DoH.
     #[cfg(feature = "dns-over-https")]
     Http2,
@@ -28,6 +31,8 @@ pub enum Version {
 impl Version {
     fn to_http(self) -> http::Version {
         match self {
+            #[cfg(feature = "dns-over-https")]
+            Self::Http11 => http::Version::HTTP_11,
             #[cfg(feature = "dns-over-https")]
             Self::Http2 => http::Version::HTTP_2,
             #[cfg(feature = "dns-over-h3")]
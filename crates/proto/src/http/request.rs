@@ -9,9 +9,9 @@
 
 use std::str::FromStr;
 
+use crate::logging::debug;
 use http::header::{ACCEPT, CONTENT_LENGTH, CONTENT_TYPE};
 use http::{header, uri, Request, Uri};
-use tracing::debug;
 
 use crate::error::ProtoError;
 use crate::http::error::Result;
@@ -28,7 +28,12 @@ use crate::http::Version;
 /// [RFC4648].
 /// ```
 #[allow(clippy::field_reassign_with_default)] // https://github.com/rust-lang/rust-clippy/issues/6527
-pub fn new(version: Version, name_server_name: &str, message_len: usize) -> Result<Request<()>> {
+pub fn new(
+    version: Version,
+    name_server_name: &str,
+    query_path: &str,
+    message_len: usize,
+) -> Result<Request<()>> {
     // TODO: this is basically the GET version, but it is more expensive than POST
     //   perhaps add an option if people want better HTTP caching options.
 
@@ -42,7 +47,10 @@ pub fn new(version: Version, name_server_name: &str, message_len: usize) -> Resu
     //     .body(());
 
     let mut parts = uri::Parts::default();
-    parts.path_and_query = Some(uri::PathAndQuery::from_static(crate::http::DNS_QUERY_PATH));
+    parts.path_and_query = Some(
+        uri::PathAndQuery::from_str(query_path)
+            .map_err(|e| ProtoError::from(format!("invalid query path: {e}")))?,
+    );
     parts.scheme = Some(uri::Scheme::HTTPS);
     parts.authority = Some(
         uri::Authority::from_str(name_server_name)
@@ -66,19 +74,44 @@ pub fn new(version: Version, name_server_name: &str, message_len: usize) -> Resu
     Ok(request)
 }
 
+/// Returns the path of POST requests of the URI template, e.g. the `dohpath` of a SVCB record
+///
+/// The template is expanded without variables, so `/dns-query{?dns}` is the path `/dns-query`,
+///  see [RFC 9461](https://www.rfc-editor.org/rfc/rfc9461#section-5).
+pub fn query_path(template: &str) -> Result<String> {
+    let mut path = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("unterminated expression in URI template: {template}"))?;
+        path.push_str(&rest[..start]);
+        rest = &rest[start + end + 1..];
+    }
+    path.push_str(rest);
+
+    if !path.starts_with('/') || path.contains('}') {
+        return Err(format!("invalid URI template: {template}").into());
+    }
+
+    uri::PathAndQuery::from_str(&path)
+        .map_err(|e| ProtoError::from(format!("invalid URI template: {e}")))?;
+    Ok(path)
+}
+
 /// Verifies the request is something we know what to deal with
-pub fn verify<T>(version: Version, name_server: Option<&str>, request: &Request<T>) -> Result<()> {
+pub fn verify<T>(
+    version: Version,
+    name_server: Option<&str>,
+    query_path: &str,
+    request: &Request<T>,
+) -> Result<()> {
     // Verify all HTTP parameters
     let uri = request.uri();
 
     // validate path
-    if uri.path() != crate::http::DNS_QUERY_PATH {
-        return Err(format!(
-            "bad path: {}, expected: {}",
-            uri.path(),
-            crate::http::DNS_QUERY_PATH
-        )
-        .into());
+    if uri.path() != query_path {
+        return Err(format!("bad path: {}, expected: {}", uri.path(), query_path).into());
     }
 
     // we only accept HTTPS
@@ -132,6 +165,8 @@ pub fn verify<T>(version: Version, name_server: Option<&str>, request: &Request<
 
     if request.version() != version.to_http() {
         let message = match version {
+            #[cfg(feature = "dns-over-https")]
+            Version::Http11 => "only HTTP/1.1 supported",
             #[cfg(feature = "dns-over-https")]
             Version::Http2 => "only HTTP/2 supported",
             #[cfg(feature = "dns-over-h3")]
@@ -156,17 +191,52 @@ pub fn verify<T>(version: Version, name_server: Option<&str>, request: &Request<
 mod tests {
     use super::*;
 
+    use crate::http::DNS_QUERY_PATH;
+
     #[test]
     #[cfg(feature = "dns-over-https")]
     fn test_new_verify_h2() {
-        let request = new(Version::Http2, "ns.example.com", 512).expect("error converting to http");
-        assert!(verify(Version::Http2, Some("ns.example.com"), &request).is_ok());
+        let request = new(Version::Http2, "ns.example.com", DNS_QUERY_PATH, 512)
+            .expect("error converting to http");
+        assert!(verify(
+            Version::Http2,
+            Some("ns.example.com"),
+            DNS_QUERY_PATH,
+            &request
+        )
+        .is_ok());
     }
 
     #[test]
     #[cfg(feature = "dns-over-h3")]
     fn test_new_verify_h3() {
-        let request = new(Version::Http3, "ns.example.com", 512).expect("error converting to http");
-        assert!(verify(Version::Http3, Some("ns.example.com"), &request).is_ok());
+        let request = new(Version::Http3, "ns.example.com", DNS_QUERY_PATH, 512)
+            .expect("error converting to http");
+        assert!(verify(
+            Version::Http3,
+            Some("ns.example.com"),
+            DNS_QUERY_PATH,
+            &request
+        )
+        .is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "dns-over-https")]
+    fn test_custom_path() {
+        let request = new(Version::Http2, "ns.example.com", "/resolve", 512).unwrap();
+        assert_eq!(request.uri().path(), "/resolve");
+        assert!(verify(Version::Http2, None, "/resolve", &request).is_ok());
+        assert!(verify(Version::Http2, None, DNS_QUERY_PATH, &request).is_err());
+    }
+
+    #[test]
+    fn test_query_path() {
+        assert_eq!(query_path("/dns-query{?dns}").unwrap(), "/dns-query");
+        assert_eq!(query_path("/q{?dns}{&ct}").unwrap(), "/q");
+        assert_eq!(query_path("/resolve").unwrap(), "/resolve");
+        assert!(query_path("dns-query{?dns}").is_err());
+        assert!(query_path("/dns-query{?dns").is_err());
+        assert!(query_path("/dns-query}").is_err());
     }
 }
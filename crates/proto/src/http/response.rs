@@ -8,7 +8,7 @@
 //! HTTP request creation and validation
 
 use http::header::{CONTENT_LENGTH, CONTENT_TYPE};
-use http::{Response, StatusCode};
+use http::{HeaderMap, Response, StatusCode};
 
 use crate::error::ProtoError;
 use crate::http::error::Result;
@@ -48,3 +48,50 @@ pub fn new(version: Version, message_len: usize) -> Result<Response<()>> {
         .body(())
         .map_err(|e| ProtoError::from(format!("invalid response: {e}")).into())
 }
+
+/// Verifies the content type of a successful response is `application/dns-message`
+///
+/// Parameters of the media type, e.g. `; charset=...`, are ignored, and a response without a
+///  content type is assumed to be in the standard DNS format.
+pub fn verify_content_type(headers: &HeaderMap) -> Result<()> {
+    let content_type = match headers.get(CONTENT_TYPE) {
+        Some(content_type) => content_type.to_str()?,
+        None => return Ok(()),
+    };
+
+    let media_type = content_type.split(';').next().unwrap_or_default().trim();
+    if !media_type.eq_ignore_ascii_case(crate::http::MIME_APPLICATION_DNS) {
+        return Err(format!(
+            "ContentType unsupported (must be '{}'): '{}'",
+            crate::http::MIME_APPLICATION_DNS,
+            content_type
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_verify_content_type() {
+        let verify = |content_type: Option<&'static str>| {
+            let mut headers = HeaderMap::new();
+            if let Some(content_type) = content_type {
+                headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+            }
+            verify_content_type(&headers).is_ok()
+        };
+
+        assert!(verify(None));
+        assert!(verify(Some("application/dns-message")));
+        assert!(verify(Some("Application/DNS-Message; charset=binary")));
+        assert!(!verify(Some("text/html")));
+        assert!(!verify(Some("application/dns-message-json")));
+    }
+}
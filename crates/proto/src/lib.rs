@@ -61,7 +61,18 @@ pub fn spawn_bg<F: Future<Output = R> + Send + 'static, R: Send + 'static>(
     runtime.spawn(background)
 }
 
+#[cfg(any(test, feature = "testing"))]
+#[cfg_attr(docsrs, doc(cfg(feature = "testing")))]
+pub mod chaos;
+pub mod clock;
+#[cfg(feature = "embedded")]
+#[cfg_attr(docsrs, doc(cfg(feature = "embedded")))]
+pub mod embedded;
+pub mod entropy;
 pub mod error;
+#[cfg(feature = "fuzzing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "fuzzing")))]
+pub mod fuzzing;
 #[cfg(feature = "dns-over-https")]
 #[cfg_attr(docsrs, doc(cfg(feature = "dns-over-https")))]
 pub mod h2;
@@ -74,6 +85,7 @@ pub mod h3;
     doc(cfg(any(feature = "dns-over-https", feature = "dns-over-h3")))
 )]
 pub mod http;
+mod logging;
 #[cfg(feature = "mdns")]
 #[cfg_attr(docsrs, doc(cfg(feature = "mdns")))]
 pub mod multicast;
@@ -95,11 +107,26 @@ pub mod rr;
 #[cfg_attr(docsrs, doc(cfg(feature = "dns-over-rustls")))]
 pub mod rustls;
 pub mod serialize;
+#[cfg(all(any(test, feature = "testing"), feature = "tokio-runtime"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "testing", feature = "tokio-runtime"))))]
+pub mod sim;
+#[cfg(feature = "smol-runtime")]
+#[cfg_attr(docsrs, doc(cfg(feature = "smol-runtime")))]
+pub mod smol_runtime;
+#[cfg(feature = "smoltcp")]
+#[cfg_attr(docsrs, doc(cfg(feature = "smoltcp")))]
+pub mod smoltcp_socket;
 pub mod tcp;
 #[cfg(any(test, feature = "testing"))]
 #[cfg_attr(docsrs, doc(cfg(feature = "testing")))]
 pub mod tests;
 pub mod udp;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "io-uring", target_os = "linux"))))]
+pub mod uring;
+#[cfg(feature = "wasm-bindgen")]
+#[cfg_attr(docsrs, doc(cfg(feature = "wasm-bindgen")))]
+pub mod wasm;
 pub mod xfer;
 
 #[doc(hidden)]
@@ -0,0 +1,88 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! The diagnostics of the crate, emitted with `tracing`, the `log` facade or `defmt`
+//!
+//! With the `tracing` feature the events go to `tracing`, otherwise with the `log` feature to the
+//!  `log` facade, e.g. for loggers writing to a serial port, otherwise with the `defmt` feature to
+//!  `defmt`. Only one of them is used. Without any of them the diagnostics are compiled out, the
+//!  arguments are only type checked.
+
+// which of the macros are used depends on the features
+#[cfg(feature = "tracing")]
+#[allow(unused_imports)]
+pub(crate) use tracing::{debug, error, info, trace, warn};
+
+#[cfg(all(feature = "log", not(feature = "tracing")))]
+#[allow(unused_imports)]
+pub(crate) use log::{debug, error, info, trace, warn};
+
+#[cfg(all(feature = "defmt", not(any(feature = "log", feature = "tracing"))))]
+#[allow(unused_macros)]
+mod defmt {
+    // defmt only takes its own format strings, the messages are formatted before they are logged
+    macro_rules! defmt_debug {
+        ($($arg:tt)+) => {
+            ::defmt::debug!("{=str}", ::std::format!($($arg)+).as_str())
+        };
+    }
+
+    macro_rules! defmt_error {
+        ($($arg:tt)+) => {
+            ::defmt::error!("{=str}", ::std::format!($($arg)+).as_str())
+        };
+    }
+
+    macro_rules! defmt_info {
+        ($($arg:tt)+) => {
+            ::defmt::info!("{=str}", ::std::format!($($arg)+).as_str())
+        };
+    }
+
+    macro_rules! defmt_trace {
+        ($($arg:tt)+) => {
+            ::defmt::trace!("{=str}", ::std::format!($($arg)+).as_str())
+        };
+    }
+
+    macro_rules! defmt_warn {
+        ($($arg:tt)+) => {
+            ::defmt::warn!("{=str}", ::std::format!($($arg)+).as_str())
+        };
+    }
+
+    pub(crate) use defmt_debug as debug;
+    pub(crate) use defmt_error as error;
+    pub(crate) use defmt_info as info;
+    pub(crate) use defmt_trace as trace;
+    pub(crate) use defmt_warn as warn;
+}
+
+#[cfg(all(feature = "defmt", not(any(feature = "log", feature = "tracing"))))]
+#[allow(unused_imports)]
+pub(crate) use self::defmt::{debug, error, info, trace, warn};
+
+#[cfg(not(any(feature = "defmt", feature = "log", feature = "tracing")))]
+mod disabled {
+    macro_rules! disabled {
+        ($($arg:tt)+) => {{
+            if false {
+                let _ = format_args!($($arg)+);
+            }
+        }};
+    }
+
+    pub(crate) use disabled as debug;
+    pub(crate) use disabled as error;
+    pub(crate) use disabled as info;
+    pub(crate) use disabled as trace;
+    pub(crate) use disabled as warn;
+}
+
+#[cfg(not(any(feature = "defmt", feature = "log", feature = "tracing")))]
+#[allow(unused_imports)]
+pub(crate) use disabled::{debug, error, info, trace, warn};
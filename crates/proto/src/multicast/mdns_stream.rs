@@ -12,6 +12,7 @@ use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 
+use crate::logging::{debug, trace};
 use futures_util::stream::{Stream, StreamExt};
 use futures_util::{future, future::Future, ready, FutureExt, TryFutureExt};
 use once_cell::sync::Lazy;
@@ -19,7 +20,6 @@ use rand;
 use rand::distributions::{uniform::Uniform, Distribution};
 use socket2::{self, Socket};
 use tokio::net::UdpSocket;
-use tracing::{debug, trace};
 
 use crate::multicast::MdnsQueryType;
 use crate::udp::UdpStream;
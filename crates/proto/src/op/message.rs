@@ -9,7 +9,7 @@
 
 use std::{fmt, iter, mem, ops::Deref, sync::Arc};
 
-use tracing::{debug, warn};
+use crate::logging::{debug, warn};
 
 use crate::{
     error::*,
@@ -19,6 +19,9 @@ use crate::{
     xfer::DnsResponse,
 };
 
+#[cfg(feature = "dnssec")]
+use crate::rr::{dnssec::rdata::DNSSECRData, RData};
+
 /// The basic request and response data structure, used for all DNS protocols.
 ///
 /// [RFC 1035, DOMAIN NAMES - IMPLEMENTATION AND SPECIFICATION, November 1987](https://tools.ietf.org/html/rfc1035)
@@ -491,6 +494,22 @@ impl Message {
         mem::take(&mut self.answers)
     }
 
+    /// Returns true if any answer was synthesized from a wildcard, as shown by the labels of its
+    ///  RRSIG, see `SIG::is_wildcard_expansion`
+    ///
+    /// RRSIGs are only sent in response to requests with the DNSSEC OK bit, and the answers are only
+    ///  authentic once validated, e.g. by the `DnssecDnsHandle`.
+    #[cfg(feature = "dnssec")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "dnssec")))]
+    pub fn has_wildcard_answer(&self) -> bool {
+        self.answers.iter().any(|record| match record.data() {
+            Some(RData::DNSSEC(DNSSECRData::RRSIG(rrsig))) => {
+                rrsig.is_wildcard_expansion(record.name())
+            }
+            _ => false,
+        })
+    }
+
     /// ```text
     /// Authority       Carries RRs which describe other authoritative servers.
     ///                 May optionally carry the SOA RR for the authoritative
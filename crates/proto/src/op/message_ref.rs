@@ -0,0 +1,678 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Borrowed views of messages, which are decoded without allocating
+
+use std::fmt::{self, Display};
+
+use crate::{
+    error::{ProtoError, ProtoResult},
+    op::{Edns, Header, Query},
+    rr::{DNSClass, Name, Record, RecordType},
+    serialize::binary::{BinDecodable, BinDecoder, BinEncodable, BinEncoder, DecodeError},
+};
+
+/// A borrowed view of a message, all sections are validated but nothing is copied
+///
+/// This is useful for servers which need to inspect or answer a large number of requests, where
+///  decoding each request into an owned [`crate::op::Message`] would dominate the cost of
+///  answering it. Owned values can still be retrieved for any part of the message, e.g. with
+///  [`ResourceRef::to_record`].
+#[derive(Clone, Copy, Debug)]
+pub struct MessageRef<'a> {
+    buffer: &'a [u8],
+    header: Header,
+    answers: usize,
+    name_servers: usize,
+    additionals: usize,
+}
+
+impl<'a> MessageRef<'a> {
+    /// Returns the header of the message
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Returns the id of the message
+    pub fn id(&self) -> u16 {
+        self.header.id()
+    }
+
+    /// Returns the queries of the message
+    pub fn queries(&self) -> SectionRef<'a, QueryRef<'a>> {
+        SectionRef::new(self.buffer, Header::len(), self.header.query_count())
+    }
+
+    /// Returns the answers of the message
+    pub fn answers(&self) -> SectionRef<'a, ResourceRef<'a>> {
+        SectionRef::new(self.buffer, self.answers, self.header.answer_count())
+    }
+
+    /// Returns the name servers of the message
+    pub fn name_servers(&self) -> SectionRef<'a, ResourceRef<'a>> {
+        SectionRef::new(
+            self.buffer,
+            self.name_servers,
+            self.header.name_server_count(),
+        )
+    }
+
+    /// Returns the additionals of the message, including the EDNS record
+    pub fn additionals(&self) -> SectionRef<'a, ResourceRef<'a>> {
+        SectionRef::new(
+            self.buffer,
+            self.additionals,
+            self.header.additional_count(),
+        )
+    }
+
+    /// Returns the raw bytes of the query section, as received
+    pub fn queries_bytes(&self) -> &'a [u8] {
+        &self.buffer[Header::len()..self.answers]
+    }
+
+    /// Returns the EDNS record of the message, if any
+    pub fn edns(&self) -> Option<ResourceRef<'a>> {
+        self.additionals()
+            .find(|record| record.record_type() == RecordType::OPT)
+    }
+
+    /// Returns the maximum payload size the sender accepts for the response, 512 without EDNS
+    pub fn max_payload(&self) -> u16 {
+        self.edns()
+            .map_or(512, |edns| u16::from(DNSClass::for_opt(edns.raw_class())))
+    }
+
+    /// Returns true if the sender has set the DNSSEC OK flag in the EDNS record
+    pub fn dnssec_ok(&self) -> bool {
+        self.edns()
+            .map_or(false, |edns| edns.ttl() & 0x0000_8000 == 0x0000_8000)
+    }
+
+    /// Serializes a response to this message
+    ///
+    /// The query section is copied from the request, followed by the answers. If the request
+    ///  contained an EDNS record, an EDNS record is added to the response with the same maximum
+    ///  payload and DNSSEC OK flag.
+    ///
+    /// # Arguments
+    ///
+    /// * `header` - the header of the response, e.g. from `Header::response_from_request`, the
+    ///   counts of the sections are set by this method
+    /// * `answers` - records for the answer section
+    pub fn to_response(&self, header: &Header, answers: &[Record]) -> ProtoResult<Vec<u8>> {
+        let edns = self.edns().map(|_| {
+            let mut edns = Edns::new();
+            edns.set_max_payload(self.max_payload())
+                .set_dnssec_ok(self.dnssec_ok());
+            edns
+        });
+
+        self.to_response_with_edns(header, answers, edns.as_ref())
+    }
+
+    /// Serializes a response to this message with the given EDNS record, see `to_response`
+    ///
+    /// The high bits of the response code of the header are set in the EDNS record.
+    pub fn to_response_with_edns(
+        &self,
+        header: &Header,
+        answers: &[Record],
+        edns: Option<&Edns>,
+    ) -> ProtoResult<Vec<u8>> {
+        let edns = edns.map(|edns| {
+            let mut edns = edns.clone();
+            edns.set_rcode_high(header.response_code().high());
+            Record::from(&edns)
+        });
+
+        let mut header = *header;
+        header
+            .set_query_count(self.header.query_count())
+            .set_answer_count(answers.len() as u16)
+            .set_name_server_count(0)
+            .set_additional_count(u16::from(edns.is_some()));
+
+        let mut buffer = Vec::with_capacity(512);
+        {
+            let mut encoder = BinEncoder::new(&mut buffer);
+            header.emit(&mut encoder)?;
+            // any pointers in the query section stay valid, as it starts right after the header
+            encoder.emit_vec(self.queries_bytes())?;
+            for answer in answers.iter().chain(edns.iter()) {
+                answer.emit(&mut encoder)?;
+            }
+        }
+
+        Ok(buffer)
+    }
+}
+
+impl<'a> BinDecodable<'a> for MessageRef<'a> {
+    /// Reads a message, the decoder must be positioned at the start of the message
+    fn read(decoder: &mut BinDecoder<'a>) -> ProtoResult<Self> {
+        let header = Header::read(decoder)?;
+
+        for _ in 0..header.query_count() {
+            QueryRef::read(decoder)?;
+        }
+        let answers = decoder.index();
+
+        for _ in 0..header.answer_count() {
+            ResourceRef::read(decoder)?;
+        }
+        let name_servers = decoder.index();
+
+        for _ in 0..header.name_server_count() {
+            ResourceRef::read(decoder)?;
+        }
+        let additionals = decoder.index();
+
+        for _ in 0..header.additional_count() {
+            ResourceRef::read(decoder)?;
+        }
+
+        // the sections are revisited with BinDecoder::clone, which is limited to u16 offsets
+        let buffer = decoder.slice_from(0)?;
+        if buffer.len() > u16::MAX as usize {
+            return Err(ProtoError::from(format!(
+                "message too long: {} bytes",
+                buffer.len()
+            )));
+        }
+
+        Ok(Self {
+            buffer,
+            header,
+            answers,
+            name_servers,
+            additionals,
+        })
+    }
+}
+
+/// Iterator over a section of a [`MessageRef`]
+#[derive(Clone)]
+pub struct SectionRef<'a, T> {
+    buffer: &'a [u8],
+    index: usize,
+    remaining: u16,
+    item: std::marker::PhantomData<T>,
+}
+
+impl<'a, T> SectionRef<'a, T> {
+    fn new(buffer: &'a [u8], start: usize, count: u16) -> Self {
+        Self {
+            buffer,
+            index: start,
+            remaining: count,
+            item: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<'a, T: BinDecodable<'a>> Iterator for SectionRef<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        // the section was already validated when the message was read
+        let mut decoder = BinDecoder::new(self.buffer).clone(self.index as u16);
+        let item = T::read(&mut decoder).ok()?;
+        self.index = decoder.index();
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining as usize))
+    }
+}
+
+/// A borrowed query, see [`Query`]
+#[derive(Clone, Copy, Debug)]
+pub struct QueryRef<'a> {
+    name: NameRef<'a>,
+    query_type: RecordType,
+    query_class: DNSClass,
+}
+
+impl<'a> QueryRef<'a> {
+    /// Returns the name of the query
+    pub fn name(&self) -> NameRef<'a> {
+        self.name
+    }
+
+    /// Returns the type of the query
+    pub fn query_type(&self) -> RecordType {
+        self.query_type
+    }
+
+    /// Returns the class of the query
+    pub fn query_class(&self) -> DNSClass {
+        self.query_class
+    }
+
+    /// Returns an owned copy of the query
+    pub fn to_query(&self) -> ProtoResult<Query> {
+        let mut query = Query::query(self.name.to_name()?, self.query_type);
+        query.set_query_class(self.query_class);
+        Ok(query)
+    }
+}
+
+impl<'a> BinDecodable<'a> for QueryRef<'a> {
+    fn read(decoder: &mut BinDecoder<'a>) -> ProtoResult<Self> {
+        let name = NameRef::read(decoder)?;
+        let query_type = RecordType::read(decoder)?;
+        let query_class = decoder.read_u16()?.unverified(/*any class is valid*/);
+
+        #[cfg(feature = "mdns")]
+        let query_class = query_class & !super::query::MDNS_UNICAST_RESPONSE;
+
+        Ok(Self {
+            name,
+            query_type,
+            query_class: DNSClass::from(query_class),
+        })
+    }
+}
+
+impl Display for QueryRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{name} {class} {ty}",
+            name = self.name,
+            class = self.query_class,
+            ty = self.query_type,
+        )
+    }
+}
+
+/// A borrowed resource record, the rdata is left in its wire format
+#[derive(Clone, Copy, Debug)]
+pub struct ResourceRef<'a> {
+    message: &'a [u8],
+    start: usize,
+    name: NameRef<'a>,
+    record_type: RecordType,
+    class: u16,
+    ttl: u32,
+    rdata: &'a [u8],
+}
+
+impl<'a> ResourceRef<'a> {
+    /// Returns the name of the record
+    pub fn name(&self) -> NameRef<'a> {
+        self.name
+    }
+
+    /// Returns the type of the record
+    pub fn record_type(&self) -> RecordType {
+        self.record_type
+    }
+
+    /// Returns the class field of the record, which is the maximum payload for OPT records
+    pub fn raw_class(&self) -> u16 {
+        self.class
+    }
+
+    /// Returns the time to live of the record, which holds the EDNS flags for OPT records
+    pub fn ttl(&self) -> u32 {
+        self.ttl
+    }
+
+    /// Returns the rdata in wire format
+    ///
+    /// Names in the rdata may be compressed, i.e. point to other parts of the message.
+    pub fn rdata(&self) -> &'a [u8] {
+        self.rdata
+    }
+
+    /// Decodes an owned copy of the record
+    pub fn to_record(&self) -> ProtoResult<Record> {
+        let mut decoder = BinDecoder::new(self.message).clone(self.start as u16);
+        Record::read(&mut decoder)
+    }
+}
+
+impl<'a> BinDecodable<'a> for ResourceRef<'a> {
+    fn read(decoder: &mut BinDecoder<'a>) -> ProtoResult<Self> {
+        let start = decoder.index();
+        let name = NameRef::read(decoder)?;
+        let record_type = RecordType::read(decoder)?;
+        let class = decoder.read_u16()?.unverified(/*interpretation depends on the type*/);
+        let ttl = decoder.read_u32()?.unverified(/*any ttl is valid*/);
+        let rd_length = decoder.read_u16()?.unverified(/*bounded by read_slice*/);
+        let rdata = decoder
+            .read_slice(rd_length as usize)?
+            .unverified(/*rdata is only interpreted by to_record*/);
+
+        Ok(Self {
+            message: decoder.slice_from(0)?,
+            start,
+            name,
+            record_type,
+            class,
+            ttl,
+            rdata,
+        })
+    }
+}
+
+/// A borrowed domain name, which may be compressed
+///
+/// The labels are only validated when read, see [`Name`] for an owned name.
+#[derive(Clone, Copy)]
+pub struct NameRef<'a> {
+    message: &'a [u8],
+    start: usize,
+}
+
+impl<'a> NameRef<'a> {
+    /// Returns an iterator over the labels of the name, following any pointers
+    pub fn labels(&self) -> LabelsRef<'a> {
+        LabelsRef {
+            message: self.message,
+            index: self.start,
+        }
+    }
+
+    /// Returns the number of labels in the name, the root has 0 labels
+    pub fn num_labels(&self) -> u8 {
+        self.labels().count() as u8
+    }
+
+    /// Returns true if this is the root name
+    pub fn is_root(&self) -> bool {
+        self.labels().next().is_none()
+    }
+
+    /// Returns an owned copy of the name
+    pub fn to_name(&self) -> ProtoResult<Name> {
+        Name::from_labels(self.labels())
+    }
+}
+
+impl<'a> BinDecodable<'a> for NameRef<'a> {
+    fn read(decoder: &mut BinDecoder<'a>) -> ProtoResult<Self> {
+        let start = decoder.index();
+        let mut len = 0;
+        validate_name(decoder, &mut len, None)?;
+
+        Ok(Self {
+            message: decoder.slice_from(0)?,
+            start,
+        })
+    }
+}
+
+/// Validates the name at the decoder with the same rules as `Name::read`, without copying it
+///
+/// `len` is the length of the labels read so far, where '.' counts as 1, see `Name::len`.
+fn validate_name(
+    decoder: &mut BinDecoder<'_>,
+    len: &mut usize,
+    max_idx: Option<usize>,
+) -> Result<(), DecodeError> {
+    let name_start = decoder.index();
+
+    loop {
+        // this protects against overlapping labels
+        if let Some(max_idx) = max_idx {
+            if decoder.index() >= max_idx {
+                return Err(DecodeError::LabelOverlapsWithOther {
+                    label: name_start,
+                    other: max_idx,
+                });
+            }
+        }
+
+        match decoder
+            .peek()
+            .map(|byte| byte.unverified(/*checked below*/))
+        {
+            Some(0) | None => {
+                decoder.pop()?;
+                break;
+            }
+            Some(byte) if byte & 0b1100_0000 == 0b1100_0000 => {
+                let pointer_location = decoder.index();
+                let location = decoder
+                    .read_u16()?
+                    .map(|u| u & 0x3FFF)
+                    .verify_unwrap(|ptr| (*ptr as usize) < name_start)
+                    .map_err(|e| DecodeError::PointerNotPriorToLabel {
+                        idx: pointer_location,
+                        ptr: e,
+                    })?;
+
+                let mut pointer = decoder.clone(location);
+                validate_name(&mut pointer, len, Some(name_start))?;
+                break;
+            }
+            Some(byte) if byte & 0b1100_0000 == 0b0000_0000 => {
+                let label = decoder
+                    .read_character_data()?
+                    .verify_unwrap(|l| l.len() <= 63)
+                    .map_err(|l| DecodeError::LabelBytesTooLong(l.len()))?;
+
+                *len += label.len() + 1;
+                if *len > 255 {
+                    return Err(DecodeError::DomainNameTooLong(label.len()));
+                }
+            }
+            Some(byte) => return Err(DecodeError::UnrecognizedLabelCode(byte)),
+        }
+    }
+
+    if (*len).max(1) >= 255 {
+        return Err(DecodeError::DomainNameTooLong(*len));
+    }
+
+    Ok(())
+}
+
+impl PartialEq<Name> for NameRef<'_> {
+    /// Case insensitive comparison, see [`Name::cmp_case`]
+    fn eq(&self, other: &Name) -> bool {
+        let mut labels = self.labels();
+        let mut other = other.iter();
+
+        loop {
+            match (labels.next(), other.next()) {
+                (Some(label), Some(other)) if label.eq_ignore_ascii_case(other) => continue,
+                (None, None) => return true,
+                _ => return false,
+            }
+        }
+    }
+}
+
+impl fmt::Debug for NameRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NameRef(\"{self}\")")
+    }
+}
+
+impl Display for NameRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.to_name().map_err(|_| fmt::Error)?;
+        write!(f, "{name}")
+    }
+}
+
+/// Iterator over the labels of a [`NameRef`]
+#[derive(Clone)]
+pub struct LabelsRef<'a> {
+    message: &'a [u8],
+    index: usize,
+}
+
+impl<'a> Iterator for LabelsRef<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // the name was validated when it was read, all pointers point to prior labels
+        loop {
+            let byte = *self.message.get(self.index)?;
+            if byte & 0b1100_0000 == 0b1100_0000 {
+                let low = *self.message.get(self.index + 1)?;
+                self.index = (usize::from(byte & 0b0011_1111) << 8) | usize::from(low);
+                continue;
+            }
+
+            if byte == 0 {
+                return None;
+            }
+
+            let start = self.index + 1;
+            let end = start + byte as usize;
+            self.index = end;
+            return self.message.get(start..end);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{
+        op::{Message, MessageType, ResponseCode},
+        rr::{rdata::A, RData},
+    };
+
+    fn request() -> Vec<u8> {
+        let mut message = Message::new();
+        message
+            .set_id(42)
+            .set_message_type(MessageType::Query)
+            .add_query(Query::query(
+                Name::from_str("www.Example.com.").unwrap(),
+                RecordType::A,
+            ))
+            .add_answer(Record::from_rdata(
+                Name::from_str("www.example.com.").unwrap(),
+                300,
+                RData::A(A::new(192, 0, 2, 1)),
+            ));
+
+        let mut edns = Edns::new();
+        edns.set_max_payload(1232).set_dnssec_ok(true);
+        message.set_edns(edns);
+
+        message.to_vec().unwrap()
+    }
+
+    #[test]
+    fn test_read() {
+        let bytes = request();
+        let message = MessageRef::read(&mut BinDecoder::new(&bytes)).unwrap();
+
+        assert_eq!(message.id(), 42);
+        assert_eq!(message.header().message_type(), MessageType::Query);
+
+        let queries = message.queries().collect::<Vec<_>>();
+        assert_eq!(queries.len(), 1);
+        let query = queries[0];
+        assert_eq!(query.name(), Name::from_str("www.example.com.").unwrap());
+        assert_ne!(query.name(), Name::from_str("example.com.").unwrap());
+        assert_eq!(query.name().num_labels(), 3);
+        assert_eq!(
+            query.name().to_name().unwrap().to_string(),
+            Message::from_vec(&bytes).unwrap().queries()[0]
+                .name()
+                .to_string()
+        );
+        assert_eq!(query.query_type(), RecordType::A);
+        assert_eq!(query.query_class(), DNSClass::IN);
+
+        // the answer is compressed, pointing to the query name
+        let answers = message.answers().collect::<Vec<_>>();
+        assert_eq!(answers.len(), 1);
+        assert_eq!(
+            answers[0].name(),
+            Name::from_str("www.example.com.").unwrap()
+        );
+        assert_eq!(answers[0].rdata(), &[192, 0, 2, 1]);
+        assert_eq!(
+            answers[0].to_record().unwrap().data(),
+            Some(&RData::A(A::new(192, 0, 2, 1)))
+        );
+
+        assert_eq!(message.name_servers().count(), 0);
+        assert!(message.edns().unwrap().name().is_root());
+        assert_eq!(message.max_payload(), 1232);
+        assert!(message.dnssec_ok());
+    }
+
+    #[test]
+    fn test_to_response() {
+        let bytes = request();
+        let request = MessageRef::read(&mut BinDecoder::new(&bytes)).unwrap();
+
+        let mut header = Header::response_from_request(request.header());
+        header.set_response_code(ResponseCode::NXDomain);
+        let answers = [Record::from_rdata(
+            Name::from_str("www.example.com.").unwrap(),
+            60,
+            RData::A(A::new(192, 0, 2, 2)),
+        )];
+
+        let response = request.to_response(&header, &answers).unwrap();
+        let response = Message::from_vec(&response).unwrap();
+
+        assert_eq!(response.id(), 42);
+        assert_eq!(response.message_type(), MessageType::Response);
+        assert_eq!(response.response_code(), ResponseCode::NXDomain);
+        assert_eq!(
+            response.queries(),
+            &[request.queries().next().unwrap().to_query().unwrap()]
+        );
+        assert_eq!(response.answers(), &answers);
+        assert_eq!(response.max_payload(), 1232);
+        assert!(response.extensions().as_ref().unwrap().dnssec_ok());
+    }
+
+    #[test]
+    fn test_read_invalid() {
+        // header with a single query
+        let header = [0, 42, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+
+        // pointer to itself
+        let mut bytes = header.to_vec();
+        bytes.extend_from_slice(&[0xC0, 12, 0, 1, 0, 1]);
+        assert!(MessageRef::read(&mut BinDecoder::new(&bytes)).is_err());
+
+        // label too long
+        let mut bytes = header.to_vec();
+        bytes.push(64);
+        bytes.extend_from_slice(&[b'a'; 64]);
+        bytes.extend_from_slice(&[0, 0, 1, 0, 1]);
+        assert!(MessageRef::read(&mut BinDecoder::new(&bytes)).is_err());
+
+        // name too long
+        let mut bytes = header.to_vec();
+        for _ in 0..5 {
+            bytes.push(63);
+            bytes.extend_from_slice(&[b'a'; 63]);
+        }
+        bytes.extend_from_slice(&[0, 0, 1, 0, 1]);
+        assert!(MessageRef::read(&mut BinDecoder::new(&bytes)).is_err());
+
+        // truncated
+        let mut bytes = header.to_vec();
+        bytes.extend_from_slice(&[3, b'w', b'w', b'w', 0, 0, 1]);
+        assert!(MessageRef::read(&mut BinDecoder::new(&bytes)).is_err());
+    }
+}
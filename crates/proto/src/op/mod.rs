@@ -21,6 +21,7 @@ mod edns;
 pub mod header;
 mod lower_query;
 pub mod message;
+mod message_ref;
 pub mod op_code;
 pub mod query;
 pub mod response_code;
@@ -32,6 +33,7 @@ pub use self::header::MessageType;
 pub use self::message::{
     Message, MessageFinalizer, MessageParts, MessageVerifier, NoopMessageFinalizer,
 };
+pub use self::message_ref::{LabelsRef, MessageRef, NameRef, QueryRef, ResourceRef, SectionRef};
 pub use self::op_code::OpCode;
 pub use self::query::Query;
 pub use self::response_code::ResponseCode;
@@ -42,6 +42,9 @@ pub enum OpCode {
 
     /// Update message [RFC 2136](https://tools.ietf.org/html/rfc2136)
     Update,
+
+    /// DNS Stateful Operations [RFC 8490](https://tools.ietf.org/html/rfc8490)
+    Dso,
 }
 
 impl fmt::Display for OpCode {
@@ -51,6 +54,7 @@ impl fmt::Display for OpCode {
             Self::Status => "STATUS",
             Self::Notify => "NOTIFY",
             Self::Update => "UPDATE",
+            Self::Dso => "DSO",
         };
 
         f.write_str(s)
@@ -77,7 +81,8 @@ impl From<OpCode> for u8 {
             // 3	Unassigned
             OpCode::Notify => 4,
             OpCode::Update => 5,
-            // 6-15	Unassigned
+            OpCode::Dso => 6,
+            // 7-15	Unassigned
         }
     }
 }
@@ -98,6 +103,7 @@ impl OpCode {
             2 => Ok(Self::Status),
             4 => Ok(Self::Notify),
             5 => Ok(Self::Update),
+            6 => Ok(Self::Dso),
             _ => Err(format!("unknown OpCode: {value}").into()),
         }
     }
@@ -8,7 +8,11 @@
 use std::{io, net::SocketAddr, sync::Arc};
 
 use quinn::{Connection, Endpoint, ServerConfig};
-use rustls::{server::ServerConfig as TlsServerConfig, version::TLS13, Certificate, PrivateKey};
+use rustls::{
+    server::{ResolvesServerCert, ServerConfig as TlsServerConfig, WantsServerCert},
+    version::TLS13,
+    Certificate, ConfigBuilder, PrivateKey,
+};
 
 use crate::{error::ProtoError, udp::UdpSocket};
 
@@ -17,6 +21,16 @@ use super::{
     quic_stream::{self, QuicStream},
 };
 
+/// The TLS 1.3 configuration of servers, without the certificate
+fn tls_config() -> ConfigBuilder<TlsServerConfig, WantsServerCert> {
+    TlsServerConfig::builder()
+        .with_safe_default_cipher_suites()
+        .with_safe_default_kx_groups()
+        .with_protocol_versions(&[&TLS13])
+        .expect("TLS1.3 not supported")
+        .with_no_client_auth()
+}
+
 /// A DNS-over-QUIC Server, see QuicClientStream for the client counterpart
 pub struct QuicServer {
     endpoint: Endpoint,
@@ -40,14 +54,24 @@ impl QuicServer {
         cert: Vec<Certificate>,
         key: PrivateKey,
     ) -> Result<Self, ProtoError> {
-        let mut config = TlsServerConfig::builder()
-            .with_safe_default_cipher_suites()
-            .with_safe_default_kx_groups()
-            .with_protocol_versions(&[&TLS13])
-            .expect("TLS1.3 not supported")
-            .with_no_client_auth()
-            .with_single_cert(cert, key)?;
+        let config = tls_config().with_single_cert(cert, key)?;
+        Self::with_socket_and_tls_config(socket, config)
+    }
+
+    /// Construct the new server with an existing socket, which selects the certificate with the
+    ///  resolver, e.g. by the SNI of the client
+    pub fn with_socket_and_cert_resolver(
+        socket: tokio::net::UdpSocket,
+        resolver: Arc<dyn ResolvesServerCert>,
+    ) -> Result<Self, ProtoError> {
+        let config = tls_config().with_cert_resolver(resolver);
+        Self::with_socket_and_tls_config(socket, config)
+    }
 
+    fn with_socket_and_tls_config(
+        socket: tokio::net::UdpSocket,
+        mut config: TlsServerConfig,
+    ) -> Result<Self, ProtoError> {
         config.alpn_protocols = vec![quic_stream::DOQ_ALPN.to_vec()];
 
         let mut server_config = ServerConfig::with_crypto(Arc::new(config));
@@ -5,9 +5,9 @@
 // https://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
+use crate::logging::debug;
 use bytes::{Bytes, BytesMut};
 use quinn::{RecvStream, SendStream, VarInt};
-use tracing::debug;
 
 use crate::{
     error::{ProtoError, ProtoErrorKind},
@@ -0,0 +1,511 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Key files of BIND, `K<name>+<algorithm>+<key tag>.key` and `.private`
+//!
+//! The `.key` file holds the DNSKEY record, the `.private` file the private key in the
+//!  `Private-key-format: v1.3` of `dnssec-keygen`. The private key of ECDSA and ED25519 keys does
+//!  not include the public key, which is read from the DNSKEY.
+
+use std::fmt::{self, Write};
+use std::str::FromStr;
+
+#[cfg(feature = "openssl")]
+use openssl::{bn::BigNum, rsa::Rsa};
+#[cfg(all(feature = "openssl", not(feature = "ring")))]
+use openssl::{
+    bn::BigNumContext,
+    ec::{EcGroup, EcKey, EcPoint},
+    nid::Nid,
+};
+#[cfg(feature = "ring")]
+use ring::{
+    rand::SystemRandom,
+    signature::{
+        EcdsaKeyPair, Ed25519KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING,
+        ECDSA_P384_SHA384_FIXED_SIGNING,
+    },
+};
+
+use crate::error::{DnsSecError, DnsSecResult};
+use crate::rr::dnssec::rdata::DNSKEY;
+use crate::rr::dnssec::{Algorithm, KeyPair, Private};
+use crate::rr::Name;
+
+/// The fields of RSA private keys, in the order of the file
+const RSA_FIELDS: [&str; 8] = [
+    "Modulus",
+    "PublicExponent",
+    "PrivateExponent",
+    "Prime1",
+    "Prime2",
+    "Exponent1",
+    "Exponent2",
+    "Coefficient",
+];
+
+/// The field of ECDSA and ED25519 private keys
+const PRIVATE_KEY: &str = "PrivateKey";
+
+/// A private key in the format of BIND's `.private` files
+#[derive(Clone, PartialEq, Eq)]
+pub struct BindPrivateKey {
+    algorithm: Algorithm,
+    fields: Vec<(String, Vec<u8>)>,
+}
+
+impl BindPrivateKey {
+    /// Reads the private key from a PKCS#8 document, as generated by `KeyPair::generate_pkcs8`
+    ///
+    /// Only ECDSA and ED25519 keys are supported.
+    pub fn from_pkcs8(algorithm: Algorithm, pkcs8: &[u8]) -> DnsSecResult<Self> {
+        // PrivateKeyInfo ::= SEQUENCE { version, algorithm, OCTET STRING privateKey, .. }
+        let (info, _) = der(pkcs8, SEQUENCE)?;
+        let (_, rest) = der(info, INTEGER)?;
+        let (_, rest) = der(rest, SEQUENCE)?;
+        let (private_key, _) = der(rest, OCTET_STRING)?;
+
+        let private_key = match algorithm {
+            // CurvePrivateKey ::= OCTET STRING, RFC 8410
+            Algorithm::ED25519 => der(private_key, OCTET_STRING)?.0,
+            // ECPrivateKey ::= SEQUENCE { version, OCTET STRING privateKey, .. }, RFC 5915
+            Algorithm::ECDSAP256SHA256 | Algorithm::ECDSAP384SHA384 => {
+                let (ec_private_key, _) = der(private_key, SEQUENCE)?;
+                let (_, rest) = der(ec_private_key, INTEGER)?;
+                der(rest, OCTET_STRING)?.0
+            }
+            _ => return Err(format!("unsupported algorithm for PKCS#8: {algorithm:?}").into()),
+        };
+
+        Ok(Self {
+            algorithm,
+            fields: vec![(PRIVATE_KEY.to_string(), private_key.to_vec())],
+        })
+    }
+
+    /// Reads the private key from an RSA or EC key pair of OpenSSL
+    #[cfg(feature = "openssl")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "openssl")))]
+    pub fn from_key_pair(algorithm: Algorithm, key: &KeyPair<Private>) -> DnsSecResult<Self> {
+        let fields = match key {
+            KeyPair::RSA(pkey) => {
+                let rsa = pkey.rsa()?;
+                let components = [
+                    Some(rsa.n()),
+                    Some(rsa.e()),
+                    Some(rsa.d()),
+                    rsa.p(),
+                    rsa.q(),
+                    rsa.dmp1(),
+                    rsa.dmq1(),
+                    rsa.iqmp(),
+                ];
+
+                RSA_FIELDS
+                    .iter()
+                    .zip(components)
+                    .map(|(field, component)| {
+                        let component = component
+                            .ok_or_else(|| format!("the RSA key has no {field}"))?
+                            .to_vec();
+                        Ok((field.to_string(), component))
+                    })
+                    .collect::<DnsSecResult<Vec<_>>>()?
+            }
+            KeyPair::EC(pkey) => {
+                let ec_key = pkey.ec_key()?;
+                let len = match algorithm {
+                    Algorithm::ECDSAP256SHA256 => 32,
+                    _ => 48,
+                };
+                vec![(
+                    PRIVATE_KEY.to_string(),
+                    ec_key.private_key().to_vec_padded(len)?,
+                )]
+            }
+            #[cfg(feature = "ring")]
+            KeyPair::ECDSA(..) | KeyPair::ED25519(..) => {
+                return Err("the private key of ring is only available as PKCS#8".into())
+            }
+        };
+
+        Ok(Self { algorithm, fields })
+    }
+
+    /// The algorithm of the key
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    fn field(&self, name: &str) -> DnsSecResult<&[u8]> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_slice())
+            .ok_or_else(|| format!("the private key has no {name}").into())
+    }
+
+    /// Creates the key pair, with the public key of the DNSKEY of the key
+    ///
+    /// # Arguments
+    ///
+    /// * `public_key` - the public key of the DNSKEY, which is not part of the private key of
+    ///   ECDSA and ED25519 keys, and checked against the private key
+    pub fn to_key_pair(&self, public_key: &[u8]) -> DnsSecResult<KeyPair<Private>> {
+        #[allow(unused, deprecated)]
+        let key: KeyPair<Private> = match self.algorithm {
+            #[cfg(feature = "openssl")]
+            Algorithm::RSASHA1
+            | Algorithm::RSASHA1NSEC3SHA1
+            | Algorithm::RSASHA256
+            | Algorithm::RSASHA512 => {
+                let mut components = RSA_FIELDS
+                    .iter()
+                    .map(|field| BigNum::from_slice(self.field(field)?).map_err(Into::into))
+                    .collect::<DnsSecResult<Vec<_>>>()?
+                    .into_iter();
+                let mut next = || components.next().expect("all fields were read");
+
+                let rsa = Rsa::from_private_components(
+                    next(),
+                    next(),
+                    next(),
+                    next(),
+                    next(),
+                    next(),
+                    next(),
+                    next(),
+                )?;
+                KeyPair::from_rsa(rsa)?
+            }
+            #[cfg(feature = "ring")]
+            Algorithm::ECDSAP256SHA256 | Algorithm::ECDSAP384SHA384 => {
+                let ring_algorithm = if self.algorithm == Algorithm::ECDSAP256SHA256 {
+                    &ECDSA_P256_SHA256_FIXED_SIGNING
+                } else {
+                    &ECDSA_P384_SHA384_FIXED_SIGNING
+                };
+                // the uncompressed point, the DNSKEY leaves out the header byte
+                let mut point = vec![0x04];
+                point.extend_from_slice(public_key);
+
+                let key = EcdsaKeyPair::from_private_key_and_public_key(
+                    ring_algorithm,
+                    self.field(PRIVATE_KEY)?,
+                    &point,
+                    &SystemRandom::new(),
+                )?;
+                KeyPair::from_ecdsa(key)
+            }
+            #[cfg(all(feature = "openssl", not(feature = "ring")))]
+            Algorithm::ECDSAP256SHA256 | Algorithm::ECDSAP384SHA384 => {
+                let nid = if self.algorithm == Algorithm::ECDSAP256SHA256 {
+                    Nid::X9_62_PRIME256V1
+                } else {
+                    Nid::SECP384R1
+                };
+                let group = EcGroup::from_curve_name(nid)?;
+                let mut point = vec![0x04];
+                point.extend_from_slice(public_key);
+                let mut context = BigNumContext::new()?;
+                let point = EcPoint::from_bytes(&group, &point, &mut context)?;
+
+                let private_key = BigNum::from_slice(self.field(PRIVATE_KEY)?)?;
+                let key = EcKey::from_private_components(&group, &private_key, &point)?;
+                key.check_key()?;
+                KeyPair::from_ec_key(key)?
+            }
+            #[cfg(feature = "ring")]
+            Algorithm::ED25519 => {
+                let key =
+                    Ed25519KeyPair::from_seed_and_public_key(self.field(PRIVATE_KEY)?, public_key)?;
+                KeyPair::from_ed25519(key)
+            }
+            algorithm => {
+                return Err(format!(
+                    "unsupported algorithm, enable openssl or ring feature: {algorithm:?}"
+                )
+                .into())
+            }
+        };
+
+        if key.to_public_bytes()? != public_key {
+            return Err("the private key does not match the public key".into());
+        }
+        Ok(key)
+    }
+}
+
+impl fmt::Debug for BindPrivateKey {
+    /// Leaves out the private key
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BindPrivateKey")
+            .field("algorithm", &self.algorithm)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for BindPrivateKey {
+    /// The contents of the `.private` file
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Private-key-format: v1.3")?;
+        writeln!(
+            f,
+            "Algorithm: {} ({})",
+            u8::from(self.algorithm),
+            self.algorithm.as_str()
+        )?;
+        for (field, value) in &self.fields {
+            writeln!(f, "{field}: {}", data_encoding::BASE64.encode(value))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for BindPrivateKey {
+    type Err = DnsSecError;
+
+    /// Parses the contents of the `.private` file, the timing fields are ignored
+    fn from_str(s: &str) -> DnsSecResult<Self> {
+        let mut algorithm = None;
+        let mut fields = Vec::new();
+
+        for line in s.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let (field, value) = line
+                .split_once(':')
+                .ok_or_else(|| format!("not a field of a private key: {line}"))?;
+            let value = value.trim();
+
+            match field {
+                "Private-key-format" if !value.starts_with("v1.") => {
+                    return Err(format!("unsupported private key format: {value}").into());
+                }
+                "Algorithm" => {
+                    let number = value.split_whitespace().next().unwrap_or_default();
+                    let number = number
+                        .parse::<u8>()
+                        .map_err(|e| format!("bad algorithm {value}: {e}"))?;
+                    algorithm = Some(Algorithm::from_u8(number));
+                }
+                PRIVATE_KEY => fields.push((field.to_string(), base64(field, value)?)),
+                field if RSA_FIELDS.contains(&field) => {
+                    fields.push((field.to_string(), base64(field, value)?))
+                }
+                // the format v1.x, Created, Publish, Activate and the other timing fields
+                _ => (),
+            }
+        }
+
+        let algorithm = algorithm.ok_or("the private key has no algorithm")?;
+        Ok(Self { algorithm, fields })
+    }
+}
+
+fn base64(field: &str, value: &str) -> DnsSecResult<Vec<u8>> {
+    data_encoding::BASE64
+        .decode(value.as_bytes())
+        .map_err(|e| format!("bad {field}: {e}").into())
+}
+
+/// The contents of the `.key` file of the key
+pub fn encode_public_key(name: &Name, dnskey: &DNSKEY) -> DnsSecResult<String> {
+    let kind = if dnskey.secure_entry_point() {
+        "key-signing"
+    } else {
+        "zone-signing"
+    };
+
+    let mut key_file = String::new();
+    writeln!(
+        key_file,
+        "; This is a {kind} key, keyid {}, for {name}",
+        dnskey.calculate_key_tag()?
+    )
+    .and_then(|_| writeln!(key_file, "{name} IN DNSKEY {dnskey}"))
+    .map_err(|e| format!("failed to write the key: {e}"))?;
+    Ok(key_file)
+}
+
+/// Parses the contents of the `.key` file, the first DNSKEY record
+///
+/// The owner name, an optional TTL and class precede the DNSKEY type, comments start with `;`.
+#[cfg(feature = "text-parsing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "text-parsing")))]
+pub fn decode_public_key(s: &str) -> DnsSecResult<(Name, DNSKEY)> {
+    use crate::rr::dnssec::rdata::DNSSECRData;
+    use crate::rr::{RData, RecordType};
+    use crate::serialize::txt::RDataParser;
+
+    for line in s.lines() {
+        let line = line.split(';').next().unwrap_or_default();
+        let mut tokens = line.split_whitespace();
+        let Some(name) = tokens.next() else {
+            continue;
+        };
+
+        if !tokens.any(|token| token.eq_ignore_ascii_case("DNSKEY")) {
+            continue;
+        }
+        let name = Name::from_str(name)?;
+        let dnskey = match RData::parse(RecordType::DNSKEY, tokens, Some(&name))
+            .map_err(|e| format!("bad DNSKEY: {e}"))?
+        {
+            RData::DNSSEC(DNSSECRData::DNSKEY(dnskey)) => dnskey,
+            _ => return Err("not a DNSKEY".into()),
+        };
+        return Ok((name, dnskey));
+    }
+
+    Err("no DNSKEY record".into())
+}
+
+const INTEGER: u8 = 0x02;
+const OCTET_STRING: u8 = 0x04;
+const SEQUENCE: u8 = 0x30;
+
+/// Reads a DER element of the tag, returns its contents and the rest of the input
+fn der(input: &[u8], tag: u8) -> DnsSecResult<(&[u8], &[u8])> {
+    let malformed = || DnsSecError::from("malformed PKCS#8 document");
+    let (&found, input) = input.split_first().ok_or_else(malformed)?;
+    if found != tag {
+        return Err(malformed());
+    }
+
+    let (&first, mut input) = input.split_first().ok_or_else(malformed)?;
+    let len = match first {
+        len @ 0..=0x7f => len as usize,
+        0x81..=0x82 => {
+            let octets = (first & 0x7f) as usize;
+            let len = input.get(..octets).ok_or_else(malformed)?;
+            input = &input[octets..];
+            len.iter().fold(0, |len, octet| len << 8 | *octet as usize)
+        }
+        _ => return Err(malformed()),
+    };
+
+    let contents = input.get(..len).ok_or_else(malformed)?;
+    Ok((contents, &input[len..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "ring")]
+    use crate::rr::dnssec::KeyFormat;
+
+    #[cfg(feature = "ring")]
+    fn round_trip_pkcs8(algorithm: Algorithm) {
+        let pkcs8 = KeyPair::generate_pkcs8(algorithm).unwrap();
+        let key = KeyFormat::Pkcs8
+            .decode_key(&pkcs8, None, algorithm)
+            .unwrap();
+        let public_key = key.to_public_bytes().unwrap();
+
+        let private_key = BindPrivateKey::from_pkcs8(algorithm, &pkcs8).unwrap();
+        let parsed = private_key.to_string().parse::<BindPrivateKey>().unwrap();
+        assert_eq!(parsed, private_key);
+        assert_eq!(parsed.algorithm(), algorithm);
+
+        let decoded = parsed.to_key_pair(&public_key).unwrap();
+        assert_eq!(decoded.to_public_bytes().unwrap(), public_key);
+
+        // the private key of another key
+        let other = KeyPair::generate_pkcs8(algorithm).unwrap();
+        let other = KeyFormat::Pkcs8
+            .decode_key(&other, None, algorithm)
+            .unwrap();
+        assert!(parsed
+            .to_key_pair(&other.to_public_bytes().unwrap())
+            .is_err());
+    }
+
+    #[cfg(feature = "ring")]
+    #[test]
+    fn test_ecdsa_p256() {
+        round_trip_pkcs8(Algorithm::ECDSAP256SHA256);
+    }
+
+    #[cfg(feature = "ring")]
+    #[test]
+    fn test_ecdsa_p384() {
+        round_trip_pkcs8(Algorithm::ECDSAP384SHA384);
+    }
+
+    #[cfg(feature = "ring")]
+    #[test]
+    fn test_ed25519() {
+        round_trip_pkcs8(Algorithm::ED25519);
+    }
+
+    #[cfg(feature = "openssl")]
+    #[test]
+    fn test_rsa() {
+        let key = KeyPair::generate_rsa(1024).unwrap();
+        let public_key = key.to_public_bytes().unwrap();
+
+        let private_key = BindPrivateKey::from_key_pair(Algorithm::RSASHA256, &key).unwrap();
+        let parsed = private_key.to_string().parse::<BindPrivateKey>().unwrap();
+        assert_eq!(parsed, private_key);
+
+        let decoded = parsed.to_key_pair(&public_key).unwrap();
+        assert_eq!(decoded.to_public_bytes().unwrap(), public_key);
+    }
+
+    #[test]
+    fn test_parse() {
+        // from RFC 8080, section 6.1
+        let private_key = "\
+Private-key-format: v1.2
+Algorithm: 15 (ED25519)
+PrivateKey: ODIyNjAzODQ2MjgwODAxMjI2NDUxOTAyMDQxNDIyNjI=
+Created: 20170101000000
+"
+        .parse::<BindPrivateKey>()
+        .unwrap();
+
+        assert_eq!(private_key.algorithm(), Algorithm::ED25519);
+        assert_eq!(
+            private_key.field(PRIVATE_KEY).unwrap(),
+            b"82260384628080122645190204142262"
+        );
+        assert!("Algorithm: 15".parse::<BindPrivateKey>().is_ok());
+        assert!("Private-key-format: v2.0\nAlgorithm: 15"
+            .parse::<BindPrivateKey>()
+            .is_err());
+        assert!("PrivateKey: AAAA".parse::<BindPrivateKey>().is_err());
+    }
+
+    #[cfg(all(feature = "ring", feature = "text-parsing"))]
+    #[test]
+    fn test_rfc8080() {
+        // from RFC 8080, section 6.1
+        let (name, dnskey) = decode_public_key(
+            "; a comment\nexample.com. 3600 IN DNSKEY 257 3 15 l02Woi0iS8Aa25FQkUd9RMzZHJpBoRQwAQEX1SxZJA4=",
+        )
+        .unwrap();
+        assert_eq!(name, Name::from_ascii("example.com.").unwrap());
+        assert_eq!(dnskey.calculate_key_tag().unwrap(), 3613);
+
+        let private_key = "\
+Private-key-format: v1.2
+Algorithm: 15 (ED25519)
+PrivateKey: ODIyNjAzODQ2MjgwODAxMjI2NDUxOTAyMDQxNDIyNjI=
+"
+        .parse::<BindPrivateKey>()
+        .unwrap();
+        private_key.to_key_pair(dnskey.public_key()).unwrap();
+
+        let key_file = encode_public_key(&name, &dnskey).unwrap();
+        assert_eq!(
+            key_file,
+            "; This is a key-signing key, keyid 3613, for example.com.\n\
+             example.com. IN DNSKEY 257 3 15 l02Woi0iS8Aa25FQkUd9RMzZHJpBoRQwAQEX1SxZJA4=\n"
+        );
+        assert_eq!(decode_public_key(&key_file).unwrap(), (name, dnskey));
+        assert!(decode_public_key("; no key").is_err());
+    }
+}
@@ -458,12 +458,7 @@ impl KeyPair<Private> {
             Algorithm::RSASHA1
             | Algorithm::RSASHA1NSEC3SHA1
             | Algorithm::RSASHA256
-            | Algorithm::RSASHA512 => {
-                // TODO: the only keysize right now, would be better for people to use other algorithms...
-                OpenSslRsa::generate(2048)
-                    .map_err(Into::into)
-                    .and_then(Self::from_rsa)
-            }
+            | Algorithm::RSASHA512 => Self::generate_rsa(2048),
             #[cfg(feature = "openssl")]
             Algorithm::ECDSAP256SHA256 => EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)
                 .and_then(|group| EcKey::generate(&group))
@@ -483,6 +478,15 @@ impl KeyPair<Private> {
         }
     }
 
+    /// Creates an RSA type keypair of the size in bits, see `generate` for the other algorithms
+    #[cfg(feature = "openssl")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "openssl")))]
+    pub fn generate_rsa(bits: u32) -> DnsSecResult<Self> {
+        OpenSslRsa::generate(bits)
+            .map_err(Into::into)
+            .and_then(Self::from_rsa)
+    }
+
     /// Generates a key, securing it with pkcs8
     #[cfg(feature = "ring")]
     #[cfg_attr(docsrs, doc(cfg(feature = "ring")))]
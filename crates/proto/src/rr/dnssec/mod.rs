@@ -8,6 +8,9 @@
 //! dns security extension related modules
 
 mod algorithm;
+#[cfg(any(feature = "openssl", feature = "ring"))]
+#[cfg_attr(docsrs, doc(cfg(any(feature = "openssl", feature = "ring"))))]
+pub mod bind_key;
 mod digest_type;
 #[cfg(any(feature = "openssl", feature = "ring"))]
 mod ec_public_key;
@@ -251,6 +251,17 @@ pub enum ProofErrorKind {
         key_tag: u16,
     },
 
+    /// The time is outside of the validity period of the RRSIG
+    #[error("rrsig is not valid at {now}: {name} key_tag: {key_tag}")]
+    RrsigNotCurrent {
+        /// The name of the rrset
+        name: Name,
+        /// The key tag of the RRSIG
+        key_tag: u16,
+        /// The time of the check, seconds since the Unix epoch truncated to 32 bits
+        now: u32,
+    },
+
     /// There was a protocol error when looking up DNSSEC records
     #[error("communication failure for query: {query}: {proto}")]
     Proto {
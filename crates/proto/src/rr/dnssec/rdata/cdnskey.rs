@@ -25,6 +25,12 @@ use super::{DNSSECRData, DNSKEY};
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub struct CDNSKEY(DNSKEY);
 
+impl From<DNSKEY> for CDNSKEY {
+    fn from(dnskey: DNSKEY) -> Self {
+        Self(dnskey)
+    }
+}
+
 impl Deref for CDNSKEY {
     type Target = DNSKEY;
 
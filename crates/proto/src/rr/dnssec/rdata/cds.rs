@@ -25,6 +25,12 @@ use super::{DNSSECRData, DS};
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub struct CDS(DS);
 
+impl From<DS> for CDS {
+    fn from(ds: DS) -> Self {
+        Self(ds)
+    }
+}
+
 impl Deref for CDS {
     type Target = DS;
 
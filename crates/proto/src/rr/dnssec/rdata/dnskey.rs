@@ -25,6 +25,8 @@ use crate::{
 };
 
 use super::DNSSECRData;
+#[cfg(any(feature = "openssl", feature = "ring"))]
+use super::DS;
 
 /// [RFC 4034](https://tools.ietf.org/html/rfc4034#section-2), DNSSEC Resource Records, March 2005
 ///
@@ -246,7 +248,7 @@ impl DNSKEY {
                 .emit(&mut encoder)
                 .and_then(|_| self.emit(&mut encoder))
             {
-                tracing::warn!("error serializing dnskey: {e}");
+                crate::logging::warn!("error serializing dnskey: {e}");
                 return Err(format!("error serializing dnskey: {e}").into());
             }
         }
@@ -261,6 +263,24 @@ impl DNSKEY {
         Err("Ring or OpenSSL must be enabled for this feature".into())
     }
 
+    /// Creates the DS record data of the key, for the parent zone
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the label of of the DNSKEY record.
+    /// * `digest_type` - the `DigestType` of the digest of the DS record.
+    #[cfg(any(feature = "openssl", feature = "ring"))]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "openssl", feature = "ring"))))]
+    pub fn to_ds(&self, name: &Name, digest_type: DigestType) -> ProtoResult<DS> {
+        let digest = self.to_digest(name, digest_type)?;
+        Ok(DS::new(
+            self.calculate_key_tag()?,
+            self.algorithm,
+            digest_type,
+            digest.as_ref().to_vec(),
+        ))
+    }
+
     /// The key tag is calculated as a hash to more quickly lookup a DNSKEY.
     ///
     /// [RFC 2535](https://tools.ietf.org/html/rfc2535), Domain Name System Security Extensions, March 1999
@@ -517,6 +537,27 @@ mod tests {
             .is_ok());
     }
 
+    #[test]
+    #[cfg(any(feature = "openssl", feature = "ring"))]
+    fn test_to_ds() {
+        // from RFC 8080, section 6.1
+        let public_key = data_encoding::BASE64
+            .decode(b"l02Woi0iS8Aa25FQkUd9RMzZHJpBoRQwAQEX1SxZJA4=")
+            .unwrap();
+        let rdata = DNSKEY::new(true, true, false, Algorithm::ED25519, public_key);
+        let ds = rdata
+            .to_ds(
+                &Name::parse("example.com.", None).unwrap(),
+                DigestType::SHA256,
+            )
+            .unwrap();
+
+        assert_eq!(
+            ds.to_string(),
+            "3613 15 2 3AA5AB37EFCE57F737FC1627013FEE07BDF241BD10F3B1964AB55C78E79A304B"
+        );
+    }
+
     #[test]
     fn test_calculate_key_tag_checksum() {
         let test_text = "The quick brown fox jumps over the lazy dog";
@@ -28,8 +28,8 @@ pub mod rrsig;
 pub mod sig;
 pub mod tsig;
 
+use crate::logging::trace;
 use enum_as_inner::EnumAsInner;
-use tracing::trace;
 
 use crate::{
     error::*,
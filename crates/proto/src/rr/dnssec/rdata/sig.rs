@@ -317,6 +317,15 @@ impl SIG {
         self.num_labels
     }
 
+    /// Returns true if the records at `name` covered by this signature were synthesized from a
+    ///  wildcard, i.e. the signature has fewer labels than the name, see `num_labels()`
+    ///
+    /// The records are then only authentic with a proof that `name` does not exist in the zone,
+    ///  see [RFC 4035](https://tools.ietf.org/html/rfc4035#section-5.3.4).
+    pub fn is_wildcard_expansion(&self, name: &Name) -> bool {
+        self.num_labels < name.num_labels()
+    }
+
     /// [RFC 2535](https://tools.ietf.org/html/rfc2535#section-4.1.4), Domain Name System Security Extensions, March 1999
     ///
     /// ```text
@@ -17,7 +17,7 @@ use crate::{
     error::{ProtoError, ProtoErrorKind, ProtoResult},
     op::{Header, Message, Query},
     rr::{
-        dns_class::DNSClass, dnssec::rdata::DNSSECRData, rdata::sshfp, record_data::RData,
+        dns_class::DNSClass, dnssec::rdata::DNSSECRData, rdata::HEX, record_data::RData,
         record_type::RecordType, Name, Record, RecordData, RecordDataDecodable,
     },
     serialize::binary::*,
@@ -464,10 +464,10 @@ impl fmt::Display for TSIG {
             algorithm = self.algorithm,
             time = self.time,
             fudge = self.fudge,
-            mac = sshfp::HEX.encode(&self.mac),
+            mac = HEX.encode(&self.mac),
             oid = self.oid,
             error = self.error,
-            other = sshfp::HEX.encode(&self.other),
+            other = HEX.encode(&self.other),
         )
     }
 }
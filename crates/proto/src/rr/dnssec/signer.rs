@@ -6,7 +6,7 @@
 // copied, modified, or distributed except according to those terms.
 
 //! signer is a structure for performing many of the signing processes of the DNSSEC specification
-use tracing::debug;
+use crate::logging::debug;
 
 #[cfg(feature = "dnssec")]
 use std::time::Duration;
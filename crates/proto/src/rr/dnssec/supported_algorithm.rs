@@ -21,7 +21,7 @@ use std::fmt::{self, Display, Formatter};
 #[cfg(feature = "serde-config")]
 use serde::{Deserialize, Serialize};
 
-use tracing::warn;
+use crate::logging::warn;
 
 use crate::error::*;
 use crate::rr::dnssec::Algorithm;
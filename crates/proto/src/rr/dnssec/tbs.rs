@@ -11,7 +11,7 @@ use std::borrow::Borrow;
 
 use crate::{
     error::*,
-    rr::{dnssec::Algorithm, DNSClass, Name, Record, RecordType},
+    rr::{dnssec::Algorithm, rr_set::canonical_rdatas, DNSClass, Name, Record, RecordType},
     serialize::binary::{BinEncodable, BinEncoder, EncodeMode},
 };
 
@@ -99,22 +99,17 @@ pub fn rrset_tbs<B: Borrow<Record>>(
     signer_name: &Name,
     records: &[B],
 ) -> ProtoResult<TBS> {
-    // TODO: change this to a BTreeSet so that it's preordered, no sort necessary
-    let mut rrset: Vec<&Record> = Vec::new();
-
-    // collect only the records for this rrset
-    for record in records {
-        let record = record.borrow();
-        if dns_class == record.dns_class()
-            && type_covered == record.record_type()
-            && name == record.name()
-        {
-            rrset.push(record);
-        }
-    }
-
-    // put records in canonical order
-    rrset.sort();
+    // collect only the records for this rrset, in canonical order
+    let rrset = canonical_rdatas(
+        records
+            .iter()
+            .map(Borrow::borrow)
+            .filter(|record: &&Record| {
+                dns_class == record.dns_class()
+                    && type_covered == record.record_type()
+                    && name == record.name()
+            }),
+    )?;
 
     let name = determine_name(name, num_labels)?;
 
@@ -146,7 +141,7 @@ pub fn rrset_tbs<B: Borrow<Record>>(
         .is_ok());
 
         // construct the rrset signing data
-        for record in rrset {
+        for (rdata, _) in rrset {
             //             RR(i) = name | type | class | OrigTTL | RDATA length | RDATA
             //
             //                name is calculated according to the function in the RFC 4035
@@ -165,19 +160,10 @@ pub fn rrset_tbs<B: Borrow<Record>>(
             assert!(encoder.emit_u32(original_ttl).is_ok());
             //
             //                RDATA length
-            // TODO: add support to the encoder to set a marker to go back and write the length
-            let mut rdata_buf = Vec::new();
-            {
-                let mut rdata_encoder = BinEncoder::new(&mut rdata_buf);
-                rdata_encoder.set_canonical_names(true);
-                if let Some(rdata) = record.data() {
-                    assert!(rdata.emit(&mut rdata_encoder).is_ok());
-                }
-            }
-            assert!(encoder.emit_u16(rdata_buf.len() as u16).is_ok());
+            assert!(encoder.emit_u16(rdata.len() as u16).is_ok());
             //
-            //                All names in the RDATA field are in canonical form (set above)
-            assert!(encoder.emit_vec(&rdata_buf).is_ok());
+            //                All names in the RDATA field are in canonical form
+            assert!(encoder.emit_vec(&rdata).is_ok());
         }
     }
 
@@ -303,3 +289,46 @@ pub fn determine_name(name: &Name, num_labels: u8) -> Result<Name, ProtoError> {
 
     Err(format!("could not determine name from {name}").into())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rr::{rdata::NS, RData};
+
+    #[test]
+    fn test_rrset_tbs_canonical() {
+        let origin = Name::from_ascii("example.com.").unwrap();
+        let ns = |target: &str| {
+            Record::from_rdata(
+                origin.clone(),
+                86400,
+                RData::NS(NS(Name::from_ascii(target).unwrap())),
+            )
+        };
+        let tbs = |records: &[Record]| {
+            rrset_tbs(
+                &origin,
+                DNSClass::IN,
+                origin.num_labels(),
+                RecordType::NS,
+                Algorithm::ED25519,
+                86400,
+                5,
+                0,
+                0,
+                &origin,
+                records,
+            )
+            .unwrap()
+        };
+
+        // case, order and duplicates of the rdata do not change the signed data
+        let lower = tbs(&[ns("a.iana-servers.net."), ns("b.iana-servers.net.")]);
+        let mixed = tbs(&[
+            ns("B.iana-servers.net."),
+            ns("A.iana-servers.net."),
+            ns("a.iana-servers.net."),
+        ]);
+        assert_eq!(lower.as_ref(), mixed.as_ref());
+    }
+}
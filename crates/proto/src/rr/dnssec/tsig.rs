@@ -16,7 +16,7 @@
 use std::ops::Range;
 use std::sync::Arc;
 
-use tracing::debug;
+use crate::logging::debug;
 
 use crate::error::ProtoErrorKind;
 use crate::error::{ProtoError, ProtoResult};
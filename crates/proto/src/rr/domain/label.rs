@@ -19,8 +19,8 @@ use std::fmt::{self, Debug, Display, Formatter, Write};
 use std::hash::{Hash, Hasher};
 use tinyvec::TinyVec;
 
+use crate::logging::debug;
 use idna;
-use tracing::debug;
 
 use crate::error::*;
 
@@ -148,17 +148,7 @@ impl Label {
 
     /// compares with the other label, ignoring case
     pub fn cmp_with_f<F: LabelCmp>(&self, other: &Self) -> Ordering {
-        let s = self.0.iter();
-        let o = other.0.iter();
-
-        for (s, o) in s.zip(o) {
-            match F::cmp_u8(*s, *o) {
-                Ordering::Equal => continue,
-                not_eq => return not_eq,
-            }
-        }
-
-        self.0.len().cmp(&other.0.len())
+        F::cmp_bytes(&self.0, &other.0)
     }
 
     /// Performs the conversion to utf8 from IDNA as necessary, see `fmt` for more details
@@ -296,9 +286,7 @@ impl Hash for Label {
     where
         H: Hasher,
     {
-        for b in self.borrow() as &[u8] {
-            state.write_u8(b.to_ascii_lowercase());
-        }
+        hash_ignore_ascii_case(self.borrow(), state)
     }
 }
 
@@ -306,6 +294,18 @@ impl Hash for Label {
 pub trait LabelCmp {
     /// this should mimic the cmp method from [`PartialOrd`]
     fn cmp_u8(l: u8, r: u8) -> Ordering;
+
+    /// Compares the bytes of two labels, this should mimic the cmp method of `[u8]`
+    fn cmp_bytes(l: &[u8], r: &[u8]) -> Ordering {
+        for (l, r) in l.iter().zip(r) {
+            match Self::cmp_u8(*l, *r) {
+                Ordering::Equal => continue,
+                not_eq => return not_eq,
+            }
+        }
+
+        l.len().cmp(&r.len())
+    }
 }
 
 /// For case sensitive comparisons
@@ -315,6 +315,10 @@ impl LabelCmp for CaseSensitive {
     fn cmp_u8(l: u8, r: u8) -> Ordering {
         l.cmp(&r)
     }
+
+    fn cmp_bytes(l: &[u8], r: &[u8]) -> Ordering {
+        l.cmp(r)
+    }
 }
 
 /// For case insensitive comparisons
@@ -322,7 +326,77 @@ pub(super) struct CaseInsensitive;
 
 impl LabelCmp for CaseInsensitive {
     fn cmp_u8(l: u8, r: u8) -> Ordering {
-        l.to_ascii_lowercase().cmp(&r.to_ascii_lowercase())
+        ASCII_LOWERCASE[l as usize].cmp(&ASCII_LOWERCASE[r as usize])
+    }
+
+    fn cmp_bytes(l: &[u8], r: &[u8]) -> Ordering {
+        let mut l_words = l.chunks_exact(WORD);
+        let mut r_words = r.chunks_exact(WORD);
+
+        // compare a word at a time, only comparing bytes in the first word which differs
+        for (l_word, r_word) in (&mut l_words).zip(&mut r_words) {
+            let l_lower = lowercase_word(l_word);
+            let r_lower = lowercase_word(r_word);
+            if l_lower != r_lower {
+                // the first differing byte decides, which is the most significant in big endian
+                return l_lower.cmp(&r_lower);
+            }
+        }
+
+        let offset = l.len().min(r.len()) / WORD * WORD;
+        for (l, r) in l[offset..].iter().zip(&r[offset..]) {
+            match Self::cmp_u8(*l, *r) {
+                Ordering::Equal => continue,
+                not_eq => return not_eq,
+            }
+        }
+
+        l.len().cmp(&r.len())
+    }
+}
+
+const WORD: usize = 8;
+
+/// Converts the ASCII upper case letters of the word to lower case, all other bytes are unchanged
+///
+/// The word is read as big endian, so comparing the results compares the bytes in order.
+fn lowercase_word(bytes: &[u8]) -> u64 {
+    const ONES: u64 = u64::from_ne_bytes([1; WORD]);
+    const HIGH_BITS: u64 = ONES * 0x80;
+
+    let word = u64::from_be_bytes(bytes.try_into().expect("chunks are a word"));
+
+    // the high bit of each byte is set if the byte is at least 'A', or greater than 'Z'. Only
+    //  the lower 7 bits are added to, so nothing carries into the next byte
+    let ascii = word & !HIGH_BITS;
+    let from_a = ascii + ONES * (0x80 - b'A' as u64);
+    let after_z = ascii + ONES * (0x80 - b'Z' as u64 - 1);
+    let upper = from_a & !after_z & !word & HIGH_BITS;
+
+    // 0x80 >> 2 is 0x20, the difference between upper and lower case letters
+    word | (upper >> 2)
+}
+
+/// ASCII lower case of all bytes, all non ASCII upper case letters map to themselves
+static ASCII_LOWERCASE: [u8; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < table.len() {
+        table[i] = (i as u8).to_ascii_lowercase();
+        i += 1;
+    }
+    table
+};
+
+/// Feeds the bytes to the hasher as ASCII lower case, so that labels equal ignoring case hash the same
+pub(super) fn hash_ignore_ascii_case<H: Hasher>(bytes: &[u8], state: &mut H) {
+    let mut buf = [0_u8; 64];
+    for chunk in bytes.chunks(buf.len()) {
+        let lower = &mut buf[..chunk.len()];
+        for (lower, b) in lower.iter_mut().zip(chunk) {
+            *lower = ASCII_LOWERCASE[*b as usize];
+        }
+        state.write(lower);
     }
 }
 
@@ -583,4 +657,56 @@ mod tests {
         );
         assert_eq!(Label::from_raw_bytes(&[0o200]).unwrap().to_ascii(), "\\200");
     }
+
+    #[test]
+    fn test_cmp_ignore_ascii_case() {
+        fn naive(l: &[u8], r: &[u8]) -> Ordering {
+            l.to_ascii_lowercase().cmp(&r.to_ascii_lowercase())
+        }
+
+        // every byte value, at every offset of a word, against the lower and upper case letters
+        let base = b"abcdefghijklmnopqrstu";
+        for i in 0..base.len() {
+            for b in 0..=u8::MAX {
+                let mut l = base.to_vec();
+                l[i] = b;
+                for r in [base.to_vec(), base.to_ascii_uppercase()] {
+                    assert_eq!(
+                        CaseInsensitive::cmp_bytes(&l, &r),
+                        naive(&l, &r),
+                        "{l:?} {r:?}"
+                    );
+                    assert_eq!(CaseInsensitive::cmp_bytes(&r, &l), naive(&r, &l));
+                }
+            }
+        }
+
+        // different lengths
+        assert_eq!(
+            CaseInsensitive::cmp_bytes(b"EXAMPLES", b"example"),
+            Ordering::Greater
+        );
+        assert_eq!(
+            CaseInsensitive::cmp_bytes(b"example", b"EXAMPLE-DOMAIN"),
+            Ordering::Less
+        );
+        assert_eq!(
+            CaseInsensitive::cmp_bytes(b"a-long-EXAMPLE", b"A-LONG-example"),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn test_hash_ignore_ascii_case() {
+        use std::collections::hash_map::DefaultHasher;
+
+        fn hash(label: &str) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            Label::from_ascii(label).unwrap().hash(&mut hasher);
+            hasher.finish()
+        }
+
+        assert_eq!(hash("Example"), hash("eXAMPLE"));
+        assert_ne!(hash("example"), hash("examples"));
+    }
 }
@@ -9,9 +9,11 @@
 
 mod label;
 mod name;
+mod static_name;
 mod try_parse_ip;
 pub mod usage;
 
 pub use self::label::{IntoLabel, Label};
 pub use self::name::{IntoName, LabelIter, Name};
+pub use self::static_name::StaticName;
 pub use self::try_parse_ip::TryParseIp;
@@ -9,16 +9,21 @@
 
 use std::char;
 use std::cmp::{Ordering, PartialEq};
+use std::collections::hash_map::RandomState;
 use std::fmt::{self, Write};
-use std::hash::{Hash, Hasher};
+use std::hash::{BuildHasher, Hash, Hasher};
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::str::FromStr;
 
 use crate::error::*;
-use crate::rr::domain::label::{CaseInsensitive, CaseSensitive, IntoLabel, Label, LabelCmp};
+use crate::rr::domain::label::{
+    hash_ignore_ascii_case, CaseInsensitive, CaseSensitive, IntoLabel, Label, LabelCmp,
+};
 use crate::rr::domain::usage::LOCALHOST as LOCALHOST_usage;
+use crate::rr::domain::StaticName;
 use crate::serialize::binary::*;
 use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+use once_cell::sync::Lazy;
 #[cfg(feature = "serde-config")]
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use tinyvec::TinyVec;
@@ -313,6 +318,10 @@ impl Name {
 
     /// same as `zone_of` allows for case sensitive call
     pub fn zone_of_case(&self, name: &Self) -> bool {
+        self.zone_of_with_f::<CaseSensitive>(name)
+    }
+
+    fn zone_of_with_f<F: LabelCmp>(&self, name: &Self) -> bool {
         let self_len = self.label_ends.len();
         let name_len = name.label_ends.len();
         if self_len == 0 {
@@ -332,7 +341,7 @@ impl Name {
         let zip_iter = self_iter.zip(name_iter);
 
         for (self_label, name_label) in zip_iter {
-            if self_label != name_label {
+            if F::cmp_bytes(self_label, name_label) != Ordering::Equal {
                 return false;
             }
         }
@@ -357,10 +366,7 @@ impl Name {
     /// assert!(!another.zone_of(&name));
     /// ```
     pub fn zone_of(&self, name: &Self) -> bool {
-        let self_lower = self.to_lowercase();
-        let name_lower = name.to_lowercase();
-
-        self_lower.zone_of_case(&name_lower)
+        self.zone_of_with_f::<CaseInsensitive>(name)
     }
 
     /// Returns the number of labels in the name, discounting `*`.
@@ -689,9 +695,7 @@ impl Name {
         let other_labels = other.iter().rev();
 
         for (l, r) in self_labels.zip(other_labels) {
-            let l = Label::from_raw_bytes(l).unwrap();
-            let r = Label::from_raw_bytes(r).unwrap();
-            match l.cmp_with_f::<F>(&r) {
+            match F::cmp_bytes(l, r) {
                 Ordering::Equal => continue,
                 not_eq => return not_eq,
             }
@@ -984,6 +988,18 @@ impl<'a> IntoIterator for &'a Name {
     }
 }
 
+impl From<StaticName> for Name {
+    /// Copies the labels, which were validated at compile time
+    fn from(name: StaticName) -> Self {
+        let mut this = Self::root();
+        for label in name.iter() {
+            this.extend_name(label)
+                .expect("static names are validated on construction");
+        }
+        this
+    }
+}
+
 impl From<IpAddr> for Name {
     fn from(addr: IpAddr) -> Self {
         match addr {
@@ -1072,15 +1088,26 @@ impl PartialEq<Self> for Name {
 
 impl Hash for Name {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.is_fqdn.hash(state);
+        state.write_u64(self.hash_ignore_case());
+    }
+}
 
-        // this needs to be CaseInsensitive like PartialEq
-        for l in self
-            .iter()
-            .map(|l| Label::from_raw_bytes(l).unwrap().to_lowercase())
-        {
-            l.hash(state);
+/// Keys for the hash of names, random for each process so the hashes can't be predicted
+static NAME_HASH_STATE: Lazy<RandomState> = Lazy::new(RandomState::new);
+
+impl Name {
+    /// Returns the hash of the name, which is the same for all names comparing equal
+    ///
+    /// This needs to be CaseInsensitive like PartialEq, and like PartialEq does not consider
+    ///  whether the name is fully qualified. `LowerName` stores this value, so the hash of a
+    ///  `Name` and its `LowerName` are the same.
+    pub(crate) fn hash_ignore_case(&self) -> u64 {
+        let mut hasher = NAME_HASH_STATE.build_hasher();
+        for label in self.iter() {
+            hasher.write_u8(label.len() as u8);
+            hash_ignore_ascii_case(label, &mut hasher);
         }
+        hasher.finish()
     }
 }
 
@@ -1571,6 +1598,30 @@ mod tests {
         assert!(!zone.zone_of(&none))
     }
 
+    #[test]
+    fn test_hash() {
+        use std::collections::hash_map::DefaultHasher;
+
+        use crate::rr::LowerName;
+
+        fn hash<T: Hash>(value: &T) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            value.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let name = Name::from_ascii("www.Example.COM.").unwrap();
+        let lower = Name::from_ascii("www.example.com").unwrap();
+        assert_eq!(name, lower);
+        assert_eq!(hash(&name), hash(&lower));
+        assert_eq!(hash(&name), hash(&LowerName::new(&name)));
+
+        // labels are hashed with their length
+        let other = Name::from_ascii("wwwe.xample.com.").unwrap();
+        assert_ne!(hash(&name), hash(&other));
+        assert_ne!(LowerName::new(&name), LowerName::new(&other));
+    }
+
     #[test]
     fn test_partial_cmp_eq() {
         let root = Some(Name::from_labels(Vec::<&str>::new()).unwrap());
@@ -0,0 +1,357 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! domain names which are validated at compile time, for static tables
+
+use std::fmt;
+
+use crate::error::ProtoResult;
+use crate::rr::domain::Name;
+use crate::serialize::binary::{BinEncodable, BinEncoder};
+
+/// The maximum length of a name in wire format, RFC 1035 section 2.3.4
+const MAX_NAME_LEN: usize = 255;
+/// The maximum length of a label, RFC 1035 section 2.3.4
+const MAX_LABEL_LEN: usize = 63;
+
+/// A fully qualified domain name in wire format, which is validated at compile time
+///
+/// The labels are borrowed from static memory, so tables of names can be placed in read-only
+///  data, and nothing needs to be parsed or allocated at runtime. Names are usually declared with
+///  the [`static_name!`](crate::static_name) macro, [`Self::from_wire`] accepts labels which are
+///  not safe ASCII.
+///
+/// # Examples
+///
+/// ```
+/// use hickory_proto::rr::{Name, StaticName};
+/// use hickory_proto::static_name;
+///
+/// static LOCAL: [(StaticName, [u8; 4]); 2] = [
+///     (static_name!("router.home."), [192, 168, 0, 1]),
+///     (static_name!("printer.home"), [192, 168, 0, 2]),
+/// ];
+///
+/// let query = Name::from_ascii("Printer.Home.").unwrap();
+/// let (_, ip) = LOCAL.iter().find(|(name, _)| *name == query).unwrap();
+/// assert_eq!(ip, &[192, 168, 0, 2]);
+/// assert_eq!(Name::from(LOCAL[0].0), Name::from_ascii("router.home.").unwrap());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct StaticName {
+    wire: &'static [u8],
+}
+
+impl StaticName {
+    /// The root name, `.`
+    pub const ROOT: Self = Self { wire: &[0] };
+
+    /// Creates a name from the labels in wire format, terminated by the root label
+    ///
+    /// Compressed labels are not allowed.
+    ///
+    /// # Panics
+    ///
+    /// If the labels are not valid, which fails the compilation in a `const` context.
+    pub const fn from_wire(wire: &'static [u8]) -> Self {
+        if wire.len() > MAX_NAME_LEN {
+            panic!("static name is longer than 255 bytes");
+        }
+
+        let mut pos = 0;
+        while pos < wire.len() {
+            let len = wire[pos] as usize;
+            if len == 0 {
+                if pos + 1 != wire.len() {
+                    panic!("static name has data after the root label");
+                }
+                return Self { wire };
+            }
+            if len > MAX_LABEL_LEN {
+                panic!("static name has a label longer than 63 bytes");
+            }
+            pos += 1 + len;
+        }
+
+        panic!("static name is not terminated by the root label")
+    }
+
+    /// Returns the labels in wire format, including the root label
+    pub const fn as_wire(&self) -> &'static [u8] {
+        self.wire
+    }
+
+    /// Returns true if this is the root name
+    pub const fn is_root(&self) -> bool {
+        self.wire.len() == 1
+    }
+
+    /// Returns an iterator over the labels, not including the root label
+    pub fn iter(&self) -> impl Iterator<Item = &'static [u8]> + Clone {
+        let mut rest = self.wire;
+        std::iter::from_fn(move || {
+            let (&len, tail) = rest.split_first()?;
+            if len == 0 {
+                return None;
+            }
+
+            let (label, tail) = tail.split_at(len as usize);
+            rest = tail;
+            Some(label)
+        })
+    }
+
+    /// Returns the number of labels, not including the root label
+    pub fn num_labels(&self) -> u8 {
+        self.iter().count() as u8
+    }
+
+    /// Case insensitive comparison with a name, which must be fully qualified to be equal
+    pub fn eq_name(&self, name: &Name) -> bool {
+        name.is_fqdn()
+            && name.num_labels() == self.num_labels()
+            && name
+                .iter()
+                .zip(self.iter())
+                .all(|(left, right)| left.eq_ignore_ascii_case(right))
+    }
+
+    /// Converts this into a [`Name`]
+    pub fn to_name(&self) -> Name {
+        Name::from(*self)
+    }
+
+    /// Returns the length in wire format of the ASCII name, used by `static_name!`
+    ///
+    /// # Panics
+    ///
+    /// If the name is not valid.
+    #[doc(hidden)]
+    pub const fn ascii_wire_len(ascii: &str) -> usize {
+        let ascii = ascii.as_bytes();
+        if ascii.len() == 1 && ascii[0] == b'.' {
+            return 1;
+        }
+
+        // trailing dot is optional, all static names are fully qualified
+        let len = if !ascii.is_empty() && ascii[ascii.len() - 1] == b'.' {
+            ascii.len() - 1
+        } else {
+            ascii.len()
+        };
+        if len == 0 {
+            panic!("static name is empty, the root is `.`");
+        }
+
+        let mut label_len = 0;
+        let mut i = 0;
+        while i < len {
+            let c = ascii[i];
+            if c == b'.' {
+                if label_len == 0 {
+                    panic!("static name has an empty label");
+                }
+                label_len = 0;
+            } else {
+                if !is_safe_ascii(c, label_len == 0) {
+                    panic!("static name has a character which is not safe ASCII");
+                }
+                label_len += 1;
+                if label_len > MAX_LABEL_LEN {
+                    panic!("static name has a label longer than 63 bytes");
+                }
+            }
+            i += 1;
+        }
+        if label_len == 0 {
+            panic!("static name has an empty label");
+        }
+
+        // each dot becomes a length byte, plus the first length and the root label
+        let wire_len = len + 2;
+        if wire_len > MAX_NAME_LEN {
+            panic!("static name is longer than 255 bytes");
+        }
+        wire_len
+    }
+
+    /// Encodes the ASCII name in wire format, `N` is the result of [`Self::ascii_wire_len`]
+    #[doc(hidden)]
+    pub const fn ascii_to_wire<const N: usize>(ascii: &str) -> [u8; N] {
+        if Self::ascii_wire_len(ascii) != N {
+            panic!("static name length does not match the buffer");
+        }
+
+        let ascii = ascii.as_bytes();
+        let mut wire = [0; N];
+        if N == 1 {
+            return wire;
+        }
+
+        // the ASCII is shifted by one, each dot becomes the length of the label which follows it
+        let mut len_pos = 0;
+        let mut i = 0;
+        while i < N - 2 {
+            let c = ascii[i];
+            if c == b'.' {
+                wire[len_pos] = (i - len_pos) as u8;
+                len_pos = i + 1;
+            } else {
+                wire[i + 1] = c;
+            }
+            i += 1;
+        }
+        wire[len_pos] = (i - len_pos) as u8;
+
+        wire
+    }
+}
+
+/// Same rules as for ASCII labels
+const fn is_safe_ascii(c: u8, is_first: bool) -> bool {
+    match c {
+        b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' => true,
+        b'-' => !is_first,
+        b'*' => is_first,
+        _ => false,
+    }
+}
+
+impl PartialEq<Name> for StaticName {
+    fn eq(&self, other: &Name) -> bool {
+        self.eq_name(other)
+    }
+}
+
+impl PartialEq<StaticName> for Name {
+    fn eq(&self, other: &StaticName) -> bool {
+        other.eq_name(self)
+    }
+}
+
+impl BinEncodable for StaticName {
+    fn emit(&self, encoder: &mut BinEncoder<'_>) -> ProtoResult<()> {
+        encoder.emit_vec(self.wire)
+    }
+}
+
+impl fmt::Display for StaticName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_root() {
+            return f.write_str(".");
+        }
+
+        for label in self.iter() {
+            for &c in label {
+                if c.is_ascii_graphic() && c != b'.' && c != b'\\' {
+                    write!(f, "{}", c as char)?;
+                } else {
+                    write!(f, "\\{c:03}")?;
+                }
+            }
+            f.write_str(".")?;
+        }
+        Ok(())
+    }
+}
+
+/// Creates a [`StaticName`](crate::rr::StaticName) from an ASCII name, validated at compile time
+///
+/// The trailing dot is optional, the name is always fully qualified. Labels are limited to safe
+///  ASCII, i.e. letters, digits, `-`, `_` and a leading `*`, use `StaticName::from_wire` for others.
+///
+/// ```
+/// use hickory_proto::rr::StaticName;
+/// use hickory_proto::static_name;
+///
+/// const WWW: StaticName = static_name!("www.example.com.");
+/// assert_eq!(WWW.as_wire(), b"\x03www\x07example\x03com\x00");
+/// ```
+///
+/// Invalid names fail the compilation:
+///
+/// ```compile_fail
+/// const BAD: hickory_proto::rr::StaticName = hickory_proto::static_name!("www..example.com.");
+/// ```
+#[macro_export]
+macro_rules! static_name {
+    ($name:expr) => {{
+        const WIRE_LEN: usize = $crate::rr::StaticName::ascii_wire_len($name);
+        const WIRE: [u8; WIRE_LEN] = $crate::rr::StaticName::ascii_to_wire::<WIRE_LEN>($name);
+        $crate::rr::StaticName::from_wire(&WIRE)
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_static_name() {
+        const NAME: StaticName = static_name!("www.Example.com");
+        assert_eq!(NAME.as_wire(), b"\x03www\x07Example\x03com\x00");
+        assert_eq!(NAME.as_wire().len(), 17);
+        assert_eq!(NAME.num_labels(), 3);
+        assert_eq!(NAME.to_string(), "www.Example.com.");
+
+        let name = NAME.to_name();
+        assert!(name.is_fqdn());
+        assert_eq!(name, Name::from_str("www.example.com.").unwrap());
+        assert_eq!(NAME, name);
+        assert_ne!(NAME, Name::from_str("www.example.com").unwrap());
+        assert_ne!(NAME, Name::from_str("example.com.").unwrap());
+        assert_eq!(NAME.to_bytes().unwrap(), name.to_bytes().unwrap());
+    }
+
+    #[test]
+    fn test_static_root() {
+        const ROOT: StaticName = static_name!(".");
+        assert_eq!(ROOT, StaticName::ROOT);
+        assert!(ROOT.is_root());
+        assert_eq!(ROOT.to_string(), ".");
+        assert!(ROOT.to_name().is_root());
+        assert_eq!(ROOT, Name::root());
+    }
+
+    #[test]
+    fn test_static_labels() {
+        const NAME: StaticName = static_name!("*._tcp.a-1.");
+        let labels: Vec<_> = NAME.iter().collect();
+        assert_eq!(labels, [&b"*"[..], b"_tcp", b"a-1"]);
+
+        let long =
+            static_name!("abcdefghijklmnopqrstuvwxyzabcdefghijklmnopqrstuvwxyzabcdefghijk.com");
+        assert_eq!(long.iter().next().unwrap().len(), 63);
+    }
+
+    #[test]
+    fn test_from_wire() {
+        const NAME: StaticName = StaticName::from_wire(b"\x05a b.c\x00");
+        assert_eq!(NAME.to_string(), "a\\032b\\046c.");
+        assert_eq!(NAME.to_name().iter().next(), Some(&b"a b.c"[..]));
+    }
+
+    #[test]
+    #[should_panic(expected = "root label")]
+    fn test_from_wire_unterminated() {
+        StaticName::from_wire(b"\x03com");
+    }
+
+    #[test]
+    #[should_panic(expected = "not safe ASCII")]
+    fn test_unsafe_ascii() {
+        StaticName::ascii_wire_len("a b.com.");
+    }
+
+    #[test]
+    #[should_panic(expected = "empty label")]
+    fn test_empty_label() {
+        StaticName::ascii_wire_len("com..");
+    }
+}
@@ -22,13 +22,25 @@ use crate::serialize::binary::*;
 
 /// TODO: all LowerNames should be stored in a global "intern" space, and then everything that uses
 ///  them should be through references. As a workaround the Strings are all Rc as well as the array
-#[derive(Default, Debug, Eq, Clone)]
-pub struct LowerName(Name);
+///
+/// The hash of the name is computed once, as comparing and hashing names is the most common
+///  operation on them when looking up records.
+#[derive(Debug, Eq, Clone)]
+pub struct LowerName {
+    name: Name,
+    hash: u64,
+}
 
 impl LowerName {
     /// Create a new domain::LowerName, i.e. label
     pub fn new(name: &Name) -> Self {
-        Self(name.to_lowercase())
+        Self::from_lowercase(name.to_lowercase())
+    }
+
+    /// The name must already be lower case
+    fn from_lowercase(name: Name) -> Self {
+        let hash = name.hash_ignore_case();
+        Self { name, hash }
     }
 
     /// Returns true if there are no labels, i.e. it's empty.
@@ -44,7 +56,7 @@ impl LowerName {
     /// assert_eq!(&root.to_string(), ".");
     /// ```
     pub fn is_root(&self) -> bool {
-        self.0.is_root()
+        self.name.is_root()
     }
 
     /// Returns true if the name is a fully qualified domain name.
@@ -70,7 +82,7 @@ impl LowerName {
     /// assert!(name.is_fqdn());
     /// ```
     pub fn is_fqdn(&self) -> bool {
-        self.0.is_fqdn()
+        self.name.is_fqdn()
     }
 
     /// Trims off the first part of the name, to help with searching for the domain piece
@@ -87,7 +99,7 @@ impl LowerName {
     /// assert_eq!(LowerName::from(Name::root().base_name()), LowerName::from(Name::root()));
     /// ```
     pub fn base_name(&self) -> Self {
-        Self(self.0.base_name())
+        Self::from_lowercase(self.name.base_name())
     }
 
     /// returns true if the name components of self are all present at the end of name
@@ -105,7 +117,7 @@ impl LowerName {
     /// assert!(!another.zone_of(&name));
     /// ```
     pub fn zone_of(&self, name: &Self) -> bool {
-        self.0.zone_of_case(&name.0)
+        self.name.zone_of_case(&name.name)
     }
 
     /// Returns the number of labels in the name, discounting `*`.
@@ -126,7 +138,7 @@ impl LowerName {
     /// assert_eq!(star_example_com.num_labels(), 2);
     /// ```
     pub fn num_labels(&self) -> u8 {
-        self.0.num_labels()
+        self.name.num_labels()
     }
 
     /// returns the length in bytes of the labels. '.' counts as 1
@@ -134,12 +146,12 @@ impl LowerName {
     /// This can be used as an estimate, when serializing labels, they will often be compressed
     /// and/or escaped causing the exact length to be different.
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.name.len()
     }
 
     /// Returns true if the name is empty
     pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        self.name.is_empty()
     }
 
     /// Emits the canonical version of the name to the encoder.
@@ -150,18 +162,23 @@ impl LowerName {
         encoder: &mut BinEncoder<'_>,
         canonical: bool,
     ) -> ProtoResult<()> {
-        self.0.emit_as_canonical(encoder, canonical)
+        self.name.emit_as_canonical(encoder, canonical)
     }
 
     /// Pass through for Name::is_wildcard
     pub fn is_wildcard(&self) -> bool {
-        self.0.is_wildcard()
+        self.name.is_wildcard()
     }
 
     /// Replaces the first label with the wildcard character, "*"
     pub fn into_wildcard(self) -> Self {
-        let name = self.0.into_wildcard();
-        Self(name)
+        Self::from_lowercase(self.name.into_wildcard())
+    }
+}
+
+impl Default for LowerName {
+    fn default() -> Self {
+        Self::from_lowercase(Name::default())
     }
 }
 
@@ -170,15 +187,13 @@ impl Hash for LowerName {
     where
         H: Hasher,
     {
-        for label in &self.0 {
-            state.write(label);
-        }
+        state.write_u64(self.hash);
     }
 }
 
 impl PartialEq<Self> for LowerName {
     fn eq(&self, other: &Self) -> bool {
-        self.0.eq_case(&other.0)
+        self.hash == other.hash && self.name.eq_case(&other.name)
     }
 }
 
@@ -191,7 +206,7 @@ impl BinEncodable for LowerName {
 
 impl fmt::Display for LowerName {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.0.fmt(f)
+        self.name.fmt(f)
     }
 }
 
@@ -238,7 +253,7 @@ impl Ord for LowerName {
     ///            \200.z.example
     /// ```
     fn cmp(&self, other: &Self) -> Ordering {
-        self.0.cmp_case(&other.0)
+        self.name.cmp_case(&other.name)
     }
 }
 
@@ -256,19 +271,19 @@ impl<'a> From<&'a Name> for LowerName {
 
 impl From<LowerName> for Name {
     fn from(name: LowerName) -> Self {
-        name.0
+        name.name
     }
 }
 
 impl<'a> From<&'a LowerName> for Name {
     fn from(name: &'a LowerName) -> Self {
-        name.0.clone()
+        name.name.clone()
     }
 }
 
 impl Borrow<Name> for LowerName {
     fn borrow(&self) -> &Name {
-        &self.0
+        &self.name
     }
 }
 
@@ -279,7 +294,7 @@ impl<'r> BinDecodable<'r> for LowerName {
     /// This will consume the portions of the Vec which it is reading...
     fn read(decoder: &mut BinDecoder<'r>) -> ProtoResult<Self> {
         let name = Name::read(decoder)?;
-        Ok(Self(name.to_lowercase()))
+        Ok(Self::new(&name))
     }
 }
 
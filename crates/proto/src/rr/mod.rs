@@ -20,6 +20,8 @@ pub mod record_type;
 pub mod resource;
 mod rr_key;
 mod rr_set;
+#[cfg(any(feature = "dnssec", feature = "rdata-csync"))]
+#[cfg_attr(docsrs, doc(cfg(any(feature = "dnssec", feature = "rdata-csync"))))]
 pub mod type_bit_map;
 
 use std::fmt::{Debug, Display};
@@ -30,7 +32,7 @@ use crate::{
 };
 
 pub use self::dns_class::DNSClass;
-pub use self::domain::{IntoName, Name, TryParseIp};
+pub use self::domain::{IntoName, Name, StaticName, TryParseIp};
 pub use self::record_data::RData;
 pub use self::record_type::RecordType;
 pub use self::resource::Record;
@@ -0,0 +1,308 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! AMTRELAY records for discovery of Automatic Multicast Tunneling relays
+
+use std::{
+    fmt,
+    net::{Ipv4Addr, Ipv6Addr},
+};
+
+#[cfg(feature = "serde-config")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{ProtoErrorKind, ProtoResult},
+    rr::{
+        domain::Name,
+        rdata::{A, AAAA},
+        RData, RecordData, RecordDataDecodable, RecordType,
+    },
+    serialize::binary::{BinDecodable, BinDecoder, BinEncodable, BinEncoder, Restrict},
+};
+
+/// [RFC 8777](https://tools.ietf.org/html/rfc8777#section-4.2)
+///
+/// ```text
+/// 4.2.  RDATA Format
+///
+///    The AMTRELAY RType has the following RDATA format:
+///
+///        0                   1                   2                   3
+///        0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+///       +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///       |   precedence  |D|    type     |                               |
+///       +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+                               +
+///       ~                            relay                              ~
+///       +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[cfg_attr(feature = "serde-config", derive(Deserialize, Serialize))]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct AMTRELAY {
+    precedence: u8,
+    discovery_optional: bool,
+    relay: Relay,
+}
+
+impl AMTRELAY {
+    /// Creates a new AMTRELAY record data.
+    ///
+    /// # Arguments
+    ///
+    /// * `precedence` - the precedence of this record, lower values are preferred.
+    /// * `discovery_optional` - the D-bit, if set the gateway MAY skip AMT Discovery for this relay.
+    /// * `relay` - the address or name of the AMT relay.
+    pub fn new(precedence: u8, discovery_optional: bool, relay: Relay) -> Self {
+        Self {
+            precedence,
+            discovery_optional,
+            relay,
+        }
+    }
+
+    /// The precedence of this record, relays with lower values are preferred.
+    pub fn precedence(&self) -> u8 {
+        self.precedence
+    }
+
+    /// The Discovery Optional flag (D-bit)
+    pub fn discovery_optional(&self) -> bool {
+        self.discovery_optional
+    }
+
+    /// The address or name of the AMT relay.
+    pub fn relay(&self) -> &Relay {
+        &self.relay
+    }
+}
+
+/// ```text
+/// 4.2.3.  RDATA Format - Type
+///
+///    The type field indicates the format of the information that is
+///    stored in the relay field.
+///
+///    The following values are defined:
+///
+///    o  type = 0:  The relay field is empty (0 bytes).
+///    o  type = 1:  The relay field contains a 4-octet IPv4 address.
+///    o  type = 2:  The relay field contains a 16-octet IPv6 address.
+///    o  type = 3:  The relay field contains a wire-encoded domain name.
+///       The wire-encoded format is self-describing, so the length is
+///       implicit.  The domain name MUST NOT be compressed.
+/// ```
+#[cfg_attr(feature = "serde-config", derive(Deserialize, Serialize))]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum Relay {
+    /// No relay is present
+    None,
+    /// An IPv4 relay address
+    Ipv4(Ipv4Addr),
+    /// An IPv6 relay address
+    Ipv6(Ipv6Addr),
+    /// A relay identified by its domain name
+    Name(Name),
+}
+
+impl Relay {
+    /// The relay type code associated with this relay
+    pub fn relay_type(&self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Ipv4(..) => 1,
+            Self::Ipv6(..) => 2,
+            Self::Name(..) => 3,
+        }
+    }
+}
+
+/// The relay presentation format, `.` is used when no relay is present
+impl fmt::Display for Relay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            Self::None => f.write_str("."),
+            Self::Ipv4(addr) => write!(f, "{addr}"),
+            Self::Ipv6(addr) => write!(f, "{addr}"),
+            Self::Name(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+impl BinEncodable for AMTRELAY {
+    fn emit(&self, encoder: &mut BinEncoder<'_>) -> ProtoResult<()> {
+        encoder.emit_u8(self.precedence)?;
+
+        let d_bit = if self.discovery_optional {
+            0b1000_0000
+        } else {
+            0
+        };
+        encoder.emit_u8(d_bit | self.relay.relay_type())?;
+
+        match self.relay {
+            Relay::None => Ok(()),
+            Relay::Ipv4(addr) => A(addr).emit(encoder),
+            Relay::Ipv6(addr) => AAAA(addr).emit(encoder),
+            // the name MUST NOT be compressed
+            Relay::Name(ref name) => name.emit_as_canonical(encoder, true),
+        }
+    }
+}
+
+impl<'r> RecordDataDecodable<'r> for AMTRELAY {
+    fn read_data(decoder: &mut BinDecoder<'r>, _length: Restrict<u16>) -> ProtoResult<Self> {
+        let precedence = decoder.read_u8()?.unverified(/*any u8 is valid*/);
+        let d_bit_and_type = decoder.read_u8()?.unverified(/*type is verified below*/);
+
+        let discovery_optional = d_bit_and_type & 0b1000_0000 != 0;
+        let relay = match d_bit_and_type & 0b0111_1111 {
+            0 => Relay::None,
+            1 => Relay::Ipv4(A::read(decoder)?.0),
+            2 => Relay::Ipv6(AAAA::read(decoder)?.0),
+            3 => Relay::Name(Name::read(decoder)?),
+            relay_type => {
+                return Err(ProtoErrorKind::Msg(format!(
+                    "unrecognized AMTRELAY relay type: {relay_type}"
+                ))
+                .into())
+            }
+        };
+
+        Ok(Self::new(precedence, discovery_optional, relay))
+    }
+}
+
+impl RecordData for AMTRELAY {
+    fn try_from_rdata(data: RData) -> Result<Self, RData> {
+        match data {
+            RData::AMTRELAY(data) => Ok(data),
+            _ => Err(data),
+        }
+    }
+
+    fn try_borrow(data: &RData) -> Option<&Self> {
+        match data {
+            RData::AMTRELAY(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    fn record_type(&self) -> RecordType {
+        RecordType::AMTRELAY
+    }
+
+    fn into_rdata(self) -> RData {
+        RData::AMTRELAY(self)
+    }
+}
+
+/// [RFC 8777](https://tools.ietf.org/html/rfc8777#section-4.3)
+///
+/// ```text
+/// 4.3.  AMTRELAY Record Presentation Format
+///
+///    The presentation format of the AMTRELAY RR is:
+///
+///       IN AMTRELAY precedence D-bit type relay
+///
+///    The D-bit is presented as 0 or 1, the type as an unsigned decimal
+///    integer, and a relay of type 0 is presented as a single period (".").
+/// ```
+impl fmt::Display for AMTRELAY {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            "{precedence} {d_bit} {relay_type} {relay}",
+            precedence = self.precedence,
+            d_bit = u8::from(self.discovery_optional),
+            relay_type = self.relay.relay_type(),
+            relay = self.relay,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn test_encode_decode(rdata: AMTRELAY, result: &[u8]) {
+        let mut bytes = Vec::new();
+        let mut encoder = BinEncoder::new(&mut bytes);
+        rdata.emit(&mut encoder).expect("failed to emit AMTRELAY");
+        let bytes = encoder.into_bytes();
+        assert_eq!(bytes, &result);
+
+        let mut decoder = BinDecoder::new(result);
+        let read_rdata = AMTRELAY::read_data(&mut decoder, Restrict::new(result.len() as u16))
+            .expect("failed to read AMTRELAY");
+        assert_eq!(read_rdata, rdata)
+    }
+
+    #[test]
+    fn test_encode_decode_amtrelay() {
+        test_encode_decode(AMTRELAY::new(10, false, Relay::None), &[10, 0]);
+        test_encode_decode(
+            AMTRELAY::new(10, true, Relay::Ipv4(Ipv4Addr::new(203, 0, 113, 15))),
+            &[10, 0b1000_0001, 203, 0, 113, 15],
+        );
+        test_encode_decode(
+            AMTRELAY::new(
+                128,
+                false,
+                Relay::Ipv6(Ipv6Addr::from_str("2001:db8::15").unwrap()),
+            ),
+            &[
+                128, 2, 0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x15,
+            ],
+        );
+        test_encode_decode(
+            AMTRELAY::new(
+                0,
+                true,
+                Relay::Name(Name::from_str("amtrelays.example.com.").unwrap()),
+            ),
+            &[
+                0,
+                0b1000_0011,
+                9,
+                b'a',
+                b'm',
+                b't',
+                b'r',
+                b'e',
+                b'l',
+                b'a',
+                b'y',
+                b's',
+                7,
+                b'e',
+                b'x',
+                b'a',
+                b'm',
+                b'p',
+                b'l',
+                b'e',
+                3,
+                b'c',
+                b'o',
+                b'm',
+                0,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let rdata = AMTRELAY::new(10, true, Relay::Ipv4(Ipv4Addr::new(203, 0, 113, 15)));
+        assert_eq!(rdata.to_string(), "10 1 1 203.0.113.15");
+
+        let rdata = AMTRELAY::new(10, false, Relay::None);
+        assert_eq!(rdata.to_string(), "10 0 0 .");
+    }
+}
@@ -0,0 +1,416 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! IPSECKEY records for storing IPsec keying material
+#![allow(clippy::use_self)]
+
+use std::{
+    fmt,
+    net::{Ipv4Addr, Ipv6Addr},
+};
+
+#[cfg(feature = "serde-config")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{ProtoError, ProtoErrorKind, ProtoResult},
+    rr::{
+        domain::Name,
+        rdata::{A, AAAA},
+        RData, RecordData, RecordDataDecodable, RecordType,
+    },
+    serialize::binary::{
+        BinDecodable, BinDecoder, BinEncodable, BinEncoder, Restrict, RestrictedMath,
+    },
+};
+
+/// [RFC 4025](https://tools.ietf.org/html/rfc4025#section-2.1)
+///
+/// ```text
+/// 2.1.  IPSECKEY RDATA Wire Format
+///
+///    The RDATA for an IPSECKEY RR consists of a precedence value, a
+///    gateway type, a public key, algorithm type, and an optional gateway
+///    address.
+///
+///        0                   1                   2                   3
+///        0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+///       +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///       |  precedence   | gateway type  |  algorithm  |     gateway     |
+///       +---------------+---------------+-------------+                 +
+///       ~                            gateway                            ~
+///       +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///       |                                                               /
+///       /                          public key                           /
+///       /                                                               /
+///       +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-|
+/// ```
+#[cfg_attr(feature = "serde-config", derive(Deserialize, Serialize))]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct IPSECKEY {
+    precedence: u8,
+    algorithm: Algorithm,
+    gateway: Gateway,
+    public_key: Vec<u8>,
+}
+
+impl IPSECKEY {
+    /// Creates a new IPSECKEY record data.
+    ///
+    /// # Arguments
+    ///
+    /// * `precedence` - the precedence of this record, lower values are preferred.
+    /// * `algorithm` - the algorithm of the public key.
+    /// * `gateway` - the gateway to which the IPsec tunnel may be created.
+    /// * `public_key` - the public key, in the format specified by the algorithm.
+    pub fn new(
+        precedence: u8,
+        algorithm: Algorithm,
+        gateway: Gateway,
+        public_key: Vec<u8>,
+    ) -> Self {
+        Self {
+            precedence,
+            algorithm,
+            gateway,
+            public_key,
+        }
+    }
+
+    /// The precedence of this record, gateways with lower values are preferred.
+    pub fn precedence(&self) -> u8 {
+        self.precedence
+    }
+
+    /// The algorithm of the public key.
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// The gateway to which the IPsec tunnel may be created.
+    pub fn gateway(&self) -> &Gateway {
+        &self.gateway
+    }
+
+    /// The public key, empty if no key is present.
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+}
+
+/// ```text
+/// 2.3.  Gateway Type
+///
+///    The gateway type field indicates the format of the information that
+///    is stored in the gateway field.
+///
+///    The following values are defined:
+///       0  No gateway is present.
+///       1  A 4-byte IPv4 address is present.
+///       2  A 16-byte IPv6 address is present.
+///       3  A wire-encoded domain name is present.  The wire-encoded format is
+///          self-describing, so the length is implicit.  The domain name MUST
+///          NOT be compressed.  (See Section 3.3 of RFC 1035.)
+/// ```
+#[cfg_attr(feature = "serde-config", derive(Deserialize, Serialize))]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum Gateway {
+    /// No gateway is present
+    None,
+    /// An IPv4 gateway address
+    Ipv4(Ipv4Addr),
+    /// An IPv6 gateway address
+    Ipv6(Ipv6Addr),
+    /// A gateway identified by its domain name
+    Name(Name),
+}
+
+impl Gateway {
+    /// The gateway type code associated with this gateway
+    pub fn gateway_type(&self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Ipv4(..) => 1,
+            Self::Ipv6(..) => 2,
+            Self::Name(..) => 3,
+        }
+    }
+
+    /// Reads a gateway of the specified type from the decoder
+    pub(crate) fn read(decoder: &mut BinDecoder<'_>, gateway_type: u8) -> ProtoResult<Self> {
+        Ok(match gateway_type {
+            0 => Self::None,
+            1 => Self::Ipv4(A::read(decoder)?.0),
+            2 => Self::Ipv6(AAAA::read(decoder)?.0),
+            3 => Self::Name(Name::read(decoder)?),
+            _ => {
+                return Err(ProtoErrorKind::Msg(format!(
+                    "unrecognized gateway type: {gateway_type}"
+                ))
+                .into())
+            }
+        })
+    }
+
+    /// Emits the gateway, the gateway type must be emitted separately
+    pub(crate) fn emit(&self, encoder: &mut BinEncoder<'_>) -> ProtoResult<()> {
+        match self {
+            Self::None => Ok(()),
+            Self::Ipv4(addr) => A(*addr).emit(encoder),
+            Self::Ipv6(addr) => AAAA(*addr).emit(encoder),
+            // the name MUST NOT be compressed
+            Self::Name(name) => name.emit_as_canonical(encoder, true),
+        }
+    }
+}
+
+/// The gateway presentation format, `.` is used when no gateway is present
+impl fmt::Display for Gateway {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            Self::None => f.write_str("."),
+            Self::Ipv4(addr) => write!(f, "{addr}"),
+            Self::Ipv6(addr) => write!(f, "{addr}"),
+            Self::Name(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+/// ```text
+/// 2.4.  Algorithm Type Field
+///
+///    The algorithm type field identifies the public key's cryptographic
+///    algorithm and determines the format of the public key field.
+///
+///    A value of 0 indicates that no key is present.
+///
+///    The following values are defined:
+///       1  A DSA key is present, in the format defined in RFC 2536.
+///       2  A RSA key is present, in the format defined in RFC 3110.
+/// ```
+///
+/// The algorithm values have been updated in
+/// [RFC 8005](https://tools.ietf.org/html/rfc8005) and
+/// [RFC 9373](https://tools.ietf.org/html/rfc9373).
+#[cfg_attr(feature = "serde-config", derive(Deserialize, Serialize))]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Algorithm {
+    /// No key is present
+    None,
+
+    /// DSA
+    DSA,
+
+    /// RSA
+    RSA,
+
+    /// ECDSA
+    ECDSA,
+
+    /// EdDSA
+    EdDSA,
+
+    /// Unassigned value
+    Unassigned(u8),
+}
+
+impl From<u8> for Algorithm {
+    fn from(alg: u8) -> Self {
+        match alg {
+            0 => Self::None,
+            1 => Self::DSA,
+            2 => Self::RSA,
+            3 => Self::ECDSA,
+            4 => Self::EdDSA,
+            _ => Self::Unassigned(alg),
+        }
+    }
+}
+
+impl From<Algorithm> for u8 {
+    fn from(algorithm: Algorithm) -> Self {
+        match algorithm {
+            Algorithm::None => 0,
+            Algorithm::DSA => 1,
+            Algorithm::RSA => 2,
+            Algorithm::ECDSA => 3,
+            Algorithm::EdDSA => 4,
+            Algorithm::Unassigned(alg) => alg,
+        }
+    }
+}
+
+impl BinEncodable for IPSECKEY {
+    fn emit(&self, encoder: &mut BinEncoder<'_>) -> ProtoResult<()> {
+        encoder.emit_u8(self.precedence)?;
+        encoder.emit_u8(self.gateway.gateway_type())?;
+        encoder.emit_u8(self.algorithm.into())?;
+        self.gateway.emit(encoder)?;
+        encoder.emit_vec(&self.public_key)
+    }
+}
+
+impl<'r> RecordDataDecodable<'r> for IPSECKEY {
+    fn read_data(decoder: &mut BinDecoder<'r>, length: Restrict<u16>) -> ProtoResult<Self> {
+        let start_idx = decoder.index();
+
+        let precedence = decoder.read_u8()?.unverified(/*any u8 is valid*/);
+        let gateway_type = decoder.read_u8()?.unverified(/*verified in Gateway::read*/);
+        let algorithm = decoder.read_u8()?.unverified(/*any u8 is valid*/).into();
+        let gateway = Gateway::read(decoder, gateway_type)?;
+
+        let public_key_len = length
+            .map(|l| l as usize)
+            .checked_sub(decoder.index() - start_idx)
+            .map_err(|_| ProtoError::from("invalid rdata length in IPSECKEY"))?
+            .unverified(/*used only as length safely*/);
+        let public_key = decoder.read_vec(public_key_len)?.unverified();
+
+        Ok(Self::new(precedence, algorithm, gateway, public_key))
+    }
+}
+
+impl RecordData for IPSECKEY {
+    fn try_from_rdata(data: RData) -> Result<Self, RData> {
+        match data {
+            RData::IPSECKEY(data) => Ok(data),
+            _ => Err(data),
+        }
+    }
+
+    fn try_borrow(data: &RData) -> Option<&Self> {
+        match data {
+            RData::IPSECKEY(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    fn record_type(&self) -> RecordType {
+        RecordType::IPSECKEY
+    }
+
+    fn into_rdata(self) -> RData {
+        RData::IPSECKEY(self)
+    }
+}
+
+/// [RFC 4025](https://tools.ietf.org/html/rfc4025#section-3.1)
+///
+/// ```text
+/// 3.1.  Representation of IPSECKEY RRs
+///
+///    IPSECKEY RRs may appear in a zone data master file.  The precedence,
+///    gateway type, algorithm, and gateway fields are REQUIRED.  The base64
+///    encoded public key block is OPTIONAL; if it is not present, the public
+///    key field of the resource record MUST be construed to be zero octets
+///    in length.
+///
+///    IN     IPSECKEY ( precedence gateway-type algorithm
+///                      gateway base64-encoded-public-key )
+/// ```
+impl fmt::Display for IPSECKEY {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            "{precedence} {gateway_type} {algorithm} {gateway}",
+            precedence = self.precedence,
+            gateway_type = self.gateway.gateway_type(),
+            algorithm = u8::from(self.algorithm),
+            gateway = self.gateway,
+        )?;
+
+        if !self.public_key.is_empty() {
+            write!(
+                f,
+                " {public_key}",
+                public_key = data_encoding::BASE64.encode(&self.public_key)
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn test_encode_decode(rdata: IPSECKEY, result: &[u8]) {
+        let mut bytes = Vec::new();
+        let mut encoder = BinEncoder::new(&mut bytes);
+        rdata.emit(&mut encoder).expect("failed to emit IPSECKEY");
+        let bytes = encoder.into_bytes();
+        assert_eq!(bytes, &result);
+
+        let mut decoder = BinDecoder::new(result);
+        let read_rdata = IPSECKEY::read_data(&mut decoder, Restrict::new(result.len() as u16))
+            .expect("failed to read IPSECKEY");
+        assert_eq!(read_rdata, rdata)
+    }
+
+    #[test]
+    fn test_encode_decode_ipseckey() {
+        test_encode_decode(
+            IPSECKEY::new(10, Algorithm::None, Gateway::None, vec![]),
+            &[10, 0, 0],
+        );
+        test_encode_decode(
+            IPSECKEY::new(
+                10,
+                Algorithm::RSA,
+                Gateway::Ipv4(Ipv4Addr::new(192, 0, 2, 38)),
+                vec![1, 2, 3],
+            ),
+            &[10, 1, 2, 192, 0, 2, 38, 1, 2, 3],
+        );
+        test_encode_decode(
+            IPSECKEY::new(
+                10,
+                Algorithm::DSA,
+                Gateway::Ipv6(Ipv6Addr::from_str("2001:db8::1").unwrap()),
+                vec![4],
+            ),
+            &[
+                10, 2, 1, 0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 4,
+            ],
+        );
+        test_encode_decode(
+            IPSECKEY::new(
+                10,
+                Algorithm::RSA,
+                Gateway::Name(Name::from_str("gw.example.").unwrap()),
+                vec![5, 6],
+            ),
+            &[
+                10, 3, 2, 2, b'g', b'w', 7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 0, 5, 6,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_bad_gateway_type() {
+        let data = [10, 4, 2, 1, 2, 3];
+        let mut decoder = BinDecoder::new(&data);
+        assert!(IPSECKEY::read_data(&mut decoder, Restrict::new(data.len() as u16)).is_err());
+    }
+
+    #[test]
+    fn test_display() {
+        let rdata = IPSECKEY::new(
+            10,
+            Algorithm::RSA,
+            Gateway::Ipv4(Ipv4Addr::new(192, 0, 2, 38)),
+            b"key".to_vec(),
+        );
+        assert_eq!(rdata.to_string(), "10 1 2 192.0.2.38 a2V5");
+
+        let rdata = IPSECKEY::new(10, Algorithm::None, Gateway::None, vec![]);
+        assert_eq!(rdata.to_string(), "10 0 0 .");
+    }
+}
@@ -12,38 +12,99 @@
 // each of these module's has the parser for that rdata embedded, to keep the file sizes down...
 pub mod a;
 pub mod aaaa;
+#[cfg(feature = "rdata-amtrelay")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rdata-amtrelay")))]
+pub mod amtrelay;
+#[cfg(feature = "rdata-caa")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rdata-caa")))]
 pub mod caa;
+#[cfg(feature = "rdata-csync")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rdata-csync")))]
 pub mod csync;
+#[cfg(feature = "rdata-hinfo")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rdata-hinfo")))]
 pub mod hinfo;
+#[cfg(feature = "rdata-svcb")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rdata-svcb")))]
 pub mod https;
+#[cfg(feature = "rdata-ipseckey")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rdata-ipseckey")))]
+pub mod ipseckey;
 pub mod mx;
 pub mod name;
+#[cfg(feature = "rdata-naptr")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rdata-naptr")))]
 pub mod naptr;
 pub mod null;
+#[cfg(feature = "rdata-openpgpkey")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rdata-openpgpkey")))]
 pub mod openpgpkey;
 pub mod opt;
 pub mod soa;
 pub mod srv;
+#[cfg(feature = "rdata-sshfp")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rdata-sshfp")))]
 pub mod sshfp;
+#[cfg(feature = "rdata-svcb")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rdata-svcb")))]
 pub mod svcb;
+#[cfg(feature = "rdata-tlsa")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rdata-tlsa")))]
 pub mod tlsa;
 pub mod txt;
 
 pub use self::a::A;
 pub use self::aaaa::AAAA;
+#[cfg(feature = "rdata-amtrelay")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rdata-amtrelay")))]
+pub use self::amtrelay::AMTRELAY;
+#[cfg(feature = "rdata-caa")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rdata-caa")))]
 pub use self::caa::CAA;
+#[cfg(feature = "rdata-csync")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rdata-csync")))]
 pub use self::csync::CSYNC;
+#[cfg(feature = "rdata-hinfo")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rdata-hinfo")))]
 pub use self::hinfo::HINFO;
+#[cfg(feature = "rdata-svcb")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rdata-svcb")))]
 pub use self::https::HTTPS;
+#[cfg(feature = "rdata-ipseckey")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rdata-ipseckey")))]
+pub use self::ipseckey::IPSECKEY;
 pub use self::mx::MX;
-pub use self::name::{ANAME, CNAME, NS, PTR};
+pub use self::name::{ANAME, CNAME, DNAME, NS, PTR};
+#[cfg(feature = "rdata-naptr")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rdata-naptr")))]
 pub use self::naptr::NAPTR;
 pub use self::null::NULL;
+#[cfg(feature = "rdata-openpgpkey")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rdata-openpgpkey")))]
 pub use self::openpgpkey::OPENPGPKEY;
 pub use self::opt::OPT;
 pub use self::soa::SOA;
 pub use self::srv::SRV;
+#[cfg(feature = "rdata-sshfp")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rdata-sshfp")))]
 pub use self::sshfp::SSHFP;
+#[cfg(feature = "rdata-svcb")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rdata-svcb")))]
 pub use self::svcb::SVCB;
+#[cfg(feature = "rdata-tlsa")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rdata-tlsa")))]
 pub use self::tlsa::TLSA;
 pub use self::txt::TXT;
+
+use data_encoding::{Encoding, Specification};
+use once_cell::sync::Lazy;
+
+/// HEX formatting specific to TLSA and SSHFP encodings, and the generic encoding of RFC 3597
+pub static HEX: Lazy<Encoding> = Lazy::new(|| {
+    let mut spec = Specification::new();
+    spec.symbols.push_str("0123456789abcdef");
+    spec.ignore.push_str(" \t\r\n");
+    spec.translate.from.push_str("ABCDEF");
+    spec.translate.to.push_str("abcdef");
+    spec.encoding().expect("error in HEX encoding")
+});
@@ -7,7 +7,7 @@
 
 //! Record type for all cname like records.
 //!
-//! A generic struct for all {*}NAME pointer RData records, CNAME, NS, and PTR. Here is the text for
+//! A generic struct for all {*}NAME pointer RData records, CNAME, DNAME, NS, and PTR. Here is the text for
 //! CNAME from RFC 1035, Domain Implementation and Specification, November 1987:
 //!
 //! [RFC 1035, DOMAIN NAMES - IMPLEMENTATION AND SPECIFICATION, November 1987](https://tools.ietf.org/html/rfc1035)
@@ -135,6 +135,28 @@ name_rdata!(CNAME);
 name_rdata!(NS);
 name_rdata!(PTR);
 name_rdata!(ANAME);
+name_rdata!(DNAME);
+
+impl DNAME {
+    /// The name which `name` is redirected to by this DNAME record, owned by `owner`
+    ///
+    /// The `owner` suffix of `name` is replaced by the target, see
+    ///  [RFC 6672, section 2.2](https://tools.ietf.org/html/rfc6672#section-2.2). The DNAME does
+    ///  not apply to its owner itself.
+    ///
+    /// # Returns
+    ///
+    /// An error if `name` is not below `owner`, or if the substituted name is too long, to which
+    ///  a server responds with YXDOMAIN
+    pub fn substitute(&self, owner: &Name, name: &Name) -> ProtoResult<Name> {
+        if name.num_labels() <= owner.num_labels() || !owner.zone_of(name) {
+            return Err(format!("{name} is not below the DNAME owner {owner}").into());
+        }
+
+        let prefix = usize::from(name.num_labels() - owner.num_labels());
+        Name::from_labels(name.iter().take(prefix))?.append_domain(&self.0)
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -163,4 +185,27 @@ mod tests {
         let read_rdata = read(&mut decoder).expect("Decoding error");
         assert_eq!(rdata, read_rdata);
     }
+
+    #[test]
+    fn test_dname_substitute() {
+        let owner = Name::from_ascii("example.com.").unwrap();
+        let dname = DNAME(Name::from_ascii("example.net.").unwrap());
+
+        assert_eq!(
+            dname
+                .substitute(&owner, &Name::from_ascii("a.b.Example.com.").unwrap())
+                .unwrap(),
+            Name::from_ascii("a.b.example.net.").unwrap()
+        );
+        assert!(dname.substitute(&owner, &owner).is_err());
+        assert!(dname
+            .substitute(&owner, &Name::from_ascii("www.example.org.").unwrap())
+            .is_err());
+
+        // the substituted name exceeds 255 octets
+        let long = DNAME(Name::from_ascii(format!("{}.net.", "x".repeat(63))).unwrap());
+        let label = "y".repeat(63);
+        let name = Name::from_ascii(format!("{label}.{label}.{label}.example.com.")).unwrap();
+        assert!(long.substitute(&owner, &name).is_err());
+    }
 }
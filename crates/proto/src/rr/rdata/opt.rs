@@ -15,7 +15,7 @@ use std::str::FromStr;
 #[cfg(feature = "serde-config")]
 use serde::{Deserialize, Serialize};
 
-use tracing::warn;
+use crate::logging::warn;
 
 use crate::{
     error::{ProtoError, ProtoErrorKind, ProtoResult},
@@ -13,9 +13,6 @@ use std::fmt;
 #[cfg(feature = "serde-config")]
 use serde::{Deserialize, Serialize};
 
-use data_encoding::{Encoding, Specification};
-use once_cell::sync::Lazy;
-
 use crate::{
     error::{ProtoError, ProtoResult},
     rr::{RData, RecordData, RecordDataDecodable, RecordType},
@@ -23,14 +20,7 @@ use crate::{
 };
 
 /// HEX formatting specific to TLSA and SSHFP encodings
-pub static HEX: Lazy<Encoding> = Lazy::new(|| {
-    let mut spec = Specification::new();
-    spec.symbols.push_str("0123456789abcdef");
-    spec.ignore.push_str(" \t\r\n");
-    spec.translate.from.push_str("ABCDEF");
-    spec.translate.to.push_str("abcdef");
-    spec.encoding().expect("error in sshfp HEX encoding")
-});
+pub use super::HEX;
 
 /// [RFC 4255](https://tools.ietf.org/html/rfc4255#section-3.1)
 ///
@@ -1063,6 +1063,46 @@ impl BinEncodable for SVCB {
     }
 }
 
+/// Reads the SvcParams which follow the TargetName in the RDATA of SVCB, from the next `len` bytes
+///
+/// SvcParams are also carried outside of SVCB records, e.g. in the DNR options of DHCP and Router
+///  Advertisements, [RFC 9463](https://www.rfc-editor.org/rfc/rfc9463). The same rules for
+///  malformed SvcParams apply there.
+pub fn read_svc_params(
+    decoder: &mut BinDecoder<'_>,
+    len: usize,
+) -> ProtoResult<Vec<(SvcParamKey, SvcParamValue)>> {
+    let start_index = decoder.index();
+    let remainder = |decoder: &BinDecoder<'_>| {
+        len.checked_sub(decoder.index() - start_index)
+            .ok_or_else(|| ProtoError::from(format!("Bad length for SvcParams: {len}")))
+    };
+
+    let mut svc_params: Vec<(SvcParamKey, SvcParamValue)> = Vec::new();
+
+    // must have at least 4 bytes left for the key and the length
+    while remainder(decoder)? >= 4 {
+        // a 2 octet field containing the SvcParamKey as an integer in
+        //      network byte order.  (See Section 14.3.2 for the defined values.)
+        let key = SvcParamKey::read(decoder)?;
+
+        // a 2 octet field containing the length of the SvcParamValue as an
+        //      integer between 0 and 65535 in network byte order (but constrained
+        //      by the RDATA and DNS message sizes).
+        let value = SvcParamValue::read(key, decoder)?;
+
+        if let Some(last_key) = svc_params.last().map(|(key, _)| key) {
+            if last_key >= &key {
+                return Err(ProtoError::from("SvcParams out of order"));
+            }
+        }
+
+        svc_params.push((key, value));
+    }
+
+    Ok(svc_params)
+}
+
 impl<'r> RecordDataDecodable<'r> for SVCB {
     /// Reads the SVCB record from the decoder.
     ///
@@ -1086,37 +1126,12 @@ impl<'r> RecordDataDecodable<'r> for SVCB {
         let svc_priority = decoder.read_u16()?.unverified(/*any u16 is valid*/);
         let target_name = Name::read(decoder)?;
 
-        let mut remainder_len = rdata_length
+        let remainder_len = rdata_length
             .map(|len| len as usize)
             .checked_sub(decoder.index() - start_index)
             .map_err(|len| format!("Bad length for RDATA of SVCB: {len}"))?
             .unverified(); // valid len
-        let mut svc_params: Vec<(SvcParamKey, SvcParamValue)> = Vec::new();
-
-        // must have at least 4 bytes left for the key and the length
-        while remainder_len >= 4 {
-            // a 2 octet field containing the SvcParamKey as an integer in
-            //      network byte order.  (See Section 14.3.2 for the defined values.)
-            let key = SvcParamKey::read(decoder)?;
-
-            // a 2 octet field containing the length of the SvcParamValue as an
-            //      integer between 0 and 65535 in network byte order (but constrained
-            //      by the RDATA and DNS message sizes).
-            let value = SvcParamValue::read(key, decoder)?;
-
-            if let Some(last_key) = svc_params.last().map(|(key, _)| key) {
-                if last_key >= &key {
-                    return Err(ProtoError::from("SvcParams out of order"));
-                }
-            }
-
-            svc_params.push((key, value));
-            remainder_len = rdata_length
-                .map(|len| len as usize)
-                .checked_sub(decoder.index() - start_index)
-                .map_err(|len| format!("Bad length for RDATA of SVCB: {len}"))?
-                .unverified(); // valid len
-        }
+        let svc_params = read_svc_params(decoder, remainder_len)?;
 
         Ok(Self {
             svc_priority,
@@ -13,7 +13,7 @@ use std::fmt;
 #[cfg(feature = "serde-config")]
 use serde::{Deserialize, Serialize};
 
-use super::sshfp;
+use super::HEX;
 
 use crate::{
     error::{ProtoError, ProtoResult},
@@ -468,7 +468,7 @@ impl fmt::Display for TLSA {
             usage = u8::from(self.cert_usage),
             selector = u8::from(self.selector),
             matching = u8::from(self.matching),
-            cert = sshfp::HEX.encode(&self.cert_data),
+            cert = HEX.encode(&self.cert_data),
         )
     }
 }
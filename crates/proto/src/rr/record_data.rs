@@ -15,24 +15,50 @@ use std::{cmp::Ordering, fmt, net::IpAddr};
 #[cfg(feature = "serde-config")]
 use serde::{Deserialize, Serialize};
 
+use crate::logging::{trace, warn};
 use enum_as_inner::EnumAsInner;
-use tracing::{trace, warn};
 
 use crate::{
     error::{ProtoError, ProtoErrorKind, ProtoResult},
     rr::{
-        rdata::{
-            A, AAAA, ANAME, CAA, CNAME, CSYNC, HINFO, HTTPS, MX, NAPTR, NS, NULL, OPENPGPKEY, OPT,
-            PTR, SOA, SRV, SSHFP, SVCB, TLSA, TXT,
-        },
+        rdata::{A, AAAA, ANAME, CNAME, DNAME, HEX, MX, NS, NULL, OPT, PTR, SOA, SRV, TXT},
         record_type::RecordType,
         RecordData, RecordDataDecodable,
     },
     serialize::binary::{BinDecodable, BinDecoder, BinEncodable, BinEncoder, Restrict},
 };
 
+#[cfg(feature = "rdata-amtrelay")]
+use crate::rr::rdata::AMTRELAY;
+#[cfg(feature = "rdata-caa")]
+use crate::rr::rdata::CAA;
+#[cfg(feature = "rdata-csync")]
+use crate::rr::rdata::CSYNC;
+#[cfg(feature = "rdata-hinfo")]
+use crate::rr::rdata::HINFO;
+#[cfg(feature = "rdata-svcb")]
+use crate::rr::rdata::HTTPS;
+#[cfg(feature = "rdata-ipseckey")]
+use crate::rr::rdata::IPSECKEY;
+#[cfg(feature = "rdata-naptr")]
+use crate::rr::rdata::NAPTR;
+#[cfg(feature = "rdata-openpgpkey")]
+use crate::rr::rdata::OPENPGPKEY;
+#[cfg(feature = "rdata-sshfp")]
+use crate::rr::rdata::SSHFP;
+#[cfg(feature = "rdata-svcb")]
+use crate::rr::rdata::SVCB;
+#[cfg(feature = "rdata-tlsa")]
+use crate::rr::rdata::TLSA;
+
 #[cfg(feature = "dnssec")]
-use super::dnssec::rdata::DNSSECRData;
+use super::{
+    dnssec::{
+        rdata::{DNSSECRData, RRSIG, SIG},
+        Algorithm,
+    },
+    Name,
+};
 
 /// Record data enum variants for all valid DNS data types.
 ///
@@ -94,6 +120,20 @@ pub enum RData {
     /// ```
     AAAA(AAAA),
 
+    /// [RFC 8777, DNS Reverse IP AMT Discovery](https://tools.ietf.org/html/rfc8777#section-4.2)
+    ///
+    /// ```text
+    ///        0                   1                   2                   3
+    ///        0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+    ///       +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+    ///       |   precedence  |D|    type     |                               |
+    ///       +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+                               +
+    ///       ~                            relay                              ~
+    ///       +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+    /// ```
+    #[cfg(feature = "rdata-amtrelay")]
+    AMTRELAY(AMTRELAY),
+
     /// ```text
     /// 2.  The ANAME resource record
     ///
@@ -137,6 +177,7 @@ pub enum RData {
     /// remaining octets in the Value field (m = d - n - 2) where d is the
     /// length of the RDATA section.
     /// ```
+    #[cfg(feature = "rdata-caa")]
     CAA(CAA),
 
     /// ```text
@@ -189,8 +230,27 @@ pub enum RData {
     /// /                     Type Bit Map (continued)                  /
     /// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
     /// ```
+    #[cfg(feature = "rdata-csync")]
     CSYNC(CSYNC),
 
+    /// [RFC 6672](https://tools.ietf.org/html/rfc6672), DNAME Redirection in the DNS, June 2012
+    ///
+    /// ```text
+    /// 2.1.  Format of the DNAME RR
+    ///
+    ///    The DNAME RR has mnemonic DNAME and type code 39 (decimal).  The
+    ///    format of the DNAME RR is identical to that of the CNAME RR:
+    ///
+    ///    <owner> <ttl> <class> DNAME <target>
+    ///
+    ///    The DNAME RR provides redirection from a part of the DNS name tree to
+    ///    another part of the DNS name tree.
+    ///
+    ///    The DNAME RDATA target name MUST NOT be sent out in compressed form
+    ///    and MUST NOT be compressed when used in the DNAME RDATA.
+    /// ```
+    DNAME(DNAME),
+
     /// ```text
     /// 3.3.2. HINFO RDATA format
     ///
@@ -215,6 +275,7 @@ pub enum RData {
     /// ```
     ///
     /// `HINFO` is also used by [RFC 8482](https://tools.ietf.org/html/rfc8482)
+    #[cfg(feature = "rdata-hinfo")]
     HINFO(HINFO),
 
     /// [RFC draft-ietf-dnsop-svcb-https-03, DNS SVCB and HTTPS RRs](https://datatracker.ietf.org/doc/html/draft-ietf-dnsop-svcb-https-03#section-8)
@@ -238,8 +299,27 @@ pub enum RData {
     ///
     ///    Name TTL IN HTTPS SvcPriority TargetName SvcParams
     /// ```
+    #[cfg(feature = "rdata-svcb")]
     HTTPS(HTTPS),
 
+    /// [RFC 4025, A Method for Storing IPsec Keying Material in DNS](https://tools.ietf.org/html/rfc4025#section-2.1)
+    ///
+    /// ```text
+    ///        0                   1                   2                   3
+    ///        0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+    ///       +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+    ///       |  precedence   | gateway type  |  algorithm  |     gateway     |
+    ///       +---------------+---------------+-------------+                 +
+    ///       ~                            gateway                            ~
+    ///       +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+    ///       |                                                               /
+    ///       /                          public key                           /
+    ///       /                                                               /
+    ///       +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-|
+    /// ```
+    #[cfg(feature = "rdata-ipseckey")]
+    IPSECKEY(IPSECKEY),
+
     /// ```text
     /// 3.3.9. MX RDATA format
     ///
@@ -374,6 +454,7 @@ pub enum RData {
     ///      returned that has values for both fields then it is considered to
     ///      be in error and SHOULD be either ignored or an error returned.
     /// ```
+    #[cfg(feature = "rdata-naptr")]
     NAPTR(NAPTR),
 
     /// ```text
@@ -426,6 +507,7 @@ pub enum RData {
     /// value consisting of a Transferable Public Key formatted as specified
     /// in [RFC4880].
     /// ```
+    #[cfg(feature = "rdata-openpgpkey")]
     OPENPGPKEY(OPENPGPKEY),
 
     /// ```text
@@ -617,6 +699,7 @@ pub enum RData {
     /// The algorithm and fingerprint type values have been updated in
     /// [RFC 6594](https://tools.ietf.org/html/rfc6594) and
     /// [RFC 7479](https://tools.ietf.org/html/rfc7479).
+    #[cfg(feature = "rdata-sshfp")]
     SSHFP(SSHFP),
 
     /// [RFC draft-ietf-dnsop-svcb-https-03, DNS SVCB and HTTPS RRs](https://datatracker.ietf.org/doc/html/draft-ietf-dnsop-svcb-https-03#section-2)
@@ -642,6 +725,7 @@ pub enum RData {
     ///   binary data, or numeric values).  The initial SvcParamKeys and
     ///   formats are defined in Section 6.
     /// ```
+    #[cfg(feature = "rdata-svcb")]
     SVCB(SVCB),
 
     /// [RFC 6698, DNS-Based Authentication for TLS](https://tools.ietf.org/html/rfc6698#section-2.1)
@@ -657,6 +741,7 @@ pub enum RData {
     ///    /                                                               /
     ///    +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
     /// ```
+    #[cfg(feature = "rdata-tlsa")]
     TLSA(TLSA),
 
     /// ```text
@@ -713,23 +798,37 @@ impl RData {
         match *self {
             Self::A(..) => RecordType::A,
             Self::AAAA(..) => RecordType::AAAA,
+            #[cfg(feature = "rdata-amtrelay")]
+            Self::AMTRELAY(..) => RecordType::AMTRELAY,
             Self::ANAME(..) => RecordType::ANAME,
+            #[cfg(feature = "rdata-caa")]
             Self::CAA(..) => RecordType::CAA,
             Self::CNAME(..) => RecordType::CNAME,
+            #[cfg(feature = "rdata-csync")]
             Self::CSYNC(..) => RecordType::CSYNC,
+            Self::DNAME(..) => RecordType::DNAME,
+            #[cfg(feature = "rdata-hinfo")]
             Self::HINFO(..) => RecordType::HINFO,
+            #[cfg(feature = "rdata-svcb")]
             Self::HTTPS(..) => RecordType::HTTPS,
+            #[cfg(feature = "rdata-ipseckey")]
+            Self::IPSECKEY(..) => RecordType::IPSECKEY,
             Self::MX(..) => RecordType::MX,
+            #[cfg(feature = "rdata-naptr")]
             Self::NAPTR(..) => RecordType::NAPTR,
             Self::NS(..) => RecordType::NS,
             Self::NULL(..) => RecordType::NULL,
+            #[cfg(feature = "rdata-openpgpkey")]
             Self::OPENPGPKEY(..) => RecordType::OPENPGPKEY,
             Self::OPT(..) => RecordType::OPT,
             Self::PTR(..) => RecordType::PTR,
             Self::SOA(..) => RecordType::SOA,
             Self::SRV(..) => RecordType::SRV,
+            #[cfg(feature = "rdata-sshfp")]
             Self::SSHFP(..) => RecordType::SSHFP,
+            #[cfg(feature = "rdata-svcb")]
             Self::SVCB(..) => RecordType::SVCB,
+            #[cfg(feature = "rdata-tlsa")]
             Self::TLSA(..) => RecordType::TLSA,
             Self::TXT(..) => RecordType::TXT,
             #[cfg(feature = "dnssec")]
@@ -748,6 +847,98 @@ impl RData {
         }
     }
 
+    /// Returns the canonical wire form of this RData
+    ///
+    /// [RFC 4034](https://tools.ietf.org/html/rfc4034#section-6.2), DNSSEC Resource Records, March 2005
+    ///
+    /// ```text
+    /// 6.2.  Canonical RR Form
+    ///
+    ///    ...
+    ///
+    ///    1.  every domain name in the RR is fully expanded (no DNS name
+    ///        compression) and fully qualified;
+    ///
+    ///    ...
+    ///
+    ///    3.  if the type of the RR is NS, MD, MF, CNAME, SOA, MB, MG, MR, PTR,
+    ///        HINFO, MINFO, MX, HINFO, RP, AFSDB, RT, SIG, PX, NXT, NAPTR, KX,
+    ///        SRV, DNAME, A6, RRSIG, or NSEC, all uppercase US-ASCII letters in
+    ///        the DNS names contained within the RDATA are replaced by the
+    ///        corresponding lowercase US-ASCII letters;
+    /// ```
+    ///
+    /// NSEC is excluded from the list of lowercased types, per
+    ///  [RFC 6840](https://tools.ietf.org/html/rfc6840#section-5.1).
+    pub fn to_canonical_bytes(&self) -> ProtoResult<Vec<u8>> {
+        let mut buf: Vec<u8> = Vec::new();
+        {
+            let mut encoder: BinEncoder<'_> = BinEncoder::new(&mut buf);
+            encoder.set_canonical_names(true);
+
+            match self.to_lowercase_names() {
+                Some(rdata) => rdata.emit(&mut encoder)?,
+                None => self.emit(&mut encoder)?,
+            }
+        }
+        Ok(buf)
+    }
+
+    /// Compares the canonical wire forms of the two RDatas, see [`RData::to_canonical_bytes`]
+    ///
+    /// This is the ordering of RRs within an RRset, defined in
+    ///  [RFC 4034](https://tools.ietf.org/html/rfc4034#section-6.3)
+    pub fn cmp_canonical(&self, other: &Self) -> ProtoResult<Ordering> {
+        Ok(self.to_canonical_bytes()?.cmp(&other.to_canonical_bytes()?))
+    }
+
+    /// Returns a copy with all embedded names lowercased, for the types which are lowercased
+    ///  in canonical form, otherwise None.
+    fn to_lowercase_names(&self) -> Option<Self> {
+        let rdata = match self {
+            Self::CNAME(cname) => Self::CNAME(CNAME(cname.to_lowercase())),
+            Self::DNAME(dname) => Self::DNAME(DNAME(dname.to_lowercase())),
+            Self::MX(mx) => Self::MX(MX::new(mx.preference(), mx.exchange().to_lowercase())),
+            #[cfg(feature = "rdata-naptr")]
+            Self::NAPTR(naptr) => Self::NAPTR(NAPTR::new(
+                naptr.order(),
+                naptr.preference(),
+                naptr.flags().into(),
+                naptr.services().into(),
+                naptr.regexp().into(),
+                naptr.replacement().to_lowercase(),
+            )),
+            Self::NS(ns) => Self::NS(NS(ns.to_lowercase())),
+            Self::PTR(ptr) => Self::PTR(PTR(ptr.to_lowercase())),
+            Self::SOA(soa) => Self::SOA(SOA::new(
+                soa.mname().to_lowercase(),
+                soa.rname().to_lowercase(),
+                soa.serial(),
+                soa.refresh(),
+                soa.retry(),
+                soa.expire(),
+                soa.minimum(),
+            )),
+            Self::SRV(srv) => Self::SRV(SRV::new(
+                srv.priority(),
+                srv.weight(),
+                srv.port(),
+                srv.target().to_lowercase(),
+            )),
+            #[cfg(feature = "dnssec")]
+            Self::DNSSEC(DNSSECRData::RRSIG(rrsig)) => {
+                Self::DNSSEC(DNSSECRData::RRSIG(lowercase_signer(rrsig, RRSIG::new)))
+            }
+            #[cfg(feature = "dnssec")]
+            Self::DNSSEC(DNSSECRData::SIG(sig)) => {
+                Self::DNSSEC(DNSSECRData::SIG(lowercase_signer(sig, SIG::new)))
+            }
+            _ => return None,
+        };
+
+        Some(rdata)
+    }
+
     /// Read data from the decoder
     pub fn read(
         decoder: &mut BinDecoder<'_>,
@@ -765,6 +956,11 @@ impl RData {
                 trace!("reading AAAA");
                 AAAA::read(decoder).map(Self::AAAA)
             }
+            #[cfg(feature = "rdata-amtrelay")]
+            RecordType::AMTRELAY => {
+                trace!("reading AMTRELAY");
+                AMTRELAY::read_data(decoder, length).map(Self::AMTRELAY)
+            }
             RecordType::ANAME => {
                 trace!("reading ANAME");
                 ANAME::read(decoder).map(Self::ANAME)
@@ -772,6 +968,7 @@ impl RData {
             rt @ RecordType::ANY | rt @ RecordType::AXFR | rt @ RecordType::IXFR => {
                 return Err(ProtoErrorKind::UnknownRecordTypeValue(rt.into()).into());
             }
+            #[cfg(feature = "rdata-caa")]
             RecordType::CAA => {
                 trace!("reading CAA");
                 CAA::read_data(decoder, length).map(Self::CAA)
@@ -780,18 +977,30 @@ impl RData {
                 trace!("reading CNAME");
                 CNAME::read(decoder).map(Self::CNAME)
             }
+            #[cfg(feature = "rdata-csync")]
             RecordType::CSYNC => {
                 trace!("reading CSYNC");
                 CSYNC::read_data(decoder, length).map(Self::CSYNC)
             }
+            RecordType::DNAME => {
+                trace!("reading DNAME");
+                DNAME::read(decoder).map(Self::DNAME)
+            }
+            #[cfg(feature = "rdata-hinfo")]
             RecordType::HINFO => {
                 trace!("reading HINFO");
                 HINFO::read_data(decoder, length).map(Self::HINFO)
             }
+            #[cfg(feature = "rdata-svcb")]
             RecordType::HTTPS => {
                 trace!("reading HTTPS");
                 HTTPS::read_data(decoder, length).map(Self::HTTPS)
             }
+            #[cfg(feature = "rdata-ipseckey")]
+            RecordType::IPSECKEY => {
+                trace!("reading IPSECKEY");
+                IPSECKEY::read_data(decoder, length).map(Self::IPSECKEY)
+            }
             RecordType::ZERO => {
                 trace!("reading EMPTY");
                 // we should never get here, since ZERO should be 0 length, and None in the Record.
@@ -803,6 +1012,7 @@ impl RData {
                 trace!("reading MX");
                 MX::read_data(decoder, length).map(Self::MX)
             }
+            #[cfg(feature = "rdata-naptr")]
             RecordType::NAPTR => {
                 trace!("reading NAPTR");
                 NAPTR::read_data(decoder, length).map(Self::NAPTR)
@@ -815,6 +1025,7 @@ impl RData {
                 trace!("reading NS");
                 NS::read(decoder).map(Self::NS)
             }
+            #[cfg(feature = "rdata-openpgpkey")]
             RecordType::OPENPGPKEY => {
                 trace!("reading OPENPGPKEY");
                 OPENPGPKEY::read_data(decoder, length).map(Self::OPENPGPKEY)
@@ -835,14 +1046,17 @@ impl RData {
                 trace!("reading SRV");
                 SRV::read_data(decoder, length).map(Self::SRV)
             }
+            #[cfg(feature = "rdata-sshfp")]
             RecordType::SSHFP => {
                 trace!("reading SSHFP");
                 SSHFP::read_data(decoder, length).map(Self::SSHFP)
             }
+            #[cfg(feature = "rdata-svcb")]
             RecordType::SVCB => {
                 trace!("reading SVCB");
                 SVCB::read_data(decoder, length).map(Self::SVCB)
             }
+            #[cfg(feature = "rdata-tlsa")]
             RecordType::TLSA => {
                 trace!("reading TLSA");
                 TLSA::read_data(decoder, length).map(Self::TLSA)
@@ -878,6 +1092,26 @@ impl RData {
     }
 }
 
+/// Rebuilds the SIG or RRSIG with `new`, with the signer name lowercased
+#[cfg(feature = "dnssec")]
+#[allow(clippy::type_complexity)]
+fn lowercase_signer<S>(
+    sig: &SIG,
+    new: fn(RecordType, Algorithm, u8, u32, u32, u32, u16, Name, Vec<u8>) -> S,
+) -> S {
+    new(
+        sig.type_covered(),
+        sig.algorithm(),
+        sig.num_labels(),
+        sig.original_ttl(),
+        sig.sig_expiration(),
+        sig.sig_inception(),
+        sig.key_tag(),
+        sig.signer_name().to_lowercase(),
+        sig.sig().to_vec(),
+    )
+}
+
 impl BinEncodable for RData {
     /// [RFC 4034](https://tools.ietf.org/html/rfc4034#section-6), DNSSEC Resource Records, March 2005
     ///
@@ -950,26 +1184,45 @@ impl BinEncodable for RData {
         match *self {
             Self::A(ref address) => address.emit(encoder),
             Self::AAAA(ref address) => address.emit(encoder),
+            #[cfg(feature = "rdata-amtrelay")]
+            Self::AMTRELAY(ref amtrelay) => {
+                encoder.with_canonical_names(|encoder| amtrelay.emit(encoder))
+            }
             Self::ANAME(ref name) => encoder.with_canonical_names(|encoder| name.emit(encoder)),
+            #[cfg(feature = "rdata-caa")]
             Self::CAA(ref caa) => encoder.with_canonical_names(|encoder| caa.emit(encoder)),
             Self::CNAME(ref cname) => cname.emit(encoder),
             Self::NS(ref ns) => ns.emit(encoder),
             Self::PTR(ref ptr) => ptr.emit(encoder),
+            #[cfg(feature = "rdata-csync")]
             Self::CSYNC(ref csync) => csync.emit(encoder),
+            // the target is never compressed, and only lowercased in canonical form
+            Self::DNAME(ref dname) => dname.0.emit_as_canonical(encoder, true),
+            #[cfg(feature = "rdata-hinfo")]
             Self::HINFO(ref hinfo) => hinfo.emit(encoder),
+            #[cfg(feature = "rdata-svcb")]
             Self::HTTPS(ref https) => https.emit(encoder),
+            #[cfg(feature = "rdata-ipseckey")]
+            Self::IPSECKEY(ref ipseckey) => {
+                encoder.with_canonical_names(|encoder| ipseckey.emit(encoder))
+            }
             Self::ZERO => Ok(()),
             Self::MX(ref mx) => mx.emit(encoder),
+            #[cfg(feature = "rdata-naptr")]
             Self::NAPTR(ref naptr) => encoder.with_canonical_names(|encoder| naptr.emit(encoder)),
             Self::NULL(ref null) => null.emit(encoder),
+            #[cfg(feature = "rdata-openpgpkey")]
             Self::OPENPGPKEY(ref openpgpkey) => {
                 encoder.with_canonical_names(|encoder| openpgpkey.emit(encoder))
             }
             Self::OPT(ref opt) => opt.emit(encoder),
             Self::SOA(ref soa) => soa.emit(encoder),
             Self::SRV(ref srv) => encoder.with_canonical_names(|encoder| srv.emit(encoder)),
+            #[cfg(feature = "rdata-sshfp")]
             Self::SSHFP(ref sshfp) => encoder.with_canonical_names(|encoder| sshfp.emit(encoder)),
+            #[cfg(feature = "rdata-svcb")]
             Self::SVCB(ref svcb) => svcb.emit(encoder),
+            #[cfg(feature = "rdata-tlsa")]
             Self::TLSA(ref tlsa) => encoder.with_canonical_names(|encoder| tlsa.emit(encoder)),
             Self::TXT(ref txt) => txt.emit(encoder),
             #[cfg(feature = "dnssec")]
@@ -1006,20 +1259,31 @@ impl fmt::Display for RData {
         match *self {
             Self::A(address) => w(f, address),
             Self::AAAA(ref address) => w(f, address),
+            #[cfg(feature = "rdata-amtrelay")]
+            Self::AMTRELAY(ref amtrelay) => w(f, amtrelay),
             Self::ANAME(ref name) => w(f, name),
+            #[cfg(feature = "rdata-caa")]
             Self::CAA(ref caa) => w(f, caa),
             // to_lowercase for rfc4034 and rfc6840
             Self::CNAME(ref cname) => w(f, cname),
             Self::NS(ref ns) => w(f, ns),
             Self::PTR(ref ptr) => w(f, ptr),
+            #[cfg(feature = "rdata-csync")]
             Self::CSYNC(ref csync) => w(f, csync),
+            Self::DNAME(ref dname) => w(f, dname),
+            #[cfg(feature = "rdata-hinfo")]
             Self::HINFO(ref hinfo) => w(f, hinfo),
+            #[cfg(feature = "rdata-svcb")]
             Self::HTTPS(ref https) => w(f, https),
+            #[cfg(feature = "rdata-ipseckey")]
+            Self::IPSECKEY(ref ipseckey) => w(f, ipseckey),
             Self::ZERO => Ok(()),
             // to_lowercase for rfc4034 and rfc6840
             Self::MX(ref mx) => w(f, mx),
+            #[cfg(feature = "rdata-naptr")]
             Self::NAPTR(ref naptr) => w(f, naptr),
             Self::NULL(ref null) => w(f, null),
+            #[cfg(feature = "rdata-openpgpkey")]
             Self::OPENPGPKEY(ref openpgpkey) => w(f, openpgpkey),
             // Opt has no display representation
             Self::OPT(_) => Err(fmt::Error),
@@ -1027,13 +1291,24 @@ impl fmt::Display for RData {
             Self::SOA(ref soa) => w(f, soa),
             // to_lowercase for rfc4034 and rfc6840
             Self::SRV(ref srv) => w(f, srv),
+            #[cfg(feature = "rdata-sshfp")]
             Self::SSHFP(ref sshfp) => w(f, sshfp),
+            #[cfg(feature = "rdata-svcb")]
             Self::SVCB(ref svcb) => w(f, svcb),
+            #[cfg(feature = "rdata-tlsa")]
             Self::TLSA(ref tlsa) => w(f, tlsa),
             Self::TXT(ref txt) => w(f, txt),
             #[cfg(feature = "dnssec")]
             Self::DNSSEC(ref rdata) => w(f, rdata),
-            Self::Unknown { ref rdata, .. } => w(f, rdata),
+            Self::Unknown { ref rdata, .. } => {
+                // RFC 3597 generic encoding, `\# <length> <hex>`
+                let rdata = rdata.anything();
+                write!(f, "\\# {}", rdata.len())?;
+                if !rdata.is_empty() {
+                    write!(f, " {}", HEX.encode(rdata))?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -1085,7 +1360,8 @@ mod tests {
     use crate::serialize::binary::*;
 
     fn get_data() -> Vec<(RData, Vec<u8>)> {
-        vec![
+        #[allow(unused_mut)]
+        let mut data = vec![
             (
                 RData::CNAME(CNAME(Name::from_str("www.example.com").unwrap())),
                 vec![
@@ -1156,11 +1432,15 @@ mod tests {
                     b'm', b'p', b'l', b'e', 3, b'c', b'o', b'm', 0,
                 ],
             ),
-            (
-                RData::HINFO(HINFO::new("cpu".to_string(), "os".to_string())),
-                vec![3, b'c', b'p', b'u', 2, b'o', b's'],
-            ),
-        ]
+        ];
+
+        #[cfg(feature = "rdata-hinfo")]
+        data.push((
+            RData::HINFO(HINFO::new("cpu".to_string(), "os".to_string())),
+            vec![3, b'c', b'p', b'u', 2, b'o', b's'],
+        ));
+
+        data
     }
 
     // TODO this test kinda sucks, shows the problem with not storing the binary parts
@@ -1229,6 +1509,53 @@ mod tests {
         assert_eq!(ordered, unordered);
     }
 
+    #[test]
+    fn test_to_canonical_bytes() {
+        let upper = Name::from_ascii("WWW.Example.COM.").unwrap();
+        let lower = upper.to_lowercase();
+
+        // names in RFC 1035 types are lowercased
+        assert_eq!(
+            RData::CNAME(CNAME(upper.clone()))
+                .to_canonical_bytes()
+                .unwrap(),
+            RData::CNAME(CNAME(lower.clone())).to_bytes()
+        );
+        assert_eq!(
+            RData::MX(MX::new(10, upper.clone()))
+                .to_canonical_bytes()
+                .unwrap(),
+            RData::MX(MX::new(10, lower.clone())).to_bytes()
+        );
+        assert_eq!(
+            RData::DNAME(DNAME(upper.clone()))
+                .to_canonical_bytes()
+                .unwrap(),
+            RData::DNAME(DNAME(lower.clone())).to_bytes()
+        );
+        assert_ne!(
+            RData::DNAME(DNAME(upper.clone())).to_bytes(),
+            RData::DNAME(DNAME(lower.clone())).to_bytes()
+        );
+
+        // later types preserve case
+        assert_eq!(
+            RData::ANAME(ANAME(upper.clone()))
+                .to_canonical_bytes()
+                .unwrap(),
+            RData::ANAME(ANAME(upper)).to_bytes()
+        );
+
+        assert_eq!(
+            RData::CNAME(CNAME(Name::from_ascii("B.example.com.").unwrap()))
+                .cmp_canonical(&RData::CNAME(CNAME(
+                    Name::from_ascii("a.example.com.").unwrap()
+                )))
+                .unwrap(),
+            Ordering::Greater
+        );
+    }
+
     #[test]
     fn test_read() {
         for (test_pass, (expect, binary)) in get_data().into_iter().enumerate() {
@@ -1248,27 +1575,62 @@ mod tests {
         }
     }
 
+    #[test]
+    #[cfg(not(feature = "rdata-caa"))]
+    fn test_read_without_feature() {
+        // CAA 0 issue "ca", kept as unknown record data
+        let binary = [0, 5, b'i', b's', b's', b'u', b'e', b'c', b'a'];
+        let mut decoder = BinDecoder::new(&binary);
+
+        assert_eq!(
+            RData::read(
+                &mut decoder,
+                RecordType::CAA,
+                Restrict::new(binary.len() as u16)
+            )
+            .unwrap(),
+            RData::Unknown {
+                code: RecordType::CAA,
+                rdata: NULL::with(binary.to_vec()),
+            }
+        );
+    }
+
     fn record_type_from_rdata(rdata: &RData) -> crate::rr::record_type::RecordType {
         match *rdata {
             RData::A(..) => RecordType::A,
             RData::AAAA(..) => RecordType::AAAA,
+            #[cfg(feature = "rdata-amtrelay")]
+            RData::AMTRELAY(..) => RecordType::AMTRELAY,
             RData::ANAME(..) => RecordType::ANAME,
+            #[cfg(feature = "rdata-caa")]
             RData::CAA(..) => RecordType::CAA,
             RData::CNAME(..) => RecordType::CNAME,
+            #[cfg(feature = "rdata-csync")]
             RData::CSYNC(..) => RecordType::CSYNC,
+            RData::DNAME(..) => RecordType::DNAME,
+            #[cfg(feature = "rdata-hinfo")]
             RData::HINFO(..) => RecordType::HINFO,
+            #[cfg(feature = "rdata-svcb")]
             RData::HTTPS(..) => RecordType::HTTPS,
+            #[cfg(feature = "rdata-ipseckey")]
+            RData::IPSECKEY(..) => RecordType::IPSECKEY,
             RData::MX(..) => RecordType::MX,
+            #[cfg(feature = "rdata-naptr")]
             RData::NAPTR(..) => RecordType::NAPTR,
             RData::NS(..) => RecordType::NS,
             RData::NULL(..) => RecordType::NULL,
+            #[cfg(feature = "rdata-openpgpkey")]
             RData::OPENPGPKEY(..) => RecordType::OPENPGPKEY,
             RData::OPT(..) => RecordType::OPT,
             RData::PTR(..) => RecordType::PTR,
             RData::SOA(..) => RecordType::SOA,
             RData::SRV(..) => RecordType::SRV,
+            #[cfg(feature = "rdata-sshfp")]
             RData::SSHFP(..) => RecordType::SSHFP,
+            #[cfg(feature = "rdata-svcb")]
             RData::SVCB(..) => RecordType::SVCB,
+            #[cfg(feature = "rdata-tlsa")]
             RData::TLSA(..) => RecordType::TLSA,
             RData::TXT(..) => RecordType::TXT,
             #[cfg(feature = "dnssec")]
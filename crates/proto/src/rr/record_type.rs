@@ -34,6 +34,8 @@ pub enum RecordType {
     A,
     /// [RFC 3596](https://tools.ietf.org/html/rfc3596) IPv6 address record
     AAAA,
+    /// [RFC 8777](https://tools.ietf.org/html/rfc8777) DNS Reverse IP AMT Discovery
+    AMTRELAY,
     /// [ANAME draft-ietf-dnsop-aname](https://tools.ietf.org/html/draft-ietf-dnsop-aname-04)
     ANAME,
     //  AFSDB,      //	18	RFC 1183	AFS database record
@@ -53,7 +55,8 @@ pub enum RecordType {
     CNAME,
     //  DHCID,      // 49 RFC 4701 DHCP identifier
     //  DLV,        //	32769	RFC 4431	DNSSEC Lookaside Validation record
-    //  DNAME,      // 39 RFC 2672 Delegation Name
+    /// [RFC 6672](https://tools.ietf.org/html/rfc6672) Delegation name, redirection of a subtree
+    DNAME,
     /// [RFC 7477](https://tools.ietf.org/html/rfc4034) Child-to-parent synchronization record
     CSYNC,
     /// [RFC 4034](https://tools.ietf.org/html/rfc4034) DNS Key record: RSASHA256 and RSASHA512, RFC5702
@@ -65,7 +68,8 @@ pub enum RecordType {
     //  HIP,        // 55 RFC 5205 Host Identity Protocol
     /// [RFC draft-ietf-dnsop-svcb-https-03](https://tools.ietf.org/html/draft-ietf-dnsop-svcb-httpssvc-03) DNS SVCB and HTTPS RRs
     HTTPS,
-    //  IPSECKEY,   // 45 RFC 4025 IPsec Key
+    /// [RFC 4025](https://tools.ietf.org/html/rfc4025) IPsec Key
+    IPSECKEY,
     /// [RFC 1996](https://tools.ietf.org/html/rfc1996) Incremental Zone Transfer
     IXFR,
     //  KX,         // 36 RFC 2230 Key eXchanger record
@@ -201,6 +205,7 @@ impl FromStr for RecordType {
         match str {
             "A" => Ok(Self::A),
             "AAAA" => Ok(Self::AAAA),
+            "AMTRELAY" => Ok(Self::AMTRELAY),
             "ANAME" => Ok(Self::ANAME),
             "AXFR" => Ok(Self::AXFR),
             "CAA" => Ok(Self::CAA),
@@ -208,10 +213,12 @@ impl FromStr for RecordType {
             "CDS" => Ok(Self::CDS),
             "CNAME" => Ok(Self::CNAME),
             "CSYNC" => Ok(Self::CSYNC),
+            "DNAME" => Ok(Self::DNAME),
             "DNSKEY" => Ok(Self::DNSKEY),
             "DS" => Ok(Self::DS),
             "HINFO" => Ok(Self::HINFO),
             "HTTPS" => Ok(Self::HTTPS),
+            "IPSECKEY" => Ok(Self::IPSECKEY),
             "KEY" => Ok(Self::KEY),
             "MX" => Ok(Self::MX),
             "NAPTR" => Ok(Self::NAPTR),
@@ -232,7 +239,13 @@ impl FromStr for RecordType {
             "TXT" => Ok(Self::TXT),
             "TSIG" => Ok(Self::TSIG),
             "ANY" | "*" => Ok(Self::ANY),
-            _ => Err(ProtoErrorKind::UnknownRecordTypeStr(str.to_string()).into()),
+            // RFC 3597 generic type names, e.g. TYPE65534
+            _ => str
+                .strip_prefix("TYPE")
+                .filter(|code| !code.is_empty() && code.bytes().all(|b| b.is_ascii_digit()))
+                .and_then(|code| code.parse::<u16>().ok())
+                .map(Self::from)
+                .ok_or_else(|| ProtoErrorKind::UnknownRecordTypeStr(str.to_string()).into()),
         }
     }
 }
@@ -250,6 +263,7 @@ impl From<u16> for RecordType {
         match value {
             1 => Self::A,
             28 => Self::AAAA,
+            260 => Self::AMTRELAY,
             // TODO: wrong value here, see https://github.com/hickory-dns/hickory-dns/issues/723
             65305 => Self::ANAME,
             255 => Self::ANY,
@@ -260,10 +274,12 @@ impl From<u16> for RecordType {
             60 => Self::CDNSKEY,
             5 => Self::CNAME,
             62 => Self::CSYNC,
+            39 => Self::DNAME,
             48 => Self::DNSKEY,
             43 => Self::DS,
             13 => Self::HINFO,
             65 => Self::HTTPS,
+            45 => Self::IPSECKEY,
             25 => Self::KEY,
             15 => Self::MX,
             35 => Self::NAPTR,
@@ -326,6 +342,7 @@ impl From<RecordType> for &'static str {
         match rt {
             RecordType::A => "A",
             RecordType::AAAA => "AAAA",
+            RecordType::AMTRELAY => "AMTRELAY",
             RecordType::ANAME => "ANAME",
             RecordType::ANY => "ANY",
             RecordType::AXFR => "AXFR",
@@ -334,10 +351,12 @@ impl From<RecordType> for &'static str {
             RecordType::CDS => "CDS",
             RecordType::CNAME => "CNAME",
             RecordType::CSYNC => "CSYNC",
+            RecordType::DNAME => "DNAME",
             RecordType::DNSKEY => "DNSKEY",
             RecordType::DS => "DS",
             RecordType::HINFO => "HINFO",
             RecordType::HTTPS => "HTTPS",
+            RecordType::IPSECKEY => "IPSECKEY",
             RecordType::KEY => "KEY",
             RecordType::IXFR => "IXFR",
             RecordType::MX => "MX",
@@ -378,6 +397,7 @@ impl From<RecordType> for u16 {
         match rt {
             RecordType::A => 1,
             RecordType::AAAA => 28,
+            RecordType::AMTRELAY => 260,
             // TODO: wrong value here, see https://github.com/hickory-dns/hickory-dns/issues/723
             RecordType::ANAME => 65305,
             RecordType::ANY => 255,
@@ -387,10 +407,12 @@ impl From<RecordType> for u16 {
             RecordType::CDS => 59,
             RecordType::CNAME => 5,
             RecordType::CSYNC => 62,
+            RecordType::DNAME => 39,
             RecordType::DNSKEY => 48,
             RecordType::DS => 43,
             RecordType::HINFO => 13,
             RecordType::HTTPS => 65,
+            RecordType::IPSECKEY => 45,
             RecordType::KEY => 25,
             RecordType::IXFR => 251,
             RecordType::MX => 15,
@@ -432,9 +454,13 @@ impl Ord for RecordType {
     }
 }
 
+/// Unknown types are displayed in the generic format of RFC 3597, e.g. `TYPE65534`
 impl Display for RecordType {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
-        f.write_str(Into::<&str>::into(*self))
+        match self {
+            Self::Unknown(code) => write!(f, "TYPE{code}"),
+            _ => f.write_str(Into::<&str>::into(*self)),
+        }
     }
 }
 
@@ -496,11 +522,14 @@ mod tests {
         let record_names = &[
             "A",
             "AAAA",
+            "AMTRELAY",
             "ANAME",
             "CAA",
             "CNAME",
             "CSYNC",
+            "DNAME",
             "HINFO",
+            "IPSECKEY",
             "NULL",
             "MX",
             "NAPTR",
@@ -541,6 +570,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_generic_record_type() {
+        assert_eq!(
+            "TYPE65534".parse::<RecordType>().unwrap(),
+            RecordType::Unknown(65534)
+        );
+        assert_eq!(RecordType::Unknown(65534).to_string(), "TYPE65534");
+
+        // generic names of known types are the known type
+        assert_eq!("TYPE1".parse::<RecordType>().unwrap(), RecordType::A);
+
+        assert!("TYPE".parse::<RecordType>().is_err());
+        assert!("TYPE+1".parse::<RecordType>().is_err());
+        assert!("TYPE65536".parse::<RecordType>().is_err());
+    }
+
     #[test]
     fn check_record_type_parse_wont_panic_with_symbols() {
         let dns_class = "a-b-c".to_ascii_uppercase().parse::<RecordType>();
@@ -158,6 +158,59 @@ impl Record<RData> {
         self.rr_type = rr_type;
         self
     }
+
+    /// Returns the canonical wire form of this record, as used for DNSSEC signing
+    ///
+    /// The owner name is lowercased and written without compression, the RDATA is written as in
+    ///  [`RData::to_canonical_bytes`]. The mDNS cache flush bit is never set in canonical form.
+    ///
+    /// [RFC 4034](https://tools.ietf.org/html/rfc4034#section-6.2), DNSSEC Resource Records, March 2005
+    pub fn to_canonical_bytes(&self) -> ProtoResult<Vec<u8>> {
+        let rdata = match self.rdata {
+            Some(ref rdata) => rdata.to_canonical_bytes()?,
+            None => Vec::new(),
+        };
+
+        let mut buf: Vec<u8> = Vec::new();
+        {
+            let mut encoder: BinEncoder<'_> = BinEncoder::new(&mut buf);
+            encoder.set_canonical_names(true);
+
+            self.name_labels.to_lowercase().emit(&mut encoder)?;
+            self.rr_type.emit(&mut encoder)?;
+            self.dns_class.emit(&mut encoder)?;
+            encoder.emit_u32(self.ttl)?;
+            if rdata.len() > u16::MAX as usize {
+                return Err(ProtoErrorKind::MaxBufferSizeExceeded(rdata.len()).into());
+            }
+            encoder.emit_u16(rdata.len() as u16)?;
+            encoder.emit_vec(&rdata)?;
+        }
+
+        Ok(buf)
+    }
+
+    /// Compares the records in canonical order
+    ///
+    /// Records are ordered by owner name in canonical DNS name order, then by type and class, and
+    ///  records of the same RRset are ordered by their canonical RDATA, see
+    ///  [RFC 4034](https://tools.ietf.org/html/rfc4034#section-6). Unlike the `Ord` implementation,
+    ///  the TTL is not considered.
+    pub fn cmp_canonical(&self, other: &Self) -> ProtoResult<Ordering> {
+        let ordering = self
+            .name_labels
+            .cmp(&other.name_labels)
+            .then(self.rr_type.cmp(&other.rr_type))
+            .then(self.dns_class.cmp(&other.dns_class));
+        if ordering != Ordering::Equal {
+            return Ok(ordering);
+        }
+
+        match (&self.rdata, &other.rdata) {
+            (Some(rdata), Some(other)) => rdata.cmp_canonical(other),
+            (rdata, other) => Ok(rdata.is_some().cmp(&other.is_some())),
+        }
+    }
 }
 
 impl<R: RecordData> Record<R> {
@@ -987,6 +1040,37 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_canonical() {
+        let record = Record::from_rdata(
+            Name::from_ascii("WWW.Example.COM.").unwrap(),
+            5,
+            RData::A(A::new(192, 168, 0, 1)),
+        );
+
+        let mut lowercase = record.clone();
+        lowercase.set_name(record.name().to_lowercase());
+        assert_eq!(
+            record.to_canonical_bytes().unwrap(),
+            lowercase.to_bytes().unwrap()
+        );
+
+        let mut greater_ttl = record.clone();
+        greater_ttl.set_ttl(10);
+        assert_eq!(record.cmp_canonical(&greater_ttl).unwrap(), Ordering::Equal);
+
+        let mut greater_rdata = record.clone();
+        greater_rdata.set_data(Some(RData::A(A::new(192, 168, 0, 255))));
+        assert_eq!(
+            record.cmp_canonical(&greater_rdata).unwrap(),
+            Ordering::Less
+        );
+
+        let mut greater_name = record.clone();
+        greater_name.set_name(Name::from_ascii("a.zzz.example.com.").unwrap());
+        assert_eq!(record.cmp_canonical(&greater_name).unwrap(), Ordering::Less);
+    }
+
     #[cfg(feature = "mdns")]
     #[test]
     fn test_mdns_cache_flush_bit_handling() {
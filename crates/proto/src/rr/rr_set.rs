@@ -7,9 +7,12 @@
 
 use std::{iter::Chain, slice::Iter, vec};
 
-use tracing::{info, warn};
+use crate::logging::{info, warn};
 
-use crate::rr::{DNSClass, Name, RData, Record, RecordType};
+use crate::{
+    error::ProtoResult,
+    rr::{DNSClass, Name, RData, Record, RecordType},
+};
 
 #[cfg(feature = "dnssec")]
 #[cfg_attr(docsrs, doc(cfg(feature = "dnssec")))]
@@ -181,6 +184,37 @@ impl RecordSet {
         }
     }
 
+    /// Returns the records of the set, without any RRSIGs, in canonical order
+    ///
+    /// Records are ordered by their canonical RDATA, see [`RData::to_canonical_bytes`], and any
+    ///  records which are duplicates in canonical form are removed.
+    ///
+    /// [RFC 4034](https://tools.ietf.org/html/rfc4034#section-6.3), DNSSEC Resource Records, March 2005
+    ///
+    /// ```text
+    /// 6.3.  Canonical RR Ordering within an RRset
+    ///
+    ///    For the purposes of DNS security, RRs with the same owner name,
+    ///    class, and type are sorted by treating the RDATA portion of the
+    ///    canonical form of each RR as a left-justified unsigned octet sequence
+    ///    in which the absence of an octet sorts before a zero octet.
+    ///
+    ///    [RFC2181] specifies that an RRset is not allowed to contain duplicate
+    ///    records (multiple RRs with the same owner name, class, type, and
+    ///    RDATA).  Therefore, if an implementation detects duplicate RRs when
+    ///    putting the RRset in canonical form, it MUST treat this as a protocol
+    ///    error.  If the implementation chooses to handle this protocol error
+    ///    in the spirit of the robustness principle (being liberal in what it
+    ///    accepts), it MUST remove all but one of the duplicate RR(s) for the
+    ///    purposes of calculating the canonical form of the RRset.
+    /// ```
+    pub fn canonical_ordering(&self) -> ProtoResult<Vec<&Record>> {
+        Ok(canonical_rdatas(&self.records)?
+            .into_iter()
+            .map(|(_, record)| record)
+            .collect())
+    }
+
     /// Returns an iterator over the records in the set
     #[deprecated(note = "see `records_without_rrsigs`")]
     pub fn iter(&self) -> Iter<'_, Record> {
@@ -344,7 +378,10 @@ impl RecordSet {
             //   same owner name; in fact, the two can be used cooperatively to
             //   redirect both the owner name address records (via ANAME) and
             //   everything under it (via DNAME).
-            RecordType::CNAME | RecordType::ANAME => {
+            //
+            // DNAME https://tools.ietf.org/html/rfc6672#section-2.4
+            //   There MUST NOT be more than one DNAME RR per owner name.
+            RecordType::CNAME | RecordType::ANAME | RecordType::DNAME => {
                 assert!(self.records.len() <= 1);
                 self.records.clear();
             }
@@ -612,6 +649,28 @@ impl<'r> Iterator for RrsetRecords<'r> {
     }
 }
 
+/// Returns the canonical RDATA of each record, along with the record, in canonical order with
+///  duplicates removed. Records without RDATA have an empty canonical RDATA.
+pub(crate) fn canonical_rdatas<'r, I>(records: I) -> ProtoResult<Vec<(Vec<u8>, &'r Record)>>
+where
+    I: IntoIterator<Item = &'r Record>,
+{
+    let mut rdatas = records
+        .into_iter()
+        .map(|record| {
+            let rdata = match record.data() {
+                Some(rdata) => rdata.to_canonical_bytes()?,
+                None => Vec::new(),
+            };
+            Ok((rdata, record))
+        })
+        .collect::<ProtoResult<Vec<_>>>()?;
+
+    rdatas.sort_by(|(left, _), (right, _)| left.cmp(right));
+    rdatas.dedup_by(|(left, _), (right, _)| left == right);
+    Ok(rdatas)
+}
+
 #[cfg(test)]
 mod test {
     use std::net::Ipv4Addr;
@@ -864,6 +923,28 @@ mod test {
         assert!(!rr_set.remove(&ns1, 0));
     }
 
+    #[test]
+    fn test_canonical_ordering() {
+        let name = Name::from_str("example.com.").unwrap();
+        let mut rr_set = RecordSet::new(&name, RecordType::NS, 0);
+
+        let ns = |target: &str| {
+            Record::from_rdata(
+                name.clone(),
+                86400,
+                RData::NS(NS(Name::from_ascii(target).unwrap())),
+            )
+        };
+
+        // 'C' sorts before 'b' unless lowercased
+        let upper = ns("C.iana-servers.net.");
+        let lower = ns("b.iana-servers.net.");
+        assert!(rr_set.insert(upper.clone(), 0));
+        assert!(rr_set.insert(lower.clone(), 0));
+
+        assert_eq!(rr_set.canonical_ordering().unwrap(), vec![&lower, &upper]);
+    }
+
     #[test]
     #[cfg(feature = "dnssec")] // This tests RFC 6975, a DNSSEC-specific feature.
     #[allow(clippy::blocks_in_conditions)]
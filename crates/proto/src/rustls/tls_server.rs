@@ -10,7 +10,9 @@
 use std::fs::File;
 use std::io::{BufReader, Read};
 use std::path::Path;
+use std::sync::Arc;
 
+use rustls::server::ResolvesServerCert;
 use rustls::{self, Certificate, PrivateKey, ServerConfig};
 use rustls_pemfile::{certs, read_one, Item};
 
@@ -103,3 +105,15 @@ pub fn new_acceptor(
     config.alpn_protocols = vec![b"h2".to_vec()];
     Ok(config)
 }
+
+/// Construct the new Acceptor, which selects the certificate with the resolver, e.g. by the SNI
+///  of the client
+pub fn new_acceptor_with_cert_resolver(resolver: Arc<dyn ResolvesServerCert>) -> ServerConfig {
+    let mut config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_cert_resolver(resolver);
+
+    config.alpn_protocols = vec![b"h2".to_vec()];
+    config
+}
@@ -0,0 +1,242 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Reusable buffers for encoding messages
+
+use std::{
+    fmt,
+    ops::{Deref, DerefMut},
+    sync::Mutex,
+};
+
+use super::{encoder::NamePointers, BinEncoder};
+
+/// Buffers for encoding messages, which are kept between messages
+///
+/// Encoding into a new `Vec` grows the buffer while the message is written, and allocates the
+///  table used for name compression. An `EncodeBuffer` keeps both allocations, so once it has
+///  grown to the size of the messages being written, encoding does not allocate.
+///
+/// ```
+/// use hickory_proto::op::Message;
+/// use hickory_proto::serialize::binary::{BinEncodable, EncodeBuffer};
+///
+/// let mut buffer = EncodeBuffer::new();
+/// for id in 0..3 {
+///     let mut message = Message::new();
+///     message.set_id(id);
+///
+///     message.emit(&mut buffer.encoder()).unwrap();
+///     assert_eq!(Message::from_vec(buffer.bytes()).unwrap().id(), id);
+/// }
+/// ```
+#[derive(Default)]
+pub struct EncodeBuffer {
+    buffer: Vec<u8>,
+    name_pointers: NamePointers,
+}
+
+impl EncodeBuffer {
+    /// Creates a new, empty buffer
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new buffer with space for a message of `capacity` bytes
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            buffer: Vec::with_capacity(capacity),
+            name_pointers: NamePointers::default(),
+        }
+    }
+
+    /// Returns an encoder writing to the start of this buffer
+    ///
+    /// Anything previously written to the buffer is discarded.
+    pub fn encoder(&mut self) -> BinEncoder<'_> {
+        BinEncoder::with_buffers(&mut self.buffer, &mut self.name_pointers)
+    }
+
+    /// Returns the bytes written by the last encoder
+    pub fn bytes(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Returns the number of bytes the buffer can hold without allocating
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+}
+
+impl fmt::Debug for EncodeBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncodeBuffer")
+            .field("len", &self.buffer.len())
+            .field("capacity", &self.buffer.capacity())
+            .finish()
+    }
+}
+
+/// A pool of [`EncodeBuffer`]s, which can be shared between tasks
+///
+/// Buffers are returned to the pool when the [`PooledEncodeBuffer`] is dropped. At most
+///  `max_pooled` buffers are kept, any further buffers are freed.
+pub struct EncodeBufferPool {
+    buffers: Mutex<Vec<EncodeBuffer>>,
+    max_pooled: usize,
+}
+
+impl EncodeBufferPool {
+    /// Creates a new, empty pool which keeps at most `max_pooled` buffers
+    pub const fn new(max_pooled: usize) -> Self {
+        Self {
+            buffers: Mutex::new(Vec::new()),
+            max_pooled,
+        }
+    }
+
+    /// Takes a buffer from the pool, or creates a new one if the pool is empty
+    pub fn get(&self) -> PooledEncodeBuffer<'_> {
+        let buffer = self
+            .buffers
+            .lock()
+            .expect("encode buffer pool poisoned")
+            .pop()
+            .unwrap_or_else(|| EncodeBuffer::with_capacity(512));
+
+        PooledEncodeBuffer {
+            pool: self,
+            buffer: Some(buffer),
+        }
+    }
+
+    /// Returns the number of buffers currently in the pool
+    pub fn len(&self) -> usize {
+        self.buffers
+            .lock()
+            .expect("encode buffer pool poisoned")
+            .len()
+    }
+
+    /// Returns true if there are no buffers in the pool
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn put(&self, buffer: EncodeBuffer) {
+        let mut buffers = self.buffers.lock().expect("encode buffer pool poisoned");
+        if buffers.len() < self.max_pooled {
+            buffers.push(buffer);
+        }
+    }
+}
+
+impl fmt::Debug for EncodeBufferPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncodeBufferPool")
+            .field("len", &self.len())
+            .field("max_pooled", &self.max_pooled)
+            .finish()
+    }
+}
+
+/// An [`EncodeBuffer`] taken from an [`EncodeBufferPool`], which is returned to the pool on drop
+#[derive(Debug)]
+pub struct PooledEncodeBuffer<'p> {
+    pool: &'p EncodeBufferPool,
+    buffer: Option<EncodeBuffer>,
+}
+
+impl Deref for PooledEncodeBuffer<'_> {
+    type Target = EncodeBuffer;
+
+    fn deref(&self) -> &Self::Target {
+        self.buffer.as_ref().expect("buffer only taken on drop")
+    }
+}
+
+impl DerefMut for PooledEncodeBuffer<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.buffer.as_mut().expect("buffer only taken on drop")
+    }
+}
+
+impl Drop for PooledEncodeBuffer<'_> {
+    fn drop(&mut self) {
+        if let Some(buffer) = self.buffer.take() {
+            self.pool.put(buffer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{
+        op::{Message, Query},
+        rr::{rdata::CNAME, Name, RData, Record, RecordType},
+        serialize::binary::BinEncodable,
+    };
+
+    fn message(name: &str) -> Message {
+        let name = Name::from_str(name).unwrap();
+        let mut message = Message::new();
+        message
+            .add_query(Query::query(name.clone(), RecordType::CNAME))
+            .add_answer(Record::from_rdata(
+                name.clone(),
+                0,
+                RData::CNAME(CNAME(
+                    Name::from_str("alias")
+                        .unwrap()
+                        .append_domain(&name)
+                        .unwrap(),
+                )),
+            ));
+        message
+    }
+
+    #[test]
+    fn test_reuse() {
+        let mut buffer = EncodeBuffer::new();
+
+        for name in ["www.example.com.", "example.net.", "www.example.com."] {
+            let message = message(name);
+            message.emit(&mut buffer.encoder()).unwrap();
+
+            // compression must only refer to names of the current message
+            assert_eq!(buffer.bytes(), message.to_vec().unwrap());
+            let decoded = Message::from_vec(buffer.bytes()).unwrap();
+            assert_eq!(decoded.queries(), message.queries());
+            assert_eq!(decoded.answers(), message.answers());
+        }
+    }
+
+    #[test]
+    fn test_pool() {
+        let pool = EncodeBufferPool::new(1);
+        assert!(pool.is_empty());
+
+        {
+            let mut first = pool.get();
+            let second = pool.get();
+            message("www.example.com.")
+                .emit(&mut first.encoder())
+                .unwrap();
+            assert!(!first.bytes().is_empty());
+            drop(second);
+        }
+
+        // only one buffer is kept
+        assert_eq!(pool.len(), 1);
+        let buffer = pool.get();
+        assert!(pool.is_empty());
+        assert!(buffer.capacity() >= 512);
+    }
+}
@@ -5,7 +5,10 @@
 // https://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
-use std::marker::PhantomData;
+use std::{
+    marker::PhantomData,
+    ops::{Deref, DerefMut, Range},
+};
 
 use crate::{
     error::{ProtoErrorKind, ProtoResult},
@@ -37,6 +40,11 @@ mod private {
             self.max_size = max as usize;
         }
 
+        /// Returns the maximum size which is enforced
+        pub(super) fn max_size(&self) -> usize {
+            self.max_size
+        }
+
         pub(super) fn write(&mut self, offset: usize, data: &[u8]) -> ProtoResult<()> {
             debug_assert!(offset <= self.buffer.len());
             if offset + data.len() > self.max_size {
@@ -78,8 +86,8 @@ mod private {
         }
 
         /// Immutable reads are always safe
-        pub(super) fn buffer(&'a self) -> &'a [u8] {
-            self.buffer as &'a [u8]
+        pub(super) fn buffer(&self) -> &[u8] {
+            self.buffer
         }
 
         /// Returns a reference to the internal buffer
@@ -93,8 +101,7 @@ mod private {
 pub struct BinEncoder<'a> {
     offset: usize,
     buffer: private::MaximalBuf<'a>,
-    /// start of label pointers with their labels in fully decompressed form for easy comparison, smallvec here?
-    name_pointers: Vec<(usize, Vec<u8>)>,
+    name_pointers: NamePointerTable<'a>,
     mode: EncodeMode,
     canonical_names: bool,
 }
@@ -133,12 +140,24 @@ impl<'a> BinEncoder<'a> {
             offset: offset as usize,
             // TODO: add max_size to signature
             buffer: private::MaximalBuf::new(u16::MAX, buf),
-            name_pointers: Vec::new(),
+            name_pointers: NamePointerTable::Owned(NamePointers::default()),
             mode,
             canonical_names: false,
         }
     }
 
+    /// Creates an encoder which reuses the given buffer and name compression table
+    ///
+    /// Both are cleared before use, see `EncodeBuffer`.
+    pub(super) fn with_buffers(buf: &'a mut Vec<u8>, name_pointers: &'a mut NamePointers) -> Self {
+        buf.clear();
+        name_pointers.clear();
+
+        let mut encoder = Self::new(buf);
+        encoder.name_pointers = NamePointerTable::Borrowed(name_pointers);
+        encoder
+    }
+
     // TODO: move to constructor (kept for backward compatibility)
     /// Sets the maximum size of the buffer
     ///
@@ -149,6 +168,11 @@ impl<'a> BinEncoder<'a> {
         self.buffer.set_max_size(max);
     }
 
+    /// Returns the maximum size of the buffer, see `set_max_size`
+    pub fn max_size(&self) -> usize {
+        self.buffer.max_size()
+    }
+
     /// Returns a reference to the internal buffer
     pub fn into_bytes(self) -> &'a Vec<u8> {
         self.buffer.into_bytes()
@@ -213,7 +237,7 @@ impl<'a> BinEncoder<'a> {
     pub fn trim(&mut self) {
         let offset = self.offset;
         self.buffer.truncate(offset);
-        self.name_pointers.retain(|&(start, _)| start < offset);
+        self.name_pointers.truncate(offset);
     }
 
     // /// returns an error if the maximum buffer size would be exceeded with the addition number of elements
@@ -244,23 +268,18 @@ impl<'a> BinEncoder<'a> {
         assert!(end <= (u16::MAX as usize));
         assert!(start <= end);
         if self.offset < 0x3FFF_usize {
-            self.name_pointers
-                .push((start, self.slice_of(start, end).to_vec())); // the next char will be at the len() location
+            assert!(start < self.offset);
+            let labels = &self.buffer.buffer()[start..end];
+            self.name_pointers.push(start, labels); // the next char will be at the len() location
         }
     }
 
     /// Looks up the index of an already written label
     pub fn get_label_pointer(&self, start: usize, end: usize) -> Option<u16> {
         let search = self.slice_of(start, end);
-
-        for (match_start, matcher) in &self.name_pointers {
-            if matcher.as_slice() == search {
-                assert!(match_start <= &(u16::MAX as usize));
-                return Some(*match_start as u16);
-            }
-        }
-
-        None
+        let match_start = self.name_pointers.find(search)?;
+        assert!(match_start <= (u16::MAX as usize));
+        Some(match_start as u16)
     }
 
     /// Emit one byte into the buffer
@@ -431,6 +450,71 @@ impl<'a> BinEncoder<'a> {
     }
 }
 
+/// The names written to a message, used for name compression
+///
+/// The labels of all names are stored in a single buffer, so that storing a name does not
+///  allocate once the buffer has grown.
+#[derive(Default)]
+pub(super) struct NamePointers {
+    /// start of label pointers with the range of their labels in `labels`
+    pointers: Vec<(usize, Range<usize>)>,
+    /// labels in fully decompressed form for easy comparison
+    labels: Vec<u8>,
+}
+
+impl NamePointers {
+    fn push(&mut self, start: usize, labels: &[u8]) {
+        let range = self.labels.len()..self.labels.len() + labels.len();
+        self.labels.extend_from_slice(labels);
+        self.pointers.push((start, range));
+    }
+
+    fn find(&self, search: &[u8]) -> Option<usize> {
+        self.pointers
+            .iter()
+            .find(|(_, range)| &self.labels[range.clone()] == search)
+            .map(|(start, _)| *start)
+    }
+
+    /// Removes all pointers at or after the offset
+    fn truncate(&mut self, offset: usize) {
+        self.pointers.retain(|(start, _)| *start < offset);
+        let end = self.pointers.iter().map(|(_, range)| range.end).max();
+        self.labels.truncate(end.unwrap_or(0));
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.pointers.clear();
+        self.labels.clear();
+    }
+}
+
+/// Name compression table of an encoder, which may be reused across encoders
+enum NamePointerTable<'a> {
+    Owned(NamePointers),
+    Borrowed(&'a mut NamePointers),
+}
+
+impl Deref for NamePointerTable<'_> {
+    type Target = NamePointers;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Owned(table) => table,
+            Self::Borrowed(table) => table,
+        }
+    }
+}
+
+impl DerefMut for NamePointerTable<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            Self::Owned(table) => table,
+            Self::Borrowed(table) => table,
+        }
+    }
+}
+
 /// A trait to return the size of a type as it will be encoded in DNS
 ///
 /// it does not necessarily equal `std::mem::size_of`, though it might, especially for primitives
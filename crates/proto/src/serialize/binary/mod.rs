@@ -8,10 +8,12 @@
 //! Binary serialization types
 
 mod decoder;
+mod encode_buffer;
 mod encoder;
 mod restrict;
 
 pub use self::decoder::{BinDecoder, DecodeError};
+pub use self::encode_buffer::{EncodeBuffer, EncodeBufferPool, PooledEncodeBuffer};
 pub use self::encoder::BinEncoder;
 pub use self::encoder::EncodeMode;
 pub use self::restrict::{Restrict, RestrictedMath, Verified};
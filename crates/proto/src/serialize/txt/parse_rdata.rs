@@ -9,15 +9,20 @@
 
 #[cfg(feature = "dnssec")]
 use crate::rr::dnssec::rdata::DNSSECRData;
+#[cfg(feature = "rdata-svcb")]
+use crate::rr::rdata::HTTPS;
 use crate::{
     rr::{
-        rdata::{ANAME, CNAME, HTTPS, NS, PTR},
+        rdata::{ANAME, CNAME, DNAME, NS, NULL, PTR},
         Name, RData, RecordType,
     },
-    serialize::txt::{
-        errors::{ParseError, ParseErrorKind, ParseResult},
-        rdata_parsers::*,
-        zone_lex::Lexer,
+    serialize::{
+        binary::{BinDecoder, Restrict},
+        txt::{
+            errors::{ParseError, ParseErrorKind, ParseResult},
+            rdata_parsers::*,
+            zone_lex::Lexer,
+        },
     },
 };
 
@@ -62,35 +67,63 @@ impl RDataParser for RData {
         tokens: I,
         origin: Option<&Name>,
     ) -> ParseResult<Self> {
+        let mut tokens = tokens.peekable();
+        if tokens.peek() == Some(&generic::GENERIC_RDATA) {
+            tokens.next();
+            return Self::from_generic(record_type, generic::parse(tokens)?);
+        }
+
         let rdata = match record_type {
             RecordType::A => Self::A(a::parse(tokens)?),
             RecordType::AAAA => Self::AAAA(aaaa::parse(tokens)?),
+            #[cfg(feature = "rdata-amtrelay")]
+            RecordType::AMTRELAY => Self::AMTRELAY(amtrelay::parse(tokens, origin)?),
             RecordType::ANAME => Self::ANAME(ANAME(name::parse(tokens, origin)?)),
             RecordType::ANY => return Err(ParseError::from("parsing ANY doesn't make sense")),
             RecordType::AXFR => return Err(ParseError::from("parsing AXFR doesn't make sense")),
+            #[cfg(feature = "rdata-caa")]
             RecordType::CAA => caa::parse(tokens).map(Self::CAA)?,
             RecordType::CNAME => Self::CNAME(CNAME(name::parse(tokens, origin)?)),
+            RecordType::DNAME => Self::DNAME(DNAME(name::parse(tokens, origin)?)),
+            #[cfg(feature = "rdata-csync")]
             RecordType::CSYNC => csync::parse(tokens).map(Self::CSYNC)?,
+            #[cfg(feature = "rdata-hinfo")]
             RecordType::HINFO => Self::HINFO(hinfo::parse(tokens)?),
+            #[cfg(feature = "rdata-svcb")]
             RecordType::HTTPS => svcb::parse(tokens).map(HTTPS).map(Self::HTTPS)?,
+            #[cfg(feature = "rdata-ipseckey")]
+            RecordType::IPSECKEY => Self::IPSECKEY(ipseckey::parse(tokens, origin)?),
             RecordType::IXFR => return Err(ParseError::from("parsing IXFR doesn't make sense")),
             RecordType::MX => Self::MX(mx::parse(tokens, origin)?),
+            #[cfg(feature = "rdata-naptr")]
             RecordType::NAPTR => Self::NAPTR(naptr::parse(tokens, origin)?),
             RecordType::NULL => Self::NULL(null::parse(tokens)?),
             RecordType::NS => Self::NS(NS(name::parse(tokens, origin)?)),
+            #[cfg(feature = "rdata-openpgpkey")]
             RecordType::OPENPGPKEY => Self::OPENPGPKEY(openpgpkey::parse(tokens)?),
             RecordType::OPT => return Err(ParseError::from("parsing OPT doesn't make sense")),
             RecordType::PTR => Self::PTR(PTR(name::parse(tokens, origin)?)),
             RecordType::SOA => Self::SOA(soa::parse(tokens, origin)?),
             RecordType::SRV => Self::SRV(srv::parse(tokens, origin)?),
+            #[cfg(feature = "rdata-sshfp")]
             RecordType::SSHFP => Self::SSHFP(sshfp::parse(tokens)?),
+            #[cfg(feature = "rdata-svcb")]
             RecordType::SVCB => svcb::parse(tokens).map(Self::SVCB)?,
+            #[cfg(feature = "rdata-tlsa")]
             RecordType::TLSA => Self::TLSA(tlsa::parse(tokens)?),
             RecordType::TXT => Self::TXT(txt::parse(tokens)?),
             RecordType::SIG => return Err(ParseError::from("parsing SIG doesn't make sense")),
+            #[cfg(feature = "dnssec")]
+            RecordType::DNSKEY => Self::DNSSEC(DNSSECRData::DNSKEY(dnskey::parse(tokens)?)),
+            #[cfg(not(feature = "dnssec"))]
             RecordType::DNSKEY => {
                 return Err(ParseError::from("DNSKEY should be dynamically generated"))
             }
+            #[cfg(feature = "dnssec")]
+            RecordType::CDNSKEY => {
+                Self::DNSSEC(DNSSECRData::CDNSKEY(dnskey::parse(tokens)?.into()))
+            }
+            #[cfg(not(feature = "dnssec"))]
             RecordType::CDNSKEY => {
                 return Err(ParseError::from("CDNSKEY should be dynamically generated"))
             }
@@ -99,26 +132,87 @@ impl RDataParser for RData {
             RecordType::DS => Self::DNSSEC(DNSSECRData::DS(ds::parse(tokens)?)),
             #[cfg(not(feature = "dnssec"))]
             RecordType::DS => return Err(ParseError::from("DS should be dynamically generated")),
+            #[cfg(feature = "dnssec")]
+            RecordType::CDS => Self::DNSSEC(DNSSECRData::CDS(ds::parse(tokens)?.into())),
+            #[cfg(not(feature = "dnssec"))]
             RecordType::CDS => return Err(ParseError::from("CDS should be dynamically generated")),
+            #[cfg(feature = "dnssec")]
+            RecordType::NSEC => Self::DNSSEC(DNSSECRData::NSEC(nsec::parse(tokens, origin)?)),
+            #[cfg(not(feature = "dnssec"))]
             RecordType::NSEC => {
                 return Err(ParseError::from("NSEC should be dynamically generated"))
             }
+            #[cfg(feature = "dnssec")]
+            RecordType::NSEC3 => Self::DNSSEC(DNSSECRData::NSEC3(nsec3::parse(tokens)?)),
+            #[cfg(not(feature = "dnssec"))]
             RecordType::NSEC3 => {
                 return Err(ParseError::from("NSEC3 should be dynamically generated"))
             }
+            #[cfg(feature = "dnssec")]
+            RecordType::NSEC3PARAM => {
+                Self::DNSSEC(DNSSECRData::NSEC3PARAM(nsec3param::parse(tokens)?))
+            }
+            #[cfg(not(feature = "dnssec"))]
             RecordType::NSEC3PARAM => {
                 return Err(ParseError::from(
                     "NSEC3PARAM should be dynamically generated",
                 ))
             }
+            #[cfg(feature = "dnssec")]
+            RecordType::RRSIG => Self::DNSSEC(DNSSECRData::RRSIG(rrsig::parse(tokens, origin)?)),
+            #[cfg(not(feature = "dnssec"))]
             RecordType::RRSIG => {
                 return Err(ParseError::from("RRSIG should be dynamically generated"))
             }
             RecordType::TSIG => return Err(ParseError::from("TSIG is only used during AXFR")),
             #[allow(deprecated)]
             RecordType::ZERO => Self::ZERO,
+            #[cfg(not(feature = "rdata-amtrelay"))]
+            r @ RecordType::AMTRELAY => {
+                return Err(ParseError::from(ParseErrorKind::UnsupportedRecordType(r)))
+            }
+            #[cfg(not(feature = "rdata-caa"))]
+            r @ RecordType::CAA => {
+                return Err(ParseError::from(ParseErrorKind::UnsupportedRecordType(r)))
+            }
+            #[cfg(not(feature = "rdata-csync"))]
+            r @ RecordType::CSYNC => {
+                return Err(ParseError::from(ParseErrorKind::UnsupportedRecordType(r)))
+            }
+            #[cfg(not(feature = "rdata-hinfo"))]
+            r @ RecordType::HINFO => {
+                return Err(ParseError::from(ParseErrorKind::UnsupportedRecordType(r)))
+            }
+            #[cfg(not(feature = "rdata-svcb"))]
+            r @ RecordType::HTTPS => {
+                return Err(ParseError::from(ParseErrorKind::UnsupportedRecordType(r)))
+            }
+            #[cfg(not(feature = "rdata-ipseckey"))]
+            r @ RecordType::IPSECKEY => {
+                return Err(ParseError::from(ParseErrorKind::UnsupportedRecordType(r)))
+            }
+            #[cfg(not(feature = "rdata-naptr"))]
+            r @ RecordType::NAPTR => {
+                return Err(ParseError::from(ParseErrorKind::UnsupportedRecordType(r)))
+            }
+            #[cfg(not(feature = "rdata-openpgpkey"))]
+            r @ RecordType::OPENPGPKEY => {
+                return Err(ParseError::from(ParseErrorKind::UnsupportedRecordType(r)))
+            }
+            #[cfg(not(feature = "rdata-sshfp"))]
+            r @ RecordType::SSHFP => {
+                return Err(ParseError::from(ParseErrorKind::UnsupportedRecordType(r)))
+            }
+            #[cfg(not(feature = "rdata-svcb"))]
+            r @ RecordType::SVCB => {
+                return Err(ParseError::from(ParseErrorKind::UnsupportedRecordType(r)))
+            }
+            #[cfg(not(feature = "rdata-tlsa"))]
+            r @ RecordType::TLSA => {
+                return Err(ParseError::from(ParseErrorKind::UnsupportedRecordType(r)))
+            }
             r @ RecordType::Unknown(..) => {
-                // TODO: add a way to associate generic record types to the zone
+                // unknown types can only be represented with the generic encoding, RFC 3597
                 return Err(ParseError::from(ParseErrorKind::UnsupportedRecordType(r)));
             }
         };
@@ -127,6 +221,40 @@ impl RDataParser for RData {
     }
 }
 
+impl RData {
+    /// Decodes generic, RFC 3597, rdata as the record type
+    ///
+    /// Known types are decoded into their specific representation, and must consume all the
+    /// rdata, unknown types are preserved as is.
+    fn from_generic(record_type: RecordType, rdata: Vec<u8>) -> ParseResult<Self> {
+        if let RecordType::Unknown(_) = record_type {
+            let rdata = if rdata.is_empty() {
+                NULL::new()
+            } else {
+                NULL::with(rdata)
+            };
+            return Ok(Self::Unknown {
+                code: record_type,
+                rdata,
+            });
+        }
+
+        let length = rdata.len() as u16;
+        let mut decoder = BinDecoder::new(&rdata);
+        let parsed = Self::read(&mut decoder, record_type, Restrict::new(length))?;
+
+        if !decoder.is_empty() {
+            return Err(ParseErrorKind::Msg(format!(
+                "generic rdata for {record_type} has {} trailing bytes",
+                decoder.len()
+            ))
+            .into());
+        }
+
+        Ok(parsed)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(clippy::dbg_macro, clippy::print_stdout)]
@@ -156,6 +284,29 @@ mod tests {
         assert_eq!(record, RData::A("192.168.0.1".parse().unwrap()));
     }
 
+    #[test]
+    fn test_generic() {
+        let record = RData::try_from_str(RecordType::A, "\\# 4 0A000001").unwrap();
+        assert_eq!(record, RData::A("10.0.0.1".parse().unwrap()));
+
+        let record = RData::try_from_str(RecordType::Unknown(65534), "\\# 3 abcdef").unwrap();
+        assert_eq!(
+            record,
+            RData::Unknown {
+                code: RecordType::Unknown(65534),
+                rdata: NULL::with(vec![0xab, 0xcd, 0xef]),
+            }
+        );
+        assert_eq!(record.to_string(), "\\# 3 abcdef");
+
+        let record = RData::try_from_str(RecordType::Unknown(65534), "\\# 0").unwrap();
+        assert_eq!(record.to_string(), "\\# 0");
+
+        // trailing data is not allowed for known types
+        assert!(RData::try_from_str(RecordType::A, "\\# 5 0A00000100").is_err());
+        assert!(RData::try_from_str(RecordType::A, "\\# 4 0A0000").is_err());
+    }
+
     #[test]
     fn test_aaaa() {
         let tokens = ["::1"];
@@ -190,6 +341,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "rdata-csync")]
     fn test_csync() {
         let tokens = ["123", "1", "A", "NS"];
         let name = Name::from_str("example.com.").unwrap();
@@ -212,6 +364,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "rdata-csync")]
     fn test_csync_parse() {
         let data = "123 1 A NS";
         let record = RData::try_from_str(RecordType::CSYNC, data).unwrap();
@@ -281,6 +434,8 @@ mod tests {
             RecordType::DNSKEY,
             RecordType::CDNSKEY,
             RecordType::KEY,
+            // a next domain name without any types is a valid NSEC
+            #[cfg(not(feature = "dnssec"))]
             RecordType::NSEC,
             RecordType::NSEC3,
             RecordType::NSEC3PARAM,
@@ -0,0 +1,107 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! AMTRELAY records for discovery of Automatic Multicast Tunneling relays
+
+use crate::rr::domain::Name;
+use crate::rr::rdata::amtrelay::{Relay, AMTRELAY};
+use crate::serialize::txt::errors::{ParseError, ParseErrorKind, ParseResult};
+
+/// Parse the RData from a set of Tokens
+///
+/// [RFC 8777](https://tools.ietf.org/html/rfc8777#section-4.3)
+///
+/// ```text
+/// 4.3.  AMTRELAY Record Presentation Format
+///
+///    The presentation format of the AMTRELAY RR is:
+///
+///       IN AMTRELAY precedence D-bit type relay
+///
+///    If the type is 0, the relay field MUST be ".", for types 1 and 2 it
+///    is an address, and for type 3 it is a domain name.
+/// ```
+pub(crate) fn parse<'i, I: Iterator<Item = &'i str>>(
+    mut tokens: I,
+    origin: Option<&Name>,
+) -> ParseResult<AMTRELAY> {
+    let mut parse_u8 = |field: &str| {
+        tokens
+            .next()
+            .ok_or_else(|| ParseError::from(ParseErrorKind::MissingToken(field.to_string())))
+            .and_then(|t| t.parse::<u8>().map_err(ParseError::from))
+    };
+
+    let precedence = parse_u8("precedence")?;
+    let discovery_optional = match parse_u8("D-bit")? {
+        0 => false,
+        1 => true,
+        _ => return Err(ParseError::from("AMTRELAY D-bit must be 0 or 1")),
+    };
+    let relay_type = parse_u8("type")?;
+
+    let relay = tokens
+        .next()
+        .ok_or_else(|| ParseError::from(ParseErrorKind::MissingToken("relay".to_string())))?;
+    let relay = match relay_type {
+        0 if relay == "." => Relay::None,
+        0 => return Err(ParseError::from("AMTRELAY relay must be '.' for type 0")),
+        1 => Relay::Ipv4(relay.parse().map_err(ParseError::from)?),
+        2 => Relay::Ipv6(relay.parse().map_err(ParseError::from)?),
+        3 => Relay::Name(Name::parse(relay, origin)?),
+        _ => {
+            return Err(ParseErrorKind::Msg(format!(
+                "unrecognized AMTRELAY relay type: {relay_type}"
+            ))
+            .into())
+        }
+    };
+
+    Some(AMTRELAY::new(precedence, discovery_optional, relay))
+        .filter(|_| tokens.next().is_none())
+        .ok_or_else(|| ParseErrorKind::Message("too many fields for AMTRELAY").into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_parsing() {
+        assert!(parse(::std::iter::empty(), None).is_err());
+        assert!(parse(vec!["10", "2", "0", "."].into_iter(), None).is_err());
+        assert!(parse(vec!["10", "0", "1", "."].into_iter(), None).is_err());
+        assert!(parse(vec!["10", "0", "0", ".", "foo"].into_iter(), None).is_err());
+
+        assert_eq!(
+            parse(vec!["10", "0", "0", "."].into_iter(), None).unwrap(),
+            AMTRELAY::new(10, false, Relay::None)
+        );
+        assert_eq!(
+            parse(vec!["10", "1", "1", "203.0.113.15"].into_iter(), None).unwrap(),
+            AMTRELAY::new(10, true, Relay::Ipv4("203.0.113.15".parse().unwrap()))
+        );
+        assert_eq!(
+            parse(vec!["10", "0", "2", "2001:db8::15"].into_iter(), None).unwrap(),
+            AMTRELAY::new(10, false, Relay::Ipv6("2001:db8::15".parse().unwrap()))
+        );
+        assert_eq!(
+            parse(
+                vec!["128", "1", "3", "amtrelays.example.com."].into_iter(),
+                None
+            )
+            .unwrap(),
+            AMTRELAY::new(
+                128,
+                true,
+                Relay::Name(Name::from_str("amtrelays.example.com.").unwrap())
+            )
+        );
+    }
+}
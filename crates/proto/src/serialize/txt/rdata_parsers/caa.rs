@@ -16,7 +16,7 @@
 
 //! mail exchange, email, record
 
-use tracing::warn;
+use crate::logging::warn;
 
 use crate::rr::rdata::caa;
 use crate::rr::rdata::caa::{Property, Value};
@@ -0,0 +1,106 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Parser for DNSKEY text form
+
+use crate::rr::dnssec::rdata::DNSKEY;
+use crate::rr::dnssec::Algorithm;
+use crate::serialize::txt::errors::{ParseError, ParseErrorKind, ParseResult};
+
+/// Parse the RData from a set of Tokens
+///
+/// [RFC 4034, Resource Records for the DNS Security Extensions](https://datatracker.ietf.org/doc/html/rfc4034#section-2.2)
+/// ```text
+/// 2.2.  The DNSKEY RR Presentation Format
+///
+///    The presentation format of the RDATA portion is as follows:
+///
+///    The Flag field MUST be represented as an unsigned decimal integer.
+///    Given the currently defined flags, the possible values are: 0, 256,
+///    and 257.
+///
+///    The Protocol Field MUST be represented as an unsigned decimal integer
+///    with a value of 3.
+///
+///    The Algorithm field MUST be represented either as an unsigned decimal
+///    integer or as an algorithm mnemonic as specified in Appendix A.1.
+///
+///    The Public Key field MUST be represented as a Base64 encoding of the
+///    Public Key.  Whitespace is allowed within the Base64 text.
+/// ```
+pub(crate) fn parse<'i, I: Iterator<Item = &'i str>>(mut tokens: I) -> ParseResult<DNSKEY> {
+    let flags: u16 = tokens
+        .next()
+        .ok_or_else(|| ParseError::from(ParseErrorKind::MissingToken("flags".to_string())))?
+        .parse()?;
+    let protocol: u8 = tokens
+        .next()
+        .ok_or_else(|| ParseError::from(ParseErrorKind::MissingToken("protocol".to_string())))?
+        .parse()?;
+    if protocol != 3 {
+        return Err(ParseErrorKind::Msg(format!("DNSKEY protocol must be 3: {protocol}")).into());
+    }
+    let algorithm =
+        algorithm(tokens.next().ok_or_else(|| {
+            ParseError::from(ParseErrorKind::MissingToken("algorithm".to_string()))
+        })?)?;
+
+    let public_key: String = tokens.collect();
+    if public_key.is_empty() {
+        return Err(ParseErrorKind::Message("public key not present").into());
+    }
+    let public_key = data_encoding::BASE64.decode(public_key.as_bytes())?;
+
+    Ok(DNSKEY::new(
+        flags & 0b0000_0001_0000_0000 != 0,
+        flags & 0b0000_0000_0000_0001 != 0,
+        flags & 0b0000_0000_1000_0000 != 0,
+        algorithm,
+        public_key,
+    ))
+}
+
+/// Parses an algorithm, either its number or its mnemonic, see `Algorithm::as_str`
+pub(crate) fn algorithm(token: &str) -> ParseResult<Algorithm> {
+    if let Ok(value) = token.parse::<u8>() {
+        return Ok(Algorithm::from_u8(value));
+    }
+
+    #[allow(deprecated)]
+    let algorithm = match token.to_ascii_uppercase().as_str() {
+        "RSAMD5" => Algorithm::RSAMD5,
+        "DSA" => Algorithm::DSA,
+        "RSASHA1" => Algorithm::RSASHA1,
+        "RSASHA1-NSEC3-SHA1" | "NSEC3RSASHA1" => Algorithm::RSASHA1NSEC3SHA1,
+        "RSASHA256" => Algorithm::RSASHA256,
+        "RSASHA512" => Algorithm::RSASHA512,
+        "ECDSAP256SHA256" => Algorithm::ECDSAP256SHA256,
+        "ECDSAP384SHA384" => Algorithm::ECDSAP384SHA384,
+        "ED25519" => Algorithm::ED25519,
+        _ => return Err(ParseErrorKind::Msg(format!("unknown algorithm: {token}")).into()),
+    };
+    Ok(algorithm)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parsing() {
+        assert_eq!(
+            parse("257 3 8 AQID BAU=".split(' ')).unwrap(),
+            DNSKEY::new(true, true, false, Algorithm::RSASHA256, vec![1, 2, 3, 4, 5])
+        );
+        assert_eq!(
+            parse("256 3 ED25519 AQID".split(' ')).unwrap(),
+            DNSKEY::new(true, false, false, Algorithm::ED25519, vec![1, 2, 3])
+        );
+        assert!(parse("257 2 8 AQID".split(' ')).is_err());
+        assert!(parse("257 3 8".split(' ')).is_err());
+    }
+}
@@ -0,0 +1,89 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! generic record data for types which are not known to the parser
+
+use crate::rr::rdata::HEX;
+use crate::serialize::txt::errors::{ParseError, ParseErrorKind, ParseResult};
+
+/// The token which introduces the generic rdata encoding
+pub(crate) const GENERIC_RDATA: &str = "\\#";
+
+/// Parse the RData from a set of Tokens, the leading `\#` must already be consumed
+///
+/// [RFC 3597](https://tools.ietf.org/html/rfc3597#section-5)
+///
+/// ```text
+/// 5.  Text Representation
+///
+///    The RDATA section of an RR of unknown type is represented as a
+///    sequence of white space separated words as follows:
+///
+///       The special token \# (a backslash immediately followed by a hash
+///       sign), which identifies the RDATA as having the generic encoding
+///       defined herein rather than a traditional type-specific encoding.
+///
+///       An unsigned decimal integer specifying the RDATA length in octets.
+///
+///       Zero or more words of hexadecimal data encoding the actual RDATA
+///       field, each containing an even number of hexadecimal digits.
+///
+///    If the RDATA is of zero length, the text representation contains only
+///    the \# token and the single zero representing the length.
+/// ```
+pub(crate) fn parse<'i, I: Iterator<Item = &'i str>>(mut tokens: I) -> ParseResult<Vec<u8>> {
+    let length: usize = tokens
+        .next()
+        .ok_or_else(|| ParseError::from(ParseErrorKind::Message("generic rdata length missing")))?
+        .parse()?;
+
+    if length > usize::from(u16::MAX) {
+        return Err(ParseErrorKind::Msg(format!("generic rdata too long: {length}")).into());
+    }
+
+    let mut rdata = Vec::with_capacity(length);
+    for word in tokens {
+        rdata.extend(HEX.decode(word.as_bytes())?);
+    }
+
+    if rdata.len() != length {
+        return Err(ParseErrorKind::Msg(format!(
+            "generic rdata length {length} does not match data length {}",
+            rdata.len()
+        ))
+        .into());
+    }
+
+    Ok(rdata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parsing() {
+        assert_eq!(parse(vec!["0"].into_iter()).unwrap(), Vec::<u8>::new());
+        assert_eq!(
+            parse(vec!["4", "0A000001"].into_iter()).unwrap(),
+            vec![10, 0, 0, 1]
+        );
+        assert_eq!(
+            parse(vec!["4", "0a00", "0001"].into_iter()).unwrap(),
+            vec![10, 0, 0, 1]
+        );
+    }
+
+    #[test]
+    fn test_parsing_fails() {
+        assert!(parse(std::iter::empty()).is_err());
+        assert!(parse(vec!["4", "0A0000"].into_iter()).is_err());
+        assert!(parse(vec!["1", "0A00"].into_iter()).is_err());
+        assert!(parse(vec!["1", "0"].into_iter()).is_err());
+        assert!(parse(vec!["1", "zz"].into_iter()).is_err());
+    }
+}
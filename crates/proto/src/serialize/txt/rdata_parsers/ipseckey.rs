@@ -0,0 +1,133 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! IPSECKEY records for storing IPsec keying material
+
+use crate::rr::domain::Name;
+use crate::rr::rdata::ipseckey::{Gateway, IPSECKEY};
+use crate::serialize::txt::errors::{ParseError, ParseErrorKind, ParseResult};
+
+/// Parse the RData from a set of Tokens
+///
+/// [RFC 4025](https://tools.ietf.org/html/rfc4025#section-3.1)
+///
+/// ```text
+/// 3.1.  Representation of IPSECKEY RRs
+///
+///    IPSECKEY RRs may appear in a zone data master file.  The precedence,
+///    gateway type, algorithm, and gateway fields are REQUIRED.  The base64
+///    encoded public key block is OPTIONAL; if it is not present, the public
+///    key field of the resource record MUST be construed to be zero octets
+///    in length.
+///
+///    IN     IPSECKEY ( precedence gateway-type algorithm
+///                      gateway base64-encoded-public-key )
+///
+///    If no gateway is to be indicated, then the gateway type field MUST be
+///    zero, and the gateway field MUST be "."
+/// ```
+pub(crate) fn parse<'i, I: Iterator<Item = &'i str>>(
+    mut tokens: I,
+    origin: Option<&Name>,
+) -> ParseResult<IPSECKEY> {
+    let mut parse_u8 = |field: &str| {
+        tokens
+            .next()
+            .ok_or_else(|| ParseError::from(ParseErrorKind::MissingToken(field.to_string())))
+            .and_then(|t| t.parse::<u8>().map_err(ParseError::from))
+    };
+
+    let precedence = parse_u8("precedence")?;
+    let gateway_type = parse_u8("gateway type")?;
+    let algorithm = parse_u8("algorithm")?.into();
+
+    let gateway = tokens
+        .next()
+        .ok_or_else(|| ParseError::from(ParseErrorKind::MissingToken("gateway".to_string())))?;
+    let gateway = match gateway_type {
+        0 if gateway == "." => Gateway::None,
+        0 => {
+            return Err(ParseError::from(
+                "IPSECKEY gateway must be '.' for gateway type 0",
+            ))
+        }
+        1 => Gateway::Ipv4(gateway.parse().map_err(ParseError::from)?),
+        2 => Gateway::Ipv6(gateway.parse().map_err(ParseError::from)?),
+        3 => Gateway::Name(Name::parse(gateway, origin)?),
+        _ => {
+            return Err(ParseErrorKind::Msg(format!(
+                "unrecognized IPSECKEY gateway type: {gateway_type}"
+            ))
+            .into())
+        }
+    };
+
+    // the base64 encoded key may be split across multiple tokens
+    let encoded_public_key = tokens.collect::<String>();
+    let public_key = data_encoding::BASE64.decode(encoded_public_key.as_bytes())?;
+
+    Ok(IPSECKEY::new(precedence, algorithm, gateway, public_key))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::rr::rdata::ipseckey::Algorithm;
+
+    #[test]
+    fn test_parsing() {
+        assert!(parse(::std::iter::empty(), None).is_err());
+        assert!(parse(vec!["10", "1", "2"].into_iter(), None).is_err());
+        assert!(parse(vec!["10", "0", "2", "192.0.2.38"].into_iter(), None).is_err());
+        assert!(parse(vec!["10", "1", "2", "gw.example."].into_iter(), None).is_err());
+        assert!(parse(vec!["10", "4", "2", "."].into_iter(), None).is_err());
+
+        assert_eq!(
+            parse(vec!["10", "0", "2", ".", "a2V5"].into_iter(), None).unwrap(),
+            IPSECKEY::new(10, Algorithm::RSA, Gateway::None, b"key".to_vec())
+        );
+        assert_eq!(
+            parse(
+                vec!["10", "1", "2", "192.0.2.38", "a2", "V5"].into_iter(),
+                None
+            )
+            .unwrap(),
+            IPSECKEY::new(
+                10,
+                Algorithm::RSA,
+                Gateway::Ipv4("192.0.2.38".parse().unwrap()),
+                b"key".to_vec()
+            )
+        );
+        assert_eq!(
+            parse(vec!["10", "2", "0", "2001:db8::1"].into_iter(), None).unwrap(),
+            IPSECKEY::new(
+                10,
+                Algorithm::None,
+                Gateway::Ipv6("2001:db8::1".parse().unwrap()),
+                vec![]
+            )
+        );
+
+        let origin = Name::from_str("example.com.").unwrap();
+        assert_eq!(
+            parse(
+                vec!["10", "3", "2", "gw", "a2V5"].into_iter(),
+                Some(&origin)
+            )
+            .unwrap(),
+            IPSECKEY::new(
+                10,
+                Algorithm::RSA,
+                Gateway::Name(Name::from_str("gw.example.com.").unwrap()),
+                b"key".to_vec()
+            )
+        );
+    }
+}
@@ -21,19 +21,42 @@
 // each of these module's has the parser for that rdata embedded, to keep the file sizes down...
 pub(crate) mod a;
 pub(crate) mod aaaa;
+#[cfg(feature = "rdata-amtrelay")]
+pub(crate) mod amtrelay;
+#[cfg(feature = "rdata-caa")]
 pub(crate) mod caa;
+#[cfg(feature = "rdata-csync")]
 pub(crate) mod csync;
 #[cfg(feature = "dnssec")]
+pub(crate) mod dnskey;
+#[cfg(feature = "dnssec")]
 pub(crate) mod ds;
+pub(crate) mod generic;
+#[cfg(feature = "rdata-hinfo")]
 pub(crate) mod hinfo;
+#[cfg(feature = "rdata-ipseckey")]
+pub(crate) mod ipseckey;
 pub(crate) mod mx;
 pub(crate) mod name;
+#[cfg(feature = "rdata-naptr")]
 pub(crate) mod naptr;
+#[cfg(feature = "dnssec")]
+pub(crate) mod nsec;
+#[cfg(feature = "dnssec")]
+pub(crate) mod nsec3;
+#[cfg(feature = "dnssec")]
+pub(crate) mod nsec3param;
 pub(crate) mod null;
+#[cfg(feature = "rdata-openpgpkey")]
 pub(crate) mod openpgpkey;
+#[cfg(feature = "dnssec")]
+pub(crate) mod rrsig;
 pub(crate) mod soa;
 pub(crate) mod srv;
+#[cfg(feature = "rdata-sshfp")]
 pub(crate) mod sshfp;
+#[cfg(feature = "rdata-svcb")]
 pub(crate) mod svcb;
+#[cfg(feature = "rdata-tlsa")]
 pub(crate) mod tlsa;
 pub(crate) mod txt;
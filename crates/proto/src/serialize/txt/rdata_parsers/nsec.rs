@@ -0,0 +1,72 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Parser for NSEC text form
+
+use std::str::FromStr;
+
+use crate::rr::dnssec::rdata::NSEC;
+use crate::rr::{Name, RecordType};
+use crate::serialize::txt::errors::{ParseErrorKind, ParseResult};
+
+/// Parse the RData from a set of Tokens
+///
+/// [RFC 4034, Resource Records for the DNS Security Extensions](https://datatracker.ietf.org/doc/html/rfc4034#section-4.2)
+/// ```text
+/// 4.2.  The NSEC RR Presentation Format
+///
+///    The presentation format of the RDATA portion is as follows:
+///
+///    The Next Domain field is represented as a domain name.
+///
+///    The Type Bit Maps field is represented as a sequence of RR type
+///    mnemonics.  When the mnemonic is not known, the TYPE representation
+///    as described in [RFC3597], Section 5, MUST be used.
+/// ```
+pub(crate) fn parse<'i, I: Iterator<Item = &'i str>>(
+    mut tokens: I,
+    origin: Option<&Name>,
+) -> ParseResult<NSEC> {
+    let next_domain_name = tokens
+        .next()
+        .ok_or_else(|| ParseErrorKind::MissingToken("next domain name".to_string()))
+        .and_then(|s| Name::parse(s, origin).map_err(Into::into))?;
+
+    Ok(NSEC::new(next_domain_name, type_bit_maps(tokens)?))
+}
+
+/// Parses the record types of a type bit map, shared with NSEC3
+pub(crate) fn type_bit_maps<'i, I: Iterator<Item = &'i str>>(
+    tokens: I,
+) -> ParseResult<Vec<RecordType>> {
+    tokens
+        .map(|token| RecordType::from_str(&token.to_ascii_uppercase()).map_err(Into::into))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parsing() {
+        let origin = Name::from_ascii("example.com.").unwrap();
+        assert_eq!(
+            parse("host A RRSIG NSEC TYPE65534".split(' '), Some(&origin)).unwrap(),
+            NSEC::new(
+                Name::from_ascii("host.example.com.").unwrap(),
+                vec![
+                    RecordType::A,
+                    RecordType::RRSIG,
+                    RecordType::NSEC,
+                    RecordType::Unknown(65534)
+                ]
+            )
+        );
+        assert!(parse("host.example.com. BOGUS".split(' '), None).is_err());
+    }
+}
@@ -0,0 +1,106 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Parser for NSEC3 text form
+
+use crate::rr::dnssec::rdata::NSEC3;
+use crate::serialize::txt::errors::{ParseErrorKind, ParseResult};
+
+use super::{nsec, nsec3param};
+
+/// Parse the RData from a set of Tokens
+///
+/// [RFC 5155, NSEC3](https://datatracker.ietf.org/doc/html/rfc5155#section-3.3)
+/// ```text
+/// 3.3.  Presentation Format
+///
+///    The presentation format of the RDATA portion is as follows:
+///
+///    o  The Hash Algorithm field is represented as an unsigned decimal
+///       integer.  The value has a maximum of 255.
+///
+///    o  The Flags field is represented as an unsigned decimal integer.
+///       The value has a maximum of 255.
+///
+///    o  The Iterations field is represented as an unsigned decimal
+///       integer.  The value is between 0 and 65535, inclusive.
+///
+///    o  The Salt Length field is not represented.
+///
+///    o  The Salt field is represented as a sequence of case-insensitive
+///       hexadecimal digits.  Whitespace is not allowed within the
+///       sequence.  The Salt field is represented as "-" (without the
+///       quotes) when the Salt Length field has a value of 0.
+///
+///    o  The Hash Length field is not represented.
+///
+///    o  The Next Hashed Owner Name field is represented as an unpadded
+///       sequence of case-insensitive base32 digits, without whitespace.
+///
+///    o  The Type Bit Maps field is represented as a sequence of RR type
+///       mnemonics.
+/// ```
+pub(crate) fn parse<'i, I: Iterator<Item = &'i str>>(mut tokens: I) -> ParseResult<NSEC3> {
+    let (hash_algorithm, opt_out, iterations, salt) = nsec3param::parameters(&mut tokens)?;
+    let next_hashed_owner_name = tokens
+        .next()
+        .ok_or_else(|| ParseErrorKind::MissingToken("next hashed owner name".to_string()))?;
+    let next_hashed_owner_name = data_encoding::BASE32_DNSSEC
+        .decode(next_hashed_owner_name.to_ascii_lowercase().as_bytes())?;
+
+    Ok(NSEC3::new(
+        hash_algorithm,
+        opt_out,
+        iterations,
+        salt,
+        next_hashed_owner_name,
+        nsec::type_bit_maps(tokens)?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rr::dnssec::Nsec3HashAlgorithm;
+    use crate::rr::RecordType;
+
+    #[test]
+    fn test_parsing() {
+        // from the example zone of RFC 5155, appendix A
+        let nsec3 = parse(
+            "1 1 12 aabbccdd 2t7b4g4vsa5smi47k61mv5bv1a22bojr MX DNSKEY NS SOA NSEC3PARAM RRSIG"
+                .split(' '),
+        )
+        .unwrap();
+        assert_eq!(
+            nsec3,
+            NSEC3::new(
+                Nsec3HashAlgorithm::SHA1,
+                true,
+                12,
+                vec![0xAA, 0xBB, 0xCC, 0xDD],
+                data_encoding::BASE32_DNSSEC
+                    .decode(b"2t7b4g4vsa5smi47k61mv5bv1a22bojr")
+                    .unwrap(),
+                vec![
+                    RecordType::MX,
+                    RecordType::DNSKEY,
+                    RecordType::NS,
+                    RecordType::SOA,
+                    RecordType::NSEC3PARAM,
+                    RecordType::RRSIG,
+                ],
+            )
+        );
+        assert_eq!(
+            nsec3.to_string(),
+            "1 1 12 AABBCCDD 2t7b4g4vsa5smi47k61mv5bv1a22bojr MX DNSKEY NS SOA NSEC3PARAM RRSIG"
+        );
+
+        assert!(parse("1 0 12 - not-base32 A".split(' ')).is_err());
+    }
+}
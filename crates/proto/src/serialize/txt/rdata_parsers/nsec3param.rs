@@ -0,0 +1,90 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Parser for NSEC3PARAM text form
+
+use crate::rr::dnssec::rdata::NSEC3PARAM;
+use crate::rr::dnssec::Nsec3HashAlgorithm;
+use crate::serialize::txt::errors::{ParseError, ParseErrorKind, ParseResult};
+
+/// Parse the RData from a set of Tokens
+///
+/// [RFC 5155, NSEC3](https://datatracker.ietf.org/doc/html/rfc5155#section-4.3)
+/// ```text
+/// 4.3.  Presentation Format
+///
+///    The presentation format of the RDATA portion is as follows:
+///
+///    o  The Hash Algorithm field is represented as an unsigned decimal
+///       integer.  The value has a maximum of 255.
+///
+///    o  The Flags field is represented as an unsigned decimal integer.
+///       The value has a maximum value of 255.
+///
+///    o  The Iterations field is represented as an unsigned decimal
+///       integer.  The value is between 0 and 65535, inclusive.
+///
+///    o  The Salt Length field is not represented.
+///
+///    o  The Salt field is represented as a sequence of case-insensitive
+///       hexadecimal digits.  Whitespace is not allowed within the
+///       sequence.  This field is represented as "-" (without the quotes)
+///       when the Salt Length field is zero.
+/// ```
+pub(crate) fn parse<'i, I: Iterator<Item = &'i str>>(mut tokens: I) -> ParseResult<NSEC3PARAM> {
+    let (hash_algorithm, opt_out, iterations, salt) = parameters(&mut tokens)?;
+    if tokens.next().is_some() {
+        return Err(ParseErrorKind::Message("NSEC3PARAM has trailing data").into());
+    }
+
+    Ok(NSEC3PARAM::new(hash_algorithm, opt_out, iterations, salt))
+}
+
+/// Parses the hash algorithm, the flags, the iterations and the salt, shared with NSEC3
+pub(crate) fn parameters<'i, I: Iterator<Item = &'i str>>(
+    tokens: &mut I,
+) -> ParseResult<(Nsec3HashAlgorithm, bool, u16, Vec<u8>)> {
+    let mut next = |field: &str| {
+        tokens
+            .next()
+            .ok_or_else(|| ParseError::from(ParseErrorKind::MissingToken(field.to_string())))
+    };
+
+    let hash_algorithm = Nsec3HashAlgorithm::from_u8(next("hash algorithm")?.parse()?)?;
+    let flags: u8 = next("flags")?.parse()?;
+    let iterations: u16 = next("iterations")?.parse()?;
+    let salt = match next("salt")? {
+        "-" => Vec::new(),
+        salt => data_encoding::HEXUPPER_PERMISSIVE.decode(salt.as_bytes())?,
+    };
+
+    Ok((hash_algorithm, flags & 0b0000_0001 != 0, iterations, salt))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parsing() {
+        assert_eq!(
+            parse("1 0 10 AABBCCDD".split(' ')).unwrap(),
+            NSEC3PARAM::new(
+                Nsec3HashAlgorithm::SHA1,
+                false,
+                10,
+                vec![0xAA, 0xBB, 0xCC, 0xDD]
+            )
+        );
+        assert_eq!(
+            parse("1 1 0 -".split(' ')).unwrap(),
+            NSEC3PARAM::new(Nsec3HashAlgorithm::SHA1, true, 0, vec![])
+        );
+        assert!(parse("2 0 10 -".split(' ')).is_err());
+        assert!(parse("1 0 10".split(' ')).is_err());
+    }
+}
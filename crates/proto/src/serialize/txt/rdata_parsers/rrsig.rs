@@ -0,0 +1,157 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Parser for RRSIG text form
+
+use std::str::FromStr;
+
+use crate::rr::dnssec::rdata::RRSIG;
+use crate::rr::{Name, RecordType};
+use crate::serialize::txt::errors::{ParseError, ParseErrorKind, ParseResult};
+
+use super::dnskey;
+
+/// Parse the RData from a set of Tokens
+///
+/// [RFC 4034, Resource Records for the DNS Security Extensions](https://datatracker.ietf.org/doc/html/rfc4034#section-3.2)
+/// ```text
+/// 3.2.  The RRSIG RR Presentation Format
+///
+///    The presentation format of the RDATA portion is as follows:
+///
+///    The Type Covered field is represented as an RR type mnemonic.
+///
+///    The Algorithm field value MUST be represented either as an unsigned
+///    decimal integer or as an algorithm mnemonic, as specified in Appendix
+///    A.1.
+///
+///    The Labels field value MUST be represented as an unsigned decimal
+///    integer.
+///
+///    The Original TTL field value MUST be represented as an unsigned
+///    decimal integer.
+///
+///    The Signature Expiration Time and Inception Time field values MUST be
+///    represented either as an unsigned decimal integer indicating seconds
+///    since 1 January 1970 00:00:00 UTC, or in the form YYYYMMDDHHmmSS in
+///    UTC.
+///
+///    The Key Tag field MUST be represented as an unsigned decimal integer.
+///
+///    The Signer's Name field value MUST be represented as a domain name.
+///
+///    The Signature field is represented as a Base64 encoding of the
+///    signature.  Whitespace is allowed within the Base64 text.
+/// ```
+pub(crate) fn parse<'i, I: Iterator<Item = &'i str>>(
+    mut tokens: I,
+    origin: Option<&Name>,
+) -> ParseResult<RRSIG> {
+    let mut next = |field: &str| {
+        tokens
+            .next()
+            .ok_or_else(|| ParseError::from(ParseErrorKind::MissingToken(field.to_string())))
+    };
+
+    let type_covered = RecordType::from_str(&next("type covered")?.to_ascii_uppercase())?;
+    let algorithm = dnskey::algorithm(next("algorithm")?)?;
+    let num_labels: u8 = next("labels")?.parse()?;
+    let original_ttl: u32 = next("original ttl")?.parse()?;
+    let sig_expiration = time(next("signature expiration")?)?;
+    let sig_inception = time(next("signature inception")?)?;
+    let key_tag: u16 = next("key tag")?.parse()?;
+    let signer_name = Name::parse(next("signer name")?, origin)?;
+
+    let sig: String = tokens.collect();
+    if sig.is_empty() {
+        return Err(ParseErrorKind::Message("signature not present").into());
+    }
+    let sig = data_encoding::BASE64.decode(sig.as_bytes())?;
+
+    Ok(RRSIG::new(
+        type_covered,
+        algorithm,
+        num_labels,
+        original_ttl,
+        sig_expiration,
+        sig_inception,
+        key_tag,
+        signer_name,
+        sig,
+    ))
+}
+
+/// Parses a time, either the seconds since the epoch or YYYYMMDDHHmmSS
+fn time(token: &str) -> ParseResult<u32> {
+    if token.len() != 14 {
+        return token.parse().map_err(Into::into);
+    }
+
+    let field = |range: std::ops::Range<usize>| -> ParseResult<i64> {
+        token
+            .get(range)
+            .ok_or_else(|| ParseErrorKind::ParseTime(token.to_string()))?
+            .parse()
+            .map_err(Into::into)
+    };
+    let (year, month, day) = (field(0..4)?, field(4..6)?, field(6..8)?);
+    let (hour, minute, second) = (field(8..10)?, field(10..12)?, field(12..14)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return Err(ParseErrorKind::ParseTime(token.to_string()).into());
+    }
+
+    // the days since the epoch of the civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let seconds = days * 86_400 + hour * 3_600 + minute * 60 + second;
+    // serial number arithmetic, RFC 4034 section 3.1.5
+    Ok(seconds.rem_euclid(1 << 32) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rr::dnssec::Algorithm;
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_parsing() {
+        // from RFC 4034, section 3.3
+        let origin = Name::from_ascii("example.com.").unwrap();
+        let rrsig = parse(
+            "A 5 3 86400 20030322173103 20030220173103 2642 example.com. oJB1W6WNGv+ldvQ3WDG0MQkg5IEhjRip8WTr PYGv07h108dUKGMeDPKijVCHX3DDKdfb+v6o B9wfuh3DTJXUAfI/M0zmO/zz8bW0Rznl8O3t GNazPwQKkRN20XPXV6nwwfoXmJQbsLNrLfkG J5D6fwFm8nN+6pBzeDQfsS3Ap3o=".split(' '),
+            Some(&origin),
+        )
+        .unwrap();
+
+        assert_eq!(rrsig.type_covered(), RecordType::A);
+        assert_eq!(rrsig.algorithm(), Algorithm::RSASHA1);
+        assert_eq!(rrsig.num_labels(), 3);
+        assert_eq!(rrsig.original_ttl(), 86400);
+        assert_eq!(rrsig.sig_expiration(), 1_048_354_263);
+        assert_eq!(rrsig.sig_inception(), 1_045_762_263);
+        assert_eq!(rrsig.key_tag(), 2642);
+        assert_eq!(rrsig.signer_name(), &origin);
+        assert_eq!(rrsig.sig().len(), 128);
+
+        // the decimal form, as it is displayed
+        assert_eq!(parse(rrsig.to_string().split(' '), None).unwrap(), rrsig);
+    }
+
+    #[test]
+    fn test_time() {
+        assert_eq!(time("19700101000000").unwrap(), 0);
+        assert_eq!(time("20000229120000").unwrap(), 951_825_600);
+        assert_eq!(time("1048354263").unwrap(), 1_048_354_263);
+        assert!(time("20031322173103").is_err());
+    }
+}
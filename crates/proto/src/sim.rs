@@ -0,0 +1,496 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! A deterministic in-memory network, for simulations of clients, resolvers and servers
+//!
+//! The [`SimNetwork`] connects the [`SimUdpSocket`]s and the [`SimTcpListener`]s bound to its
+//!  addresses without real sockets. Its links have latencies, and can be partitioned to test the
+//!  retries and timeouts of the transports.
+//!
+//! The time of the network is the time of Tokio. Under a current thread runtime with paused time,
+//!  e.g. `#[tokio::test(start_paused = true)]`, the time only advances when all tasks wait, and
+//!  then jumps to the next timer. Latencies and timeouts of minutes then elapse instantly, and a
+//!  simulation sees the same events in the same order on each run. The [`SimNetwork::clock`] of
+//!  the network follows this time, for the expiry of cached records and signatures.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use futures_channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use futures_util::{ready, StreamExt};
+use tokio::io::DuplexStream;
+
+use crate::clock::{Clock, Instant};
+use crate::iocompat::AsyncIoTokioAsStd;
+use crate::udp::{DnsUdpSocket, QuicLocalAddr};
+use crate::TokioTime;
+
+/// The first port assigned to the sockets bound to port 0
+const FIRST_EPHEMERAL_PORT: u16 = 49152;
+
+/// The size of the buffers of each direction of a stream
+const STREAM_BUFFER_SIZE: usize = 64 * 1024;
+
+/// A stream of a simulated TCP connection
+pub type SimTcpStream = AsyncIoTokioAsStd<DuplexStream>;
+
+/// The sockets, listeners and links of a network
+#[derive(Default)]
+struct NetworkState {
+    udp: HashMap<SocketAddr, UnboundedSender<(Vec<u8>, SocketAddr)>>,
+    tcp: HashMap<SocketAddr, UnboundedSender<(SimTcpStream, SocketAddr)>>,
+    next_port: u16,
+    default_latency: Duration,
+    latencies: HashMap<(IpAddr, IpAddr), Duration>,
+    partitions: HashSet<(IpAddr, IpAddr)>,
+}
+
+impl NetworkState {
+    /// Assigns the port of an address bound to port 0
+    fn assign_port(&mut self, addr: SocketAddr, tcp: bool) -> io::Result<SocketAddr> {
+        if addr.port() != 0 {
+            let bound = if tcp {
+                self.tcp.contains_key(&addr)
+            } else {
+                self.udp.contains_key(&addr)
+            };
+            return if bound {
+                Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("{addr} is already bound"),
+                ))
+            } else {
+                Ok(addr)
+            };
+        }
+
+        for _ in FIRST_EPHEMERAL_PORT..=u16::MAX {
+            let port = self.next_port.max(FIRST_EPHEMERAL_PORT);
+            self.next_port = port.checked_add(1).unwrap_or(FIRST_EPHEMERAL_PORT);
+
+            let addr = SocketAddr::new(addr.ip(), port);
+            if !self.udp.contains_key(&addr) && !self.tcp.contains_key(&addr) {
+                return Ok(addr);
+            }
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::AddrNotAvailable,
+            "no ephemeral ports left",
+        ))
+    }
+
+    /// The latency of the link, or None if it is partitioned
+    fn link(&self, from: IpAddr, to: IpAddr) -> Option<Duration> {
+        let link = link(from, to);
+        if self.partitions.contains(&link) {
+            return None;
+        }
+
+        Some(
+            self.latencies
+                .get(&link)
+                .copied()
+                .unwrap_or(self.default_latency),
+        )
+    }
+}
+
+/// The key of the link between the addresses, which is the same in both directions
+fn link(a: IpAddr, b: IpAddr) -> (IpAddr, IpAddr) {
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// A simulated network, see the [module](self) documentation
+///
+/// The clones of the network share its sockets and links. The network is created in the runtime
+///  of the simulation, the time of its clock starts at the creation.
+#[derive(Clone)]
+pub struct SimNetwork {
+    state: Arc<Mutex<NetworkState>>,
+    clock: Arc<SimClock>,
+}
+
+impl SimNetwork {
+    /// Creates a network without latency, the time of its clock is 2023-11-14 22:13:20 UTC
+    pub fn new() -> Self {
+        Self::with_system_time(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+    }
+
+    /// Creates a network without latency, with the time of its clock
+    pub fn with_system_time(system_time: SystemTime) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(NetworkState::default())),
+            clock: Arc::new(SimClock {
+                started: tokio::time::Instant::now(),
+                system_time,
+            }),
+        }
+    }
+
+    /// The clock of the network, which follows the time of Tokio
+    pub fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
+
+    /// Sets the latency of the links without their own latency
+    pub fn set_default_latency(&self, latency: Duration) {
+        self.state().default_latency = latency;
+    }
+
+    /// Sets the latency of the link between the addresses, in each direction
+    pub fn set_latency(&self, a: IpAddr, b: IpAddr, latency: Duration) {
+        self.state().latencies.insert(link(a, b), latency);
+    }
+
+    /// Cuts the link between the addresses, the packets are dropped and the connections hang
+    pub fn partition(&self, a: IpAddr, b: IpAddr) {
+        self.state().partitions.insert(link(a, b));
+    }
+
+    /// Restores the link between the addresses
+    pub fn heal(&self, a: IpAddr, b: IpAddr) {
+        self.state().partitions.remove(&link(a, b));
+    }
+
+    /// Binds a UDP socket to the address, a free port is assigned to port 0
+    pub fn bind_udp(&self, addr: SocketAddr) -> io::Result<SimUdpSocket> {
+        let mut state = self.state();
+        let addr = state.assign_port(addr, false)?;
+        let (sender, receiver) = mpsc::unbounded();
+        state.udp.insert(addr, sender);
+
+        Ok(SimUdpSocket {
+            network: self.clone(),
+            local_addr: addr,
+            receiver: Mutex::new(receiver),
+        })
+    }
+
+    /// Listens for TCP connections on the address, a free port is assigned to port 0
+    pub fn listen_tcp(&self, addr: SocketAddr) -> io::Result<SimTcpListener> {
+        let mut state = self.state();
+        let addr = state.assign_port(addr, true)?;
+        let (sender, receiver) = mpsc::unbounded();
+        state.tcp.insert(addr, sender);
+
+        Ok(SimTcpListener {
+            network: self.clone(),
+            local_addr: addr,
+            receiver: Mutex::new(receiver),
+        })
+    }
+
+    /// Connects from the address to a listener
+    ///
+    /// The connection takes the round trip time of the link, and never completes over a
+    ///  partitioned link. It is refused without a listener.
+    pub async fn connect_tcp(&self, from: IpAddr, to: SocketAddr) -> io::Result<SimTcpStream> {
+        let latency = self.state().link(from, to.ip());
+        let Some(latency) = latency else {
+            return futures_util::future::pending().await;
+        };
+        tokio::time::sleep(latency * 2).await;
+
+        let mut state = self.state();
+        let local_addr = state.assign_port(SocketAddr::new(from, 0), true)?;
+        let listener = state.tcp.get(&to).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                format!("no listener on {to}"),
+            )
+        })?;
+
+        let (client, server) = tokio::io::duplex(STREAM_BUFFER_SIZE);
+        listener
+            .unbounded_send((AsyncIoTokioAsStd(server), local_addr))
+            .map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::ConnectionRefused,
+                    format!("no listener on {to}"),
+                )
+            })?;
+
+        Ok(AsyncIoTokioAsStd(client))
+    }
+
+    /// Sends the packet over the link, it is dropped without a socket or over a partitioned link
+    fn send_udp(&self, from: SocketAddr, to: SocketAddr, packet: Vec<u8>) {
+        let state = self.state();
+        let (Some(latency), Some(socket)) = (state.link(from.ip(), to.ip()), state.udp.get(&to))
+        else {
+            return;
+        };
+
+        if latency.is_zero() {
+            let _ = socket.unbounded_send((packet, from));
+        } else {
+            let socket = socket.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(latency).await;
+                let _ = socket.unbounded_send((packet, from));
+            });
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, NetworkState> {
+        self.state.lock().expect("simulated network poisoned")
+    }
+}
+
+impl Default for SimNetwork {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for SimNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state();
+        f.debug_struct("SimNetwork")
+            .field("udp", &state.udp.keys())
+            .field("tcp", &state.tcp.keys())
+            .finish()
+    }
+}
+
+/// The clock of a network, see `SimNetwork::clock`
+#[derive(Debug)]
+struct SimClock {
+    started: tokio::time::Instant,
+    system_time: SystemTime,
+}
+
+impl Clock for SimClock {
+    fn instant(&self) -> Instant {
+        tokio::time::Instant::now().into_std()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        self.system_time + self.started.elapsed()
+    }
+}
+
+/// A UDP socket of a simulated network, it is unbound when dropped
+pub struct SimUdpSocket {
+    network: SimNetwork,
+    local_addr: SocketAddr,
+    receiver: Mutex<UnboundedReceiver<(Vec<u8>, SocketAddr)>>,
+}
+
+impl SimUdpSocket {
+    /// The address of the socket
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl fmt::Debug for SimUdpSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SimUdpSocket")
+            .field("local_addr", &self.local_addr)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl DnsUdpSocket for SimUdpSocket {
+    type Time = TokioTime;
+
+    fn poll_recv_from(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<(usize, SocketAddr)>> {
+        let mut receiver = self.receiver.lock().expect("simulated socket poisoned");
+        let Some((packet, from)) = ready!(receiver.poll_next_unpin(cx)) else {
+            return Poll::Ready(Err(io::ErrorKind::NotConnected.into()));
+        };
+
+        let len = packet.len().min(buf.len());
+        buf[..len].copy_from_slice(&packet[..len]);
+        Poll::Ready(Ok((len, from)))
+    }
+
+    fn poll_send_to(
+        &self,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+        target: SocketAddr,
+    ) -> Poll<io::Result<usize>> {
+        self.network.send_udp(self.local_addr, target, buf.to_vec());
+        Poll::Ready(Ok(buf.len()))
+    }
+}
+
+impl QuicLocalAddr for SimUdpSocket {
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.local_addr)
+    }
+}
+
+impl Drop for SimUdpSocket {
+    fn drop(&mut self) {
+        self.network.state().udp.remove(&self.local_addr);
+    }
+}
+
+/// A TCP listener of a simulated network, it is unbound when dropped
+pub struct SimTcpListener {
+    network: SimNetwork,
+    local_addr: SocketAddr,
+    receiver: Mutex<UnboundedReceiver<(SimTcpStream, SocketAddr)>>,
+}
+
+impl SimTcpListener {
+    /// The address of the listener
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Polls for the next connection, with the address of its client
+    pub fn poll_accept(
+        &self,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<(SimTcpStream, SocketAddr)>> {
+        let mut receiver = self.receiver.lock().expect("simulated listener poisoned");
+        match ready!(receiver.poll_next_unpin(cx)) {
+            Some(connection) => Poll::Ready(Ok(connection)),
+            None => Poll::Ready(Err(io::ErrorKind::NotConnected.into())),
+        }
+    }
+
+    /// Accepts the next connection, with the address of its client
+    pub async fn accept(&self) -> io::Result<(SimTcpStream, SocketAddr)> {
+        futures_util::future::poll_fn(|cx| self.poll_accept(cx)).await
+    }
+}
+
+impl fmt::Debug for SimTcpListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SimTcpListener")
+            .field("local_addr", &self.local_addr)
+            .finish()
+    }
+}
+
+impl Drop for SimTcpListener {
+    fn drop(&mut self) {
+        self.network.state().tcp.remove(&self.local_addr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use futures_util::future::poll_fn;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    const CLIENT: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    const SERVER: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 53));
+
+    #[tokio::test(start_paused = true)]
+    async fn test_udp_latency() {
+        let network = SimNetwork::new();
+        network.set_latency(CLIENT, SERVER, Duration::from_millis(150));
+
+        let server = network.bind_udp(SocketAddr::new(SERVER, 53)).unwrap();
+        let client = network.bind_udp(SocketAddr::new(CLIENT, 0)).unwrap();
+        assert_eq!(client.local_addr().port(), FIRST_EPHEMERAL_PORT);
+        assert!(network.bind_udp(SocketAddr::new(SERVER, 53)).is_err());
+
+        let started = tokio::time::Instant::now();
+        let system_time = network.clock().system_time();
+        client
+            .send_to(b"query", SocketAddr::new(SERVER, 53))
+            .await
+            .unwrap();
+
+        let mut buf = [0_u8; 512];
+        let (len, from) = server.recv_from(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"query");
+        assert_eq!(from, client.local_addr());
+        assert_eq!(started.elapsed(), Duration::from_millis(150));
+        assert_eq!(
+            network.clock().system_time(),
+            system_time + Duration::from_millis(150)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_udp_partition() {
+        let network = SimNetwork::new();
+        let server = network.bind_udp(SocketAddr::new(SERVER, 53)).unwrap();
+        let client = network.bind_udp(SocketAddr::new(CLIENT, 0)).unwrap();
+
+        network.partition(SERVER, CLIENT);
+        client
+            .send_to(b"lost", SocketAddr::new(SERVER, 53))
+            .await
+            .unwrap();
+        network.heal(CLIENT, SERVER);
+        client
+            .send_to(b"delivered", SocketAddr::new(SERVER, 53))
+            .await
+            .unwrap();
+
+        let mut buf = [0_u8; 512];
+        let (len, _) = server.recv_from(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"delivered");
+
+        // the socket is unbound when dropped
+        drop(server);
+        assert!(network.bind_udp(SocketAddr::new(SERVER, 53)).is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_tcp() {
+        let network = SimNetwork::new();
+        network.set_default_latency(Duration::from_millis(10));
+        let listener = network.listen_tcp(SocketAddr::new(SERVER, 53)).unwrap();
+
+        let started = tokio::time::Instant::now();
+        let mut client = network
+            .connect_tcp(CLIENT, SocketAddr::new(SERVER, 53))
+            .await
+            .unwrap();
+        assert_eq!(started.elapsed(), Duration::from_millis(20));
+
+        let (mut server, from) = poll_fn(|cx| listener.poll_accept(cx)).await.unwrap();
+        assert_eq!(from.ip(), CLIENT);
+        client.0.write_all(b"query").await.unwrap();
+        let mut buf = [0_u8; 5];
+        server.0.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"query");
+
+        let refused = network
+            .connect_tcp(CLIENT, SocketAddr::new(SERVER, 853))
+            .await;
+        assert!(matches!(refused, Err(e) if e.kind() == io::ErrorKind::ConnectionRefused));
+
+        network.partition(CLIENT, SERVER);
+        let hung = tokio::time::timeout(
+            Duration::from_secs(60),
+            network.connect_tcp(CLIENT, SocketAddr::new(SERVER, 53)),
+        )
+        .await;
+        assert!(hung.is_err());
+    }
+}
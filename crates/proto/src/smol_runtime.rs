@@ -0,0 +1,230 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Sockets and timers on the [smol](https://docs.rs/smol) runtime
+//!
+//! UDP and TCP are implemented for [`smol::Async`] wrapping the standard sockets, the reactor of
+//!  smol drives them on any executor. Together with [`SmolTime`] they are everything needed to run
+//!  the clients without Tokio.
+
+use std::future::Future;
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, UdpSocket as StdUdpSocket};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_util::ready;
+use smol::{future, Async, Timer};
+use socket2::{Domain, Protocol, Socket, Type};
+
+use crate::tcp::{Connect, DnsTcpStream};
+use crate::udp::{DnsUdpSocket, QuicLocalAddr, UdpSocket};
+use crate::Time;
+
+/// New type which is implemented using the timers of smol
+#[derive(Clone, Copy, Debug)]
+pub struct SmolTime;
+
+#[async_trait]
+impl Time for SmolTime {
+    async fn delay_for(duration: Duration) {
+        Timer::after(duration).await;
+    }
+
+    async fn timeout<F: 'static + Future + Send>(
+        duration: Duration,
+        future: F,
+    ) -> Result<F::Output, io::Error> {
+        future::or(async { Ok(future.await) }, async {
+            Timer::after(duration).await;
+            Err(io::Error::new(io::ErrorKind::TimedOut, "future timed out"))
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl DnsUdpSocket for Async<StdUdpSocket> {
+    type Time = SmolTime;
+
+    fn poll_recv_from(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<(usize, SocketAddr)>> {
+        loop {
+            match self.get_ref().recv_from(buf) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => ready!(self.poll_readable(cx))?,
+                result => return Poll::Ready(result),
+            }
+        }
+    }
+
+    fn poll_send_to(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        target: SocketAddr,
+    ) -> Poll<io::Result<usize>> {
+        loop {
+            match self.get_ref().send_to(buf, target) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => ready!(self.poll_writable(cx))?,
+                result => return Poll::Ready(result),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl UdpSocket for Async<StdUdpSocket> {
+    /// setups up a "client" udp connection that will only receive packets from the associated address
+    ///
+    /// if the addr is ipv4 then it will bind local addr to 0.0.0.0:0, ipv6 \[::\]0
+    async fn connect(addr: SocketAddr) -> io::Result<Self> {
+        let bind_addr: SocketAddr = match addr {
+            SocketAddr::V4(_addr) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            SocketAddr::V6(_addr) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+        };
+
+        Self::connect_with_bind(addr, bind_addr).await
+    }
+
+    /// same as connect, but binds to the specified local address for sending address
+    async fn connect_with_bind(_addr: SocketAddr, bind_addr: SocketAddr) -> io::Result<Self> {
+        // the inherent `Async::bind`, not this trait function
+        Self::bind(bind_addr)
+    }
+
+    async fn bind(addr: SocketAddr) -> io::Result<Self> {
+        Self::bind(addr)
+    }
+}
+
+impl QuicLocalAddr for Async<StdUdpSocket> {
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.get_ref().local_addr()
+    }
+}
+
+impl DnsTcpStream for Async<TcpStream> {
+    type Time = SmolTime;
+}
+
+#[async_trait]
+impl Connect for Async<TcpStream> {
+    async fn connect_with_bind(
+        addr: SocketAddr,
+        bind_addr: Option<SocketAddr>,
+    ) -> io::Result<Self> {
+        let stream = match bind_addr {
+            Some(bind_addr) => {
+                let socket =
+                    Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+                socket.set_nonblocking(true)?;
+                socket.bind(&bind_addr.into())?;
+                match socket.connect(&addr.into()) {
+                    Ok(()) => (),
+                    #[cfg(unix)]
+                    Err(e) if e.raw_os_error() == Some(libc::EINPROGRESS) => (),
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => (),
+                    Err(e) => return Err(e),
+                }
+
+                // the stream becomes writable once connected
+                let stream = Self::new(TcpStream::from(socket))?;
+                stream.writable().await?;
+                if let Some(e) = stream.get_ref().take_error()? {
+                    return Err(e);
+                }
+                stream
+            }
+            // the inherent `Async::connect`, not this trait function
+            None => Self::connect(addr).await?,
+        };
+        stream.get_ref().set_nodelay(true)?;
+        Ok(stream)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::*;
+    use crate::tests::{
+        next_random_socket_test, tcp_client_stream_test, tcp_stream_test, udp_client_stream_test,
+        udp_stream_test,
+    };
+    use crate::Executor;
+
+    const LOCALHOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+    struct SmolExecutor;
+
+    impl Executor for SmolExecutor {
+        fn new() -> Self {
+            Self
+        }
+
+        fn block_on<F: Future>(&mut self, future: F) -> F::Output {
+            smol::block_on(future)
+        }
+    }
+
+    #[test]
+    fn test_next_random_socket() {
+        next_random_socket_test::<Async<StdUdpSocket>, SmolExecutor>(SmolExecutor)
+    }
+
+    #[test]
+    fn test_udp_stream() {
+        smol::block_on(udp_stream_test::<Async<StdUdpSocket>>(LOCALHOST));
+    }
+
+    #[test]
+    fn test_udp_client_stream() {
+        udp_client_stream_test::<Async<StdUdpSocket>, SmolExecutor>(LOCALHOST, SmolExecutor)
+    }
+
+    #[test]
+    fn test_tcp_stream() {
+        tcp_stream_test::<Async<TcpStream>, SmolExecutor>(LOCALHOST, SmolExecutor)
+    }
+
+    #[test]
+    fn test_tcp_client_stream() {
+        tcp_client_stream_test::<Async<TcpStream>, SmolExecutor>(LOCALHOST, SmolExecutor)
+    }
+
+    #[test]
+    fn test_tcp_connect_with_bind() {
+        let listener = std::net::TcpListener::bind((LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let stream = smol::block_on(Async::<TcpStream>::connect_with_bind(
+            addr,
+            Some(SocketAddr::new(LOCALHOST, 0)),
+        ))
+        .unwrap();
+        let (_, remote) = listener.accept().unwrap();
+        assert_eq!(stream.get_ref().local_addr().unwrap(), remote);
+    }
+
+    #[test]
+    fn test_timeout() {
+        smol::block_on(async {
+            let timed_out =
+                SmolTime::timeout(Duration::from_millis(10), future::pending::<()>()).await;
+            assert_eq!(timed_out.unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+            let ready = SmolTime::timeout(Duration::from_secs(10), future::ready(1)).await;
+            assert_eq!(ready.unwrap(), 1);
+        });
+    }
+}
@@ -0,0 +1,159 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! UDP sockets of the [smoltcp](https://docs.rs/smoltcp) network stack as [`DnsUdpSocket`]
+//!
+//! The smoltcp socket stays in its [`SocketSet`], which is shared with the loop polling the
+//!  interface. Each receive and send locks the set, and registers the waker of the task with the
+//!  socket when it has to wait, the task is woken when the interface is polled.
+
+use std::io;
+use std::marker::PhantomData;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use async_trait::async_trait;
+use smoltcp::iface::{SocketHandle, SocketSet};
+use smoltcp::socket::udp::{RecvError, SendError, Socket};
+
+use crate::udp::DnsUdpSocket;
+use crate::Time;
+
+/// A UDP socket of a smoltcp [`SocketSet`], with the timers of `T`
+pub struct SmoltcpUdpSocket<T> {
+    sockets: Arc<Mutex<SocketSet<'static>>>,
+    handle: SocketHandle,
+    time: PhantomData<fn() -> T>,
+}
+
+impl<T> SmoltcpUdpSocket<T> {
+    /// Adapts the UDP socket `handle` of the socket set, the socket should be bound
+    pub fn new(sockets: Arc<Mutex<SocketSet<'static>>>, handle: SocketHandle) -> Self {
+        Self {
+            sockets,
+            handle,
+            time: PhantomData,
+        }
+    }
+
+    /// The handle of the socket in the socket set
+    pub fn handle(&self) -> SocketHandle {
+        self.handle
+    }
+
+    fn with_socket<R>(&self, f: impl FnOnce(&mut Socket<'static>) -> R) -> R {
+        let mut sockets = self.sockets.lock().expect("socket set poisoned");
+        f(sockets.get_mut::<Socket<'static>>(self.handle))
+    }
+}
+
+#[async_trait]
+impl<T: Time> DnsUdpSocket for SmoltcpUdpSocket<T> {
+    type Time = T;
+
+    fn poll_recv_from(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<(usize, SocketAddr)>> {
+        self.with_socket(|socket| match socket.recv_slice(buf) {
+            Ok((len, meta)) => Poll::Ready(Ok((
+                len,
+                SocketAddr::new(meta.endpoint.addr.into(), meta.endpoint.port),
+            ))),
+            // the set is locked, the interface can not receive before the waker is registered
+            Err(RecvError::Exhausted) => {
+                socket.register_recv_waker(cx.waker());
+                Poll::Pending
+            }
+            Err(RecvError::Truncated) => Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "datagram is larger than the buffer",
+            ))),
+        })
+    }
+
+    fn poll_send_to(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        target: SocketAddr,
+    ) -> Poll<io::Result<usize>> {
+        self.with_socket(|socket| match socket.send_slice(buf, target) {
+            Ok(()) => Poll::Ready(Ok(buf.len())),
+            Err(SendError::BufferFull) => {
+                socket.register_send_waker(cx.waker());
+                Poll::Pending
+            }
+            Err(SendError::Unaddressable) => Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::AddrNotAvailable,
+                format!("{target} is not addressable from the socket"),
+            ))),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use futures_executor::block_on;
+    use futures_util::task::noop_waker_ref;
+    use smoltcp::iface::{Config, Interface};
+    use smoltcp::phy::{Loopback, Medium};
+    use smoltcp::socket::udp::{PacketBuffer, PacketMetadata};
+    use smoltcp::time::Instant;
+    use smoltcp::wire::{HardwareAddress, IpAddress, IpCidr};
+
+    use super::*;
+    use crate::TokioTime;
+
+    fn udp_socket(port: u16) -> Socket<'static> {
+        let buffer = || PacketBuffer::new(vec![PacketMetadata::EMPTY; 4], vec![0; 1024]);
+        let mut socket = Socket::new(buffer(), buffer());
+        socket.bind(port).unwrap();
+        socket
+    }
+
+    #[test]
+    fn test_send_and_receive() {
+        let mut device = Loopback::new(Medium::Ip);
+        let mut iface =
+            Interface::new(Config::new(HardwareAddress::Ip), &mut device, Instant::ZERO);
+        iface.update_ip_addrs(|addrs| {
+            addrs
+                .push(IpCidr::new(IpAddress::v4(127, 0, 0, 1), 8))
+                .unwrap()
+        });
+
+        let mut sockets = SocketSet::new(vec![]);
+        let server = sockets.add(udp_socket(53));
+        let client = sockets.add(udp_socket(40000));
+        let sockets = Arc::new(Mutex::new(sockets));
+        let server = SmoltcpUdpSocket::<TokioTime>::new(Arc::clone(&sockets), server);
+        let client = SmoltcpUdpSocket::<TokioTime>::new(Arc::clone(&sockets), client);
+
+        let mut buf = [0; 512];
+        let mut cx = Context::from_waker(noop_waker_ref());
+        assert!(server.poll_recv_from(&mut cx, &mut buf).is_pending());
+
+        let server_addr = SocketAddr::from((Ipv4Addr::LOCALHOST, 53));
+        assert_eq!(block_on(client.send_to(b"query", server_addr)).unwrap(), 5);
+        // the datagram is sent to the loopback device, and received from it in the next poll
+        for _ in 0..2 {
+            iface.poll(Instant::ZERO, &mut device, &mut sockets.lock().unwrap());
+        }
+
+        let (len, src) = match server.poll_recv_from(&mut cx, &mut buf) {
+            Poll::Ready(result) => result.unwrap(),
+            Poll::Pending => panic!("the datagram was not received"),
+        };
+        assert_eq!(&buf[..len], b"query");
+        assert_eq!(src, SocketAddr::from((Ipv4Addr::LOCALHOST, 40000)));
+    }
+}
@@ -37,18 +37,44 @@ pub mod tokio {
         addr: &SocketAddr,
         bind_addr: &Option<SocketAddr>,
     ) -> Result<TokioTcpStream, io::Error> {
-        let stream = match bind_addr {
-            Some(bind_addr) => {
-                let socket = match bind_addr {
+        connect_with_bind_device(addr, bind_addr, None).await
+    }
+
+    pub async fn connect_with_bind_device(
+        addr: &SocketAddr,
+        bind_addr: &Option<SocketAddr>,
+        interface: Option<&str>,
+    ) -> Result<TokioTcpStream, io::Error> {
+        let stream = match (bind_addr, interface) {
+            (None, None) => TokioTcpStream::connect(addr).await?,
+            _ => {
+                let socket = match bind_addr.unwrap_or(*addr) {
                     SocketAddr::V4(_) => TokioTcpSocket::new_v4()?,
                     SocketAddr::V6(_) => TokioTcpSocket::new_v6()?,
                 };
-                socket.bind(*bind_addr)?;
+                if let Some(interface) = interface {
+                    bind_device(&socket, interface)?;
+                }
+                if let Some(bind_addr) = bind_addr {
+                    socket.bind(*bind_addr)?;
+                }
                 socket.connect(*addr).await?
             }
-            None => TokioTcpStream::connect(addr).await?,
         };
         stream.set_nodelay(true)?;
         Ok(stream)
     }
+
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    fn bind_device(socket: &TokioTcpSocket, interface: &str) -> Result<(), io::Error> {
+        socket.bind_device(Some(interface.as_bytes()))
+    }
+
+    #[cfg(not(any(target_os = "android", target_os = "fuchsia", target_os = "linux")))]
+    fn bind_device(_socket: &TokioTcpSocket, interface: &str) -> Result<(), io::Error> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("binding to the interface {interface} is not supported on this platform"),
+        ))
+    }
 }
@@ -12,10 +12,10 @@ use std::pin::Pin;
 use std::task::{Context, Poll};
 use std::time::Duration;
 
+use crate::logging::warn;
 #[cfg(feature = "tokio-runtime")]
 use async_trait::async_trait;
 use futures_util::{future::Future, stream::Stream, StreamExt, TryFutureExt};
-use tracing::warn;
 
 use crate::error::ProtoError;
 #[cfg(feature = "tokio-runtime")]
@@ -87,6 +87,38 @@ impl<S: Connect> TcpClientStream<S> {
 
         (TcpClientConnect(new_future), sender)
     }
+
+    /// Constructs a new TcpStream for a client to the specified SocketAddr, connecting through a
+    ///  network interface.
+    ///
+    /// # Arguments
+    ///
+    /// * `name_server` - the IP and Port of the DNS server to connect to
+    /// * `bind_addr` - the IP and port to connect from
+    /// * `interface` - the network interface, or on Linux the VRF, to connect through
+    /// * `timeout` - connection timeout
+    #[allow(clippy::new_ret_no_self)]
+    pub fn with_bind_device_and_timeout(
+        name_server: SocketAddr,
+        bind_addr: Option<SocketAddr>,
+        interface: String,
+        timeout: Duration,
+    ) -> (TcpClientConnect<S>, BufDnsStreamHandle) {
+        let (stream_future, sender) = TcpStream::<S>::with_bind_device_and_timeout(
+            name_server,
+            bind_addr,
+            interface,
+            timeout,
+        );
+
+        let new_future = Box::pin(
+            stream_future
+                .map_ok(move |tcp_stream| Self { tcp_stream })
+                .map_err(ProtoError::from),
+        );
+
+        (TcpClientConnect(new_future), sender)
+    }
 }
 
 impl<S: DnsTcpStream> TcpClientStream<S> {
@@ -187,6 +219,16 @@ impl Connect for AsyncIoTokioAsStd<TokioTcpStream> {
             .await
             .map(AsyncIoTokioAsStd)
     }
+
+    async fn connect_with_bind_device(
+        addr: SocketAddr,
+        bind_addr: Option<SocketAddr>,
+        interface: String,
+    ) -> io::Result<Self> {
+        super::tokio::connect_with_bind_device(&addr, &bind_addr, Some(&interface))
+            .await
+            .map(AsyncIoTokioAsStd)
+    }
 }
 
 #[cfg(test)]
@@ -215,4 +257,33 @@ mod tests {
             io_loop,
         )
     }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_tcp_stream_bind_device() {
+        use std::time::Duration;
+
+        use super::TcpClientStream;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+
+        let (connect, _sender) =
+            TcpClientStream::<AsyncIoTokioAsStd<TokioTcpStream>>::with_bind_device_and_timeout(
+                server_addr,
+                None,
+                "lo".to_string(),
+                Duration::from_secs(5),
+            );
+        assert!(connect.await.is_ok());
+
+        let (connect, _sender) =
+            TcpClientStream::<AsyncIoTokioAsStd<TokioTcpStream>>::with_bind_device_and_timeout(
+                server_addr,
+                None,
+                "nonexistent0".to_string(),
+                Duration::from_secs(5),
+            );
+        assert!(connect.await.is_err());
+    }
 }
@@ -14,11 +14,11 @@ use std::pin::Pin;
 use std::task::{Context, Poll};
 use std::time::Duration;
 
+use crate::logging::debug;
 use async_trait::async_trait;
 use futures_io::{AsyncRead, AsyncWrite};
 use futures_util::stream::Stream;
 use futures_util::{self, future::Future, ready, FutureExt};
-use tracing::debug;
 
 use crate::xfer::{SerialMessage, StreamReceiver};
 use crate::BufDnsStreamHandle;
@@ -41,6 +41,20 @@ pub trait Connect: DnsTcpStream {
     /// connect to tcp with address to connect from
     async fn connect_with_bind(addr: SocketAddr, bind_addr: Option<SocketAddr>)
         -> io::Result<Self>;
+
+    /// connect to tcp from a socket bound to a network interface, or on Linux a VRF, with
+    ///  `SO_BINDTODEVICE`. Unsupported by default.
+    async fn connect_with_bind_device(
+        addr: SocketAddr,
+        bind_addr: Option<SocketAddr>,
+        interface: String,
+    ) -> io::Result<Self> {
+        let _ = (addr, bind_addr);
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("binding to the interface {interface} is not supported by this stream"),
+        ))
+    }
 }
 
 /// Current state while writing to the remote of the TCP connection
@@ -129,7 +143,7 @@ impl<S: Connect> TcpStream<S> {
 
         // This set of futures collapses the next tcp socket into a stream which can be used for
         //  sending and receiving tcp packets.
-        let stream_fut = Self::connect(name_server, None, timeout, outbound_messages);
+        let stream_fut = Self::connect(name_server, None, None, timeout, outbound_messages);
 
         (stream_fut, message_sender)
     }
@@ -151,7 +165,38 @@ impl<S: Connect> TcpStream<S> {
         BufDnsStreamHandle,
     ) {
         let (message_sender, outbound_messages) = BufDnsStreamHandle::new(name_server);
-        let stream_fut = Self::connect(name_server, bind_addr, timeout, outbound_messages);
+        let stream_fut = Self::connect(name_server, bind_addr, None, timeout, outbound_messages);
+
+        (stream_fut, message_sender)
+    }
+
+    /// Creates a new future of the eventually establish a IO stream connection or fail trying,
+    ///  from a socket bound to a network interface
+    ///
+    /// # Arguments
+    ///
+    /// * `name_server` - the IP and Port of the DNS server to connect to
+    /// * `bind_addr` - the IP and port to connect from
+    /// * `interface` - the network interface, or on Linux the VRF, to connect through
+    /// * `timeout` - connection timeout
+    #[allow(clippy::type_complexity)]
+    pub fn with_bind_device_and_timeout(
+        name_server: SocketAddr,
+        bind_addr: Option<SocketAddr>,
+        interface: String,
+        timeout: Duration,
+    ) -> (
+        impl Future<Output = Result<Self, io::Error>> + Send,
+        BufDnsStreamHandle,
+    ) {
+        let (message_sender, outbound_messages) = BufDnsStreamHandle::new(name_server);
+        let stream_fut = Self::connect(
+            name_server,
+            bind_addr,
+            Some(interface),
+            timeout,
+            outbound_messages,
+        );
 
         (stream_fut, message_sender)
     }
@@ -159,11 +204,20 @@ impl<S: Connect> TcpStream<S> {
     async fn connect(
         name_server: SocketAddr,
         bind_addr: Option<SocketAddr>,
+        interface: Option<String>,
         timeout: Duration,
         outbound_messages: StreamReceiver,
     ) -> Result<Self, io::Error> {
-        let tcp = S::connect_with_bind(name_server, bind_addr);
-        Self::connect_with_future(tcp, name_server, timeout, outbound_messages).await
+        match interface {
+            Some(interface) => {
+                let tcp = S::connect_with_bind_device(name_server, bind_addr, interface);
+                Self::connect_with_future(tcp, name_server, timeout, outbound_messages).await
+            }
+            None => {
+                let tcp = S::connect_with_bind(name_server, bind_addr);
+                Self::connect_with_future(tcp, name_server, timeout, outbound_messages).await
+            }
+        }
     }
 }
 
@@ -189,100 +243,16 @@ impl<S: DnsTcpStream> TcpStream<S> {
         )
     }
 
-    /// Initializes a TcpStream.
-    ///
-    /// This is intended for use with a TcpListener and Incoming.
-    ///
-    /// # Arguments
-    ///
-    /// * `stream` - the established IO stream for communication
-    /// * `peer_addr` - sources address of the stream
-    pub fn from_stream(stream: S, peer_addr: SocketAddr) -> (Self, BufDnsStreamHandle) {
-        let (message_sender, outbound_messages) = BufDnsStreamHandle::new(peer_addr);
-        let stream = Self::from_stream_with_receiver(stream, peer_addr, outbound_messages);
-        (stream, message_sender)
-    }
-
-    /// Wraps a stream where a sender and receiver have already been established
-    pub fn from_stream_with_receiver(
-        socket: S,
-        peer_addr: SocketAddr,
-        outbound_messages: StreamReceiver,
-    ) -> Self {
-        Self {
-            socket,
-            outbound_messages,
-            send_state: None,
-            read_state: ReadTcpState::LenBytes {
-                pos: 0,
-                bytes: [0u8; 2],
-            },
-            peer_addr,
-        }
-    }
-
-    /// Creates a new future of the eventually establish a IO stream connection or fail trying
-    ///
-    /// # Arguments
+    /// Sends the messages which are queued for the peer, without receiving
     ///
-    /// * `future` - underlying stream future which this tcp stream relies on
-    /// * `name_server` - the IP and Port of the DNS server to connect to
-    /// * `timeout` - connection timeout
-    #[allow(clippy::type_complexity)]
-    pub fn with_future<F: Future<Output = Result<S, io::Error>> + Send + 'static>(
-        future: F,
-        name_server: SocketAddr,
-        timeout: Duration,
-    ) -> (
-        impl Future<Output = Result<Self, io::Error>> + Send,
-        BufDnsStreamHandle,
-    ) {
-        let (message_sender, outbound_messages) = BufDnsStreamHandle::new(name_server);
-        let stream_fut = Self::connect_with_future(future, name_server, timeout, outbound_messages);
-
-        (stream_fut, message_sender)
-    }
-
-    async fn connect_with_future<F: Future<Output = Result<S, io::Error>> + Send + 'static>(
-        future: F,
-        name_server: SocketAddr,
-        timeout: Duration,
-        outbound_messages: StreamReceiver,
-    ) -> Result<Self, io::Error> {
-        S::Time::timeout(timeout, future)
-            .map(move |tcp_stream: Result<Result<S, io::Error>, _>| {
-                tcp_stream
-                    .and_then(|tcp_stream| tcp_stream)
-                    .map(|tcp_stream| {
-                        debug!("TCP connection established to: {}", name_server);
-                        Self {
-                            socket: tcp_stream,
-                            outbound_messages,
-                            send_state: None,
-                            read_state: ReadTcpState::LenBytes {
-                                pos: 0,
-                                bytes: [0u8; 2],
-                            },
-                            peer_addr: name_server,
-                        }
-                    })
-            })
-            .await
-    }
-}
-
-impl<S: DnsTcpStream> Stream for TcpStream<S> {
-    type Item = io::Result<SerialMessage>;
-
-    #[allow(clippy::cognitive_complexity)]
-    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    /// This completes once all the messages sent to the stream handles so far are written, e.g.
+    ///  to send the responses to the requests in flight before closing the connection.
+    pub fn poll_send_queued(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
         let peer = self.peer_addr;
-        let (socket, outbound_messages, send_state, read_state) = self.pollable_split();
+        let (socket, outbound_messages, send_state, _) = self.pollable_split();
         let mut socket = Pin::new(socket);
         let mut outbound_messages = Pin::new(outbound_messages);
 
-        // this will not accept incoming data while there is data to send
-        //  makes this self throttling.
         // TODO: it might be interesting to try and split the sending and receiving futures.
         loop {
             // in the case we are sending, send it all?
@@ -350,10 +320,10 @@ impl<S: DnsTcpStream> Stream for TcpStream<S> {
                         // This is an error if the destination is not our peer (this is TCP after all)
                         //  This will kill the connection...
                         if peer != dst {
-                            return Poll::Ready(Some(Err(io::Error::new(
+                            return Poll::Ready(Err(io::Error::new(
                                 io::ErrorKind::InvalidData,
                                 format!("mismatched peer: {peer} and dst: {dst}"),
-                            ))));
+                            )));
                         }
 
                         // will return if the socket will block
@@ -367,8 +337,7 @@ impl<S: DnsTcpStream> Stream for TcpStream<S> {
                             bytes: buffer,
                         });
                     }
-                    // now we get to drop through to the receives...
-                    // TODO: should we also return None if there are no more messages to send?
+                    // nothing more is queued
                     Poll::Pending => break,
                     Poll::Ready(None) => {
                         debug!("no messages to send");
@@ -378,6 +347,105 @@ impl<S: DnsTcpStream> Stream for TcpStream<S> {
             }
         }
 
+        Poll::Ready(Ok(()))
+    }
+
+    /// Initializes a TcpStream.
+    ///
+    /// This is intended for use with a TcpListener and Incoming.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream` - the established IO stream for communication
+    /// * `peer_addr` - sources address of the stream
+    pub fn from_stream(stream: S, peer_addr: SocketAddr) -> (Self, BufDnsStreamHandle) {
+        let (message_sender, outbound_messages) = BufDnsStreamHandle::new(peer_addr);
+        let stream = Self::from_stream_with_receiver(stream, peer_addr, outbound_messages);
+        (stream, message_sender)
+    }
+
+    /// Wraps a stream where a sender and receiver have already been established
+    pub fn from_stream_with_receiver(
+        socket: S,
+        peer_addr: SocketAddr,
+        outbound_messages: StreamReceiver,
+    ) -> Self {
+        Self {
+            socket,
+            outbound_messages,
+            send_state: None,
+            read_state: ReadTcpState::LenBytes {
+                pos: 0,
+                bytes: [0u8; 2],
+            },
+            peer_addr,
+        }
+    }
+
+    /// Creates a new future of the eventually establish a IO stream connection or fail trying
+    ///
+    /// # Arguments
+    ///
+    /// * `future` - underlying stream future which this tcp stream relies on
+    /// * `name_server` - the IP and Port of the DNS server to connect to
+    /// * `timeout` - connection timeout
+    #[allow(clippy::type_complexity)]
+    pub fn with_future<F: Future<Output = Result<S, io::Error>> + Send + 'static>(
+        future: F,
+        name_server: SocketAddr,
+        timeout: Duration,
+    ) -> (
+        impl Future<Output = Result<Self, io::Error>> + Send,
+        BufDnsStreamHandle,
+    ) {
+        let (message_sender, outbound_messages) = BufDnsStreamHandle::new(name_server);
+        let stream_fut = Self::connect_with_future(future, name_server, timeout, outbound_messages);
+
+        (stream_fut, message_sender)
+    }
+
+    async fn connect_with_future<F: Future<Output = Result<S, io::Error>> + Send + 'static>(
+        future: F,
+        name_server: SocketAddr,
+        timeout: Duration,
+        outbound_messages: StreamReceiver,
+    ) -> Result<Self, io::Error> {
+        S::Time::timeout(timeout, future)
+            .map(move |tcp_stream: Result<Result<S, io::Error>, _>| {
+                tcp_stream
+                    .and_then(|tcp_stream| tcp_stream)
+                    .map(|tcp_stream| {
+                        debug!("TCP connection established to: {}", name_server);
+                        Self {
+                            socket: tcp_stream,
+                            outbound_messages,
+                            send_state: None,
+                            read_state: ReadTcpState::LenBytes {
+                                pos: 0,
+                                bytes: [0u8; 2],
+                            },
+                            peer_addr: name_server,
+                        }
+                    })
+            })
+            .await
+    }
+}
+
+impl<S: DnsTcpStream> Stream for TcpStream<S> {
+    type Item = io::Result<SerialMessage>;
+
+    #[allow(clippy::cognitive_complexity)]
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        // this will not accept incoming data while there is data to send
+        //  makes this self throttling.
+        if let Err(e) = ready!(self.poll_send_queued(cx)) {
+            return Poll::Ready(Some(Err(e)));
+        }
+
+        let (socket, _, _, read_state) = self.pollable_split();
+        let mut socket = Pin::new(socket);
+
         let mut ret_buf: Option<Vec<u8>> = None;
 
         // this will loop while there is data to read, or the data has been read, or an IO
@@ -0,0 +1,144 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Randomization of the case of query names, "0x20 encoding", against spoofed UDP responses
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use rand::Rng;
+
+use crate::error::ProtoResult;
+use crate::rr::Name;
+
+/// The number of consecutive responses with the question in a different case, after which the
+///  case of queries to a name server is no longer randomized
+const DEFAULT_MAX_MISMATCHES: u32 = 3;
+
+/// Randomizes the case of the letters of the query names of UDP queries, and only accepts
+///  responses which echo the question in exactly the same case
+///
+/// Name servers copy the question into the response as it was received, so each letter of the
+///  query name adds a bit which an off-path attacker has to guess along with the query id and the
+///  source port, see [draft-vixie-dnsext-dns0x20](https://datatracker.ietf.org/doc/html/draft-vixie-dnsext-dns0x20-00).
+///
+/// Some name servers normalize the case of the question. After a name server sent
+///  `max_mismatches` consecutive responses in a different case, without any in the exact case,
+///  the queries to it are sent unchanged. Clones share this state of the name servers, so a
+///  single instance can be attached to the connections to many name servers.
+#[derive(Clone, Debug)]
+pub struct CaseRandomization {
+    max_mismatches: u32,
+    mismatches: Arc<Mutex<HashMap<SocketAddr, u32>>>,
+}
+
+impl CaseRandomization {
+    /// Creates a new instance, which falls back after 3 mismatched responses of a name server
+    pub fn new() -> Self {
+        Self {
+            max_mismatches: DEFAULT_MAX_MISMATCHES,
+            mismatches: Arc::default(),
+        }
+    }
+
+    /// Sets the number of consecutive responses with the question in a different case, after
+    ///  which the case of the queries to a name server is no longer randomized
+    pub fn with_max_mismatches(mut self, max_mismatches: u32) -> Self {
+        self.max_mismatches = max_mismatches;
+        self
+    }
+
+    /// Returns false if the name server does not echo the case of the question, and the queries
+    ///  to it are sent unchanged
+    pub fn is_enabled_for(&self, name_server: SocketAddr) -> bool {
+        let mismatches = self
+            .mismatches
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        mismatches
+            .get(&name_server)
+            .map_or(true, |count| *count < self.max_mismatches)
+    }
+
+    /// Returns the name with the case of each ASCII letter chosen at random
+    pub(crate) fn randomize(&self, name: &Name) -> ProtoResult<Name> {
+        let mut rng = rand::thread_rng();
+        let labels = name.iter().map(|label| {
+            label
+                .iter()
+                .map(|byte| match rng.gen::<bool>() {
+                    true => byte.to_ascii_uppercase(),
+                    false => byte.to_ascii_lowercase(),
+                })
+                .collect::<Vec<u8>>()
+        });
+
+        let mut randomized = Name::from_labels(labels)?;
+        randomized.set_fqdn(name.is_fqdn());
+        Ok(randomized)
+    }
+
+    /// Records a response of the name server with the question in exactly the same case
+    pub(crate) fn record_match(&self, name_server: SocketAddr) {
+        self.mismatches
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&name_server);
+    }
+
+    /// Records a response of the name server with the question in a different case
+    pub(crate) fn record_mismatch(&self, name_server: SocketAddr) {
+        let mut mismatches = self
+            .mismatches
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let count = mismatches.entry(name_server).or_default();
+        *count = count.saturating_add(1);
+    }
+}
+
+impl Default for CaseRandomization {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_randomize() {
+        let randomization = CaseRandomization::new();
+        let name = Name::from_str("www.example.com.").unwrap();
+
+        let randomized = randomization.randomize(&name).unwrap();
+        assert_eq!(randomized, name);
+        assert!(randomized.is_fqdn());
+        assert_eq!(randomized.to_ascii().len(), name.to_ascii().len());
+    }
+
+    #[test]
+    fn test_fallback_per_server() {
+        let randomization = CaseRandomization::new().with_max_mismatches(2);
+        let normalizing = SocketAddr::from(([192, 0, 2, 1], 53));
+        let preserving = SocketAddr::from(([192, 0, 2, 2], 53));
+
+        randomization.record_mismatch(normalizing);
+        randomization.record_match(normalizing);
+        randomization.record_mismatch(normalizing);
+        assert!(randomization.is_enabled_for(normalizing));
+
+        // clones share the state
+        randomization.clone().record_mismatch(normalizing);
+        assert!(!randomization.is_enabled_for(normalizing));
+        assert!(randomization.is_enabled_for(preserving));
+    }
+}
@@ -0,0 +1,141 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Detection of spoofed UDP responses by watching for further answers to a query
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Spawns the task which keeps listening for responses after an answer was returned, see
+///  [`crate::udp::UdpClientConnect::with_duplicate_monitor`]
+pub type WatchSpawner = Arc<dyn Fn(Pin<Box<dyn Future<Output = ()> + Send>>) + Send + Sync>;
+
+/// Counters for the responses seen by a [`DuplicateResponseMonitor`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DuplicateResponseStats {
+    /// Queries for which the socket was watched after an answer was accepted
+    pub monitored: u64,
+    /// Further responses which were identical to the accepted answer
+    pub duplicates: u64,
+    /// Further responses which differed from the accepted answer, one of them may be spoofed
+    pub conflicts: u64,
+}
+
+#[derive(Debug, Default)]
+struct DuplicateResponseCounters {
+    monitored: AtomicU64,
+    duplicates: AtomicU64,
+    conflicts: AtomicU64,
+}
+
+/// Keeps listening on a UDP socket for a short time after an answer to a query was accepted
+///
+/// An off-path attacker racing the real name server can only win by having its forged response
+///  arrive first, the genuine answer will usually still arrive afterwards. A second response with
+///  the same id and question, but different content, is therefore a strong indicator of a
+///  spoofing attempt.
+///
+/// Clones share their counters, so a single monitor can be attached to many connections.
+#[derive(Clone, Debug)]
+pub struct DuplicateResponseMonitor {
+    window: Duration,
+    reject_conflicting: bool,
+    counters: Arc<DuplicateResponseCounters>,
+}
+
+impl DuplicateResponseMonitor {
+    /// Creates a new monitor which waits for `window` after each accepted answer
+    ///
+    /// Conflicting responses are only logged and counted, see
+    ///  [`Self::reject_conflicting`] to fail the query instead.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            reject_conflicting: false,
+            counters: Arc::default(),
+        }
+    }
+
+    /// Sets the time to keep listening after an answer was accepted, the counters stay shared
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// If true, a query which received conflicting responses fails with
+    ///  [`crate::error::ProtoErrorKind::ConflictingResponses`] so that it can be confirmed over
+    ///  another transport
+    ///
+    /// The answer of every query is then only returned after the window elapsed, otherwise it is
+    ///  returned immediately and the socket is watched in the background.
+    pub fn reject_conflicting(mut self, reject_conflicting: bool) -> Self {
+        self.reject_conflicting = reject_conflicting;
+        self
+    }
+
+    /// The time to keep listening after an answer was accepted
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Returns true if queries with conflicting responses are failed
+    pub fn is_rejecting_conflicting(&self) -> bool {
+        self.reject_conflicting
+    }
+
+    /// Returns the counters of all queries watched by this monitor and its clones
+    pub fn stats(&self) -> DuplicateResponseStats {
+        DuplicateResponseStats {
+            monitored: self.counters.monitored.load(Ordering::Relaxed),
+            duplicates: self.counters.duplicates.load(Ordering::Relaxed),
+            conflicts: self.counters.conflicts.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn record_monitored(&self) {
+        self.counters.monitored.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_duplicate(&self) {
+        self.counters.duplicates.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_conflict(&self) {
+        self.counters.conflicts.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clones_share_counters() {
+        let monitor = DuplicateResponseMonitor::new(Duration::from_millis(50));
+        let clone = monitor.clone().reject_conflicting(true);
+
+        monitor.record_monitored();
+        clone.record_monitored();
+        clone.record_duplicate();
+        monitor.record_conflict();
+
+        assert!(!monitor.is_rejecting_conflicting());
+        assert!(clone.is_rejecting_conflicting());
+        assert_eq!(
+            clone.stats(),
+            DuplicateResponseStats {
+                monitored: 2,
+                duplicates: 1,
+                conflicts: 1,
+            }
+        );
+    }
+}
@@ -16,9 +16,22 @@
 
 //! UDP protocol related components for DNS
 
+mod case_randomization;
+mod duplicate_response;
+mod socket_pool;
+#[cfg(feature = "udp-batch")]
+mod udp_batch;
 mod udp_client_stream;
 mod udp_stream;
 
+pub use self::case_randomization::CaseRandomization;
+pub use self::duplicate_response::{
+    DuplicateResponseMonitor, DuplicateResponseStats, WatchSpawner,
+};
+pub use self::socket_pool::{UdpSocketPool, UdpSocketPoolConfig};
+#[cfg(feature = "udp-batch")]
+#[cfg_attr(docsrs, doc(cfg(feature = "udp-batch")))]
+pub use self::udp_batch::{send_batch, RecvBatch, MAX_BATCH_SIZE};
 pub use self::udp_client_stream::{UdpClientConnect, UdpClientStream};
 pub use self::udp_stream::{DnsUdpSocket, QuicLocalAddr, UdpSocket, UdpStream};
 
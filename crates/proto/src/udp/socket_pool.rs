@@ -0,0 +1,459 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! A pool of bound UDP sockets, which are reused for queries instead of binding one per query
+
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use rand::Rng;
+#[cfg(feature = "serde-config")]
+use serde::{Deserialize, Serialize};
+
+use crate::clock::{Clock, SystemClock};
+use crate::logging::debug;
+use crate::udp::udp_stream::UdpCreator;
+use crate::udp::DnsUdpSocket;
+
+/// Configuration of a [`UdpSocketPool`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde-config",
+    derive(Serialize, Deserialize),
+    serde(default)
+)]
+#[non_exhaustive]
+pub struct UdpSocketPoolConfig {
+    /// The number of sockets kept bound for each address family, defaults to 16
+    ///
+    /// Queries are sent from a socket of the pool which is not in use by another query. When all
+    ///  of them are in use, a socket is bound for the query only, as without a pool.
+    pub size: usize,
+    /// The local ports the sockets are bound to, each socket is bound to a random port of these
+    ///  ranges. Defaults to 1024-65535, as recommended by
+    ///  [RFC 6056](https://datatracker.ietf.org/doc/html/rfc6056#section-3.2)
+    pub port_ranges: Vec<RangeInclusive<u16>>,
+    /// A socket is closed after it was used for this many queries, and replaced by a socket on a
+    ///  new port. Defaults to 64
+    pub max_uses: u32,
+    /// A socket is closed once it was bound for this long, and replaced by a socket on a new
+    ///  port. Defaults to 60 seconds
+    pub max_age: Duration,
+}
+
+impl Default for UdpSocketPoolConfig {
+    fn default() -> Self {
+        Self {
+            size: 16,
+            port_ranges: vec![1024..=u16::MAX],
+            max_uses: 64,
+            max_age: Duration::from_secs(60),
+        }
+    }
+}
+
+/// A pool of bound UDP sockets, which are used in turn for queries
+///
+/// Binding a new socket on a random port for every query makes it hardest to spoof responses,
+///  but it costs two system calls per query and every query in flight holds a file descriptor. The
+///  pool binds `size` sockets on random ports, sends each query from a random idle one, and
+///  refreshes the sockets after `max_uses` queries or `max_age`, which bounds how long an attacker
+///  has to learn a port.
+///
+/// Clones share the sockets, a single pool can be attached to the connections to many name
+///  servers. The sockets are not connected to a name server, so the socket creator must not
+///  connect them either.
+pub struct UdpSocketPool<S> {
+    config: Arc<UdpSocketPoolConfig>,
+    clock: Arc<dyn Clock>,
+    state: Arc<Mutex<PoolState<S>>>,
+}
+
+impl<S> UdpSocketPool<S> {
+    /// Creates a new pool, the sockets are bound when the first query is sent
+    pub fn new(config: UdpSocketPoolConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            clock: Arc::new(SystemClock),
+            state: Arc::new(Mutex::new(PoolState {
+                idle: Vec::new(),
+                bound: [0; 2],
+            })),
+        }
+    }
+
+    /// Sets the clock for the age of the sockets, the system clock by default
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The configuration of this pool
+    pub fn config(&self) -> &UdpSocketPoolConfig {
+        &self.config
+    }
+
+    /// The number of sockets which are bound and not in use by a query
+    pub fn idle(&self) -> usize {
+        self.lock().idle.len()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, PoolState<S>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns true if the socket should be closed instead of being used again
+    fn is_expired(&self, socket: &PooledSocket<S>) -> bool {
+        socket.uses >= self.config.max_uses
+            || self
+                .clock
+                .instant()
+                .saturating_duration_since(socket.bound_at)
+                >= self.config.max_age
+    }
+
+    /// Chooses a random port of the configured ranges, or 0 if there are none
+    fn random_port(&self) -> u16 {
+        let total = self
+            .config
+            .port_ranges
+            .iter()
+            .map(|range| range.clone().count())
+            .sum::<usize>();
+        if total == 0 {
+            return 0;
+        }
+
+        let mut index = rand::thread_rng().gen_range(0..total);
+        for range in self.config.port_ranges.iter() {
+            let len = range.clone().count();
+            if index < len {
+                return range.start() + index as u16;
+            }
+            index -= len;
+        }
+
+        0
+    }
+}
+
+impl<S: DnsUdpSocket + Send> UdpSocketPool<S> {
+    /// Takes an idle socket for a query to the name server, binding the sockets of the pool first
+    pub(crate) async fn acquire(
+        &self,
+        name_server: SocketAddr,
+        creator: &UdpCreator<S>,
+    ) -> io::Result<PooledUdpSocket<S>> {
+        let family = Family::of(name_server);
+
+        // reserve the missing sockets, so that concurrent queries do not bind them as well
+        let missing = {
+            let mut state = self.lock();
+            let PoolState { idle, bound } = &mut *state;
+            idle.retain(|socket| {
+                let expired = self.is_expired(socket);
+                if expired {
+                    bound[socket.family as usize] -= 1;
+                }
+                !expired
+            });
+
+            let missing = self
+                .config
+                .size
+                .saturating_sub(state.bound[family as usize]);
+            state.bound[family as usize] += missing;
+            missing
+        };
+
+        for bound in 0..missing {
+            match self.bind(name_server, creator).await {
+                Ok(socket) => self.lock().idle.push(PooledSocket {
+                    socket,
+                    family,
+                    uses: 0,
+                    bound_at: self.clock.instant(),
+                }),
+                Err(err) => {
+                    self.lock().bound[family as usize] -= missing - bound;
+                    return Err(err);
+                }
+            }
+        }
+
+        let pooled = {
+            let mut state = self.lock();
+            let candidates = state
+                .idle
+                .iter()
+                .enumerate()
+                .filter(|(_, socket)| socket.family == family)
+                .map(|(index, _)| index)
+                .collect::<Vec<_>>();
+
+            match candidates.len() {
+                0 => None,
+                len => {
+                    let index = candidates[rand::thread_rng().gen_range(0..len)];
+                    Some(state.idle.swap_remove(index))
+                }
+            }
+        };
+
+        match pooled {
+            Some(socket) => Ok(PooledUdpSocket {
+                socket: Some(socket),
+                pool: Some(self.clone()),
+            }),
+            None => {
+                debug!("all sockets of the pool are in use, binding one for this query");
+                let socket = self.bind(name_server, creator).await?;
+                Ok(PooledUdpSocket::unpooled(socket))
+            }
+        }
+    }
+
+    /// Binds a new socket on a random port, retrying ports which are in use
+    async fn bind(&self, name_server: SocketAddr, creator: &UdpCreator<S>) -> io::Result<S> {
+        let ip = match name_server {
+            SocketAddr::V4(..) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            SocketAddr::V6(..) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        };
+
+        let mut attempt = 0;
+        loop {
+            let bind_addr = SocketAddr::new(ip, self.random_port());
+            match (*creator)(bind_addr, name_server).await {
+                Err(err) if err.kind() == io::ErrorKind::AddrInUse && attempt < 10 => {
+                    debug!("unable to bind port, attempt: {}: {}", attempt, err);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Returns the socket to the pool after a query, or closes it if it expired
+    fn release(&self, mut socket: PooledSocket<S>) {
+        socket.uses += 1;
+
+        let mut state = self.lock();
+        if self.is_expired(&socket) {
+            debug!("closing pooled socket after {} queries", socket.uses);
+            state.bound[socket.family as usize] -= 1;
+        } else {
+            state.idle.push(socket);
+        }
+    }
+}
+
+impl<S> Clone for UdpSocketPool<S> {
+    fn clone(&self) -> Self {
+        Self {
+            config: Arc::clone(&self.config),
+            clock: Arc::clone(&self.clock),
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+impl<S> fmt::Debug for UdpSocketPool<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UdpSocketPool")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+struct PoolState<S> {
+    idle: Vec<PooledSocket<S>>,
+    /// The number of sockets of the pool for each address family, idle or in use
+    bound: [usize; 2],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Family {
+    Ipv4 = 0,
+    Ipv6 = 1,
+}
+
+impl Family {
+    fn of(addr: SocketAddr) -> Self {
+        match addr {
+            SocketAddr::V4(..) => Self::Ipv4,
+            SocketAddr::V6(..) => Self::Ipv6,
+        }
+    }
+}
+
+struct PooledSocket<S> {
+    socket: S,
+    family: Family,
+    uses: u32,
+    bound_at: Instant,
+}
+
+/// A socket used by a single query, which is returned to its pool when dropped
+pub(crate) struct PooledUdpSocket<S: DnsUdpSocket + Send> {
+    /// Only `None` while being dropped
+    socket: Option<PooledSocket<S>>,
+    pool: Option<UdpSocketPool<S>>,
+}
+
+impl<S: DnsUdpSocket + Send> PooledUdpSocket<S> {
+    /// A socket which is closed after the query
+    pub(crate) fn unpooled(socket: S) -> Self {
+        Self {
+            socket: Some(PooledSocket {
+                socket,
+                // neither is used without a pool
+                family: Family::Ipv4,
+                uses: 0,
+                bound_at: Instant::now(),
+            }),
+            pool: None,
+        }
+    }
+
+    fn socket(&self) -> &S {
+        &self
+            .socket
+            .as_ref()
+            .expect("socket is only taken on drop")
+            .socket
+    }
+}
+
+impl<S: DnsUdpSocket + Send> Drop for PooledUdpSocket<S> {
+    fn drop(&mut self) {
+        if let (Some(pool), Some(socket)) = (self.pool.take(), self.socket.take()) {
+            pool.release(socket);
+        }
+    }
+}
+
+#[async_trait]
+impl<S: DnsUdpSocket + Send> DnsUdpSocket for PooledUdpSocket<S> {
+    type Time = S::Time;
+
+    fn poll_recv_from(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<(usize, SocketAddr)>> {
+        self.socket().poll_recv_from(cx, buf)
+    }
+
+    fn poll_send_to(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        target: SocketAddr,
+    ) -> Poll<io::Result<usize>> {
+        self.socket().poll_send_to(cx, buf, target)
+    }
+
+    fn bind_device(&self, interface: &str) -> io::Result<()> {
+        self.socket().bind_device(interface)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "tokio-runtime")]
+mod tests {
+    use std::time::SystemTime;
+
+    use tokio::net::UdpSocket as TokioUdpSocket;
+
+    use super::*;
+    use crate::clock::ManualClock;
+
+    fn creator() -> UdpCreator<TokioUdpSocket> {
+        Arc::new(|local_addr: _, _server_addr: _| Box::pin(TokioUdpSocket::bind(local_addr)))
+    }
+
+    fn name_server() -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], 53))
+    }
+
+    #[tokio::test]
+    async fn test_sockets_are_reused() {
+        let config = UdpSocketPoolConfig {
+            size: 2,
+            port_ranges: vec![20000..=20999, 40000..=40999],
+            ..UdpSocketPoolConfig::default()
+        };
+        let pool = UdpSocketPool::new(config);
+        let creator = creator();
+
+        let first = pool.acquire(name_server(), &creator).await.unwrap();
+        let second = pool.acquire(name_server(), &creator).await.unwrap();
+        let pooled_ports =
+            [&first, &second].map(|socket| socket.socket().local_addr().unwrap().port());
+        for port in pooled_ports {
+            assert!((20000..=20999).contains(&port) || (40000..=40999).contains(&port));
+        }
+
+        // both sockets are in use, the third is closed after the query
+        let third = pool.acquire(name_server(), &creator).await.unwrap();
+        assert!(third.pool.is_none());
+        drop(third);
+        assert_eq!(pool.idle(), 0);
+
+        drop(first);
+        drop(second);
+        assert_eq!(pool.idle(), 2);
+
+        for _ in 0..4 {
+            let socket = pool.acquire(name_server(), &creator).await.unwrap();
+            let port = socket.socket().local_addr().unwrap().port();
+            assert!(pooled_ports.contains(&port));
+        }
+        assert_eq!(pool.idle(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_sockets_are_refreshed() {
+        let clock = Arc::new(ManualClock::new(SystemTime::now()));
+        let config = UdpSocketPoolConfig {
+            size: 1,
+            max_uses: 2,
+            max_age: Duration::from_secs(10),
+            ..UdpSocketPoolConfig::default()
+        };
+        let pool = UdpSocketPool::new(config).with_clock(clock.clone());
+        let creator = creator();
+
+        let socket = pool.acquire(name_server(), &creator).await.unwrap();
+        let port = socket.socket().local_addr().unwrap().port();
+        drop(socket);
+        let socket = pool.acquire(name_server(), &creator).await.unwrap();
+        assert_eq!(socket.socket().local_addr().unwrap().port(), port);
+
+        // closed after the second use
+        drop(socket);
+        assert_eq!(pool.idle(), 0);
+
+        let socket = pool.acquire(name_server(), &creator).await.unwrap();
+        drop(socket);
+        assert_eq!(pool.idle(), 1);
+
+        // expired while idle, replaced by a new socket
+        clock.advance(Duration::from_secs(10));
+        let socket = pool.acquire(name_server(), &creator).await.unwrap();
+        assert!(socket.pool.is_some());
+        assert_eq!(pool.lock().bound, [1, 0]);
+        drop(socket);
+        assert_eq!(pool.idle(), 1);
+    }
+}
@@ -0,0 +1,317 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Receiving and sending batches of datagrams, with one system call per batch where supported
+//!
+//! On Linux `recvmmsg` and `sendmmsg` are used, on all other platforms the datagrams are received
+//!  and sent one at a time, but still without waiting between the datagrams of a batch.
+//!
+//! GRO and GSO are not used, the kernel only coalesces datagrams of the same flow, while the
+//!  datagrams of a DNS server are usually one request and response per client.
+
+use std::io;
+use std::net::SocketAddr;
+
+use crate::logging::warn;
+use tokio::net::UdpSocket as TokioUdpSocket;
+
+use crate::udp::MAX_RECEIVE_BUFFER_SIZE;
+use crate::xfer::SerialMessage;
+
+/// The maximum number of datagrams received or sent with one system call
+pub const MAX_BATCH_SIZE: usize = 64;
+
+/// Buffers for receiving a batch of datagrams
+pub struct RecvBatch {
+    buffer: Vec<u8>,
+    received: Vec<(usize, SocketAddr)>,
+}
+
+impl RecvBatch {
+    /// Creates buffers for receiving up to `capacity` datagrams at once
+    ///
+    /// The capacity is limited to [`MAX_BATCH_SIZE`].
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.clamp(1, MAX_BATCH_SIZE);
+        Self {
+            buffer: vec![0; capacity * MAX_RECEIVE_BUFFER_SIZE],
+            received: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Returns the maximum number of datagrams received at once
+    pub fn capacity(&self) -> usize {
+        self.buffer.len() / MAX_RECEIVE_BUFFER_SIZE
+    }
+
+    /// Waits for datagrams on the socket, and receives as many as are available up to the capacity
+    ///
+    /// This is cancel safe, the datagrams of the previous call are discarded.
+    ///
+    /// # Return
+    ///
+    /// The number of datagrams received, at least one
+    pub async fn recv(&mut self, socket: &TokioUdpSocket) -> io::Result<usize> {
+        self.received.clear();
+
+        loop {
+            socket.readable().await?;
+
+            match socket.try_io(tokio::io::Interest::READABLE, || {
+                sys::recv(socket, &mut self.buffer, &mut self.received)
+            }) {
+                Ok(()) if self.received.is_empty() => continue,
+                Ok(()) => return Ok(self.received.len()),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Returns the datagrams of the last call to `recv`, with the address they were received from
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8], SocketAddr)> + '_ {
+        self.buffer
+            .chunks_exact(MAX_RECEIVE_BUFFER_SIZE)
+            .zip(&self.received)
+            .map(|(buffer, (len, addr))| (&buffer[..*len], *addr))
+    }
+
+    /// Returns the datagrams of the last call to `recv` as messages
+    pub fn messages(&self) -> impl Iterator<Item = SerialMessage> + '_ {
+        self.iter()
+            .map(|(bytes, addr)| SerialMessage::new(bytes.to_vec(), addr))
+    }
+}
+
+/// Sends all messages to their addresses
+///
+/// Messages which could not be sent are logged and dropped, as with single datagrams.
+pub async fn send_batch(socket: &TokioUdpSocket, messages: &[SerialMessage]) -> io::Result<()> {
+    let mut remaining = messages;
+    while !remaining.is_empty() {
+        socket.writable().await?;
+
+        match socket.try_io(tokio::io::Interest::WRITABLE, || {
+            sys::send(socket, remaining)
+        }) {
+            Ok(sent) => remaining = &remaining[sent..],
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+            Err(e) => {
+                // the first datagram failed, the others may still be sent
+                warn!(
+                    "error sending message to {} on udp_socket, dropping response: {}",
+                    remaining[0].addr(),
+                    e
+                );
+                remaining = &remaining[1..];
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use std::io;
+    use std::mem;
+    use std::net::SocketAddr;
+    use std::os::unix::io::AsRawFd;
+    use std::ptr;
+
+    use socket2::SockAddr;
+    use tokio::net::UdpSocket as TokioUdpSocket;
+
+    use super::MAX_BATCH_SIZE;
+    use crate::udp::MAX_RECEIVE_BUFFER_SIZE;
+    use crate::xfer::SerialMessage;
+
+    /// Receives up to one datagram per `MAX_RECEIVE_BUFFER_SIZE` of the buffer with `recvmmsg`
+    pub(super) fn recv(
+        socket: &TokioUdpSocket,
+        buffer: &mut [u8],
+        received: &mut Vec<(usize, SocketAddr)>,
+    ) -> io::Result<()> {
+        // Safety: all of these are plain C structs, for which all zeroes is a valid value
+        let mut addrs: [libc::sockaddr_storage; MAX_BATCH_SIZE] = unsafe { mem::zeroed() };
+        let mut iovecs: [libc::iovec; MAX_BATCH_SIZE] = unsafe { mem::zeroed() };
+        let mut headers: [libc::mmsghdr; MAX_BATCH_SIZE] = unsafe { mem::zeroed() };
+
+        let mut count = 0;
+        for (((chunk, addr), iovec), header) in buffer
+            .chunks_exact_mut(MAX_RECEIVE_BUFFER_SIZE)
+            .zip(addrs.iter_mut())
+            .zip(iovecs.iter_mut())
+            .zip(headers.iter_mut())
+        {
+            iovec.iov_base = chunk.as_mut_ptr().cast();
+            iovec.iov_len = chunk.len();
+            header.msg_hdr.msg_name = (addr as *mut libc::sockaddr_storage).cast();
+            header.msg_hdr.msg_namelen = mem::size_of::<libc::sockaddr_storage>() as _;
+            header.msg_hdr.msg_iov = iovec;
+            header.msg_hdr.msg_iovlen = 1;
+            count += 1;
+        }
+
+        // Safety: the headers point to the buffers and addresses above, which outlive the call
+        let received_count = unsafe {
+            libc::recvmmsg(
+                socket.as_raw_fd(),
+                headers.as_mut_ptr(),
+                count as _,
+                0,
+                ptr::null_mut(),
+            )
+        };
+        if received_count < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        for (header, addr) in headers.iter().zip(addrs).take(received_count as usize) {
+            // Safety: the address was written by recvmmsg, with the length in the header
+            let addr = unsafe { SockAddr::new(addr, header.msg_hdr.msg_namelen) };
+            let addr = addr
+                .as_socket()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not an IP address"))?;
+            received.push((header.msg_len as usize, addr));
+        }
+
+        Ok(())
+    }
+
+    /// Sends up to `MAX_BATCH_SIZE` of the messages with `sendmmsg`, returning the number sent
+    pub(super) fn send(socket: &TokioUdpSocket, messages: &[SerialMessage]) -> io::Result<usize> {
+        // Safety: all of these are plain C structs, for which all zeroes is a valid value
+        let mut addrs: [libc::sockaddr_storage; MAX_BATCH_SIZE] = unsafe { mem::zeroed() };
+        let mut iovecs: [libc::iovec; MAX_BATCH_SIZE] = unsafe { mem::zeroed() };
+        let mut headers: [libc::mmsghdr; MAX_BATCH_SIZE] = unsafe { mem::zeroed() };
+
+        let mut count = 0;
+        for (((message, addr), iovec), header) in messages
+            .iter()
+            .zip(addrs.iter_mut())
+            .zip(iovecs.iter_mut())
+            .zip(headers.iter_mut())
+        {
+            let sock_addr = SockAddr::from(message.addr());
+            // Safety: the storage is large enough for any address
+            unsafe {
+                ptr::copy_nonoverlapping(
+                    sock_addr.as_ptr().cast::<u8>(),
+                    (addr as *mut libc::sockaddr_storage).cast::<u8>(),
+                    sock_addr.len() as usize,
+                );
+            }
+
+            // sendmmsg does not write to the buffers
+            iovec.iov_base = message.bytes().as_ptr() as *mut _;
+            iovec.iov_len = message.bytes().len();
+            header.msg_hdr.msg_name = (addr as *mut libc::sockaddr_storage).cast();
+            header.msg_hdr.msg_namelen = sock_addr.len();
+            header.msg_hdr.msg_iov = iovec;
+            header.msg_hdr.msg_iovlen = 1;
+            count += 1;
+        }
+
+        // Safety: the headers point to the messages and addresses above, which outlive the call
+        let sent =
+            unsafe { libc::sendmmsg(socket.as_raw_fd(), headers.as_mut_ptr(), count as _, 0) };
+        if sent < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(sent as usize)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod sys {
+    use std::io;
+    use std::net::SocketAddr;
+
+    use tokio::net::UdpSocket as TokioUdpSocket;
+
+    use crate::udp::MAX_RECEIVE_BUFFER_SIZE;
+    use crate::xfer::SerialMessage;
+
+    /// Receives up to one datagram per `MAX_RECEIVE_BUFFER_SIZE` of the buffer, one at a time
+    pub(super) fn recv(
+        socket: &TokioUdpSocket,
+        buffer: &mut [u8],
+        received: &mut Vec<(usize, SocketAddr)>,
+    ) -> io::Result<()> {
+        for chunk in buffer.chunks_exact_mut(MAX_RECEIVE_BUFFER_SIZE) {
+            match socket.try_recv_from(chunk) {
+                Ok(datagram) => received.push(datagram),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock && !received.is_empty() => break,
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sends the first message, returning the number sent
+    pub(super) fn send(socket: &TokioUdpSocket, messages: &[SerialMessage]) -> io::Result<usize> {
+        socket.try_send_to(messages[0].bytes(), messages[0].addr())?;
+        Ok(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_batch() {
+        let server = TokioUdpSocket::bind((Ipv4Addr::LOCALHOST, 0))
+            .await
+            .unwrap();
+        let client = TokioUdpSocket::bind((Ipv4Addr::LOCALHOST, 0))
+            .await
+            .unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let client_addr = client.local_addr().unwrap();
+
+        // more datagrams than fit in one batch
+        let requests = (0..5_u8)
+            .map(|i| SerialMessage::new(vec![i; i as usize + 1], server_addr))
+            .collect::<Vec<_>>();
+        send_batch(&client, &requests).await.unwrap();
+
+        let mut batch = RecvBatch::new(3);
+        assert_eq!(batch.capacity(), 3);
+
+        let mut received = Vec::new();
+        while received.len() < requests.len() {
+            let count = batch.recv(&server).await.unwrap();
+            assert!(count <= 3);
+            received.extend(batch.messages());
+        }
+
+        for (request, received) in requests.iter().zip(&received) {
+            assert_eq!(received.bytes(), request.bytes());
+            assert_eq!(received.addr(), client_addr);
+        }
+
+        // and back, to the sender of each datagram
+        let responses = received
+            .iter()
+            .map(|message| SerialMessage::new(message.bytes().to_vec(), message.addr()))
+            .collect::<Vec<_>>();
+        send_batch(&server, &responses).await.unwrap();
+
+        let mut buf = [0; 16];
+        for response in &responses {
+            let (len, addr) = client.recv_from(&mut buf).await.unwrap();
+            assert_eq!(&buf[..len], response.bytes());
+            assert_eq!(addr, server_addr);
+        }
+    }
+}
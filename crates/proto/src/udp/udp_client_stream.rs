@@ -7,19 +7,24 @@
 
 use std::borrow::Borrow;
 use std::fmt::{self, Display};
+use std::io;
 use std::marker::PhantomData;
 use std::net::SocketAddr;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::Duration;
 
+use crate::logging::{debug, trace, warn};
 use futures_util::{future::Future, stream::Stream};
-use tracing::{debug, trace, warn};
 
-use crate::error::ProtoError;
+use crate::clock::{Clock, SystemClock};
+use crate::error::{ProtoError, ProtoErrorKind};
 use crate::op::message::NoopMessageFinalizer;
-use crate::op::{Message, MessageFinalizer, MessageVerifier};
+use crate::op::{Message, MessageFinalizer, MessageVerifier, Query};
+use crate::udp::case_randomization::CaseRandomization;
+use crate::udp::duplicate_response::{DuplicateResponseMonitor, WatchSpawner};
+use crate::udp::socket_pool::{PooledUdpSocket, UdpSocketPool};
 use crate::udp::udp_stream::{NextRandomUdpSocket, UdpCreator, UdpSocket};
 use crate::udp::{DnsUdpSocket, MAX_RECEIVE_BUFFER_SIZE};
 use crate::xfer::{DnsRequest, DnsRequestSender, DnsResponse, DnsResponseStream, SerialMessage};
@@ -40,6 +45,10 @@ where
     is_shutdown: bool,
     signer: Option<Arc<MF>>,
     creator: UdpCreator<S>,
+    clock: Arc<dyn Clock>,
+    duplicate_monitor: Option<(DuplicateResponseMonitor, WatchSpawner)>,
+    case_randomization: Option<CaseRandomization>,
+    socket_pool: Option<UdpSocketPool<S>>,
     marker: PhantomData<S>,
 }
 
@@ -108,6 +117,10 @@ impl<S: UdpSocket + Send + 'static, MF: MessageFinalizer> UdpClientStream<S, MF>
                     &Some(local_addr),
                 ))
             }),
+            clock: Arc::new(SystemClock),
+            duplicate_monitor: None,
+            case_randomization: None,
+            socket_pool: None,
             marker: PhantomData::<S>,
         }
     }
@@ -135,6 +148,10 @@ impl<S: UdpSocket + Send + 'static, MF: MessageFinalizer> UdpClientStream<S, MF>
                     &Some(bind_addr.unwrap_or(local_addr)),
                 ))
             }),
+            clock: Arc::new(SystemClock),
+            duplicate_monitor: None,
+            case_randomization: None,
+            socket_pool: None,
             marker: PhantomData::<S>,
         }
     }
@@ -160,6 +177,10 @@ impl<S: DnsUdpSocket + Send, MF: MessageFinalizer> UdpClientStream<S, MF> {
             timeout,
             signer,
             creator,
+            clock: Arc::new(SystemClock),
+            duplicate_monitor: None,
+            case_randomization: None,
+            socket_pool: None,
             marker: PhantomData::<S>,
         }
     }
@@ -191,13 +212,24 @@ impl<S: DnsUdpSocket + Send + 'static, MF: MessageFinalizer> DnsRequestSender
         //   does not need to be globally unique
         message.set_id(random_query_id());
 
-        let now = match SystemTime::now().duration_since(UNIX_EPOCH) {
-            Ok(now) => now.as_secs(),
-            Err(_) => return ProtoError::from("Current time is before the Unix epoch.").into(),
+        let now = match self.clock.unix_time() {
+            Ok(now) => now,
+            Err(err) => return err.into(),
         };
 
-        // TODO: truncates u64 to u32, error on overflow?
-        let now = now as u32;
+        // the case is randomized before the message is signed, the signature covers the query names
+        let case_randomization = match self.case_randomization {
+            Some(ref randomization) if randomization.is_enabled_for(self.name_server) => {
+                for query in message.queries_mut() {
+                    match randomization.randomize(query.name()) {
+                        Ok(name) => query.set_name(name),
+                        Err(err) => return err.into(),
+                    };
+                }
+                Some(randomization.clone())
+            }
+            _ => None,
+        };
 
         let mut verifier = None;
         if let Some(ref signer) = self.signer {
@@ -232,17 +264,72 @@ impl<S: DnsUdpSocket + Send + 'static, MF: MessageFinalizer> DnsRequestSender
                 .expect("bizarre we just made this message")
         );
         let creator = self.creator.clone();
+        let socket_pool = self.socket_pool.clone();
         let addr = message.addr();
 
-        S::Time::timeout::<Pin<Box<dyn Future<Output = Result<DnsResponse, ProtoError>> + Send>>>(
-            self.timeout,
-            Box::pin(async move {
-                let socket: S = NextRandomUdpSocket::new_with_closure(&addr, creator).await?;
-                send_serial_message_inner(message, message_id, verifier, socket, recv_buf_size)
+        let Some((monitor, spawn)) = self.duplicate_monitor.clone() else {
+            return S::Time::timeout::<
+                Pin<Box<dyn Future<Output = Result<DnsResponse, ProtoError>> + Send>>,
+            >(
+                self.timeout,
+                Box::pin(async move {
+                    let socket = bind_socket(socket_pool, addr, creator).await?;
+                    send_serial_message_inner(
+                        message,
+                        message_id,
+                        verifier,
+                        case_randomization,
+                        socket,
+                        recv_buf_size,
+                    )
                     .await
-            }),
-        )
-        .into()
+                    .map(|(response, _)| response)
+                }),
+            )
+            .into();
+        };
+
+        // only the first answer is bound by the timeout, the monitor has its own window
+        let timeout = self.timeout;
+        let response = async move {
+            let (response, pending) = S::Time::timeout(
+                timeout,
+                Box::pin(async move {
+                    let socket = bind_socket(socket_pool, addr, creator).await?;
+                    send_serial_message_inner(
+                        message,
+                        message_id,
+                        verifier,
+                        case_randomization,
+                        socket,
+                        recv_buf_size,
+                    )
+                    .await
+                }),
+            )
+            .await??;
+
+            monitor.record_monitored();
+            let rejecting = monitor.is_rejecting_conflicting();
+            let watch = watch_for_duplicates(
+                monitor,
+                pending,
+                response.as_buffer().to_vec(),
+                recv_buf_size,
+            );
+
+            if !rejecting {
+                spawn(Box::pin(async move {
+                    watch.await;
+                }));
+            } else if watch.await {
+                return Err(ProtoErrorKind::ConflictingResponses(addr).into());
+            }
+
+            Ok(response)
+        };
+
+        DnsResponseStream::from(Box::pin(response))
     }
 
     fn shutdown(&mut self) {
@@ -278,9 +365,72 @@ where
     timeout: Duration,
     signer: Option<Arc<MF>>,
     creator: UdpCreator<S>,
+    clock: Arc<dyn Clock>,
+    duplicate_monitor: Option<(DuplicateResponseMonitor, WatchSpawner)>,
+    case_randomization: Option<CaseRandomization>,
+    socket_pool: Option<UdpSocketPool<S>>,
     marker: PhantomData<S>,
 }
 
+impl<S: Send, MF: MessageFinalizer> UdpClientConnect<S, MF> {
+    /// Sets the clock for the time of signed messages, the system clock by default
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Keeps listening after an answer was accepted, to detect conflicting (possibly spoofed)
+    ///  responses to the same query, see [`DuplicateResponseMonitor`]
+    ///
+    /// The answer is returned immediately, and the socket is watched in a task started with
+    ///  `spawn`. Only if the monitor rejects conflicting responses, the answer is held back until
+    ///  the window elapsed.
+    pub fn with_duplicate_monitor(
+        mut self,
+        monitor: DuplicateResponseMonitor,
+        spawn: WatchSpawner,
+    ) -> Self {
+        self.duplicate_monitor = Some((monitor, spawn));
+        self
+    }
+
+    /// Randomizes the case of the query names, and ignores responses which do not echo it
+    ///  exactly, see [`CaseRandomization`]
+    pub fn with_case_randomization(mut self, case_randomization: CaseRandomization) -> Self {
+        self.case_randomization = Some(case_randomization);
+        self
+    }
+
+    /// Sends the queries from the sockets of the pool, instead of binding a socket for each
+    ///  query, see [`UdpSocketPool`]
+    ///
+    /// The sockets are bound by the creator of the stream which first needed them, a pool must
+    ///  not be shared by streams bound to different interfaces.
+    pub fn with_socket_pool(mut self, socket_pool: UdpSocketPool<S>) -> Self {
+        self.socket_pool = Some(socket_pool);
+        self
+    }
+}
+
+impl<S: DnsUdpSocket + Send + 'static, MF: MessageFinalizer> UdpClientConnect<S, MF> {
+    /// Binds the sockets of the queries to a network interface, or on Linux a VRF, so that the
+    ///  queries are sent through it, see [`DnsUdpSocket::bind_device`]
+    pub fn with_bind_device(mut self, interface: impl Into<String>) -> Self {
+        let interface: Arc<str> = Arc::from(interface.into());
+        let creator = Arc::clone(&self.creator);
+        self.creator = Arc::new(move |local_addr: _, server_addr: _| {
+            let socket = creator(local_addr, server_addr);
+            let interface = Arc::clone(&interface);
+            Box::pin(async move {
+                let socket = socket.await?;
+                socket.bind_device(&interface)?;
+                Ok(socket)
+            })
+        });
+        self
+    }
+}
+
 impl<S: Send + Unpin, MF: MessageFinalizer> Future for UdpClientConnect<S, MF> {
     type Output = Result<UdpClientStream<S, MF>, ProtoError>;
 
@@ -292,18 +442,37 @@ impl<S: Send + Unpin, MF: MessageFinalizer> Future for UdpClientConnect<S, MF> {
             timeout: self.timeout,
             signer: self.signer.take(),
             creator: self.creator.clone(),
+            clock: Arc::clone(&self.clock),
+            duplicate_monitor: self.duplicate_monitor.take(),
+            case_randomization: self.case_randomization.take(),
+            socket_pool: self.socket_pool.take(),
             marker: PhantomData,
         }))
     }
 }
 
+/// Takes a socket from the pool, or binds a socket for this query only
+async fn bind_socket<S: DnsUdpSocket + Send>(
+    socket_pool: Option<UdpSocketPool<S>>,
+    name_server: SocketAddr,
+    creator: UdpCreator<S>,
+) -> io::Result<PooledUdpSocket<S>> {
+    match socket_pool {
+        Some(socket_pool) => socket_pool.acquire(name_server, &creator).await,
+        None => NextRandomUdpSocket::new_with_closure(&name_server, creator)
+            .await
+            .map(PooledUdpSocket::unpooled),
+    }
+}
+
 async fn send_serial_message_inner<S: DnsUdpSocket + Send>(
     msg: SerialMessage,
     msg_id: u16,
     verifier: Option<MessageVerifier>,
+    case_randomization: Option<CaseRandomization>,
     socket: S,
     recv_buf_size: usize,
-) -> Result<DnsResponse, ProtoError> {
+) -> Result<(DnsResponse, PendingQuery<S>), ProtoError> {
     let bytes = msg.bytes();
     let addr = msg.addr();
     let len_sent: usize = socket.send_to(bytes, addr).await?;
@@ -316,19 +485,60 @@ async fn send_serial_message_inner<S: DnsUdpSocket + Send>(
         )));
     }
 
+    let mut request_message = Message::from_vec(msg.bytes())?;
+    let pending = PendingQuery {
+        socket,
+        name_server: addr,
+        msg_id,
+        queries: request_message.take_queries(),
+        case_randomization,
+    };
+
     // Create the receive buffer.
     trace!("creating UDP receive buffer with size {recv_buf_size}");
     let mut recv_buf = vec![0; recv_buf_size];
 
     // TODO: limit the max number of attempted messages? this relies on a timeout to die...
     loop {
-        let (len, src) = socket.recv_from(&mut recv_buf).await?;
+        let (len, src) = pending.socket.recv_from(&mut recv_buf).await?;
 
         // Copy the slice of read bytes.
         let buffer: Vec<_> = Vec::from(&recv_buf[0..len]);
 
+        let Some(message) = pending.validate_response(src, &buffer) else {
+            continue;
+        };
+
+        if !pending.echoes_case(&message) {
+            continue;
+        }
+
+        debug!("received message id: {}", message.id());
+        let response = if let Some(mut verifier) = verifier {
+            verifier(&buffer)?
+        } else {
+            DnsResponse::new(message, buffer)
+        };
+
+        return Ok((response, pending));
+    }
+}
+
+/// The socket of a query which was sent, and what is needed to match responses to it
+struct PendingQuery<S> {
+    socket: S,
+    name_server: SocketAddr,
+    msg_id: u16,
+    queries: Vec<Query>,
+    /// Set if the case of the query names was randomized
+    case_randomization: Option<CaseRandomization>,
+}
+
+impl<S> PendingQuery<S> {
+    /// Returns the message if the received packet is a response to this query
+    fn validate_response(&self, src: SocketAddr, buffer: &[u8]) -> Option<Message> {
         // compare expected src to received packet
-        let request_target = msg.addr();
+        let request_target = self.name_server;
 
         // Comparing the IP and Port directly as internal information about the link is stored with the IpAddr, see https://github.com/hickory-dns/hickory-dns/issues/2081
         if src.ip() != request_target.ip() || src.port() != request_target.port() {
@@ -338,21 +548,21 @@ async fn send_serial_message_inner<S: DnsUdpSocket + Send>(
             );
 
             // await an answer from the correct NameServer
-            continue;
+            return None;
         }
 
-        match Message::from_vec(&buffer) {
+        match Message::from_vec(buffer) {
             Ok(message) => {
                 // Validate the message id in the response matches the value chosen for the query.
-                if msg_id != message.id() {
+                if self.msg_id != message.id() {
                     // on wrong id, attempted poison?
                     warn!(
                         "expected message id: {} got: {}, dropped",
-                        msg_id,
+                        self.msg_id,
                         message.id()
                     );
 
-                    continue;
+                    return None;
                 }
 
                 // Validate the returned query name.
@@ -380,8 +590,7 @@ async fn send_serial_message_inner<S: DnsUdpSocket + Send>(
                 //  - RR data in responses of dubious reliability.  When a resolver
                 // receives unsolicited responses or RR data other than that
                 // requested, it should discard it without caching it.
-                let request_message = Message::from_vec(msg.bytes())?;
-                let request_queries = request_message.queries();
+                let request_queries = &self.queries;
                 let response_queries = message.queries();
 
                 if !response_queries
@@ -389,34 +598,115 @@ async fn send_serial_message_inner<S: DnsUdpSocket + Send>(
                     .all(|elem| request_queries.contains(elem))
                 {
                     warn!("detected forged question section: we expected '{request_queries:?}', but received '{response_queries:?}' from server {src}");
-                    continue;
+                    return None;
                 }
 
-                debug!("received message id: {}", message.id());
-                if let Some(mut verifier) = verifier {
-                    return verifier(&buffer);
-                } else {
-                    return Ok(DnsResponse::new(message, buffer));
-                }
+                Some(message)
             }
             Err(e) => {
                 // on errors deserializing, continue
                 warn!(
                     "dropped malformed message waiting for id: {} err: {}",
-                    msg_id, e
+                    self.msg_id, e
                 );
 
-                continue;
+                None
             }
         }
     }
+
+    /// Returns false if the case of the query names was randomized, and the response did not
+    ///  echo it exactly
+    fn echoes_case(&self, message: &Message) -> bool {
+        let Some(ref randomization) = self.case_randomization else {
+            return true;
+        };
+
+        let echoed = message.queries().iter().all(|response| {
+            self.queries
+                .iter()
+                .any(|request| request.name().eq_case(response.name()))
+        });
+
+        if echoed {
+            randomization.record_match(self.name_server);
+        } else {
+            warn!(
+                "ignoring response from {} with the case of the question changed: expected '{:?}', received '{:?}'",
+                self.name_server,
+                self.queries,
+                message.queries()
+            );
+            randomization.record_mismatch(self.name_server);
+        }
+
+        echoed
+    }
+}
+
+/// Listens for further responses to an answered query until the window of the monitor elapses
+///
+/// Responses identical to the accepted answer are retransmissions or duplicated packets, a
+///  different response to the same query means that one of them was likely forged.
+///
+/// # Return
+///
+/// True if a conflicting response was received
+async fn watch_for_duplicates<S: DnsUdpSocket + Send + 'static>(
+    monitor: DuplicateResponseMonitor,
+    pending: PendingQuery<S>,
+    accepted: Vec<u8>,
+    recv_buf_size: usize,
+) -> bool {
+    let watch_monitor = monitor.clone();
+    let watch = async move {
+        let mut recv_buf = vec![0; recv_buf_size];
+        loop {
+            let (len, src) = match pending.socket.recv_from(&mut recv_buf).await {
+                Ok(received) => received,
+                // the answer was already accepted, there is nothing more to learn from this socket
+                Err(_) => return false,
+            };
+
+            let buffer = &recv_buf[0..len];
+            if pending.validate_response(src, buffer).is_none() {
+                continue;
+            }
+
+            if buffer == accepted.as_slice() {
+                trace!("received duplicate response for id: {}", pending.msg_id);
+                watch_monitor.record_duplicate();
+                continue;
+            }
+
+            warn!(
+                "received conflicting responses for id: {} from {}, one of them may be spoofed",
+                pending.msg_id, src
+            );
+            watch_monitor.record_conflict();
+            return true;
+        }
+    };
+
+    // the window elapsing without a conflict is the expected outcome
+    S::Time::timeout(monitor.window(), watch)
+        .await
+        .unwrap_or(false)
 }
 
 #[cfg(test)]
 #[cfg(feature = "tokio-runtime")]
 mod tests {
     #![allow(clippy::dbg_macro, clippy::print_stdout)]
+    use super::*;
+    use crate::op::{Message, Query};
+    use crate::rr::{Name, RecordType};
     use crate::tests::udp_client_stream_test;
+    use crate::udp::{
+        CaseRandomization, DuplicateResponseStats, UdpSocketPool, UdpSocketPoolConfig,
+    };
+    use crate::xfer::{DnsRequestOptions, FirstAnswer};
+    use futures_util::StreamExt;
     use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
     use tokio::{net::UdpSocket as TokioUdpSocket, runtime::Runtime};
 
@@ -437,4 +727,256 @@ mod tests {
             io_loop,
         )
     }
+
+    /// Answers a single query twice, the second answer is changed if `conflicting` is set
+    async fn answer_twice(
+        conflicting: bool,
+        monitor: DuplicateResponseMonitor,
+    ) -> Result<DnsResponse, ProtoError> {
+        let server = TokioUdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = server.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let mut buffer = [0_u8; 512];
+            let (len, addr) = server.recv_from(&mut buffer).await.unwrap();
+
+            let mut response = Message::from_vec(&buffer[..len]).unwrap();
+            response.set_message_type(crate::op::MessageType::Response);
+            let first = response.to_vec().unwrap();
+            server.send_to(&first, addr).await.unwrap();
+
+            if conflicting {
+                response.set_authoritative(true);
+            }
+            let second = response.to_vec().unwrap();
+            server.send_to(&second, addr).await.unwrap();
+        });
+
+        let mut stream = UdpClientStream::<TokioUdpSocket>::new(server_addr)
+            .with_duplicate_monitor(
+                monitor,
+                Arc::new(|watch| {
+                    tokio::spawn(watch);
+                }),
+            )
+            .await
+            .unwrap();
+
+        let mut query = Message::new();
+        query.add_query(Query::query(
+            Name::from_ascii("dead.beef.").unwrap(),
+            RecordType::A,
+        ));
+
+        stream
+            .send_message(DnsRequest::new(query, DnsRequestOptions::default()))
+            .first_answer()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_case_randomization_fallback() {
+        let server = TokioUdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = server.local_addr().unwrap();
+
+        // answers with the question normalized to lower case
+        tokio::spawn(async move {
+            let mut buffer = [0_u8; 512];
+            loop {
+                let (len, addr) = server.recv_from(&mut buffer).await.unwrap();
+
+                let mut response = Message::from_vec(&buffer[..len]).unwrap();
+                response.set_message_type(crate::op::MessageType::Response);
+                for query in response.queries_mut() {
+                    let name = query.name().to_lowercase();
+                    query.set_name(name);
+                }
+                server
+                    .send_to(&response.to_vec().unwrap(), addr)
+                    .await
+                    .unwrap();
+            }
+        });
+
+        let randomization = CaseRandomization::new().with_max_mismatches(1);
+        let mut stream = UdpClientStream::<TokioUdpSocket>::with_timeout(
+            server_addr,
+            Duration::from_millis(500),
+        )
+        .with_case_randomization(randomization.clone())
+        .await
+        .unwrap();
+
+        let mut query = Message::new();
+        query.add_query(Query::query(
+            Name::from_ascii("www.example.com.").unwrap(),
+            RecordType::A,
+        ));
+        let request = DnsRequest::new(query, DnsRequestOptions::default());
+
+        // the response does not echo the case, it might be spoofed
+        assert!(stream
+            .send_message(request.clone())
+            .first_answer()
+            .await
+            .is_err());
+        assert!(!randomization.is_enabled_for(server_addr));
+
+        // the next query is sent unchanged
+        let response = stream.send_message(request).first_answer().await.unwrap();
+        assert!(response.queries()[0]
+            .name()
+            .eq_case(&Name::from_ascii("www.example.com.").unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_socket_pool() {
+        let server = TokioUdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = server.local_addr().unwrap();
+
+        // answers each query, and reports the port it came from
+        let (sender, mut receiver) = futures_channel::mpsc::unbounded();
+        tokio::spawn(async move {
+            let mut buffer = [0_u8; 512];
+            loop {
+                let (len, addr) = server.recv_from(&mut buffer).await.unwrap();
+
+                let mut response = Message::from_vec(&buffer[..len]).unwrap();
+                response.set_message_type(crate::op::MessageType::Response);
+                server
+                    .send_to(&response.to_vec().unwrap(), addr)
+                    .await
+                    .unwrap();
+                sender.unbounded_send(addr.port()).unwrap();
+            }
+        });
+
+        let pool = UdpSocketPool::new(UdpSocketPoolConfig {
+            size: 1,
+            ..UdpSocketPoolConfig::default()
+        });
+        let mut stream = UdpClientStream::<TokioUdpSocket>::new(server_addr)
+            .with_socket_pool(pool.clone())
+            .await
+            .unwrap();
+
+        let mut query = Message::new();
+        query.add_query(Query::query(
+            Name::from_ascii("www.example.com.").unwrap(),
+            RecordType::A,
+        ));
+        let request = DnsRequest::new(query, DnsRequestOptions::default());
+
+        for _ in 0..2 {
+            stream
+                .send_message(request.clone())
+                .first_answer()
+                .await
+                .unwrap();
+        }
+
+        // both queries were sent from the socket of the pool
+        assert_eq!(receiver.next().await, receiver.next().await);
+        assert_eq!(pool.idle(), 1);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_bind_device() {
+        let server = TokioUdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = server.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let mut buffer = [0_u8; 512];
+            let (len, addr) = server.recv_from(&mut buffer).await.unwrap();
+
+            let mut response = Message::from_vec(&buffer[..len]).unwrap();
+            response.set_message_type(crate::op::MessageType::Response);
+            server
+                .send_to(&response.to_vec().unwrap(), addr)
+                .await
+                .unwrap();
+        });
+
+        let mut query = Message::new();
+        query.add_query(Query::query(
+            Name::from_ascii("www.example.com.").unwrap(),
+            RecordType::A,
+        ));
+        let request = DnsRequest::new(query, DnsRequestOptions::default());
+
+        let mut stream = UdpClientStream::<TokioUdpSocket>::new(server_addr)
+            .with_bind_device("nonexistent0")
+            .await
+            .unwrap();
+        assert!(stream
+            .send_message(request.clone())
+            .first_answer()
+            .await
+            .is_err());
+
+        let mut stream = UdpClientStream::<TokioUdpSocket>::new(server_addr)
+            .with_bind_device("lo")
+            .await
+            .unwrap();
+        assert!(stream.send_message(request).first_answer().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_response() {
+        let monitor =
+            DuplicateResponseMonitor::new(Duration::from_millis(500)).reject_conflicting(true);
+        let result = answer_twice(false, monitor.clone()).await;
+
+        assert!(result.is_ok());
+        assert_eq!(
+            monitor.stats(),
+            DuplicateResponseStats {
+                monitored: 1,
+                duplicates: 1,
+                conflicts: 0,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_conflicting_response() {
+        let monitor =
+            DuplicateResponseMonitor::new(Duration::from_millis(500)).reject_conflicting(true);
+        let result = answer_twice(true, monitor.clone()).await;
+
+        assert!(result.unwrap_err().is_conflicting_responses());
+        assert_eq!(
+            monitor.stats(),
+            DuplicateResponseStats {
+                monitored: 1,
+                duplicates: 0,
+                conflicts: 1,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_conflicting_response_watched_in_background() {
+        let monitor = DuplicateResponseMonitor::new(Duration::from_secs(30));
+
+        // the answer is returned long before the window elapsed
+        let result =
+            tokio::time::timeout(Duration::from_secs(5), answer_twice(true, monitor.clone()))
+                .await
+                .unwrap();
+        assert!(result.is_ok());
+
+        while monitor.stats().conflicts == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(
+            monitor.stats(),
+            DuplicateResponseStats {
+                monitored: 1,
+                duplicates: 0,
+                conflicts: 1,
+            }
+        );
+    }
 }
@@ -12,12 +12,12 @@ use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 
+use crate::logging::{debug, warn};
 use async_trait::async_trait;
 use futures_util::stream::Stream;
 use futures_util::{future::Future, ready, TryFutureExt};
 use rand;
 use rand::distributions::{uniform::Uniform, Distribution};
-use tracing::{debug, warn};
 
 use crate::udp::MAX_RECEIVE_BUFFER_SIZE;
 use crate::xfer::{BufDnsStreamHandle, SerialMessage, StreamReceiver};
@@ -67,6 +67,16 @@ where
     async fn send_to(&self, buf: &[u8], target: SocketAddr) -> io::Result<usize> {
         futures_util::future::poll_fn(|cx| self.poll_send_to(cx, buf, target)).await
     }
+
+    /// Binds the socket to a network interface, or on Linux a VRF, with `SO_BINDTODEVICE`
+    ///
+    /// Packets are then only sent and received through this interface. Unsupported by default.
+    fn bind_device(&self, interface: &str) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("binding to the interface {interface} is not supported by this socket"),
+        ))
+    }
 }
 
 /// Trait for UdpSocket
@@ -395,6 +405,11 @@ impl DnsUdpSocket for tokio::net::UdpSocket {
     ) -> Poll<io::Result<usize>> {
         Self::poll_send_to(self, cx, buf, target)
     }
+
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    fn bind_device(&self, interface: &str) -> io::Result<()> {
+        Self::bind_device(self, Some(interface.as_bytes()))
+    }
 }
 
 #[cfg(test)]
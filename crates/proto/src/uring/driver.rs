@@ -0,0 +1,271 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! The ring shared by all sockets, and the operations submitted to it
+//!
+//! The ring is created on first use, along with a thread which waits for the completions and wakes
+//!  the tasks polling the operations. Submissions are made from the polling tasks directly, so
+//!  that a request and its response each take at most one system call.
+
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+use crate::logging::{debug, error};
+use io_uring::{opcode, squeue, IoUring};
+use once_cell::sync::OnceCell;
+
+/// Number of entries of the submission queue, the completion queue is twice as large
+const RING_ENTRIES: u32 = 1024;
+
+/// The `user_data` of entries whose completion is not of interest, e.g. cancellations
+const IGNORED: u64 = u64::MAX;
+
+static DRIVER: OnceCell<Driver> = OnceCell::new();
+
+/// The shared ring
+struct Driver {
+    ring: Arc<IoUring>,
+    /// Held while pushing to the submission queue, which only supports one producer
+    submission: Mutex<()>,
+}
+
+impl Driver {
+    /// Returns the shared ring, creating it and starting the completion thread on first use
+    fn get() -> io::Result<&'static Self> {
+        DRIVER.get_or_try_init(|| {
+            let ring = Arc::new(IoUring::new(RING_ENTRIES)?);
+
+            let completions = ring.clone();
+            thread::Builder::new()
+                .name("hickory-io-uring".to_string())
+                .spawn(move || complete(&completions))?;
+
+            debug!("started io_uring with {} entries", RING_ENTRIES);
+            Ok(Self {
+                ring,
+                submission: Mutex::new(()),
+            })
+        })
+    }
+
+    /// Pushes the entry to the submission queue and submits it
+    ///
+    /// On error the entry was not pushed, and its `user_data` is still owned by the caller.
+    fn submit(&self, entry: &squeue::Entry) -> io::Result<()> {
+        let _submission = self.submission.lock().expect("submission lock poisoned");
+
+        loop {
+            // SAFETY: the lock makes this the only producer of the submission queue, and the
+            //  memory referenced by the entry is valid until its completion, see `Op::submit`
+            if unsafe { self.ring.submission_shared().push(entry) }.is_ok() {
+                break;
+            }
+
+            // the queue is full, the kernel must consume it first
+            match self.ring.submit() {
+                Ok(_) => continue,
+                Err(e) if is_busy(&e) => thread::yield_now(),
+                Err(e) => return Err(e),
+            }
+        }
+
+        match self.ring.submit() {
+            Ok(_) => Ok(()),
+            // the entry is submitted by the completion thread instead
+            Err(e) if is_busy(&e) => Ok(()),
+            Err(e) => {
+                // the entry stays in the queue, and is submitted with the next one
+                error!("failed to submit to io_uring: {}", e);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Waits for completions on the ring, and completes the operations, until the ring fails
+fn complete(ring: &IoUring) {
+    loop {
+        if let Err(e) = ring.submit_and_wait(1) {
+            if e.kind() == io::ErrorKind::Interrupted || is_busy(&e) {
+                continue;
+            }
+
+            error!("waiting for io_uring completions failed: {}", e);
+            return;
+        }
+
+        // SAFETY: this thread is the only consumer of the completion queue
+        for entry in unsafe { ring.completion_shared() } {
+            if entry.user_data() == IGNORED {
+                continue;
+            }
+
+            // SAFETY: the user data of all other entries is a pointer from `Op::submit`, and the
+            //  kernel posts exactly one completion per entry
+            let operation = unsafe { Box::from_raw(entry.user_data() as *mut Arc<dyn Complete>) };
+            operation.complete(entry.result());
+        }
+    }
+}
+
+/// The queues are full, and completions must be reaped before more entries can be submitted
+fn is_busy(error: &io::Error) -> bool {
+    matches!(error.raw_os_error(), Some(libc::EBUSY) | Some(libc::EAGAIN))
+}
+
+/// An operation which can be completed from the completion thread
+trait Complete: Send + Sync {
+    /// Stores the result of the operation, and wakes the task waiting for it
+    fn complete(&self, result: i32);
+}
+
+#[derive(Default)]
+struct Completion {
+    result: Option<i32>,
+    waker: Option<Waker>,
+}
+
+struct State<T> {
+    /// Only referenced by the kernel until the completion, and by the `Op` afterwards
+    data: UnsafeCell<T>,
+    completion: Mutex<Completion>,
+}
+
+// SAFETY: the data is never referenced from two threads at once, see `data`
+unsafe impl<T: Send> Sync for State<T> {}
+
+impl<T: Send> Complete for State<T> {
+    fn complete(&self, result: i32) {
+        let waker = {
+            let mut completion = self.completion.lock().expect("completion lock poisoned");
+            completion.result = Some(result);
+            completion.waker.take()
+        };
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// An operation submitted to the ring, which owns the memory referenced by the operation
+///
+/// Dropping an operation before it completed cancels it, the memory is only freed once the kernel
+///  posted the completion.
+pub(super) struct Op<T: Send + 'static> {
+    state: Arc<State<T>>,
+    user_data: u64,
+}
+
+impl<T: Send + 'static> Op<T> {
+    /// Submits the entry built from the data
+    ///
+    /// # Safety
+    ///
+    /// The entry must only reference memory owned by the data, or memory which otherwise outlives
+    ///  the operation, e.g. the socket kept alive by the data.
+    pub(super) unsafe fn submit(
+        data: T,
+        entry: impl FnOnce(&mut T) -> squeue::Entry,
+    ) -> io::Result<Self> {
+        let driver = Driver::get()?;
+        let state = Arc::new(State {
+            data: UnsafeCell::new(data),
+            completion: Mutex::new(Completion::default()),
+        });
+
+        // nothing else references the data before the submission
+        let entry = entry(&mut *state.data.get());
+
+        // one reference is owned by the kernel, and released with the completion
+        let operation: Arc<dyn Complete> = state.clone();
+        let user_data = Box::into_raw(Box::new(operation)) as u64;
+
+        if let Err(e) = driver.submit(&entry.user_data(user_data)) {
+            drop(Box::from_raw(user_data as *mut Arc<dyn Complete>));
+            return Err(e);
+        }
+
+        Ok(Self { state, user_data })
+    }
+
+    /// Polls for the result of the operation, as returned by the equivalent system call
+    pub(super) fn poll(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<u32>> {
+        let mut completion = self
+            .state
+            .completion
+            .lock()
+            .expect("completion lock poisoned");
+        match completion.result {
+            Some(result) if result < 0 => Poll::Ready(Err(io::Error::from_raw_os_error(-result))),
+            Some(result) => Poll::Ready(Ok(result as u32)),
+            None => {
+                match completion.waker {
+                    Some(ref waker) if waker.will_wake(cx.waker()) => (),
+                    _ => completion.waker = Some(cx.waker().clone()),
+                }
+                Poll::Pending
+            }
+        }
+    }
+
+    /// Returns the data of the operation
+    ///
+    /// # Panics
+    ///
+    /// If the operation did not complete yet, i.e. `poll` did not return `Ready`
+    pub(super) fn data(&mut self) -> &mut T {
+        let completion = self
+            .state
+            .completion
+            .lock()
+            .expect("completion lock poisoned");
+        assert!(
+            completion.result.is_some(),
+            "data of a pending io_uring operation"
+        );
+
+        // SAFETY: the kernel does not reference the data after the completion, and the completion
+        //  thread never references the data
+        unsafe { &mut *self.state.data.get() }
+    }
+}
+
+impl<T: Send + 'static> fmt::Debug for Op<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Op")
+            .field("user_data", &self.user_data)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Send + 'static> Drop for Op<T> {
+    fn drop(&mut self) {
+        // the lock is held while cancelling, so the `user_data` can not be reused before
+        let completion = self
+            .state
+            .completion
+            .lock()
+            .expect("completion lock poisoned");
+        if completion.result.is_some() {
+            return;
+        }
+
+        let cancel = opcode::AsyncCancel::new(self.user_data)
+            .build()
+            .user_data(IGNORED);
+        match Driver::get().and_then(|driver| driver.submit(&cancel)) {
+            Ok(()) => (),
+            // the operation still completes eventually, and releases its data then
+            Err(e) => debug!("failed to cancel io_uring operation: {}", e),
+        }
+    }
+}
@@ -0,0 +1,100 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! UDP and TCP sockets which receive and send with io_uring, on Linux 5.6 or newer
+//!
+//! All sockets share one ring, the completions are reaped by a dedicated thread which wakes the
+//!  tasks of the sockets. The sockets are otherwise used from a Tokio runtime, for the timers.
+//!
+//! Buffers are owned by the operations in flight, the bytes are copied between them and the
+//!  buffers of the callers, as the poll based traits can not lend buffers to the kernel.
+
+mod driver;
+mod tcp;
+mod udp;
+
+pub use self::tcp::{IoUringTcpListener, IoUringTcpStream};
+pub use self::udp::IoUringUdpSocket;
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::runtime::Runtime;
+
+    use super::*;
+    use crate::iocompat::AsyncIoStdAsTokio;
+    use crate::tcp::Connect;
+    use crate::tests::{
+        next_random_socket_test, tcp_client_stream_test, tcp_stream_test, udp_client_stream_test,
+        udp_stream_test,
+    };
+
+    const LOCALHOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+    #[test]
+    fn test_next_random_socket() {
+        let io_loop = Runtime::new().expect("failed to create tokio runtime");
+        next_random_socket_test::<IoUringUdpSocket, Runtime>(io_loop)
+    }
+
+    #[test]
+    fn test_udp_stream() {
+        let io_loop = Runtime::new().expect("failed to create tokio runtime");
+        io_loop.block_on(udp_stream_test::<IoUringUdpSocket>(LOCALHOST));
+    }
+
+    #[test]
+    fn test_udp_client_stream() {
+        let io_loop = Runtime::new().expect("failed to create tokio runtime");
+        udp_client_stream_test::<IoUringUdpSocket, Runtime>(LOCALHOST, io_loop)
+    }
+
+    #[test]
+    fn test_tcp_stream() {
+        let io_loop = Runtime::new().expect("failed to create tokio runtime");
+        tcp_stream_test::<IoUringTcpStream, Runtime>(LOCALHOST, io_loop)
+    }
+
+    #[test]
+    fn test_tcp_client_stream() {
+        let io_loop = Runtime::new().expect("failed to create tokio runtime");
+        tcp_client_stream_test::<IoUringTcpStream, Runtime>(LOCALHOST, io_loop)
+    }
+
+    #[tokio::test]
+    async fn test_tcp_listener() {
+        let listener = IoUringTcpListener::bind(SocketAddr::new(LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let client = tokio::spawn(async move {
+            let stream = IoUringTcpStream::connect(addr).await.unwrap();
+            let mut stream = AsyncIoStdAsTokio(stream);
+            stream.write_all(b"ping").await.unwrap();
+            stream.shutdown().await.unwrap();
+
+            let mut response = [0; 4];
+            stream.read_exact(&mut response).await.unwrap();
+            response
+        });
+
+        let (stream, remote) = listener.accept().await.unwrap();
+        assert_eq!(remote, stream.peer_addr().unwrap());
+        let mut stream = AsyncIoStdAsTokio(stream);
+
+        let mut request = [0; 8];
+        stream.read_exact(&mut request[..4]).await.unwrap();
+        assert_eq!(&request[..4], b"ping");
+        // the client closed its side
+        assert_eq!(stream.read(&mut request).await.unwrap(), 0);
+        stream.write_all(b"pong").await.unwrap();
+        stream.shutdown().await.unwrap();
+
+        assert_eq!(&client.await.unwrap(), b"pong");
+    }
+}
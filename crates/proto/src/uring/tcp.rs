@@ -0,0 +1,331 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::io;
+use std::mem;
+use std::net::{Shutdown, SocketAddr, TcpListener as StdTcpListener, TcpStream as StdTcpStream};
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use async_trait::async_trait;
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_util::{future, ready};
+use io_uring::{opcode, squeue, types::Fd};
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+
+use crate::tcp::{Connect, DnsTcpStream};
+use crate::uring::driver::Op;
+use crate::TokioTime;
+
+/// Size of the buffer which is received into, a whole DNS message in most cases
+const RECV_BUFFER_SIZE: usize = 4096;
+
+/// A TCP stream which receives and sends with io_uring
+///
+/// Received bytes are buffered, written bytes are copied and sent in the background, the errors of
+///  sending are returned by the next write or flush.
+#[derive(Debug)]
+pub struct IoUringTcpStream {
+    socket: Arc<StdTcpStream>,
+    recv: Option<Op<Transfer>>,
+    received: Vec<u8>,
+    pos: usize,
+    send: Option<Op<Transfer>>,
+}
+
+impl IoUringTcpStream {
+    /// Creates a stream from a connected standard stream, which is made blocking for io_uring
+    pub fn from_std(stream: StdTcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(false)?;
+        Ok(Self::new(Arc::new(stream)))
+    }
+
+    fn new(socket: Arc<StdTcpStream>) -> Self {
+        Self {
+            socket,
+            recv: None,
+            received: Vec::new(),
+            pos: 0,
+            send: None,
+        }
+    }
+
+    /// Returns the local address of the stream
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Returns the remote address of the stream
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.peer_addr()
+    }
+
+    /// Waits until the bytes of the last write are sent
+    fn poll_sent(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while let Some(op) = self.send.as_mut() {
+            let result = ready!(op.poll(cx));
+            let mut op = self.send.take().expect("send submitted");
+            let send = op.data();
+            send.pos += result? as usize;
+
+            // partially sent, the rest is sent with a new operation
+            if send.pos < send.buffer.len() {
+                let rest = Transfer {
+                    socket: self.socket.clone(),
+                    buffer: mem::take(&mut send.buffer),
+                    pos: send.pos,
+                };
+                self.send = Some(Transfer::submit_send(rest)?);
+            }
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncRead for IoUringTcpStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        if this.pos == this.received.len() {
+            if this.recv.is_none() {
+                this.recv = Some(Transfer::submit_recv(this.socket.clone())?);
+            }
+
+            let result = ready!(this.recv.as_mut().expect("recv submitted").poll(cx));
+            let mut op = this.recv.take().expect("recv submitted");
+            let len = result? as usize;
+
+            let recv = op.data();
+            recv.buffer.truncate(len);
+            this.received = mem::take(&mut recv.buffer);
+            this.pos = 0;
+        }
+
+        let len = (this.received.len() - this.pos).min(buf.len());
+        buf[..len].copy_from_slice(&this.received[this.pos..this.pos + len]);
+        this.pos += len;
+        Poll::Ready(Ok(len))
+    }
+}
+
+impl AsyncWrite for IoUringTcpStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.poll_sent(cx))?;
+
+        let send = Transfer {
+            socket: self.socket.clone(),
+            buffer: buf.to_vec(),
+            pos: 0,
+        };
+        self.send = Some(Transfer::submit_send(send)?);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_sent(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_sent(cx))?;
+        Poll::Ready(self.socket.shutdown(Shutdown::Write))
+    }
+}
+
+impl DnsTcpStream for IoUringTcpStream {
+    type Time = TokioTime;
+}
+
+#[async_trait]
+impl Connect for IoUringTcpStream {
+    async fn connect_with_bind(
+        addr: SocketAddr,
+        bind_addr: Option<SocketAddr>,
+    ) -> io::Result<Self> {
+        let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+        if let Some(bind_addr) = bind_addr {
+            socket.bind(&SockAddr::from(bind_addr))?;
+        }
+
+        let connect = ConnectTo {
+            socket: Arc::new(StdTcpStream::from(socket)),
+            addr: SockAddr::from(addr),
+        };
+        // SAFETY: the entry only references the data
+        let mut op = unsafe {
+            Op::submit(connect, |connect| {
+                opcode::Connect::new(
+                    Fd(connect.socket.as_raw_fd()),
+                    connect.addr.as_ptr(),
+                    connect.addr.len(),
+                )
+                .build()
+            })?
+        };
+
+        future::poll_fn(|cx| op.poll(cx)).await?;
+        Ok(Self::new(op.data().socket.clone()))
+    }
+}
+
+/// A TCP listener which accepts with io_uring
+#[derive(Debug)]
+pub struct IoUringTcpListener {
+    listener: Arc<StdTcpListener>,
+    accept: Mutex<Option<Op<Accept>>>,
+}
+
+impl IoUringTcpListener {
+    /// Binds a listener to the address
+    pub fn bind(addr: SocketAddr) -> io::Result<Self> {
+        Self::from_std(StdTcpListener::bind(addr)?)
+    }
+
+    /// Creates a listener from a listening standard listener, which is made blocking for io_uring
+    pub fn from_std(listener: StdTcpListener) -> io::Result<Self> {
+        listener.set_nonblocking(false)?;
+        Ok(Self {
+            listener: Arc::new(listener),
+            accept: Mutex::new(None),
+        })
+    }
+
+    /// Returns the local address of the listener
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Polls to accept a connection, with the address of the remote
+    pub fn poll_accept(
+        &self,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<(IoUringTcpStream, SocketAddr)>> {
+        let mut accept = self.accept.lock().expect("accept lock poisoned");
+        if accept.is_none() {
+            *accept = Some(Accept::submit(self.listener.clone())?);
+        }
+
+        let result = ready!(accept.as_mut().expect("accept submitted").poll(cx));
+        let mut op = accept.take().expect("accept submitted");
+
+        // SAFETY: the result is the descriptor of the accepted socket, owned by nothing else
+        let stream = unsafe { StdTcpStream::from_raw_fd(result? as i32) };
+        let remote = op.data().remote_addr()?;
+        Poll::Ready(Ok((IoUringTcpStream::new(Arc::new(stream)), remote)))
+    }
+
+    /// Accepts a connection, with the address of the remote
+    pub async fn accept(&self) -> io::Result<(IoUringTcpStream, SocketAddr)> {
+        future::poll_fn(|cx| self.poll_accept(cx)).await
+    }
+}
+
+/// A `recv` into, or a `send` from an owned buffer
+struct Transfer {
+    socket: Arc<StdTcpStream>,
+    buffer: Vec<u8>,
+    /// The position in the buffer from which to send
+    pos: usize,
+}
+
+impl Transfer {
+    fn submit_recv(socket: Arc<StdTcpStream>) -> io::Result<Op<Self>> {
+        let recv = Self {
+            socket,
+            buffer: vec![0; RECV_BUFFER_SIZE],
+            pos: 0,
+        };
+
+        // SAFETY: the entry only references the data
+        unsafe {
+            Op::submit(recv, |recv| {
+                opcode::Recv::new(
+                    Fd(recv.socket.as_raw_fd()),
+                    recv.buffer.as_mut_ptr(),
+                    recv.buffer.len() as u32,
+                )
+                .build()
+            })
+        }
+    }
+
+    fn submit_send(send: Self) -> io::Result<Op<Self>> {
+        // SAFETY: the entry only references the data
+        unsafe { Op::submit(send, Self::send_entry) }
+    }
+
+    fn send_entry(&mut self) -> squeue::Entry {
+        let rest = &self.buffer[self.pos..];
+        opcode::Send::new(
+            Fd(self.socket.as_raw_fd()),
+            rest.as_ptr(),
+            rest.len() as u32,
+        )
+        .flags(libc::MSG_NOSIGNAL)
+        .build()
+    }
+}
+
+/// A `connect` to an owned address
+struct ConnectTo {
+    socket: Arc<StdTcpStream>,
+    addr: SockAddr,
+}
+
+/// An `accept` with an owned buffer for the remote address
+struct Accept {
+    listener: Arc<StdTcpListener>,
+    remote_addr: libc::sockaddr_storage,
+    remote_addr_len: libc::socklen_t,
+}
+
+// SAFETY: the address is plain data
+unsafe impl Send for Accept {}
+
+impl Accept {
+    fn submit(listener: Arc<StdTcpListener>) -> io::Result<Op<Self>> {
+        let accept = Self {
+            listener,
+            // SAFETY: all zeros is a valid address storage
+            remote_addr: unsafe { mem::zeroed() },
+            remote_addr_len: mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t,
+        };
+
+        // SAFETY: the entry only references the data
+        unsafe {
+            Op::submit(accept, |accept| {
+                opcode::Accept::new(
+                    Fd(accept.listener.as_raw_fd()),
+                    (&mut accept.remote_addr as *mut libc::sockaddr_storage).cast(),
+                    &mut accept.remote_addr_len,
+                )
+                .flags(libc::SOCK_CLOEXEC)
+                .build()
+            })
+        }
+    }
+
+    fn remote_addr(&self) -> io::Result<SocketAddr> {
+        // SAFETY: the kernel initialized the address, and set its length
+        unsafe { SockAddr::new(self.remote_addr, self.remote_addr_len) }
+            .as_socket()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not an IP address"))
+    }
+}
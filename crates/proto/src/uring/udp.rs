@@ -0,0 +1,221 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::io;
+use std::mem;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket as StdUdpSocket};
+use std::os::unix::io::AsRawFd;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use async_trait::async_trait;
+use futures_util::ready;
+use io_uring::{opcode, squeue, types::Fd};
+use socket2::SockAddr;
+
+use crate::udp::{DnsUdpSocket, QuicLocalAddr, UdpSocket, MAX_RECEIVE_BUFFER_SIZE};
+use crate::uring::driver::Op;
+use crate::TokioTime;
+
+/// A UDP socket which receives and sends with io_uring
+///
+/// One datagram at a time is received and sent, the socket is meant to be used by one receiving
+///  and one sending task, like the tasks of the client and server streams.
+#[derive(Debug)]
+pub struct IoUringUdpSocket {
+    socket: Arc<StdUdpSocket>,
+    recv: Mutex<Option<Op<RecvMsg>>>,
+    send: Mutex<Option<Op<SendMsg>>>,
+}
+
+impl IoUringUdpSocket {
+    /// Creates a socket from a bound standard socket, which is made blocking for io_uring
+    pub fn from_std(socket: StdUdpSocket) -> io::Result<Self> {
+        socket.set_nonblocking(false)?;
+        Ok(Self {
+            socket: Arc::new(socket),
+            recv: Mutex::new(None),
+            send: Mutex::new(None),
+        })
+    }
+
+    /// Returns the local address of the socket
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+}
+
+#[async_trait]
+impl DnsUdpSocket for IoUringUdpSocket {
+    type Time = TokioTime;
+
+    fn poll_recv_from(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<(usize, SocketAddr)>> {
+        let mut recv = self.recv.lock().expect("recv lock poisoned");
+        if recv.is_none() {
+            *recv = Some(RecvMsg::submit(self.socket.clone())?);
+        }
+
+        let result = ready!(recv.as_mut().expect("recv submitted").poll(cx));
+        let mut op = recv.take().expect("recv submitted");
+        let received = op.data();
+
+        // datagrams larger than the buffer are truncated, as with `recv_from`
+        let len = (result? as usize).min(buf.len());
+        buf[..len].copy_from_slice(&received.buffer[..len]);
+        Poll::Ready(Ok((len, received.src_addr()?)))
+    }
+
+    fn poll_send_to(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        target: SocketAddr,
+    ) -> Poll<io::Result<usize>> {
+        let mut send = self.send.lock().expect("send lock poisoned");
+        if let Some(op) = send.as_mut() {
+            let result = ready!(op.poll(cx));
+            let mut op = send.take().expect("send submitted");
+
+            // otherwise this is the datagram of a call which was abandoned, and which was sent
+            if op.data().is(buf, target) {
+                return Poll::Ready(result.map(|len| len as usize));
+            }
+        }
+
+        let op = send.insert(SendMsg::submit(self.socket.clone(), buf, target)?);
+        let result = ready!(op.poll(cx));
+        *send = None;
+        Poll::Ready(result.map(|len| len as usize))
+    }
+}
+
+#[async_trait]
+impl UdpSocket for IoUringUdpSocket {
+    /// setups up a "client" udp connection that will only receive packets from the associated address
+    ///
+    /// if the addr is ipv4 then it will bind local addr to 0.0.0.0:0, ipv6 \[::\]0
+    async fn connect(addr: SocketAddr) -> io::Result<Self> {
+        let bind_addr: SocketAddr = match addr {
+            SocketAddr::V4(_addr) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            SocketAddr::V6(_addr) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+        };
+
+        Self::connect_with_bind(addr, bind_addr).await
+    }
+
+    /// same as connect, but binds to the specified local address for sending address
+    async fn connect_with_bind(_addr: SocketAddr, bind_addr: SocketAddr) -> io::Result<Self> {
+        Self::bind(bind_addr).await
+    }
+
+    async fn bind(addr: SocketAddr) -> io::Result<Self> {
+        Self::from_std(StdUdpSocket::bind(addr)?)
+    }
+}
+
+impl QuicLocalAddr for IoUringUdpSocket {
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+}
+
+/// A `recvmsg` into an owned buffer
+struct RecvMsg {
+    socket: Arc<StdUdpSocket>,
+    buffer: Vec<u8>,
+    iovec: libc::iovec,
+    src_addr: libc::sockaddr_storage,
+    msghdr: libc::msghdr,
+}
+
+// SAFETY: the pointers only reference the other fields
+unsafe impl Send for RecvMsg {}
+
+impl RecvMsg {
+    fn submit(socket: Arc<StdUdpSocket>) -> io::Result<Op<Self>> {
+        let recv = Self {
+            socket,
+            buffer: vec![0; MAX_RECEIVE_BUFFER_SIZE],
+            // SAFETY: all zeros is valid for the C structs, the pointers are set by `entry`
+            iovec: unsafe { mem::zeroed() },
+            src_addr: unsafe { mem::zeroed() },
+            msghdr: unsafe { mem::zeroed() },
+        };
+
+        // SAFETY: the entry only references the data
+        unsafe { Op::submit(recv, Self::entry) }
+    }
+
+    fn entry(&mut self) -> squeue::Entry {
+        self.iovec.iov_base = self.buffer.as_mut_ptr().cast();
+        self.iovec.iov_len = self.buffer.len();
+        self.msghdr.msg_name = (&mut self.src_addr as *mut libc::sockaddr_storage).cast();
+        self.msghdr.msg_namelen = mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+        self.msghdr.msg_iov = &mut self.iovec;
+        self.msghdr.msg_iovlen = 1;
+
+        opcode::RecvMsg::new(Fd(self.socket.as_raw_fd()), &mut self.msghdr).build()
+    }
+
+    fn src_addr(&self) -> io::Result<SocketAddr> {
+        // SAFETY: the kernel initialized the address, and set its length
+        unsafe { SockAddr::new(self.src_addr, self.msghdr.msg_namelen) }
+            .as_socket()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not an IP address"))
+    }
+}
+
+/// A `sendmsg` from an owned copy of the datagram
+struct SendMsg {
+    socket: Arc<StdUdpSocket>,
+    buffer: Vec<u8>,
+    target: SocketAddr,
+    target_addr: SockAddr,
+    iovec: libc::iovec,
+    msghdr: libc::msghdr,
+}
+
+// SAFETY: the pointers only reference the other fields
+unsafe impl Send for SendMsg {}
+
+impl SendMsg {
+    fn submit(socket: Arc<StdUdpSocket>, buf: &[u8], target: SocketAddr) -> io::Result<Op<Self>> {
+        let send = Self {
+            socket,
+            buffer: buf.to_vec(),
+            target,
+            target_addr: SockAddr::from(target),
+            // SAFETY: all zeros is valid for the C structs, the pointers are set by `entry`
+            iovec: unsafe { mem::zeroed() },
+            msghdr: unsafe { mem::zeroed() },
+        };
+
+        // SAFETY: the entry only references the data
+        unsafe { Op::submit(send, Self::entry) }
+    }
+
+    fn entry(&mut self) -> squeue::Entry {
+        self.iovec.iov_base = self.buffer.as_mut_ptr().cast();
+        self.iovec.iov_len = self.buffer.len();
+        // the kernel does not write to the address
+        self.msghdr.msg_name = self.target_addr.as_ptr() as *mut libc::c_void;
+        self.msghdr.msg_namelen = self.target_addr.len();
+        self.msghdr.msg_iov = &mut self.iovec;
+        self.msghdr.msg_iovlen = 1;
+
+        opcode::SendMsg::new(Fd(self.socket.as_raw_fd()), &self.msghdr).build()
+    }
+
+    /// Returns true if this sends the datagram to the target
+    fn is(&self, buf: &[u8], target: SocketAddr) -> bool {
+        self.target == target && self.buffer == buf
+    }
+}
@@ -0,0 +1,194 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Timers and DNS over HTTPS on a JavaScript host, for `wasm32-unknown-unknown`
+//!
+//! There are no sockets in browsers, browser extensions and most serverless hosts, but all of them
+//!  provide `fetch` and `setTimeout`. Both are looked up on the global object, so they work in
+//!  windows as well as in workers.
+//!
+//! The JavaScript values can not leave the thread of the host, the work is done in tasks spawned
+//!  on the current thread, and only the results are passed on. This keeps the futures `Send`, as
+//!  required by the clients.
+
+use std::future::Future;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_channel::oneshot;
+use futures_util::future::{self, Either};
+use js_sys::{Function, Promise, Reflect, Uint8Array};
+use wasm_bindgen_crate::closure::Closure;
+use wasm_bindgen_crate::{JsCast, JsValue};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{Headers, Request, RequestInit, Response};
+
+use crate::error::{ProtoError, ProtoErrorKind};
+use crate::op::Message;
+use crate::xfer::{DnsHandle, DnsRequest, DnsResponse, DnsResponseStream};
+use crate::Time;
+
+/// The media type of DNS messages in HTTP requests and responses
+const MIME_APPLICATION_DNS: &str = "application/dns-message";
+
+/// New type which is implemented using `setTimeout` of the JavaScript host
+#[derive(Clone, Copy, Debug)]
+pub struct WasmTime;
+
+#[async_trait]
+impl Time for WasmTime {
+    async fn delay_for(duration: Duration) {
+        // the timer is only dropped once it fired
+        let _ = set_timeout(duration).await;
+    }
+
+    async fn timeout<F: 'static + Future + Send>(
+        duration: Duration,
+        future: F,
+    ) -> Result<F::Output, io::Error> {
+        match future::select(Box::pin(future), set_timeout(duration)).await {
+            Either::Left((output, _)) => Ok(output),
+            Either::Right(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "future timed out")),
+        }
+    }
+}
+
+/// Returns a receiver which completes after the duration
+fn set_timeout(duration: Duration) -> oneshot::Receiver<()> {
+    let (sender, receiver) = oneshot::channel();
+    let callback = Closure::once_into_js(move || {
+        let _ = sender.send(());
+    });
+
+    // the delay of `setTimeout` is a signed 32 bit number of milliseconds
+    let millis = duration.as_millis().min(i32::MAX as u128) as f64;
+    let global = js_sys::global();
+    global_function("setTimeout")
+        .and_then(|set_timeout| {
+            set_timeout
+                .call2(&global, &callback, &millis.into())
+                .map_err(js_error)
+        })
+        .expect("setTimeout is provided by all JavaScript hosts");
+
+    receiver
+}
+
+/// A client for DNS over HTTPS, with the `fetch` of the JavaScript host
+///
+/// Each request is POSTed to the URL of the DNS API as an `application/dns-message`, the host
+///  manages the HTTP connections and the TLS. See [RFC 8484](https://tools.ietf.org/html/rfc8484).
+#[derive(Clone, Debug)]
+pub struct FetchClient {
+    url: Arc<str>,
+}
+
+impl FetchClient {
+    /// Creates a client for the URL of the DNS API, e.g. `https://dns.google/dns-query`
+    pub fn new(url: impl Into<Arc<str>>) -> Self {
+        Self { url: url.into() }
+    }
+
+    /// Returns the URL of the DNS API
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl DnsHandle for FetchClient {
+    type Response = DnsResponseStream;
+
+    fn send<R: Into<DnsRequest> + Unpin + Send + 'static>(&self, request: R) -> Self::Response {
+        let mut request = request.into();
+
+        // per the RFC, a zero id allows for the HTTP packet to be cached better
+        request.set_id(0);
+
+        let bytes = match request.to_vec() {
+            Ok(bytes) => bytes,
+            Err(err) => return err.into(),
+        };
+
+        let (sender, receiver) = oneshot::channel();
+        let url = Arc::clone(&self.url);
+        spawn_local(async move {
+            let _ = sender.send(fetch(&url, bytes).await);
+        });
+
+        Box::pin(async move {
+            receiver
+                .await
+                .map_err(|canceled| ProtoError::from(ProtoErrorKind::Canceled(canceled)))?
+        })
+        .into()
+    }
+}
+
+/// POSTs the DNS message to the URL, and returns the DNS message of the response
+async fn fetch(url: &str, message: Vec<u8>) -> Result<DnsResponse, ProtoError> {
+    let headers = Headers::new().map_err(js_error)?;
+    headers
+        .set("accept", MIME_APPLICATION_DNS)
+        .map_err(js_error)?;
+    headers
+        .set("content-type", MIME_APPLICATION_DNS)
+        .map_err(js_error)?;
+
+    let mut init = RequestInit::new();
+    init.method("POST")
+        .headers(&headers)
+        .body(Some(&Uint8Array::from(message.as_slice())));
+    let request = Request::new_with_str_and_init(url, &init).map_err(js_error)?;
+
+    let global = js_sys::global();
+    let promise = global_function("fetch")?
+        .call1(&global, &request)
+        .map_err(js_error)?;
+    let response: Response = JsFuture::from(Promise::from(promise))
+        .await
+        .and_then(JsCast::dyn_into)
+        .map_err(js_error)?;
+
+    if !response.ok() {
+        return Err(ProtoError::from(format!(
+            "http unsuccessful code: {}",
+            response.status()
+        )));
+    }
+
+    // in the case that the ContentType is not specified, we assume it's the standard DNS format
+    if let Some(content_type) = response.headers().get("content-type").map_err(js_error)? {
+        if content_type != MIME_APPLICATION_DNS {
+            return Err(ProtoError::from(format!(
+                "ContentType unsupported (must be '{MIME_APPLICATION_DNS}'): '{content_type}'"
+            )));
+        }
+    }
+
+    let buffer = JsFuture::from(response.array_buffer().map_err(js_error)?)
+        .await
+        .map_err(js_error)?;
+    let bytes = Uint8Array::new(&buffer).to_vec();
+    let message = Message::from_vec(&bytes)?;
+    Ok(DnsResponse::new(message, bytes))
+}
+
+/// Returns the function of the global object, i.e. of the window or the worker
+fn global_function(name: &str) -> Result<Function, ProtoError> {
+    Reflect::get(&js_sys::global(), &JsValue::from_str(name))
+        .and_then(JsCast::dyn_into)
+        .map_err(|_| ProtoError::from(format!("{name} is not a function of the host")))
+}
+
+fn js_error(value: JsValue) -> ProtoError {
+    match value.dyn_ref::<js_sys::Error>() {
+        Some(error) => ProtoError::from(String::from(error.message())),
+        None => ProtoError::from(format!("{value:?}")),
+    }
+}
@@ -11,10 +11,10 @@ use std::marker::PhantomData;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
+use crate::logging::{debug, warn};
 use futures_channel::mpsc;
 use futures_util::future::{Future, FutureExt};
 use futures_util::stream::{Peekable, Stream, StreamExt};
-use tracing::{debug, warn};
 
 use crate::error::*;
 use crate::xfer::dns_handle::DnsHandle;
@@ -188,10 +188,7 @@ where
                     return Poll::Ready(Ok(()));
                 }
                 Poll::Ready(Some(Err(err))) => {
-                    debug!(
-                        error = err.as_dyn(),
-                        "io_stream hit an error, shutting down"
-                    );
+                    debug!("io_stream hit an error, shutting down: {err}");
 
                     return Poll::Ready(Err(err));
                 }
@@ -337,7 +334,7 @@ where
                         }
                         Poll::Pending => return Poll::Pending,
                         Poll::Ready(Err(error)) => {
-                            debug!(error = error.as_dyn(), "stream errored while connecting");
+                            debug!("stream errored while connecting: {error}");
                             next = Self::FailAll {
                                 error,
                                 outbound_messages: outbound_messages
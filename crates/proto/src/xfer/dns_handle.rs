@@ -7,9 +7,9 @@
 
 //! `DnsHandle` types perform conversions of the raw DNS messages before sending the messages on the specified streams.
 
+use crate::logging::debug;
 use futures_util::stream::Stream;
 use rand;
-use tracing::debug;
 
 use crate::op::{Message, MessageType, OpCode, Query};
 use crate::xfer::{DnsRequest, DnsRequestOptions, DnsResponse, SerialMessage};
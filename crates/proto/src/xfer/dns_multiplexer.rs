@@ -15,9 +15,10 @@ use std::{
     pin::Pin,
     sync::Arc,
     task::{Context, Poll},
-    time::{Duration, SystemTime, UNIX_EPOCH},
+    time::Duration,
 };
 
+use crate::logging::debug;
 use futures_channel::mpsc;
 use futures_util::{
     future::Future,
@@ -29,9 +30,9 @@ use rand::{
     self,
     distributions::{Distribution, Standard},
 };
-use tracing::debug;
 
 use crate::{
+    clock::{Clock, SystemClock},
     error::{ProtoError, ProtoErrorKind},
     op::{MessageFinalizer, MessageVerifier},
     xfer::{
@@ -104,6 +105,7 @@ where
     stream_handle: BufDnsStreamHandle,
     active_requests: HashMap<u16, ActiveRequest>,
     signer: Option<Arc<MF>>,
+    clock: Arc<dyn Clock>,
     is_shutdown: bool,
 }
 
@@ -156,6 +158,7 @@ where
             stream_handle: Some(stream_handle),
             timeout_duration,
             signer,
+            clock: Arc::new(SystemClock),
         }
     }
 
@@ -206,7 +209,7 @@ where
 
     /// Closes all outstanding completes with a closed stream error
     fn stream_closed_close_all(&mut self, error: ProtoError) {
-        debug!(error = error.as_dyn(), stream = %self.stream);
+        debug!("stream closed with error: {error}, stream: {}", self.stream);
 
         for (_, active_request) in self.active_requests.drain() {
             // complete the request, it's failed...
@@ -227,6 +230,20 @@ where
     stream_handle: Option<BufDnsStreamHandle>,
     timeout_duration: Duration,
     signer: Option<Arc<MF>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl<F, S, MF> DnsMultiplexerConnect<F, S, MF>
+where
+    F: Future<Output = Result<S, ProtoError>> + Send + Unpin + 'static,
+    S: Stream<Item = Result<SerialMessage, ProtoError>> + Unpin,
+    MF: MessageFinalizer + Send + Sync + 'static,
+{
+    /// Sets the clock for the time of signed messages, the system clock by default
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
 }
 
 impl<F, S, MF> Future for DnsMultiplexerConnect<F, S, MF>
@@ -249,6 +266,7 @@ where
                 .expect("must not poll after complete"),
             active_requests: HashMap::new(),
             signer: self.signer.clone(),
+            clock: Arc::clone(&self.clock),
             is_shutdown: false,
         }))
     }
@@ -286,14 +304,11 @@ where
         let (mut request, _) = request.into_parts();
         request.set_id(query_id);
 
-        let now = match SystemTime::now().duration_since(UNIX_EPOCH) {
-            Ok(now) => now.as_secs(),
-            Err(_) => return ProtoError::from("Current time is before the Unix epoch.").into(),
+        let now = match self.clock.unix_time() {
+            Ok(now) => now,
+            Err(err) => return err.into(),
         };
 
-        // TODO: truncates u64 to u32, error on overflow?
-        let now = now as u32;
-
         let mut verifier = None;
         if let Some(ref signer) = self.signer {
             if signer.should_finalize_message(&request) {
@@ -318,7 +333,7 @@ where
 
         match request.to_vec() {
             Ok(buffer) => {
-                debug!(id = %active_request.request_id(), "sending message");
+                debug!("sending message id: {}", active_request.request_id());
                 let serial_message = SerialMessage::new(buffer, self.stream.name_server_addr());
 
                 debug!(
@@ -339,9 +354,8 @@ where
             }
             Err(e) => {
                 debug!(
-                    id = %active_request.request_id(),
-                    error = e.as_dyn(),
-                    "error message"
+                    "error message id: {}, error: {e}",
+                    active_request.request_id()
                 );
                 // complete with the error, don't add to the map of active requests
                 return e.into();
@@ -406,7 +420,7 @@ where
                             Entry::Vacant(..) => debug!("unexpected request_id: {}", message.id()),
                         },
                         // TODO: return src address for diagnostics
-                        Err(error) => debug!(error = error.as_dyn(), "error decoding message"),
+                        Err(error) => debug!("error decoding message: {error}"),
                     }
                 }
                 Poll::Ready(err) => {
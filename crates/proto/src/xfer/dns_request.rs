@@ -29,6 +29,16 @@ pub struct DnsRequestOptions {
     pub max_request_depth: usize,
     /// set recursion desired (or not) for any requests
     pub recursion_desired: bool,
+    /// When true, a NXDOMAIN response must be confirmed by a second name server.
+    ///
+    /// This is only supported by handles which query multiple name servers. If no other name
+    ///  server confirms or contradicts the NXDOMAIN, it is returned as not trusted.
+    pub confirm_nxdomain: bool,
+    /// The number of the preferred name servers to skip, e.g. to retry with a different one.
+    ///
+    /// This is set by the `RetryDnsHandle` of a `RetryPolicy` which retries on a different name
+    ///  server. It is only supported by handles which query multiple name servers.
+    pub skip_servers: usize,
 }
 
 impl Default for DnsRequestOptions {
@@ -39,6 +49,8 @@ impl Default for DnsRequestOptions {
             expects_multiple_responses: false,
             use_edns: false,
             recursion_desired: true,
+            confirm_nxdomain: false,
+            skip_servers: 0,
         }
     }
 }
@@ -63,6 +75,11 @@ impl DnsRequest {
         &self.options
     }
 
+    /// Get a mutable reference to the request options associated with this request
+    pub fn options_mut(&mut self) -> &mut DnsRequestOptions {
+        &mut self.options
+    }
+
     /// Unwraps the raw message
     pub fn into_parts(self) -> (Message, DnsRequestOptions) {
         (self.message, self.options)
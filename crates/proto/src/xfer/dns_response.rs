@@ -134,13 +134,38 @@ type TimeoutFuture = Pin<
 pub struct DnsResponse {
     message: Message,
     buffer: Vec<u8>,
+    transport: Option<Transport>,
+}
+
+/// The transport over which a response was received, see [`DnsResponse::transport`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Transport {
+    /// DNS over UDP
+    Udp,
+    /// DNS over TCP
+    Tcp,
+    /// DNS over TLS
+    Tls,
+    /// DNS over HTTPS
+    Https,
+    /// DNS over QUIC
+    Quic,
+    /// DNS over HTTP/3
+    H3,
+    /// Multicast DNS
+    Mdns,
 }
 
 // TODO: when `impl Trait` lands in stable, remove this, and expose FlatMap over answers, et al.
 impl DnsResponse {
     /// Constructs a new DnsResponse
     pub fn new(message: Message, buffer: Vec<u8>) -> Self {
-        Self { message, buffer }
+        Self {
+            message,
+            buffer,
+            transport: None,
+        }
     }
 
     /// Constructs a new DnsResponse with a buffer synthesized from the message
@@ -148,9 +173,25 @@ impl DnsResponse {
         Ok(Self {
             buffer: message.to_vec()?,
             message,
+            transport: None,
         })
     }
 
+    /// The transport over which the response was received, if recorded by the handle it was
+    ///  sent through
+    ///
+    /// The resolver records the transport of the name server which answered, e.g. `Tcp` when a
+    ///  truncated response over UDP was retried over TCP.
+    pub fn transport(&self) -> Option<Transport> {
+        self.transport
+    }
+
+    /// Records the transport over which the response was received
+    pub fn set_transport(&mut self, transport: Transport) -> &mut Self {
+        self.transport = Some(transport);
+        self
+    }
+
     /// Retrieves the SOA from the response. This will only exist if it was an authoritative response.
     pub fn soa(&self) -> Option<RecordRef<'_, SOA>> {
         self.name_servers()
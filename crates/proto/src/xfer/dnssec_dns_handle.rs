@@ -14,19 +14,20 @@ use std::{
     sync::Arc,
 };
 
+use crate::logging::{debug, trace};
 use async_recursion::async_recursion;
 use futures_util::{
     future::{self, FutureExt, TryFutureExt},
     stream::{self, Stream, TryStreamExt},
 };
-use tracing::{debug, trace};
 
 use crate::{
+    clock::{Clock, SystemClock},
     error::{ProtoError, ProtoErrorKind},
     op::{Edns, OpCode, Query},
     rr::{
         dnssec::{
-            rdata::{DNSSECRData, DNSKEY, DS, RRSIG},
+            rdata::{DNSSECRData, DNSKEY, DS, NSEC3, RRSIG},
             Algorithm, Proof, ProofError, ProofErrorKind, SupportedAlgorithms, TrustAnchor,
         },
         rdata::opt::EdnsOption,
@@ -39,7 +40,7 @@ use crate::{
 use crate::rr::dnssec::Verifier;
 
 // TODO: combine this with crate::rr::RecordSet?
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 struct Rrset<'r> {
     pub(crate) name: Name,
     pub(crate) record_type: RecordType,
@@ -63,6 +64,7 @@ where
     request_depth: usize,
     minimum_key_len: usize,
     minimum_algorithm: Algorithm, // used to prevent down grade attacks...
+    clock: Arc<dyn Clock>,
 }
 
 impl<H> DnssecDnsHandle<H>
@@ -93,9 +95,18 @@ where
             request_depth: 0,
             minimum_key_len: 0,
             minimum_algorithm: Algorithm::RSASHA256,
+            clock: Arc::new(SystemClock),
         }
     }
 
+    /// Sets the clock against which the validity period of RRSIGs is checked
+    ///
+    /// This is the system clock by default.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
     /// An internal function used to clone the handle, but maintain some information back to the
     ///  original handle, such as the request_depth such that infinite recursion does
     ///  not occur.
@@ -106,6 +117,7 @@ where
             request_depth: self.request_depth + 1,
             minimum_key_len: self.minimum_key_len,
             minimum_algorithm: self.minimum_algorithm,
+            clock: Arc::clone(&self.clock),
         }
     }
 }
@@ -260,9 +272,24 @@ where
     let nameservers = message.take_name_servers();
     let additionals = message.take_additionals();
 
-    let answers = verify_rrsets(handle.clone(), &query, answers, options).await;
-    let nameservers = verify_rrsets(handle.clone(), &query, nameservers, options).await;
-    let additionals = verify_rrsets(handle.clone(), &query, additionals, options).await;
+    let (mut answers, expansions) = verify_rrsets(handle.clone(), &query, answers, options).await;
+    let (nameservers, _) = verify_rrsets(handle.clone(), &query, nameservers, options).await;
+    let (additionals, _) = verify_rrsets(handle.clone(), &query, additionals, options).await;
+
+    // an answer synthesized from a wildcard requires the proof that there was no closer match
+    for (name, record_type, num_labels) in expansions {
+        if proves_wildcard_expansion(&name, num_labels, &nameservers) {
+            continue;
+        }
+
+        debug!("{name} {record_type} is expanded from a wildcard, but {name} is not proven absent");
+        for record in answers
+            .iter_mut()
+            .filter(|rr| rr.name() == &name && rr.record_type() == record_type)
+        {
+            record.set_proof(Proof::Bogus);
+        }
+    }
 
     message.insert_answers(answers);
     message.insert_name_servers(nameservers);
@@ -273,13 +300,16 @@ where
 
 /// This pulls all answers returned in a Message response and returns a future which will
 ///  validate all of them.
+///
+/// The rrsets which were validated with the RRSIG of a wildcard are returned with the least number
+///  of labels of these RRSIGs, the proof that the name does not exist is checked by the caller.
 #[allow(clippy::type_complexity)]
 async fn verify_rrsets<H>(
     handle: DnssecDnsHandle<H>,
     query: &Query,
     records: Vec<Record>,
     options: DnsRequestOptions,
-) -> Vec<Record>
+) -> (Vec<Record>, Vec<(Name, RecordType, u8)>)
 where
     H: DnsHandle + Sync + Unpin,
 {
@@ -305,9 +335,13 @@ where
 
     // there were no records to verify
     if rrset_types.is_empty() {
-        return records;
+        return (records, vec![]);
     }
 
+    // the unsigned CNAMEs synthesized from DNAMEs, with the owner of the DNAME
+    let mut synthesized: Vec<(Name, Name)> = Vec::new();
+    let mut expansions: Vec<(Name, RecordType, u8)> = Vec::new();
+
     // collect all the rrsets to verify
     // TODO: is there a way to get rid of this clone() safely?
     for (name, record_type) in rrset_types {
@@ -325,6 +359,18 @@ where
             .filter(|rrsig| rrsig.type_covered() == record_type)
             .collect();
 
+        // the CNAME is as secure as the DNAME it is synthesized from, which is verified on its own
+        if record_type == RecordType::CNAME && rrsigs.is_empty() {
+            if let Some(dname) = rrs_to_verify
+                .first()
+                .and_then(|cname| synthesizing_dname(cname, &records))
+            {
+                debug!("{name} CNAME is synthesized from DNAME {}", dname.name());
+                synthesized.push((name, dname.name().clone()));
+                continue;
+            }
+        }
+
         // if there is already an active validation going on, assume the other validation will
         //  complete properly or error if it is invalid
         let rrset = Rrset {
@@ -340,10 +386,35 @@ where
             rrsig_len = rrsigs.len()
         );
 
+        // the RRSIGs of a wildcard are only used if the name itself is not validly signed, as
+        //  the expansion requires a proof that the name does not exist, see RFC 4035 section 5.3.4
+        let (rrsigs, wildcard_rrsigs): (Vec<&RRSIG>, Vec<&RRSIG>) = rrsigs
+            .into_iter()
+            .partition(|rrsig| !rrsig.is_wildcard_expansion(&name));
+
         // verify this rrset
-        let proof = verify_rrset(handle.clone_with_context(), rrset, rrsigs, options).await;
+        let mut proof = None;
+        if !rrsigs.is_empty() || wildcard_rrsigs.is_empty() {
+            proof = Some(
+                verify_rrset(handle.clone_with_context(), rrset.clone(), rrsigs, options).await,
+            );
+        }
+        if !matches!(proof, Some(Ok(Proof::Secure))) && !wildcard_rrsigs.is_empty() {
+            // the least labels, so that a forged RRSIG can not make the proof easier
+            let num_labels = wildcard_rrsigs
+                .iter()
+                .map(|rrsig| rrsig.num_labels())
+                .min()
+                .unwrap_or_default();
+            let wildcard_proof =
+                verify_rrset(handle.clone_with_context(), rrset, wildcard_rrsigs, options).await;
+            if let Ok(Proof::Secure) = wildcard_proof {
+                expansions.push((name.clone(), record_type, num_labels));
+            }
+            proof = Some(wildcard_proof);
+        }
 
-        let proof = match proof {
+        let proof = match proof.expect("the rrset was verified") {
             Ok(proof) => {
                 debug!("verified: {name} record_type: {record_type}",);
                 proof
@@ -357,6 +428,14 @@ where
         rrset_proofs.insert((name, record_type), proof);
     }
 
+    for (name, owner) in synthesized {
+        let proof = rrset_proofs
+            .get(&(owner, RecordType::DNAME))
+            .copied()
+            .unwrap_or(Proof::Bogus);
+        rrset_proofs.insert((name, RecordType::CNAME), proof);
+    }
+
     // set the proofs of all the records, all records are returned, it's up to downstream users to check for correctness
     let mut records = records;
     for record in &mut records {
@@ -365,7 +444,87 @@ where
             .map(|proof| record.set_proof(*proof));
     }
 
+    (records, expansions)
+}
+
+/// Returns true if a secure NSEC or NSEC3 record proves that `name` does not exist, nor any name
+///  between it and the wildcard with `num_labels` labels from which its records were expanded
+///
+/// It is sufficient that the next closer name, the ancestor of `name` with one more label than the
+///  wildcard, is covered, see [RFC 4035](https://tools.ietf.org/html/rfc4035#section-5.3.4) and
+///  [RFC 5155](https://tools.ietf.org/html/rfc5155#section-8.8).
+fn proves_wildcard_expansion(name: &Name, num_labels: u8, records: &[Record]) -> bool {
+    let next_closer = name.trim_to(usize::from(num_labels) + 1);
+
     records
+        .iter()
+        .filter(|record| record.proof().is_secure())
+        .any(|record| match record.data() {
+            Some(RData::DNSSEC(DNSSECRData::NSEC(nsec))) => {
+                let (owner, next) = (record.name(), nsec.next_domain_name());
+                // the last NSEC record of the zone wraps to the apex
+                *owner < next_closer && (next_closer < *next || next <= owner)
+            }
+            Some(RData::DNSSEC(DNSSECRData::NSEC3(nsec3))) => {
+                nsec3_covers(record.name(), nsec3, &next_closer)
+            }
+            _ => false,
+        })
+}
+
+/// Returns true if the hash of `name` is between the hashed owner name and the next one
+#[cfg(any(feature = "openssl", feature = "ring"))]
+fn nsec3_covers(owner: &Name, nsec3: &NSEC3, name: &Name) -> bool {
+    use data_encoding::BASE32_DNSSEC;
+
+    // the NSEC3 records are in the apex of the zone
+    if !owner.base_name().zone_of(name) {
+        return false;
+    }
+
+    let Some(owner_hash) = owner
+        .iter()
+        .next()
+        .and_then(|label| BASE32_DNSSEC.decode(&label.to_ascii_lowercase()).ok())
+    else {
+        return false;
+    };
+    let Ok(hash) = nsec3
+        .hash_algorithm()
+        .hash(nsec3.salt(), name, nsec3.iterations())
+    else {
+        return false;
+    };
+
+    let (owner, hash, next) = (
+        owner_hash.as_slice(),
+        hash.as_ref(),
+        nsec3.next_hashed_owner_name(),
+    );
+    // the last NSEC3 record of the zone wraps to the first hash
+    owner < hash && (hash < next || next <= owner)
+}
+
+/// Without hash algorithms, NSEC3 records can not prove anything
+#[cfg(not(any(feature = "openssl", feature = "ring")))]
+fn nsec3_covers(_owner: &Name, _nsec3: &NSEC3, _name: &Name) -> bool {
+    false
+}
+
+/// Returns the DNAME record from which the CNAME record was synthesized
+///
+/// Servers do not sign the CNAME records they synthesize, a validator instead checks that the
+///  CNAME is the substitution of the signed DNAME, see
+///  [RFC 6672](https://tools.ietf.org/html/rfc6672#section-5.3.3).
+fn synthesizing_dname<'r>(cname: &Record, records: &'r [Record]) -> Option<&'r Record> {
+    let target = cname.data().and_then(RData::as_cname)?;
+
+    records.iter().find(|record| match record.data() {
+        Some(RData::DNAME(dname)) => {
+            dname.substitute(record.name(), cname.name()).ok().as_ref() == Some(&target.0)
+        }
+        _ => false,
+    })
 }
 
 // TODO: is this method useful/necessary?
@@ -618,6 +777,10 @@ where
         }
     }
 
+    let now = handle.clock.unix_time().map_err(|err| {
+        ProofError::new(Proof::Indeterminate, ProofErrorKind::Msg(err.to_string()))
+    })?;
+
     // the record set is going to be shared across a bunch of futures, Arc for that.
     trace!(
         "default validation {}, record_type: {:?}",
@@ -644,7 +807,7 @@ where
                     .filter_map(|(d, n)| DNSKEY::try_borrow(d).map(|d| (d, n)))
                     .find_map(|(dnskey, dnskey_name)| {
                         // If we had rrsigs to verify, then we want them to be secure, or the result is a Bogus proof
-                        verify_rrset_with_dnskey(dnskey_name, dnskey, rrsig, &rrset).ok()
+                        verify_rrset_with_dnskey(dnskey_name, dnskey, rrsig, &rrset, now).ok()
                     })
             })
             .ok_or_else(|| {
@@ -692,7 +855,7 @@ where
                             DNSKEY::try_borrow(data).map(|data| (dnskey_name, data))
                         })
                         .find_map(|(dnskey_name, dnskey)| {
-                            verify_rrset_with_dnskey(dnskey_name, dnskey, rrsig, &rrset).ok()
+                            verify_rrset_with_dnskey(dnskey_name, dnskey, rrsig, &rrset, now).ok()
                         })
                 })
         })
@@ -729,7 +892,25 @@ fn verify_rrset_with_dnskey(
     dnskey: &DNSKEY,
     rrsig: &RRSIG,
     rrset: &Rrset<'_>,
+    now: u32,
 ) -> Result<Proof, ProofError> {
+    if !is_rrsig_current(rrsig, now) {
+        debug!(
+            "rrsig of {} is not valid at {}, inception: {} expiration: {}",
+            rrset.name,
+            now,
+            rrsig.sig_inception(),
+            rrsig.sig_expiration()
+        );
+        return Err(ProofError::new(
+            Proof::Bogus,
+            ProofErrorKind::RrsigNotCurrent {
+                name: rrset.name.clone(),
+                key_tag: rrsig.key_tag(),
+                now,
+            },
+        ));
+    }
     if dnskey.revoke() {
         debug!("revoked");
         return Err(ProofError::new(
@@ -784,6 +965,14 @@ fn verify_rrset_with_dnskey(
         })
 }
 
+/// Checks that the time is within the validity period of the RRSIG, RFC 4035 section 5.3.1
+///
+/// The times are compared with serial number arithmetic, see RFC 4034 section 3.1.5.
+fn is_rrsig_current(rrsig: &RRSIG, now: u32) -> bool {
+    now.wrapping_sub(rrsig.sig_inception()) as i32 >= 0
+        && rrsig.sig_expiration().wrapping_sub(now) as i32 >= 0
+}
+
 /// Will always return an error. To enable record verification compile with the openssl feature.
 #[cfg(not(feature = "dnssec"))]
 fn verify_rrset_with_dnskey(_: &DNSKEY, _: &RRSIG, _: &Rrset) -> ProtoResult<()> {
@@ -912,3 +1101,120 @@ pub fn verify_nsec(query: Arc<Query>, soa_name: &Name, nsecs: &[&Record]) -> Pro
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rrsig(inception: u32, expiration: u32) -> RRSIG {
+        RRSIG::new(
+            RecordType::A,
+            Algorithm::ED25519,
+            2,
+            3600,
+            expiration,
+            inception,
+            1234,
+            Name::from_ascii("example.com.").unwrap(),
+            vec![],
+        )
+    }
+
+    #[test]
+    fn test_rrsig_current() {
+        let rrsig = rrsig(1_000, 2_000);
+        assert!(!is_rrsig_current(&rrsig, 999));
+        assert!(is_rrsig_current(&rrsig, 1_000));
+        assert!(is_rrsig_current(&rrsig, 2_000));
+        assert!(!is_rrsig_current(&rrsig, 2_001));
+    }
+
+    #[test]
+    fn test_rrsig_current_wraps() {
+        // the validity period spans the wrap around of the 32 bit time
+        let rrsig = rrsig(u32::MAX - 10, 10);
+        assert!(is_rrsig_current(&rrsig, u32::MAX));
+        assert!(is_rrsig_current(&rrsig, 5));
+        assert!(!is_rrsig_current(&rrsig, 11));
+        assert!(!is_rrsig_current(&rrsig, u32::MAX - 11));
+    }
+
+    #[test]
+    fn test_synthesizing_dname() {
+        use crate::rr::rdata::{CNAME, DNAME};
+
+        let name = |name: &str| Name::from_ascii(name).unwrap();
+        let dname = Record::from_rdata(
+            name("old.example.com."),
+            300,
+            RData::DNAME(DNAME(name("new.example.com."))),
+        );
+        let records = vec![dname.clone()];
+
+        let cname = Record::from_rdata(
+            name("www.old.example.com."),
+            300,
+            RData::CNAME(CNAME(name("www.new.example.com."))),
+        );
+        assert_eq!(synthesizing_dname(&cname, &records), Some(&dname));
+
+        // a CNAME to another name is not derived from the DNAME
+        let forged = Record::from_rdata(
+            name("www.old.example.com."),
+            300,
+            RData::CNAME(CNAME(name("www.example.org."))),
+        );
+        assert_eq!(synthesizing_dname(&forged, &records), None);
+    }
+
+    #[test]
+    fn test_proves_wildcard_expansion() {
+        use crate::rr::dnssec::rdata::NSEC;
+
+        let name = |name: &str| Name::from_ascii(name).unwrap();
+        let expanded = name("www.example.com.");
+        assert!(rrsig(0, 0).is_wildcard_expansion(&expanded));
+        assert!(!rrsig(0, 0).is_wildcard_expansion(&name("example.com.")));
+
+        let nsec = |owner: &str, next: &str| {
+            let mut record = Record::from_rdata(
+                name(owner),
+                300,
+                RData::DNSSEC(DNSSECRData::NSEC(NSEC::new(
+                    name(next),
+                    vec![RecordType::A],
+                ))),
+            );
+            record.set_proof(Proof::Secure);
+            record
+        };
+
+        assert!(proves_wildcard_expansion(
+            &expanded,
+            2,
+            &[nsec("*.example.com.", "xyz.example.com.")]
+        ));
+        // the last NSEC wraps to the apex
+        assert!(proves_wildcard_expansion(
+            &expanded,
+            2,
+            &[nsec("a.example.com.", "example.com.")]
+        ));
+        // www.example.com. exists
+        assert!(!proves_wildcard_expansion(
+            &expanded,
+            2,
+            &[nsec("www.example.com.", "xyz.example.com.")]
+        ));
+        // the next closer name of a deeper expansion is an.example.com.
+        assert!(!proves_wildcard_expansion(
+            &name("a.an.example.com."),
+            2,
+            &[nsec("an.example.com.", "xyz.example.com.")]
+        ));
+
+        let mut insecure = nsec("*.example.com.", "xyz.example.com.");
+        insecure.set_proof(Proof::Insecure);
+        assert!(!proves_wildcard_expansion(&expanded, 2, &[insecure]));
+    }
+}
@@ -9,12 +9,12 @@ use std::net::SocketAddr;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
+use crate::logging::{debug, warn};
 use futures_channel::mpsc;
 use futures_channel::oneshot;
 use futures_util::future::Future;
 use futures_util::ready;
 use futures_util::stream::{Fuse, Peekable, Stream, StreamExt};
-use tracing::{debug, warn};
 
 use crate::error::*;
 use crate::Time;
@@ -36,11 +36,13 @@ pub use self::dns_exchange::{
 pub use self::dns_handle::{DnsHandle, DnsStreamHandle};
 pub use self::dns_multiplexer::{DnsMultiplexer, DnsMultiplexerConnect};
 pub use self::dns_request::{DnsRequest, DnsRequestOptions};
-pub use self::dns_response::{DnsResponse, DnsResponseStream};
+pub use self::dns_response::{DnsResponse, DnsResponseStream, Transport};
 #[cfg(feature = "dnssec")]
 #[cfg_attr(docsrs, doc(cfg(feature = "dnssec")))]
 pub use self::dnssec_dns_handle::DnssecDnsHandle;
-pub use self::retry_dns_handle::RetryDnsHandle;
+pub use self::retry_dns_handle::{
+    Backoff, RetryDecision, RetryDnsHandle, RetryPolicy, RetryPolicyBuilder,
+};
 pub use self::serial_message::SerialMessage;
 
 /// Ignores the result of a send operation and logs and ignores errors
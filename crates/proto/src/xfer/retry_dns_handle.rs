@@ -7,27 +7,30 @@
 
 //! `RetryDnsHandle` allows for DnsQueries to be reattempted on failure
 
+use std::fmt;
+use std::future::Future;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::Duration;
 
+use futures_util::ready;
 use futures_util::stream::{Stream, StreamExt};
 
 use crate::error::{ProtoError, ProtoErrorKind};
+use crate::op::ResponseCode;
 use crate::xfer::{DnsRequest, DnsResponse};
-use crate::DnsHandle;
+use crate::{DnsHandle, Time};
 
 /// Can be used to reattempt queries if they fail
 ///
-/// Note: this does not reattempt queries that fail with a negative response.
-/// For example, if a query gets a `NODATA` response from a name server, the
-/// query will not be retried. It only reattempts queries that effectively
-/// failed to get a response, such as queries that resulted in IO or timeout
-/// errors.
+/// When and how often a query is reattempted is determined by the [`RetryPolicy`]. By default
+/// this does not reattempt queries that fail with a negative response. For example, if a query
+/// gets a `NODATA` response from a name server, the query will not be retried. It only
+/// reattempts queries that effectively failed to get a response, such as queries that resulted
+/// in IO or timeout errors.
 ///
-/// Whether an error is retryable by the [`RetryDnsHandle`] is determined by the
-/// [`RetryableError`] trait.
-///
-/// *note* Current value of this is not clear, it may be removed
+/// Whether an error is retryable by default is determined by the [`RetryableError`] trait.
 #[derive(Clone)]
 #[must_use = "queries can only be sent through a ClientHandle"]
 pub struct RetryDnsHandle<H>
@@ -35,7 +38,7 @@ where
     H: DnsHandle + Unpin + Send,
 {
     handle: H,
-    attempts: usize,
+    policy: Arc<RetryPolicy>,
 }
 
 impl<H> RetryDnsHandle<H>
@@ -49,7 +52,20 @@ where
     /// * `handle` - handle to the dns connection
     /// * `attempts` - number of attempts before failing
     pub fn new(handle: H, attempts: usize) -> Self {
-        Self { handle, attempts }
+        Self::with_policy(handle, RetryPolicy::builder().attempts(attempts).build())
+    }
+
+    /// Creates a new Client handler for reattempting requests as specified by the policy.
+    pub fn with_policy(handle: H, policy: RetryPolicy) -> Self {
+        Self {
+            handle,
+            policy: Arc::new(policy),
+        }
+    }
+
+    /// The policy for reattempting requests
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
     }
 }
 
@@ -70,7 +86,10 @@ where
             request,
             handle: self.handle.clone(),
             stream,
-            remaining_attempts: self.attempts,
+            remaining_attempts: self.policy.attempts,
+            retries: 0,
+            delay: None,
+            policy: Arc::clone(&self.policy),
         })
     }
 }
@@ -84,6 +103,21 @@ where
     handle: H,
     stream: <H as DnsHandle>::Response,
     remaining_attempts: usize,
+    retries: usize,
+    delay: Option<Pin<Box<dyn Future<Output = ()> + Send>>>,
+    policy: Arc<RetryPolicy>,
+}
+
+impl<H: DnsHandle> RetrySendStream<H> {
+    fn resend(&mut self) {
+        // TODO: if the "sent" Message is part of the error result,
+        //  then we can just reuse it... and no clone necessary
+        let mut request = self.request.clone();
+        if self.policy.different_server {
+            request.options_mut().skip_servers = self.retries;
+        }
+        self.stream = self.handle.send(request);
+    }
 }
 
 impl<H: DnsHandle + Unpin> Stream for RetrySendStream<H> {
@@ -93,20 +127,29 @@ impl<H: DnsHandle + Unpin> Stream for RetrySendStream<H> {
         // loop over the stream, on errors, spawn a new stream
         //  on ready and not ready return.
         loop {
+            if let Some(delay) = self.delay.as_mut() {
+                ready!(delay.as_mut().poll(cx));
+                self.delay = None;
+                self.resend();
+            }
+
             match self.stream.poll_next_unpin(cx) {
                 Poll::Ready(Some(Err(e))) => {
-                    if self.remaining_attempts == 0 || !e.should_retry() {
+                    let decision = self.policy.classify(&e);
+                    if self.remaining_attempts == 0 || decision == RetryDecision::Fail {
                         return Poll::Ready(Some(Err(e)));
                     }
 
-                    if e.attempted() {
+                    if decision == RetryDecision::Retry {
                         self.remaining_attempts -= 1;
                     }
 
-                    // TODO: if the "sent" Message is part of the error result,
-                    //  then we can just reuse it... and no clone necessary
-                    let request = self.request.clone();
-                    self.stream = self.handle.send(request);
+                    let backoff = self.policy.backoff.delay(self.retries);
+                    self.retries += 1;
+                    match self.policy.delay {
+                        Some(delay) if !backoff.is_zero() => self.delay = Some(delay(backoff)),
+                        _ => self.resend(),
+                    }
                 }
                 poll => return poll,
             }
@@ -114,6 +157,212 @@ impl<H: DnsHandle + Unpin> Stream for RetrySendStream<H> {
     }
 }
 
+/// Whether a failed request should be reattempted, see [`RetryPolicyBuilder::classify`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RetryDecision {
+    /// The request is reattempted, if there are attempts remaining
+    Retry,
+    /// The request is reattempted without counting it as an attempt, e.g. if the server was busy
+    RetryUncounted,
+    /// The request fails with the error
+    Fail,
+}
+
+/// The time to wait before reattempting a request
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Backoff {
+    /// Requests are reattempted immediately
+    None,
+    /// Requests are reattempted after the same delay
+    Fixed(Duration),
+    /// The delay starts at `initial` and doubles with each retry, up to `max`
+    Exponential {
+        /// the delay before the first retry
+        initial: Duration,
+        /// the maximum delay
+        max: Duration,
+    },
+}
+
+impl Backoff {
+    /// The delay before the retry, the first retry is `0`
+    pub fn delay(&self, retry: usize) -> Duration {
+        match *self {
+            Self::None => Duration::ZERO,
+            Self::Fixed(delay) => delay,
+            Self::Exponential { initial, max } => {
+                let factor = 1_u32.checked_shl(retry as u32).unwrap_or(u32::MAX);
+                initial.saturating_mul(factor).min(max)
+            }
+        }
+    }
+}
+
+type Classifier = Arc<dyn Fn(&ProtoError) -> Option<RetryDecision> + Send + Sync>;
+type Delay = fn(Duration) -> Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// When and how often the [`RetryDnsHandle`] reattempts requests
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// use hickory_proto::op::ResponseCode;
+/// use hickory_proto::xfer::{Backoff, RetryDecision, RetryPolicy};
+/// use hickory_proto::TokioTime;
+///
+/// let policy = RetryPolicy::builder()
+///     .attempts(3)
+///     .backoff::<TokioTime>(Backoff::Exponential {
+///         initial: Duration::from_millis(50),
+///         max: Duration::from_secs(1),
+///     })
+///     .retry_response_codes([ResponseCode::ServFail])
+///     .retry_on_different_server(true)
+///     .classify(|error| error.is_busy().then_some(RetryDecision::Fail))
+///     .build();
+/// assert_eq!(policy.attempts(), 3);
+/// ```
+#[derive(Clone)]
+pub struct RetryPolicy {
+    attempts: usize,
+    backoff: Backoff,
+    delay: Option<Delay>,
+    response_codes: Vec<ResponseCode>,
+    different_server: bool,
+    classifier: Option<Classifier>,
+}
+
+impl RetryPolicy {
+    /// Builder for a RetryPolicy, starting with the defaults
+    pub fn builder() -> RetryPolicyBuilder {
+        RetryPolicyBuilder(Self::default())
+    }
+
+    /// The number of times a request is reattempted
+    pub fn attempts(&self) -> usize {
+        self.attempts
+    }
+
+    /// The time to wait before reattempting a request
+    pub fn backoff(&self) -> Backoff {
+        self.backoff
+    }
+
+    /// The response codes for which a request is reattempted
+    pub fn response_codes(&self) -> &[ResponseCode] {
+        &self.response_codes
+    }
+
+    /// Whether a request is reattempted with a different name server
+    pub fn retry_on_different_server(&self) -> bool {
+        self.different_server
+    }
+
+    /// Whether a request which failed with the error should be reattempted
+    ///
+    /// The hook set with [`RetryPolicyBuilder::classify`] is asked first, then errors for the
+    ///  response codes of the policy are retried. Otherwise the error decides by its
+    ///  [`RetryableError`] implementation.
+    pub fn classify(&self, error: &ProtoError) -> RetryDecision {
+        if let Some(decision) = self
+            .classifier
+            .as_ref()
+            .and_then(|classify| classify(error))
+        {
+            return decision;
+        }
+
+        match error.kind() {
+            ProtoErrorKind::NoRecordsFound { response_code, .. }
+                if self.response_codes.contains(response_code) =>
+            {
+                RetryDecision::Retry
+            }
+            _ if !error.should_retry() => RetryDecision::Fail,
+            _ if error.attempted() => RetryDecision::Retry,
+            _ => RetryDecision::RetryUncounted,
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 2,
+            backoff: Backoff::None,
+            delay: None,
+            response_codes: vec![],
+            different_server: false,
+            classifier: None,
+        }
+    }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("attempts", &self.attempts)
+            .field("backoff", &self.backoff)
+            .field("response_codes", &self.response_codes)
+            .field("different_server", &self.different_server)
+            .field("classifier", &self.classifier.is_some())
+            .finish()
+    }
+}
+
+/// A builder for a [`RetryPolicy`]
+#[derive(Clone, Debug)]
+pub struct RetryPolicyBuilder(RetryPolicy);
+
+impl RetryPolicyBuilder {
+    /// The number of times a request is reattempted, `2` by default
+    pub fn attempts(mut self, attempts: usize) -> Self {
+        self.0.attempts = attempts;
+        self
+    }
+
+    /// The time to wait before reattempting a request, waiting with the timer of the runtime
+    pub fn backoff<T: Time>(mut self, backoff: Backoff) -> Self {
+        self.0.backoff = backoff;
+        self.0.delay = Some(|duration| T::delay_for(duration));
+        self
+    }
+
+    /// The response codes for which a request is reattempted, e.g. `SERVFAIL` or `REFUSED`
+    ///
+    /// Responses with these codes are errors which are not retried by default.
+    pub fn retry_response_codes(mut self, codes: impl IntoIterator<Item = ResponseCode>) -> Self {
+        self.0.response_codes = codes.into_iter().collect();
+        self
+    }
+
+    /// Whether a request is reattempted with a different name server, if the handle queries
+    ///  multiple name servers, see [`DnsRequestOptions::skip_servers`]
+    ///
+    /// [`DnsRequestOptions::skip_servers`]: crate::xfer::DnsRequestOptions::skip_servers
+    pub fn retry_on_different_server(mut self, different_server: bool) -> Self {
+        self.0.different_server = different_server;
+        self
+    }
+
+    /// A hook to classify errors, e.g. custom errors of a handle
+    ///
+    /// The hook returns `None` for errors which are classified by the policy.
+    pub fn classify<F>(mut self, classifier: F) -> Self
+    where
+        F: Fn(&ProtoError) -> Option<RetryDecision> + Send + Sync + 'static,
+    {
+        self.0.classifier = Some(Arc::new(classifier));
+        self
+    }
+
+    /// Returns the policy
+    pub fn build(self) -> RetryPolicy {
+        self.0
+    }
+}
+
 /// What errors should be retried
 pub trait RetryableError {
     /// Whether the query should be retried after this error
@@ -189,6 +438,110 @@ mod test {
         assert_eq!(result.id(), 1); // this is checking the number of iterations the TestClient ran
     }
 
+    #[derive(Clone)]
+    struct ServFailClient {
+        skipped: Arc<std::sync::Mutex<Vec<usize>>>,
+    }
+
+    impl DnsHandle for ServFailClient {
+        type Response = Box<dyn Stream<Item = Result<DnsResponse, ProtoError>> + Send + Unpin>;
+
+        fn send<R: Into<DnsRequest>>(&self, request: R) -> Self::Response {
+            let request = request.into();
+            self.skipped
+                .lock()
+                .unwrap()
+                .push(request.options().skip_servers);
+
+            let mut message = Message::new();
+            message
+                .set_message_type(MessageType::Response)
+                .set_response_code(ResponseCode::ServFail);
+            let response = DnsResponse::from_message(message).unwrap();
+            Box::new(once(ready(ProtoError::from_response(response, false))))
+        }
+    }
+
+    fn send_servfail(policy: RetryPolicy) -> Vec<usize> {
+        let client = ServFailClient {
+            skipped: Arc::new(std::sync::Mutex::new(vec![])),
+        };
+        let handle = RetryDnsHandle::with_policy(client.clone(), policy);
+        assert!(block_on(handle.send(Message::new()).first_answer()).is_err());
+
+        let skipped = client.skipped.lock().unwrap().clone();
+        skipped
+    }
+
+    #[test]
+    fn test_retry_response_codes() {
+        assert_eq!(send_servfail(RetryPolicy::default()), vec![0]);
+
+        let policy = RetryPolicy::builder()
+            .attempts(2)
+            .retry_response_codes([ResponseCode::ServFail])
+            .build();
+        assert_eq!(send_servfail(policy), vec![0, 0, 0]);
+
+        let policy = RetryPolicy::builder()
+            .attempts(2)
+            .retry_response_codes([ResponseCode::ServFail])
+            .retry_on_different_server(true)
+            .build();
+        assert_eq!(send_servfail(policy), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_classify() {
+        let policy = RetryPolicy::builder()
+            .attempts(1)
+            .classify(|e| e.is_no_connections().then_some(RetryDecision::Retry))
+            .build();
+
+        assert_eq!(
+            policy.classify(&ProtoError::from(ProtoErrorKind::NoConnections)),
+            RetryDecision::Retry
+        );
+        assert_eq!(
+            policy.classify(&ProtoError::from(ProtoErrorKind::Busy)),
+            RetryDecision::RetryUncounted
+        );
+        assert_eq!(send_servfail(policy), vec![0]);
+    }
+
+    #[test]
+    fn test_backoff() {
+        let backoff = Backoff::Exponential {
+            initial: Duration::from_millis(100),
+            max: Duration::from_secs(1),
+        };
+        assert_eq!(backoff.delay(0), Duration::from_millis(100));
+        assert_eq!(backoff.delay(2), Duration::from_millis(400));
+        assert_eq!(backoff.delay(4), Duration::from_secs(1));
+        assert_eq!(backoff.delay(64), Duration::from_secs(1));
+        assert_eq!(Backoff::None.delay(3), Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_backoff() {
+        let handle = RetryDnsHandle::with_policy(
+            TestClient {
+                last_succeed: true,
+                retries: 2,
+                attempts: Arc::new(AtomicU16::new(0)),
+            },
+            RetryPolicy::builder()
+                .attempts(2)
+                .backoff::<crate::TokioTime>(Backoff::Fixed(Duration::from_millis(10)))
+                .build(),
+        );
+
+        let started = std::time::Instant::now();
+        let result = handle.send(Message::new()).first_answer().await.unwrap();
+        assert_eq!(result.id(), 2);
+        assert!(started.elapsed() >= Duration::from_millis(20));
+    }
+
     #[test]
     fn test_error() {
         let client = RetryDnsHandle::new(
@@ -0,0 +1,126 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::time::{Duration, Instant};
+
+use lru_cache::LruCache;
+use parking_lot::Mutex;
+use tracing::warn;
+
+use crate::resolver::Name;
+
+/// The failures of the name servers of a zone since its last answer
+struct Failures {
+    count: u32,
+    failing_until: Option<Instant>,
+}
+
+/// Zones whose name servers failed repeatedly, which are not queried for a while
+///
+/// This keeps unreachable or broken delegations from being queried for every client query, see
+///  `RecursorBuilder::max_delegation_failures`.
+pub(crate) struct DelegationFailures {
+    max_failures: u32,
+    ttl: Duration,
+    zones: Mutex<LruCache<Name, Failures>>,
+}
+
+impl DelegationFailures {
+    pub(crate) fn new(max_failures: u32, ttl: Duration, capacity: usize) -> Self {
+        Self {
+            max_failures,
+            ttl,
+            zones: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    /// Returns true if the name servers of the zone are not to be queried
+    pub(crate) fn is_failing(&self, zone: &Name, now: Instant) -> bool {
+        self.zones
+            .lock()
+            .get_mut(zone)
+            .and_then(|failures| failures.failing_until)
+            .map_or(false, |failing_until| now < failing_until)
+    }
+
+    /// Records a failure of the name servers of the zone
+    pub(crate) fn failed(&self, zone: &Name, now: Instant) {
+        if self.max_failures == 0 {
+            return;
+        }
+
+        let mut zones = self.zones.lock();
+        if !zones.contains_key(zone) {
+            zones.insert(
+                zone.clone(),
+                Failures {
+                    count: 0,
+                    failing_until: None,
+                },
+            );
+        }
+
+        let Some(failures) = zones.get_mut(zone) else {
+            return;
+        };
+        failures.count = failures.count.saturating_add(1);
+        if failures.count >= self.max_failures {
+            warn!(
+                "name servers of {zone} failed {} times, not querying them for {:?}",
+                failures.count, self.ttl
+            );
+            failures.failing_until = Some(now + self.ttl);
+        }
+    }
+
+    /// Records an answer of the name servers of the zone, which ends its failures
+    pub(crate) fn answered(&self, zone: &Name) {
+        self.zones.lock().remove(zone);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_delegation_failures() {
+        let failures = DelegationFailures::new(2, Duration::from_secs(30), 16);
+        let zone = Name::from_str("example.com.").unwrap();
+        let now = Instant::now();
+
+        failures.failed(&zone, now);
+        assert!(!failures.is_failing(&zone, now));
+        failures.failed(&zone, now);
+        assert!(failures.is_failing(&zone, now));
+        assert!(!failures.is_failing(&Name::from_str("example.net.").unwrap(), now));
+
+        // the zone is queried again after the ttl, and fails right away until it answers
+        let later = now + Duration::from_secs(30);
+        assert!(!failures.is_failing(&zone, later));
+        failures.failed(&zone, later);
+        assert!(failures.is_failing(&zone, later));
+
+        failures.answered(&zone);
+        assert!(!failures.is_failing(&zone, later));
+        failures.failed(&zone, later);
+        assert!(!failures.is_failing(&zone, later));
+    }
+
+    #[test]
+    fn test_disabled() {
+        let failures = DelegationFailures::new(0, Duration::from_secs(30), 16);
+        let zone = Name::root();
+        let now = Instant::now();
+        for _ in 0..10 {
+            failures.failed(&zone, now);
+        }
+        assert!(!failures.is_failing(&zone, now));
+    }
+}
@@ -0,0 +1,282 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use futures_util::StreamExt;
+use hickory_resolver::name_server::TokioConnectionProvider;
+use lru_cache::LruCache;
+use parking_lot::Mutex;
+use tracing::{debug, info};
+
+use crate::{
+    proto::{
+        error::{ProtoError, ProtoErrorKind},
+        op::{
+            update_message::MAX_PAYLOAD_LEN, Edns, Message, MessageType, OpCode, Query,
+            ResponseCode,
+        },
+        xfer::{DnsRequest, DnsRequestOptions, DnsResponse},
+        DnsHandle,
+    },
+    recursor::QueryBudget,
+    resolver::{
+        config::{NameServerConfig, Protocol, ResolverOpts},
+        error::ResolveError,
+        name_server::{GenericNameServer, GenericNameServerPool, TokioRuntimeProvider},
+        Name,
+    },
+};
+
+/// The name servers of the delegations, with what was learned about each of them
+///
+/// The servers are shared by all the zones they serve, so that their round-trip times, lameness
+///  and EDNS support are remembered when the name servers of a zone are resolved again.
+pub(crate) struct InfraCache {
+    servers: Mutex<LruCache<SocketAddr, InfraServer>>,
+    options: ResolverOpts,
+    ttl: Duration,
+}
+
+impl InfraCache {
+    pub(crate) fn new(capacity: usize, ttl: Duration, options: ResolverOpts) -> Self {
+        Self {
+            servers: Mutex::new(LruCache::new(capacity)),
+            options,
+            ttl,
+        }
+    }
+
+    /// Returns the name server at the address, which is queried over UDP and then TCP
+    pub(crate) fn server(&self, socket_addr: SocketAddr) -> InfraServer {
+        let mut servers = self.servers.lock();
+        if let Some(server) = servers.get_mut(&socket_addr) {
+            return server.clone();
+        }
+
+        let name_server = |protocol| {
+            let mut config = NameServerConfig::new(socket_addr, protocol);
+            config.trust_negative_responses = true;
+            GenericNameServer::new(
+                config,
+                self.options.clone(),
+                TokioConnectionProvider::default(),
+            )
+        };
+        let server = InfraServer {
+            socket_addr,
+            pool: GenericNameServerPool::from_nameservers(
+                self.options.clone(),
+                vec![name_server(Protocol::Udp)],
+                vec![name_server(Protocol::Tcp)],
+            ),
+            state: Arc::default(),
+            ttl: self.ttl,
+        };
+
+        servers.insert(socket_addr, server.clone());
+        server
+    }
+}
+
+/// What was learned about a name server
+#[derive(Default)]
+struct ServerState {
+    /// The zones for which the server is lame, until the instant
+    lame_zones: HashMap<Name, Instant>,
+    /// The server does not support EDNS, until the instant
+    no_edns_until: Option<Instant>,
+}
+
+/// A name server in the `InfraCache`
+#[derive(Clone)]
+pub(crate) struct InfraServer {
+    socket_addr: SocketAddr,
+    pool: GenericNameServerPool<TokioRuntimeProvider>,
+    state: Arc<Mutex<ServerState>>,
+    ttl: Duration,
+}
+
+impl InfraServer {
+    pub(crate) fn socket_addr(&self) -> SocketAddr {
+        self.socket_addr
+    }
+
+    /// The smoothed round-trip time of the queries over UDP
+    pub(crate) fn srtt(&self) -> Duration {
+        self.pool
+            .health_stats()
+            .first()
+            .map_or(Duration::ZERO, |stats| stats.srtt)
+    }
+
+    /// Returns true if the server did not answer authoritatively for the zone
+    pub(crate) fn is_lame(&self, zone: &Name, now: Instant) -> bool {
+        self.state
+            .lock()
+            .lame_zones
+            .get(zone)
+            .map_or(false, |until| now < *until)
+    }
+
+    fn mark_lame(&self, zone: &Name, now: Instant) {
+        info!("{} is lame for {zone}", self.socket_addr);
+        let mut state = self.state.lock();
+        state.lame_zones.retain(|_, until| now < *until);
+        state.lame_zones.insert(zone.clone(), now + self.ttl);
+    }
+
+    /// Returns true unless the server failed to answer queries with EDNS
+    pub(crate) fn supports_edns(&self, now: Instant) -> bool {
+        self.state
+            .lock()
+            .no_edns_until
+            .map_or(true, |until| until <= now)
+    }
+
+    fn mark_no_edns(&self, now: Instant) {
+        debug!("{} does not support EDNS", self.socket_addr);
+        self.state.lock().no_edns_until = Some(now + self.ttl);
+    }
+
+    /// Queries the server, retrying without EDNS if the server does not support it
+    ///
+    /// The DO bit is set with `dnssec_ok`, so that the server includes the RRSIGs of the records,
+    ///  as long as it supports EDNS.
+    ///
+    /// The server is marked as lame for the zone if it refuses the query, fails, or answers
+    ///  without authority and without a referral. The query and its retry are taken from the
+    ///  budget.
+    pub(crate) async fn lookup(
+        &self,
+        zone: &Name,
+        query: Query,
+        dnssec_ok: bool,
+        now: Instant,
+        budget: &QueryBudget,
+    ) -> Result<DnsResponse, ResolveError> {
+        let mut options = DnsRequestOptions::default();
+        options.recursion_desired = false;
+        options.use_edns = self.supports_edns(now);
+
+        loop {
+            budget.spend(&query)?;
+            let response = self
+                .pool
+                .send(request(query.clone(), options, dnssec_ok))
+                .into_future()
+                .await
+                .0
+                .unwrap_or_else(|| Err(ProtoError::from("no response from nameserver")));
+
+            match response {
+                Ok(response) => {
+                    if !response.authoritative()
+                        && response.name_servers().is_empty()
+                        && !response.answers().is_empty()
+                    {
+                        self.mark_lame(zone, now);
+                        return Err(ResolveError::from("lame response"));
+                    }
+
+                    return Ok(response);
+                }
+                Err(e) => {
+                    match e.kind() {
+                        ProtoErrorKind::NoRecordsFound {
+                            response_code: ResponseCode::FormErr,
+                            ..
+                        } if options.use_edns => {
+                            self.mark_no_edns(now);
+                            options.use_edns = false;
+                            continue;
+                        }
+                        ProtoErrorKind::NoRecordsFound {
+                            response_code:
+                                ResponseCode::Refused | ResponseCode::ServFail | ResponseCode::NotAuth,
+                            ..
+                        } => self.mark_lame(zone, now),
+                        _ => (),
+                    }
+
+                    return Err(e.into());
+                }
+            }
+        }
+    }
+}
+
+/// Builds the request for the query without recursion, as `DnsHandle::lookup` does, with the DO bit
+fn request(query: Query, options: DnsRequestOptions, dnssec_ok: bool) -> DnsRequest {
+    let mut message = Message::new();
+    message
+        .add_query(query)
+        .set_message_type(MessageType::Query)
+        .set_op_code(OpCode::Query)
+        .set_recursion_desired(options.recursion_desired);
+
+    if options.use_edns {
+        message
+            .extensions_mut()
+            .get_or_insert_with(Edns::new)
+            .set_max_payload(MAX_PAYLOAD_LEN)
+            .set_version(0)
+            .set_dnssec_ok(dnssec_ok);
+    }
+
+    DnsRequest::new(message, options)
+}
+
+/// Returns true if the error is an answer of the name server, which is not to be retried at the
+///  other name servers of the zone
+pub(crate) fn is_negative_answer(error: &ResolveError) -> bool {
+    matches!(
+        error.proto().map(ProtoError::kind),
+        Some(ProtoErrorKind::NoRecordsFound {
+            response_code: ResponseCode::NXDomain | ResponseCode::NoError,
+            ..
+        })
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_infra_cache() {
+        let cache = InfraCache::new(16, Duration::from_secs(900), ResolverOpts::default());
+        let socket_addr = SocketAddr::from(([192, 0, 2, 1], 53));
+        let server = cache.server(socket_addr);
+        let zone = Name::from_str("example.com.").unwrap();
+        let now = Instant::now();
+
+        assert!(!server.is_lame(&zone, now));
+        assert!(server.supports_edns(now));
+        server.mark_lame(&zone, now);
+        server.mark_no_edns(now);
+
+        // what was learned is shared with the zones which use the same server
+        let server = cache.server(socket_addr);
+        assert_eq!(server.socket_addr(), socket_addr);
+        assert!(server.is_lame(&zone, now));
+        assert!(!server.is_lame(&Name::from_str("example.net.").unwrap(), now));
+        assert!(!server.supports_edns(now));
+
+        // and forgotten after the ttl
+        let later = now + Duration::from_secs(900);
+        assert!(!server.is_lame(&zone, later));
+        assert!(server.supports_edns(later));
+    }
+}
@@ -26,12 +26,22 @@
 #![recursion_limit = "2048"]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+mod delegation_failures;
 pub mod error;
+mod infra_cache;
+mod priming;
 mod recursor;
 pub(crate) mod recursor_pool;
+#[cfg(feature = "root-zone-mirror")]
+mod root_mirror;
+#[cfg(feature = "dnssec")]
+mod validation;
 
 pub use error::{Error, ErrorKind};
 pub use hickory_proto as proto;
 pub use hickory_resolver as resolver;
 pub use hickory_resolver::config::NameServerConfig;
-pub use recursor::Recursor;
+pub use priming::root_hints;
+pub use recursor::{Recursor, RecursorBuilder};
+#[cfg(feature = "root-zone-mirror")]
+pub use root_mirror::root_zone_servers;
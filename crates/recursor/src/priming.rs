@@ -0,0 +1,120 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Priming of the root name servers, see
+//!  [RFC 8109](https://datatracker.ietf.org/doc/html/rfc8109)
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::resolver::config::NameServerConfigGroup;
+#[cfg(feature = "dnssec")]
+use crate::{
+    proto::rr::{dnssec::TrustAnchor, Record, RecordType},
+    validation::{is_signed, trusted_keys},
+    Error,
+};
+
+/// The addresses of the root name servers, a. to m.root-servers.net., as of November 2023
+const ROOT_HINTS: [IpAddr; 26] = [
+    IpAddr::V4(Ipv4Addr::new(198, 41, 0, 4)),
+    IpAddr::V6(Ipv6Addr::new(0x2001, 0x503, 0xba3e, 0, 0, 0, 0x2, 0x30)),
+    IpAddr::V4(Ipv4Addr::new(170, 247, 170, 2)),
+    IpAddr::V6(Ipv6Addr::new(0x2801, 0x1b8, 0x10, 0, 0, 0, 0, 0xb)),
+    IpAddr::V4(Ipv4Addr::new(192, 33, 4, 12)),
+    IpAddr::V6(Ipv6Addr::new(0x2001, 0x500, 0x2, 0, 0, 0, 0, 0xc)),
+    IpAddr::V4(Ipv4Addr::new(199, 7, 91, 13)),
+    IpAddr::V6(Ipv6Addr::new(0x2001, 0x500, 0x2d, 0, 0, 0, 0, 0xd)),
+    IpAddr::V4(Ipv4Addr::new(192, 203, 230, 10)),
+    IpAddr::V6(Ipv6Addr::new(0x2001, 0x500, 0xa8, 0, 0, 0, 0, 0xe)),
+    IpAddr::V4(Ipv4Addr::new(192, 5, 5, 241)),
+    IpAddr::V6(Ipv6Addr::new(0x2001, 0x500, 0x2f, 0, 0, 0, 0, 0xf)),
+    IpAddr::V4(Ipv4Addr::new(192, 112, 36, 4)),
+    IpAddr::V6(Ipv6Addr::new(0x2001, 0x500, 0x12, 0, 0, 0, 0, 0xd0d)),
+    IpAddr::V4(Ipv4Addr::new(198, 97, 190, 53)),
+    IpAddr::V6(Ipv6Addr::new(0x2001, 0x500, 0x1, 0, 0, 0, 0, 0x53)),
+    IpAddr::V4(Ipv4Addr::new(192, 36, 148, 17)),
+    IpAddr::V6(Ipv6Addr::new(0x2001, 0x7fe, 0, 0, 0, 0, 0, 0x53)),
+    IpAddr::V4(Ipv4Addr::new(192, 58, 128, 30)),
+    IpAddr::V6(Ipv6Addr::new(0x2001, 0x503, 0xc27, 0, 0, 0, 0x2, 0x30)),
+    IpAddr::V4(Ipv4Addr::new(193, 0, 14, 129)),
+    IpAddr::V6(Ipv6Addr::new(0x2001, 0x7fd, 0, 0, 0, 0, 0, 0x1)),
+    IpAddr::V4(Ipv4Addr::new(199, 7, 83, 42)),
+    IpAddr::V6(Ipv6Addr::new(0x2001, 0x500, 0x9f, 0, 0, 0, 0, 0x42)),
+    IpAddr::V4(Ipv4Addr::new(202, 12, 27, 33)),
+    IpAddr::V6(Ipv6Addr::new(0x2001, 0xdc3, 0, 0, 0, 0, 0, 0x35)),
+];
+
+/// The built-in root hints, the addresses of the root name servers
+///
+/// The hints only need to contain some of the current root name servers, the others are learned by
+///  priming, see `Recursor::prime`. Custom hints, e.g. from the `named.root` file of IANA, are
+///  needed to resolve from other roots.
+pub fn root_hints() -> NameServerConfigGroup {
+    NameServerConfigGroup::from_ips_clear(&ROOT_HINTS, 53, false)
+}
+
+/// Verifies the NS records of the priming response with the DNSKEYs of the root zone, which are
+///  verified with the trust anchor
+///
+/// `now` is the current time in seconds since the unix epoch, in which the signatures must be
+///  valid.
+#[cfg(feature = "dnssec")]
+pub(crate) fn verify_priming(
+    ns_answers: &[Record],
+    dnskey_answers: &[Record],
+    trust_anchor: &TrustAnchor,
+    now: u32,
+) -> Result<(), Error> {
+    let keys = trusted_keys(
+        &rrset(dnskey_answers, RecordType::DNSKEY),
+        &rrset(dnskey_answers, RecordType::RRSIG),
+        trust_anchor,
+        now,
+    )?;
+
+    if !is_signed(
+        &rrset(ns_answers, RecordType::NS),
+        &rrset(ns_answers, RecordType::RRSIG),
+        &keys,
+        now,
+    ) {
+        return Err(Error::from(
+            "the NS records of the root zone are not signed by its DNSKEYs",
+        ));
+    }
+
+    Ok(())
+}
+
+/// The records of the type at the root
+#[cfg(feature = "dnssec")]
+fn rrset(answers: &[Record], record_type: RecordType) -> Vec<&Record> {
+    answers
+        .iter()
+        .filter(|record| record.record_type() == record_type && record.name().is_root())
+        .collect()
+}
+
+#[cfg(all(test, feature = "dnssec"))]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::proto::rr::{rdata::NS, Name, RData};
+
+    #[test]
+    fn test_verify_priming_unsigned() {
+        let ns = Record::from_rdata(
+            Name::root(),
+            518400,
+            RData::NS(NS(Name::from_str("a.root-servers.net.").unwrap())),
+        );
+
+        let err = verify_priming(&[ns], &[], &TrustAnchor::default(), 0).unwrap_err();
+        assert!(err.to_string().contains("DNSKEYs"), "{err}");
+    }
+}
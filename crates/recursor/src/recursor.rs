@@ -5,7 +5,14 @@
 // https://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
-use std::{net::SocketAddr, time::Instant};
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
 use async_recursion::async_recursion;
 use futures_util::{future::select_all, FutureExt};
@@ -17,14 +24,19 @@ use tracing::{debug, info, warn};
 #[cfg(test)]
 use std::str::FromStr;
 
+#[cfg(feature = "root-zone-mirror")]
+use crate::root_mirror::RootMirror;
 use crate::{
+    delegation_failures::DelegationFailures,
+    infra_cache::{InfraCache, InfraServer},
     proto::{
+        error::{ProtoError, ProtoErrorKind},
         op::Query,
-        rr::{RData, RecordType},
+        rr::{RData, Record, RecordType},
     },
     recursor_pool::RecursorPool,
     resolver::{
-        config::{NameServerConfig, NameServerConfigGroup, Protocol, ResolverOpts},
+        config::{NameServerConfigGroup, ResolverOpts},
         dns_lru::{DnsLru, TtlConfig},
         error::ResolveError,
         lookup::Lookup,
@@ -33,22 +45,212 @@ use crate::{
     },
     Error, ErrorKind,
 };
+#[cfg(feature = "dnssec")]
+use crate::{priming::verify_priming, proto::rr::dnssec::TrustAnchor, validation::unix_now};
+
+/// Set of nameservers by the zone name, with the instant until which their records are valid
+type NameServerCache<P> = LruCache<Name, (RecursorPool<P>, Instant)>;
+
+/// A builder for the `Recursor`, with the limits which bound the work done for each query
+///
+/// The limits protect the recursor, and the name servers it queries, from delegations which are
+///  broken or crafted to amplify the queries, e.g. the
+///  [NXNSAttack](https://www.nxnsattack.com/).
+#[derive(Clone, Copy, Debug)]
+pub struct RecursorBuilder {
+    ns_cache_size: usize,
+    record_cache_size: usize,
+    max_ns_names_per_delegation: usize,
+    max_upstream_queries: usize,
+    max_delegation_failures: u32,
+    delegation_failure_ttl: Duration,
+    infra_cache_size: usize,
+    infra_cache_ttl: Duration,
+    #[cfg(feature = "dnssec")]
+    dnssec_validation: bool,
+}
+
+impl Default for RecursorBuilder {
+    fn default() -> Self {
+        Self {
+            ns_cache_size: 1024,
+            record_cache_size: 1048576,
+            max_ns_names_per_delegation: 5,
+            max_upstream_queries: 100,
+            max_delegation_failures: 3,
+            delegation_failure_ttl: Duration::from_secs(30),
+            infra_cache_size: 10000,
+            infra_cache_ttl: Duration::from_secs(900),
+            #[cfg(feature = "dnssec")]
+            dnssec_validation: false,
+        }
+    }
+}
+
+impl RecursorBuilder {
+    /// The number of zones whose name servers are cached, defaults to 1024
+    pub fn ns_cache_size(mut self, size: usize) -> Self {
+        self.ns_cache_size = size;
+        self
+    }
+
+    /// The number of records which are cached, defaults to 1048576
+    pub fn record_cache_size(mut self, size: usize) -> Self {
+        self.record_cache_size = size;
+        self
+    }
+
+    /// The number of name servers without glue whose addresses are resolved for a delegation,
+    ///  one after the other until one of them has addresses. Defaults to 5.
+    ///
+    /// The other name servers of the delegation are ignored, so that a delegation to many names
+    ///  can not make the recursor query for all of them.
+    pub fn max_ns_names_per_delegation(mut self, max: usize) -> Self {
+        self.max_ns_names_per_delegation = max;
+        self
+    }
 
-/// Set of nameservers by the zone name
-type NameServerCache<P> = LruCache<Name, RecursorPool<P>>;
+    /// The number of queries sent to name servers for a client query, including those for the
+    ///  delegations and the addresses of name servers, and the retries at the other name servers
+    ///  of a delegation. The client query fails once they are exceeded. Defaults to 100.
+    pub fn max_upstream_queries(mut self, max: usize) -> Self {
+        self.max_upstream_queries = max;
+        self
+    }
+
+    /// The number of consecutive failures of the name servers of a zone, e.g. timeouts, after
+    ///  which queries for the zone fail right away for the `delegation_failure_ttl`.
+    ///  Defaults to 3, 0 disables this.
+    pub fn max_delegation_failures(mut self, max: u32) -> Self {
+        self.max_delegation_failures = max;
+        self
+    }
+
+    /// The time for which queries for a zone fail right away, once its name servers failed
+    ///  `max_delegation_failures` times. Defaults to 30 seconds.
+    pub fn delegation_failure_ttl(mut self, ttl: Duration) -> Self {
+        self.delegation_failure_ttl = ttl;
+        self
+    }
+
+    /// The number of name servers of the delegations which are remembered, along with their
+    ///  round-trip times, lameness and EDNS support. Defaults to 10000.
+    pub fn infra_cache_size(mut self, size: usize) -> Self {
+        self.infra_cache_size = size;
+        self
+    }
+
+    /// The time for which a name server which answered without authority for a zone is not
+    ///  queried for it, and one which failed queries with EDNS is queried without it.
+    ///  Defaults to 15 minutes.
+    pub fn infra_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.infra_cache_ttl = ttl;
+        self
+    }
+
+    /// Validates the root name servers of the priming response with DNSSEC, against the trust
+    ///  anchor of the root zone. Priming fails if the NS records of the root zone are not signed,
+    ///  the root hints are then queried instead. Defaults to false.
+    #[cfg(feature = "dnssec")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "dnssec")))]
+    pub fn dnssec_validation(mut self, validate: bool) -> Self {
+        self.dnssec_validation = validate;
+        self
+    }
+
+    /// Construct a new recursor using the list of NameServerConfigs for the root node list
+    ///
+    /// The roots are the root hints, which are primed on the first query or with `Recursor::prime`.
+    ///  Only their addresses are used, they are queried over UDP and TCP, see `root_hints`.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the roots are empty.
+    pub fn build(self, roots: impl Into<NameServerConfigGroup>) -> Result<Recursor, ResolveError> {
+        // configure the hickory-resolver
+        let roots: NameServerConfigGroup = roots.into();
+
+        assert!(!roots.is_empty(), "roots must not be empty");
+
+        debug!(
+            "Using cache sizes {}/{}",
+            self.ns_cache_size, self.record_cache_size
+        );
+        let opts = recursor_opts();
+        let infra_cache =
+            InfraCache::new(self.infra_cache_size, self.infra_cache_ttl, opts.clone());
+        let mut hints: Vec<InfraServer> = Vec::new();
+        for config in roots.iter() {
+            if hints
+                .iter()
+                .all(|hint| hint.socket_addr() != config.socket_addr)
+            {
+                hints.push(infra_cache.server(config.socket_addr));
+            }
+        }
+        let hints = RecursorPool::delegation(Name::root(), hints);
+        #[cfg(feature = "dnssec")]
+        let hints = if self.dnssec_validation {
+            hints.with_dnssec_ok()
+        } else {
+            hints
+        };
+        // the hints are used until the roots are primed, on the first query
+        let roots = Mutex::new((hints.clone(), Instant::now()));
+        let name_server_cache = Mutex::new(NameServerCache::new(self.ns_cache_size));
+        let record_cache = DnsLru::new(self.record_cache_size, TtlConfig::default());
+        let delegation_failures = DelegationFailures::new(
+            self.max_delegation_failures,
+            self.delegation_failure_ttl,
+            self.ns_cache_size,
+        );
+
+        Ok(Recursor {
+            hints,
+            roots,
+            priming_retry: self.delegation_failure_ttl,
+            #[cfg(feature = "dnssec")]
+            dnssec_validation: self.dnssec_validation,
+            configured_zones: Vec::new(),
+            #[cfg(feature = "root-zone-mirror")]
+            root_mirror: None,
+            name_server_cache,
+            record_cache,
+            delegation_failures,
+            infra_cache,
+            max_ns_names_per_delegation: self.max_ns_names_per_delegation,
+            max_upstream_queries: self.max_upstream_queries,
+        })
+    }
+}
 
 /// A top down recursive resolver which operates off a list of roots for initial recursive requests.
 ///
 /// This is the well known root nodes, referred to as hints in RFCs. See the IANA [Root Servers](https://www.iana.org/domains/root/servers) list.
 pub struct Recursor {
-    roots: RecursorPool<TokioRuntimeProvider>,
+    hints: RecursorPool<TokioRuntimeProvider>,
+    /// The primed root name servers, or the hints if priming failed, until the instant
+    roots: Mutex<(RecursorPool<TokioRuntimeProvider>, Instant)>,
+    priming_retry: Duration,
+    #[cfg(feature = "dnssec")]
+    dnssec_validation: bool,
+    /// Forward and stub zones, the most specific first
+    configured_zones: Vec<RecursorPool<TokioRuntimeProvider>>,
+    #[cfg(feature = "root-zone-mirror")]
+    root_mirror: Option<Arc<RootMirror>>,
     name_server_cache: Mutex<NameServerCache<TokioRuntimeProvider>>,
     record_cache: DnsLru,
+    delegation_failures: DelegationFailures,
+    infra_cache: InfraCache,
+    max_ns_names_per_delegation: usize,
+    max_upstream_queries: usize,
 }
 
 impl Recursor {
     /// Construct a new recursor using the list of NameServerConfigs for the root node list
     ///
+    /// The other limits have their defaults, see `RecursorBuilder`.
+    ///
     /// # Panics
     ///
     /// This will panic if the roots are empty.
@@ -57,24 +259,133 @@ impl Recursor {
         ns_cache_size: usize,
         record_cache_size: usize,
     ) -> Result<Self, ResolveError> {
-        // configure the hickory-resolver
-        let roots: NameServerConfigGroup = roots.into();
+        Self::builder()
+            .ns_cache_size(ns_cache_size)
+            .record_cache_size(record_cache_size)
+            .build(roots)
+    }
 
-        assert!(!roots.is_empty(), "roots must not be empty");
+    /// Returns a builder for the recursor, see `RecursorBuilder`
+    pub fn builder() -> RecursorBuilder {
+        RecursorBuilder::default()
+    }
 
-        debug!("Using cache sizes {}/{}", ns_cache_size, record_cache_size);
-        let opts = recursor_opts();
-        let roots =
-            GenericNameServerPool::from_config(roots, opts, TokioConnectionProvider::default());
-        let roots = RecursorPool::from(Name::root(), roots);
-        let name_server_cache = Mutex::new(NameServerCache::new(ns_cache_size));
-        let record_cache = DnsLru::new(record_cache_size, TtlConfig::default());
+    /// Primes the root name servers, by querying the root hints for the NS records of the root zone
+    ///
+    /// See [RFC 8109](https://datatracker.ietf.org/doc/html/rfc8109). The root name servers
+    ///  are primed again once their records expire, on the next query which needs them. If priming
+    ///  fails, the root hints are queried instead until it is retried.
+    pub async fn prime(&self) -> Result<(), Error> {
+        let budget = QueryBudget::new(self.max_upstream_queries);
+        self.prime_roots(Instant::now(), &budget).await.map(drop)
+    }
 
-        Ok(Self {
-            roots,
-            name_server_cache,
-            record_cache,
-        })
+    /// Answers the queries to the root name servers from a local copy of the root zone, which is
+    ///  transferred from the given servers
+    ///
+    /// See [RFC 8806](https://datatracker.ietf.org/doc/html/rfc8806), the servers of ICANN are
+    ///  returned by `root_zone_servers`. The copy is only used once it is validated with DNSSEC,
+    ///  and until the expire time of its SOA record if it could not be refreshed. The root name
+    ///  servers are queried otherwise. The zone is transferred on the first query, or with
+    ///  `Recursor::refresh_root_zone`.
+    #[cfg(feature = "root-zone-mirror")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "root-zone-mirror")))]
+    pub fn with_root_zone_mirror(mut self, servers: impl IntoIterator<Item = SocketAddr>) -> Self {
+        self.root_mirror = Some(Arc::new(RootMirror::new(servers.into_iter().collect())));
+        self
+    }
+
+    /// Transfers the root zone, if it changed since the last transfer, see
+    ///  `Recursor::with_root_zone_mirror`
+    ///
+    /// Returns an error if there is no local copy of the root zone, or if the transfer or its
+    ///  validation failed at all servers.
+    #[cfg(feature = "root-zone-mirror")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "root-zone-mirror")))]
+    pub async fn refresh_root_zone(&self) -> Result<(), Error> {
+        match &self.root_mirror {
+            Some(mirror) => mirror.refresh().await,
+            None => Err(Error::from("no local copy of the root zone is configured")),
+        }
+    }
+
+    /// Forwards all queries for names in the zone to the given recursive resolvers, instead of
+    ///  resolving them from the roots
+    ///
+    /// This is the `forward-zone` of unbound. The answers of the resolvers are trusted, and may
+    ///  contain records outside of the zone, e.g. the targets of CNAMEs. The most specific forward
+    ///  or stub zone for a name is used.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the name servers are empty.
+    pub fn with_forward_zone(
+        self,
+        zone: Name,
+        name_servers: impl Into<NameServerConfigGroup>,
+    ) -> Self {
+        self.with_configured_zone(zone, name_servers.into(), true)
+    }
+
+    /// Uses the given authoritative name servers for the zone, instead of the ones delegated to by
+    ///  the parent zone
+    ///
+    /// This is the `stub-zone` of unbound. The name servers are queried without recursion, so
+    ///  delegations below the zone are still followed. The most specific forward or stub zone for
+    ///  a name is used.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the name servers are empty.
+    pub fn with_stub_zone(
+        self,
+        zone: Name,
+        name_servers: impl Into<NameServerConfigGroup>,
+    ) -> Self {
+        self.with_configured_zone(zone, name_servers.into(), false)
+    }
+
+    fn with_configured_zone(
+        mut self,
+        mut zone: Name,
+        name_servers: NameServerConfigGroup,
+        forwarding: bool,
+    ) -> Self {
+        assert!(
+            !name_servers.is_empty(),
+            "name servers for {zone} must not be empty"
+        );
+
+        zone.set_fqdn(true);
+        debug!(
+            "using {} for {zone}",
+            if forwarding { "forwarders" } else { "stub" }
+        );
+
+        let ns = GenericNameServerPool::from_config(
+            name_servers,
+            recursor_opts(),
+            TokioConnectionProvider::default(),
+        );
+        let pool = if forwarding {
+            RecursorPool::forwarding(zone, ns)
+        } else {
+            RecursorPool::from(zone, ns)
+        };
+
+        self.configured_zones
+            .retain(|configured| configured.zone() != pool.zone());
+        self.configured_zones.push(pool);
+        self.configured_zones
+            .sort_by_key(|pool| std::cmp::Reverse(pool.zone().num_labels()));
+        self
+    }
+
+    /// Returns the most specific forward or stub zone containing the name
+    fn configured_zone(&self, name: &Name) -> Option<&RecursorPool<TokioRuntimeProvider>> {
+        self.configured_zones
+            .iter()
+            .find(|pool| pool.zone().zone_of(name))
     }
 
     /// Perform a recursive resolution
@@ -239,10 +550,30 @@ impl Recursor {
     /// contiguous zone at ISI.EDU.
     /// ```
     pub async fn resolve(&self, query: Query, request_time: Instant) -> Result<Lookup, Error> {
+        let budget = QueryBudget::new(self.max_upstream_queries);
+        self.resolve_with_budget(query, request_time, &budget).await
+    }
+
+    async fn resolve_with_budget(
+        &self,
+        query: Query,
+        request_time: Instant,
+        budget: &QueryBudget,
+    ) -> Result<Lookup, Error> {
         if let Some(lookup) = self.record_cache.get(&query, request_time) {
             return lookup.map_err(Into::into);
         }
 
+        if let Some(forward) = self
+            .configured_zone(query.name())
+            .filter(|pool| pool.is_forwarding())
+        {
+            debug!("forwarding {} to resolvers for {}", query, forward.zone());
+            return self
+                .lookup(query, forward.clone(), request_time, budget)
+                .await;
+        }
+
         // not in cache, let's look for an ns record for lookup
         let zone = match query.query_type() {
             RecordType::NS => query.name().base_name(),
@@ -255,7 +586,10 @@ impl Recursor {
 
         // max number of forwarding processes
         'max_forward: for _ in 0..20 {
-            match self.ns_pool_for_zone(zone.clone(), request_time).await {
+            match self
+                .ns_pool_for_zone(zone.clone(), request_time, budget)
+                .await
+            {
                 Ok(found) => {
                     // found the nameserver
                     ns = Some(found);
@@ -280,7 +614,7 @@ impl Recursor {
         let ns = ns.ok_or_else(|| Error::from(format!("no nameserver found for {zone}")))?;
         debug!("found zone {} for {}", ns.zone(), query);
 
-        let response = self.lookup(query, ns, request_time).await?;
+        let response = self.lookup(query, ns, request_time, budget).await?;
         Ok(response)
     }
 
@@ -289,37 +623,69 @@ impl Recursor {
         query: Query,
         ns: RecursorPool<TokioRuntimeProvider>,
         now: Instant,
+        budget: &QueryBudget,
     ) -> Result<Lookup, Error> {
         if let Some(lookup) = self.record_cache.get(&query, now) {
             debug!("cached data {lookup:?}");
             return lookup.map_err(Into::into);
         }
 
-        let response = ns.lookup(query.clone());
+        if self.delegation_failures.is_failing(ns.zone(), now) {
+            return Err(Error::from(format!(
+                "name servers of {} are failing",
+                ns.zone()
+            )));
+        }
+
+        #[cfg(feature = "root-zone-mirror")]
+        if ns.zone().is_root() {
+            let mirrored = self
+                .root_mirror
+                .as_ref()
+                .and_then(|mirror| mirror.lookup(&query, now));
+            if let Some(result) = mirrored {
+                debug!("answering {query} from the local copy of the root zone");
+                let records = result?;
+                return self
+                    .record_cache
+                    .insert_records(query, records.into_iter(), now)
+                    .ok_or_else(|| Error::from("no records found"));
+            }
+        }
+
+        let response = ns.lookup(query.clone(), budget);
 
         // TODO: we are only expecting one response
         // TODO: should we change DnsHandle to always be a single response? And build a totally custom handler for other situations?
         // TODO: check if data is "authentic"
         match response.await {
             Ok(r) => {
+                self.delegation_failures.answered(ns.zone());
                 let mut r = r.into_message();
                 info!("response: {}", r.header());
-                let records = r
+                let in_bailiwick = |x: &Record| {
+                    if !is_subzone(ns.zone().clone(), x.name().clone()) {
+                        warn!(
+                            "Dropping out of bailiwick record {x} for zone {}",
+                            ns.zone().clone()
+                        );
+                        false
+                    } else {
+                        true
+                    }
+                };
+
+                // the answers of forwarders are trusted, as they resolve the full chain
+                let answers = r
                     .take_answers()
                     .into_iter()
-                    .chain(r.take_name_servers())
-                    .chain(r.take_additionals())
-                    .filter(|x| {
-                        if !is_subzone(ns.zone().clone(), x.name().clone()) {
-                            warn!(
-                                "Dropping out of bailiwick record {x} for zone {}",
-                                ns.zone().clone()
-                            );
-                            false
-                        } else {
-                            true
-                        }
-                    });
+                    .filter(|x| ns.is_forwarding() || in_bailiwick(x));
+                let records = answers.chain(
+                    r.take_name_servers()
+                        .into_iter()
+                        .chain(r.take_additionals())
+                        .filter(|x| in_bailiwick(x)),
+                );
 
                 let lookup = self.record_cache.insert_records(query, records, now);
 
@@ -327,6 +693,15 @@ impl Recursor {
             }
             Err(e) => {
                 warn!("lookup error: {e}");
+                // negative answers are answers of the name servers
+                if matches!(
+                    e.proto().map(ProtoError::kind),
+                    Some(ProtoErrorKind::NoRecordsFound { .. })
+                ) {
+                    self.delegation_failures.answered(ns.zone());
+                } else {
+                    self.delegation_failures.failed(ns.zone(), now);
+                }
                 Err(Error::from(e))
             }
         }
@@ -337,45 +712,57 @@ impl Recursor {
         &self,
         zone: Name,
         request_time: Instant,
+        budget: &QueryBudget,
     ) -> Result<RecursorPool<TokioRuntimeProvider>, Error> {
-        // TODO: need to check TTLs here.
-        if let Some(ns) = self.name_server_cache.lock().get_mut(&zone) {
-            return Ok(ns.clone());
+        if let Some(stub) = self
+            .configured_zones
+            .iter()
+            .find(|pool| !pool.is_forwarding() && pool.zone() == &zone)
+        {
+            return Ok(stub.clone());
+        }
+
+        // the name servers are resolved again once their records expired
+        if let Some((ns, valid_until)) = self.name_server_cache.lock().get_mut(&zone) {
+            if request_time < *valid_until {
+                return Ok(ns.clone());
+            }
+
+            debug!("nameservers of {zone} expired");
         };
 
+        if zone.is_root() {
+            return Ok(self.roots(request_time, budget).await);
+        }
+
         let parent_zone = zone.base_name();
 
         let nameserver_pool = if parent_zone.is_root() {
             debug!("using roots for {zone} nameservers");
-            self.roots.clone()
+            self.roots(request_time, budget).await
         } else {
-            self.ns_pool_for_zone(parent_zone, request_time).await?
+            self.ns_pool_for_zone(parent_zone, request_time, budget)
+                .await?
         };
 
-        // TODO: check for cached ns pool for this zone
-
         let lookup = Query::query(zone.clone(), RecordType::NS);
         let response = self
-            .lookup(lookup.clone(), nameserver_pool.clone(), request_time)
+            .lookup(
+                lookup.clone(),
+                nameserver_pool.clone(),
+                request_time,
+                budget,
+            )
             .await?;
 
-        // let zone_nameservers = response.name_servers();
-        // let glue = response.additionals();
-
-        // TODO: grab TTL and use for cache
-        // get all the NS records and glue
-        let mut config_group = NameServerConfigGroup::new();
+        // get all the NS records and glue, the name servers are valid as long as all of them
+        let mut servers = Vec::new();
+        let mut valid_until = response.valid_until();
         let mut need_ips_for_names = Vec::new();
 
         // unpack all glued records
         for zns in response.record_iter() {
             if let Some(ns_data) = zns.data().and_then(RData::as_ns) {
-                // let glue_ips = glue
-                //     .iter()
-                //     .filter(|g| g.name() == ns_data)
-                //     .filter_map(Record::data)
-                //     .filter_map(RData::to_ip_addr);
-
                 if !is_subzone(zone.base_name().clone(), zns.name().clone()) {
                     warn!(
                         "Dropping out of bailiwick record for {:?} with parent {:?}",
@@ -385,35 +772,15 @@ impl Recursor {
                     continue;
                 }
 
-                let cached_a = self.record_cache.get(
-                    &Query::query(ns_data.0.clone(), RecordType::A),
-                    request_time,
-                );
-                let cached_aaaa = self.record_cache.get(
-                    &Query::query(ns_data.0.clone(), RecordType::AAAA),
-                    request_time,
-                );
-
-                let cached_a = cached_a.and_then(Result::ok).map(Lookup::into_iter);
-                let cached_aaaa = cached_aaaa.and_then(Result::ok).map(Lookup::into_iter);
-
-                let glue_ips = cached_a
-                    .into_iter()
-                    .flatten()
-                    .chain(cached_aaaa.into_iter().flatten())
-                    .filter_map(|r| RData::ip_addr(&r));
-
                 let mut had_glue = false;
-                for ip in glue_ips {
-                    let mut udp = NameServerConfig::new(SocketAddr::from((ip, 53)), Protocol::Udp);
-                    let mut tcp = NameServerConfig::new(SocketAddr::from((ip, 53)), Protocol::Tcp);
-
-                    udp.trust_negative_responses = true;
-                    tcp.trust_negative_responses = true;
-
-                    config_group.push(udp);
-                    config_group.push(tcp);
-                    had_glue = true;
+                for record_type in [RecordType::A, RecordType::AAAA] {
+                    let cached = self
+                        .record_cache
+                        .get(&Query::query(ns_data.0.clone(), record_type), request_time);
+                    if let Some(Ok(glue)) = cached {
+                        had_glue |= glue.iter().any(|r| RData::ip_addr(r).is_some());
+                        self.add_servers(&mut servers, &mut valid_until, &glue);
+                    }
                 }
 
                 if !had_glue {
@@ -423,21 +790,34 @@ impl Recursor {
             }
         }
 
-        // collect missing IP addresses, select over them all, get the addresses
-        // make it configurable to query for all records?
-        if config_group.is_empty() && !need_ips_for_names.is_empty() {
-            debug!("need glue for {}", zone);
-            let a_resolves = need_ips_for_names.iter().take(1).map(|name| {
-                let a_query = Query::query(name.0.clone(), RecordType::A);
-                self.resolve(a_query, request_time).boxed()
-            });
+        // collect missing IP addresses, until one of the name servers has addresses. The number of
+        //  names is limited, so that a delegation to many names can not cause many queries
+        if need_ips_for_names.len() > self.max_ns_names_per_delegation {
+            debug!(
+                "resolving at most {} of the {} name servers of {} without glue",
+                self.max_ns_names_per_delegation,
+                need_ips_for_names.len(),
+                zone
+            );
+        }
 
-            let aaaa_resolves = need_ips_for_names.iter().take(1).map(|name| {
-                let aaaa_query = Query::query(name.0.clone(), RecordType::AAAA);
-                self.resolve(aaaa_query, request_time).boxed()
-            });
+        for name in need_ips_for_names
+            .iter()
+            .take(self.max_ns_names_per_delegation)
+        {
+            if !servers.is_empty() {
+                break;
+            }
 
-            let mut a_resolves: Vec<_> = a_resolves.chain(aaaa_resolves).collect();
+            debug!("need glue for {}", zone);
+            let a_query = Query::query(name.0.clone(), RecordType::A);
+            let aaaa_query = Query::query(name.0.clone(), RecordType::AAAA);
+            let mut a_resolves = vec![
+                self.resolve_with_budget(a_query, request_time, budget)
+                    .boxed(),
+                self.resolve_with_budget(aaaa_query, request_time, budget)
+                    .boxed(),
+            ];
             while !a_resolves.is_empty() {
                 let (next, _, rest) = select_all(a_resolves).await;
                 a_resolves = rest;
@@ -445,17 +825,7 @@ impl Recursor {
                 match next {
                     Ok(response) => {
                         debug!("A or AAAA response: {:?}", response);
-                        let ips = response.iter().filter_map(RData::ip_addr);
-
-                        for ip in ips {
-                            let udp =
-                                NameServerConfig::new(SocketAddr::from((ip, 53)), Protocol::Udp);
-                            let tcp =
-                                NameServerConfig::new(SocketAddr::from((ip, 53)), Protocol::Tcp);
-
-                            config_group.push(udp);
-                            config_group.push(tcp);
-                        }
+                        self.add_servers(&mut servers, &mut valid_until, &response);
                     }
                     Err(e) => {
                         warn!("resolve failed {}", e);
@@ -464,19 +834,164 @@ impl Recursor {
             }
         }
 
-        // now construct a namesever pool based off the NS and glue records
-        let ns = GenericNameServerPool::from_config(
-            config_group,
-            recursor_opts(),
-            TokioConnectionProvider::default(),
-        );
-        let ns = RecursorPool::from(zone.clone(), ns);
+        // the delegation is unreachable, which is not cached as a pool without name servers
+        if servers.is_empty() {
+            self.delegation_failures.failed(&zone, request_time);
+            return Err(Error::from(format!(
+                "no addresses found for the name servers of {zone}"
+            )));
+        }
+
+        let ns = RecursorPool::delegation(zone.clone(), servers);
 
         // store in cache for future usage
-        debug!("found nameservers for {}", zone);
-        self.name_server_cache.lock().insert(zone, ns.clone());
+        debug!(
+            "found nameservers for {} valid until {:?}",
+            zone, valid_until
+        );
+        self.name_server_cache
+            .lock()
+            .insert(zone, (ns.clone(), valid_until));
         Ok(ns)
     }
+
+    /// Returns the root name servers, which are primed if they expired
+    async fn roots(
+        &self,
+        request_time: Instant,
+        budget: &QueryBudget,
+    ) -> RecursorPool<TokioRuntimeProvider> {
+        {
+            let (roots, valid_until) = &*self.roots.lock();
+            if request_time < *valid_until {
+                return roots.clone();
+            }
+        }
+
+        // the queries to the roots are answered by the local copy of the root zone
+        #[cfg(feature = "root-zone-mirror")]
+        if self
+            .root_mirror
+            .as_ref()
+            .map_or(false, |mirror| mirror.is_current(request_time))
+        {
+            return self.hints.clone();
+        }
+
+        match self.prime_roots(request_time, budget).await {
+            Ok(roots) => roots,
+            Err(e) => {
+                warn!("priming failed, using the root hints: {e}");
+                *self.roots.lock() = (self.hints.clone(), request_time + self.priming_retry);
+                self.hints.clone()
+            }
+        }
+    }
+
+    async fn prime_roots(
+        &self,
+        request_time: Instant,
+        budget: &QueryBudget,
+    ) -> Result<RecursorPool<TokioRuntimeProvider>, Error> {
+        let query = Query::query(Name::root(), RecordType::NS);
+        let response = self.hints.lookup(query.clone(), budget).await?;
+
+        #[cfg(feature = "dnssec")]
+        if self.dnssec_validation {
+            let dnskey_query = Query::query(Name::root(), RecordType::DNSKEY);
+            let dnskeys = self.hints.lookup(dnskey_query, budget).await?;
+            verify_priming(
+                response.answers(),
+                dnskeys.answers(),
+                &TrustAnchor::default(),
+                unix_now(),
+            )?;
+        }
+
+        // the addresses of the root name servers are in the additional section
+        let mut response = response.into_message();
+        let records = response
+            .take_answers()
+            .into_iter()
+            .chain(response.take_additionals());
+        let lookup = self
+            .record_cache
+            .insert_records(query, records, request_time)
+            .ok_or_else(|| Error::from("no NS records in the priming response"))?;
+
+        let mut servers = Vec::new();
+        let mut valid_until = lookup.valid_until();
+        for ns in lookup.iter().filter_map(RData::as_ns) {
+            for record_type in [RecordType::A, RecordType::AAAA] {
+                let cached = self
+                    .record_cache
+                    .get(&Query::query(ns.0.clone(), record_type), request_time);
+                if let Some(Ok(glue)) = cached {
+                    self.add_servers(&mut servers, &mut valid_until, &glue);
+                }
+            }
+        }
+
+        if servers.is_empty() {
+            return Err(Error::from(
+                "no addresses of the root name servers in the priming response",
+            ));
+        }
+
+        info!(
+            "primed {} root name servers valid until {valid_until:?}",
+            servers.len()
+        );
+        let roots = RecursorPool::delegation(Name::root(), servers);
+        *self.roots.lock() = (roots.clone(), valid_until);
+        Ok(roots)
+    }
+
+    /// Adds the name servers at the addresses of the lookup, which are valid until the lookup is
+    fn add_servers(
+        &self,
+        servers: &mut Vec<InfraServer>,
+        valid_until: &mut Instant,
+        lookup: &Lookup,
+    ) {
+        *valid_until = (*valid_until).min(lookup.valid_until());
+        for ip in lookup.iter().filter_map(RData::ip_addr) {
+            let socket_addr = SocketAddr::from((ip, 53));
+            if servers
+                .iter()
+                .all(|server| server.socket_addr() != socket_addr)
+            {
+                servers.push(self.infra_cache.server(socket_addr));
+            }
+        }
+    }
+}
+
+/// The number of queries which may still be sent to name servers for a client query, see
+///  `RecursorBuilder::max_upstream_queries`
+///
+/// Each query sent to a name server is taken from it, including the retries at the other name
+///  servers of a delegation and without EDNS. The clones share the budget.
+#[derive(Clone)]
+pub(crate) struct QueryBudget(Arc<AtomicUsize>);
+
+impl QueryBudget {
+    fn new(max_upstream_queries: usize) -> Self {
+        Self(Arc::new(AtomicUsize::new(max_upstream_queries)))
+    }
+
+    /// Takes a query from the budget, or returns an error if it is spent
+    pub(crate) fn spend(&self, query: &Query) -> Result<(), ResolveError> {
+        self.0
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| {
+                left.checked_sub(1)
+            })
+            .map(drop)
+            .map_err(|_| {
+                warn!("upstream query limit reached before {query}");
+                ResolveError::from("upstream query limit reached")
+            })
+    }
 }
 
 fn recursor_opts() -> ResolverOpts {
@@ -574,3 +1089,106 @@ fn is_subzone_test() {
         Name::from_str("example.com.").unwrap()
     ));
 }
+
+#[test]
+fn configured_zone_test() {
+    let servers =
+        NameServerConfigGroup::from_ips_clear(&[std::net::IpAddr::from([192, 0, 2, 1])], 53, true);
+    let recursor = Recursor::new(servers.clone(), 1, 1)
+        .unwrap()
+        .with_forward_zone(Name::from_str("example.com").unwrap(), servers.clone())
+        .with_stub_zone(
+            Name::from_str("corp.example.com.").unwrap(),
+            servers.clone(),
+        )
+        .with_forward_zone(Name::from_str("lab.corp.example.com.").unwrap(), servers);
+
+    let zone = |name: &str| {
+        recursor
+            .configured_zone(&Name::from_str(name).unwrap())
+            .map(|pool| (pool.zone().to_string(), pool.is_forwarding()))
+    };
+
+    assert_eq!(
+        zone("www.example.com."),
+        Some(("example.com.".to_string(), true))
+    );
+    assert_eq!(
+        zone("www.corp.example.com."),
+        Some(("corp.example.com.".to_string(), false))
+    );
+    assert_eq!(
+        zone("www.lab.corp.example.com."),
+        Some(("lab.corp.example.com.".to_string(), true))
+    );
+    assert_eq!(zone("www.example.net."), None);
+}
+
+#[test]
+fn query_budget_test() {
+    let query = Query::query(Name::from_str("example.com.").unwrap(), RecordType::A);
+    let budget = QueryBudget::new(2);
+    assert!(budget.spend(&query).is_ok());
+    assert!(budget.clone().spend(&query).is_ok());
+    assert!(budget.spend(&query).is_err());
+    assert!(budget.clone().spend(&query).is_err());
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn prime_test() {
+    use crate::proto::{
+        op::{Message, MessageType},
+        rr::rdata::{A, NS},
+    };
+
+    // a root hint which answers the priming query
+    let socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let hint = socket.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut buf = [0; 4096];
+        loop {
+            let (len, src) = socket.recv_from(&mut buf).await.unwrap();
+            let request = Message::from_vec(&buf[..len]).unwrap();
+            let root_server = Name::from_str("a.root-servers.net.").unwrap();
+            let mut response = Message::new();
+            response
+                .set_id(request.id())
+                .set_message_type(MessageType::Response)
+                .set_authoritative(true)
+                .add_queries(request.queries().to_vec())
+                .add_answer(Record::from_rdata(
+                    Name::root(),
+                    518400,
+                    RData::NS(NS(root_server.clone())),
+                ))
+                .add_additional(Record::from_rdata(
+                    root_server,
+                    3600,
+                    RData::A(A::new(192, 0, 2, 53)),
+                ));
+            socket
+                .send_to(&response.to_vec().unwrap(), src)
+                .await
+                .unwrap();
+        }
+    });
+
+    let recursor = Recursor::new(
+        NameServerConfigGroup::from_ips_clear(&[hint.ip()], hint.port(), true),
+        16,
+        16,
+    )
+    .unwrap();
+    let now = Instant::now();
+    recursor.prime().await.unwrap();
+
+    // the roots are primed again once the addresses of the root name servers expire
+    let valid_until = recursor.roots.lock().1;
+    assert!(valid_until > now + Duration::from_secs(3500));
+    assert!(valid_until <= Instant::now() + Duration::from_secs(3600));
+    let cached = recursor
+        .record_cache
+        .get(&Query::query(Name::root(), RecordType::NS), Instant::now());
+    assert!(matches!(cached, Some(Ok(_))));
+}
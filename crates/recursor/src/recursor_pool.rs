@@ -10,6 +10,7 @@ use std::{
     pin::Pin,
     sync::Arc,
     task::{Context, Poll},
+    time::Instant,
 };
 
 use futures_util::{future::Shared, Future, FutureExt, StreamExt};
@@ -25,7 +26,10 @@ use hickory_resolver::{
     Name,
 };
 use parking_lot::Mutex;
-use tracing::info;
+use tracing::{debug, info};
+
+use crate::infra_cache::{is_negative_answer, InfraServer};
+use crate::recursor::QueryBudget;
 
 /// Active request cache
 ///
@@ -49,21 +53,61 @@ impl Future for SharedLookup {
     }
 }
 
+/// The name servers of a zone
+#[derive(Clone)]
+#[allow(clippy::large_enum_variant)]
+enum NameServers<P: RuntimeProvider + Send + 'static> {
+    /// Configured name servers, which are queried as a pool
+    Pool(GenericNameServerPool<P>),
+    /// The name servers delegated to, which are queried one after the other, the fastest first
+    Delegation(Arc<[InfraServer]>),
+}
+
 #[derive(Clone)]
 pub(crate) struct RecursorPool<P: RuntimeProvider + Send + 'static> {
     zone: Name,
-    ns: GenericNameServerPool<P>,
+    ns: NameServers<P>,
     active_requests: Arc<Mutex<ActiveRequests>>,
+    forwarding: bool,
+    dnssec_ok: bool,
 }
 
 impl RecursorPool<TokioRuntimeProvider> {
     pub(crate) fn from(zone: Name, ns: GenericNameServerPool<TokioRuntimeProvider>) -> Self {
+        Self::with_name_servers(zone, NameServers::Pool(ns))
+    }
+
+    /// A pool of recursive resolvers, which are sent requests with recursion desired
+    pub(crate) fn forwarding(zone: Name, ns: GenericNameServerPool<TokioRuntimeProvider>) -> Self {
+        Self {
+            forwarding: true,
+            ..Self::from(zone, ns)
+        }
+    }
+
+    /// The name servers which the zone is delegated to, see `InfraCache`
+    pub(crate) fn delegation(zone: Name, servers: Vec<InfraServer>) -> Self {
+        Self::with_name_servers(zone, NameServers::Delegation(servers.into()))
+    }
+
+    fn with_name_servers(zone: Name, ns: NameServers<TokioRuntimeProvider>) -> Self {
         let active_requests = Arc::new(Mutex::new(ActiveRequests::default()));
 
         Self {
             zone,
             ns,
             active_requests,
+            forwarding: false,
+            dnssec_ok: false,
+        }
+    }
+
+    /// Queries the name servers of the delegation with the DO bit, for the RRSIGs of the records
+    #[cfg(feature = "dnssec")]
+    pub(crate) fn with_dnssec_ok(self) -> Self {
+        Self {
+            dnssec_ok: true,
+            ..self
         }
     }
 }
@@ -76,10 +120,28 @@ where
         &self.zone
     }
 
-    pub(crate) async fn lookup(&self, query: Query) -> Result<DnsResponse, ResolveError> {
+    /// Returns true if the name servers are recursive resolvers, which the zone is forwarded to
+    pub(crate) fn is_forwarding(&self) -> bool {
+        self.forwarding
+    }
+
+    /// Queries the name servers, the queries sent are taken from the budget
+    pub(crate) async fn lookup(
+        &self,
+        query: Query,
+        budget: &QueryBudget,
+    ) -> Result<DnsResponse, ResolveError> {
         let ns = self.ns.clone();
+        // the lookup of a pool is a single query, a delegation takes each of its queries
+        if let NameServers::Pool(_) = ns {
+            budget.spend(&query)?;
+        }
+        let budget = budget.clone();
 
         let query_cpy = query.clone();
+        let forwarding = self.forwarding;
+        let dnssec_ok = self.dnssec_ok;
+        let zone = self.zone.clone();
 
         // block concurrent requests
         let lookup = self
@@ -89,17 +151,25 @@ where
             .or_insert_with(move || {
                 info!("querying {} for {}", self.zone, query_cpy);
 
-                let mut options = DnsRequestOptions::default();
-                options.use_edns = false; // TODO: this should be configurable
-                options.recursion_desired = false;
-
                 // convert the lookup into a shared future
-                let lookup = ns
-                    .lookup(query_cpy, options)
-                    .into_future()
-                    .map(|(next, _)| next.map(|r| r.map_err(ResolveError::from)))
-                    .boxed()
-                    .shared();
+                let lookup = match ns {
+                    NameServers::Pool(ns) => {
+                        let mut options = DnsRequestOptions::default();
+                        options.use_edns = false; // TODO: this should be configurable
+                        options.recursion_desired = forwarding;
+
+                        ns.lookup(query_cpy, options)
+                            .into_future()
+                            .map(|(next, _)| next.map(|r| r.map_err(ResolveError::from)))
+                            .boxed()
+                    }
+                    NameServers::Delegation(servers) => {
+                        lookup_delegation(servers, zone, query_cpy, dnssec_ok, budget)
+                            .map(Some)
+                            .boxed()
+                    }
+                }
+                .shared();
 
                 SharedLookup(lookup)
             })
@@ -113,3 +183,44 @@ where
         result
     }
 }
+
+/// Queries the name servers of the delegation one after the other, the fastest first, until one of
+///  them answers
+///
+/// The name servers which are lame for the zone are only queried if all of them are. Each query
+///  sent is taken from the budget, the lookup fails when it is spent.
+async fn lookup_delegation(
+    servers: Arc<[InfraServer]>,
+    zone: Name,
+    query: Query,
+    dnssec_ok: bool,
+    budget: QueryBudget,
+) -> Result<DnsResponse, ResolveError> {
+    let now = Instant::now();
+    let mut candidates = servers
+        .iter()
+        .filter(|server| !server.is_lame(&zone, now))
+        .collect::<Vec<_>>();
+    if candidates.is_empty() {
+        debug!("all name servers of {zone} are lame");
+        candidates = servers.iter().collect();
+    }
+    candidates.sort_by_key(|server| server.srtt());
+
+    let mut last_error = ResolveError::from("no name servers");
+    for server in candidates {
+        match server
+            .lookup(&zone, query.clone(), dnssec_ok, now, &budget)
+            .await
+        {
+            Ok(response) => return Ok(response),
+            Err(e) if is_negative_answer(&e) => return Err(e),
+            Err(e) => {
+                debug!("{} failed for {query}: {e}", server.socket_addr());
+                last_error = e;
+            }
+        }
+    }
+
+    Err(last_error)
+}
@@ -0,0 +1,485 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! A local copy of the root zone, see
+//!  [RFC 8806](https://datatracker.ietf.org/doc/html/rfc8806)
+
+use std::{
+    collections::BTreeMap,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use hickory_client::client::{AsyncClient, ClientHandle, ZoneTransferClient, ZoneTransferLimits};
+use parking_lot::RwLock;
+use tokio::net::TcpStream as TokioTcpStream;
+use tracing::{debug, info, warn};
+
+use crate::{
+    proto::{
+        error::ProtoError,
+        iocompat::AsyncIoTokioAsStd,
+        op::{Query, ResponseCode},
+        rr::{
+            dnssec::TrustAnchor, rdata::SOA, DNSClass, LowerName, RData, Record, RecordSet,
+            RecordType, RrKey,
+        },
+        tcp::TcpClientStream,
+    },
+    resolver::{error::ResolveError, Name},
+    validation::{is_signed, trusted_keys, unix_now},
+    Error,
+};
+
+/// The servers of ICANN and the root server operators which allow transfers of the root zone,
+///  see [RFC 8806, Appendix A](https://datatracker.ietf.org/doc/html/rfc8806#appendix-A)
+const ROOT_ZONE_SERVERS: [IpAddr; 12] = [
+    // lax.xfr.dns.icann.org
+    IpAddr::V4(Ipv4Addr::new(192, 0, 32, 132)),
+    IpAddr::V6(Ipv6Addr::new(0x2620, 0, 0xd0, 0x202, 0, 0, 0, 0x132)),
+    // iad.xfr.dns.icann.org
+    IpAddr::V4(Ipv4Addr::new(192, 0, 47, 132)),
+    IpAddr::V6(Ipv6Addr::new(0x2620, 0, 0x2830, 0x202, 0, 0, 0, 0x132)),
+    // c.root-servers.net
+    IpAddr::V4(Ipv4Addr::new(192, 33, 4, 12)),
+    IpAddr::V6(Ipv6Addr::new(0x2001, 0x500, 0x2, 0, 0, 0, 0, 0xc)),
+    // f.root-servers.net
+    IpAddr::V4(Ipv4Addr::new(192, 5, 5, 241)),
+    IpAddr::V6(Ipv6Addr::new(0x2001, 0x500, 0x2f, 0, 0, 0, 0, 0xf)),
+    // g.root-servers.net
+    IpAddr::V4(Ipv4Addr::new(192, 112, 36, 4)),
+    IpAddr::V6(Ipv6Addr::new(0x2001, 0x500, 0x12, 0, 0, 0, 0, 0xd0d)),
+    // k.root-servers.net
+    IpAddr::V4(Ipv4Addr::new(193, 0, 14, 129)),
+    IpAddr::V6(Ipv6Addr::new(0x2001, 0x7fd, 0, 0, 0, 0, 0, 0x1)),
+];
+
+/// The time allowed for a transfer of the root zone
+const TRANSFER_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The limit of the size of the root zone, which is about 2 MB with its signatures
+const MAX_ZONE_BYTES: usize = 64 * 1024 * 1024;
+
+/// The servers which allow transfers of the root zone, see `Recursor::with_root_zone_mirror`
+pub fn root_zone_servers() -> Vec<SocketAddr> {
+    ROOT_ZONE_SERVERS
+        .iter()
+        .map(|ip| SocketAddr::from((*ip, 53)))
+        .collect()
+}
+
+/// The validated copy of the root zone
+struct MirroredZone {
+    records: BTreeMap<RrKey, RecordSet>,
+    soa: SOA,
+    /// The copy is not used anymore after the instant, once it was not refreshed for the expire
+    ///  time of the SOA
+    expires: Instant,
+}
+
+struct MirrorState {
+    zone: Option<MirroredZone>,
+    next_refresh: Instant,
+}
+
+/// A local copy of the root zone, which answers the queries to the root name servers
+///
+/// The zone is transferred again after the refresh time of its SOA record, if its serial changed,
+///  and only used once it is validated with DNSSEC. Queries are sent to the root name servers
+///  while there is no current copy of the zone.
+pub(crate) struct RootMirror {
+    servers: Vec<SocketAddr>,
+    state: RwLock<MirrorState>,
+    refreshing: AtomicBool,
+}
+
+impl RootMirror {
+    pub(crate) fn new(servers: Vec<SocketAddr>) -> Self {
+        Self {
+            servers,
+            state: RwLock::new(MirrorState {
+                zone: None,
+                next_refresh: Instant::now(),
+            }),
+            refreshing: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns true if there is a current copy of the zone
+    pub(crate) fn is_current(&self, now: Instant) -> bool {
+        self.state
+            .read()
+            .zone
+            .as_ref()
+            .map_or(false, |zone| now < zone.expires)
+    }
+
+    /// Answers the query from the copy of the zone, as the root name servers would
+    ///
+    /// The records are those of the root zone for queries of the root, and otherwise the referral to
+    ///  the top level domain with its glue. Returns `None` if there is no current copy, the query
+    ///  is then to be sent to the root name servers. A refresh is started in the background if
+    ///  the copy is due for one.
+    pub(crate) fn lookup(
+        self: &Arc<Self>,
+        query: &Query,
+        now: Instant,
+    ) -> Option<Result<Vec<Record>, ResolveError>> {
+        let state = self.state.read();
+        if now >= state.next_refresh {
+            self.refresh_in_background();
+        }
+
+        let zone = state.zone.as_ref().filter(|zone| now < zone.expires)?;
+        let name = query.name();
+        let top_level = if name.is_root() {
+            name.clone()
+        } else {
+            name.trim_to(1)
+        };
+
+        // the DS and NSEC records of the top level domains are in the root zone
+        let is_parent_side = name.num_labels() == 1
+            && matches!(query.query_type(), RecordType::DS | RecordType::NSEC);
+        if !top_level.is_root() && !is_parent_side {
+            return Some(zone.referral(query, &top_level));
+        }
+
+        Some(zone.answer(query))
+    }
+
+    /// Transfers the root zone if it changed, from the first of the servers which answers
+    pub(crate) async fn refresh(&self) -> Result<(), Error> {
+        let mut last_error = Error::from("no servers for the root zone");
+        for server in &self.servers {
+            match self.refresh_from(*server).await {
+                Ok(()) => {
+                    self.refreshed(false);
+                    return Ok(());
+                }
+                Err(e) => {
+                    warn!("failed to transfer the root zone from {server}: {e}");
+                    last_error = e;
+                }
+            }
+        }
+
+        self.refreshed(true);
+        Err(last_error)
+    }
+
+    fn refresh_in_background(self: &Arc<Self>) {
+        if self.refreshing.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let mirror = Arc::clone(self);
+        tokio::spawn(async move {
+            // the error is logged by refresh
+            let _ = mirror.refresh().await;
+        });
+    }
+
+    /// Schedules the next refresh, after the refresh or retry time of the SOA record
+    fn refreshed(&self, failed: bool) {
+        let mut state = self.state.write();
+        let interval = match (&state.zone, failed) {
+            (Some(zone), false) => zone.soa.refresh(),
+            (Some(zone), true) => zone.soa.retry(),
+            // the zone was never transferred
+            (None, _) => 300,
+        };
+        state.next_refresh = Instant::now() + Duration::from_secs(interval.max(0) as u64);
+        self.refreshing.store(false, Ordering::Release);
+    }
+
+    async fn refresh_from(&self, server: SocketAddr) -> Result<(), Error> {
+        let (stream, sender) = TcpClientStream::<AsyncIoTokioAsStd<TokioTcpStream>>::new(server);
+        let (mut client, bg) =
+            AsyncClient::with_timeout(stream, sender, TRANSFER_TIMEOUT, None).await?;
+        tokio::spawn(bg);
+
+        // the zone is only transferred if its serial changed
+        let serial = self
+            .state
+            .read()
+            .zone
+            .as_ref()
+            .map(|zone| zone.soa.serial());
+        if let Some(serial) = serial {
+            let response = client
+                .query(Name::root(), DNSClass::IN, RecordType::SOA)
+                .await
+                .map_err(|e| Error::from(e.to_string()))?;
+            let current = response
+                .answers()
+                .iter()
+                .filter_map(|record| record.data().and_then(RData::as_soa))
+                .any(|soa| soa.serial() == serial);
+            if current {
+                debug!("root zone is current at serial {serial}");
+                let mut state = self.state.write();
+                if let Some(zone) = &mut state.zone {
+                    zone.expires = Instant::now() + expire(&zone.soa);
+                }
+                return Ok(());
+            }
+        }
+
+        let mut transfer = ZoneTransferClient::new(client, Name::root());
+        let mut limits = ZoneTransferLimits::default();
+        limits.max_bytes = Some(MAX_ZONE_BYTES);
+        transfer.set_limits(limits);
+        transfer
+            .transfer()
+            .await
+            .map_err(|e| Error::from(e.to_string()))?;
+
+        let records = transfer.into_records();
+        validate(&records, &TrustAnchor::default(), unix_now())?;
+        let soa = records
+            .get(&RrKey::new(LowerName::from(Name::root()), RecordType::SOA))
+            .and_then(|rrset| rrset.records_without_rrsigs().next())
+            .and_then(|record| record.data().and_then(RData::as_soa))
+            .cloned()
+            .ok_or_else(|| Error::from("the root zone has no SOA record"))?;
+
+        info!(
+            "transferred the root zone at serial {} from {server}",
+            soa.serial()
+        );
+        self.state.write().zone = Some(MirroredZone {
+            records,
+            expires: Instant::now() + expire(&soa),
+            soa,
+        });
+        Ok(())
+    }
+}
+
+impl MirroredZone {
+    fn rrset(&self, name: &Name, record_type: RecordType) -> Option<&RecordSet> {
+        self.records
+            .get(&RrKey::new(LowerName::from(name), record_type))
+    }
+
+    /// The records of the root zone for the query, or the negative answer
+    fn answer(&self, query: &Query) -> Result<Vec<Record>, ResolveError> {
+        if let Some(rrset) = self.rrset(query.name(), query.query_type()) {
+            return Ok(rrset.records_without_rrsigs().cloned().collect());
+        }
+
+        let exists = self
+            .records
+            .keys()
+            .any(|key| key.name == LowerName::from(query.name()));
+        let response_code = if exists {
+            ResponseCode::NoError
+        } else {
+            ResponseCode::NXDomain
+        };
+        Err(self.negative(query, response_code))
+    }
+
+    /// The name servers of the top level domain and their addresses in the zone
+    fn referral(&self, query: &Query, top_level: &Name) -> Result<Vec<Record>, ResolveError> {
+        let Some(ns) = self.rrset(top_level, RecordType::NS) else {
+            return Err(self.negative(query, ResponseCode::NXDomain));
+        };
+
+        let mut records = ns.records_without_rrsigs().cloned().collect::<Vec<_>>();
+        for target in ns
+            .records_without_rrsigs()
+            .filter_map(|record| record.data().and_then(RData::as_ns))
+        {
+            for record_type in [RecordType::A, RecordType::AAAA] {
+                if let Some(glue) = self.rrset(&target.0, record_type) {
+                    records.extend(glue.records_without_rrsigs().cloned());
+                }
+            }
+        }
+
+        Ok(records)
+    }
+
+    fn negative(&self, query: &Query, response_code: ResponseCode) -> ResolveError {
+        let soa = Record::from_rdata(Name::root(), self.soa.minimum(), self.soa.clone());
+        ProtoError::nx_error(
+            query.clone(),
+            Some(soa),
+            Some(self.soa.minimum()),
+            response_code,
+            true,
+        )
+        .into()
+    }
+}
+
+fn expire(soa: &SOA) -> Duration {
+    Duration::from_secs(soa.expire().max(0) as u64)
+}
+
+/// Validates the copy of the root zone with DNSSEC
+///
+/// All the records the root zone is authoritative for must be signed with its DNSKEYs, which are
+///  signed by the trust anchor. The name servers of the delegations and their addresses are not
+///  signed.
+fn validate(
+    records: &BTreeMap<RrKey, RecordSet>,
+    trust_anchor: &TrustAnchor,
+    now: u32,
+) -> Result<(), Error> {
+    let root = LowerName::from(Name::root());
+    let dnskeys = records
+        .get(&RrKey::new(root.clone(), RecordType::DNSKEY))
+        .ok_or_else(|| Error::from("the root zone has no DNSKEY records"))?;
+    let keys = trusted_keys(
+        &dnskeys.records_without_rrsigs().collect::<Vec<_>>(),
+        &dnskeys.rrsigs().iter().collect::<Vec<_>>(),
+        trust_anchor,
+        now,
+    )?;
+
+    for rrset in records.values() {
+        let is_delegation = rrset.name() != &Name::root()
+            && matches!(
+                rrset.record_type(),
+                RecordType::NS | RecordType::A | RecordType::AAAA
+            );
+        if is_delegation {
+            continue;
+        }
+
+        let signed = is_signed(
+            &rrset.records_without_rrsigs().collect::<Vec<_>>(),
+            &rrset.rrsigs().iter().collect::<Vec<_>>(),
+            &keys,
+            now,
+        );
+        if !signed {
+            return Err(Error::from(format!(
+                "{} {} of the root zone is not signed",
+                rrset.name(),
+                rrset.record_type()
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::proto::rr::rdata::{A, NS};
+
+    fn zone() -> MirroredZone {
+        let soa = SOA::new(
+            Name::from_str("a.root-servers.net.").unwrap(),
+            Name::from_str("nstld.verisign-grs.com.").unwrap(),
+            2023120100,
+            1800,
+            900,
+            604800,
+            86400,
+        );
+        let ns = Name::from_str("a.nic.example.").unwrap();
+        let mut records = BTreeMap::new();
+        for record in [
+            Record::from_rdata(Name::root(), 86400, RData::SOA(soa.clone())),
+            Record::from_rdata(
+                Name::from_str("example.").unwrap(),
+                172800,
+                RData::NS(NS(ns.clone())),
+            ),
+            Record::from_rdata(ns, 172800, RData::A(A::new(192, 0, 2, 53))),
+        ] {
+            let key = RrKey::new(LowerName::from(record.name()), record.record_type());
+            records
+                .entry(key)
+                .or_insert_with(|| RecordSet::new(record.name(), record.record_type(), 0))
+                .insert(record, 0);
+        }
+
+        MirroredZone {
+            records,
+            soa,
+            expires: Instant::now() + Duration::from_secs(60),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_root_mirror_lookup() {
+        let mirror = Arc::new(RootMirror::new(Vec::new()));
+        let now = Instant::now();
+        let query =
+            |name: &str, record_type| Query::query(Name::from_str(name).unwrap(), record_type);
+
+        // without a copy of the zone, the root name servers are queried
+        assert!(!mirror.is_current(now));
+        assert!(mirror
+            .lookup(&query("example.", RecordType::NS), now)
+            .is_none());
+
+        let mut state = mirror.state.write();
+        state.zone = Some(zone());
+        state.next_refresh = now + Duration::from_secs(1800);
+        drop(state);
+        assert!(mirror.is_current(now));
+
+        // the referral to the top level domain with its glue
+        let referral = mirror
+            .lookup(&query("www.example.", RecordType::A), now)
+            .unwrap()
+            .unwrap();
+        assert_eq!(referral.len(), 2);
+        assert_eq!(referral[0].record_type(), RecordType::NS);
+        assert_eq!(
+            referral[1].data().and_then(RData::ip_addr),
+            Some(IpAddr::from([192, 0, 2, 53]))
+        );
+
+        let soa = mirror
+            .lookup(&query(".", RecordType::SOA), now)
+            .unwrap()
+            .unwrap();
+        assert_eq!(soa.len(), 1);
+
+        // names which are not delegated do not exist
+        let err = mirror
+            .lookup(&query("www.invalid.", RecordType::A), now)
+            .unwrap()
+            .unwrap_err();
+        assert!(matches!(
+            err.proto().map(ProtoError::kind),
+            Some(crate::proto::error::ProtoErrorKind::NoRecordsFound {
+                response_code: ResponseCode::NXDomain,
+                ..
+            })
+        ));
+
+        // an expired copy is not used
+        assert!(mirror
+            .lookup(
+                &query("example.", RecordType::NS),
+                now + Duration::from_secs(120)
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn test_validate_unsigned() {
+        let err = validate(&zone().records, &TrustAnchor::default(), 0).unwrap_err();
+        assert!(err.to_string().contains("DNSKEY"), "{err}");
+    }
+}
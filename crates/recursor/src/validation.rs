@@ -0,0 +1,89 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! DNSSEC validation of the data of the root zone, against its trust anchor
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    proto::rr::{
+        dnssec::{
+            rdata::{DNSSECRData, DNSKEY, RRSIG},
+            TrustAnchor, Verifier,
+        },
+        RData, Record,
+    },
+    Error,
+};
+
+/// The current time in seconds since the unix epoch, as in the RRSIGs
+pub(crate) fn unix_now() -> u32 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs() as u32)
+}
+
+/// Returns the DNSKEYs, once their RRset is verified with a key of the trust anchor
+pub(crate) fn trusted_keys<'a>(
+    dnskeys: &[&'a Record],
+    rrsigs: &[&Record],
+    trust_anchor: &TrustAnchor,
+    now: u32,
+) -> Result<Vec<&'a DNSKEY>, Error> {
+    let keys = dnskeys
+        .iter()
+        .filter_map(|record| match record.data() {
+            Some(RData::DNSSEC(DNSSECRData::DNSKEY(key))) => Some(key),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let anchors = keys
+        .iter()
+        .copied()
+        .filter(|key| trust_anchor.contains_dnskey_bytes(key.public_key()))
+        .collect::<Vec<_>>();
+
+    if !is_signed(dnskeys, rrsigs, &anchors, now) {
+        return Err(Error::from(
+            "the DNSKEYs of the root zone are not signed by a trust anchor",
+        ));
+    }
+
+    Ok(keys)
+}
+
+/// Returns true if one of the RRSIGs is a current signature of the records by one of the keys
+pub(crate) fn is_signed(
+    records: &[&Record],
+    rrsigs: &[&Record],
+    keys: &[&DNSKEY],
+    now: u32,
+) -> bool {
+    let Some(first) = records.first() else {
+        return false;
+    };
+
+    rrsigs
+        .iter()
+        .filter_map(|record| match record.data() {
+            Some(RData::DNSSEC(DNSSECRData::RRSIG(rrsig))) => Some(rrsig),
+            _ => None,
+        })
+        .filter(|rrsig| rrsig.type_covered() == first.record_type())
+        .filter(|rrsig| rrsig.sig_inception() <= now && now <= rrsig.sig_expiration())
+        .any(|rrsig| keys.iter().any(|key| verifies(key, rrsig, records)))
+}
+
+fn verifies(key: &DNSKEY, rrsig: &RRSIG, records: &[&Record]) -> bool {
+    key.zone_key()
+        && !key.revoke()
+        && key.algorithm() == rrsig.algorithm()
+        && key.calculate_key_tag().ok() == Some(rrsig.key_tag())
+        && key
+            .verify_rrsig(records[0].name(), records[0].dns_class(), rrsig, records)
+            .is_ok()
+}
@@ -0,0 +1,27 @@
+//! Resolves a name with the smol runtime, without Tokio
+//!
+//! ```text
+//! cargo run --example smol --no-default-features --features smol-runtime -- www.example.com.
+//! ```
+
+use hickory_resolver::config::{ResolverConfig, ResolverOpts};
+use hickory_resolver::SmolAsyncResolver;
+
+fn main() {
+    let name = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "www.example.com.".to_string());
+
+    smol::block_on(async {
+        let resolver = SmolAsyncResolver::smol(ResolverConfig::default(), ResolverOpts::default());
+
+        match resolver.lookup_ip(name.as_str()).await {
+            Ok(response) => {
+                for ip in response.iter() {
+                    println!("{name}: {ip}");
+                }
+            }
+            Err(e) => println!("failed to look up {name}: {e}"),
+        }
+    });
+}
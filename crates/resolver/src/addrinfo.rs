@@ -0,0 +1,464 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Lookups of socket addresses like `getaddrinfo`, see `AsyncResolver::lookup_host_ordered`
+//!
+//! Services are resolved to ports with the services file of the system, and the addresses are
+//!  ordered with the destination address selection of
+//!  [RFC 6724](https://tools.ietf.org/html/rfc6724#section-6).
+
+use std::cmp::Ordering;
+use std::io::{self, BufRead};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+#[cfg(any(unix, windows))]
+use std::path::Path;
+
+use crate::error::{ResolveError, ResolveErrorKind};
+
+/// The address family of the results, see `AddrInfoHints`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AddressFamily {
+    /// IPv4 and IPv6 addresses, `AF_UNSPEC`
+    Unspecified,
+    /// Only IPv4 addresses, `AF_INET`
+    Ipv4,
+    /// Only IPv6 addresses, `AF_INET6`
+    Ipv6,
+}
+
+impl Default for AddressFamily {
+    /// Returns [`AddressFamily::Unspecified`] as the default.
+    fn default() -> Self {
+        Self::Unspecified
+    }
+}
+
+/// The hints of `AsyncResolver::lookup_host_ordered`, like those of `getaddrinfo`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct AddrInfoHints {
+    /// The address family of the results
+    pub family: AddressFamily,
+    /// Only return addresses of the families the host has a route for, `AI_ADDRCONFIG`
+    ///
+    /// If the host has no route for either family, for example as it is offline, both families
+    ///  are returned so that the names of the host itself still resolve.
+    pub addr_config: bool,
+    /// The service must be a port number, it is not looked up in the services file,
+    ///  `AI_NUMERICSERV`
+    pub numeric_service: bool,
+}
+
+impl Default for AddrInfoHints {
+    /// The same as `getaddrinfo` without hints, both families with `AI_ADDRCONFIG`
+    fn default() -> Self {
+        Self {
+            family: AddressFamily::Unspecified,
+            addr_config: true,
+            numeric_service: false,
+        }
+    }
+}
+
+impl AddrInfoHints {
+    /// Returns which of IPv4 and IPv6 addresses are requested
+    pub(crate) fn families(&self) -> (bool, bool) {
+        let (mut ipv4, mut ipv6) = match self.family {
+            AddressFamily::Unspecified => (true, true),
+            AddressFamily::Ipv4 => (true, false),
+            AddressFamily::Ipv6 => (false, true),
+        };
+
+        if self.addr_config {
+            let routed_ipv4 = source_addr(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))).is_some();
+            let routed_ipv6 =
+                source_addr(IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1))).is_some();
+
+            if routed_ipv4 || routed_ipv6 {
+                ipv4 &= routed_ipv4;
+                ipv6 &= routed_ipv6;
+            }
+        }
+
+        (ipv4, ipv6)
+    }
+}
+
+/// Returns the port of the service, a port number or a name in the services file
+pub(crate) fn service_port(service: &str, numeric_only: bool) -> Result<u16, ResolveError> {
+    if let Ok(port) = service.parse::<u16>() {
+        return Ok(port);
+    }
+
+    let port = if numeric_only {
+        None
+    } else {
+        read_services_port(service)?
+    };
+
+    port.ok_or_else(|| ResolveErrorKind::Msg(format!("unknown service: {service}")).into())
+}
+
+#[cfg(any(unix, windows))]
+fn read_services_port(service: &str) -> io::Result<Option<u16>> {
+    match std::fs::File::open(services_path()) {
+        Ok(file) => find_service_port(io::BufReader::new(file), service),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn read_services_port(_service: &str) -> io::Result<Option<u16>> {
+    Ok(None)
+}
+
+#[cfg(unix)]
+fn services_path() -> &'static Path {
+    Path::new("/etc/services")
+}
+
+#[cfg(windows)]
+fn services_path() -> std::path::PathBuf {
+    let system_root =
+        std::env::var_os("SystemRoot").expect("Environtment variable SystemRoot not found");
+    Path::new(&system_root).join("System32\\drivers\\etc\\services")
+}
+
+/// Finds the port of the service in the services file, by its name or one of its aliases
+///
+/// Lines have the form `name port/protocol alias1 alias2 ...`, comments start with `#`.
+fn find_service_port(src: impl BufRead, service: &str) -> io::Result<Option<u16>> {
+    for line in src.lines() {
+        let line = line?;
+        let mut fields = line
+            .split('#')
+            .next()
+            .unwrap_or_default()
+            .split_whitespace();
+
+        let (Some(name), Some(port)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        if !name.eq_ignore_ascii_case(service)
+            && !fields.any(|alias| alias.eq_ignore_ascii_case(service))
+        {
+            continue;
+        }
+
+        let port = port.split('/').next().unwrap_or_default();
+        if let Ok(port) = port.parse::<u16>() {
+            return Ok(Some(port));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Orders the addresses by RFC 6724, with the source addresses the host would use
+pub(crate) fn sort_addrs(addrs: &mut [SocketAddr]) {
+    sort_by_rfc6724(addrs, source_addr);
+}
+
+/// Returns the source address of packets to the destination, `None` if there is no route
+///
+/// Connecting a UDP socket selects the source address without sending any packets.
+fn source_addr(destination: IpAddr) -> Option<IpAddr> {
+    let unspecified = match destination {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+
+    let socket = UdpSocket::bind(SocketAddr::new(unspecified, 0)).ok()?;
+    socket.connect(SocketAddr::new(destination, 9)).ok()?;
+    socket.local_addr().ok().map(|addr| addr.ip())
+}
+
+/// The attributes of an address for the ordering
+#[derive(Clone, Copy)]
+struct Attributes {
+    scope: u8,
+    precedence: u8,
+    label: u8,
+}
+
+impl Attributes {
+    fn new(addr: IpAddr) -> Self {
+        let (precedence, label) = policy(addr);
+        Self {
+            scope: scope(addr),
+            precedence,
+            label,
+        }
+    }
+}
+
+struct Candidate {
+    addr: SocketAddr,
+    destination: Attributes,
+    source: Option<(IpAddr, Attributes)>,
+}
+
+/// Orders the destination addresses, RFC 6724 section 6
+///
+/// Rules 3, 4 and 7 need information on the interfaces which isn't available, they are skipped.
+///  Like most implementations rule 9 is only applied to IPv6, applying it to IPv4 would defeat
+///  the round robin of DNS.
+fn sort_by_rfc6724(addrs: &mut [SocketAddr], source: impl Fn(IpAddr) -> Option<IpAddr>) {
+    let mut candidates = addrs
+        .iter()
+        .map(|addr| Candidate {
+            addr: *addr,
+            destination: Attributes::new(addr.ip()),
+            source: source(addr.ip()).map(|source| (source, Attributes::new(source))),
+        })
+        .collect::<Vec<_>>();
+
+    // the sort is stable, which is rule 10
+    candidates.sort_by(compare);
+
+    for (addr, candidate) in addrs.iter_mut().zip(candidates) {
+        *addr = candidate.addr;
+    }
+}
+
+/// Compares the candidates, `Less` if `a` is preferred
+fn compare(a: &Candidate, b: &Candidate) -> Ordering {
+    let (Some((a_source, a_source_attributes)), Some((b_source, b_source_attributes))) =
+        (a.source, b.source)
+    else {
+        // Rule 1: Avoid unusable destinations
+        return b.source.is_some().cmp(&a.source.is_some());
+    };
+    let (a_dst, b_dst) = (a.destination, b.destination);
+
+    // Rule 2: Prefer matching scope
+    let a_matches = a_dst.scope == a_source_attributes.scope;
+    let b_matches = b_dst.scope == b_source_attributes.scope;
+    if a_matches != b_matches {
+        return b_matches.cmp(&a_matches);
+    }
+
+    // Rule 5: Prefer matching label
+    let a_matches = a_dst.label == a_source_attributes.label;
+    let b_matches = b_dst.label == b_source_attributes.label;
+    if a_matches != b_matches {
+        return b_matches.cmp(&a_matches);
+    }
+
+    // Rule 6: Prefer higher precedence
+    if a_dst.precedence != b_dst.precedence {
+        return b_dst.precedence.cmp(&a_dst.precedence);
+    }
+
+    // Rule 8: Prefer smaller scope
+    if a_dst.scope != b_dst.scope {
+        return a_dst.scope.cmp(&b_dst.scope);
+    }
+
+    // Rule 9: Use longest matching prefix
+    if let (IpAddr::V6(a_addr), IpAddr::V6(a_source), IpAddr::V6(b_addr), IpAddr::V6(b_source)) =
+        (a.addr.ip(), a_source, b.addr.ip(), b_source)
+    {
+        let a_len = common_prefix_len(a_addr, a_source);
+        let b_len = common_prefix_len(b_addr, b_source);
+        return b_len.cmp(&a_len);
+    }
+
+    Ordering::Equal
+}
+
+/// The length of the common prefix, up to the 64 bits of the interface identifier
+fn common_prefix_len(a: Ipv6Addr, b: Ipv6Addr) -> u32 {
+    (u128::from(a) ^ u128::from(b)).leading_zeros().min(64)
+}
+
+const SCOPE_LINK_LOCAL: u8 = 0x2;
+const SCOPE_SITE_LOCAL: u8 = 0x5;
+const SCOPE_GLOBAL: u8 = 0xe;
+
+/// The scope of the address, RFC 6724 section 3.1
+fn scope(addr: IpAddr) -> u8 {
+    match addr {
+        IpAddr::V4(addr) if addr.is_loopback() || addr.is_link_local() => SCOPE_LINK_LOCAL,
+        IpAddr::V4(_) => SCOPE_GLOBAL,
+        IpAddr::V6(addr) if addr.is_multicast() => addr.octets()[1] & 0xf,
+        IpAddr::V6(addr) if addr.is_loopback() => SCOPE_LINK_LOCAL,
+        IpAddr::V6(addr) => match addr.segments()[0] & 0xffc0 {
+            0xfe80 => SCOPE_LINK_LOCAL,
+            0xfec0 => SCOPE_SITE_LOCAL,
+            _ => SCOPE_GLOBAL,
+        },
+    }
+}
+
+/// The default policy table, RFC 6724 section 2.1, as (prefix, length, precedence, label)
+///
+/// The entries are ordered from the longest to the shortest prefix, the first match is used.
+const POLICY_TABLE: [(u128, u32, u8, u8); 9] = [
+    (0x0000_0000_0000_0000_0000_0000_0000_0001, 128, 50, 0),
+    (0x0000_0000_0000_0000_0000_ffff_0000_0000, 96, 35, 4),
+    (0x0000_0000_0000_0000_0000_0000_0000_0000, 96, 1, 3),
+    (0x2001_0000_0000_0000_0000_0000_0000_0000, 32, 5, 5),
+    (0x2002_0000_0000_0000_0000_0000_0000_0000, 16, 30, 2),
+    (0x3ffe_0000_0000_0000_0000_0000_0000_0000, 16, 1, 12),
+    (0xfec0_0000_0000_0000_0000_0000_0000_0000, 10, 1, 11),
+    (0xfc00_0000_0000_0000_0000_0000_0000_0000, 7, 3, 13),
+    (0x0000_0000_0000_0000_0000_0000_0000_0000, 0, 40, 1),
+];
+
+/// The precedence and label of the address, IPv4 addresses are mapped to IPv6
+fn policy(addr: IpAddr) -> (u8, u8) {
+    let addr = u128::from(match addr {
+        IpAddr::V4(addr) => addr.to_ipv6_mapped(),
+        IpAddr::V6(addr) => addr,
+    });
+
+    POLICY_TABLE
+        .iter()
+        .find(|(prefix, len, _, _)| *len == 0 || (addr ^ prefix) >> (128 - len) == 0)
+        .map(|(_, _, precedence, label)| (*precedence, *label))
+        .unwrap_or((40, 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(destinations: &[&str], sources: &[(&str, &str)]) -> Vec<IpAddr> {
+        let mut addrs = destinations
+            .iter()
+            .map(|addr| SocketAddr::new(addr.parse().unwrap(), 443))
+            .collect::<Vec<_>>();
+        let sources = sources
+            .iter()
+            .map(|(dst, src)| (dst.parse().unwrap(), src.parse().unwrap()))
+            .collect::<Vec<(IpAddr, IpAddr)>>();
+
+        sort_by_rfc6724(&mut addrs, |dst| {
+            sources.iter().find(|(d, _)| *d == dst).map(|(_, s)| *s)
+        });
+        addrs.iter().map(SocketAddr::ip).collect()
+    }
+
+    fn ips(addrs: &[&str]) -> Vec<IpAddr> {
+        addrs.iter().map(|addr| addr.parse().unwrap()).collect()
+    }
+
+    // the examples of RFC 6724 section 10.2
+
+    #[test]
+    fn test_prefer_matching_scope() {
+        assert_eq!(
+            sorted(
+                &["198.51.100.121", "2001:db8:1::1"],
+                &[
+                    ("2001:db8:1::1", "2001:db8:1::2"),
+                    ("198.51.100.121", "169.254.13.78")
+                ]
+            ),
+            ips(&["2001:db8:1::1", "198.51.100.121"])
+        );
+
+        assert_eq!(
+            sorted(
+                &["2001:db8:1::1", "198.51.100.121"],
+                &[
+                    ("2001:db8:1::1", "fe80::1"),
+                    ("198.51.100.121", "198.51.100.117")
+                ]
+            ),
+            ips(&["198.51.100.121", "2001:db8:1::1"])
+        );
+    }
+
+    #[test]
+    fn test_prefer_higher_precedence() {
+        assert_eq!(
+            sorted(
+                &["10.1.2.3", "2001:db8:1::1"],
+                &[("2001:db8:1::1", "2001:db8:1::2"), ("10.1.2.3", "10.1.2.4")]
+            ),
+            ips(&["2001:db8:1::1", "10.1.2.3"])
+        );
+    }
+
+    #[test]
+    fn test_prefer_smaller_scope() {
+        assert_eq!(
+            sorted(
+                &["2001:db8:1::1", "fe80::1"],
+                &[("2001:db8:1::1", "2001:db8:1::2"), ("fe80::1", "fe80::2")]
+            ),
+            ips(&["fe80::1", "2001:db8:1::1"])
+        );
+    }
+
+    #[test]
+    fn test_prefer_matching_label() {
+        assert_eq!(
+            sorted(
+                &["2001:db8:1::1", "2002:c633:6401::1"],
+                &[
+                    ("2002:c633:6401::1", "2002:c633:6401::2"),
+                    ("2001:db8:1::1", "2002:c633:6401::2")
+                ]
+            ),
+            ips(&["2002:c633:6401::1", "2001:db8:1::1"])
+        );
+    }
+
+    #[test]
+    fn test_longest_matching_prefix() {
+        assert_eq!(
+            sorted(
+                &["2001:db8:2::1", "2001:db8:1::1"],
+                &[
+                    ("2001:db8:1::1", "2001:db8:1::2"),
+                    ("2001:db8:2::1", "2001:db8:1::2")
+                ]
+            ),
+            ips(&["2001:db8:1::1", "2001:db8:2::1"])
+        );
+    }
+
+    #[test]
+    fn test_avoid_unusable_and_keep_order() {
+        assert_eq!(
+            sorted(
+                &["2001:db8:1::1", "198.51.100.1", "198.51.100.2"],
+                &[
+                    ("198.51.100.1", "198.51.100.117"),
+                    ("198.51.100.2", "198.51.100.117")
+                ]
+            ),
+            ips(&["198.51.100.1", "198.51.100.2", "2001:db8:1::1"])
+        );
+    }
+
+    #[test]
+    fn test_find_service_port() {
+        let services = "\
+# comment
+ftp             21/tcp
+http            80/tcp          www www-http    # WorldWideWeb HTTP
+domain          53/udp
+";
+        let find = |service| find_service_port(services.as_bytes(), service).unwrap();
+        assert_eq!(find("http"), Some(80));
+        assert_eq!(find("WWW"), Some(80));
+        assert_eq!(find("domain"), Some(53));
+        assert_eq!(find("comment"), None);
+        assert_eq!(find("gopher"), None);
+    }
+
+    #[test]
+    fn test_service_port() {
+        assert_eq!(service_port("8443", true).unwrap(), 8443);
+        assert!(service_port("https", true).is_err());
+    }
+}
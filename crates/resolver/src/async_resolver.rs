@@ -7,29 +7,60 @@
 
 //! Structs for creating and using a AsyncResolver
 use std::fmt;
-use std::net::IpAddr;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
-
+#[cfg(all(unix, feature = "system-config"))]
+use std::sync::{Mutex, PoisonError};
+#[cfg(all(unix, feature = "system-config"))]
+use std::time::Duration;
+
+use futures_util::future::{join_all, FutureExt};
+use futures_util::stream::{self, Stream, StreamExt};
+use ipnet::IpNet;
 use proto::error::ProtoResult;
 use proto::op::Query;
 use proto::rr::domain::usage::ONION;
 use proto::rr::domain::TryParseIp;
 use proto::rr::{IntoName, Name, Record, RecordType};
-use proto::xfer::{DnsRequestOptions, RetryDnsHandle};
+use proto::udp::DuplicateResponseStats;
+use proto::xfer::{DnsRequestOptions, RetryDnsHandle, RetryPolicy};
+use proto::Time;
 use tracing::{debug, trace};
 
-use crate::caching_client::CachingClient;
-use crate::config::{ResolverConfig, ResolverOpts};
+use crate::addrinfo::{self, AddrInfoHints};
+use crate::blocklist::SharedBlocklist;
+use crate::caching_client::{CachingClient, NxDomainRecheckStats, QueryCoalescingStats};
+use crate::config::{
+    EffectiveResolverConfig, LookupIpStrategy, NxDomainRecheck, ResolverConfig, ResolverOpts,
+};
 use crate::dns_lru::{self, DnsLru};
 use crate::error::*;
+use crate::local_data::LocalData;
 use crate::lookup::{self, Lookup, LookupEither, LookupFuture};
 use crate::lookup_ip::{LookupIp, LookupIpFuture};
+#[cfg(feature = "wasm-bindgen")]
+use crate::name_server::FetchConnectionProvider;
+#[cfg(feature = "smol-runtime")]
+use crate::name_server::SmolConnectionProvider;
 #[cfg(feature = "tokio-runtime")]
 use crate::name_server::TokioConnectionProvider;
-use crate::name_server::{ConnectionProvider, NameServerPool};
-
+use crate::name_server::{
+    ConnectionProvider, NameServerHealthStats, NameServerPool, RuntimeProvider,
+};
+use crate::reverse_range::{self, ReverseNaming};
+use crate::target_lookup::{self, Target, TargetGroup};
+use crate::warm_up::{WarmUpCounters, WarmUpReport, WarmUpStats};
+
+use crate::hosts::HostsSource;
+#[cfg(all(unix, feature = "system-config"))]
+use crate::system_conf::ResolvConfWatch;
 use crate::Hosts;
 
+/// How often resolv.conf is checked for changes, see `ResolverOpts::watch_resolv_conf`
+#[cfg(all(unix, feature = "system-config"))]
+const RESOLV_CONF_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
 /// An asynchronous resolver for DNS generic over async Runtimes.
 ///
 /// Creating a `AsyncResolver` returns a new handle and a future that should
@@ -61,7 +92,18 @@ pub struct AsyncResolver<P: ConnectionProvider> {
     config: ResolverConfig,
     options: ResolverOpts,
     client_cache: CachingClient<LookupEither<P>>,
-    hosts: Option<Arc<Hosts>>,
+    /// the name servers of `client_cache`, which are replaced if `resolv_conf` changes
+    pool: NameServerPool<P>,
+    /// the caching clients of the scoped name servers, in the order of `ResolverConfig::scoped`
+    scoped: Vec<CachingClient<LookupEither<P>>>,
+    hosts: Option<HostsSource>,
+    blocklist: Option<Arc<SharedBlocklist>>,
+    local_data: Option<Arc<LocalData>>,
+    conn_provider: P,
+    retry_policy: Option<RetryPolicy>,
+    #[cfg(all(unix, feature = "system-config"))]
+    resolv_conf: Option<Arc<Mutex<ResolvConfWatch>>>,
+    warm_up_counters: Arc<WarmUpCounters>,
 }
 
 /// An AsyncResolver used with Tokio
@@ -69,6 +111,16 @@ pub struct AsyncResolver<P: ConnectionProvider> {
 #[cfg_attr(docsrs, doc(cfg(feature = "tokio-runtime")))]
 pub type TokioAsyncResolver = AsyncResolver<TokioConnectionProvider>;
 
+/// An AsyncResolver used with smol
+#[cfg(feature = "smol-runtime")]
+#[cfg_attr(docsrs, doc(cfg(feature = "smol-runtime")))]
+pub type SmolAsyncResolver = AsyncResolver<SmolConnectionProvider>;
+
+/// An AsyncResolver used on a JavaScript host, with DNS over HTTPS
+#[cfg(feature = "wasm-bindgen")]
+#[cfg_attr(docsrs, doc(cfg(feature = "wasm-bindgen")))]
+pub type WasmAsyncResolver = AsyncResolver<FetchConnectionProvider>;
+
 macro_rules! lookup_fn {
     ($p:ident, $l:ty, $r:path) => {
         /// Performs a lookup for the associated type.
@@ -136,6 +188,54 @@ impl TokioAsyncResolver {
     }
 }
 
+#[cfg(feature = "smol-runtime")]
+#[cfg_attr(docsrs, doc(cfg(feature = "smol-runtime")))]
+impl SmolAsyncResolver {
+    /// Construct a new smol based `AsyncResolver` with the provided configuration.
+    ///
+    /// The lookups can be awaited on any executor, the connections to the name servers are driven
+    ///  by the reactor and the global executor of smol.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - configuration, name_servers, etc. for the Resolver
+    /// * `options` - basic lookup options for the resolver
+    pub fn smol(config: ResolverConfig, options: ResolverOpts) -> Self {
+        Self::new(config, options, SmolConnectionProvider::default())
+    }
+
+    /// Constructs a new smol based Resolver with the system configuration.
+    ///
+    /// This will use `/etc/resolv.conf` on Unix OSes and the registry on Windows.
+    #[cfg(any(unix, target_os = "windows"))]
+    #[cfg(feature = "system-config")]
+    #[cfg_attr(
+        docsrs,
+        doc(cfg(all(feature = "system-config", any(unix, target_os = "windows"))))
+    )]
+    pub fn smol_from_system_conf() -> Result<Self, ResolveError> {
+        Self::from_system_conf(SmolConnectionProvider::default())
+    }
+}
+
+#[cfg(feature = "wasm-bindgen")]
+#[cfg_attr(docsrs, doc(cfg(feature = "wasm-bindgen")))]
+impl WasmAsyncResolver {
+    /// Construct a new `AsyncResolver` for a JavaScript host with the provided configuration.
+    ///
+    /// The name servers are queried with DNS over HTTPS using `fetch`, see
+    ///  [`FetchConnectionProvider`] for their URLs. The lookups must be awaited on the thread of
+    ///  the host, e.g. with `wasm_bindgen_futures::spawn_local`.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - configuration, name_servers, etc. for the Resolver
+    /// * `options` - basic lookup options for the resolver
+    pub fn wasm(config: ResolverConfig, options: ResolverOpts) -> Self {
+        Self::new(config, options, FetchConnectionProvider::default())
+    }
+}
+
 impl<R: ConnectionProvider> AsyncResolver<R> {
     /// Construct a new generic `AsyncResolver` with the provided configuration.
     ///
@@ -174,6 +274,31 @@ impl<R: ConnectionProvider> AsyncResolver<R> {
     /// Flushes/Removes all entries from the cache
     pub fn clear_cache(&self) {
         self.client_cache.clear_cache();
+        for client_cache in &self.scoped {
+            client_cache.clear_cache();
+        }
+    }
+
+    /// Returns the counters for NXDOMAIN responses to names with expired answers in the cache
+    ///
+    /// See [`ResolverOpts::nxdomain_recheck`] for how these responses are handled.
+    pub fn nxdomain_recheck_stats(&self) -> NxDomainRecheckStats {
+        self.client_cache.nxdomain_recheck_stats()
+    }
+
+    /// Returns the counters for the queries sent for lookups which missed the cache
+    ///
+    /// See [`ResolverOpts::coalesce_queries`] for when lookups wait for a query in flight.
+    pub fn query_coalescing_stats(&self) -> QueryCoalescingStats {
+        self.client_cache.query_coalescing_stats()
+    }
+
+    /// Returns the counters for responses received after an answer was accepted over UDP
+    ///
+    /// See [`ResolverOpts::duplicate_response_window`], conflicting responses may indicate a
+    ///  spoofing attempt.
+    pub fn duplicate_response_stats(&self) -> DuplicateResponseStats {
+        self.conn_provider.duplicate_response_stats()
     }
 
     /// Read the config for this resolver.
@@ -185,6 +310,29 @@ impl<R: ConnectionProvider> AsyncResolver<R> {
     pub fn options(&self) -> &ResolverOpts {
         &self.options
     }
+
+    /// Returns the configuration and options this resolver is running with
+    ///
+    /// This includes the values read from the system configuration, and the name servers of
+    ///  resolv.conf if it changed since the resolver was created, see
+    ///  [`ResolverOpts::watch_resolv_conf`].
+    pub fn effective_opts(&self) -> EffectiveResolverConfig {
+        #[allow(unused_mut)]
+        let mut config = self.config.clone();
+
+        #[cfg(all(unix, feature = "system-config"))]
+        if let Some(resolv_conf) = &self.resolv_conf {
+            let resolv_conf = resolv_conf.lock().unwrap_or_else(PoisonError::into_inner);
+            if let Some(name_servers) = resolv_conf.name_servers() {
+                config.set_name_servers(name_servers.to_vec().into());
+            }
+        }
+
+        EffectiveResolverConfig {
+            config,
+            options: self.options.clone(),
+        }
+    }
 }
 
 impl<P: ConnectionProvider> AsyncResolver<P> {
@@ -202,15 +350,97 @@ impl<P: ConnectionProvider> AsyncResolver<P> {
     /// documentation for `AsyncResolver` for more information on how to use
     /// the background future.
     pub fn new_with_conn(config: ResolverConfig, options: ResolverOpts, conn_provider: P) -> Self {
-        let pool =
-            NameServerPool::from_config_with_provider(&config, options.clone(), conn_provider);
+        Self::build(config, options, conn_provider, None)
+    }
+
+    fn build(
+        config: ResolverConfig,
+        options: ResolverOpts,
+        conn_provider: P,
+        retry_policy: Option<RetryPolicy>,
+    ) -> Self {
+        let retry_policy_or_default = retry_policy
+            .clone()
+            .unwrap_or_else(|| RetryPolicy::builder().attempts(options.attempts).build());
+        let pool = NameServerPool::from_config_with_provider(
+            &config,
+            options.clone(),
+            conn_provider.clone(),
+        );
+        let client_cache = Self::client_cache(
+            pool.clone(),
+            &options,
+            &conn_provider,
+            retry_policy_or_default.clone(),
+        );
+        let scoped = config
+            .scoped()
+            .iter()
+            .map(|scoped| {
+                let scoped_config =
+                    ResolverConfig::from_parts(None, vec![], scoped.name_servers.clone());
+                let pool = NameServerPool::from_config_with_provider(
+                    &scoped_config,
+                    options.clone(),
+                    conn_provider.clone(),
+                );
+                Self::client_cache(
+                    pool,
+                    &options,
+                    &conn_provider,
+                    retry_policy_or_default.clone(),
+                )
+            })
+            .collect();
+
+        let hosts = if options.use_hosts_file {
+            Some(HostsSource::system(options.hosts))
+        } else {
+            None
+        };
+
+        #[cfg(all(unix, feature = "system-config"))]
+        let resolv_conf = options.watch_resolv_conf.then(|| {
+            Arc::new(Mutex::new(ResolvConfWatch::new(
+                "/etc/resolv.conf",
+                RESOLV_CONF_CHECK_INTERVAL,
+            )))
+        });
+
+        trace!("handle passed back");
+        Self {
+            config,
+            client_cache,
+            pool,
+            scoped,
+            options,
+            hosts,
+            blocklist: None,
+            local_data: None,
+            conn_provider,
+            retry_policy,
+            #[cfg(all(unix, feature = "system-config"))]
+            resolv_conf,
+            warm_up_counters: Arc::default(),
+        }
+    }
+
+    fn client_cache(
+        pool: NameServerPool<P>,
+        options: &ResolverOpts,
+        conn_provider: &P,
+        retry_policy: RetryPolicy,
+    ) -> CachingClient<LookupEither<P>> {
+        let clock = conn_provider.clock();
         let either;
-        let client = RetryDnsHandle::new(pool, options.attempts);
+        let client = RetryDnsHandle::with_policy(pool, retry_policy);
         if options.validate {
             #[cfg(feature = "dnssec")]
             {
                 use proto::xfer::DnssecDnsHandle;
-                either = LookupEither::Secure(DnssecDnsHandle::new(client));
+                either = LookupEither::Secure(
+                    DnssecDnsHandle::new(client).with_clock(Arc::clone(&clock)),
+                );
             }
 
             #[cfg(not(feature = "dnssec"))]
@@ -223,20 +453,48 @@ impl<P: ConnectionProvider> AsyncResolver<P> {
             either = LookupEither::Retry(client);
         }
 
-        let hosts = if options.use_hosts_file {
-            Some(Arc::new(Hosts::new()))
-        } else {
-            None
-        };
+        let lru = DnsLru::new(options.cache_size, dns_lru::TtlConfig::from_opts(options))
+            .keep_stale(options.nxdomain_recheck != NxDomainRecheck::Disabled)
+            .max_rrset_records(options.max_rrset_records);
+        CachingClient::with_cache(lru, either, options.preserve_intermediates)
+            .with_nxdomain_recheck(options.nxdomain_recheck)
+            .with_coalesce_queries(options.coalesce_queries)
+            .with_clock(clock)
+    }
 
-        trace!("handle passed back");
-        let lru = DnsLru::new(options.cache_size, dns_lru::TtlConfig::from_opts(&options));
-        Self {
+    /// Replaces the configuration and options of this resolver, e.g. when the system
+    ///  configuration changed, see `system_conf::SystemConfWatcher`
+    ///
+    /// The caches are cleared, and hosts set with `set_hosts` are replaced by those of the options.
+    ///  The blocklist and local data set with `set_blocklist` and `set_local_data` are kept. Clones
+    ///  of this resolver keep the previous configuration.
+    pub fn reconfigure(&mut self, config: ResolverConfig, options: ResolverOpts) {
+        let blocklist = self.blocklist.take();
+        let local_data = self.local_data.take();
+        *self = Self::build(
             config,
-            client_cache: CachingClient::with_cache(lru, either, options.preserve_intermediates),
             options,
-            hosts,
-        }
+            self.conn_provider.clone(),
+            self.retry_policy.clone(),
+        );
+        self.blocklist = blocklist;
+        self.local_data = local_data;
+    }
+
+    /// Reattempts failed queries as specified by the policy, instead of `ResolverOpts::attempts`
+    ///
+    /// The policy applies to the queries to the name servers, after all of them were tried. The
+    ///  caches are cleared, as with `reconfigure`.
+    pub fn with_retry_policy(self, retry_policy: RetryPolicy) -> Self {
+        let mut resolver = Self::build(
+            self.config,
+            self.options,
+            self.conn_provider,
+            Some(retry_policy),
+        );
+        resolver.blocklist = self.blocklist;
+        resolver.local_data = self.local_data;
+        resolver
     }
 
     /// Constructs a new Resolver with the system configuration.
@@ -253,6 +511,73 @@ impl<P: ConnectionProvider> AsyncResolver<P> {
         Ok(Self::new_with_conn(config, options, conn_provider))
     }
 
+    /// Resolves the names of [`ResolverOpts::warm_up`], so that their answers are cached
+    ///
+    /// This is meant to be awaited at startup, before the resolver is used, so that the names of
+    ///  critical dependencies do not have to be looked up when they are first needed. The names
+    ///  are looked up concurrently, as with `lookup_ip`.
+    pub async fn warm_up(&self) -> WarmUpReport {
+        let lookups = self.options.warm_up.iter().map(|name| async move {
+            let result = self.lookup_ip(name.clone()).await;
+            (name.clone(), result)
+        });
+
+        let mut report = WarmUpReport::default();
+        for (name, result) in join_all(lookups).await {
+            match result {
+                Ok(_) => report.resolved.push(name),
+                Err(e) => {
+                    debug!("failed to warm up {name}: {e}");
+                    report.failed.push((name, e));
+                }
+            }
+        }
+
+        self.warm_up_counters.record(&report);
+        report
+    }
+
+    /// Flushes the caches, then resolves the names of [`ResolverOpts::warm_up`] again
+    ///
+    /// See [`Self::warm_up`].
+    pub async fn clear_cache_and_warm_up(&self) -> WarmUpReport {
+        self.client_cache.clear_cache();
+        for client_cache in &self.scoped {
+            client_cache.clear_cache();
+        }
+
+        self.warm_up().await
+    }
+
+    /// Returns the counters of all warm-ups of this resolver and its clones
+    pub fn warm_up_stats(&self) -> WarmUpStats {
+        self.warm_up_counters.stats()
+    }
+
+    /// Returns the health and latency of the name servers, see [`ResolverOpts::health`]
+    ///
+    /// The name servers of [`ResolverConfig::scoped`] are not included.
+    pub fn name_server_health(&self) -> Vec<NameServerHealthStats> {
+        self.pool.health_stats()
+    }
+
+    /// Probes the name servers every `HealthOpts::probe_interval`, this never completes
+    ///
+    /// Without probes, a demoted name server is only promoted again after its backoff expired,
+    ///  or when it answered a query sent after all other name servers failed. The probes also
+    ///  detect failures of name servers which are rarely queried. This should be spawned on the
+    ///  runtime of the resolver, e.g. with `tokio::spawn(resolver.health_probes())`.
+    pub fn health_probes(&self) -> impl Future<Output = ()> + Send + 'static {
+        let pool = self.pool.clone();
+        let interval = self.options.health.probe_interval;
+        async move {
+            loop {
+                <P::RuntimeProvider as RuntimeProvider>::Timer::delay_for(interval).await;
+                pool.probe().await;
+            }
+        }
+    }
+
     /// Per request options based on the ResolverOpts
     pub(crate) fn request_options(&self) -> DnsRequestOptions {
         let mut request_opts = DnsRequestOptions::default();
@@ -288,12 +613,123 @@ impl<P: ConnectionProvider> AsyncResolver<P> {
             .await
     }
 
+    /// Looks up many names concurrently, with at most [`ResolverOpts::batch_concurrency`] lookups
+    ///  in flight at a time
+    ///
+    /// # Arguments
+    ///
+    /// * `queries` - the names and types of the records to lookup
+    ///
+    /// # Returns
+    ///
+    /// The result of each lookup, in the order of `queries`
+    pub async fn lookup_batch<I, N>(&self, queries: I) -> Vec<Result<Lookup, ResolveError>>
+    where
+        I: IntoIterator<Item = (N, RecordType)>,
+        N: IntoName,
+    {
+        stream::iter(queries)
+            .map(|(name, record_type)| self.lookup(name, record_type))
+            .buffered(self.options.batch_concurrency.max(1))
+            .collect()
+            .await
+    }
+
+    /// Looks up many names concurrently, like [`Self::lookup_batch`], but yields each result as
+    ///  soon as its lookup completed
+    ///
+    /// # Returns
+    ///
+    /// A stream of the results, each with the position of its query in `queries`
+    pub fn lookup_batch_stream<'a, I, N>(
+        &'a self,
+        queries: I,
+    ) -> impl Stream<Item = (usize, Result<Lookup, ResolveError>)> + 'a
+    where
+        I: IntoIterator<Item = (N, RecordType)>,
+        I::IntoIter: 'a,
+        N: IntoName + 'a,
+    {
+        stream::iter(queries.into_iter().enumerate())
+            .map(move |(idx, (name, record_type))| {
+                self.lookup(name, record_type)
+                    .map(move |result| (idx, result))
+            })
+            .buffer_unordered(self.options.batch_concurrency.max(1))
+    }
+
+    /// Looks up the PTR records of each address in the network, with at most
+    ///  [`ResolverOpts::batch_concurrency`] lookups in flight at a time
+    ///
+    /// # Arguments
+    ///
+    /// * `net` - the network, whose network and broadcast addresses are included
+    /// * `naming` - how the names of the PTR records are formed, see `ReverseNaming`
+    ///
+    /// # Returns
+    ///
+    /// A stream of the results, each with its address, in the order the lookups completed
+    pub fn reverse_lookup_range(
+        &self,
+        net: IpNet,
+        naming: ReverseNaming,
+    ) -> impl Stream<Item = (IpAddr, Result<lookup::ReverseLookup, ResolveError>)> + '_ {
+        stream::iter(reverse_range::reverse_names(net, naming))
+            .map(move |(addr, name)| {
+                self.inner_lookup(name, RecordType::PTR, self.request_options())
+                    .map(move |result| (addr, result))
+            })
+            .buffer_unordered(self.options.batch_concurrency.max(1))
+    }
+
     fn push_name(name: Name, names: &mut Vec<Name>) {
         if !names.contains(&name) {
             names.push(name);
         }
     }
 
+    /// The caching client of the scoped name servers of the first name in their domain, otherwise
+    ///  the default one
+    fn client_cache_for(&self, names: &[Name]) -> CachingClient<LookupEither<P>> {
+        self.reload_resolv_conf();
+
+        names
+            .iter()
+            .find_map(|name| self.config.scoped_for(name))
+            .and_then(|scoped| {
+                let idx = self
+                    .config
+                    .scoped()
+                    .iter()
+                    .position(|other| std::ptr::eq(other, scoped))?;
+                self.scoped.get(idx)
+            })
+            .unwrap_or(&self.client_cache)
+            .clone()
+    }
+
+    /// Replaces the name servers by those of resolv.conf if it changed, see
+    ///  `ResolverOpts::watch_resolv_conf`
+    fn reload_resolv_conf(&self) {
+        #[cfg(all(unix, feature = "system-config"))]
+        if let Some(resolv_conf) = &self.resolv_conf {
+            let changed = resolv_conf
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .poll_changed();
+            if let Some(config) = changed {
+                debug!("resolv.conf changed, replacing the name servers");
+                let pool = NameServerPool::from_config_with_provider(
+                    &config,
+                    self.options.clone(),
+                    self.conn_provider.clone(),
+                );
+                self.pool.replace_name_servers(&pool);
+                self.client_cache.clear_cache();
+            }
+        }
+    }
+
     fn build_names(&self, name: Name) -> Vec<Name> {
         // if it's fully qualified, we can short circuit the lookup logic
         if name.is_fqdn()
@@ -318,8 +754,12 @@ impl<P: ConnectionProvider> AsyncResolver<P> {
             let raw_name_first: bool =
                 name.num_labels() as usize > self.options.ndots || name.is_localhost();
 
+            // names without a dot are not looked up as they are, see no-tld-query in resolv.conf
+            let raw_name =
+                !self.options.no_tld_query || name.num_labels() > 1 || name.is_localhost();
+
             // if not meeting ndots, we always do the raw name in the final lookup
-            if !raw_name_first {
+            if !raw_name_first && raw_name {
                 names.push(name.clone());
             }
 
@@ -348,7 +788,7 @@ impl<P: ConnectionProvider> AsyncResolver<P> {
             }
 
             // this is the direct name lookup
-            if raw_name_first {
+            if raw_name_first && raw_name {
                 // adding the name as though it's an FQDN for lookup
                 names.push(name);
             }
@@ -366,8 +806,23 @@ impl<P: ConnectionProvider> AsyncResolver<P> {
     where
         L: From<Lookup> + Send + 'static,
     {
+        // reverse lookups of the addresses in the hosts file
+        if record_type == RecordType::PTR {
+            if let Some(hosts) = &self.hosts {
+                let query = Query::query(name.clone(), record_type);
+                if let Some(lookup) = hosts.hosts().lookup_static_host(&query) {
+                    return Ok(L::from(lookup));
+                }
+            }
+        }
+
         let names = self.build_names(name);
-        LookupFuture::lookup(names, record_type, options, self.client_cache.clone())
+        if let Some(local) = self.local_lookup(&names, &[record_type]) {
+            return local.map(L::from);
+        }
+
+        let client_cache = self.client_cache_for(&names);
+        LookupFuture::lookup(names, record_type, options, client_cache)
             .await
             .map(L::from)
     }
@@ -381,6 +836,73 @@ impl<P: ConnectionProvider> AsyncResolver<P> {
     pub async fn lookup_ip<N: IntoName + TryParseIp>(
         &self,
         host: N,
+    ) -> Result<LookupIp, ResolveError> {
+        self.lookup_ip_with_strategy(host, self.options.ip_strategy)
+            .await
+    }
+
+    /// Looks up the socket addresses of a host and service, like `getaddrinfo`
+    ///
+    /// The host is an IP address, a name in the hosts file if `ResolverOpts::use_hosts_file` is
+    ///  set, or else looked up like `lookup_ip`. The service is a port number or a name in the
+    ///  services file of the system, the port is 0 without a service. The addresses are ordered
+    ///  by the destination address selection of RFC 6724, the first is the one to connect to
+    ///  first.
+    ///
+    /// # Arguments
+    ///
+    /// * `host` - string hostname, if this is an invalid hostname, an error will be returned.
+    /// * `service` - the port number or the name of the service
+    /// * `hints` - the address families to return and how the service is resolved
+    pub async fn lookup_host_ordered<N: IntoName + TryParseIp>(
+        &self,
+        host: N,
+        service: Option<&str>,
+        hints: AddrInfoHints,
+    ) -> Result<Vec<SocketAddr>, ResolveError> {
+        let port = match service {
+            Some(service) => addrinfo::service_port(service, hints.numeric_service)?,
+            None => 0,
+        };
+
+        let strategy = match hints.families() {
+            (true, true) => LookupIpStrategy::Ipv4AndIpv6,
+            (true, false) => LookupIpStrategy::Ipv4Only,
+            (false, true) => LookupIpStrategy::Ipv6Only,
+            (false, false) => {
+                return Err(ResolveErrorKind::Message(
+                    "the host has no route for the requested address family",
+                )
+                .into())
+            }
+        };
+
+        let lookup = self.lookup_ip_with_strategy(host, strategy).await?;
+        let mut addrs = lookup
+            .iter()
+            .filter(|ip| match strategy {
+                LookupIpStrategy::Ipv4Only => ip.is_ipv4(),
+                LookupIpStrategy::Ipv6Only => ip.is_ipv6(),
+                _ => true,
+            })
+            .map(|ip| SocketAddr::new(ip, port))
+            .collect::<Vec<_>>();
+
+        if addrs.is_empty() {
+            return Err(ResolveErrorKind::Message(
+                "the host has no address of the requested family",
+            )
+            .into());
+        }
+
+        addrinfo::sort_addrs(&mut addrs);
+        Ok(addrs)
+    }
+
+    async fn lookup_ip_with_strategy<N: IntoName + TryParseIp>(
+        &self,
+        host: N,
+        ip_strategy: LookupIpStrategy,
     ) -> Result<LookupIp, ResolveError> {
         let mut finally_ip_addr: Option<Record> = None;
         let maybe_ip = host.try_parse_ip();
@@ -418,12 +940,25 @@ impl<P: ConnectionProvider> AsyncResolver<P> {
         };
 
         let names = self.build_names(name);
-        let hosts = self.hosts.as_ref().cloned();
+        let record_types: &[RecordType] = match ip_strategy {
+            LookupIpStrategy::Ipv4Only => &[RecordType::A],
+            LookupIpStrategy::Ipv6Only => &[RecordType::AAAA],
+            LookupIpStrategy::Ipv4AndIpv6 | LookupIpStrategy::Ipv4thenIpv6 => {
+                &[RecordType::A, RecordType::AAAA]
+            }
+            LookupIpStrategy::Ipv6thenIpv4 => &[RecordType::AAAA, RecordType::A],
+        };
+        if let Some(local) = self.local_lookup(&names, record_types) {
+            return local.map(LookupIp::from);
+        }
+
+        let hosts = self.hosts.as_ref().map(HostsSource::hosts);
+        let client_cache = self.client_cache_for(&names);
 
         LookupIpFuture::lookup(
             names,
-            self.options.ip_strategy,
-            self.client_cache.clone(),
+            ip_strategy,
+            client_cache,
             self.request_options(),
             hosts,
             finally_ip_addr.and_then(Record::into_data),
@@ -433,7 +968,41 @@ impl<P: ConnectionProvider> AsyncResolver<P> {
 
     /// Customizes the static hosts used in this resolver.
     pub fn set_hosts(&mut self, hosts: Option<Hosts>) {
-        self.hosts = hosts.map(Arc::new);
+        self.hosts = hosts.map(|hosts| HostsSource::Fixed(Arc::new(hosts)));
+    }
+
+    /// Sets the blocklist of this resolver, see the [`blocklist`](crate::blocklist) module
+    ///
+    /// Lookups of blocked names are answered by the blocklist, without querying the name servers
+    ///  or the hosts file. The blocklist is shared, e.g. with the clones of this resolver, and
+    ///  replaced when its lists change with `SharedBlocklist::reload_if_changed`.
+    pub fn set_blocklist(&mut self, blocklist: Option<Arc<SharedBlocklist>>) {
+        self.blocklist = blocklist;
+    }
+
+    /// Sets the local records of this resolver, see the [`local_data`](crate::local_data) module
+    ///
+    /// Lookups of names with local records are answered by them, without querying the name
+    ///  servers or the hosts file, and before the blocklist is checked.
+    pub fn set_local_data(&mut self, local_data: Option<Arc<LocalData>>) {
+        self.local_data = local_data;
+    }
+
+    /// The answer of the local data, or of the blocklist if any of the names is blocked
+    fn local_lookup(
+        &self,
+        names: &[Name],
+        record_types: &[RecordType],
+    ) -> Option<Result<Lookup, ResolveError>> {
+        if let Some(local) = self
+            .local_data
+            .as_ref()
+            .and_then(|local_data| local_data.lookup_names(names, record_types))
+        {
+            return Some(local);
+        }
+
+        self.blocklist.as_ref()?.lookup(names, record_types)
     }
 
     lookup_fn!(
@@ -448,8 +1017,93 @@ impl<P: ConnectionProvider> AsyncResolver<P> {
     lookup_fn!(ns_lookup, lookup::NsLookup, RecordType::NS);
     lookup_fn!(soa_lookup, lookup::SoaLookup, RecordType::SOA);
     lookup_fn!(srv_lookup, lookup::SrvLookup, RecordType::SRV);
+    #[cfg(feature = "rdata-tlsa")]
     lookup_fn!(tlsa_lookup, lookup::TlsaLookup, RecordType::TLSA);
     lookup_fn!(txt_lookup, lookup::TxtLookup, RecordType::TXT);
+
+    /// Looks up the MX records of the name, along with the addresses of the mail exchangers
+    ///
+    /// The exchangers are grouped by preference, and shuffled within their group as required by
+    ///  RFC 5321. See [`Self::lookup_srv_with_ips`] for how the addresses are resolved.
+    pub async fn lookup_mx_with_ips<N: IntoName>(
+        &self,
+        query: N,
+    ) -> Result<Vec<TargetGroup>, ResolveError> {
+        let lookup = self.mx_lookup(query).await?;
+        let targets = lookup
+            .iter()
+            .map(|mx| (mx.preference(), Target::new(mx.exchange().clone(), 0, 0)))
+            .collect();
+        let groups = target_lookup::group_targets(targets, false, &mut rand::thread_rng());
+
+        Ok(self.resolve_targets(lookup.as_lookup(), groups).await)
+    }
+
+    /// Looks up the SRV records of the name, along with the addresses of the targets
+    ///
+    /// The targets are grouped by priority, and ordered within their group by the weighted
+    ///  selection of RFC 2782. Duplicate targets are only returned once, and a target of `.`
+    ///  means that the service is not available, in which case no targets are returned.
+    ///
+    /// The addresses are taken from the additional section of the response when present,
+    ///  otherwise they are looked up as with `lookup_ip`, once for each name.
+    pub async fn lookup_srv_with_ips<N: IntoName>(
+        &self,
+        query: N,
+    ) -> Result<Vec<TargetGroup>, ResolveError> {
+        let lookup = self.srv_lookup(query).await?;
+        let targets = lookup
+            .iter()
+            .map(|srv| {
+                let target = Target::new(srv.target().clone(), srv.port(), srv.weight());
+                (srv.priority(), target)
+            })
+            .collect();
+        let groups = target_lookup::group_targets(targets, true, &mut rand::thread_rng());
+
+        Ok(self.resolve_targets(lookup.as_lookup(), groups).await)
+    }
+
+    /// Fills in the addresses of the targets, from the lookup of their records or with lookups
+    async fn resolve_targets(
+        &self,
+        lookup: &Lookup,
+        mut groups: Vec<TargetGroup>,
+    ) -> Vec<TargetGroup> {
+        let mut names = Vec::<Name>::new();
+        for target in groups.iter().flat_map(|group| &group.targets) {
+            if !names.contains(&target.name) {
+                names.push(target.name.clone());
+            }
+        }
+
+        let ips = join_all(names.into_iter().map(|name| async move {
+            let ips = target_lookup::additional_ips(lookup, &name);
+            if !ips.is_empty() {
+                return (name, ips);
+            }
+
+            match self.lookup_ip(name.clone()).await {
+                Ok(lookup) => {
+                    let ips = lookup.iter().collect();
+                    (name, ips)
+                }
+                Err(e) => {
+                    debug!("failed to resolve the addresses of {name}: {e}");
+                    (name, vec![])
+                }
+            }
+        }))
+        .await;
+
+        for target in groups.iter_mut().flat_map(|group| &mut group.targets) {
+            if let Some((_, target_ips)) = ips.iter().find(|(name, _)| *name == target.name) {
+                target.ips = target_ips.clone();
+            }
+        }
+
+        groups
+    }
 }
 
 impl<P: ConnectionProvider> fmt::Debug for AsyncResolver<P> {
@@ -472,6 +1126,10 @@ pub mod testing {
     use crate::AsyncResolver;
     use proto::{rr::Name, Executor};
 
+    #[cfg(all(feature = "testing", feature = "tokio-runtime"))]
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "testing", feature = "tokio-runtime"))))]
+    pub use crate::mock_name_server::{MockNameServer, MockNameServerBuilder, MockResponse};
+
     /// Test IP lookup from URLs.
     pub fn lookup_test<E: Executor, R: ConnectionProvider>(
         config: ResolverConfig,
@@ -1043,6 +1701,10 @@ pub mod testing {
 #[cfg(feature = "tokio-runtime")]
 #[allow(clippy::extra_unused_type_parameters)]
 mod tests {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    use proto::error::{ProtoError, ProtoErrorKind};
+    use proto::op::ResponseCode;
     use proto::xfer::DnsRequest;
     use tokio::runtime::Runtime;
 
@@ -1223,6 +1885,165 @@ mod tests {
         localhost_ipv6_test::<Runtime, TokioConnectionProvider>(io_loop, handle);
     }
 
+    #[test]
+    fn test_lookup_host_ordered() {
+        use crate::addrinfo::AddressFamily;
+
+        let io_loop = Runtime::new().expect("failed to create tokio runtime io_loop");
+        let resolver = AsyncResolver::new(
+            ResolverConfig::default(),
+            ResolverOpts::default(),
+            TokioConnectionProvider::default(),
+        );
+
+        let hints = AddrInfoHints {
+            addr_config: false,
+            ..AddrInfoHints::default()
+        };
+        let addrs = io_loop
+            .block_on(resolver.lookup_host_ordered("127.0.0.1", Some("8080"), hints))
+            .expect("failed to run lookup");
+        assert_eq!(addrs, vec![SocketAddr::from(([127, 0, 0, 1], 8080))]);
+
+        let hints = AddrInfoHints {
+            family: AddressFamily::Ipv6,
+            ..hints
+        };
+        assert!(io_loop
+            .block_on(resolver.lookup_host_ordered("127.0.0.1", None, hints))
+            .is_err());
+    }
+
+    #[test]
+    fn test_warm_up() {
+        let io_loop = Runtime::new().expect("failed to create tokio runtime io_loop");
+
+        let options = ResolverOpts {
+            warm_up: vec![
+                Name::from_ascii("localhost.").unwrap(),
+                Name::from_ascii("www.example.com.").unwrap(),
+            ],
+            ..ResolverOpts::default()
+        };
+        // no name servers, only localhost can be resolved
+        let resolver = AsyncResolver::new(
+            ResolverConfig::new(),
+            options,
+            TokioConnectionProvider::default(),
+        );
+
+        let report = io_loop.block_on(resolver.warm_up());
+        assert!(!report.is_complete());
+        assert_eq!(
+            report.resolved,
+            vec![Name::from_ascii("localhost.").unwrap()]
+        );
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(
+            report.failed[0].0,
+            Name::from_ascii("www.example.com.").unwrap()
+        );
+
+        io_loop.block_on(resolver.clear_cache_and_warm_up());
+        let stats = resolver.warm_up_stats();
+        assert_eq!((stats.runs, stats.resolved, stats.failed), (2, 2, 2));
+    }
+
+    #[test]
+    fn test_lookup_batch() {
+        let io_loop = Runtime::new().expect("failed to create tokio runtime io_loop");
+
+        let options = ResolverOpts {
+            batch_concurrency: 2,
+            ..ResolverOpts::default()
+        };
+        // no name servers, only localhost can be resolved
+        let resolver = AsyncResolver::new(
+            ResolverConfig::new(),
+            options,
+            TokioConnectionProvider::default(),
+        );
+        let queries = [
+            ("localhost.", RecordType::A),
+            ("www.example.com.", RecordType::A),
+            ("localhost.", RecordType::AAAA),
+        ];
+
+        let results = io_loop.block_on(resolver.lookup_batch(queries));
+        assert_eq!(results.len(), 3);
+        assert_eq!(
+            results[0].as_ref().unwrap().query().query_type(),
+            RecordType::A
+        );
+        assert!(results[1].is_err());
+        assert_eq!(
+            results[2].as_ref().unwrap().query().query_type(),
+            RecordType::AAAA
+        );
+
+        let mut results: Vec<_> = io_loop.block_on(resolver.lookup_batch_stream(queries).collect());
+        results.sort_by_key(|(idx, _)| *idx);
+        assert_eq!(
+            results
+                .iter()
+                .map(|(idx, result)| (*idx, result.is_ok()))
+                .collect::<Vec<_>>(),
+            vec![(0, true), (1, false), (2, true)]
+        );
+    }
+
+    #[test]
+    fn test_reverse_lookup_range() {
+        let io_loop = Runtime::new().expect("failed to create tokio runtime io_loop");
+
+        // no name servers, only the loopback addresses can be resolved
+        let resolver = AsyncResolver::new(
+            ResolverConfig::new(),
+            ResolverOpts::default(),
+            TokioConnectionProvider::default(),
+        );
+
+        let net = "127.0.0.0/30".parse().unwrap();
+        let mut results: Vec<_> = io_loop.block_on(
+            resolver
+                .reverse_lookup_range(net, ReverseNaming::Standard)
+                .collect(),
+        );
+        results.sort_by_key(|(addr, _)| *addr);
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[3].0, IpAddr::from([127, 0, 0, 3]));
+        assert_eq!(
+            results[3].1.as_ref().unwrap().iter().next().unwrap().0,
+            Name::from_ascii("localhost.").unwrap()
+        );
+
+        let net = "192.0.2.0/31".parse().unwrap();
+        let results: Vec<_> = io_loop.block_on(
+            resolver
+                .reverse_lookup_range(net, ReverseNaming::Classless)
+                .collect(),
+        );
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|(_, result)| result.is_err()));
+    }
+
+    #[test]
+    fn test_effective_opts() {
+        let options = ResolverOpts {
+            cache_size: 16,
+            ..ResolverOpts::default()
+        };
+        let resolver = AsyncResolver::new(
+            ResolverConfig::quad9(),
+            options.clone(),
+            TokioConnectionProvider::default(),
+        );
+
+        let effective = resolver.effective_opts();
+        assert_eq!(effective.config, ResolverConfig::quad9());
+        assert_eq!(effective.options, options);
+    }
+
     #[test]
     fn test_search_ipv4_large_ndots() {
         use super::testing::search_ipv4_large_ndots_test;
@@ -1275,4 +2096,125 @@ mod tests {
             assert_eq!(resolver.build_names(name.clone()).len(), 2);
         }
     }
+
+    #[test]
+    fn test_build_names_no_tld_query() {
+        let handle = TokioConnectionProvider::default();
+        let mut config = ResolverConfig::default();
+        config.add_search(Name::from_ascii("example.com.").unwrap());
+        let options = ResolverOpts {
+            no_tld_query: true,
+            ..ResolverOpts::default()
+        };
+        let resolver = AsyncResolver::<TokioConnectionProvider>::new(config, options, handle);
+
+        assert_eq!(
+            resolver.build_names(Name::from_ascii("www").unwrap()),
+            vec![Name::from_ascii("www.example.com.").unwrap()]
+        );
+        assert_eq!(
+            resolver
+                .build_names(Name::from_ascii("www.example").unwrap())
+                .len(),
+            2
+        );
+        assert_eq!(
+            resolver
+                .build_names(Name::from_ascii("localhost").unwrap())
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_blocklist() {
+        use crate::blocklist::{BlockAction, Blocklist, ListFormat, SharedBlocklist};
+
+        let io_loop = Runtime::new().expect("failed to create tokio runtime io_loop");
+        let mut resolver = AsyncResolver::<TokioConnectionProvider>::new(
+            ResolverConfig::default(),
+            ResolverOpts::default(),
+            TokioConnectionProvider::default(),
+        );
+        let mut blocklist = Blocklist::new(BlockAction::Null);
+        blocklist
+            .read("||ads.example.com^\n".as_bytes(), ListFormat::AdBlock)
+            .unwrap();
+        resolver.set_blocklist(Some(Arc::new(SharedBlocklist::new(blocklist))));
+
+        let lookup = io_loop
+            .block_on(resolver.lookup_ip("www.ads.example.com."))
+            .unwrap();
+        assert_eq!(
+            lookup.iter().collect::<Vec<_>>(),
+            vec![
+                IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+            ]
+        );
+
+        let err = io_loop
+            .block_on(resolver.lookup("ads.example.com.", RecordType::MX))
+            .unwrap_err();
+        assert!(
+            matches!(
+                err.proto().map(ProtoError::kind),
+                Some(ProtoErrorKind::NoRecordsFound {
+                    response_code: ResponseCode::NoError,
+                    ..
+                })
+            ),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_local_data() {
+        use crate::blocklist::{BlockAction, Blocklist, SharedBlocklist};
+        use std::str::FromStr;
+
+        use crate::local_data::LocalData;
+        use proto::rr::{rdata::A, RData};
+
+        let io_loop = Runtime::new().expect("failed to create tokio runtime io_loop");
+        let mut resolver = AsyncResolver::<TokioConnectionProvider>::new(
+            ResolverConfig::default(),
+            ResolverOpts::default(),
+            TokioConnectionProvider::default(),
+        );
+        let mut local_data = LocalData::new();
+        local_data.insert(Record::from_rdata(
+            Name::from_str("*.example.com.").unwrap(),
+            300,
+            RData::A(A::new(192, 0, 2, 1)),
+        ));
+        resolver.set_local_data(Some(Arc::new(local_data)));
+
+        // the local data overrides the blocklist
+        let mut blocklist = Blocklist::new(BlockAction::NxDomain);
+        blocklist.block(&Name::from_str("example.com.").unwrap(), true);
+        resolver.set_blocklist(Some(Arc::new(SharedBlocklist::new(blocklist))));
+
+        let lookup = io_loop
+            .block_on(resolver.lookup_ip("www.example.com."))
+            .unwrap();
+        assert_eq!(
+            lookup.iter().collect::<Vec<_>>(),
+            vec![IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))]
+        );
+
+        let err = io_loop
+            .block_on(resolver.lookup("www.example.com.", RecordType::MX))
+            .unwrap_err();
+        assert!(
+            matches!(
+                err.proto().map(ProtoError::kind),
+                Some(ProtoErrorKind::NoRecordsFound {
+                    response_code: ResponseCode::NoError,
+                    ..
+                })
+            ),
+            "{err}"
+        );
+    }
 }
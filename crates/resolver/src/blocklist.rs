@@ -0,0 +1,918 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Blocking of queries by name, e.g. of the domains of advertising and tracking
+//!
+//! A [`Blocklist`] holds the names of lists in the formats of hosts files, AdBlock filters,
+//!  response policy zones and plain domains, in a trie of their labels. Queries of blocked names
+//!  are answered with the [`BlockAction`] of the blocklist, without being resolved.
+//!
+//! ```
+//! use std::str::FromStr;
+//!
+//! use hickory_resolver::blocklist::{BlockAction, Blocked, Blocklist, ListFormat};
+//! use hickory_resolver::proto::rr::{Name, RecordType};
+//!
+//! let mut blocklist = Blocklist::new(BlockAction::Null);
+//! blocklist
+//!     .read("||ads.example.com^\n@@||good.ads.example.com^\n".as_bytes(), ListFormat::AdBlock)
+//!     .unwrap();
+//!
+//! let name = Name::from_str("tracker.ads.example.com.").unwrap();
+//! assert!(matches!(blocklist.check(&name, RecordType::A), Some(Blocked::Records(_))));
+//! let name = Name::from_str("good.ads.example.com.").unwrap();
+//! assert!(blocklist.check(&name, RecordType::A).is_none());
+//! ```
+//!
+//! A [`SharedBlocklist`] reads the lists from files, and reads them again when they changed.
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use parking_lot::RwLock;
+use proto::error::ProtoError;
+use proto::op::{Query, ResponseCode};
+use proto::rr::rdata::{A, AAAA};
+use proto::rr::{Name, RData, Record, RecordType};
+use tracing::{debug, info, warn};
+
+use crate::error::ResolveError;
+use crate::lookup::Lookup;
+
+/// The default TTL of the answers for blocked names, in seconds
+pub const DEFAULT_TTL: u32 = 60;
+
+/// The names of the hosts files of blocklists which are not to be blocked
+const LOCAL_HOST_NAMES: [&str; 8] = [
+    "localhost",
+    "localhost.localdomain",
+    "local",
+    "broadcasthost",
+    "ip6-localhost",
+    "ip6-loopback",
+    "ip6-localnet",
+    "0.0.0.0",
+];
+
+/// The format of a list of names, see [`Blocklist::read`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-config", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde-config", serde(rename_all = "lowercase"))]
+pub enum ListFormat {
+    /// Lines of an address and names, as in `/etc/hosts`, e.g. `0.0.0.0 ads.example.com`, which
+    ///  block the names but not their subdomains
+    Hosts,
+    /// AdBlock filters, `||example.com^` blocks the name and its subdomains, and
+    ///  `@@||example.com^` allows them again. Filters with options other than `important`,
+    ///  paths or wildcards are ignored.
+    AdBlock,
+    /// A response policy zone, RPZ, in the format of a zone file. `CNAME .` answers with
+    ///  NXDOMAIN, `CNAME *.` without records, and `CNAME rpz-passthru.` allows the name. Other
+    ///  records block the name with the action of the blocklist. `*.example.com` matches the
+    ///  subdomains of the name, the triggers on addresses and name servers are ignored.
+    Rpz,
+    /// Lines of one name each, `*.example.com` blocks the subdomains of the name
+    Domains,
+}
+
+/// The answer to the queries of blocked names
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-config", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde-config", serde(rename_all = "lowercase"))]
+pub enum BlockAction {
+    /// The name does not exist
+    #[default]
+    NxDomain,
+    /// The name exists, without records
+    NoData,
+    /// `0.0.0.0` for A queries and `::` for AAAA queries, as in the hosts files of blocklists,
+    ///  the name exists without records for other queries
+    Null,
+    /// The addresses of the family of A or AAAA queries, e.g. of a server which shows why the
+    ///  name is blocked, the name exists without records for other queries
+    Addresses(Vec<IpAddr>),
+}
+
+/// The answer to a query of a blocked name, see [`Blocklist::check`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Blocked {
+    /// The name does not exist
+    NxDomain,
+    /// The name exists, without records of the type of the query
+    NoData,
+    /// The records of the answer
+    Records(Vec<Record>),
+}
+
+/// What a list says about a name, or about its subdomains
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Rule {
+    /// Blocked with the action of the blocklist
+    Block,
+    /// Not blocked, even if a less specific name is
+    Allow,
+    /// Blocked with `BlockAction::NxDomain`
+    NxDomain,
+    /// Blocked with `BlockAction::NoData`
+    NoData,
+}
+
+/// Which names a rule applies to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Scope {
+    /// The name
+    Name,
+    /// The subdomains of the name
+    Subdomains,
+    /// The name and its subdomains
+    Both,
+}
+
+/// A label of the trie of the blocked names
+///
+/// The children are sorted by their lowercase label, and looked up with a binary search, which
+///  needs much less memory than a map per node for the millions of names of large lists.
+#[derive(Default)]
+struct Node {
+    children: Vec<(Box<[u8]>, Self)>,
+    /// The rule of the name of the node
+    name: Option<Rule>,
+    /// The rule of the subdomains of the name of the node
+    subdomains: Option<Rule>,
+}
+
+impl Node {
+    fn child(&self, label: &[u8]) -> Option<&Self> {
+        self.children
+            .binary_search_by(|(child, _)| (**child).cmp(label))
+            .ok()
+            .map(|idx| &self.children[idx].1)
+    }
+
+    fn child_mut(&mut self, label: &[u8]) -> &mut Self {
+        let idx = match self
+            .children
+            .binary_search_by(|(child, _)| (**child).cmp(label))
+        {
+            Ok(idx) => idx,
+            Err(idx) => {
+                self.children.insert(idx, (label.into(), Self::default()));
+                idx
+            }
+        };
+        &mut self.children[idx].1
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.children.shrink_to_fit();
+        for (_, child) in &mut self.children {
+            child.shrink_to_fit();
+        }
+    }
+}
+
+/// Sets the rule, a name which is allowed by any list stays allowed
+fn set_rule(slot: &mut Option<Rule>, rule: Rule) -> bool {
+    match slot {
+        Some(Rule::Allow) => false,
+        Some(_) if rule != Rule::Allow => false,
+        _ => {
+            *slot = Some(rule);
+            true
+        }
+    }
+}
+
+/// The blocked names, see the [module](self) documentation
+pub struct Blocklist {
+    root: Node,
+    action: BlockAction,
+    ttl: u32,
+    len: usize,
+}
+
+impl fmt::Debug for Blocklist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Blocklist")
+            .field("action", &self.action)
+            .field("ttl", &self.ttl)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+impl Default for Blocklist {
+    fn default() -> Self {
+        Self::new(BlockAction::default())
+    }
+}
+
+impl Blocklist {
+    /// Creates an empty blocklist, which answers the queries of blocked names with the action
+    pub fn new(action: BlockAction) -> Self {
+        Self {
+            root: Node::default(),
+            action,
+            ttl: DEFAULT_TTL,
+            len: 0,
+        }
+    }
+
+    /// Sets the TTL of the answers for blocked names, defaults to [`DEFAULT_TTL`]
+    pub fn with_ttl(mut self, ttl: u32) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// The answer to the queries of blocked names
+    pub fn action(&self) -> &BlockAction {
+        &self.action
+    }
+
+    /// The TTL of the answers for blocked names, in seconds
+    pub fn ttl(&self) -> u32 {
+        self.ttl
+    }
+
+    /// The number of rules of the blocklist
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the blocklist has no rules
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Blocks the name, and its subdomains if `subdomains` is set
+    pub fn block(&mut self, name: &Name, subdomains: bool) {
+        self.insert(name, Self::scope(subdomains), Rule::Block);
+    }
+
+    /// Allows the name, and its subdomains if `subdomains` is set, even if a less specific name
+    ///  is blocked
+    pub fn allow(&mut self, name: &Name, subdomains: bool) {
+        self.insert(name, Self::scope(subdomains), Rule::Allow);
+    }
+
+    fn scope(subdomains: bool) -> Scope {
+        if subdomains {
+            Scope::Both
+        } else {
+            Scope::Name
+        }
+    }
+
+    fn insert(&mut self, name: &Name, scope: Scope, rule: Rule) {
+        let name = name.to_lowercase();
+        let mut node = &mut self.root;
+        for label in name.iter().rev() {
+            node = node.child_mut(label);
+        }
+
+        let name_set = matches!(scope, Scope::Name | Scope::Both) && set_rule(&mut node.name, rule);
+        let subdomains_set = matches!(scope, Scope::Subdomains | Scope::Both)
+            && set_rule(&mut node.subdomains, rule);
+        if name_set || subdomains_set {
+            self.len += 1;
+        }
+    }
+
+    /// Reads the rules of a list in the format
+    ///
+    /// Lines which are not understood are skipped. Returns the number of rules which were read.
+    pub fn read(&mut self, src: impl Read, format: ListFormat) -> io::Result<usize> {
+        let len = self.len;
+        let mut rpz = RpzState::default();
+        for line in BufReader::new(src).lines() {
+            let line = line?;
+            match format {
+                ListFormat::Hosts => self.read_hosts_line(&line),
+                ListFormat::AdBlock => self.read_adblock_line(&line),
+                ListFormat::Rpz => self.read_rpz_line(&line, &mut rpz),
+                ListFormat::Domains => self.read_domains_line(&line),
+            }
+        }
+
+        self.root.shrink_to_fit();
+        Ok(self.len - len)
+    }
+
+    /// Reads the rules of a list in the format from the file, see `read`
+    pub fn read_file(&mut self, path: &Path, format: ListFormat) -> io::Result<usize> {
+        self.read(File::open(path)?, format)
+    }
+
+    fn read_hosts_line(&mut self, line: &str) {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut fields = line.split_whitespace();
+        if fields
+            .next()
+            .and_then(|ip| IpAddr::from_str(ip).ok())
+            .is_none()
+        {
+            return;
+        }
+
+        for name in fields {
+            if LOCAL_HOST_NAMES
+                .iter()
+                .any(|local| local.eq_ignore_ascii_case(name))
+            {
+                continue;
+            }
+            if let Some(name) = parse_name(name) {
+                self.insert(&name, Scope::Name, Rule::Block);
+            }
+        }
+    }
+
+    fn read_adblock_line(&mut self, line: &str) {
+        let line = line.trim();
+        if line.starts_with('!') || line.starts_with('[') || line.contains('#') {
+            return;
+        }
+
+        let (line, rule) = match line.strip_prefix("@@") {
+            Some(line) => (line, Rule::Allow),
+            None => (line, Rule::Block),
+        };
+        let Some(line) = line.strip_prefix("||") else {
+            return;
+        };
+        let (pattern, options) = line.split_once('$').unwrap_or((line, ""));
+        if !options.is_empty() && options != "important" {
+            return;
+        }
+
+        let Some(domain) = pattern
+            .strip_suffix("^|")
+            .or_else(|| pattern.strip_suffix('^'))
+        else {
+            return;
+        };
+        if domain.contains(['/', '*', '^', '|']) {
+            return;
+        }
+        if let Some(name) = parse_name(domain) {
+            self.insert(&name, Scope::Both, rule);
+        }
+    }
+
+    fn read_domains_line(&mut self, line: &str) {
+        let line = line.split('#').next().unwrap_or_default();
+        let Some(domain) = line.split_whitespace().next() else {
+            return;
+        };
+
+        let (domain, scope) = match domain.strip_prefix("*.") {
+            Some(domain) => (domain, Scope::Subdomains),
+            None => (domain, Scope::Name),
+        };
+        if let Some(name) = parse_name(domain) {
+            self.insert(&name, scope, Rule::Block);
+        }
+    }
+
+    fn read_rpz_line(&mut self, line: &str, state: &mut RpzState) {
+        let line = line.split(';').next().unwrap_or_default();
+        let in_parentheses = state.parentheses > 0;
+        state.parentheses += line.matches('(').count();
+        state.parentheses = state.parentheses.saturating_sub(line.matches(')').count());
+        if in_parentheses || line.trim().is_empty() {
+            return;
+        }
+
+        let mut fields = line.split_whitespace();
+        if line.starts_with('$') {
+            if let (Some(directive), Some(origin)) = (fields.next(), fields.next()) {
+                if directive.eq_ignore_ascii_case("$ORIGIN") {
+                    state.origin = Some(origin.trim_end_matches('.').to_ascii_lowercase());
+                }
+            }
+            return;
+        }
+
+        // a line starting with whitespace has the owner of the previous line
+        if !line.starts_with(char::is_whitespace) {
+            state.owner = fields.next().map(str::to_ascii_lowercase);
+        }
+        let Some(owner) = state.owner.clone() else {
+            return;
+        };
+
+        // the TTL and the class may precede the type
+        let Some(record_type) = fields.find(|field| {
+            field.parse::<u32>().is_err()
+                && !["IN", "CH", "HS"]
+                    .iter()
+                    .any(|class| class.eq_ignore_ascii_case(field))
+        }) else {
+            return;
+        };
+        let record_type = record_type.to_ascii_uppercase();
+        if record_type == "SOA" || record_type == "NS" {
+            return;
+        }
+
+        let rule = if record_type == "CNAME" {
+            match fields.next().map(str::to_ascii_lowercase).as_deref() {
+                Some(".") => Rule::NxDomain,
+                Some("*.") => Rule::NoData,
+                Some("rpz-passthru.") => Rule::Allow,
+                Some(target) if target.starts_with("rpz-") && target != "rpz-drop." => return,
+                _ => Rule::Block,
+            }
+        } else {
+            Rule::Block
+        };
+
+        // the owner is relative to the origin of the zone
+        let trigger = match owner.strip_suffix('.') {
+            Some(owner) => match &state.origin {
+                Some(origin) => match owner.strip_suffix(origin.as_str()) {
+                    Some(trigger) => trigger.trim_end_matches('.'),
+                    None => return,
+                },
+                None => return,
+            },
+            None => owner.as_str(),
+        };
+        if trigger.is_empty()
+            || trigger == "@"
+            || trigger.split('.').any(|label| label.starts_with("rpz-"))
+        {
+            return;
+        }
+
+        let (trigger, scope) = match trigger.strip_prefix("*.") {
+            Some(trigger) => (trigger, Scope::Subdomains),
+            None => (trigger, Scope::Name),
+        };
+        if let Some(name) = parse_name(trigger) {
+            self.insert(&name, scope, rule);
+        }
+    }
+
+    /// The rule of the most specific name, and of the name itself before its subdomains
+    fn rule(&self, name: &Name) -> Option<Rule> {
+        let name = name.to_lowercase();
+        let labels = name.iter().rev().collect::<Vec<_>>();
+
+        let mut node = &self.root;
+        let mut rule = None;
+        for (idx, label) in labels.iter().enumerate() {
+            node = match node.child(label) {
+                Some(node) => node,
+                None => return rule,
+            };
+
+            if idx + 1 == labels.len() {
+                return node.name.or(rule);
+            }
+            if node.subdomains.is_some() {
+                rule = node.subdomains;
+            }
+        }
+
+        rule
+    }
+
+    /// Returns true if the queries of the name are blocked
+    pub fn is_blocked(&self, name: &Name) -> bool {
+        !matches!(self.rule(name), None | Some(Rule::Allow))
+    }
+
+    /// The answer to the query if the name is blocked, or `None` if it is to be resolved
+    pub fn check(&self, name: &Name, record_type: RecordType) -> Option<Blocked> {
+        let action = match self.rule(name)? {
+            Rule::Allow => return None,
+            Rule::NxDomain => return Some(Blocked::NxDomain),
+            Rule::NoData => return Some(Blocked::NoData),
+            Rule::Block => &self.action,
+        };
+
+        let addresses = match action {
+            BlockAction::NxDomain => return Some(Blocked::NxDomain),
+            BlockAction::NoData => return Some(Blocked::NoData),
+            BlockAction::Null => vec![
+                IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+            ],
+            BlockAction::Addresses(addresses) => addresses.clone(),
+        };
+
+        let records = addresses
+            .into_iter()
+            .filter_map(|address| match (address, record_type) {
+                (IpAddr::V4(ip), RecordType::A) => Some(RData::A(A(ip))),
+                (IpAddr::V6(ip), RecordType::AAAA) => Some(RData::AAAA(AAAA(ip))),
+                _ => None,
+            })
+            .map(|rdata| Record::from_rdata(name.clone(), self.ttl, rdata))
+            .collect::<Vec<_>>();
+
+        if records.is_empty() {
+            Some(Blocked::NoData)
+        } else {
+            Some(Blocked::Records(records))
+        }
+    }
+}
+
+/// The state of the lines read of a response policy zone
+#[derive(Default)]
+struct RpzState {
+    origin: Option<String>,
+    owner: Option<String>,
+    parentheses: usize,
+}
+
+/// Parses a name of a list as a fully qualified name
+fn parse_name(name: &str) -> Option<Name> {
+    let mut name = Name::from_ascii(name.trim_end_matches('.')).ok()?;
+    if name.is_root() {
+        return None;
+    }
+    name.set_fqdn(true);
+    Some(name)
+}
+
+/// A list read by a [`SharedBlocklist`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-config", derive(Serialize, Deserialize))]
+pub struct BlocklistSource {
+    /// The path of the list
+    pub path: PathBuf,
+    /// The format of the list
+    pub format: ListFormat,
+}
+
+impl BlocklistSource {
+    /// The modification time and the length of the file
+    fn version(&self) -> Option<(SystemTime, u64)> {
+        let metadata = fs::metadata(&self.path).ok()?;
+        Some((metadata.modified().ok()?, metadata.len()))
+    }
+}
+
+/// A blocklist which is shared by the lookups of resolvers and servers, and replaced when its
+///  lists changed
+///
+/// The lists are not checked for changes on lookups, as reading large lists takes a while. An
+///  application calls [`SharedBlocklist::reload_if_changed`] from time to time, e.g. from a
+///  blocking task.
+#[derive(Debug)]
+pub struct SharedBlocklist {
+    sources: Vec<BlocklistSource>,
+    action: BlockAction,
+    ttl: u32,
+    state: RwLock<SharedState>,
+}
+
+#[derive(Debug)]
+struct SharedState {
+    versions: Vec<Option<(SystemTime, u64)>>,
+    blocklist: Arc<Blocklist>,
+}
+
+impl SharedBlocklist {
+    /// Shares a blocklist, which is never reloaded
+    pub fn new(blocklist: Blocklist) -> Self {
+        Self {
+            sources: Vec::new(),
+            action: blocklist.action.clone(),
+            ttl: blocklist.ttl,
+            state: RwLock::new(SharedState {
+                versions: Vec::new(),
+                blocklist: Arc::new(blocklist),
+            }),
+        }
+    }
+
+    /// Reads the lists into a blocklist, which answers the queries of blocked names with the
+    ///  action and TTL
+    pub fn from_files(
+        sources: Vec<BlocklistSource>,
+        action: BlockAction,
+        ttl: u32,
+    ) -> io::Result<Self> {
+        let versions = sources.iter().map(BlocklistSource::version).collect();
+        let blocklist = Self::read(&sources, action.clone(), ttl)?;
+
+        Ok(Self {
+            sources,
+            action,
+            ttl,
+            state: RwLock::new(SharedState {
+                versions,
+                blocklist: Arc::new(blocklist),
+            }),
+        })
+    }
+
+    fn read(sources: &[BlocklistSource], action: BlockAction, ttl: u32) -> io::Result<Blocklist> {
+        let started = Instant::now();
+        let mut blocklist = Blocklist::new(action).with_ttl(ttl);
+        for source in sources {
+            let rules = blocklist
+                .read_file(&source.path, source.format)
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", source.path.display())))?;
+            debug!("read {rules} rules from {}", source.path.display());
+        }
+
+        info!(
+            "read {} rules from {} blocklists in {:?}",
+            blocklist.len(),
+            sources.len(),
+            started.elapsed()
+        );
+        Ok(blocklist)
+    }
+
+    /// The current blocklist
+    pub fn blocklist(&self) -> Arc<Blocklist> {
+        Arc::clone(&self.state.read().blocklist)
+    }
+
+    /// The answer to the query if the name is blocked, see [`Blocklist::check`]
+    pub fn check(&self, name: &Name, record_type: RecordType) -> Option<Blocked> {
+        self.state.read().blocklist.check(name, record_type)
+    }
+
+    /// Reads the lists again if any of them changed since they were last read
+    ///
+    /// The current blocklist is kept if a list can not be read. Returns true if the blocklist was
+    ///  replaced.
+    pub fn reload_if_changed(&self) -> io::Result<bool> {
+        let versions = self
+            .sources
+            .iter()
+            .map(BlocklistSource::version)
+            .collect::<Vec<_>>();
+        if versions == self.state.read().versions {
+            return Ok(false);
+        }
+
+        let blocklist = match Self::read(&self.sources, self.action.clone(), self.ttl) {
+            Ok(blocklist) => blocklist,
+            Err(e) => {
+                warn!("could not reload blocklists: {e}");
+                // the lists are read again on their next change
+                self.state.write().versions = versions;
+                return Err(e);
+            }
+        };
+
+        let mut state = self.state.write();
+        state.versions = versions;
+        state.blocklist = Arc::new(blocklist);
+        Ok(true)
+    }
+
+    /// The lookup of the first blocked name of `names` for the record types
+    ///
+    /// The records of all the record types are answered together, e.g. for `lookup_ip`, the name
+    ///  does not exist if it does not exist for the first record type.
+    pub(crate) fn lookup(
+        &self,
+        names: &[Name],
+        record_types: &[RecordType],
+    ) -> Option<Result<Lookup, ResolveError>> {
+        let blocklist = self.state.read();
+        let blocklist = &blocklist.blocklist;
+        let name = names.iter().find(|name| blocklist.is_blocked(name))?;
+        let first_type = *record_types.first()?;
+        let query = Query::query(name.clone(), first_type);
+
+        let mut records = Vec::new();
+        for record_type in record_types {
+            match blocklist.check(name, *record_type)? {
+                Blocked::NxDomain => return Some(Err(negative(query, blocklist.ttl, true))),
+                Blocked::NoData => (),
+                Blocked::Records(answers) => records.extend(answers),
+            }
+        }
+
+        if records.is_empty() {
+            return Some(Err(negative(query, blocklist.ttl, false)));
+        }
+
+        let valid_until = Instant::now() + Duration::from_secs(u64::from(blocklist.ttl));
+        Some(Ok(Lookup::new_with_deadline(
+            query,
+            Arc::from(records),
+            valid_until,
+        )))
+    }
+}
+
+/// The error of a blocked name which does not exist, or has no records
+fn negative(query: Query, ttl: u32, nx_domain: bool) -> ResolveError {
+    let response_code = if nx_domain {
+        ResponseCode::NXDomain
+    } else {
+        ResponseCode::NoError
+    };
+    ProtoError::nx_error(query, None, Some(ttl), response_code, false).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use proto::error::ProtoErrorKind;
+
+    use super::*;
+
+    fn name(name: &str) -> Name {
+        Name::from_str(name).unwrap()
+    }
+
+    #[test]
+    fn test_hosts() {
+        let mut blocklist = Blocklist::new(BlockAction::Null);
+        let hosts = "127.0.0.1 localhost\n\
+                     ::1 localhost ip6-localhost\n\
+                     # comment\n\
+                     0.0.0.0 ads.example.com tracker.example.com # trailing\n\
+                     0.0.0.0\n";
+        assert_eq!(
+            blocklist.read(hosts.as_bytes(), ListFormat::Hosts).unwrap(),
+            2
+        );
+
+        assert!(blocklist.is_blocked(&name("ads.example.com.")));
+        assert!(blocklist.is_blocked(&name("TRACKER.example.com.")));
+        assert!(!blocklist.is_blocked(&name("sub.ads.example.com.")));
+        assert!(!blocklist.is_blocked(&name("localhost.")));
+
+        assert_eq!(
+            blocklist.check(&name("ads.example.com."), RecordType::AAAA),
+            Some(Blocked::Records(vec![Record::from_rdata(
+                name("ads.example.com."),
+                DEFAULT_TTL,
+                RData::AAAA(AAAA(Ipv6Addr::UNSPECIFIED)),
+            )]))
+        );
+        assert_eq!(
+            blocklist.check(&name("ads.example.com."), RecordType::MX),
+            Some(Blocked::NoData)
+        );
+    }
+
+    #[test]
+    fn test_adblock() {
+        let mut blocklist = Blocklist::default();
+        let filters = "[Adblock Plus 2.0]\n\
+                       ! comment\n\
+                       ||ads.example.com^\n\
+                       @@||good.ads.example.com^\n\
+                       ||important.example.net^$important\n\
+                       ||third.example.net^$third-party\n\
+                       ||example.org/banner.png\n\
+                       example.com##.banner\n";
+        assert_eq!(
+            blocklist
+                .read(filters.as_bytes(), ListFormat::AdBlock)
+                .unwrap(),
+            3
+        );
+
+        assert!(blocklist.is_blocked(&name("ads.example.com.")));
+        assert!(blocklist.is_blocked(&name("a.b.ads.example.com.")));
+        assert!(!blocklist.is_blocked(&name("good.ads.example.com.")));
+        assert!(!blocklist.is_blocked(&name("www.good.ads.example.com.")));
+        assert!(blocklist.is_blocked(&name("important.example.net.")));
+        assert!(!blocklist.is_blocked(&name("third.example.net.")));
+        assert!(!blocklist.is_blocked(&name("example.org.")));
+        assert!(!blocklist.is_blocked(&name("example.com.")));
+        assert_eq!(
+            blocklist.check(&name("ads.example.com."), RecordType::A),
+            Some(Blocked::NxDomain)
+        );
+    }
+
+    #[test]
+    fn test_rpz() {
+        let mut blocklist = Blocklist::new(BlockAction::Addresses(vec![IpAddr::V4(
+            Ipv4Addr::new(192, 0, 2, 1),
+        )]));
+        let zone = "$TTL 300\n\
+                    $ORIGIN rpz.example.\n\
+                    @ IN SOA localhost. root.localhost. (\n\
+                        1 3600 600 86400 300 )\n\
+                    @ IN NS localhost.\n\
+                    nx.example.com CNAME .\n\
+                    *.nx.example.com CNAME .\n\
+                    nodata.example.com 300 IN CNAME *.\n\
+                    ok.nx.example.com CNAME rpz-passthru.\n\
+                    local.example.com A 192.0.2.2\n\
+                    \tTXT \"blocked\"\n\
+                    abs.example.com.rpz.example. CNAME .\n\
+                    32.1.2.0.192.rpz-ip CNAME .\n";
+        assert_eq!(blocklist.read(zone.as_bytes(), ListFormat::Rpz).unwrap(), 6);
+
+        assert_eq!(
+            blocklist.check(&name("nx.example.com."), RecordType::A),
+            Some(Blocked::NxDomain)
+        );
+        assert_eq!(
+            blocklist.check(&name("sub.nx.example.com."), RecordType::A),
+            Some(Blocked::NxDomain)
+        );
+        assert_eq!(
+            blocklist.check(&name("nodata.example.com."), RecordType::A),
+            Some(Blocked::NoData)
+        );
+        assert_eq!(
+            blocklist.check(&name("ok.nx.example.com."), RecordType::A),
+            None
+        );
+        assert_eq!(
+            blocklist.check(&name("local.example.com."), RecordType::A),
+            Some(Blocked::Records(vec![Record::from_rdata(
+                name("local.example.com."),
+                DEFAULT_TTL,
+                RData::A(A::new(192, 0, 2, 1)),
+            )]))
+        );
+        assert!(blocklist.is_blocked(&name("abs.example.com.")));
+        assert!(!blocklist.is_blocked(&name("rpz.example.")));
+        assert!(!blocklist.is_blocked(&name("nodata.example.com.rpz.example.")));
+    }
+
+    #[test]
+    fn test_domains() {
+        let mut blocklist = Blocklist::new(BlockAction::NoData);
+        let domains = "# comment\nads.example.com\n*.tracker.example.com\n\n";
+        assert_eq!(
+            blocklist
+                .read(domains.as_bytes(), ListFormat::Domains)
+                .unwrap(),
+            2
+        );
+
+        assert!(blocklist.is_blocked(&name("ads.example.com")));
+        assert!(!blocklist.is_blocked(&name("www.ads.example.com.")));
+        assert!(!blocklist.is_blocked(&name("tracker.example.com.")));
+        assert!(blocklist.is_blocked(&name("a.tracker.example.com.")));
+        assert!(!blocklist.is_blocked(&name(".")));
+    }
+
+    #[test]
+    fn test_shared_blocklist_reload() {
+        let dir = std::env::temp_dir().join(format!("hickory-blocklist-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("domains");
+        fs::write(&path, "ads.example.com\n").unwrap();
+
+        let source = BlocklistSource {
+            path: path.clone(),
+            format: ListFormat::Domains,
+        };
+        let shared = SharedBlocklist::from_files(vec![source], BlockAction::NxDomain, 5).unwrap();
+        assert!(!shared.reload_if_changed().unwrap());
+        assert_eq!(
+            shared.check(&name("ads.example.com."), RecordType::A),
+            Some(Blocked::NxDomain)
+        );
+
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"tracker.example.com\n").unwrap();
+        drop(file);
+        assert!(shared.reload_if_changed().unwrap());
+        assert_eq!(shared.blocklist().len(), 2);
+
+        let lookup = shared.lookup(
+            &[name("tracker.example.com.")],
+            &[RecordType::A, RecordType::AAAA],
+        );
+        let err = lookup.unwrap().unwrap_err();
+        assert!(
+            matches!(
+                err.proto().map(ProtoError::kind),
+                Some(ProtoErrorKind::NoRecordsFound {
+                    response_code: ResponseCode::NXDomain,
+                    ..
+                })
+            ),
+            "{err}"
+        );
+        assert!(shared
+            .lookup(&[name("www.example.com.")], &[RecordType::A])
+            .is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -9,23 +9,27 @@
 
 use std::{
     borrow::Cow,
+    collections::HashMap,
+    fmt,
     pin::Pin,
     sync::{
-        atomic::{AtomicU8, Ordering},
-        Arc,
+        atomic::{AtomicU64, AtomicU8, Ordering},
+        Arc, Mutex, PoisonError,
     },
-    time::Instant,
 };
 
-use futures_util::future::{Future, TryFutureExt};
+use futures_util::future::{Future, FutureExt, Shared, TryFutureExt};
 use hickory_proto::error::ProtoErrorKind;
 use once_cell::sync::Lazy;
+use tracing::{debug, warn};
 
 use crate::{
+    config::NxDomainRecheck,
     dns_lru::{self, DnsLru, TtlConfig},
     error::ResolveError,
     lookup::Lookup,
     proto::{
+        clock::{Clock, SystemClock},
         error::ProtoError,
         op::{Query, ResponseCode},
         rr::{
@@ -65,6 +69,114 @@ impl Drop for DepthTracker {
     }
 }
 
+/// Counters for NXDOMAIN responses to lookups of names with an expired answer in the cache
+///
+/// See [`NxDomainRecheck`] for how these responses are handled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct NxDomainRecheckStats {
+    /// Lookups for names with an expired answer in the cache, where a NXDOMAIN response had to be
+    /// confirmed by a second name server
+    pub rechecked: u64,
+    /// NXDOMAIN responses which were confirmed, the expired answer was evicted
+    pub confirmed: u64,
+    /// NXDOMAIN responses which could not be confirmed, but were accepted
+    pub unconfirmed: u64,
+    /// Lookups which returned the expired answer, as the response could not be confirmed
+    pub served_stale: u64,
+}
+
+#[derive(Debug, Default)]
+struct NxDomainRecheckCounters {
+    rechecked: AtomicU64,
+    confirmed: AtomicU64,
+    unconfirmed: AtomicU64,
+    served_stale: AtomicU64,
+}
+
+impl NxDomainRecheckCounters {
+    fn increment(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn stats(&self) -> NxDomainRecheckStats {
+        NxDomainRecheckStats {
+            rechecked: self.rechecked.load(Ordering::Relaxed),
+            confirmed: self.confirmed.load(Ordering::Relaxed),
+            unconfirmed: self.unconfirmed.load(Ordering::Relaxed),
+            served_stale: self.served_stale.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Counters for the queries sent for lookups which missed the cache
+///
+/// See [`ResolverOpts::coalesce_queries`](crate::config::ResolverOpts::coalesce_queries).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct QueryCoalescingStats {
+    /// Queries sent to the name servers
+    pub sent: u64,
+    /// Lookups which waited for the response to an identical query in flight, instead of sending it
+    pub coalesced: u64,
+}
+
+type ResponseFuture = Pin<Box<dyn Future<Output = Result<DnsResponse, ProtoError>> + Send>>;
+
+struct InFlightQuery {
+    id: u64,
+    options: DnsRequestOptions,
+    response: Shared<ResponseFuture>,
+}
+
+/// The queries sent for lookups, until their responses are cached
+#[derive(Default)]
+struct InFlightQueries {
+    queries: Mutex<HashMap<Query, InFlightQuery>>,
+    next_id: AtomicU64,
+    sent: AtomicU64,
+    coalesced: AtomicU64,
+}
+
+impl InFlightQueries {
+    fn stats(&self) -> QueryCoalescingStats {
+        QueryCoalescingStats {
+            sent: self.sent.load(Ordering::Relaxed),
+            coalesced: self.coalesced.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl fmt::Debug for InFlightQueries {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InFlightQueries")
+            .field("stats", &self.stats())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Removes a sent query from the queries in flight when dropped
+struct InFlightGuard {
+    in_flight: Arc<InFlightQueries>,
+    query: Query,
+    id: u64,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        let mut queries = self
+            .in_flight
+            .queries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        // the query may have been replaced by one with different options
+        if queries.get(&self.query).map(|in_flight| in_flight.id) == Some(self.id) {
+            queries.remove(&self.query);
+        }
+    }
+}
+
 // TODO: need to consider this storage type as it compares to Authority in server...
 //       should it just be an variation on Authority?
 #[derive(Clone, Debug)]
@@ -77,6 +189,11 @@ where
     client: C,
     query_depth: Arc<AtomicU8>,
     preserve_intermediates: bool,
+    nxdomain_recheck: NxDomainRecheck,
+    recheck_counters: Arc<NxDomainRecheckCounters>,
+    coalesce_queries: bool,
+    in_flight: Arc<InFlightQueries>,
+    clock: Arc<dyn Clock>,
 }
 
 impl<C> CachingClient<C>
@@ -99,9 +216,34 @@ where
             client,
             query_depth,
             preserve_intermediates,
+            nxdomain_recheck: NxDomainRecheck::Disabled,
+            recheck_counters: Arc::default(),
+            coalesce_queries: false,
+            in_flight: Arc::default(),
+            clock: Arc::new(SystemClock),
         }
     }
 
+    /// Sets the clock for the expiry of cached records
+    pub(crate) fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Sets how NXDOMAIN responses for names with expired answers are handled
+    ///
+    /// The cache must keep expired answers, see `DnsLru::keep_stale`.
+    pub(crate) fn with_nxdomain_recheck(mut self, nxdomain_recheck: NxDomainRecheck) -> Self {
+        self.nxdomain_recheck = nxdomain_recheck;
+        self
+    }
+
+    /// Sets if lookups wait for the response to an identical query in flight, instead of sending it
+    pub(crate) fn with_coalesce_queries(mut self, coalesce_queries: bool) -> Self {
+        self.coalesce_queries = coalesce_queries;
+        self
+    }
+
     /// Perform a lookup against this caching client, looking first in the cache for a result
     pub fn lookup(
         &mut self,
@@ -184,12 +326,17 @@ where
             return cached_lookup;
         };
 
-        let response_message = client
-            .client
-            .lookup(query.clone(), options)
-            .first_answer()
-            .await
-            .map_err(ProtoError::into);
+        // a NXDOMAIN for a name which resolved before must be confirmed, see NxDomainRecheck
+        let stale = match client.nxdomain_recheck {
+            NxDomainRecheck::Disabled => None,
+            _ => client.lru.get_stale(&query, client.clock.instant()),
+        };
+        let mut request_options = options;
+        request_options.confirm_nxdomain |= stale.is_some();
+
+        // held until the response is cached, so that lookups in the meantime wait for it
+        let (response_message, _in_flight) = client.send_query(&query, request_options);
+        let response_message = response_message.await;
 
         // TODO: technically this might be duplicating work, as name_server already performs this evaluation.
         //  we may want to create a new type, if evaluated... but this is most generic to support any impl in LookupState...
@@ -199,6 +346,12 @@ where
             response_message
         };
 
+        if let Some(stale) = stale {
+            if let Some(lookup) = client.recheck(&query, &response_message, stale) {
+                return Ok(lookup);
+            }
+        }
+
         // TODO: take all records and cache them?
         //  if it's DNSSEC they must be signed, otherwise?
         let records: Result<Records, ProtoError> = match response_message {
@@ -254,9 +407,114 @@ where
         }
     }
 
+    /// Sends the query, or waits for the response to an identical query in flight
+    ///
+    /// A guard is returned for a sent query, which must be held until the response is cached.
+    fn send_query(
+        &self,
+        query: &Query,
+        options: DnsRequestOptions,
+    ) -> (ResponseFuture, Option<InFlightGuard>) {
+        let in_flight = &self.in_flight;
+        let send = || -> ResponseFuture {
+            in_flight.sent.fetch_add(1, Ordering::Relaxed);
+            Box::pin(self.client.lookup(query.clone(), options).first_answer())
+        };
+
+        if !self.coalesce_queries {
+            return (send(), None);
+        }
+
+        let mut queries = in_flight
+            .queries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(sent) = queries.get(query).filter(|sent| sent.options == options) {
+            debug!("waiting for the response to {query} in flight");
+            in_flight.coalesced.fetch_add(1, Ordering::Relaxed);
+            return (Box::pin(sent.response.clone()), None);
+        }
+
+        let id = in_flight.next_id.fetch_add(1, Ordering::Relaxed);
+        let response = send().shared();
+        queries.insert(
+            query.clone(),
+            InFlightQuery {
+                id,
+                options,
+                response: response.clone(),
+            },
+        );
+
+        let guard = InFlightGuard {
+            in_flight: Arc::clone(in_flight),
+            query: query.clone(),
+            id,
+        };
+        (Box::pin(response), Some(guard))
+    }
+
+    /// Returns the counters for the queries sent for lookups which missed the cache
+    pub fn query_coalescing_stats(&self) -> QueryCoalescingStats {
+        self.in_flight.stats()
+    }
+
     /// Check if this query is already cached
     fn lookup_from_cache(&self, query: &Query) -> Option<Result<Lookup, ProtoError>> {
-        self.lru.get(query, Instant::now())
+        self.lru.get(query, self.clock.instant())
+    }
+
+    /// Handles the response to a lookup of a name with an expired answer, see `NxDomainRecheck`
+    ///
+    /// # Returns
+    ///
+    /// The expired answer, if it should be returned instead of the response
+    fn recheck(
+        &self,
+        query: &Query,
+        response: &Result<DnsResponse, ProtoError>,
+        stale: Lookup,
+    ) -> Option<Lookup> {
+        let counters = &self.recheck_counters;
+        NxDomainRecheckCounters::increment(&counters.rechecked);
+
+        // the name still resolves, the expired answer is replaced as usual
+        let Err(error) = response else {
+            return None;
+        };
+
+        match error.kind() {
+            ProtoErrorKind::NoRecordsFound {
+                response_code: ResponseCode::NXDomain,
+                trusted: true,
+                ..
+            } => {
+                debug!("NXDOMAIN for {query} confirmed, evicting expired answer");
+                NxDomainRecheckCounters::increment(&counters.confirmed);
+                self.lru.remove(query);
+                None
+            }
+            _ if self.nxdomain_recheck == NxDomainRecheck::ConfirmOrServeStale => {
+                warn!("response for {query} not confirmed, serving expired answer: {error}");
+                NxDomainRecheckCounters::increment(&counters.served_stale);
+                Some(stale)
+            }
+            ProtoErrorKind::NoRecordsFound {
+                response_code: ResponseCode::NXDomain,
+                ..
+            } => {
+                warn!("NXDOMAIN for {query} not confirmed, evicting expired answer");
+                NxDomainRecheckCounters::increment(&counters.unconfirmed);
+                self.lru.remove(query);
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the counters for NXDOMAIN responses to names with expired answers
+    pub fn nxdomain_recheck_stats(&self) -> NxDomainRecheckStats {
+        self.recheck_counters.stats()
     }
 
     /// See https://tools.ietf.org/html/rfc2308
@@ -411,7 +669,12 @@ where
                         {
                             found_name = true;
                             Some((r, ttl))
-                        } else if query.query_type().is_ns() && r.record_type().is_ip_addr() {
+                        } else if (query.query_type().is_ns()
+                            || query.query_type().is_srv()
+                            || query.query_type() == RecordType::MX)
+                            && r.record_type().is_ip_addr()
+                        {
+                            // the addresses of the name servers, targets or mail exchangers
                             Some((r, ttl))
                         } else {
                             None
@@ -469,7 +732,9 @@ where
     #[allow(clippy::unnecessary_wraps)]
     fn cname(&self, lookup: Lookup, query: Query, cname_ttl: u32) -> Result<Lookup, ProtoError> {
         // this duplicates the cache entry under the original query
-        Ok(self.lru.duplicate(query, lookup, cname_ttl, Instant::now()))
+        Ok(self
+            .lru
+            .duplicate(query, lookup, cname_ttl, self.clock.instant()))
     }
 
     fn cache(
@@ -479,8 +744,8 @@ where
     ) -> Result<Lookup, ProtoError> {
         // this will put this object into an inconsistent state, but no one should call poll again...
         match records {
-            Ok(rdata) => Ok(self.lru.insert(query, rdata, Instant::now())),
-            Err(err) => Err(self.lru.negative(query, err, Instant::now())),
+            Ok(rdata) => Ok(self.lru.insert(query, rdata, self.clock.instant())),
+            Err(err) => Err(self.lru.negative(query, err, self.clock.instant())),
         }
     }
 
@@ -508,8 +773,9 @@ mod tests {
     use std::time::*;
 
     use futures_executor::block_on;
+    use proto::clock::ManualClock;
     use proto::op::{Message, Query};
-    use proto::rr::rdata::{NS, SRV};
+    use proto::rr::rdata::{MX, NS, SRV};
     use proto::rr::{Name, Record};
 
     use super::*;
@@ -613,6 +879,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_cache_expires_with_clock() {
+        let cache = DnsLru::new(1, dns_lru::TtlConfig::default());
+        let clock = Arc::new(ManualClock::from_unix_time(1_700_000_000));
+        let lookup = |response| {
+            let client = CachingClient::with_cache(cache.clone(), mock(vec![response]), false)
+                .with_clock(clock.clone());
+            block_on(CachingClient::inner_lookup(
+                Query::new(),
+                DnsRequestOptions::default(),
+                client,
+                vec![],
+            ))
+        };
+
+        assert!(lookup(v4_message()).is_ok());
+
+        // the TTL of the record is a day
+        clock.advance(Duration::from_secs(86_399));
+        assert!(lookup(empty()).is_ok());
+
+        clock.advance(Duration::from_secs(2));
+        assert!(lookup(empty()).is_err());
+    }
+
     #[allow(clippy::unnecessary_wraps)]
     pub(crate) fn cname_message() -> Result<DnsResponse, ProtoError> {
         let mut message = Message::new();
@@ -877,6 +1168,50 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_mx_query_additionals() {
+        let cache = DnsLru::new(1, dns_lru::TtlConfig::default());
+
+        let mut message = Message::new();
+        message.add_query(Query::query(
+            Name::from_str("example.com.").unwrap(),
+            RecordType::MX,
+        ));
+        message.add_answer(Record::from_rdata(
+            Name::from_str("example.com.").unwrap(),
+            86400,
+            RData::MX(MX::new(10, Name::from_str("mail.example.com.").unwrap())),
+        ));
+        message.insert_additionals(vec![Record::from_rdata(
+            Name::from_str("mail.example.com.").unwrap(),
+            86400,
+            RData::A(A::new(127, 0, 0, 1)),
+        )]);
+
+        let client = mock(vec![
+            error(),
+            Ok(DnsResponse::from_message(message).unwrap()),
+        ]);
+        let client = CachingClient::with_cache(cache, client, false);
+
+        let lookup = block_on(CachingClient::inner_lookup(
+            Query::query(Name::from_str("example.com.").unwrap(), RecordType::MX),
+            DnsRequestOptions::default(),
+            client,
+            vec![],
+        ))
+        .expect("lookup failed");
+
+        // the address of the mail exchanger is kept, see `AsyncResolver::lookup_mx_with_ips`
+        assert_eq!(
+            lookup.iter().cloned().collect::<Vec<_>>(),
+            vec![
+                RData::MX(MX::new(10, Name::from_str("mail.example.com.").unwrap())),
+                RData::A(A::new(127, 0, 0, 1)),
+            ]
+        );
+    }
+
     fn cname_ttl_test(first: u32, second: u32) {
         let lru = DnsLru::new(1, dns_lru::TtlConfig::default());
         // expecting no queries to be performed
@@ -925,6 +1260,104 @@ mod tests {
         cname_ttl_test(2, 1);
     }
 
+    fn nx_message(trusted: bool) -> Result<DnsResponse, ProtoError> {
+        let mut message = Message::new();
+        message.add_query(Query::query(Name::root(), RecordType::A));
+        message.set_response_code(ResponseCode::NXDomain);
+        message.add_name_server(Record::from_rdata(
+            Name::root(),
+            300,
+            RData::SOA(SOA::new(
+                Name::root(),
+                Name::root(),
+                1,
+                3600,
+                600,
+                86400,
+                300,
+            )),
+        ));
+        ProtoError::from_response(DnsResponse::from_message(message).unwrap(), trusted)
+    }
+
+    /// Returns a cache with an expired answer for `Query::new()`
+    fn stale_cache() -> DnsLru {
+        let cache = DnsLru::new(1, dns_lru::TtlConfig::default()).keep_stale(true);
+        let query = Query::new();
+        cache.insert(
+            query.clone(),
+            vec![(
+                Record::from_rdata(query.name().clone(), 1, RData::A(A::new(127, 0, 0, 2))),
+                1,
+            )],
+            Instant::now() - Duration::from_secs(60),
+        );
+        cache
+    }
+
+    fn recheck(
+        recheck: NxDomainRecheck,
+        cache: &DnsLru,
+        response: Result<DnsResponse, ProtoError>,
+    ) -> (Result<Lookup, ProtoError>, NxDomainRecheckStats) {
+        let client = CachingClient::with_cache(cache.clone(), mock(vec![response]), false)
+            .with_nxdomain_recheck(recheck);
+        let result = block_on(CachingClient::inner_lookup(
+            Query::new(),
+            DnsRequestOptions::default(),
+            client.clone(),
+            vec![],
+        ));
+        (result, client.nxdomain_recheck_stats())
+    }
+
+    #[test]
+    fn test_nxdomain_recheck() {
+        let stale = vec![RData::A(A::new(127, 0, 0, 2))];
+
+        // confirmed NXDOMAIN evicts the expired answer
+        let cache = stale_cache();
+        let (result, stats) = recheck(NxDomainRecheck::Confirm, &cache, nx_message(true));
+        assert!(result.is_err());
+        assert_eq!((stats.rechecked, stats.confirmed), (1, 1));
+        assert!(cache.get_stale(&Query::new(), Instant::now()).is_none());
+
+        // unconfirmed NXDOMAIN is accepted
+        let cache = stale_cache();
+        let (result, stats) = recheck(NxDomainRecheck::Confirm, &cache, nx_message(false));
+        assert!(result.is_err());
+        assert_eq!(stats.unconfirmed, 1);
+
+        // or the expired answer is served
+        let cache = stale_cache();
+        let (result, stats) = recheck(
+            NxDomainRecheck::ConfirmOrServeStale,
+            &cache,
+            nx_message(false),
+        );
+        assert_eq!(result.unwrap().iter().cloned().collect::<Vec<_>>(), stale);
+        assert_eq!(stats.served_stale, 1);
+        assert!(cache.get_stale(&Query::new(), Instant::now()).is_some());
+
+        // also if the name servers fail
+        let (result, _) = recheck(NxDomainRecheck::ConfirmOrServeStale, &cache, error());
+        assert_eq!(result.unwrap().iter().cloned().collect::<Vec<_>>(), stale);
+
+        // a new answer replaces the expired answer
+        let (result, stats) = recheck(NxDomainRecheck::Confirm, &cache, v4_message());
+        assert_eq!(
+            result.unwrap().iter().cloned().collect::<Vec<_>>(),
+            vec![RData::A(A::new(127, 0, 0, 1))]
+        );
+        assert_eq!((stats.rechecked, stats.confirmed), (1, 0));
+
+        // without rechecks the expired answer is not used
+        let cache = stale_cache();
+        let (result, stats) = recheck(NxDomainRecheck::Disabled, &cache, nx_message(false));
+        assert!(result.is_err());
+        assert_eq!(stats, NxDomainRecheckStats::default());
+    }
+
     #[test]
     fn test_early_return_localhost() {
         let cache = DnsLru::new(0, dns_lru::TtlConfig::default());
@@ -1046,4 +1479,74 @@ mod tests {
         ))
         .is_ok());
     }
+
+    /// Counts the queries, which are answered on the second poll
+    #[derive(Clone, Default)]
+    struct DelayedDnsHandle {
+        sent: Arc<AtomicU64>,
+    }
+
+    impl DnsHandle for DelayedDnsHandle {
+        type Response = Pin<
+            Box<dyn futures_util::Stream<Item = Result<DnsResponse, ProtoError>> + Send + Unpin>,
+        >;
+
+        fn send<R: Into<proto::xfer::DnsRequest>>(&self, _: R) -> Self::Response {
+            self.sent.fetch_add(1, Ordering::Relaxed);
+
+            let mut polled = false;
+            Box::pin(futures_util::stream::once(futures_util::future::poll_fn(
+                move |cx| {
+                    if polled {
+                        return std::task::Poll::Ready(v4_message());
+                    }
+                    polled = true;
+                    cx.waker().wake_by_ref();
+                    std::task::Poll::Pending
+                },
+            )))
+        }
+    }
+
+    fn concurrent_lookups(coalesce_queries: bool) -> (u64, QueryCoalescingStats) {
+        let handle = DelayedDnsHandle::default();
+        let cache = DnsLru::new(1, dns_lru::TtlConfig::default());
+        let mut client = CachingClient::with_cache(cache, handle.clone(), false)
+            .with_coalesce_queries(coalesce_queries);
+        let query = Query::query(Name::root(), RecordType::A);
+
+        let (first, second) = block_on(futures_util::future::join(
+            client.lookup(query.clone(), DnsRequestOptions::default()),
+            client.lookup(query, DnsRequestOptions::default()),
+        ));
+        assert_eq!(first.unwrap().records(), second.unwrap().records());
+
+        (
+            handle.sent.load(Ordering::Relaxed),
+            client.query_coalescing_stats(),
+        )
+    }
+
+    #[test]
+    fn test_coalesce_queries() {
+        let (sent, stats) = concurrent_lookups(true);
+        assert_eq!(sent, 1);
+        assert_eq!(
+            stats,
+            QueryCoalescingStats {
+                sent: 1,
+                coalesced: 1,
+            }
+        );
+
+        let (sent, stats) = concurrent_lookups(false);
+        assert_eq!(sent, 2);
+        assert_eq!(
+            stats,
+            QueryCoalescingStats {
+                sent: 2,
+                coalesced: 0,
+            }
+        );
+    }
 }
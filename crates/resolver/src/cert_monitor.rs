@@ -0,0 +1,620 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Monitoring of the certificates of DNS over TLS and DNS over HTTPS name servers
+//!
+//! A certificate which expired or was revoked makes every connection to the name server fail,
+//!  which to the user looks like the name server timing out. The [`CertificateMonitor`] records
+//!  the expiry and the stapled OCSP status of the certificate seen in each handshake, warns ahead
+//!  of the expiry, and exposes the recorded status for metrics.
+//!
+//! ```no_run
+//! use std::{sync::Arc, time::Duration};
+//!
+//! use hickory_resolver::cert_monitor::CertificateMonitor;
+//! use hickory_resolver::config::{ResolverConfig, ResolverOpts};
+//! use hickory_resolver::TokioAsyncResolver;
+//!
+//! # async fn example() {
+//! let monitor = CertificateMonitor::new(Duration::from_secs(14 * 24 * 60 * 60));
+//! let mut config = ResolverConfig::cloudflare_tls();
+//! config.set_tls_client_config(Arc::new(monitor.client_config().unwrap()));
+//!
+//! let resolver = TokioAsyncResolver::tokio(config, ResolverOpts::default());
+//! tokio::spawn(monitor.clone().run(Duration::from_secs(60 * 60)));
+//!
+//! for status in monitor.statuses() {
+//!     println!("{}: expires at {:?}", status.server_name, status.not_after);
+//! }
+//! # }
+//! ```
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use rustls::{
+    client::{
+        HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier, ServerName, WebPkiVerifier,
+    },
+    Certificate, ClientConfig, DigitallySignedStruct, SignatureScheme,
+};
+use tracing::{debug, warn};
+
+use crate::proto::error::ProtoError;
+use crate::tls::{client_config, root_store};
+
+/// Records the certificates of name servers, see the [module](self) documentation
+pub struct CertificateMonitor {
+    statuses: Mutex<HashMap<String, CertificateStatus>>,
+    warn_before: Duration,
+}
+
+impl CertificateMonitor {
+    /// Creates a new monitor, which warns about certificates expiring within `warn_before`
+    pub fn new(warn_before: Duration) -> Arc<Self> {
+        Arc::new(Self {
+            statuses: Mutex::new(HashMap::new()),
+            warn_before,
+        })
+    }
+
+    /// Returns the default configuration for connections to name servers, with certificates
+    ///  verified as usual and recorded by this monitor
+    pub fn client_config(self: &Arc<Self>) -> Result<ClientConfig, ProtoError> {
+        let root_store = root_store()?;
+        let mut config = client_config(root_store.clone());
+        config.dangerous().set_certificate_verifier(
+            self.verifier(Arc::new(WebPkiVerifier::new(root_store, None))),
+        );
+        Ok(config)
+    }
+
+    /// Returns a verifier which records the certificates verified by `inner`
+    ///
+    /// Use this to monitor a custom `ClientConfig`, the result of `inner` is returned unchanged.
+    pub fn verifier(
+        self: &Arc<Self>,
+        inner: Arc<dyn ServerCertVerifier>,
+    ) -> Arc<dyn ServerCertVerifier> {
+        Arc::new(MonitoringVerifier {
+            inner,
+            monitor: Arc::clone(self),
+        })
+    }
+
+    /// Returns the last recorded status of the certificate of each name server
+    pub fn statuses(&self) -> Vec<CertificateStatus> {
+        let statuses = self.statuses.lock().expect("certificate monitor poisoned");
+        let mut statuses = statuses.values().cloned().collect::<Vec<_>>();
+        statuses.sort_by(|a, b| a.server_name.cmp(&b.server_name));
+        statuses
+    }
+
+    /// Logs a warning for each certificate which needs attention at `now`
+    ///
+    /// # Return
+    ///
+    /// The status of the certificates which are not valid, or expire within `warn_before`
+    pub fn check(&self, now: SystemTime) -> Vec<CertificateStatus> {
+        let needs_attention = self
+            .statuses()
+            .into_iter()
+            .filter(|status| self.needs_attention(status, now))
+            .collect::<Vec<_>>();
+
+        for status in &needs_attention {
+            self.warn(status, now);
+        }
+
+        needs_attention
+    }
+
+    /// Checks the recorded certificates every `interval`, see `check`
+    ///
+    /// Certificates are only recorded on new connections, so the expiry of a certificate which
+    ///  was replaced is reported until the next connection to the name server.
+    pub async fn run(self: Arc<Self>, interval: Duration) {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            self.check(SystemTime::now());
+        }
+    }
+
+    fn record(
+        &self,
+        server_name: &ServerName,
+        end_entity: &Certificate,
+        ocsp_response: &[u8],
+        result: &Result<ServerCertVerified, rustls::Error>,
+        now: SystemTime,
+    ) {
+        let server_name = match server_name {
+            ServerName::DnsName(name) => name.as_ref().to_string(),
+            ServerName::IpAddress(ip) => ip.to_string(),
+            name => format!("{name:?}"),
+        };
+
+        let status = CertificateStatus {
+            server_name: server_name.clone(),
+            not_after: certificate_not_after(&end_entity.0),
+            ocsp: OcspStatus::from_response(ocsp_response),
+            error: result.as_ref().err().map(ToString::to_string),
+            checked_at: now,
+        };
+
+        if self.needs_attention(&status, now) {
+            self.warn(&status, now);
+        } else {
+            debug!(
+                "certificate of {} is valid until {:?}, OCSP status: {:?}",
+                server_name, status.not_after, status.ocsp
+            );
+        }
+
+        self.statuses
+            .lock()
+            .expect("certificate monitor poisoned")
+            .insert(server_name, status);
+    }
+
+    fn needs_attention(&self, status: &CertificateStatus, now: SystemTime) -> bool {
+        status.error.is_some()
+            || status.ocsp == OcspStatus::Revoked
+            || status
+                .expires_in(now)
+                .map_or(true, |expires_in| expires_in < self.warn_before)
+    }
+
+    fn warn(&self, status: &CertificateStatus, now: SystemTime) {
+        let name = &status.server_name;
+        if let Some(error) = &status.error {
+            warn!("certificate of {name} failed verification: {error}");
+        }
+        if status.ocsp == OcspStatus::Revoked {
+            warn!("certificate of {name} was revoked according to its OCSP response");
+        }
+        match (status.not_after, status.expires_in(now)) {
+            (None, _) => warn!("expiry of the certificate of {name} could not be read"),
+            (Some(not_after), None) => {
+                warn!("certificate of {name} expired at {not_after:?}")
+            }
+            (Some(not_after), Some(expires_in)) if expires_in < self.warn_before => warn!(
+                "certificate of {name} expires in {}h at {not_after:?}",
+                expires_in.as_secs() / 3600
+            ),
+            _ => (),
+        }
+    }
+}
+
+/// The status of the certificate of a name server, as of the last handshake
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CertificateStatus {
+    /// The name with which the name server was verified
+    pub server_name: String,
+    /// The end of the validity period of the certificate, if it could be read
+    pub not_after: Option<SystemTime>,
+    /// The status in the OCSP response stapled by the name server
+    pub ocsp: OcspStatus,
+    /// The error if the certificate failed verification
+    pub error: Option<String>,
+    /// The time of the handshake
+    pub checked_at: SystemTime,
+}
+
+impl CertificateStatus {
+    /// Returns the time until the certificate expires, or `None` if it is expired or unknown
+    pub fn expires_in(&self, now: SystemTime) -> Option<Duration> {
+        self.not_after?.duration_since(now).ok()
+    }
+}
+
+/// The certificate status in an OCSP response
+///
+/// The signature of the response is not verified, the status is only used for monitoring.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OcspStatus {
+    /// No OCSP response was stapled to the handshake
+    NotStapled,
+    /// The certificate is not revoked
+    Good,
+    /// The certificate is revoked
+    Revoked,
+    /// The responder does not know the certificate
+    Unknown,
+    /// The response was not successful, or could not be read
+    Invalid,
+}
+
+impl OcspStatus {
+    /// Reads the status of the first certificate in a DER encoded OCSP response, RFC 6960
+    fn from_response(response: &[u8]) -> Self {
+        if response.is_empty() {
+            return Self::NotStapled;
+        }
+
+        ocsp_cert_status(response).unwrap_or(Self::Invalid)
+    }
+}
+
+struct MonitoringVerifier {
+    inner: Arc<dyn ServerCertVerifier>,
+    monitor: Arc<CertificateMonitor>,
+}
+
+impl ServerCertVerifier for MonitoringVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let result = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            scts,
+            ocsp_response,
+            now,
+        );
+
+        self.monitor
+            .record(server_name, end_entity, ocsp_response, &result, now);
+        result
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &Certificate,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &Certificate,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+
+    fn request_scts(&self) -> bool {
+        self.inner.request_scts()
+    }
+}
+
+const SEQUENCE: u8 = 0x30;
+const ENUMERATED: u8 = 0x0a;
+const OCTET_STRING: u8 = 0x04;
+const UTC_TIME: u8 = 0x17;
+const GENERALIZED_TIME: u8 = 0x18;
+
+/// Reads one DER element, returning its tag, its contents and the remaining input
+fn der_element(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, input) = input.split_first()?;
+    let (&len, input) = input.split_first()?;
+
+    let (len, input) = if len < 0x80 {
+        (usize::from(len), input)
+    } else {
+        let count = usize::from(len & 0x7f);
+        if count == 0 || count > 4 || input.len() < count {
+            return None;
+        }
+        let (len, input) = input.split_at(count);
+        let len = len
+            .iter()
+            .fold(0_usize, |len, byte| len << 8 | usize::from(*byte));
+        (len, input)
+    };
+
+    if input.len() < len {
+        return None;
+    }
+    let (contents, input) = input.split_at(len);
+    Some((tag, contents, input))
+}
+
+/// Reads a DER element with the expected tag, returning its contents and the remaining input
+fn der_expect(input: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+    match der_element(input)? {
+        (found, contents, input) if found == tag => Some((contents, input)),
+        _ => None,
+    }
+}
+
+/// Reads the end of the validity period of a DER encoded X.509 certificate, RFC 5280
+fn certificate_not_after(certificate: &[u8]) -> Option<SystemTime> {
+    let (certificate, _) = der_expect(certificate, SEQUENCE)?;
+    let (tbs_certificate, _) = der_expect(certificate, SEQUENCE)?;
+
+    // the version is optional and explicitly tagged [0]
+    let (tag, _, mut fields) = der_element(tbs_certificate)?;
+    if tag != 0xa0 {
+        fields = tbs_certificate;
+    }
+
+    // serialNumber, signature and issuer precede the validity
+    for _ in 0..3 {
+        (_, _, fields) = der_element(fields)?;
+    }
+
+    let (validity, _) = der_expect(fields, SEQUENCE)?;
+    let (_, _, validity) = der_element(validity)?;
+    let (tag, not_after, _) = der_element(validity)?;
+    der_time(tag, not_after)
+}
+
+/// Reads the certStatus of the first SingleResponse in an OCSPResponse
+fn ocsp_cert_status(response: &[u8]) -> Option<OcspStatus> {
+    let (response, _) = der_expect(response, SEQUENCE)?;
+    let (response_status, response) = der_expect(response, ENUMERATED)?;
+    if response_status != [0] {
+        // not successful
+        return Some(OcspStatus::Invalid);
+    }
+
+    // responseBytes [0] EXPLICIT SEQUENCE { responseType, response OCTET STRING }
+    let (response_bytes, _) = der_expect(response, 0xa0)?;
+    let (response_bytes, _) = der_expect(response_bytes, SEQUENCE)?;
+    let (_, _, response_bytes) = der_element(response_bytes)?;
+    let (basic_response, _) = der_expect(response_bytes, OCTET_STRING)?;
+
+    let (basic_response, _) = der_expect(basic_response, SEQUENCE)?;
+    let (response_data, _) = der_expect(basic_response, SEQUENCE)?;
+
+    // the version is optional and explicitly tagged [0], followed by responderID and producedAt
+    let (tag, _, mut fields) = der_element(response_data)?;
+    if tag != 0xa0 {
+        fields = response_data;
+    }
+    for _ in 0..2 {
+        (_, _, fields) = der_element(fields)?;
+    }
+
+    let (responses, _) = der_expect(fields, SEQUENCE)?;
+    let (single_response, _) = der_expect(responses, SEQUENCE)?;
+    let (_, _, single_response) = der_element(single_response)?;
+    let (cert_status, _, _) = der_element(single_response)?;
+
+    Some(match cert_status {
+        0x80 => OcspStatus::Good,
+        0xa1 => OcspStatus::Revoked,
+        0x82 => OcspStatus::Unknown,
+        _ => OcspStatus::Invalid,
+    })
+}
+
+/// Converts a UTCTime or GeneralizedTime in UTC, without fractional seconds
+fn der_time(tag: u8, time: &[u8]) -> Option<SystemTime> {
+    let time = std::str::from_utf8(time).ok()?.strip_suffix('Z')?;
+    let (year, time) = match tag {
+        UTC_TIME if time.len() == 12 => {
+            let year = time[..2].parse::<i64>().ok()?;
+            // RFC 5280, two digit years of 50 and above are in the 20th century
+            (
+                if year >= 50 { 1900 + year } else { 2000 + year },
+                &time[2..],
+            )
+        }
+        GENERALIZED_TIME if time.len() == 14 => (time[..4].parse::<i64>().ok()?, &time[4..]),
+        _ => return None,
+    };
+
+    let field = |i: usize| time.get(i..i + 2)?.parse::<i64>().ok();
+    let (month, day) = (field(0)?, field(2)?);
+    let (hour, minute, second) = (field(4)?, field(6)?, field(8)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // days since the epoch in the proleptic Gregorian calendar
+    let (year, month) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let seconds = days * 86_400 + hour * 3600 + minute * 60 + second;
+    Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(seconds).ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufReader;
+
+    use rustls::RootCertStore;
+
+    use super::*;
+
+    const CA: &[u8] = include_bytes!("../../../tests/test-data/ca.der");
+    const CERT: &[u8] = include_bytes!("../../../tests/test-data/cert.pem");
+
+    fn cert() -> Certificate {
+        let mut reader = BufReader::new(CERT);
+        Certificate(rustls_pemfile::certs(&mut reader).unwrap().remove(0))
+    }
+
+    fn der(tag: u8, contents: &[&[u8]]) -> Vec<u8> {
+        let contents = contents.concat();
+        let mut der = vec![tag];
+        if contents.len() < 0x80 {
+            der.push(contents.len() as u8);
+        } else {
+            der.extend([0x82, (contents.len() >> 8) as u8, contents.len() as u8]);
+        }
+        der.extend(contents);
+        der
+    }
+
+    fn ocsp_response(cert_status: &[u8]) -> Vec<u8> {
+        let single_response = der(
+            SEQUENCE,
+            &[
+                &der(SEQUENCE, &[]),
+                cert_status,
+                &der(GENERALIZED_TIME, &[b"20240101000000Z"]),
+            ],
+        );
+        let response_data = der(
+            SEQUENCE,
+            &[
+                &der(0xa2, &[&der(OCTET_STRING, &[&[0; 20]])]),
+                &der(GENERALIZED_TIME, &[b"20240101000000Z"]),
+                &der(SEQUENCE, &[&single_response]),
+            ],
+        );
+        let basic_response = der(SEQUENCE, &[&response_data, &der(SEQUENCE, &[])]);
+
+        der(
+            SEQUENCE,
+            &[
+                &der(ENUMERATED, &[&[0]]),
+                &der(
+                    0xa0,
+                    &[&der(
+                        SEQUENCE,
+                        &[
+                            &der(
+                                0x06,
+                                &[&[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x01, 0x01]],
+                            ),
+                            &der(OCTET_STRING, &[&basic_response]),
+                        ],
+                    )],
+                ),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_certificate_not_after() {
+        assert_eq!(
+            certificate_not_after(CA),
+            Some(UNIX_EPOCH + Duration::from_secs(1_733_097_898))
+        );
+        assert_eq!(
+            certificate_not_after(&cert().0),
+            Some(UNIX_EPOCH + Duration::from_secs(1_733_097_898))
+        );
+        assert_eq!(certificate_not_after(&CA[..100]), None);
+    }
+
+    #[test]
+    fn test_der_time() {
+        let time = |tag, time: &str| {
+            der_time(tag, time.as_bytes()).map(|t| t.duration_since(UNIX_EPOCH).unwrap().as_secs())
+        };
+
+        assert_eq!(time(UTC_TIME, "700101000000Z"), Some(0));
+        assert_eq!(time(UTC_TIME, "000229120000Z"), Some(951_825_600));
+        assert_eq!(
+            time(GENERALIZED_TIME, "20491231235959Z"),
+            Some(2_524_607_999)
+        );
+        assert_eq!(time(GENERALIZED_TIME, "20491331235959Z"), None);
+        assert_eq!(time(UTC_TIME, "20491231235959Z"), None);
+    }
+
+    #[test]
+    fn test_ocsp_status() {
+        assert_eq!(OcspStatus::from_response(&[]), OcspStatus::NotStapled);
+        assert_eq!(
+            OcspStatus::from_response(&ocsp_response(&[0x80, 0])),
+            OcspStatus::Good
+        );
+        assert_eq!(
+            OcspStatus::from_response(&ocsp_response(&der(
+                0xa1,
+                &[&der(GENERALIZED_TIME, &[b"20240101000000Z"])]
+            ))),
+            OcspStatus::Revoked
+        );
+        assert_eq!(
+            OcspStatus::from_response(&ocsp_response(&[0x82, 0])),
+            OcspStatus::Unknown
+        );
+
+        // tryLater
+        let try_later = der(SEQUENCE, &[&der(ENUMERATED, &[&[3]])]);
+        assert_eq!(OcspStatus::from_response(&try_later), OcspStatus::Invalid);
+        assert_eq!(
+            OcspStatus::from_response(&[0x30, 0x05]),
+            OcspStatus::Invalid
+        );
+    }
+
+    #[test]
+    fn test_monitor() {
+        let mut roots = RootCertStore::empty();
+        roots.add(&Certificate(CA.to_vec())).unwrap();
+
+        let monitor = CertificateMonitor::new(Duration::from_secs(14 * 24 * 60 * 60));
+        let verifier = monitor.verifier(Arc::new(WebPkiVerifier::new(roots, None)));
+        let server_name = ServerName::try_from("ns.example.com").unwrap();
+        let not_after = UNIX_EPOCH + Duration::from_secs(1_733_097_898);
+
+        // valid for more than two weeks
+        let now = not_after - Duration::from_secs(30 * 24 * 60 * 60);
+        let response = ocsp_response(&[0x80, 0]);
+        verifier
+            .verify_server_cert(
+                &cert(),
+                &[],
+                &server_name,
+                &mut [].into_iter(),
+                &response,
+                now,
+            )
+            .unwrap();
+
+        let statuses = monitor.statuses();
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].server_name, "ns.example.com");
+        assert_eq!(statuses[0].not_after, Some(not_after));
+        assert_eq!(statuses[0].ocsp, OcspStatus::Good);
+        assert_eq!(statuses[0].error, None);
+        assert!(monitor.check(now).is_empty());
+
+        // expiring soon
+        let soon = not_after - Duration::from_secs(24 * 60 * 60);
+        assert_eq!(monitor.check(soon), statuses);
+
+        // expired, which fails verification and replaces the recorded status
+        let later = not_after + Duration::from_secs(1);
+        verifier
+            .verify_server_cert(&cert(), &[], &server_name, &mut [].into_iter(), &[], later)
+            .unwrap_err();
+
+        let statuses = monitor.check(later);
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].ocsp, OcspStatus::NotStapled);
+        assert!(statuses[0].error.is_some());
+        assert_eq!(statuses[0].expires_in(later), None);
+    }
+}
@@ -17,6 +17,8 @@ use std::time::Duration;
 use std::sync::Arc;
 
 use proto::rr::Name;
+use proto::udp::UdpSocketPoolConfig;
+use proto::xfer::Transport;
 #[cfg(feature = "dns-over-rustls")]
 use rustls::ClientConfig;
 
@@ -38,6 +40,9 @@ pub struct ResolverConfig {
     search: Vec<Name>,
     // nameservers to use for resolution.
     name_servers: NameServerConfigGroup,
+    // nameservers to use for the names in specific domains
+    #[cfg_attr(feature = "serde-config", serde(default))]
+    scoped: Vec<ScopedResolverConfig>,
 }
 
 impl ResolverConfig {
@@ -48,6 +53,7 @@ impl ResolverConfig {
             domain: None,
             search: vec![],
             name_servers: NameServerConfigGroup::new(),
+            scoped: vec![],
         }
     }
 
@@ -62,6 +68,7 @@ impl ResolverConfig {
             domain: None,
             search: vec![],
             name_servers: NameServerConfigGroup::google(),
+            scoped: vec![],
         }
     }
 
@@ -78,6 +85,7 @@ impl ResolverConfig {
             domain: None,
             search: vec![],
             name_servers: NameServerConfigGroup::google_tls(),
+            scoped: vec![],
         }
     }
 
@@ -94,6 +102,7 @@ impl ResolverConfig {
             domain: None,
             search: vec![],
             name_servers: NameServerConfigGroup::google_https(),
+            scoped: vec![],
         }
     }
 
@@ -110,6 +119,7 @@ impl ResolverConfig {
             domain: None,
             search: vec![],
             name_servers: NameServerConfigGroup::google_h3(),
+            scoped: vec![],
         }
     }
 
@@ -124,6 +134,7 @@ impl ResolverConfig {
             domain: None,
             search: vec![],
             name_servers: NameServerConfigGroup::cloudflare(),
+            scoped: vec![],
         }
     }
 
@@ -140,6 +151,7 @@ impl ResolverConfig {
             domain: None,
             search: vec![],
             name_servers: NameServerConfigGroup::cloudflare_tls(),
+            scoped: vec![],
         }
     }
 
@@ -156,6 +168,7 @@ impl ResolverConfig {
             domain: None,
             search: vec![],
             name_servers: NameServerConfigGroup::cloudflare_https(),
+            scoped: vec![],
         }
     }
 
@@ -170,6 +183,7 @@ impl ResolverConfig {
             domain: None,
             search: vec![],
             name_servers: NameServerConfigGroup::quad9(),
+            scoped: vec![],
         }
     }
 
@@ -186,6 +200,7 @@ impl ResolverConfig {
             domain: None,
             search: vec![],
             name_servers: NameServerConfigGroup::quad9_tls(),
+            scoped: vec![],
         }
     }
 
@@ -202,6 +217,7 @@ impl ResolverConfig {
             domain: None,
             search: vec![],
             name_servers: NameServerConfigGroup::quad9_https(),
+            scoped: vec![],
         }
     }
 
@@ -221,6 +237,7 @@ impl ResolverConfig {
             domain,
             search,
             name_servers: name_servers.into(),
+            scoped: vec![],
         }
     }
 
@@ -260,6 +277,38 @@ impl ResolverConfig {
         &self.name_servers
     }
 
+    /// Replaces the name servers, e.g. by those of a changed resolv.conf
+    pub(crate) fn set_name_servers(&mut self, name_servers: NameServerConfigGroup) {
+        self.name_servers = name_servers;
+    }
+
+    /// Add name servers for the names in a domain, these are used instead of the name servers of
+    ///  this configuration
+    pub fn add_scoped(&mut self, scoped: ScopedResolverConfig) {
+        self.scoped.push(scoped);
+    }
+
+    /// Returns the name servers for the names in specific domains
+    pub fn scoped(&self) -> &[ScopedResolverConfig] {
+        &self.scoped
+    }
+
+    /// Returns the scoped configuration for the name, if any
+    ///
+    /// The most specific domain containing the name is chosen, of equally specific domains the
+    ///  one with the lowest `search_order`.
+    pub fn scoped_for(&self, name: &Name) -> Option<&ScopedResolverConfig> {
+        self.scoped
+            .iter()
+            .filter(|scoped| scoped.domain.zone_of(name))
+            .min_by_key(|scoped| {
+                (
+                    std::cmp::Reverse(scoped.domain.num_labels()),
+                    scoped.search_order,
+                )
+            })
+    }
+
     /// return the associated TlsClientConfig
     #[cfg(feature = "dns-over-rustls")]
     #[cfg_attr(docsrs, doc(cfg(feature = "dns-over-rustls")))]
@@ -315,6 +364,33 @@ impl Default for ResolverConfig {
     }
 }
 
+/// Name servers for the names in a domain, which are used instead of the default name servers
+///
+/// These are e.g. the scoped resolvers of macOS, configured in `/etc/resolver`, or the rules of
+///  the Name Resolution Policy Table (NRPT) on Windows.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-config", derive(Serialize, Deserialize))]
+pub struct ScopedResolverConfig {
+    /// The domain of the names which are resolved with these name servers
+    pub domain: Name,
+    /// The name servers for the names in the domain
+    pub name_servers: NameServerConfigGroup,
+    /// Of domains which are equally specific, the one with the lowest order is used
+    #[cfg_attr(feature = "serde-config", serde(default))]
+    pub search_order: u32,
+}
+
+impl ScopedResolverConfig {
+    /// Creates a configuration for the names in the domain
+    pub fn new<G: Into<NameServerConfigGroup>>(domain: Name, name_servers: G) -> Self {
+        Self {
+            domain,
+            name_servers: name_servers.into(),
+            search_order: 0,
+        }
+    }
+}
+
 /// The protocol on which a NameServer should be communicated with
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[cfg_attr(
@@ -371,6 +447,25 @@ impl fmt::Display for Protocol {
     }
 }
 
+impl From<Protocol> for Transport {
+    fn from(protocol: Protocol) -> Self {
+        match protocol {
+            Protocol::Udp => Self::Udp,
+            Protocol::Tcp => Self::Tcp,
+            #[cfg(feature = "dns-over-tls")]
+            Protocol::Tls => Self::Tls,
+            #[cfg(feature = "dns-over-https")]
+            Protocol::Https => Self::Https,
+            #[cfg(feature = "dns-over-quic")]
+            Protocol::Quic => Self::Quic,
+            #[cfg(feature = "dns-over-h3")]
+            Protocol::H3 => Self::H3,
+            #[cfg(feature = "mdns")]
+            Protocol::Mdns => Self::Mdns,
+        }
+    }
+}
+
 impl Protocol {
     /// Returns true if this is a datagram oriented protocol, e.g. UDP
     pub fn is_datagram(self) -> bool {
@@ -472,6 +567,15 @@ pub struct NameServerConfig {
     /// Defaults to false.
     #[cfg_attr(feature = "serde-config", serde(default))]
     pub trust_negative_responses: bool,
+    /// How failures of the encryption of this name server are handled, only relevant for
+    ///  encrypted protocols
+    #[cfg_attr(feature = "serde-config", serde(default))]
+    pub privacy: PrivacyPolicy,
+    /// The share of the queries sent to this name server with
+    ///  [`ServerOrderingStrategy::WeightedRandom`], relative to the weights of the other name
+    ///  servers. A weight of 0 only uses this name server if all others failed. Defaults to 1
+    #[cfg_attr(feature = "serde-config", serde(default = "default_weight"))]
+    pub weight: u32,
     #[cfg(feature = "dns-over-rustls")]
     #[cfg_attr(docsrs, doc(cfg(feature = "dns-over-rustls")))]
     #[cfg_attr(feature = "serde-config", serde(skip))]
@@ -482,6 +586,82 @@ pub struct NameServerConfig {
     pub tls_config: Option<TlsClientConfig>,
     /// The client address (IP and port) to use for connecting to the server.
     pub bind_addr: Option<SocketAddr>,
+    /// The network interface, or on Linux the VRF, to send the queries through, with
+    ///  `SO_BINDTODEVICE`
+    ///
+    /// This forces the queries out of a specific uplink on multi-homed hosts and routers. It is
+    ///  used for the UDP and TCP protocols, and only supported on Linux, Android and Fuchsia.
+    #[cfg_attr(feature = "serde-config", serde(default))]
+    pub bind_device: Option<String>,
+    /// The options of the HTTP requests, only relevant for DNS over HTTPS
+    #[cfg_attr(feature = "serde-config", serde(default))]
+    pub http: Option<HttpConfig>,
+}
+
+/// The options of the HTTP requests to a name server with DNS over HTTPS
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde-config",
+    derive(Serialize, Deserialize),
+    serde(default)
+)]
+#[non_exhaustive]
+pub struct HttpConfig {
+    /// The URI template of the queries, e.g. the `dohpath` of a SVCB record, like
+    ///  `/dns-query{?dns}`. Defaults to `/dns-query`
+    pub path: String,
+    /// The headers added to all requests, e.g. `Authorization` with a token of the name server
+    pub headers: Vec<(String, String)>,
+    /// Speak HTTP/1.1 with name servers and middleboxes which do not negotiate HTTP/2, the queries
+    ///  are then sent one at a time. Disabled by default
+    pub http1_fallback: bool,
+}
+
+impl HttpConfig {
+    /// Creates the options for the URI template of the queries, e.g. `/dns-query{?dns}`
+    pub fn with_path(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            path: "/dns-query".to_string(),
+            headers: Vec::new(),
+            http1_fallback: false,
+        }
+    }
+}
+
+/// How a name server with an encrypted protocol is used when the encryption fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-config", derive(Serialize, Deserialize))]
+pub enum PrivacyPolicy {
+    /// Only the encrypted protocol is used, queries fail if the connection, or the authentication
+    ///  of the name server, fails
+    Strict,
+    /// If the encrypted connection fails, queries fall back to unencrypted DNS over TCP on port 53
+    ///  of the same address. Encryption is attempted again after
+    ///  `ResolverOpts::encryption_probe_interval`.
+    Opportunistic,
+    /// Encryption is not used, the address is queried with unencrypted DNS over TCP on port 53
+    Off,
+}
+
+impl Default for PrivacyPolicy {
+    /// Returns [`PrivacyPolicy::Strict`] as the default.
+    fn default() -> Self {
+        Self::Strict
+    }
+}
+
+#[cfg(feature = "serde-config")]
+fn default_weight() -> u32 {
+    1
 }
 
 impl NameServerConfig {
@@ -491,10 +671,14 @@ impl NameServerConfig {
             socket_addr,
             protocol,
             trust_negative_responses: true,
+            privacy: PrivacyPolicy::default(),
+            weight: 1,
             tls_dns_name: None,
             #[cfg(feature = "dns-over-rustls")]
             tls_config: None,
             bind_addr: None,
+            bind_device: None,
+            http: None,
         }
     }
 }
@@ -577,18 +761,26 @@ impl NameServerConfigGroup {
                 protocol: Protocol::Udp,
                 tls_dns_name: None,
                 trust_negative_responses,
+                privacy: PrivacyPolicy::default(),
+                weight: 1,
                 #[cfg(feature = "dns-over-rustls")]
                 tls_config: None,
                 bind_addr: None,
+                bind_device: None,
+                http: None,
             };
             let tcp = NameServerConfig {
                 socket_addr,
                 protocol: Protocol::Tcp,
                 tls_dns_name: None,
                 trust_negative_responses,
+                privacy: PrivacyPolicy::default(),
+                weight: 1,
                 #[cfg(feature = "dns-over-rustls")]
                 tls_config: None,
                 bind_addr: None,
+                bind_device: None,
+                http: None,
             };
 
             name_servers.push(udp);
@@ -616,9 +808,13 @@ impl NameServerConfigGroup {
                 protocol,
                 tls_dns_name: Some(tls_dns_name.clone()),
                 trust_negative_responses,
+                privacy: PrivacyPolicy::default(),
+                weight: 1,
                 #[cfg(feature = "dns-over-rustls")]
                 tls_config: None,
                 bind_addr: None,
+                bind_device: None,
+                http: None,
             };
 
             name_servers.push(config);
@@ -830,6 +1026,14 @@ impl NameServerConfigGroup {
         }
         self
     }
+
+    /// Sets the network interface, or on Linux the VRF, to connect through on all name servers.
+    pub fn with_bind_device(mut self, bind_device: Option<String>) -> Self {
+        for server in &mut self.0 {
+            server.bind_device = bind_device.clone();
+        }
+        self
+    }
 }
 
 impl Default for NameServerConfigGroup {
@@ -864,6 +1068,185 @@ impl From<Vec<NameServerConfig>> for NameServerConfigGroup {
     }
 }
 
+/// A proxy the connections to the name servers are tunneled through, see `ResolverOpts::proxy`
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-config", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub struct ProxyConfig {
+    /// The protocol spoken by the proxy
+    pub protocol: ProxyProtocol,
+    /// The address of the proxy
+    pub addr: SocketAddr,
+    /// The credentials to authenticate to the proxy with, if it requires them
+    #[cfg_attr(feature = "serde-config", serde(default))]
+    pub auth: Option<ProxyAuth>,
+    /// Which queries share a circuit of a Tor proxy, see [`StreamIsolation`]
+    #[cfg_attr(feature = "serde-config", serde(default))]
+    pub isolation: StreamIsolation,
+}
+
+impl ProxyConfig {
+    /// A SOCKS5 proxy, without authentication
+    pub fn socks5(addr: SocketAddr) -> Self {
+        Self {
+            protocol: ProxyProtocol::Socks5,
+            addr,
+            auth: None,
+            isolation: StreamIsolation::None,
+        }
+    }
+
+    /// An HTTP proxy supporting the CONNECT method, without authentication
+    pub fn http_connect(addr: SocketAddr) -> Self {
+        Self {
+            protocol: ProxyProtocol::HttpConnect,
+            addr,
+            auth: None,
+            isolation: StreamIsolation::None,
+        }
+    }
+
+    /// Sets the credentials to authenticate to the proxy with
+    pub fn with_auth(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.auth = Some(ProxyAuth {
+            username: username.into(),
+            password: password.into(),
+        });
+        self
+    }
+
+    /// Sets which queries share a circuit of a Tor proxy
+    pub fn with_isolation(mut self, isolation: StreamIsolation) -> Self {
+        self.isolation = isolation;
+        self
+    }
+}
+
+/// Which queries are sent over the same circuit of a Tor proxy
+///
+/// Tor sends the streams with different SOCKS credentials over different circuits, so that the
+///  exit relays can not correlate them. With isolation, each query gets a connection of its own,
+///  with credentials derived from the isolation, and the connections to the name servers are not
+///  reused. The username of `ProxyConfig::auth` is kept and its password replaced, as Tor accepts
+///  any credentials.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-config", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum StreamIsolation {
+    /// All queries share the connections, and so the circuits
+    None,
+    /// Every query is sent over a circuit of its own
+    PerQuery,
+    /// Queries for the same name share a circuit, queries for different names do not
+    PerName,
+}
+
+impl Default for StreamIsolation {
+    /// Returns [`StreamIsolation::None`] as the default.
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+/// The protocol of a [`ProxyConfig`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-config", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum ProxyProtocol {
+    /// SOCKS version 5, [RFC 1928](https://datatracker.ietf.org/doc/html/rfc1928), with the
+    ///  username and password authentication of [RFC 1929](https://datatracker.ietf.org/doc/html/rfc1929)
+    Socks5,
+    /// The HTTP CONNECT method, [RFC 9110](https://datatracker.ietf.org/doc/html/rfc9110#section-9.3.6),
+    ///  with basic authentication
+    HttpConnect,
+}
+
+/// The username and password to authenticate to a proxy with
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-config", derive(Serialize, Deserialize))]
+pub struct ProxyAuth {
+    /// The username
+    pub username: String,
+    /// The password
+    pub password: String,
+}
+
+// the password is not logged
+impl fmt::Debug for ProxyAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProxyAuth")
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+
+/// How the hosts file is used, see `ResolverOpts::use_hosts_file`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde-config",
+    derive(Serialize, Deserialize),
+    serde(default)
+)]
+#[non_exhaustive]
+pub struct HostsOpts {
+    /// Return all addresses of a name, otherwise only the first of each family. Defaults to true
+    pub multiple_addresses: bool,
+    /// Resolve the aliases following the canonical name of an entry. Defaults to true
+    pub aliases: bool,
+    /// Answer reverse lookups of the addresses with the canonical name of their first entry.
+    ///  Defaults to true
+    pub reverse: bool,
+    /// Reload the file when it changed, which is checked at most once a second. Defaults to false
+    pub reload: bool,
+}
+
+impl Default for HostsOpts {
+    fn default() -> Self {
+        Self {
+            multiple_addresses: true,
+            aliases: true,
+            reverse: true,
+            reload: false,
+        }
+    }
+}
+
+/// Health tracking of the name servers, see `ResolverOpts::health`
+///
+/// A name server which failed `failure_threshold` times in a row is demoted, it is only queried
+///  after all other name servers, until its backoff expired or it answered again. The backoff
+///  doubles with each further failure, up to `max_backoff`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde-config",
+    derive(Serialize, Deserialize),
+    serde(default)
+)]
+#[non_exhaustive]
+pub struct HealthOpts {
+    /// Consecutive failures after which a name server is demoted, 0 disables demotion.
+    ///  Defaults to 3
+    pub failure_threshold: u32,
+    /// How long a name server is demoted after reaching the threshold. Defaults to 1 second
+    pub backoff: Duration,
+    /// The maximum backoff. Defaults to 5 minutes
+    pub max_backoff: Duration,
+    /// How often all name servers are probed with a query for the root name servers, see
+    ///  `AsyncResolver::health_probes`. Defaults to 30 seconds
+    pub probe_interval: Duration,
+}
+
+impl Default for HealthOpts {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 3,
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(300),
+            probe_interval: Duration::from_secs(30),
+        }
+    }
+}
+
 /// The lookup ip strategy
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "serde-config", derive(Serialize, Deserialize))]
@@ -897,6 +1280,12 @@ pub enum ServerOrderingStrategy {
     /// The order provided to the resolver is used. The ordering does not vary
     /// over time.
     UserProvidedOrder,
+    /// Each query starts with the next name server in the order provided to the resolver, so
+    /// that the queries are distributed evenly.
+    RoundRobin,
+    /// The name servers are ordered randomly for each query, with the probability of a name
+    /// server being first proportional to its `NameServerConfig::weight`.
+    WeightedRandom,
 }
 
 impl Default for ServerOrderingStrategy {
@@ -906,6 +1295,44 @@ impl Default for ServerOrderingStrategy {
     }
 }
 
+/// How a NXDOMAIN response for a name with an expired answer in the cache is handled
+///
+/// This protects against transient failures of a single name server or registry, where a name
+///  which resolved before suddenly does not exist anymore.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-config", derive(Serialize, Deserialize))]
+pub enum NxDomainRecheck {
+    /// The NXDOMAIN is returned and cached like any other response
+    Disabled,
+    /// The NXDOMAIN must be confirmed by another name server before the cached answer is
+    /// evicted. If another name server still returns an answer, that answer is used instead.
+    Confirm,
+    /// As `Confirm`, but if the NXDOMAIN can not be confirmed, e.g. because the other name
+    /// servers do not respond, the expired answer is returned instead (serve stale).
+    ConfirmOrServeStale,
+}
+
+impl Default for NxDomainRecheck {
+    /// Returns [`NxDomainRecheck::Disabled`] as the default.
+    fn default() -> Self {
+        Self::Disabled
+    }
+}
+
+/// The configuration and options a resolver is running with, see
+///  `AsyncResolver::effective_opts`
+///
+/// With the `serde-config` feature this can be serialized, e.g. to be included in a bug report.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde-config", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub struct EffectiveResolverConfig {
+    /// The name servers and search domains, including those read from the system configuration
+    pub config: ResolverConfig,
+    /// The options, with the defaults and the options of the system configuration applied
+    pub options: ResolverOpts,
+}
+
 /// Configuration for the Resolver
 #[derive(Debug, Clone, Eq, PartialEq)]
 #[cfg_attr(
@@ -923,6 +1350,8 @@ pub struct ResolverOpts {
     /// Specify the timeout for a request. Defaults to 5 seconds
     pub timeout: Duration,
     /// Number of retries after lookup failure before giving up. Defaults to 2
+    ///
+    /// See `AsyncResolver::with_retry_policy` for more control over the retries.
     pub attempts: usize,
     /// Rotate through the resource records in the response (if there is more than one for a given name)
     pub rotate: bool,
@@ -939,6 +1368,8 @@ pub struct ResolverOpts {
     pub cache_size: usize,
     /// Check /ect/hosts file before dns requery (only works for unix like OS)
     pub use_hosts_file: bool,
+    /// How the hosts file is used
+    pub hosts: HostsOpts,
     /// Optional minimum TTL for positive responses.
     ///
     /// If this is set, any positive responses with a TTL lower than this value will have a TTL of
@@ -969,8 +1400,14 @@ pub struct ResolverOpts {
     /// to a number of servers in parallel. Defaults to 2; 0 or 1 will execute requests serially.
     pub num_concurrent_reqs: usize,
     /// Preserve all intermediate records in the lookup response, such as CNAME records
+    ///
+    /// This retains the chain of aliases of `Lookup::cname_chain`.
     pub preserve_intermediates: bool,
     /// Try queries over TCP if they fail over UDP.
+    ///
+    /// Truncated responses over UDP are always retried over TCP, on the connection to each name
+    ///  server which is shared by all requests. The transport which produced the final response
+    ///  is recorded in `DnsResponse::transport`.
     pub try_tcp_on_error: bool,
     /// The server ordering strategy that the resolver should use.
     pub server_ordering_strategy: ServerOrderingStrategy,
@@ -982,6 +1419,72 @@ pub struct ResolverOpts {
     pub authentic_data: bool,
     /// Shuffle DNS servers before each query.
     pub shuffle_dns_servers: bool,
+    /// How NXDOMAIN responses for names with expired answers in the cache are handled.
+    ///
+    /// When enabled, expired answers are kept in the cache until they are replaced or evicted.
+    pub nxdomain_recheck: NxDomainRecheck,
+    /// Maximum number of records of one RRset which are cached, any further records in an answer
+    ///  are dropped. Defaults to 256
+    pub max_rrset_records: usize,
+    /// Do not look up names without a dot as they are, only with the search list and domain
+    ///
+    /// This is the `no-tld-query` option of resolv.conf.
+    pub no_tld_query: bool,
+    /// Replace the name servers by those of `/etc/resolv.conf` when the file changes
+    ///
+    /// The file is checked for changes on lookups, at most once a second. The other options
+    ///  and the search list are not changed, see `system_conf::SystemConfWatcher` for those.
+    ///  This only applies to unix with the `system-config` feature.
+    pub watch_resolv_conf: bool,
+    /// Keep listening for this long after an answer was accepted over UDP, to detect a second,
+    ///  different answer to the same query
+    ///
+    /// Such conflicting answers are a strong indicator of a spoofing attempt, they are logged and
+    ///  counted, see `AsyncResolver::duplicate_response_stats`. Disabled by default, as every UDP
+    ///  query then holds its socket open for the window.
+    ///
+    /// The answer is returned as soon as it arrives, and the socket is watched in a background
+    ///  task. With `requery_conflicting_over_tcp` however, every UDP query takes at least this
+    ///  long, as the answer is only returned once the window elapsed without a conflict.
+    pub duplicate_response_window: Option<Duration>,
+    /// Confirm queries which received conflicting answers over TCP, instead of accepting the
+    ///  first answer. Only used with `duplicate_response_window`.
+    pub requery_conflicting_over_tcp: bool,
+    /// Randomize the case of the query names sent over UDP, and ignore responses which do not echo
+    ///  it exactly ("0x20 encoding")
+    ///
+    /// This makes spoofed responses harder to forge. It is no longer used for a name server which
+    ///  answered with the question in a different case three times in a row, as some name servers
+    ///  normalize the case. Disabled by default.
+    pub case_randomization: bool,
+    /// Send the UDP queries from a pool of sockets, which are reused and refreshed, instead of
+    ///  binding a socket on a random port for each query
+    ///
+    /// This saves the system calls and file descriptors of a socket per query under high load, at
+    ///  the cost of fewer source ports an attacker has to guess. Disabled by default.
+    pub udp_socket_pool: Option<UdpSocketPoolConfig>,
+    /// Tunnel the connections to the name servers through a SOCKS5 or HTTP CONNECT proxy
+    ///
+    /// TCP and the encrypted protocols over TCP are tunneled through the proxy. The name servers
+    ///  configured for UDP are queried over TCP through the proxy instead, and connections with
+    ///  DNS over QUIC or HTTP/3 fail, so that no query bypasses the proxy. Disabled by default.
+    ///
+    /// See `ProxyConfig::isolation` to separate the queries over the circuits of Tor.
+    pub proxy: Option<ProxyConfig>,
+    /// Names to resolve at startup and after the cache was cleared, see `AsyncResolver::warm_up`
+    pub warm_up: Vec<Name>,
+    /// How long queries to a name server with `PrivacyPolicy::Opportunistic` use unencrypted DNS,
+    ///  after its encrypted connection failed, before encryption is attempted again.
+    ///  Defaults to 5 minutes.
+    pub encryption_probe_interval: Duration,
+    /// When name servers are demoted after failures, and how often they are probed
+    pub health: HealthOpts,
+    /// Lookups which miss the cache wait for the response to an identical query in flight, instead
+    ///  of sending it again. Queries are identical if their name, type, class and options are equal.
+    ///  Defaults to `true`.
+    pub coalesce_queries: bool,
+    /// The maximum number of concurrent lookups of `AsyncResolver::lookup_batch`, defaults to 16
+    pub batch_concurrency: usize,
 }
 
 impl Default for ResolverOpts {
@@ -1000,6 +1503,7 @@ impl Default for ResolverOpts {
             ip_strategy: LookupIpStrategy::default(),
             cache_size: 32,
             use_hosts_file: true,
+            hosts: HostsOpts::default(),
             positive_min_ttl: None,
             negative_min_ttl: None,
             positive_max_ttl: None,
@@ -1014,6 +1518,20 @@ impl Default for ResolverOpts {
             recursion_desired: true,
             authentic_data: false,
             shuffle_dns_servers: false,
+            nxdomain_recheck: NxDomainRecheck::default(),
+            max_rrset_records: crate::dns_lru::MAX_RRSET_RECORDS,
+            no_tld_query: false,
+            watch_resolv_conf: false,
+            duplicate_response_window: None,
+            requery_conflicting_over_tcp: false,
+            case_randomization: false,
+            udp_socket_pool: None,
+            proxy: None,
+            warm_up: vec![],
+            encryption_probe_interval: Duration::from_secs(300),
+            health: HealthOpts::default(),
+            coalesce_queries: true,
+            batch_concurrency: 16,
         }
     }
 }
@@ -0,0 +1,631 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Discovery of Network-designated Resolvers (DNR), [RFC 9463](https://www.rfc-editor.org/rfc/rfc9463)
+//!
+//! Networks announce their encrypted resolvers in options of DHCPv4, DHCPv6 and IPv6 Router
+//!  Advertisements. Receiving these options is up to the host, this module parses their payload
+//!  into [`DesignatedResolver`]s, and those into a [`ResolverConfig`].
+//!
+//! The Authentication Domain Name (ADN) of a designated resolver is always used as the name to
+//!  validate the certificate of the resolver against, see section 7.1 of the RFC. The addresses
+//!  in the options are never sufficient to authenticate a resolver.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use proto::error::{ProtoError, ProtoResult};
+use proto::rr::rdata::svcb::{self, Alpn, Mandatory, SvcParamKey, SvcParamValue, Unknown};
+use proto::rr::Name;
+use proto::serialize::binary::{BinDecodable, BinDecoder};
+use tracing::debug;
+
+use crate::config::{
+    HttpConfig, NameServerConfig, NameServerConfigGroup, Protocol, ResolverConfig,
+};
+use crate::error::ResolveResult;
+
+/// The code of the DNR option of DHCPv4
+pub const OPTION_V4_DNR: u8 = 162;
+/// The code of the DNR option of DHCPv6
+pub const OPTION_V6_DNR: u16 = 144;
+/// The type of the Encrypted DNS option of IPv6 Router Advertisements
+pub const RA_ENCRYPTED_DNS: u8 = 144;
+
+/// The dohpath SvcParamKey, [RFC 9461](https://www.rfc-editor.org/rfc/rfc9461)
+const DOHPATH: SvcParamKey = SvcParamKey::Unknown(7);
+
+/// The path of DNS queries over HTTP/3, the only one the HTTP/3 client supports
+const DOH_QUERY_PATH: &str = "/dns-query";
+
+/// An encrypted resolver designated by the network
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DesignatedResolver {
+    /// The priority of this resolver, lower values are preferred
+    pub priority: u16,
+    /// The Authentication Domain Name, the certificate of the resolver is validated against it
+    pub adn: Name,
+    /// The addresses of the resolver, empty in ADN-only mode, where the ADN needs to be resolved
+    pub addrs: Vec<IpAddr>,
+    /// The protocols the resolver supports, e.g. `dot`, `h2`, `h3` or `doq`
+    pub alpn: Vec<String>,
+    /// The port of the resolver, if it is not the default of the protocol
+    pub port: Option<u16>,
+    /// The URI template of DNS over HTTPS, e.g. `/dns-query{?dns}`
+    pub dohpath: Option<String>,
+    /// The time the resolver may be used for, only announced in Router Advertisements
+    pub lifetime: Option<Duration>,
+}
+
+impl DesignatedResolver {
+    fn new(
+        priority: u16,
+        adn: Name,
+        addrs: Vec<IpAddr>,
+        svc_params: Vec<(SvcParamKey, SvcParamValue)>,
+        lifetime: Option<Duration>,
+    ) -> ProtoResult<Self> {
+        let mut resolver = Self {
+            priority,
+            adn,
+            addrs,
+            alpn: vec![],
+            port: None,
+            dohpath: None,
+            lifetime,
+        };
+
+        // ADN-only mode, the SvcParams are absent
+        if resolver.is_adn_only() && svc_params.is_empty() {
+            return Ok(resolver);
+        }
+
+        for (key, value) in svc_params {
+            match (key, value) {
+                (SvcParamKey::Mandatory, SvcParamValue::Mandatory(Mandatory(keys))) => {
+                    if let Some(key) = keys.iter().find(|key| !is_supported_key(**key)) {
+                        return Err(ProtoError::from(format!(
+                            "unsupported mandatory SvcParamKey of designated resolver: {key}"
+                        )));
+                    }
+                }
+                (SvcParamKey::Alpn, SvcParamValue::Alpn(Alpn(alpn))) => resolver.alpn = alpn,
+                (SvcParamKey::Port, SvcParamValue::Port(port)) => resolver.port = Some(port),
+                (DOHPATH, SvcParamValue::Unknown(Unknown(path))) => {
+                    let path = String::from_utf8(path)
+                        .map_err(|_| ProtoError::from("dohpath is not valid UTF-8"))?;
+                    resolver.dohpath = Some(path);
+                }
+                _ => {}
+            }
+        }
+
+        // the alpn SvcParam is required, see section 3.1.5 of RFC 9463
+        if resolver.alpn.is_empty() {
+            return Err("designated resolver without alpn SvcParam".into());
+        }
+        // DNS over HTTPS requires the URI template, see RFC 9461
+        let is_doh = resolver
+            .alpn
+            .iter()
+            .any(|alpn| matches!(alpn.as_str(), "h2" | "h3"));
+        if is_doh && resolver.dohpath.is_none() {
+            return Err("designated resolver for DNS over HTTPS without dohpath SvcParam".into());
+        }
+
+        Ok(resolver)
+    }
+
+    /// Parses the data of a DHCPv6 DNR option, without the option code and length
+    pub fn from_dhcpv6(data: &[u8]) -> ResolveResult<Self> {
+        let mut decoder = BinDecoder::new(data);
+        Ok(read_dhcpv6(&mut decoder)?)
+    }
+
+    /// Parses the data of a DHCPv4 DNR option, without the option code and length
+    ///
+    /// The option carries any number of resolvers. Options which were split, see
+    ///  [RFC 3396](https://www.rfc-editor.org/rfc/rfc3396), need to be concatenated first.
+    pub fn from_dhcpv4(data: &[u8]) -> ResolveResult<Vec<Self>> {
+        let mut decoder = BinDecoder::new(data);
+        Ok(read_dhcpv4(&mut decoder)?)
+    }
+
+    /// Parses an Encrypted DNS option of a Router Advertisement, including its type and length
+    pub fn from_router_advertisement(option: &[u8]) -> ResolveResult<Self> {
+        let mut decoder = BinDecoder::new(option);
+        Ok(read_router_advertisement(&mut decoder)?)
+    }
+
+    /// Returns true if the addresses of the resolver are not known, only its name
+    pub fn is_adn_only(&self) -> bool {
+        self.addrs.is_empty()
+    }
+
+    /// Returns the configurations for all addresses and supported protocols of this resolver
+    ///
+    /// Protocols which are not enabled by features of this crate are skipped, as are resolvers in
+    ///  ADN-only mode and resolvers whose lifetime is zero. DNS over HTTPS queries the dohpath of
+    ///  the resolver, DNS over HTTP/3 is only used with the path `/dns-query`.
+    pub fn name_server_configs(&self) -> Vec<NameServerConfig> {
+        if self.lifetime == Some(Duration::ZERO) {
+            debug!("designated resolver {} is no longer valid", self.adn);
+            return vec![];
+        }
+
+        let mut adn = self.adn.clone();
+        adn.set_fqdn(false);
+        let tls_dns_name = adn.to_ascii();
+
+        let mut configs = Vec::new();
+        for alpn in &self.alpn {
+            let Some((protocol, default_port)) = protocol(alpn) else {
+                debug!(
+                    "unsupported protocol {alpn} of designated resolver {}",
+                    self.adn
+                );
+                continue;
+            };
+
+            if alpn == "h3" && !self.dohpath.as_deref().map_or(false, is_supported_dohpath) {
+                debug!(
+                    "unsupported dohpath {:?} of designated resolver {}",
+                    self.dohpath, self.adn
+                );
+                continue;
+            }
+
+            for addr in &self.addrs {
+                let socket_addr = SocketAddr::new(*addr, self.port.unwrap_or(default_port));
+                let mut config = NameServerConfig::new(socket_addr, protocol);
+                config.tls_dns_name = Some(tls_dns_name.clone());
+                if alpn == "h2" {
+                    config.http = self.dohpath.clone().map(HttpConfig::with_path);
+                }
+                configs.push(config);
+            }
+        }
+
+        configs
+    }
+}
+
+/// Builds the configuration of the resolvers designated by the network
+///
+/// The name servers are ordered by the priority of the resolvers, use
+///  `ServerOrderingStrategy::UserProvidedOrder` to keep that order.
+pub fn resolver_config<'a>(
+    resolvers: impl IntoIterator<Item = &'a DesignatedResolver>,
+) -> ResolverConfig {
+    let mut resolvers = resolvers.into_iter().collect::<Vec<_>>();
+    resolvers.sort_by_key(|resolver| resolver.priority);
+
+    let name_servers = resolvers
+        .into_iter()
+        .flat_map(DesignatedResolver::name_server_configs)
+        .collect::<Vec<_>>();
+
+    ResolverConfig::from_parts(None, vec![], NameServerConfigGroup::from(name_servers))
+}
+
+fn protocol(alpn: &str) -> Option<(Protocol, u16)> {
+    match alpn {
+        #[cfg(feature = "dns-over-tls")]
+        "dot" => Some((Protocol::Tls, 853)),
+        #[cfg(feature = "dns-over-https")]
+        "h2" => Some((Protocol::Https, 443)),
+        #[cfg(feature = "dns-over-h3")]
+        "h3" => Some((Protocol::H3, 443)),
+        #[cfg(feature = "dns-over-quic")]
+        "doq" => Some((Protocol::Quic, 853)),
+        _ => None,
+    }
+}
+
+fn is_supported_dohpath(dohpath: &str) -> bool {
+    // the template has a variable for the query, e.g. `/dns-query{?dns}`
+    dohpath.split('{').next() == Some(DOH_QUERY_PATH)
+}
+
+//   0                   1                   2                   3
+//   0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//  |          OPTION_V6_DNR        |         Option-length         |
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//  |       Service Priority        |           ADN Length          |
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//  ~                  authentication-domain-name                   ~
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//  |           Addr Length         |                               |
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+                               |
+//  ~                        ipv6-address(es)                       ~
+//  |                               +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//  |                               |                               |
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+                               |
+//  ~                 Service Parameters (SvcParams)                ~
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+fn read_dhcpv6(decoder: &mut BinDecoder<'_>) -> ProtoResult<DesignatedResolver> {
+    let priority = decoder.read_u16()?.unverified(/*any u16 is valid*/);
+    let adn_len = decoder.read_u16()?.unverified(/*checked by read_adn*/);
+    let adn = read_adn(decoder, adn_len as usize)?;
+
+    // ADN-only mode
+    if decoder.is_empty() {
+        return DesignatedResolver::new(priority, adn, vec![], vec![], None);
+    }
+
+    let addrs_len = decoder.read_u16()?.unverified(/*checked by read_addrs*/);
+    let addrs = read_addrs(decoder, addrs_len as usize, 16)?;
+    let svc_params = svcb::read_svc_params(decoder, decoder.len())?;
+
+    DesignatedResolver::new(priority, adn, addrs, svc_params, None)
+}
+
+//   0                   1                   2                   3
+//   0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//  |     DNR Instance Data Length  |       Service Priority        |
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//  |  ADN Length   |                                               |
+//  +-+-+-+-+-+-+-+-+                                               |
+//  ~                  authentication-domain-name                   ~
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//  | Addr Length   |                                               |
+//  +-+-+-+-+-+-+-+-+                                               |
+//  ~                       IPv4 Address(es)                        ~
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//  ~                 Service Parameters (SvcParams)                ~
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+fn read_dhcpv4(decoder: &mut BinDecoder<'_>) -> ProtoResult<Vec<DesignatedResolver>> {
+    let mut resolvers = Vec::new();
+
+    while !decoder.is_empty() {
+        let len = decoder.read_u16()?.unverified(/*checked by read_slice*/);
+        let instance = decoder.read_slice(len as usize)?.unverified();
+        resolvers.push(read_dhcpv4_instance(&mut BinDecoder::new(instance))?);
+    }
+
+    Ok(resolvers)
+}
+
+fn read_dhcpv4_instance(decoder: &mut BinDecoder<'_>) -> ProtoResult<DesignatedResolver> {
+    let priority = decoder.read_u16()?.unverified(/*any u16 is valid*/);
+    let adn_len = decoder.read_u8()?.unverified(/*checked by read_adn*/);
+    let adn = read_adn(decoder, adn_len as usize)?;
+
+    // ADN-only mode
+    if decoder.is_empty() {
+        return DesignatedResolver::new(priority, adn, vec![], vec![], None);
+    }
+
+    let addrs_len = decoder.read_u8()?.unverified(/*checked by read_addrs*/);
+    let addrs = read_addrs(decoder, addrs_len as usize, 4)?;
+    let svc_params = svcb::read_svc_params(decoder, decoder.len())?;
+
+    DesignatedResolver::new(priority, adn, addrs, svc_params, None)
+}
+
+//   0                   1                   2                   3
+//   0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//  |     Type      |     Length    |        Service Priority       |
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//  |                          Lifetime                             |
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//  |          ADN Length           |                               |
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+                               |
+//  ~                 Authentication Domain Name                    ~
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//  |          Addr Length          |                               |
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+                               |
+//  ~                        IPv6 Address(es)                       ~
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//  |       SvcParams Length        |                               |
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+                               |
+//  ~                 Service Parameters (SvcParams)                ~
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//  ~                           Padding                             ~
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+fn read_router_advertisement(decoder: &mut BinDecoder<'_>) -> ProtoResult<DesignatedResolver> {
+    let option_type = decoder.read_u8()?.unverified(/*checked below*/);
+    if option_type != RA_ENCRYPTED_DNS {
+        return Err(format!("not an Encrypted DNS option: {option_type}").into());
+    }
+
+    // the length is in units of 8 octets, including the type and length
+    let len = decoder.read_u8()?.unverified(/*checked below*/) as usize * 8;
+    if len != decoder.len() + 2 {
+        return Err(format!(
+            "length of Encrypted DNS option ({len}) does not match the data ({})",
+            decoder.len() + 2
+        )
+        .into());
+    }
+
+    let priority = decoder.read_u16()?.unverified(/*any u16 is valid*/);
+    let lifetime = match decoder.read_u32()?.unverified(/*any u32 is valid*/) {
+        u32::MAX => None,
+        secs => Some(Duration::from_secs(secs.into())),
+    };
+
+    let adn_len = decoder.read_u16()?.unverified(/*checked by read_adn*/);
+    let adn = read_adn(decoder, adn_len as usize)?;
+
+    let addrs_len = decoder.read_u16()?.unverified(/*checked by read_addrs*/);
+    let addrs = read_addrs(decoder, addrs_len as usize, 16)?;
+
+    let svc_params_len = decoder.read_u16()?.unverified(/*checked by read_slice*/);
+    let svc_params = decoder.read_slice(svc_params_len as usize)?.unverified();
+    let svc_params = svcb::read_svc_params(&mut BinDecoder::new(svc_params), svc_params.len())?;
+
+    // the remainder is padding
+    DesignatedResolver::new(priority, adn, addrs, svc_params, lifetime)
+}
+
+/// The ADN is encoded as a fully qualified domain name, without compression
+fn read_adn(decoder: &mut BinDecoder<'_>, len: usize) -> ProtoResult<Name> {
+    let data = decoder.read_slice(len)?.unverified(/*checked while reading the name*/);
+    let mut adn_decoder = BinDecoder::new(data);
+    let adn = Name::read(&mut adn_decoder)?;
+
+    if !adn_decoder.is_empty() {
+        return Err("trailing data after the Authentication Domain Name".into());
+    }
+    if adn.is_root() {
+        return Err("the Authentication Domain Name is empty".into());
+    }
+
+    Ok(adn)
+}
+
+fn read_addrs(
+    decoder: &mut BinDecoder<'_>,
+    len: usize,
+    addr_len: usize,
+) -> ProtoResult<Vec<IpAddr>> {
+    if len % addr_len != 0 {
+        return Err(format!("length of addresses is not a multiple of {addr_len}: {len}").into());
+    }
+
+    let data = decoder.read_slice(len)?.unverified(/*any address is valid*/);
+    let addrs = data.chunks_exact(addr_len).map(|octets| match addr_len {
+        4 => IpAddr::from(Ipv4Addr::new(octets[0], octets[1], octets[2], octets[3])),
+        _ => IpAddr::from(Ipv6Addr::from(
+            <[u8; 16]>::try_from(octets).expect("chunks are 16 octets"),
+        )),
+    });
+
+    // the addresses must not be multicast, loopback or unspecified, those are ignored
+    Ok(addrs
+        .filter(|addr| {
+            let valid = !(addr.is_multicast() || addr.is_loopback() || addr.is_unspecified());
+            if !valid {
+                debug!("ignoring invalid address of designated resolver: {addr}");
+            }
+            valid
+        })
+        .collect())
+}
+
+fn is_supported_key(key: SvcParamKey) -> bool {
+    matches!(
+        key,
+        SvcParamKey::Mandatory | SvcParamKey::Alpn | SvcParamKey::Port | DOHPATH
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    /// The SvcParams alpn, port and dohpath, in this order
+    fn svc_params(alpn: &[&str], port: Option<u16>, dohpath: Option<&str>) -> Vec<u8> {
+        let mut params = vec![];
+        if !alpn.is_empty() {
+            let mut value = vec![];
+            for alpn in alpn {
+                value.push(alpn.len() as u8);
+                value.extend_from_slice(alpn.as_bytes());
+            }
+            params.extend_from_slice(&1_u16.to_be_bytes());
+            params.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            params.extend(value);
+        }
+        if let Some(port) = port {
+            params.extend_from_slice(&3_u16.to_be_bytes());
+            params.extend_from_slice(&2_u16.to_be_bytes());
+            params.extend_from_slice(&port.to_be_bytes());
+        }
+        if let Some(dohpath) = dohpath {
+            params.extend_from_slice(&7_u16.to_be_bytes());
+            params.extend_from_slice(&(dohpath.len() as u16).to_be_bytes());
+            params.extend_from_slice(dohpath.as_bytes());
+        }
+        params
+    }
+
+    /// `resolver.example.net.` in wire format
+    const ADN: &[u8] = b"\x08resolver\x07example\x03net\x00";
+
+    fn dhcpv6(addrs: &[Ipv6Addr], svc_params: &[u8]) -> Vec<u8> {
+        let mut data = vec![];
+        data.extend_from_slice(&10_u16.to_be_bytes());
+        data.extend_from_slice(&(ADN.len() as u16).to_be_bytes());
+        data.extend_from_slice(ADN);
+        data.extend_from_slice(&(addrs.len() as u16 * 16).to_be_bytes());
+        for addr in addrs {
+            data.extend_from_slice(&addr.octets());
+        }
+        data.extend_from_slice(svc_params);
+        data
+    }
+
+    #[test]
+    fn test_dhcpv6() {
+        let addrs = [
+            Ipv6Addr::from_str("2001:db8::1").unwrap(),
+            Ipv6Addr::LOCALHOST,
+        ];
+        let data = dhcpv6(
+            &addrs,
+            &svc_params(&["dot", "h2"], None, Some("/dns-query{?dns}")),
+        );
+
+        let resolver = DesignatedResolver::from_dhcpv6(&data).unwrap();
+        assert_eq!(resolver.priority, 10);
+        assert_eq!(
+            resolver.adn,
+            Name::from_ascii("resolver.example.net.").unwrap()
+        );
+        // the loopback address is not valid for a designated resolver
+        assert_eq!(resolver.addrs, vec![IpAddr::from(addrs[0])]);
+        assert_eq!(resolver.alpn, vec!["dot".to_string(), "h2".to_string()]);
+        assert_eq!(resolver.port, None);
+        assert_eq!(resolver.dohpath.as_deref(), Some("/dns-query{?dns}"));
+        assert_eq!(resolver.lifetime, None);
+    }
+
+    #[test]
+    fn test_dhcpv6_adn_only() {
+        let mut data = vec![0, 1];
+        data.extend_from_slice(&(ADN.len() as u16).to_be_bytes());
+        data.extend_from_slice(ADN);
+
+        let resolver = DesignatedResolver::from_dhcpv6(&data).unwrap();
+        assert!(resolver.is_adn_only());
+        assert!(resolver.name_server_configs().is_empty());
+    }
+
+    #[test]
+    fn test_dhcpv6_requires_alpn() {
+        let addrs = [Ipv6Addr::from_str("2001:db8::1").unwrap()];
+
+        let data = dhcpv6(&addrs, &svc_params(&[], Some(853), None));
+        assert!(DesignatedResolver::from_dhcpv6(&data).is_err());
+
+        // DNS over HTTPS requires the dohpath
+        let data = dhcpv6(&addrs, &svc_params(&["h2"], None, None));
+        assert!(DesignatedResolver::from_dhcpv6(&data).is_err());
+    }
+
+    #[test]
+    fn test_dhcpv4() {
+        let mut instance = vec![];
+        instance.extend_from_slice(&1_u16.to_be_bytes());
+        instance.push(ADN.len() as u8);
+        instance.extend_from_slice(ADN);
+        instance.push(8);
+        instance.extend_from_slice(&[192, 0, 2, 1, 192, 0, 2, 2]);
+        instance.extend(svc_params(&["doq"], Some(8853), None));
+
+        let mut data = vec![];
+        for _ in 0..2 {
+            data.extend_from_slice(&(instance.len() as u16).to_be_bytes());
+            data.extend_from_slice(&instance);
+        }
+
+        let resolvers = DesignatedResolver::from_dhcpv4(&data).unwrap();
+        assert_eq!(resolvers.len(), 2);
+        assert_eq!(
+            resolvers[0].addrs,
+            vec![IpAddr::from([192, 0, 2, 1]), IpAddr::from([192, 0, 2, 2])]
+        );
+        assert_eq!(resolvers[0].alpn, vec!["doq".to_string()]);
+        assert_eq!(resolvers[0].port, Some(8853));
+    }
+
+    #[test]
+    fn test_router_advertisement() {
+        let addr = Ipv6Addr::from_str("2001:db8::53").unwrap();
+        let params = svc_params(&["dot"], None, None);
+
+        let mut body = vec![];
+        body.extend_from_slice(&5_u16.to_be_bytes());
+        body.extend_from_slice(&1800_u32.to_be_bytes());
+        body.extend_from_slice(&(ADN.len() as u16).to_be_bytes());
+        body.extend_from_slice(ADN);
+        body.extend_from_slice(&16_u16.to_be_bytes());
+        body.extend_from_slice(&addr.octets());
+        body.extend_from_slice(&(params.len() as u16).to_be_bytes());
+        body.extend(params);
+
+        let len = (body.len() + 2 + 7) / 8;
+        let mut option = vec![RA_ENCRYPTED_DNS, len as u8];
+        option.extend(body);
+        option.resize(len * 8, 0);
+
+        let resolver = DesignatedResolver::from_router_advertisement(&option).unwrap();
+        assert_eq!(resolver.priority, 5);
+        assert_eq!(resolver.addrs, vec![IpAddr::from(addr)]);
+        assert_eq!(resolver.lifetime, Some(Duration::from_secs(1800)));
+
+        // the length must match the option
+        option.truncate(option.len() - 8);
+        assert!(DesignatedResolver::from_router_advertisement(&option).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "dns-over-tls")]
+    fn test_resolver_config() {
+        let addr = Ipv6Addr::from_str("2001:db8::1").unwrap();
+        let first = DesignatedResolver::from_dhcpv6(&dhcpv6(
+            &[addr],
+            &svc_params(&["dot", "unknown"], Some(8853), None),
+        ))
+        .unwrap();
+        let mut second = first.clone();
+        second.priority = 1;
+        second.port = None;
+
+        let config = resolver_config([&first, &second]);
+        let name_servers = config.name_servers();
+        assert_eq!(name_servers.len(), 2);
+
+        // ordered by priority
+        assert_eq!(
+            name_servers[0].socket_addr,
+            SocketAddr::new(addr.into(), 853)
+        );
+        assert_eq!(
+            name_servers[1].socket_addr,
+            SocketAddr::new(addr.into(), 8853)
+        );
+        for name_server in name_servers {
+            assert_eq!(name_server.protocol, Protocol::Tls);
+            assert_eq!(
+                name_server.tls_dns_name.as_deref(),
+                Some("resolver.example.net")
+            );
+        }
+
+        // expired
+        second.lifetime = Some(Duration::ZERO);
+        assert!(second.name_server_configs().is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "dns-over-https")]
+    fn test_dohpath() {
+        let addr = Ipv6Addr::from_str("2001:db8::1").unwrap();
+        let resolver = DesignatedResolver::from_dhcpv6(&dhcpv6(
+            &[addr],
+            &svc_params(&["h2"], None, Some("/query{?dns}")),
+        ))
+        .unwrap();
+
+        let configs = resolver.name_server_configs();
+        assert_eq!(configs.len(), 1);
+        assert_eq!(configs[0].protocol, Protocol::Https);
+        assert_eq!(
+            configs[0].http.as_ref().map(|http| http.path.as_str()),
+            Some("/query{?dns}")
+        );
+    }
+}
@@ -9,12 +9,14 @@
 
 use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use hickory_proto::error::{ProtoError, ProtoErrorKind};
 use lru_cache::LruCache;
 use parking_lot::Mutex;
+use tracing::debug;
 
+use proto::clock::Instant;
 use proto::op::Query;
 use proto::rr::Record;
 
@@ -25,6 +27,16 @@ use crate::lookup::Lookup;
 ///   Setting this to a value of 1 day, in seconds
 pub(crate) const MAX_TTL: u32 = 86400_u32;
 
+/// TTL of expired answers which are returned from the cache, as recommended by
+///  [RFC 8767](https://tools.ietf.org/html/rfc8767#section-4)
+pub(crate) const STALE_TTL: u32 = 30;
+
+/// Expired answers are not returned anymore once they have been expired for this long, 1 day
+const MAX_STALE: Duration = Duration::from_secs(MAX_TTL as u64);
+
+/// Default maximum number of records of one RRset which are cached
+pub(crate) const MAX_RRSET_RECORDS: usize = 256;
+
 #[derive(Debug)]
 struct LruValue {
     // In the None case, this represents an NXDomain
@@ -110,6 +122,10 @@ pub struct DnsLru {
     ///
     /// [`MAX_TTL`]: const.MAX_TTL.html
     negative_max_ttl: Duration,
+    /// Keep expired positive responses, so that they can be returned by `get_stale`
+    keep_stale: bool,
+    /// Any further records of an RRset are dropped before caching
+    max_rrset_records: usize,
 }
 
 /// The time-to-live, TTL, configuration for use by the cache.
@@ -178,19 +194,46 @@ impl DnsLru {
                 .unwrap_or_else(|| Duration::from_secs(u64::from(MAX_TTL))),
             negative_max_ttl: negative_max_ttl
                 .unwrap_or_else(|| Duration::from_secs(u64::from(MAX_TTL))),
+            keep_stale: false,
+            max_rrset_records: MAX_RRSET_RECORDS,
         }
     }
 
+    /// Keep expired positive responses in the cache until they are replaced or evicted
+    ///
+    /// Expired responses are never returned by `get`, only by `get_stale`.
+    pub(crate) fn keep_stale(mut self, keep_stale: bool) -> Self {
+        self.keep_stale = keep_stale;
+        self
+    }
+
+    /// Limit the number of cached records of each RRset, see `insert`
+    pub(crate) fn max_rrset_records(mut self, max_rrset_records: usize) -> Self {
+        self.max_rrset_records = max_rrset_records;
+        self
+    }
+
     pub(crate) fn clear(&self) {
         self.cache.lock().clear();
     }
 
+    /// Removes any response for the query
+    pub(crate) fn remove(&self, query: &Query) {
+        self.cache.lock().remove(query);
+    }
+
+    /// Inserts the records as the answer to the query
+    ///
+    /// The records are normalized first: owner names are lowercased, and the records of each
+    ///  RRset are put in canonical order without duplicates, and limited to `max_rrset_records`.
+    ///  The RRsets themselves keep their order, as in a CNAME chain.
     pub(crate) fn insert(
         &self,
         query: Query,
         records_and_ttl: Vec<(Record, u32)>,
         now: Instant,
     ) -> Lookup {
+        let records_and_ttl = normalize(records_and_ttl, self.max_rrset_records);
         let len = records_and_ttl.len();
         // collapse the values, we're going to take the Minimum TTL as the correct one
         let (records, ttl): (Vec<Record>, Duration) = records_and_ttl.into_iter().fold(
@@ -344,7 +387,7 @@ impl DnsLru {
                 }
                 Some(result)
             } else {
-                out_of_date = true;
+                out_of_date = !self.keep_stale || value.lookup.is_err();
                 None
             }
         });
@@ -358,9 +401,93 @@ impl DnsLru {
 
         lookup
     }
+
+    /// Returns an expired positive response for the query, if it was kept, see `keep_stale`
+    ///
+    /// The TTL of the returned records is set to [`STALE_TTL`]. Responses which expired more than
+    ///  a day ago are not returned.
+    pub(crate) fn get_stale(&self, query: &Query, now: Instant) -> Option<Lookup> {
+        let mut cache = self.cache.lock();
+        let value = cache.get_mut(query)?;
+        let lookup = match value.lookup {
+            Ok(ref lookup)
+                if !value.is_current(now)
+                    && now.saturating_duration_since(value.valid_until) <= MAX_STALE =>
+            {
+                lookup
+            }
+            _ => return None,
+        };
+
+        let records = lookup
+            .records()
+            .iter()
+            .map(|record| {
+                let mut record = record.clone();
+                record.set_ttl(STALE_TTL);
+                record
+            })
+            .collect::<Vec<Record>>();
+
+        Some(Lookup::new_with_deadline(
+            lookup.query().clone(),
+            Arc::from(records),
+            now + Duration::from_secs(u64::from(STALE_TTL)),
+        ))
+    }
 }
 
 // see also the lookup_tests.rs in integration-tests crate
+/// Lowercases the owner names, and sorts, deduplicates and limits the records of each RRset
+fn normalize(records_and_ttl: Vec<(Record, u32)>, max_rrset_records: usize) -> Vec<(Record, u32)> {
+    // the canonical RDATA is the sort key, records which can not be encoded are sorted first
+    let mut rrsets = Vec::<Vec<(Option<Vec<u8>>, Record, u32)>>::new();
+    for (mut record, ttl) in records_and_ttl {
+        record.set_name(record.name().to_lowercase());
+        let key = record
+            .data()
+            .and_then(|rdata| rdata.to_canonical_bytes().ok());
+
+        match rrsets.iter_mut().find(|rrset| {
+            let (_, first, _) = &rrset[0];
+            first.name() == record.name()
+                && first.record_type() == record.record_type()
+                && first.dns_class() == record.dns_class()
+        }) {
+            Some(rrset) => rrset.push((key, record, ttl)),
+            None => rrsets.push(vec![(key, record, ttl)]),
+        }
+    }
+
+    let mut normalized = Vec::new();
+    for mut rrset in rrsets {
+        rrset.sort_by(|(a, ..), (b, ..)| a.cmp(b));
+        rrset.dedup_by(|(key, record, ttl), (kept_key, kept, kept_ttl)| {
+            let duplicate = (key.is_some() && key == kept_key) || record.data() == kept.data();
+            if duplicate {
+                *kept_ttl = (*kept_ttl).min(*ttl);
+            }
+            duplicate
+        });
+
+        if rrset.len() > max_rrset_records {
+            let (_, record, _) = &rrset[0];
+            debug!(
+                "caching {} of {} {} records for {}",
+                max_rrset_records,
+                rrset.len(),
+                record.record_type(),
+                record.name()
+            );
+            rrset.truncate(max_rrset_records);
+        }
+
+        normalized.extend(rrset.into_iter().map(|(_, record, ttl)| (record, ttl)));
+    }
+
+    normalized
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
@@ -590,6 +717,64 @@ mod tests {
         assert_eq!(*rc_ips.iter().next().unwrap(), ips[0]);
     }
 
+    #[test]
+    fn test_insert_normalizes() {
+        use proto::rr::rdata::CNAME;
+
+        let now = Instant::now();
+        let alias = Name::from_str("WWW.Example.COM.").unwrap();
+        let name = Name::from_str("Target.Example.COM.").unwrap();
+        let query = Query::query(alias.clone(), RecordType::A);
+        let a = |octet, ttl| {
+            (
+                Record::from_rdata(name.clone(), ttl, RData::A(A::new(192, 0, 2, octet))),
+                ttl,
+            )
+        };
+
+        let records = vec![
+            (
+                Record::from_rdata(alias.clone(), 300, RData::CNAME(CNAME(name.clone()))),
+                300,
+            ),
+            a(3, 300),
+            a(1, 300),
+            a(3, 60),
+            a(2, 300),
+        ];
+        let lru = DnsLru::new(1, TtlConfig::default()).max_rrset_records(2);
+        let lookup = lru.insert(query, records, now);
+
+        // the CNAME stays first, the addresses are sorted, deduplicated and limited
+        let names = lookup
+            .record_iter()
+            .map(|record| record.name().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "www.example.com.",
+                "target.example.com.",
+                "target.example.com."
+            ]
+        );
+        let rdatas = lookup.iter().cloned().collect::<Vec<_>>();
+        assert_eq!(
+            rdatas,
+            vec![
+                RData::CNAME(CNAME(name.clone())),
+                RData::A(A::new(192, 0, 2, 1)),
+                RData::A(A::new(192, 0, 2, 2)),
+            ]
+        );
+
+        // the shortest TTL of the duplicates is kept
+        let records = vec![a(3, 300), a(3, 60)];
+        let lookup = lru.insert(Query::query(alias, RecordType::A), records, now);
+        assert_eq!(lookup.record_iter().count(), 1);
+        assert_eq!(lookup.valid_until(), now + Duration::from_secs(60));
+    }
+
     #[test]
     fn test_update_ttl() {
         let now = Instant::now();
@@ -653,6 +838,41 @@ mod tests {
         assert!(rc_ips.is_none());
     }
 
+    #[test]
+    fn test_keep_stale() {
+        let now = Instant::now();
+        let name = Name::from_str("www.example.com.").unwrap();
+        let query = Query::query(name.clone(), RecordType::A);
+        let ips_ttl = vec![(
+            Record::from_rdata(name, 1, RData::A(A::new(127, 0, 0, 1))),
+            1,
+        )];
+
+        // without keep_stale, expired responses are removed
+        let lru = DnsLru::new(1, TtlConfig::default());
+        lru.insert(query.clone(), ips_ttl.clone(), now);
+        let later = now + Duration::from_secs(2);
+        assert!(lru.get(&query, later).is_none());
+        assert!(lru.get_stale(&query, later).is_none());
+
+        let lru = DnsLru::new(1, TtlConfig::default()).keep_stale(true);
+        lru.insert(query.clone(), ips_ttl, now);
+        assert!(lru.get_stale(&query, now).is_none());
+        assert!(lru.get(&query, later).is_none());
+
+        let stale = lru
+            .get_stale(&query, later)
+            .expect("stale records should exist");
+        assert_eq!(
+            *stale.iter().next().unwrap(),
+            RData::A(A::new(127, 0, 0, 1))
+        );
+        assert_eq!(stale.record_iter().next().unwrap().ttl(), STALE_TTL);
+
+        lru.remove(&query);
+        assert!(lru.get_stale(&query, later).is_none());
+    }
+
     #[test]
     fn test_insert_positive_min_ttl() {
         let now = Instant::now();
@@ -10,12 +10,13 @@ use std::net::SocketAddr;
 
 use crate::tls::CLIENT_CONFIG;
 
+use proto::error::ProtoResult;
 use proto::h2::{HttpsClientConnect, HttpsClientStream, HttpsClientStreamBuilder};
 use proto::tcp::{Connect, DnsTcpStream};
 use proto::xfer::{DnsExchange, DnsExchangeConnect};
 use proto::TokioTime;
 
-use crate::config::TlsClientConfig;
+use crate::config::{HttpConfig, TlsClientConfig};
 
 #[allow(clippy::type_complexity)]
 #[allow(unused)]
@@ -50,6 +51,7 @@ pub(crate) fn new_https_stream_with_future<S, F>(
     socket_addr: SocketAddr,
     dns_name: String,
     client_config: Option<TlsClientConfig>,
+    http_config: Option<&HttpConfig>,
 ) -> DnsExchangeConnect<HttpsClientConnect<S>, HttpsClientStream, TokioTime>
 where
     S: DnsTcpStream,
@@ -64,12 +66,26 @@ where
         }
     };
 
-    DnsExchange::connect(HttpsClientStreamBuilder::build_with_future(
-        future,
-        client_config,
-        socket_addr,
-        dns_name,
-    ))
+    let mut https_builder = HttpsClientStreamBuilder::with_client_config(client_config);
+    if let Some(http_config) = http_config {
+        if let Err(error) = configure(&mut https_builder, http_config) {
+            return DnsExchange::error(error);
+        }
+    }
+
+    DnsExchange::connect(https_builder.build_from_future(future, socket_addr, dns_name))
+}
+
+fn configure(
+    https_builder: &mut HttpsClientStreamBuilder,
+    http_config: &HttpConfig,
+) -> ProtoResult<()> {
+    https_builder.query_path(&http_config.path)?;
+    for (name, value) in &http_config.headers {
+        https_builder.header(name, value)?;
+    }
+    https_builder.http1_fallback(http_config.http1_fallback);
+    Ok(())
 }
 
 #[cfg(any(feature = "webpki-roots", feature = "native-certs"))]
@@ -2,15 +2,24 @@
 
 use std::collections::HashMap;
 use std::io;
+use std::net::IpAddr;
 use std::path::Path;
+#[cfg(any(unix, windows))]
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
+#[cfg(any(unix, windows))]
+use std::time::{Duration, Instant, SystemTime};
 
+#[cfg(any(unix, windows))]
+use parking_lot::Mutex;
 use proto::op::Query;
+use proto::rr::rdata::PTR;
 use proto::rr::{Name, RecordType};
 use proto::rr::{RData, Record};
 use tracing::warn;
 
+use crate::config::HostsOpts;
 use crate::dns_lru;
 use crate::lookup::Lookup;
 
@@ -20,6 +29,8 @@ struct LookupType {
     a: Option<Lookup>,
     /// represents the AAAA record type
     aaaa: Option<Lookup>,
+    /// represents the PTR record type, for the reverse names of the addresses
+    ptr: Option<Lookup>,
 }
 
 /// Configuration for the local hosts file
@@ -27,30 +38,45 @@ struct LookupType {
 pub struct Hosts {
     /// Name -> RDatas map
     by_name: HashMap<Name, LookupType>,
+    opts: HostsOpts,
 }
 
 impl Hosts {
     /// Creates a new configuration from the system hosts file,
     /// only works for Windows and Unix-like OSes,
     /// will return empty configuration on others
-    #[cfg(any(unix, windows))]
     pub fn new() -> Self {
-        read_hosts_conf(hosts_path()).unwrap_or_default()
+        Self::new_with_opts(HostsOpts::default())
+    }
+
+    /// Creates a new configuration from the system hosts file, read with the options
+    #[cfg(any(unix, windows))]
+    pub fn new_with_opts(opts: HostsOpts) -> Self {
+        read_hosts_conf(hosts_path(), opts).unwrap_or_else(|_| Self::default().with_opts(opts))
     }
 
     /// Creates a default configuration for non Windows or Unix-like OSes
     #[cfg(not(any(unix, windows)))]
-    pub fn new() -> Self {
-        Hosts::default()
+    pub fn new_with_opts(opts: HostsOpts) -> Self {
+        Self::default().with_opts(opts)
+    }
+
+    /// Sets the options for reading entries with `read_hosts_conf`
+    pub fn with_opts(mut self, opts: HostsOpts) -> Self {
+        self.opts = opts;
+        self
     }
 
     /// Look up the addresses for the given host from the system hosts file.
+    ///
+    /// PTR queries of the reverse names of addresses return the names of their entries.
     pub fn lookup_static_host(&self, query: &Query) -> Option<Lookup> {
         if !self.by_name.is_empty() {
             if let Some(val) = self.by_name.get(query.name()) {
                 let result = match query.query_type() {
                     RecordType::A => val.a.clone(),
                     RecordType::AAAA => val.aaaa.clone(),
+                    RecordType::PTR => val.ptr.clone(),
                     _ => None,
                 };
 
@@ -62,44 +88,40 @@ impl Hosts {
 
     /// Insert a new Lookup for the associated `Name` and `RecordType`
     pub fn insert(&mut self, name: Name, record_type: RecordType, lookup: Lookup) {
-        assert!(record_type == RecordType::A || record_type == RecordType::AAAA);
+        assert!(
+            record_type == RecordType::A
+                || record_type == RecordType::AAAA
+                || record_type == RecordType::PTR
+        );
 
         let lookup_type = self.by_name.entry(name.clone()).or_default();
-
-        let new_lookup = {
-            let old_lookup = match record_type {
-                RecordType::A => lookup_type.a.get_or_insert_with(|| {
-                    let query = Query::query(name.clone(), record_type);
-                    Lookup::new_with_max_ttl(query, Arc::from([]))
-                }),
-                RecordType::AAAA => lookup_type.aaaa.get_or_insert_with(|| {
-                    let query = Query::query(name.clone(), record_type);
-                    Lookup::new_with_max_ttl(query, Arc::from([]))
-                }),
-                _ => {
-                    tracing::warn!("unsupported IP type from Hosts file: {:#?}", record_type);
-                    return;
-                }
-            };
-
-            old_lookup.append(lookup)
+        let old_lookup = match record_type {
+            RecordType::A => &mut lookup_type.a,
+            RecordType::AAAA => &mut lookup_type.aaaa,
+            RecordType::PTR => &mut lookup_type.ptr,
+            _ => {
+                tracing::warn!("unsupported IP type from Hosts file: {:#?}", record_type);
+                return;
+            }
         };
 
-        // replace the appended version
-        match record_type {
-            RecordType::A => lookup_type.a = Some(new_lookup),
-            RecordType::AAAA => lookup_type.aaaa = Some(new_lookup),
-            _ => tracing::warn!("unsupported IP type from Hosts file"),
-        }
+        // append to the existing records
+        let new_lookup = match old_lookup.take() {
+            Some(old_lookup) => old_lookup.append(lookup),
+            None => lookup,
+        };
+        *old_lookup = Some(new_lookup);
     }
 
     /// parse configuration from `src`
+    ///
+    /// The entries are read with the options set by `with_opts`.
     pub fn read_hosts_conf(mut self, src: impl io::Read) -> io::Result<Self> {
         use std::io::{BufRead, BufReader};
 
         use proto::rr::domain::TryParseIp;
 
-        // lines in the src should have the form `addr host1 host2 host3 ...`
+        // lines in the src should have the form `addr canonical_name alias1 alias2 ...`
         // line starts with `#` will be regarded with comments and ignored,
         // also empty line also will be ignored,
         // if line only include `addr` without `host` will be ignored,
@@ -123,35 +145,60 @@ impl Hosts {
                 warn!("could not parse an IP from hosts file");
                 continue;
             };
+            let (record_type, ip) = match addr {
+                RData::A(a) => (RecordType::A, IpAddr::V4(a.0)),
+                RData::AAAA(aaaa) => (RecordType::AAAA, IpAddr::V6(aaaa.0)),
+                _ => {
+                    warn!("unsupported IP type from Hosts file: {:#?}", addr);
+                    continue;
+                }
+            };
 
-            for domain in fields.iter().skip(1).map(|domain| domain.to_lowercase()) {
+            // the first name is the canonical name, the others are aliases
+            let names = if self.opts.aliases {
+                &fields[1..]
+            } else {
+                &fields[1..2]
+            };
+
+            for domain in names.iter().map(|domain| domain.to_lowercase()) {
                 if let Ok(name) = Name::from_str(&domain) {
-                    let record = Record::from_rdata(name.clone(), dns_lru::MAX_TTL, addr.clone());
+                    let query = Query::query(name.clone(), record_type);
+                    if !self.opts.multiple_addresses && self.lookup_static_host(&query).is_some() {
+                        continue;
+                    }
 
-                    match addr {
-                        RData::A(..) => {
-                            let query = Query::query(name.clone(), RecordType::A);
-                            let lookup = Lookup::new_with_max_ttl(query, Arc::from([record]));
-                            self.insert(name.clone(), RecordType::A, lookup);
-                        }
-                        RData::AAAA(..) => {
-                            let query = Query::query(name.clone(), RecordType::AAAA);
-                            let lookup = Lookup::new_with_max_ttl(query, Arc::from([record]));
-                            self.insert(name.clone(), RecordType::AAAA, lookup);
-                        }
-                        _ => {
-                            warn!("unsupported IP type from Hosts file: {:#?}", addr);
-                            continue;
-                        }
-                    };
-
-                    // TODO: insert reverse lookup as well.
+                    let record = Record::from_rdata(name.clone(), dns_lru::MAX_TTL, addr.clone());
+                    let lookup = Lookup::new_with_max_ttl(query, Arc::from([record]));
+                    self.insert(name, record_type, lookup);
                 };
             }
+
+            if self.opts.reverse {
+                self.insert_reverse(ip, &fields[1].to_lowercase());
+            }
         }
 
         Ok(self)
     }
+
+    /// Inserts the PTR record of the address, unless an earlier entry had the same address
+    fn insert_reverse(&mut self, ip: IpAddr, canonical_name: &str) {
+        let reverse = Name::from(ip);
+        let query = Query::query(reverse.clone(), RecordType::PTR);
+        if self.lookup_static_host(&query).is_some() {
+            return;
+        }
+
+        let Ok(mut name) = Name::from_str(canonical_name) else {
+            return;
+        };
+        name.set_fqdn(true);
+
+        let record = Record::from_rdata(reverse.clone(), dns_lru::MAX_TTL, RData::PTR(PTR(name)));
+        let lookup = Lookup::new_with_max_ttl(query, Arc::from([record]));
+        self.insert(reverse, RecordType::PTR, lookup);
+    }
 }
 
 #[cfg(unix)]
@@ -170,11 +217,122 @@ fn hosts_path() -> std::path::PathBuf {
 /// parse configuration from `path`
 #[cfg(any(unix, windows))]
 #[cfg_attr(docsrs, doc(cfg(any(unix, windows))))]
-pub(crate) fn read_hosts_conf<P: AsRef<Path>>(path: P) -> io::Result<Hosts> {
+pub(crate) fn read_hosts_conf<P: AsRef<Path>>(path: P, opts: HostsOpts) -> io::Result<Hosts> {
     use std::fs::File;
 
     let file = File::open(path)?;
-    Hosts::default().read_hosts_conf(file)
+    Hosts::default().with_opts(opts).read_hosts_conf(file)
+}
+
+/// The hosts of the resolver, which are fixed or read from a file
+#[derive(Clone, Debug)]
+pub(crate) enum HostsSource {
+    Fixed(Arc<Hosts>),
+    #[cfg(any(unix, windows))]
+    File(Arc<HostsFile>),
+}
+
+impl HostsSource {
+    /// The system hosts file, which is reloaded when it changed if `HostsOpts::reload` is set
+    pub(crate) fn system(opts: HostsOpts) -> Self {
+        #[cfg(any(unix, windows))]
+        if opts.reload {
+            return Self::File(Arc::new(HostsFile::new(hosts_path().into(), opts)));
+        }
+
+        Self::Fixed(Arc::new(Hosts::new_with_opts(opts)))
+    }
+
+    /// The current hosts
+    pub(crate) fn hosts(&self) -> Arc<Hosts> {
+        match self {
+            Self::Fixed(hosts) => Arc::clone(hosts),
+            #[cfg(any(unix, windows))]
+            Self::File(file) => file.hosts(),
+        }
+    }
+}
+
+/// The minimum time between checks whether the hosts file changed
+#[cfg(any(unix, windows))]
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A hosts file, which is read again when its modification time or length changed
+#[cfg(any(unix, windows))]
+#[derive(Debug)]
+pub(crate) struct HostsFile {
+    path: PathBuf,
+    opts: HostsOpts,
+    state: Mutex<HostsFileState>,
+}
+
+#[cfg(any(unix, windows))]
+#[derive(Debug)]
+struct HostsFileState {
+    checked: Instant,
+    version: Option<(SystemTime, u64)>,
+    hosts: Arc<Hosts>,
+}
+
+#[cfg(any(unix, windows))]
+impl HostsFile {
+    pub(crate) fn new(path: PathBuf, opts: HostsOpts) -> Self {
+        let version = Self::version(&path);
+        let hosts =
+            read_hosts_conf(&path, opts).unwrap_or_else(|_| Hosts::default().with_opts(opts));
+
+        Self {
+            path,
+            opts,
+            state: Mutex::new(HostsFileState {
+                checked: Instant::now(),
+                version,
+                hosts: Arc::new(hosts),
+            }),
+        }
+    }
+
+    /// The current hosts, the file is checked for changes at most once a second
+    pub(crate) fn hosts(&self) -> Arc<Hosts> {
+        let due = self.state.lock().checked.elapsed() >= RELOAD_CHECK_INTERVAL;
+        if due {
+            self.reload_if_changed();
+        }
+
+        Arc::clone(&self.state.lock().hosts)
+    }
+
+    /// Reads the file again if it changed since it was last read
+    fn reload_if_changed(&self) {
+        let version = Self::version(&self.path);
+
+        let mut state = self.state.lock();
+        state.checked = Instant::now();
+        if version == state.version {
+            return;
+        }
+
+        match read_hosts_conf(&self.path, self.opts) {
+            Ok(hosts) => state.hosts = Arc::new(hosts),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                state.hosts = Arc::new(Hosts::default().with_opts(self.opts))
+            }
+            Err(err) => {
+                // keep the last entries, the file is read again on the next change
+                warn!(
+                    "could not reload hosts file {}: {}",
+                    self.path.display(),
+                    err
+                );
+            }
+        }
+        state.version = version;
+    }
+
+    fn version(path: &Path) -> Option<(SystemTime, u64)> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some((metadata.modified().ok()?, metadata.len()))
+    }
 }
 
 #[cfg(any(unix, windows))]
@@ -192,7 +350,7 @@ mod tests {
     #[test]
     fn test_read_hosts_conf() {
         let path = format!("{}/hosts", tests_dir());
-        let hosts = read_hosts_conf(path).unwrap();
+        let hosts = read_hosts_conf(path, HostsOpts::default()).unwrap();
 
         let name = Name::from_str("localhost").unwrap();
         let rdatas = hosts
@@ -255,4 +413,109 @@ mod tests {
             .collect::<Vec<RData>>();
         assert_eq!(rdatas, vec![RData::A(Ipv4Addr::new(10, 0, 1, 111).into())]);
     }
+
+    fn lookup_a(hosts: &Hosts, name: &str) -> Vec<RData> {
+        let name = Name::from_str(name).unwrap();
+        hosts
+            .lookup_static_host(&Query::query(name, RecordType::A))
+            .map(|lookup| lookup.iter().map(ToOwned::to_owned).collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_multiple_addresses() {
+        let src = "10.0.0.1 multi.example.com\n10.0.0.2 multi.example.com\n";
+
+        let hosts = Hosts::default().read_hosts_conf(src.as_bytes()).unwrap();
+        assert_eq!(
+            lookup_a(&hosts, "multi.example.com"),
+            vec![
+                RData::A(Ipv4Addr::new(10, 0, 0, 1).into()),
+                RData::A(Ipv4Addr::new(10, 0, 0, 2).into())
+            ]
+        );
+
+        let opts = HostsOpts {
+            multiple_addresses: false,
+            ..HostsOpts::default()
+        };
+        let hosts = Hosts::default()
+            .with_opts(opts)
+            .read_hosts_conf(src.as_bytes())
+            .unwrap();
+        assert_eq!(
+            lookup_a(&hosts, "multi.example.com"),
+            vec![RData::A(Ipv4Addr::new(10, 0, 0, 1).into())]
+        );
+    }
+
+    #[test]
+    fn test_aliases_disabled() {
+        let opts = HostsOpts {
+            aliases: false,
+            ..HostsOpts::default()
+        };
+        let hosts = read_hosts_conf(format!("{}/hosts", tests_dir()), opts).unwrap();
+
+        assert_eq!(
+            lookup_a(&hosts, "a.example.com"),
+            vec![RData::A(Ipv4Addr::new(10, 0, 1, 111).into())]
+        );
+        assert!(lookup_a(&hosts, "b.example.com").is_empty());
+    }
+
+    #[test]
+    fn test_reverse() {
+        let path = format!("{}/hosts", tests_dir());
+        let hosts = read_hosts_conf(&path, HostsOpts::default()).unwrap();
+
+        let name = Name::from(IpAddr::V4(Ipv4Addr::new(10, 0, 1, 111)));
+        let rdatas = hosts
+            .lookup_static_host(&Query::query(name.clone(), RecordType::PTR))
+            .unwrap()
+            .iter()
+            .map(ToOwned::to_owned)
+            .collect::<Vec<RData>>();
+        assert_eq!(
+            rdatas,
+            vec![RData::PTR(PTR(Name::from_str("a.example.com.").unwrap()))]
+        );
+
+        let opts = HostsOpts {
+            reverse: false,
+            ..HostsOpts::default()
+        };
+        let hosts = read_hosts_conf(&path, opts).unwrap();
+        assert!(hosts
+            .lookup_static_host(&Query::query(name, RecordType::PTR))
+            .is_none());
+    }
+
+    #[test]
+    fn test_reload() {
+        let path = env::temp_dir().join(format!("hickory-hosts-{}", std::process::id()));
+        std::fs::write(&path, "10.0.0.1 reload.example.com\n").unwrap();
+
+        let opts = HostsOpts {
+            reload: true,
+            ..HostsOpts::default()
+        };
+        let file = HostsFile::new(path.clone(), opts);
+        assert_eq!(
+            lookup_a(&file.hosts(), "reload.example.com"),
+            vec![RData::A(Ipv4Addr::new(10, 0, 0, 1).into())]
+        );
+
+        // the length differs, so the change is seen even if the modification time is the same
+        std::fs::write(&path, "10.0.0.22 reload.example.com\n").unwrap();
+        file.reload_if_changed();
+        assert_eq!(
+            lookup_a(&file.hosts(), "reload.example.com"),
+            vec![RData::A(Ipv4Addr::new(10, 0, 0, 22).into())]
+        );
+
+        std::fs::remove_file(&path).unwrap();
+        file.reload_if_changed();
+        assert!(lookup_a(&file.hosts(), "reload.example.com").is_empty());
+    }
 }
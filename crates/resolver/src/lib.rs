@@ -253,9 +253,17 @@ extern crate cfg_if;
 extern crate serde;
 pub extern crate hickory_proto as proto;
 
+pub mod addrinfo;
 mod async_resolver;
+pub mod blocklist;
 pub mod caching_client;
+#[cfg(feature = "tls-monitor")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tls-monitor")))]
+pub mod cert_monitor;
 pub mod config;
+#[cfg(feature = "dnr")]
+#[cfg_attr(docsrs, doc(cfg(feature = "dnr")))]
+pub mod dnr;
 pub mod dns_lru;
 pub mod dns_sd;
 pub mod error;
@@ -264,17 +272,23 @@ mod h2;
 #[cfg(feature = "dns-over-h3")]
 mod h3;
 mod hosts;
+pub mod local_data;
 pub mod lookup;
 pub mod lookup_ip;
+#[cfg(all(any(test, feature = "testing"), feature = "tokio-runtime"))]
+mod mock_name_server;
 // TODO: consider #[doc(hidden)]
 pub mod name_server;
 #[cfg(feature = "dns-over-quic")]
 mod quic;
 #[cfg(feature = "tokio-runtime")]
 mod resolver;
+pub mod reverse_range;
 pub mod system_conf;
+pub mod target_lookup;
 #[cfg(feature = "dns-over-tls")]
 mod tls;
+pub mod warm_up;
 
 // reexports from proto
 pub use self::proto::rr::{IntoName, Name, TryParseIp};
@@ -283,9 +297,15 @@ pub use self::proto::rr::{IntoName, Name, TryParseIp};
 #[cfg_attr(docsrs, doc(cfg(feature = "testing")))]
 pub use async_resolver::testing;
 pub use async_resolver::AsyncResolver;
+#[cfg(feature = "smol-runtime")]
+#[cfg_attr(docsrs, doc(cfg(feature = "smol-runtime")))]
+pub use async_resolver::SmolAsyncResolver;
 #[cfg(feature = "tokio-runtime")]
 #[cfg_attr(docsrs, doc(cfg(feature = "tokio-runtime")))]
 pub use async_resolver::TokioAsyncResolver;
+#[cfg(feature = "wasm-bindgen")]
+#[cfg_attr(docsrs, doc(cfg(feature = "wasm-bindgen")))]
+pub use async_resolver::WasmAsyncResolver;
 pub use hosts::Hosts;
 #[cfg(feature = "tokio-runtime")]
 #[cfg_attr(docsrs, doc(cfg(feature = "tokio-runtime")))]
@@ -0,0 +1,284 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Local records which override the records of the name servers, like `local-data` of unbound
+//!
+//! The names of the records of [`LocalData`] are answered from the local records alone: queries
+//!  of the types of the records are answered with them, queries of other types are answered
+//!  without records. A record of a wildcard name, e.g. `*.test.example.com.`, answers for all the
+//!  names below `test.example.com.` without records of their own, with the name of the query.
+//!
+//! ```
+//! use std::{str::FromStr, sync::Arc};
+//!
+//! use hickory_resolver::config::{ResolverConfig, ResolverOpts};
+//! use hickory_resolver::local_data::LocalData;
+//! use hickory_resolver::proto::rr::{rdata::A, Name, RData, Record};
+//! use hickory_resolver::TokioAsyncResolver;
+//!
+//! # async fn example() {
+//! let mut local_data = LocalData::new();
+//! local_data.insert(Record::from_rdata(
+//!     Name::from_str("*.dev.example.com.").unwrap(),
+//!     300,
+//!     RData::A(A::new(127, 0, 0, 1)),
+//! ));
+//!
+//! let mut resolver = TokioAsyncResolver::tokio(ResolverConfig::default(), ResolverOpts::default());
+//! resolver.set_local_data(Some(Arc::new(local_data)));
+//! let lookup = resolver.lookup_ip("api.dev.example.com.").await.unwrap();
+//! assert_eq!(lookup.iter().next(), Some("127.0.0.1".parse().unwrap()));
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use proto::error::ProtoError;
+use proto::op::{Query, ResponseCode};
+use proto::rr::{Name, Record, RecordType};
+
+use crate::error::ResolveError;
+use crate::lookup::Lookup;
+
+/// Local records which override the records of the name servers, see the
+///  [module](self) documentation
+#[derive(Clone, Debug, Default)]
+pub struct LocalData {
+    /// The records by their lowercase name
+    names: HashMap<Name, Vec<Record>>,
+    /// The records of wildcard names, by the lowercase name below which they match
+    wildcards: HashMap<Name, Vec<Record>>,
+}
+
+impl LocalData {
+    /// Creates empty local data
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a record, which is a wildcard if the first label of its name is `*`
+    pub fn insert(&mut self, record: Record) {
+        let mut name = record.name().to_lowercase();
+        name.set_fqdn(true);
+        if name.is_wildcard() {
+            self.wildcards
+                .entry(name.base_name())
+                .or_default()
+                .push(record);
+        } else {
+            self.names.entry(name).or_default().push(record);
+        }
+    }
+
+    /// The number of names and wildcards with records
+    pub fn len(&self) -> usize {
+        self.names.len() + self.wildcards.len()
+    }
+
+    /// Returns true if there are no records
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty() && self.wildcards.is_empty()
+    }
+
+    /// The records of the name, or of the most specific wildcard which matches it, with the name
+    fn records(&self, name: &Name) -> Option<(Vec<Record>, bool)> {
+        let mut name = name.to_lowercase();
+        name.set_fqdn(true);
+        if let Some(records) = self.names.get(&name) {
+            return Some((records.clone(), false));
+        }
+
+        let mut parent = name.clone();
+        while !parent.is_root() {
+            parent = parent.base_name();
+            if let Some(records) = self.wildcards.get(&parent) {
+                return Some((records.clone(), true));
+            }
+        }
+
+        None
+    }
+
+    /// The local records of the name and type
+    ///
+    /// Returns `None` if the name has no local records, and an empty list if it has no records of
+    ///  the type. The CNAME record of a name is returned for queries of all types.
+    pub fn lookup(&self, name: &Name, record_type: RecordType) -> Option<Vec<Record>> {
+        let (records, wildcard) = self.records(name)?;
+        let matches =
+            |record: &Record| record_type == RecordType::ANY || record.record_type() == record_type;
+
+        let cname = records
+            .iter()
+            .any(|record| record.record_type() == RecordType::CNAME);
+        let mut answers = records
+            .into_iter()
+            .filter(|record| {
+                matches(record) || (cname && record.record_type() == RecordType::CNAME)
+            })
+            .collect::<Vec<_>>();
+
+        if wildcard {
+            for record in &mut answers {
+                record.set_name(name.clone());
+            }
+        }
+
+        Some(answers)
+    }
+
+    /// The lookup of the first of `names` with local records, of the record types
+    pub(crate) fn lookup_names(
+        &self,
+        names: &[Name],
+        record_types: &[RecordType],
+    ) -> Option<Result<Lookup, ResolveError>> {
+        let (name, ttl) = names.iter().find_map(|name| {
+            let (records, _) = self.records(name)?;
+            let ttl = records.iter().map(Record::ttl).min().unwrap_or_default();
+            Some((name, ttl))
+        })?;
+        let query = Query::query(name.clone(), *record_types.first()?);
+
+        let mut records = Vec::new();
+        for record_type in record_types {
+            for record in self.lookup(name, *record_type)? {
+                if !records.contains(&record) {
+                    records.push(record);
+                }
+            }
+        }
+
+        if records.is_empty() {
+            let error = ProtoError::nx_error(query, None, Some(ttl), ResponseCode::NoError, false);
+            return Some(Err(error.into()));
+        }
+
+        let valid_until = Instant::now() + Duration::from_secs(u64::from(ttl));
+        Some(Ok(Lookup::new_with_deadline(
+            query,
+            Arc::from(records),
+            valid_until,
+        )))
+    }
+}
+
+impl FromIterator<Record> for LocalData {
+    fn from_iter<T: IntoIterator<Item = Record>>(records: T) -> Self {
+        let mut local_data = Self::new();
+        for record in records {
+            local_data.insert(record);
+        }
+        local_data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use proto::rr::rdata::{A, CNAME, TXT};
+    use proto::rr::RData;
+
+    use super::*;
+
+    fn record(name: &str, rdata: RData) -> Record {
+        Record::from_rdata(Name::from_str(name).unwrap(), 300, rdata)
+    }
+
+    #[test]
+    fn test_lookup() {
+        let local_data = [
+            record("www.example.com.", RData::A(A::new(192, 0, 2, 1))),
+            record(
+                "www.example.com.",
+                RData::TXT(TXT::new(vec!["local".into()])),
+            ),
+            record("*.dev.example.com.", RData::A(A::new(127, 0, 0, 1))),
+            record("*.a.dev.example.com.", RData::A(A::new(127, 0, 0, 2))),
+            record(
+                "alias.example.com.",
+                RData::CNAME(CNAME(Name::from_str("www.example.com.").unwrap())),
+            ),
+        ]
+        .into_iter()
+        .collect::<LocalData>();
+        assert_eq!(local_data.len(), 4);
+
+        let name = Name::from_str("WWW.example.com").unwrap();
+        assert_eq!(
+            local_data.lookup(&name, RecordType::A),
+            Some(vec![record(
+                "www.example.com.",
+                RData::A(A::new(192, 0, 2, 1))
+            )])
+        );
+        assert_eq!(local_data.lookup(&name, RecordType::AAAA), Some(vec![]));
+        assert_eq!(local_data.lookup(&name, RecordType::ANY).unwrap().len(), 2);
+
+        // the most specific wildcard answers, with the name of the query
+        let name = Name::from_str("b.a.dev.example.com.").unwrap();
+        assert_eq!(
+            local_data.lookup(&name, RecordType::A),
+            Some(vec![record(
+                "b.a.dev.example.com.",
+                RData::A(A::new(127, 0, 0, 2))
+            )])
+        );
+        let name = Name::from_str("api.dev.example.com.").unwrap();
+        assert_eq!(
+            local_data.lookup(&name, RecordType::A),
+            Some(vec![record(
+                "api.dev.example.com.",
+                RData::A(A::new(127, 0, 0, 1))
+            )])
+        );
+        assert_eq!(
+            local_data.lookup(&Name::from_str("dev.example.com.").unwrap(), RecordType::A),
+            None
+        );
+
+        let name = Name::from_str("alias.example.com.").unwrap();
+        assert_eq!(
+            local_data.lookup(&name, RecordType::A).unwrap()[0].record_type(),
+            RecordType::CNAME
+        );
+        assert_eq!(
+            local_data.lookup(&Name::from_str("example.com.").unwrap(), RecordType::A),
+            None
+        );
+    }
+
+    #[test]
+    fn test_lookup_names() {
+        let local_data = [record("www.example.com.", RData::A(A::new(192, 0, 2, 1)))]
+            .into_iter()
+            .collect::<LocalData>();
+        let names = [
+            Name::from_str("www.example.net.").unwrap(),
+            Name::from_str("www.example.com.").unwrap(),
+        ];
+
+        let lookup = local_data
+            .lookup_names(&names, &[RecordType::A, RecordType::AAAA])
+            .unwrap()
+            .unwrap();
+        assert_eq!(lookup.query().name(), &names[1]);
+        assert_eq!(lookup.records().len(), 1);
+
+        let error = local_data
+            .lookup_names(&names, &[RecordType::AAAA])
+            .unwrap()
+            .unwrap_err();
+        assert!(error.to_string().contains("no record"), "{error}");
+        assert!(local_data
+            .lookup_names(&names[..1], &[RecordType::A])
+            .is_none());
+    }
+}
@@ -13,7 +13,7 @@ use std::{
     slice::Iter,
     sync::Arc,
     task::{Context, Poll},
-    time::{Duration, Instant},
+    time::Duration,
 };
 
 use futures_util::{
@@ -29,6 +29,7 @@ use crate::{
     lookup_ip::LookupIpIter,
     name_server::{ConnectionProvider, NameServerPool},
     proto::{
+        clock::Instant,
         error::ProtoError,
         op::Query,
         rr::{
@@ -41,7 +42,10 @@ use crate::{
 };
 
 #[cfg(feature = "dnssec")]
-use proto::{rr::dnssec::Proven, DnssecDnsHandle};
+use proto::{
+    rr::dnssec::{Proof, Proven},
+    DnssecDnsHandle,
+};
 
 /// Result of a DNS query when querying for any record type supported by the Hickory DNS Proto library.
 ///
@@ -128,6 +132,62 @@ impl Lookup {
         self.records.as_ref()
     }
 
+    /// Returns the chain of aliases from the queried name to the name of the records
+    ///
+    /// Each hop is a CNAME record, as received with its own TTL. DNAME records are followed by
+    ///  the CNAME records synthesized by the server, which are validated against the signed
+    ///  DNAME record, see RFC 6672. The chain is only retained
+    ///  with [`ResolverOpts::preserve_intermediates`], which is the default, and is empty if the
+    ///  queried name is not an alias.
+    ///
+    /// [`ResolverOpts::preserve_intermediates`]: crate::config::ResolverOpts::preserve_intermediates
+    pub fn cname_chain(&self) -> Vec<AliasHop> {
+        let mut chain = Vec::<AliasHop>::new();
+        let mut name = self.query.name();
+        while let Some(record) = self
+            .records
+            .iter()
+            .find(|record| record.record_type() == RecordType::CNAME && record.name() == name)
+        {
+            let Some(RData::CNAME(target)) = record.data() else {
+                break;
+            };
+
+            // a loop of aliases is not followed more than once
+            if chain.iter().any(|hop| hop.name == *record.name()) {
+                break;
+            }
+
+            chain.push(AliasHop {
+                name: record.name().clone(),
+                target: target.0.clone(),
+                ttl: record.ttl(),
+                #[cfg(feature = "dnssec")]
+                proof: record.proof(),
+            });
+            name = &target.0;
+        }
+
+        chain
+    }
+
+    /// Returns the name the records are for, the end of [`Self::cname_chain`]
+    pub fn canonical_name(&self) -> &Name {
+        let mut name = self.query.name();
+        for _ in 0..self.records.len() {
+            let target = self.records.iter().find_map(|record| match record.data() {
+                Some(RData::CNAME(target)) if record.name() == name => Some(&target.0),
+                _ => None,
+            });
+            match target {
+                Some(target) => name = target,
+                None => break,
+            }
+        }
+
+        name
+    }
+
     /// Clones the inner vec, appends the other vec
     pub(crate) fn append(&self, other: Self) -> Self {
         let mut records = Vec::with_capacity(self.len() + other.len());
@@ -140,6 +200,22 @@ impl Lookup {
     }
 }
 
+/// An alias on the way to the records of a `Lookup`, see `Lookup::cname_chain`
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct AliasHop {
+    /// The name which is an alias
+    pub name: Name,
+    /// The name it is an alias for
+    pub target: Name,
+    /// The TTL of the CNAME record
+    pub ttl: u32,
+    /// The DNSSEC validation status of the CNAME record
+    #[cfg(feature = "dnssec")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "dnssec")))]
+    pub proof: Proof,
+}
+
 /// Borrowed view of set of [`RData`]s returned from a Lookup
 pub struct LookupIter<'a>(Iter<'a, Record>);
 
@@ -542,6 +618,7 @@ lookup_type!(
     RData::MX,
     rdata::MX
 );
+#[cfg(feature = "rdata-tlsa")]
 lookup_type!(
     TlsaLookup,
     TlsaLookupIter,
@@ -740,6 +817,72 @@ pub mod tests {
         assert_eq!(lookup.next(), None);
     }
 
+    #[test]
+    fn test_cname_chain() {
+        let cname = |name: &str, target: &str, ttl| {
+            Record::from_rdata(
+                Name::from_str(name).unwrap(),
+                ttl,
+                RData::CNAME(rdata::CNAME(Name::from_str(target).unwrap())),
+            )
+        };
+        let a = Record::from_rdata(
+            Name::from_str("edge.cdn.example.net.").unwrap(),
+            20,
+            RData::A(A::new(192, 0, 2, 1)),
+        );
+
+        // the records are not necessarily in the order of the chain
+        let lookup = Lookup::new_with_max_ttl(
+            Query::query(Name::from_str("www.example.com.").unwrap(), RecordType::A),
+            Arc::from([
+                cname("cdn.example.com.", "edge.cdn.example.net.", 60),
+                cname("www.example.com.", "cdn.example.com.", 300),
+                a,
+            ]),
+        );
+
+        let chain = lookup.cname_chain();
+        assert_eq!(
+            chain
+                .iter()
+                .map(|hop| (hop.name.to_string(), hop.target.to_string(), hop.ttl))
+                .collect::<Vec<_>>(),
+            vec![
+                ("www.example.com.".into(), "cdn.example.com.".into(), 300),
+                (
+                    "cdn.example.com.".into(),
+                    "edge.cdn.example.net.".into(),
+                    60
+                ),
+            ]
+        );
+        assert_eq!(
+            *lookup.canonical_name(),
+            Name::from_str("edge.cdn.example.net.").unwrap()
+        );
+
+        // loops end the chain
+        let lookup = Lookup::new_with_max_ttl(
+            Query::query(Name::from_str("a.example.com.").unwrap(), RecordType::A),
+            Arc::from([
+                cname("a.example.com.", "b.example.com.", 60),
+                cname("b.example.com.", "a.example.com.", 60),
+            ]),
+        );
+        assert_eq!(lookup.cname_chain().len(), 2);
+
+        let lookup = Lookup::from_rdata(
+            Query::query(Name::from_str("a.example.com.").unwrap(), RecordType::A),
+            RData::A(A::new(192, 0, 2, 1)),
+        );
+        assert!(lookup.cname_chain().is_empty());
+        assert_eq!(
+            *lookup.canonical_name(),
+            Name::from_str("a.example.com.").unwrap()
+        );
+    }
+
     #[test]
     #[cfg(feature = "dnssec")]
     fn test_dnssec_lookup() {
@@ -0,0 +1,571 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! A name server with scripted responses, for tests of resolvers over real sockets
+
+use std::collections::HashMap;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::FutureExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::task::JoinSet;
+use tracing::debug;
+
+use crate::config::NameServerConfigGroup;
+use crate::proto::op::{Edns, Message, MessageType, ResponseCode};
+use crate::proto::rr::{LowerName, Name, Record, RecordType};
+use crate::proto::udp::MAX_RECEIVE_BUFFER_SIZE;
+
+/// The attempts to find a port which is free for UDP and TCP
+const BIND_ATTEMPTS: usize = 16;
+
+/// The contents of a response
+#[derive(Clone, Debug)]
+enum Body {
+    Message,
+    Malformed,
+    Raw(Vec<u8>),
+    Nothing,
+}
+
+/// A scripted response of a [`MockNameServer`]
+///
+/// The responses echo the ID, the queries and the recursion desired flag of the requests, and
+///  include an EDNS record if the request does.
+#[derive(Clone, Debug)]
+pub struct MockResponse {
+    body: Body,
+    response_code: ResponseCode,
+    answers: Vec<Record>,
+    name_servers: Vec<Record>,
+    additionals: Vec<Record>,
+    delay: Duration,
+    truncated: bool,
+    wrong_id: bool,
+}
+
+impl MockResponse {
+    fn new(body: Body, response_code: ResponseCode) -> Self {
+        Self {
+            body,
+            response_code,
+            answers: Vec::new(),
+            name_servers: Vec::new(),
+            additionals: Vec::new(),
+            delay: Duration::ZERO,
+            truncated: false,
+            wrong_id: false,
+        }
+    }
+
+    /// A response with the answers, NOERROR without any
+    pub fn answers(answers: Vec<Record>) -> Self {
+        Self {
+            answers,
+            ..Self::new(Body::Message, ResponseCode::NoError)
+        }
+    }
+
+    /// A response without records, with the response code
+    pub fn error(response_code: ResponseCode) -> Self {
+        Self::new(Body::Message, response_code)
+    }
+
+    /// A header with the ID of the request, which counts an answer that is missing
+    pub fn malformed() -> Self {
+        Self::new(Body::Malformed, ResponseCode::NoError)
+    }
+
+    /// The bytes, as they are
+    pub fn raw(bytes: Vec<u8>) -> Self {
+        Self::new(Body::Raw(bytes), ResponseCode::NoError)
+    }
+
+    /// No response at all, the request times out
+    pub fn no_response() -> Self {
+        Self::new(Body::Nothing, ResponseCode::NoError)
+    }
+
+    /// Adds the records to the authority section
+    pub fn with_name_servers(mut self, name_servers: Vec<Record>) -> Self {
+        self.name_servers = name_servers;
+        self
+    }
+
+    /// Adds the records to the additional section
+    pub fn with_additionals(mut self, additionals: Vec<Record>) -> Self {
+        self.additionals = additionals;
+        self
+    }
+
+    /// Sends the response after the delay
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Sets the truncated flag and leaves out the records over UDP, the response over TCP is
+    ///  complete
+    pub fn with_truncation(mut self) -> Self {
+        self.truncated = true;
+        self
+    }
+
+    /// Sends the response with another ID than the one of the request
+    pub fn with_wrong_id(mut self) -> Self {
+        self.wrong_id = true;
+        self
+    }
+
+    /// The bytes of the response to the request, none for no response
+    fn encode(&self, request: &Message, tcp: bool) -> Option<Vec<u8>> {
+        let id = if self.wrong_id {
+            request.id().wrapping_add(1)
+        } else {
+            request.id()
+        };
+
+        match &self.body {
+            Body::Message => (),
+            Body::Malformed => {
+                let [high, low] = id.to_be_bytes();
+                return Some(vec![high, low, 0x81, 0x80, 0, 0, 0, 1, 0, 0, 0, 0]);
+            }
+            Body::Raw(bytes) => return Some(bytes.clone()),
+            Body::Nothing => return None,
+        }
+
+        let mut response = Message::new();
+        response
+            .set_id(id)
+            .set_message_type(MessageType::Response)
+            .set_op_code(request.op_code())
+            .set_recursion_desired(request.recursion_desired())
+            .set_recursion_available(true)
+            .set_response_code(self.response_code)
+            .add_queries(request.queries().iter().cloned());
+
+        if self.truncated && !tcp {
+            response.set_truncated(true);
+        } else {
+            response
+                .add_answers(self.answers.iter().cloned())
+                .add_name_servers(self.name_servers.iter().cloned())
+                .add_additionals(self.additionals.iter().cloned());
+        }
+
+        if request.extensions().is_some() {
+            response.set_edns(Edns::new());
+        }
+
+        match response.to_vec() {
+            Ok(bytes) => Some(bytes),
+            Err(e) => {
+                debug!("error encoding the mock response: {}", e);
+                None
+            }
+        }
+    }
+}
+
+/// The responses to a query, and the number of its requests
+#[derive(Debug, Default)]
+struct Script {
+    responses: Vec<MockResponse>,
+    received: usize,
+}
+
+#[derive(Debug)]
+struct State {
+    scripts: HashMap<(LowerName, RecordType), Script>,
+    fallback: MockResponse,
+}
+
+impl State {
+    /// The request, and its next response
+    fn respond(state: &Mutex<Self>, bytes: &[u8]) -> Option<(Message, MockResponse)> {
+        let request = match Message::from_vec(bytes) {
+            Ok(request) => request,
+            Err(e) => {
+                debug!("mock name server received an invalid request: {}", e);
+                return None;
+            }
+        };
+
+        let mut state = state.lock().expect("mock name server poisoned");
+        let Some(query) = request.query() else {
+            return Some((request, state.fallback.clone()));
+        };
+
+        let script = state
+            .scripts
+            .entry((LowerName::from(query.name()), query.query_type()))
+            .or_default();
+        script.received += 1;
+
+        // the last response repeats
+        let response = script
+            .responses
+            .get(script.received - 1)
+            .or_else(|| script.responses.last())
+            .cloned();
+        let response = response.unwrap_or_else(|| state.fallback.clone());
+
+        Some((request, response))
+    }
+}
+
+/// The builder of a [`MockNameServer`]
+#[derive(Debug)]
+pub struct MockNameServerBuilder {
+    scripts: HashMap<(LowerName, RecordType), Script>,
+    fallback: MockResponse,
+}
+
+impl MockNameServerBuilder {
+    /// Appends the response to the script of the query
+    ///
+    /// The requests of the query get the responses in order, and the last one after that, over
+    ///  UDP and TCP alike.
+    pub fn respond(mut self, name: Name, record_type: RecordType, response: MockResponse) -> Self {
+        self.scripts
+            .entry((LowerName::from(name), record_type))
+            .or_default()
+            .responses
+            .push(response);
+        self
+    }
+
+    /// The response to the queries without a script, REFUSED by default
+    pub fn fallback(mut self, response: MockResponse) -> Self {
+        self.fallback = response;
+        self
+    }
+
+    /// Starts the server on an ephemeral port of localhost, for UDP and TCP
+    ///
+    /// This must be called from within a Tokio runtime.
+    pub async fn start(self) -> io::Result<MockNameServer> {
+        let (socket, listener) = bind().await?;
+        let addr = socket.local_addr()?;
+        let state = Arc::new(Mutex::new(State {
+            scripts: self.scripts,
+            fallback: self.fallback,
+        }));
+
+        let mut tasks = JoinSet::new();
+        tasks.spawn(serve_udp(Arc::new(socket), Arc::clone(&state)));
+        tasks.spawn(serve_tcp(listener, Arc::clone(&state)));
+
+        Ok(MockNameServer { addr, state, tasks })
+    }
+}
+
+/// A name server with scripted responses per query, see [`MockResponse`]
+///
+/// ```
+/// # use std::net::Ipv4Addr;
+/// # use std::str::FromStr;
+/// # use hickory_resolver::config::{ResolverConfig, ResolverOpts};
+/// # use hickory_resolver::proto::rr::{Name, RData, Record, RecordType};
+/// # use hickory_resolver::testing::{MockNameServer, MockResponse};
+/// # use hickory_resolver::TokioAsyncResolver;
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let name = Name::from_str("www.example.com.").unwrap();
+/// let answer = Record::from_rdata(
+///     name.clone(),
+///     300,
+///     RData::A(Ipv4Addr::new(192, 0, 2, 1).into()),
+/// );
+///
+/// // the response over UDP is truncated, the resolver retries over TCP
+/// let truncated = MockResponse::answers(vec![answer.clone()]).with_truncation();
+/// let server = MockNameServer::builder()
+///     .respond(name.clone(), RecordType::A, truncated)
+///     .start()
+///     .await
+///     .unwrap();
+///
+/// let resolver = TokioAsyncResolver::tokio(
+///     ResolverConfig::from_parts(None, vec![], server.name_servers()),
+///     ResolverOpts::default(),
+/// );
+/// let lookup = resolver.lookup(name.clone(), RecordType::A).await.unwrap();
+/// assert_eq!(lookup.records(), &[answer]);
+/// assert_eq!(server.received(&name, RecordType::A), 2);
+/// # });
+/// ```
+///
+/// The server stops when it is dropped.
+#[derive(Debug)]
+pub struct MockNameServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    tasks: JoinSet<()>,
+}
+
+impl MockNameServer {
+    /// The builder of a server, which refuses all queries without scripts
+    pub fn builder() -> MockNameServerBuilder {
+        MockNameServerBuilder {
+            scripts: HashMap::new(),
+            fallback: MockResponse::error(ResponseCode::Refused),
+        }
+    }
+
+    /// The address of the server, for UDP and TCP
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The configuration of the server as the name server of a resolver, over UDP and TCP
+    pub fn name_servers(&self) -> NameServerConfigGroup {
+        NameServerConfigGroup::from_ips_clear(&[self.addr.ip()], self.addr.port(), true)
+    }
+
+    /// The number of requests of the query, over UDP and TCP
+    pub fn received(&self, name: &Name, record_type: RecordType) -> usize {
+        let state = self.state.lock().expect("mock name server poisoned");
+        state
+            .scripts
+            .get(&(LowerName::from(name), record_type))
+            .map_or(0, |script| script.received)
+    }
+}
+
+impl Drop for MockNameServer {
+    fn drop(&mut self) {
+        self.tasks.abort_all();
+    }
+}
+
+/// Binds a UDP socket and a TCP listener to the same ephemeral port of localhost
+async fn bind() -> io::Result<(UdpSocket, TcpListener)> {
+    let mut error = None;
+    for _ in 0..BIND_ATTEMPTS {
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+        match TcpListener::bind(socket.local_addr()?).await {
+            Ok(listener) => return Ok((socket, listener)),
+            Err(e) => error = Some(e),
+        }
+    }
+
+    Err(error.unwrap_or_else(|| io::ErrorKind::AddrInUse.into()))
+}
+
+async fn serve_udp(socket: Arc<UdpSocket>, state: Arc<Mutex<State>>) {
+    let mut buf = [0_u8; MAX_RECEIVE_BUFFER_SIZE];
+    let mut responses = JoinSet::new();
+
+    loop {
+        let (len, src) = match socket.recv_from(&mut buf).await {
+            Ok(received) => received,
+            Err(e) => {
+                debug!("mock name server error receiving over udp: {}", e);
+                continue;
+            }
+        };
+        let Some((request, response)) = State::respond(&state, &buf[..len]) else {
+            continue;
+        };
+
+        let socket = Arc::clone(&socket);
+        responses.spawn(async move {
+            tokio::time::sleep(response.delay).await;
+            if let Some(bytes) = response.encode(&request, false) {
+                let _ = socket.send_to(&bytes, src).await;
+            }
+        });
+        reap_tasks(&mut responses);
+    }
+}
+
+async fn serve_tcp(listener: TcpListener, state: Arc<Mutex<State>>) {
+    let mut connections = JoinSet::new();
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                connections.spawn(serve_connection(stream, Arc::clone(&state)));
+            }
+            Err(e) => debug!("mock name server error accepting over tcp: {}", e),
+        }
+        reap_tasks(&mut connections);
+    }
+}
+
+/// Answers the requests of the connection in order, until it is closed
+async fn serve_connection(mut stream: TcpStream, state: Arc<Mutex<State>>) {
+    loop {
+        let Ok(len) = stream.read_u16().await else {
+            return;
+        };
+        let mut buf = vec![0; usize::from(len)];
+        if stream.read_exact(&mut buf).await.is_err() {
+            return;
+        }
+
+        let Some((request, response)) = State::respond(&state, &buf) else {
+            continue;
+        };
+        tokio::time::sleep(response.delay).await;
+        let Some(bytes) = response.encode(&request, true) else {
+            continue;
+        };
+
+        let Ok(len) = u16::try_from(bytes.len()) else {
+            debug!("mock response of {} bytes is too long for tcp", bytes.len());
+            continue;
+        };
+        if stream.write_u16(len).await.is_err() || stream.write_all(&bytes).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Reap finished tasks from a `JoinSet`, without awaiting or blocking.
+fn reap_tasks(join_set: &mut JoinSet<()>) {
+    while FutureExt::now_or_never(join_set.join_next())
+        .flatten()
+        .is_some()
+    {}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::config::{NameServerConfig, Protocol, ResolverConfig, ResolverOpts};
+    use crate::proto::rr::RData;
+    use crate::TokioAsyncResolver;
+
+    fn www() -> Name {
+        Name::from_str("www.example.com.").unwrap()
+    }
+
+    fn answer() -> Record {
+        Record::from_rdata(www(), 300, RData::A(Ipv4Addr::new(192, 0, 2, 1).into()))
+    }
+
+    fn resolver(server: &MockNameServer) -> TokioAsyncResolver {
+        let options = ResolverOpts {
+            timeout: Duration::from_millis(200),
+            attempts: 1,
+            cache_size: 0,
+            ..ResolverOpts::default()
+        };
+
+        TokioAsyncResolver::tokio(
+            ResolverConfig::from_parts(None, vec![], server.name_servers()),
+            options,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_answers() {
+        let server = MockNameServer::builder()
+            .respond(www(), RecordType::A, MockResponse::answers(vec![answer()]))
+            .start()
+            .await
+            .unwrap();
+        let resolver = resolver(&server);
+
+        for received in 1..=2 {
+            let lookup = resolver.lookup(www(), RecordType::A).await.unwrap();
+            assert_eq!(lookup.records(), &[answer()]);
+            assert_eq!(server.received(&www(), RecordType::A), received);
+        }
+
+        // the queries without a script are refused
+        assert!(resolver.lookup(www(), RecordType::AAAA).await.is_err());
+        assert_eq!(server.received(&www(), RecordType::AAAA), 1);
+    }
+
+    #[tokio::test]
+    async fn test_truncation() {
+        let server = MockNameServer::builder()
+            .respond(
+                www(),
+                RecordType::A,
+                MockResponse::answers(vec![answer()]).with_truncation(),
+            )
+            .start()
+            .await
+            .unwrap();
+
+        // the resolver retries over TCP
+        let lookup = resolver(&server)
+            .lookup(www(), RecordType::A)
+            .await
+            .unwrap();
+        assert_eq!(lookup.records(), &[answer()]);
+        assert_eq!(server.received(&www(), RecordType::A), 2);
+    }
+
+    #[tokio::test]
+    async fn test_script() {
+        let server = MockNameServer::builder()
+            .respond(
+                www(),
+                RecordType::A,
+                MockResponse::answers(vec![answer()]).with_wrong_id(),
+            )
+            .respond(
+                www(),
+                RecordType::A,
+                MockResponse::answers(vec![answer()]).with_delay(Duration::from_secs(1)),
+            )
+            .respond(www(), RecordType::A, MockResponse::answers(vec![answer()]))
+            .start()
+            .await
+            .unwrap();
+
+        // the response with the wrong ID is ignored, the delayed one times out, and the
+        //  reattempt gets the answer
+        let options = ResolverOpts {
+            timeout: Duration::from_millis(200),
+            attempts: 2,
+            ..ResolverOpts::default()
+        };
+        let resolver = TokioAsyncResolver::tokio(
+            ResolverConfig::from_parts(
+                None,
+                vec![],
+                vec![NameServerConfig::new(server.addr(), Protocol::Udp)],
+            ),
+            options,
+        );
+        let lookup = resolver.lookup(www(), RecordType::A).await.unwrap();
+        assert_eq!(lookup.records(), &[answer()]);
+        assert_eq!(server.received(&www(), RecordType::A), 3);
+    }
+
+    #[tokio::test]
+    async fn test_malformed() {
+        for response in [
+            MockResponse::malformed(),
+            MockResponse::raw(vec![0xFF; 5]),
+            MockResponse::no_response(),
+        ] {
+            let server = MockNameServer::builder()
+                .respond(www(), RecordType::A, response)
+                .start()
+                .await
+                .unwrap();
+
+            assert!(resolver(&server)
+                .lookup(www(), RecordType::A)
+                .await
+                .is_err());
+        }
+    }
+}
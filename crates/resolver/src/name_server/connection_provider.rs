@@ -5,6 +5,7 @@
 // https://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
+use std::collections::HashMap;
 use std::io;
 use std::marker::Unpin;
 use std::net::SocketAddr;
@@ -13,8 +14,9 @@ use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::Duration;
 
-use futures_util::future::{Future, FutureExt};
+use futures_util::future::{self, Future, FutureExt};
 use futures_util::ready;
 use futures_util::stream::{Stream, StreamExt};
 #[cfg(feature = "tokio-runtime")]
@@ -31,6 +33,7 @@ use tokio_openssl::SslStream as TokioTlsStream;
 use tokio_rustls::client::TlsStream as TokioTlsStream;
 
 use crate::config::{NameServerConfig, Protocol, ResolverOpts};
+use crate::name_server::proxy;
 #[cfg(any(feature = "dns-over-quic", feature = "dns-over-h3"))]
 use hickory_proto::udp::QuicLocalAddr;
 #[cfg(feature = "dns-over-https")]
@@ -42,9 +45,13 @@ use proto::multicast::{MdnsClientConnect, MdnsClientStream, MdnsQueryType};
 #[cfg(feature = "dns-over-quic")]
 use proto::quic::{QuicClientConnect, QuicClientStream};
 use proto::tcp::DnsTcpStream;
-use proto::udp::DnsUdpSocket;
+use proto::udp::{
+    CaseRandomization, DnsUdpSocket, DuplicateResponseMonitor, DuplicateResponseStats,
+    UdpSocketPool,
+};
 use proto::{
     self,
+    clock::{Clock, SystemClock},
     error::ProtoError,
     op::NoopMessageFinalizer,
     tcp::TcpClientConnect,
@@ -87,6 +94,20 @@ pub trait RuntimeProvider: Clone + Send + Sync + Unpin + 'static {
         server_addr: SocketAddr,
     ) -> Pin<Box<dyn Send + Future<Output = io::Result<Self::Tcp>>>>;
 
+    /// Create a TCP connection from a socket bound to a network interface, or on Linux a VRF, see
+    ///  `NameServerConfig::bind_device`. Unsupported by default.
+    fn connect_tcp_with_bind_device(
+        &self,
+        server_addr: SocketAddr,
+        interface: String,
+    ) -> Pin<Box<dyn Send + Future<Output = io::Result<Self::Tcp>>>> {
+        let _ = server_addr;
+        Box::pin(future::ready(Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("binding to the interface {interface} is not supported by this runtime"),
+        ))))
+    }
+
     /// Create a UDP socket bound to `local_addr`. The returned value should **not** be connected to `server_addr`.
     /// *Notice: the future should be ready once returned at best effort. Otherwise UDP DNS may need much more retries.*
     fn bind_udp(
@@ -94,6 +115,13 @@ pub trait RuntimeProvider: Clone + Send + Sync + Unpin + 'static {
         local_addr: SocketAddr,
         server_addr: SocketAddr,
     ) -> Pin<Box<dyn Send + Future<Output = io::Result<Self::Udp>>>>;
+
+    /// The clock for the expiry of cached records, the validity of signatures and signing
+    ///
+    /// This is the system clock by default, targets without one or tests can supply their own.
+    fn clock(&self) -> Arc<dyn Clock> {
+        Arc::new(SystemClock)
+    }
 }
 
 /// Create `DnsHandle` with the help of `RuntimeProvider`.
@@ -109,6 +137,17 @@ pub trait ConnectionProvider: 'static + Clone + Send + Sync + Unpin {
     /// Create a new connection.
     fn new_connection(&self, config: &NameServerConfig, options: &ResolverOpts)
         -> Self::FutureConn;
+
+    /// The clock of the runtime, see `RuntimeProvider::clock`
+    fn clock(&self) -> Arc<dyn Clock> {
+        Arc::new(SystemClock)
+    }
+
+    /// Counters for responses received after an answer was accepted over UDP, see
+    ///  `ResolverOpts::duplicate_response_window`
+    fn duplicate_response_stats(&self) -> DuplicateResponseStats {
+        DuplicateResponseStats::default()
+    }
 }
 
 /// A type defines the Handle which can spawn future.
@@ -245,27 +284,88 @@ impl DnsHandle for GenericConnection {
     }
 }
 
+/// A socket future which fails, for the protocols which can not be tunneled through a proxy
+#[cfg(any(feature = "dns-over-quic", feature = "dns-over-h3"))]
+fn proxy_unsupported<S: Send + 'static>(
+    protocol: Protocol,
+) -> Pin<Box<dyn Send + Future<Output = io::Result<S>>>> {
+    Box::pin(future::ready(Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("{protocol} can not be tunneled through the proxy"),
+    ))))
+}
+
+/// The UDP socket pools of a connector, by the interface the sockets are bound to
+type UdpSocketPools<S> = Arc<parking_lot::Mutex<HashMap<Option<String>, UdpSocketPool<S>>>>;
+
 /// Default connector for `GenericConnection`
-#[derive(Clone)]
 pub struct GenericConnector<P: RuntimeProvider> {
     runtime_provider: P,
+    duplicate_monitor: DuplicateResponseMonitor,
+    case_randomization: CaseRandomization,
+    /// The UDP socket pools, sockets bound to an interface are only shared with the name servers
+    ///  bound to the same interface
+    udp_socket_pools: UdpSocketPools<P::Udp>,
 }
 
 impl<P: RuntimeProvider> GenericConnector<P> {
     /// Create a new instance.
     pub fn new(runtime_provider: P) -> Self {
-        Self { runtime_provider }
+        Self {
+            runtime_provider,
+            duplicate_monitor: DuplicateResponseMonitor::new(Duration::ZERO),
+            case_randomization: CaseRandomization::new(),
+            udp_socket_pools: Arc::default(),
+        }
+    }
+
+    /// Connects to the name server over TCP, through the proxy if one is configured
+    fn connect_tcp(
+        &self,
+        config: &NameServerConfig,
+        options: &ResolverOpts,
+    ) -> Pin<Box<dyn Send + Future<Output = io::Result<P::Tcp>>>> {
+        let connect_addr = match options.proxy {
+            Some(ref proxy) => proxy.addr,
+            None => config.socket_addr,
+        };
+        let tcp_future = match config.bind_device {
+            Some(ref interface) => self
+                .runtime_provider
+                .connect_tcp_with_bind_device(connect_addr, interface.clone()),
+            None => self.runtime_provider.connect_tcp(connect_addr),
+        };
+
+        let Some(proxy) = options.proxy.clone() else {
+            return tcp_future;
+        };
+        let target = config.socket_addr;
+        Box::pin(async move {
+            let mut stream = tcp_future.await?;
+            proxy::connect(&mut stream, &proxy, target).await?;
+            Ok(stream)
+        })
     }
 }
 
-impl<P: RuntimeProvider + Default> Default for GenericConnector<P> {
-    fn default() -> Self {
+// not derived, which would require the socket type to be `Clone`
+impl<P: RuntimeProvider> Clone for GenericConnector<P> {
+    fn clone(&self) -> Self {
         Self {
-            runtime_provider: P::default(),
+            runtime_provider: self.runtime_provider.clone(),
+            duplicate_monitor: self.duplicate_monitor.clone(),
+            case_randomization: self.case_randomization.clone(),
+            udp_socket_pools: Arc::clone(&self.udp_socket_pools),
         }
     }
 }
 
+impl<P: RuntimeProvider + Default> Default for GenericConnector<P> {
+    fn default() -> Self {
+        Self::new(P::default())
+    }
+}
+
 impl<P: RuntimeProvider> ConnectionProvider for GenericConnector<P> {
     type Conn = GenericConnection;
     type FutureConn = ConnectionFuture<P>;
@@ -277,7 +377,7 @@ impl<P: RuntimeProvider> ConnectionProvider for GenericConnector<P> {
         options: &ResolverOpts,
     ) -> Self::FutureConn {
         let dns_connect = match config.protocol {
-            Protocol::Udp => {
+            Protocol::Udp if options.proxy.is_none() => {
                 let provider_handle = self.runtime_provider.clone();
                 let closure = move |local_addr: SocketAddr, server_addr: SocketAddr| {
                     provider_handle.bind_udp(local_addr, server_addr)
@@ -287,14 +387,56 @@ impl<P: RuntimeProvider> ConnectionProvider for GenericConnector<P> {
                     None,
                     options.timeout,
                     Arc::new(closure),
-                );
+                )
+                .with_clock(self.runtime_provider.clock());
+                let stream = match config.bind_device {
+                    Some(ref interface) => stream.with_bind_device(interface.clone()),
+                    None => stream,
+                };
+                let stream = match options.duplicate_response_window {
+                    Some(window) => {
+                        let handle = self.runtime_provider.create_handle();
+                        stream.with_duplicate_monitor(
+                            self.duplicate_monitor
+                                .clone()
+                                .with_window(window)
+                                .reject_conflicting(options.requery_conflicting_over_tcp),
+                            Arc::new(move |watch| {
+                                handle.clone().spawn_bg(async move {
+                                    watch.await;
+                                    Ok(())
+                                })
+                            }),
+                        )
+                    }
+                    None => stream,
+                };
+                let stream = match options.case_randomization {
+                    true => stream.with_case_randomization(self.case_randomization.clone()),
+                    false => stream,
+                };
+                // the pool is shared by the connections to all name servers
+                let stream = match options.udp_socket_pool {
+                    Some(ref pool_config) => stream.with_socket_pool(
+                        self.udp_socket_pools
+                            .lock()
+                            .entry(config.bind_device.clone())
+                            .or_insert_with(|| {
+                                UdpSocketPool::new(pool_config.clone())
+                                    .with_clock(self.runtime_provider.clock())
+                            })
+                            .clone(),
+                    ),
+                    None => stream,
+                };
                 let exchange = DnsExchange::connect(stream);
                 ConnectionConnect::Udp(exchange)
             }
-            Protocol::Tcp => {
+            // UDP can not be tunneled through the proxy, so it is replaced by TCP
+            Protocol::Tcp | Protocol::Udp => {
                 let socket_addr = config.socket_addr;
                 let timeout = options.timeout;
-                let tcp_future = self.runtime_provider.connect_tcp(socket_addr);
+                let tcp_future = self.connect_tcp(config, options);
 
                 let (stream, handle) =
                     TcpClientStream::with_future(tcp_future, socket_addr, timeout);
@@ -304,7 +446,8 @@ impl<P: RuntimeProvider> ConnectionProvider for GenericConnector<P> {
                     handle,
                     timeout,
                     NoopMessageFinalizer::new(),
-                );
+                )
+                .with_clock(self.runtime_provider.clock());
 
                 let exchange = DnsExchange::connect(dns_conn);
                 ConnectionConnect::Tcp(exchange)
@@ -314,7 +457,7 @@ impl<P: RuntimeProvider> ConnectionProvider for GenericConnector<P> {
                 let socket_addr = config.socket_addr;
                 let timeout = options.timeout;
                 let tls_dns_name = config.tls_dns_name.clone().unwrap_or_default();
-                let tcp_future = self.runtime_provider.connect_tcp(socket_addr);
+                let tcp_future = self.connect_tcp(config, options);
 
                 #[cfg(feature = "dns-over-rustls")]
                 let client_config = config.tls_config.clone();
@@ -338,7 +481,8 @@ impl<P: RuntimeProvider> ConnectionProvider for GenericConnector<P> {
                     handle,
                     timeout,
                     NoopMessageFinalizer::new(),
-                );
+                )
+                .with_clock(self.runtime_provider.clock());
 
                 let exchange = DnsExchange::connect(dns_conn);
                 ConnectionConnect::Tls(exchange)
@@ -349,13 +493,14 @@ impl<P: RuntimeProvider> ConnectionProvider for GenericConnector<P> {
                 let tls_dns_name = config.tls_dns_name.clone().unwrap_or_default();
                 #[cfg(feature = "dns-over-rustls")]
                 let client_config = config.tls_config.clone();
-                let tcp_future = self.runtime_provider.connect_tcp(socket_addr);
+                let tcp_future = self.connect_tcp(config, options);
 
                 let exchange = crate::h2::new_https_stream_with_future(
                     tcp_future,
                     socket_addr,
                     tls_dns_name,
                     client_config,
+                    config.http.as_ref(),
                 );
                 ConnectionConnect::Https(exchange)
             }
@@ -371,7 +516,11 @@ impl<P: RuntimeProvider> ConnectionProvider for GenericConnector<P> {
                 let tls_dns_name = config.tls_dns_name.clone().unwrap_or_default();
                 #[cfg(feature = "dns-over-rustls")]
                 let client_config = config.tls_config.clone();
-                let udp_future = self.runtime_provider.bind_udp(bind_addr, socket_addr);
+                let udp_future = match options.proxy {
+                    // QUIC would bypass the proxy
+                    Some(_) => proxy_unsupported(config.protocol),
+                    None => self.runtime_provider.bind_udp(bind_addr, socket_addr),
+                };
 
                 let exchange = crate::quic::new_quic_stream_with_future(
                     udp_future,
@@ -392,7 +541,11 @@ impl<P: RuntimeProvider> ConnectionProvider for GenericConnector<P> {
                 });
                 let tls_dns_name = config.tls_dns_name.clone().unwrap_or_default();
                 let client_config = config.tls_config.clone();
-                let udp_future = self.runtime_provider.bind_udp(bind_addr, socket_addr);
+                let udp_future = match options.proxy {
+                    // QUIC would bypass the proxy
+                    Some(_) => proxy_unsupported(config.protocol),
+                    None => self.runtime_provider.bind_udp(bind_addr, socket_addr),
+                };
 
                 let exchange = crate::h3::new_h3_stream_with_future(
                     udp_future,
@@ -415,7 +568,8 @@ impl<P: RuntimeProvider> ConnectionProvider for GenericConnector<P> {
                     handle,
                     timeout,
                     NoopMessageFinalizer::new(),
-                );
+                )
+                .with_clock(self.runtime_provider.clock());
 
                 let exchange = DnsExchange::connect(dns_conn);
                 ConnectionConnect::Mdns(exchange)
@@ -427,6 +581,14 @@ impl<P: RuntimeProvider> ConnectionProvider for GenericConnector<P> {
             spawner: self.runtime_provider.create_handle(),
         }
     }
+
+    fn clock(&self) -> Arc<dyn Clock> {
+        self.runtime_provider.clock()
+    }
+
+    fn duplicate_response_stats(&self) -> DuplicateResponseStats {
+        self.duplicate_monitor.stats()
+    }
 }
 
 /// A stream of response to a DNS request.
@@ -446,6 +608,7 @@ impl Stream for ConnectionResponse {
 #[allow(unreachable_pub)]
 pub mod tokio_runtime {
     use super::*;
+    use proto::tcp::Connect;
     use std::sync::{Arc, Mutex};
     use tokio::net::UdpSocket as TokioUdpSocket;
     use tokio::task::JoinSet;
@@ -499,6 +662,20 @@ pub mod tokio_runtime {
             })
         }
 
+        fn connect_tcp_with_bind_device(
+            &self,
+            server_addr: SocketAddr,
+            interface: String,
+        ) -> Pin<Box<dyn Send + Future<Output = io::Result<Self::Tcp>>>> {
+            Box::pin(
+                AsyncIoTokioAsStd::<TokioTcpStream>::connect_with_bind_device(
+                    server_addr,
+                    None,
+                    interface,
+                ),
+            )
+        }
+
         fn bind_udp(
             &self,
             local_addr: SocketAddr,
@@ -519,3 +696,417 @@ pub mod tokio_runtime {
     /// Default ConnectionProvider with `GenericConnection`.
     pub type TokioConnectionProvider = GenericConnector<TokioRuntimeProvider>;
 }
+
+#[cfg(all(feature = "testing", feature = "tokio-runtime"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "testing", feature = "tokio-runtime"))))]
+#[allow(unreachable_pub)]
+pub mod sim_runtime {
+    use super::tokio_runtime::TokioHandle;
+    use super::*;
+    use proto::sim::{SimNetwork, SimTcpStream, SimUdpSocket};
+    use std::net::IpAddr;
+
+    /// The runtime of a host of a simulated network, see [`proto::sim`]
+    ///
+    /// The timers are the timers of Tokio, the time of the simulation is deterministic when Tokio
+    ///  runs on the current thread with paused time.
+    #[derive(Clone)]
+    pub struct SimRuntimeProvider {
+        network: SimNetwork,
+        ip: IpAddr,
+        handle: TokioHandle,
+    }
+
+    impl SimRuntimeProvider {
+        /// Create the runtime of the host with the address on the network
+        pub fn new(network: SimNetwork, ip: IpAddr) -> Self {
+            Self {
+                network,
+                ip,
+                handle: TokioHandle::default(),
+            }
+        }
+    }
+
+    impl RuntimeProvider for SimRuntimeProvider {
+        type Handle = TokioHandle;
+        type Timer = TokioTime;
+        type Udp = SimUdpSocket;
+        type Tcp = SimTcpStream;
+
+        fn create_handle(&self) -> Self::Handle {
+            self.handle.clone()
+        }
+
+        fn connect_tcp(
+            &self,
+            server_addr: SocketAddr,
+        ) -> Pin<Box<dyn Send + Future<Output = io::Result<Self::Tcp>>>> {
+            let network = self.network.clone();
+            let ip = self.ip;
+            Box::pin(async move { network.connect_tcp(ip, server_addr).await })
+        }
+
+        fn bind_udp(
+            &self,
+            local_addr: SocketAddr,
+            _server_addr: SocketAddr,
+        ) -> Pin<Box<dyn Send + Future<Output = io::Result<Self::Udp>>>> {
+            // the unspecified address is the address of the host
+            let local_addr = if local_addr.ip().is_unspecified() {
+                SocketAddr::new(self.ip, local_addr.port())
+            } else {
+                local_addr
+            };
+            Box::pin(future::ready(self.network.bind_udp(local_addr)))
+        }
+
+        fn clock(&self) -> Arc<dyn Clock> {
+            self.network.clock()
+        }
+    }
+
+    /// ConnectionProvider with `GenericConnection` on a simulated network.
+    pub type SimConnectionProvider = GenericConnector<SimRuntimeProvider>;
+}
+
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "io-uring", target_os = "linux"))))]
+#[allow(unreachable_pub)]
+pub mod io_uring_runtime {
+    use super::tokio_runtime::TokioHandle;
+    use super::*;
+    use proto::tcp::Connect;
+    use proto::udp::UdpSocket;
+    use proto::uring::{IoUringTcpStream, IoUringUdpSocket};
+
+    /// The runtime with UDP and TCP connections on io_uring, and Tokio for the timers and tasks
+    ///
+    /// The connections of all resolvers share one ring, see [`proto::uring`].
+    #[derive(Clone, Default)]
+    pub struct IoUringRuntimeProvider(TokioHandle);
+
+    impl IoUringRuntimeProvider {
+        /// Create an io_uring runtime
+        pub fn new() -> Self {
+            Self::default()
+        }
+    }
+
+    impl RuntimeProvider for IoUringRuntimeProvider {
+        type Handle = TokioHandle;
+        type Timer = TokioTime;
+        type Udp = IoUringUdpSocket;
+        type Tcp = IoUringTcpStream;
+
+        fn create_handle(&self) -> Self::Handle {
+            self.0.clone()
+        }
+
+        fn connect_tcp(
+            &self,
+            server_addr: SocketAddr,
+        ) -> Pin<Box<dyn Send + Future<Output = io::Result<Self::Tcp>>>> {
+            Box::pin(IoUringTcpStream::connect(server_addr))
+        }
+
+        fn bind_udp(
+            &self,
+            local_addr: SocketAddr,
+            _server_addr: SocketAddr,
+        ) -> Pin<Box<dyn Send + Future<Output = io::Result<Self::Udp>>>> {
+            Box::pin(IoUringUdpSocket::bind(local_addr))
+        }
+    }
+
+    /// ConnectionProvider with `GenericConnection` on io_uring.
+    pub type IoUringConnectionProvider = GenericConnector<IoUringRuntimeProvider>;
+}
+
+#[cfg(feature = "smol-runtime")]
+#[cfg_attr(docsrs, doc(cfg(feature = "smol-runtime")))]
+#[allow(unreachable_pub)]
+pub mod smol_runtime {
+    use super::*;
+    use proto::smol_runtime::SmolTime;
+    use proto::tcp::Connect;
+    use smol::Async;
+    use std::net::{TcpStream as StdTcpStream, UdpSocket as StdUdpSocket};
+
+    /// A handle to the global executor of smol
+    #[derive(Clone, Copy, Default)]
+    pub struct SmolHandle;
+
+    impl Spawn for SmolHandle {
+        fn spawn_bg<F>(&mut self, future: F)
+        where
+            F: Future<Output = Result<(), ProtoError>> + Send + 'static,
+        {
+            smol::spawn(future).detach();
+        }
+    }
+
+    /// The smol runtime, which works with any executor as the reactor of smol runs on its own
+    #[derive(Clone, Copy, Default)]
+    pub struct SmolRuntimeProvider;
+
+    impl SmolRuntimeProvider {
+        /// Create a smol runtime
+        pub fn new() -> Self {
+            Self
+        }
+    }
+
+    impl RuntimeProvider for SmolRuntimeProvider {
+        type Handle = SmolHandle;
+        type Timer = SmolTime;
+        type Udp = Async<StdUdpSocket>;
+        type Tcp = Async<StdTcpStream>;
+
+        fn create_handle(&self) -> Self::Handle {
+            SmolHandle
+        }
+
+        fn connect_tcp(
+            &self,
+            server_addr: SocketAddr,
+        ) -> Pin<Box<dyn Send + Future<Output = io::Result<Self::Tcp>>>> {
+            Box::pin(<Async<StdTcpStream> as Connect>::connect(server_addr))
+        }
+
+        fn bind_udp(
+            &self,
+            local_addr: SocketAddr,
+            _server_addr: SocketAddr,
+        ) -> Pin<Box<dyn Send + Future<Output = io::Result<Self::Udp>>>> {
+            Box::pin(futures_util::future::ready(Async::<StdUdpSocket>::bind(
+                local_addr,
+            )))
+        }
+    }
+
+    /// ConnectionProvider with `GenericConnection` on smol.
+    pub type SmolConnectionProvider = GenericConnector<SmolRuntimeProvider>;
+}
+
+#[cfg(feature = "wasm-bindgen")]
+#[cfg_attr(docsrs, doc(cfg(feature = "wasm-bindgen")))]
+#[allow(unreachable_pub)]
+pub mod wasm_runtime {
+    use super::*;
+    use futures_io::{AsyncRead, AsyncWrite};
+    use futures_util::future::{self, Ready};
+    use proto::wasm::{FetchClient, WasmTime};
+    use std::net::IpAddr;
+
+    /// A handle to the event loop of the JavaScript host
+    #[derive(Clone, Copy, Default)]
+    pub struct WasmHandle;
+
+    impl Spawn for WasmHandle {
+        fn spawn_bg<F>(&mut self, future: F)
+        where
+            F: Future<Output = Result<(), ProtoError>> + Send + 'static,
+        {
+            wasm_bindgen_futures::spawn_local(async move {
+                let _ = future.await;
+            });
+        }
+    }
+
+    /// A socket which can not be created, the JavaScript hosts have no sockets
+    #[derive(Clone, Copy, Debug)]
+    pub enum NoSocket {}
+
+    impl DnsUdpSocket for NoSocket {
+        type Time = WasmTime;
+
+        fn poll_recv_from(
+            &self,
+            _cx: &mut Context<'_>,
+            _buf: &mut [u8],
+        ) -> Poll<io::Result<(usize, SocketAddr)>> {
+            match *self {}
+        }
+
+        fn poll_send_to(
+            &self,
+            _cx: &mut Context<'_>,
+            _buf: &[u8],
+            _target: SocketAddr,
+        ) -> Poll<io::Result<usize>> {
+            match *self {}
+        }
+    }
+
+    #[cfg(any(feature = "dns-over-quic", feature = "dns-over-h3"))]
+    impl QuicLocalAddr for NoSocket {
+        fn local_addr(&self) -> io::Result<SocketAddr> {
+            match *self {}
+        }
+    }
+
+    impl AsyncRead for NoSocket {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            _buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            match *self {}
+        }
+    }
+
+    impl AsyncWrite for NoSocket {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            _buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            match *self {}
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            match *self {}
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            match *self {}
+        }
+    }
+
+    impl DnsTcpStream for NoSocket {
+        type Time = WasmTime;
+    }
+
+    /// The runtime of a JavaScript host, with its timers and event loop, but without sockets
+    #[derive(Clone, Copy, Default)]
+    pub struct WasmRuntimeProvider;
+
+    impl WasmRuntimeProvider {
+        /// Create a runtime on the JavaScript host
+        pub fn new() -> Self {
+            Self
+        }
+    }
+
+    impl RuntimeProvider for WasmRuntimeProvider {
+        type Handle = WasmHandle;
+        type Timer = WasmTime;
+        type Udp = NoSocket;
+        type Tcp = NoSocket;
+
+        fn create_handle(&self) -> Self::Handle {
+            WasmHandle
+        }
+
+        fn connect_tcp(
+            &self,
+            _server_addr: SocketAddr,
+        ) -> Pin<Box<dyn Send + Future<Output = io::Result<Self::Tcp>>>> {
+            Box::pin(future::ready(Err(no_sockets())))
+        }
+
+        fn bind_udp(
+            &self,
+            _local_addr: SocketAddr,
+            _server_addr: SocketAddr,
+        ) -> Pin<Box<dyn Send + Future<Output = io::Result<Self::Udp>>>> {
+            Box::pin(future::ready(Err(no_sockets())))
+        }
+    }
+
+    fn no_sockets() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "sockets are not available on the JavaScript host",
+        )
+    }
+
+    /// ConnectionProvider with DNS over HTTPS, sent with the `fetch` of the JavaScript host
+    ///
+    /// Every name server is queried with DNS over HTTPS, regardless of its protocol, at
+    ///  `https://{tls_dns_name}{path}`. Without a TLS name the IP address is the host, and a port
+    ///  other than 443 is added to the URL.
+    #[derive(Clone)]
+    pub struct FetchConnectionProvider {
+        path: Arc<str>,
+    }
+
+    impl FetchConnectionProvider {
+        /// Create a provider which queries the well-known path `/dns-query` of the name servers
+        pub fn new() -> Self {
+            Self::with_path("/dns-query")
+        }
+
+        /// Create a provider which queries the path of the name servers, e.g. `/resolve`
+        pub fn with_path(path: impl Into<Arc<str>>) -> Self {
+            Self { path: path.into() }
+        }
+
+        fn url(&self, config: &NameServerConfig) -> String {
+            let host = match (&config.tls_dns_name, config.socket_addr.ip()) {
+                (Some(name), _) => name.clone(),
+                (None, IpAddr::V4(ip)) => ip.to_string(),
+                (None, IpAddr::V6(ip)) => format!("[{ip}]"),
+            };
+
+            match config.socket_addr.port() {
+                443 => format!("https://{host}{}", self.path),
+                port => format!("https://{host}:{port}{}", self.path),
+            }
+        }
+    }
+
+    impl Default for FetchConnectionProvider {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl ConnectionProvider for FetchConnectionProvider {
+        type Conn = FetchClient;
+        type FutureConn = Ready<Result<FetchClient, ProtoError>>;
+        type RuntimeProvider = WasmRuntimeProvider;
+
+        fn new_connection(
+            &self,
+            config: &NameServerConfig,
+            _options: &ResolverOpts,
+        ) -> Self::FutureConn {
+            future::ready(Ok(FetchClient::new(self.url(config))))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn url(addr: &str, tls_dns_name: Option<&str>) -> String {
+            let mut config = NameServerConfig::new(addr.parse().unwrap(), Protocol::Udp);
+            config.tls_dns_name = tls_dns_name.map(str::to_owned);
+            FetchConnectionProvider::new().url(&config)
+        }
+
+        #[test]
+        fn test_url() {
+            assert_eq!(
+                url("8.8.8.8:443", Some("dns.google")),
+                "https://dns.google/dns-query"
+            );
+            assert_eq!(url("1.1.1.1:443", None), "https://1.1.1.1/dns-query");
+            assert_eq!(
+                url("[2606:4700:4700::1111]:8443", None),
+                "https://[2606:4700:4700::1111]:8443/dns-query"
+            );
+        }
+
+        #[test]
+        fn test_url_with_path() {
+            let config = NameServerConfig::new("9.9.9.9:443".parse().unwrap(), Protocol::Udp);
+            assert_eq!(
+                FetchConnectionProvider::with_path("/resolve").url(&config),
+                "https://9.9.9.9/resolve"
+            );
+        }
+    }
+}
@@ -10,9 +10,11 @@
 mod connection_provider;
 #[allow(clippy::module_inception)]
 mod name_server;
+mod name_server_health;
 mod name_server_pool;
 mod name_server_state;
 mod name_server_stats;
+mod proxy;
 
 pub use self::connection_provider::{ConnectionProvider, RuntimeProvider, Spawn};
 pub use self::connection_provider::{GenericConnection, GenericConnector};
@@ -20,12 +22,32 @@ pub use self::connection_provider::{GenericConnection, GenericConnector};
 #[cfg_attr(docsrs, doc(cfg(feature = "mdns")))]
 pub(crate) use self::name_server::mdns_nameserver;
 pub use self::name_server::{GenericNameServer, NameServer};
+use self::name_server_health::NameServerHealth;
+pub use self::name_server_health::NameServerHealthStats;
 pub use self::name_server_pool::{GenericNameServerPool, NameServerPool};
 use self::name_server_state::NameServerState;
 use self::name_server_stats::NameServerStats;
 
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "io-uring", target_os = "linux"))))]
+pub use self::connection_provider::io_uring_runtime::{
+    IoUringConnectionProvider, IoUringRuntimeProvider,
+};
+#[cfg(all(feature = "testing", feature = "tokio-runtime"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "testing", feature = "tokio-runtime"))))]
+pub use self::connection_provider::sim_runtime::{SimConnectionProvider, SimRuntimeProvider};
+#[cfg(feature = "smol-runtime")]
+#[cfg_attr(docsrs, doc(cfg(feature = "smol-runtime")))]
+pub use self::connection_provider::smol_runtime::{
+    SmolConnectionProvider, SmolHandle, SmolRuntimeProvider,
+};
 #[cfg(feature = "tokio-runtime")]
 #[cfg_attr(docsrs, doc(cfg(feature = "tokio-runtime")))]
 pub use self::connection_provider::tokio_runtime::{
     TokioConnectionProvider, TokioHandle, TokioRuntimeProvider,
 };
+#[cfg(feature = "wasm-bindgen")]
+#[cfg_attr(docsrs, doc(cfg(feature = "wasm-bindgen")))]
+pub use self::connection_provider::wasm_runtime::{
+    FetchConnectionProvider, NoSocket, WasmHandle, WasmRuntimeProvider,
+};
@@ -7,9 +7,10 @@
 
 use std::cmp::Ordering;
 use std::fmt::{self, Debug, Formatter};
+use std::net::SocketAddr;
 use std::pin::Pin;
-use std::sync::Arc;
-use std::time::Instant;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex as StdMutex, PoisonError};
 
 use futures_util::lock::Mutex;
 use futures_util::stream::{once, Stream};
@@ -17,14 +18,19 @@ use futures_util::stream::{once, Stream};
 #[cfg(feature = "mdns")]
 use proto::multicast::MDNS_IPV4;
 use proto::{
+    clock::Instant,
     error::ProtoError,
-    xfer::{DnsHandle, DnsRequest, DnsResponse, FirstAnswer},
+    op::Query,
+    rr::{Name, RecordType},
+    xfer::{DnsHandle, DnsRequest, DnsRequestOptions, DnsResponse, FirstAnswer},
 };
-use tracing::debug;
+use tracing::{debug, warn};
 
-use crate::config::{NameServerConfig, ResolverOpts};
+use crate::config::{NameServerConfig, PrivacyPolicy, Protocol, ProxyConfig, ResolverOpts};
 use crate::name_server::connection_provider::{ConnectionProvider, GenericConnector};
-use crate::name_server::{NameServerState, NameServerStats};
+use crate::name_server::{
+    proxy, NameServerHealth, NameServerHealthStats, NameServerState, NameServerStats,
+};
 #[cfg(feature = "mdns")]
 use proto::multicast::{MdnsClientConnect, MdnsClientStream, MdnsQueryType};
 
@@ -37,6 +43,11 @@ pub struct NameServer<P: ConnectionProvider> {
     state: Arc<NameServerState>,
     stats: Arc<NameServerStats>,
     connection_provider: P,
+    /// when the encrypted connection failed, see `PrivacyPolicy::Opportunistic`
+    downgraded: Arc<StdMutex<Option<Instant>>>,
+    /// true if `client` is connected with unencrypted DNS instead of the configured protocol
+    plaintext: Arc<AtomicBool>,
+    health: Arc<NameServerHealth>,
 }
 
 /// Specifies the details of a remote NameServer used for lookups
@@ -64,6 +75,9 @@ where
             state: Arc::new(NameServerState::init(None)),
             stats: Arc::new(NameServerStats::default()),
             connection_provider,
+            downgraded: Arc::default(),
+            plaintext: Arc::default(),
+            health: Arc::default(),
         }
     }
 
@@ -81,6 +95,9 @@ where
             state: Arc::new(NameServerState::init(None)),
             stats: Arc::new(NameServerStats::default()),
             connection_provider,
+            downgraded: Arc::default(),
+            plaintext: Arc::default(),
+            health: Arc::default(),
         }
     }
 
@@ -96,27 +113,105 @@ where
             }
     }
 
+    /// Returns true if unencrypted DNS is used instead of the configured encrypted protocol
+    fn use_plaintext(&self) -> bool {
+        if !self.config.protocol.is_encrypted() {
+            return false;
+        }
+
+        match self.config.privacy {
+            PrivacyPolicy::Strict => false,
+            PrivacyPolicy::Off => true,
+            PrivacyPolicy::Opportunistic => {
+                let downgraded = self
+                    .downgraded
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner);
+                downgraded.map_or(false, |since| {
+                    since.elapsed() < self.options.encryption_probe_interval
+                })
+            }
+        }
+    }
+
+    /// Returns true if a failure of the encrypted connection falls back to unencrypted DNS
+    fn can_downgrade(&self) -> bool {
+        self.config.protocol.is_encrypted()
+            && self.config.privacy == PrivacyPolicy::Opportunistic
+            && !self.plaintext.load(AtomicOrdering::Acquire)
+    }
+
+    fn downgrade(&self, error: &ProtoError) {
+        warn!(
+            "encrypted connection to {} failed, falling back to unencrypted DNS: {error}",
+            self.config
+        );
+        *self
+            .downgraded
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(Instant::now());
+    }
+
+    /// Unencrypted DNS over TCP on port 53 of the configured address
+    fn plaintext_config(&self) -> NameServerConfig {
+        let mut config = self.config.clone();
+        config.socket_addr = SocketAddr::new(self.config.socket_addr.ip(), 53);
+        config.protocol = Protocol::Tcp;
+        config.tls_dns_name = None;
+        config
+    }
+
+    /// Establishes a new connection, falling back to unencrypted DNS as the privacy policy allows
+    ///
+    /// Returns the connection, and whether it is unencrypted.
+    async fn new_client(&self, options: &ResolverOpts) -> Result<(P::Conn, bool), ProtoError> {
+        if self.use_plaintext() {
+            let client = self
+                .connection_provider
+                .new_connection(&self.plaintext_config(), options)
+                .await?;
+            return Ok((client, true));
+        }
+
+        match self
+            .connection_provider
+            .new_connection(&self.config, options)
+            .await
+        {
+            Ok(client) => Ok((client, false)),
+            Err(e) if self.config.privacy == PrivacyPolicy::Opportunistic => {
+                self.downgrade(&e);
+                let client = self
+                    .connection_provider
+                    .new_connection(&self.plaintext_config(), options)
+                    .await?;
+                Ok((client, true))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
     /// This will return a mutable client to allows for sending messages.
     ///
     /// If the connection is in a failed state, then this will establish a new connection
     async fn connected_mut_client(&mut self) -> Result<P::Conn, ProtoError> {
         let mut client = self.client.lock().await;
+        let plaintext = self.use_plaintext();
 
-        // if this is in a failure state
-        if self.state.is_failed() || client.is_none() {
+        // if this is in a failure state, or the encryption changed
+        if self.state.is_failed()
+            || client.is_none()
+            || plaintext != self.plaintext.load(AtomicOrdering::Acquire)
+        {
             debug!("reconnecting: {:?}", self.config);
 
             // TODO: we need the local EDNS options
             self.state.reinit(None);
 
-            let new_client = Box::pin(
-                self.connection_provider
-                    .new_connection(&self.config, &self.options),
-            )
-            .await?;
-
             // establish a new connection
+            let (new_client, plaintext) = self.new_client(&self.options).await?;
             *client = Some(new_client);
+            self.plaintext.store(plaintext, AtomicOrdering::Release);
         } else {
             debug!("existing connection: {:?}", self.config);
         }
@@ -126,43 +221,82 @@ where
             .expect("bad state, client should be connected"))
     }
 
-    async fn inner_send<R: Into<DnsRequest> + Unpin + Send + 'static>(
-        mut self,
-        request: R,
-    ) -> Result<DnsResponse, ProtoError> {
-        let client = self.connected_mut_client().await?;
-        let now = Instant::now();
-        let response = client.send(request).first_answer().await;
-        let rtt = now.elapsed();
-
-        match response {
-            Ok(response) => {
-                // Record the measured latency.
-                self.stats.record_rtt(rtt);
-
-                // First evaluate if the message succeeded.
-                let response =
-                    ProtoError::from_response(response, self.config.trust_negative_responses)?;
-
-                // TODO: consider making message::take_edns...
-                let remote_edns = response.extensions().clone();
-
-                // take the remote edns options and store them
-                self.state.establish(remote_edns);
+    /// Returns a connection of its own through the proxy, isolated from the other queries, see
+    ///  `StreamIsolation`
+    async fn isolated_client(&self, proxy: ProxyConfig) -> Result<P::Conn, ProtoError> {
+        debug!("isolated connection: {:?}", self.config);
 
-                Ok(response)
-            }
-            Err(error) => {
-                debug!("name_server connection failure: {}", error);
-
-                // this transitions the state to failure
-                self.state.fail(Instant::now());
-
-                // record the failure
-                self.stats.record_connection_failure();
+        let mut options = self.options.clone();
+        options.proxy = Some(proxy);
+        let (client, plaintext) = self.new_client(&options).await?;
+        self.plaintext.store(plaintext, AtomicOrdering::Release);
+        Ok(client)
+    }
 
-                // These are connection failures, not lookup failures, that is handled in the resolver layer
-                Err(error)
+    async fn inner_send(mut self, request: DnsRequest) -> Result<DnsResponse, ProtoError> {
+        loop {
+            let isolated = self
+                .options
+                .proxy
+                .as_ref()
+                .and_then(|proxy| proxy::isolate(proxy, request.queries().first()));
+            let client = match isolated {
+                Some(proxy) => self.isolated_client(proxy).await?,
+                None => self.connected_mut_client().await?,
+            };
+            let now = Instant::now();
+            let response = client.send(request.clone()).first_answer().await;
+            let rtt = now.elapsed();
+
+            match response {
+                Ok(response) => {
+                    // Record the measured latency.
+                    self.stats.record_rtt(rtt);
+                    self.health.record_success();
+
+                    // First evaluate if the message succeeded.
+                    let mut response =
+                        ProtoError::from_response(response, self.config.trust_negative_responses)?;
+
+                    let protocol = if self.plaintext.load(AtomicOrdering::Acquire) {
+                        Protocol::Tcp
+                    } else {
+                        self.config.protocol
+                    };
+                    response.set_transport(protocol.into());
+
+                    // TODO: consider making message::take_edns...
+                    let remote_edns = response.extensions().clone();
+
+                    // take the remote edns options and store them
+                    self.state.establish(remote_edns);
+
+                    return Ok(response);
+                }
+                Err(error) => {
+                    debug!("name_server connection failure: {}", error);
+
+                    // this transitions the state to failure
+                    self.state.fail(Instant::now());
+
+                    // record the failure
+                    self.stats.record_connection_failure();
+                    if let Some(backoff) = self
+                        .health
+                        .record_failure(Instant::now(), &self.options.health)
+                    {
+                        debug!("demoting {} for {:?}", self.config, backoff);
+                    }
+
+                    // the encrypted connection failed, e.g. in the TLS handshake, retry unencrypted
+                    if self.can_downgrade() {
+                        self.downgrade(&error);
+                        continue;
+                    }
+
+                    // These are connection failures, not lookup failures, that is handled in the resolver layer
+                    return Err(error);
+                }
             }
         }
     }
@@ -171,6 +305,35 @@ where
     pub fn trust_nx_responses(&self) -> bool {
         self.config.trust_negative_responses
     }
+
+    /// The relative weight of this name server, see `ServerOrderingStrategy::WeightedRandom`
+    pub(crate) fn weight(&self) -> u32 {
+        self.config.weight
+    }
+
+    /// Returns true if this name server is demoted after failures, see `ResolverOpts::health`
+    pub(crate) fn is_demoted(&self) -> bool {
+        self.health.is_demoted(Instant::now())
+    }
+
+    /// Returns the health and latency of this name server
+    pub(crate) fn health_stats(&self) -> NameServerHealthStats {
+        self.health.stats(
+            self.config.socket_addr,
+            self.config.protocol,
+            self.stats.srtt(),
+            Instant::now(),
+        )
+    }
+
+    /// Queries the name servers of the root zone, the outcome is recorded in the health stats
+    pub(crate) async fn probe(&self) -> Result<(), ProtoError> {
+        let query = Query::query(Name::root(), RecordType::NS);
+        self.lookup(query, DnsRequestOptions::default())
+            .first_answer()
+            .await
+            .map(drop)
+    }
 }
 
 impl<P> DnsHandle for NameServer<P>
@@ -187,7 +350,7 @@ where
     fn send<R: Into<DnsRequest> + Unpin + Send + 'static>(&self, request: R) -> Self::Response {
         let this = self.clone();
         // if state is failed, return future::err(), unless retry delay expired..
-        Box::pin(once(this.inner_send(request)))
+        Box::pin(once(this.inner_send(request.into())))
     }
 }
 
@@ -242,9 +405,13 @@ where
         protocol: Protocol::Mdns,
         tls_dns_name: None,
         trust_negative_responses,
+        privacy: PrivacyPolicy::default(),
+        weight: 1,
         #[cfg(feature = "dns-over-rustls")]
         tls_config: None,
         bind_addr: None,
+        bind_device: None,
+        http: None,
     };
     GenericNameServer::new_with_provider(config, options, conn_provider)
 }
@@ -263,7 +430,6 @@ mod tests {
     use proto::xfer::{DnsHandle, DnsRequestOptions, FirstAnswer};
 
     use super::*;
-    use crate::config::Protocol;
     use crate::name_server::TokioConnectionProvider;
 
     #[test]
@@ -275,9 +441,13 @@ mod tests {
             protocol: Protocol::Udp,
             tls_dns_name: None,
             trust_negative_responses: false,
+            privacy: PrivacyPolicy::default(),
+            weight: 1,
             #[cfg(feature = "dns-over-rustls")]
             tls_config: None,
             bind_addr: None,
+            bind_device: None,
+            http: None,
         };
         let io_loop = Runtime::new().unwrap();
         let name_server = future::lazy(|_| {
@@ -313,9 +483,13 @@ mod tests {
             protocol: Protocol::Udp,
             tls_dns_name: None,
             trust_negative_responses: false,
+            privacy: PrivacyPolicy::default(),
+            weight: 1,
             #[cfg(feature = "dns-over-rustls")]
             tls_config: None,
             bind_addr: None,
+            bind_device: None,
+            http: None,
         };
         let io_loop = Runtime::new().unwrap();
         let name_server = future::lazy(|_| {
@@ -334,4 +508,48 @@ mod tests {
             }))
             .is_err());
     }
+
+    #[cfg(feature = "dns-over-rustls")]
+    #[test]
+    fn test_privacy_policy() {
+        let config = NameServerConfig {
+            socket_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 853),
+            protocol: Protocol::Tls,
+            tls_dns_name: Some("dns.example.com".to_string()),
+            trust_negative_responses: false,
+            privacy: PrivacyPolicy::Strict,
+            weight: 1,
+            tls_config: None,
+            bind_addr: None,
+            bind_device: None,
+            http: None,
+        };
+        let new_name_server = |privacy| {
+            GenericNameServer::new(
+                NameServerConfig {
+                    privacy,
+                    ..config.clone()
+                },
+                ResolverOpts::default(),
+                TokioConnectionProvider::default(),
+            )
+        };
+
+        let strict = new_name_server(PrivacyPolicy::Strict);
+        assert!(!strict.use_plaintext());
+        assert!(!strict.can_downgrade());
+
+        let off = new_name_server(PrivacyPolicy::Off);
+        assert!(off.use_plaintext());
+        let plaintext = off.plaintext_config();
+        assert_eq!(plaintext.protocol, Protocol::Tcp);
+        assert_eq!(plaintext.socket_addr.port(), 53);
+        assert_eq!(plaintext.tls_dns_name, None);
+
+        let opportunistic = new_name_server(PrivacyPolicy::Opportunistic);
+        assert!(!opportunistic.use_plaintext());
+        assert!(opportunistic.can_downgrade());
+        opportunistic.downgrade(&ProtoError::from("handshake failed"));
+        assert!(opportunistic.use_plaintext());
+    }
 }
@@ -0,0 +1,188 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::net::SocketAddr;
+use std::sync::atomic::{self, AtomicU32, AtomicU64};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use proto::clock::Instant;
+
+use crate::config::{HealthOpts, Protocol};
+
+/// The health of a name server, see `NameServerPool::health_stats`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct NameServerHealthStats {
+    /// The address of the name server
+    pub socket_addr: SocketAddr,
+    /// The protocol used for the name server
+    pub protocol: Protocol,
+    /// The smoothed round-trip time of the queries
+    pub srtt: Duration,
+    /// Queries which received a response
+    pub successes: u64,
+    /// Queries which failed without a response, e.g. with a timeout
+    pub failures: u64,
+    /// Failures since the last response
+    pub consecutive_failures: u32,
+    /// The remaining time the name server is demoted for, `None` if it is not demoted
+    pub demoted_for: Option<Duration>,
+}
+
+/// Passive failure tracking of a name server, with exponential backoff
+#[derive(Debug, Default)]
+pub(crate) struct NameServerHealth {
+    successes: AtomicU64,
+    failures: AtomicU64,
+    consecutive_failures: AtomicU32,
+    demoted_until: Mutex<Option<Instant>>,
+}
+
+impl NameServerHealth {
+    /// Records a response, which promotes a demoted name server
+    pub(crate) fn record_success(&self) {
+        self.successes.fetch_add(1, atomic::Ordering::Relaxed);
+        self.consecutive_failures
+            .store(0, atomic::Ordering::Relaxed);
+        *self
+            .demoted_until
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = None;
+    }
+
+    /// Records a failure, and demotes the name server once the threshold is reached
+    ///
+    /// # Return
+    ///
+    /// The backoff, if the name server was demoted
+    pub(crate) fn record_failure(&self, now: Instant, options: &HealthOpts) -> Option<Duration> {
+        self.failures.fetch_add(1, atomic::Ordering::Relaxed);
+        let consecutive_failures = self
+            .consecutive_failures
+            .fetch_add(1, atomic::Ordering::Relaxed)
+            .saturating_add(1);
+
+        if options.failure_threshold == 0 || consecutive_failures < options.failure_threshold {
+            return None;
+        }
+
+        let doublings = (consecutive_failures - options.failure_threshold).min(31);
+        let backoff = options
+            .backoff
+            .checked_mul(1 << doublings)
+            .map_or(options.max_backoff, |backoff| {
+                backoff.min(options.max_backoff)
+            });
+        *self
+            .demoted_until
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(now + backoff);
+
+        Some(backoff)
+    }
+
+    /// Returns the remaining time of the demotion
+    pub(crate) fn demoted_for(&self, now: Instant) -> Option<Duration> {
+        let demoted_until = *self
+            .demoted_until
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        demoted_until
+            .filter(|until| now < *until)
+            .map(|until| until - now)
+    }
+
+    pub(crate) fn is_demoted(&self, now: Instant) -> bool {
+        self.demoted_for(now).is_some()
+    }
+
+    pub(crate) fn stats(
+        &self,
+        socket_addr: SocketAddr,
+        protocol: Protocol,
+        srtt: Duration,
+        now: Instant,
+    ) -> NameServerHealthStats {
+        NameServerHealthStats {
+            socket_addr,
+            protocol,
+            srtt,
+            successes: self.successes.load(atomic::Ordering::Relaxed),
+            failures: self.failures.load(atomic::Ordering::Relaxed),
+            consecutive_failures: self.consecutive_failures.load(atomic::Ordering::Relaxed),
+            demoted_for: self.demoted_for(now),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let options = HealthOpts {
+            failure_threshold: 2,
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(3),
+            ..HealthOpts::default()
+        };
+        let health = NameServerHealth::default();
+        let now = Instant::now();
+
+        assert_eq!(health.record_failure(now, &options), None);
+        assert!(!health.is_demoted(now));
+
+        // the backoff doubles with each further failure, up to the maximum
+        assert_eq!(
+            health.record_failure(now, &options),
+            Some(Duration::from_secs(1))
+        );
+        assert!(health.is_demoted(now));
+        assert!(!health.is_demoted(now + Duration::from_secs(1)));
+        assert_eq!(
+            health.record_failure(now, &options),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            health.record_failure(now, &options),
+            Some(Duration::from_secs(3))
+        );
+
+        // a response promotes the name server again
+        health.record_success();
+        assert!(!health.is_demoted(now));
+
+        let stats = health.stats(
+            "127.0.0.1:53".parse().unwrap(),
+            Protocol::Udp,
+            Duration::ZERO,
+            now,
+        );
+        assert_eq!(
+            (stats.successes, stats.failures, stats.consecutive_failures),
+            (1, 4, 0)
+        );
+        assert_eq!(stats.demoted_for, None);
+    }
+
+    #[test]
+    fn test_demotion_disabled() {
+        let options = HealthOpts {
+            failure_threshold: 0,
+            ..HealthOpts::default()
+        };
+        let health = NameServerHealth::default();
+        let now = Instant::now();
+
+        for _ in 0..8 {
+            assert_eq!(health.record_failure(now, &options), None);
+        }
+        assert!(!health.is_demoted(now));
+    }
+}
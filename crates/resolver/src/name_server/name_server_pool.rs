@@ -7,7 +7,8 @@
 
 use std::cmp::Ordering;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::{Arc, PoisonError, RwLock};
 use std::task::{Context, Poll};
 use std::time::Duration;
 
@@ -16,9 +17,10 @@ use futures_util::stream::{once, FuturesUnordered, Stream, StreamExt};
 use hickory_proto::error::ProtoErrorKind;
 use smallvec::SmallVec;
 
+use proto::op::ResponseCode;
 use proto::xfer::{DnsHandle, DnsRequest, DnsResponse, FirstAnswer};
 use proto::Time;
-use tracing::debug;
+use tracing::{debug, warn};
 
 use rand::thread_rng as rng;
 use rand::Rng;
@@ -28,23 +30,42 @@ use crate::config::{NameServerConfigGroup, ResolverConfig, ResolverOpts, ServerO
 use crate::name_server;
 use crate::name_server::connection_provider::{ConnectionProvider, GenericConnector};
 use crate::name_server::name_server::NameServer;
-use crate::name_server::RuntimeProvider;
 #[cfg(test)]
 #[cfg(feature = "tokio-runtime")]
 use crate::name_server::TokioRuntimeProvider;
+use crate::name_server::{NameServerHealthStats, RuntimeProvider};
 use crate::proto::error::ProtoError;
 
 /// Abstract interface for mocking purpose
 #[derive(Clone)]
 pub struct NameServerPool<P: ConnectionProvider + Send + 'static> {
-    // TODO: switch to FuturesMutex (Mutex will have some undesirable locking)
-    datagram_conns: Arc<[NameServer<P>]>, /* All NameServers must be the same type */
-    stream_conns: Arc<[NameServer<P>]>,   /* All NameServers must be the same type */
+    /// shared by the clones of the pool, so that the name servers can be replaced
+    conns: Arc<RwLock<PoolConns<P>>>,
     #[cfg(feature = "mdns")]
     mdns_conns: NameServer<P>, /* All NameServers must be the same type */
     options: ResolverOpts,
 }
 
+struct PoolConns<P: ConnectionProvider + Send + 'static> {
+    datagram: Arc<[NameServer<P>]>, /* All NameServers must be the same type */
+    stream: Arc<[NameServer<P>]>,   /* All NameServers must be the same type */
+    /// the number of requests sent, for `ServerOrderingStrategy::RoundRobin`
+    requests: AtomicUsize,
+}
+
+impl<P: ConnectionProvider + Send + 'static> PoolConns<P> {
+    fn shared(
+        datagram: impl Into<Arc<[NameServer<P>]>>,
+        stream: impl Into<Arc<[NameServer<P>]>>,
+    ) -> Arc<RwLock<Self>> {
+        Arc::new(RwLock::new(Self {
+            datagram: datagram.into(),
+            stream: stream.into(),
+            requests: AtomicUsize::new(0),
+        }))
+    }
+}
+
 /// A pool of NameServers
 ///
 /// This is not expected to be used directly, see [crate::AsyncResolver].
@@ -108,8 +129,7 @@ where
             .collect();
 
         Self {
-            datagram_conns: Arc::from(datagram_conns),
-            stream_conns: Arc::from(stream_conns),
+            conns: PoolConns::shared(datagram_conns, stream_conns),
             #[cfg(feature = "mdns")]
             mdns_conns: name_server::mdns_nameserver(options, conn_provider.clone(), false),
             options,
@@ -134,8 +154,7 @@ where
         let stream_conns: Vec<_> = stream.into_iter().map(map_config_to_ns).collect();
 
         Self {
-            datagram_conns: Arc::from(datagram_conns),
-            stream_conns: Arc::from(stream_conns),
+            conns: PoolConns::shared(datagram_conns, stream_conns),
             #[cfg(feature = "mdns")]
             mdns_conns: name_server::mdns_nameserver(*options, conn_provider.clone(), false),
             options,
@@ -150,8 +169,7 @@ where
         stream_conns: Vec<NameServer<P>>,
     ) -> Self {
         Self {
-            datagram_conns: Arc::from(datagram_conns),
-            stream_conns: Arc::from(stream_conns),
+            conns: PoolConns::shared(datagram_conns, stream_conns),
             options,
         }
     }
@@ -165,8 +183,7 @@ where
         mdns_conns: NameServer<P>,
     ) -> Self {
         GenericNameServerPool {
-            datagram_conns: Arc::from(datagram_conns),
-            stream_conns: Arc::from(stream_conns),
+            conns: PoolConns::shared(datagram_conns, stream_conns),
             mdns_conns,
             options,
         }
@@ -181,8 +198,7 @@ where
         stream_conns: Arc<[NameServer<P>]>,
     ) -> Self {
         Self {
-            datagram_conns,
-            stream_conns,
+            conns: PoolConns::shared(datagram_conns, stream_conns),
             options,
         }
     }
@@ -196,17 +212,65 @@ where
         mdns_conns: NameServer<P>,
     ) -> Self {
         GenericNameServerPool {
-            datagram_conns,
-            stream_conns,
+            conns: PoolConns::shared(datagram_conns, stream_conns),
             mdns_conns,
             options: *options,
         }
     }
 
+    /// Replaces the name servers of this pool and its clones by those of the other pool
+    ///
+    /// Requests which were already sent are completed by the previous name servers.
+    pub fn replace_name_servers(&self, other: &Self) {
+        let (datagram, stream) = {
+            let other = other.conns.read().unwrap_or_else(PoisonError::into_inner);
+            (Arc::clone(&other.datagram), Arc::clone(&other.stream))
+        };
+
+        let mut conns = self.conns.write().unwrap_or_else(PoisonError::into_inner);
+        conns.datagram = datagram;
+        conns.stream = stream;
+    }
+
+    /// Returns the health and latency of the name servers, the datagram ones first
+    pub fn health_stats(&self) -> Vec<NameServerHealthStats> {
+        let conns = self.conns.read().unwrap_or_else(PoisonError::into_inner);
+        conns
+            .datagram
+            .iter()
+            .chain(conns.stream.iter())
+            .map(NameServer::health_stats)
+            .collect()
+    }
+
+    /// Probes all name servers concurrently, see `HealthOpts::probe_interval`
+    ///
+    /// A name server which answers is promoted again, one which fails is demoted like after a
+    ///  failed query.
+    pub async fn probe(&self) {
+        let conns: Vec<NameServer<P>> = {
+            let conns = self.conns.read().unwrap_or_else(PoisonError::into_inner);
+            conns
+                .datagram
+                .iter()
+                .chain(conns.stream.iter())
+                .cloned()
+                .collect()
+        };
+
+        let probes = conns.iter().map(|conn| async move {
+            if let Err(e) = conn.probe().await {
+                debug!("health probe failed: {}", e);
+            }
+        });
+        futures_util::future::join_all(probes).await;
+    }
+
     async fn try_send(
         opts: ResolverOpts,
         conns: Arc<[NameServer<P>]>,
         request: DnsRequest,
+        request_count: usize,
     ) -> Result<DnsResponse, ProtoError> {
         let mut conns: Vec<NameServer<P>> = conns.to_vec();
 
@@ -216,6 +280,20 @@ where
             //   this reorders the inner set
             ServerOrderingStrategy::QueryStatistics => conns.sort_unstable(),
             ServerOrderingStrategy::UserProvidedOrder => {}
+            ServerOrderingStrategy::RoundRobin => {
+                if !conns.is_empty() {
+                    let next = request_count % conns.len();
+                    conns.rotate_left(next);
+                }
+            }
+            ServerOrderingStrategy::WeightedRandom => conns = weighted_random_order(conns),
+        }
+        // demoted name servers are only tried after all others, in the same order
+        conns.sort_by_key(NameServer::is_demoted);
+        // a retry of the request on a different name server, see RetryPolicy
+        if !conns.is_empty() {
+            let skip = request.options().skip_servers % conns.len();
+            conns.rotate_left(skip);
         }
         let request_loop = request.clone();
 
@@ -232,8 +310,14 @@ where
     fn send<R: Into<DnsRequest>>(&self, request: R) -> Self::Response {
         let opts = self.options.clone();
         let request = request.into();
-        let datagram_conns = Arc::clone(&self.datagram_conns);
-        let stream_conns = Arc::clone(&self.stream_conns);
+        let (datagram_conns, stream_conns, request_count) = {
+            let conns = self.conns.read().unwrap_or_else(PoisonError::into_inner);
+            (
+                Arc::clone(&conns.datagram),
+                Arc::clone(&conns.stream),
+                conns.requests.fetch_add(1, AtomicOrdering::Relaxed),
+            )
+        };
         // TODO: remove this clone, return the Message in the error?
         let tcp_message = request.clone();
 
@@ -259,11 +343,15 @@ where
 
             // First try the UDP connections
             let udp_res: Result<DnsResponse, ProtoError> =
-                match Self::try_send(opts.clone(), datagram_conns, request).await {
+                match Self::try_send(opts.clone(), datagram_conns, request, request_count).await {
                     Ok(response) if response.truncated() => {
                         debug!("truncated response received, retrying over TCP");
                         Ok(response)
                     }
+                    Err(e) if e.is_conflicting_responses() => {
+                        debug!("conflicting responses over UDP, confirming over TCP: {}", e);
+                        Err(e)
+                    }
                     Err(e) if opts.try_tcp_on_error || e.is_no_connections() => {
                         debug!("error from UDP, retrying over TCP: {}", e);
                         Err(e)
@@ -278,7 +366,7 @@ where
 
             // Try query over TCP, as response to query over UDP was either truncated or was an
             // error.
-            let tcp_res = Self::try_send(opts, stream_conns, tcp_message).await;
+            let tcp_res = Self::try_send(opts, stream_conns, tcp_message, request_count).await;
 
             let tcp_err = match tcp_res {
                 res @ Ok(..) => return res.map_err(ProtoError::from),
@@ -299,6 +387,30 @@ where
     }
 }
 
+/// Orders the name servers randomly, with a name server of twice the weight twice as likely to be first
+///
+/// Each name server gets the key `u^(1/weight)` for a uniformly random `u`, the highest key is
+///  tried first. Name servers with a weight of `0` are only tried after all others.
+fn weighted_random_order<P>(conns: Vec<NameServer<P>>) -> Vec<NameServer<P>>
+where
+    P: ConnectionProvider + 'static,
+{
+    let mut rng = rng();
+    let mut keyed: Vec<(f64, NameServer<P>)> = conns
+        .into_iter()
+        .map(|conn| {
+            let key = match conn.weight() {
+                0 => -1.0,
+                weight => rng.gen::<f64>().powf(1.0 / f64::from(weight)),
+            };
+            (key, conn)
+        })
+        .collect();
+
+    keyed.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    keyed.into_iter().map(|(_, conn)| conn).collect()
+}
+
 // TODO: we should be able to have a self-referential future here with Pin and not require cloned conns
 /// An async function that will loop over all the conns with a max parallel request count of ops.num_concurrent_req
 async fn parallel_conn_loop<P>(
@@ -324,6 +436,10 @@ where
     let mut backoff = Duration::from_millis(20);
     let mut busy = SmallVec::<[NameServer<P>; 2]>::new();
 
+    // the first NXDOMAIN, while it is not yet confirmed by another name server,
+    //  see DnsRequestOptions::confirm_nxdomain
+    let mut unconfirmed_nx: Option<ProtoError> = None;
+
     loop {
         let request_cont = request.clone();
 
@@ -357,6 +473,14 @@ where
                 backoff *= 2;
                 continue;
             }
+
+            if let Some(mut nx) = unconfirmed_nx {
+                debug!("NXDOMAIN for {:?} not confirmed", request.queries());
+                if let ProtoErrorKind::NoRecordsFound { trusted, .. } = nx.kind.as_mut() {
+                    *trusted = false;
+                }
+                return Err(nx);
+            }
             return Err(err);
         }
 
@@ -371,17 +495,44 @@ where
 
         while let Some(result) = requests.next().await {
             let (conn, e) = match result {
-                Ok(sent) => return Ok(sent),
+                Ok(sent) => {
+                    if unconfirmed_nx.is_some() {
+                        warn!(
+                            "NXDOMAIN for {:?} contradicted by another name server",
+                            request.queries()
+                        );
+                    }
+                    return Ok(sent);
+                }
                 Err((conn, e)) => (conn, e),
             };
 
             match e.kind() {
-                ProtoErrorKind::NoRecordsFound { trusted, .. } if *trusted => {
-                    return Err(e);
+                ProtoErrorKind::NoRecordsFound {
+                    trusted,
+                    response_code,
+                    ..
+                } if *trusted => {
+                    let confirm = request.options().confirm_nxdomain
+                        && *response_code == ResponseCode::NXDomain
+                        && unconfirmed_nx.is_none();
+                    if !confirm {
+                        return Err(e);
+                    }
+
+                    debug!(
+                        "NXDOMAIN for {:?}, confirming with another name server",
+                        request.queries()
+                    );
+                    unconfirmed_nx = Some(e);
                 }
                 _ if e.is_busy() => {
                     busy.push(conn);
                 }
+                // possibly spoofed, the query needs to be confirmed over TCP
+                _ if e.is_conflicting_responses() => {
+                    return Err(e);
+                }
                 _ if err.cmp_specificity(&e) == Ordering::Less => {
                     err = e;
                 }
@@ -483,8 +634,8 @@ mod tests {
     use proto::xfer::{DnsHandle, DnsRequestOptions};
 
     use super::*;
-    use crate::config::NameServerConfig;
     use crate::config::Protocol;
+    use crate::config::{NameServerConfig, PrivacyPolicy};
     use crate::name_server::TokioRuntimeProvider;
     use crate::name_server::{GenericNameServer, TokioConnectionProvider};
 
@@ -498,9 +649,13 @@ mod tests {
             protocol: Protocol::Udp,
             tls_dns_name: None,
             trust_negative_responses: false,
+            privacy: PrivacyPolicy::default(),
+            weight: 1,
             #[cfg(feature = "dns-over-rustls")]
             tls_config: None,
             bind_addr: None,
+            bind_device: None,
+            http: None,
         };
 
         let config2 = NameServerConfig {
@@ -508,9 +663,13 @@ mod tests {
             protocol: Protocol::Udp,
             tls_dns_name: None,
             trust_negative_responses: false,
+            privacy: PrivacyPolicy::default(),
+            weight: 1,
             #[cfg(feature = "dns-over-rustls")]
             tls_config: None,
             bind_addr: None,
+            bind_device: None,
+            http: None,
         };
 
         let mut resolver_config = ResolverConfig::new();
@@ -570,9 +729,13 @@ mod tests {
             protocol: Protocol::Tcp,
             tls_dns_name: None,
             trust_negative_responses: false,
+            privacy: PrivacyPolicy::default(),
+            weight: 1,
             #[cfg(feature = "dns-over-rustls")]
             tls_config: None,
             bind_addr: None,
+            bind_device: None,
+            http: None,
         };
 
         let opts = ResolverOpts {
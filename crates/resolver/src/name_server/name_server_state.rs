@@ -8,9 +8,9 @@
 use std::cmp::Ordering;
 use std::sync::atomic::{self, AtomicU8};
 use std::sync::Arc;
-use std::time::Instant;
 
 use futures_util::lock::Mutex;
+use proto::clock::Instant;
 use proto::op::Edns;
 
 pub(crate) struct NameServerState {
@@ -15,7 +15,9 @@ use parking_lot::Mutex;
 use rand::Rng as _;
 
 #[cfg(not(test))]
-use std::time::{Duration, Instant};
+use proto::clock::Instant;
+#[cfg(not(test))]
+use std::time::Duration;
 #[cfg(test)]
 use tokio::time::{Duration, Instant};
 
@@ -119,7 +121,7 @@ impl NameServerStats {
     /// Returns the raw SRTT value.
     ///
     /// Prefer to use `decayed_srtt` when ordering name servers.
-    fn srtt(&self) -> Duration {
+    pub(crate) fn srtt(&self) -> Duration {
         Duration::from_micros(u64::from(
             self.srtt_microseconds.load(atomic::Ordering::Acquire),
         ))
@@ -0,0 +1,396 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Tunnels for the connections to the name servers through SOCKS5 and HTTP CONNECT proxies
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::io;
+use std::net::SocketAddr;
+
+use futures_util::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use once_cell::sync::Lazy;
+use proto::op::Query;
+
+use crate::config::{ProxyAuth, ProxyConfig, ProxyProtocol, StreamIsolation};
+
+/// The username for isolated streams, if none is configured
+const ISOLATION_USERNAME: &str = "hickory-dns";
+
+/// The maximum length of the response header of an HTTP proxy
+const MAX_HTTP_HEADER_LEN: usize = 8192;
+
+/// Opens a tunnel to the target through the proxy, over a stream connected to the proxy
+///
+/// Once this returns, the stream carries the connection to the target.
+pub(crate) async fn connect<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    proxy: &ProxyConfig,
+    target: SocketAddr,
+) -> io::Result<()> {
+    match proxy.protocol {
+        ProxyProtocol::Socks5 => socks5_connect(stream, proxy.auth.as_ref(), target).await,
+        ProxyProtocol::HttpConnect => http_connect(stream, proxy.auth.as_ref(), target).await,
+    }
+}
+
+/// Returns the proxy configuration with the credentials isolating the query, or `None` if the
+///  query shares the connections with the other queries, see [`StreamIsolation`]
+pub(crate) fn isolate(proxy: &ProxyConfig, query: Option<&Query>) -> Option<ProxyConfig> {
+    let token = match (proxy.isolation, query) {
+        (StreamIsolation::None, _) => return None,
+        (StreamIsolation::PerName, Some(query)) => {
+            // keyed, so that the proxy can not tell the names from the credentials
+            static KEY: Lazy<RandomState> = Lazy::new(RandomState::new);
+            let mut hasher = KEY.build_hasher();
+            query.name().to_lowercase().hash(&mut hasher);
+            hasher.finish()
+        }
+        (StreamIsolation::PerQuery, _) | (StreamIsolation::PerName, None) => rand::random(),
+    };
+
+    let username = match proxy.auth {
+        Some(ref auth) => auth.username.clone(),
+        None => ISOLATION_USERNAME.to_string(),
+    };
+    Some(proxy.clone().with_auth(username, format!("{token:016x}")))
+}
+
+mod socks5 {
+    pub(super) const VERSION: u8 = 5;
+    pub(super) const NO_AUTH: u8 = 0;
+    pub(super) const USERNAME_PASSWORD: u8 = 2;
+    pub(super) const NO_ACCEPTABLE_METHODS: u8 = 0xFF;
+    pub(super) const USERNAME_PASSWORD_VERSION: u8 = 1;
+    pub(super) const CONNECT: u8 = 1;
+    pub(super) const IPV4: u8 = 1;
+    pub(super) const DOMAIN_NAME: u8 = 3;
+    pub(super) const IPV6: u8 = 4;
+    pub(super) const SUCCEEDED: u8 = 0;
+}
+
+/// The handshake of [RFC 1928](https://datatracker.ietf.org/doc/html/rfc1928), with the
+///  authentication of [RFC 1929](https://datatracker.ietf.org/doc/html/rfc1929)
+async fn socks5_connect<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    auth: Option<&ProxyAuth>,
+    target: SocketAddr,
+) -> io::Result<()> {
+    let greeting: &[u8] = match auth {
+        Some(_) => &[
+            socks5::VERSION,
+            2,
+            socks5::NO_AUTH,
+            socks5::USERNAME_PASSWORD,
+        ],
+        None => &[socks5::VERSION, 1, socks5::NO_AUTH],
+    };
+    stream.write_all(greeting).await?;
+
+    let mut choice = [0_u8; 2];
+    stream.read_exact(&mut choice).await?;
+    if choice[0] != socks5::VERSION {
+        return Err(proxy_error(format!(
+            "unsupported SOCKS version: {}",
+            choice[0]
+        )));
+    }
+
+    match (choice[1], auth) {
+        (socks5::NO_AUTH, _) => (),
+        (socks5::USERNAME_PASSWORD, Some(auth)) => {
+            let username = auth.username.as_bytes();
+            let password = auth.password.as_bytes();
+            let (Ok(username_len), Ok(password_len)) =
+                (u8::try_from(username.len()), u8::try_from(password.len()))
+            else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "the SOCKS username and password are limited to 255 bytes",
+                ));
+            };
+
+            let mut request = Vec::with_capacity(3 + username.len() + password.len());
+            request.push(socks5::USERNAME_PASSWORD_VERSION);
+            request.push(username_len);
+            request.extend_from_slice(username);
+            request.push(password_len);
+            request.extend_from_slice(password);
+            stream.write_all(&request).await?;
+
+            let mut status = [0_u8; 2];
+            stream.read_exact(&mut status).await?;
+            if status[1] != socks5::SUCCEEDED {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    "the SOCKS proxy rejected the username and password",
+                ));
+            }
+        }
+        (socks5::NO_ACCEPTABLE_METHODS, _) => {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "the SOCKS proxy requires an unsupported authentication",
+            ))
+        }
+        (method, _) => {
+            return Err(proxy_error(format!(
+                "the SOCKS proxy chose an authentication which was not offered: {method}"
+            )))
+        }
+    }
+
+    let mut request = vec![socks5::VERSION, socks5::CONNECT, 0];
+    match target {
+        SocketAddr::V4(addr) => {
+            request.push(socks5::IPV4);
+            request.extend_from_slice(&addr.ip().octets());
+        }
+        SocketAddr::V6(addr) => {
+            request.push(socks5::IPV6);
+            request.extend_from_slice(&addr.ip().octets());
+        }
+    }
+    request.extend_from_slice(&target.port().to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply = [0_u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[1] != socks5::SUCCEEDED {
+        return Err(io::Error::new(
+            io::ErrorKind::ConnectionRefused,
+            format!(
+                "the SOCKS proxy failed to connect to {target}: {}",
+                socks5_reply_reason(reply[1])
+            ),
+        ));
+    }
+
+    // the address the proxy connected from, which is not needed
+    let bound_len = match reply[3] {
+        socks5::IPV4 => 4,
+        socks5::IPV6 => 16,
+        socks5::DOMAIN_NAME => {
+            let mut len = [0_u8; 1];
+            stream.read_exact(&mut len).await?;
+            usize::from(len[0])
+        }
+        address_type => {
+            return Err(proxy_error(format!(
+                "unsupported SOCKS address type: {address_type}"
+            )))
+        }
+    };
+    let mut bound = vec![0_u8; bound_len + 2];
+    stream.read_exact(&mut bound).await
+}
+
+fn socks5_reply_reason(reply: u8) -> &'static str {
+    match reply {
+        1 => "general SOCKS server failure",
+        2 => "connection not allowed by ruleset",
+        3 => "network unreachable",
+        4 => "host unreachable",
+        5 => "connection refused",
+        6 => "TTL expired",
+        7 => "command not supported",
+        8 => "address type not supported",
+        _ => "unknown error",
+    }
+}
+
+/// Opens a tunnel with the CONNECT method of HTTP/1.1
+async fn http_connect<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    auth: Option<&ProxyAuth>,
+    target: SocketAddr,
+) -> io::Result<()> {
+    let mut request = format!("CONNECT {target} HTTP/1.1\r\nHost: {target}\r\n");
+    if let Some(auth) = auth {
+        let credentials = format!("{}:{}", auth.username, auth.password);
+        request.push_str("Proxy-Authorization: Basic ");
+        request.push_str(&data_encoding::BASE64.encode(credentials.as_bytes()));
+        request.push_str("\r\n");
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+
+    // read byte by byte, anything after the header already belongs to the tunnel
+    let mut header = Vec::new();
+    while !header.ends_with(b"\r\n\r\n") {
+        if header.len() >= MAX_HTTP_HEADER_LEN {
+            return Err(proxy_error("the response of the HTTP proxy is too long"));
+        }
+
+        let mut byte = [0_u8; 1];
+        stream.read_exact(&mut byte).await?;
+        header.push(byte[0]);
+    }
+
+    let header = String::from_utf8_lossy(&header);
+    let status_line = header.lines().next().unwrap_or_default();
+    let mut parts = status_line.splitn(3, ' ');
+    let (Some(version), Some(status)) = (parts.next(), parts.next()) else {
+        return Err(proxy_error(format!(
+            "malformed response of the HTTP proxy: {status_line}"
+        )));
+    };
+    if !version.starts_with("HTTP/1.") {
+        return Err(proxy_error(format!(
+            "malformed response of the HTTP proxy: {status_line}"
+        )));
+    }
+
+    match status {
+        "200" => Ok(()),
+        "407" => Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("the HTTP proxy requires authentication: {status_line}"),
+        )),
+        _ => Err(io::Error::new(
+            io::ErrorKind::ConnectionRefused,
+            format!("the HTTP proxy failed to connect to {target}: {status_line}"),
+        )),
+    }
+}
+
+fn proxy_error(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+#[cfg(test)]
+#[cfg(feature = "tokio-runtime")]
+mod tests {
+    use proto::iocompat::AsyncIoTokioAsStd;
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+    use tokio::net::{TcpListener, TcpStream};
+
+    use super::*;
+
+    /// Accepts a single connection, expects the request and sends the response
+    async fn proxy_server(exchanges: Vec<(Vec<u8>, Vec<u8>)>) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            for (request, response) in exchanges {
+                let mut received = vec![0; request.len()];
+                stream.read_exact(&mut received).await.unwrap();
+                assert_eq!(received, request);
+                stream.write_all(&response).await.unwrap();
+            }
+
+            // the tunnel to the target echoes, if it was opened
+            let mut received = [0; 4];
+            if stream.read_exact(&mut received).await.is_ok() {
+                stream.write_all(&received).await.unwrap();
+            }
+        });
+
+        addr
+    }
+
+    async fn connect_through(proxy: ProxyConfig, target: SocketAddr) -> io::Result<()> {
+        let mut stream = AsyncIoTokioAsStd(TcpStream::connect(proxy.addr).await.unwrap());
+        connect(&mut stream, &proxy, target).await?;
+
+        stream.write_all(b"ping").await?;
+        let mut echoed = [0; 4];
+        stream.read_exact(&mut echoed).await?;
+        assert_eq!(&echoed, b"ping");
+        Ok(())
+    }
+
+    #[test]
+    fn test_isolate() {
+        let proxy = ProxyConfig::socks5(SocketAddr::from(([127, 0, 0, 1], 9050)));
+        let query = |name| Query::query(name, proto::rr::RecordType::A);
+        let www = query(proto::rr::Name::from_ascii("www.example.com.").unwrap());
+        let mail = query(proto::rr::Name::from_ascii("mail.example.com.").unwrap());
+        let www_upper = query(proto::rr::Name::from_ascii("WWW.example.com.").unwrap());
+
+        assert!(isolate(&proxy, Some(&www)).is_none());
+
+        let per_name = proxy.clone().with_isolation(StreamIsolation::PerName);
+        let auth = |query| isolate(&per_name, Some(query)).unwrap().auth.unwrap();
+        assert_eq!(auth(&www), auth(&www_upper));
+        assert_ne!(auth(&www).password, auth(&mail).password);
+        assert_eq!(auth(&www).username, ISOLATION_USERNAME);
+
+        let per_query = proxy
+            .with_auth("tor", "ignored")
+            .with_isolation(StreamIsolation::PerQuery);
+        let first = isolate(&per_query, Some(&www)).unwrap().auth.unwrap();
+        let second = isolate(&per_query, Some(&www)).unwrap().auth.unwrap();
+        assert_ne!(first.password, second.password);
+        assert_eq!(first.username, "tor");
+    }
+
+    #[tokio::test]
+    async fn test_socks5_with_auth() {
+        let target = SocketAddr::from(([192, 0, 2, 1], 853));
+        let addr = proxy_server(vec![
+            (vec![5, 2, 0, 2], vec![5, 2]),
+            (b"\x01\x04user\x06secret".to_vec(), vec![1, 0]),
+            (
+                vec![5, 1, 0, 1, 192, 0, 2, 1, 3, 85],
+                vec![5, 0, 0, 1, 127, 0, 0, 1, 0x1F, 0x90],
+            ),
+        ])
+        .await;
+
+        let proxy = ProxyConfig::socks5(addr).with_auth("user", "secret");
+        connect_through(proxy, target).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_socks5_connection_refused() {
+        let target = SocketAddr::from(([192, 0, 2, 1], 853));
+        let addr = proxy_server(vec![
+            (vec![5, 1, 0], vec![5, 0]),
+            (
+                vec![5, 1, 0, 1, 192, 0, 2, 1, 3, 85],
+                vec![5, 5, 0, 1, 0, 0, 0, 0, 0, 0],
+            ),
+        ])
+        .await;
+
+        let err = connect_through(ProxyConfig::socks5(addr), target)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+    }
+
+    #[tokio::test]
+    async fn test_http_connect_with_auth() {
+        let target = SocketAddr::from(([0x2001, 0xdb8, 0, 0, 0, 0, 0, 1], 443));
+        let addr = proxy_server(vec![(
+            b"CONNECT [2001:db8::1]:443 HTTP/1.1\r\nHost: [2001:db8::1]:443\r\nProxy-Authorization: Basic dXNlcjpzZWNyZXQ=\r\n\r\n".to_vec(),
+            b"HTTP/1.1 200 Connection established\r\n\r\n".to_vec(),
+        )])
+        .await;
+
+        let proxy = ProxyConfig::http_connect(addr).with_auth("user", "secret");
+        connect_through(proxy, target).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_http_connect_requires_auth() {
+        let target = SocketAddr::from(([192, 0, 2, 1], 443));
+        let addr = proxy_server(vec![(
+            b"CONNECT 192.0.2.1:443 HTTP/1.1\r\nHost: 192.0.2.1:443\r\n\r\n".to_vec(),
+            b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n".to_vec(),
+        )])
+        .await;
+
+        let err = connect_through(ProxyConfig::http_connect(addr), target)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+    }
+}
@@ -7,7 +7,7 @@
 
 //! Structs for creating and using a Resolver
 use std::io;
-use std::net::IpAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Mutex;
 
 use proto::rr::domain::TryParseIp;
@@ -15,7 +15,8 @@ use proto::rr::IntoName;
 use proto::rr::RecordType;
 use tokio::runtime::{self, Runtime};
 
-use crate::config::{ResolverConfig, ResolverOpts};
+use crate::addrinfo::AddrInfoHints;
+use crate::config::{EffectiveResolverConfig, ResolverConfig, ResolverOpts};
 use crate::error::*;
 use crate::lookup;
 use crate::lookup::Lookup;
@@ -130,6 +131,12 @@ impl Resolver {
         self.async_resolver.options()
     }
 
+    /// Returns the configuration and options this resolver is running with, see
+    ///  `AsyncResolver::effective_opts`
+    pub fn effective_opts(&self) -> EffectiveResolverConfig {
+        self.async_resolver.effective_opts()
+    }
+
     /// Generic lookup for any RecordType
     ///
     /// *WARNING* This interface may change in the future, please use [`Self::lookup_ip`] or another variant for more stable interfaces.
@@ -143,6 +150,22 @@ impl Resolver {
         self.runtime.lock()?.block_on(lookup)
     }
 
+    /// Looks up many names concurrently
+    ///
+    /// See [`AsyncResolver::lookup_batch`](crate::AsyncResolver::lookup_batch).
+    ///
+    /// # Arguments
+    ///
+    /// * `queries` - the names and types of the records to lookup
+    pub fn lookup_batch<I, N>(&self, queries: I) -> ResolveResult<Vec<ResolveResult<Lookup>>>
+    where
+        I: IntoIterator<Item = (N, RecordType)>,
+        N: IntoName,
+    {
+        let lookup = self.async_resolver.lookup_batch(queries);
+        Ok(self.runtime.lock()?.block_on(lookup))
+    }
+
     /// Performs a dual-stack DNS lookup for the IP for the given hostname.
     ///
     /// See the configuration and options parameters for controlling the way in which A(Ipv4) and AAAA(Ipv6) lookups will be performed. For the least expensive query a fully-qualified-domain-name, FQDN, which ends in a final `.`, e.g. `www.example.com.`, will only issue one query. Anything else will always incur the cost of querying the `ResolverConfig::domain` and `ResolverConfig::search`.
@@ -155,6 +178,27 @@ impl Resolver {
         self.runtime.lock()?.block_on(lookup)
     }
 
+    /// Looks up the socket addresses of a host and service, like `getaddrinfo`
+    ///
+    /// See [`AsyncResolver::lookup_host_ordered`](crate::AsyncResolver::lookup_host_ordered).
+    ///
+    /// # Arguments
+    ///
+    /// * `host` - string hostname, if this is an invalid hostname, an error will be returned.
+    /// * `service` - the port number or the name of the service
+    /// * `hints` - the address families to return and how the service is resolved
+    pub fn lookup_host_ordered<N: IntoName + TryParseIp>(
+        &self,
+        host: N,
+        service: Option<&str>,
+        hints: AddrInfoHints,
+    ) -> ResolveResult<Vec<SocketAddr>> {
+        let lookup = self
+            .async_resolver
+            .lookup_host_ordered(host, service, hints);
+        self.runtime.lock()?.block_on(lookup)
+    }
+
     lookup_fn!(reverse_lookup, lookup::ReverseLookup, IpAddr);
     lookup_fn!(ipv4_lookup, lookup::Ipv4Lookup);
     lookup_fn!(ipv6_lookup, lookup::Ipv6Lookup);
@@ -162,6 +206,7 @@ impl Resolver {
     lookup_fn!(ns_lookup, lookup::NsLookup);
     lookup_fn!(soa_lookup, lookup::SoaLookup);
     lookup_fn!(srv_lookup, lookup::SrvLookup);
+    #[cfg(feature = "rdata-tlsa")]
     lookup_fn!(tlsa_lookup, lookup::TlsaLookup);
     lookup_fn!(txt_lookup, lookup::TxtLookup);
 }
@@ -0,0 +1,125 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Names of the PTR records of the addresses in a network, see `AsyncResolver::reverse_lookup_range`
+
+use std::net::{IpAddr, Ipv4Addr};
+
+use ipnet::{IpAddrRange, IpNet, Ipv4AddrRange, Ipv6AddrRange};
+
+use crate::Name;
+
+/// How the names of the PTR records of a network are formed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ReverseNaming {
+    /// The names of RFC 1035 and RFC 3596, e.g. `1.2.0.192.in-addr.arpa.`
+    ///
+    /// Classless delegations, which the parent zone aliases with CNAME records, are followed.
+    Standard,
+    /// The names of [RFC 2317](https://tools.ietf.org/html/rfc2317) classless delegations, e.g.
+    ///  `1.0/26.2.0.192.in-addr.arpa.`, for IPv4 networks with prefixes from /25 to /31
+    ///
+    /// This queries the delegated zone directly, the standard names are used for other networks.
+    Classless,
+}
+
+/// Returns each address in the network, including the network and broadcast addresses, with the
+///  name of its PTR record
+pub fn reverse_names(
+    net: IpNet,
+    naming: ReverseNaming,
+) -> impl Iterator<Item = (IpAddr, Name)> + Send + 'static {
+    let addrs = match net {
+        IpNet::V4(net) => IpAddrRange::from(Ipv4AddrRange::new(net.network(), net.broadcast())),
+        IpNet::V6(net) => IpAddrRange::from(Ipv6AddrRange::new(net.network(), net.broadcast())),
+    };
+    let prefix_len = net.prefix_len();
+
+    addrs.map(move |addr| {
+        let name = match (naming, addr) {
+            (ReverseNaming::Classless, IpAddr::V4(addr)) => {
+                classless_reverse_name(addr, prefix_len).unwrap_or_else(|| Name::from(addr))
+            }
+            _ => Name::from(addr),
+        };
+        (addr, name)
+    })
+}
+
+/// The name of the PTR record of the address in an RFC 2317 classless delegation
+///
+/// The zone of the delegation is named after the first address and the prefix length of the
+///  network, e.g. `1.0/26.2.0.192.in-addr.arpa.` for `192.0.2.1` in `192.0.2.0/26`.
+///
+/// # Returns
+///
+/// `None` unless the prefix length is from 25 to 31
+pub fn classless_reverse_name(addr: Ipv4Addr, prefix_len: u8) -> Option<Name> {
+    if !(25..=31).contains(&prefix_len) {
+        return None;
+    }
+
+    let [a, b, c, d] = addr.octets();
+    let first = d & (u8::MAX << (32 - prefix_len));
+    Name::from_labels(vec![
+        d.to_string().into_bytes(),
+        format!("{first}/{prefix_len}").into_bytes(),
+        c.to_string().into_bytes(),
+        b.to_string().into_bytes(),
+        a.to_string().into_bytes(),
+        b"in-addr".to_vec(),
+        b"arpa".to_vec(),
+    ])
+    .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classless_reverse_name() {
+        let addr = Ipv4Addr::new(192, 0, 2, 65);
+        assert_eq!(
+            classless_reverse_name(addr, 26).unwrap(),
+            Name::from_labels(vec![
+                &b"65"[..],
+                b"64/26",
+                b"2",
+                b"0",
+                b"192",
+                b"in-addr",
+                b"arpa"
+            ])
+            .unwrap()
+        );
+        assert_eq!(classless_reverse_name(addr, 24), None);
+        assert_eq!(classless_reverse_name(addr, 32), None);
+    }
+
+    #[test]
+    fn test_reverse_names() {
+        let net = "192.0.2.128/30".parse().unwrap();
+        let names = reverse_names(net, ReverseNaming::Standard).collect::<Vec<_>>();
+        assert_eq!(names.len(), 4);
+        assert_eq!(names[0].0, IpAddr::from([192, 0, 2, 128]));
+        assert_eq!(names[3].1, Name::from(Ipv4Addr::new(192, 0, 2, 131)));
+
+        let (_, name) = reverse_names(net, ReverseNaming::Classless).nth(1).unwrap();
+        assert_eq!(
+            name,
+            classless_reverse_name([192, 0, 2, 129].into(), 30).unwrap()
+        );
+
+        // IPv6 networks always use the standard names
+        let net = "2001:db8::/127".parse().unwrap();
+        let names = reverse_names(net, ReverseNaming::Classless).collect::<Vec<_>>();
+        assert_eq!(names.len(), 2);
+        assert_eq!(names[1].1, Name::from(names[1].0));
+    }
+}
@@ -29,3 +29,19 @@ mod windows;
 #[cfg(feature = "system-config")]
 #[cfg_attr(docsrs, doc(cfg(all(feature = "system-config", windows))))]
 pub use self::windows::read_system_conf;
+
+#[cfg(any(unix, target_os = "windows"))]
+#[cfg(feature = "system-config")]
+mod watcher;
+
+#[cfg(any(unix, target_os = "windows"))]
+#[cfg(feature = "system-config")]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(all(feature = "system-config", any(unix, target_os = "windows"))))
+)]
+pub use self::watcher::SystemConfWatcher;
+
+#[cfg(unix)]
+#[cfg(feature = "system-config")]
+pub(crate) use self::watcher::ResolvConfWatch;
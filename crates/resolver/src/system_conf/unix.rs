@@ -14,6 +14,8 @@
 use std::fs::File;
 use std::io;
 use std::io::Read;
+#[cfg(any(target_os = "macos", test))]
+use std::net::IpAddr;
 use std::net::SocketAddr;
 use std::path::Path;
 use std::str::FromStr;
@@ -21,17 +23,197 @@ use std::time::Duration;
 
 use resolv_conf;
 
-use crate::config::{NameServerConfig, Protocol, ResolverConfig, ResolverOpts};
+use crate::config::{NameServerConfig, PrivacyPolicy, Protocol, ResolverConfig, ResolverOpts};
+#[cfg(any(target_os = "macos", test))]
+use crate::config::{NameServerConfigGroup, ScopedResolverConfig};
 use crate::error::ResolveResult;
 use crate::proto::rr::Name;
 
 const DEFAULT_PORT: u16 = 53;
 
+#[cfg(not(target_os = "macos"))]
 pub fn read_system_conf() -> ResolveResult<(ResolverConfig, ResolverOpts)> {
     read_resolv_conf("/etc/resolv.conf")
 }
 
-fn read_resolv_conf<P: AsRef<Path>>(path: P) -> ResolveResult<(ResolverConfig, ResolverOpts)> {
+/// Reads `/etc/resolv.conf`, with the scoped resolvers of macOS
+#[cfg(target_os = "macos")]
+pub fn read_system_conf() -> ResolveResult<(ResolverConfig, ResolverOpts)> {
+    let (mut config, options) = read_resolv_conf("/etc/resolv.conf")?;
+    for scoped in read_scoped_resolvers() {
+        config.add_scoped(scoped);
+    }
+    Ok((config, options))
+}
+
+/// The scoped resolvers of SystemConfiguration, as reported by `scutil --dns`, and of the files
+///  in `/etc/resolver` which are not known to it yet
+#[cfg(target_os = "macos")]
+fn read_scoped_resolvers() -> Vec<ScopedResolverConfig> {
+    use std::process::Command;
+
+    let mut scoped = match Command::new("/usr/sbin/scutil").arg("--dns").output() {
+        Ok(output) if output.status.success() => {
+            parse_scutil_dns(&String::from_utf8_lossy(&output.stdout))
+        }
+        Ok(output) => {
+            tracing::warn!("scutil --dns failed: {}", output.status);
+            vec![]
+        }
+        Err(e) => {
+            tracing::warn!("could not run scutil --dns: {}", e);
+            vec![]
+        }
+    };
+
+    let Ok(dir) = std::fs::read_dir("/etc/resolver") else {
+        return scoped;
+    };
+    for entry in dir.flatten() {
+        let Some(domain) = entry.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+        let resolver = std::fs::read_to_string(entry.path())
+            .map_err(Into::into)
+            .and_then(|data| parse_resolver_file(&domain, &data));
+
+        match resolver {
+            Ok(resolver) if !scoped.iter().any(|s| s.domain == resolver.domain) => {
+                scoped.push(resolver)
+            }
+            Ok(_) => (),
+            Err(e) => tracing::warn!("could not read /etc/resolver/{}: {}", domain, e),
+        }
+    }
+
+    scoped
+}
+
+#[cfg(any(target_os = "macos", test))]
+fn parse_error(e: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, format!("Error parsing resolver: {e}"))
+}
+
+/// Parses a file of `/etc/resolver`, in the format of `resolver(5)` on macOS
+///
+/// The file name is the domain of the resolver, unless the file contains a `domain`.
+#[cfg(any(target_os = "macos", test))]
+fn parse_resolver_file(file_name: &str, data: &str) -> ResolveResult<ScopedResolverConfig> {
+    let mut domain = Name::from_str_relaxed(file_name).map_err(parse_error)?;
+    let mut port = DEFAULT_PORT;
+    let mut ips = vec![];
+    let mut search_order = 0;
+
+    for line in data.lines() {
+        let line = line.split(['#', ';']).next().unwrap_or_default();
+        let mut fields = line.split_whitespace();
+        match (fields.next(), fields.next()) {
+            (Some("nameserver"), Some(ip)) => ips.push(ip.parse::<IpAddr>().map_err(parse_error)?),
+            (Some("port"), Some(value)) => port = value.parse().map_err(parse_error)?,
+            (Some("domain"), Some(value)) => {
+                domain = Name::from_str_relaxed(value).map_err(parse_error)?
+            }
+            (Some("search_order"), Some(value)) => {
+                search_order = value.parse().map_err(parse_error)?
+            }
+            // search, sortlist, timeout and options do not apply to scoped resolvers
+            _ => (),
+        }
+    }
+
+    if ips.is_empty() {
+        return Err(parse_error(format!("no nameserver for {domain}")).into());
+    }
+
+    domain.set_fqdn(true);
+    Ok(ScopedResolverConfig {
+        search_order,
+        ..ScopedResolverConfig::new(
+            domain,
+            NameServerConfigGroup::from_ips_clear(&ips, port, false),
+        )
+    })
+}
+
+/// Parses the resolvers for domains from the output of `scutil --dns` on macOS
+///
+/// The resolvers without a domain are those of `/etc/resolv.conf`, and those for queries scoped
+///  to an interface are not used. mDNS resolvers are skipped as well.
+#[cfg(any(target_os = "macos", test))]
+fn parse_scutil_dns(output: &str) -> Vec<ScopedResolverConfig> {
+    #[derive(Default)]
+    struct Resolver {
+        domain: Option<String>,
+        ips: Vec<IpAddr>,
+        port: Option<u16>,
+        order: u32,
+        mdns: bool,
+    }
+
+    fn finish(resolver: Resolver, scoped: &mut Vec<ScopedResolverConfig>) {
+        let Some(domain) = resolver.domain else {
+            return;
+        };
+        if resolver.mdns || resolver.ips.is_empty() {
+            return;
+        }
+        let Ok(mut domain) = Name::from_str_relaxed(&domain) else {
+            return;
+        };
+        domain.set_fqdn(true);
+
+        let port = resolver.port.unwrap_or(DEFAULT_PORT);
+        scoped.push(ScopedResolverConfig {
+            search_order: resolver.order,
+            ..ScopedResolverConfig::new(
+                domain,
+                NameServerConfigGroup::from_ips_clear(&resolver.ips, port, false),
+            )
+        });
+    }
+
+    let mut scoped = vec![];
+    let mut resolver: Option<Resolver> = None;
+    for line in output.lines().map(str::trim) {
+        if line.starts_with("DNS configuration (for scoped queries)") {
+            break;
+        }
+        if line.starts_with("resolver #") {
+            if let Some(resolver) = resolver.replace(Resolver::default()) {
+                finish(resolver, &mut scoped);
+            }
+            continue;
+        }
+
+        let (Some(resolver), Some((key, value))) = (resolver.as_mut(), line.split_once(':')) else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "domain" => resolver.domain = Some(value.to_string()),
+            key if key.starts_with("nameserver[") => {
+                // link local addresses are followed by the interface, e.g. `fe80::1%en0`
+                let ip = value.split('%').next().unwrap_or_default();
+                if let Ok(ip) = ip.parse() {
+                    resolver.ips.push(ip);
+                }
+            }
+            "port" => resolver.port = value.parse().ok(),
+            "order" => resolver.order = value.parse().unwrap_or_default(),
+            "options" => resolver.mdns |= value.split_whitespace().any(|o| o == "mdns"),
+            _ => (),
+        }
+    }
+    if let Some(resolver) = resolver {
+        finish(resolver, &mut scoped);
+    }
+
+    scoped
+}
+
+pub(crate) fn read_resolv_conf<P: AsRef<Path>>(
+    path: P,
+) -> ResolveResult<(ResolverConfig, ResolverOpts)> {
     let mut data = String::new();
     let mut file = File::open(path)?;
     file.read_to_string(&mut data)?;
@@ -62,26 +244,36 @@ fn into_resolver_config(
         None
     };
 
-    // nameservers
+    // nameservers, only over TCP with use-vc
     let mut nameservers = Vec::<NameServerConfig>::with_capacity(parsed_config.nameservers.len());
     for ip in &parsed_config.nameservers {
-        nameservers.push(NameServerConfig {
-            socket_addr: SocketAddr::new(ip.into(), DEFAULT_PORT),
-            protocol: Protocol::Udp,
-            tls_dns_name: None,
-            trust_negative_responses: false,
-            #[cfg(feature = "dns-over-rustls")]
-            tls_config: None,
-            bind_addr: None,
-        });
+        if !parsed_config.use_vc {
+            nameservers.push(NameServerConfig {
+                socket_addr: SocketAddr::new(ip.into(), DEFAULT_PORT),
+                protocol: Protocol::Udp,
+                tls_dns_name: None,
+                trust_negative_responses: false,
+                privacy: PrivacyPolicy::default(),
+                weight: 1,
+                #[cfg(feature = "dns-over-rustls")]
+                tls_config: None,
+                bind_addr: None,
+                bind_device: None,
+                http: None,
+            });
+        }
         nameservers.push(NameServerConfig {
             socket_addr: SocketAddr::new(ip.into(), DEFAULT_PORT),
             protocol: Protocol::Tcp,
             tls_dns_name: None,
             trust_negative_responses: false,
+            privacy: PrivacyPolicy::default(),
+            weight: 1,
             #[cfg(feature = "dns-over-rustls")]
             tls_config: None,
             bind_addr: None,
+            bind_device: None,
+            http: None,
         });
     }
     if nameservers.is_empty() {
@@ -110,6 +302,10 @@ fn into_resolver_config(
         ndots: parsed_config.ndots as usize,
         timeout: Duration::from_secs(u64::from(parsed_config.timeout)),
         attempts: parsed_config.attempts as usize,
+        // rotate selects the name servers round robin
+        shuffle_dns_servers: parsed_config.rotate,
+        no_tld_query: parsed_config.no_tld_query,
+        authentic_data: parsed_config.trust_ad,
         ..ResolverOpts::default()
     };
 
@@ -136,18 +332,26 @@ mod tests {
                 protocol: Protocol::Udp,
                 tls_dns_name: None,
                 trust_negative_responses: false,
+                privacy: PrivacyPolicy::default(),
+                weight: 1,
                 #[cfg(feature = "dns-over-rustls")]
                 tls_config: None,
                 bind_addr: None,
+                bind_device: None,
+                http: None,
             },
             NameServerConfig {
                 socket_addr: addr,
                 protocol: Protocol::Tcp,
                 tls_dns_name: None,
                 trust_negative_responses: false,
+                privacy: PrivacyPolicy::default(),
+                weight: 1,
                 #[cfg(feature = "dns-over-rustls")]
                 tls_config: None,
                 bind_addr: None,
+                bind_device: None,
+                http: None,
             },
         ]
     }
@@ -184,20 +388,24 @@ mod tests {
             parse_resolv_conf("\n\nnameserver 127.0.0.53\noptions edns0 trust-ad\nsearch -- lan\n")
                 .expect("failed");
         let mut cfg = empty_config();
+        let options = ResolverOpts {
+            authentic_data: true,
+            ..ResolverOpts::default()
+        };
 
         {
             let nameservers = nameserver_config("127.0.0.53");
             cfg.add_name_server(nameservers[0].clone());
             cfg.add_name_server(nameservers[1].clone());
             assert_eq!(cfg.name_servers(), parsed.0.name_servers());
-            assert_eq!(ResolverOpts::default(), parsed.1);
+            assert_eq!(options, parsed.1);
         }
 
         // This is the important part, that the invalid `--` is skipped during parsing
         {
             cfg.add_search(Name::from_str("lan").unwrap());
             assert_eq!(cfg.search(), parsed.0.search());
-            assert_eq!(ResolverOpts::default(), parsed.1);
+            assert_eq!(options, parsed.1);
         }
     }
 
@@ -219,6 +427,95 @@ mod tests {
         assert_eq!(ResolverOpts::default(), parsed.1);
     }
 
+    #[test]
+    fn test_options() {
+        let parsed = parse_resolv_conf(
+            "nameserver 127.0.0.1\noptions ndots:3 timeout:2 attempts:4 rotate no-tld-query trust-ad",
+        )
+        .expect("failed");
+        let options = parsed.1;
+        assert_eq!(options.ndots, 3);
+        assert_eq!(options.timeout, Duration::from_secs(2));
+        assert_eq!(options.attempts, 4);
+        assert!(options.shuffle_dns_servers);
+        assert!(options.no_tld_query);
+        assert!(options.authentic_data);
+        assert_eq!(parsed.0.name_servers().len(), 2);
+
+        let parsed = parse_resolv_conf("nameserver 127.0.0.1\noptions use-vc").expect("failed");
+        assert_eq!(parsed.0.name_servers().len(), 1);
+        assert_eq!(parsed.0.name_servers()[0].protocol, Protocol::Tcp);
+    }
+
+    #[test]
+    fn test_parse_resolver_file() {
+        let scoped = parse_resolver_file(
+            "corp.example.com",
+            "# vpn\nnameserver 10.0.0.1\nnameserver 10.0.0.2 ; backup\nport 5353\nsearch_order 5\n",
+        )
+        .expect("failed");
+        assert_eq!(scoped.domain, Name::from_str("corp.example.com.").unwrap());
+        assert_eq!(scoped.search_order, 5);
+        assert_eq!(scoped.name_servers.len(), 4);
+        assert_eq!(
+            scoped.name_servers[0].socket_addr,
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 5353)
+        );
+
+        let scoped = parse_resolver_file("file", "domain other.example.com\nnameserver ::1")
+            .expect("failed");
+        assert_eq!(scoped.domain, Name::from_str("other.example.com.").unwrap());
+
+        assert!(parse_resolver_file("empty", "port 53").is_err());
+        assert!(parse_resolver_file("bad", "nameserver example.com").is_err());
+    }
+
+    #[test]
+    fn test_parse_scutil_dns() {
+        let output = r#"
+DNS configuration
+
+resolver #1
+  search domain[0] : example.com
+  nameserver[0] : 192.168.1.1
+  if_index : 6 (en0)
+  flags    : Request A records
+  reach    : 0x00020002 (Reachable,Directly Reachable Address)
+
+resolver #2
+  domain   : local
+  options  : mdns
+  timeout  : 5
+  flags    : Request A records, Request AAAA records
+  order    : 300000
+
+resolver #3
+  domain   : corp.example.com
+  nameserver[0] : 10.0.0.1
+  nameserver[1] : fe80::1%en0
+  port     : 5353
+  order    : 100
+
+DNS configuration (for scoped queries)
+
+resolver #1
+  domain   : scoped.example.com
+  nameserver[0] : 192.168.1.1
+"#;
+
+        let scoped = parse_scutil_dns(output);
+        assert_eq!(scoped.len(), 1);
+        assert_eq!(
+            scoped[0].domain,
+            Name::from_str("corp.example.com.").unwrap()
+        );
+        assert_eq!(scoped[0].search_order, 100);
+        assert_eq!(
+            scoped[0].name_servers[2].socket_addr,
+            "[fe80::1]:5353".parse::<SocketAddr>().unwrap()
+        );
+    }
+
     #[test]
     fn test_read_resolv_conf() {
         read_resolv_conf(format!("{}/resolv.conf-simple", tests_dir())).expect("simple failed");
@@ -0,0 +1,213 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Detection of changes of the system configuration
+
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::config::{NameServerConfig, ResolverConfig, ResolverOpts};
+use crate::error::ResolveResult;
+
+type ReadSystemConf = fn() -> ResolveResult<(ResolverConfig, ResolverOpts)>;
+
+/// Detects changes of the system configuration, so that resolvers can be reconfigured at runtime
+///
+/// The configuration is read again on each call of `poll_changed`, which is cheap compared to
+///  a lookup. Call it periodically, or when the application is notified of a network change.
+///
+/// ```rust,no_run
+/// # fn main() {
+/// # #[cfg(feature = "tokio-runtime")]
+/// # {
+/// # use std::time::Duration;
+/// # use tokio::runtime::Runtime;
+/// use hickory_resolver::TokioAsyncResolver;
+/// use hickory_resolver::system_conf::SystemConfWatcher;
+///
+/// # let io_loop = Runtime::new().unwrap();
+/// # io_loop.block_on(async {
+/// let mut watcher = SystemConfWatcher::new();
+/// let (config, options) = watcher.poll_changed().unwrap().unwrap();
+/// let mut resolver = TokioAsyncResolver::tokio(config, options);
+///
+/// loop {
+///     // after a network change, or periodically
+/// #   std::thread::sleep(Duration::from_secs(5));
+///     if let Ok(Some((config, options))) = watcher.poll_changed() {
+///         resolver.reconfigure(config, options);
+///     }
+/// }
+/// # });
+/// # }
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SystemConfWatcher {
+    read: ReadSystemConf,
+    current: Option<(ResolverConfig, ResolverOpts)>,
+}
+
+impl SystemConfWatcher {
+    /// Creates a watcher, the first call of `poll_changed` returns the current configuration
+    pub fn new() -> Self {
+        Self::with_reader(super::read_system_conf)
+    }
+
+    fn with_reader(read: ReadSystemConf) -> Self {
+        Self {
+            read,
+            current: None,
+        }
+    }
+
+    /// Reads the system configuration, and returns it if it changed since the last call
+    pub fn poll_changed(&mut self) -> ResolveResult<Option<(ResolverConfig, ResolverOpts)>> {
+        let read = (self.read)()?;
+        if self.current.as_ref() == Some(&read) {
+            return Ok(None);
+        }
+
+        self.current = Some(read.clone());
+        Ok(Some(read))
+    }
+}
+
+impl Default for SystemConfWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Detects changes of `resolv.conf` by its modification time and length, see
+///  `ResolverOpts::watch_resolv_conf`
+#[cfg(unix)]
+#[derive(Debug)]
+pub(crate) struct ResolvConfWatch {
+    path: PathBuf,
+    interval: Duration,
+    stamp: Option<(SystemTime, u64)>,
+    next_check: Instant,
+    /// the name servers of the last change which was read
+    name_servers: Option<Vec<NameServerConfig>>,
+}
+
+#[cfg(unix)]
+impl ResolvConfWatch {
+    /// Watches the file, which is checked at most once per interval
+    pub(crate) fn new(path: impl Into<PathBuf>, interval: Duration) -> Self {
+        let path = path.into();
+        Self {
+            stamp: Self::stamp(&path),
+            path,
+            interval,
+            next_check: Instant::now() + interval,
+            name_servers: None,
+        }
+    }
+
+    /// Returns the name servers read from the file, if it changed since the watch was created
+    pub(crate) fn name_servers(&self) -> Option<&[NameServerConfig]> {
+        self.name_servers.as_deref()
+    }
+
+    fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some((metadata.modified().ok()?, metadata.len()))
+    }
+
+    /// Reads the file if it changed since the last check
+    ///
+    /// A file which was removed or can not be parsed is ignored, until it changes again.
+    pub(crate) fn poll_changed(&mut self) -> Option<ResolverConfig> {
+        let now = Instant::now();
+        if now < self.next_check {
+            return None;
+        }
+        self.next_check = now + self.interval;
+
+        let stamp = Self::stamp(&self.path);
+        if stamp.is_none() || stamp == self.stamp {
+            return None;
+        }
+        self.stamp = stamp;
+
+        match super::unix::read_resolv_conf(&self.path) {
+            Ok((config, _)) => {
+                self.name_servers = Some(config.name_servers().to_vec());
+                Some(config)
+            }
+            Err(e) => {
+                tracing::warn!("could not read {}: {}", self.path.display(), e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::config::NameServerConfigGroup;
+
+    static READS: AtomicUsize = AtomicUsize::new(0);
+
+    fn read_changing() -> ResolveResult<(ResolverConfig, ResolverOpts)> {
+        // the name servers change on the third read
+        let ip = if READS.fetch_add(1, Ordering::SeqCst) < 2 {
+            [10, 0, 0, 1]
+        } else {
+            [10, 0, 0, 2]
+        };
+        let name_servers = NameServerConfigGroup::from_ips_clear(&[ip.into()], 53, false);
+
+        Ok((
+            ResolverConfig::from_parts(None, vec![], name_servers),
+            ResolverOpts::default(),
+        ))
+    }
+
+    #[test]
+    fn test_poll_changed() {
+        let mut watcher = SystemConfWatcher::with_reader(read_changing);
+
+        assert!(watcher.poll_changed().unwrap().is_some());
+        assert!(watcher.poll_changed().unwrap().is_none());
+
+        let (config, _) = watcher.poll_changed().unwrap().unwrap();
+        assert_eq!(
+            config.name_servers()[0].socket_addr,
+            ([10, 0, 0, 2], 53).into()
+        );
+        assert!(watcher.poll_changed().unwrap().is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolv_conf_watch() {
+        let path = std::env::temp_dir().join(format!("resolv.conf-watch-{}", std::process::id()));
+        std::fs::write(&path, "nameserver 10.0.0.1\n").unwrap();
+
+        let mut watch = ResolvConfWatch::new(&path, Duration::ZERO);
+        assert!(watch.poll_changed().is_none());
+
+        std::fs::write(&path, "nameserver 10.0.0.2\nnameserver 10.0.0.3\n").unwrap();
+        let config = watch.poll_changed().unwrap();
+        assert_eq!(
+            config.name_servers()[0].socket_addr,
+            ([10, 0, 0, 2], 53).into()
+        );
+        assert!(watch.poll_changed().is_none());
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(watch.poll_changed().is_none());
+    }
+}
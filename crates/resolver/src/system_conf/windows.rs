@@ -7,20 +7,30 @@
 
 //! System configuration loading for windows
 
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::str::FromStr;
 
 use ipconfig::computer::{get_domain, get_search_list, is_round_robin_enabled};
-use ipconfig::get_adapters;
+use ipconfig::{get_adapters, Adapter, OperStatus};
+use winreg::enums::HKEY_LOCAL_MACHINE;
+use winreg::RegKey;
 
 use proto::rr::Name;
 
-use crate::config::{NameServerConfig, Protocol, ResolverConfig, ResolverOpts};
+use crate::config::{
+    NameServerConfig, NameServerConfigGroup, PrivacyPolicy, Protocol, ResolverConfig, ResolverOpts,
+    ScopedResolverConfig,
+};
 use crate::error::ResolveResult;
 
+/// The keys of the Name Resolution Policy Table, set by group policy or locally
+const NRPT_KEYS: [&str; 2] = [
+    r"SOFTWARE\Policies\Microsoft\Windows NT\DNSClient\DnsPolicyConfig",
+    r"SYSTEM\CurrentControlSet\Services\Dnscache\Parameters\DnsPolicyConfig",
+];
+
 /// Returns the name servers of the computer (of all adapters)
-fn get_name_servers() -> ResolveResult<Vec<NameServerConfig>> {
-    let adapters = get_adapters()?;
+fn get_name_servers(adapters: &[Adapter]) -> Vec<NameServerConfig> {
     let mut name_servers = vec![];
 
     for dns_server in adapters
@@ -33,27 +43,115 @@ fn get_name_servers() -> ResolveResult<Vec<NameServerConfig>> {
             protocol: Protocol::Udp,
             tls_dns_name: None,
             trust_negative_responses: false,
+            privacy: PrivacyPolicy::default(),
+            weight: 1,
             #[cfg(feature = "dns-over-rustls")]
             tls_config: None,
             bind_addr: None,
+            bind_device: None,
+            http: None,
         });
         name_servers.push(NameServerConfig {
             socket_addr,
             protocol: Protocol::Tcp,
             tls_dns_name: None,
             trust_negative_responses: false,
+            privacy: PrivacyPolicy::default(),
+            weight: 1,
             #[cfg(feature = "dns-over-rustls")]
             tls_config: None,
             bind_addr: None,
+            bind_device: None,
+            http: None,
         });
     }
-    Ok(name_servers)
+    name_servers
+}
+
+/// Returns the resolvers for the connection specific suffixes of the adapters which are up
+fn get_adapter_resolvers(adapters: &[Adapter]) -> Vec<ScopedResolverConfig> {
+    adapters
+        .iter()
+        .filter(|adapter| adapter.oper_status() == OperStatus::IfOperStatusUp)
+        .filter(|adapter| !adapter.dns_suffix().is_empty() && !adapter.dns_servers().is_empty())
+        .filter_map(|adapter| {
+            let mut domain = Name::from_str(adapter.dns_suffix()).ok()?;
+            domain.set_fqdn(true);
+
+            Some(ScopedResolverConfig {
+                // the adapter with the lowest metric is preferred by windows as well
+                search_order: adapter.ipv4_metric(),
+                ..ScopedResolverConfig::new(
+                    domain,
+                    NameServerConfigGroup::from_ips_clear(adapter.dns_servers(), 53, false),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Returns the resolvers of the rules of the Name Resolution Policy Table
+///
+/// Only the rules for DNS servers of namespaces are supported, rules for DNSSEC or DirectAccess
+///  without DNS servers are skipped.
+fn get_nrpt_resolvers() -> Vec<ScopedResolverConfig> {
+    let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
+    let mut scoped = vec![];
+
+    for policy_config in NRPT_KEYS
+        .iter()
+        .filter_map(|key| hklm.open_subkey(key).ok())
+    {
+        for rule in policy_config.enum_keys().filter_map(Result::ok) {
+            let Ok(rule) = policy_config.open_subkey(&rule) else {
+                continue;
+            };
+            let (Ok(names), Ok(servers)) = (
+                rule.get_value::<Vec<String>, _>("Name"),
+                rule.get_value::<String, _>("GenericDNSServers"),
+            ) else {
+                continue;
+            };
+
+            scoped.extend(parse_nrpt_rule(&names, &servers));
+        }
+    }
+
+    scoped
+}
+
+/// Parses a rule of the Name Resolution Policy Table
+///
+/// # Arguments
+///
+/// * `names` - the namespaces of the rule, suffixes start with a `.`, otherwise it is a FQDN
+/// * `servers` - the DNS servers of the rule, separated by `;`
+fn parse_nrpt_rule(names: &[String], servers: &str) -> Vec<ScopedResolverConfig> {
+    let ips = servers
+        .split(';')
+        .filter_map(|ip| ip.trim().parse::<IpAddr>().ok())
+        .collect::<Vec<_>>();
+    if ips.is_empty() {
+        return vec![];
+    }
+    let name_servers = NameServerConfigGroup::from_ips_clear(&ips, 53, false);
+
+    names
+        .iter()
+        .filter_map(|name| Name::from_str(name.trim_start_matches('.')).ok())
+        .filter(|name| !name.is_root())
+        .map(|mut domain| {
+            domain.set_fqdn(true);
+            ScopedResolverConfig::new(domain, name_servers.clone())
+        })
+        .collect()
 }
 
 pub fn read_system_conf() -> ResolveResult<(ResolverConfig, ResolverOpts)> {
-    let name_servers = get_name_servers()?;
+    let adapters = get_adapters()?;
+    let name_servers = get_name_servers(&adapters);
 
-    let search_list: Vec<Name> = get_search_list()?
+    let mut search_list: Vec<Name> = get_search_list()?
         .iter()
         .map(|x| Name::from_str(x))
         .collect::<Result<Vec<_>, _>>()?;
@@ -63,7 +161,20 @@ pub fn read_system_conf() -> ResolveResult<(ResolverConfig, ResolverOpts)> {
         None => Name::root(),
     };
 
-    let config = ResolverConfig::from_parts(Some(domain), search_list, name_servers);
+    let adapter_resolvers = get_adapter_resolvers(&adapters);
+    for scoped in &adapter_resolvers {
+        if !search_list.contains(&scoped.domain) {
+            search_list.push(scoped.domain.clone());
+        }
+    }
+
+    let mut config = ResolverConfig::from_parts(Some(domain), search_list, name_servers);
+    // the rules of the policy table take precedence over the adapters
+    for scoped in get_nrpt_resolvers().into_iter().chain(adapter_resolvers) {
+        if !config.scoped().iter().any(|s| s.domain == scoped.domain) {
+            config.add_scoped(scoped);
+        }
+    }
 
     let rotate = is_round_robin_enabled()?;
 
@@ -73,3 +184,31 @@ pub fn read_system_conf() -> ResolveResult<(ResolverConfig, ResolverOpts)> {
     };
     Ok((config, opts))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nrpt_rule() {
+        let names = vec![
+            ".corp.example.com".to_string(),
+            "host.example.com".to_string(),
+        ];
+        let scoped = parse_nrpt_rule(&names, "10.0.0.1; 10.0.0.2");
+
+        assert_eq!(scoped.len(), 2);
+        assert_eq!(
+            scoped[0].domain,
+            Name::from_str("corp.example.com.").unwrap()
+        );
+        assert_eq!(
+            scoped[1].domain,
+            Name::from_str("host.example.com.").unwrap()
+        );
+        assert_eq!(scoped[0].name_servers.len(), 4);
+
+        // DNSSEC only rules have no DNS servers
+        assert!(parse_nrpt_rule(&names, "").is_empty());
+    }
+}
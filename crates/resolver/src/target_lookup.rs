@@ -0,0 +1,213 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! The hosts named by MX and SRV records, see `AsyncResolver::lookup_srv_with_ips`
+
+use std::net::IpAddr;
+
+use rand::Rng;
+
+use crate::lookup::Lookup;
+use crate::Name;
+
+/// A host named by an MX or SRV record, with its addresses
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Target {
+    /// The name of the host
+    pub name: Name,
+    /// The port of the service, 0 for MX records
+    pub port: u16,
+    /// The weight among the targets of the same priority, 0 for MX records
+    pub weight: u16,
+    /// The addresses of the host, empty if they could not be resolved
+    pub ips: Vec<IpAddr>,
+}
+
+impl Target {
+    pub(crate) fn new(name: Name, port: u16, weight: u16) -> Self {
+        Self {
+            name,
+            port,
+            weight,
+            ips: vec![],
+        }
+    }
+}
+
+/// The targets of the same SRV priority, or MX preference
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TargetGroup {
+    /// The priority of the targets, lower values are to be tried first
+    pub priority: u16,
+    /// The targets, in the order they are to be tried
+    pub targets: Vec<Target>,
+}
+
+/// Groups the targets by priority, in ascending order
+///
+/// Targets with the same name and port are only kept once, with their lowest priority. The root
+///  name means that the service is not available, see RFC 2782 and RFC 7505, and is dropped.
+///
+/// # Arguments
+///
+/// * `targets` - the targets with their priority, in the order of the records
+/// * `weighted` - if true the targets of a group are ordered by the weighted selection of
+///   RFC 2782, otherwise they are shuffled as required for MX records by RFC 5321
+pub(crate) fn group_targets(
+    mut targets: Vec<(u16, Target)>,
+    weighted: bool,
+    rng: &mut impl Rng,
+) -> Vec<TargetGroup> {
+    targets.retain(|(_, target)| !target.name.is_root());
+    // the sort is stable, so duplicates keep the order of the records
+    targets.sort_by_key(|(priority, _)| *priority);
+
+    let mut groups = Vec::<TargetGroup>::new();
+    for (priority, target) in targets {
+        let duplicate = groups
+            .iter()
+            .flat_map(|group| &group.targets)
+            .any(|other| other.port == target.port && other.name == target.name);
+        if duplicate {
+            continue;
+        }
+
+        match groups.last_mut() {
+            Some(group) if group.priority == priority => group.targets.push(target),
+            _ => groups.push(TargetGroup {
+                priority,
+                targets: vec![target],
+            }),
+        }
+    }
+
+    for group in &mut groups {
+        let targets = std::mem::take(&mut group.targets);
+        group.targets = if weighted {
+            weighted_order(targets, rng)
+        } else {
+            shuffled(targets, rng)
+        };
+    }
+
+    groups
+}
+
+/// Orders the targets by the weighted selection of RFC 2782
+///
+/// Each target is selected with a probability proportional to its weight among the remaining
+///  ones, those with a weight of 0 having a very small chance of being selected first.
+fn weighted_order(mut targets: Vec<Target>, rng: &mut impl Rng) -> Vec<Target> {
+    // the zero weight targets are placed first, so that they can be selected
+    targets.sort_by_key(|target| target.weight != 0);
+
+    let mut ordered = Vec::with_capacity(targets.len());
+    while !targets.is_empty() {
+        let total = targets
+            .iter()
+            .map(|target| u32::from(target.weight))
+            .sum::<u32>();
+        let selected = rng.gen_range(0..=total);
+
+        let mut sum = 0;
+        let idx = targets
+            .iter()
+            .position(|target| {
+                sum += u32::from(target.weight);
+                sum >= selected
+            })
+            .unwrap_or(0);
+        ordered.push(targets.remove(idx));
+    }
+
+    ordered
+}
+
+fn shuffled(mut targets: Vec<Target>, rng: &mut impl Rng) -> Vec<Target> {
+    for idx in (1..targets.len()).rev() {
+        targets.swap(idx, rng.gen_range(0..=idx));
+    }
+    targets
+}
+
+/// The addresses of the name from the records of the lookup, e.g. from the additional section
+pub(crate) fn additional_ips(lookup: &Lookup, name: &Name) -> Vec<IpAddr> {
+    lookup
+        .records()
+        .iter()
+        .filter(|record| record.name() == name)
+        .filter_map(|record| record.data()?.ip_addr())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn target(name: &str, port: u16, weight: u16) -> Target {
+        Target::new(Name::from_ascii(name).unwrap(), port, weight)
+    }
+
+    #[test]
+    fn test_group_targets() {
+        let targets = vec![
+            (20, target("b.example.com.", 443, 0)),
+            (10, target("a.example.com.", 443, 0)),
+            (10, target(".", 0, 0)),
+            (20, target("a.example.com.", 443, 0)),
+            (20, target("a.example.com.", 8443, 0)),
+        ];
+        let groups = group_targets(targets, true, &mut StdRng::seed_from_u64(0));
+
+        assert_eq!(
+            groups,
+            [
+                TargetGroup {
+                    priority: 10,
+                    targets: vec![target("a.example.com.", 443, 0)],
+                },
+                TargetGroup {
+                    priority: 20,
+                    targets: vec![
+                        target("b.example.com.", 443, 0),
+                        target("a.example.com.", 8443, 0),
+                    ],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_weighted_order() {
+        let targets = vec![
+            target("a.example.com.", 443, 10),
+            target("b.example.com.", 443, 0),
+            target("c.example.com.", 443, 90),
+        ];
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let mut first = [0; 3];
+        for _ in 0..1000 {
+            let ordered = weighted_order(targets.clone(), &mut rng);
+            assert_eq!(ordered.len(), 3);
+            let idx = targets
+                .iter()
+                .position(|target| *target == ordered[0])
+                .unwrap();
+            first[idx] += 1;
+        }
+
+        // the targets are selected first in proportion to their weight
+        assert!(first[2] > 800, "{first:?}");
+        assert!(first[0] > 50, "{first:?}");
+        assert!(first[1] < 50, "{first:?}");
+    }
+}
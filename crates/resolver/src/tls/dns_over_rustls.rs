@@ -26,7 +26,11 @@ use proto::BufDnsStreamHandle;
 
 use crate::config::TlsClientConfig;
 
-pub(crate) static CLIENT_CONFIG: Lazy<Result<Arc<ClientConfig>, ProtoError>> = Lazy::new(|| {
+pub(crate) static CLIENT_CONFIG: Lazy<Result<Arc<ClientConfig>, ProtoError>> =
+    Lazy::new(|| Ok(Arc::new(client_config(root_store()?))));
+
+/// Returns the trust anchors enabled by the `native-certs` and `webpki-roots` features
+pub(crate) fn root_store() -> Result<RootCertStore, ProtoError> {
     #[cfg_attr(
         not(any(feature = "native-certs", feature = "webpki-roots")),
         allow(unused_mut)
@@ -59,6 +63,11 @@ pub(crate) static CLIENT_CONFIG: Lazy<Result<Arc<ClientConfig>, ProtoError>> = L
         )
     }));
 
+    Ok(root_store)
+}
+
+/// Returns the default configuration for connections to name servers, trusting `root_store`
+pub(crate) fn client_config(root_store: RootCertStore) -> ClientConfig {
     let mut client_config = ClientConfig::builder()
         .with_safe_default_cipher_suites()
         .with_safe_default_kx_groups()
@@ -70,8 +79,8 @@ pub(crate) static CLIENT_CONFIG: Lazy<Result<Arc<ClientConfig>, ProtoError>> = L
     // The port (853) of DOT is for dns dedicated, SNI is unnecessary. (ISP block by the SNI name)
     client_config.enable_sni = false;
 
-    Ok(Arc::new(client_config))
-});
+    client_config
+}
 
 #[allow(clippy::type_complexity)]
 pub(crate) fn new_tls_stream_with_future<S, F>(
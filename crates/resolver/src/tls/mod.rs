@@ -16,6 +16,8 @@ cfg_if! {
         pub(crate) use self::dns_over_rustls::new_tls_stream_with_future;
         #[cfg(any(feature = "dns-over-https-rustls", feature = "dns-over-quic", feature = "dns-over-h3"))]
         pub(crate) use self::dns_over_rustls::CLIENT_CONFIG;
+        #[cfg(feature = "tls-monitor")]
+        pub(crate) use self::dns_over_rustls::{client_config, root_store};
     } else if #[cfg(feature = "dns-over-native-tls")] {
         pub(crate) use self::dns_over_native_tls::new_tls_stream_with_future;
     } else if #[cfg(feature = "dns-over-openssl")] {
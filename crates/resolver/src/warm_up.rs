@@ -0,0 +1,67 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Resolving a list of names ahead of time, so that their answers are cached before they are needed
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::error::ResolveError;
+use crate::Name;
+
+/// The outcome of resolving the warm-up names, see `AsyncResolver::warm_up`
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct WarmUpReport {
+    /// The names which were resolved, and whose answers are now cached
+    pub resolved: Vec<Name>,
+    /// The names which could not be resolved, with the error of their lookup
+    pub failed: Vec<(Name, ResolveError)>,
+}
+
+impl WarmUpReport {
+    /// Returns true if all names were resolved
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Counters for the warm-ups of a resolver and its clones
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct WarmUpStats {
+    /// The number of times the warm-up names were resolved
+    pub runs: u64,
+    /// Lookups of warm-up names which succeeded
+    pub resolved: u64,
+    /// Lookups of warm-up names which failed
+    pub failed: u64,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct WarmUpCounters {
+    runs: AtomicU64,
+    resolved: AtomicU64,
+    failed: AtomicU64,
+}
+
+impl WarmUpCounters {
+    pub(crate) fn record(&self, report: &WarmUpReport) {
+        self.runs.fetch_add(1, Ordering::Relaxed);
+        self.resolved
+            .fetch_add(report.resolved.len() as u64, Ordering::Relaxed);
+        self.failed
+            .fetch_add(report.failed.len() as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn stats(&self) -> WarmUpStats {
+        WarmUpStats {
+            runs: self.runs.load(Ordering::Relaxed),
+            resolved: self.resolved.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -16,7 +16,7 @@ use crate::proto::rr::{
 };
 use crate::{
     authority::{LookupError, MessageRequest, UpdateResult, ZoneType},
-    proto::rr::{LowerName, RecordSet, RecordType, RrsetRecords},
+    proto::rr::{LowerName, Record, RecordSet, RecordType, RrsetRecords},
     server::RequestInfo,
 };
 
@@ -148,6 +148,23 @@ pub trait Authority: Send + Sync {
         lookup_options: LookupOptions,
     ) -> Result<Self::Lookup, LookupError>;
 
+    /// Looks up the answer of a simple query without awaiting, see `RequestHandler::answer_ref`
+    ///
+    /// Only answers which are a single RRset of the queried type, and need no name server or
+    ///  additional records, are returned. `None`, the default, means that the query has to be
+    ///  answered by `search`.
+    ///
+    /// [`RequestHandler::answer_ref`]: crate::server::RequestHandler::answer_ref
+    fn lookup_now(
+        &self,
+        name: &LowerName,
+        rtype: RecordType,
+        lookup_options: LookupOptions,
+    ) -> Option<Vec<Record>> {
+        let _ = (name, rtype, lookup_options);
+        None
+    }
+
     /// Get the NS, NameServer, record for the zone
     async fn ns(&self, lookup_options: LookupOptions) -> Result<Self::Lookup, LookupError> {
         self.lookup(self.origin(), RecordType::NS, lookup_options)
@@ -167,6 +184,19 @@ pub trait Authority: Send + Sync {
         lookup_options: LookupOptions,
     ) -> Result<Self::Lookup, LookupError>;
 
+    /// Return the NSEC records proving that there is no closer match for an answer synthesized
+    ///  from a wildcard, see [RFC 4035](https://tools.ietf.org/html/rfc4035#section-3.1.3.3)
+    ///
+    /// # Arguments
+    ///
+    /// * `next_closer` - the name which does not exist, with one more label than the wildcard
+    /// * `lookup_options` - if DNSSEC is enabled then it will return RRSIG records as well
+    async fn get_wildcard_proof(
+        &self,
+        next_closer: &LowerName,
+        lookup_options: LookupOptions,
+    ) -> Result<Self::Lookup, LookupError>;
+
     /// Returns the SOA of the authority.
     ///
     /// *Note*: This will only return the SOA, if this is fulfilling a request, a standard lookup
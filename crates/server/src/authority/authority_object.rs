@@ -73,6 +73,17 @@ pub trait AuthorityObject: Send + Sync {
         lookup_options: LookupOptions,
     ) -> Result<Box<dyn LookupObject>, LookupError>;
 
+    /// Looks up the answer of a simple query without awaiting, see `Authority::lookup_now`
+    fn lookup_now(
+        &self,
+        name: &LowerName,
+        rtype: RecordType,
+        lookup_options: LookupOptions,
+    ) -> Option<Vec<Record>> {
+        let _ = (name, rtype, lookup_options);
+        None
+    }
+
     /// Get the NS, NameServer, record for the zone
     async fn ns(
         &self,
@@ -95,6 +106,14 @@ pub trait AuthorityObject: Send + Sync {
         lookup_options: LookupOptions,
     ) -> Result<Box<dyn LookupObject>, LookupError>;
 
+    /// Return the NSEC records proving that there is no closer match for an answer synthesized
+    ///  from a wildcard, see `Authority::get_wildcard_proof`
+    async fn get_wildcard_proof(
+        &self,
+        next_closer: &LowerName,
+        lookup_options: LookupOptions,
+    ) -> Result<Box<dyn LookupObject>, LookupError>;
+
     /// Returns the SOA of the authority.
     ///
     /// *Note*: This will only return the SOA, if this is fulfilling a request, a standard lookup
@@ -192,6 +211,16 @@ where
         lookup.map(|l| Box::new(l) as Box<dyn LookupObject>)
     }
 
+    /// Looks up the answer of a simple query without awaiting, see `Authority::lookup_now`
+    fn lookup_now(
+        &self,
+        name: &LowerName,
+        rtype: RecordType,
+        lookup_options: LookupOptions,
+    ) -> Option<Vec<Record>> {
+        Authority::lookup_now(self.as_ref(), name, rtype, lookup_options)
+    }
+
     /// Return the NSEC records based on the given name
     ///
     /// # Arguments
@@ -207,6 +236,18 @@ where
         let lookup = Authority::get_nsec_records(self.as_ref(), name, lookup_options).await;
         lookup.map(|l| Box::new(l) as Box<dyn LookupObject>)
     }
+
+    /// Return the NSEC records proving that there is no closer match for an answer synthesized
+    ///  from a wildcard, see `Authority::get_wildcard_proof`
+    async fn get_wildcard_proof(
+        &self,
+        next_closer: &LowerName,
+        lookup_options: LookupOptions,
+    ) -> Result<Box<dyn LookupObject>, LookupError> {
+        let lookup =
+            Authority::get_wildcard_proof(self.as_ref(), next_closer, lookup_options).await;
+        lookup.map(|l| Box::new(l) as Box<dyn LookupObject>)
+    }
 }
 
 /// An Object Safe Lookup for Authority
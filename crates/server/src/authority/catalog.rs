@@ -8,30 +8,51 @@
 // TODO, I've implemented this as a separate entity from the cache, but I wonder if the cache
 //  should be the only "front-end" for lookups, where if that misses, then we go to the catalog
 //  then, if requested, do a recursive lookup... i.e. the catalog would only point to files.
-use std::{borrow::Borrow, collections::HashMap, future::Future, io};
+#[cfg(feature = "hickory-resolver")]
+use std::sync::Arc;
+use std::{
+    borrow::Borrow,
+    collections::HashMap,
+    future::Future,
+    io,
+    net::SocketAddr,
+    str::FromStr,
+    time::{Duration, Instant},
+};
 
 use cfg_if::cfg_if;
 use tracing::{debug, error, info, trace, warn};
 
 #[cfg(feature = "dnssec")]
-use crate::proto::rr::{
-    dnssec::{Algorithm, SupportedAlgorithms},
-    rdata::opt::{EdnsCode, EdnsOption},
-};
+use crate::proto::rr::dnssec::{rdata::DNSSECRData, Algorithm, SupportedAlgorithms};
+#[cfg(feature = "hickory-resolver")]
+use crate::resolver::blocklist::{Blocked, SharedBlocklist};
 use crate::{
     authority::{
         AuthLookup, AuthorityObject, EmptyLookup, LookupError, LookupObject, LookupOptions,
-        MessageResponse, MessageResponseBuilder, ZoneType,
+        MessageResponse, MessageResponseBuilder, ZoneSyncTracker, ZoneType,
+    },
+    proto::op::{Edns, Header, LowerQuery, MessageRef, MessageType, OpCode, ResponseCode},
+    proto::rr::{
+        rdata::{
+            opt::{EdnsCode, EdnsOption},
+            TXT,
+        },
+        DNSClass, LowerName, Name, RData, Record, RecordType,
     },
-    proto::op::{Edns, Header, LowerQuery, MessageType, OpCode, ResponseCode},
-    proto::rr::{LowerName, Record, RecordType},
-    server::{Request, RequestHandler, RequestInfo, ResponseHandler, ResponseInfo},
+    server::{Protocol, Request, RequestHandler, RequestInfo, ResponseHandler, ResponseInfo},
 };
 
 /// Set of authorities, zones, available to this server.
 #[derive(Default)]
 pub struct Catalog {
     authorities: HashMap<LowerName, Box<dyn AuthorityObject>>,
+    server_id: Option<String>,
+    sync_tracker: ZoneSyncTracker,
+    request_timeout: Option<Duration>,
+    slow_query_threshold: Option<Duration>,
+    #[cfg(feature = "hickory-resolver")]
+    blocklist: Option<Arc<SharedBlocklist>>,
 }
 
 #[allow(unused_mut, unused_variables)]
@@ -49,27 +70,29 @@ async fn send_response<'a, R: ResponseHandler>(
 ) -> io::Result<ResponseInfo> {
     if let Some(mut resp_edns) = response_edns {
         #[cfg(feature = "dnssec")]
-        {
-            // set edns DAU and DHU
-            // send along the algorithms which are supported by this authority
-            let mut algorithms = SupportedAlgorithms::default();
-            algorithms.set(Algorithm::RSASHA256);
-            algorithms.set(Algorithm::ECDSAP256SHA256);
-            algorithms.set(Algorithm::ECDSAP384SHA384);
-            algorithms.set(Algorithm::ED25519);
-
-            let dau = EdnsOption::DAU(algorithms);
-            let dhu = EdnsOption::DHU(algorithms);
-
-            resp_edns.options_mut().insert(dau);
-            resp_edns.options_mut().insert(dhu);
-        }
+        insert_supported_algorithms(&mut resp_edns);
         response.set_edns(resp_edns);
     }
 
     response_handle.send_response(response).await
 }
 
+/// Sets the EDNS DAU and DHU options to the algorithms which are supported by this authority
+#[cfg(feature = "dnssec")]
+fn insert_supported_algorithms(edns: &mut Edns) {
+    let mut algorithms = SupportedAlgorithms::default();
+    algorithms.set(Algorithm::RSASHA256);
+    algorithms.set(Algorithm::ECDSAP256SHA256);
+    algorithms.set(Algorithm::ECDSAP384SHA384);
+    algorithms.set(Algorithm::ED25519);
+
+    let dau = EdnsOption::DAU(algorithms);
+    let dhu = EdnsOption::DHU(algorithms);
+
+    edns.options_mut().insert(dau);
+    edns.options_mut().insert(dhu);
+}
+
 #[async_trait::async_trait]
 impl RequestHandler for Catalog {
     /// Determines what needs to happen given the type of request, i.e. Query or Update.
@@ -126,6 +149,16 @@ impl RequestHandler for Catalog {
                 };
             }
 
+            // RFC 5001, answer the NSID option with the identity of this instance
+            if let Some(server_id) = &self.server_id {
+                if req_edns.option(EdnsCode::NSID).is_some() {
+                    resp_edns.options_mut().insert(EdnsOption::Unknown(
+                        EdnsCode::NSID.into(),
+                        server_id.as_bytes().to_vec(),
+                    ));
+                }
+            }
+
             response_edns = Some(resp_edns);
         } else {
             response_edns = None;
@@ -172,6 +205,78 @@ impl RequestHandler for Catalog {
             Ok(info) => info,
         }
     }
+
+    /// Answers the queries of a single RRset in an authoritative zone, see
+    ///  `Authority::lookup_now`
+    ///
+    /// Requests with EDNS options which are answered, DNSSEC OK or of a blocked name are left to
+    ///  `handle_request`.
+    fn answer_ref(
+        &self,
+        request: &MessageRef<'_>,
+        _src: SocketAddr,
+        _protocol: Protocol,
+    ) -> Option<Vec<u8>> {
+        let header = request.header();
+        if header.message_type() != MessageType::Query
+            || header.op_code() != OpCode::Query
+            || header.query_count() != 1
+        {
+            return None;
+        }
+
+        let query = request.queries().next()?;
+        if query.query_class() != DNSClass::IN {
+            return None;
+        }
+
+        // the version is checked and NSID is answered by handle_request
+        let edns = request.edns();
+        if let Some(edns) = edns {
+            let version = (edns.ttl() & 0x00FF_0000) >> 16;
+            if version != 0 || (self.server_id.is_some() && !edns.rdata().is_empty()) {
+                return None;
+            }
+        }
+        #[cfg(feature = "dnssec")]
+        if request.dnssec_ok() {
+            return None;
+        }
+
+        let name = query.name().to_name().ok()?;
+        #[cfg(feature = "hickory-resolver")]
+        if let Some(blocklist) = &self.blocklist {
+            if blocklist.check(&name, query.query_type()).is_some() {
+                return None;
+            }
+        }
+
+        let name = LowerName::from(name);
+        let authority = self.find(&name)?;
+        if !authority.zone_type().is_authoritative() {
+            return None;
+        }
+        let answers = authority.lookup_now(&name, query.query_type(), LookupOptions::default())?;
+
+        let mut response_header = Header::response_from_request(header);
+        response_header.set_authoritative(true);
+
+        // the same EDNS record as in handle_request
+        let response_edns = edns.map(|_| {
+            let mut resp_edns = Edns::new();
+            resp_edns.set_dnssec_ok(true);
+            resp_edns.set_max_payload(request.max_payload().max(512));
+            resp_edns.set_version(0);
+            #[cfg(feature = "dnssec")]
+            insert_supported_algorithms(&mut resp_edns);
+            resp_edns
+        });
+
+        request
+            .to_response_with_edns(&response_header, &answers, response_edns.as_ref())
+            .map_err(|e| warn!("failed to serialize response: {}", e))
+            .ok()
+    }
 }
 
 impl Catalog {
@@ -179,9 +284,81 @@ impl Catalog {
     pub fn new() -> Self {
         Self {
             authorities: HashMap::new(),
+            server_id: None,
+            sync_tracker: ZoneSyncTracker::default(),
+            request_timeout: None,
+            slow_query_threshold: None,
+            #[cfg(feature = "hickory-resolver")]
+            blocklist: None,
         }
     }
 
+    /// Sets the identity of this instance, e.g. to tell apart the instances behind an anycast
+    ///  address
+    ///
+    /// The identity is returned in the NSID option of EDNS responses, see
+    ///  [RFC 5001](https://tools.ietf.org/html/rfc5001), and in answers to `TXT` queries of
+    ///  class `CH` for `hostname.bind.` and `id.server.`.
+    pub fn set_server_id(&mut self, server_id: Option<String>) {
+        self.server_id = server_id;
+    }
+
+    /// The identity of this instance, see `set_server_id`
+    pub fn server_id(&self) -> Option<&str> {
+        self.server_id.as_deref()
+    }
+
+    /// Sets the time in which the lookups of a query must complete, e.g. to not wait on a hung
+    ///  forwarder for longer than clients wait for the response
+    ///
+    /// Lookups which take longer are aborted, and answered with SERVFAIL. There is no deadline
+    ///  by default.
+    pub fn set_request_timeout(&mut self, request_timeout: Option<Duration>) {
+        self.request_timeout = request_timeout;
+    }
+
+    /// The time in which the lookups of a query must complete, see `set_request_timeout`
+    pub fn request_timeout(&self) -> Option<Duration> {
+        self.request_timeout
+    }
+
+    /// Sets the time after which the handling of a query is logged as slow
+    ///
+    /// Slow queries are logged as a warning, with the time taken by the lookups in the authority
+    ///  and by sending the response in the `lookup_ms` and `send_ms` fields of the event. Nothing
+    ///  is logged by default.
+    pub fn set_slow_query_threshold(&mut self, slow_query_threshold: Option<Duration>) {
+        self.slow_query_threshold = slow_query_threshold;
+    }
+
+    /// The time after which the handling of a query is logged as slow, see
+    ///  `set_slow_query_threshold`
+    pub fn slow_query_threshold(&self) -> Option<Duration> {
+        self.slow_query_threshold
+    }
+
+    /// Sets the blocklist of the queries, e.g. of the domains of advertising and tracking
+    ///
+    /// Queries of blocked names are answered by the blocklist before the authorities are searched,
+    ///  see `hickory_resolver::blocklist`. No names are blocked by default.
+    #[cfg(feature = "hickory-resolver")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "resolver")))]
+    pub fn set_blocklist(&mut self, blocklist: Option<Arc<SharedBlocklist>>) {
+        self.blocklist = blocklist;
+    }
+
+    /// The blocklist of the queries, see `set_blocklist`
+    #[cfg(feature = "hickory-resolver")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "resolver")))]
+    pub fn blocklist(&self) -> Option<&Arc<SharedBlocklist>> {
+        self.blocklist.as_ref()
+    }
+
+    /// The tracker of the synchronization status of the secondary zones
+    pub fn sync_tracker(&self) -> &ZoneSyncTracker {
+        &self.sync_tracker
+    }
+
     /// Insert or update a zone authority
     ///
     /// # Arguments
@@ -189,11 +366,17 @@ impl Catalog {
     /// * `name` - zone name, e.g. example.com.
     /// * `authority` - the zone data
     pub fn upsert(&mut self, name: LowerName, authority: Box<dyn AuthorityObject>) {
+        if authority.zone_type() == ZoneType::Secondary {
+            self.sync_tracker.track(name.clone());
+        } else {
+            self.sync_tracker.untrack(&name);
+        }
         self.authorities.insert(name, authority);
     }
 
     /// Remove a zone from the catalog
     pub fn remove(&mut self, name: &LowerName) -> Option<Box<dyn AuthorityObject>> {
+        self.sync_tracker.untrack(name);
         self.authorities.remove(name)
     }
 
@@ -346,6 +529,19 @@ impl Catalog {
         response_handle: R,
     ) -> ResponseInfo {
         let request_info = request.request_info();
+        if let Some(server_id) = self.server_id_query(request_info.query) {
+            return send_server_id(request, server_id, response_edns, response_handle).await;
+        }
+
+        #[cfg(feature = "hickory-resolver")]
+        if let Some(blocked) = self.blocklist.as_ref().and_then(|blocklist| {
+            let query = request_info.query.original();
+            blocklist.check(query.name(), query.query_type())
+        }) {
+            debug!("request: {} blocked {}", request.id(), request_info.query);
+            return send_blocked(request, blocked, response_edns, response_handle).await;
+        }
+
         let authority = self.find(request_info.query.name());
 
         if let Some(authority) = authority {
@@ -357,6 +553,8 @@ impl Catalog {
                     .as_ref()
                     .map(|arc| Borrow::<Edns>::borrow(arc).clone()),
                 response_handle.clone(),
+                self.request_timeout,
+                self.slow_query_threshold,
             )
             .await
         } else {
@@ -380,6 +578,19 @@ impl Catalog {
         }
     }
 
+    /// The identity of this instance, if the query asks for it
+    fn server_id_query(&self, query: &LowerQuery) -> Option<&str> {
+        let server_id = self.server_id.as_deref()?;
+        if query.query_class() != DNSClass::CH || query.query_type() != RecordType::TXT {
+            return None;
+        }
+
+        let is_id_name = ["hostname.bind.", "id.server."]
+            .iter()
+            .any(|name| Name::from_str(name).map_or(false, |name| query.name() == &name.into()));
+        is_id_name.then_some(server_id)
+    }
+
     /// Recursively searches the catalog for a matching authority
     pub fn find(&self, name: &LowerName) -> Option<&(dyn AuthorityObject + 'static)> {
         debug!("searching authorities for: {}", name);
@@ -403,6 +614,8 @@ async fn lookup<'a, R: ResponseHandler + Unpin>(
     request: &Request,
     response_edns: Option<Edns>,
     response_handle: R,
+    request_timeout: Option<Duration>,
+    slow_query_threshold: Option<Duration>,
 ) -> ResponseInfo {
     let query = request_info.query;
     debug!(
@@ -411,15 +624,35 @@ async fn lookup<'a, R: ResponseHandler + Unpin>(
         authority.origin()
     );
 
-    let (response_header, sections) = build_response(
+    let start = Instant::now();
+    let response = build_response(
         authority,
         request_info,
         request.id(),
         request.header(),
         query,
         request.edns(),
-    )
-    .await;
+    );
+    let (response_header, sections) = match request_timeout {
+        Some(request_timeout) => match tokio::time::timeout(request_timeout, response).await {
+            Ok(response) => response,
+            Err(_) => {
+                warn!(
+                    "request: {} lookup of {} in {} timed out after {:?}",
+                    request.id(),
+                    query,
+                    authority.origin(),
+                    request_timeout
+                );
+                let mut response_header = Header::response_from_request(request.header());
+                response_header.set_response_code(ResponseCode::ServFail);
+                (response_header, LookupSections::empty())
+            }
+        },
+        None => response.await,
+    };
+    let lookup_elapsed = start.elapsed();
+    let response_code = response_header.response_code();
 
     let response = MessageResponseBuilder::new(Some(request.raw_query())).build(
         response_header,
@@ -431,6 +664,22 @@ async fn lookup<'a, R: ResponseHandler + Unpin>(
 
     let result = send_response(response_edns.clone(), response, response_handle.clone()).await;
 
+    let elapsed = start.elapsed();
+    if slow_query_threshold.map_or(false, |threshold| elapsed >= threshold) {
+        warn!(
+            id = request.id(),
+            src = %request.src(),
+            protocol = %request.protocol(),
+            query = %query,
+            zone = %authority.origin(),
+            response_code = %response_code,
+            lookup_ms = lookup_elapsed.as_millis() as u64,
+            send_ms = (elapsed - lookup_elapsed).as_millis() as u64,
+            total_ms = elapsed.as_millis() as u64,
+            "slow query"
+        );
+    }
+
     match result {
         Err(e) => {
             error!("error sending response: {}", e);
@@ -440,6 +689,77 @@ async fn lookup<'a, R: ResponseHandler + Unpin>(
     }
 }
 
+/// Answers a `CH` class `TXT` query for the identity of this instance
+async fn send_server_id<R: ResponseHandler>(
+    request: &Request,
+    server_id: &str,
+    response_edns: Option<Edns>,
+    response_handle: R,
+) -> ResponseInfo {
+    let mut record = Record::from_rdata(
+        request.query().original().name().clone(),
+        0,
+        RData::TXT(TXT::new(vec![server_id.to_string()])),
+    );
+    record.set_dns_class(DNSClass::CH);
+
+    let mut response_header = Header::response_from_request(request.header());
+    response_header.set_authoritative(true);
+
+    let answers = [record];
+    let response = MessageResponseBuilder::new(Some(request.raw_query())).build(
+        response_header,
+        answers.iter(),
+        None.iter(),
+        None.iter(),
+        None.iter(),
+    );
+
+    match send_response(response_edns, response, response_handle).await {
+        Err(e) => {
+            error!("error sending response: {}", e);
+            ResponseInfo::serve_failed()
+        }
+        Ok(i) => i,
+    }
+}
+
+/// Answers a query of a blocked name
+#[cfg(feature = "hickory-resolver")]
+async fn send_blocked<R: ResponseHandler>(
+    request: &Request,
+    blocked: Blocked,
+    response_edns: Option<Edns>,
+    response_handle: R,
+) -> ResponseInfo {
+    let mut response_header = Header::response_from_request(request.header());
+    response_header.set_recursion_available(true);
+    let answers = match blocked {
+        Blocked::NxDomain => {
+            response_header.set_response_code(ResponseCode::NXDomain);
+            Vec::new()
+        }
+        Blocked::NoData => Vec::new(),
+        Blocked::Records(records) => records,
+    };
+
+    let response = MessageResponseBuilder::new(Some(request.raw_query())).build(
+        response_header,
+        answers.iter(),
+        None.iter(),
+        None.iter(),
+        None.iter(),
+    );
+
+    match send_response(response_edns, response, response_handle).await {
+        Err(e) => {
+            error!("error sending response: {}", e);
+            ResponseInfo::serve_failed()
+        }
+        Ok(i) => i,
+    }
+}
+
 #[allow(unused_variables)]
 fn lookup_options_for_edns(edns: Option<&Edns>) -> LookupOptions {
     let edns = match edns {
@@ -502,7 +822,8 @@ async fn build_response(
             .await
         }
         ZoneType::Forward | ZoneType::Hint => {
-            send_forwarded_response(future, request_header, &mut response_header).await
+            send_forwarded_response(future, request_header, &mut response_header, lookup_options)
+                .await
         }
     };
 
@@ -538,6 +859,17 @@ async fn send_authoritative_response(
                 additionals: Box::<AuthLookup>::default(),
             };
         }
+        // The DNAME substitution of the name is too long, see RFC 6672
+        Err(LookupError::ResponseCode(ResponseCode::YXDomain)) => {
+            response_header.set_response_code(ResponseCode::YXDomain);
+            response_header.set_authoritative(true);
+            return LookupSections {
+                answers: Box::<AuthLookup>::default(),
+                ns: Box::<AuthLookup>::default(),
+                soa: Box::<AuthLookup>::default(),
+                additionals: Box::<AuthLookup>::default(),
+            };
+        }
         Err(e) => {
             if e.is_nx_domain() {
                 response_header.set_response_code(ResponseCode::NXDomain);
@@ -548,9 +880,30 @@ async fn send_authoritative_response(
         }
     };
 
+    // an answer synthesized from a wildcard requires the proof that there was no closer match
+    #[cfg(feature = "dnssec")]
+    let next_closer = answers
+        .as_deref()
+        .filter(|_| lookup_options.is_dnssec())
+        .and_then(wildcard_next_closer);
+    #[cfg(not(feature = "dnssec"))]
+    let next_closer = None::<LowerName>;
+
     let (ns, soa) = if answers.is_some() {
         // SOA queries should return the NS records as well.
-        if query.query_type().is_soa() {
+        if let Some(next_closer) = next_closer {
+            debug!("request: {} wildcard answer adding nsecs", request_id);
+            match authority
+                .get_wildcard_proof(&next_closer, lookup_options)
+                .await
+            {
+                Ok(nsecs) => (Some(nsecs), None),
+                Err(e) => {
+                    warn!("failed to lookup wildcard proof: {}", e);
+                    (None, None)
+                }
+            }
+        } else if query.query_type().is_soa() {
             // This was a successful authoritative lookup for SOA:
             //   get the NS records as well.
             match authority.ns(lookup_options).await {
@@ -613,10 +966,12 @@ async fn send_authoritative_response(
     }
 }
 
+#[cfg_attr(not(feature = "dnssec"), allow(unused_variables))]
 async fn send_forwarded_response(
     future: impl Future<Output = Result<Box<dyn LookupObject>, LookupError>>,
     request_header: &Header,
     response_header: &mut Header,
+    lookup_options: LookupOptions,
 ) -> LookupSections {
     response_header.set_recursion_available(true);
     response_header.set_authoritative(false);
@@ -636,8 +991,9 @@ async fn send_forwarded_response(
     } else {
         match future.await {
             Err(e) => {
-                if e.is_nx_domain() {
-                    response_header.set_response_code(ResponseCode::NXDomain);
+                // e.g. NXDomain, or ServFail for answers which failed validation
+                if let LookupError::ResponseCode(code) = e {
+                    response_header.set_response_code(code);
                 }
                 debug!("error resolving: {}", e);
                 Box::new(EmptyLookup)
@@ -646,6 +1002,13 @@ async fn send_forwarded_response(
         }
     };
 
+    #[cfg(feature = "dnssec")]
+    response_header.set_authentic_data(is_authentic_data(
+        request_header,
+        lookup_options,
+        &*answers,
+    ));
+
     LookupSections {
         answers,
         ns: Box::<AuthLookup>::default(),
@@ -654,9 +1017,112 @@ async fn send_forwarded_response(
     }
 }
 
+/// Returns true if the AD bit should be set on the response to a forwarded request
+///
+/// [RFC 6840, section 5.8](https://tools.ietf.org/html/rfc6840#section-5.8), only clients which
+///  requested it, by the DO or AD bit, are signaled authentic data. The AD bit from upstream is
+///  never trusted, every record must have been validated locally.
+#[cfg(feature = "dnssec")]
+fn is_authentic_data(
+    request_header: &Header,
+    lookup_options: LookupOptions,
+    answers: &dyn LookupObject,
+) -> bool {
+    (lookup_options.is_dnssec() || request_header.authentic_data())
+        && !answers.is_empty()
+        && answers.iter().all(|record| record.proof().is_secure())
+}
+
+/// Returns the next closer name of an answer synthesized from a wildcard, i.e. the name with one
+///  more label than the wildcard, see [RFC 4035](https://tools.ietf.org/html/rfc4035#section-3.1.3.3)
+#[cfg(feature = "dnssec")]
+fn wildcard_next_closer(answers: &dyn LookupObject) -> Option<LowerName> {
+    answers.iter().find_map(|record| match record.data() {
+        Some(RData::DNSSEC(DNSSECRData::RRSIG(rrsig)))
+            if rrsig.is_wildcard_expansion(record.name()) =>
+        {
+            let next_closer = record.name().trim_to(usize::from(rrsig.num_labels()) + 1);
+            Some(LowerName::from(next_closer))
+        }
+        _ => None,
+    })
+}
+
 struct LookupSections {
     answers: Box<dyn LookupObject>,
     ns: Box<dyn LookupObject>,
     soa: Box<dyn LookupObject>,
     additionals: Box<dyn LookupObject>,
 }
+
+impl LookupSections {
+    fn empty() -> Self {
+        Self {
+            answers: Box::<AuthLookup>::default(),
+            ns: Box::<AuthLookup>::default(),
+            soa: Box::<AuthLookup>::default(),
+            additionals: Box::<AuthLookup>::default(),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "dnssec"))]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::proto::rr::{dnssec::Proof, rdata::A, Name, RData};
+
+    struct Records(Vec<Record>);
+
+    impl LookupObject for Records {
+        fn is_empty(&self) -> bool {
+            self.0.is_empty()
+        }
+
+        fn iter<'a>(&'a self) -> Box<dyn Iterator<Item = &'a Record> + Send + 'a> {
+            Box::new(self.0.iter())
+        }
+
+        fn take_additionals(&mut self) -> Option<Box<dyn LookupObject>> {
+            None
+        }
+    }
+
+    fn record(proof: Proof) -> Record {
+        let mut record = Record::from_rdata(
+            Name::from_str("www.example.com.").unwrap(),
+            300,
+            RData::A(A::new(93, 184, 216, 34)),
+        );
+        record.set_proof(proof);
+        record
+    }
+
+    #[test]
+    fn test_is_authentic_data() {
+        let dnssec = LookupOptions::default().set_is_dnssec(true);
+        let mut header = Header::new();
+
+        let secure = Records(vec![record(Proof::Secure), record(Proof::Secure)]);
+        assert!(is_authentic_data(&header, dnssec, &secure));
+
+        // only signaled to clients which requested DO or AD
+        assert!(!is_authentic_data(
+            &header,
+            LookupOptions::default(),
+            &secure
+        ));
+        header.set_authentic_data(true);
+        assert!(is_authentic_data(
+            &header,
+            LookupOptions::default(),
+            &secure
+        ));
+
+        // every record must have been validated
+        let mixed = Records(vec![record(Proof::Secure), record(Proof::Indeterminate)]);
+        assert!(!is_authentic_data(&header, dnssec, &mixed));
+        assert!(!is_authentic_data(&header, dnssec, &Records(vec![])));
+    }
+}
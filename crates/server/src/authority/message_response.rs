@@ -5,6 +5,10 @@
 // https://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
+use tracing::warn;
+
+#[cfg(feature = "dnssec")]
+use crate::proto::rr::{dnssec::rdata::DNSSECRData, RData};
 use crate::{
     authority::{
         message_request::{MessageRequest, QueriesEmitAndCount},
@@ -13,13 +17,13 @@ use crate::{
     proto::{
         error::*,
         op::{
-            message::{self, EmitAndCount},
+            message::{self, EmitAndCount, HeaderCounts},
             Edns, Header, ResponseCode,
         },
-        rr::Record,
-        serialize::binary::BinEncoder,
+        rr::{DNSClass, Name, Record, RecordType},
+        serialize::binary::{BinEncodable, BinEncoder, EncodeMode},
     },
-    server::ResponseInfo,
+    server::{ResponseConfig, ResponseInfo},
 };
 
 use super::message_request::WireQuery;
@@ -102,24 +106,158 @@ where
     }
 
     /// Consumes self, and emits to the encoder.
-    pub fn destructive_emit(mut self, encoder: &mut BinEncoder<'_>) -> ProtoResult<ResponseInfo> {
-        // soa records are part of the nameserver section
-        let mut name_servers = self.name_servers.chain(self.soa);
-
-        message::emit_message_parts(
-            &self.header,
-            &mut EmptyOrQueries::from(self.query),
-            &mut self.answers,
-            &mut name_servers,
-            &mut self.additionals,
-            self.edns.as_ref(),
-            &self.sig0,
-            encoder,
-        )
-        .map(Into::into)
+    ///
+    /// This is `destructive_emit_with` and the default `ResponseConfig`.
+    pub fn destructive_emit(self, encoder: &mut BinEncoder<'_>) -> ProtoResult<ResponseInfo> {
+        self.destructive_emit_with(encoder, &ResponseConfig::default())
+    }
+
+    /// Consumes self, and emits to the encoder as many whole RRsets as fit into its maximum size
+    ///
+    /// The first RRset of the answer or authority section which does not fit is left out along
+    ///  with all following records, and the TC flag is set. The EDNS and SIG(0) records are
+    ///  always kept, and the additional section and minimal responses are handled as configured
+    ///  by the `ResponseConfig`.
+    pub fn destructive_emit_with(
+        self,
+        encoder: &mut BinEncoder<'_>,
+        config: &ResponseConfig,
+    ) -> ProtoResult<ResponseInfo> {
+        let include_signature = encoder.mode() != EncodeMode::Signing;
+        let place = encoder.place::<Header>()?;
+        let query_count = EmptyOrQueries::from(self.query).emit(encoder)?;
+
+        let edns = self.edns.map(|mut edns| {
+            // need to commit the error code
+            edns.set_rcode_high(self.header.response_code().high());
+            Record::from(&edns)
+        });
+        if edns.is_none() && self.header.response_code().high() > 0 {
+            warn!(
+                "response code: {} for request: {} requires EDNS but none available",
+                self.header.response_code(),
+                self.header.id()
+            );
+        }
+
+        let sig0 = if include_signature {
+            &self.sig0[..]
+        } else {
+            &[]
+        };
+
+        // the OPT and SIG(0) records are written last, keep room for them
+        let mut reserved = 0;
+        for record in edns.iter().chain(sig0) {
+            reserved += emitted_len(record)?;
+        }
+        let limit = encoder.max_size().saturating_sub(reserved);
+
+        let mut was_truncated = self.header.truncated();
+        let (answer_count, truncated) = emit_rrsets(encoder, self.answers, limit)?;
+        was_truncated |= truncated;
+
+        // with answers, the NS records of the zone and the additional records are optional
+        let minimal = config.minimal_responses
+            && answer_count > 0
+            && self.header.response_code() == ResponseCode::NoError;
+
+        let mut nameserver_count = 0;
+        if !was_truncated {
+            // soa records are part of the nameserver section
+            let name_servers = self
+                .name_servers
+                .chain(self.soa)
+                .filter(|record| !(minimal && rrset_type(record) == RecordType::NS));
+            let (count, truncated) = emit_rrsets(encoder, name_servers, limit)?;
+            nameserver_count = count;
+            was_truncated |= truncated;
+        }
+
+        let mut additional_count = 0;
+        if !was_truncated && !minimal {
+            let (count, truncated) = emit_rrsets(encoder, self.additionals, limit)?;
+            additional_count = count;
+            was_truncated |= truncated && !config.omit_additionals;
+        }
+
+        let (count, truncated) =
+            message::count_was_truncated(encoder.emit_all(edns.iter().chain(sig0)))?;
+        additional_count += count;
+        was_truncated |= truncated;
+
+        let counts = HeaderCounts {
+            query_count,
+            answer_count,
+            nameserver_count,
+            additional_count,
+        };
+        let final_header = message::update_header_counts(&self.header, was_truncated, counts);
+        place.replace(encoder, final_header)?;
+        Ok(final_header.into())
     }
 }
 
+/// Emits whole RRsets until one does not fit within the `limit`
+///
+/// RRsets are runs of records with the same name, class and type, along with the RRSIGs covering
+///  them.
+///
+/// # Return
+///
+/// The number of records written, and whether records were left out.
+fn emit_rrsets<'a>(
+    encoder: &mut BinEncoder<'_>,
+    records: impl Iterator<Item = &'a Record>,
+    limit: usize,
+) -> ProtoResult<(usize, bool)> {
+    let mut count = 0;
+    let mut rrset: Option<(&Name, DNSClass, RecordType)> = None;
+    let mut rrset_start = encoder.offset();
+    let mut rrset_count = 0;
+
+    for record in records {
+        let key = (record.name(), record.dns_class(), rrset_type(record));
+        if rrset != Some(key) {
+            rrset = Some(key);
+            rrset_start = encoder.offset();
+            count += rrset_count;
+            rrset_count = 0;
+        }
+
+        let fits = match record.emit(encoder) {
+            Ok(()) => encoder.offset() <= limit,
+            Err(e) if matches!(e.kind(), ProtoErrorKind::MaxBufferSizeExceeded(_)) => false,
+            Err(e) => return Err(e),
+        };
+        if !fits {
+            encoder.set_offset(rrset_start);
+            encoder.trim();
+            return Ok((count, true));
+        }
+
+        rrset_count += 1;
+    }
+
+    Ok((count + rrset_count, false))
+}
+
+/// The type of the RRset of the record, the covered type for RRSIGs
+fn rrset_type(record: &Record) -> RecordType {
+    match record.data() {
+        #[cfg(feature = "dnssec")]
+        Some(RData::DNSSEC(DNSSECRData::RRSIG(rrsig))) => rrsig.type_covered(),
+        _ => record.record_type(),
+    }
+}
+
+/// The length of the record on the wire, without name compression
+fn emitted_len(record: &Record) -> ProtoResult<usize> {
+    let mut buffer = Vec::new();
+    record.emit(&mut BinEncoder::new(&mut buffer))?;
+    Ok(buffer.len())
+}
+
 /// A builder for MessageResponses
 pub struct MessageResponseBuilder<'q> {
     query: Option<&'q WireQuery>,
@@ -257,7 +395,7 @@ mod tests {
     use std::str::FromStr;
 
     use crate::proto::op::{Header, Message};
-    use crate::proto::rr::{DNSClass, Name, RData, Record, RecordType};
+    use crate::proto::rr::{rdata::NS, DNSClass, Name, RData, Record, RecordType};
     use crate::proto::serialize::binary::BinEncoder;
 
     use super::*;
@@ -294,7 +432,8 @@ mod tests {
 
         let response = Message::from_vec(&buf).expect("failed to decode");
         assert!(response.header().truncated());
-        assert!(response.answer_count() > 1);
+        // the RRset does not fit as a whole, so none of it is written
+        assert_eq!(response.answer_count(), 0);
         // should never have written the name server field...
         assert_eq!(response.name_server_count(), 0);
     }
@@ -332,6 +471,118 @@ mod tests {
         let response = Message::from_vec(&buf).expect("failed to decode");
         assert!(response.header().truncated());
         assert_eq!(response.answer_count(), 0);
-        assert!(response.name_server_count() > 1);
+        assert_eq!(response.name_server_count(), 0);
+        assert_eq!(response.additional_count(), 0);
+    }
+
+    fn a_record(name: &str, address: u8) -> Record {
+        Record::from_rdata(
+            Name::from_str(name).unwrap(),
+            86400,
+            RData::A(Ipv4Addr::new(192, 0, 2, address).into()),
+        )
+    }
+
+    fn ns_record(name: &str, target: &str) -> Record {
+        Record::from_rdata(
+            Name::from_str(name).unwrap(),
+            86400,
+            RData::NS(NS(Name::from_str(target).unwrap())),
+        )
+    }
+
+    fn emit_with(
+        max_size: u16,
+        config: &ResponseConfig,
+        answers: &[Record],
+        name_servers: &[Record],
+        additionals: &[Record],
+    ) -> Message {
+        let mut buf = Vec::with_capacity(512);
+        {
+            let mut encoder = BinEncoder::new(&mut buf);
+            encoder.set_max_size(max_size);
+
+            let mut edns = Edns::new();
+            edns.set_max_payload(max_size);
+            let message = MessageResponse {
+                header: Header::new(),
+                query: None,
+                answers: answers.iter(),
+                name_servers: name_servers.iter(),
+                soa: iter::empty(),
+                additionals: additionals.iter(),
+                sig0: vec![],
+                edns: Some(edns),
+            };
+
+            message
+                .destructive_emit_with(&mut encoder, config)
+                .expect("failed to encode");
+        }
+
+        Message::from_vec(&buf).expect("failed to decode")
+    }
+
+    #[test]
+    fn test_truncation_whole_rrsets() {
+        let mut answers = vec![a_record("a.example.com.", 1), a_record("a.example.com.", 2)];
+        answers.extend((0..64).map(|i| a_record("b.example.com.", i)));
+
+        let response = emit_with(512, &ResponseConfig::default(), &answers, &[], &[]);
+        assert!(response.header().truncated());
+        assert_eq!(response.answers(), &answers[..2]);
+        // the OPT record is kept
+        assert!(response.extensions().is_some());
+
+        let response = emit_with(u16::MAX, &ResponseConfig::default(), &answers, &[], &[]);
+        assert!(!response.header().truncated());
+        assert_eq!(response.answer_count(), 66);
+    }
+
+    #[test]
+    fn test_truncation_omit_additionals() {
+        let answers = [a_record("www.example.com.", 1)];
+        let name_servers = [ns_record("example.com.", "ns.example.com.")];
+        let additionals = (0..64)
+            .map(|i| a_record("ns.example.com.", i))
+            .collect::<Vec<_>>();
+
+        let mut config = ResponseConfig::default();
+        let response = emit_with(512, &config, &answers, &name_servers, &additionals);
+        assert!(!response.header().truncated());
+        assert_eq!(response.answers(), &answers);
+        assert_eq!(response.name_servers(), &name_servers);
+        assert!(response.additionals().is_empty());
+        assert!(response.extensions().is_some());
+
+        config.omit_additionals = false;
+        let response = emit_with(512, &config, &answers, &name_servers, &additionals);
+        assert!(response.header().truncated());
+        assert_eq!(response.answers(), &answers);
+        assert!(response.additionals().is_empty());
+    }
+
+    #[test]
+    fn test_minimal_responses() {
+        let answers = [a_record("www.example.com.", 1)];
+        let name_servers = [ns_record("example.com.", "ns.example.com.")];
+        let additionals = [a_record("ns.example.com.", 2)];
+
+        let mut config = ResponseConfig::default();
+        let response = emit_with(512, &config, &answers, &name_servers, &additionals);
+        assert_eq!(response.name_servers(), &name_servers);
+        assert_eq!(response.additionals(), &additionals);
+
+        config.minimal_responses = true;
+        let response = emit_with(512, &config, &answers, &name_servers, &additionals);
+        assert_eq!(response.answers(), &answers);
+        assert!(response.name_servers().is_empty());
+        assert!(response.additionals().is_empty());
+
+        // referrals keep the NS records and the glue
+        let response = emit_with(512, &config, &[], &name_servers, &additionals);
+        assert_eq!(response.name_servers(), &name_servers);
+        assert_eq!(response.additionals(), &additionals);
     }
 }
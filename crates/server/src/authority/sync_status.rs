@@ -0,0 +1,143 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Synchronization status of the secondary zones of this server
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use crate::proto::rr::LowerName;
+
+/// The synchronization status of a secondary zone
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ZoneSyncStatus {
+    /// the name of the zone
+    pub zone: LowerName,
+    /// the serial of the zone served by this instance, if known
+    pub serial: Option<u32>,
+    /// the serial of the zone on the primary when it was last checked, if known
+    pub primary_serial: Option<u32>,
+    /// the last time the zone was loaded or refreshed from the primary
+    pub last_refresh: SystemTime,
+}
+
+impl ZoneSyncStatus {
+    /// The number of serials the zone is behind the primary, compared per RFC 1982
+    ///
+    /// `None` if either serial is unknown, `Some(0)` if the zone is in sync or ahead.
+    pub fn serial_lag(&self) -> Option<u32> {
+        let lag = self.primary_serial?.wrapping_sub(self.serial?);
+        // a difference of 2^31 or more means the primary is behind
+        Some(if lag < 1 << 31 { lag } else { 0 })
+    }
+
+    /// The time since the zone was last loaded or refreshed
+    pub fn since_refresh(&self) -> Duration {
+        self.last_refresh.elapsed().unwrap_or_default()
+    }
+}
+
+/// Tracks the synchronization status of the secondary zones of a `Catalog`
+///
+/// The tracker is shared, a clone taken before the `Catalog` is handed to the server may be used
+///  by the task refreshing the secondary zones, and to report the status.
+#[derive(Clone, Debug, Default)]
+pub struct ZoneSyncTracker {
+    zones: Arc<Mutex<HashMap<LowerName, ZoneSyncStatus>>>,
+}
+
+impl ZoneSyncTracker {
+    /// Starts tracking a zone which was just loaded, with unknown serials
+    pub(crate) fn track(&self, zone: LowerName) {
+        let status = ZoneSyncStatus {
+            zone: zone.clone(),
+            serial: None,
+            primary_serial: None,
+            last_refresh: SystemTime::now(),
+        };
+        self.lock().insert(zone, status);
+    }
+
+    /// Stops tracking a zone
+    pub(crate) fn untrack(&self, zone: &LowerName) {
+        self.lock().remove(zone);
+    }
+
+    /// Records a refresh of the zone from its primary
+    ///
+    /// # Arguments
+    ///
+    /// * `zone` - the name of the zone, zones which are not secondary zones are ignored
+    /// * `serial` - the serial of the zone served after the refresh
+    /// * `primary_serial` - the serial of the zone on the primary
+    pub fn record_refresh(&self, zone: &LowerName, serial: u32, primary_serial: u32) {
+        if let Some(status) = self.lock().get_mut(zone) {
+            status.serial = Some(serial);
+            status.primary_serial = Some(primary_serial);
+            status.last_refresh = SystemTime::now();
+        }
+    }
+
+    /// Records the serial of the zone on the primary, e.g. from a NOTIFY or an SOA query
+    pub fn record_primary_serial(&self, zone: &LowerName, primary_serial: u32) {
+        if let Some(status) = self.lock().get_mut(zone) {
+            status.primary_serial = Some(primary_serial);
+        }
+    }
+
+    /// The status of all secondary zones, ordered by name
+    pub fn status(&self) -> Vec<ZoneSyncStatus> {
+        let mut status = self.lock().values().cloned().collect::<Vec<_>>();
+        status.sort_by(|a, b| a.zone.cmp(&b.zone));
+        status
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<LowerName, ZoneSyncStatus>> {
+        // the map is always consistent, even if a panic poisoned the lock
+        self.zones
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::proto::rr::Name;
+
+    fn zone() -> LowerName {
+        LowerName::from(Name::from_str("example.com.").unwrap())
+    }
+
+    #[test]
+    fn test_serial_lag() {
+        let tracker = ZoneSyncTracker::default();
+        tracker.track(zone());
+        assert_eq!(tracker.status()[0].serial_lag(), None);
+
+        tracker.record_refresh(&zone(), 10, 10);
+        assert_eq!(tracker.status()[0].serial_lag(), Some(0));
+
+        tracker.record_primary_serial(&zone(), 13);
+        assert_eq!(tracker.status()[0].serial_lag(), Some(3));
+
+        // the serial wrapped around on the primary
+        tracker.record_refresh(&zone(), u32::MAX - 1, 2);
+        assert_eq!(tracker.status()[0].serial_lag(), Some(4));
+
+        tracker.record_refresh(&zone(), 20, 10);
+        assert_eq!(tracker.status()[0].serial_lag(), Some(0));
+
+        tracker.untrack(&zone());
+        assert!(tracker.status().is_empty());
+    }
+}
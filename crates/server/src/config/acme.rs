@@ -0,0 +1,96 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Configuration types for obtaining the TLS certificates with ACME
+
+use std::path::Path;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+/// The directory of Let's Encrypt
+static DEFAULT_DIRECTORY_URL: &str = "https://acme-v02.api.letsencrypt.org/directory";
+
+static DEFAULT_ACCOUNT_PATH: &str = "acme_account.json";
+
+static DEFAULT_RENEW_BEFORE_DAYS: u32 = 30;
+
+/// Configuration of the ACME account with which the `tls_cert` and `tls_certs` are obtained and
+///  renewed, [RFC 8555](https://www.rfc-editor.org/rfc/rfc8555)
+///
+/// A certificate is requested for the `endpoint_name` of each TLS certificate, and written to its
+///  `path` and `private_key` in PEM format, from where the listeners reload it.
+///
+/// ```toml
+/// [acme]
+/// directory_url = "https://acme-v02.api.letsencrypt.org/directory"
+/// contact = ["mailto:hostmaster@example.com"]
+/// account_path = "acme_account.json"
+/// challenge = "tls_alpn_01"
+/// renew_before_days = 30
+/// ```
+#[derive(Deserialize, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct AcmeConfig {
+    /// the directory of the ACME server, defaults to Let's Encrypt
+    directory_url: Option<String>,
+    /// the contact URLs of the account, e.g. `mailto:hostmaster@example.com`
+    #[serde(default)]
+    contact: Vec<String>,
+    /// path of the credentials of the account, which is created if it does not exist
+    account_path: Option<String>,
+    /// the type of challenge which proves the control of the names
+    #[serde(default)]
+    challenge: AcmeChallenge,
+    /// the number of days before the expiration at which certificates are renewed
+    renew_before_days: Option<u32>,
+}
+
+impl AcmeConfig {
+    /// the directory of the ACME server, defaults to Let's Encrypt
+    pub fn get_directory_url(&self) -> &str {
+        self.directory_url
+            .as_deref()
+            .unwrap_or(DEFAULT_DIRECTORY_URL)
+    }
+
+    /// the contact URLs of the account
+    pub fn get_contact(&self) -> &[String] {
+        &self.contact
+    }
+
+    /// path of the credentials of the account, defaults to `acme_account.json`
+    pub fn get_account_path(&self) -> &Path {
+        Path::new(self.account_path.as_deref().unwrap_or(DEFAULT_ACCOUNT_PATH))
+    }
+
+    /// the type of challenge which proves the control of the names
+    pub fn get_challenge(&self) -> AcmeChallenge {
+        self.challenge
+    }
+
+    /// the time before the expiration at which certificates are renewed, defaults to 30 days
+    pub fn get_renew_before(&self) -> Duration {
+        let days = self.renew_before_days.unwrap_or(DEFAULT_RENEW_BEFORE_DAYS);
+        Duration::from_secs(u64::from(days) * 24 * 60 * 60)
+    }
+}
+
+/// The type of challenge which proves the control of the names to the ACME server
+#[derive(Deserialize, PartialEq, Eq, Debug, Clone, Copy, Default)]
+#[non_exhaustive]
+pub enum AcmeChallenge {
+    /// TLS-ALPN-01, the ACME server connects to the HTTPS listener, which must be on port 443,
+    ///  [RFC 8737](https://www.rfc-editor.org/rfc/rfc8737)
+    #[default]
+    #[serde(rename = "tls_alpn_01")]
+    TlsAlpn01,
+    /// DNS-01, the `_acme-challenge` TXT records are published in zones served by this server,
+    ///  which must be the only authoritative name server of the names
+    #[serde(rename = "dns_01")]
+    Dns01,
+}
@@ -0,0 +1,70 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Configuration types for the resolution of the additional records outside of a zone
+
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::resolver::config::{NameServerConfigGroup, ResolverOpts};
+
+static DEFAULT_TIMEOUT_MS: u64 = 500;
+
+/// Configuration of the resolution of the addresses of the targets of the MX and SRV records of a
+///  zone which are outside of it, see `AdditionalResolver`
+///
+/// The addresses are only resolved when `enabled` is set, the server then sends queries to other
+///  servers while it answers the queries of its zones.
+///
+/// ```toml
+/// [[zones]]
+/// zone = "example.com"
+/// zone_type = "Primary"
+/// file = "example.com.zone"
+///
+/// [zones.additionals]
+/// enabled = true
+/// name_servers = [{ socket_addr = "192.0.2.53:53", protocol = "udp", trust_negative_responses = true }]
+/// timeout_ms = 500
+/// ```
+#[derive(Clone, Deserialize, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct AdditionalsConfig {
+    /// the addresses are resolved, defaults to false
+    #[serde(default)]
+    enabled: bool,
+    /// the name servers which resolve the targets, defaults to those of the system
+    name_servers: Option<NameServerConfigGroup>,
+    /// the options of the resolver, e.g. the size of its cache
+    options: Option<ResolverOpts>,
+    /// the time in milliseconds after which the answer is sent without the addresses, defaults to
+    ///  500 milliseconds
+    timeout_ms: Option<u64>,
+}
+
+impl AdditionalsConfig {
+    /// true if the addresses are resolved
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// the name servers which resolve the targets, if not those of the system
+    pub fn get_name_servers(&self) -> Option<&NameServerConfigGroup> {
+        self.name_servers.as_ref()
+    }
+
+    /// the options of the resolver
+    pub fn get_options(&self) -> ResolverOpts {
+        self.options.clone().unwrap_or_default()
+    }
+
+    /// the time after which the answer is sent without the addresses which are not resolved
+    pub fn get_timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS))
+    }
+}
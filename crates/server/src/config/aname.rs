@@ -0,0 +1,66 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Configuration types for the flattening of ANAME records
+
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::resolver::config::{NameServerConfigGroup, ResolverOpts};
+
+static DEFAULT_MIN_REFRESH: u64 = 30;
+static DEFAULT_MAX_REFRESH: u64 = 3600;
+
+/// Configuration of the resolution of the targets of the ANAME records of a zone, see
+///  `AnameFlattener`
+///
+/// ```toml
+/// [[zones]]
+/// zone = "example.com"
+/// zone_type = "Primary"
+/// file = "example.com.zone"
+///
+/// [zones.aname]
+/// name_servers = [{ socket_addr = "192.0.2.53:53", protocol = "udp", trust_negative_responses = true }]
+/// min_refresh = 30
+/// max_refresh = 3600
+/// ```
+#[derive(Clone, Deserialize, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct AnameConfig {
+    /// the name servers which resolve the targets, defaults to those of the system
+    name_servers: Option<NameServerConfigGroup>,
+    /// the options of the resolver
+    options: Option<ResolverOpts>,
+    /// the shortest time in seconds between the resolutions of the targets, defaults to 30 seconds
+    min_refresh: Option<u64>,
+    /// the longest time in seconds between the resolutions of the targets, defaults to an hour
+    max_refresh: Option<u64>,
+}
+
+impl AnameConfig {
+    /// the name servers which resolve the targets, if not those of the system
+    pub fn get_name_servers(&self) -> Option<&NameServerConfigGroup> {
+        self.name_servers.as_ref()
+    }
+
+    /// the options of the resolver
+    pub fn get_options(&self) -> ResolverOpts {
+        self.options.clone().unwrap_or_default()
+    }
+
+    /// the shortest time between the resolutions of the targets
+    pub fn get_min_refresh(&self) -> Duration {
+        Duration::from_secs(self.min_refresh.unwrap_or(DEFAULT_MIN_REFRESH))
+    }
+
+    /// the longest time between the resolutions of the targets
+    pub fn get_max_refresh(&self) -> Duration {
+        Duration::from_secs(self.max_refresh.unwrap_or(DEFAULT_MAX_REFRESH))
+    }
+}
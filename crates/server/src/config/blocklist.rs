@@ -0,0 +1,94 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Configuration types for blocking queries by name
+
+use std::net::IpAddr;
+use std::path::Path;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::resolver::blocklist::{BlockAction, BlocklistSource, SharedBlocklist, DEFAULT_TTL};
+
+static DEFAULT_RELOAD_INTERVAL: u64 = 60;
+
+/// Configuration of the blocklist of the queries, see `Catalog::set_blocklist`
+///
+/// ```toml
+/// [blocklist]
+/// lists = [
+///     { path = "blocklists/hosts", format = "hosts" },
+///     { path = "blocklists/filters.txt", format = "adblock" },
+/// ]
+/// action = "null"
+/// # addresses = ["192.0.2.1"]
+/// ttl = 60
+/// reload_interval = 60
+/// ```
+#[derive(Deserialize, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BlocklistConfig {
+    /// the lists of blocked names, relative paths are relative to the directory of the server
+    lists: Vec<BlocklistSource>,
+    /// the answer to the queries of blocked names, defaults to NXDOMAIN
+    #[serde(default)]
+    action: BlockAction,
+    /// the addresses answered for blocked names instead of the action, e.g. of a server which
+    ///  shows why the name is blocked
+    #[serde(default)]
+    addresses: Vec<IpAddr>,
+    /// the TTL of the answers, defaults to 60 seconds
+    ttl: Option<u32>,
+    /// the time in seconds between checks whether the lists changed, defaults to 60 seconds, 0
+    ///  disables the checks
+    reload_interval: Option<u64>,
+}
+
+impl BlocklistConfig {
+    /// the lists of blocked names
+    pub fn get_lists(&self) -> &[BlocklistSource] {
+        &self.lists
+    }
+
+    /// the answer to the queries of blocked names, `BlockAction::Addresses` if addresses are
+    ///  configured
+    pub fn get_action(&self) -> BlockAction {
+        if self.addresses.is_empty() {
+            self.action.clone()
+        } else {
+            BlockAction::Addresses(self.addresses.clone())
+        }
+    }
+
+    /// the TTL of the answers for blocked names
+    pub fn get_ttl(&self) -> u32 {
+        self.ttl.unwrap_or(DEFAULT_TTL)
+    }
+
+    /// the time between checks whether the lists changed, `None` if they are not checked
+    pub fn get_reload_interval(&self) -> Option<Duration> {
+        match self.reload_interval.unwrap_or(DEFAULT_RELOAD_INTERVAL) {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        }
+    }
+
+    /// Reads the lists, relative paths are relative to `directory`
+    pub fn read(&self, directory: &Path) -> std::io::Result<SharedBlocklist> {
+        let sources = self
+            .lists
+            .iter()
+            .map(|source| BlocklistSource {
+                path: directory.join(&source.path),
+                format: source.format,
+            })
+            .collect();
+
+        SharedBlocklist::from_files(sources, self.get_action(), self.get_ttl())
+    }
+}
@@ -0,0 +1,102 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Configuration types for registering hosts from DHCP lease events and lease files
+
+use std::path::Path;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::store::dhcp::{LeaseFileSource, LeaseFiles};
+
+static DEFAULT_TTL: u32 = 300;
+static DEFAULT_RELOAD_INTERVAL: u64 = 10;
+
+/// Configuration of the zones maintained from the lease events and lease files of a DHCP server
+///
+/// ```toml
+/// [dhcp]
+/// socket_path = "/run/hickory-dns/dhcp.sock"
+/// lease_files = [{ path = "/var/lib/kea/kea-leases4.csv", format = "kea" }]
+/// zone = "lan.example.com."
+/// reverse_zones = ["1.168.192.in-addr.arpa."]
+/// ttl = 300
+/// reload_interval = 10
+/// ```
+#[derive(Deserialize, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct DhcpConfig {
+    /// path of the unix socket on which lease events are accepted, if any
+    socket_path: Option<String>,
+    /// the lease files of the DHCP server, relative paths are relative to the directory of the
+    ///  server
+    #[serde(default)]
+    lease_files: Vec<LeaseFileSource>,
+    /// the zone in which the A and AAAA records of the hosts are maintained
+    zone: String,
+    /// the zones in which the PTR records of the leased addresses are maintained
+    #[serde(default)]
+    reverse_zones: Vec<String>,
+    /// the maximum TTL of the records, defaults to 300 seconds
+    ttl: Option<u32>,
+    /// the time in seconds between checks whether the lease files changed, defaults to 10 seconds
+    reload_interval: Option<u64>,
+}
+
+impl DhcpConfig {
+    /// path of the unix socket on which lease events are accepted, if any
+    pub fn get_socket_path(&self) -> Option<&Path> {
+        self.socket_path.as_deref().map(Path::new)
+    }
+
+    /// the lease files of the DHCP server
+    pub fn get_lease_files(&self) -> &[LeaseFileSource] {
+        &self.lease_files
+    }
+
+    /// the zone in which the A and AAAA records of the hosts are maintained
+    pub fn get_zone(&self) -> &str {
+        &self.zone
+    }
+
+    /// the zones in which the PTR records of the leased addresses are maintained
+    pub fn get_reverse_zones(&self) -> &[String] {
+        &self.reverse_zones
+    }
+
+    /// the maximum TTL of the records, the TTL is also limited by the remaining lease time
+    pub fn get_ttl(&self) -> u32 {
+        self.ttl.unwrap_or(DEFAULT_TTL)
+    }
+
+    /// the time between checks whether the lease files changed
+    pub fn get_reload_interval(&self) -> Duration {
+        Duration::from_secs(
+            self.reload_interval
+                .unwrap_or(DEFAULT_RELOAD_INTERVAL)
+                .max(1),
+        )
+    }
+
+    /// The lease files, relative paths are relative to `directory`, `None` if there are none
+    pub fn lease_files(&self, directory: &Path) -> Option<LeaseFiles> {
+        if self.lease_files.is_empty() {
+            return None;
+        }
+
+        let sources = self
+            .lease_files
+            .iter()
+            .map(|source| LeaseFileSource {
+                path: directory.join(&source.path),
+                format: source.format,
+            })
+            .collect();
+        Some(LeaseFiles::new(sources))
+    }
+}
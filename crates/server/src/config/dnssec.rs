@@ -149,6 +149,56 @@ impl KeyConfig {
         self.is_zone_update_auth.unwrap_or(false)
     }
 
+    /// Reads the private key from the file, in the format of its extension
+    #[cfg(feature = "dnssec")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "dnssec")))]
+    pub fn read_key(&self) -> Result<KeyPair<Private>, String> {
+        use std::fs;
+
+        use tracing::info;
+
+        let key_path = self.key_path();
+        let algorithm = self
+            .algorithm()
+            .map_err(|e| format!("bad algorithm: {e}"))?;
+
+        info!("reading key: {:?}", key_path);
+        // the private key files of BIND, with the DNSKEY in the .key file next to them
+        #[cfg(any(feature = "dnssec-openssl", feature = "dnssec-ring"))]
+        if key_path
+            .extension()
+            .is_some_and(|extension| extension == "private")
+        {
+            use crate::proto::rr::dnssec::bind_key::{self, BindPrivateKey};
+
+            let read = |path: &Path| {
+                fs::read_to_string(path)
+                    .map_err(|e| format!("could not read key from: {path:?}: {e}"))
+            };
+            let public_key_path = key_path.with_extension("key");
+            let (_, dnskey) = bind_key::decode_public_key(&read(&public_key_path)?)
+                .map_err(|e| format!("could not decode key: {public_key_path:?}: {e}"))?;
+            let private_key = read(key_path)?
+                .parse::<BindPrivateKey>()
+                .map_err(|e| format!("could not decode key: {e}"))?;
+            if private_key.algorithm() != algorithm || dnskey.algorithm() != algorithm {
+                return Err(format!("the key is not a {algorithm:?} key: {key_path:?}"));
+            }
+
+            return private_key
+                .to_key_pair(dnskey.public_key())
+                .map_err(|e| format!("could not decode key: {e}"));
+        }
+
+        let format = self.format().map_err(|e| format!("bad key format: {e}"))?;
+        let key_bytes = fs::read(key_path)
+            .map_err(|e| format!("could not read key from: {key_path:?}: {e}"))?;
+
+        format
+            .decode_key(&key_bytes, self.password(), algorithm)
+            .map_err(|e| format!("could not decode key: {e}"))
+    }
+
     /// Tries to read the defined key into a Signer
     #[cfg(feature = "dnssec")]
     #[cfg_attr(docsrs, doc(cfg(feature = "dnssec")))]
@@ -200,8 +250,22 @@ impl Default for PrivateKeyType {
     }
 }
 
+/// The kind of records which prove the non-existence of names in negative answers of a signed zone
+#[derive(Deserialize, PartialEq, Eq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum NxProofKind {
+    /// The chain of NSEC records of the zone, generated when the zone is signed
+    #[default]
+    Nsec,
+    /// NSEC records which only cover the queried name, generated and signed for each response,
+    ///  see [RFC 4470](https://tools.ietf.org/html/rfc4470). The zone can't be enumerated with
+    ///  the proofs, but the zone signing keys are used for each negative answer.
+    MinimallyCoveringNsec,
+}
+
 /// Configuration for a TLS certificate
-#[derive(Deserialize, PartialEq, Eq, Debug)]
+#[derive(Deserialize, PartialEq, Eq, Debug, Clone)]
 pub struct TlsCertConfig {
     path: String,
     endpoint_name: Option<String>,
@@ -259,36 +323,12 @@ impl TlsCertConfig {
 ///  keys = [ "my_rsa_2048|RSASHA256", "/path/to/my_ed25519|ED25519" ]
 #[cfg(feature = "dnssec")]
 fn load_key(zone_name: Name, key_config: &KeyConfig) -> Result<SigSigner, String> {
-    use tracing::info;
-
-    use std::fs::File;
-    use std::io::Read;
-
     use time::Duration;
 
-    let key_path = key_config.key_path();
     let algorithm = key_config
         .algorithm()
         .map_err(|e| format!("bad algorithm: {e}"))?;
-    let format = key_config
-        .format()
-        .map_err(|e| format!("bad key format: {e}"))?;
-
-    // read the key in
-    let key: KeyPair<Private> = {
-        info!("reading key: {:?}", key_path);
-
-        let mut file = File::open(key_path)
-            .map_err(|e| format!("error opening private key file: {key_path:?}: {e}"))?;
-
-        let mut key_bytes = Vec::with_capacity(256);
-        file.read_to_end(&mut key_bytes)
-            .map_err(|e| format!("could not read key from: {key_path:?}: {e}"))?;
-
-        format
-            .decode_key(&key_bytes, key_config.password(), algorithm)
-            .map_err(|e| format!("could not decode key: {e}"))?
-    };
+    let key = key_config.read_key()?;
 
     let name = key_config
         .signer_name()
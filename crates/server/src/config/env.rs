@@ -0,0 +1,110 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Overlays of the configuration with `HICKORY_*` environment variables
+//!
+//! The name of a variable after the prefix is the path of the key, lowercased, with `__`
+//!  between the keys of nested tables and the indexes of arrays, e.g. `HICKORY_LISTEN_PORT`,
+//!  `HICKORY_TLS_CERT__PATH` or `HICKORY_ZONES__0__FILE`. An index one past the end of an
+//!  array appends to it. The values are parsed as JSON, e.g. `["0.0.0.0", "::"]` or `true`, and
+//!  are strings otherwise, or if they replace a string and are not quoted. The prefix alone does
+//!  not name a key, such a variable is ignored.
+
+use serde_json::{Map, Value};
+
+use crate::error::{ConfigErrorKind, ConfigResult};
+
+/// The prefix of the environment variables which override the configuration
+pub const ENV_PREFIX: &str = "HICKORY_";
+
+/// An environment variable which overrides a key of the configuration
+pub(super) struct Overlay {
+    name: String,
+    path: Vec<Segment>,
+    value: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Segment {
+    Index(usize),
+    Key(String),
+}
+
+/// The variables with the prefix, in the order in which they are applied
+pub(super) fn overlays<I>(vars: I) -> Vec<Overlay>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let mut overlays = vars
+        .into_iter()
+        .filter_map(|(name, value)| {
+            let path = name
+                .strip_prefix(ENV_PREFIX)
+                .filter(|key| !key.is_empty())?
+                .split("__")
+                .map(|segment| match segment.parse() {
+                    Ok(index) => Segment::Index(index),
+                    Err(_) => Segment::Key(segment.to_ascii_lowercase()),
+                })
+                .collect();
+            Some(Overlay { name, path, value })
+        })
+        .collect::<Vec<_>>();
+
+    // arrays are appended to in the order of their indexes
+    overlays.sort_by(|a, b| a.path.cmp(&b.path));
+    overlays
+}
+
+/// Sets the values of the variables in the configuration
+pub(super) fn apply(config: &mut Value, overlays: Vec<Overlay>) -> ConfigResult<()> {
+    for Overlay { name, path, value } in overlays {
+        let target =
+            lookup(config, &path).map_err(|message| ConfigErrorKind::Env { name, message })?;
+
+        *target = match serde_json::from_str::<Value>(&value) {
+            Ok(parsed) if parsed.is_string() || !target.is_string() => parsed,
+            _ => Value::String(value),
+        };
+    }
+
+    Ok(())
+}
+
+/// The value at the path, the tables and array elements on the way are created if missing
+fn lookup<'v>(mut value: &'v mut Value, path: &[Segment]) -> Result<&'v mut Value, String> {
+    for segment in path {
+        if value.is_null() {
+            *value = match segment {
+                Segment::Index(_) => Value::Array(Vec::new()),
+                Segment::Key(_) => Value::Object(Map::new()),
+            };
+        }
+
+        value = match (segment, value) {
+            (Segment::Key(key), _) if key.is_empty() => return Err("empty key".to_string()),
+            (Segment::Key(key), Value::Object(table)) => {
+                table.entry(key.clone()).or_insert(Value::Null)
+            }
+            (Segment::Index(index), Value::Array(array)) => {
+                if *index > array.len() {
+                    return Err(format!(
+                        "index {index} is past the end of an array of {} elements",
+                        array.len()
+                    ));
+                } else if *index == array.len() {
+                    array.push(Value::Null);
+                }
+                &mut array[*index]
+            }
+            (Segment::Key(key), _) => return Err(format!("{key} is not in a table")),
+            (Segment::Index(index), _) => return Err(format!("{index} is not in an array")),
+        };
+    }
+
+    Ok(value)
+}
@@ -0,0 +1,99 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Configuration types for authenticated DNS over HTTPS
+
+use std::path::Path;
+
+use serde::Deserialize;
+
+/// Authentication and quota configuration for DNS over HTTPS requests
+///
+/// ```toml
+/// [https_auth]
+/// allow_anonymous = false
+/// client_ca_path = "sec/clients.ca.pem"
+///
+/// [[https_auth.profiles]]
+/// name = "restricted"
+/// queries_per_second = 10
+/// burst = 20
+/// denied_query_types = ["ANY", "AXFR"]
+///
+/// [[https_auth.principals]]
+/// name = "alice"
+/// bearer_tokens = ["s3cr3t"]
+/// client_certificates = ["sec/alice.cert.pem"]
+/// profile = "restricted"
+/// ```
+#[derive(Deserialize, PartialEq, Eq, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct HttpsAuthConfig {
+    /// allow requests which present no credentials, these are not subject to any quota
+    allow_anonymous: Option<bool>,
+    /// PEM encoded CA certificates used to verify client certificates
+    client_ca_path: Option<String>,
+    /// named policy profiles, referenced by principals
+    #[serde(default)]
+    profiles: Vec<PolicyProfileConfig>,
+    /// the set of principals allowed to issue requests
+    #[serde(default)]
+    principals: Vec<PrincipalConfig>,
+}
+
+impl HttpsAuthConfig {
+    /// true if requests without any credentials should be served
+    pub fn allow_anonymous(&self) -> bool {
+        self.allow_anonymous.unwrap_or(false)
+    }
+
+    /// path to the CA certificates used to verify client certificates, if mTLS is enabled
+    pub fn get_client_ca_path(&self) -> Option<&Path> {
+        self.client_ca_path.as_deref().map(Path::new)
+    }
+
+    /// the named policy profiles
+    pub fn get_profiles(&self) -> &[PolicyProfileConfig] {
+        &self.profiles
+    }
+
+    /// the principals allowed to issue requests
+    pub fn get_principals(&self) -> &[PrincipalConfig] {
+        &self.principals
+    }
+}
+
+/// A named set of limits and policies which may be shared between principals
+#[derive(Deserialize, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyProfileConfig {
+    /// name of the profile, referenced from `PrincipalConfig::profile`
+    pub name: String,
+    /// sustained number of queries per second allowed for each principal, unlimited if not set
+    pub queries_per_second: Option<u32>,
+    /// number of queries which may be issued in a burst, defaults to `queries_per_second`
+    pub burst: Option<u32>,
+    /// query types which principals with this profile may not query, e.g. `["ANY", "AXFR"]`
+    #[serde(default)]
+    pub denied_query_types: Vec<String>,
+}
+
+/// A named principal, identified by bearer tokens or client certificates
+#[derive(Deserialize, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PrincipalConfig {
+    /// name of the principal, used in logs
+    pub name: String,
+    /// tokens accepted in an `Authorization: Bearer <token>` header
+    #[serde(default)]
+    pub bearer_tokens: Vec<String>,
+    /// paths to PEM encoded client certificates which identify this principal
+    #[serde(default)]
+    pub client_certificates: Vec<String>,
+    /// the profile to apply to this principal, no limits are applied if not set
+    pub profile: Option<String>,
+}
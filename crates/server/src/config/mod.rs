@@ -6,14 +6,39 @@
 // copied, modified, or distributed except according to those terms.
 
 //! Configuration module for the server binary, `named`.
-
+//!
+//! The configuration is read from a TOML file, or from a YAML file if its extension is `yaml` or
+//!  `yml`. With [`Config::read_config_with_env`], any key may be overridden by a `HICKORY_*`
+//!  environment variable, and the binary overrides the ports with its command line flags. The
+//!  order of precedence, from lowest to highest, is: the defaults, the configuration file, the
+//!  environment variables and the command line.
+
+pub mod acme;
+#[cfg(feature = "hickory-resolver")]
+#[cfg_attr(docsrs, doc(cfg(feature = "resolver")))]
+pub mod additionals;
+#[cfg(feature = "hickory-resolver")]
+#[cfg_attr(docsrs, doc(cfg(feature = "resolver")))]
+pub mod aname;
+#[cfg(feature = "hickory-resolver")]
+#[cfg_attr(docsrs, doc(cfg(feature = "resolver")))]
+pub mod blocklist;
+pub mod dhcp;
 pub mod dnssec;
+#[cfg(feature = "toml")]
+mod env;
+pub mod https_auth;
+pub mod named_conf;
+pub mod validate;
+pub mod xdp;
 
+use std::collections::HashMap;
 #[cfg(feature = "toml")]
 use std::fs::File;
 #[cfg(feature = "toml")]
 use std::io::Read;
 use std::net::{AddrParseError, Ipv4Addr, Ipv6Addr};
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::time::Duration;
@@ -28,14 +53,19 @@ use crate::proto::rr::Name;
 use crate::authority::ZoneType;
 #[cfg(feature = "toml")]
 use crate::error::ConfigResult;
+use crate::server::{AcceptConfig, AdmissionConfig, Overflow, ResponseConfig};
 use crate::store::StoreConfig;
 
+#[cfg(feature = "toml")]
+pub use self::env::ENV_PREFIX;
+
 static DEFAULT_PATH: &str = "/var/named"; // TODO what about windows (do I care? ;)
 static DEFAULT_PORT: u16 = 53;
 static DEFAULT_TLS_PORT: u16 = 853;
 static DEFAULT_HTTPS_PORT: u16 = 443;
 static DEFAULT_QUIC_PORT: u16 = 853; // https://www.ietf.org/archive/id/draft-ietf-dprive-dnsoquic-11.html#name-reservation-of-dedicated-po
 static DEFAULT_H3_PORT: u16 = 443;
+static DEFAULT_H3_MAX_CONCURRENT_STREAMS: u32 = 100;
 static DEFAULT_TCP_REQUEST_TIMEOUT: u64 = 5;
 
 /// Server configuration
@@ -57,10 +87,46 @@ pub struct Config {
     quic_listen_port: Option<u16>,
     /// HTTP/3 port to listen on
     h3_listen_port: Option<u16>,
+    /// Number of requests a client may send at a time on each HTTP/3 connection
+    h3_max_concurrent_streams: Option<u32>,
     /// Timeout associated to a request before it is closed.
     tcp_request_timeout: Option<u64>,
+    /// Number of connections open at a time on each TCP and TLS listener
+    max_tcp_connections: Option<NonZeroUsize>,
+    /// Number of connections open at a time from one IP address on each TCP and TLS listener
+    max_tcp_connections_per_ip: Option<NonZeroUsize>,
+    /// Time in seconds after which TCP and TLS connections are closed
+    max_tcp_connection_lifetime: Option<u64>,
+    /// TCP connections start with a PROXY protocol v2 header from a load balancer
+    #[serde(default)]
+    tcp_proxy_protocol: bool,
+    /// TLS connections start with a PROXY protocol v2 header from a load balancer
+    #[serde(default)]
+    tls_proxy_protocol: bool,
+    /// Time in milliseconds in which the lookups of a query must complete
+    request_timeout_ms: Option<u64>,
+    /// Time in milliseconds after which the handling of a query is logged as slow
+    slow_query_threshold_ms: Option<u64>,
+    /// Number of requests handled at a time by each UDP, TCP and TLS listener
+    max_requests_in_flight: Option<NonZeroUsize>,
+    /// Number of requests waiting for the requests in flight of each listener
+    max_requests_queued: Option<usize>,
+    /// Number of requests handled at a time for each TCP and TLS connection
+    max_requests_in_flight_per_connection: Option<NonZeroUsize>,
+    /// Whether the requests over the limits are dropped or answered with SERVFAIL
+    #[serde(default)]
+    request_overflow: Overflow,
+    /// Number of worker threads, and of UDP and TCP listeners sharing each address
+    workers: Option<NonZeroUsize>,
+    /// Maximum size of responses over UDP
+    max_udp_payload: Option<u16>,
+    /// Leave out the optional records of the authority and additional sections
+    #[serde(default)]
+    minimal_responses: bool,
     /// Level at which to log, default is INFO
     log_level: Option<String>,
+    /// Identity of this instance, returned for NSID and `hostname.bind.` queries
+    server_id: Option<String>,
     /// Base configuration directory, i.e. root path for zones
     directory: Option<String>,
     /// List of configurations for zones
@@ -69,6 +135,22 @@ pub struct Config {
     /// Certificate to associate to TLS connections (currently the same is used for HTTPS and TLS)
     #[cfg(feature = "dnssec")]
     tls_cert: Option<dnssec::TlsCertConfig>,
+    /// Additional certificates for TLS connections, selected by the SNI of clients with their
+    ///  `endpoint_name`, the `tls_cert` is used for all other names
+    #[cfg(feature = "dnssec")]
+    #[serde(default)]
+    tls_certs: Vec<dnssec::TlsCertConfig>,
+    /// Authentication and quotas for DNS over HTTPS requests
+    https_auth: Option<https_auth::HttpsAuthConfig>,
+    /// Zones maintained from the lease events of a DHCP server
+    dhcp: Option<dhcp::DhcpConfig>,
+    /// Obtaining and renewing the TLS certificates with ACME
+    acme: Option<acme::AcmeConfig>,
+    /// Names which are blocked, e.g. the domains of advertising and tracking
+    #[cfg(feature = "hickory-resolver")]
+    blocklist: Option<blocklist::BlocklistConfig>,
+    /// Experimental AF_XDP sockets answering simple authoritative UDP queries
+    xdp: Option<xdp::XdpConfig>,
     /// Networks denied to access the server
     #[serde(default)]
     deny_networks: Vec<IpNet>,
@@ -79,12 +161,54 @@ pub struct Config {
 
 impl Config {
     /// read a Config file from the file specified at path.
+    ///
+    /// The file is YAML if its extension is `yaml` or `yml`, and TOML otherwise.
     #[cfg(feature = "toml")]
     pub fn read_config(path: &Path) -> ConfigResult<Self> {
         let mut file = File::open(path)?;
-        let mut toml = String::new();
-        file.read_to_string(&mut toml)?;
-        Self::from_toml(&toml)
+        let mut text = String::new();
+        file.read_to_string(&mut text)?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            #[cfg(feature = "yaml")]
+            Some("yaml") | Some("yml") => Self::from_yaml(&text),
+            _ => Self::from_toml(&text),
+        }
+    }
+
+    /// read a Config file like `read_config`, with its keys overridden by the environment
+    ///
+    /// The names of the variables are [`ENV_PREFIX`] followed by the path of the key, with `__`
+    ///  between the keys of nested tables and the indexes of arrays, e.g. `HICKORY_LISTEN_PORT`,
+    ///  `HICKORY_TLS_CERT__PATH` or `HICKORY_ZONES__0__FILE`. The values are parsed as JSON,
+    ///  e.g. `["0.0.0.0", "::"]`, and are strings otherwise, or if they replace a string and are
+    ///  not quoted.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The configuration file
+    /// * `vars` - The environment variables, usually `std::env::vars()`
+    #[cfg(feature = "toml")]
+    pub fn read_config_with_env<I>(path: &Path, vars: I) -> ConfigResult<Self>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let overlays = env::overlays(vars);
+        if overlays.is_empty() {
+            return Self::read_config(path);
+        }
+
+        let mut file = File::open(path)?;
+        let mut text = String::new();
+        file.read_to_string(&mut text)?;
+        let mut config: serde_json::Value =
+            match path.extension().and_then(|extension| extension.to_str()) {
+                #[cfg(feature = "yaml")]
+                Some("yaml") | Some("yml") => serde_yaml::from_str(&text)?,
+                _ => basic_toml::from_str(&text)?,
+            };
+
+        env::apply(&mut config, overlays)?;
+        Ok(serde_json::from_value(config)?)
     }
 
     /// Read a [`Config`] from the given TOML string.
@@ -93,6 +217,13 @@ impl Config {
         Ok(basic_toml::from_str(toml)?)
     }
 
+    /// Read a [`Config`] from the given YAML string.
+    #[cfg(feature = "yaml")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "yaml")))]
+    pub fn from_yaml(yaml: &str) -> ConfigResult<Self> {
+        Ok(serde_yaml::from_str(yaml)?)
+    }
+
     /// set of listening ipv4 addresses (for TCP and UDP)
     pub fn get_listen_addrs_ipv4(&self) -> Result<Vec<Ipv4Addr>, AddrParseError> {
         self.listen_addrs_ipv4.iter().map(|s| s.parse()).collect()
@@ -128,6 +259,40 @@ impl Config {
         self.h3_listen_port.unwrap_or(DEFAULT_H3_PORT)
     }
 
+    /// overrides the port on which to listen, e.g. with the one given on the command line
+    pub fn set_listen_port(&mut self, port: u16) {
+        self.listen_port = Some(port);
+    }
+
+    /// overrides the port on which to listen for TLS connections
+    pub fn set_tls_listen_port(&mut self, port: u16) {
+        self.tls_listen_port = Some(port);
+    }
+
+    /// overrides the port on which to listen for HTTPS connections
+    pub fn set_https_listen_port(&mut self, port: u16) {
+        self.https_listen_port = Some(port);
+    }
+
+    /// overrides the port on which to listen for QUIC connections
+    pub fn set_quic_listen_port(&mut self, port: u16) {
+        self.quic_listen_port = Some(port);
+    }
+
+    /// overrides the port on which to listen for HTTP/3 connections
+    pub fn set_h3_listen_port(&mut self, port: u16) {
+        self.h3_listen_port = Some(port);
+    }
+
+    /// the number of requests a client may send at a time on each HTTP/3 connection, 100 if not
+    ///  configured
+    ///
+    /// see `ServerFuture::register_h3_listener_with_max_concurrent_streams`
+    pub fn get_h3_max_concurrent_streams(&self) -> u32 {
+        self.h3_max_concurrent_streams
+            .unwrap_or(DEFAULT_H3_MAX_CONCURRENT_STREAMS)
+    }
+
     /// default timeout for all TCP connections before forcibly shutdown
     pub fn get_tcp_request_timeout(&self) -> Duration {
         Duration::from_secs(
@@ -136,6 +301,88 @@ impl Config {
         )
     }
 
+    /// whether the connections to the TCP listeners start with a PROXY protocol v2 header, which
+    ///  passes the address of the client from a load balancer
+    ///
+    /// see `ServerFuture::set_proxy_protocol`
+    pub fn get_tcp_proxy_protocol(&self) -> bool {
+        self.tcp_proxy_protocol
+    }
+
+    /// whether the connections to the TLS listeners start with a PROXY protocol v2 header, which
+    ///  is sent ahead of the TLS handshake
+    ///
+    /// see `ServerFuture::set_proxy_protocol`
+    pub fn get_tls_proxy_protocol(&self) -> bool {
+        self.tls_proxy_protocol
+    }
+
+    /// the time in which the lookups of a query must complete, lookups which take longer are
+    ///  answered with SERVFAIL
+    ///
+    /// see `Catalog::set_request_timeout`
+    pub fn get_request_timeout(&self) -> Option<Duration> {
+        self.request_timeout_ms.map(Duration::from_millis)
+    }
+
+    /// the time after which the handling of a query is logged as slow
+    ///
+    /// see `Catalog::set_slow_query_threshold`
+    pub fn get_slow_query_threshold(&self) -> Option<Duration> {
+        self.slow_query_threshold_ms.map(Duration::from_millis)
+    }
+
+    /// the number of worker threads, and of UDP and TCP listeners bound to each address
+    ///
+    /// The listeners share their address with `SO_REUSEPORT`, see
+    ///  `ServerFuture::register_sharded_socket`. If not configured, a single listener is bound to
+    ///  each address.
+    pub fn get_workers(&self) -> Option<NonZeroUsize> {
+        self.workers
+    }
+
+    /// how the responses are sized and which optional records they carry
+    ///
+    /// see `ServerFuture::set_response_config`
+    pub fn get_response_config(&self) -> ResponseConfig {
+        let mut response_config = ResponseConfig::default();
+        if let Some(max_udp_payload) = self.max_udp_payload {
+            response_config.max_udp_payload = max_udp_payload;
+        }
+        response_config.minimal_responses = self.minimal_responses;
+        response_config
+    }
+
+    /// the limits of the connections to the TCP and TLS listeners, the connections are not
+    ///  limited by default
+    ///
+    /// see `ServerFuture::set_accept_config`, the idle timeout of the connections is
+    ///  `get_tcp_request_timeout`
+    pub fn get_accept_config(&self) -> AcceptConfig {
+        AcceptConfig {
+            max_connections: self.max_tcp_connections,
+            max_connections_per_ip: self.max_tcp_connections_per_ip,
+            max_connection_lifetime: self.max_tcp_connection_lifetime.map(Duration::from_secs),
+            ..AcceptConfig::default()
+        }
+    }
+
+    /// the limits of the requests handled at a time by the UDP, TCP and TLS listeners, the
+    ///  requests are not limited by default
+    ///
+    /// see `ServerFuture::set_admission_config`
+    pub fn get_admission_config(&self) -> AdmissionConfig {
+        let default = AdmissionConfig::default();
+        AdmissionConfig {
+            max_in_flight: self.max_requests_in_flight,
+            max_queued: self.max_requests_queued.unwrap_or(default.max_queued),
+            max_in_flight_per_connection: self
+                .max_requests_in_flight_per_connection
+                .unwrap_or(default.max_in_flight_per_connection),
+            overflow: self.request_overflow,
+        }
+    }
+
     /// specify the log level which should be used, ["Trace", "Debug", "Info", "Warn", "Error"]
     pub fn get_log_level(&self) -> tracing::Level {
         if let Some(ref level_str) = self.log_level {
@@ -145,6 +392,13 @@ impl Config {
         }
     }
 
+    /// the identity of this instance, e.g. to tell apart the instances behind an anycast address
+    ///
+    /// see `Catalog::set_server_id`
+    pub fn get_server_id(&self) -> Option<&str> {
+        self.server_id.as_deref()
+    }
+
     /// the path for all zone configurations, defaults to `/var/named`
     pub fn get_directory(&self) -> &Path {
         self.directory
@@ -168,6 +422,44 @@ impl Config {
         }
     }
 
+    /// the additional tls certificates, selected by the server name requested by clients
+    pub fn get_tls_certs(&self) -> &[dnssec::TlsCertConfig] {
+        cfg_if! {
+            if #[cfg(feature = "dnssec")] {
+                &self.tls_certs
+            } else {
+                &[]
+            }
+        }
+    }
+
+    /// authentication and quota configuration for DNS over HTTPS, if any
+    pub fn get_https_auth(&self) -> Option<&https_auth::HttpsAuthConfig> {
+        self.https_auth.as_ref()
+    }
+
+    /// the zones maintained from DHCP lease events, if any
+    pub fn get_dhcp(&self) -> Option<&dhcp::DhcpConfig> {
+        self.dhcp.as_ref()
+    }
+
+    /// the ACME account with which the TLS certificates are obtained and renewed, if any
+    pub fn get_acme(&self) -> Option<&acme::AcmeConfig> {
+        self.acme.as_ref()
+    }
+
+    /// the blocklist of the queries, if any
+    #[cfg(feature = "hickory-resolver")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "resolver")))]
+    pub fn get_blocklist(&self) -> Option<&blocklist::BlocklistConfig> {
+        self.blocklist.as_ref()
+    }
+
+    /// the AF_XDP sockets answering simple authoritative UDP queries, if any
+    pub fn get_xdp(&self) -> Option<&xdp::XdpConfig> {
+        self.xdp.as_ref()
+    }
+
     /// get the networks denied access to this server
     pub fn get_deny_networks(&self) -> &[IpNet] {
         &self.deny_networks
@@ -197,6 +489,27 @@ pub struct ZoneConfig {
     /// Keys for use by the zone
     #[serde(default)]
     pub keys: Vec<dnssec::KeyConfig>,
+    /// The kind of proofs of non-existence in negative answers, if the zone is signed
+    #[serde(default)]
+    pub nx_proof_kind: dnssec::NxProofKind,
+    /// How queries of type ANY are answered
+    #[serde(default)]
+    pub any_query: AnyQueryPolicy,
+    /// How the records of the RRsets of answers are ordered
+    #[serde(default)]
+    pub rotation: RotationPolicy,
+    /// The orders of the records of some RRsets, which override the `rotation` of the zone
+    #[serde(default)]
+    pub rotations: Vec<RotationConfig>,
+    /// The resolution of the targets of the ANAME records of the zone outside of it
+    #[cfg(feature = "hickory-resolver")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "resolver")))]
+    pub aname: Option<aname::AnameConfig>,
+    /// The resolution of the addresses of the targets outside of the zone for the additional
+    ///  section, off unless enabled
+    #[cfg(feature = "hickory-resolver")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "resolver")))]
+    pub additionals: Option<additionals::AdditionalsConfig>,
     /// Store configurations, TODO: allow chained Stores
     #[serde(default)]
     pub stores: Option<StoreConfig>,
@@ -231,6 +544,14 @@ impl ZoneConfig {
             allow_axfr,
             enable_dnssec,
             keys,
+            nx_proof_kind: dnssec::NxProofKind::default(),
+            any_query: AnyQueryPolicy::default(),
+            rotation: RotationPolicy::default(),
+            rotations: Vec::new(),
+            #[cfg(feature = "hickory-resolver")]
+            aname: None,
+            #[cfg(feature = "hickory-resolver")]
+            additionals: None,
             stores: None,
         }
     }
@@ -282,4 +603,100 @@ impl ZoneConfig {
     pub fn get_keys(&self) -> &[dnssec::KeyConfig] {
         &self.keys
     }
+
+    /// the kind of proofs of non-existence in negative answers of the signed zone
+    pub fn get_nx_proof_kind(&self) -> dnssec::NxProofKind {
+        self.nx_proof_kind
+    }
+
+    /// how queries of type ANY are answered
+    pub fn get_any_query(&self) -> AnyQueryPolicy {
+        self.any_query
+    }
+
+    /// how the records of the RRsets of answers are ordered
+    pub fn get_rotation(&self) -> RotationPolicy {
+        self.rotation
+    }
+
+    /// the orders of the records of some RRsets
+    pub fn get_rotations(&self) -> &[RotationConfig] {
+        &self.rotations
+    }
+
+    /// the resolution of the targets of the ANAME records outside of the zone, if they are
+    ///  flattened
+    #[cfg(feature = "hickory-resolver")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "resolver")))]
+    pub fn get_aname(&self) -> Option<&aname::AnameConfig> {
+        self.aname.as_ref()
+    }
+
+    /// the resolution of the addresses of the targets outside of the zone, if it is enabled
+    #[cfg(feature = "hickory-resolver")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "resolver")))]
+    pub fn get_additionals(&self) -> Option<&additionals::AdditionalsConfig> {
+        self.additionals
+            .as_ref()
+            .filter(|additionals| additionals.is_enabled())
+    }
+}
+
+/// How queries of type ANY are answered, see [RFC 8482](https://tools.ietf.org/html/rfc8482)
+///
+/// The complete answers can be much larger than the queries, which makes them a tool of
+///  amplification attacks. Names which do not exist are answered as for any other type.
+#[derive(Deserialize, PartialEq, Eq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum AnyQueryPolicy {
+    /// All the RRsets of the name
+    #[default]
+    All,
+    /// One RRset of the name, with its RRSIGs if the zone is signed. A CNAME is preferred, as it
+    ///  is the only RRset of its name.
+    OneRrset,
+    /// A synthesized HINFO record, with the CPU `RFC8482` and an empty OS, unless the name has a
+    ///  CNAME or HINFO RRset. The record is signed for each answer if the zone is signed.
+    Hinfo,
+}
+
+/// How the records of an RRset are ordered in answers
+///
+/// Most clients use the first address of an answer, the order spreads them across the addresses.
+#[derive(Deserialize, PartialEq, Eq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum RotationPolicy {
+    /// The order of the zone
+    #[default]
+    Fixed,
+    /// The records are rotated by one for each answer
+    RoundRobin,
+    /// The records are shuffled for each answer, a record with more weight is more likely to be
+    ///  first. The records have the same weight unless configured in a `RotationConfig`.
+    Weighted,
+}
+
+/// The order of the records of the RRsets of a name
+///
+/// ```toml
+/// [[zones.rotations]]
+/// name = "www"
+/// record_type = "A"
+/// policy = "weighted"
+/// weights = { "192.0.2.1" = 3, "192.0.2.2" = 1 }
+/// ```
+#[derive(Deserialize, PartialEq, Eq, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RotationConfig {
+    /// the name, relative to the zone unless fully qualified
+    pub name: String,
+    /// the type of the RRset, all the RRsets of the name if unset
+    pub record_type: Option<String>,
+    /// the order of the records
+    pub policy: RotationPolicy,
+    /// the weights of the records by their data, which requires the `record_type`, defaults to 1
+    #[serde(default)]
+    pub weights: HashMap<String, u32>,
 }
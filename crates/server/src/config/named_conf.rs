@@ -0,0 +1,938 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Conversion of BIND's `named.conf` to the configuration of the server
+//!
+//! The statements which are converted are `options` (directory, listen-on, forwarders,
+//!  allow-query, blackhole, allow-transfer and a few limits), `acl`, `key` and `zone` of the
+//!  types primary, secondary, forward and hint. Everything else is reported as a warning, as
+//!  are the parts of converted statements which have no equivalent, e.g. per zone ACLs.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display, Write};
+use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use ipnet::IpNet;
+
+/// A directive of `named.conf` which is not converted, or only in part
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Warning {
+    /// The file and line of the directive, e.g. `named.conf:12`
+    pub location: String,
+    /// What is not converted, and why
+    pub message: String,
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+/// The result of converting a `named.conf`
+#[derive(Clone, Debug)]
+pub struct Conversion {
+    /// The configuration of the server, in TOML
+    pub toml: String,
+    /// The directives which are not converted, or only in part
+    pub warnings: Vec<Warning>,
+}
+
+/// Reads a `named.conf` and converts it, see `convert`
+///
+/// Relative paths of `include` statements are relative to the directory of the file.
+pub fn convert_file(path: &Path) -> Result<Conversion, String> {
+    let statements = read_statements(path)?;
+    Ok(Converter::default().convert(&statements))
+}
+
+/// Converts the text of a `named.conf` to the configuration of the server
+///
+/// The error is a syntax error, or an `include` which can not be read.
+///
+/// # Arguments
+///
+/// * `named_conf` - The text of the `named.conf`
+/// * `include_dir` - The directory of relative paths of `include` statements, if any
+pub fn convert(named_conf: &str, include_dir: Option<&Path>) -> Result<Conversion, String> {
+    let statements = parse(named_conf, "named.conf", include_dir)?;
+    Ok(Converter::default().convert(&statements))
+}
+
+/// A statement, with its words and its block, e.g. `zone "example.com" IN { ... };`
+#[derive(Clone, Debug)]
+struct Statement {
+    location: String,
+    words: Vec<String>,
+    block: Option<Vec<Self>>,
+}
+
+impl Statement {
+    fn keyword(&self) -> &str {
+        self.words.first().map_or("", String::as_str)
+    }
+
+    fn block(&self) -> &[Self] {
+        self.block.as_deref().unwrap_or(&[])
+    }
+
+    /// The single argument of an option, e.g. `directory "/var/named";`
+    fn argument(&self) -> Option<&str> {
+        match self.words.as_slice() {
+            [_, argument] => Some(argument),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Open,
+    Close,
+    End,
+}
+
+fn read_statements(path: &Path) -> Result<Vec<Statement>, String> {
+    let text =
+        fs::read_to_string(path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+    parse(&text, &path.display().to_string(), path.parent())
+}
+
+fn parse(text: &str, source: &str, include_dir: Option<&Path>) -> Result<Vec<Statement>, String> {
+    let tokens = tokenize(text, source)?;
+    let mut tokens = tokens.into_iter().peekable();
+    let statements = statements(&mut tokens, source, false)?;
+
+    // the included files are parsed in place of the include statements
+    let mut expanded = Vec::with_capacity(statements.len());
+    for statement in statements {
+        if statement.keyword() != "include" {
+            expanded.push(statement);
+            continue;
+        }
+
+        let Some(file) = statement.argument() else {
+            return Err(format!("{}: include needs a file", statement.location));
+        };
+        let path = match include_dir {
+            Some(dir) => dir.join(file),
+            None => PathBuf::from(file),
+        };
+        expanded.extend(read_statements(&path)?);
+    }
+
+    Ok(expanded)
+}
+
+fn tokenize(text: &str, source: &str) -> Result<Vec<(Token, String)>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+
+    while let Some(c) = chars.next() {
+        let location = format!("{source}:{line}");
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => (),
+            '#' => while chars.next_if(|&c| c != '\n').is_some() {},
+            '/' if chars.peek() == Some(&'/') => while chars.next_if(|&c| c != '\n').is_some() {},
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                loop {
+                    match chars.next() {
+                        Some('/') if previous == '*' => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            previous = c;
+                        }
+                        None => return Err(format!("{location}: unterminated comment")),
+                    }
+                }
+            }
+            '"' => {
+                let mut word = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => word.extend(chars.next()),
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            word.push(c);
+                        }
+                        None => return Err(format!("{location}: unterminated string")),
+                    }
+                }
+                tokens.push((Token::Word(word), location));
+            }
+            '{' => tokens.push((Token::Open, location)),
+            '}' => tokens.push((Token::Close, location)),
+            ';' => tokens.push((Token::End, location)),
+            c => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(|&c| !c.is_whitespace() && !"{};\"".contains(c)) {
+                    word.push(c);
+                }
+                tokens.push((Token::Word(word), location));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn statements<I>(
+    tokens: &mut std::iter::Peekable<I>,
+    source: &str,
+    in_block: bool,
+) -> Result<Vec<Statement>, String>
+where
+    I: Iterator<Item = (Token, String)>,
+{
+    let mut statements = Vec::new();
+    loop {
+        let mut statement = match tokens.peek() {
+            None if in_block => return Err(format!("{source}: missing }}")),
+            None => return Ok(statements),
+            Some((Token::Close, _)) if in_block => {
+                tokens.next();
+                return Ok(statements);
+            }
+            Some((_, location)) => Statement {
+                location: location.clone(),
+                words: Vec::new(),
+                block: None,
+            },
+        };
+
+        loop {
+            match tokens.next() {
+                Some((Token::Word(word), location)) => {
+                    if statement.block.is_some() {
+                        return Err(format!("{location}: expected ; after }}"));
+                    }
+                    statement.words.push(word);
+                }
+                Some((Token::Open, location)) => {
+                    if statement.block.is_some() {
+                        return Err(format!("{location}: expected ; after }}"));
+                    }
+                    statement.block = Some(self::statements(tokens, source, true)?);
+                }
+                Some((Token::End, _)) => break,
+                Some((Token::Close, location)) => {
+                    return Err(format!("{location}: expected ; before }}"))
+                }
+                None => return Err(format!("{}: missing ;", statement.location)),
+            }
+        }
+
+        statements.push(statement);
+    }
+}
+
+/// A zone of the converted configuration
+struct Zone {
+    name: String,
+    zone_type: &'static str,
+    file: Option<String>,
+    allow_axfr: Option<bool>,
+    store: Store,
+}
+
+enum Store {
+    Default,
+    Sqlite { allow_update: bool },
+    Forward(Vec<SocketAddr>),
+    Recursor { roots: Option<String> },
+}
+
+/// The networks of an address match list, as allowed and denied networks of the server
+#[derive(Default)]
+struct Networks {
+    allow: Vec<IpNet>,
+    deny: Vec<IpNet>,
+    any: bool,
+    keys: Vec<String>,
+}
+
+#[derive(Default)]
+struct Converter {
+    warnings: Vec<Warning>,
+    acls: HashMap<String, Vec<Statement>>,
+    /// The names of the TSIG keys, which may be named in address match lists
+    keys: HashSet<String>,
+    directory: Option<String>,
+    listen_port: Option<u16>,
+    listen_addrs_ipv4: Vec<String>,
+    listen_addrs_ipv6: Vec<String>,
+    allow_networks: Vec<IpNet>,
+    deny_networks: Vec<IpNet>,
+    server_id: Option<String>,
+    max_tcp_connections: Option<u32>,
+    forwarders: Vec<SocketAddr>,
+    forwarders_location: String,
+    recursion: Option<bool>,
+    allow_transfer: Option<bool>,
+    zones: Vec<Zone>,
+}
+
+impl Converter {
+    fn warn(&mut self, statement: &Statement, message: impl Into<String>) {
+        self.warnings.push(Warning {
+            location: statement.location.clone(),
+            message: message.into(),
+        });
+    }
+
+    fn convert(mut self, statements: &[Statement]) -> Conversion {
+        // ACLs and keys may be used before they are defined
+        for statement in statements {
+            match (statement.keyword(), statement.argument()) {
+                ("acl", Some(name)) => {
+                    self.acls
+                        .insert(name.to_string(), statement.block().to_vec());
+                }
+                ("key", Some(name)) => self.key(name, statement),
+                _ => (),
+            }
+        }
+
+        for statement in statements {
+            match statement.keyword() {
+                "acl" | "key" if statement.argument().is_some() => (),
+                "options" => self.options(statement.block()),
+                "zone" => self.zone(statement),
+                "view" => self.warn(
+                    statement,
+                    "views are not supported, the view and its zones are not converted",
+                ),
+                keyword => self.warn(statement, format!("unsupported statement {keyword}")),
+            }
+        }
+
+        self.forwarding_and_recursion();
+
+        Conversion {
+            toml: self.toml(),
+            warnings: self.warnings,
+        }
+    }
+
+    fn key(&mut self, name: &str, statement: &Statement) {
+        let algorithm = statement
+            .block()
+            .iter()
+            .find(|option| option.keyword() == "algorithm")
+            .and_then(Statement::argument)
+            .unwrap_or("hmac-md5")
+            .to_string();
+
+        self.warn(
+            statement,
+            format!(
+                "TSIG key {name} ({algorithm}) is not converted, zone transfers are not \
+                 authenticated and updates are authenticated with SIG(0) keys"
+            ),
+        );
+        self.keys.insert(name.to_string());
+    }
+
+    fn options(&mut self, options: &[Statement]) {
+        for option in options {
+            match option.keyword() {
+                "directory" => self.directory = self.string_argument(option),
+                "listen-on" => self.listen_on(option, false),
+                "listen-on-v6" => self.listen_on(option, true),
+                "forwarders" => {
+                    let forwarders = self.forwarders(option);
+                    self.forwarders = forwarders;
+                    self.forwarders_location = option.location.clone();
+                }
+                "forward" => {
+                    if option.argument() == Some("first") {
+                        self.warn(
+                            option,
+                            "forward first is not supported, queries are only forwarded",
+                        );
+                    }
+                }
+                "recursion" => self.recursion = self.bool_argument(option),
+                "allow-query" => {
+                    let networks = self.networks(option, option.block());
+                    self.restrict(option, networks);
+                }
+                "blackhole" => {
+                    let networks = self.networks(option, option.block());
+                    if !networks.deny.is_empty() {
+                        self.warn(option, "negated elements of blackhole are not converted");
+                    }
+                    self.deny_networks.extend(networks.allow);
+                }
+                "allow-transfer" => {
+                    let allow_transfer = self.allow_transfer(option);
+                    self.allow_transfer = Some(allow_transfer);
+                }
+                "server-id" => match option.argument() {
+                    Some("none") => self.server_id = None,
+                    Some("hostname") => self.warn(
+                        option,
+                        "server-id hostname is not supported, set server_id to the name",
+                    ),
+                    _ => self.server_id = self.string_argument(option),
+                },
+                "tcp-clients" => {
+                    self.max_tcp_connections =
+                        self.string_argument(option).and_then(|n| n.parse().ok())
+                }
+                "dnssec-validation" => self.warn(
+                    option,
+                    "dnssec-validation is converted for the recursor only, enable the \
+                     validate option of forwarders",
+                ),
+                "pid-file"
+                | "dump-file"
+                | "statistics-file"
+                | "memstatistics-file"
+                | "session-keyfile"
+                | "managed-keys-directory"
+                | "bindkeys-file"
+                | "version"
+                | "hostname" => self.warn(option, format!("{} is ignored", option.keyword())),
+                keyword => self.warn(option, format!("unsupported option {keyword}")),
+            }
+        }
+    }
+
+    fn string_argument(&mut self, statement: &Statement) -> Option<String> {
+        let argument = statement.argument().map(str::to_string);
+        if argument.is_none() {
+            self.warn(
+                statement,
+                format!("{} needs a single argument", statement.keyword()),
+            );
+        }
+        argument
+    }
+
+    fn bool_argument(&mut self, statement: &Statement) -> Option<bool> {
+        match statement.argument() {
+            Some("yes") | Some("true") | Some("1") => Some(true),
+            Some("no") | Some("false") | Some("0") => Some(false),
+            _ => {
+                self.warn(
+                    statement,
+                    format!("{} needs yes or no", statement.keyword()),
+                );
+                None
+            }
+        }
+    }
+
+    /// `listen-on [port N] { address_match_list };`
+    fn listen_on(&mut self, option: &Statement, ipv6: bool) {
+        match option.words.as_slice() {
+            [_] => (),
+            [_, port, number] if port == "port" => match number.parse() {
+                Ok(number) if self.listen_port.map_or(true, |port| port == number) => {
+                    self.listen_port = Some(number)
+                }
+                Ok(_) => self.warn(
+                    option,
+                    "listening on different ports is not supported, the first port is used",
+                ),
+                Err(_) => self.warn(option, format!("bad port {number}")),
+            },
+            _ => self.warn(option, "only the port option of listen-on is supported"),
+        }
+
+        let unspecified = if ipv6 { "::" } else { "0.0.0.0" };
+        let mut addrs = Vec::new();
+        for element in option.block() {
+            match element.words.as_slice() {
+                [any] if any == "any" => addrs.push(unspecified.to_string()),
+                [none] if none == "none" => (),
+                [addr] if IpAddr::from_str(addr).map_or(false, |ip| ip.is_ipv6() == ipv6) => {
+                    addrs.push(addr.clone())
+                }
+                _ => self.warn(
+                    element,
+                    format!(
+                        "only addresses, any and none are supported in {}",
+                        option.keyword()
+                    ),
+                ),
+            }
+        }
+
+        // the server listens on IPv4 by default, but not on IPv6
+        if ipv6 {
+            self.listen_addrs_ipv6 = addrs;
+        } else if addrs.is_empty()
+            && !option.block().is_empty()
+            && self.listen_addrs_ipv6.is_empty()
+        {
+            self.warn(
+                option,
+                "the server always listens on an address, 0.0.0.0 if no other is configured",
+            );
+        } else {
+            self.listen_addrs_ipv4 = addrs;
+        }
+    }
+
+    /// `forwarders [port N] { address [port N]; ... };`
+    fn forwarders(&mut self, option: &Statement) -> Vec<SocketAddr> {
+        let default_port = match option.words.as_slice() {
+            [_, port, number] if port == "port" => number.parse().unwrap_or(53),
+            _ => 53,
+        };
+
+        let mut forwarders = Vec::new();
+        for forwarder in option.block() {
+            let (addr, port) = match forwarder.words.as_slice() {
+                [addr] => (addr, default_port),
+                [addr, port, number] if port == "port" => {
+                    (addr, number.parse().unwrap_or(default_port))
+                }
+                _ => {
+                    self.warn(
+                        forwarder,
+                        "only addresses and ports of forwarders are supported",
+                    );
+                    continue;
+                }
+            };
+            match IpAddr::from_str(addr) {
+                Ok(ip) => forwarders.push(SocketAddr::new(ip, port)),
+                Err(e) => self.warn(forwarder, format!("bad forwarder {addr}: {e}")),
+            }
+        }
+        forwarders
+    }
+
+    /// Flattens an address match list, with the ACLs it names
+    fn networks(&mut self, option: &Statement, elements: &[Statement]) -> Networks {
+        let mut networks = Networks::default();
+        self.collect_networks(option, elements, false, &mut networks, 0);
+        networks
+    }
+
+    fn collect_networks(
+        &mut self,
+        option: &Statement,
+        elements: &[Statement],
+        negated: bool,
+        networks: &mut Networks,
+        depth: usize,
+    ) {
+        for element in elements {
+            let mut words = element.words.iter().map(String::as_str).collect::<Vec<_>>();
+            let mut negated = negated;
+            match words.first() {
+                Some(&"!") => {
+                    negated = !negated;
+                    words.remove(0);
+                }
+                Some(word) if word.starts_with('!') => {
+                    negated = !negated;
+                    words[0] = &word[1..];
+                }
+                _ => (),
+            }
+
+            let push = |networks: &mut Networks, net: IpNet| {
+                if negated {
+                    networks.deny.push(net)
+                } else {
+                    networks.allow.push(net)
+                }
+            };
+
+            match words.as_slice() {
+                [] if element.block.is_some() => {
+                    self.collect_networks(option, element.block(), negated, networks, depth + 1)
+                }
+                ["any"] if negated => {
+                    networks
+                        .deny
+                        .push(IpNet::from_str("0.0.0.0/0").expect("bad network"));
+                    networks
+                        .deny
+                        .push(IpNet::from_str("::/0").expect("bad network"));
+                }
+                ["any"] => networks.any = true,
+                ["none"] if negated => networks.any = true,
+                ["none"] => (),
+                ["localhost"] => {
+                    push(
+                        networks,
+                        IpNet::from_str("127.0.0.1/32").expect("bad network"),
+                    );
+                    push(networks, IpNet::from_str("::1/128").expect("bad network"));
+                }
+                ["key", key] => {
+                    if !self.keys.contains(*key) {
+                        self.warn(element, format!("unknown key {key}"));
+                    }
+                    networks.keys.push(key.to_string());
+                }
+                [name] if self.acls.contains_key(*name) && depth < 16 => {
+                    let acl = self.acls[*name].clone();
+                    self.collect_networks(option, &acl, negated, networks, depth + 1);
+                }
+                [network] => match IpNet::from_str(network)
+                    .or_else(|_| IpAddr::from_str(network).map(IpNet::from))
+                {
+                    Ok(net) => push(networks, net),
+                    Err(_) => self.warn(
+                        element,
+                        format!("{network} is not supported in {}", option.keyword()),
+                    ),
+                },
+                _ => self.warn(
+                    element,
+                    format!(
+                        "unsupported element {} in {}",
+                        element.words.join(" "),
+                        option.keyword()
+                    ),
+                ),
+            }
+        }
+    }
+
+    /// Converts `allow-query` to the networks allowed and denied to access the server
+    ///
+    /// The allowed networks of the server take precedence over the denied ones, the order of
+    ///  the elements is lost if they are mixed.
+    fn restrict(&mut self, option: &Statement, networks: Networks) {
+        if !networks.keys.is_empty() {
+            self.warn(option, "keys are not supported in allow-query");
+        }
+
+        match (networks.any, networks.deny.is_empty()) {
+            (true, true) => (),
+            (true, false) => {
+                if !networks.allow.is_empty() {
+                    self.warn(
+                        option,
+                        "the allowed networks take precedence over the negated ones",
+                    );
+                }
+                self.allow_networks.extend(networks.allow);
+                self.deny_networks.extend(networks.deny);
+            }
+            (false, true) if networks.allow.is_empty() => {
+                self.deny_networks
+                    .push(IpNet::from_str("0.0.0.0/0").expect("bad network"));
+                self.deny_networks
+                    .push(IpNet::from_str("::/0").expect("bad network"));
+            }
+            (false, true) => self.allow_networks.extend(networks.allow),
+            (false, false) => {
+                self.warn(
+                    option,
+                    "negated elements without any are not converted, only the allowed networks \
+                     have access",
+                );
+                self.allow_networks.extend(networks.allow);
+            }
+        }
+    }
+
+    /// Converts `allow-transfer` to whether zone transfers are allowed
+    fn allow_transfer(&mut self, option: &Statement) -> bool {
+        let networks = self.networks(option, option.block());
+        let allowed = networks.any || !networks.allow.is_empty() || !networks.keys.is_empty();
+        if allowed && !(networks.any && networks.deny.is_empty()) {
+            self.warn(
+                option,
+                "zone transfers are allowed to all clients with access to the server, not only \
+                 to the listed ones",
+            );
+        }
+        allowed
+    }
+
+    fn zone(&mut self, statement: &Statement) {
+        let (name, class) = match statement.words.as_slice() {
+            [_, name] => (name.clone(), "IN"),
+            [_, name, class] => (name.clone(), class.as_str()),
+            _ => {
+                self.warn(statement, "zone needs a name");
+                return;
+            }
+        };
+        if !class.eq_ignore_ascii_case("IN") {
+            self.warn(
+                statement,
+                format!("zone {name} of class {class} is not converted"),
+            );
+            return;
+        }
+
+        let mut zone_type = None;
+        let mut file = None;
+        let mut allow_axfr = None;
+        let mut allow_update = false;
+        let mut forwarders = Vec::new();
+        for option in statement.block() {
+            match option.keyword() {
+                "type" => zone_type = option.argument().map(str::to_string),
+                "file" => file = self.string_argument(option),
+                "allow-transfer" => allow_axfr = Some(self.allow_transfer(option)),
+                "allow-update" => {
+                    let networks = self.networks(option, option.block());
+                    allow_update = networks.any || !networks.allow.is_empty();
+                    if !networks.keys.is_empty() {
+                        allow_update = true;
+                        self.warn(
+                            option,
+                            "updates are authenticated with SIG(0) keys, add the KEY records of \
+                             the clients to the zone",
+                        );
+                    } else if allow_update {
+                        self.warn(
+                            option,
+                            "updates are authenticated with SIG(0) keys, not allowed by address",
+                        );
+                    }
+                }
+                "forwarders" => forwarders = self.forwarders(option),
+                "forward" => {
+                    if option.argument() == Some("first") {
+                        self.warn(
+                            option,
+                            "forward first is not supported, queries are only forwarded",
+                        );
+                    }
+                }
+                "masters" | "primaries" => self.warn(
+                    option,
+                    "zone transfers from primaries are not supported, the zone file is served as \
+                     it is",
+                ),
+                "notify" | "also-notify" => self.warn(option, "notifications are not supported"),
+                "allow-query" => self.warn(
+                    option,
+                    "allow-query of zones is not supported, only of the server",
+                ),
+                keyword => self.warn(option, format!("unsupported zone option {keyword}")),
+            }
+        }
+
+        let zone_type = zone_type.unwrap_or_default();
+        let (zone_type, store) = match zone_type.as_str() {
+            "master" | "primary" if allow_update => ("Primary", Store::Sqlite { allow_update }),
+            "master" | "primary" => ("Primary", Store::Default),
+            "slave" | "secondary" => ("Secondary", Store::Default),
+            "forward" if forwarders.is_empty() => {
+                self.warn(statement, format!("forward zone {name} has no forwarders"));
+                return;
+            }
+            "forward" => ("Forward", Store::Forward(forwarders)),
+            "hint" => {
+                // the roots of the recursor, which resolves names if recursion is enabled
+                if self.recursion != Some(false) {
+                    self.zones.push(Zone {
+                        name,
+                        zone_type: "Hint",
+                        file: None,
+                        allow_axfr: None,
+                        store: Store::Recursor { roots: file },
+                    });
+                }
+                return;
+            }
+            zone_type => {
+                self.warn(
+                    statement,
+                    format!("zone {name} of type {zone_type} is not converted"),
+                );
+                return;
+            }
+        };
+
+        if file.is_none() && !matches!(store, Store::Forward(_)) {
+            self.warn(statement, format!("zone {name} has no file"));
+            return;
+        }
+
+        self.zones.push(Zone {
+            name,
+            zone_type,
+            file,
+            allow_axfr,
+            store,
+        });
+    }
+
+    /// The forwarders of the options forward the root zone, unless it is resolved recursively
+    fn forwarding_and_recursion(&mut self) {
+        let has_root = self.zones.iter().any(|zone| zone.name == ".");
+        if !self.forwarders.is_empty() {
+            if has_root {
+                self.warnings.push(Warning {
+                    location: self.forwarders_location.clone(),
+                    message: "the forwarders are not converted, as the root zone is configured"
+                        .to_string(),
+                });
+            } else {
+                self.zones.push(Zone {
+                    name: ".".to_string(),
+                    zone_type: "Forward",
+                    file: None,
+                    allow_axfr: None,
+                    store: Store::Forward(std::mem::take(&mut self.forwarders)),
+                });
+            }
+        } else if self.recursion == Some(true) && !has_root {
+            self.zones.push(Zone {
+                name: ".".to_string(),
+                zone_type: "Hint",
+                file: None,
+                allow_axfr: None,
+                store: Store::Recursor { roots: None },
+            });
+        }
+    }
+
+    fn toml(&self) -> String {
+        let mut toml = String::from(
+            "## The configuration of the Hickory DNS server, converted from named.conf\n\n",
+        );
+
+        if let Some(directory) = &self.directory {
+            writeln!(toml, "directory = {}", quote(directory)).expect("write failed");
+        }
+        if let Some(port) = self.listen_port {
+            writeln!(toml, "listen_port = {port}").expect("write failed");
+        }
+        if !self.listen_addrs_ipv4.is_empty() {
+            writeln!(
+                toml,
+                "listen_addrs_ipv4 = {}",
+                array(&self.listen_addrs_ipv4)
+            )
+            .expect("write failed");
+        }
+        if !self.listen_addrs_ipv6.is_empty() {
+            writeln!(
+                toml,
+                "listen_addrs_ipv6 = {}",
+                array(&self.listen_addrs_ipv6)
+            )
+            .expect("write failed");
+        }
+        if let Some(server_id) = &self.server_id {
+            writeln!(toml, "server_id = {}", quote(server_id)).expect("write failed");
+        }
+        if let Some(max_tcp_connections) = self.max_tcp_connections.filter(|n| *n > 0) {
+            writeln!(toml, "max_tcp_connections = {max_tcp_connections}").expect("write failed");
+        }
+        if !self.allow_networks.is_empty() {
+            writeln!(toml, "allow_networks = {}", array(&self.allow_networks))
+                .expect("write failed");
+        }
+        if !self.deny_networks.is_empty() {
+            writeln!(toml, "deny_networks = {}", array(&self.deny_networks)).expect("write failed");
+        }
+
+        for zone in &self.zones {
+            toml.push_str("\n[[zones]]\n");
+            writeln!(toml, "zone = {}", quote(&zone.name)).expect("write failed");
+            writeln!(toml, "zone_type = {}", quote(zone.zone_type)).expect("write failed");
+
+            let allow_axfr = zone.allow_axfr.or(self.allow_transfer);
+            if let (Some(allow_axfr), "Primary" | "Secondary") = (allow_axfr, zone.zone_type) {
+                writeln!(toml, "allow_axfr = {allow_axfr}").expect("write failed");
+            }
+
+            match &zone.store {
+                Store::Default => {
+                    if let Some(file) = &zone.file {
+                        writeln!(toml, "file = {}", quote(file)).expect("write failed");
+                    }
+                }
+                Store::Sqlite { allow_update } => {
+                    let file = zone.file.as_deref().unwrap_or_default();
+                    let journal = Path::new(file).with_extension("jrnl");
+                    writeln!(
+                        toml,
+                        "stores = {{ type = \"sqlite\", zone_file_path = {}, journal_file_path = {}, allow_update = {allow_update} }}",
+                        quote(file),
+                        quote(&journal.to_string_lossy()),
+                    )
+                    .expect("write failed");
+                }
+                Store::Forward(forwarders) => {
+                    let name_servers = forwarders
+                        .iter()
+                        .flat_map(|addr| {
+                            ["udp", "tcp"].map(|protocol| {
+                                format!(
+                                    "{{ socket_addr = \"{addr}\", protocol = \"{protocol}\", trust_negative_responses = false }}"
+                                )
+                            })
+                        })
+                        .collect::<Vec<_>>()
+                        .join(",\n    ");
+                    writeln!(
+                        toml,
+                        "stores = {{ type = \"forward\", name_servers = [\n    {name_servers},\n] }}"
+                    )
+                    .expect("write failed");
+                }
+                Store::Recursor { roots } => {
+                    toml.push_str("stores = { type = \"recursor\"");
+                    if let Some(roots) = roots {
+                        write!(toml, ", roots = {}", quote(roots)).expect("write failed");
+                    }
+                    toml.push_str(" }\n");
+                }
+            }
+        }
+
+        toml
+    }
+}
+
+/// A TOML basic string
+fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => write!(quoted, "\\u{:04X}", c as u32).expect("write failed"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// A TOML array of strings
+fn array<T: Display>(values: &[T]) -> String {
+    let values = values
+        .iter()
+        .map(|value| quote(&value.to_string()))
+        .collect::<Vec<_>>();
+    format!("[{}]", values.join(", "))
+}
@@ -0,0 +1,414 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Validation of a configuration without loading it, e.g. in CI before a deployment
+
+use std::collections::HashSet;
+use std::fmt::{self, Display};
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use super::dnssec::TlsCertConfig;
+use super::{Config, ZoneConfig};
+use crate::proto::rr::Name;
+use crate::store::file::{check_zone, Check, Diagnostic, Severity};
+use crate::store::in_memory::Rotation;
+use crate::store::StoreConfig;
+
+/// A problem found in a configuration by [`Config::validate`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Problem {
+    /// How serious the problem is, errors prevent the server from starting
+    pub severity: Severity,
+    /// The part of the configuration with the problem, e.g. `zones[example.com.]` or `tls_cert`
+    pub section: String,
+    /// A description of the problem
+    pub message: String,
+}
+
+impl Problem {
+    fn error(section: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            section: section.into(),
+            message: message.into(),
+        }
+    }
+
+    fn warning(section: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            section: section.into(),
+            message: message.into(),
+        }
+    }
+
+    fn from_diagnostic(section: &str, diagnostic: &Diagnostic) -> Self {
+        let mut message = format!("{}: ", diagnostic.check);
+        if let Some(name) = &diagnostic.name {
+            message.push_str(&format!("{name} "));
+        }
+        if let Some(record_type) = &diagnostic.record_type {
+            message.push_str(&format!("{record_type} "));
+        }
+        message.push_str(&diagnostic.message);
+
+        // the server still loads the zones with problems other than syntax errors
+        let severity = match diagnostic.check {
+            Check::Syntax => diagnostic.severity,
+            _ => diagnostic.severity.min(Severity::Warning),
+        };
+
+        Self {
+            severity,
+            section: section.to_string(),
+            message,
+        }
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.severity, self.section, self.message)
+    }
+}
+
+/// A listener of the server, as configured
+struct Listener {
+    protocol: &'static str,
+    transport: &'static str,
+    port: u16,
+}
+
+impl Config {
+    /// Checks the configuration without binding any sockets, and returns all the problems found
+    ///
+    /// The zone files are parsed and checked like with `check_zone`, the key and certificate
+    ///  files are read, and the listeners are checked for ports used by more than one of them.
+    ///  The findings of the zone checks other than syntax errors are at most warnings, as the
+    ///  zones are still served with them.
+    ///
+    /// # Arguments
+    ///
+    /// * `zone_dir` - The directory of the zone files, usually `get_directory()`
+    pub fn validate(&self, zone_dir: &Path) -> Vec<Problem> {
+        let mut problems = Vec::new();
+
+        if !zone_dir.is_dir() {
+            problems.push(Problem::error(
+                "directory",
+                format!("{} is not a directory", zone_dir.display()),
+            ));
+        }
+
+        if let Some(log_level) = &self.log_level {
+            if tracing::Level::from_str(log_level).is_err() {
+                problems.push(Problem::warning(
+                    "log_level",
+                    format!("unknown level {log_level}, INFO is used instead"),
+                ));
+            }
+        }
+
+        self.validate_listeners(&mut problems);
+        self.validate_tls_certs(zone_dir, &mut problems);
+
+        let mut origins = HashSet::new();
+        for zone_config in &self.zones {
+            let origin = match zone_config.get_zone() {
+                Ok(origin) => origin,
+                Err(e) => {
+                    problems.push(Problem::error(
+                        format!("zones[{}]", zone_config.zone),
+                        format!("bad zone name: {e}"),
+                    ));
+                    continue;
+                }
+            };
+            if !origins.insert(origin.clone()) {
+                problems.push(Problem::error(
+                    format!("zones[{origin}]"),
+                    "the zone is configured more than once",
+                ));
+            }
+
+            validate_zone(&origin, zone_config, zone_dir, &mut problems);
+        }
+
+        #[cfg(feature = "hickory-resolver")]
+        if let Some(blocklist) = &self.blocklist {
+            if let Err(e) = blocklist.read(zone_dir) {
+                problems.push(Problem::error(
+                    "blocklist",
+                    format!("failed to read the lists: {e}"),
+                ));
+            }
+        }
+
+        problems
+    }
+
+    fn validate_listeners(&self, problems: &mut Vec<Problem>) {
+        for addr in &self.listen_addrs_ipv4 {
+            if let Err(e) = Ipv4Addr::from_str(addr) {
+                problems.push(Problem::error(
+                    "listen_addrs_ipv4",
+                    format!("bad address {addr}: {e}"),
+                ));
+            }
+        }
+        for addr in &self.listen_addrs_ipv6 {
+            if let Err(e) = Ipv6Addr::from_str(addr) {
+                problems.push(Problem::error(
+                    "listen_addrs_ipv6",
+                    format!("bad address {addr}: {e}"),
+                ));
+            }
+        }
+
+        #[cfg_attr(not(feature = "dns-over-tls"), allow(unused_mut, clippy::useless_vec))]
+        let mut listeners = vec![
+            Listener {
+                protocol: "DNS",
+                transport: "UDP",
+                port: self.get_listen_port(),
+            },
+            Listener {
+                protocol: "DNS",
+                transport: "TCP",
+                port: self.get_listen_port(),
+            },
+        ];
+
+        let secure_ports = [
+            ("tls_listen_port", self.tls_listen_port),
+            ("https_listen_port", self.https_listen_port),
+            ("quic_listen_port", self.quic_listen_port),
+            ("h3_listen_port", self.h3_listen_port),
+        ];
+        if self.get_tls_cert().is_none() {
+            for (key, _) in secure_ports.iter().filter(|(_, port)| port.is_some()) {
+                problems.push(Problem::warning(
+                    *key,
+                    "the port is not listened on without a tls_cert",
+                ));
+            }
+        } else {
+            #[cfg(feature = "dns-over-tls")]
+            listeners.push(Listener {
+                protocol: "TLS",
+                transport: "TCP",
+                port: self.get_tls_listen_port(),
+            });
+            #[cfg(feature = "dns-over-https")]
+            listeners.push(Listener {
+                protocol: "HTTPS",
+                transport: "TCP",
+                port: self.get_https_listen_port(),
+            });
+            #[cfg(feature = "dns-over-quic")]
+            listeners.push(Listener {
+                protocol: "QUIC",
+                transport: "UDP",
+                port: self.get_quic_listen_port(),
+            });
+            #[cfg(feature = "dns-over-h3")]
+            listeners.push(Listener {
+                protocol: "HTTP/3",
+                transport: "UDP",
+                port: self.get_h3_listen_port(),
+            });
+        }
+
+        // a port of 0 is chosen by the system, it never conflicts
+        for (i, listener) in listeners.iter().enumerate() {
+            let conflict = listeners[..i].iter().find(|other| {
+                listener.port != 0
+                    && other.transport == listener.transport
+                    && other.port == listener.port
+            });
+            if let Some(other) = conflict {
+                problems.push(Problem::error(
+                    "listeners",
+                    format!(
+                        "{} and {} both listen on {} port {}",
+                        other.protocol, listener.protocol, listener.transport, listener.port
+                    ),
+                ));
+            }
+        }
+    }
+
+    fn validate_tls_certs(&self, zone_dir: &Path, problems: &mut Vec<Problem>) {
+        let Some(tls_cert_config) = self.get_tls_cert() else {
+            if !self.get_tls_certs().is_empty() {
+                problems.push(Problem::warning(
+                    "tls_certs",
+                    "the certificates are not used without a tls_cert",
+                ));
+            }
+            return;
+        };
+
+        if !cfg!(feature = "dns-over-tls") {
+            problems.push(Problem::warning(
+                "tls_cert",
+                "the server is built without DNS over TLS, HTTPS or QUIC",
+            ));
+        }
+
+        let tls_cert_configs = std::iter::once(("tls_cert", tls_cert_config))
+            .chain(self.get_tls_certs().iter().map(|c| ("tls_certs", c)));
+        for (section, tls_cert_config) in tls_cert_configs {
+            if section == "tls_certs" && tls_cert_config.get_endpoint_name().is_none() {
+                problems.push(Problem::warning(
+                    section,
+                    format!(
+                        "the certificate {} has no endpoint_name, it is never selected",
+                        tls_cert_config.get_path().display()
+                    ),
+                ));
+            }
+
+            let files = std::iter::once(tls_cert_config.get_path())
+                .chain(tls_cert_config.get_private_key());
+            let mut readable = true;
+            for path in files.map(|path| zone_dir.join(path)) {
+                if let Err(e) = std::fs::metadata(&path) {
+                    problems.push(Problem::error(
+                        section,
+                        format!("failed to read {}: {e}", path.display()),
+                    ));
+                    readable = false;
+                }
+            }
+
+            if readable {
+                if let Err(e) = load_cert(zone_dir, tls_cert_config) {
+                    problems.push(Problem::error(section, e));
+                }
+            }
+        }
+    }
+}
+
+/// The zone file of the store of the zone, if it has one which must be parsed when loading
+fn zone_file(zone_config: &ZoneConfig) -> Result<Option<&str>, &'static str> {
+    match &zone_config.stores {
+        None => zone_config
+            .file
+            .as_deref()
+            .map(Some)
+            .ok_or("file is a necessary parameter of zone_config"),
+        Some(StoreConfig::File(config)) => Ok(Some(&config.zone_file_path)),
+        Some(StoreConfig::Health(config)) => Ok(Some(&config.zone_file_path)),
+        #[cfg(feature = "geoip")]
+        Some(StoreConfig::Geo(config)) => Ok(Some(&config.zone_file_path)),
+        #[cfg(feature = "sqlite")]
+        Some(StoreConfig::Sqlite(config)) => Ok(Some(&config.zone_file_path)),
+        #[allow(unreachable_patterns)]
+        Some(_) => Ok(None),
+    }
+}
+
+fn validate_zone(
+    origin: &Name,
+    zone_config: &ZoneConfig,
+    zone_dir: &Path,
+    problems: &mut Vec<Problem>,
+) {
+    let section = format!("zones[{origin}]");
+
+    if let Err(e) = Rotation::try_from_config(
+        origin,
+        zone_config.get_rotation(),
+        zone_config.get_rotations(),
+    ) {
+        problems.push(Problem::error(&section, e));
+    }
+
+    match zone_file(zone_config) {
+        Ok(Some(zone_file)) => {
+            // the journal of a SQLite store replaces the zone file once it exists
+            let journal: Option<PathBuf> = match &zone_config.stores {
+                #[cfg(feature = "sqlite")]
+                Some(StoreConfig::Sqlite(config)) => Some(config.journal_file_path.clone().into()),
+                #[cfg(feature = "sqlite")]
+                None if zone_config.is_update_allowed() => {
+                    Some(Path::new(zone_file).with_extension("jrnl"))
+                }
+                _ => None,
+            };
+
+            if !journal.map_or(false, |journal| zone_dir.join(journal).exists()) {
+                let zone_path = zone_dir.join(zone_file);
+                problems.extend(
+                    check_zone(origin, &zone_path)
+                        .iter()
+                        .filter(|diagnostic| diagnostic.severity > Severity::Info)
+                        .map(|diagnostic| Problem::from_diagnostic(&section, diagnostic)),
+                );
+            }
+        }
+        Ok(None) => (),
+        Err(e) => problems.push(Problem::error(&section, e)),
+    }
+
+    if zone_config.is_dnssec_enabled() {
+        validate_keys(origin, zone_config, &section, problems);
+    }
+}
+
+#[cfg(feature = "dnssec")]
+fn validate_keys(
+    origin: &Name,
+    zone_config: &ZoneConfig,
+    section: &str,
+    problems: &mut Vec<Problem>,
+) {
+    for key_config in zone_config.get_keys() {
+        let section = format!("{section}.keys[{}]", key_config.key_path().display());
+        if !key_config.is_zone_signing_key() && !key_config.is_zone_update_auth() {
+            problems.push(Problem::warning(
+                &section,
+                "the key is neither a zone signing key nor a zone update key",
+            ));
+        }
+
+        // reading the key also parses its algorithm, format and signer name
+        if let Err(e) = key_config.try_into_signer(origin.clone()) {
+            problems.push(Problem::error(&section, e));
+        }
+    }
+}
+
+#[cfg(not(feature = "dnssec"))]
+fn validate_keys(
+    _origin: &Name,
+    _zone_config: &ZoneConfig,
+    section: &str,
+    problems: &mut Vec<Problem>,
+) {
+    problems.push(Problem::warning(
+        section,
+        "enable_dnssec is ignored, the server is built without dnssec",
+    ));
+}
+
+/// Reads the certificate and its private key like the TLS listeners
+#[cfg(any(feature = "dns-over-openssl", feature = "dns-over-rustls"))]
+fn load_cert(zone_dir: &Path, tls_cert_config: &TlsCertConfig) -> Result<(), String> {
+    super::dnssec::load_cert(zone_dir, tls_cert_config).map(drop)
+}
+
+#[cfg(not(any(feature = "dns-over-openssl", feature = "dns-over-rustls")))]
+fn load_cert(_zone_dir: &Path, _tls_cert_config: &TlsCertConfig) -> Result<(), String> {
+    Ok(())
+}
@@ -0,0 +1,94 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Configuration of the experimental AF_XDP fast path for UDP queries
+
+use std::path::Path;
+
+use serde::Deserialize;
+
+/// Configuration of the AF_XDP sockets answering simple authoritative UDP queries
+///
+/// The XDP program attached to the interface must redirect the UDP packets to the listen port into
+///  the pinned map. The sockets replace the UDP sockets of the first listen address, the queries
+///  which are not answered from them are served from UDP sockets bound to that address.
+///
+/// ```toml
+/// [xdp]
+/// interface = "eth0"
+/// xsk_map = "/sys/fs/bpf/hickory_xsks"
+/// queues = [0, 1]
+/// frames = 4096
+/// zero_copy = false
+/// ```
+#[derive(Deserialize, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct XdpConfig {
+    /// name of the network interface
+    interface: String,
+    /// path of the pinned `BPF_MAP_TYPE_XSKMAP` which the XDP program redirects to
+    xsk_map: String,
+    /// the receive queues of the interface, one socket is bound to each, defaults to queue 0
+    #[serde(default)]
+    queues: Vec<u32>,
+    /// the number of frames of the UMEM of each socket, a power of two
+    frames: Option<u32>,
+    /// require zero copy mode, which is only supported by some drivers
+    #[serde(default)]
+    zero_copy: bool,
+}
+
+impl XdpConfig {
+    /// name of the network interface
+    pub fn get_interface(&self) -> &str {
+        &self.interface
+    }
+
+    /// path of the pinned `BPF_MAP_TYPE_XSKMAP` which the XDP program redirects to
+    pub fn get_xsk_map(&self) -> &Path {
+        Path::new(&self.xsk_map)
+    }
+
+    /// the receive queues of the interface, one socket is bound to each
+    pub fn get_queues(&self) -> &[u32] {
+        if self.queues.is_empty() {
+            &[0]
+        } else {
+            &self.queues
+        }
+    }
+
+    /// the number of frames of the UMEM of each socket, if not the default
+    pub fn get_frames(&self) -> Option<u32> {
+        self.frames
+    }
+
+    /// require zero copy mode, otherwise copy mode is used
+    pub fn get_zero_copy(&self) -> bool {
+        self.zero_copy
+    }
+
+    /// the configurations of the sockets, one for each queue, for the DNS server on `port`
+    #[cfg(all(feature = "xdp", target_os = "linux"))]
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "xdp", target_os = "linux"))))]
+    pub fn to_socket_configs(&self, port: u16) -> Vec<crate::server::XdpConfig> {
+        self.get_queues()
+            .iter()
+            .map(|&queue_id| {
+                let mut config =
+                    crate::server::XdpConfig::new(&*self.interface, self.get_xsk_map());
+                config.queue_id = queue_id;
+                if let Some(frames) = self.frames {
+                    config.frames = frames;
+                }
+                config.zero_copy = self.zero_copy;
+                config.port = port;
+                config
+            })
+            .collect()
+    }
+}
@@ -29,6 +29,26 @@ pub enum ErrorKind {
     #[error("toml decode error: {0}")]
     TomlDecode(#[from] basic_toml::Error),
 
+    /// An error occurred while decoding yaml data
+    #[cfg(feature = "yaml")]
+    #[error("yaml decode error: {0}")]
+    YamlDecode(#[from] serde_yaml::Error),
+
+    /// An error occurred while decoding the configuration with the overlays of the environment
+    #[cfg(feature = "toml")]
+    #[error("config decode error: {0}")]
+    Decode(#[from] serde_json::Error),
+
+    /// An environment variable overriding the configuration is invalid
+    #[cfg(feature = "toml")]
+    #[error("bad environment variable {name}: {message}")]
+    Env {
+        /// The name of the variable
+        name: String,
+        /// What is wrong with it
+        message: String,
+    },
+
     /// An error occurred while parsing a zone file
     #[error("failed to parse the zone file: {0}")]
     ZoneParse(#[from] crate::proto::serialize::txt::ParseError),
@@ -33,6 +33,22 @@ pub enum ErrorKind {
         expect: usize,
     },
 
+    /// The SOA serial recorded in the journal decreased between two entries
+    #[error(
+        "journal serial went backwards at row {}: {} follows {}",
+        row_id,
+        serial,
+        previous
+    )]
+    SerialRegression {
+        /// The row of the journal entry with the lower serial
+        row_id: i64,
+        /// The serial of the offending journal entry
+        serial: u32,
+        /// The serial of the preceding journal entry
+        previous: u32,
+    },
+
     // foreign
     /// An error got returned by the hickory-proto crate
     #[error("proto error: {0}")]
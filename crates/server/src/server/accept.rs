@@ -0,0 +1,382 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Limits and recovery of the loops accepting connections
+
+use std::collections::HashMap;
+use std::io;
+use std::net::IpAddr;
+use std::num::{NonZeroU32, NonZeroUsize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::future;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, warn};
+
+use crate::server::{server_future::is_unrecoverable_socket_error, Protocol};
+
+/// How connections are accepted by the TCP, TLS, HTTPS, QUIC and H3 listeners of a `ServerFuture`
+///
+/// See `ServerFuture::set_accept_config`, the configuration applies to each listener separately.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct AcceptConfig {
+    /// The maximum number of connections accepted per second, `None` for no limit
+    pub max_rate: Option<NonZeroU32>,
+    /// The pause after the process ran out of file descriptors or memory, before connections are
+    ///  accepted again. Defaults to 100 milliseconds.
+    pub exhausted_pause: Duration,
+    /// The number of times a listener is restarted after consecutive failures of its socket,
+    ///  before it is closed. Defaults to 10.
+    pub max_restarts: u32,
+    /// The pause before the first restart of a listener, doubled for each further restart.
+    ///  Defaults to 1 second.
+    pub restart_backoff: Duration,
+    /// The maximum number of connections open at a time on a TCP or TLS listener, no further
+    ///  connections are accepted until one is closed. `None` for no limit.
+    pub max_connections: Option<NonZeroUsize>,
+    /// The maximum number of connections open at a time from one IP address on a TCP or TLS
+    ///  listener, further connections are closed right away. `None` for no limit.
+    pub max_connections_per_ip: Option<NonZeroUsize>,
+    /// The time after which no more requests are read from a TCP or TLS connection, which is
+    ///  closed once the requests in flight are answered. `None` for no limit.
+    ///
+    /// The idle timeout of the connections is the `timeout` of the listener.
+    pub max_connection_lifetime: Option<Duration>,
+}
+
+impl Default for AcceptConfig {
+    fn default() -> Self {
+        Self {
+            max_rate: None,
+            exhausted_pause: Duration::from_millis(100),
+            max_restarts: 10,
+            restart_backoff: Duration::from_secs(1),
+            max_connections: None,
+            max_connections_per_ip: None,
+            max_connection_lifetime: None,
+        }
+    }
+}
+
+/// The state of the accept loop of one listener
+pub(crate) struct AcceptLimiter {
+    config: AcceptConfig,
+    protocol: Protocol,
+    shutdown: CancellationToken,
+    window_start: Instant,
+    accepted: u32,
+    restarts: u32,
+    connections: Arc<Connections>,
+}
+
+impl AcceptLimiter {
+    pub(crate) fn new(
+        config: AcceptConfig,
+        protocol: Protocol,
+        shutdown: CancellationToken,
+    ) -> Self {
+        Self {
+            config,
+            protocol,
+            shutdown,
+            window_start: Instant::now(),
+            accepted: 0,
+            restarts: 0,
+            connections: Arc::new(Connections {
+                permits: config
+                    .max_connections
+                    .map(|max| Arc::new(Semaphore::new(max.get()))),
+                per_ip: Mutex::default(),
+            }),
+        }
+    }
+
+    /// Waits until another connection may be open on the listener, see
+    ///  `AcceptConfig::max_connections`
+    ///
+    /// Returns `None` if the server is shut down in the meantime.
+    pub(crate) async fn reserve(&self) -> Option<ConnectionSlot> {
+        let permit = match &self.connections.permits {
+            Some(permits) => {
+                if permits.available_permits() == 0 {
+                    debug!("{} connection limit reached", self.protocol);
+                }
+
+                tokio::select! {
+                    // the semaphore is never closed
+                    permit = Arc::clone(permits).acquire_owned() => permit.ok(),
+                    _ = self.shutdown.cancelled() => return None,
+                }
+            }
+            None => None,
+        };
+
+        Some(ConnectionSlot {
+            config: self.config,
+            connections: Arc::clone(&self.connections),
+            _permit: permit,
+        })
+    }
+
+    /// Waits until another connection may be accepted, see `AcceptConfig::max_rate`
+    pub(crate) async fn throttle(&mut self) {
+        let Some(max_rate) = self.config.max_rate else {
+            return;
+        };
+
+        let window = Duration::from_secs(1);
+        let elapsed = self.window_start.elapsed();
+        if elapsed >= window {
+            self.window_start = Instant::now();
+            self.accepted = 0;
+        } else if self.accepted >= max_rate.get() {
+            debug!("{} accept rate of {max_rate}/s reached", self.protocol);
+            self.pause(window - elapsed).await;
+            self.window_start = Instant::now();
+            self.accepted = 0;
+        }
+    }
+
+    /// Records an accepted connection, which ends a series of restarts
+    pub(crate) fn accepted(&mut self) {
+        self.accepted = self.accepted.saturating_add(1);
+        self.restarts = 0;
+    }
+
+    /// Handles an error accepting a connection, pausing the listener if necessary
+    ///
+    /// # Returns
+    ///
+    /// `false` if the listener must be closed
+    pub(crate) async fn failed(&mut self, error: &io::Error) -> bool {
+        let protocol = self.protocol;
+
+        if is_resource_exhaustion(error) {
+            let pause = self.config.exhausted_pause;
+            warn!(
+                "out of resources accepting {protocol} connections, pausing for {pause:?}: {error}"
+            );
+            self.pause(pause).await;
+            return true;
+        }
+
+        if !is_unrecoverable_socket_error(error) {
+            debug!("error receiving {protocol} connection: {error}");
+            return true;
+        }
+
+        if self.restarts >= self.config.max_restarts {
+            error!(
+                "closing {protocol} listener after {} restarts: {error}",
+                self.restarts
+            );
+            return false;
+        }
+
+        let backoff = self
+            .config
+            .restart_backoff
+            .saturating_mul(1 << self.restarts.min(16));
+        self.restarts += 1;
+        warn!(
+            "restarting {protocol} listener in {backoff:?}, attempt {}: {error}",
+            self.restarts
+        );
+        self.pause(backoff).await;
+        true
+    }
+
+    /// Sleeps for the duration, unless the server is shut down
+    async fn pause(&self, duration: Duration) {
+        tokio::select! {
+            _ = tokio::time::sleep(duration) => {},
+            _ = self.shutdown.cancelled() => {},
+        }
+    }
+}
+
+/// The connections open on one listener
+struct Connections {
+    permits: Option<Arc<Semaphore>>,
+    per_ip: Mutex<HashMap<IpAddr, usize>>,
+}
+
+/// A connection which may be accepted, see `AcceptLimiter::reserve`
+pub(crate) struct ConnectionSlot {
+    config: AcceptConfig,
+    connections: Arc<Connections>,
+    // RAII guard, the connection is counted against `max_connections` until it is dropped
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl ConnectionSlot {
+    /// Opens the connection from the client, see `AcceptConfig::max_connections_per_ip`
+    ///
+    /// Returns `None` if the client has too many connections open, the connection is then to be
+    ///  closed.
+    pub(crate) fn open(self, ip: IpAddr) -> Option<Connection> {
+        if let Some(max) = self.config.max_connections_per_ip {
+            let mut per_ip = self.connections.per_ip.lock().expect("per_ip poisoned");
+            let count = per_ip.entry(ip).or_default();
+            if *count >= max.get() {
+                return None;
+            }
+            *count += 1;
+        }
+
+        let expiration = self
+            .config
+            .max_connection_lifetime
+            .map(|lifetime| Instant::now() + lifetime);
+        Some(Connection {
+            ip,
+            expiration,
+            slot: self,
+        })
+    }
+}
+
+/// An open connection, which is counted until it is dropped
+pub(crate) struct Connection {
+    ip: IpAddr,
+    expiration: Option<Instant>,
+    slot: ConnectionSlot,
+}
+
+impl Connection {
+    /// Completes once the connection reached its `AcceptConfig::max_connection_lifetime`
+    pub(crate) async fn expired(&self) {
+        match self.expiration {
+            Some(expiration) => tokio::time::sleep_until(expiration).await,
+            None => future::pending().await,
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        if self.slot.config.max_connections_per_ip.is_none() {
+            return;
+        }
+
+        let mut per_ip = self
+            .slot
+            .connections
+            .per_ip
+            .lock()
+            .expect("per_ip poisoned");
+        if let Some(count) = per_ip.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                per_ip.remove(&self.ip);
+            }
+        }
+    }
+}
+
+/// Returns true if the process or system ran out of file descriptors or memory
+fn is_resource_exhaustion(error: &io::Error) -> bool {
+    #[cfg(target_os = "linux")]
+    const ENOBUFS: i32 = 105;
+    #[cfg(all(unix, not(target_os = "linux")))]
+    const ENOBUFS: i32 = 55;
+
+    if error.kind() == io::ErrorKind::OutOfMemory {
+        return true;
+    }
+
+    match error.raw_os_error() {
+        // ENOMEM, ENFILE and EMFILE
+        #[cfg(unix)]
+        Some(12 | 23 | 24 | ENOBUFS) => true,
+        // WSAEMFILE and WSAENOBUFS
+        #[cfg(windows)]
+        Some(10024 | 10055) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_restarts() {
+        let config = AcceptConfig {
+            exhausted_pause: Duration::ZERO,
+            max_restarts: 2,
+            restart_backoff: Duration::ZERO,
+            ..AcceptConfig::default()
+        };
+        let mut limiter = AcceptLimiter::new(config, Protocol::Tcp, CancellationToken::new());
+        let aborted = io::Error::from(io::ErrorKind::ConnectionAborted);
+
+        // a transient error does not count as a restart
+        assert!(limiter.failed(&io::Error::from_raw_os_error(24)).await);
+        assert!(limiter.failed(&aborted).await);
+        assert!(limiter.failed(&aborted).await);
+        assert!(!limiter.failed(&aborted).await);
+
+        // an accepted connection resets the restarts
+        limiter.accepted();
+        assert!(limiter.failed(&aborted).await);
+    }
+
+    #[tokio::test]
+    async fn test_connections() {
+        let config = AcceptConfig {
+            max_connections: NonZeroUsize::new(2),
+            max_connections_per_ip: NonZeroUsize::new(1),
+            ..AcceptConfig::default()
+        };
+        let shutdown = CancellationToken::new();
+        let limiter = AcceptLimiter::new(config, Protocol::Tcp, shutdown.clone());
+        let client = IpAddr::from([192, 0, 2, 1]);
+
+        let first = limiter.reserve().await.unwrap().open(client).unwrap();
+        // a second connection from the client is refused, which frees its slot
+        assert!(limiter.reserve().await.unwrap().open(client).is_none());
+        let second = limiter
+            .reserve()
+            .await
+            .unwrap()
+            .open(IpAddr::from([192, 0, 2, 2]))
+            .unwrap();
+
+        // the listener is full until a connection is closed
+        let reserve = limiter.reserve();
+        tokio::pin!(reserve);
+        assert!(futures_util::FutureExt::now_or_never(reserve.as_mut()).is_none());
+        drop(first);
+        let third = reserve.await.unwrap().open(client).unwrap();
+
+        drop((second, third));
+        assert!(limiter.connections.per_ip.lock().unwrap().is_empty());
+
+        // waiting for a connection ends on shutdown
+        let _first = limiter.reserve().await.unwrap();
+        let _second = limiter.reserve().await.unwrap();
+        shutdown.cancel();
+        assert!(limiter.reserve().await.is_none());
+    }
+
+    #[test]
+    fn test_resource_exhaustion() {
+        assert!(is_resource_exhaustion(&io::Error::from(
+            io::ErrorKind::OutOfMemory
+        )));
+        #[cfg(unix)]
+        assert!(is_resource_exhaustion(&io::Error::from_raw_os_error(24)));
+        assert!(!is_resource_exhaustion(&io::Error::from(
+            io::ErrorKind::ConnectionReset
+        )));
+    }
+}
@@ -0,0 +1,281 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Limits of the requests handled at a time, so that a burst of requests can not exhaust the
+//!  memory or starve the runtime
+
+use std::num::NonZeroUsize;
+use std::sync::{
+    atomic::{AtomicU64, AtomicUsize, Ordering},
+    Arc,
+};
+
+use serde::Deserialize;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// What happens to the requests which are over the limits of `AdmissionConfig`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[non_exhaustive]
+pub enum Overflow {
+    /// The requests are dropped without a response, clients retry them after their timeout
+    #[default]
+    #[serde(rename = "drop")]
+    Drop,
+    /// The requests are answered with SERVFAIL without being handled, clients may then retry
+    ///  them at another server right away
+    #[serde(rename = "servfail")]
+    ServFail,
+}
+
+/// How many requests are handled at a time by the UDP, TCP and TLS listeners of a `ServerFuture`
+///
+/// See `ServerFuture::set_admission_config`, the limits apply to each listener separately.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct AdmissionConfig {
+    /// The maximum number of requests handled at a time by a listener, `None` for no limit
+    pub max_in_flight: Option<NonZeroUsize>,
+    /// The number of requests which wait for a request in flight to complete, once
+    ///  `max_in_flight` is reached. Further requests overflow. Defaults to 1024.
+    pub max_queued: usize,
+    /// The maximum number of requests handled at a time for each TCP or TLS connection, further
+    ///  requests are not read from the connection until one of them is answered. Defaults to 1.
+    pub max_in_flight_per_connection: NonZeroUsize,
+    /// What happens to the requests which overflow the queue, defaults to `Overflow::Drop`
+    pub overflow: Overflow,
+}
+
+impl Default for AdmissionConfig {
+    fn default() -> Self {
+        Self {
+            max_in_flight: None,
+            max_queued: 1024,
+            max_in_flight_per_connection: NonZeroUsize::new(1).expect("1 is not zero"),
+            overflow: Overflow::default(),
+        }
+    }
+}
+
+/// The requests admitted by the listeners of a `ServerFuture`, see
+///  `ServerFuture::admission_stats`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct AdmissionStats {
+    /// The number of requests being handled
+    pub in_flight: usize,
+    /// The number of requests waiting to be handled
+    pub queued: usize,
+    /// The number of requests which were handled, including those in flight
+    pub admitted: u64,
+    /// The number of requests which overflowed, and were dropped
+    pub dropped: u64,
+    /// The number of requests which overflowed, and were answered with SERVFAIL
+    pub servfailed: u64,
+}
+
+/// The counters of the requests of all listeners
+#[derive(Default)]
+pub(crate) struct AdmissionCounters {
+    in_flight: AtomicUsize,
+    queued: AtomicUsize,
+    admitted: AtomicU64,
+    dropped: AtomicU64,
+    servfailed: AtomicU64,
+}
+
+impl AdmissionCounters {
+    pub(crate) fn stats(&self) -> AdmissionStats {
+        AdmissionStats {
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+            queued: self.queued.load(Ordering::Relaxed),
+            admitted: self.admitted.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            servfailed: self.servfailed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The requests in flight and queued on one listener
+pub(crate) struct Admission {
+    config: AdmissionConfig,
+    permits: Option<Arc<Semaphore>>,
+    queued: AtomicUsize,
+    counters: Arc<AdmissionCounters>,
+}
+
+impl Admission {
+    pub(crate) fn new(config: AdmissionConfig, counters: Arc<AdmissionCounters>) -> Arc<Self> {
+        Arc::new(Self {
+            config,
+            permits: config
+                .max_in_flight
+                .map(|max| Arc::new(Semaphore::new(max.get()))),
+            queued: AtomicUsize::new(0),
+            counters,
+        })
+    }
+
+    /// What happens to the requests for which `admit` returns `None`
+    pub(crate) fn overflow(&self) -> Overflow {
+        self.config.overflow
+    }
+
+    pub(crate) fn max_in_flight_per_connection(&self) -> usize {
+        self.config.max_in_flight_per_connection.get()
+    }
+
+    /// Admits a request, which may need to wait in the queue before it is handled
+    ///
+    /// Returns `None` if the queue is full, the request is then counted as overflowed.
+    pub(crate) fn admit(self: &Arc<Self>) -> Option<Ticket> {
+        let Some(permits) = &self.permits else {
+            return Some(Ticket::new(self, None, false));
+        };
+
+        if let Ok(permit) = Arc::clone(permits).try_acquire_owned() {
+            return Some(Ticket::new(self, Some(permit), false));
+        }
+
+        if self.queued.fetch_add(1, Ordering::Relaxed) >= self.config.max_queued {
+            self.queued.fetch_sub(1, Ordering::Relaxed);
+            let overflowed = match self.config.overflow {
+                Overflow::Drop => &self.counters.dropped,
+                Overflow::ServFail => &self.counters.servfailed,
+            };
+            overflowed.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+
+        self.counters.queued.fetch_add(1, Ordering::Relaxed);
+        Some(Ticket::new(self, None, true))
+    }
+}
+
+/// An admitted request, see `Admission::admit`
+pub(crate) struct Ticket {
+    admission: Arc<Admission>,
+    permit: Option<OwnedSemaphorePermit>,
+    queued: bool,
+}
+
+impl Ticket {
+    fn new(admission: &Arc<Admission>, permit: Option<OwnedSemaphorePermit>, queued: bool) -> Self {
+        Self {
+            admission: Arc::clone(admission),
+            permit,
+            queued,
+        }
+    }
+
+    /// Waits in the queue until the request may be handled
+    ///
+    /// The request is in flight until the returned guard is dropped.
+    pub(crate) async fn ready(mut self) -> InFlight {
+        if self.queued {
+            if let Some(permits) = &self.admission.permits {
+                // the semaphore is never closed
+                self.permit = Arc::clone(permits).acquire_owned().await.ok();
+            }
+            self.dequeue();
+        }
+
+        let counters = Arc::clone(&self.admission.counters);
+        counters.in_flight.fetch_add(1, Ordering::Relaxed);
+        counters.admitted.fetch_add(1, Ordering::Relaxed);
+        InFlight {
+            counters,
+            _permit: self.permit.take(),
+        }
+    }
+
+    fn dequeue(&mut self) {
+        if self.queued {
+            self.queued = false;
+            self.admission.queued.fetch_sub(1, Ordering::Relaxed);
+            self.admission
+                .counters
+                .queued
+                .fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        // e.g. the task of a queued request is aborted on shutdown
+        self.dequeue();
+    }
+}
+
+/// A request in flight, see `Ticket::ready`
+pub(crate) struct InFlight {
+    counters: Arc<AdmissionCounters>,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.counters.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_admission() {
+        let config = AdmissionConfig {
+            max_in_flight: NonZeroUsize::new(1),
+            max_queued: 1,
+            overflow: Overflow::ServFail,
+            ..AdmissionConfig::default()
+        };
+        let counters = Arc::new(AdmissionCounters::default());
+        let admission = Admission::new(config, Arc::clone(&counters));
+
+        let in_flight = admission.admit().unwrap().ready().await;
+        let queued = admission.admit().unwrap();
+        assert!(admission.admit().is_none());
+        assert_eq!(
+            counters.stats(),
+            AdmissionStats {
+                in_flight: 1,
+                queued: 1,
+                admitted: 1,
+                dropped: 0,
+                servfailed: 1,
+            }
+        );
+
+        // the queued request is handled once the request in flight completes
+        let ready = tokio::spawn(queued.ready());
+        drop(in_flight);
+        let in_flight = ready.await.unwrap();
+        assert_eq!(counters.stats().queued, 0);
+        assert_eq!(counters.stats().admitted, 2);
+
+        // a queued request which is dropped leaves the queue
+        drop(admission.admit().unwrap());
+        assert_eq!(counters.stats().queued, 0);
+        drop(in_flight);
+        assert_eq!(counters.stats().in_flight, 0);
+    }
+
+    #[tokio::test]
+    async fn test_unlimited() {
+        let counters = Arc::new(AdmissionCounters::default());
+        let admission = Admission::new(AdmissionConfig::default(), Arc::clone(&counters));
+
+        let in_flight =
+            futures_util::future::join_all((0..4096).map(|_| admission.admit().unwrap().ready()))
+                .await;
+        assert_eq!(counters.stats().in_flight, 4096);
+        drop(in_flight);
+        assert_eq!(counters.stats().in_flight, 0);
+    }
+}
@@ -0,0 +1,87 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! The configuration a server is actually running with
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use ipnet::IpNet;
+use serde::Serialize;
+
+use crate::server::Protocol;
+
+/// The listeners and access rules of a `ServerFuture`, see `ServerFuture::effective_config`
+///
+/// This can be serialized, e.g. to be included in a bug report.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct EffectiveConfig {
+    /// The registered sockets and listeners, in the order of their registration
+    pub listeners: Vec<ListenerConfig>,
+    /// Networks which are denied access, unless also allowed
+    pub denied_networks: Vec<IpNet>,
+    /// Networks which are allowed access
+    pub allowed_networks: Vec<IpNet>,
+}
+
+/// A socket or listener registered with a `ServerFuture`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct ListenerConfig {
+    /// The protocol of the requests received
+    pub protocol: Protocol,
+    /// The local address, `None` if it could not be determined
+    pub addr: Option<SocketAddr>,
+    /// The timeout of idle connections, `None` for UDP
+    pub timeout: Option<Duration>,
+    /// The name of the server in its certificate, if set for the listener
+    pub dns_hostname: Option<String>,
+    /// The number of requests a client may send at a time on each connection, for HTTP/3
+    pub max_concurrent_streams: Option<u32>,
+    /// Whether the connections start with a PROXY protocol v2 header, for TCP and TLS
+    pub proxy_protocol: bool,
+}
+
+impl ListenerConfig {
+    pub(crate) fn new(protocol: Protocol, addr: Option<SocketAddr>) -> Self {
+        Self {
+            protocol,
+            addr,
+            timeout: None,
+            dns_hostname: None,
+            max_concurrent_streams: None,
+            proxy_protocol: false,
+        }
+    }
+
+    pub(crate) fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    #[cfg(any(
+        feature = "dns-over-https-rustls",
+        feature = "dns-over-quic",
+        feature = "dns-over-h3"
+    ))]
+    pub(crate) fn with_dns_hostname(mut self, dns_hostname: Option<String>) -> Self {
+        self.dns_hostname = dns_hostname;
+        self
+    }
+
+    pub(crate) fn with_proxy_protocol(mut self, proxy_protocol: bool) -> Self {
+        self.proxy_protocol = proxy_protocol;
+        self
+    }
+
+    #[cfg(feature = "dns-over-h3")]
+    pub(crate) fn with_max_concurrent_streams(mut self, max_concurrent_streams: u32) -> Self {
+        self.max_concurrent_streams = Some(max_concurrent_streams);
+        self
+    }
+}
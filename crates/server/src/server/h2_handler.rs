@@ -10,27 +10,34 @@ use std::{io, net::SocketAddr, sync::Arc};
 use bytes::{Bytes, BytesMut};
 use futures_util::lock::Mutex;
 use h2::server;
-use hickory_proto::{http::Version, rr::Record};
+use hickory_proto::{http::Version, rr::Record, serialize::binary::BinDecodable};
+use http::{header::WWW_AUTHENTICATE, Response};
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, warn};
 
 use crate::{
     access::AccessControl,
-    authority::MessageResponse,
+    authority::{MessageRequest, MessageResponse},
     proto::h2::h2_server,
     server::{
-        request_handler::RequestHandler, response_handler::ResponseHandler, server_future,
-        Protocol, ResponseInfo,
+        https_auth::{HttpsAuth, HttpsAuthError, Principal},
+        request_handler::RequestHandler,
+        response_handler::ResponseHandler,
+        server_future, Protocol, ResponseConfig, ResponseInfo,
     },
 };
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) async fn h2_handler<T, I>(
     access: Arc<AccessControl>,
     handler: Arc<T>,
+    response_config: ResponseConfig,
     io: I,
     src_addr: SocketAddr,
     dns_hostname: Option<Arc<str>>,
+    auth: Option<Arc<HttpsAuth>>,
+    client_cert: Option<Vec<u8>>,
     shutdown: CancellationToken,
 ) where
     T: RequestHandler,
@@ -49,8 +56,9 @@ pub(crate) async fn h2_handler<T, I>(
 
     // Accept all inbound HTTP/2.0 streams sent over the
     // connection.
+    let mut shutting_down = false;
     loop {
-        let (request, respond) = tokio::select! {
+        let (request, mut respond) = tokio::select! {
             result = h2.accept() => match result {
                 Some(Ok(next_request)) => next_request,
                 Some(Err(err)) => {
@@ -61,29 +69,103 @@ pub(crate) async fn h2_handler<T, I>(
                     return;
                 }
             },
-            _ = shutdown.cancelled() => {
-                // A graceful shutdown was initiated.
-                return
+            _ = shutdown.cancelled(), if !shutting_down => {
+                // A graceful shutdown was initiated, the connection is closed once the requests
+                //  in flight are answered.
+                h2.graceful_shutdown();
+                shutting_down = true;
+                continue;
             },
         };
 
         debug!("Received request: {:#?}", request);
+        let principal = match authorize(auth.as_deref(), &request, client_cert.as_deref()) {
+            Ok(principal) => principal,
+            Err(err) => {
+                reject(&mut respond, err, src_addr);
+                continue;
+            }
+        };
+
         let dns_hostname = dns_hostname.clone();
         let handler = handler.clone();
         let access = access.clone();
-        let responder = HttpsResponseHandle(Arc::new(Mutex::new(respond)));
+        let responder = HttpsResponseHandle(Arc::new(Mutex::new(respond)), response_config);
 
         tokio::spawn(async move {
-            match h2_server::message_from(dns_hostname, request).await {
-                Ok(bytes) => handle_request(bytes, src_addr, access, handler, responder).await,
-                Err(err) => warn!("error while handling request from {}: {}", src_addr, err),
+            let bytes = match h2_server::message_from(dns_hostname, request).await {
+                Ok(bytes) => bytes,
+                Err(err) => {
+                    warn!("error while handling request from {}: {}", src_addr, err);
+                    return;
+                }
             };
+
+            if let Some(principal) = principal {
+                if let Err(err) = check_policy(&principal, &bytes) {
+                    reject(&mut *responder.0.lock().await, err, src_addr);
+                    return;
+                }
+            }
+
+            handle_request(bytes, src_addr, access, handler, responder).await
         });
 
         // we'll continue handling requests from here.
     }
 }
 
+/// Authenticates the request and takes it from the principal's quota
+fn authorize<R>(
+    auth: Option<&HttpsAuth>,
+    request: &http::Request<R>,
+    client_cert: Option<&[u8]>,
+) -> Result<Option<Arc<Principal>>, HttpsAuthError> {
+    let Some(auth) = auth else {
+        return Ok(None);
+    };
+
+    let principal = auth.authenticate(request.headers(), client_cert)?;
+    if let Some(ref principal) = principal {
+        debug!("request authenticated as: {}", principal.name());
+        principal.check_quota()?;
+    }
+
+    Ok(principal)
+}
+
+/// Verifies that the principal's profile allows the query, malformed messages are left for the
+///  request handler to reject
+fn check_policy(principal: &Principal, bytes: &[u8]) -> Result<(), HttpsAuthError> {
+    match MessageRequest::from_bytes(bytes) {
+        Ok(message) => principal.check_query_type(message.query().query_type()),
+        Err(_) => Ok(()),
+    }
+}
+
+fn reject(respond: &mut server::SendResponse<Bytes>, err: HttpsAuthError, src_addr: SocketAddr) {
+    warn!("rejecting request from {}: {:?}", src_addr, err);
+
+    let mut response = Response::builder()
+        .status(err.status())
+        .version(http::Version::HTTP_2);
+    if err == HttpsAuthError::Unauthorized {
+        response = response.header(WWW_AUTHENTICATE, "Bearer");
+    }
+
+    let response = match response.body(()) {
+        Ok(response) => response,
+        Err(err) => {
+            warn!("error building response for {}: {}", src_addr, err);
+            return;
+        }
+    };
+
+    if let Err(err) = respond.send_response(response, true) {
+        warn!("error sending response to {}: {}", src_addr, err);
+    }
+}
+
 async fn handle_request<T>(
     bytes: BytesMut,
     src_addr: SocketAddr,
@@ -105,7 +187,7 @@ async fn handle_request<T>(
 }
 
 #[derive(Clone)]
-struct HttpsResponseHandle(Arc<Mutex<server::SendResponse<Bytes>>>);
+struct HttpsResponseHandle(Arc<Mutex<server::SendResponse<Bytes>>>, ResponseConfig);
 
 #[async_trait::async_trait]
 impl ResponseHandler for HttpsResponseHandle {
@@ -128,7 +210,7 @@ impl ResponseHandler for HttpsResponseHandle {
         // mut block
         let info = {
             let mut encoder = BinEncoder::new(&mut bytes);
-            response.destructive_emit(&mut encoder)?
+            response.destructive_emit_with(&mut encoder, &self.1)?
         };
         let bytes = Bytes::from(bytes);
         let response = response::new(Version::Http2, bytes.len())?;
@@ -14,6 +14,7 @@ use h3_quinn::BidiStream;
 use hickory_proto::{
     error::ProtoError, h3::h3_server::H3Connection, h3::H3Error, http::Version, rr::Record,
 };
+use tokio::task::JoinSet;
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, warn};
 
@@ -22,13 +23,14 @@ use crate::{
     authority::MessageResponse,
     server::{
         request_handler::RequestHandler, response_handler::ResponseHandler, server_future,
-        Protocol, ResponseInfo,
+        Protocol, ResponseConfig, ResponseInfo,
     },
 };
 
 pub(crate) async fn h3_handler<T>(
     access: Arc<AccessControl>,
     handler: Arc<T>,
+    response_config: ResponseConfig,
     mut connection: H3Connection,
     src_addr: SocketAddr,
     _dns_hostname: Option<Arc<str>>,
@@ -39,6 +41,8 @@ where
 {
     // TODO: we should make this configurable
     let mut max_requests = 100u32;
+    // the requests in flight, which are answered before the connection is dropped
+    let mut requests = JoinSet::new();
 
     // Accept all inbound requests sent over the connection.
     loop {
@@ -54,7 +58,8 @@ where
                 }
             },
             _ = shutdown.cancelled() => {
-                // A graceful shutdown was initiated.
+                // A graceful shutdown was initiated, the requests in flight are still answered.
+                connection.shutdown().await?;
                 break;
             },
         };
@@ -75,9 +80,9 @@ where
         let handler = handler.clone();
         let access = access.clone();
         let stream = Arc::new(Mutex::new(stream));
-        let responder = H3ResponseHandle(stream.clone());
+        let responder = H3ResponseHandle(stream.clone(), response_config);
 
-        tokio::spawn(handle_request(
+        requests.spawn(handle_request(
             request, src_addr, access, handler, responder,
         ));
 
@@ -90,6 +95,7 @@ where
         // we'll continue handling requests from here.
     }
 
+    while requests.join_next().await.is_some() {}
     Ok(())
 }
 
@@ -106,7 +112,10 @@ async fn handle_request<T>(
 }
 
 #[derive(Clone)]
-struct H3ResponseHandle(Arc<Mutex<RequestStream<BidiStream<Bytes>, Bytes>>>);
+struct H3ResponseHandle(
+    Arc<Mutex<RequestStream<BidiStream<Bytes>, Bytes>>>,
+    ResponseConfig,
+);
 
 #[async_trait::async_trait]
 impl ResponseHandler for H3ResponseHandle {
@@ -128,7 +137,7 @@ impl ResponseHandler for H3ResponseHandle {
         // mut block
         let info = {
             let mut encoder = BinEncoder::new(&mut bytes);
-            response.destructive_emit(&mut encoder)?
+            response.destructive_emit_with(&mut encoder, &self.1)?
         };
         let bytes = Bytes::from(bytes);
         let response = response::new(Version::Http3, bytes.len())?;
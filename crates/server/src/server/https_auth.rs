@@ -0,0 +1,493 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Authentication and quota enforcement for DNS over HTTPS
+
+#[cfg(feature = "dns-over-https-rustls")]
+use std::path::{Path, PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use http::{header::AUTHORIZATION, HeaderMap, StatusCode};
+#[cfg(feature = "dns-over-https-rustls")]
+use rustls::RootCertStore;
+#[cfg(feature = "dns-over-https-rustls")]
+use tracing::info;
+
+#[cfg(feature = "dns-over-https-rustls")]
+use crate::{config::https_auth::HttpsAuthConfig, proto::rustls::tls_server::read_cert};
+use crate::{config::https_auth::PolicyProfileConfig, proto::rr::RecordType};
+
+/// Authenticates DNS over HTTPS requests and enforces the quotas and policies of each principal.
+///
+/// Requests are authenticated by an `Authorization: Bearer <token>` header, or by the client
+///  certificate presented during the TLS handshake. Bearer tokens take precedence over client
+///  certificates.
+pub struct HttpsAuth {
+    allow_anonymous: bool,
+    #[cfg(feature = "dns-over-https-rustls")]
+    client_roots: Option<RootCertStore>,
+    bearer_tokens: HashMap<String, Arc<Principal>>,
+    client_certificates: HashMap<Vec<u8>, Arc<Principal>>,
+}
+
+impl HttpsAuth {
+    /// Creates a new HttpsAuth, without any principals
+    ///
+    /// # Arguments
+    ///
+    /// * `allow_anonymous` - if true, requests without credentials will be served
+    pub fn new(allow_anonymous: bool) -> Self {
+        Self {
+            allow_anonymous,
+            #[cfg(feature = "dns-over-https-rustls")]
+            client_roots: None,
+            bearer_tokens: HashMap::new(),
+            client_certificates: HashMap::new(),
+        }
+    }
+
+    /// Loads the principals, profiles and client certificates from the configuration
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - the https_auth section of the server configuration
+    /// * `root_dir` - directory against which relative certificate paths are resolved
+    #[cfg(feature = "dns-over-https-rustls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "dns-over-https-rustls")))]
+    pub fn try_from_config(
+        config: &HttpsAuthConfig,
+        root_dir: Option<&Path>,
+    ) -> Result<Self, String> {
+        let root_dir = root_dir.map(PathBuf::from).unwrap_or_default();
+        let mut auth = Self::new(config.allow_anonymous());
+
+        if let Some(client_ca_path) = config.get_client_ca_path() {
+            let client_ca_path = root_dir.join(client_ca_path);
+            info!(
+                "loading client CA certificates from: {}",
+                client_ca_path.display()
+            );
+
+            let mut roots = RootCertStore::empty();
+            for cert in read_cert(&client_ca_path).map_err(|e| e.to_string())? {
+                roots
+                    .add(&cert)
+                    .map_err(|e| format!("bad client CA certificate: {e}"))?;
+            }
+            auth.client_roots = Some(roots);
+        }
+
+        let mut profiles = HashMap::new();
+        for profile in config.get_profiles() {
+            let policy = PolicyProfile::try_from_config(profile)?;
+            if profiles
+                .insert(profile.name.as_str(), Arc::new(policy))
+                .is_some()
+            {
+                return Err(format!("duplicate profile: {}", profile.name));
+            }
+        }
+
+        for principal_config in config.get_principals() {
+            let profile = match principal_config.profile {
+                Some(ref name) => Arc::clone(profiles.get(name.as_str()).ok_or_else(|| {
+                    format!("unknown profile {name} for {}", principal_config.name)
+                })?),
+                None => Arc::new(PolicyProfile::default()),
+            };
+
+            let principal = Arc::new(Principal::new(principal_config.name.as_str(), profile));
+
+            for token in &principal_config.bearer_tokens {
+                auth.insert_bearer_token(token.clone(), Arc::clone(&principal))?;
+            }
+
+            for cert_path in &principal_config.client_certificates {
+                if auth.client_roots.is_none() {
+                    return Err(format!(
+                        "client_ca_path is required for client certificates of {}",
+                        principal.name()
+                    ));
+                }
+
+                let cert_path = root_dir.join(cert_path);
+                let cert = read_cert(&cert_path)
+                    .map_err(|e| e.to_string())?
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| format!("no certificate in: {}", cert_path.display()))?;
+                auth.insert_client_certificate(cert.0, Arc::clone(&principal))?;
+            }
+        }
+
+        Ok(auth)
+    }
+
+    /// Associates a bearer token with the principal
+    pub fn insert_bearer_token(
+        &mut self,
+        token: String,
+        principal: Arc<Principal>,
+    ) -> Result<(), String> {
+        if self.bearer_tokens.contains_key(&token) {
+            return Err(format!(
+                "bearer token of {} is not unique",
+                principal.name()
+            ));
+        }
+
+        self.bearer_tokens.insert(token, principal);
+        Ok(())
+    }
+
+    /// Associates a DER encoded client certificate with the principal, the CA for the certificate
+    ///  must be registered with `set_client_roots`
+    pub fn insert_client_certificate(
+        &mut self,
+        cert: Vec<u8>,
+        principal: Arc<Principal>,
+    ) -> Result<(), String> {
+        if self.client_certificates.contains_key(&cert) {
+            return Err(format!(
+                "client certificate of {} is not unique",
+                principal.name()
+            ));
+        }
+
+        self.client_certificates.insert(cert, principal);
+        Ok(())
+    }
+
+    /// Sets the CA certificates used to verify client certificates
+    #[cfg(feature = "dns-over-https-rustls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "dns-over-https-rustls")))]
+    pub fn set_client_roots(&mut self, roots: RootCertStore) {
+        self.client_roots = Some(roots);
+    }
+
+    /// The CA certificates used to verify client certificates, if client certificates are enabled
+    #[cfg(feature = "dns-over-https-rustls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "dns-over-https-rustls")))]
+    pub fn client_roots(&self) -> Option<&RootCertStore> {
+        self.client_roots.as_ref()
+    }
+
+    /// Authenticates a request, returning the principal which issued it.
+    ///
+    /// `None` is returned for anonymous requests, if those are allowed.
+    ///
+    /// # Arguments
+    ///
+    /// * `headers` - the headers of the HTTP request
+    /// * `client_cert` - the DER encoded end-entity certificate presented by the client, if any
+    pub(crate) fn authenticate(
+        &self,
+        headers: &HeaderMap,
+        client_cert: Option<&[u8]>,
+    ) -> Result<Option<Arc<Principal>>, HttpsAuthError> {
+        if let Some(authorization) = headers.get(AUTHORIZATION) {
+            let token = authorization
+                .to_str()
+                .ok()
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .ok_or(HttpsAuthError::Unauthorized)?;
+
+            return self
+                .bearer_tokens
+                .get(token.trim())
+                .cloned()
+                .map(Some)
+                .ok_or(HttpsAuthError::Unauthorized);
+        }
+
+        if let Some(principal) = client_cert.and_then(|cert| self.client_certificates.get(cert)) {
+            return Ok(Some(Arc::clone(principal)));
+        }
+
+        if self.allow_anonymous {
+            Ok(None)
+        } else {
+            Err(HttpsAuthError::Unauthorized)
+        }
+    }
+}
+
+/// A named principal, with its own quota, which may issue requests
+pub struct Principal {
+    name: Arc<str>,
+    profile: Arc<PolicyProfile>,
+    bucket: Mutex<TokenBucket>,
+}
+
+impl Principal {
+    /// Creates a new principal, limited by the profile
+    pub fn new(name: impl Into<Arc<str>>, profile: Arc<PolicyProfile>) -> Self {
+        let bucket = Mutex::new(TokenBucket::new(profile.burst, Instant::now()));
+
+        Self {
+            name: name.into(),
+            profile,
+            bucket,
+        }
+    }
+
+    /// The name of the principal
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The profile which applies to this principal
+    pub fn profile(&self) -> &PolicyProfile {
+        &self.profile
+    }
+
+    /// Takes a request from this principal's quota, returns an error if the quota is exhausted
+    pub(crate) fn check_quota(&self) -> Result<(), HttpsAuthError> {
+        self.check_quota_at(Instant::now())
+    }
+
+    fn check_quota_at(&self, now: Instant) -> Result<(), HttpsAuthError> {
+        let Some(queries_per_second) = self.profile.queries_per_second else {
+            return Ok(());
+        };
+
+        let mut bucket = self.bucket.lock().expect("bucket poisoned");
+        if bucket.take(queries_per_second, self.profile.burst, now) {
+            Ok(())
+        } else {
+            Err(HttpsAuthError::RateLimited)
+        }
+    }
+
+    /// Checks that the principal's profile allows the query type
+    pub(crate) fn check_query_type(&self, query_type: RecordType) -> Result<(), HttpsAuthError> {
+        if self.profile.denied_query_types.contains(&query_type) {
+            Err(HttpsAuthError::Forbidden)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl fmt::Debug for Principal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Principal")
+            .field("name", &self.name)
+            .field("profile", &self.profile)
+            .finish()
+    }
+}
+
+/// Limits and policies which apply to principals
+#[derive(Debug, Default)]
+pub struct PolicyProfile {
+    name: String,
+    queries_per_second: Option<u32>,
+    burst: u32,
+    denied_query_types: HashSet<RecordType>,
+}
+
+impl PolicyProfile {
+    /// Creates a new profile
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of the profile
+    /// * `queries_per_second` - sustained rate of queries allowed, unlimited if `None`
+    /// * `burst` - number of queries which may be issued at once, at least one is always allowed
+    /// * `denied_query_types` - query types which are refused
+    pub fn new(
+        name: String,
+        queries_per_second: Option<u32>,
+        burst: u32,
+        denied_query_types: HashSet<RecordType>,
+    ) -> Self {
+        Self {
+            name,
+            queries_per_second,
+            burst: burst.max(1),
+            denied_query_types,
+        }
+    }
+
+    fn try_from_config(config: &PolicyProfileConfig) -> Result<Self, String> {
+        let denied_query_types = config
+            .denied_query_types
+            .iter()
+            .map(|query_type| {
+                RecordType::from_str(query_type)
+                    .map_err(|e| format!("bad query type in profile {}: {e}", config.name))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self::new(
+            config.name.clone(),
+            config.queries_per_second,
+            config.burst.or(config.queries_per_second).unwrap_or(1),
+            denied_query_types,
+        ))
+    }
+
+    /// The name of the profile
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// A token bucket refilled at a constant rate, up to the burst size
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(burst: u32, now: Instant) -> Self {
+        Self {
+            tokens: f64::from(burst),
+            last_refill: now,
+        }
+    }
+
+    fn take(&mut self, rate: u32, burst: u32, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * f64::from(rate)).min(f64::from(burst));
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Reasons for which an HTTPS request is rejected
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum HttpsAuthError {
+    /// No valid credentials were presented
+    Unauthorized,
+    /// The principal's quota is exhausted
+    RateLimited,
+    /// The principal's profile does not allow the request
+    Forbidden,
+}
+
+impl HttpsAuthError {
+    /// The HTTP status to respond with
+    pub(crate) fn status(self) -> StatusCode {
+        match self {
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            Self::Forbidden => StatusCode::FORBIDDEN,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use http::HeaderValue;
+
+    use super::*;
+
+    fn limited_principal(queries_per_second: u32, burst: u32) -> Principal {
+        let profile = PolicyProfile::new(
+            "limited".to_string(),
+            Some(queries_per_second),
+            burst,
+            [RecordType::ANY].into_iter().collect(),
+        );
+        Principal::new("alice", Arc::new(profile))
+    }
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {token}")).unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn test_authenticate_bearer_token() {
+        let principal = Arc::new(limited_principal(1, 1));
+        let mut auth = HttpsAuth::new(false);
+        auth.insert_bearer_token("s3cr3t".to_string(), principal)
+            .unwrap();
+
+        let found = auth.authenticate(&bearer("s3cr3t"), None).unwrap();
+        assert_eq!(found.unwrap().name(), "alice");
+
+        assert_eq!(
+            auth.authenticate(&bearer("wrong"), None).unwrap_err(),
+            HttpsAuthError::Unauthorized
+        );
+        assert_eq!(
+            auth.authenticate(&HeaderMap::new(), None).unwrap_err(),
+            HttpsAuthError::Unauthorized
+        );
+    }
+
+    #[test]
+    fn test_authenticate_client_certificate() {
+        let cert = vec![1, 2, 3];
+        let mut auth = HttpsAuth::new(true);
+        auth.insert_client_certificate(cert.clone(), Arc::new(limited_principal(1, 1)))
+            .unwrap();
+
+        let found = auth
+            .authenticate(&HeaderMap::new(), Some(&cert[..]))
+            .unwrap();
+        assert_eq!(found.unwrap().name(), "alice");
+
+        // unknown certificates are treated as anonymous
+        let unknown = [4_u8, 5, 6];
+        assert!(auth
+            .authenticate(&HeaderMap::new(), Some(&unknown[..]))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_quota() {
+        let principal = limited_principal(2, 3);
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            assert!(principal.check_quota_at(start).is_ok());
+        }
+        assert_eq!(
+            principal.check_quota_at(start).unwrap_err(),
+            HttpsAuthError::RateLimited
+        );
+
+        // two queries per second refill a token every 500ms
+        let later = start + Duration::from_millis(500);
+        assert!(principal.check_quota_at(later).is_ok());
+        assert!(principal.check_quota_at(later).is_err());
+    }
+
+    #[test]
+    fn test_denied_query_types() {
+        let principal = limited_principal(1, 1);
+
+        assert!(principal.check_query_type(RecordType::A).is_ok());
+        assert_eq!(
+            principal.check_query_type(RecordType::ANY).unwrap_err(),
+            HttpsAuthError::Forbidden
+        );
+    }
+}
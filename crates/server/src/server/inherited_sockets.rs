@@ -0,0 +1,156 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Sockets inherited from the parent process, e.g. with systemd socket activation
+
+use std::io;
+#[cfg(unix)]
+use std::{
+    env,
+    os::unix::io::{FromRawFd, OwnedFd, RawFd},
+};
+
+#[cfg(unix)]
+use socket2::{Socket, Type};
+#[cfg(unix)]
+use tracing::debug;
+
+/// The first file descriptor passed with the socket activation protocol
+#[cfg(unix)]
+const LISTEN_FDS_START: RawFd = 3;
+
+/// A bound socket which was inherited from the parent process
+///
+/// Sockets which stay open across a restart, e.g. held by systemd or passed by the previous
+///  process, allow the server to be restarted without refusing queries: they are queued by the
+///  kernel until the new process registers the sockets with `ServerFuture::register_inherited_socket`.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum InheritedSocket {
+    /// A UDP socket
+    Udp(std::net::UdpSocket),
+    /// A TCP socket, which is listening
+    Tcp(std::net::TcpListener),
+}
+
+impl InheritedSocket {
+    /// Takes the ownership of a bound socket passed from another process, which is made
+    ///  non-blocking
+    ///
+    /// Returns an error if the file descriptor is not a UDP or a TCP socket.
+    #[cfg(unix)]
+    #[cfg_attr(docsrs, doc(cfg(unix)))]
+    pub fn from_fd(fd: OwnedFd) -> io::Result<Self> {
+        let socket = Socket::from(fd);
+        socket.set_nonblocking(true)?;
+        // the socket is not passed on to the processes spawned by this one
+        socket.set_cloexec(true)?;
+
+        let ty = socket.r#type()?;
+        if ty == Type::DGRAM {
+            Ok(Self::Udp(socket.into()))
+        } else if ty == Type::STREAM {
+            Ok(Self::Tcp(socket.into()))
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("inherited socket of unsupported type: {ty:?}"),
+            ))
+        }
+    }
+}
+
+/// Returns no sockets, the socket activation protocol is only supported on unix
+#[cfg(not(unix))]
+pub fn listen_fds() -> io::Result<Vec<(String, InheritedSocket)>> {
+    Ok(Vec::new())
+}
+
+/// Returns the sockets passed with the systemd socket activation protocol, along with their
+///  names, see [sd_listen_fds(3)](https://www.freedesktop.org/software/systemd/man/sd_listen_fds.html)
+///
+/// The sockets are passed in `LISTEN_FDS` file descriptors starting at 3, if `LISTEN_PID` is the
+///  id of this process. Their names are set with `FileDescriptorName=` in the socket units, and
+///  passed in `LISTEN_FDNAMES`, the name is `unknown` if it is not passed. The variables are
+///  removed from the environment, so that the sockets are only taken once. No sockets are returned
+///  if the variables are not set.
+///
+/// This must be called at most once.
+#[cfg(unix)]
+pub fn listen_fds() -> io::Result<Vec<(String, InheritedSocket)>> {
+    let pid = env::var("LISTEN_PID").ok();
+    let fds = env::var("LISTEN_FDS").ok();
+    let names = env::var("LISTEN_FDNAMES").ok();
+    env::remove_var("LISTEN_PID");
+    env::remove_var("LISTEN_FDS");
+    env::remove_var("LISTEN_FDNAMES");
+
+    let (Some(pid), Some(fds)) = (pid, fds) else {
+        return Ok(Vec::new());
+    };
+
+    // the sockets were passed to another process, which spawned this one
+    if pid.parse::<u32>().ok() != Some(std::process::id()) {
+        debug!("ignoring LISTEN_FDS of process {}", pid);
+        return Ok(Vec::new());
+    }
+
+    let fds = fds.parse::<RawFd>().map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("bad LISTEN_FDS {fds}: {e}"),
+        )
+    })?;
+    let mut names = names
+        .as_deref()
+        .map(|names| names.split(':').collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter();
+
+    (LISTEN_FDS_START..LISTEN_FDS_START.saturating_add(fds))
+        .map(|fd| {
+            // SAFETY: the file descriptors from 3 on are passed to this process by the protocol,
+            //  and are owned only here as the variables were removed
+            let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+            let name = names.next().unwrap_or("unknown").to_string();
+            Ok((name, InheritedSocket::from_fd(fd)?))
+        })
+        .collect()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::net::{TcpListener, UdpSocket};
+
+    use super::*;
+
+    #[test]
+    fn test_from_fd() {
+        let udp = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = udp.local_addr().unwrap();
+        match InheritedSocket::from_fd(OwnedFd::from(udp)).unwrap() {
+            InheritedSocket::Udp(udp) => assert_eq!(udp.local_addr().unwrap(), addr),
+            socket => panic!("expected udp: {socket:?}"),
+        }
+
+        let tcp = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = tcp.local_addr().unwrap();
+        match InheritedSocket::from_fd(OwnedFd::from(tcp)).unwrap() {
+            InheritedSocket::Tcp(tcp) => assert_eq!(tcp.local_addr().unwrap(), addr),
+            socket => panic!("expected tcp: {socket:?}"),
+        }
+    }
+
+    #[test]
+    fn test_listen_fds_of_other_process() {
+        env::set_var("LISTEN_PID", "1");
+        env::set_var("LISTEN_FDS", "2");
+
+        assert!(listen_fds().unwrap().is_empty());
+        assert!(env::var("LISTEN_FDS").is_err());
+    }
+}
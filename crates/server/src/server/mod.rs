@@ -7,20 +7,48 @@
 
 //! `Server` component for hosting a domain name servers operations.
 
+mod accept;
+mod admission;
+mod effective_config;
 #[cfg(feature = "dns-over-https")]
 mod h2_handler;
 #[cfg(feature = "dns-over-h3")]
 mod h3_handler;
+#[cfg(feature = "dns-over-https")]
+mod https_auth;
+mod inherited_sockets;
+mod op_code_router;
 mod protocol;
+mod proxy_protocol;
 #[cfg(feature = "dns-over-quic")]
 mod quic_handler;
 mod request_handler;
 mod response_handler;
+mod sd_notify;
 mod server_future;
+#[cfg(feature = "dns-over-rustls")]
+mod sni_cert_resolver;
 mod timeout_stream;
+#[cfg(all(feature = "xdp", target_os = "linux"))]
+mod xdp;
 
+pub use self::accept::AcceptConfig;
+pub use self::admission::{AdmissionConfig, AdmissionStats, Overflow};
+pub use self::effective_config::{EffectiveConfig, ListenerConfig};
+#[cfg(feature = "dns-over-https")]
+#[cfg_attr(docsrs, doc(cfg(feature = "dns-over-https")))]
+pub use self::https_auth::{HttpsAuth, PolicyProfile, Principal};
+pub use self::inherited_sockets::{listen_fds, InheritedSocket};
+pub use self::op_code_router::{OpCodeHandler, OpCodeRouter};
 pub use self::protocol::Protocol;
 pub use self::request_handler::{Request, RequestHandler, RequestInfo, ResponseInfo};
-pub use self::response_handler::{ResponseHandle, ResponseHandler};
+pub use self::response_handler::{ResponseConfig, ResponseHandle, ResponseHandler};
+pub use self::sd_notify::{sd_notify, sd_watchdog_interval};
 pub use self::server_future::ServerFuture;
+#[cfg(feature = "dns-over-rustls")]
+#[cfg_attr(docsrs, doc(cfg(feature = "dns-over-rustls")))]
+pub use self::sni_cert_resolver::{CertLoader, SniCertResolver, ACME_TLS_ALPN_PROTOCOL};
 pub use self::timeout_stream::TimeoutStream;
+#[cfg(all(feature = "xdp", target_os = "linux"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "xdp", target_os = "linux"))))]
+pub use self::xdp::{XdpConfig, XdpSocket};
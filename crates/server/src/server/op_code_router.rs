@@ -0,0 +1,190 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Dispatch of requests to handlers per operation code
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use tracing::{debug, error};
+
+use crate::{
+    authority::MessageResponseBuilder,
+    proto::op::{Header, MessageRef, MessageType, OpCode, ResponseCode},
+    server::{Protocol, Request, RequestHandler, ResponseHandler, ResponseInfo},
+};
+
+/// A handler of the requests with one operation code, see `OpCodeRouter`
+///
+/// Unlike `RequestHandler`, the response only consists of the response code, which allows
+///  workflows such as purging a cache or calling a webhook on NOTIFY.
+#[async_trait::async_trait]
+pub trait OpCodeHandler: Send + Sync + 'static {
+    /// Handles the request, which is never a response message
+    ///
+    /// # Return
+    ///
+    /// The response code sent to the client, along with the header and queries of the request
+    async fn handle(&self, request: &Request) -> ResponseCode;
+}
+
+/// Dispatches requests to the handler registered for their operation code
+///
+/// Requests with other operation codes, and responses, are passed to the wrapped
+///  `RequestHandler`, e.g. a `Catalog`.
+pub struct OpCodeRouter<T: RequestHandler> {
+    handler: T,
+    routes: HashMap<OpCode, Arc<dyn OpCodeHandler>>,
+}
+
+impl<T: RequestHandler> OpCodeRouter<T> {
+    /// Creates a router passing all requests to `handler`, until others are registered
+    pub fn new(handler: T) -> Self {
+        Self {
+            handler,
+            routes: HashMap::new(),
+        }
+    }
+
+    /// Registers the handler of the requests with the operation code, replacing any previous one
+    pub fn register(&mut self, op_code: OpCode, handler: impl OpCodeHandler) {
+        self.routes.insert(op_code, Arc::new(handler));
+    }
+
+    /// Removes the handler of the operation code, passing its requests to the wrapped handler
+    pub fn unregister(&mut self, op_code: OpCode) {
+        self.routes.remove(&op_code);
+    }
+
+    /// The handler of the requests without a registered operation code
+    pub fn handler(&self) -> &T {
+        &self.handler
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: RequestHandler> RequestHandler for OpCodeRouter<T> {
+    async fn handle_request<R: ResponseHandler>(
+        &self,
+        request: &Request,
+        mut response_handle: R,
+    ) -> ResponseInfo {
+        let route = match request.message_type() {
+            MessageType::Query => self.routes.get(&request.op_code()),
+            MessageType::Response => None,
+        };
+        let Some(route) = route else {
+            return self.handler.handle_request(request, response_handle).await;
+        };
+
+        debug!("{} received: {}", request.op_code(), request.id());
+        let response_code = route.handle(request).await;
+
+        let mut header = Header::response_from_request(request.header());
+        header.set_response_code(response_code);
+        let response = MessageResponseBuilder::new(Some(request.raw_query()));
+        match response_handle
+            .send_response(response.build_no_records(header))
+            .await
+        {
+            Ok(info) => info,
+            Err(e) => {
+                error!("request failed: {}", e);
+                ResponseInfo::serve_failed()
+            }
+        }
+    }
+
+    fn answer_ref(
+        &self,
+        request: &MessageRef<'_>,
+        src: SocketAddr,
+        protocol: Protocol,
+    ) -> Option<Vec<u8>> {
+        if self.routes.contains_key(&request.header().op_code()) {
+            return None;
+        }
+
+        self.handler.answer_ref(request, src, protocol)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use futures_util::StreamExt;
+
+    use super::*;
+    use crate::{
+        authority::{Catalog, MessageRequest},
+        proto::{
+            op::{Message, Query},
+            rr::{Name, RecordType},
+            serialize::binary::{BinDecodable, BinDecoder},
+            BufDnsStreamHandle,
+        },
+        server::ResponseHandle,
+    };
+
+    /// Counts the NOTIFY requests, e.g. to purge a cache
+    #[derive(Clone, Default)]
+    struct NotifyCounter(Arc<AtomicUsize>);
+
+    #[async_trait::async_trait]
+    impl OpCodeHandler for NotifyCounter {
+        async fn handle(&self, _request: &Request) -> ResponseCode {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            ResponseCode::NoError
+        }
+    }
+
+    async fn send(router: &OpCodeRouter<Catalog>, op_code: OpCode) -> Message {
+        let src = SocketAddr::from(([127, 0, 0, 1], 4096));
+        let mut message = Message::new();
+        message.set_id(7).set_op_code(op_code);
+        message.add_query(Query::query(
+            Name::from_ascii("example.com.").unwrap(),
+            RecordType::SOA,
+        ));
+        let bytes = message.to_vec().unwrap();
+        let request = MessageRequest::read(&mut BinDecoder::new(&bytes)).unwrap();
+
+        let (stream_handle, mut receiver) = BufDnsStreamHandle::new(src);
+        router
+            .handle_request(
+                &Request::new(request, src, Protocol::Udp),
+                ResponseHandle::new(src, stream_handle, Protocol::Udp),
+            )
+            .await;
+
+        Message::from_vec(receiver.next().await.unwrap().bytes()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_op_code_router() {
+        let notifies = NotifyCounter::default();
+        let mut router = OpCodeRouter::new(Catalog::new());
+        router.register(OpCode::Notify, notifies.clone());
+
+        let response = send(&router, OpCode::Notify).await;
+        assert_eq!(response.id(), 7);
+        assert_eq!(response.op_code(), OpCode::Notify);
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        assert_eq!(notifies.0.load(Ordering::Relaxed), 1);
+
+        // other operation codes are passed to the catalog, which has no zones
+        let response = send(&router, OpCode::Query).await;
+        assert_eq!(response.response_code(), ResponseCode::Refused);
+
+        router.unregister(OpCode::Notify);
+        let response = send(&router, OpCode::Notify).await;
+        assert_eq!(response.response_code(), ResponseCode::NotImp);
+        assert_eq!(notifies.0.load(Ordering::Relaxed), 1);
+    }
+}
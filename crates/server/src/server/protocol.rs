@@ -7,9 +7,11 @@
 
 use std::fmt;
 
+use serde::Serialize;
+
 /// For tracking purposes of inbound requests, which protocol was used
 #[non_exhaustive]
-#[derive(Clone, Copy, Hash, Eq, PartialEq)]
+#[derive(Clone, Copy, Hash, Eq, PartialEq, Serialize)]
 pub enum Protocol {
     /// User Datagram Protocol, the default for all DNS requests
     Udp,
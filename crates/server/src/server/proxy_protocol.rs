@@ -0,0 +1,205 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! The PROXY protocol v2 of HAProxy, with which load balancers pass the address of the client
+//!  ahead of the forwarded connection, see
+//!  [proxy-protocol.txt](https://www.haproxy.org/download/2.8/doc/proxy-protocol.txt)
+
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use futures_util::{AsyncRead, AsyncReadExt};
+
+/// The first bytes of every header
+const SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+
+/// The length of the signature, the version and command, the family and the length of the
+///  addresses
+const HEADER_LEN: usize = 16;
+
+const VERSION_2: u8 = 0x20;
+const COMMAND_LOCAL: u8 = 0x00;
+const COMMAND_PROXY: u8 = 0x01;
+
+const FAMILY_INET: u8 = 0x10;
+const FAMILY_INET6: u8 = 0x20;
+
+/// Reads the PROXY protocol v2 header from the start of the connection
+///
+/// Returns the source address of the forwarded connection, or `None` if the addresses are not
+///  passed, e.g. for the health checks of the load balancer with the `LOCAL` command or for
+///  connections other than TCP over IPv4 or IPv6. The connection is then to be treated as coming
+///  from the load balancer itself.
+///
+/// Returns an error if the connection does not start with a valid header.
+pub(crate) async fn read_proxy_header<S: AsyncRead + Unpin>(
+    stream: &mut S,
+) -> io::Result<Option<SocketAddr>> {
+    let mut header = [0; HEADER_LEN];
+    stream.read_exact(&mut header).await?;
+
+    if header[..SIGNATURE.len()] != SIGNATURE {
+        return Err(invalid_data("missing the PROXY protocol v2 signature"));
+    }
+
+    let version = header[12] & 0xF0;
+    let command = header[12] & 0x0F;
+    if version != VERSION_2 {
+        return Err(invalid_data(format!(
+            "unsupported PROXY protocol version: {}",
+            version >> 4
+        )));
+    }
+
+    // the addresses, which are followed by TLVs, are always read to reach the DNS messages
+    let len = u16::from_be_bytes([header[14], header[15]]);
+    let mut addresses = vec![0; usize::from(len)];
+    stream.read_exact(&mut addresses).await?;
+
+    match command {
+        COMMAND_LOCAL => return Ok(None),
+        COMMAND_PROXY => (),
+        _ => {
+            return Err(invalid_data(format!(
+                "unsupported PROXY protocol command: {command}"
+            )))
+        }
+    }
+
+    // the transport protocol is not checked, the addresses are passed the same for all of them
+    let src_addr = match header[13] & 0xF0 {
+        FAMILY_INET => {
+            let addresses: &[u8; 12] = addresses
+                .get(..12)
+                .and_then(|a| a.try_into().ok())
+                .ok_or_else(|| invalid_data("truncated PROXY protocol IPv4 addresses"))?;
+            let ip = Ipv4Addr::from([addresses[0], addresses[1], addresses[2], addresses[3]]);
+            let port = u16::from_be_bytes([addresses[8], addresses[9]]);
+            SocketAddr::from((ip, port))
+        }
+        FAMILY_INET6 => {
+            let addresses: &[u8; 36] = addresses
+                .get(..36)
+                .and_then(|a| a.try_into().ok())
+                .ok_or_else(|| invalid_data("truncated PROXY protocol IPv6 addresses"))?;
+            let mut ip = [0; 16];
+            ip.copy_from_slice(&addresses[..16]);
+            let port = u16::from_be_bytes([addresses[32], addresses[33]]);
+            SocketAddr::from((Ipv6Addr::from(ip), port))
+        }
+        // unspecified or unix sockets
+        _ => return Ok(None),
+    };
+
+    Ok(Some(src_addr))
+}
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Returns the PROXY protocol v2 header of a TCP connection from `src_addr` to `dst_addr`
+#[cfg(test)]
+pub(crate) fn proxy_header(src_addr: SocketAddr, dst_addr: SocketAddr) -> Vec<u8> {
+    let mut header = SIGNATURE.to_vec();
+    header.push(VERSION_2 | COMMAND_PROXY);
+    let addresses = match (src_addr, dst_addr) {
+        (SocketAddr::V4(src), SocketAddr::V4(dst)) => {
+            header.push(FAMILY_INET | 0x01);
+            [&src.ip().octets()[..], &dst.ip().octets()[..]].concat()
+        }
+        (SocketAddr::V6(src), SocketAddr::V6(dst)) => {
+            header.push(FAMILY_INET6 | 0x01);
+            [&src.ip().octets()[..], &dst.ip().octets()[..]].concat()
+        }
+        _ => panic!("the addresses must be of the same family"),
+    };
+
+    header.extend_from_slice(&(addresses.len() as u16 + 4).to_be_bytes());
+    header.extend_from_slice(&addresses);
+    header.extend_from_slice(&src_addr.port().to_be_bytes());
+    header.extend_from_slice(&dst_addr.port().to_be_bytes());
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_executor::block_on;
+
+    use super::*;
+
+    fn read(mut bytes: &[u8]) -> io::Result<Option<SocketAddr>> {
+        let result = block_on(read_proxy_header(&mut bytes));
+        // the header is read completely, but not the message after it
+        if result.is_ok() {
+            assert_eq!(bytes, b"query");
+        }
+        result
+    }
+
+    #[test]
+    fn test_read_proxy_header() {
+        let src = SocketAddr::from(([192, 0, 2, 1], 4096));
+        let dst = SocketAddr::from(([192, 0, 2, 53], 53));
+        let header = [proxy_header(src, dst), b"query".to_vec()].concat();
+        assert_eq!(read(&header).unwrap(), Some(src));
+
+        let src = SocketAddr::from(([0x2001, 0xdb8, 0, 0, 0, 0, 0, 1], 4096));
+        let dst = SocketAddr::from(([0x2001, 0xdb8, 0, 0, 0, 0, 0, 53], 53));
+        let header = [proxy_header(src, dst), b"query".to_vec()].concat();
+        assert_eq!(read(&header).unwrap(), Some(src));
+    }
+
+    #[test]
+    fn test_read_proxy_header_tlvs() {
+        let src = SocketAddr::from(([192, 0, 2, 1], 4096));
+        let dst = SocketAddr::from(([192, 0, 2, 53], 53));
+        let mut header = proxy_header(src, dst);
+        // a PP2_TYPE_NOOP TLV
+        header[15] += 4;
+        header.extend_from_slice(&[0x04, 0x00, 0x01, 0x00]);
+        header.extend_from_slice(b"query");
+        assert_eq!(read(&header).unwrap(), Some(src));
+    }
+
+    #[test]
+    fn test_read_proxy_header_local() {
+        let mut header = SIGNATURE.to_vec();
+        header.extend_from_slice(&[VERSION_2 | COMMAND_LOCAL, 0x00, 0x00, 0x00]);
+        header.extend_from_slice(b"query");
+        assert_eq!(read(&header).unwrap(), None);
+    }
+
+    #[test]
+    fn test_read_proxy_header_invalid() {
+        // a DNS message without the header
+        let err = read(&[
+            0x00, 0x1d, 0x12, 0x34, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x03, b'w', b'w', b'w', 0x00, 0x00, 0x01, 0x00, 0x01,
+        ])
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // version 1
+        let mut header = SIGNATURE.to_vec();
+        header.extend_from_slice(&[0x11, 0x11, 0x00, 0x00]);
+        let err = read(&header).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // addresses shorter than the family requires
+        let mut header = SIGNATURE.to_vec();
+        header.extend_from_slice(&[VERSION_2 | COMMAND_PROXY, FAMILY_INET | 0x01, 0x00, 0x04]);
+        header.extend_from_slice(&[192, 0, 2, 1]);
+        header.extend_from_slice(b"query");
+        let err = read(&header).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // the connection is closed before the end of the header
+        let err = read(&SIGNATURE).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -23,13 +23,14 @@ use crate::{
     proto::quic::QuicStreams,
     server::{
         request_handler::RequestHandler, response_handler::ResponseHandler, server_future,
-        Protocol, ResponseInfo,
+        Protocol, ResponseConfig, ResponseInfo,
     },
 };
 
 pub(crate) async fn quic_handler<T>(
     access: Arc<AccessControl>,
     handler: Arc<T>,
+    response_config: ResponseConfig,
     mut quic_streams: QuicStreams,
     src_addr: SocketAddr,
     _dns_hostname: Option<Arc<str>>,
@@ -69,7 +70,7 @@ where
         let handler = handler.clone();
         let access = access.clone();
         let stream = Arc::new(Mutex::new(request_stream));
-        let responder = QuicResponseHandle(stream.clone());
+        let responder = QuicResponseHandle(stream.clone(), response_config);
 
         handle_request(request, src_addr, access, handler, responder).await;
 
@@ -100,7 +101,7 @@ async fn handle_request<T>(
 }
 
 #[derive(Clone)]
-struct QuicResponseHandle(Arc<Mutex<QuicStream>>);
+struct QuicResponseHandle(Arc<Mutex<QuicStream>>, ResponseConfig);
 
 #[async_trait::async_trait]
 impl ResponseHandler for QuicResponseHandle {
@@ -124,7 +125,7 @@ impl ResponseHandler for QuicResponseHandle {
         let mut bytes = Vec::with_capacity(512);
         let info = {
             let mut encoder = BinEncoder::new(&mut bytes);
-            response.destructive_emit(&mut encoder)?
+            response.destructive_emit_with(&mut encoder, &self.1)?
         };
         let bytes = Bytes::from(bytes);
 
@@ -11,7 +11,8 @@ use std::net::SocketAddr;
 
 use crate::{
     authority::MessageRequest,
-    proto::op::{Header, LowerQuery, ResponseCode},
+    proto::op::{Header, LowerQuery, MessageRef, ResponseCode},
+    proto::rr::rdata::opt::{ClientSubnet, EdnsCode, EdnsOption},
     server::{Protocol, ResponseHandler},
 };
 
@@ -45,6 +46,15 @@ impl Request {
             protocol: self.protocol,
             header: self.message.header(),
             query: self.message.query(),
+            client_subnet: self.client_subnet(),
+        }
+    }
+
+    /// The subnet of the client in the EDNS Client Subnet option of the request, if any
+    pub fn client_subnet(&self) -> Option<ClientSubnet> {
+        match self.message.edns()?.option(EdnsCode::Subnet)? {
+            EdnsOption::Subnet(subnet) => Some(*subnet),
+            _ => None,
         }
     }
 
@@ -80,6 +90,9 @@ pub struct RequestInfo<'a> {
     pub header: &'a Header,
     /// The query from the request
     pub query: &'a LowerQuery,
+    /// The subnet of the client in the EDNS Client Subnet option, see
+    ///  [RFC 7871](https://tools.ietf.org/html/rfc7871)
+    pub client_subnet: Option<ClientSubnet>,
 }
 
 impl<'a> RequestInfo<'a> {
@@ -102,8 +115,15 @@ impl<'a> RequestInfo<'a> {
             protocol,
             header,
             query,
+            client_subnet: None,
         }
     }
+
+    /// The address of the client, from the EDNS Client Subnet option if the request has one
+    pub fn client_addr(&self) -> std::net::IpAddr {
+        self.client_subnet
+            .map_or_else(|| self.src.ip(), |subnet| subnet.addr())
+    }
 }
 
 /// Information about the response sent for a request
@@ -147,6 +167,32 @@ pub trait RequestHandler: Send + Sync + Unpin + 'static {
         request: &Request,
         response_handle: R,
     ) -> ResponseInfo;
+
+    /// Answers the request from a borrowed view of the message, before it is fully decoded
+    ///
+    /// This allows simple queries to be answered without decoding the request into owned
+    ///  values, e.g. for servers under high load. If `None` is returned, the request is decoded
+    ///  and passed to `handle_request`. It is also passed there if the response is larger than
+    ///  the client accepts, so that it can be truncated.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - the request, which is never a response message
+    /// * `src` - source address of the client
+    /// * `protocol` - protocol the request was received on
+    ///
+    /// # Return
+    ///
+    /// The serialized response, see `MessageRef::to_response`
+    fn answer_ref(
+        &self,
+        request: &MessageRef<'_>,
+        src: SocketAddr,
+        protocol: Protocol,
+    ) -> Option<Vec<u8>> {
+        let _ = (request, src, protocol);
+        None
+    }
 }
 
 #[cfg(test)]
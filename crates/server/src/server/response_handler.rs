@@ -14,11 +14,56 @@ use crate::server::Protocol;
 use crate::{
     authority::MessageResponse,
     proto::{
-        serialize::binary::BinEncoder, xfer::SerialMessage, BufDnsStreamHandle, DnsStreamHandle,
+        serialize::binary::EncodeBufferPool, xfer::SerialMessage, BufDnsStreamHandle,
+        DnsStreamHandle,
     },
     server::ResponseInfo,
 };
 
+/// Buffers for encoding responses, shared by all `ResponseHandle`s
+///
+/// The buffers and name compression tables are reused, so that only the final copy of the
+///  response is allocated.
+static ENCODE_BUFFERS: EncodeBufferPool = EncodeBufferPool::new(256);
+
+/// How the responses of a `ServerFuture` are sized, and which optional records they carry
+///
+/// See `ServerFuture::set_response_config`. Responses are truncated to whole RRsets, if an RRset
+///  of the answer or authority section does not fit, it and all following records are left out
+///  and the TC flag is set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ResponseConfig {
+    /// The maximum size of responses over UDP, the payload size advertised by clients with EDNS
+    ///  is capped to this. Values below 512 are raised to 512. Defaults to 1232, which avoids IP
+    ///  fragmentation on nearly all paths.
+    pub max_udp_payload: u16,
+    /// Whether the RRsets of the additional section which do not fit are left out without
+    ///  setting the TC flag, as allowed by RFC 2181 section 9. Defaults to true.
+    pub omit_additionals: bool,
+    /// Whether records of the authority and additional sections are only sent when required, i.e.
+    ///  the NS records and additional records of positive answers are left out. Negative
+    ///  responses and referrals are not changed. Defaults to false.
+    pub minimal_responses: bool,
+}
+
+impl Default for ResponseConfig {
+    fn default() -> Self {
+        Self {
+            max_udp_payload: 1232,
+            omit_additionals: true,
+            minimal_responses: false,
+        }
+    }
+}
+
+impl ResponseConfig {
+    /// The maximum size of a UDP response, for a client which advertised the `max_payload`
+    pub(crate) fn udp_payload(&self, max_payload: u16) -> u16 {
+        max_payload.min(self.max_udp_payload).max(512)
+    }
+}
+
 /// A handler for send a response to a client
 #[async_trait::async_trait]
 pub trait ResponseHandler: Clone + Send + Sync + Unpin + 'static {
@@ -48,6 +93,7 @@ pub struct ResponseHandle {
     dst: SocketAddr,
     stream_handle: BufDnsStreamHandle,
     protocol: Protocol,
+    config: ResponseConfig,
 }
 
 impl ResponseHandle {
@@ -57,9 +103,16 @@ impl ResponseHandle {
             dst,
             stream_handle,
             protocol,
+            config: ResponseConfig::default(),
         }
     }
 
+    /// Sets how the responses are sized and which optional records they carry
+    pub fn with_config(mut self, config: ResponseConfig) -> Self {
+        self.config = config;
+        self
+    }
+
     /// Selects an appropriate maximum serialized size for the given response.
     fn max_size_for_response<'a>(
         &self,
@@ -76,7 +129,7 @@ impl ResponseHandle {
             Protocol::Udp => {
                 // Use EDNS, if available.
                 if let Some(edns) = response.get_edns() {
-                    edns.max_payload()
+                    self.config.udp_payload(edns.max_payload())
                 } else {
                     // No EDNS, use the recommended max from RFC6891.
                     hickory_proto::udp::MAX_RECEIVE_BUFFER_SIZE as u16
@@ -108,9 +161,9 @@ impl ResponseHandler for ResponseHandle {
             response.header().id(),
             response.header().response_code(),
         );
-        let mut buffer = Vec::with_capacity(512);
+        let mut buffer = ENCODE_BUFFERS.get();
         let encode_result = {
-            let mut encoder = BinEncoder::new(&mut buffer);
+            let mut encoder = buffer.encoder();
 
             // Set an appropriate maximum on the encoder.
             let max_size = self.max_size_for_response(&response);
@@ -120,7 +173,7 @@ impl ResponseHandler for ResponseHandle {
             );
             encoder.set_max_size(max_size);
 
-            response.destructive_emit(&mut encoder)
+            response.destructive_emit_with(&mut encoder, &self.config)
         };
 
         let info = encode_result.map_err(|e| {
@@ -128,7 +181,7 @@ impl ResponseHandler for ResponseHandle {
         })?;
 
         self.stream_handle
-            .send(SerialMessage::new(buffer, self.dst))
+            .send(SerialMessage::new(buffer.bytes().to_vec(), self.dst))
             .map_err(|_| io::Error::new(io::ErrorKind::Other, "unknown"))?;
 
         Ok(info)
@@ -0,0 +1,95 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Notifications of the state of the server to systemd
+
+use std::env;
+use std::io;
+use std::time::Duration;
+#[cfg(unix)]
+use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+#[cfg(unix)]
+use socket2::{Domain, SockAddr, Socket, Type};
+
+/// Sends the state to the service manager, e.g. `READY=1` once the server is listening,
+///  `STOPPING=1` on shutdown or `WATCHDOG=1`, see
+///  [sd_notify(3)](https://www.freedesktop.org/software/systemd/man/sd_notify.html)
+///
+/// Returns `false` if the server is not run by a service manager, i.e. `NOTIFY_SOCKET` is not set.
+#[cfg(unix)]
+pub fn sd_notify(state: &str) -> io::Result<bool> {
+    let Some(path) = env::var_os("NOTIFY_SOCKET") else {
+        return Ok(false);
+    };
+
+    // sockets in the abstract namespace of Linux are prefixed with @
+    let mut path = path.as_bytes().to_vec();
+    if path.first() == Some(&b'@') {
+        path[0] = 0;
+    }
+
+    let addr = SockAddr::unix(OsStr::from_bytes(&path))?;
+    let socket = Socket::new(Domain::UNIX, Type::DGRAM, None)?;
+    socket.send_to(state.as_bytes(), &addr)?;
+    Ok(true)
+}
+
+/// Returns `false`, the notifications are only supported on unix
+#[cfg(not(unix))]
+pub fn sd_notify(_state: &str) -> io::Result<bool> {
+    Ok(false)
+}
+
+/// Returns the interval in which the service manager expects `WATCHDOG=1` notifications, if the
+///  watchdog is enabled for this process with `WatchdogSec=`
+///
+/// The notifications should be sent at half of the interval, see
+///  [sd_watchdog_enabled(3)](https://www.freedesktop.org/software/systemd/man/sd_watchdog_enabled.html).
+pub fn sd_watchdog_interval() -> Option<Duration> {
+    if let Ok(pid) = env::var("WATCHDOG_PID") {
+        if pid.parse::<u32>().ok() != Some(std::process::id()) {
+            return None;
+        }
+    }
+
+    env::var("WATCHDOG_USEC")
+        .ok()?
+        .parse::<u64>()
+        .ok()
+        .filter(|usec| *usec > 0)
+        .map(Duration::from_micros)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::net::UnixDatagram;
+
+    use super::*;
+
+    #[test]
+    fn test_sd_notify() {
+        let dir = env::temp_dir().join(format!("hickory-sd-notify-{}", std::process::id()));
+        let _ = std::fs::remove_file(&dir);
+        let receiver = UnixDatagram::bind(&dir).unwrap();
+
+        env::set_var("NOTIFY_SOCKET", &dir);
+        env::set_var("WATCHDOG_USEC", "30000000");
+        assert!(sd_notify("READY=1").unwrap());
+        assert_eq!(sd_watchdog_interval(), Some(Duration::from_secs(30)));
+        env::remove_var("NOTIFY_SOCKET");
+        env::remove_var("WATCHDOG_USEC");
+
+        let mut buf = [0; 16];
+        let len = receiver.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"READY=1");
+        std::fs::remove_file(&dir).unwrap();
+
+        assert!(!sd_notify("READY=1").unwrap());
+        assert_eq!(sd_watchdog_interval(), None);
+    }
+}
@@ -8,36 +8,69 @@ use std::{
     io,
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
     sync::Arc,
+    task::{Context, Poll},
     time::Duration,
 };
 
-use futures_util::{FutureExt, StreamExt};
+use futures_util::{future, FutureExt, StreamExt};
 use hickory_proto::{op::MessageType, rr::Record};
 use ipnet::IpNet;
 #[cfg(feature = "dns-over-rustls")]
-use rustls::{Certificate, PrivateKey, ServerConfig};
+use rustls::{server::ResolvesServerCert, Certificate, PrivateKey, ServerConfig};
+use socket2::{Domain, Socket, Type};
 use tokio::{net, task::JoinSet};
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, info, warn};
 
 #[cfg(all(feature = "dns-over-openssl", not(feature = "dns-over-rustls")))]
 use crate::proto::openssl::tls_server::*;
+#[cfg(feature = "testing")]
+use crate::proto::sim::{SimTcpListener, SimTcpStream, SimUdpSocket};
+#[cfg(feature = "udp-batch")]
+use crate::proto::udp::{send_batch, RecvBatch, MAX_BATCH_SIZE};
+#[cfg(any(
+    not(feature = "udp-batch"),
+    all(feature = "io-uring", target_os = "linux"),
+    feature = "testing"
+))]
+use crate::proto::udp::{DnsUdpSocket, UdpStream};
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+use crate::proto::uring::{IoUringTcpListener, IoUringUdpSocket};
+#[cfg(any(feature = "udp-batch", all(feature = "xdp", target_os = "linux")))]
+use crate::proto::xfer::StreamReceiver;
+#[cfg(feature = "dns-over-https-rustls")]
+use crate::server::HttpsAuth;
 use crate::{
     access::AccessControl,
     authority::{MessageRequest, MessageResponseBuilder},
     proto::{
         error::ProtoError,
         iocompat::AsyncIoTokioAsStd,
-        op::{Edns, Header, LowerQuery, Query, ResponseCode},
+        op::{Edns, Header, LowerQuery, MessageRef, Query, ResponseCode},
         serialize::binary::{BinDecodable, BinDecoder},
-        tcp::TcpStream,
-        udp::UdpStream,
+        tcp::{DnsTcpStream, TcpStream},
         xfer::SerialMessage,
-        BufDnsStreamHandle,
+        BufDnsStreamHandle, DnsStreamHandle,
     },
-    server::{Protocol, Request, RequestHandler, ResponseHandle, ResponseHandler, TimeoutStream},
+    server::{
+        accept::{AcceptLimiter, Connection},
+        admission::{Admission, AdmissionCounters},
+        proxy_protocol::read_proxy_header,
+        AcceptConfig, AdmissionConfig, AdmissionStats, EffectiveConfig, InheritedSocket,
+        ListenerConfig, Overflow, Protocol, Request, RequestHandler, ResponseConfig,
+        ResponseHandle, ResponseHandler, TimeoutStream,
+    },
+};
+#[cfg(all(feature = "xdp", target_os = "linux"))]
+use crate::{
+    proto::udp::MAX_RECEIVE_BUFFER_SIZE,
+    server::xdp::{self, XdpConfig, XdpSocket},
 };
 
+/// The number of requests from the XDP socket which may wait for the normal UDP path
+#[cfg(all(feature = "xdp", target_os = "linux"))]
+const XDP_FALLBACK_QUEUE: usize = 1024;
+
 // TODO, would be nice to have a Slab for buffers here...
 /// A Futures based implementation of a DNS server
 pub struct ServerFuture<T: RequestHandler> {
@@ -45,6 +78,12 @@ pub struct ServerFuture<T: RequestHandler> {
     join_set: JoinSet<Result<(), ProtoError>>,
     shutdown_token: CancellationToken,
     access: Arc<AccessControl>,
+    effective_config: EffectiveConfig,
+    accept_config: AcceptConfig,
+    admission_config: AdmissionConfig,
+    admission_counters: Arc<AdmissionCounters>,
+    response_config: ResponseConfig,
+    proxy_protocol: bool,
 }
 
 impl<T: RequestHandler> ServerFuture<T> {
@@ -64,13 +103,103 @@ impl<T: RequestHandler> ServerFuture<T> {
             join_set: JoinSet::new(),
             shutdown_token: CancellationToken::new(),
             access: Arc::new(access),
+            effective_config: EffectiveConfig {
+                listeners: vec![],
+                denied_networks: denied_networks.to_vec(),
+                allowed_networks: allowed_networks.to_vec(),
+            },
+            accept_config: AcceptConfig::default(),
+            admission_config: AdmissionConfig::default(),
+            admission_counters: Arc::default(),
+            response_config: ResponseConfig::default(),
+            proxy_protocol: false,
         }
     }
 
+    /// Sets the limits and recovery of the accept loops, see `AcceptConfig`
+    ///
+    /// This applies to the listeners registered afterwards.
+    pub fn set_accept_config(&mut self, accept_config: AcceptConfig) {
+        self.accept_config = accept_config;
+    }
+
+    /// Sets how many requests are handled at a time, see `AdmissionConfig`
+    ///
+    /// This applies to the listeners registered afterwards.
+    pub fn set_admission_config(&mut self, admission_config: AdmissionConfig) {
+        self.admission_config = admission_config;
+    }
+
+    /// Returns the number of requests in flight, queued and overflowed on all listeners
+    pub fn admission_stats(&self) -> AdmissionStats {
+        self.admission_counters.stats()
+    }
+
+    /// Sets how the responses are sized and which optional records they carry, see
+    ///  `ResponseConfig`
+    ///
+    /// This applies to the listeners registered afterwards.
+    pub fn set_response_config(&mut self, response_config: ResponseConfig) {
+        self.response_config = response_config;
+    }
+
+    /// Sets whether the connections to the TCP and TLS listeners start with a PROXY protocol v2
+    ///  header, which passes the address of the client when the server is behind a load balancer
+    ///
+    /// The address in the header is then used for the access rules and passed to the handler in
+    ///  the `RequestInfo`. Connections without a valid header are closed, so this must only be
+    ///  enabled for listeners which are reachable only through the load balancer.
+    ///
+    /// This applies to the listeners registered afterwards.
+    pub fn set_proxy_protocol(&mut self, proxy_protocol: bool) {
+        self.proxy_protocol = proxy_protocol;
+    }
+
+    /// Returns the listeners and access rules this server is running with
+    ///
+    /// The addresses are those the sockets are actually bound to, e.g. with the port chosen by
+    ///  the OS when bound to port 0.
+    pub fn effective_config(&self) -> EffectiveConfig {
+        self.effective_config.clone()
+    }
+
+    fn record_listener(&mut self, listener: ListenerConfig) {
+        self.effective_config.listeners.push(listener);
+    }
+
+    /// The limits of the requests of a new listener
+    fn admission(&self) -> Arc<Admission> {
+        Admission::new(self.admission_config, Arc::clone(&self.admission_counters))
+    }
+
     /// Register a UDP socket. Should be bound before calling this function.
+    ///
+    /// With the `udp-batch` feature, requests are received and responses are sent in batches,
+    ///  with `recvmmsg` and `sendmmsg` on Linux.
     pub fn register_socket(&mut self, socket: net::UdpSocket) {
         debug!("registering udp: {:?}", socket);
+        self.record_listener(ListenerConfig::new(Protocol::Udp, socket.local_addr().ok()));
+
+        #[cfg(feature = "udp-batch")]
+        self.join_set.spawn(handle_udp_batches(
+            socket,
+            self.handler.clone(),
+            self.access.clone(),
+            self.response_config,
+            self.admission(),
+            self.shutdown_token.clone(),
+        ));
+
+        #[cfg(not(feature = "udp-batch"))]
+        self.register_udp_stream(socket);
+    }
 
+    #[cfg(any(
+        not(feature = "udp-batch"),
+        all(feature = "io-uring", target_os = "linux"),
+        feature = "testing"
+    ))]
+    fn register_udp_stream<S: DnsUdpSocket + Send + 'static>(&mut self, socket: S) {
         // create the new UdpStream, the IP address isn't relevant, and ideally goes essentially no where.
         //   the address used is acquired from the inbound queries
         let (mut stream, stream_handle) =
@@ -78,6 +207,8 @@ impl<T: RequestHandler> ServerFuture<T> {
         let shutdown = self.shutdown_token.clone();
         let handler = self.handler.clone();
         let access = self.access.clone();
+        let response_config = self.response_config;
+        let admission = self.admission();
 
         // this spawns a ForEach future which handles all the requests into a Handler.
         self.join_set.spawn({
@@ -116,19 +247,31 @@ impl<T: RequestHandler> ServerFuture<T> {
                         continue;
                     }
 
-                    let handler = handler.clone();
-                    let access = access.clone();
-                    let stream_handle = stream_handle.with_remote_addr(src_addr);
-
-                    inner_join_set.spawn(async move {
-                        handle_raw_request(message, Protocol::Udp, access, handler, stream_handle)
-                            .await;
-                    });
+                    spawn_request(
+                        &mut inner_join_set,
+                        &admission,
+                        message,
+                        Protocol::Udp,
+                        access.clone(),
+                        response_config,
+                        handler.clone(),
+                        stream_handle.with_remote_addr(src_addr),
+                    );
 
                     reap_tasks(&mut inner_join_set);
                 }
 
                 if shutdown.is_cancelled() {
+                    // answer the requests in flight, the stream sends the responses and drops
+                    //  the new requests meanwhile
+                    while !inner_join_set.is_empty() {
+                        tokio::select! {
+                            _ = inner_join_set.join_next() => (),
+                            _ = stream.next() => (),
+                        }
+                    }
+                    // and sends the responses of the last requests
+                    let _ = stream.next().now_or_never();
                     Ok(())
                 } else {
                     // TODO: let's consider capturing all the initial configuration details so that the socket could be recreated...
@@ -144,6 +287,155 @@ impl<T: RequestHandler> ServerFuture<T> {
         Ok(())
     }
 
+    /// Register a UDP socket on io_uring. Should be bound before calling this function.
+    ///
+    /// Requests are received and responses are sent one at a time, also with the `udp-batch`
+    ///  feature.
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "io-uring", target_os = "linux"))))]
+    pub fn register_io_uring_socket(&mut self, socket: IoUringUdpSocket) {
+        debug!("registering io_uring udp: {:?}", socket);
+        self.record_listener(ListenerConfig::new(Protocol::Udp, socket.local_addr().ok()));
+        self.register_udp_stream(socket);
+    }
+
+    /// Register a UDP socket of a simulated network, see [`crate::proto::sim`]
+    ///
+    /// Requests are received and responses are sent one at a time, also with the `udp-batch`
+    ///  feature.
+    #[cfg(feature = "testing")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "testing")))]
+    pub fn register_sim_socket(&mut self, socket: SimUdpSocket) {
+        debug!("registering simulated udp: {:?}", socket);
+        self.record_listener(ListenerConfig::new(
+            Protocol::Udp,
+            Some(socket.local_addr()),
+        ));
+        self.register_udp_stream(socket);
+    }
+
+    /// Binds `shards` UDP sockets to the address, and registers each of them
+    ///
+    /// The sockets share the address with `SO_REUSEPORT`, so the kernel spreads the requests over
+    ///  them, and each socket is served by its own task, which runs on any of the worker threads of
+    ///  the runtime. On platforms without `SO_REUSEPORT` only one socket is bound. If the port is 0,
+    ///  all sockets are bound to the port chosen for the first one.
+    ///
+    /// This must be called from within a Tokio runtime.
+    ///
+    /// # Return
+    ///
+    /// The local address of the sockets
+    pub fn register_sharded_socket(
+        &mut self,
+        addr: SocketAddr,
+        shards: usize,
+    ) -> io::Result<SocketAddr> {
+        let mut addr = addr;
+        for _ in 0..supported_shards(shards) {
+            let socket = bind_shard(addr, Type::DGRAM, shards)?;
+            let socket = net::UdpSocket::from_std(socket.into())?;
+            addr = socket.local_addr()?;
+            self.register_socket(socket);
+        }
+
+        Ok(addr)
+    }
+
+    /// Register an AF_XDP socket, along with a UDP socket bound to the address of the server
+    ///
+    /// The requests received on the XDP socket which the handler answers with
+    ///  [`RequestHandler::answer_ref`] are responded to from a dedicated thread, without the
+    ///  network stack of the kernel. All other requests, and those received on the UDP socket, are
+    ///  handled as with [`Self::register_socket`], and responded to from the UDP socket.
+    ///
+    /// This is experimental, see [`XdpSocket`] for the requirements.
+    #[cfg(all(feature = "xdp", target_os = "linux"))]
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "xdp", target_os = "linux"))))]
+    pub fn register_xdp_socket(&mut self, socket: XdpSocket, fallback: net::UdpSocket) {
+        debug!("registering xdp: {:?} with udp: {:?}", socket, fallback);
+        self.record_listener(ListenerConfig::new(
+            Protocol::Udp,
+            fallback.local_addr().ok(),
+        ));
+
+        let (requests, fallback_requests) = tokio::sync::mpsc::channel(XDP_FALLBACK_QUEUE);
+        let handler = self.handler.clone();
+        let access = self.access.clone();
+        let response_config = self.response_config;
+        let shutdown = self.shutdown_token.clone();
+        self.join_set.spawn(async move {
+            let serve = tokio::task::spawn_blocking(move || {
+                xdp::serve(
+                    socket,
+                    &shutdown,
+                    |bytes, src_addr| {
+                        // unsafe addresses are dropped by the normal path
+                        sanitize_src_address(src_addr).ok()?;
+                        answer_ref(
+                            bytes,
+                            src_addr,
+                            Protocol::Udp,
+                            &access,
+                            &response_config,
+                            &*handler,
+                        )
+                    },
+                    |message| {
+                        if requests.try_send(message).is_err() {
+                            debug!("too many requests from the xdp socket, dropping request");
+                        }
+                    },
+                )
+            });
+
+            match serve.await {
+                Ok(result) => result.map_err(ProtoError::from),
+                Err(e) => Err(ProtoError::from(format!("Internal error in spawn: {e}"))),
+            }
+        });
+
+        self.join_set.spawn(handle_xdp_fallback(
+            fallback,
+            fallback_requests,
+            self.handler.clone(),
+            self.access.clone(),
+            self.response_config,
+            self.admission(),
+            self.shutdown_token.clone(),
+        ));
+    }
+
+    /// Binds an AF_XDP socket for each of the configurations, and a UDP socket to the address for
+    ///  each of them, and registers them with [`Self::register_xdp_socket`]
+    ///
+    /// The UDP sockets share the address as with [`Self::register_sharded_socket`]. If the port is
+    ///  0, all sockets are bound to the port chosen for the first one.
+    ///
+    /// This must be called from within a Tokio runtime.
+    ///
+    /// # Return
+    ///
+    /// The local address of the UDP sockets
+    #[cfg(all(feature = "xdp", target_os = "linux"))]
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "xdp", target_os = "linux"))))]
+    pub fn register_xdp_sockets(
+        &mut self,
+        configs: &[XdpConfig],
+        addr: SocketAddr,
+    ) -> io::Result<SocketAddr> {
+        let mut addr = addr;
+        for config in configs {
+            let socket = XdpSocket::bind(config)?;
+            let fallback = bind_shard(addr, Type::DGRAM, configs.len())?;
+            let fallback = net::UdpSocket::from_std(fallback.into())?;
+            addr = fallback.local_addr()?;
+            self.register_xdp_socket(socket, fallback);
+        }
+
+        Ok(addr)
+    }
+
     /// Register a TcpListener to the Server. This should already be bound to either an IPv6 or an
     ///  IPv4 address.
     ///
@@ -158,21 +450,40 @@ impl<T: RequestHandler> ServerFuture<T> {
     ///               only, this would require some type of whitelisting.
     pub fn register_listener(&mut self, listener: net::TcpListener, timeout: Duration) {
         debug!("register tcp: {:?}", listener);
+        self.record_listener(
+            ListenerConfig::new(Protocol::Tcp, listener.local_addr().ok())
+                .with_timeout(timeout)
+                .with_proxy_protocol(self.proxy_protocol),
+        );
+        self.register_tcp_listener(listener, timeout);
+    }
 
+    fn register_tcp_listener<L: TcpAccept>(&mut self, listener: L, timeout: Duration) {
         let handler = self.handler.clone();
         let access = self.access.clone();
+        let response_config = self.response_config;
+        let proxy_protocol = self.proxy_protocol;
+        let admission = self.admission();
 
         // for each incoming request...
+        let mut limiter = AcceptLimiter::new(
+            self.accept_config,
+            Protocol::Tcp,
+            self.shutdown_token.clone(),
+        );
         let shutdown = self.shutdown_token.clone();
         self.join_set.spawn(async move {
             let mut inner_join_set = JoinSet::new();
             loop {
+                limiter.throttle().await;
+                let Some(slot) = limiter.reserve().await else {
+                    break;
+                };
                 let (tcp_stream, src_addr) = tokio::select! {
-                    tcp_stream = listener.accept() => match tcp_stream {
+                    tcp_stream = future::poll_fn(|cx| listener.poll_accept(cx)) => match tcp_stream {
                         Ok((t, s)) => (t, s),
                         Err(e) => {
-                            debug!("error receiving TCP tcp_stream error: {}", e);
-                            if is_unrecoverable_socket_error(&e) {
+                            if !limiter.failed(&e).await {
                                 break;
                             }
                             continue;
@@ -183,6 +494,7 @@ impl<T: RequestHandler> ServerFuture<T> {
                         break;
                     },
                 };
+                limiter.accepted();
 
                 // verify that the src address is safe for responses
                 if let Err(e) = sanitize_src_address(src_addr) {
@@ -196,16 +508,30 @@ impl<T: RequestHandler> ServerFuture<T> {
 
                 let handler = handler.clone();
                 let access = access.clone();
+                let admission = admission.clone();
+                let shutdown = shutdown.clone();
 
                 // and spawn to the io_loop
                 inner_join_set.spawn(async move {
+                    let mut tcp_stream = tcp_stream;
+                    let Some(src_addr) =
+                        client_addr(&mut tcp_stream, src_addr, proxy_protocol, timeout).await
+                    else {
+                        return;
+                    };
+                    let Some(connection) = slot.open(src_addr.ip()) else {
+                        debug!("too many connections from: {}", src_addr);
+                        return;
+                    };
+
                     debug!("accepted request from: {}", src_addr);
                     // take the created stream...
                     let (buf_stream, stream_handle) =
-                        TcpStream::from_stream(AsyncIoTokioAsStd(tcp_stream), src_addr);
+                        TcpStream::from_stream(tcp_stream, src_addr);
                     let mut timeout_stream = TimeoutStream::new(buf_stream, timeout);
+                    let mut requests = JoinSet::new();
 
-                    while let Some(message) = timeout_stream.next().await {
+                    while let Some(message) = next_request(&mut timeout_stream, &shutdown, &connection).await {
                         let message = match message {
                             Ok(message) => message,
                             Err(e) => {
@@ -218,22 +544,30 @@ impl<T: RequestHandler> ServerFuture<T> {
                             }
                         };
 
-                        // we don't spawn here to limit clients from getting too many resources
-                        handle_raw_request(
+                        // the number of requests in flight is limited to keep clients from
+                        //  getting too many resources
+                        wait_for_connection_slot(&mut requests, &admission).await;
+                        spawn_request(
+                            &mut requests,
+                            &admission,
                             message,
                             Protocol::Tcp,
                             access.clone(),
+                            response_config,
                             handler.clone(),
                             stream_handle.clone(),
-                        )
-                        .await;
+                        );
                     }
+
+                    finish_connection(&mut timeout_stream, &mut requests, timeout).await;
                 });
 
                 reap_tasks(&mut inner_join_set);
             }
 
             if shutdown.is_cancelled() {
+                // answer the requests in flight
+                drain_tasks(&mut inner_join_set).await;
                 Ok(())
             } else {
                 Err(ProtoError::from("unexpected close of socket"))
@@ -262,6 +596,78 @@ impl<T: RequestHandler> ServerFuture<T> {
         Ok(())
     }
 
+    /// Register a socket inherited from the parent process, see [`listen_fds`](crate::server::listen_fds)
+    ///
+    /// UDP sockets are registered as with [`Self::register_socket`], and TCP listeners as with
+    ///  [`Self::register_listener`] with the `timeout`.
+    ///
+    /// This must be called from within a Tokio runtime.
+    pub fn register_inherited_socket(
+        &mut self,
+        socket: InheritedSocket,
+        timeout: Duration,
+    ) -> io::Result<()> {
+        match socket {
+            InheritedSocket::Udp(socket) => self.register_socket_std(socket),
+            InheritedSocket::Tcp(listener) => self.register_listener_std(listener, timeout),
+        }
+    }
+
+    /// Register a TCP listener on io_uring, see [`Self::register_listener`] for the `timeout`
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "io-uring", target_os = "linux"))))]
+    pub fn register_io_uring_listener(&mut self, listener: IoUringTcpListener, timeout: Duration) {
+        debug!("register io_uring tcp: {:?}", listener);
+        self.record_listener(
+            ListenerConfig::new(Protocol::Tcp, listener.local_addr().ok())
+                .with_timeout(timeout)
+                .with_proxy_protocol(self.proxy_protocol),
+        );
+        self.register_tcp_listener(listener, timeout);
+    }
+
+    /// Register a TCP listener of a simulated network, see [`Self::register_listener`] for the
+    ///  `timeout`
+    #[cfg(feature = "testing")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "testing")))]
+    pub fn register_sim_listener(&mut self, listener: SimTcpListener, timeout: Duration) {
+        debug!("register simulated tcp: {:?}", listener);
+        self.record_listener(
+            ListenerConfig::new(Protocol::Tcp, Some(listener.local_addr()))
+                .with_timeout(timeout)
+                .with_proxy_protocol(self.proxy_protocol),
+        );
+        self.register_tcp_listener(listener, timeout);
+    }
+
+    /// Binds `shards` TCP listeners to the address, and registers each of them
+    ///
+    /// See `register_sharded_socket` for how the listeners share the address, and
+    ///  `register_listener` for the `timeout`.
+    ///
+    /// This must be called from within a Tokio runtime.
+    ///
+    /// # Return
+    ///
+    /// The local address of the listeners
+    pub fn register_sharded_listener(
+        &mut self,
+        addr: SocketAddr,
+        shards: usize,
+        timeout: Duration,
+    ) -> io::Result<SocketAddr> {
+        let mut addr = addr;
+        for _ in 0..supported_shards(shards) {
+            let socket = bind_shard(addr, Type::STREAM, shards)?;
+            socket.listen(1024)?;
+            let listener = net::TcpListener::from_std(socket.into())?;
+            addr = listener.local_addr()?;
+            self.register_listener(listener, timeout);
+        }
+
+        Ok(addr)
+    }
+
     /// Register a TlsListener to the Server. The TlsListener should already be bound to either an
     /// IPv6 or an IPv4 address.
     ///
@@ -294,21 +700,36 @@ impl<T: RequestHandler> ServerFuture<T> {
         let ((cert, chain), key) = certificate_and_key;
 
         let handler = self.handler.clone();
+        let proxy_protocol = self.proxy_protocol;
+        let admission = self.admission();
         debug!("registered tcp: {:?}", listener);
+        self.record_listener(
+            ListenerConfig::new(Protocol::Tls, listener.local_addr().ok())
+                .with_timeout(timeout)
+                .with_proxy_protocol(self.proxy_protocol),
+        );
 
         let tls_acceptor = Box::pin(tls_server::new_acceptor(cert, chain, key)?);
 
         // for each incoming request...
+        let mut limiter = AcceptLimiter::new(
+            self.accept_config,
+            Protocol::Tls,
+            self.shutdown_token.clone(),
+        );
         let shutdown = self.shutdown_watch.clone();
         self.join_set.spawn(async move {
             let mut inner_join_set = JoinSet::new();
             loop {
+                limiter.throttle().await;
+                let Some(slot) = limiter.reserve().await else {
+                    break;
+                };
                 let (tcp_stream, src_addr) = tokio::select! {
-                    tcp_stream = listener.accept() => match tcp_stream {
+                    tcp_stream = future::poll_fn(|cx| listener.poll_accept(cx)) => match tcp_stream {
                         Ok((t, s)) => (t, s),
                         Err(e) => {
-                            debug!("error receiving TLS tcp_stream error: {}", e);
-                            if is_unrecoverable_socket_error(&e) {
+                            if !limiter.failed(&e).await {
                                 break;
                             }
                             continue;
@@ -319,6 +740,7 @@ impl<T: RequestHandler> ServerFuture<T> {
                         break;
                     },
                 };
+                limiter.accepted();
 
                 // verify that the src address is safe for responses
                 if let Err(e) = sanitize_src_address(src_addr) {
@@ -331,10 +753,27 @@ impl<T: RequestHandler> ServerFuture<T> {
                 }
 
                 let handler = handler.clone();
+                let admission = admission.clone();
                 let tls_acceptor = tls_acceptor.clone();
 
                 // kick out to a different task immediately, let them do the TLS handshake
                 inner_join_set.spawn(async move {
+                    let mut tcp_stream = tcp_stream;
+                    let Some(src_addr) = client_addr(
+                        &mut AsyncIoTokioAsStd(&mut tcp_stream),
+                        src_addr,
+                        proxy_protocol,
+                        timeout,
+                    )
+                    .await
+                    else {
+                        return;
+                    };
+                    let Some(connection) = slot.open(src_addr.ip()) else {
+                        debug!("too many connections from: {}", src_addr);
+                        return;
+                    };
+
                     debug!("starting TLS request from: {}", src_addr);
 
                     // perform the TLS
@@ -358,7 +797,8 @@ impl<T: RequestHandler> ServerFuture<T> {
                     let (buf_stream, stream_handle) =
                         TlsStream::from_stream(AsyncIoTokioAsStd(tls_stream), src_addr);
                     let mut timeout_stream = TimeoutStream::new(buf_stream, timeout);
-                    while let Some(message) = timeout_stream.next().await {
+                    let mut requests = JoinSet::new();
+                    while let Some(message) = next_request(&mut timeout_stream, &shutdown, &connection).await {
                         let message = match message {
                             Ok(message) => message,
                             Err(e) => {
@@ -372,21 +812,28 @@ impl<T: RequestHandler> ServerFuture<T> {
                             }
                         };
 
-                        self::handle_raw_request(
+                        wait_for_connection_slot(&mut requests, &admission).await;
+                        spawn_request(
+                            &mut requests,
+                            &admission,
                             message,
                             Protocol::Tls,
                             access.clone(),
+                            response_config,
                             handler.clone(),
                             stream_handle.clone(),
-                        )
-                        .await;
+                        );
                     }
+
+                    finish_connection(&mut timeout_stream, &mut requests, timeout).await;
                 });
 
                 reap_tasks(&mut inner_join_set);
             }
 
             if shutdown.is_cancelled() {
+                // answer the requests in flight
+                drain_tasks(&mut inner_join_set).await;
                 Ok(())
             } else {
                 Err(ProtoError::from("unexpected close of socket"))
@@ -453,22 +900,38 @@ impl<T: RequestHandler> ServerFuture<T> {
 
         let handler = self.handler.clone();
         let access = self.access.clone();
+        let response_config = self.response_config;
+        let proxy_protocol = self.proxy_protocol;
+        let admission = self.admission();
 
         debug!("registered tcp: {:?}", listener);
+        self.record_listener(
+            ListenerConfig::new(Protocol::Tls, listener.local_addr().ok())
+                .with_timeout(timeout)
+                .with_proxy_protocol(self.proxy_protocol),
+        );
 
         let tls_acceptor = TlsAcceptor::from(tls_config);
 
         // for each incoming request...
+        let mut limiter = AcceptLimiter::new(
+            self.accept_config,
+            Protocol::Tls,
+            self.shutdown_token.clone(),
+        );
         let shutdown = self.shutdown_token.clone();
         self.join_set.spawn(async move {
             let mut inner_join_set = JoinSet::new();
             loop {
+                limiter.throttle().await;
+                let Some(slot) = limiter.reserve().await else {
+                    break;
+                };
                 let (tcp_stream, src_addr) = tokio::select! {
-                    tcp_stream = listener.accept() => match tcp_stream {
+                    tcp_stream = future::poll_fn(|cx| listener.poll_accept(cx)) => match tcp_stream {
                         Ok((t, s)) => (t, s),
                         Err(e) => {
-                            debug!("error receiving TLS tcp_stream error: {}", e);
-                            if is_unrecoverable_socket_error(&e) {
+                            if !limiter.failed(&e).await {
                                 break;
                             }
                             continue;
@@ -479,6 +942,7 @@ impl<T: RequestHandler> ServerFuture<T> {
                         break;
                     },
                 };
+                limiter.accepted();
 
                 // verify that the src address is safe for responses
                 if let Err(e) = sanitize_src_address(src_addr) {
@@ -492,10 +956,28 @@ impl<T: RequestHandler> ServerFuture<T> {
 
                 let handler = handler.clone();
                 let access = access.clone();
+                let admission = admission.clone();
+                let shutdown = shutdown.clone();
                 let tls_acceptor = tls_acceptor.clone();
 
                 // kick out to a different task immediately, let them do the TLS handshake
                 inner_join_set.spawn(async move {
+                    let mut tcp_stream = tcp_stream;
+                    let Some(src_addr) = client_addr(
+                        &mut AsyncIoTokioAsStd(&mut tcp_stream),
+                        src_addr,
+                        proxy_protocol,
+                        timeout,
+                    )
+                    .await
+                    else {
+                        return;
+                    };
+                    let Some(connection) = slot.open(src_addr.ip()) else {
+                        debug!("too many connections from: {}", src_addr);
+                        return;
+                    };
+
                     debug!("starting TLS request from: {}", src_addr);
 
                     // perform the TLS
@@ -511,7 +993,8 @@ impl<T: RequestHandler> ServerFuture<T> {
                     debug!("accepted TLS request from: {}", src_addr);
                     let (buf_stream, stream_handle) = tls_from_stream(tls_stream, src_addr);
                     let mut timeout_stream = TimeoutStream::new(buf_stream, timeout);
-                    while let Some(message) = timeout_stream.next().await {
+                    let mut requests = JoinSet::new();
+                    while let Some(message) = next_request(&mut timeout_stream, &shutdown, &connection).await {
                         let message = match message {
                             Ok(message) => message,
                             Err(e) => {
@@ -525,21 +1008,28 @@ impl<T: RequestHandler> ServerFuture<T> {
                             }
                         };
 
-                        handle_raw_request(
+                        wait_for_connection_slot(&mut requests, &admission).await;
+                        spawn_request(
+                            &mut requests,
+                            &admission,
                             message,
                             Protocol::Tls,
                             access.clone(),
+                            response_config,
                             handler.clone(),
                             stream_handle.clone(),
-                        )
-                        .await;
+                        );
                     }
+
+                    finish_connection(&mut timeout_stream, &mut requests, timeout).await;
                 });
 
                 reap_tasks(&mut inner_join_set);
             }
 
             if shutdown.is_cancelled() {
+                // answer the requests in flight
+                drain_tasks(&mut inner_join_set).await;
                 Ok(())
             } else {
                 Err(ProtoError::from("unexpected close of socket"))
@@ -583,6 +1073,24 @@ impl<T: RequestHandler> ServerFuture<T> {
         Self::register_tls_listener_with_tls_config(self, listener, timeout, Arc::new(tls_acceptor))
     }
 
+    /// Register a TlsListener to the Server, which selects the certificate for each client with
+    ///  the resolver, e.g. an [`SniCertResolver`](crate::server::SniCertResolver).
+    ///
+    /// See [`Self::register_tls_listener`] for the other arguments.
+    #[cfg(feature = "dns-over-rustls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "dns-over-rustls")))]
+    pub fn register_tls_listener_with_cert_resolver(
+        &mut self,
+        listener: net::TcpListener,
+        timeout: Duration,
+        resolver: Arc<dyn ResolvesServerCert>,
+    ) -> io::Result<()> {
+        use crate::proto::rustls::tls_server;
+
+        let tls_acceptor = tls_server::new_acceptor_with_cert_resolver(resolver);
+        self.register_tls_listener_with_tls_config(listener, timeout, Arc::new(tls_acceptor))
+    }
+
     /// Register a TlsListener to the Server. The TlsListener should already be bound to either an
     /// IPv6 or an IPv4 address.
     ///
@@ -632,26 +1140,142 @@ impl<T: RequestHandler> ServerFuture<T> {
     #[cfg(feature = "dns-over-https-rustls")]
     #[cfg_attr(docsrs, doc(cfg(feature = "dns-over-https-rustls")))]
     pub fn register_https_listener(
+        &mut self,
+        listener: net::TcpListener,
+        timeout: Duration,
+        certificate_and_key: (Vec<Certificate>, PrivateKey),
+        dns_hostname: Option<String>,
+    ) -> io::Result<()> {
+        self.register_https(
+            listener,
+            timeout,
+            ServerCert::Single(certificate_and_key),
+            dns_hostname,
+            None,
+        )
+    }
+
+    /// Register a TcpListener for HTTPS (h2) to the Server for supporting authenticated DoH
+    ///  (dns-over-https). The TcpListener should already be bound to either an IPv6 or an IPv4
+    ///  address.
+    ///
+    /// Each request must be authenticated by `auth`, either with a bearer token or with a client
+    ///  certificate, and is subject to the quota and policy of the authenticated principal.
+    ///  Client certificates are requested during the TLS handshake if `auth` has client roots.
+    ///
+    /// # Arguments
+    /// * `listener` - a bound TCP (needs to be on a different port from standard TCP connections) socket
+    /// * `timeout` - timeout duration of incoming requests, see `register_https_listener`
+    /// * `certificate_and_key` - certificate and key used to announce to clients
+    /// * `dns_hostname` - the expected `:authority` of requests, if any
+    /// * `auth` - principals, quotas and policies to enforce, shared by listeners using the same `auth`
+    #[cfg(feature = "dns-over-https-rustls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "dns-over-https-rustls")))]
+    pub fn register_https_listener_with_auth(
+        &mut self,
+        listener: net::TcpListener,
+        timeout: Duration,
+        certificate_and_key: (Vec<Certificate>, PrivateKey),
+        dns_hostname: Option<String>,
+        auth: Arc<HttpsAuth>,
+    ) -> io::Result<()> {
+        self.register_https(
+            listener,
+            timeout,
+            ServerCert::Single(certificate_and_key),
+            dns_hostname,
+            Some(auth),
+        )
+    }
+
+    /// Register a TcpListener for HTTPS (h2) to the Server for supporting DoH (dns-over-https),
+    ///  which selects the certificate for each client with the resolver, e.g. an
+    ///  [`SniCertResolver`](crate::server::SniCertResolver).
+    ///
+    /// See [`Self::register_https_listener_with_auth`] for the other arguments, requests are not
+    ///  authenticated without `auth`.
+    ///
+    /// The [`ACME_TLS_ALPN_PROTOCOL`](crate::server::ACME_TLS_ALPN_PROTOCOL) is accepted besides
+    ///  h2, the resolver answers the validation handshakes of ACME TLS-ALPN-01 challenges.
+    #[cfg(feature = "dns-over-https-rustls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "dns-over-https-rustls")))]
+    pub fn register_https_listener_with_cert_resolver(
+        &mut self,
+        listener: net::TcpListener,
+        timeout: Duration,
+        resolver: Arc<dyn ResolvesServerCert>,
+        dns_hostname: Option<String>,
+        auth: Option<Arc<HttpsAuth>>,
+    ) -> io::Result<()> {
+        self.register_https(
+            listener,
+            timeout,
+            ServerCert::Resolver(resolver),
+            dns_hostname,
+            auth,
+        )
+    }
+
+    #[cfg(feature = "dns-over-https-rustls")]
+    fn register_https(
         &mut self,
         listener: net::TcpListener,
         // TODO: need to set a timeout between requests.
         _timeout: Duration,
-        certificate_and_key: (Vec<Certificate>, PrivateKey),
+        cert: ServerCert,
         dns_hostname: Option<String>,
+        auth: Option<Arc<HttpsAuth>>,
     ) -> io::Result<()> {
+        use rustls::server::AllowAnyAnonymousOrAuthenticatedClient;
         use tokio_rustls::TlsAcceptor;
 
         use crate::proto::rustls::tls_server;
-        use crate::server::h2_handler::h2_handler;
+        use crate::server::{h2_handler::h2_handler, ACME_TLS_ALPN_PROTOCOL};
 
+        self.record_listener(
+            ListenerConfig::new(Protocol::Https, listener.local_addr().ok())
+                .with_timeout(_timeout)
+                .with_dns_hostname(dns_hostname.clone()),
+        );
         let dns_hostname: Option<Arc<str>> = dns_hostname.map(|n| n.into());
 
         let handler = self.handler.clone();
         let access = self.access.clone();
+        let response_config = self.response_config;
         debug!("registered https: {listener:?}");
 
-        let tls_acceptor = tls_server::new_acceptor(certificate_and_key.0, certificate_and_key.1)
-            .map_err(|e| {
+        // resolvers may answer the validation handshakes of ACME TLS-ALPN-01 challenges
+        let acme_tls_alpn = matches!(cert, ServerCert::Resolver(_));
+        let tls_acceptor = match auth.as_ref().and_then(|auth| auth.client_roots()) {
+            Some(client_roots) => {
+                let verifier =
+                    AllowAnyAnonymousOrAuthenticatedClient::new(client_roots.clone()).boxed();
+                let builder = ServerConfig::builder()
+                    .with_safe_defaults()
+                    .with_client_cert_verifier(verifier);
+                match cert {
+                    ServerCert::Single((cert, key)) => builder.with_single_cert(cert, key),
+                    ServerCert::Resolver(resolver) => Ok(builder.with_cert_resolver(resolver)),
+                }
+                .map(|mut config| {
+                    config.alpn_protocols = vec![b"h2".to_vec()];
+                    config
+                })
+            }
+            None => match cert {
+                ServerCert::Single((cert, key)) => tls_server::new_acceptor(cert, key),
+                ServerCert::Resolver(resolver) => {
+                    Ok(tls_server::new_acceptor_with_cert_resolver(resolver))
+                }
+            },
+        }
+        .map(|mut config| {
+            if acme_tls_alpn {
+                config.alpn_protocols.push(ACME_TLS_ALPN_PROTOCOL.to_vec());
+            }
+            config
+        })
+        .map_err(|e| {
             io::Error::new(
                 io::ErrorKind::Other,
                 format!("error creating TLS acceptor: {e}"),
@@ -660,17 +1284,22 @@ impl<T: RequestHandler> ServerFuture<T> {
         let tls_acceptor = TlsAcceptor::from(Arc::new(tls_acceptor));
 
         // for each incoming request...
+        let mut limiter = AcceptLimiter::new(
+            self.accept_config,
+            Protocol::Https,
+            self.shutdown_token.clone(),
+        );
         let shutdown = self.shutdown_token.clone();
         self.join_set.spawn(async move {
             let mut inner_join_set = JoinSet::new();
             loop {
+                limiter.throttle().await;
                 let shutdown = shutdown.clone();
                 let (tcp_stream, src_addr) = tokio::select! {
-                    tcp_stream = listener.accept() => match tcp_stream {
+                    tcp_stream = future::poll_fn(|cx| listener.poll_accept(cx)) => match tcp_stream {
                         Ok((t, s)) => (t, s),
                         Err(e) => {
-                            debug!("error receiving HTTPS tcp_stream error: {}", e);
-                            if is_unrecoverable_socket_error(&e) {
+                            if !limiter.failed(&e).await {
                                 break;
                             }
                             continue;
@@ -681,6 +1310,7 @@ impl<T: RequestHandler> ServerFuture<T> {
                         break;
                     },
                 };
+                limiter.accepted();
 
                 // verify that the src address is safe for responses
                 if let Err(e) = sanitize_src_address(src_addr) {
@@ -692,6 +1322,7 @@ impl<T: RequestHandler> ServerFuture<T> {
                 let access = access.clone();
                 let tls_acceptor = tls_acceptor.clone();
                 let dns_hostname = dns_hostname.clone();
+                let auth = auth.clone();
 
                 inner_join_set.spawn(async move {
                     debug!("starting HTTPS request from: {src_addr}");
@@ -707,14 +1338,28 @@ impl<T: RequestHandler> ServerFuture<T> {
                             return;
                         }
                     };
+                    if tls_stream.get_ref().1.alpn_protocol() == Some(ACME_TLS_ALPN_PROTOCOL) {
+                        debug!("answered ACME TLS-ALPN-01 validation from: {src_addr}");
+                        return;
+                    }
                     debug!("accepted HTTPS request from: {src_addr}");
 
+                    let client_cert = tls_stream
+                        .get_ref()
+                        .1
+                        .peer_certificates()
+                        .and_then(|certs| certs.first())
+                        .map(|cert| cert.0.clone());
+
                     h2_handler(
                         access,
                         handler,
+                        response_config,
                         tls_stream,
                         src_addr,
                         dns_hostname,
+                        auth,
+                        client_cert,
                         shutdown.clone(),
                     )
                     .await;
@@ -724,6 +1369,8 @@ impl<T: RequestHandler> ServerFuture<T> {
             }
 
             if shutdown.is_cancelled() {
+                // answer the requests in flight
+                drain_tasks(&mut inner_join_set).await;
                 Ok(())
             } else {
                 Err(ProtoError::from("unexpected close of socket"))
@@ -749,31 +1396,84 @@ impl<T: RequestHandler> ServerFuture<T> {
     #[cfg(feature = "dns-over-quic")]
     #[cfg_attr(docsrs, doc(cfg(feature = "dns-over-quic")))]
     pub fn register_quic_listener(
+        &mut self,
+        socket: net::UdpSocket,
+        timeout: Duration,
+        certificate_and_key: (Vec<Certificate>, PrivateKey),
+        dns_hostname: Option<String>,
+    ) -> io::Result<()> {
+        self.register_quic(
+            socket,
+            timeout,
+            ServerCert::Single(certificate_and_key),
+            dns_hostname,
+        )
+    }
+
+    /// Register a UdpSocket to the Server for supporting DoQ (dns-over-quic), which selects the
+    ///  certificate for each client with the resolver, e.g. an
+    ///  [`SniCertResolver`](crate::server::SniCertResolver).
+    ///
+    /// See [`Self::register_quic_listener`] for the other arguments.
+    #[cfg(feature = "dns-over-quic")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "dns-over-quic")))]
+    pub fn register_quic_listener_with_cert_resolver(
+        &mut self,
+        socket: net::UdpSocket,
+        timeout: Duration,
+        resolver: Arc<dyn ResolvesServerCert>,
+        dns_hostname: Option<String>,
+    ) -> io::Result<()> {
+        self.register_quic(
+            socket,
+            timeout,
+            ServerCert::Resolver(resolver),
+            dns_hostname,
+        )
+    }
+
+    #[cfg(feature = "dns-over-quic")]
+    fn register_quic(
         &mut self,
         socket: net::UdpSocket,
         // TODO: need to set a timeout between requests.
         _timeout: Duration,
-        certificate_and_key: (Vec<Certificate>, PrivateKey),
+        cert: ServerCert,
         dns_hostname: Option<String>,
     ) -> io::Result<()> {
         use crate::proto::quic::QuicServer;
         use crate::server::quic_handler::quic_handler;
 
+        self.record_listener(
+            ListenerConfig::new(Protocol::Quic, socket.local_addr().ok())
+                .with_dns_hostname(dns_hostname.clone()),
+        );
         let dns_hostname: Option<Arc<str>> = dns_hostname.map(|n| n.into());
 
         let handler = self.handler.clone();
         let access = self.access.clone();
+        let response_config = self.response_config;
 
         debug!("registered quic: {:?}", socket);
-        let mut server =
-            QuicServer::with_socket(socket, certificate_and_key.0, certificate_and_key.1)?;
+        let mut server = match cert {
+            ServerCert::Single((cert, key)) => QuicServer::with_socket(socket, cert, key)?,
+            ServerCert::Resolver(resolver) => {
+                QuicServer::with_socket_and_cert_resolver(socket, resolver)?
+            }
+        };
 
         // for each incoming request...
+        let mut limiter = AcceptLimiter::new(
+            self.accept_config,
+            Protocol::Quic,
+            self.shutdown_token.clone(),
+        );
         let shutdown = self.shutdown_token.clone();
         self.join_set.spawn(async move {
             let mut inner_join_set = JoinSet::new();
             loop {
                 let shutdown = shutdown.clone();
+                limiter.throttle().await;
                 let (streams, src_addr) = tokio::select! {
                     result = server.next() => match result {
                         Ok(Some(c)) => c,
@@ -788,6 +1488,7 @@ impl<T: RequestHandler> ServerFuture<T> {
                         break;
                     },
                 };
+                limiter.accepted();
 
                 // verify that the src address is safe for responses
                 // TODO: we're relying the quinn library to actually validate responses before we get here, but this check is still worth doing
@@ -811,6 +1512,7 @@ impl<T: RequestHandler> ServerFuture<T> {
                     let result = quic_handler(
                         access,
                         handler,
+                        response_config,
                         streams,
                         src_addr,
                         dns_hostname,
@@ -826,6 +1528,8 @@ impl<T: RequestHandler> ServerFuture<T> {
                 reap_tasks(&mut inner_join_set);
             }
 
+            // answer the requests in flight
+            drain_tasks(&mut inner_join_set).await;
             Ok(())
         });
 
@@ -850,29 +1554,116 @@ impl<T: RequestHandler> ServerFuture<T> {
     pub fn register_h3_listener(
         &mut self,
         socket: net::UdpSocket,
-        // TODO: need to set a timeout between requests.
-        _timeout: Duration,
+        timeout: Duration,
         certificate_and_key: (Vec<Certificate>, PrivateKey),
         dns_hostname: Option<String>,
     ) -> io::Result<()> {
-        use crate::proto::h3::h3_server::H3Server;
-        use crate::server::h3_handler::h3_handler;
-
-        let dns_hostname: Option<Arc<str>> = dns_hostname.map(|n| n.into());
-
-        let handler = self.handler.clone();
-        let access = self.access.clone();
+        self.register_h3_listener_with_max_concurrent_streams(
+            socket,
+            timeout,
+            certificate_and_key,
+            dns_hostname,
+            crate::proto::h3::h3_server::DEFAULT_MAX_CONCURRENT_STREAMS,
+        )
+    }
 
-        debug!("registered h3: {:?}", socket);
-        let mut server =
-            H3Server::with_socket(socket, certificate_and_key.0, certificate_and_key.1)?;
+    /// Register a UdpSocket to the Server for supporting DoH3 (dns-over-h3), which accepts up to
+    ///  `max_concurrent_streams` requests at a time on each connection
+    ///
+    /// See [`Self::register_h3_listener`] for the other arguments, which uses a default of
+    ///  `DEFAULT_MAX_CONCURRENT_STREAMS` requests.
+    #[cfg(feature = "dns-over-h3")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "dns-over-h3")))]
+    pub fn register_h3_listener_with_max_concurrent_streams(
+        &mut self,
+        socket: net::UdpSocket,
+        timeout: Duration,
+        certificate_and_key: (Vec<Certificate>, PrivateKey),
+        dns_hostname: Option<String>,
+        max_concurrent_streams: u32,
+    ) -> io::Result<()> {
+        self.register_h3(
+            socket,
+            timeout,
+            ServerCert::Single(certificate_and_key),
+            dns_hostname,
+            max_concurrent_streams,
+        )
+    }
 
-        // for each incoming request...
+    /// Register a UdpSocket to the Server for supporting DoH3 (dns-over-h3), which selects the
+    ///  certificate for each client with the resolver, e.g. an
+    ///  [`SniCertResolver`](crate::server::SniCertResolver).
+    ///
+    /// See [`Self::register_h3_listener_with_max_concurrent_streams`] for the other arguments.
+    #[cfg(feature = "dns-over-h3")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "dns-over-h3")))]
+    pub fn register_h3_listener_with_cert_resolver(
+        &mut self,
+        socket: net::UdpSocket,
+        timeout: Duration,
+        resolver: Arc<dyn ResolvesServerCert>,
+        dns_hostname: Option<String>,
+        max_concurrent_streams: u32,
+    ) -> io::Result<()> {
+        self.register_h3(
+            socket,
+            timeout,
+            ServerCert::Resolver(resolver),
+            dns_hostname,
+            max_concurrent_streams,
+        )
+    }
+
+    #[cfg(feature = "dns-over-h3")]
+    fn register_h3(
+        &mut self,
+        socket: net::UdpSocket,
+        // TODO: need to set a timeout between requests.
+        _timeout: Duration,
+        cert: ServerCert,
+        dns_hostname: Option<String>,
+        max_concurrent_streams: u32,
+    ) -> io::Result<()> {
+        use crate::proto::h3::h3_server::H3Server;
+        use crate::server::h3_handler::h3_handler;
+
+        self.record_listener(
+            ListenerConfig::new(Protocol::H3, socket.local_addr().ok())
+                .with_dns_hostname(dns_hostname.clone())
+                .with_max_concurrent_streams(max_concurrent_streams),
+        );
+        let dns_hostname: Option<Arc<str>> = dns_hostname.map(|n| n.into());
+
+        let handler = self.handler.clone();
+        let access = self.access.clone();
+        let response_config = self.response_config;
+
+        debug!("registered h3: {:?}", socket);
+        let mut server = match cert {
+            ServerCert::Single((cert, key)) => H3Server::with_socket_and_max_concurrent_streams(
+                socket,
+                cert,
+                key,
+                max_concurrent_streams,
+            )?,
+            ServerCert::Resolver(resolver) => {
+                H3Server::with_socket_and_cert_resolver(socket, resolver, max_concurrent_streams)?
+            }
+        };
+
+        // for each incoming request...
+        let mut limiter = AcceptLimiter::new(
+            self.accept_config,
+            Protocol::H3,
+            self.shutdown_token.clone(),
+        );
         let shutdown = self.shutdown_token.clone();
         self.join_set.spawn(async move {
             let mut inner_join_set = JoinSet::new();
             loop {
                 let shutdown = shutdown.clone();
+                limiter.throttle().await;
                 let (streams, src_addr) = tokio::select! {
                     result = server.accept() => match result {
                         Ok(Some(c)) => c,
@@ -887,6 +1678,7 @@ impl<T: RequestHandler> ServerFuture<T> {
                         break;
                     },
                 };
+                limiter.accepted();
 
                 // verify that the src address is safe for responses
                 // TODO: we're relying the quinn library to actually validate responses before we get here, but this check is still worth doing
@@ -910,6 +1702,7 @@ impl<T: RequestHandler> ServerFuture<T> {
                     let result = h3_handler(
                         access,
                         handler,
+                        response_config,
                         streams,
                         src_addr,
                         dns_hostname,
@@ -925,6 +1718,8 @@ impl<T: RequestHandler> ServerFuture<T> {
                 reap_tasks(&mut inner_join_set);
             }
 
+            // answer the requests in flight
+            drain_tasks(&mut inner_join_set).await;
             Ok(())
         });
 
@@ -933,6 +1728,10 @@ impl<T: RequestHandler> ServerFuture<T> {
 
     /// Triggers a graceful shutdown the server. All background tasks will stop accepting
     /// new connections and the returned future will complete once all tasks have terminated.
+    ///
+    /// The requests in flight are answered, including the zone transfers, and the connections
+    ///  are closed once their responses are sent, see [`Self::shutdown_gracefully_with_deadline`]
+    ///  to bound the wait.
     pub async fn shutdown_gracefully(&mut self) -> Result<(), ProtoError> {
         self.shutdown_token.cancel();
 
@@ -940,6 +1739,26 @@ impl<T: RequestHandler> ServerFuture<T> {
         block_until_done(&mut self.join_set).await
     }
 
+    /// Triggers a graceful shutdown like [`Self::shutdown_gracefully`], the tasks which are still
+    ///  running after the `deadline` are aborted, and an error is returned.
+    pub async fn shutdown_gracefully_with_deadline(
+        &mut self,
+        deadline: Duration,
+    ) -> Result<(), ProtoError> {
+        self.shutdown_token.cancel();
+
+        match tokio::time::timeout(deadline, block_until_done(&mut self.join_set)).await {
+            Ok(result) => result,
+            Err(_) => {
+                let remaining = self.join_set.len();
+                self.join_set.shutdown().await;
+                Err(ProtoError::from(format!(
+                    "{remaining} listeners were aborted after the shutdown deadline"
+                )))
+            }
+        }
+    }
+
     /// This will run until all background tasks complete. If one or more tasks return an error,
     /// one will be chosen as the returned error for this future.
     pub async fn block_until_done(&mut self) -> Result<(), ProtoError> {
@@ -974,6 +1793,435 @@ async fn block_until_done(
     out
 }
 
+/// Receives requests and sends responses on a UDP socket in batches, until shutdown
+#[cfg(feature = "udp-batch")]
+async fn handle_udp_batches<T: RequestHandler>(
+    socket: net::UdpSocket,
+    handler: Arc<T>,
+    access: Arc<AccessControl>,
+    response_config: ResponseConfig,
+    admission: Arc<Admission>,
+    shutdown: CancellationToken,
+) -> Result<(), ProtoError> {
+    // as with the UdpStream, the responses are sent to the address of each request
+    let (stream_handle, mut outbound) = BufDnsStreamHandle::new(([127, 255, 255, 254], 0).into());
+    let mut batch = RecvBatch::new(MAX_BATCH_SIZE);
+    let mut responses = Vec::with_capacity(MAX_BATCH_SIZE);
+    let mut inner_join_set = JoinSet::new();
+
+    loop {
+        tokio::select! {
+            received = batch.recv(&socket) => {
+                if let Err(e) = received {
+                    warn!("error receiving message on udp_socket: {}", e);
+                    if is_unrecoverable_socket_error(&e) {
+                        break;
+                    }
+                    continue;
+                }
+
+                for message in batch.messages() {
+                    let src_addr = message.addr();
+                    debug!("received udp request from: {}", src_addr);
+
+                    // verify that the src address is safe for responses
+                    if let Err(e) = sanitize_src_address(src_addr) {
+                        warn!("address can not be responded to {src_addr}: {e}");
+                        continue;
+                    }
+
+                    spawn_request(
+                        &mut inner_join_set,
+                        &admission,
+                        message,
+                        Protocol::Udp,
+                        access.clone(),
+                        response_config,
+                        handler.clone(),
+                        stream_handle.with_remote_addr(src_addr),
+                    );
+                }
+
+                reap_tasks(&mut inner_join_set);
+            }
+            response = outbound.next() => {
+                // the stream_handle is held by this loop, so the channel is never closed
+                let Some(response) = response else { break };
+
+                // send all responses which are ready along with this one
+                responses.push(response);
+                while responses.len() < MAX_BATCH_SIZE {
+                    match outbound.next().now_or_never() {
+                        Some(Some(response)) => responses.push(response),
+                        _ => break,
+                    }
+                }
+
+                let sent = send_batch(&socket, &responses).await;
+                responses.clear();
+                if let Err(e) = sent {
+                    warn!("error sending messages on udp_socket: {}", e);
+                    if is_unrecoverable_socket_error(&e) {
+                        break;
+                    }
+                }
+            }
+            _ = shutdown.cancelled() => break,
+        }
+    }
+
+    if shutdown.is_cancelled() {
+        // answer the requests in flight
+        drop(stream_handle);
+        send_remaining(&socket, &mut outbound).await;
+        drain_tasks(&mut inner_join_set).await;
+        Ok(())
+    } else {
+        Err(ProtoError::from("unexpected close of UDP socket"))
+    }
+}
+
+/// Handles the requests received on the UDP socket, or handed over from the XDP socket, until
+///  shutdown
+#[cfg(all(feature = "xdp", target_os = "linux"))]
+async fn handle_xdp_fallback<T: RequestHandler>(
+    socket: net::UdpSocket,
+    mut requests: tokio::sync::mpsc::Receiver<SerialMessage>,
+    handler: Arc<T>,
+    access: Arc<AccessControl>,
+    response_config: ResponseConfig,
+    admission: Arc<Admission>,
+    shutdown: CancellationToken,
+) -> Result<(), ProtoError> {
+    // as with the UdpStream, the responses are sent to the address of each request
+    let (stream_handle, mut outbound) = BufDnsStreamHandle::new(([127, 255, 255, 254], 0).into());
+    let mut buffer = [0; MAX_RECEIVE_BUFFER_SIZE];
+    let mut inner_join_set = JoinSet::new();
+
+    loop {
+        let message = tokio::select! {
+            received = socket.recv_from(&mut buffer) => match received {
+                Ok((len, src_addr)) => SerialMessage::new(buffer[..len].to_vec(), src_addr),
+                Err(e) => {
+                    warn!("error receiving message on udp_socket: {}", e);
+                    if is_unrecoverable_socket_error(&e) {
+                        break;
+                    }
+                    continue;
+                }
+            },
+            Some(message) = requests.recv() => message,
+            response = outbound.next() => {
+                // the stream_handle is held by this loop, so the channel is never closed
+                let Some(response) = response else { break };
+
+                if let Err(e) = socket.send_to(response.bytes(), response.addr()).await {
+                    warn!(
+                        "error sending message to {} on udp_socket, dropping response: {}",
+                        response.addr(),
+                        e
+                    );
+                }
+                continue;
+            }
+            _ = shutdown.cancelled() => break,
+        };
+
+        let src_addr = message.addr();
+        debug!("received udp request from: {}", src_addr);
+
+        // verify that the src address is safe for responses
+        if let Err(e) = sanitize_src_address(src_addr) {
+            warn!("address can not be responded to {src_addr}: {e}");
+            continue;
+        }
+
+        spawn_request(
+            &mut inner_join_set,
+            &admission,
+            message,
+            Protocol::Udp,
+            access.clone(),
+            response_config,
+            handler.clone(),
+            stream_handle.with_remote_addr(src_addr),
+        );
+
+        reap_tasks(&mut inner_join_set);
+    }
+
+    if shutdown.is_cancelled() {
+        // answer the requests in flight
+        drop(stream_handle);
+        send_remaining(&socket, &mut outbound).await;
+        drain_tasks(&mut inner_join_set).await;
+        Ok(())
+    } else {
+        Err(ProtoError::from("unexpected close of UDP socket"))
+    }
+}
+
+/// The certificate of a listener, either fixed or selected for each client
+#[cfg(any(
+    feature = "dns-over-https-rustls",
+    feature = "dns-over-quic",
+    feature = "dns-over-h3"
+))]
+enum ServerCert {
+    Single((Vec<Certificate>, PrivateKey)),
+    Resolver(Arc<dyn ResolvesServerCert>),
+}
+
+/// A listener of TCP connections, served by the same loop for all kinds of sockets
+trait TcpAccept: Send + Sync + 'static {
+    type Stream: DnsTcpStream;
+
+    fn poll_accept(&self, cx: &mut Context<'_>) -> Poll<io::Result<(Self::Stream, SocketAddr)>>;
+}
+
+impl TcpAccept for net::TcpListener {
+    type Stream = AsyncIoTokioAsStd<net::TcpStream>;
+
+    fn poll_accept(&self, cx: &mut Context<'_>) -> Poll<io::Result<(Self::Stream, SocketAddr)>> {
+        Self::poll_accept(self, cx).map_ok(|(stream, addr)| (AsyncIoTokioAsStd(stream), addr))
+    }
+}
+
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+impl TcpAccept for IoUringTcpListener {
+    type Stream = crate::proto::uring::IoUringTcpStream;
+
+    fn poll_accept(&self, cx: &mut Context<'_>) -> Poll<io::Result<(Self::Stream, SocketAddr)>> {
+        Self::poll_accept(self, cx)
+    }
+}
+
+#[cfg(feature = "testing")]
+impl TcpAccept for SimTcpListener {
+    type Stream = SimTcpStream;
+
+    fn poll_accept(&self, cx: &mut Context<'_>) -> Poll<io::Result<(Self::Stream, SocketAddr)>> {
+        Self::poll_accept(self, cx)
+    }
+}
+
+/// Returns the number of sockets to bind to one address, which is 1 without `SO_REUSEPORT`
+fn supported_shards(shards: usize) -> usize {
+    if cfg!(all(
+        unix,
+        not(any(target_os = "solaris", target_os = "illumos"))
+    )) {
+        shards.max(1)
+    } else {
+        if shards > 1 {
+            warn!("SO_REUSEPORT is not supported on this platform, binding a single socket");
+        }
+        1
+    }
+}
+
+/// Binds a non-blocking socket, which shares the address with the other shards if there are any
+#[cfg_attr(
+    not(all(unix, not(any(target_os = "solaris", target_os = "illumos")))),
+    allow(unused_variables)
+)]
+fn bind_shard(addr: SocketAddr, ty: Type, shards: usize) -> io::Result<Socket> {
+    let socket = Socket::new(Domain::for_address(addr), ty, None)?;
+
+    // as with the listeners bound by tokio
+    #[cfg(unix)]
+    if ty == Type::STREAM {
+        socket.set_reuse_address(true)?;
+    }
+
+    #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+    if shards > 1 {
+        socket.set_reuse_port(true)?;
+    }
+
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    Ok(socket)
+}
+
+/// Sends the responses to the UDP requests in flight after a graceful shutdown, until the tasks
+///  answering them dropped their stream handles
+#[cfg(any(feature = "udp-batch", all(feature = "xdp", target_os = "linux")))]
+async fn send_remaining(socket: &net::UdpSocket, outbound: &mut StreamReceiver) {
+    while let Some(response) = outbound.next().await {
+        if let Err(e) = socket.send_to(response.bytes(), response.addr()).await {
+            warn!(
+                "error sending message to {} on udp_socket, dropping response: {}",
+                response.addr(),
+                e
+            );
+        }
+    }
+}
+
+/// Returns the next request received on a TCP or TLS connection, or `None` once it is closed
+///
+/// After a graceful shutdown, the connection is closed once the responses to the previous
+///  requests are sent.
+async fn next_request<S: DnsTcpStream>(
+    stream: &mut TimeoutStream<TcpStream<S>>,
+    shutdown: &CancellationToken,
+    connection: &Connection,
+) -> Option<io::Result<SerialMessage>> {
+    // the responses queued so far are sent by `finish_connection`
+    tokio::select! {
+        biased;
+        _ = shutdown.cancelled() => None,
+        _ = connection.expired() => {
+            debug!("closing connection from {} at the end of its lifetime", stream.get_mut().peer_addr());
+            None
+        }
+        message = stream.next() => message,
+    }
+}
+
+/// Handles the request in a task of the join set, once it is admitted by the listener
+#[allow(clippy::too_many_arguments)]
+fn spawn_request<T: RequestHandler>(
+    join_set: &mut JoinSet<()>,
+    admission: &Arc<Admission>,
+    message: SerialMessage,
+    protocol: Protocol,
+    access: Arc<AccessControl>,
+    response_config: ResponseConfig,
+    handler: Arc<T>,
+    response_handler: BufDnsStreamHandle,
+) {
+    let Some(ticket) = admission.admit() else {
+        overflow_request(
+            &message,
+            protocol,
+            &access,
+            admission.overflow(),
+            response_handler,
+        );
+        return;
+    };
+
+    join_set.spawn(async move {
+        let _in_flight = ticket.ready().await;
+        handle_raw_request(
+            message,
+            protocol,
+            access,
+            response_config,
+            handler,
+            response_handler,
+        )
+        .await;
+    });
+}
+
+/// Drops or answers a request which is over the limits of the listener, see `Overflow`
+fn overflow_request(
+    message: &SerialMessage,
+    protocol: Protocol,
+    access: &AccessControl,
+    overflow: Overflow,
+    mut response_handler: BufDnsStreamHandle,
+) {
+    let src_addr = message.addr();
+    debug!("request from {protocol}://{src_addr} overflowed: {overflow:?}");
+    if overflow != Overflow::ServFail || !access.allow(src_addr.ip()) {
+        return;
+    }
+
+    // the response is built from the borrowed message, to spend as little as possible on it
+    let Ok(request) = MessageRef::read(&mut BinDecoder::new(message.bytes())) else {
+        return;
+    };
+    if request.header().message_type() == MessageType::Response {
+        return;
+    }
+
+    let mut header = Header::response_from_request(request.header());
+    header.set_response_code(ResponseCode::ServFail);
+    let Ok(response) = request.to_response(&header, &[]) else {
+        return;
+    };
+    if let Err(e) = response_handler.send(SerialMessage::new(response, src_addr)) {
+        warn!("failed to send response to client: {}", e);
+    }
+}
+
+/// Waits until another request may be handled on the connection, see
+///  `AdmissionConfig::max_in_flight_per_connection`
+async fn wait_for_connection_slot(requests: &mut JoinSet<()>, admission: &Admission) {
+    reap_tasks(requests);
+    while requests.len() >= admission.max_in_flight_per_connection() {
+        requests.join_next().await;
+    }
+}
+
+/// Answers the requests in flight on a connection which is closed, or shut down
+///
+/// The responses must be sent within the `timeout` of the listener, so that clients which do not
+///  read them can not keep the connection open.
+async fn finish_connection<S: DnsTcpStream>(
+    stream: &mut TimeoutStream<TcpStream<S>>,
+    requests: &mut JoinSet<()>,
+    timeout: Duration,
+) {
+    drain_tasks(requests).await;
+    let send = future::poll_fn(|cx| stream.get_mut().poll_send_queued(cx));
+    let result = if timeout.is_zero() {
+        send.await
+    } else {
+        tokio::time::timeout(timeout, send)
+            .await
+            .unwrap_or_else(|_| Err(io::ErrorKind::TimedOut.into()))
+    };
+    if let Err(e) = result {
+        debug!("error sending the last responses: {e}");
+    }
+}
+
+/// Waits for the tasks of the requests and connections in flight, after a graceful shutdown
+async fn drain_tasks(join_set: &mut JoinSet<()>) {
+    while join_set.join_next().await.is_some() {}
+}
+
+/// Returns the address of the client on the connection from `peer_addr`, which is read from the
+///  PROXY protocol header with `proxy_protocol`, or `None` if the connection is to be closed
+async fn client_addr<S: futures_util::AsyncRead + Unpin>(
+    stream: &mut S,
+    peer_addr: SocketAddr,
+    proxy_protocol: bool,
+    timeout: Duration,
+) -> Option<SocketAddr> {
+    if !proxy_protocol {
+        return Some(peer_addr);
+    }
+
+    let src_addr = match tokio::time::timeout(timeout, read_proxy_header(stream)).await {
+        Ok(Ok(Some(src_addr))) => src_addr,
+        // e.g. a health check of the load balancer
+        Ok(Ok(None)) => peer_addr,
+        Ok(Err(e)) => {
+            debug!("invalid PROXY protocol header from: {peer_addr} error: {e}");
+            return None;
+        }
+        Err(_) => {
+            debug!("timeout reading the PROXY protocol header from: {peer_addr}");
+            return None;
+        }
+    };
+
+    // verify that the src address is safe for responses
+    if let Err(e) = sanitize_src_address(src_addr) {
+        warn!("address can not be responded to {src_addr}: {e}");
+        return None;
+    }
+
+    debug!("connection from {peer_addr} is proxied for: {src_addr}");
+    Some(src_addr)
+}
+
 /// Reap finished tasks from a `JoinSet`, without awaiting or blocking.
 fn reap_tasks(join_set: &mut JoinSet<()>) {
     while FutureExt::now_or_never(join_set.join_next())
@@ -986,11 +2234,28 @@ pub(crate) async fn handle_raw_request<T: RequestHandler>(
     message: SerialMessage,
     protocol: Protocol,
     access: Arc<AccessControl>,
+    response_config: ResponseConfig,
     request_handler: Arc<T>,
     response_handler: BufDnsStreamHandle,
 ) {
     let src_addr = message.addr();
-    let response_handler = ResponseHandle::new(message.addr(), response_handler, protocol);
+    if let Some(response) = answer_ref(
+        message.bytes(),
+        src_addr,
+        protocol,
+        &access,
+        &response_config,
+        &*request_handler,
+    ) {
+        let mut response_handler = response_handler;
+        if let Err(e) = response_handler.send(SerialMessage::new(response, src_addr)) {
+            warn!("failed to send response to client: {}", e);
+        }
+        return;
+    }
+
+    let response_handler = ResponseHandle::new(message.addr(), response_handler, protocol)
+        .with_config(response_config);
 
     handle_request(
         message.bytes(),
@@ -1003,6 +2268,51 @@ pub(crate) async fn handle_raw_request<T: RequestHandler>(
     .await;
 }
 
+/// Answers the request with `RequestHandler::answer_ref`, if the handler supports it
+fn answer_ref<T: RequestHandler>(
+    message_bytes: &[u8],
+    src_addr: SocketAddr,
+    protocol: Protocol,
+    access: &AccessControl,
+    response_config: &ResponseConfig,
+    request_handler: &T,
+) -> Option<Vec<u8>> {
+    if !access.allow(src_addr.ip()) {
+        // refused requests are logged by handle_request
+        return None;
+    }
+
+    // errors are reported to the client by handle_request
+    let request = MessageRef::read(&mut BinDecoder::new(message_bytes)).ok()?;
+    if request.header().message_type() == MessageType::Response {
+        return None;
+    }
+
+    let response = request_handler.answer_ref(&request, src_addr, protocol)?;
+    let max_size = match protocol {
+        Protocol::Udp => response_config.udp_payload(request.max_payload()),
+        _ => u16::MAX,
+    };
+    if response.len() > max_size as usize {
+        debug!(
+            "request:{id} response of {len} bytes exceeds max size: {max_size}",
+            id = request.id(),
+            len = response.len(),
+        );
+        return None;
+    }
+
+    debug!(
+        "request:{id} src:{proto}://{addr}#{port} answered from borrowed message, qflags:{qflags}",
+        id = request.id(),
+        proto = protocol,
+        addr = src_addr.ip(),
+        port = src_addr.port(),
+        qflags = request.header().flags(),
+    );
+    Some(response)
+}
+
 #[derive(Clone)]
 struct ReportingResponseHandler<R: ResponseHandler> {
     request_header: Header,
@@ -1245,7 +2555,7 @@ fn sanitize_src_address(src: SocketAddr) -> Result<(), String> {
     }
 }
 
-fn is_unrecoverable_socket_error(err: &io::Error) -> bool {
+pub(super) fn is_unrecoverable_socket_error(err: &io::Error) -> bool {
     matches!(
         err.kind(),
         io::ErrorKind::NotConnected | io::ErrorKind::ConnectionAborted
@@ -1255,11 +2565,12 @@ fn is_unrecoverable_socket_error(err: &io::Error) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::authority::Catalog;
+    use crate::{authority::Catalog, server::ResponseInfo};
     use futures_util::future;
     #[cfg(feature = "dns-over-rustls")]
     use rustls::{Certificate, PrivateKey};
     use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicBool, Ordering};
     use tokio::net::{TcpListener, UdpSocket};
     use tokio::time::timeout;
 
@@ -1294,6 +2605,446 @@ mod tests {
         endpoints.rebind_all().await;
     }
 
+    #[tokio::test]
+    async fn test_sharded() {
+        use crate::proto::{op::Message, rr::RecordType};
+        use std::str::FromStr;
+
+        let mut server_future = ServerFuture::new(Catalog::new());
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        let udp_addr = server_future.register_sharded_socket(addr, 4).unwrap();
+        let tcp_addr = server_future
+            .register_sharded_listener(addr, 4, Duration::from_secs(1))
+            .unwrap();
+        assert_ne!(udp_addr.port(), 0);
+        assert_ne!(tcp_addr.port(), 0);
+
+        // requests from many clients are spread over the shards, all of which respond
+        let mut request = Message::new();
+        request.add_query(Query::query(
+            crate::proto::rr::Name::from_str("www.example.com.").unwrap(),
+            RecordType::A,
+        ));
+        for id in 0..16 {
+            request.set_id(id);
+            let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            client
+                .send_to(&request.to_vec().unwrap(), udp_addr)
+                .await
+                .unwrap();
+
+            let mut buf = [0; 512];
+            let (len, _) = timeout(Duration::from_secs(2), client.recv_from(&mut buf))
+                .await
+                .expect("timed out waiting for the response")
+                .unwrap();
+            let response = Message::from_vec(&buf[..len]).unwrap();
+            assert_eq!(response.id(), id);
+            assert_eq!(response.response_code(), ResponseCode::Refused);
+        }
+
+        tokio::net::TcpStream::connect(tcp_addr).await.unwrap();
+
+        timeout(Duration::from_secs(2), server_future.shutdown_gracefully())
+            .await
+            .expect("timed out waiting for the server to complete")
+            .expect("error while awaiting tasks");
+    }
+
+    /// Answers all requests with SERVFAIL after the delay
+    struct SlowHandler(Duration);
+
+    #[async_trait::async_trait]
+    impl RequestHandler for SlowHandler {
+        async fn handle_request<R: ResponseHandler>(
+            &self,
+            request: &Request,
+            mut response_handle: R,
+        ) -> ResponseInfo {
+            tokio::time::sleep(self.0).await;
+            let response = MessageResponseBuilder::from_message_request(request);
+            response_handle
+                .send_response(response.error_msg(request.header(), ResponseCode::ServFail))
+                .await
+                .unwrap_or_else(|_| ResponseInfo::serve_failed())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_answers_requests_in_flight() {
+        use crate::proto::{op::Message, rr::RecordType};
+        use std::str::FromStr;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut server_future = ServerFuture::new(SlowHandler(Duration::from_millis(200)));
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        let udp_addr = server_future.register_sharded_socket(addr, 1).unwrap();
+        let tcp_addr = server_future
+            .register_sharded_listener(addr, 1, Duration::from_secs(5))
+            .unwrap();
+
+        let mut request = Message::new();
+        request.set_id(7).add_query(Query::query(
+            crate::proto::rr::Name::from_str("www.example.com.").unwrap(),
+            RecordType::A,
+        ));
+        let request = request.to_vec().unwrap();
+
+        let udp_client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        udp_client.send_to(&request, udp_addr).await.unwrap();
+        let mut tcp_client = tokio::net::TcpStream::connect(tcp_addr).await.unwrap();
+        tcp_client
+            .write_all(&(request.len() as u16).to_be_bytes())
+            .await
+            .unwrap();
+        tcp_client.write_all(&request).await.unwrap();
+
+        // the requests are being handled when the shutdown starts
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let responses = async {
+            let mut buf = [0; 512];
+            let (len, _) = udp_client.recv_from(&mut buf).await.unwrap();
+            let udp_response = Message::from_vec(&buf[..len]).unwrap();
+
+            let mut len = [0; 2];
+            tcp_client.read_exact(&mut len).await.unwrap();
+            let mut buf = vec![0; usize::from(u16::from_be_bytes(len))];
+            tcp_client.read_exact(&mut buf).await.unwrap();
+            let tcp_response = Message::from_vec(&buf).unwrap();
+
+            // and the connection is closed afterwards
+            assert_eq!(tcp_client.read(&mut len).await.unwrap(), 0);
+            (udp_response, tcp_response)
+        };
+
+        let (shutdown, responses) = timeout(
+            Duration::from_secs(2),
+            future::join(server_future.shutdown_gracefully(), responses),
+        )
+        .await
+        .expect("timed out waiting for the server to complete");
+        shutdown.expect("error while awaiting tasks");
+
+        for response in [responses.0, responses.1] {
+            assert_eq!(response.id(), 7);
+            assert_eq!(response.response_code(), ResponseCode::ServFail);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_deadline() {
+        let mut server_future = ServerFuture::new(SlowHandler(Duration::from_secs(60)));
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        let udp_addr = server_future.register_sharded_socket(addr, 1).unwrap();
+
+        let mut request = crate::proto::op::Message::new();
+        request.add_query(Query::new());
+        let udp_client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        udp_client
+            .send_to(&request.to_vec().unwrap(), udp_addr)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // the request in flight is aborted
+        timeout(
+            Duration::from_secs(2),
+            server_future.shutdown_gracefully_with_deadline(Duration::from_millis(100)),
+        )
+        .await
+        .expect("timed out waiting for the server to complete")
+        .expect_err("the request should still be in flight");
+    }
+
+    #[tokio::test]
+    async fn test_admission_overflow() {
+        use crate::proto::op::Message;
+
+        let mut server_future = ServerFuture::new(SlowHandler(Duration::from_millis(300)));
+        server_future.set_admission_config(AdmissionConfig {
+            max_in_flight: std::num::NonZeroUsize::new(1),
+            max_queued: 0,
+            overflow: Overflow::ServFail,
+            ..AdmissionConfig::default()
+        });
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        let udp_addr = server_future.register_sharded_socket(addr, 1).unwrap();
+
+        let udp_client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        for id in [1, 2] {
+            let mut request = Message::new();
+            request.set_id(id).add_query(Query::new());
+            udp_client
+                .send_to(&request.to_vec().unwrap(), udp_addr)
+                .await
+                .unwrap();
+        }
+
+        // the request over the limit is answered right away, before the one in flight
+        let mut buf = [0; 512];
+        for id in [2, 1] {
+            let (len, _) = timeout(Duration::from_secs(2), udp_client.recv_from(&mut buf))
+                .await
+                .expect("timed out waiting for the response")
+                .unwrap();
+            let response = Message::from_vec(&buf[..len]).unwrap();
+            assert_eq!(response.id(), id);
+            assert_eq!(response.response_code(), ResponseCode::ServFail);
+        }
+
+        let stats = server_future.admission_stats();
+        assert_eq!((stats.admitted, stats.servfailed, stats.dropped), (1, 1, 0));
+
+        timeout(Duration::from_secs(2), server_future.shutdown_gracefully())
+            .await
+            .expect("timed out waiting for the server to complete")
+            .expect("error while awaiting tasks");
+        assert_eq!(server_future.admission_stats().in_flight, 0);
+    }
+
+    #[tokio::test]
+    async fn test_connection_limits() {
+        use tokio::io::AsyncReadExt;
+
+        let mut server_future = ServerFuture::new(Catalog::new());
+        server_future.set_accept_config(AcceptConfig {
+            max_connections_per_ip: std::num::NonZeroUsize::new(1),
+            max_connection_lifetime: Some(Duration::from_millis(200)),
+            ..AcceptConfig::default()
+        });
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        let tcp_addr = server_future
+            .register_sharded_listener(addr, 1, Duration::from_secs(5))
+            .unwrap();
+
+        // a second connection from the same address is closed right away
+        let mut first = tokio::net::TcpStream::connect(tcp_addr).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let mut second = tokio::net::TcpStream::connect(tcp_addr).await.unwrap();
+        let mut buf = [0; 512];
+        let len = timeout(Duration::from_millis(100), second.read(&mut buf))
+            .await
+            .expect("the second connection should be closed")
+            .unwrap_or(0);
+        assert_eq!(len, 0);
+
+        // the first one is closed at the end of its lifetime, before the idle timeout
+        let len = timeout(Duration::from_secs(1), first.read(&mut buf))
+            .await
+            .expect("the first connection should be closed")
+            .unwrap_or(0);
+        assert_eq!(len, 0);
+
+        // which allows another connection from the address
+        let mut third = tokio::net::TcpStream::connect(tcp_addr).await.unwrap();
+        assert!(timeout(Duration::from_millis(100), third.read(&mut buf))
+            .await
+            .is_err());
+
+        timeout(Duration::from_secs(2), server_future.shutdown_gracefully())
+            .await
+            .expect("timed out waiting for the server to complete")
+            .expect("error while awaiting tasks");
+    }
+
+    /// Answers A queries from the borrowed message, with `count` addresses
+    struct BorrowedHandler {
+        count: usize,
+        handled: AtomicBool,
+    }
+
+    #[async_trait::async_trait]
+    impl RequestHandler for BorrowedHandler {
+        async fn handle_request<R: ResponseHandler>(
+            &self,
+            _request: &Request,
+            _response_handle: R,
+        ) -> ResponseInfo {
+            self.handled.store(true, Ordering::Relaxed);
+            ResponseInfo::serve_failed()
+        }
+
+        fn answer_ref(
+            &self,
+            request: &MessageRef<'_>,
+            _src: SocketAddr,
+            _protocol: Protocol,
+        ) -> Option<Vec<u8>> {
+            use crate::proto::rr::{rdata::A, RData, RecordType};
+
+            let query = request.queries().next()?;
+            if query.query_type() != RecordType::A {
+                return None;
+            }
+
+            let name = query.name().to_name().ok()?;
+            let answers = (0..self.count)
+                .map(|i| {
+                    Record::from_rdata(name.clone(), 300, RData::A(A::new(192, 0, 2, i as u8)))
+                })
+                .collect::<Vec<_>>();
+
+            let mut header = Header::response_from_request(request.header());
+            header.set_authoritative(true);
+            request.to_response(&header, &answers).ok()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_effective_config() {
+        let denied = ["192.0.2.0/24".parse().unwrap()];
+        let mut server_future = ServerFuture::with_access(Catalog::new(), &denied, &[]);
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        let udp = UdpSocket::bind(addr).await.unwrap();
+        let udp_addr = udp.local_addr().unwrap();
+        server_future.register_socket(udp);
+        let tcp_addr = server_future
+            .register_sharded_listener(addr, 1, Duration::from_secs(1))
+            .unwrap();
+
+        let config = server_future.effective_config();
+        assert_eq!(config.denied_networks, denied);
+        assert!(config.allowed_networks.is_empty());
+        assert_eq!(
+            config.listeners,
+            [
+                ListenerConfig::new(Protocol::Udp, Some(udp_addr)),
+                ListenerConfig::new(Protocol::Tcp, Some(tcp_addr))
+                    .with_timeout(Duration::from_secs(1)),
+            ]
+        );
+
+        timeout(Duration::from_secs(2), server_future.shutdown_gracefully())
+            .await
+            .expect("timed out waiting for the server to complete")
+            .expect("error while awaiting tasks");
+    }
+
+    #[tokio::test]
+    async fn test_proxy_protocol() {
+        use crate::proto::{op::Message, rr::RecordType};
+        use crate::server::proxy_protocol::proxy_header;
+        use std::str::FromStr;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let denied = ["192.0.2.0/24".parse().unwrap()];
+        let mut server_future =
+            ServerFuture::with_access(SlowHandler(Duration::ZERO), &denied, &[]);
+        server_future.set_proxy_protocol(true);
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        let tcp_addr = server_future
+            .register_sharded_listener(addr, 1, Duration::from_secs(1))
+            .unwrap();
+        assert!(server_future.effective_config().listeners[0].proxy_protocol);
+
+        let mut request = Message::new();
+        request.set_id(7).add_query(Query::query(
+            crate::proto::rr::Name::from_str("www.example.com.").unwrap(),
+            RecordType::A,
+        ));
+        let request = request.to_vec().unwrap();
+
+        // the access rules apply to the address of the client in the header
+        for (client, allowed) in [([198, 51, 100, 1], true), ([192, 0, 2, 1], false)] {
+            let mut tcp_client = tokio::net::TcpStream::connect(tcp_addr).await.unwrap();
+            let header = proxy_header(SocketAddr::from((client, 4096)), tcp_addr);
+            tcp_client.write_all(&header).await.unwrap();
+            tcp_client
+                .write_all(&(request.len() as u16).to_be_bytes())
+                .await
+                .unwrap();
+            tcp_client.write_all(&request).await.unwrap();
+
+            let mut len = [0; 2];
+            let read = timeout(Duration::from_millis(500), tcp_client.read_exact(&mut len)).await;
+            if !allowed {
+                assert!(read.is_err(), "denied requests are not answered");
+                continue;
+            }
+
+            read.expect("timed out waiting for the response").unwrap();
+            let mut buf = vec![0; usize::from(u16::from_be_bytes(len))];
+            tcp_client.read_exact(&mut buf).await.unwrap();
+            let response = Message::from_vec(&buf).unwrap();
+            assert_eq!(response.id(), 7);
+            assert_eq!(response.response_code(), ResponseCode::ServFail);
+        }
+
+        // connections without the header are closed
+        let mut tcp_client = tokio::net::TcpStream::connect(tcp_addr).await.unwrap();
+        tcp_client
+            .write_all(&(request.len() as u16).to_be_bytes())
+            .await
+            .unwrap();
+        tcp_client.write_all(&request).await.unwrap();
+        let mut buf = [0; 512];
+        let len = timeout(Duration::from_secs(2), tcp_client.read(&mut buf))
+            .await
+            .expect("timed out waiting for the close")
+            .unwrap_or(0);
+        assert_eq!(len, 0);
+
+        timeout(Duration::from_secs(2), server_future.shutdown_gracefully())
+            .await
+            .expect("timed out waiting for the server to complete")
+            .expect("error while awaiting tasks");
+    }
+
+    #[tokio::test]
+    async fn test_answer_ref() {
+        use crate::proto::{op::Message, rr::RecordType};
+        use std::str::FromStr;
+
+        let src = SocketAddr::from(([192, 0, 2, 7], 4096));
+        let mut request = Message::new();
+        request.add_query(Query::query(
+            crate::proto::rr::Name::from_str("www.example.com.").unwrap(),
+            RecordType::A,
+        ));
+        let request = request.to_vec().unwrap();
+
+        // small responses are sent directly
+        let handler = Arc::new(BorrowedHandler {
+            count: 1,
+            handled: AtomicBool::default(),
+        });
+        let (stream_handle, mut receiver) = BufDnsStreamHandle::new(src);
+        handle_raw_request(
+            SerialMessage::new(request.clone(), src),
+            Protocol::Udp,
+            Arc::new(AccessControl::default()),
+            ResponseConfig::default(),
+            handler.clone(),
+            stream_handle,
+        )
+        .await;
+
+        assert!(!handler.handled.load(Ordering::Relaxed));
+        let response = receiver.next().await.unwrap();
+        let response = Message::from_vec(response.bytes()).unwrap();
+        assert_eq!(response.message_type(), MessageType::Response);
+        assert!(response.authoritative());
+        assert_eq!(response.answers().len(), 1);
+
+        // responses exceeding the max payload of the client are handled by handle_request
+        let handler = Arc::new(BorrowedHandler {
+            count: 64,
+            handled: AtomicBool::default(),
+        });
+        let (stream_handle, _receiver) = BufDnsStreamHandle::new(src);
+        handle_raw_request(
+            SerialMessage::new(request, src),
+            Protocol::Udp,
+            Arc::new(AccessControl::default()),
+            ResponseConfig::default(),
+            handler.clone(),
+            stream_handle,
+        )
+        .await;
+
+        assert!(handler.handled.load(Ordering::Relaxed));
+    }
+
     #[test]
     fn test_sanitize_src_addr() {
         // ipv4 tests
@@ -0,0 +1,371 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Selection of the certificate of TLS, HTTPS, QUIC and HTTP/3 listeners by the SNI of clients
+
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+    sync::{Arc, PoisonError, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use rustls::{
+    server::{ClientHello, ResolvesServerCert},
+    sign::{self, CertifiedKey},
+    Certificate, PrivateKey,
+};
+use tracing::{info, warn};
+
+/// The ALPN protocol of the handshakes validating ACME TLS-ALPN-01 challenges, [RFC 8737](https://www.rfc-editor.org/rfc/rfc8737)
+pub const ACME_TLS_ALPN_PROTOCOL: &[u8] = b"acme-tls/1";
+
+/// Loads a certificate chain and its private key, e.g. from files on disk
+pub type CertLoader = Box<dyn Fn() -> Result<(Vec<Certificate>, PrivateKey), String> + Send + Sync>;
+
+/// Selects the certificate for each TLS handshake by the server name (SNI) sent by the client
+///
+/// Each certificate is served for a list of names, where `*.example.com` matches any name with
+///  a single label below `example.com`. Clients without SNI, or with a name which matches no
+///  certificate, are served the first certificate. Certificates are reloaded with
+///  [`Self::reload`] when one of their files changed on disk, handshakes in progress keep the
+///  certificate they started with.
+///
+/// Handshakes offering the [`ACME_TLS_ALPN_PROTOCOL`] are served the certificate of the
+///  pending ACME challenge of the name, see [`Self::add_acme_challenge`], or fail.
+#[derive(Default)]
+pub struct SniCertResolver {
+    entries: RwLock<Vec<Entry>>,
+    acme_challenges: RwLock<HashMap<String, Arc<CertifiedKey>>>,
+}
+
+struct Entry {
+    names: Vec<String>,
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+    load: CertLoader,
+    certified_key: Arc<CertifiedKey>,
+}
+
+impl SniCertResolver {
+    /// Creates a resolver without certificates, which fails all handshakes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a certificate for the names, which is loaded now and again when one of the files
+    ///  changed, the first certificate is the default
+    ///
+    /// # Arguments
+    /// * `names` - the names to serve the certificate for, e.g. `dot.example.com` or `*.example.net`
+    /// * `files` - the files to watch for changes, usually the certificate chain and the key
+    /// * `load` - loads the certificate chain and the key
+    pub fn add(
+        &mut self,
+        names: Vec<String>,
+        files: Vec<PathBuf>,
+        load: CertLoader,
+    ) -> Result<(), String> {
+        let files = files
+            .into_iter()
+            .map(|path| {
+                let modified = modified(&path);
+                (path, modified)
+            })
+            .collect();
+        let certified_key = certified_key(&load)?;
+
+        self.entries
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Entry {
+                names: names.iter().map(|name| normalize(name)).collect(),
+                files,
+                load,
+                certified_key,
+            });
+        Ok(())
+    }
+
+    /// Reloads the certificates with files which changed since they were loaded
+    ///
+    /// A certificate which fails to load is logged and still served, it is loaded again on the
+    ///  next call.
+    pub fn reload(&self) {
+        let mut entries = self.entries.write().unwrap_or_else(PoisonError::into_inner);
+
+        for entry in entries.iter_mut() {
+            let files = entry
+                .files
+                .iter()
+                .map(|(path, _)| {
+                    let modified = modified(path);
+                    (path.clone(), modified)
+                })
+                .collect::<Vec<_>>();
+            if files == entry.files {
+                continue;
+            }
+
+            match certified_key(&entry.load) {
+                Ok(certified_key) => {
+                    info!("reloaded certificate for {:?}", entry.names);
+                    entry.certified_key = certified_key;
+                    entry.files = files;
+                }
+                Err(e) => warn!("failed to reload certificate for {:?}: {e}", entry.names),
+            }
+        }
+    }
+
+    /// Serves the self-signed certificate of a TLS-ALPN-01 challenge for the name, until it is
+    ///  removed with [`Self::remove_acme_challenge`]
+    pub fn add_acme_challenge(
+        &self,
+        name: &str,
+        cert: Certificate,
+        key: &PrivateKey,
+    ) -> Result<(), String> {
+        let certified_key = to_certified_key(vec![cert], key)?;
+        self.acme_challenges
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(normalize(name), certified_key);
+        Ok(())
+    }
+
+    /// Removes the certificate of the TLS-ALPN-01 challenge for the name
+    pub fn remove_acme_challenge(&self, name: &str) {
+        self.acme_challenges
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&normalize(name));
+    }
+
+    /// Reloads the changed certificates at the interval, this never returns
+    pub async fn reload_every(self: Arc<Self>, interval: Duration) {
+        loop {
+            tokio::time::sleep(interval).await;
+            self.reload();
+        }
+    }
+
+    /// Returns the certificate for the server name, or the default
+    fn select(&self, server_name: Option<&str>) -> Option<Arc<CertifiedKey>> {
+        let entries = self.entries.read().unwrap_or_else(PoisonError::into_inner);
+
+        let selected = server_name.map(normalize).and_then(|server_name| {
+            entries.iter().find(|entry| {
+                entry
+                    .names
+                    .iter()
+                    .any(|name| matches_name(name, &server_name))
+            })
+        });
+
+        selected
+            .or_else(|| entries.first())
+            .map(|entry| Arc::clone(&entry.certified_key))
+    }
+
+    /// Returns the certificate of the pending ACME challenge for the server name, if any
+    fn select_acme_challenge(&self, server_name: Option<&str>) -> Option<Arc<CertifiedKey>> {
+        let challenges = self
+            .acme_challenges
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        server_name
+            .and_then(|server_name| challenges.get(&normalize(server_name)))
+            .cloned()
+    }
+}
+
+impl ResolvesServerCert for SniCertResolver {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        let acme = client_hello.alpn().map_or(false, |mut protocols| {
+            protocols.any(|protocol| protocol == ACME_TLS_ALPN_PROTOCOL)
+        });
+        match acme {
+            true => self.select_acme_challenge(client_hello.server_name()),
+            false => self.select(client_hello.server_name()),
+        }
+    }
+}
+
+impl fmt::Debug for SniCertResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries = self.entries.read().unwrap_or_else(PoisonError::into_inner);
+        f.debug_list()
+            .entries(entries.iter().map(|entry| &entry.names))
+            .finish()
+    }
+}
+
+fn normalize(name: &str) -> String {
+    name.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// Returns true if the name, or the wildcard, matches the normalized server name
+fn matches_name(name: &str, server_name: &str) -> bool {
+    match name.strip_prefix("*.") {
+        Some(parent) => server_name
+            .split_once('.')
+            .map_or(false, |(label, rest)| !label.is_empty() && rest == parent),
+        None => name == server_name,
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn certified_key(load: &CertLoader) -> Result<Arc<CertifiedKey>, String> {
+    let (cert, key) = load()?;
+    to_certified_key(cert, &key)
+}
+
+fn to_certified_key(cert: Vec<Certificate>, key: &PrivateKey) -> Result<Arc<CertifiedKey>, String> {
+    let key = sign::any_supported_type(key).map_err(|e| format!("unsupported key: {e}"))?;
+    Ok(Arc::new(CertifiedKey::new(cert, key)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use hickory_proto::rustls::tls_server;
+
+    use super::*;
+
+    fn load(cert: &'static str, key: &'static str) -> CertLoader {
+        let root = env::var("TDNS_WORKSPACE_ROOT").unwrap_or_else(|_| "../..".to_owned());
+        Box::new(move || {
+            let cert = tls_server::read_cert(Path::new(&format!("{root}/{cert}")))
+                .map_err(|e| e.to_string())?;
+            let key = tls_server::read_key_from_pem(Path::new(&format!("{root}/{key}")))
+                .map_err(|e| e.to_string())?;
+            Ok((cert, key))
+        })
+    }
+
+    fn first() -> CertLoader {
+        load("tests/test-data/cert.pem", "tests/test-data/cert.key")
+    }
+
+    fn second() -> CertLoader {
+        load(
+            "tests/test-data/test_configs/sec/example.cert.pem",
+            "tests/test-data/test_configs/sec/example.key.pem",
+        )
+    }
+
+    fn cert(resolver: &SniCertResolver, server_name: Option<&str>) -> Certificate {
+        resolver.select(server_name).unwrap().cert[0].clone()
+    }
+
+    #[test]
+    fn test_select() {
+        let mut resolver = SniCertResolver::new();
+        assert!(resolver.select(Some("dot.example.com")).is_none());
+
+        resolver
+            .add(vec!["dot.example.com".to_string()], vec![], first())
+            .unwrap();
+        resolver
+            .add(vec!["*.Example.NET.".to_string()], vec![], second())
+            .unwrap();
+
+        let (first, _) = first()().unwrap();
+        let (second, _) = second()().unwrap();
+        assert_ne!(first[0], second[0]);
+
+        assert_eq!(cert(&resolver, Some("dot.example.com")), first[0]);
+        assert_eq!(cert(&resolver, Some("doh.example.net")), second[0]);
+        assert_eq!(cert(&resolver, Some("DOH.EXAMPLE.NET")), second[0]);
+
+        // the wildcard matches a single label, everything else gets the default
+        assert_eq!(cert(&resolver, Some("example.net")), first[0]);
+        assert_eq!(cert(&resolver, Some("a.doh.example.net")), first[0]);
+        assert_eq!(cert(&resolver, None), first[0]);
+    }
+
+    #[test]
+    fn test_acme_challenge() {
+        let mut resolver = SniCertResolver::new();
+        resolver
+            .add(vec!["dot.example.com".to_string()], vec![], first())
+            .unwrap();
+
+        let (challenge, key) = second()().unwrap();
+        resolver
+            .add_acme_challenge("DOT.example.com.", challenge[0].clone(), &key)
+            .unwrap();
+
+        let selected = resolver.select_acme_challenge(Some("dot.example.com"));
+        assert_eq!(selected.unwrap().cert[0], challenge[0]);
+        assert!(resolver
+            .select_acme_challenge(Some("doh.example.net"))
+            .is_none());
+        assert!(resolver.select_acme_challenge(None).is_none());
+
+        // the certificate of the name is not changed by the challenge
+        assert_ne!(cert(&resolver, Some("dot.example.com")), challenge[0]);
+
+        resolver.remove_acme_challenge("dot.example.com");
+        assert!(resolver
+            .select_acme_challenge(Some("dot.example.com"))
+            .is_none());
+    }
+
+    #[test]
+    fn test_reload() {
+        let watched = env::temp_dir().join(format!("sni_cert_resolver_{}", std::process::id()));
+        std::fs::write(&watched, b"cert").unwrap();
+
+        let loads = Arc::new(AtomicUsize::new(0));
+        let loader = {
+            let loads = Arc::clone(&loads);
+            let (first, second) = (first(), second());
+            Box::new(move || match loads.fetch_add(1, Ordering::SeqCst) {
+                0 => first(),
+                1 => Err("partially written".to_string()),
+                _ => second(),
+            })
+        };
+
+        let mut resolver = SniCertResolver::new();
+        resolver
+            .add(
+                vec!["dot.example.com".to_string()],
+                vec![watched.clone()],
+                loader,
+            )
+            .unwrap();
+        let (first, _) = first()().unwrap();
+        let (second, _) = second()().unwrap();
+
+        // unchanged files are not loaded again
+        resolver.reload();
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+
+        // the old certificate is served until the new one loads
+        std::fs::remove_file(&watched).unwrap();
+        resolver.reload();
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+        assert_eq!(cert(&resolver, Some("dot.example.com")), first[0]);
+
+        resolver.reload();
+        assert_eq!(loads.load(Ordering::SeqCst), 3);
+        assert_eq!(cert(&resolver, Some("dot.example.com")), second[0]);
+
+        resolver.reload();
+        assert_eq!(loads.load(Ordering::SeqCst), 3);
+    }
+}
@@ -34,6 +34,11 @@ impl<S> TimeoutStream<S> {
         }
     }
 
+    /// Returns the wrapped stream
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
     fn timeout(timeout_duration: Duration) -> Option<Pin<Box<Sleep>>> {
         if timeout_duration > Duration::from_millis(0) {
             Some(Box::pin(tokio::time::sleep(timeout_duration)))
@@ -0,0 +1,83 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Experimental fast path answering UDP queries from AF_XDP sockets
+//!
+//! The packets are received and the responses are sent without the network stack of the kernel.
+//!  This requires an XDP program attached to the network interface, which redirects the UDP
+//!  packets to the DNS port into a pinned `BPF_MAP_TYPE_XSKMAP`, and passes all other packets on.
+//!
+//! Only IPv4 packets without options, IPv6 packets without extension headers, and an optional
+//!  VLAN tag are supported. Requests which are answered by [`RequestHandler::answer_ref`] are
+//!  responded to in place, all other requests are handed to the normal UDP path.
+//!
+//! [`RequestHandler::answer_ref`]: crate::server::RequestHandler::answer_ref
+
+mod packet;
+mod socket;
+
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+use crate::proto::xfer::SerialMessage;
+
+use self::packet::UdpFrame;
+pub use self::socket::{XdpConfig, XdpSocket};
+
+/// How long to wait for packets before checking for shutdown
+const POLL_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Receives and answers requests on the socket, until shutdown
+///
+/// This busy polls the socket, so it must run on its own thread.
+///
+/// # Arguments
+///
+/// * `answer` - returns the response to a request from an address, if it can be answered
+///   synchronously
+/// * `fallback` - takes the requests which are not answered by `answer`
+pub(crate) fn serve(
+    mut socket: XdpSocket,
+    shutdown: &CancellationToken,
+    mut answer: impl FnMut(&[u8], SocketAddr) -> Option<Vec<u8>>,
+    mut fallback: impl FnMut(SerialMessage),
+) -> io::Result<()> {
+    let port = socket.port();
+
+    while !shutdown.is_cancelled() {
+        socket.process(POLL_TIMEOUT, |buffer, len| {
+            let Some(frame) = UdpFrame::parse(&buffer[..len], port) else {
+                debug!("dropping unsupported packet redirected to the xdp socket");
+                return None;
+            };
+
+            if let Some(response) = answer(frame.payload(buffer), frame.src) {
+                if let Some(len) = frame.write_response(buffer, &response) {
+                    return Some(len);
+                }
+
+                // only possible for EDNS payload sizes above the MTU
+                debug!(
+                    "response of {} bytes does not fit in the frame",
+                    response.len()
+                );
+            }
+
+            fallback(SerialMessage::new(
+                frame.payload(buffer).to_vec(),
+                frame.src,
+            ));
+            None
+        })?;
+    }
+
+    Ok(())
+}
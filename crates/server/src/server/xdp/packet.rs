@@ -0,0 +1,359 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Parsing of UDP frames, and writing the response in place of the request
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+const ETHERNET_HEADER_LEN: usize = 14;
+const VLAN_TAG_LEN: usize = 4;
+const IPV4_HEADER_LEN: usize = 20;
+const IPV6_HEADER_LEN: usize = 40;
+const UDP_HEADER_LEN: usize = 8;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const IPPROTO_UDP: u8 = 17;
+
+/// TTL, or hop limit, of the responses
+const RESPONSE_TTL: u8 = 64;
+
+/// The layout of an Ethernet frame carrying a UDP datagram
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) struct UdpFrame {
+    /// Source of the datagram
+    pub(super) src: SocketAddr,
+    /// Destination of the datagram
+    pub(super) dst: SocketAddr,
+    ip_offset: usize,
+    udp_offset: usize,
+    payload_len: usize,
+}
+
+impl UdpFrame {
+    /// Parses an Ethernet frame with an optional VLAN tag, and an IPv4 or IPv6 header without
+    ///  options or extension headers, followed by a UDP datagram to `port`
+    ///
+    /// Anything else, including fragments, returns `None`.
+    pub(super) fn parse(frame: &[u8], port: u16) -> Option<Self> {
+        let mut ip_offset = ETHERNET_HEADER_LEN;
+        let mut ethertype = read_u16(frame, 12)?;
+        if ethertype == ETHERTYPE_VLAN {
+            ethertype = read_u16(frame, 16)?;
+            ip_offset += VLAN_TAG_LEN;
+        }
+
+        let (src_ip, dst_ip, udp_offset, ip_payload_len) = match ethertype {
+            ETHERTYPE_IPV4 => {
+                let ip = frame.get(ip_offset..ip_offset + IPV4_HEADER_LEN)?;
+                // version 4 without options, not a fragment
+                let fragment = u16::from_be_bytes([ip[6], ip[7]]);
+                if ip[0] != 0x45 || ip[9] != IPPROTO_UDP || fragment & 0x3fff != 0 {
+                    return None;
+                }
+
+                let total_len = usize::from(u16::from_be_bytes([ip[2], ip[3]]));
+                let src = Ipv4Addr::new(ip[12], ip[13], ip[14], ip[15]);
+                let dst = Ipv4Addr::new(ip[16], ip[17], ip[18], ip[19]);
+                (
+                    IpAddr::V4(src),
+                    IpAddr::V4(dst),
+                    ip_offset + IPV4_HEADER_LEN,
+                    total_len.checked_sub(IPV4_HEADER_LEN)?,
+                )
+            }
+            ETHERTYPE_IPV6 => {
+                let ip = frame.get(ip_offset..ip_offset + IPV6_HEADER_LEN)?;
+                if ip[0] >> 4 != 6 || ip[6] != IPPROTO_UDP {
+                    return None;
+                }
+
+                let payload_len = usize::from(u16::from_be_bytes([ip[4], ip[5]]));
+                let src = <[u8; 16]>::try_from(&ip[8..24]).ok()?;
+                let dst = <[u8; 16]>::try_from(&ip[24..40]).ok()?;
+                (
+                    IpAddr::V6(Ipv6Addr::from(src)),
+                    IpAddr::V6(Ipv6Addr::from(dst)),
+                    ip_offset + IPV6_HEADER_LEN,
+                    payload_len,
+                )
+            }
+            _ => return None,
+        };
+
+        let udp = frame.get(udp_offset..udp_offset + UDP_HEADER_LEN)?;
+        let src_port = u16::from_be_bytes([udp[0], udp[1]]);
+        let dst_port = u16::from_be_bytes([udp[2], udp[3]]);
+        let udp_len = usize::from(u16::from_be_bytes([udp[4], udp[5]]));
+
+        // short frames may be padded, the lengths in the headers are authoritative
+        if dst_port != port
+            || udp_len < UDP_HEADER_LEN
+            || udp_len > ip_payload_len
+            || udp_offset + udp_len > frame.len()
+        {
+            return None;
+        }
+
+        Some(Self {
+            src: SocketAddr::new(src_ip, src_port),
+            dst: SocketAddr::new(dst_ip, dst_port),
+            ip_offset,
+            udp_offset,
+            payload_len: udp_len - UDP_HEADER_LEN,
+        })
+    }
+
+    /// Returns the UDP payload of the frame
+    pub(super) fn payload<'a>(&self, frame: &'a [u8]) -> &'a [u8] {
+        let offset = self.udp_offset + UDP_HEADER_LEN;
+        &frame[offset..offset + self.payload_len]
+    }
+
+    /// Replaces the request in `buffer` with a frame sending `payload` back to the source
+    ///
+    /// The addresses and ports are swapped, and the lengths and checksums are updated.
+    ///
+    /// # Return
+    ///
+    /// The length of the response frame, or `None` if it does not fit in the buffer, in which case
+    ///  the buffer is unchanged
+    pub(super) fn write_response(&self, buffer: &mut [u8], payload: &[u8]) -> Option<usize> {
+        let payload_offset = self.udp_offset + UDP_HEADER_LEN;
+        let frame_len = payload_offset + payload.len();
+        let udp_len = u16::try_from(UDP_HEADER_LEN + payload.len()).ok()?;
+        if frame_len > buffer.len() {
+            return None;
+        }
+
+        let ip = &mut buffer[self.ip_offset..self.udp_offset];
+        match (self.src.ip(), self.dst.ip()) {
+            (IpAddr::V4(src), IpAddr::V4(dst)) => {
+                let total_len = u16::try_from(IPV4_HEADER_LEN).ok()? + udp_len;
+                ip[1] = 0; // DSCP and ECN
+                ip[2..4].copy_from_slice(&total_len.to_be_bytes());
+                ip[4..6].copy_from_slice(&[0, 0]); // identification
+                ip[6..8].copy_from_slice(&0x4000_u16.to_be_bytes()); // don't fragment
+                ip[8] = RESPONSE_TTL;
+                ip[10..12].copy_from_slice(&[0, 0]);
+                ip[12..16].copy_from_slice(&dst.octets());
+                ip[16..20].copy_from_slice(&src.octets());
+
+                let checksum = !fold(sum(0, ip));
+                ip[10..12].copy_from_slice(&checksum.to_be_bytes());
+            }
+            (IpAddr::V6(src), IpAddr::V6(dst)) => {
+                ip[4..6].copy_from_slice(&udp_len.to_be_bytes());
+                ip[7] = RESPONSE_TTL;
+                ip[8..24].copy_from_slice(&dst.octets());
+                ip[24..40].copy_from_slice(&src.octets());
+            }
+            _ => return None,
+        }
+
+        // Ethernet, destination and source
+        let (destination, source) = buffer[..12].split_at_mut(6);
+        destination.swap_with_slice(source);
+
+        buffer[payload_offset..frame_len].copy_from_slice(payload);
+
+        let udp = &mut buffer[self.udp_offset..payload_offset];
+        udp[0..2].copy_from_slice(&self.dst.port().to_be_bytes());
+        udp[2..4].copy_from_slice(&self.src.port().to_be_bytes());
+        udp[4..6].copy_from_slice(&udp_len.to_be_bytes());
+        udp[6..8].copy_from_slice(&[0, 0]);
+
+        let checksum = udp_checksum(
+            self.dst.ip(),
+            self.src.ip(),
+            &buffer[self.udp_offset..frame_len],
+        );
+        buffer[self.udp_offset + 6..self.udp_offset + 8].copy_from_slice(&checksum.to_be_bytes());
+
+        Some(frame_len)
+    }
+}
+
+fn read_u16(frame: &[u8], offset: usize) -> Option<u16> {
+    let bytes = frame.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+/// Adds the bytes as big endian 16 bit words to the ones' complement sum, RFC 1071
+fn sum(mut sum: u32, bytes: &[u8]) -> u32 {
+    let mut words = bytes.chunks_exact(2);
+    for word in &mut words {
+        sum += u32::from(u16::from_be_bytes([word[0], word[1]]));
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    if let [last] = words.remainder() {
+        sum += u32::from(*last) << 8;
+    }
+    sum
+}
+
+fn fold(mut sum: u32) -> u16 {
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    sum as u16
+}
+
+/// Computes the checksum of a UDP datagram, including the pseudo header of the IP addresses
+fn udp_checksum(src: IpAddr, dst: IpAddr, datagram: &[u8]) -> u16 {
+    let mut pseudo_header = match (src, dst) {
+        (IpAddr::V4(src), IpAddr::V4(dst)) => sum(sum(0, &src.octets()), &dst.octets()),
+        (src, dst) => sum(sum(0, &ip_octets(src)), &ip_octets(dst)),
+    };
+    pseudo_header += u32::from(IPPROTO_UDP) + datagram.len() as u32;
+
+    // a checksum of 0 means no checksum, it is sent as all ones instead, RFC 768
+    match !fold(sum(pseudo_header, datagram)) {
+        0 => 0xffff,
+        checksum => checksum,
+    }
+}
+
+fn ip_octets(ip: IpAddr) -> [u8; 16] {
+    match ip {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped().octets(),
+        IpAddr::V6(ip) => ip.octets(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLIENT_MAC: [u8; 6] = [2, 0, 0, 0, 0, 1];
+    const SERVER_MAC: [u8; 6] = [2, 0, 0, 0, 0, 2];
+
+    fn frame(src: SocketAddr, dst: SocketAddr, vlan: bool, payload: &[u8]) -> Vec<u8> {
+        let mut frame = Vec::new();
+        frame.extend(SERVER_MAC);
+        frame.extend(CLIENT_MAC);
+        if vlan {
+            frame.extend(ETHERTYPE_VLAN.to_be_bytes());
+            frame.extend(7_u16.to_be_bytes());
+        }
+
+        let udp_len = (UDP_HEADER_LEN + payload.len()) as u16;
+        match (src.ip(), dst.ip()) {
+            (IpAddr::V4(src), IpAddr::V4(dst)) => {
+                frame.extend(ETHERTYPE_IPV4.to_be_bytes());
+                frame.extend([0x45, 0]);
+                frame.extend((IPV4_HEADER_LEN as u16 + udp_len).to_be_bytes());
+                frame.extend([0, 1, 0, 0, 64, IPPROTO_UDP, 0, 0]);
+                frame.extend(src.octets());
+                frame.extend(dst.octets());
+            }
+            (IpAddr::V6(src), IpAddr::V6(dst)) => {
+                frame.extend(ETHERTYPE_IPV6.to_be_bytes());
+                frame.extend([0x60, 0, 0, 0]);
+                frame.extend(udp_len.to_be_bytes());
+                frame.extend([IPPROTO_UDP, 64]);
+                frame.extend(src.octets());
+                frame.extend(dst.octets());
+            }
+            _ => unreachable!(),
+        }
+
+        frame.extend(src.port().to_be_bytes());
+        frame.extend(dst.port().to_be_bytes());
+        frame.extend(udp_len.to_be_bytes());
+        frame.extend([0, 0]);
+        frame.extend(payload);
+        frame
+    }
+
+    fn test_response(client: SocketAddr, server: SocketAddr, vlan: bool) {
+        let request = frame(client, server, vlan, b"request");
+        let parsed = UdpFrame::parse(&request, 53).unwrap();
+        assert_eq!(parsed.src, client);
+        assert_eq!(parsed.dst, server);
+        assert_eq!(parsed.payload(&request), b"request");
+
+        let mut buffer = request.clone();
+        buffer.resize(2048, 0);
+        let len = parsed
+            .write_response(&mut buffer, b"a longer response")
+            .unwrap();
+        let response = &buffer[..len];
+
+        let expected = frame(server, client, vlan, b"a longer response");
+        assert_eq!(response[..6], CLIENT_MAC);
+        assert_eq!(response[6..12], SERVER_MAC);
+
+        let reply = UdpFrame::parse(response, client.port()).unwrap();
+        assert_eq!(reply.src, server);
+        assert_eq!(reply.dst, client);
+        assert_eq!(reply.payload(response), b"a longer response");
+        assert_eq!(response.len(), expected.len());
+
+        // the checksums of a valid packet sum to all ones
+        let udp = &response[reply.udp_offset..];
+        assert_eq!(
+            udp_checksum(server.ip(), client.ip(), udp),
+            0xffff,
+            "bad udp checksum"
+        );
+        if server.is_ipv4() {
+            let ip = &response[reply.ip_offset..reply.udp_offset];
+            assert_eq!(fold(sum(0, ip)), 0xffff, "bad ip checksum");
+        }
+    }
+
+    #[test]
+    fn test_ipv4_response() {
+        let client = SocketAddr::from(([192, 0, 2, 1], 4096));
+        let server = SocketAddr::from(([192, 0, 2, 53], 53));
+        test_response(client, server, false);
+        test_response(client, server, true);
+    }
+
+    #[test]
+    fn test_ipv6_response() {
+        let client = SocketAddr::from(([0x2001, 0xdb8, 0, 0, 0, 0, 0, 1], 4096));
+        let server = SocketAddr::from(([0x2001, 0xdb8, 0, 0, 0, 0, 0, 0x53], 53));
+        test_response(client, server, false);
+        test_response(client, server, true);
+    }
+
+    #[test]
+    fn test_parse_rejects() {
+        let client = SocketAddr::from(([192, 0, 2, 1], 4096));
+        let server = SocketAddr::from(([192, 0, 2, 53], 53));
+        let request = frame(client, server, false, b"request");
+
+        // other port
+        assert!(UdpFrame::parse(&request, 853).is_none());
+
+        // fragments
+        let mut fragment = request.clone();
+        fragment[ETHERNET_HEADER_LEN + 6] = 0x20;
+        assert!(UdpFrame::parse(&fragment, 53).is_none());
+
+        // truncated
+        assert!(UdpFrame::parse(&request[..request.len() - 1], 53).is_none());
+
+        // padded frames are fine
+        let mut padded = request.clone();
+        padded.extend([0; 8]);
+        assert_eq!(
+            UdpFrame::parse(&padded, 53).unwrap().payload(&padded),
+            b"request"
+        );
+
+        // does not fit
+        let parsed = UdpFrame::parse(&request, 53).unwrap();
+        let mut buffer = request.clone();
+        assert!(parsed
+            .write_response(&mut buffer, b"a longer response")
+            .is_none());
+    }
+}
@@ -0,0 +1,598 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! AF_XDP sockets, with the UMEM and the rings shared with the kernel
+
+use std::ffi::CString;
+use std::fmt;
+use std::io;
+use std::mem;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::path::PathBuf;
+use std::ptr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+/// The size of each frame of the UMEM, one frame holds one packet
+const FRAME_SIZE: usize = 4096;
+
+/// The number of frames of the UMEM by default
+const DEFAULT_FRAMES: u32 = 4096;
+
+// commands of the bpf system call, from linux/bpf.h
+const BPF_MAP_UPDATE_ELEM: libc::c_long = 2;
+const BPF_OBJ_GET: libc::c_long = 7;
+
+/// Configuration of an [`XdpSocket`]
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct XdpConfig {
+    /// Name of the network interface
+    pub interface: String,
+    /// Receive queue of the interface to bind to
+    pub queue_id: u32,
+    /// Path of the pinned `BPF_MAP_TYPE_XSKMAP` which the XDP program redirects to, the socket
+    ///  is inserted at the index of the queue
+    pub xsk_map: PathBuf,
+    /// Number of frames of the UMEM, a power of two, each frame is 4096 bytes
+    pub frames: u32,
+    /// Require zero copy mode, which is only supported by some drivers, otherwise copy mode is used
+    pub zero_copy: bool,
+    /// UDP port of the DNS server, packets to other ports are dropped
+    pub port: u16,
+}
+
+impl XdpConfig {
+    /// Returns the configuration for queue 0 of the interface and port 53
+    pub fn new(interface: impl Into<String>, xsk_map: impl Into<PathBuf>) -> Self {
+        Self {
+            interface: interface.into(),
+            queue_id: 0,
+            xsk_map: xsk_map.into(),
+            frames: DEFAULT_FRAMES,
+            zero_copy: false,
+            port: 53,
+        }
+    }
+}
+
+/// An AF_XDP socket bound to one receive queue of a network interface
+///
+/// The packets which the XDP program attached to the interface redirects to the socket are
+///  received directly into the UMEM, and the responses are written in place of the requests and
+///  transmitted from there, bypassing the network stack of the kernel.
+pub struct XdpSocket {
+    // the rings are unmapped before the socket is closed
+    fill: Ring<u64>,
+    completion: Ring<u64>,
+    rx: Ring<libc::xdp_desc>,
+    tx: Ring<libc::xdp_desc>,
+    umem: Umem,
+    fd: OwnedFd,
+    port: u16,
+}
+
+// Safety: the mappings are owned by the socket and only accessed through `&mut self`
+unsafe impl Send for XdpSocket {}
+
+impl XdpSocket {
+    /// Creates the socket, binds it to the queue of the interface and inserts it into the XSKMAP
+    ///
+    /// This requires `CAP_NET_RAW` and `CAP_BPF`, or `CAP_SYS_ADMIN` on older kernels.
+    pub fn bind(config: &XdpConfig) -> io::Result<Self> {
+        let frames = config.frames;
+        if !frames.is_power_of_two() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the number of frames must be a power of two",
+            ));
+        }
+
+        let interface = CString::new(config.interface.as_str())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        // Safety: the name is a nul terminated string
+        let ifindex = unsafe { libc::if_nametoindex(interface.as_ptr()) };
+        if ifindex == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // Safety: a new socket is owned by nobody else
+        let fd = unsafe {
+            let fd = libc::socket(libc::AF_XDP, libc::SOCK_RAW | libc::SOCK_CLOEXEC, 0);
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            OwnedFd::from_raw_fd(fd)
+        };
+
+        let umem = Umem::new(frames as usize * FRAME_SIZE)?;
+        let umem_reg = libc::xdp_umem_reg {
+            addr: umem.ptr as u64,
+            len: umem.len as u64,
+            chunk_size: FRAME_SIZE as u32,
+            headroom: 0,
+            flags: 0,
+        };
+        set_option(fd.as_raw_fd(), libc::XDP_UMEM_REG, &umem_reg)?;
+
+        // all frames may be in the fill or completion rings, or in the rx or tx rings, at once
+        for ring in [
+            libc::XDP_UMEM_FILL_RING,
+            libc::XDP_UMEM_COMPLETION_RING,
+            libc::XDP_RX_RING,
+            libc::XDP_TX_RING,
+        ] {
+            set_option(fd.as_raw_fd(), ring, &frames)?;
+        }
+
+        // Safety: all zeroes is a valid value of the plain C struct
+        let mut offsets: libc::xdp_mmap_offsets = unsafe { mem::zeroed() };
+        let mut len = mem::size_of::<libc::xdp_mmap_offsets>() as libc::socklen_t;
+        // Safety: the length is the size of the struct
+        let ret = unsafe {
+            libc::getsockopt(
+                fd.as_raw_fd(),
+                libc::SOL_XDP,
+                libc::XDP_MMAP_OFFSETS,
+                (&mut offsets as *mut libc::xdp_mmap_offsets).cast(),
+                &mut len,
+            )
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let raw_fd = fd.as_raw_fd();
+        let mut fill = Ring::map(
+            raw_fd,
+            &offsets.fr,
+            frames,
+            libc::XDP_UMEM_PGOFF_FILL_RING as libc::off_t,
+        )?;
+        let completion = Ring::map(
+            raw_fd,
+            &offsets.cr,
+            frames,
+            libc::XDP_UMEM_PGOFF_COMPLETION_RING as libc::off_t,
+        )?;
+        let rx = Ring::map(raw_fd, &offsets.rx, frames, libc::XDP_PGOFF_RX_RING)?;
+        let tx = Ring::map(raw_fd, &offsets.tx, frames, libc::XDP_PGOFF_TX_RING)?;
+
+        let mode = if config.zero_copy {
+            libc::XDP_ZEROCOPY
+        } else {
+            libc::XDP_COPY
+        };
+        let addr = libc::sockaddr_xdp {
+            sxdp_family: libc::AF_XDP as u16,
+            sxdp_flags: libc::XDP_USE_NEED_WAKEUP | mode,
+            sxdp_ifindex: ifindex,
+            sxdp_queue_id: config.queue_id,
+            sxdp_shared_umem_fd: 0,
+        };
+        // Safety: the length is the size of the address
+        let ret = unsafe {
+            libc::bind(
+                raw_fd,
+                (&addr as *const libc::sockaddr_xdp).cast(),
+                mem::size_of::<libc::sockaddr_xdp>() as libc::socklen_t,
+            )
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // hand all frames to the kernel for receiving
+        for frame in 0..frames {
+            fill.set(frame, u64::from(frame) * FRAME_SIZE as u64);
+        }
+        fill.produce(frames);
+
+        insert_into_map(&config.xsk_map, config.queue_id, raw_fd)?;
+
+        Ok(Self {
+            fill,
+            completion,
+            rx,
+            tx,
+            umem,
+            fd,
+            port: config.port,
+        })
+    }
+
+    /// Returns the UDP port of the DNS server
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Receives the available packets, waiting up to `timeout` if there are none
+    ///
+    /// `handle` is called with the buffer of each packet and the length of the packet, and returns
+    ///  the length of the response written to the buffer, or `None` to drop the packet.
+    ///
+    /// # Return
+    ///
+    /// The number of packets received
+    pub(super) fn process(
+        &mut self,
+        timeout: Duration,
+        mut handle: impl FnMut(&mut [u8], usize) -> Option<usize>,
+    ) -> io::Result<u32> {
+        self.recycle_completed();
+
+        let mut received = self.rx.consumable();
+        if received == 0 {
+            self.wait(timeout)?;
+            received = self.rx.consumable();
+        }
+
+        let mut transmitted = 0;
+        let mut recycled = 0;
+        for i in 0..received {
+            let desc = self.rx.get(i);
+            let addr = desc.addr as usize;
+            // frames are aligned, the packet starts at an offset within its frame
+            let frame = addr - addr % FRAME_SIZE;
+
+            // Safety: the kernel only hands out addresses within the UMEM, and the frame is owned
+            //  by the socket until it is given back to the kernel
+            let buffer = unsafe {
+                std::slice::from_raw_parts_mut(self.umem.ptr.add(addr), frame + FRAME_SIZE - addr)
+            };
+
+            // the tx ring holds all frames, so there is always room
+            match handle(buffer, desc.len as usize) {
+                Some(len) => {
+                    self.tx.set(
+                        transmitted,
+                        libc::xdp_desc {
+                            addr: desc.addr,
+                            len: len as u32,
+                            options: 0,
+                        },
+                    );
+                    transmitted += 1;
+                }
+                None => {
+                    self.fill.set(recycled, frame as u64);
+                    recycled += 1;
+                }
+            }
+        }
+
+        self.rx.consume(received);
+        self.fill.produce(recycled);
+        if transmitted > 0 {
+            self.tx.produce(transmitted);
+            self.wake_tx()?;
+        }
+
+        Ok(received)
+    }
+
+    /// Gives the frames of transmitted packets back to the kernel for receiving
+    fn recycle_completed(&mut self) {
+        let completed = self.completion.consumable();
+        for i in 0..completed {
+            let addr = self.completion.get(i) as usize;
+            self.fill.set(i, (addr - addr % FRAME_SIZE) as u64);
+        }
+
+        self.completion.consume(completed);
+        self.fill.produce(completed);
+    }
+
+    /// Waits for packets with `poll`, which also lets the kernel refill its receive queue
+    fn wait(&self, timeout: Duration) -> io::Result<()> {
+        let mut fds = libc::pollfd {
+            fd: self.fd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout = timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int;
+
+        // Safety: there is one valid pollfd
+        if unsafe { libc::poll(&mut fds, 1, timeout) } < 0 {
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Lets the kernel transmit the packets of the tx ring, if it is not already busy doing so
+    fn wake_tx(&self) -> io::Result<()> {
+        if !self.tx.needs_wakeup() {
+            return Ok(());
+        }
+
+        // Safety: an empty message, which only kicks the driver
+        let ret = unsafe {
+            libc::sendto(
+                self.fd.as_raw_fd(),
+                ptr::null(),
+                0,
+                libc::MSG_DONTWAIT,
+                ptr::null(),
+                0,
+            )
+        };
+        if ret < 0 {
+            let err = io::Error::last_os_error();
+            // the kernel is still busy with earlier packets, which is retried on the next call
+            match err.raw_os_error() {
+                Some(libc::EAGAIN | libc::EBUSY | libc::ENOBUFS | libc::ENETDOWN) => (),
+                _ => return Err(err),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for XdpSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("XdpSocket")
+            .field("fd", &self.fd)
+            .field("port", &self.port)
+            .finish()
+    }
+}
+
+impl AsRawFd for XdpSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+/// The memory area holding the frames, which is shared with the kernel
+struct Umem {
+    ptr: *mut u8,
+    len: usize,
+}
+
+impl Umem {
+    fn new(len: usize) -> io::Result<Self> {
+        // Safety: a new anonymous mapping
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Self {
+            ptr: ptr.cast(),
+            len,
+        })
+    }
+}
+
+impl Drop for Umem {
+    fn drop(&mut self) {
+        // Safety: the mapping is not referenced anymore
+        unsafe { libc::munmap(self.ptr.cast(), self.len) };
+    }
+}
+
+/// A single producer, single consumer ring shared with the kernel
+///
+/// The fill and tx rings are produced by the socket, the rx and completion rings are consumed.
+///  Only the index of the side owned by the socket is cached, the other one is always loaded.
+struct Ring<T> {
+    map: *mut libc::c_void,
+    map_len: usize,
+    producer: *const AtomicU32,
+    consumer: *const AtomicU32,
+    flags: *const AtomicU32,
+    descs: *mut T,
+    mask: u32,
+    /// The producer index of produced rings, or the consumer index of consumed rings
+    index: u32,
+}
+
+impl<T: Copy> Ring<T> {
+    fn map(
+        fd: RawFd,
+        offsets: &libc::xdp_ring_offset,
+        size: u32,
+        pgoff: libc::off_t,
+    ) -> io::Result<Self> {
+        let map_len = offsets.desc as usize + size as usize * mem::size_of::<T>();
+        // Safety: the kernel allocated the ring with the size set on the socket
+        let map = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                map_len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED | libc::MAP_POPULATE,
+                fd,
+                pgoff,
+            )
+        };
+        if map == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        // Safety: the offsets are within the mapping, as reported by the kernel
+        let (producer, consumer, flags, descs) = unsafe {
+            let base = map.cast::<u8>();
+            (
+                base.add(offsets.producer as usize).cast::<AtomicU32>(),
+                base.add(offsets.consumer as usize).cast::<AtomicU32>(),
+                base.add(offsets.flags as usize).cast::<AtomicU32>(),
+                base.add(offsets.desc as usize).cast::<T>(),
+            )
+        };
+
+        let mut ring = Self {
+            map,
+            map_len,
+            producer,
+            consumer,
+            flags,
+            descs,
+            mask: size - 1,
+            index: 0,
+        };
+        // both indexes start out equal
+        ring.index = ring.producer().load(Ordering::Relaxed);
+        Ok(ring)
+    }
+
+    fn producer(&self) -> &AtomicU32 {
+        // Safety: the pointer is valid for the lifetime of the mapping
+        unsafe { &*self.producer }
+    }
+
+    fn consumer(&self) -> &AtomicU32 {
+        // Safety: the pointer is valid for the lifetime of the mapping
+        unsafe { &*self.consumer }
+    }
+
+    fn needs_wakeup(&self) -> bool {
+        // Safety: the pointer is valid for the lifetime of the mapping
+        unsafe { &*self.flags }.load(Ordering::Relaxed) & libc::XDP_RING_NEED_WAKEUP != 0
+    }
+
+    /// Returns the number of entries produced by the kernel, and not yet consumed
+    fn consumable(&self) -> u32 {
+        self.producer()
+            .load(Ordering::Acquire)
+            .wrapping_sub(self.index)
+    }
+
+    /// Returns the `i`th consumable entry
+    fn get(&self, i: u32) -> T {
+        let slot = (self.index.wrapping_add(i) & self.mask) as usize;
+        // Safety: the slot is within the ring, and owned by the consumer until it is consumed
+        unsafe { ptr::read_volatile(self.descs.add(slot)) }
+    }
+
+    /// Hands the first `count` consumable entries back to the kernel
+    fn consume(&mut self, count: u32) {
+        if count > 0 {
+            self.index = self.index.wrapping_add(count);
+            self.consumer().store(self.index, Ordering::Release);
+        }
+    }
+
+    /// Sets the `i`th entry to be produced
+    fn set(&mut self, i: u32, value: T) {
+        let slot = (self.index.wrapping_add(i) & self.mask) as usize;
+        // Safety: the slot is within the ring, and owned by the producer until it is produced
+        unsafe { ptr::write_volatile(self.descs.add(slot), value) }
+    }
+
+    /// Hands the first `count` entries set to the kernel
+    fn produce(&mut self, count: u32) {
+        if count > 0 {
+            self.index = self.index.wrapping_add(count);
+            self.producer().store(self.index, Ordering::Release);
+        }
+    }
+}
+
+impl<T> Drop for Ring<T> {
+    fn drop(&mut self) {
+        // Safety: the mapping is not referenced anymore
+        unsafe { libc::munmap(self.map, self.map_len) };
+    }
+}
+
+fn set_option<T>(fd: RawFd, name: libc::c_int, value: &T) -> io::Result<()> {
+    // Safety: the length is the size of the value
+    let ret = unsafe {
+        libc::setsockopt(
+            fd,
+            libc::SOL_XDP,
+            name,
+            (value as *const T).cast(),
+            mem::size_of::<T>() as libc::socklen_t,
+        )
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+/// Inserts the socket into the pinned XSKMAP at the index of its queue
+fn insert_into_map(path: &std::path::Path, queue_id: u32, fd: RawFd) -> io::Result<()> {
+    // the prefixes of `union bpf_attr` for the commands, the kernel zero extends shorter attrs
+    #[repr(C)]
+    struct ObjGetAttr {
+        pathname: u64,
+        bpf_fd: u32,
+        file_flags: u32,
+    }
+
+    #[repr(C)]
+    struct MapUpdateAttr {
+        map_fd: u32,
+        _pad: u32,
+        key: u64,
+        value: u64,
+        flags: u64,
+    }
+
+    let path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let attr = ObjGetAttr {
+        pathname: path.as_ptr() as u64,
+        bpf_fd: 0,
+        file_flags: 0,
+    };
+    // Safety: the attr points to the path, which outlives the call, the new fd is owned here
+    let map = unsafe {
+        let map = libc::syscall(
+            libc::SYS_bpf,
+            BPF_OBJ_GET,
+            &attr as *const ObjGetAttr,
+            mem::size_of::<ObjGetAttr>() as libc::c_uint,
+        );
+        if map < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        OwnedFd::from_raw_fd(map as RawFd)
+    };
+
+    let value = fd as u32;
+    let attr = MapUpdateAttr {
+        map_fd: map.as_raw_fd() as u32,
+        _pad: 0,
+        key: &queue_id as *const u32 as u64,
+        value: &value as *const u32 as u64,
+        flags: 0, // BPF_ANY
+    };
+    // Safety: the attr points to the key and value, which outlive the call
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_bpf,
+            BPF_MAP_UPDATE_ELEM,
+            &attr as *const MapUpdateAttr,
+            mem::size_of::<MapUpdateAttr>() as libc::c_uint,
+        )
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
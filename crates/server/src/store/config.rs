@@ -9,9 +9,14 @@
 
 use serde::Deserialize;
 
+#[cfg(feature = "etcd")]
+use crate::store::discovery::EtcdConfig;
 use crate::store::file::FileConfig;
 #[cfg(feature = "hickory-resolver")]
 use crate::store::forwarder::ForwardConfig;
+#[cfg(feature = "geoip")]
+use crate::store::geo::GeoConfig;
+use crate::store::health::HealthConfig;
 #[cfg(feature = "hickory-recursor")]
 use crate::store::recursor::RecursiveConfig;
 #[cfg(feature = "sqlite")]
@@ -37,4 +42,14 @@ pub enum StoreConfig {
     #[cfg(feature = "hickory-recursor")]
     #[cfg_attr(docsrs, doc(cfg(feature = "recursor")))]
     Recursor(RecursiveConfig),
+    /// Services registered in etcd
+    #[cfg(feature = "etcd")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "etcd")))]
+    Etcd(EtcdConfig),
+    /// Zone file with records selected by the location of the client
+    #[cfg(feature = "geoip")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "geoip")))]
+    Geo(GeoConfig),
+    /// Zone file with addresses answered while they are healthy
+    Health(HealthConfig),
 }
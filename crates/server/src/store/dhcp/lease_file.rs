@@ -0,0 +1,434 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    net::IpAddr,
+    path::PathBuf,
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+use serde::Deserialize;
+use time::{Date, Month, PrimitiveDateTime, Time};
+
+use crate::proto::rr::Name;
+
+/// The valid lifetime of an infinite lease in the lease files of Kea
+const KEA_INFINITE_LIFETIME: u64 = u32::MAX as u64;
+
+/// The format of the lease file of a DHCP server
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum LeaseFileFormat {
+    /// The `dhcpd.leases` file of the ISC DHCP server, only its IPv4 leases are read
+    Dhcpd,
+    /// The CSV lease files of the memfile backend of Kea, e.g. `kea-leases4.csv`
+    Kea,
+}
+
+/// A lease file of a DHCP server
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct LeaseFileSource {
+    /// The path of the lease file
+    pub path: PathBuf,
+    /// The format of the lease file
+    pub format: LeaseFileFormat,
+}
+
+/// An active lease of a host with a hostname, read from a lease file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileLease {
+    /// the leased address
+    pub addr: IpAddr,
+    /// the hostname of the client, relative to the zone unless fully qualified
+    pub hostname: Name,
+    /// the time when the lease expires, `None` is an infinite lease
+    pub expires: Option<SystemTime>,
+}
+
+/// Reads the active leases of the hosts with a hostname from the contents of a lease file
+///
+/// The lease files are appended to by the DHCP servers, a later entry of an address replaces
+///  the earlier ones. Leases which already expired are returned as well.
+pub fn parse_leases(src: &str, format: LeaseFileFormat) -> Result<Vec<FileLease>, String> {
+    let leases = match format {
+        LeaseFileFormat::Dhcpd => parse_dhcpd(src)?,
+        LeaseFileFormat::Kea => parse_kea(src)?,
+    };
+
+    Ok(leases.into_values().flatten().collect())
+}
+
+/// The leases of a `dhcpd.leases` file, `None` for addresses which are not leased anymore
+fn parse_dhcpd(src: &str) -> Result<BTreeMap<IpAddr, Option<FileLease>>, String> {
+    let mut leases = BTreeMap::new();
+    let mut current: Option<DhcpdLease> = None;
+
+    for (number, line) in src.lines().enumerate() {
+        let line = match line.find('#') {
+            Some(comment) => &line[..comment],
+            None => line,
+        };
+        let line = line.trim().trim_end_matches(';').trim_end();
+        if line.is_empty() {
+            continue;
+        }
+        let error = |e: String| format!("line {}: {e}", number + 1);
+
+        let Some(lease) = &mut current else {
+            // the IPv6 leases and the other declarations are skipped with their blocks
+            if let Some(addr) = line
+                .strip_prefix("lease ")
+                .and_then(|lease| lease.strip_suffix('{'))
+            {
+                let addr = addr
+                    .trim()
+                    .parse::<IpAddr>()
+                    .map_err(|e| error(format!("bad address: {e}")))?;
+                current = Some(DhcpdLease::new(addr));
+            }
+            continue;
+        };
+
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        match fields.as_slice() {
+            ["}"] => {
+                if let Some(lease) = current.take() {
+                    leases.insert(lease.addr, lease.into_lease());
+                }
+            }
+            ["ends", "never"] => lease.expires = None,
+            ["ends", "epoch", secs, ..] => {
+                let secs = secs
+                    .parse::<u64>()
+                    .map_err(|e| error(format!("bad lease end: {e}")))?;
+                lease.expires = Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+            }
+            ["ends", _weekday, date, time] => {
+                lease.expires = Some(parse_dhcpd_time(date, time).map_err(error)?);
+            }
+            ["binding", "state", state] => lease.active = *state == "active",
+            ["client-hostname", hostname] => lease.hostname = Some(unquote(hostname)),
+            ["set", "ddns-fwd-name", "=", hostname] if lease.hostname.is_none() => {
+                lease.hostname = Some(unquote(hostname))
+            }
+            _ => (),
+        }
+    }
+
+    Ok(leases)
+}
+
+/// A lease of a `dhcpd.leases` file, while it is read
+struct DhcpdLease {
+    addr: IpAddr,
+    hostname: Option<String>,
+    expires: Option<SystemTime>,
+    active: bool,
+}
+
+impl DhcpdLease {
+    fn new(addr: IpAddr) -> Self {
+        Self {
+            addr,
+            hostname: None,
+            expires: None,
+            active: true,
+        }
+    }
+
+    fn into_lease(self) -> Option<FileLease> {
+        if !self.active {
+            return None;
+        }
+
+        Some(FileLease {
+            addr: self.addr,
+            hostname: Name::from_str(&self.hostname?).ok()?,
+            expires: self.expires,
+        })
+    }
+}
+
+fn unquote(value: &str) -> String {
+    value.trim_matches('"').to_string()
+}
+
+/// The time of a lease, in UTC as `2023/10/11 21:00:00`
+fn parse_dhcpd_time(date: &str, time: &str) -> Result<SystemTime, String> {
+    let bad_time = || format!("bad lease end: {date} {time}");
+    let numbers = |value: &str, separator: char| {
+        value
+            .split(separator)
+            .map(|number| number.parse::<u32>().map_err(|_| bad_time()))
+            .collect::<Result<Vec<_>, _>>()
+    };
+
+    let (date, time) = match (&numbers(date, '/')?[..], &numbers(time, ':')?[..]) {
+        (&[year, month, day], &[hour, minute, second]) => {
+            let month = u8::try_from(month)
+                .ok()
+                .and_then(|month| Month::try_from(month).ok())
+                .ok_or_else(bad_time)?;
+            let date =
+                Date::from_calendar_date(year as i32, month, day as u8).map_err(|_| bad_time())?;
+            let time =
+                Time::from_hms(hour as u8, minute as u8, second as u8).map_err(|_| bad_time())?;
+            (date, time)
+        }
+        _ => return Err(bad_time()),
+    };
+
+    let secs = PrimitiveDateTime::new(date, time)
+        .assume_utc()
+        .unix_timestamp();
+    let secs = u64::try_from(secs).map_err(|_| bad_time())?;
+    Ok(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// The leases of a Kea CSV lease file, `None` for addresses which are not leased anymore
+fn parse_kea(src: &str) -> Result<BTreeMap<IpAddr, Option<FileLease>>, String> {
+    let mut leases = BTreeMap::new();
+    let mut lines = src.lines().enumerate();
+
+    // the columns differ between the IPv4 and IPv6 lease files, and between versions of Kea
+    let Some((_, header)) = lines.next() else {
+        return Ok(leases);
+    };
+    let columns = header.split(',').map(str::trim).collect::<Vec<_>>();
+    let column = |name: &str| {
+        columns
+            .iter()
+            .position(|column| *column == name)
+            .ok_or_else(|| format!("missing column {name}"))
+    };
+    let (address, valid_lifetime, expire, hostname) = (
+        column("address")?,
+        column("valid_lifetime")?,
+        column("expire")?,
+        column("hostname")?,
+    );
+    let state = column("state").ok();
+
+    for (number, line) in lines {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let error = |e: String| format!("line {}: {e}", number + 1);
+
+        let fields = line.split(',').map(str::trim).collect::<Vec<_>>();
+        let field = |index: usize| {
+            fields
+                .get(index)
+                .copied()
+                .ok_or_else(|| error(format!("missing field {}", columns[index])))
+        };
+        let number = |index: usize| {
+            field(index)?
+                .parse::<u64>()
+                .map_err(|e| error(format!("bad {}: {e}", columns[index])))
+        };
+
+        let addr = field(address)?
+            .parse::<IpAddr>()
+            .map_err(|e| error(format!("bad address: {e}")))?;
+
+        // only the default state is leased, the others are declined, reclaimed or released
+        let leased = match state {
+            Some(state) => number(state)? == 0,
+            None => true,
+        };
+        let lifetime = number(valid_lifetime)?;
+        let hostname = field(hostname)?;
+        if !leased || lifetime == 0 || hostname.is_empty() {
+            leases.insert(addr, None);
+            continue;
+        }
+
+        let expires = (lifetime != KEA_INFINITE_LIFETIME)
+            .then(|| number(expire))
+            .transpose()?
+            .map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+        let lease = Name::from_str(hostname).ok().map(|hostname| FileLease {
+            addr,
+            hostname,
+            expires,
+        });
+        leases.insert(addr, lease);
+    }
+
+    Ok(leases)
+}
+
+/// The modification time and length of a lease file, `None` if it does not exist
+type Modified = Option<(SystemTime, u64)>;
+
+/// Lease files which are read again when they change
+pub struct LeaseFiles {
+    sources: Vec<LeaseFileSource>,
+    /// the modification times and lengths of the files when they were last read
+    modified: Mutex<Option<Vec<Modified>>>,
+}
+
+impl LeaseFiles {
+    /// Creates the lease files, which are read by the first call to `read_if_changed`
+    pub fn new(sources: Vec<LeaseFileSource>) -> Self {
+        Self {
+            sources,
+            modified: Mutex::new(None),
+        }
+    }
+
+    /// The lease files
+    pub fn sources(&self) -> &[LeaseFileSource] {
+        &self.sources
+    }
+
+    /// Reads the leases of all the files, if any of them changed since they were last read
+    ///
+    /// Files which do not exist have no leases, e.g. before the DHCP server leased any address.
+    pub fn read_if_changed(&self) -> io::Result<Option<Vec<FileLease>>> {
+        let modified = self
+            .sources
+            .iter()
+            .map(|source| match fs::metadata(&source.path) {
+                Ok(metadata) => Ok(Some((metadata.modified()?, metadata.len()))),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e),
+            })
+            .collect::<io::Result<Vec<Modified>>>()?;
+
+        let mut last_modified = self.modified.lock().expect("lease files lock poisoned");
+        if last_modified.as_ref() == Some(&modified) {
+            return Ok(None);
+        }
+
+        let mut leases = Vec::new();
+        for (source, modified) in self.sources.iter().zip(&modified) {
+            if modified.is_none() {
+                continue;
+            }
+
+            let src = fs::read_to_string(&source.path)?;
+            let file_leases = parse_leases(&src, source.format).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {e}", source.path.display()),
+                )
+            })?;
+            leases.extend(file_leases);
+        }
+
+        *last_modified = Some(modified);
+        Ok(Some(leases))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dhcpd() {
+        let src = "# The format of this file is documented in the dhcpd.leases(5) manual page.
+authoring-byte-order little-endian;
+
+lease 192.168.1.23 {
+  starts 3 2023/10/11 09:00:00;
+  ends 3 2023/10/11 21:00:00;
+  binding state active;
+  next binding state free;
+  hardware ethernet 00:11:22:33:44:55;
+  client-hostname \"laptop\";
+}
+lease 192.168.1.24 {
+  ends never;
+  binding state active;
+  set ddns-fwd-name = \"printer.lan.example.com.\";
+}
+lease 192.168.1.25 {
+  ends epoch 1697058000; # Wed Oct 11 21:00:00 2023
+  binding state active;
+  client-hostname \"phone\";
+}
+lease 192.168.1.25 {
+  binding state free;
+  client-hostname \"phone\";
+}
+lease 192.168.1.26 {
+  binding state active;
+}
+";
+
+        let leases = parse_leases(src, LeaseFileFormat::Dhcpd).unwrap();
+        let expires = SystemTime::UNIX_EPOCH + Duration::from_secs(1697058000);
+        assert_eq!(
+            leases,
+            vec![
+                FileLease {
+                    addr: "192.168.1.23".parse().unwrap(),
+                    hostname: Name::from_str("laptop").unwrap(),
+                    expires: Some(expires),
+                },
+                FileLease {
+                    addr: "192.168.1.24".parse().unwrap(),
+                    hostname: Name::from_str("printer.lan.example.com.").unwrap(),
+                    expires: None,
+                },
+            ]
+        );
+
+        assert!(parse_leases("lease laptop {\n}\n", LeaseFileFormat::Dhcpd).is_err());
+        assert!(parse_leases(
+            "lease 192.168.1.23 {\n ends 3 2023/13/11 21:00:00;\n}\n",
+            LeaseFileFormat::Dhcpd
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_kea() {
+        let src = "address,hwaddr,client_id,valid_lifetime,expire,subnet_id,fqdn_fwd,fqdn_rev,hostname,state,user_context
+192.168.1.23,00:11:22:33:44:55,,3600,1697058000,1,0,0,laptop,0,
+192.168.1.24,00:11:22:33:44:56,,4294967295,0,1,0,0,printer.lan.example.com.,0,
+192.168.1.25,00:11:22:33:44:57,,3600,1697058000,1,0,0,phone,0,
+192.168.1.25,00:11:22:33:44:57,,0,1697054400,1,0,0,phone,0,
+192.168.1.26,00:11:22:33:44:58,,3600,1697058000,1,0,0,,0,
+192.168.1.27,00:11:22:33:44:59,,3600,1697058000,1,0,0,tablet,1,
+";
+
+        let leases = parse_leases(src, LeaseFileFormat::Kea).unwrap();
+        assert_eq!(
+            leases,
+            vec![
+                FileLease {
+                    addr: "192.168.1.23".parse().unwrap(),
+                    hostname: Name::from_str("laptop").unwrap(),
+                    expires: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1697058000)),
+                },
+                FileLease {
+                    addr: "192.168.1.24".parse().unwrap(),
+                    hostname: Name::from_str("printer.lan.example.com.").unwrap(),
+                    expires: None,
+                },
+            ]
+        );
+
+        let src = "address,duid,valid_lifetime,expire,subnet_id,pref_lifetime,lease_type,iaid,prefix_len,fqdn_fwd,fqdn_rev,hostname,hwaddr,state
+2001:db8::23,00:01:02:03,3600,1697058000,1,1800,0,1,128,0,0,laptop,,0
+";
+        let leases = parse_leases(src, LeaseFileFormat::Kea).unwrap();
+        assert_eq!(leases[0].addr, "2001:db8::23".parse::<IpAddr>().unwrap());
+
+        assert!(parse_leases("address,expire\n", LeaseFileFormat::Kea).is_err());
+    }
+}
@@ -0,0 +1,464 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+use tokio::sync::Mutex;
+#[cfg(unix)]
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    authority::AuthorityObject,
+    config::dhcp::DhcpConfig,
+    proto::rr::{rdata::PTR, LowerName, Name, RData, Record, RecordType, RrKey},
+    store::{
+        dhcp::{FileLease, LeaseFiles},
+        in_memory::InMemoryAuthority,
+    },
+};
+
+/// A lease event pushed by a DHCP server, see the module documentation for the syntax
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LeaseEvent {
+    /// The address is leased to the host, `None` is an infinite lease
+    Add {
+        /// the leased address
+        addr: IpAddr,
+        /// the hostname of the client, relative to the zone unless fully qualified
+        hostname: Name,
+        /// the time until the lease expires
+        lease_time: Option<Duration>,
+    },
+    /// The lease of the address was released or expired
+    Delete {
+        /// the released address
+        addr: IpAddr,
+    },
+}
+
+impl FromStr for LeaseEvent {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut fields = line.split_whitespace();
+        let action = fields.next().ok_or("empty lease event")?;
+        let addr = fields
+            .next()
+            .ok_or("missing address")?
+            .parse::<IpAddr>()
+            .map_err(|e| format!("bad address: {e}"))?;
+
+        match action {
+            "add" | "old" => {
+                let hostname = fields.next().ok_or("missing hostname")?;
+                let hostname =
+                    Name::from_str(hostname).map_err(|e| format!("bad hostname: {e}"))?;
+                let lease_time = match fields.next().ok_or("missing lease time")? {
+                    "infinite" => None,
+                    secs => Some(Duration::from_secs(
+                        secs.parse::<u64>()
+                            .map_err(|e| format!("bad lease time: {e}"))?,
+                    )),
+                };
+
+                Ok(Self::Add {
+                    addr,
+                    hostname,
+                    lease_time,
+                })
+            }
+            // the hostname of a release is optional, the address identifies the lease
+            "del" => Ok(Self::Delete { addr }),
+            _ => Err(format!("unknown action: {action}")),
+        }
+    }
+}
+
+struct Lease {
+    name: Name,
+    expires: Option<Instant>,
+    /// the lease was read from a lease file, and is replaced when the files are read again
+    from_file: bool,
+}
+
+struct LeasesState {
+    leases: HashMap<IpAddr, Lease>,
+    serial: u32,
+}
+
+/// The forward and reverse zones of the hosts which were leased addresses by a DHCP server
+///
+/// The A and AAAA records of a host are maintained in the zone, and the PTR records of its
+///  addresses in the reverse zone containing them. The records are removed when the lease is
+///  released or expired.
+pub struct DhcpLeases {
+    zone: Arc<InMemoryAuthority>,
+    reverse_zones: Vec<Arc<InMemoryAuthority>>,
+    ttl: u32,
+    state: Mutex<LeasesState>,
+}
+
+impl DhcpLeases {
+    /// Creates the zones, without any leases
+    ///
+    /// # Arguments
+    ///
+    /// * `zone` - the zone of the A and AAAA records of the hosts
+    /// * `reverse_zones` - the zones of the PTR records of the addresses
+    /// * `ttl` - the maximum TTL of the records
+    pub fn new(zone: Name, reverse_zones: Vec<Name>, ttl: u32) -> Self {
+        let serial = InMemoryAuthority::serial_now();
+
+        let zone = Arc::new(InMemoryAuthority::empty_primary(zone, serial, ttl));
+        let reverse_zones = reverse_zones
+            .into_iter()
+            .map(|origin| Arc::new(InMemoryAuthority::empty_primary(origin, serial, ttl)))
+            .collect();
+
+        Self {
+            zone,
+            reverse_zones,
+            ttl,
+            state: Mutex::new(LeasesState {
+                leases: HashMap::new(),
+                serial,
+            }),
+        }
+    }
+
+    /// Creates the zones from the `[dhcp]` section of the configuration
+    pub fn try_from_config(config: &DhcpConfig) -> Result<Self, String> {
+        if config.get_socket_path().is_none() && config.get_lease_files().is_empty() {
+            return Err("neither a socket_path nor lease_files are configured".to_string());
+        }
+
+        let zone = Name::from_str(config.get_zone())
+            .map_err(|e| format!("bad dhcp zone {}: {e}", config.get_zone()))?;
+        let reverse_zones = config
+            .get_reverse_zones()
+            .iter()
+            .map(|zone| Name::from_str(zone).map_err(|e| format!("bad dhcp zone {zone}: {e}")))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self::new(zone, reverse_zones, config.get_ttl()))
+    }
+
+    /// The zone and reverse zones, to be added to the `Catalog`
+    pub fn authorities(&self) -> Vec<(LowerName, Box<dyn AuthorityObject>)> {
+        std::iter::once(&self.zone)
+            .chain(&self.reverse_zones)
+            .map(|zone| {
+                (
+                    zone.origin().clone(),
+                    Box::new(Arc::clone(zone)) as Box<dyn AuthorityObject>,
+                )
+            })
+            .collect()
+    }
+
+    /// Updates the records of the hosts for the event
+    pub async fn apply(&self, event: LeaseEvent) -> Result<(), String> {
+        let now = Instant::now();
+        let mut state = self.state.lock().await;
+
+        let (addr, previous) = match event {
+            LeaseEvent::Add {
+                addr,
+                hostname,
+                lease_time,
+            } => {
+                let name = self.qualify(hostname)?;
+                let lease = Lease {
+                    name,
+                    expires: lease_time.map(|lease_time| now + lease_time),
+                    from_file: false,
+                };
+
+                debug!("dhcp lease of {} to {}", addr, lease.name);
+                (addr, state.leases.insert(addr, lease))
+            }
+            LeaseEvent::Delete { addr } => {
+                debug!("dhcp release of {}", addr);
+                (addr, state.leases.remove(&addr))
+            }
+        };
+
+        let current = state.leases.get(&addr).map(|lease| lease.name.clone());
+        let mut names = previous
+            .map(|lease| lease.name)
+            .into_iter()
+            .collect::<Vec<_>>();
+        names.extend(current);
+        names.dedup();
+
+        self.update(&mut state, &names, &[addr], now).await;
+        Ok(())
+    }
+
+    /// Replaces the leases which were read from the lease files
+    ///
+    /// Leases of hostnames which are not in the zone, and leases which already expired, are
+    ///  skipped. A lease of an address replaces its lease of an event, until the address is
+    ///  leased again.
+    pub async fn replace_file_leases(&self, leases: Vec<FileLease>) {
+        let now = Instant::now();
+        let system_now = SystemTime::now();
+        let mut state = self.state.lock().await;
+
+        let previous = state
+            .leases
+            .iter()
+            .filter(|(_, lease)| lease.from_file)
+            .map(|(addr, _)| *addr)
+            .collect::<Vec<_>>();
+        let mut names = Vec::with_capacity(previous.len() + leases.len());
+        let mut addrs = previous.clone();
+        for addr in &previous {
+            if let Some(lease) = state.leases.remove(addr) {
+                names.push(lease.name);
+            }
+        }
+
+        for lease in leases {
+            let expires = match lease.expires {
+                Some(expires) => match expires.duration_since(system_now) {
+                    Ok(remaining) if !remaining.is_zero() => Some(now + remaining),
+                    _ => continue,
+                },
+                None => None,
+            };
+            let name = match self.qualify(lease.hostname) {
+                Ok(name) => name,
+                Err(e) => {
+                    debug!("skipping dhcp lease of {}: {}", lease.addr, e);
+                    continue;
+                }
+            };
+
+            names.push(name.clone());
+            addrs.push(lease.addr);
+            let file_lease = Lease {
+                name,
+                expires,
+                from_file: true,
+            };
+            if let Some(previous) = state.leases.insert(lease.addr, file_lease) {
+                names.push(previous.name);
+            }
+        }
+        names.sort();
+        names.dedup();
+        addrs.sort();
+        addrs.dedup();
+
+        self.update(&mut state, &names, &addrs, now).await;
+    }
+
+    /// Reads the lease files when they change, at the interval, this never returns
+    ///
+    /// The files are read immediately, and errors reading them are logged.
+    pub async fn watch_lease_files(self: Arc<Self>, files: LeaseFiles, interval: Duration) {
+        let files = Arc::new(files);
+        let mut interval = tokio::time::interval(interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+
+            let read_files = Arc::clone(&files);
+            match tokio::task::spawn_blocking(move || read_files.read_if_changed()).await {
+                Ok(Ok(Some(leases))) => {
+                    info!("read {} dhcp leases from the lease files", leases.len());
+                    self.replace_file_leases(leases).await;
+                }
+                Ok(Ok(None)) => (),
+                Ok(Err(e)) => warn!("could not read dhcp lease files: {}", e),
+                Err(e) => warn!("reading dhcp lease files failed: {}", e),
+            }
+        }
+    }
+
+    /// Removes the records of the expired leases
+    pub async fn remove_expired(&self) {
+        let now = Instant::now();
+        let mut state = self.state.lock().await;
+
+        let expired = state
+            .leases
+            .iter()
+            .filter(|(_, lease)| lease.expires.map_or(false, |expires| expires <= now))
+            .map(|(addr, _)| *addr)
+            .collect::<Vec<_>>();
+        if expired.is_empty() {
+            return;
+        }
+
+        let mut names = Vec::with_capacity(expired.len());
+        for addr in &expired {
+            if let Some(lease) = state.leases.remove(addr) {
+                debug!("dhcp lease of {} to {} expired", addr, lease.name);
+                names.push(lease.name);
+            }
+        }
+        names.sort();
+        names.dedup();
+
+        self.update(&mut state, &names, &expired, now).await;
+    }
+
+    /// Removes the records of the expired leases at the interval, this never returns
+    pub async fn remove_expired_every(self: Arc<Self>, interval: Duration) {
+        loop {
+            tokio::time::sleep(interval).await;
+            self.remove_expired().await;
+        }
+    }
+
+    /// Accepts lease events on the listener, this returns only if accepting fails
+    #[cfg(unix)]
+    #[cfg_attr(docsrs, doc(cfg(unix)))]
+    pub async fn serve(self: Arc<Self>, listener: UnixListener) -> std::io::Result<()> {
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let leases = Arc::clone(&self);
+            tokio::spawn(async move {
+                if let Err(e) = leases.serve_connection(stream).await {
+                    warn!("dhcp lease connection failed: {}", e);
+                }
+            });
+        }
+    }
+
+    #[cfg(unix)]
+    async fn serve_connection(&self, stream: UnixStream) -> std::io::Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let result = match LeaseEvent::from_str(&line) {
+                Ok(event) => self.apply(event).await,
+                Err(e) => Err(e),
+            };
+
+            let reply = match result {
+                Ok(()) => "ok\n".to_string(),
+                Err(e) => {
+                    info!("rejected dhcp lease event {:?}: {}", line, e);
+                    format!("error: {e}\n")
+                }
+            };
+            writer.write_all(reply.as_bytes()).await?;
+        }
+
+        Ok(())
+    }
+
+    /// The name of the host in the zone
+    fn qualify(&self, hostname: Name) -> Result<Name, String> {
+        let zone = Name::from(self.zone.origin());
+        let name = if hostname.is_fqdn() {
+            hostname
+        } else {
+            hostname
+                .append_domain(&zone)
+                .map_err(|e| format!("bad hostname: {e}"))?
+        };
+
+        if !zone.zone_of(&name) || name == zone {
+            return Err(format!("{name} is not in the zone {zone}"));
+        }
+
+        Ok(name.to_lowercase())
+    }
+
+    /// Replaces the A and AAAA records of the names, and the PTR records of the addresses
+    async fn update(
+        &self,
+        state: &mut LeasesState,
+        names: &[Name],
+        addrs: &[IpAddr],
+        now: Instant,
+    ) {
+        state.serial = state.serial.wrapping_add(1);
+        let serial = state.serial;
+
+        for name in names {
+            let lower = LowerName::new(name);
+            {
+                let mut records = self.zone.records_mut().await;
+                records.remove(&RrKey::new(lower.clone(), RecordType::A));
+                records.remove(&RrKey::new(lower, RecordType::AAAA));
+            }
+
+            for (addr, lease) in state.leases.iter().filter(|(_, l)| &l.name == name) {
+                let rdata = match addr {
+                    IpAddr::V4(addr) => RData::A((*addr).into()),
+                    IpAddr::V6(addr) => RData::AAAA((*addr).into()),
+                };
+                let record = Record::from_rdata(name.clone(), self.lease_ttl(lease, now), rdata);
+                self.zone.upsert(record, serial).await;
+            }
+        }
+        self.zone
+            .upsert(
+                InMemoryAuthority::primary_soa(&self.zone.origin().into(), serial, self.ttl),
+                serial,
+            )
+            .await;
+
+        for addr in addrs {
+            let reverse = Name::from(*addr);
+            let Some(zone) = self
+                .reverse_zones
+                .iter()
+                .find(|zone| zone.origin().zone_of(&LowerName::new(&reverse)))
+            else {
+                continue;
+            };
+
+            zone.records_mut()
+                .await
+                .remove(&RrKey::new(LowerName::new(&reverse), RecordType::PTR));
+            if let Some(lease) = state.leases.get(addr) {
+                let ptr = RData::PTR(PTR(lease.name.clone()));
+                let record = Record::from_rdata(reverse, self.lease_ttl(lease, now), ptr);
+                zone.upsert(record, serial).await;
+            }
+            zone.upsert(
+                InMemoryAuthority::primary_soa(&zone.origin().into(), serial, self.ttl),
+                serial,
+            )
+            .await;
+        }
+    }
+
+    /// The TTL of the records of the lease, which is not longer than the remaining lease time
+    fn lease_ttl(&self, lease: &Lease, now: Instant) -> u32 {
+        match lease.expires {
+            Some(expires) => {
+                let remaining = expires.saturating_duration_since(now).as_secs();
+                self.ttl.min(u32::try_from(remaining).unwrap_or(u32::MAX))
+            }
+            None => self.ttl,
+        }
+    }
+}
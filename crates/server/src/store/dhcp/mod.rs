@@ -0,0 +1,32 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Zones maintained from the lease events of a DHCP server
+//!
+//! A DHCP server pushes its lease events to a unix socket, one per line. The events have the form
+//!  of the arguments passed to the `dhcp-script` of dnsmasq:
+//!
+//! ```text
+//! add 192.168.1.23 laptop 3600
+//! old 192.168.1.23 laptop 3600
+//! del 192.168.1.23
+//! ```
+//!
+//! `add` and `old` register the address of the host for the lease time in seconds, which may be
+//!  `infinite`, and `del` releases the address. Each line is answered with `ok`, or with `error: `
+//!  followed by the reason. Hostnames which are not fully qualified are relative to the zone.
+//!
+//! The leases can also be read from the lease files of a DHCP server, the `dhcpd.leases` file of
+//!  the ISC DHCP server or the CSV lease files of Kea, which are read again when they change.
+//!
+//! The records of the zones are not persisted, and the zones are not signed.
+
+mod lease_file;
+mod leases;
+
+pub use self::lease_file::{parse_leases, FileLease, LeaseFileFormat, LeaseFileSource, LeaseFiles};
+pub use self::leases::{DhcpLeases, LeaseEvent};
@@ -0,0 +1,296 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::{collections::HashMap, net::IpAddr, sync::Arc, time::Duration};
+
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+use crate::{
+    authority::{Authority, LookupError, LookupOptions, MessageRequest, UpdateResult, ZoneType},
+    proto::rr::{rdata::SRV, LowerName, Name, RData, Record, RecordType, RrKey},
+    server::RequestInfo,
+    store::{
+        discovery::{Service, ServiceEvent, ServiceWatch},
+        in_memory::InMemoryAuthority,
+    },
+};
+
+/// The registered services, with the names of their records
+struct ServicesState {
+    services: HashMap<Name, Vec<Name>>,
+    serial: u32,
+}
+
+/// A zone of the services registered in a service discovery backend
+///
+/// The A and AAAA records of the endpoints of a service are maintained at its name, and the SRV
+///  records of its named ports at `_<port>._<protocol>.<service>`, as the DNS of a cluster
+///  answers. The zone is updated by the events of a `ServiceWatch`, see `watch`.
+pub struct DiscoveryAuthority {
+    zone: InMemoryAuthority,
+    ttl: u32,
+    state: Mutex<ServicesState>,
+}
+
+impl DiscoveryAuthority {
+    /// Creates the zone, without any services
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - the zone of the records of the services, e.g. `svc.cluster.local.`
+    /// * `ttl` - the TTL of the records
+    pub fn new(origin: Name, ttl: u32) -> Self {
+        let serial = InMemoryAuthority::serial_now();
+        let zone = InMemoryAuthority::empty_primary(origin, serial, ttl);
+
+        Self {
+            zone,
+            ttl,
+            state: Mutex::new(ServicesState {
+                services: HashMap::new(),
+                serial,
+            }),
+        }
+    }
+
+    /// The names of the registered services
+    pub async fn services(&self) -> Vec<Name> {
+        let mut services = self
+            .state
+            .lock()
+            .await
+            .services
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        services.sort();
+        services
+    }
+
+    /// Updates the records of the services for the event
+    pub async fn apply(&self, event: ServiceEvent) -> Result<(), String> {
+        let mut state = self.state.lock().await;
+        state.serial = state.serial.wrapping_add(1);
+        let serial = state.serial;
+
+        match event {
+            ServiceEvent::Reset(services) => {
+                let names = state.services.drain().collect::<Vec<_>>();
+                for (_, records) in names {
+                    self.remove_records(&records).await;
+                }
+
+                for service in services {
+                    if let Err(e) = self.insert(&mut state, service, serial).await {
+                        warn!("skipping service: {}", e);
+                    }
+                }
+            }
+            ServiceEvent::Update(service) => self.insert(&mut state, service, serial).await?,
+            ServiceEvent::Remove(name) => {
+                let name = self.qualify(name)?;
+                debug!("service {} removed", name);
+                if let Some(records) = state.services.remove(&name) {
+                    self.remove_records(&records).await;
+                }
+            }
+        }
+
+        self.zone
+            .upsert(
+                InMemoryAuthority::primary_soa(&self.zone.origin().into(), serial, self.ttl),
+                serial,
+            )
+            .await;
+        Ok(())
+    }
+
+    /// Applies the events of the watch, this never returns
+    ///
+    /// Errors of the watch are logged, and the watch is retried after `retry_interval`.
+    pub async fn watch<W: ServiceWatch>(self: Arc<Self>, mut watch: W, retry_interval: Duration) {
+        loop {
+            match watch.next_event().await {
+                Ok(event) => {
+                    if let ServiceEvent::Reset(services) = &event {
+                        info!(
+                            "watching {} services of zone {}",
+                            services.len(),
+                            self.zone.origin()
+                        );
+                    }
+                    if let Err(e) = self.apply(event).await {
+                        warn!("rejected service event: {}", e);
+                    }
+                }
+                Err(e) => {
+                    warn!("service watch of zone {} failed: {}", self.zone.origin(), e);
+                    tokio::time::sleep(retry_interval).await;
+                }
+            }
+        }
+    }
+
+    /// Replaces the records of the service
+    async fn insert(
+        &self,
+        state: &mut ServicesState,
+        service: Service,
+        serial: u32,
+    ) -> Result<(), String> {
+        let name = self.qualify(service.name)?;
+        debug!("service {} with {} endpoints", name, service.addrs.len());
+        if let Some(records) = state.services.remove(&name) {
+            self.remove_records(&records).await;
+        }
+
+        let mut names = vec![name.clone()];
+        for addr in &service.addrs {
+            let rdata = match addr {
+                IpAddr::V4(addr) => RData::A((*addr).into()),
+                IpAddr::V6(addr) => RData::AAAA((*addr).into()),
+            };
+            self.zone
+                .upsert(Record::from_rdata(name.clone(), self.ttl, rdata), serial)
+                .await;
+        }
+
+        for port in service.ports.iter().filter(|port| !port.name.is_empty()) {
+            let srv_name = Name::from_labels([
+                format!("_{}", port.name).as_bytes(),
+                format!("_{}", port.protocol).as_bytes(),
+            ])
+            .and_then(|srv_name| srv_name.append_domain(&name))
+            .map_err(|e| format!("bad port {} of {name}: {e}", port.name))?
+            .to_lowercase();
+
+            let srv = SRV::new(0, 100, port.port, name.clone());
+            self.zone
+                .upsert(
+                    Record::from_rdata(srv_name.clone(), self.ttl, RData::SRV(srv)),
+                    serial,
+                )
+                .await;
+            names.push(srv_name);
+        }
+
+        names.sort();
+        names.dedup();
+        state.services.insert(name, names);
+        Ok(())
+    }
+
+    /// Removes the A, AAAA and SRV records of the names
+    async fn remove_records(&self, names: &[Name]) {
+        let mut records = self.zone.records_mut().await;
+        for name in names {
+            let name = LowerName::new(name);
+            for record_type in [RecordType::A, RecordType::AAAA, RecordType::SRV] {
+                records.remove(&RrKey::new(name.clone(), record_type));
+            }
+        }
+    }
+
+    /// The name of the service in the zone
+    fn qualify(&self, name: Name) -> Result<Name, String> {
+        let zone = Name::from(self.zone.origin());
+        let name = if name.is_fqdn() {
+            name
+        } else {
+            name.append_domain(&zone)
+                .map_err(|e| format!("bad service name: {e}"))?
+        };
+
+        if !zone.zone_of(&name) || name == zone {
+            return Err(format!("{name} is not in the zone {zone}"));
+        }
+
+        Ok(name.to_lowercase())
+    }
+}
+
+#[async_trait::async_trait]
+impl Authority for DiscoveryAuthority {
+    type Lookup = <InMemoryAuthority as Authority>::Lookup;
+
+    /// What type is this zone
+    fn zone_type(&self) -> ZoneType {
+        self.zone.zone_type()
+    }
+
+    /// Return true if AXFR is allowed
+    fn is_axfr_allowed(&self) -> bool {
+        false
+    }
+
+    /// The services are only registered in the service discovery backend
+    async fn update(&self, _update: &MessageRequest) -> UpdateResult<bool> {
+        use crate::proto::op::ResponseCode;
+        Err(ResponseCode::NotImp)
+    }
+
+    /// Get the origin of this zone
+    fn origin(&self) -> &LowerName {
+        self.zone.origin()
+    }
+
+    /// Looks up all Resource Records matching the giving `Name` and `RecordType`.
+    async fn lookup(
+        &self,
+        name: &LowerName,
+        rtype: RecordType,
+        lookup_options: LookupOptions,
+    ) -> Result<Self::Lookup, LookupError> {
+        self.zone.lookup(name, rtype, lookup_options).await
+    }
+
+    /// Using the specified query, perform a lookup against this zone.
+    async fn search(
+        &self,
+        request_info: RequestInfo<'_>,
+        lookup_options: LookupOptions,
+    ) -> Result<Self::Lookup, LookupError> {
+        self.zone.search(request_info, lookup_options).await
+    }
+
+    /// Get the NS, NameServer, record for the zone
+    async fn ns(&self, lookup_options: LookupOptions) -> Result<Self::Lookup, LookupError> {
+        self.zone.ns(lookup_options).await
+    }
+
+    /// Return the NSEC records based on the given name
+    async fn get_nsec_records(
+        &self,
+        name: &LowerName,
+        lookup_options: LookupOptions,
+    ) -> Result<Self::Lookup, LookupError> {
+        self.zone.get_nsec_records(name, lookup_options).await
+    }
+
+    /// Return the NSEC records proving that there is no closer match for an answer synthesized
+    ///  from a wildcard
+    async fn get_wildcard_proof(
+        &self,
+        next_closer: &LowerName,
+        lookup_options: LookupOptions,
+    ) -> Result<Self::Lookup, LookupError> {
+        self.zone
+            .get_wildcard_proof(next_closer, lookup_options)
+            .await
+    }
+
+    /// Returns the SOA of the authority.
+    async fn soa(&self) -> Result<Self::Lookup, LookupError> {
+        self.zone.soa().await
+    }
+
+    /// Returns the SOA record for the zone
+    async fn soa_secure(&self, lookup_options: LookupOptions) -> Result<Self::Lookup, LookupError> {
+        self.zone.soa_secure(lookup_options).await
+    }
+}
@@ -0,0 +1,46 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::{net::SocketAddr, time::Duration};
+
+use serde::Deserialize;
+
+static DEFAULT_TTL: u32 = 30;
+static DEFAULT_RETRY_INTERVAL: u64 = 5;
+
+/// Configuration of a zone of the services registered in etcd
+///
+/// ```toml
+/// [[zones]]
+/// zone = "svc.cluster.local"
+/// zone_type = "Primary"
+/// stores = { type = "etcd", endpoint = "127.0.0.1:2379", prefix = "/services/" }
+/// ```
+#[derive(Clone, Deserialize, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct EtcdConfig {
+    /// the address of the HTTP endpoint of the etcd server
+    pub endpoint: SocketAddr,
+    /// the prefix of the keys of the services
+    pub prefix: String,
+    /// the TTL of the records, defaults to 30 seconds
+    pub ttl: Option<u32>,
+    /// the time in seconds before a failed watch is retried, defaults to 5 seconds
+    pub retry_interval: Option<u64>,
+}
+
+impl EtcdConfig {
+    /// the TTL of the records
+    pub fn get_ttl(&self) -> u32 {
+        self.ttl.unwrap_or(DEFAULT_TTL)
+    }
+
+    /// the time before a failed watch is retried
+    pub fn get_retry_interval(&self) -> Duration {
+        Duration::from_secs(self.retry_interval.unwrap_or(DEFAULT_RETRY_INTERVAL))
+    }
+}
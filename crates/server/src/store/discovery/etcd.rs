@@ -0,0 +1,480 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::{collections::VecDeque, io, net::IpAddr, net::SocketAddr, str::FromStr};
+
+use data_encoding::BASE64;
+use serde::Deserialize;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+use tracing::debug;
+
+use crate::{
+    proto::rr::Name,
+    store::discovery::{Service, ServiceEvent, ServicePort, ServiceWatch},
+};
+
+/// A watch of the services registered in etcd, with its v3 JSON gateway over HTTP
+///
+/// Each service is a key below the prefix, the path below the prefix is the name of the service
+///  with its labels in reverse order, e.g. `/services/default/web` is `web.default`. The value
+///  is the JSON of the endpoints of the service:
+///
+/// ```json
+/// {"addrs": ["10.0.0.1", "10.0.0.2"], "ports": [{"name": "http", "protocol": "tcp", "port": 80}]}
+/// ```
+pub struct EtcdWatch {
+    endpoint: SocketAddr,
+    prefix: String,
+    /// the events of the last response of the watch, which were not returned yet
+    pending: VecDeque<ServiceEvent>,
+    /// the stream of the watch, `None` until the services were listed
+    stream: Option<HttpBody>,
+}
+
+impl EtcdWatch {
+    /// Creates a watch of the keys below the prefix, on the etcd server at the endpoint
+    pub fn new(endpoint: SocketAddr, prefix: String) -> Self {
+        Self {
+            endpoint,
+            prefix,
+            pending: VecDeque::new(),
+            stream: None,
+        }
+    }
+
+    /// The end of the range of keys with the prefix
+    fn range_end(&self) -> Vec<u8> {
+        let mut end = self.prefix.as_bytes().to_vec();
+        while let Some(last) = end.pop() {
+            if last < u8::MAX {
+                end.push(last + 1);
+                return end;
+            }
+        }
+
+        // all keys
+        vec![0]
+    }
+
+    /// Lists the services, and starts the watch after the revision of the list
+    async fn start(&mut self) -> io::Result<ServiceEvent> {
+        let request = format!(
+            r#"{{"key":"{}","range_end":"{}"}}"#,
+            BASE64.encode(self.prefix.as_bytes()),
+            BASE64.encode(&self.range_end()),
+        );
+        let mut body = HttpBody::post(self.endpoint, "/v3/kv/range", &request).await?;
+        let response = body.read_to_end().await?;
+        let range = serde_json::from_slice::<RangeResponse>(&response).map_err(invalid_data)?;
+
+        let services = range
+            .kvs
+            .iter()
+            .filter_map(|kv| match self.service(kv) {
+                Ok(service) => Some(service),
+                Err(e) => {
+                    debug!("skipping etcd key: {}", e);
+                    None
+                }
+            })
+            .collect();
+
+        let revision = range.header.revision.parse::<i64>().map_err(invalid_data)?;
+        let request = format!(
+            r#"{{"create_request":{{"key":"{}","range_end":"{}","start_revision":"{}"}}}}"#,
+            BASE64.encode(self.prefix.as_bytes()),
+            BASE64.encode(&self.range_end()),
+            revision + 1,
+        );
+        self.stream = Some(HttpBody::post(self.endpoint, "/v3/watch", &request).await?);
+
+        Ok(ServiceEvent::Reset(services))
+    }
+
+    /// The name of the service of the key
+    fn service_name(&self, key: &str) -> Result<Name, String> {
+        let key = BASE64
+            .decode(key.as_bytes())
+            .map_err(|e| format!("bad key: {e}"))?;
+        let key = String::from_utf8(key).map_err(|e| format!("bad key: {e}"))?;
+        let path = key
+            .strip_prefix(&self.prefix)
+            .ok_or_else(|| format!("key {key} is not below the prefix"))?;
+
+        let mut labels = path
+            .split('/')
+            .filter(|label| !label.is_empty())
+            .collect::<Vec<_>>();
+        labels.reverse();
+        Name::from_str(&labels.join(".")).map_err(|e| format!("bad key {key}: {e}"))
+    }
+
+    /// The service of the key and its value
+    fn service(&self, kv: &KeyValue) -> Result<Service, String> {
+        let name = self.service_name(&kv.key)?;
+        let value = BASE64
+            .decode(kv.value.as_deref().unwrap_or_default().as_bytes())
+            .map_err(|e| format!("bad value of {name}: {e}"))?;
+        let value = serde_json::from_slice::<ServiceValue>(&value)
+            .map_err(|e| format!("bad value of {name}: {e}"))?;
+
+        Ok(Service {
+            name,
+            addrs: value.addrs,
+            ports: value.ports,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ServiceWatch for EtcdWatch {
+    async fn next_event(&mut self) -> io::Result<ServiceEvent> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(event);
+            }
+
+            let Some(stream) = &mut self.stream else {
+                return self.start().await;
+            };
+
+            let message = match stream.next_message().await {
+                Ok(Some(message)) => message,
+                // the server closed the watch, it is started again
+                Ok(None) => {
+                    self.stream = None;
+                    continue;
+                }
+                Err(e) => {
+                    self.stream = None;
+                    return Err(e);
+                }
+            };
+
+            let response = serde_json::from_slice::<WatchMessage>(&message).map_err(|e| {
+                self.stream = None;
+                invalid_data(e)
+            })?;
+            let Some(result) = response.result else {
+                self.stream = None;
+                return Err(invalid_data(
+                    response
+                        .error
+                        .unwrap_or_else(|| "empty watch response".to_string()),
+                ));
+            };
+
+            // e.g. the revision was compacted, the services are listed again
+            if result.canceled {
+                self.stream = None;
+                continue;
+            }
+
+            for event in result.events {
+                let event = if event.kind.as_deref() == Some("DELETE") {
+                    self.service_name(&event.kv.key).map(ServiceEvent::Remove)
+                } else {
+                    self.service(&event.kv).map(ServiceEvent::Update)
+                };
+
+                match event {
+                    Ok(event) => self.pending.push_back(event),
+                    Err(e) => debug!("skipping etcd key: {}", e),
+                }
+            }
+        }
+    }
+}
+
+fn invalid_data(e: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+/// The value of the key of a service
+#[derive(Deserialize)]
+struct ServiceValue {
+    #[serde(default)]
+    addrs: Vec<IpAddr>,
+    #[serde(default)]
+    ports: Vec<ServicePort>,
+}
+
+#[derive(Deserialize)]
+struct ResponseHeader {
+    /// the revisions are 64 bit integers, which are strings in JSON
+    revision: String,
+}
+
+#[derive(Deserialize)]
+struct KeyValue {
+    key: String,
+    /// the value is missing for deleted keys
+    value: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RangeResponse {
+    header: ResponseHeader,
+    #[serde(default)]
+    kvs: Vec<KeyValue>,
+}
+
+#[derive(Deserialize)]
+struct WatchMessage {
+    result: Option<WatchResult>,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct WatchResult {
+    #[serde(default)]
+    canceled: bool,
+    #[serde(default)]
+    events: Vec<WatchEvent>,
+}
+
+#[derive(Deserialize)]
+struct WatchEvent {
+    /// `DELETE`, or missing for the default `PUT`
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    kv: KeyValue,
+}
+
+/// The body of the response to an HTTP/1.1 request, of newline delimited messages
+struct HttpBody {
+    reader: BufReader<TcpStream>,
+    chunked: bool,
+    /// the remaining length of a body which is not chunked, `None` until the connection closes
+    remaining: Option<usize>,
+    buffer: Vec<u8>,
+    done: bool,
+}
+
+impl HttpBody {
+    /// Sends the JSON body, and reads the header of the response
+    async fn post(endpoint: SocketAddr, path: &str, body: &str) -> io::Result<Self> {
+        let mut stream = TcpStream::connect(endpoint).await?;
+        let request = format!(
+            "POST {path} HTTP/1.1\r\nHost: {endpoint}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(request.as_bytes()).await?;
+
+        let mut reader = BufReader::new(stream);
+        let mut status = String::new();
+        reader.read_line(&mut status).await?;
+        match status.split_whitespace().nth(1) {
+            Some("200") => (),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!("etcd request {path} failed: {}", status.trim()),
+                ))
+            }
+        }
+
+        let (mut chunked, mut remaining) = (false, None);
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header).await? == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            let header = header.trim();
+            if header.is_empty() {
+                break;
+            }
+
+            let Some((name, value)) = header.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("transfer-encoding") {
+                chunked = value.eq_ignore_ascii_case("chunked");
+            } else if name.eq_ignore_ascii_case("content-length") {
+                remaining = Some(value.parse::<usize>().map_err(invalid_data)?);
+            }
+        }
+
+        Ok(Self {
+            reader,
+            chunked,
+            remaining,
+            buffer: Vec::new(),
+            done: false,
+        })
+    }
+
+    /// The next message of the body, `None` at its end
+    async fn next_message(&mut self) -> io::Result<Option<Vec<u8>>> {
+        loop {
+            if let Some(end) = self.buffer.iter().position(|b| *b == b'\n') {
+                let message = self.buffer.drain(..=end).collect::<Vec<_>>();
+                if message.iter().all(u8::is_ascii_whitespace) {
+                    continue;
+                }
+                return Ok(Some(message));
+            }
+
+            if self.done {
+                if self.buffer.iter().all(u8::is_ascii_whitespace) {
+                    return Ok(None);
+                }
+                return Ok(Some(std::mem::take(&mut self.buffer)));
+            }
+
+            self.read().await?;
+        }
+    }
+
+    /// The remaining body
+    async fn read_to_end(&mut self) -> io::Result<Vec<u8>> {
+        while !self.done {
+            self.read().await?;
+        }
+        Ok(std::mem::take(&mut self.buffer))
+    }
+
+    /// Reads the next chunk of the body into the buffer
+    async fn read(&mut self) -> io::Result<()> {
+        if self.chunked {
+            let mut size = String::new();
+            self.reader.read_line(&mut size).await?;
+            let size = size.trim().split(';').next().unwrap_or_default();
+            let size = usize::from_str_radix(size, 16).map_err(invalid_data)?;
+            if size == 0 {
+                self.done = true;
+                return Ok(());
+            }
+
+            let start = self.buffer.len();
+            self.buffer.resize(start + size + 2, 0);
+            self.reader.read_exact(&mut self.buffer[start..]).await?;
+            // the CRLF after the chunk
+            self.buffer.truncate(start + size);
+            return Ok(());
+        }
+
+        let mut chunk = [0; 4096];
+        let len = match self.remaining {
+            Some(remaining) => chunk.len().min(remaining),
+            None => chunk.len(),
+        };
+        let read = if len == 0 {
+            0
+        } else {
+            self.reader.read(&mut chunk[..len]).await?
+        };
+
+        self.buffer.extend_from_slice(&chunk[..read]);
+        if let Some(remaining) = &mut self.remaining {
+            *remaining -= read;
+        }
+        if read == 0 {
+            self.done = true;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// Answers the requests on the listener with the responses, in chunks
+    async fn serve(listener: TcpListener, responses: Vec<Vec<String>>) {
+        for chunks in responses {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![0; 4096];
+            let _ = stream.read(&mut request).await.unwrap();
+
+            let mut response = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n".to_string();
+            for chunk in chunks {
+                response.push_str(&format!("{:x}\r\n{chunk}\r\n", chunk.len()));
+            }
+            response.push_str("0\r\n\r\n");
+            stream.write_all(response.as_bytes()).await.unwrap();
+        }
+    }
+
+    fn kv(key: &str, value: Option<&str>) -> String {
+        let value = value
+            .map(|value| format!(r#","value":"{}""#, BASE64.encode(value.as_bytes())))
+            .unwrap_or_default();
+        format!(r#"{{"key":"{}"{value}}}"#, BASE64.encode(key.as_bytes()))
+    }
+
+    #[tokio::test]
+    async fn test_etcd_watch() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let endpoint = listener.local_addr().unwrap();
+
+        let web = kv(
+            "/services/default/web",
+            Some(r#"{"addrs":["10.0.0.1"],"ports":[{"name":"http","port":80}]}"#),
+        );
+        let range = format!(r#"{{"header":{{"revision":"7"}},"kvs":[{web}],"count":"1"}}"#);
+        let put = format!(
+            "{{\"result\":{{\"header\":{{}},\"events\":[{{\"kv\":{}}}]}}}}\n",
+            kv("/services/default/db", Some(r#"{"addrs":["10.0.0.2"]}"#))
+        );
+        let delete = format!(
+            "{{\"result\":{{\"events\":[{{\"type\":\"DELETE\",\"kv\":{}}}]}}}}\n",
+            kv("/services/default/web", None)
+        );
+
+        tokio::spawn(serve(
+            listener,
+            vec![
+                vec![range],
+                vec![
+                    "{\"result\":{\"header\":{},\"created\":true}}\n".to_string(),
+                    put,
+                    // a message may be split across chunks
+                    delete[..10].to_string(),
+                    delete[10..].to_string(),
+                ],
+            ],
+        ));
+
+        let mut watch = EtcdWatch::new(endpoint, "/services/".to_string());
+        assert_eq!(
+            watch.next_event().await.unwrap(),
+            ServiceEvent::Reset(vec![Service {
+                name: Name::from_str("web.default").unwrap(),
+                addrs: vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))],
+                ports: vec![ServicePort {
+                    name: "http".to_string(),
+                    protocol: "tcp".to_string(),
+                    port: 80,
+                }],
+            }])
+        );
+        assert_eq!(
+            watch.next_event().await.unwrap(),
+            ServiceEvent::Update(Service {
+                name: Name::from_str("db.default").unwrap(),
+                addrs: vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2))],
+                ports: vec![],
+            })
+        );
+        assert_eq!(
+            watch.next_event().await.unwrap(),
+            ServiceEvent::Remove(Name::from_str("web.default").unwrap())
+        );
+
+        // the watch is started again when the server closes it, which fails without the server
+        assert!(watch.next_event().await.is_err());
+    }
+}
@@ -0,0 +1,30 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Zones of the services registered in a service discovery backend, e.g. the DNS of a cluster
+//!
+//! A `ServiceWatch` streams the changes of the services of the backend, which are applied to a
+//!  `DiscoveryAuthority` as they happen. With the `etcd` feature, `EtcdWatch` watches the services
+//!  registered in etcd.
+//!
+//! The records of the zones are not persisted, and the zones are not signed.
+
+mod authority;
+#[cfg(feature = "etcd")]
+mod config;
+#[cfg(feature = "etcd")]
+mod etcd;
+mod watch;
+
+pub use self::authority::DiscoveryAuthority;
+#[cfg(feature = "etcd")]
+#[cfg_attr(docsrs, doc(cfg(feature = "etcd")))]
+pub use self::config::EtcdConfig;
+#[cfg(feature = "etcd")]
+#[cfg_attr(docsrs, doc(cfg(feature = "etcd")))]
+pub use self::etcd::EtcdWatch;
+pub use self::watch::{Service, ServiceEvent, ServicePort, ServiceWatch};
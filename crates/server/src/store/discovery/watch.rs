@@ -0,0 +1,68 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::{io, net::IpAddr};
+
+use serde::Deserialize;
+
+use crate::proto::rr::Name;
+
+/// A service registered in a service discovery backend
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Service {
+    /// the name of the service, relative to the zone unless fully qualified
+    pub name: Name,
+    /// the addresses of the endpoints of the service
+    pub addrs: Vec<IpAddr>,
+    /// the ports of the service, which have SRV records if they are named
+    pub ports: Vec<ServicePort>,
+}
+
+/// A port of a service, e.g. `http` on TCP port 80
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ServicePort {
+    /// the name of the port, the SRV record is `_<name>._<protocol>.<service>`
+    #[serde(default)]
+    pub name: String,
+    /// the transport protocol of the port, defaults to `tcp`
+    #[serde(default = "ServicePort::default_protocol")]
+    pub protocol: String,
+    /// the port number
+    pub port: u16,
+}
+
+impl ServicePort {
+    fn default_protocol() -> String {
+        "tcp".to_string()
+    }
+}
+
+/// A change of the services of a service discovery backend
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ServiceEvent {
+    /// All the services, which replace the current ones, e.g. after the watch was (re)started
+    Reset(Vec<Service>),
+    /// The service was registered, or its endpoints changed
+    Update(Service),
+    /// The service with the name was removed
+    Remove(Name),
+}
+
+/// A watch of the services registered in a service discovery backend, e.g. etcd or the API of a
+///  cluster
+///
+/// The first event of a watch, and the first event after it failed, is expected to be a
+///  `ServiceEvent::Reset` with all the services.
+#[async_trait::async_trait]
+pub trait ServiceWatch: Send {
+    /// Waits for the next change of the services
+    ///
+    /// After an error the watch is retried with another call, which should start it again.
+    async fn next_event(&mut self) -> io::Result<ServiceEvent>;
+}
@@ -23,7 +23,7 @@ use crate::{
 };
 use crate::{
     authority::{Authority, LookupError, LookupOptions, MessageRequest, UpdateResult, ZoneType},
-    proto::rr::{LowerName, Name, RecordSet, RecordType, RrKey},
+    proto::rr::{LowerName, Name, Record, RecordSet, RecordType, RrKey},
     proto::serialize::txt::Parser,
     server::RequestInfo,
     store::{file::FileConfig, in_memory::InMemoryAuthority},
@@ -179,6 +179,16 @@ impl Authority for FileAuthority {
         self.0.search(request_info, lookup_options).await
     }
 
+    /// Looks up the answer of a simple query without awaiting, see `Authority::lookup_now`
+    fn lookup_now(
+        &self,
+        name: &LowerName,
+        rtype: RecordType,
+        lookup_options: LookupOptions,
+    ) -> Option<Vec<Record>> {
+        self.0.lookup_now(name, rtype, lookup_options)
+    }
+
     /// Get the NS, NameServer, record for the zone
     async fn ns(&self, lookup_options: LookupOptions) -> Result<Self::Lookup, LookupError> {
         self.0.ns(lookup_options).await
@@ -199,6 +209,16 @@ impl Authority for FileAuthority {
         self.0.get_nsec_records(name, lookup_options).await
     }
 
+    /// Return the NSEC records proving that there is no closer match for an answer synthesized
+    ///  from a wildcard
+    async fn get_wildcard_proof(
+        &self,
+        next_closer: &LowerName,
+        lookup_options: LookupOptions,
+    ) -> Result<Self::Lookup, LookupError> {
+        self.0.get_wildcard_proof(next_closer, lookup_options).await
+    }
+
     /// Returns the SOA of the authority.
     ///
     /// *Note*: This will only return the SOA, if this is fulfilling a request, a standard lookup
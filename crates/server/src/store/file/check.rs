@@ -0,0 +1,1157 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Consistency checks of zones, like BIND's `named-checkzone`
+
+use std::collections::BTreeMap;
+#[cfg(feature = "dnssec")]
+use std::collections::BTreeSet;
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Serialize, Serializer};
+
+use crate::proto::{
+    rr::{LowerName, Name, RData, RecordSet, RecordType, RrKey},
+    serialize::txt::Parser,
+};
+
+/// The severity of a diagnostic, errors prevent the zone from being served correctly
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// A remark which does not need to be acted upon
+    Info,
+    /// A likely mistake, the zone can still be served
+    Warning,
+    /// The zone is broken, or will be rejected by resolvers
+    Error,
+}
+
+impl Severity {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "info" => Ok(Self::Info),
+            "warning" => Ok(Self::Warning),
+            "error" => Ok(Self::Error),
+            _ => Err(format!("unknown severity: {s}")),
+        }
+    }
+}
+
+/// The check which reported a diagnostic
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Check {
+    /// The zone file can not be read or parsed
+    Syntax,
+    /// A record is not within the zone
+    OutOfZone,
+    /// The SOA record is missing, misplaced or has inconsistent timers
+    Soa,
+    /// The apex has no NS records, or too few of them
+    ApexNs,
+    /// A name has a CNAME record and other data, or several CNAME records
+    CnameAndOtherData,
+    /// A name server within the zone has no address records
+    MissingGlue,
+    /// A CNAME, MX or SRV target does not exist in the zone, or is an alias
+    DanglingTarget,
+    /// Records below a delegation, other than glue, are never served
+    OccludedData,
+    /// DS records outside of a delegation point
+    MisplacedDs,
+    /// An RRset is not signed by a key of the zone, or its signatures are not valid now
+    Signatures,
+    /// The NSEC or NSEC3 records do not cover every name of the zone
+    DenialOfExistence,
+}
+
+impl Check {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Syntax => "syntax",
+            Self::OutOfZone => "out-of-zone",
+            Self::Soa => "soa",
+            Self::ApexNs => "apex-ns",
+            Self::CnameAndOtherData => "cname-and-other-data",
+            Self::MissingGlue => "missing-glue",
+            Self::DanglingTarget => "dangling-target",
+            Self::OccludedData => "occluded-data",
+            Self::MisplacedDs => "misplaced-ds",
+            Self::Signatures => "signatures",
+            Self::DenialOfExistence => "denial-of-existence",
+        }
+    }
+}
+
+impl Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A problem found in a zone
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// How serious the problem is
+    pub severity: Severity,
+    /// The check which found the problem
+    pub check: Check,
+    /// The name of the records with the problem, if it concerns specific records
+    #[serde(serialize_with = "serialize_display")]
+    pub name: Option<Name>,
+    /// The type of the records with the problem, if it concerns specific records
+    #[serde(serialize_with = "serialize_display")]
+    pub record_type: Option<RecordType>,
+    /// A description of the problem
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn new(severity: Severity, check: Check, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            check,
+            name: None,
+            record_type: None,
+            message: message.into(),
+        }
+    }
+
+    fn at(mut self, name: impl Into<Name>, record_type: Option<RecordType>) -> Self {
+        self.name = Some(name.into());
+        self.record_type = record_type;
+        self
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: ", self.severity, self.check)?;
+        if let Some(name) = &self.name {
+            write!(f, "{name} ")?;
+        }
+        if let Some(record_type) = &self.record_type {
+            write!(f, "{record_type} ")?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+fn serialize_display<S: Serializer, T: Display>(
+    value: &Option<T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(value) => serializer.collect_str(value),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Reads the zone file and checks the zone, see `check_records`
+///
+/// A zone file which can not be read or parsed is reported as a single `Check::Syntax` error.
+pub fn check_zone(origin: &Name, zone_path: &Path) -> Vec<Diagnostic> {
+    let buf = match fs::read_to_string(zone_path) {
+        Ok(buf) => buf,
+        Err(e) => {
+            return vec![Diagnostic::new(
+                Severity::Error,
+                Check::Syntax,
+                format!("failed to read {}: {e}", zone_path.display()),
+            )]
+        }
+    };
+
+    match Parser::new(buf, Some(zone_path.to_owned()), Some(origin.clone())).parse() {
+        Ok((origin, records)) => check_records(&origin, &records),
+        Err(e) => vec![Diagnostic::new(
+            Severity::Error,
+            Check::Syntax,
+            format!("failed to parse {}: {e}", zone_path.display()),
+        )],
+    }
+}
+
+/// Checks the records of the zone
+///
+/// The checks cover the SOA and the NS records of the apex, CNAME records with other data,
+///  the glue of the delegations, the targets of the CNAME, MX and SRV records within the zone, and
+///  for signed zones the signatures and the NSEC or NSEC3 chain.
+///
+/// # Return
+///
+/// The problems found, an empty list if the zone is consistent
+pub fn check_records(origin: &Name, records: &BTreeMap<RrKey, RecordSet>) -> Vec<Diagnostic> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs() as u32);
+
+    ZoneChecker::new(origin, records, now).check()
+}
+
+/// The records of the zone, grouped by name
+struct ZoneChecker<'r> {
+    origin: LowerName,
+    nodes: BTreeMap<LowerName, BTreeMap<RecordType, &'r RecordSet>>,
+    delegations: Vec<LowerName>,
+    #[cfg_attr(not(feature = "dnssec"), allow(dead_code))]
+    now: u32,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'r> ZoneChecker<'r> {
+    fn new(origin: &Name, records: &'r BTreeMap<RrKey, RecordSet>, now: u32) -> Self {
+        let origin = LowerName::from(origin);
+        let mut diagnostics = Vec::new();
+        let mut nodes = BTreeMap::<_, BTreeMap<_, _>>::new();
+
+        for (key, set) in records {
+            if !origin.zone_of(key.name()) {
+                diagnostics.push(
+                    Diagnostic::new(
+                        Severity::Error,
+                        Check::OutOfZone,
+                        format!("is not in the zone {origin}"),
+                    )
+                    .at(key.name(), Some(key.record_type)),
+                );
+                continue;
+            }
+
+            nodes
+                .entry(key.name().clone())
+                .or_default()
+                .insert(key.record_type, set);
+        }
+
+        let delegations = nodes
+            .iter()
+            .filter(|(name, types)| **name != origin && types.contains_key(&RecordType::NS))
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        Self {
+            origin,
+            nodes,
+            delegations,
+            now,
+            diagnostics,
+        }
+    }
+
+    fn check(mut self) -> Vec<Diagnostic> {
+        self.check_soa();
+        self.check_apex_ns();
+        self.check_cnames();
+        self.check_glue();
+        self.check_targets();
+        self.check_occluded();
+        self.check_ds();
+        #[cfg(feature = "dnssec")]
+        {
+            self.check_signatures();
+            self.check_nsec();
+            #[cfg(any(feature = "dnssec-openssl", feature = "dnssec-ring"))]
+            self.check_nsec3();
+        }
+
+        self.diagnostics
+    }
+
+    fn report(
+        &mut self,
+        severity: Severity,
+        check: Check,
+        name: &LowerName,
+        record_type: Option<RecordType>,
+        message: impl Into<String>,
+    ) {
+        self.diagnostics
+            .push(Diagnostic::new(severity, check, message).at(name, record_type));
+    }
+
+    /// The delegation above the name, if it is below a delegation
+    fn delegation_of(&self, name: &LowerName) -> Option<&LowerName> {
+        self.delegations
+            .iter()
+            .find(|delegation| *delegation != name && delegation.zone_of(name))
+    }
+
+    /// The records of the type at the name
+    fn records(
+        &self,
+        name: &LowerName,
+        record_type: RecordType,
+    ) -> impl Iterator<Item = &'r RData> {
+        let set = self
+            .nodes
+            .get(name)
+            .and_then(|types| types.get(&record_type))
+            .copied();
+
+        set.into_iter()
+            .flat_map(|set| set.records_without_rrsigs())
+            .filter_map(|record| record.data())
+    }
+
+    fn has_address(&self, name: &LowerName) -> bool {
+        self.nodes.get(name).map_or(false, |types| {
+            types.contains_key(&RecordType::A) || types.contains_key(&RecordType::AAAA)
+        })
+    }
+
+    fn check_soa(&mut self) {
+        let misplaced = self
+            .nodes
+            .iter()
+            .filter(|(name, types)| **name != self.origin && types.contains_key(&RecordType::SOA))
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        for name in misplaced {
+            self.report(
+                Severity::Error,
+                Check::Soa,
+                &name,
+                Some(RecordType::SOA),
+                "is not at the apex of the zone",
+            );
+        }
+
+        let origin = self.origin.clone();
+        let Some(RData::SOA(soa)) = self.records(&origin, RecordType::SOA).next() else {
+            self.report(
+                Severity::Error,
+                Check::Soa,
+                &origin,
+                None,
+                "the zone has no SOA record at its apex",
+            );
+            return;
+        };
+
+        let (refresh, retry, expire) = (soa.refresh(), soa.retry(), soa.expire());
+        if retry >= refresh {
+            self.report(
+                Severity::Warning,
+                Check::Soa,
+                &origin,
+                Some(RecordType::SOA),
+                format!("retry {retry} is not less than refresh {refresh}"),
+            );
+        }
+        if i64::from(expire) < i64::from(refresh) + i64::from(retry) {
+            self.report(
+                Severity::Warning,
+                Check::Soa,
+                &origin,
+                Some(RecordType::SOA),
+                format!("expire {expire} is less than refresh {refresh} plus retry {retry}"),
+            );
+        }
+        if soa.minimum() > 86400 {
+            self.report(
+                Severity::Warning,
+                Check::Soa,
+                &origin,
+                Some(RecordType::SOA),
+                format!(
+                    "negative caching TTL {} is more than a day, see RFC 2308",
+                    soa.minimum()
+                ),
+            );
+        }
+
+        let mname = soa.mname().clone();
+        let is_name_server = self
+            .records(&origin, RecordType::NS)
+            .any(|data| matches!(data, RData::NS(ns) if ns.0 == mname));
+        if !is_name_server {
+            self.report(
+                Severity::Info,
+                Check::Soa,
+                &origin,
+                Some(RecordType::SOA),
+                format!("primary name server {mname} is not an NS of the zone"),
+            );
+        }
+    }
+
+    fn check_apex_ns(&mut self) {
+        let origin = self.origin.clone();
+        match self.records(&origin, RecordType::NS).count() {
+            0 => self.report(
+                Severity::Error,
+                Check::ApexNs,
+                &origin,
+                Some(RecordType::NS),
+                "the zone has no NS records at its apex",
+            ),
+            1 => self.report(
+                Severity::Warning,
+                Check::ApexNs,
+                &origin,
+                Some(RecordType::NS),
+                "the zone has a single name server, RFC 1034 requires at least two",
+            ),
+            _ => (),
+        }
+    }
+
+    fn check_cnames(&mut self) {
+        let mut problems = Vec::new();
+        for (name, types) in &self.nodes {
+            let Some(cnames) = types.get(&RecordType::CNAME) else {
+                continue;
+            };
+
+            let count = cnames.records_without_rrsigs().count();
+            if count > 1 {
+                problems.push((name.clone(), format!("has {count} CNAME records")));
+            }
+
+            // RFC 4035 allows the DNSSEC records next to a CNAME
+            let others = types
+                .keys()
+                .filter(|ty| {
+                    !matches!(ty, RecordType::CNAME | RecordType::RRSIG | RecordType::NSEC)
+                })
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+            if !others.is_empty() {
+                problems.push((
+                    name.clone(),
+                    format!("has a CNAME record and {} records", others.join(", ")),
+                ));
+            }
+        }
+
+        for (name, message) in problems {
+            self.report(
+                Severity::Error,
+                Check::CnameAndOtherData,
+                &name,
+                Some(RecordType::CNAME),
+                message,
+            );
+        }
+    }
+
+    /// The name servers within the zone must have address records, those below a delegation are
+    ///  the glue of the delegation
+    fn check_glue(&mut self) {
+        let mut problems = Vec::new();
+        for (name, types) in &self.nodes {
+            if *name != self.origin && !self.delegations.contains(name) {
+                continue;
+            }
+            let Some(name_servers) = types.get(&RecordType::NS) else {
+                continue;
+            };
+
+            for data in name_servers
+                .records_without_rrsigs()
+                .filter_map(|r| r.data())
+            {
+                let RData::NS(target) = data else {
+                    continue;
+                };
+                let target = LowerName::from(&target.0);
+                if !self.origin.zone_of(&target) || self.has_address(&target) {
+                    continue;
+                }
+
+                let kind = match name.zone_of(&target) && *name != self.origin {
+                    true => "glue",
+                    false => "address records",
+                };
+                problems.push((
+                    name.clone(),
+                    format!("name server {target} has no {kind}, it needs A or AAAA records"),
+                ));
+            }
+        }
+
+        for (name, message) in problems {
+            self.report(
+                Severity::Error,
+                Check::MissingGlue,
+                &name,
+                Some(RecordType::NS),
+                message,
+            );
+        }
+    }
+
+    /// The targets within the zone must exist, and the targets of NS, MX and SRV records must not
+    ///  be aliases, see RFC 2181 section 10.3
+    fn check_targets(&mut self) {
+        let mut problems = Vec::new();
+        for (name, types) in &self.nodes {
+            if self.delegation_of(name).is_some() {
+                continue;
+            }
+
+            for (record_type, set) in types {
+                for data in set.records_without_rrsigs().filter_map(|r| r.data()) {
+                    let target = match data {
+                        RData::CNAME(cname) => &cname.0,
+                        RData::NS(ns) => &ns.0,
+                        RData::MX(mx) => mx.exchange(),
+                        RData::SRV(srv) => srv.target(),
+                        _ => continue,
+                    };
+                    // `.` is the null MX of RFC 7505, and the unavailable service of RFC 2782
+                    if target.is_root() {
+                        continue;
+                    }
+
+                    let target = LowerName::from(target);
+                    if !self.origin.zone_of(&target) || self.delegation_of(&target).is_some() {
+                        continue;
+                    }
+
+                    match self.nodes.get(&target) {
+                        None if *record_type != RecordType::NS => problems.push((
+                            Severity::Warning,
+                            name.clone(),
+                            *record_type,
+                            format!("target {target} does not exist in the zone"),
+                        )),
+                        Some(target_types)
+                            if *record_type != RecordType::CNAME
+                                && target_types.contains_key(&RecordType::CNAME) =>
+                        {
+                            problems.push((
+                                Severity::Error,
+                                name.clone(),
+                                *record_type,
+                                format!("target {target} is an alias, see RFC 2181"),
+                            ))
+                        }
+                        _ => (),
+                    }
+                }
+            }
+        }
+
+        for (severity, name, record_type, message) in problems {
+            self.report(
+                severity,
+                Check::DanglingTarget,
+                &name,
+                Some(record_type),
+                message,
+            );
+        }
+    }
+
+    fn check_occluded(&mut self) {
+        let mut problems = Vec::new();
+        for (name, types) in &self.nodes {
+            let Some(delegation) = self.delegation_of(name) else {
+                continue;
+            };
+
+            for record_type in types.keys() {
+                if matches!(record_type, RecordType::A | RecordType::AAAA) {
+                    continue;
+                }
+                problems.push((
+                    name.clone(),
+                    *record_type,
+                    format!("is below the delegation {delegation} and is never served"),
+                ));
+            }
+        }
+
+        for (name, record_type, message) in problems {
+            self.report(
+                Severity::Warning,
+                Check::OccludedData,
+                &name,
+                Some(record_type),
+                message,
+            );
+        }
+    }
+
+    fn check_ds(&mut self) {
+        let misplaced = self
+            .nodes
+            .iter()
+            .filter(|(name, types)| {
+                types.contains_key(&RecordType::DS) && !self.delegations.contains(name)
+            })
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+
+        for name in misplaced {
+            self.report(
+                Severity::Error,
+                Check::MisplacedDs,
+                &name,
+                Some(RecordType::DS),
+                "DS records are only allowed at delegation points",
+            );
+        }
+    }
+
+    /// The names the zone is authoritative for, the delegation points included
+    #[cfg(feature = "dnssec")]
+    fn authoritative_names(&self) -> Vec<LowerName> {
+        self.nodes
+            .keys()
+            .filter(|name| self.delegation_of(name).is_none())
+            .cloned()
+            .collect()
+    }
+
+    /// Each authoritative RRset must be signed by a key of the zone, with a valid signature
+    #[cfg(feature = "dnssec")]
+    fn check_signatures(&mut self) {
+        use crate::proto::rr::dnssec::rdata::DNSSECRData;
+
+        let origin = self.origin.clone();
+        let keys = self
+            .records(&origin, RecordType::DNSKEY)
+            .filter_map(|data| match data {
+                RData::DNSSEC(DNSSECRData::DNSKEY(key)) if key.zone_key() && !key.revoke() => {
+                    key.calculate_key_tag().ok()
+                }
+                _ => None,
+            })
+            .collect::<BTreeSet<_>>();
+
+        // the signatures are in their own RRsets when parsed, or attached to the RRsets they cover
+        let mut signatures = BTreeMap::<(LowerName, RecordType), Vec<_>>::new();
+        for (name, types) in &self.nodes {
+            let attached = types.values().flat_map(|set| set.rrsigs());
+            let separate = types
+                .get(&RecordType::RRSIG)
+                .into_iter()
+                .flat_map(|set| set.records_without_rrsigs());
+            for data in attached.chain(separate).filter_map(|r| r.data()) {
+                if let RData::DNSSEC(DNSSECRData::RRSIG(rrsig)) = data {
+                    signatures
+                        .entry((name.clone(), rrsig.type_covered()))
+                        .or_default()
+                        .push(rrsig);
+                }
+            }
+        }
+
+        if keys.is_empty() {
+            if !signatures.is_empty() {
+                self.report(
+                    Severity::Error,
+                    Check::Signatures,
+                    &origin,
+                    Some(RecordType::DNSKEY),
+                    "the zone has RRSIG records but no zone key at its apex",
+                );
+            }
+            return;
+        }
+
+        let mut problems = Vec::new();
+        for name in self.authoritative_names() {
+            let is_delegation = self.delegations.contains(&name);
+            for record_type in self.nodes[&name].keys() {
+                // the delegations only sign the records the parent is authoritative for
+                let signed = match record_type {
+                    RecordType::RRSIG => false,
+                    RecordType::DS | RecordType::NSEC => true,
+                    _ => !is_delegation,
+                };
+                if !signed {
+                    continue;
+                }
+
+                let Some(rrsigs) = signatures.get(&(name.clone(), *record_type)) else {
+                    problems.push((
+                        Severity::Error,
+                        name.clone(),
+                        *record_type,
+                        "is not signed".to_string(),
+                    ));
+                    continue;
+                };
+
+                for rrsig in rrsigs {
+                    let tag = rrsig.key_tag();
+                    if LowerName::from(rrsig.signer_name()) != origin {
+                        problems.push((
+                            Severity::Error,
+                            name.clone(),
+                            *record_type,
+                            format!(
+                                "signature {tag} has the signer {}, not the zone",
+                                rrsig.signer_name()
+                            ),
+                        ));
+                    }
+                    if !keys.contains(&tag) {
+                        problems.push((
+                            Severity::Warning,
+                            name.clone(),
+                            *record_type,
+                            format!("signature {tag} is not made by a key of the zone"),
+                        ));
+                    }
+                    if rrsig.sig_expiration() < self.now {
+                        problems.push((
+                            Severity::Error,
+                            name.clone(),
+                            *record_type,
+                            format!("signature {tag} expired at {}", rrsig.sig_expiration()),
+                        ));
+                    }
+                    if rrsig.sig_inception() > self.now {
+                        problems.push((
+                            Severity::Warning,
+                            name.clone(),
+                            *record_type,
+                            format!(
+                                "signature {tag} is not valid before {}",
+                                rrsig.sig_inception()
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+
+        for (severity, name, record_type, message) in problems {
+            self.report(
+                severity,
+                Check::Signatures,
+                &name,
+                Some(record_type),
+                message,
+            );
+        }
+    }
+
+    /// Every authoritative name has an NSEC record with its types, and the chain of the NSEC
+    ///  records goes through all of them
+    #[cfg(feature = "dnssec")]
+    fn check_nsec(&mut self) {
+        use crate::proto::rr::dnssec::rdata::DNSSECRData;
+
+        let mut chain = BTreeMap::new();
+        for (name, types) in &self.nodes {
+            if let Some(set) = types.get(&RecordType::NSEC) {
+                for data in set.records_without_rrsigs().filter_map(|r| r.data()) {
+                    if let RData::DNSSEC(DNSSECRData::NSEC(nsec)) = data {
+                        chain.insert(name.clone(), nsec);
+                    }
+                }
+            }
+        }
+        if chain.is_empty() {
+            return;
+        }
+
+        let mut problems = Vec::new();
+        let names = self.authoritative_names();
+        for name in &names {
+            let Some(nsec) = chain.get(name) else {
+                problems.push((name.clone(), "has no NSEC record".to_string()));
+                continue;
+            };
+
+            let mut expected = self.nodes[name].keys().copied().collect::<BTreeSet<_>>();
+            expected.insert(RecordType::NSEC);
+            expected.insert(RecordType::RRSIG);
+            if self.delegations.contains(name) {
+                expected.retain(|ty| {
+                    matches!(
+                        ty,
+                        RecordType::NS | RecordType::DS | RecordType::NSEC | RecordType::RRSIG
+                    )
+                });
+            }
+            let listed = nsec
+                .type_bit_maps()
+                .iter()
+                .copied()
+                .collect::<BTreeSet<_>>();
+            if listed != expected {
+                let types = |types: &BTreeSet<RecordType>| {
+                    types
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                };
+                problems.push((
+                    name.clone(),
+                    format!(
+                        "NSEC lists the types {}, the name has {}",
+                        types(&listed),
+                        types(&expected)
+                    ),
+                ));
+            }
+        }
+
+        let mut current = self.origin.clone();
+        let mut visited = 0;
+        while visited < chain.len() {
+            let Some(nsec) = chain.get(&current) else {
+                problems.push((
+                    current.clone(),
+                    "the NSEC chain is broken, the name has no NSEC record".to_string(),
+                ));
+                break;
+            };
+            visited += 1;
+            current = LowerName::from(nsec.next_domain_name());
+            if current == self.origin {
+                break;
+            }
+        }
+        if visited < names.len() {
+            problems.push((
+                self.origin.clone(),
+                format!(
+                    "the NSEC chain from the apex covers {visited} of the {} names",
+                    names.len()
+                ),
+            ));
+        }
+
+        for (name, message) in problems {
+            self.report(
+                Severity::Error,
+                Check::DenialOfExistence,
+                &name,
+                Some(RecordType::NSEC),
+                message,
+            );
+        }
+    }
+
+    /// With an NSEC3PARAM record, every authoritative name and empty non-terminal has an NSEC3
+    ///  record, except the insecure delegations of an opt-out chain
+    #[cfg(any(feature = "dnssec-openssl", feature = "dnssec-ring"))]
+    fn check_nsec3(&mut self) {
+        use crate::proto::rr::dnssec::rdata::DNSSECRData;
+        use data_encoding::BASE32_DNSSEC;
+
+        let origin = self.origin.clone();
+        let Some(RData::DNSSEC(DNSSECRData::NSEC3PARAM(param))) =
+            self.records(&origin, RecordType::NSEC3PARAM).next()
+        else {
+            return;
+        };
+
+        let hashed = self
+            .nodes
+            .iter()
+            .filter(|(_, types)| types.contains_key(&RecordType::NSEC3))
+            .map(|(name, _)| name.clone())
+            .collect::<BTreeSet<_>>();
+        let opt_out = self
+            .nodes
+            .values()
+            .filter_map(|types| types.get(&RecordType::NSEC3))
+            .flat_map(|set| set.records_without_rrsigs())
+            .any(|record| {
+                matches!(record.data(), Some(RData::DNSSEC(DNSSECRData::NSEC3(nsec3))) if nsec3.opt_out())
+            });
+
+        // the empty non-terminals have NSEC3 records too
+        let mut names = BTreeSet::new();
+        for name in self.authoritative_names() {
+            let mut name = name;
+            while names.insert(name.clone()) && name != origin {
+                name = name.base_name();
+            }
+        }
+
+        let mut problems = Vec::new();
+        for name in names {
+            if hashed.contains(&name) {
+                continue;
+            }
+            let insecure = self.delegations.contains(&name)
+                && !self.nodes[&name].contains_key(&RecordType::DS);
+            if opt_out && insecure {
+                continue;
+            }
+
+            let hash = match param.hash_algorithm().hash(
+                param.salt(),
+                &Name::from(&name),
+                param.iterations(),
+            ) {
+                Ok(hash) => hash,
+                Err(e) => {
+                    problems.push((name.clone(), format!("can not be hashed: {e}")));
+                    continue;
+                }
+            };
+            let label = BASE32_DNSSEC.encode(hash.as_ref());
+            let owner = match Name::parse(&label, Some(&Name::from(&origin))) {
+                Ok(owner) => LowerName::from(owner),
+                Err(e) => {
+                    problems.push((name.clone(), format!("can not be hashed: {e}")));
+                    continue;
+                }
+            };
+            if !hashed.contains(&owner) {
+                problems.push((name, format!("has no NSEC3 record at {owner}")));
+            }
+        }
+
+        for (name, message) in problems {
+            self.report(
+                Severity::Error,
+                Check::DenialOfExistence,
+                &name,
+                Some(RecordType::NSEC3),
+                message,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(zone: &str) -> Vec<Diagnostic> {
+        let origin = Name::from_ascii("example.com.").unwrap();
+        let (origin, records) = Parser::new(zone, None, Some(origin)).parse().unwrap();
+        ZoneChecker::new(&origin, &records, 0).check()
+    }
+
+    fn checks(diagnostics: &[Diagnostic]) -> Vec<(Severity, Check, String)> {
+        diagnostics
+            .iter()
+            .map(|d| {
+                let name = d.name.as_ref().map(ToString::to_string).unwrap_or_default();
+                (d.severity, d.check, name)
+            })
+            .collect()
+    }
+
+    const HEADER: &str = "\
+$TTL 3600
+@ IN SOA ns1 hostmaster 1 7200 3600 1209600 3600
+@ IN NS ns1
+@ IN NS ns2
+ns1 IN A 192.0.2.1
+ns2 IN A 192.0.2.2
+";
+
+    #[test]
+    fn test_consistent_zone() {
+        let zone = format!(
+            "{HEADER}\
+www IN A 192.0.2.10
+alias IN CNAME www
+@ IN MX 10 mail
+mail IN AAAA 2001:db8::25
+sub IN NS ns.sub
+ns.sub IN A 192.0.2.53
+external IN CNAME www.example.net.
+"
+        );
+        assert_eq!(check(&zone), vec![]);
+    }
+
+    #[test]
+    fn test_soa_and_apex_ns() {
+        let zone = "\
+$TTL 3600
+@ IN SOA ns.example.net. hostmaster 1 3600 7200 3600 172800
+@ IN NS ns.example.net.
+";
+        assert_eq!(
+            checks(&check(zone)),
+            vec![
+                (Severity::Warning, Check::Soa, "example.com.".to_string()),
+                (Severity::Warning, Check::Soa, "example.com.".to_string()),
+                (Severity::Warning, Check::Soa, "example.com.".to_string()),
+                (Severity::Warning, Check::ApexNs, "example.com.".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cname_and_other_data() {
+        let zone = format!(
+            "{HEADER}\
+www IN CNAME ns1
+www IN TXT \"text\"
+"
+        );
+        assert_eq!(
+            checks(&check(&zone)),
+            vec![(
+                Severity::Error,
+                Check::CnameAndOtherData,
+                "www.example.com.".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_glue_targets_and_occluded_data() {
+        let zone = format!(
+            "{HEADER}\
+sub IN NS ns.sub
+sub IN NS ns3
+alias IN CNAME ns1
+@ IN MX 10 alias
+@ IN MX 20 nowhere
+txt.sub IN TXT \"hidden\"
+"
+        );
+        assert_eq!(
+            checks(&check(&zone)),
+            vec![
+                (
+                    Severity::Error,
+                    Check::MissingGlue,
+                    "sub.example.com.".to_string()
+                ),
+                (
+                    Severity::Error,
+                    Check::MissingGlue,
+                    "sub.example.com.".to_string()
+                ),
+                (
+                    Severity::Error,
+                    Check::DanglingTarget,
+                    "example.com.".to_string()
+                ),
+                (
+                    Severity::Warning,
+                    Check::DanglingTarget,
+                    "example.com.".to_string()
+                ),
+                (
+                    Severity::Warning,
+                    Check::OccludedData,
+                    "txt.sub.example.com.".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_syntax_and_out_of_zone() {
+        let diagnostics = check_zone(
+            &Name::from_ascii("example.com.").unwrap(),
+            Path::new("does-not-exist.zone"),
+        );
+        assert_eq!(diagnostics[0].check, Check::Syntax);
+
+        let zone = format!("{HEADER}www.example.net. IN A 192.0.2.1\n");
+        assert_eq!(
+            checks(&check(&zone)),
+            vec![(
+                Severity::Error,
+                Check::OutOfZone,
+                "www.example.net.".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let diagnostic = Diagnostic::new(Severity::Warning, Check::ApexNs, "message").at(
+            Name::from_ascii("example.com.").unwrap(),
+            Some(RecordType::NS),
+        );
+        assert_eq!(
+            serde_json::to_string(&diagnostic).unwrap(),
+            r#"{"severity":"warning","check":"apex-ns","name":"example.com.","record_type":"NS","message":"message"}"#
+        );
+        assert_eq!(
+            diagnostic.to_string(),
+            "warning: apex-ns: example.com. NS message"
+        );
+    }
+
+    #[cfg(feature = "dnssec")]
+    #[test]
+    fn test_nsec_chain() {
+        use crate::proto::rr::dnssec::rdata::{DNSSECRData, NSEC};
+        use crate::proto::rr::Record;
+
+        let origin = Name::from_ascii("example.com.").unwrap();
+        let (origin, mut records) = Parser::new(HEADER, None, Some(origin.clone()))
+            .parse()
+            .unwrap();
+
+        let nsec = |name: &str, next: &str, types: Vec<RecordType>| {
+            let name = Name::from_ascii(name).unwrap();
+            let next = Name::from_ascii(next).unwrap();
+            let record = Record::from_rdata(
+                name.clone(),
+                3600,
+                RData::DNSSEC(DNSSECRData::NSEC(NSEC::new(next, types))),
+            );
+            (RrKey::new(name.into(), RecordType::NSEC), record.into())
+        };
+        let apex_types = vec![
+            RecordType::NS,
+            RecordType::SOA,
+            RecordType::RRSIG,
+            RecordType::NSEC,
+        ];
+        let host_types = vec![RecordType::A, RecordType::RRSIG, RecordType::NSEC];
+        // ns2 is missing from the chain
+        records.extend([
+            nsec("example.com.", "ns1.example.com.", apex_types),
+            nsec("ns1.example.com.", "example.com.", host_types),
+        ]);
+
+        let diagnostics = ZoneChecker::new(&origin, &records, 0).check();
+        assert_eq!(
+            checks(&diagnostics),
+            vec![
+                (
+                    Severity::Error,
+                    Check::DenialOfExistence,
+                    "ns2.example.com.".to_string()
+                ),
+                (
+                    Severity::Error,
+                    Check::DenialOfExistence,
+                    "example.com.".to_string()
+                ),
+            ]
+        );
+    }
+}
@@ -8,7 +8,17 @@
 //! Zone file based serving with Dynamic DNS and journaling support
 
 mod authority;
+mod check;
 mod config;
+#[cfg(any(feature = "dnssec-openssl", feature = "dnssec-ring"))]
+mod sign;
 
 pub use self::authority::FileAuthority;
+pub use self::check::{check_records, check_zone, Check, Diagnostic, Severity};
 pub use self::config::FileConfig;
+#[cfg(any(feature = "dnssec-openssl", feature = "dnssec-ring"))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(feature = "dnssec-openssl", feature = "dnssec-ring")))
+)]
+pub use self::sign::{sign_records, sign_zone, Denial, SignOptions, SignedZone, ZoneKey};
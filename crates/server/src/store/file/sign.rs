@@ -0,0 +1,901 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Offline signing of zones, like BIND's `dnssec-signzone`
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tracing::debug;
+
+use crate::proto::{
+    rr::{
+        dnssec::{
+            rdata::{DNSSECRData, DNSKEY, DS, NSEC, NSEC3, NSEC3PARAM, RRSIG},
+            tbs, Algorithm, DigestType, DnsSecResult, KeyPair, Nsec3HashAlgorithm, Private,
+            SigSigner, Verifier,
+        },
+        DNSClass, LowerName, Name, RData, Record, RecordSet, RecordType, RrKey,
+    },
+    serialize::txt::Parser,
+};
+
+/// A key which signs the zone
+pub struct ZoneKey {
+    signer: SigSigner,
+    dnskey: DNSKEY,
+    key_tag: u16,
+}
+
+impl ZoneKey {
+    /// Creates the key of the zone from the key pair
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - the private key
+    /// * `algorithm` - the algorithm of the key
+    /// * `origin` - the zone, the owner of the DNSKEY record
+    /// * `is_key_signing_key` - the key has the SEP flag and only signs the DNSKEY, CDS and CDNSKEY
+    ///   records, unless the zone has no zone signing keys
+    pub fn new(
+        key: KeyPair<Private>,
+        algorithm: Algorithm,
+        origin: Name,
+        is_key_signing_key: bool,
+    ) -> DnsSecResult<Self> {
+        let dnskey = DNSKEY::new(
+            true,
+            is_key_signing_key,
+            false,
+            algorithm,
+            key.to_public_bytes()?,
+        );
+        let key_tag = dnskey.calculate_key_tag()?;
+        // the validity of the signatures is given by the `SignOptions`
+        let signer = SigSigner::dnssec(dnskey.clone(), key, origin, Duration::ZERO);
+
+        Ok(Self {
+            signer,
+            dnskey,
+            key_tag,
+        })
+    }
+
+    /// The DNSKEY record data of the key
+    pub fn dnskey(&self) -> &DNSKEY {
+        &self.dnskey
+    }
+
+    /// The key tag of the DNSKEY
+    pub fn key_tag(&self) -> u16 {
+        self.key_tag
+    }
+
+    /// The algorithm of the key
+    pub fn algorithm(&self) -> Algorithm {
+        self.dnskey.algorithm()
+    }
+
+    /// True if the key has the SEP flag, see `ZoneKey::new`
+    pub fn is_key_signing_key(&self) -> bool {
+        self.dnskey.secure_entry_point()
+    }
+
+    /// The DS record data of the key, for the parent of the zone
+    pub fn to_ds(&self, origin: &Name, digest_type: DigestType) -> DnsSecResult<DS> {
+        Ok(self.dnskey.to_ds(origin, digest_type)?)
+    }
+}
+
+/// The records which prove the non-existence of names in the signed zone
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Denial {
+    /// The chain of NSEC records, see [RFC 4034](https://tools.ietf.org/html/rfc4034#section-4)
+    Nsec,
+    /// The chain of NSEC3 records, see [RFC 5155](https://tools.ietf.org/html/rfc5155)
+    Nsec3 {
+        /// The additional iterations of the hash, 0 is recommended by RFC 9276
+        iterations: u16,
+        /// The salt of the hash, empty is recommended by RFC 9276
+        salt: Vec<u8>,
+        /// Insecure delegations, those without DS records, are left out of the chain
+        opt_out: bool,
+    },
+}
+
+/// The options of signing a zone
+#[derive(Clone, Debug)]
+pub struct SignOptions {
+    /// The records which prove the non-existence of names
+    pub denial: Denial,
+    /// The current time, in seconds since the epoch
+    pub now: u32,
+    /// The validity of new signatures, in seconds
+    pub validity: u32,
+    /// Existing signatures are replaced when they expire within this many seconds
+    pub refresh: u32,
+    /// Replace all existing signatures, not only those about to expire
+    pub resign_all: bool,
+    /// Increment the serial of the SOA record
+    pub increment_serial: bool,
+}
+
+impl Default for SignOptions {
+    /// Signs with NSEC records, signatures are valid for 30 days and refreshed after 3/4 of that
+    fn default() -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.as_secs() as u32);
+        let validity = 30 * 86_400;
+
+        Self {
+            denial: Denial::Nsec,
+            now,
+            validity,
+            refresh: validity / 4,
+            resign_all: false,
+            increment_serial: false,
+        }
+    }
+}
+
+/// The signed zone, and how many signatures were replaced
+#[derive(Debug)]
+pub struct SignedZone {
+    /// The records of the zone, with the RRSIGs attached to the RRsets they cover
+    pub records: BTreeMap<RrKey, RecordSet>,
+    /// The number of new signatures
+    pub signed: usize,
+    /// The number of existing signatures which are still valid and kept
+    pub kept: usize,
+    /// The number of existing signatures which are expired, about to expire, made by a key which
+    ///  is not used anymore, or do not cover the current records
+    pub dropped: usize,
+}
+
+impl SignedZone {
+    /// Writes the zone in the zone file format, the SOA record first and each RRset followed by
+    ///  its signatures
+    pub fn to_zone_file(&self) -> String {
+        let (soa, others): (Vec<_>, Vec<_>) = self
+            .records
+            .values()
+            .partition(|set| set.record_type() == RecordType::SOA);
+
+        let mut zone_file = String::new();
+        for set in soa.into_iter().chain(others) {
+            for record in set.records_without_rrsigs().chain(set.rrsigs()) {
+                writeln!(zone_file, "{record}").expect("write failed");
+            }
+        }
+        zone_file
+    }
+}
+
+/// Reads the zone file and signs the zone, see `sign_records`
+pub fn sign_zone(
+    origin: &Name,
+    zone_path: &Path,
+    keys: &[ZoneKey],
+    options: &SignOptions,
+) -> DnsSecResult<SignedZone> {
+    let buf = fs::read_to_string(zone_path)
+        .map_err(|e| format!("failed to read {}: {e}", zone_path.display()))?;
+    let (origin, records) = Parser::new(buf, Some(zone_path.to_owned()), Some(origin.clone()))
+        .parse()
+        .map_err(|e| format!("failed to parse {}: {e}", zone_path.display()))?;
+
+    sign_records(&origin, records, keys, options)
+}
+
+/// Signs the records of the zone
+///
+/// The DNSKEY records of the keys are added to the apex, the NSEC or NSEC3 chain is
+///  (re)generated, and every authoritative RRset is signed. The DNSKEY, CDS and CDNSKEY RRsets
+///  are signed by the key signing keys, all others by the zone signing keys, or by all keys if
+///  the zone only has keys of one kind.
+///
+/// The existing signatures of a zone which is already signed are kept, unless they are about to
+///  expire, are not made by one of the keys, or do not verify against the current records. Only
+///  the remaining RRsets are signed again.
+pub fn sign_records(
+    origin: &Name,
+    records: BTreeMap<RrKey, RecordSet>,
+    keys: &[ZoneKey],
+    options: &SignOptions,
+) -> DnsSecResult<SignedZone> {
+    if keys.is_empty() {
+        return Err("no keys to sign the zone with".into());
+    }
+
+    let origin = LowerName::from(origin);
+    let mut records = records;
+    let mut signatures = BTreeMap::<(LowerName, RecordType), Vec<Record>>::new();
+
+    // the signatures are in their own RRsets when parsed, or attached to the RRsets they cover
+    let rr_keys = records.keys().cloned().collect::<Vec<_>>();
+    for rr_key in rr_keys {
+        if !origin.zone_of(rr_key.name()) {
+            return Err(format!("{} is not in the zone {origin}", rr_key.name()).into());
+        }
+
+        // the chain of the denial of existence is regenerated
+        let set = match rr_key.record_type {
+            RecordType::RRSIG | RecordType::NSEC | RecordType::NSEC3 | RecordType::NSEC3PARAM => {
+                records.remove(&rr_key).expect("the key was just listed")
+            }
+            _ => {
+                let set = records.get_mut(&rr_key).expect("the key was just listed");
+                let rrsigs = set.rrsigs().to_vec();
+                set.clear_rrsigs();
+                for rrsig in rrsigs {
+                    add_signature(&mut signatures, rrsig);
+                }
+                continue;
+            }
+        };
+
+        if rr_key.record_type == RecordType::RRSIG {
+            for rrsig in set.records_without_rrsigs() {
+                add_signature(&mut signatures, rrsig.clone());
+            }
+        }
+        for rrsig in set.rrsigs() {
+            add_signature(&mut signatures, rrsig.clone());
+        }
+    }
+
+    let (class, soa_ttl, zone_ttl) = {
+        let soa = records
+            .get(&RrKey::new(origin.clone(), RecordType::SOA))
+            .and_then(|set| set.records_without_rrsigs().next())
+            .ok_or_else(|| format!("the zone {origin} has no SOA record"))?;
+        let minimum = soa
+            .data()
+            .and_then(RData::as_soa)
+            .map_or(0, |soa| soa.minimum());
+        (soa.dns_class(), soa.ttl(), minimum.min(soa.ttl()))
+    };
+
+    if options.increment_serial {
+        increment_serial(&mut records, &origin);
+    }
+
+    // publish the keys
+    let dnskeys = records
+        .entry(RrKey::new(origin.clone(), RecordType::DNSKEY))
+        .or_insert_with(|| RecordSet::with_ttl(origin.clone().into(), RecordType::DNSKEY, soa_ttl));
+    for key in keys {
+        let mut record = Record::from_rdata(
+            origin.clone().into(),
+            dnskeys.ttl(),
+            RData::DNSSEC(DNSSECRData::DNSKEY(key.dnskey.clone())),
+        );
+        record.set_dns_class(class);
+        dnskeys.insert(record, 0);
+    }
+
+    let zone = Zone::new(&origin, &records);
+    let denial = match &options.denial {
+        Denial::Nsec => zone.nsec_records(class, zone_ttl),
+        Denial::Nsec3 {
+            iterations,
+            salt,
+            opt_out,
+        } => zone.nsec3_records(class, zone_ttl, *iterations, salt, *opt_out)?,
+    };
+    let signed_keys = zone.signed_keys();
+    for record in denial {
+        let key = RrKey::new(record.name().into(), record.record_type());
+        records
+            .entry(key)
+            .or_insert_with(|| {
+                let mut set = RecordSet::with_ttl(record.name().clone(), record.record_type(), 0);
+                set.set_dns_class(class);
+                set
+            })
+            .insert(record, 0);
+    }
+
+    let key_signing_keys = keys
+        .iter()
+        .filter(|key| key.is_key_signing_key())
+        .collect::<Vec<_>>();
+    let zone_signing_keys = keys
+        .iter()
+        .filter(|key| !key.is_key_signing_key())
+        .collect::<Vec<_>>();
+
+    let mut signed_zone = SignedZone {
+        records: BTreeMap::new(),
+        signed: 0,
+        kept: 0,
+        dropped: 0,
+    };
+    for (key, mut set) in records {
+        // the glue and the NS records of delegations are not signed
+        let is_signed = signed_keys.contains(&key)
+            || matches!(
+                key.record_type,
+                RecordType::NSEC | RecordType::NSEC3 | RecordType::NSEC3PARAM
+            );
+        if is_signed {
+            let signers = match key.record_type {
+                RecordType::DNSKEY | RecordType::CDS | RecordType::CDNSKEY
+                    if !key_signing_keys.is_empty() =>
+                {
+                    &key_signing_keys
+                }
+                _ if zone_signing_keys.is_empty() => &key_signing_keys,
+                _ => &zone_signing_keys,
+            };
+            let existing = signatures
+                .remove(&(key.name().clone(), key.record_type))
+                .unwrap_or_default();
+            sign_rrset(&mut set, signers, existing, options, &mut signed_zone)?;
+        }
+
+        signed_zone.records.insert(key, set);
+    }
+    signed_zone.dropped += signatures.values().map(Vec::len).sum::<usize>();
+
+    debug!(
+        "signed zone {origin}: {} new signatures, {} kept, {} dropped",
+        signed_zone.signed, signed_zone.kept, signed_zone.dropped
+    );
+    Ok(signed_zone)
+}
+
+fn add_signature(signatures: &mut BTreeMap<(LowerName, RecordType), Vec<Record>>, rrsig: Record) {
+    if let Some(RData::DNSSEC(DNSSECRData::RRSIG(data))) = rrsig.data() {
+        signatures
+            .entry((rrsig.name().into(), data.type_covered()))
+            .or_default()
+            .push(rrsig);
+    }
+}
+
+fn increment_serial(records: &mut BTreeMap<RrKey, RecordSet>, origin: &LowerName) {
+    let Some(set) = records.get_mut(&RrKey::new(origin.clone(), RecordType::SOA)) else {
+        return;
+    };
+    let Some(mut soa) = set.records_without_rrsigs().next().cloned() else {
+        return;
+    };
+
+    if let Some(RData::SOA(data)) = soa.data_mut() {
+        data.increment_serial();
+    }
+    set.insert(soa, 0);
+}
+
+/// Keeps the valid signatures of each key, and signs the RRset with the keys which have none
+fn sign_rrset(
+    set: &mut RecordSet,
+    keys: &[&ZoneKey],
+    existing: Vec<Record>,
+    options: &SignOptions,
+    signed_zone: &mut SignedZone,
+) -> DnsSecResult<()> {
+    let records = set.records_without_rrsigs().cloned().collect::<Vec<_>>();
+    let record_refs = records.iter().collect::<Vec<_>>();
+    let mut existing = existing;
+
+    for key in keys {
+        let position = existing.iter().position(|rrsig| {
+            let Some(RData::DNSSEC(DNSSECRData::RRSIG(data))) = rrsig.data() else {
+                return false;
+            };
+
+            !options.resign_all
+                && data.key_tag() == key.key_tag
+                && data.algorithm() == key.algorithm()
+                && data.signer_name() == key.signer.signer_name()
+                && data.original_ttl() == set.ttl()
+                && data.sig_inception() <= options.now
+                && data.sig_expiration() > options.now.saturating_add(options.refresh)
+                && key
+                    .dnskey
+                    .verify_rrsig(set.name(), set.dns_class(), data, &record_refs)
+                    .is_ok()
+        });
+
+        match position {
+            Some(position) => {
+                set.insert_rrsig(existing.swap_remove(position));
+                signed_zone.kept += 1;
+            }
+            None => {
+                set.insert_rrsig(sign(set, &records, key, options)?);
+                signed_zone.signed += 1;
+            }
+        }
+    }
+
+    signed_zone.dropped += existing.len();
+    Ok(())
+}
+
+/// Signs the RRset with the key, the signature is valid from an hour before now, for clocks
+///  which are behind
+fn sign(
+    set: &RecordSet,
+    records: &[Record],
+    key: &ZoneKey,
+    options: &SignOptions,
+) -> DnsSecResult<Record> {
+    let inception = options.now.saturating_sub(3_600);
+    let expiration = options.now.saturating_add(options.validity);
+    let signer_name = key.signer.signer_name();
+
+    let tbs = tbs::rrset_tbs(
+        set.name(),
+        set.dns_class(),
+        set.name().num_labels(),
+        set.record_type(),
+        key.algorithm(),
+        set.ttl(),
+        expiration,
+        inception,
+        key.key_tag,
+        signer_name,
+        records,
+    )?;
+    let signature = key.signer.sign(&tbs)?;
+
+    let mut rrsig = Record::from_rdata(
+        set.name().clone(),
+        set.ttl(),
+        RData::DNSSEC(DNSSECRData::RRSIG(RRSIG::new(
+            set.record_type(),
+            key.algorithm(),
+            set.name().num_labels(),
+            set.ttl(),
+            expiration,
+            inception,
+            key.key_tag,
+            signer_name.clone(),
+            signature,
+        ))),
+    );
+    rrsig.set_dns_class(set.dns_class());
+    Ok(rrsig)
+}
+
+/// The record types of the zone, grouped by name
+struct Zone {
+    origin: LowerName,
+    nodes: BTreeMap<LowerName, BTreeSet<RecordType>>,
+    delegations: Vec<LowerName>,
+}
+
+impl Zone {
+    fn new(origin: &LowerName, records: &BTreeMap<RrKey, RecordSet>) -> Self {
+        let mut nodes = BTreeMap::<_, BTreeSet<_>>::new();
+        for key in records.keys() {
+            nodes
+                .entry(key.name().clone())
+                .or_default()
+                .insert(key.record_type);
+        }
+
+        let delegations = nodes
+            .iter()
+            .filter(|(name, types)| *name != origin && types.contains(&RecordType::NS))
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        Self {
+            origin: origin.clone(),
+            nodes,
+            delegations,
+        }
+    }
+
+    fn is_delegation(&self, name: &LowerName) -> bool {
+        self.delegations.contains(name)
+    }
+
+    /// The names the zone is authoritative for, the delegation points included, in canonical
+    ///  order
+    fn authoritative_names(&self) -> impl Iterator<Item = (&LowerName, &BTreeSet<RecordType>)> {
+        self.nodes.iter().filter(|(name, _)| {
+            !self
+                .delegations
+                .iter()
+                .any(|delegation| delegation != *name && delegation.zone_of(name))
+        })
+    }
+
+    /// The RRsets which are signed, i.e. all authoritative ones except the NS records of the
+    ///  delegations
+    fn signed_keys(&self) -> BTreeSet<RrKey> {
+        self.authoritative_names()
+            .flat_map(|(name, types)| {
+                let is_delegation = self.is_delegation(name);
+                types
+                    .iter()
+                    .filter(move |ty| !is_delegation || **ty == RecordType::DS)
+                    .map(move |ty| RrKey::new(name.clone(), *ty))
+            })
+            .collect()
+    }
+
+    /// The types listed in the type bit map of the name, RRSIG and NSEC or NSEC3 not included
+    fn types_at(&self, name: &LowerName, types: &BTreeSet<RecordType>) -> BTreeSet<RecordType> {
+        let mut types = types.clone();
+        if self.is_delegation(name) {
+            types.retain(|ty| matches!(ty, RecordType::NS | RecordType::DS));
+        }
+        types
+    }
+
+    /// The chain of NSEC records through all authoritative names
+    fn nsec_records(&self, class: DNSClass, ttl: u32) -> Vec<Record> {
+        let names = self.authoritative_names().collect::<Vec<_>>();
+
+        let mut records = Vec::with_capacity(names.len());
+        for (i, (name, types)) in names.iter().enumerate() {
+            let next = names.get(i + 1).map_or(&self.origin, |(next, _)| *next);
+            // the NSEC record itself is signed, even at an insecure delegation
+            let mut types = self.types_at(name, types);
+            types.extend([RecordType::RRSIG, RecordType::NSEC]);
+
+            let mut record = Record::from_rdata(
+                (*name).clone().into(),
+                ttl,
+                RData::DNSSEC(DNSSECRData::NSEC(NSEC::new(
+                    next.clone().into(),
+                    types.into_iter().collect(),
+                ))),
+            );
+            record.set_dns_class(class);
+            records.push(record);
+        }
+        records
+    }
+
+    /// The chain of NSEC3 records through the hashes of all authoritative names and the empty
+    ///  non-terminals, and the NSEC3PARAM record of the apex
+    fn nsec3_records(
+        &self,
+        class: DNSClass,
+        ttl: u32,
+        iterations: u16,
+        salt: &[u8],
+        opt_out: bool,
+    ) -> DnsSecResult<Vec<Record>> {
+        let hash_algorithm = Nsec3HashAlgorithm::SHA1;
+        let empty = BTreeSet::new();
+
+        let mut names = BTreeMap::new();
+        for (name, types) in self.authoritative_names() {
+            let insecure = self.is_delegation(name) && !types.contains(&RecordType::DS);
+            if !(opt_out && insecure) {
+                names.insert(name.clone(), types);
+            }
+
+            // the empty non-terminals between the name and the apex
+            let mut name = name.clone();
+            while name != self.origin {
+                name = name.base_name();
+                names.entry(name.clone()).or_insert(&empty);
+            }
+        }
+
+        let mut hashes = BTreeMap::new();
+        for (name, types) in names {
+            let hash = hash_algorithm.hash(salt, &Name::from(&name), iterations)?;
+            // the empty non-terminals and the insecure delegations have no signed records
+            let mut types = self.types_at(&name, types);
+            if types.iter().any(|ty| *ty != RecordType::NS) {
+                types.insert(RecordType::RRSIG);
+            }
+            if name == self.origin {
+                types.insert(RecordType::NSEC3PARAM);
+            }
+
+            if let Some((collision, _)) =
+                hashes.insert(hash.as_ref().to_vec(), (name.clone(), types))
+            {
+                return Err(format!(
+                    "the NSEC3 hashes of {name} and {collision} collide, use another salt"
+                )
+                .into());
+            }
+        }
+
+        let origin = Name::from(&self.origin);
+        let hashed = hashes.keys().cloned().collect::<Vec<_>>();
+        let mut records = Vec::with_capacity(hashed.len() + 1);
+        for (i, (hash, (_, types))) in hashes.into_iter().enumerate() {
+            let next = hashed[(i + 1) % hashed.len()].clone();
+            let owner = Name::parse(&data_encoding::BASE32_DNSSEC.encode(&hash), Some(&origin))?;
+
+            let mut record = Record::from_rdata(
+                owner,
+                ttl,
+                RData::DNSSEC(DNSSECRData::NSEC3(NSEC3::new(
+                    hash_algorithm,
+                    opt_out,
+                    iterations,
+                    salt.to_vec(),
+                    next,
+                    types.into_iter().collect(),
+                ))),
+            );
+            record.set_dns_class(class);
+            records.push(record);
+        }
+
+        // the opt-out flag of the NSEC3PARAM record is always clear, RFC 5155 section 4.1.2
+        let mut record = Record::from_rdata(
+            origin,
+            0,
+            RData::DNSSEC(DNSSECRData::NSEC3PARAM(NSEC3PARAM::new(
+                hash_algorithm,
+                false,
+                iterations,
+                salt.to_vec(),
+            ))),
+        );
+        record.set_dns_class(class);
+        records.push(record);
+
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::rr::dnssec::KeyFormat;
+
+    const ZONE: &str = "\
+$TTL 3600
+@ IN SOA ns1 hostmaster 1 7200 3600 1209600 300
+@ IN NS ns1
+ns1 IN A 192.0.2.1
+www IN A 192.0.2.10
+www IN A 192.0.2.11
+a.b.c IN TXT \"empty-non-terminals\"
+secure IN NS ns.secure
+secure IN DS 60485 13 2 D4B7D520E7BB5F0F67674A0CCEB1E3E0614B93C4F9E99B8383F6A1E4469DA50A
+ns.secure IN A 192.0.2.53
+insecure IN NS ns.insecure
+ns.insecure IN A 192.0.2.54
+";
+
+    fn origin() -> Name {
+        Name::from_ascii("example.com.").unwrap()
+    }
+
+    fn parse(zone: &str) -> BTreeMap<RrKey, RecordSet> {
+        Parser::new(zone, None, Some(origin())).parse().unwrap().1
+    }
+
+    fn key(is_key_signing_key: bool) -> ZoneKey {
+        #[cfg(feature = "dnssec-ring")]
+        let format = KeyFormat::Pkcs8;
+        #[cfg(not(feature = "dnssec-ring"))]
+        let format = KeyFormat::Der;
+
+        let algorithm = Algorithm::ECDSAP256SHA256;
+        let bytes = format.generate_and_encode(algorithm, None).unwrap();
+        let key = format.decode_key(&bytes, None, algorithm).unwrap();
+        ZoneKey::new(key, algorithm, origin(), is_key_signing_key).unwrap()
+    }
+
+    fn options(denial: Denial) -> SignOptions {
+        SignOptions {
+            denial,
+            now: 1_700_000_000,
+            ..SignOptions::default()
+        }
+    }
+
+    /// The key tags of the valid signatures of each RRset
+    fn signatures(zone: &SignedZone, keys: &[ZoneKey]) -> BTreeMap<RrKey, Vec<u16>> {
+        let mut signatures = BTreeMap::new();
+        for (rr_key, set) in &zone.records {
+            let records = set.records_without_rrsigs().collect::<Vec<_>>();
+            for rrsig in set.rrsigs() {
+                let Some(RData::DNSSEC(DNSSECRData::RRSIG(data))) = rrsig.data() else {
+                    panic!("not an RRSIG: {rrsig}");
+                };
+                let key = keys
+                    .iter()
+                    .find(|key| key.key_tag() == data.key_tag())
+                    .expect("unknown key");
+                key.dnskey()
+                    .verify_rrsig(set.name(), set.dns_class(), data, &records)
+                    .expect("invalid signature");
+                signatures
+                    .entry(rr_key.clone())
+                    .or_insert_with(Vec::new)
+                    .push(data.key_tag());
+            }
+        }
+        signatures
+    }
+
+    fn rr_key(name: &str, record_type: RecordType) -> RrKey {
+        let name = match name {
+            "@" => origin(),
+            name => Name::parse(name, Some(&origin())).unwrap(),
+        };
+        RrKey::new(name.into(), record_type)
+    }
+
+    #[test]
+    fn test_sign_nsec() {
+        let keys = [key(true), key(false)];
+        let (ksk, zsk) = (keys[0].key_tag(), keys[1].key_tag());
+        let zone = sign_records(&origin(), parse(ZONE), &keys, &options(Denial::Nsec)).unwrap();
+        let signatures = signatures(&zone, &keys);
+
+        assert_eq!(signatures[&rr_key("@", RecordType::DNSKEY)], vec![ksk]);
+        assert_eq!(signatures[&rr_key("@", RecordType::SOA)], vec![zsk]);
+        assert_eq!(signatures[&rr_key("www", RecordType::A)], vec![zsk]);
+        assert_eq!(signatures[&rr_key("secure", RecordType::DS)], vec![zsk]);
+        assert_eq!(signatures[&rr_key("insecure", RecordType::NSEC)], vec![zsk]);
+        // delegations and glue are not signed
+        assert!(!signatures.contains_key(&rr_key("secure", RecordType::NS)));
+        assert!(!signatures.contains_key(&rr_key("ns.secure", RecordType::A)));
+        assert!(!signatures.contains_key(&rr_key("ns.insecure", RecordType::A)));
+
+        let chain = zone
+            .records
+            .values()
+            .filter(|set| set.record_type() == RecordType::NSEC)
+            .map(|set| {
+                let record = set.records_without_rrsigs().next().unwrap();
+                let Some(RData::DNSSEC(DNSSECRData::NSEC(nsec))) = record.data() else {
+                    panic!("not an NSEC: {record}");
+                };
+                (
+                    record.name().to_string(),
+                    nsec.next_domain_name().to_string(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            chain,
+            [
+                ("example.com.", "a.b.c.example.com."),
+                ("a.b.c.example.com.", "insecure.example.com."),
+                ("insecure.example.com.", "ns1.example.com."),
+                ("ns1.example.com.", "secure.example.com."),
+                ("secure.example.com.", "www.example.com."),
+                ("www.example.com.", "example.com."),
+            ]
+            .map(|(name, next)| (name.to_string(), next.to_string()))
+        );
+
+        let nsec = &zone.records[&rr_key("secure", RecordType::NSEC)];
+        let Some(RData::DNSSEC(DNSSECRData::NSEC(nsec))) =
+            nsec.records_without_rrsigs().next().unwrap().data()
+        else {
+            panic!("not an NSEC");
+        };
+        assert_eq!(
+            nsec.type_bit_maps(),
+            [
+                RecordType::NS,
+                RecordType::DS,
+                RecordType::RRSIG,
+                RecordType::NSEC
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sign_nsec3() {
+        let keys = [key(false)];
+        let denial = |opt_out| Denial::Nsec3 {
+            iterations: 0,
+            salt: vec![0xaa, 0xbb],
+            opt_out,
+        };
+        let count = |zone: &SignedZone| {
+            zone.records
+                .keys()
+                .filter(|key| key.record_type == RecordType::NSEC3)
+                .count()
+        };
+
+        // the apex, a.b.c, b.c, c, insecure, ns1, secure and www
+        let zone = sign_records(&origin(), parse(ZONE), &keys, &options(denial(false))).unwrap();
+        assert_eq!(count(&zone), 8);
+        let signatures = signatures(&zone, &keys);
+        assert!(signatures.contains_key(&rr_key("@", RecordType::NSEC3PARAM)));
+        assert!(signatures.contains_key(&rr_key("@", RecordType::DNSKEY)));
+
+        // the insecure delegation is left out of the chain
+        let zone = sign_records(&origin(), parse(ZONE), &keys, &options(denial(true))).unwrap();
+        assert_eq!(count(&zone), 7);
+    }
+
+    #[test]
+    fn test_resign() {
+        let keys = [key(true), key(false)];
+        let options = options(Denial::Nsec);
+        let zone = sign_records(&origin(), parse(ZONE), &keys, &options).unwrap();
+        let total = zone.signed;
+        assert_eq!(zone.kept, 0);
+
+        // the signatures are still valid a day later
+        let signed = parse(&zone.to_zone_file());
+        let later = SignOptions {
+            now: options.now + 86_400,
+            ..options.clone()
+        };
+        let zone = sign_records(&origin(), signed.clone(), &keys, &later).unwrap();
+        assert_eq!((zone.signed, zone.kept, zone.dropped), (0, total, 0));
+        signatures(&zone, &keys);
+
+        // a changed RRset is signed again
+        let mut changed = signed.clone();
+        let www = changed.get_mut(&rr_key("www", RecordType::A)).unwrap();
+        let mut record = www.records_without_rrsigs().next().unwrap().clone();
+        record.set_data(Some(RData::A("192.0.2.12".parse().unwrap())));
+        www.insert(record, 0);
+        let zone = sign_records(&origin(), changed, &keys, &later).unwrap();
+        assert_eq!((zone.signed, zone.kept, zone.dropped), (1, total - 1, 1));
+
+        // the signatures are about to expire
+        let expiring = SignOptions {
+            now: options.now + options.validity - options.refresh + 1,
+            ..options.clone()
+        };
+        let zone = sign_records(&origin(), signed.clone(), &keys, &expiring).unwrap();
+        assert_eq!((zone.signed, zone.kept, zone.dropped), (total, 0, total));
+
+        // the signatures are made by another key
+        let zone = sign_records(&origin(), signed, &[key(false)], &later).unwrap();
+        assert_eq!(zone.kept, 0);
+    }
+
+    #[test]
+    fn test_errors() {
+        let keys = [key(false)];
+        let options = options(Denial::Nsec);
+
+        assert!(sign_records(&origin(), parse(ZONE), &[], &options).is_err());
+
+        let no_soa = "$TTL 3600\n@ IN NS ns1\nns1 IN A 192.0.2.1\n";
+        assert!(sign_records(&origin(), parse(no_soa), &keys, &options).is_err());
+
+        let mut records = parse(ZONE);
+        let outside = Name::from_ascii("www.example.net.").unwrap();
+        records.insert(
+            RrKey::new(outside.clone().into(), RecordType::A),
+            RecordSet::new(&outside, RecordType::A, 0),
+        );
+        assert!(sign_records(&origin(), records, &keys, &options).is_err());
+    }
+
+    #[test]
+    fn test_increment_serial() {
+        let options = SignOptions {
+            increment_serial: true,
+            ..options(Denial::Nsec)
+        };
+        let zone = sign_records(&origin(), parse(ZONE), &[key(false)], &options).unwrap();
+        let soa = &zone.records[&rr_key("@", RecordType::SOA)];
+        let serial = soa
+            .records_without_rrsigs()
+            .next()
+            .and_then(Record::data)
+            .and_then(RData::as_soa)
+            .map(|soa| soa.serial());
+        assert_eq!(serial, Some(2));
+    }
+}
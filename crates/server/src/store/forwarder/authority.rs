@@ -5,10 +5,10 @@
 // https://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
-use std::io;
+use std::{io, sync::Arc, time::Instant};
 
 use hickory_resolver::name_server::TokioConnectionProvider;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 use crate::{
     authority::{
@@ -18,9 +18,17 @@ use crate::{
         op::ResponseCode,
         rr::{LowerName, Name, Record, RecordType},
     },
-    resolver::{config::ResolverConfig, lookup::Lookup as ResolverLookup, TokioAsyncResolver},
+    resolver::{
+        config::{ResolverConfig, ResolverOpts},
+        lookup::Lookup as ResolverLookup,
+        warm_up::WarmUpReport,
+        TokioAsyncResolver,
+    },
     server::RequestInfo,
-    store::forwarder::ForwardConfig,
+    store::forwarder::{
+        partition::CachePartition, rewrite::Rewriting, CachePartitionConfig, CachePartitionStats,
+        ForwardConfig, ResponseRewriter,
+    },
 };
 
 /// An authority that will forward resolutions to upstream resolvers.
@@ -29,6 +37,10 @@ use crate::{
 pub struct ForwardAuthority {
     origin: LowerName,
     resolver: TokioAsyncResolver,
+    validate: bool,
+    cache_size: usize,
+    rewriting: Option<Rewriting>,
+    partitions: Vec<CachePartition>,
 }
 
 impl ForwardAuthority {
@@ -38,10 +50,16 @@ impl ForwardAuthority {
     pub fn new(runtime: TokioConnectionProvider) -> Result<Self, String> {
         let resolver = TokioAsyncResolver::from_system_conf(runtime)
             .map_err(|e| format!("error constructing new Resolver: {e}"))?;
+        let validate = resolver.options().validate;
+        let cache_size = resolver.options().cache_size;
 
         Ok(Self {
             origin: Name::root().into(),
             resolver,
+            validate,
+            cache_size,
+            rewriting: None,
+            partitions: vec![],
         })
     }
 
@@ -49,12 +67,12 @@ impl ForwardAuthority {
     pub fn try_from_config(
         origin: Name,
         _zone_type: ZoneType,
-        config: &ForwardConfig,
+        forward_config: &ForwardConfig,
     ) -> Result<Self, String> {
         info!("loading forwarder config: {}", origin);
 
-        let name_servers = config.name_servers.clone();
-        let mut options = config.options.clone().unwrap_or_default();
+        let name_servers = forward_config.name_servers.clone();
+        let mut options = forward_config.options.clone().unwrap_or_default();
 
         // See RFC 1034, Section 4.3.2:
         // "If the data at the node is a CNAME, and QTYPE doesn't match
@@ -75,8 +93,19 @@ impl ForwardAuthority {
             options.preserve_intermediates = true;
         }
 
+        // With validation the DO bit is set on upstream requests, and all answers are validated
+        //  locally. Only locally validated answers are returned with the AD bit set.
+        if options.validate && !cfg!(feature = "dnssec") {
+            return Err(format!(
+                "validate is set for forwarder {origin}, but the dnssec feature is not enabled"
+            ));
+        }
+
         let config = ResolverConfig::from_parts(None, vec![], name_servers);
+        let validate = options.validate;
+        let cache_size = options.cache_size;
 
+        let partitions = config_partitions(&config, &options, &forward_config.cache_partitions);
         let resolver = TokioAsyncResolver::new(config, options, TokioConnectionProvider::default());
 
         info!("forward resolver configured: {}: ", origin);
@@ -85,8 +114,84 @@ impl ForwardAuthority {
         Ok(Self {
             origin: origin.into(),
             resolver,
+            validate,
+            cache_size,
+            rewriting: None,
+            partitions,
         })
     }
+
+    /// Resolves the warm-up names of the resolver options, so that they are cached before the
+    ///  first request, see `AsyncResolver::warm_up`
+    ///
+    /// The caches of all partitions are warmed up as well, the returned report is the one of the
+    ///  default cache.
+    pub async fn warm_up(&self) -> WarmUpReport {
+        let report = self.resolver.warm_up().await;
+        for (name, e) in &report.failed {
+            warn!(
+                "failed to warm up {name} for forwarder {}: {e}",
+                self.origin
+            );
+        }
+
+        for partition in &self.partitions {
+            for (name, e) in partition.resolver().warm_up().await.failed {
+                warn!(
+                    "failed to warm up {name} for partition {} of forwarder {}: {e}",
+                    partition.name(),
+                    self.origin
+                );
+            }
+        }
+
+        report
+    }
+
+    /// Returns the stats of the cache partition with the given name
+    pub fn cache_partition_stats(&self, name: &str) -> Option<CachePartitionStats> {
+        self.partitions
+            .iter()
+            .find(|partition| partition.name() == name)
+            .map(CachePartition::stats)
+    }
+
+    /// Returns the cache partition of the client, if any
+    fn partition(&self, request_info: &RequestInfo<'_>) -> Option<&CachePartition> {
+        let ip = request_info.src.ip();
+        self.partitions
+            .iter()
+            .find(|partition| partition.contains(&ip))
+    }
+
+    async fn resolve(
+        &self,
+        resolver: &TokioAsyncResolver,
+        name: &LowerName,
+        rtype: RecordType,
+        lookup_options: LookupOptions,
+    ) -> Result<ForwardLookup, LookupError> {
+        // TODO: make this an error?
+        debug_assert!(self.origin.zone_of(name));
+
+        debug!("forwarding lookup: {} {}", name, rtype);
+        let name: LowerName = name.clone();
+        let lookup = resolver.lookup(name, rtype).await?;
+
+        if self.validate {
+            return validated(lookup, lookup_options).map(ForwardLookup);
+        }
+
+        Ok(ForwardLookup(lookup))
+    }
+
+    /// Sets a hook to rewrite the upstream answers returned to clients
+    ///
+    /// Rewritten answers are cached separately from the upstream answers, per policy key of the
+    ///  `ResponseRewriter` and cache partition, with the same capacity as the resolver cache.
+    pub fn set_response_rewriter(&mut self, rewriter: Arc<dyn ResponseRewriter>) {
+        self.rewriting = Some(Rewriting::new(rewriter, self.cache_size));
+    }
 }
 
 #[async_trait::async_trait]
@@ -121,16 +226,10 @@ impl Authority for ForwardAuthority {
         &self,
         name: &LowerName,
         rtype: RecordType,
-        _lookup_options: LookupOptions,
+        lookup_options: LookupOptions,
     ) -> Result<Self::Lookup, LookupError> {
-        // TODO: make this an error?
-        debug_assert!(self.origin.zone_of(name));
-
-        debug!("forwarding lookup: {} {}", name, rtype);
-        let name: LowerName = name.clone();
-        let resolve = self.resolver.lookup(name, rtype).await;
-
-        resolve.map(ForwardLookup).map_err(LookupError::from)
+        self.resolve(&self.resolver, name, rtype, lookup_options)
+            .await
     }
 
     async fn search(
@@ -138,12 +237,50 @@ impl Authority for ForwardAuthority {
         request_info: RequestInfo<'_>,
         lookup_options: LookupOptions,
     ) -> Result<Self::Lookup, LookupError> {
-        self.lookup(
-            request_info.query.name(),
-            request_info.query.query_type(),
-            lookup_options,
-        )
-        .await
+        let partition = self.partition(&request_info);
+        let resolver = partition.map_or(&self.resolver, CachePartition::resolver);
+        let lookup = |lookup_options| async move {
+            let lookup = self
+                .resolve(
+                    resolver,
+                    request_info.query.name(),
+                    request_info.query.query_type(),
+                    lookup_options,
+                )
+                .await;
+            if let Some(partition) = partition {
+                partition.record_lookup(lookup.is_ok());
+            }
+            lookup
+        };
+
+        let Some(rewriting) = &self.rewriting else {
+            return lookup(lookup_options).await;
+        };
+
+        // rewritten answers must not be shared between partitions either
+        let policy_key = rewriting
+            .policy_key(&request_info)
+            .map(|policy_key| match partition {
+                Some(partition) => format!("{}/{policy_key}", partition.name()),
+                None => policy_key,
+            });
+        if let Some(policy_key) = &policy_key {
+            let query = request_info.query;
+            if let Some(lookup) = rewriting.cached(policy_key, query, Instant::now()) {
+                debug!("rewritten answer cached for {}: {}", policy_key, query);
+                return Ok(ForwardLookup(lookup));
+            }
+        }
+
+        let ForwardLookup(lookup) = lookup(lookup_options).await?;
+
+        Ok(ForwardLookup(rewriting.rewrite(
+            &request_info,
+            policy_key,
+            lookup,
+            Instant::now(),
+        )))
     }
 
     async fn get_nsec_records(
@@ -156,6 +293,86 @@ impl Authority for ForwardAuthority {
             "Getting NSEC records is unimplemented for the forwarder",
         )))
     }
+
+    async fn get_wildcard_proof(
+        &self,
+        _next_closer: &LowerName,
+        _lookup_options: LookupOptions,
+    ) -> Result<Self::Lookup, LookupError> {
+        Err(LookupError::from(io::Error::new(
+            io::ErrorKind::Other,
+            "Getting wildcard proofs is unimplemented for the forwarder",
+        )))
+    }
+}
+
+fn config_partitions(
+    config: &ResolverConfig,
+    options: &ResolverOpts,
+    partitions: &[CachePartitionConfig],
+) -> Vec<CachePartition> {
+    partitions
+        .iter()
+        .map(|partition| {
+            info!(
+                "forwarder cache partition {}: {:?}",
+                partition.name, partition.networks
+            );
+            CachePartition::new(partition, config.clone(), options.clone())
+        })
+        .collect()
+}
+
+/// Checks the proofs of a locally validated lookup
+///
+/// Answers with any bogus records are rejected with ServFail. The proofs of the remaining records
+///  are retained, and are used to set the AD bit in the response. RRSIGs received from upstream
+///  are only returned to clients which requested DNSSEC records.
+#[cfg(feature = "dnssec")]
+fn validated(
+    lookup: ResolverLookup,
+    lookup_options: LookupOptions,
+) -> Result<ResolverLookup, LookupError> {
+    if let Some(record) = lookup.records().iter().find(|r| r.proof().is_bogus()) {
+        tracing::warn!(
+            "forwarded answer failed validation: {} {}",
+            record.name(),
+            record.record_type()
+        );
+        return Err(LookupError::from(ResponseCode::ServFail));
+    }
+
+    let query_type = lookup.query().query_type();
+    if lookup_options.is_dnssec()
+        || query_type == RecordType::RRSIG
+        || !lookup
+            .records()
+            .iter()
+            .any(|r| r.record_type() == RecordType::RRSIG)
+    {
+        return Ok(lookup);
+    }
+
+    let records = lookup
+        .records()
+        .iter()
+        .filter(|r| r.record_type() != RecordType::RRSIG)
+        .cloned()
+        .collect::<Vec<_>>();
+
+    Ok(ResolverLookup::new_with_deadline(
+        lookup.query().clone(),
+        Arc::from(records),
+        lookup.valid_until(),
+    ))
+}
+
+#[cfg(not(feature = "dnssec"))]
+fn validated(
+    lookup: ResolverLookup,
+    _lookup_options: LookupOptions,
+) -> Result<ResolverLookup, LookupError> {
+    Ok(lookup)
 }
 
 /// A structure that holds the results of a forwarding lookup.
@@ -176,3 +393,67 @@ impl LookupObject for ForwardLookup {
         None
     }
 }
+
+#[cfg(all(test, feature = "dnssec"))]
+mod tests {
+    use std::{str::FromStr, time::Duration};
+
+    use super::*;
+    use crate::proto::{
+        op::Query,
+        rr::{
+            dnssec::{rdata::RRSIG, Algorithm, Proof},
+            rdata::A,
+            RData,
+        },
+    };
+
+    fn lookup(proof: Proof) -> ResolverLookup {
+        let name = Name::from_str("www.example.com.").unwrap();
+        let mut a = Record::from_rdata(name.clone(), 300, RData::A(A::new(93, 184, 216, 34)));
+        a.set_proof(proof);
+        let rrsig = Record::from_rdata(
+            name.clone(),
+            300,
+            RRSIG::new(
+                RecordType::A,
+                Algorithm::ED25519,
+                3,
+                300,
+                0,
+                0,
+                0,
+                Name::from_str("example.com.").unwrap(),
+                vec![],
+            ),
+        )
+        .into_record_of_rdata();
+
+        ResolverLookup::new_with_deadline(
+            Query::query(name, RecordType::A),
+            Arc::from([a, rrsig]),
+            Instant::now() + Duration::from_secs(300),
+        )
+    }
+
+    #[test]
+    fn test_validated() {
+        let dnssec = LookupOptions::default().set_is_dnssec(true);
+
+        let secure = validated(lookup(Proof::Secure), dnssec).unwrap();
+        assert_eq!(secure.records().len(), 2);
+
+        // RRSIGs are only returned to clients which requested them
+        let secure = validated(lookup(Proof::Secure), LookupOptions::default()).unwrap();
+        assert_eq!(secure.records().len(), 1);
+        assert!(secure.records()[0].proof().is_secure());
+
+        let insecure = validated(lookup(Proof::Insecure), dnssec).unwrap();
+        assert_eq!(insecure.records().len(), 2);
+
+        assert!(matches!(
+            validated(lookup(Proof::Bogus), dnssec),
+            Err(LookupError::ResponseCode(ResponseCode::ServFail))
+        ));
+    }
+}
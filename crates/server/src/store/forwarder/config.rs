@@ -8,6 +8,7 @@
 use serde::Deserialize;
 
 use crate::resolver::config::{NameServerConfigGroup, ResolverOpts};
+use crate::store::forwarder::CachePartitionConfig;
 
 /// Configuration for file based zones
 #[derive(Clone, Deserialize, PartialEq, Eq, Debug)]
@@ -15,5 +16,13 @@ pub struct ForwardConfig {
     /// upstream name_server configurations
     pub name_servers: NameServerConfigGroup,
     /// Resolver options
+    ///
+    /// With `validate` set, answers are requested from upstream with the DO bit, and validated
+    ///  locally. Bogus answers are rejected, and the AD bit is only set for validated answers.
     pub options: Option<ResolverOpts>,
+    /// Separate caches for groups of clients, the first partition containing the client is used
+    ///
+    /// Clients in none of the partitions share the default cache.
+    #[serde(default)]
+    pub cache_partitions: Vec<CachePartitionConfig>,
 }
@@ -11,7 +11,11 @@
 
 mod authority;
 mod config;
+mod partition;
+mod rewrite;
 
 pub use self::authority::ForwardAuthority;
 pub use self::authority::ForwardLookup;
 pub use self::config::ForwardConfig;
+pub use self::partition::{CachePartitionConfig, CachePartitionStats};
+pub use self::rewrite::ResponseRewriter;
@@ -0,0 +1,143 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Separate caches for groups of clients of the forwarder
+
+use std::{
+    net::IpAddr,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use hickory_resolver::name_server::TokioConnectionProvider;
+use ipnet::IpNet;
+use serde::Deserialize;
+
+use crate::resolver::{
+    config::{ResolverConfig, ResolverOpts},
+    TokioAsyncResolver,
+};
+
+/// Configuration of a cache partition, used for all clients in its networks
+///
+/// Each partition has its own resolver cache, so answers tailored to a group of clients, e.g. by
+///  the policy of a `ResponseRewriter`, are never returned to clients of another group.
+#[derive(Clone, Deserialize, PartialEq, Eq, Debug)]
+pub struct CachePartitionConfig {
+    /// Name of the partition, used in logs and for its stats
+    pub name: String,
+    /// Source networks of the clients in this partition
+    pub networks: Vec<IpNet>,
+    /// Maximum number of cached answers, the `cache_size` of the resolver options if not set
+    pub cache_size: Option<usize>,
+}
+
+/// Counters of the requests answered from a cache partition
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CachePartitionStats {
+    /// Maximum number of cached answers in the partition
+    pub cache_size: usize,
+    /// Lookups of clients in the partition
+    pub lookups: u64,
+    /// Lookups of clients in the partition which failed
+    pub failures: u64,
+}
+
+/// A resolver with its own cache for the clients of a partition
+pub(crate) struct CachePartition {
+    name: String,
+    networks: Vec<IpNet>,
+    cache_size: usize,
+    resolver: TokioAsyncResolver,
+    lookups: AtomicU64,
+    failures: AtomicU64,
+}
+
+impl CachePartition {
+    /// Creates the partition, with a resolver for `config` which only differs in its cache size
+    pub(crate) fn new(
+        partition: &CachePartitionConfig,
+        config: ResolverConfig,
+        mut options: ResolverOpts,
+    ) -> Self {
+        if let Some(cache_size) = partition.cache_size {
+            options.cache_size = cache_size;
+        }
+        let cache_size = options.cache_size;
+
+        Self {
+            name: partition.name.clone(),
+            networks: partition.networks.clone(),
+            cache_size,
+            resolver: TokioAsyncResolver::new(config, options, TokioConnectionProvider::default()),
+            lookups: AtomicU64::new(0),
+            failures: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn resolver(&self) -> &TokioAsyncResolver {
+        &self.resolver
+    }
+
+    /// Returns true if the client address is in one of the networks of the partition
+    pub(crate) fn contains(&self, ip: &IpAddr) -> bool {
+        self.networks.iter().any(|network| network.contains(ip))
+    }
+
+    pub(crate) fn record_lookup(&self, success: bool) {
+        self.lookups.fetch_add(1, Ordering::Relaxed);
+        if !success {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn stats(&self) -> CachePartitionStats {
+        CachePartitionStats {
+            cache_size: self.cache_size,
+            lookups: self.lookups.load(Ordering::Relaxed),
+            failures: self.failures.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    #[test]
+    fn test_partition() {
+        let config = CachePartitionConfig {
+            name: "lab".to_string(),
+            networks: vec!["192.0.2.0/24".parse().unwrap()],
+            cache_size: Some(16),
+        };
+        let partition =
+            CachePartition::new(&config, ResolverConfig::default(), ResolverOpts::default());
+
+        assert_eq!(partition.name(), "lab");
+        assert!(partition.contains(&IpAddr::V4(Ipv4Addr::new(192, 0, 2, 7))));
+        assert!(!partition.contains(&IpAddr::V4(Ipv4Addr::new(198, 51, 100, 7))));
+        assert_eq!(partition.resolver().options().cache_size, 16);
+
+        partition.record_lookup(true);
+        partition.record_lookup(false);
+        assert_eq!(
+            partition.stats(),
+            CachePartitionStats {
+                cache_size: 16,
+                lookups: 2,
+                failures: 1,
+            }
+        );
+    }
+}
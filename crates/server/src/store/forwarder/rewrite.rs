@@ -0,0 +1,264 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Rewriting of forwarded answers based on the client of the request
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use tracing::debug;
+
+use crate::{
+    proto::{
+        op::LowerQuery,
+        rr::{LowerName, Record, RecordType},
+    },
+    resolver::lookup::Lookup as ResolverLookup,
+    server::RequestInfo,
+};
+
+/// A hook which may rewrite the answers received from the upstream resolvers before they are
+///  returned to the client.
+///
+/// This can be used to return answers tailored to the client, e.g. replacing the addresses of a
+///  CDN with those of a local cache node, or removing AAAA records for clients with broken IPv6
+///  connectivity.
+///
+/// Rewritten answers are never stored in the shared cache of the forwarder, which only ever holds
+///  the upstream answers. If a policy key is returned for the client, rewritten answers are
+///  cached for all clients which share that key.
+pub trait ResponseRewriter: Send + Sync {
+    /// Returns the key of the policy applied to this client
+    ///
+    /// All clients with the same key must receive the same rewritten answers for a query, as
+    ///  rewritten answers are cached per key. If `None`, the rewritten answers are not cached
+    ///  and `rewrite` is called for every request.
+    fn policy_key(&self, request_info: &RequestInfo<'_>) -> Option<String> {
+        let _ = request_info;
+        None
+    }
+
+    /// Rewrites the records of the upstream answer for the request
+    ///
+    /// # Return
+    ///
+    /// The records to return to the client, or `None` if the answer should be returned unchanged
+    fn rewrite(&self, request_info: &RequestInfo<'_>, records: &[Record]) -> Option<Vec<Record>>;
+}
+
+/// Applies a `ResponseRewriter` to lookups, caching the rewritten answers by policy key
+pub(crate) struct Rewriting {
+    rewriter: Arc<dyn ResponseRewriter>,
+    cache: Mutex<HashMap<(String, LowerName, RecordType), ResolverLookup>>,
+    capacity: usize,
+}
+
+impl Rewriting {
+    /// Creates a new `Rewriting`, caching at most `capacity` rewritten answers
+    pub(crate) fn new(rewriter: Arc<dyn ResponseRewriter>, capacity: usize) -> Self {
+        Self {
+            rewriter,
+            cache: Mutex::new(HashMap::new()),
+            capacity,
+        }
+    }
+
+    /// Returns the policy key for the request, see `ResponseRewriter::policy_key`
+    pub(crate) fn policy_key(&self, request_info: &RequestInfo<'_>) -> Option<String> {
+        self.rewriter.policy_key(request_info)
+    }
+
+    /// Returns the cached rewritten answer to the query for the policy key, if still valid
+    pub(crate) fn cached(
+        &self,
+        policy_key: &str,
+        query: &LowerQuery,
+        now: Instant,
+    ) -> Option<ResolverLookup> {
+        let mut cache = self.cache.lock().expect("rewrite cache poisoned");
+        let key = (
+            policy_key.to_string(),
+            query.name().clone(),
+            query.query_type(),
+        );
+
+        match cache.get(&key) {
+            Some(lookup) if now < lookup.valid_until() => Some(lookup.clone()),
+            Some(_) => {
+                cache.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Rewrites the upstream lookup for the request, caching the result under the policy key
+    pub(crate) fn rewrite(
+        &self,
+        request_info: &RequestInfo<'_>,
+        policy_key: Option<String>,
+        lookup: ResolverLookup,
+        now: Instant,
+    ) -> ResolverLookup {
+        let lookup = match self.rewriter.rewrite(request_info, lookup.records()) {
+            Some(records) => ResolverLookup::new_with_deadline(
+                lookup.query().clone(),
+                Arc::from(records),
+                lookup.valid_until(),
+            ),
+            None => lookup,
+        };
+
+        let Some(policy_key) = policy_key else {
+            return lookup;
+        };
+
+        let mut cache = self.cache.lock().expect("rewrite cache poisoned");
+        if cache.len() >= self.capacity {
+            cache.retain(|_, lookup| now < lookup.valid_until());
+        }
+        if cache.len() >= self.capacity {
+            debug!("rewrite cache full, clearing {} entries", cache.len());
+            cache.clear();
+        }
+
+        if self.capacity > 0 {
+            let query = request_info.query;
+            cache.insert(
+                (policy_key, query.name().clone(), query.query_type()),
+                lookup.clone(),
+            );
+        }
+
+        lookup
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{IpAddr, Ipv4Addr, SocketAddr},
+        str::FromStr,
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::{
+        proto::{
+            op::{Header, Query},
+            rr::{rdata::A, Name, RData},
+        },
+        server::Protocol,
+    };
+
+    /// Replaces all A records with 10.0.0.1 for clients in 192.0.2.0/24
+    struct LocalCache;
+
+    impl ResponseRewriter for LocalCache {
+        fn policy_key(&self, request_info: &RequestInfo<'_>) -> Option<String> {
+            match request_info.src.ip() {
+                IpAddr::V4(ip) if ip.octets()[..3] == [192, 0, 2] => Some("local".to_string()),
+                _ => None,
+            }
+        }
+
+        fn rewrite(
+            &self,
+            request_info: &RequestInfo<'_>,
+            records: &[Record],
+        ) -> Option<Vec<Record>> {
+            self.policy_key(request_info)?;
+
+            Some(
+                records
+                    .iter()
+                    .map(|record| {
+                        Record::from_rdata(
+                            record.name().clone(),
+                            record.ttl(),
+                            RData::A(A::new(10, 0, 0, 1)),
+                        )
+                    })
+                    .collect(),
+            )
+        }
+    }
+
+    fn upstream(query: &Query, now: Instant) -> ResolverLookup {
+        let record =
+            Record::from_rdata(query.name().clone(), 300, RData::A(A::new(203, 0, 113, 1)));
+        ResolverLookup::new_with_deadline(
+            query.clone(),
+            Arc::from([record]),
+            now + Duration::from_secs(300),
+        )
+    }
+
+    fn addresses(lookup: &ResolverLookup) -> Vec<RData> {
+        lookup.iter().cloned().collect()
+    }
+
+    #[test]
+    fn test_rewrite() {
+        let rewriting = Rewriting::new(Arc::new(LocalCache), 8);
+        let query = Query::query(Name::from_str("www.example.com.").unwrap(), RecordType::A);
+        let lower_query = LowerQuery::from(query.clone());
+        let header = Header::new();
+        let now = Instant::now();
+
+        let local_src = SocketAddr::from((Ipv4Addr::new(192, 0, 2, 7), 53));
+        let local = RequestInfo::new(local_src, Protocol::Udp, &header, &lower_query);
+        let remote_src = SocketAddr::from((Ipv4Addr::new(198, 51, 100, 7), 53));
+        let remote = RequestInfo::new(remote_src, Protocol::Udp, &header, &lower_query);
+
+        // clients without a policy get the upstream answer, and nothing is cached
+        let key = rewriting.policy_key(&remote);
+        assert!(key.is_none());
+        let lookup = rewriting.rewrite(&remote, key, upstream(&query, now), now);
+        assert_eq!(addresses(&lookup), vec![RData::A(A::new(203, 0, 113, 1))]);
+
+        // clients with a policy get the rewritten answer, which is cached for the policy
+        let key = rewriting.policy_key(&local).unwrap();
+        assert!(rewriting.cached(&key, &lower_query, now).is_none());
+        let lookup = rewriting.rewrite(&local, Some(key.clone()), upstream(&query, now), now);
+        assert_eq!(addresses(&lookup), vec![RData::A(A::new(10, 0, 0, 1))]);
+
+        let cached = rewriting.cached(&key, &lower_query, now).unwrap();
+        assert_eq!(addresses(&cached), vec![RData::A(A::new(10, 0, 0, 1))]);
+        assert!(rewriting.cached("other", &lower_query, now).is_none());
+
+        // expired answers are not returned
+        let later = now + Duration::from_secs(301);
+        assert!(rewriting.cached(&key, &lower_query, later).is_none());
+    }
+
+    #[test]
+    fn test_rewrite_cache_capacity() {
+        let rewriting = Rewriting::new(Arc::new(LocalCache), 1);
+        let header = Header::new();
+        let now = Instant::now();
+        let src = SocketAddr::from((Ipv4Addr::new(192, 0, 2, 7), 53));
+
+        let first = Query::query(Name::from_str("a.example.com.").unwrap(), RecordType::A);
+        let second = Query::query(Name::from_str("b.example.com.").unwrap(), RecordType::A);
+
+        for query in [&first, &second] {
+            let lower_query = LowerQuery::from(query.clone());
+            let request_info = RequestInfo::new(src, Protocol::Udp, &header, &lower_query);
+            let key = rewriting.policy_key(&request_info);
+            rewriting.rewrite(&request_info, key, upstream(query, now), now);
+        }
+
+        let first = LowerQuery::from(first);
+        let second = LowerQuery::from(second);
+        assert!(rewriting.cached("local", &first, now).is_none());
+        assert!(rewriting.cached("local", &second, now).is_some());
+    }
+}
@@ -0,0 +1,249 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    net::{IpAddr, Ipv4Addr},
+    path::Path,
+    sync::Arc,
+};
+
+use tracing::debug;
+
+use crate::{
+    authority::{AuthLookup, LookupError, LookupOptions, LookupRecords, ZoneType},
+    proto::rr::{LowerName, Name, RData, RecordSet, RecordType},
+    store::{
+        file::{FileAuthority, FileConfig},
+        geo::{GeoConfig, Locate, Location, MmdbLocator},
+        in_memory::InMemoryAuthority,
+    },
+};
+
+/// The records of a name for the clients at some locations
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GeoPool {
+    /// the name of the pool, e.g. `europe`
+    pub name: String,
+    /// the codes of the continents of the clients of the pool, e.g. `EU`
+    pub continents: Vec<String>,
+    /// the ISO 3166-1 codes of the countries of the clients of the pool, e.g. `DE`
+    pub countries: Vec<String>,
+    /// the autonomous systems of the clients of the pool
+    pub asns: Vec<u32>,
+    /// the share of the clients at no location of any pool which are answered by this pool
+    pub weight: u32,
+    /// the records of the pool
+    pub records: Vec<RData>,
+}
+
+impl GeoPool {
+    /// How closely the location matches the pool, `None` if it does not
+    fn specificity(&self, location: &Location) -> Option<u8> {
+        let contains = |codes: &[String], code: &Option<String>| {
+            code.as_ref().map_or(false, |code| {
+                codes.iter().any(|c| c.eq_ignore_ascii_case(code))
+            })
+        };
+
+        if location.asn.map_or(false, |asn| self.asns.contains(&asn)) {
+            Some(3)
+        } else if contains(&self.countries, &location.country) {
+            Some(2)
+        } else if contains(&self.continents, &location.continent) {
+            Some(1)
+        } else {
+            None
+        }
+    }
+}
+
+/// The pools of a name, with their records
+struct GeoName {
+    pools: Vec<GeoPool>,
+    /// the record sets of each of the pools
+    rrsets: Vec<Vec<Arc<RecordSet>>>,
+}
+
+impl GeoName {
+    /// The pool of the client, which is the pool of its most specific location, or one of the
+    ///  pools by their weights
+    ///
+    /// The pool chosen by weight is the same for each address.
+    fn select(&self, location: &Location, addr: IpAddr) -> Option<usize> {
+        let closest = self
+            .pools
+            .iter()
+            .enumerate()
+            .filter_map(|(index, pool)| Some((pool.specificity(location)?, index)))
+            // the first of the pools with the same specificity
+            .max_by_key(|(specificity, index)| (*specificity, usize::MAX - index));
+        if let Some((_, index)) = closest {
+            return Some(index);
+        }
+
+        let total = self
+            .pools
+            .iter()
+            .map(|pool| u64::from(pool.weight))
+            .sum::<u64>();
+        if total == 0 {
+            return None;
+        }
+
+        let mut hasher = DefaultHasher::new();
+        addr.hash(&mut hasher);
+        let mut point = hasher.finish() % total;
+        for (index, pool) in self.pools.iter().enumerate() {
+            let weight = u64::from(pool.weight);
+            if point < weight {
+                return Some(index);
+            }
+            point -= weight;
+        }
+
+        None
+    }
+}
+
+/// A zone with names whose records depend on the location of the client, i.e. GeoDNS
+///
+/// The records of the other names of the zone are answered as usual. The location of the client
+///  is that of the address in its EDNS Client Subnet option, or of its source address, which is
+///  found with a `Locate`, e.g. the MaxMind databases of `MmdbLocator`. Lookups without a client,
+///  e.g. of the additional records, use the pools by their weights.
+pub struct GeoAuthority {
+    zone: InMemoryAuthority,
+    locator: Arc<dyn Locate>,
+    names: HashMap<LowerName, GeoName>,
+}
+
+impl GeoAuthority {
+    /// Creates the authority of the zone, without any names with pools
+    pub fn new(zone: InMemoryAuthority, locator: Arc<dyn Locate>) -> Self {
+        Self {
+            zone,
+            locator,
+            names: HashMap::new(),
+        }
+    }
+
+    /// Read the zone file and the pools of the configuration
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - The name of the zone
+    /// * `zone_type` - The type of zone, must be `ZoneType::Primary`
+    /// * `allow_axfr` - Whether zone transfers of the records of the zone file are allowed
+    /// * `root_dir` - The root directory of the zone file and the databases
+    /// * `config` - The configuration of the zone file and of the pools
+    pub fn try_from_config(
+        origin: Name,
+        zone_type: ZoneType,
+        allow_axfr: bool,
+        root_dir: Option<&Path>,
+        config: &GeoConfig,
+    ) -> Result<Self, String> {
+        let file_config = FileConfig {
+            zone_file_path: config.zone_file_path.clone(),
+        };
+        let zone = FileAuthority::try_from_config(
+            origin.clone(),
+            zone_type,
+            allow_axfr,
+            root_dir,
+            &file_config,
+        )?;
+
+        let databases = config
+            .databases
+            .iter()
+            .map(|path| root_dir.map_or_else(|| path.clone(), |dir| dir.join(path)))
+            .collect::<Vec<_>>();
+        let locator = MmdbLocator::open(&databases)?;
+
+        let mut authority = Self::new(zone.unwrap(), Arc::new(locator));
+        for name in &config.names {
+            let (geo_name, ttl, pools) = name.to_pools(&origin)?;
+            authority.insert(geo_name, ttl, pools);
+        }
+
+        Ok(authority)
+    }
+
+    /// Sets the pools of the name, which replace its records in the zone
+    pub fn insert(&mut self, name: Name, ttl: u32, pools: Vec<GeoPool>) {
+        let rrsets = pools
+            .iter()
+            .map(|pool| {
+                let mut rrsets = HashMap::<RecordType, RecordSet>::new();
+                for rdata in &pool.records {
+                    rrsets
+                        .entry(rdata.record_type())
+                        .or_insert_with(|| {
+                            RecordSet::with_ttl(name.clone(), rdata.record_type(), ttl)
+                        })
+                        .add_rdata(rdata.clone());
+                }
+
+                let mut rrsets = rrsets.into_values().map(Arc::new).collect::<Vec<_>>();
+                rrsets.sort_by_key(|rrset| rrset.record_type());
+                rrsets
+            })
+            .collect();
+
+        self.names
+            .insert(LowerName::new(&name), GeoName { pools, rrsets });
+    }
+
+    /// The records of the pool of the client, if the name has pools
+    fn geo_lookup(
+        &self,
+        name: &LowerName,
+        rtype: RecordType,
+        client: Option<IpAddr>,
+        lookup_options: LookupOptions,
+    ) -> Option<Result<AuthLookup, LookupError>> {
+        let geo_name = self.names.get(name)?;
+        // lookups outside of a request have no location
+        let addr = client.unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        let location = self.locator.locate(addr);
+        let Some(index) = geo_name.select(&location, addr) else {
+            return Some(Err(LookupError::NameExists));
+        };
+        debug!(
+            "answering {} for {:?} from pool {}",
+            name, location, geo_name.pools[index].name
+        );
+
+        let rrsets = &geo_name.rrsets[index];
+        let cname = rrsets
+            .iter()
+            .find(|rrset| rrset.record_type() == RecordType::CNAME);
+        let rrsets = match (rtype, cname) {
+            (RecordType::ANY, _) => rrsets.clone(),
+            (RecordType::CNAME, _) | (_, None) => rrsets
+                .iter()
+                .filter(|rrset| rrset.record_type() == rtype)
+                .cloned()
+                .collect(),
+            // the CNAME of the name answers the queries of all types
+            (_, Some(cname)) => vec![Arc::clone(cname)],
+        };
+
+        if rrsets.is_empty() {
+            return Some(Err(LookupError::NameExists));
+        }
+        Some(Ok(AuthLookup::answers(
+            LookupRecords::many(lookup_options, rrsets),
+            None,
+        )))
+    }
+}
+
+pool_authority!(GeoAuthority, geo_lookup);
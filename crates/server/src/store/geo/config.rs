@@ -0,0 +1,135 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::{path::PathBuf, str::FromStr};
+
+use serde::Deserialize;
+
+use crate::{
+    proto::rr::{Name, RData, RecordType},
+    proto::serialize::txt::RDataParser,
+    store::geo::GeoPool,
+};
+
+static DEFAULT_TTL: u32 = 60;
+static DEFAULT_WEIGHT: u32 = 1;
+
+/// Configuration of a zone file with names whose records depend on the location of the client
+///
+/// ```toml
+/// [[zones]]
+/// zone = "example.com"
+/// zone_type = "Primary"
+///
+/// [zones.stores]
+/// type = "geo"
+/// zone_file_path = "example.com.zone"
+/// databases = ["GeoLite2-Country.mmdb", "GeoLite2-ASN.mmdb"]
+///
+/// [[zones.stores.names]]
+/// name = "www"
+/// ttl = 60
+/// pools = [
+///     { name = "europe", continents = ["EU"], records = ["A 192.0.2.1"] },
+///     { name = "americas", continents = ["NA", "SA"], weight = 2, records = ["A 192.0.2.2"] },
+/// ]
+/// ```
+#[derive(Clone, Deserialize, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct GeoConfig {
+    /// path to the zone file of the other names of the zone
+    pub zone_file_path: String,
+    /// paths to the MaxMind databases of the locations, e.g. of countries and autonomous systems
+    pub databases: Vec<PathBuf>,
+    /// the names whose records depend on the location of the client
+    #[serde(default)]
+    pub names: Vec<GeoNameConfig>,
+}
+
+/// Configuration of the pools of a name
+#[derive(Clone, Deserialize, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct GeoNameConfig {
+    /// the name, relative to the zone unless fully qualified
+    pub name: String,
+    /// the TTL of the records, defaults to 60 seconds
+    pub ttl: Option<u32>,
+    /// the pools of the records, the pool of the most specific location of the client answers
+    pub pools: Vec<GeoPoolConfig>,
+}
+
+/// Configuration of a pool of the records of a name
+#[derive(Clone, Deserialize, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct GeoPoolConfig {
+    /// the name of the pool, which is logged
+    pub name: String,
+    /// the codes of the continents of the clients of the pool, e.g. `EU`
+    #[serde(default)]
+    pub continents: Vec<String>,
+    /// the ISO 3166-1 codes of the countries of the clients of the pool, e.g. `DE`
+    #[serde(default)]
+    pub countries: Vec<String>,
+    /// the autonomous systems of the clients of the pool
+    #[serde(default)]
+    pub asns: Vec<u32>,
+    /// the share of the clients at no location of any pool, defaults to 1, 0 never answers them
+    pub weight: Option<u32>,
+    /// the records as their type and data, e.g. `A 192.0.2.1` or `CNAME eu.example.net.`, names in
+    ///  the data must be fully qualified
+    pub records: Vec<String>,
+}
+
+impl GeoNameConfig {
+    /// The name in the zone, the TTL and the pools
+    pub fn to_pools(&self, origin: &Name) -> Result<(Name, u32, Vec<GeoPool>), String> {
+        let name = Name::from_str(&self.name)
+            .and_then(|name| {
+                if name.is_fqdn() {
+                    Ok(name)
+                } else {
+                    name.append_domain(origin)
+                }
+            })
+            .map_err(|e| format!("bad geo name {}: {e}", self.name))?;
+
+        let pools = self
+            .pools
+            .iter()
+            .map(|pool| {
+                let records = pool
+                    .records
+                    .iter()
+                    .map(|record| parse_record(record))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| format!("bad record of pool {} of {name}: {e}", pool.name))?;
+
+                Ok(GeoPool {
+                    name: pool.name.clone(),
+                    continents: pool.continents.clone(),
+                    countries: pool.countries.clone(),
+                    asns: pool.asns.clone(),
+                    weight: pool.weight.unwrap_or(DEFAULT_WEIGHT),
+                    records,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok((name, self.ttl.unwrap_or(DEFAULT_TTL), pools))
+    }
+}
+
+/// The data of a record from its type and data, e.g. `A 192.0.2.1`
+fn parse_record(record: &str) -> Result<RData, String> {
+    let (record_type, rdata) = record
+        .trim()
+        .split_once(char::is_whitespace)
+        .ok_or_else(|| format!("{record} has no data"))?;
+    let record_type = RecordType::from_str(record_type).map_err(|e| format!("{record}: {e}"))?;
+
+    RData::try_from_str(record_type, rdata.trim()).map_err(|e| format!("{record}: {e}"))
+}
@@ -0,0 +1,83 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::{net::IpAddr, path::Path};
+
+use maxminddb::{geoip2, MaxMindDBError, Reader};
+
+/// The location of a client address
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Location {
+    /// the code of the continent, e.g. `EU`
+    pub continent: Option<String>,
+    /// the ISO 3166-1 code of the country, e.g. `DE`
+    pub country: Option<String>,
+    /// the number of the autonomous system of the address
+    pub asn: Option<u32>,
+}
+
+/// Finds the locations of client addresses
+pub trait Locate: Send + Sync {
+    /// The location of the address, which is empty if it is unknown
+    fn locate(&self, addr: IpAddr) -> Location;
+}
+
+/// The locations of MaxMind databases, e.g. GeoLite2 Country and ASN
+///
+/// The location of an address combines the records of all the databases.
+pub struct MmdbLocator {
+    readers: Vec<Reader<Vec<u8>>>,
+}
+
+impl MmdbLocator {
+    /// Opens the databases
+    pub fn open<P: AsRef<Path>>(paths: &[P]) -> Result<Self, String> {
+        let readers = paths
+            .iter()
+            .map(|path| {
+                Reader::open_readfile(path).map_err(|e| {
+                    format!(
+                        "could not open geoip database {}: {e}",
+                        path.as_ref().display()
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { readers })
+    }
+}
+
+impl Locate for MmdbLocator {
+    fn locate(&self, addr: IpAddr) -> Location {
+        let mut location = Location::default();
+
+        for reader in &self.readers {
+            // the record types of the databases differ, and their fields are optional
+            match reader.lookup::<geoip2::Country<'_>>(addr) {
+                Ok(country) => {
+                    if let Some(code) = country.continent.and_then(|continent| continent.code) {
+                        location.continent.get_or_insert_with(|| code.to_string());
+                    }
+                    if let Some(code) = country.country.and_then(|country| country.iso_code) {
+                        location.country.get_or_insert_with(|| code.to_string());
+                    }
+                }
+                Err(MaxMindDBError::AddressNotFoundError(_)) => continue,
+                Err(_) => (),
+            }
+
+            if let Ok(asn) = reader.lookup::<geoip2::Asn<'_>>(addr) {
+                if location.asn.is_none() {
+                    location.asn = asn.autonomous_system_number;
+                }
+            }
+        }
+
+        location
+    }
+}
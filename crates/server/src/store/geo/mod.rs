@@ -0,0 +1,20 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Zones with names whose records depend on the location of the client, i.e. GeoDNS
+//!
+//! The records of a name are grouped in the pools of a `GeoAuthority`, and a query is answered
+//!  from the pool of the most specific location of the client, by autonomous system, country and
+//!  continent. The locations are found in MaxMind databases by `MmdbLocator`.
+
+mod authority;
+mod config;
+mod location;
+
+pub use self::authority::{GeoAuthority, GeoPool};
+pub use self::config::{GeoConfig, GeoNameConfig, GeoPoolConfig};
+pub use self::location::{Locate, Location, MmdbLocator};
@@ -0,0 +1,258 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use futures_util::future::join_all;
+use tracing::{debug, info, warn};
+
+use crate::{
+    authority::{AuthLookup, LookupError, LookupOptions, LookupRecords, ZoneType},
+    proto::rr::{LowerName, Name, RData, RecordSet, RecordType},
+    store::{
+        file::{FileAuthority, FileConfig},
+        health::{HealthConfig, HealthPoolConfig},
+        in_memory::InMemoryAuthority,
+    },
+};
+
+/// The results of the last checks of a member
+#[derive(Debug)]
+struct Health {
+    healthy: bool,
+    passed: u32,
+    failed: u32,
+}
+
+/// A member of a pool
+struct Member {
+    addr: IpAddr,
+    health: Mutex<Health>,
+}
+
+/// The members of a name
+struct Pool {
+    name: Name,
+    config: HealthPoolConfig,
+    members: Vec<Member>,
+}
+
+impl Pool {
+    /// Checks each member once, and withdraws or restores it after enough successive results
+    async fn check(&self) {
+        let timeout = self.config.get_timeout();
+        let results = join_all(
+            self.members
+                .iter()
+                .map(|member| self.config.probe.check(member.addr, timeout)),
+        )
+        .await;
+
+        for (member, result) in self.members.iter().zip(results) {
+            let mut health = match member.health.lock() {
+                Ok(health) => health,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+
+            match result {
+                Ok(()) => {
+                    health.passed = health.passed.saturating_add(1);
+                    health.failed = 0;
+                    if !health.healthy && health.passed >= self.config.get_healthy_threshold() {
+                        info!("restoring {} of {}", member.addr, self.name);
+                        health.healthy = true;
+                    }
+                }
+                Err(e) => {
+                    debug!("check of {} of {} failed: {}", member.addr, self.name, e);
+                    health.failed = health.failed.saturating_add(1);
+                    health.passed = 0;
+                    if health.healthy && health.failed >= self.config.get_unhealthy_threshold() {
+                        warn!("withdrawing {} of {}: {}", member.addr, self.name, e);
+                        health.healthy = false;
+                    }
+                }
+            }
+        }
+    }
+
+    /// The addresses of the healthy members, or the fallback while no member is healthy
+    fn addrs(&self) -> Vec<IpAddr> {
+        let healthy = self
+            .members
+            .iter()
+            .filter(|member| match member.health.lock() {
+                Ok(health) => health.healthy,
+                Err(poisoned) => poisoned.into_inner().healthy,
+            })
+            .map(|member| member.addr)
+            .collect::<Vec<_>>();
+
+        match (healthy.is_empty(), self.config.fallback.is_empty()) {
+            (false, _) => healthy,
+            (true, false) => self.config.fallback.clone(),
+            // answering all of the members is better than answering none
+            (true, true) => self.members.iter().map(|member| member.addr).collect(),
+        }
+    }
+
+    /// The record set of the addresses of the type
+    fn records(&self, record_type: RecordType) -> Option<Arc<RecordSet>> {
+        let mut records =
+            RecordSet::with_ttl(self.name.clone(), record_type, self.config.get_ttl());
+        for addr in self.addrs() {
+            match (record_type, addr) {
+                (RecordType::A, IpAddr::V4(addr)) => records.add_rdata(RData::A(addr.into())),
+                (RecordType::AAAA, IpAddr::V6(addr)) => records.add_rdata(RData::AAAA(addr.into())),
+                _ => continue,
+            };
+        }
+
+        if records.is_empty() {
+            None
+        } else {
+            Some(Arc::new(records))
+        }
+    }
+}
+
+/// A zone with names whose addresses are answered while they are healthy
+///
+/// The A and AAAA records of a pool are those of its healthy members, with the TTL of the pool.
+///  The records of the other names of the zone are answered as usual. The checks of the members
+///  are run by `run_checks`, until the first checks fail all members are healthy.
+pub struct HealthAuthority {
+    zone: InMemoryAuthority,
+    pools: HashMap<LowerName, Pool>,
+}
+
+impl HealthAuthority {
+    /// Creates the authority of the zone, without any pools
+    pub fn new(zone: InMemoryAuthority) -> Self {
+        Self {
+            zone,
+            pools: HashMap::new(),
+        }
+    }
+
+    /// Read the zone file and the pools of the configuration
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - The name of the zone
+    /// * `zone_type` - The type of zone, must be `ZoneType::Primary`
+    /// * `allow_axfr` - Whether zone transfers of the records of the zone file are allowed
+    /// * `root_dir` - The root directory of the zone file
+    /// * `config` - The configuration of the zone file and of the pools
+    pub fn try_from_config(
+        origin: Name,
+        zone_type: ZoneType,
+        allow_axfr: bool,
+        root_dir: Option<&Path>,
+        config: &HealthConfig,
+    ) -> Result<Self, String> {
+        let file_config = FileConfig {
+            zone_file_path: config.zone_file_path.clone(),
+        };
+        let zone = FileAuthority::try_from_config(
+            origin.clone(),
+            zone_type,
+            allow_axfr,
+            root_dir,
+            &file_config,
+        )?;
+
+        let mut authority = Self::new(zone.unwrap());
+        for pool in &config.pools {
+            authority.insert(pool.get_name(&origin)?, pool.clone());
+        }
+
+        Ok(authority)
+    }
+
+    /// Sets the pool of the name, which replaces its A and AAAA records in the zone
+    pub fn insert(&mut self, name: Name, config: HealthPoolConfig) {
+        let members = config
+            .members
+            .iter()
+            .map(|addr| Member {
+                addr: *addr,
+                health: Mutex::new(Health {
+                    healthy: true,
+                    passed: 0,
+                    failed: 0,
+                }),
+            })
+            .collect();
+
+        self.pools.insert(
+            LowerName::new(&name),
+            Pool {
+                name,
+                config,
+                members,
+            },
+        );
+    }
+
+    /// The addresses answered for the name, if it has a pool
+    pub fn addrs(&self, name: &LowerName) -> Option<Vec<IpAddr>> {
+        self.pools.get(name).map(Pool::addrs)
+    }
+
+    /// Checks each member of each pool once
+    pub async fn check(&self) {
+        join_all(self.pools.values().map(Pool::check)).await;
+    }
+
+    /// Checks the members of the pools at their intervals, this never returns
+    pub async fn run_checks(self: Arc<Self>) {
+        join_all(self.pools.values().map(|pool| async move {
+            let mut interval = tokio::time::interval(pool.config.get_interval());
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                pool.check().await;
+            }
+        }))
+        .await;
+    }
+
+    /// The records of the healthy members, if the name has a pool
+    fn pool_lookup(
+        &self,
+        name: &LowerName,
+        rtype: RecordType,
+        _client: Option<IpAddr>,
+        lookup_options: LookupOptions,
+    ) -> Option<Result<AuthLookup, LookupError>> {
+        let pool = self.pools.get(name)?;
+        let rrsets = match rtype {
+            RecordType::A | RecordType::AAAA => pool.records(rtype).into_iter().collect(),
+            RecordType::ANY => [RecordType::A, RecordType::AAAA]
+                .into_iter()
+                .filter_map(|rtype| pool.records(rtype))
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        if rrsets.is_empty() {
+            return Some(Err(LookupError::NameExists));
+        }
+        Some(Ok(AuthLookup::answers(
+            LookupRecords::many(lookup_options, rrsets),
+            None,
+        )))
+    }
+}
+
+pool_authority!(HealthAuthority, pool_lookup);
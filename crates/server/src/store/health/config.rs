@@ -0,0 +1,114 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::{net::IpAddr, str::FromStr, time::Duration};
+
+use serde::Deserialize;
+
+use crate::{proto::rr::Name, store::health::Probe};
+
+static DEFAULT_TTL: u32 = 30;
+static DEFAULT_INTERVAL: u64 = 10;
+static DEFAULT_TIMEOUT: u64 = 2;
+static DEFAULT_HEALTHY_THRESHOLD: u32 = 2;
+static DEFAULT_UNHEALTHY_THRESHOLD: u32 = 3;
+
+/// Configuration of a zone file with names whose addresses are answered while they are healthy
+///
+/// ```toml
+/// [[zones]]
+/// zone = "example.com"
+/// zone_type = "Primary"
+///
+/// [zones.stores]
+/// type = "health"
+/// zone_file_path = "example.com.zone"
+///
+/// [[zones.stores.pools]]
+/// name = "www"
+/// members = ["192.0.2.1", "192.0.2.2", "2001:db8::1"]
+/// fallback = ["198.51.100.1"]
+/// probe = { type = "http", port = 80, path = "/health" }
+/// ```
+#[derive(Clone, Deserialize, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct HealthConfig {
+    /// path to the zone file of the other names of the zone
+    pub zone_file_path: String,
+    /// the names whose addresses are checked
+    #[serde(default)]
+    pub pools: Vec<HealthPoolConfig>,
+}
+
+/// Configuration of the members of a name, and of their checks
+#[derive(Clone, Deserialize, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct HealthPoolConfig {
+    /// the name, relative to the zone unless fully qualified
+    pub name: String,
+    /// the TTL of the A and AAAA records, defaults to 30 seconds
+    pub ttl: Option<u32>,
+    /// the addresses of the members, which are answered while they are healthy
+    pub members: Vec<IpAddr>,
+    /// the addresses answered while no member is healthy, all the members if empty
+    #[serde(default)]
+    pub fallback: Vec<IpAddr>,
+    /// the check of the members
+    pub probe: Probe,
+    /// the time in seconds between the checks of a member, defaults to 10 seconds
+    pub interval: Option<u64>,
+    /// the time in seconds before a check fails, defaults to 2 seconds
+    pub timeout: Option<u64>,
+    /// the number of successive passed checks which restore a member, defaults to 2
+    pub healthy_threshold: Option<u32>,
+    /// the number of successive failed checks which withdraw a member, defaults to 3
+    pub unhealthy_threshold: Option<u32>,
+}
+
+impl HealthPoolConfig {
+    /// the name in the zone
+    pub fn get_name(&self, origin: &Name) -> Result<Name, String> {
+        Name::from_str(&self.name)
+            .and_then(|name| {
+                if name.is_fqdn() {
+                    Ok(name)
+                } else {
+                    name.append_domain(origin)
+                }
+            })
+            .map_err(|e| format!("bad pool name {}: {e}", self.name))
+    }
+
+    /// the TTL of the records
+    pub fn get_ttl(&self) -> u32 {
+        self.ttl.unwrap_or(DEFAULT_TTL)
+    }
+
+    /// the time between the checks of a member
+    pub fn get_interval(&self) -> Duration {
+        Duration::from_secs(self.interval.unwrap_or(DEFAULT_INTERVAL))
+    }
+
+    /// the time before a check fails
+    pub fn get_timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout.unwrap_or(DEFAULT_TIMEOUT))
+    }
+
+    /// the number of successive passed checks which restore a member
+    pub fn get_healthy_threshold(&self) -> u32 {
+        self.healthy_threshold
+            .unwrap_or(DEFAULT_HEALTHY_THRESHOLD)
+            .max(1)
+    }
+
+    /// the number of successive failed checks which withdraw a member
+    pub fn get_unhealthy_threshold(&self) -> u32 {
+        self.unhealthy_threshold
+            .unwrap_or(DEFAULT_UNHEALTHY_THRESHOLD)
+            .max(1)
+    }
+}
@@ -0,0 +1,20 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Zones with names whose addresses are answered while they are healthy, i.e. failover pools
+//!
+//! The members of a pool are checked by a `Probe` in the background, see
+//!  `HealthAuthority::run_checks`. A member is withdrawn from the answers after successive failed
+//!  checks, and restored after successive passed checks.
+
+mod authority;
+mod config;
+mod probe;
+
+pub use self::authority::HealthAuthority;
+pub use self::config::{HealthConfig, HealthPoolConfig};
+pub use self::probe::Probe;
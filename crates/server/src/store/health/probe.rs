@@ -0,0 +1,240 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::{
+    io,
+    net::{IpAddr, SocketAddr},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Deserialize;
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpStream, UdpSocket},
+};
+
+/// A check of the health of a member of a pool
+///
+/// ```toml
+/// probe = { type = "tcp", port = 443 }
+/// probe = { type = "http", port = 8080, path = "/health", host = "www.example.com" }
+/// probe = { type = "icmp" }
+/// ```
+#[derive(Clone, Deserialize, PartialEq, Eq, Debug)]
+#[serde(tag = "type")]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum Probe {
+    /// The member accepts TCP connections on the port
+    Tcp {
+        /// the port of the connections
+        port: u16,
+    },
+    /// The member answers a GET request over HTTP/1.1 with a 2xx or 3xx status
+    Http {
+        /// the port of the server, defaults to 80
+        port: Option<u16>,
+        /// the path of the request, defaults to `/`
+        path: Option<String>,
+        /// the Host header of the request, defaults to the address of the member
+        host: Option<String>,
+    },
+    /// The member answers an ICMP echo request
+    ///
+    /// The request is sent from an unprivileged ICMP socket, which on Linux requires the group of
+    ///  the server to be in `net.ipv4.ping_group_range`.
+    Icmp,
+}
+
+impl Probe {
+    /// Checks the health of the member at the address, failing after the timeout
+    pub async fn check(&self, addr: IpAddr, timeout: Duration) -> io::Result<()> {
+        match tokio::time::timeout(timeout, self.probe(addr)).await {
+            Ok(result) => result,
+            Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "probe timed out")),
+        }
+    }
+
+    async fn probe(&self, addr: IpAddr) -> io::Result<()> {
+        match self {
+            Self::Tcp { port } => TcpStream::connect(SocketAddr::new(addr, *port))
+                .await
+                .map(drop),
+            Self::Http { port, path, host } => {
+                let host = host.clone().unwrap_or_else(|| match addr {
+                    IpAddr::V4(addr) => addr.to_string(),
+                    IpAddr::V6(addr) => format!("[{addr}]"),
+                });
+                http_get(
+                    SocketAddr::new(addr, port.unwrap_or(80)),
+                    &host,
+                    path.as_deref().unwrap_or("/"),
+                )
+                .await
+            }
+            Self::Icmp => ping(addr).await,
+        }
+    }
+}
+
+/// Sends a GET request, which succeeds with a 2xx or 3xx status
+async fn http_get(addr: SocketAddr, host: &str, path: &str) -> io::Result<()> {
+    let mut stream = TcpStream::connect(addr).await?;
+    let request = format!(
+        "GET {path} HTTP/1.1\r\nHost: {host}\r\nUser-Agent: hickory-dns\r\nConnection: close\r\n\r\n"
+    );
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut status_line = String::new();
+    BufReader::new(stream).read_line(&mut status_line).await?;
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse::<u16>().ok())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("bad status line: {}", status_line.trim_end()),
+            )
+        })?;
+
+    if (200..400).contains(&status) {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("HTTP status {status}"),
+        ))
+    }
+}
+
+/// Sends an ICMP echo request, and waits for its reply
+async fn ping(addr: IpAddr) -> io::Result<()> {
+    let (domain, protocol, echo_request, echo_reply) = match addr {
+        IpAddr::V4(_) => (Domain::IPV4, Protocol::ICMPV4, 8, 0),
+        IpAddr::V6(_) => (Domain::IPV6, Protocol::ICMPV6, 128, 129),
+    };
+
+    let socket = Socket::new(domain, Type::DGRAM, Some(protocol))?;
+    socket.set_nonblocking(true)?;
+    let socket = UdpSocket::from_std(socket.into())?;
+
+    // the identifier is set by the kernel, the sequence tells the replies of the probes apart
+    let sequence = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.subsec_nanos() as u16)
+        .to_be_bytes();
+    let mut request = [
+        echo_request,
+        0,
+        0,
+        0,
+        0,
+        0,
+        sequence[0],
+        sequence[1],
+        b'h',
+        b'i',
+        b'c',
+        b'k',
+        b'o',
+        b'r',
+        b'y',
+        0,
+    ];
+    // the kernel replaces the checksum of ICMPv6
+    let checksum = checksum(&request).to_be_bytes();
+    request[2..4].copy_from_slice(&checksum);
+    socket.send_to(&request, SocketAddr::new(addr, 0)).await?;
+
+    let mut buf = [0_u8; 1500];
+    loop {
+        let (len, from) = socket.recv_from(&mut buf).await?;
+        let mut reply = &buf[..len];
+        // some systems include the IPv4 header
+        if addr.is_ipv4() && reply.first().map_or(false, |b| b >> 4 == 4) {
+            let header_len = usize::from(reply[0] & 0x0f) * 4;
+            reply = reply.get(header_len..).unwrap_or_default();
+        }
+
+        if from.ip() == addr
+            && reply.len() >= 8
+            && reply[0] == echo_reply
+            && reply[6..8] == sequence
+        {
+            return Ok(());
+        }
+    }
+}
+
+/// The internet checksum of the ICMP message, see RFC 1071
+fn checksum(message: &[u8]) -> u16 {
+    let mut sum = message
+        .chunks(2)
+        .map(|word| u32::from(word[0]) << 8 | u32::from(*word.get(1).unwrap_or(&0)))
+        .sum::<u32>();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+
+    !(sum as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use tokio::{io::AsyncReadExt, net::TcpListener};
+
+    use super::*;
+
+    /// A server which answers each request with the status
+    async fn http_server(status: &'static str) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = [0_u8; 1024];
+                let _ = stream.read(&mut request).await;
+                let response = format!("HTTP/1.1 {status}\r\nContent-Length: 0\r\n\r\n");
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_http() {
+        let timeout = Duration::from_secs(5);
+        let localhost = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+        let ok = http_server("200 OK").await;
+        let probe = Probe::Http {
+            port: Some(ok.port()),
+            path: Some("/health".to_string()),
+            host: None,
+        };
+        probe.check(localhost, timeout).await.unwrap();
+
+        let unavailable = http_server("503 Service Unavailable").await;
+        let probe = Probe::Http {
+            port: Some(unavailable.port()),
+            path: None,
+            host: None,
+        };
+        assert!(probe.check(localhost, timeout).await.is_err());
+    }
+
+    #[test]
+    fn test_checksum() {
+        // an echo request with the identifier 1 and the sequence 1
+        let request = [8, 0, 0, 0, 0, 1, 0, 1];
+        assert_eq!(checksum(&request), 0xf7fd);
+        assert_eq!(checksum(&[8, 0, 0xf7, 0xfd, 0, 1, 0, 1]), 0);
+    }
+}
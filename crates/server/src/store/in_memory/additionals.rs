@@ -0,0 +1,141 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::{sync::Arc, time::Duration};
+
+use futures_util::StreamExt;
+use tokio::time::Instant;
+use tracing::debug;
+
+use crate::{
+    authority::{LookupOptions, LookupRecords},
+    config::additionals::AdditionalsConfig,
+    proto::rr::{LowerName, Name, RData, RecordSet, RecordType},
+    resolver::{config::ResolverConfig, name_server::TokioConnectionProvider, TokioAsyncResolver},
+};
+
+/// Resolves the addresses of the targets of MX and SRV answers which are outside of the zone, for
+///  the additional section
+///
+/// The addresses which are resolved before the timeout are added to the additional records of the
+///  answer, the others are left out. The resolver caches the addresses for their TTLs, see
+///  `ResolverOpts::cache_size`.
+pub struct AdditionalResolver {
+    resolver: TokioAsyncResolver,
+    timeout: Duration,
+}
+
+impl AdditionalResolver {
+    /// Creates the resolver of the additional addresses
+    ///
+    /// # Arguments
+    ///
+    /// * `resolver` - The resolver of the addresses of the targets
+    /// * `timeout` - The time after which the answer is sent without the addresses
+    pub fn new(resolver: TokioAsyncResolver, timeout: Duration) -> Self {
+        Self { resolver, timeout }
+    }
+
+    /// Creates the resolver of the configuration
+    pub fn try_from_config(config: &AdditionalsConfig) -> Result<Self, String> {
+        let resolver = match config.get_name_servers() {
+            Some(name_servers) => TokioAsyncResolver::new(
+                ResolverConfig::from_parts(None, vec![], name_servers.clone()),
+                config.get_options(),
+                TokioConnectionProvider::default(),
+            ),
+            None => TokioAsyncResolver::tokio_from_system_conf()
+                .map_err(|e| format!("error constructing the additionals resolver: {e}"))?,
+        };
+
+        Ok(Self::new(resolver, config.get_timeout()))
+    }
+
+    /// Adds the addresses of the targets of the answers outside of the zone to the additionals
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - The name of the zone, the addresses of its names are not resolved
+    /// * `answers` - The answers, with the MX and SRV records of the targets
+    /// * `additionals` - The additional records of the zone
+    /// * `lookup_options` - The options of the lookup of the answers
+    pub async fn complete(
+        &self,
+        origin: &LowerName,
+        answers: &LookupRecords,
+        additionals: Option<LookupRecords>,
+        lookup_options: LookupOptions,
+    ) -> Option<LookupRecords> {
+        let mut targets = Vec::<Name>::new();
+        for record in answers.iter() {
+            let target = match record.data() {
+                Some(RData::MX(mx)) => mx.exchange(),
+                Some(RData::SRV(srv)) => srv.target(),
+                _ => continue,
+            };
+            // the root is the target of unavailable services
+            if target.is_root() || origin.zone_of(&LowerName::from(target)) {
+                continue;
+            }
+            if !targets.contains(target) {
+                targets.push(target.clone());
+            }
+        }
+        if targets.is_empty() {
+            return additionals;
+        }
+
+        let queries = targets
+            .into_iter()
+            .flat_map(|target| [(target.clone(), RecordType::A), (target, RecordType::AAAA)]);
+        let mut lookups = Box::pin(self.resolver.lookup_batch_stream(queries));
+        let deadline = Instant::now() + self.timeout;
+
+        let mut resolved = Vec::<RecordSet>::new();
+        loop {
+            let lookup = match tokio::time::timeout_at(deadline, lookups.next()).await {
+                Ok(Some((_, Ok(lookup)))) => lookup,
+                Ok(Some((_, Err(e)))) => {
+                    debug!("no additional addresses: {}", e);
+                    continue;
+                }
+                Ok(None) => break,
+                Err(_) => {
+                    debug!("timed out resolving the additional addresses");
+                    break;
+                }
+            };
+
+            for record in lookup.record_iter() {
+                match resolved.iter_mut().find(|rrset| {
+                    rrset.name() == record.name() && rrset.record_type() == record.record_type()
+                }) {
+                    Some(rrset) => {
+                        rrset.insert(record.clone(), 0);
+                    }
+                    None => resolved.push(RecordSet::from(record.clone())),
+                }
+            }
+        }
+
+        if resolved.is_empty() {
+            return additionals;
+        }
+
+        let mut rrsets = match additionals {
+            Some(LookupRecords::Records { records, .. }) => vec![records],
+            Some(LookupRecords::ManyRecords(_, mut records)) => {
+                // the records are stored in reverse, see `LookupRecords::many`
+                records.reverse();
+                records
+            }
+            _ => Vec::new(),
+        };
+        rrsets.extend(resolved.into_iter().map(Arc::new));
+        Some(LookupRecords::many(lookup_options, rrsets))
+    }
+}
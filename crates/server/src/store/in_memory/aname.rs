@@ -0,0 +1,171 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::{
+    ops::Deref,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use tracing::{debug, warn};
+
+use crate::{
+    authority::Authority,
+    config::aname::AnameConfig,
+    proto::{
+        error::ProtoErrorKind,
+        rr::{LowerName, Name, RData, Record, RecordSet, RecordType, RrKey},
+    },
+    resolver::{config::ResolverConfig, name_server::TokioConnectionProvider, TokioAsyncResolver},
+    store::in_memory::InMemoryAuthority,
+};
+
+/// Flattens the ANAME records of a zone whose targets are outside of it
+///
+/// The A and AAAA records of each target are resolved, and kept at the name of its ANAME record
+///  as the sibling address records of the ANAME, see
+///  [draft-ietf-dnsop-aname](https://tools.ietf.org/html/draft-ietf-dnsop-aname-04). The queries
+///  of the addresses of the name are answered by them, e.g. at the apex of the zone where a CNAME
+///  is not allowed. Their TTL is the lower of the TTLs of the ANAME and of the addresses of the
+///  target, and the target is resolved again when they expire.
+///
+/// The targets in the zone are followed for each query instead. The sibling address records are
+///  not journaled, nor signed when the zone is signed.
+pub struct AnameFlattener {
+    resolver: TokioAsyncResolver,
+    min_refresh: Duration,
+    max_refresh: Duration,
+}
+
+impl AnameFlattener {
+    /// Creates the flattener with the resolver of the targets
+    ///
+    /// # Arguments
+    ///
+    /// * `resolver` - The resolver of the addresses of the targets
+    /// * `min_refresh` - The shortest time between the resolutions, e.g. when they fail
+    /// * `max_refresh` - The longest time between the resolutions, e.g. of addresses with long TTLs
+    pub fn new(resolver: TokioAsyncResolver, min_refresh: Duration, max_refresh: Duration) -> Self {
+        Self {
+            resolver,
+            min_refresh,
+            max_refresh: max_refresh.max(min_refresh),
+        }
+    }
+
+    /// Creates the flattener with the resolver of the configuration
+    pub fn try_from_config(config: &AnameConfig) -> Result<Self, String> {
+        let resolver = match config.get_name_servers() {
+            Some(name_servers) => TokioAsyncResolver::new(
+                ResolverConfig::from_parts(None, vec![], name_servers.clone()),
+                config.get_options(),
+                TokioConnectionProvider::default(),
+            ),
+            None => TokioAsyncResolver::tokio_from_system_conf()
+                .map_err(|e| format!("error constructing the ANAME resolver: {e}"))?,
+        };
+
+        Ok(Self::new(
+            resolver,
+            config.get_min_refresh(),
+            config.get_max_refresh(),
+        ))
+    }
+
+    /// Resolves the targets of the ANAME records of the zone once, and replaces the address
+    ///  records of their names
+    ///
+    /// Returns the time until the targets should be resolved again. The address records are kept
+    ///  when their resolution fails, and removed when the target has no addresses of their type.
+    pub async fn flatten(&self, authority: &InMemoryAuthority) -> Duration {
+        let origin = Name::from(authority.origin());
+        let anames = authority
+            .records()
+            .await
+            .into_values()
+            .filter(|rrset| rrset.record_type() == RecordType::ANAME)
+            .filter_map(|rrset| {
+                let target = rrset
+                    .records_without_rrsigs()
+                    .next()
+                    .and_then(Record::data)
+                    .and_then(RData::as_aname)?
+                    .0
+                    .clone();
+                Some((rrset.name().clone(), target, rrset.ttl()))
+            })
+            .filter(|(_, target, _)| !origin.zone_of(target))
+            .collect::<Vec<_>>();
+
+        let mut refresh = self.max_refresh;
+        for (name, target, ttl) in anames {
+            for record_type in [RecordType::A, RecordType::AAAA] {
+                let key = RrKey::new(LowerName::new(&name), record_type);
+                match self.resolver.lookup(target.clone(), record_type).await {
+                    Ok(lookup) => {
+                        let expires = lookup
+                            .valid_until()
+                            .saturating_duration_since(Instant::now());
+                        refresh = refresh.min(expires);
+
+                        let addrs = lookup
+                            .records()
+                            .iter()
+                            .filter(|record| record.record_type() == record_type)
+                            .collect::<Vec<_>>();
+                        let ttl = addrs.iter().map(|record| record.ttl()).fold(ttl, u32::min);
+
+                        let serial = authority.serial().await;
+                        let mut rrset = RecordSet::new(&name, record_type, serial);
+                        for addr in addrs.into_iter().filter_map(Record::data) {
+                            rrset.insert(
+                                Record::from_rdata(name.clone(), ttl, addr.clone()),
+                                serial,
+                            );
+                        }
+                        debug!("flattened {} of {} to {:?}", record_type, name, rrset);
+
+                        let mut records = authority.records_mut().await;
+                        if rrset.is_empty() {
+                            records.remove(&key);
+                        } else {
+                            records.insert(key, Arc::new(rrset));
+                        }
+                    }
+                    Err(e) => match e.proto().map(|e| e.kind()) {
+                        Some(ProtoErrorKind::NoRecordsFound { negative_ttl, .. }) => {
+                            debug!("{} of {} has no {} records", target, name, record_type);
+                            if let Some(negative_ttl) = negative_ttl {
+                                refresh =
+                                    refresh.min(Duration::from_secs(u64::from(*negative_ttl)));
+                            }
+                            authority.records_mut().await.remove(&key);
+                        }
+                        _ => {
+                            warn!("failed to resolve {} of {}: {}", target, name, e);
+                            refresh = refresh.min(self.min_refresh);
+                        }
+                    },
+                }
+            }
+        }
+
+        refresh.max(self.min_refresh)
+    }
+
+    /// Flattens the ANAME records of the zone, and again when the addresses expire, this never
+    ///  returns
+    pub async fn run<A>(self, authority: Arc<A>)
+    where
+        A: Deref<Target = InMemoryAuthority> + Send + Sync,
+    {
+        loop {
+            let refresh = self.flatten(&authority).await;
+            tokio::time::sleep(refresh).await;
+        }
+    }
+}
@@ -7,10 +7,10 @@
 
 //! All authority related types
 
-#[cfg(feature = "dnssec")]
-use std::borrow::Borrow;
 #[cfg(all(feature = "dnssec", feature = "testing"))]
 use std::ops::Deref;
+#[cfg(feature = "dnssec")]
+use std::{borrow::Borrow, iter};
 use std::{
     collections::{BTreeMap, HashSet},
     ops::DerefMut,
@@ -19,7 +19,6 @@ use std::{
 
 use cfg_if::cfg_if;
 use futures_util::future::{self, TryFutureExt};
-#[cfg(feature = "dnssec")]
 use time::OffsetDateTime;
 use tracing::{debug, error, warn};
 
@@ -28,6 +27,7 @@ use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 #[cfg(feature = "dnssec")]
 use crate::{
     authority::DnssecAuthority,
+    config::dnssec::NxProofKind,
     proto::rr::dnssec::{
         rdata::{key::KEY, DNSSECRData, NSEC},
         {tbs, DnsSecResult, SigSigner, SupportedAlgorithms},
@@ -39,16 +39,21 @@ use crate::{
         AnyRecords, AuthLookup, Authority, LookupError, LookupOptions, LookupRecords, LookupResult,
         MessageRequest, UpdateResult, ZoneType,
     },
+    config::AnyQueryPolicy,
     proto::{
         op::ResponseCode,
         rr::{
-            rdata::SOA,
+            rdata::{CNAME, HINFO, SOA},
             {DNSClass, LowerName, Name, RData, Record, RecordSet, RecordType, RrKey},
         },
     },
     server::RequestInfo,
+    store::in_memory::Rotation,
 };
 
+#[cfg(feature = "hickory-resolver")]
+use crate::store::in_memory::AdditionalResolver;
+
 /// InMemoryAuthority is responsible for storing the resource records for a particular zone.
 ///
 /// Authorities default to DNSClass IN. The ZoneType specifies if this should be treated as the
@@ -58,6 +63,12 @@ pub struct InMemoryAuthority {
     class: DNSClass,
     zone_type: ZoneType,
     allow_axfr: bool,
+    #[cfg(feature = "dnssec")]
+    nx_proof_kind: NxProofKind,
+    any_query: AnyQueryPolicy,
+    rotation: Rotation,
+    #[cfg(feature = "hickory-resolver")]
+    additional_resolver: Option<AdditionalResolver>,
     inner: RwLock<InnerInMemory>,
 }
 
@@ -126,10 +137,52 @@ impl InMemoryAuthority {
             class: DNSClass::IN,
             zone_type,
             allow_axfr,
+            #[cfg(feature = "dnssec")]
+            nx_proof_kind: NxProofKind::default(),
+            any_query: AnyQueryPolicy::default(),
+            rotation: Rotation::default(),
+            #[cfg(feature = "hickory-resolver")]
+            additional_resolver: None,
             inner: RwLock::new(InnerInMemory::default()),
         }
     }
 
+    /// Creates a primary zone with only a SOA record, for zones whose records are maintained at
+    ///  runtime instead of being loaded
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - the name of the zone
+    /// * `serial` - the serial of the zone, usually [`Self::serial_now`]
+    /// * `ttl` - the TTL of the SOA record, and the TTL of negative responses
+    pub fn empty_primary(mut origin: Name, serial: u32, ttl: u32) -> Self {
+        origin.set_fqdn(true);
+
+        let mut authority = Self::empty(origin.clone(), ZoneType::Primary, false);
+        authority.upsert_mut(Self::primary_soa(&origin, serial, ttl), serial);
+        authority
+    }
+
+    /// The SOA record of a zone created with [`Self::empty_primary`], to be upserted when the
+    ///  serial is incremented
+    ///
+    /// The responsible mailbox is `hostmaster` in the zone.
+    pub fn primary_soa(origin: &Name, serial: u32, ttl: u32) -> Record {
+        let rname = Name::from_ascii("hostmaster")
+            .and_then(|name| name.append_domain(origin))
+            .unwrap_or_else(|_| origin.clone());
+        let soa = SOA::new(origin.clone(), rname, serial, 3600, 600, 86400, ttl);
+
+        Record::from_rdata(origin.clone(), ttl, RData::SOA(soa))
+    }
+
+    /// The initial serial of a zone maintained at runtime
+    ///
+    /// There is no persisted serial, the time keeps it increasing across restarts.
+    pub fn serial_now() -> u32 {
+        OffsetDateTime::now_utc().unix_timestamp() as u32
+    }
+
     /// The DNSClass of this zone
     pub fn class(&self) -> DNSClass {
         self.class
@@ -142,6 +195,31 @@ impl InMemoryAuthority {
         self.allow_axfr = allow_axfr;
     }
 
+    /// Set the kind of proofs of non-existence in negative answers
+    #[cfg(feature = "dnssec")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "dnssec")))]
+    pub fn set_nx_proof_kind(&mut self, nx_proof_kind: NxProofKind) {
+        self.nx_proof_kind = nx_proof_kind;
+    }
+
+    /// Set how queries of type ANY are answered
+    pub fn set_any_query(&mut self, any_query: AnyQueryPolicy) {
+        self.any_query = any_query;
+    }
+
+    /// Set how the records of the RRsets of answers are ordered
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        self.rotation = rotation;
+    }
+
+    /// Set the resolver of the addresses of the targets outside of the zone, which are added to
+    ///  the additional records of MX and SRV answers
+    #[cfg(feature = "hickory-resolver")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "resolver")))]
+    pub fn set_additional_resolver(&mut self, additional_resolver: AdditionalResolver) {
+        self.additional_resolver = Some(additional_resolver);
+    }
+
     /// Clears all records (including SOA, etc)
     pub fn clear(&mut self) {
         self.inner.get_mut().records.clear()
@@ -301,6 +379,21 @@ impl InMemoryAuthority {
         inner.get_mut().secure_zone_mut(origin, self.class)
     }
 
+    /// Verifies the signatures of all RecordSets in the zone against the zone signing keys.
+    ///
+    /// Returns an error describing the first RecordSet that is unsigned, carries an RRSIG that is
+    ///  not valid at the current time, or carries an RRSIG that fails cryptographic verification.
+    ///  Zones without any signing keys always verify successfully.
+    #[cfg(feature = "dnssec")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "dnssec")))]
+    pub async fn verify_zone(&self) -> DnsSecResult<()> {
+        let now = OffsetDateTime::now_utc().unix_timestamp() as u32;
+        self.inner
+            .read()
+            .await
+            .verify_zone(self.origin(), self.class, now)
+    }
+
     /// (Re)generates the nsec records, increments the serial number and signs the zone
     #[cfg(not(feature = "dnssec"))]
     #[cfg_attr(docsrs, doc(cfg(feature = "dnssec")))]
@@ -454,15 +547,135 @@ impl InnerInMemory {
                     }
                 }
 
+                // the RRSIGs are owned by the query name too, their labels show the expansion
                 #[cfg(feature = "dnssec")]
                 for rrsig in _rrsigs {
-                    new_answer.insert_rrsig(rrsig.clone())
+                    let mut rrsig = rrsig.clone();
+                    rrsig.set_name(Name::from(name));
+                    new_answer.insert_rrsig(rrsig)
                 }
 
                 Arc::new(new_answer)
             })
     }
 
+    /// Returns the single RRset answering a query of type ANY, see
+    ///  [RFC 8482](https://tools.ietf.org/html/rfc8482#section-4)
+    ///
+    /// # Return value
+    ///
+    /// None if there are no records at the name
+    #[cfg_attr(not(feature = "dnssec"), allow(unused_variables))]
+    fn minimal_any(
+        &self,
+        name: &LowerName,
+        any_query: AnyQueryPolicy,
+        origin: &LowerName,
+        dns_class: DNSClass,
+        lookup_options: LookupOptions,
+    ) -> Option<Arc<RecordSet>> {
+        let start_range_key = RrKey::new(name.clone(), RecordType::Unknown(u16::min_value()));
+        let end_range_key = RrKey::new(name.clone(), RecordType::Unknown(u16::max_value()));
+
+        // the NSEC records are not data of the name
+        let rr_sets = self
+            .records
+            .range(&start_range_key..&end_range_key)
+            .map(|(_key, rr_set)| rr_set)
+            .filter(|rr_set| rr_set.record_type() != RecordType::NSEC)
+            .collect::<Vec<_>>();
+        let first = rr_sets.first()?;
+
+        let existing = rr_sets
+            .iter()
+            .find(|rr_set| rr_set.record_type() == RecordType::CNAME)
+            .or_else(|| match any_query {
+                AnyQueryPolicy::Hinfo => rr_sets
+                    .iter()
+                    .find(|rr_set| rr_set.record_type() == RecordType::HINFO),
+                _ => Some(first),
+            });
+        if let Some(rr_set) = existing {
+            return Some(Arc::clone(rr_set));
+        }
+
+        let ttl = self.minimum_ttl(origin);
+        let mut rr_set = RecordSet::with_ttl(name.into(), RecordType::HINFO, ttl);
+        rr_set.add_rdata(RData::HINFO(HINFO::new(
+            "RFC8482".to_string(),
+            String::new(),
+        )));
+
+        // the record is synthesized for the answer, so it needs to be signed now
+        #[cfg(feature = "dnssec")]
+        if lookup_options.is_dnssec() {
+            Self::sign_rrset(&mut rr_set, &self.secure_keys, ttl, dns_class)
+                .map_err(|e| warn!("failed to sign HINFO record: {}", e))
+                .ok();
+        }
+
+        Some(Arc::new(rr_set))
+    }
+
+    /// Returns the DNAME record owned by the closest ancestor of the name within the zone
+    ///
+    /// A DNAME redirects all the names below its owner, but not the owner itself, see
+    ///  [RFC 6672](https://tools.ietf.org/html/rfc6672#section-2.3).
+    fn dname_ancestor(&self, origin: &LowerName, name: &LowerName) -> Option<Arc<RecordSet>> {
+        let mut ancestor = name.clone();
+        while !ancestor.is_root() {
+            ancestor = ancestor.base_name();
+            if !origin.zone_of(&ancestor) {
+                break;
+            }
+
+            let key = RrKey::new(ancestor.clone(), RecordType::DNAME);
+            if let Some(dname) = self.records.get(&key) {
+                return Some(dname.clone());
+            }
+        }
+
+        None
+    }
+
+    /// Answers with the DNAME record and the CNAME record synthesized from it for the name
+    ///
+    /// The synthesized CNAME has the TTL of the DNAME and is not signed, validators derive it from
+    ///  the signed DNAME, see [RFC 6672](https://tools.ietf.org/html/rfc6672#section-5.3.2). Its
+    ///  target is then searched like the target of any CNAME record.
+    fn dname_lookup(
+        &self,
+        name: &LowerName,
+        query_type: RecordType,
+        dname: Arc<RecordSet>,
+        lookup_options: LookupOptions,
+    ) -> Result<AuthLookup, LookupError> {
+        let target = dname
+            .records_without_rrsigs()
+            .next()
+            .and_then(Record::data)
+            .and_then(RData::as_dname)
+            .ok_or(ResponseCode::ServFail)?;
+        // the substituted name is too long
+        let target = target
+            .substitute(dname.name(), &Name::from(name))
+            .map_err(|_| ResponseCode::YXDomain)?;
+        debug!("synthesizing CNAME from DNAME for {name}: {target}");
+
+        let mut cname = RecordSet::with_ttl(Name::from(name), RecordType::CNAME, dname.ttl());
+        cname.add_rdata(RData::CNAME(CNAME(target)));
+        let cname = Arc::new(cname);
+
+        let additionals = maybe_next_name(&cname, query_type)
+            .and_then(|(search_name, search_type)| {
+                self.additional_search(name, query_type, search_name, search_type, lookup_options)
+            })
+            .map(|additionals| LookupRecords::many(lookup_options, additionals));
+        let answers = LookupRecords::many(lookup_options, vec![dname, cname]);
+
+        Ok(AuthLookup::answers(answers, additionals))
+    }
+
     /// Search for additional records to include in the response
     ///
     /// # Arguments
@@ -853,6 +1066,262 @@ impl InnerInMemory {
 
         Ok(())
     }
+
+    /// Returns the NSEC record of the chain which covers the name, i.e. the one with the greatest
+    ///  name less than or equal to `name`
+    #[cfg(feature = "dnssec")]
+    fn closest_nsec(&self, name: &LowerName) -> Option<Arc<RecordSet>> {
+        self.records
+            .values()
+            .rev()
+            .filter(|rr_set| rr_set.record_type() == RecordType::NSEC)
+            // the name must be greater than the name in the nsec
+            .filter(|rr_set| *name >= rr_set.name().into())
+            // now find the next record where the covered name is greater
+            .find(|rr_set| {
+                // there should only be one record
+                rr_set
+                    .records(false, SupportedAlgorithms::default())
+                    .next()
+                    .and_then(Record::data)
+                    .and_then(RData::as_dnssec)
+                    .and_then(DNSSECRData::as_nsec)
+                    .map_or(false, |r| {
+                        // the search name is less than the next NSEC record
+                        *name < r.next_domain_name().into() ||
+                        // this is the last record, and wraps to the beginning of the zone
+                        r.next_domain_name() < rr_set.name()
+                    })
+            })
+            .cloned()
+    }
+
+    /// Returns true if there are any records at names after `start` and before `end`
+    #[cfg(feature = "dnssec")]
+    fn has_names_between(&self, start: &LowerName, end: &LowerName) -> bool {
+        let start_key = RrKey::new(start.clone(), RecordType::Unknown(u16::max_value()));
+
+        self.records
+            .range(start_key..)
+            .map(|(key, _)| &key.name)
+            .skip_while(|name| *name == start)
+            .take_while(|name| *name < end)
+            .next()
+            .is_some()
+    }
+
+    /// Returns the NSEC record which minimally covers `name`, see [RFC 4470](https://tools.ietf.org/html/rfc4470)
+    ///
+    /// An empty non-terminal gets an NSEC record at its own name, any other name is covered by an
+    ///  NSEC record from its predecessor to its successor. The NSEC record of the chain is
+    ///  returned if that would cover any other names of the zone.
+    #[cfg(feature = "dnssec")]
+    fn minimally_covering_nsec(
+        &self,
+        name: &LowerName,
+        origin: &LowerName,
+        dns_class: DNSClass,
+    ) -> Option<Arc<RecordSet>> {
+        let is_empty_non_terminal = self.records.keys().any(|key| name.zone_of(&key.name));
+        let nsec = if is_empty_non_terminal {
+            successor(name).map(|next| (name.clone(), next))
+        } else {
+            predecessor(name)
+                .zip(successor(name))
+                .filter(|(prev, _)| !self.records.keys().any(|key| key.name == *prev))
+        }
+        .filter(|(owner, next)| !self.has_names_between(owner, next));
+
+        match nsec {
+            Some((owner, next)) => Some(self.signed_nsec(
+                owner,
+                next,
+                vec![RecordType::RRSIG, RecordType::NSEC],
+                origin,
+                dns_class,
+            )),
+            None => self.closest_nsec(name),
+        }
+    }
+
+    /// Returns the NSEC record which proves that there is no wildcard below `name`
+    ///
+    /// If `name` exists, the NSEC record at `name` only extends to the successor of the wildcard,
+    ///  otherwise `name` itself is minimally covered.
+    #[cfg(feature = "dnssec")]
+    fn minimally_covering_wildcard_nsec(
+        &self,
+        name: &LowerName,
+        origin: &LowerName,
+        dns_class: DNSClass,
+    ) -> Option<Arc<RecordSet>> {
+        let rr_key = RrKey::new(name.clone(), RecordType::NSEC);
+        let types = match self.records.get(&rr_key).and_then(|rr_set| {
+            rr_set
+                .records_without_rrsigs()
+                .next()
+                .and_then(Record::data)
+                .and_then(RData::as_dnssec)
+                .and_then(DNSSECRData::as_nsec)
+                .map(|nsec| nsec.type_bit_maps().to_vec())
+        }) {
+            Some(types) => types,
+            None => return self.minimally_covering_nsec(name, origin, dns_class),
+        };
+
+        let wildcard =
+            Name::from_labels(iter::once(&b"*"[..]).chain(Borrow::<Name>::borrow(name).iter()))
+                .ok()
+                .map(LowerName::from);
+        match wildcard
+            .as_ref()
+            .and_then(successor)
+            .filter(|next| !self.has_names_between(name, next))
+        {
+            Some(next) => Some(self.signed_nsec(name.clone(), next, types, origin, dns_class)),
+            None => self.closest_nsec(name),
+        }
+    }
+
+    /// Creates an NSEC record for a response, signed with the zone signing keys
+    #[cfg(feature = "dnssec")]
+    fn signed_nsec(
+        &self,
+        owner: LowerName,
+        next: LowerName,
+        types: Vec<RecordType>,
+        origin: &LowerName,
+        dns_class: DNSClass,
+    ) -> Arc<RecordSet> {
+        let ttl = self.minimum_ttl(origin);
+        let mut rr_set = RecordSet::new(&owner.into(), RecordType::NSEC, ttl);
+        rr_set.add_rdata(RData::DNSSEC(DNSSECRData::NSEC(NSEC::new(
+            next.into(),
+            types,
+        ))));
+
+        // rather than failing the request, we'll just warn
+        Self::sign_rrset(&mut rr_set, &self.secure_keys, ttl, dns_class)
+            .map_err(|e| warn!("failed to sign NSEC record: {}", e))
+            .ok();
+
+        Arc::new(rr_set)
+    }
+
+    /// Verifies that every RecordSet in the zone carries a valid RRSIG from each of the zone
+    ///  signing keys, and that none of those signatures have expired at `now`
+    #[cfg(feature = "dnssec")]
+    fn verify_zone(&self, origin: &LowerName, dns_class: DNSClass, now: u32) -> DnsSecResult<()> {
+        use crate::proto::rr::dnssec::Verifier;
+
+        if self.secure_keys.is_empty() {
+            return Ok(());
+        }
+
+        debug!("verifying zone signatures: {}", origin);
+
+        let dnskeys = self
+            .secure_keys
+            .iter()
+            .map(|signer| {
+                let dnskey = signer.key().to_dnskey(signer.algorithm())?;
+                let key_tag = dnskey.calculate_key_tag()?;
+                Ok((dnskey, key_tag))
+            })
+            .collect::<DnsSecResult<Vec<_>>>()?;
+
+        for rr_set in self.records.values() {
+            let records = rr_set.records_without_rrsigs().collect::<Vec<_>>();
+
+            for (dnskey, key_tag) in &dnskeys {
+                let rrsig = rr_set
+                    .rrsigs()
+                    .iter()
+                    .filter_map(|rrsig| rrsig.data())
+                    .filter_map(|rdata| rdata.as_dnssec())
+                    .filter_map(|rdata| rdata.as_rrsig())
+                    .find(|rrsig| {
+                        rrsig.key_tag() == *key_tag && rrsig.algorithm() == dnskey.algorithm()
+                    })
+                    .ok_or_else(|| {
+                        format!(
+                            "{} {} is not signed by key_tag: {}",
+                            rr_set.name(),
+                            rr_set.record_type(),
+                            key_tag
+                        )
+                    })?;
+
+                if now < rrsig.sig_inception() || rrsig.sig_expiration() < now {
+                    return Err(format!(
+                        "RRSIG for {} {} is not valid at {}, inception: {}, expiration: {}",
+                        rr_set.name(),
+                        rr_set.record_type(),
+                        now,
+                        rrsig.sig_inception(),
+                        rrsig.sig_expiration()
+                    )
+                    .into());
+                }
+
+                dnskey
+                    .verify_rrsig(rr_set.name(), dns_class, rrsig, &records)
+                    .map_err(|e| {
+                        format!(
+                            "RRSIG for {} {} failed to verify: {}",
+                            rr_set.name(),
+                            rr_set.record_type(),
+                            e
+                        )
+                    })?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns `\000.name`, the first name after `name` in the canonical order
+#[cfg(feature = "dnssec")]
+fn successor(name: &LowerName) -> Option<LowerName> {
+    let name: &Name = name.borrow();
+    Name::from_labels(iter::once(&[0_u8][..]).chain(name.iter()))
+        .ok()
+        .map(LowerName::from)
+}
+
+/// Returns a name with the same parent which sorts just before `name` in the canonical order
+///
+/// The last octet of the first label is decremented and the label is filled up with `\255`.
+///  `None` if there is no such name, i.e. `name` is the root or its first label is `\000`.
+#[cfg(feature = "dnssec")]
+fn predecessor(name: &LowerName) -> Option<LowerName> {
+    let name: &Name = name.borrow();
+    let name_len = name.iter().map(|label| label.len() + 1).sum::<usize>() + 1;
+    let mut labels = name.iter();
+    let mut label = labels.next()?.to_vec();
+
+    match label.pop()? {
+        // a prefix sorts before the label
+        0 => {}
+        octet => {
+            // uppercase letters sort as lowercase ones
+            label.push(match octet - 1 {
+                b'A'..=b'Z' => b'@',
+                octet => octet,
+            });
+            let padding = (63 - label.len()).min(255 - name_len);
+            label.resize(label.len() + padding, 0xff);
+        }
+    }
+
+    if label.is_empty() {
+        return None;
+    }
+
+    Name::from_labels(iter::once(label).chain(labels.map(<[u8]>::to_vec)))
+        .ok()
+        .map(LowerName::from)
 }
 
 /// Gets the next search name, and returns the RecordType that it originated from
@@ -1011,9 +1480,30 @@ impl Authority for InMemoryAuthority {
     ) -> Result<Self::Lookup, LookupError> {
         let inner = self.inner.read().await;
 
+        // the names below a DNAME are redirected, any records they have are occluded
+        if query_type != RecordType::AXFR {
+            if let Some(dname) = inner.dname_ancestor(self.origin(), name) {
+                return inner.dname_lookup(name, query_type, dname, lookup_options);
+            }
+        }
+
         // Collect the records from each rr_set
         let (result, additionals): (LookupResult<LookupRecords>, Option<LookupRecords>) =
             match query_type {
+                RecordType::ANY if self.any_query != AnyQueryPolicy::All => {
+                    let answer = inner
+                        .minimal_any(
+                            name,
+                            self.any_query,
+                            self.origin(),
+                            self.class,
+                            lookup_options,
+                        )
+                        .map_or(Err(LookupError::from(ResponseCode::NXDomain)), |rr_set| {
+                            Ok(LookupRecords::new(lookup_options, rr_set))
+                        });
+                    (answer, None)
+                }
                 RecordType::AXFR | RecordType::ANY => {
                     let result = AnyRecords::new(
                         lookup_options,
@@ -1127,9 +1617,12 @@ impl Authority for InMemoryAuthority {
                         };
 
                     // map the answer to a result
-                    let answer = answer
-                        .map_or(Err(LookupError::from(ResponseCode::NXDomain)), |rr_set| {
-                            Ok(LookupRecords::new(lookup_options, rr_set))
+                    let answer =
+                        answer.map_or(Err(LookupError::from(ResponseCode::NXDomain)), |rr_set| {
+                            Ok(LookupRecords::new(
+                                lookup_options,
+                                self.rotation.order(rr_set),
+                            ))
                         });
 
                     let additionals = additionals.map(|a| LookupRecords::many(lookup_options, a));
@@ -1164,6 +1657,18 @@ impl Authority for InMemoryAuthority {
             o => o,
         };
 
+        // the addresses outside of the zone are resolved without holding the lock of the records
+        #[cfg(feature = "hickory-resolver")]
+        let additionals = match (&self.additional_resolver, &result) {
+            (Some(additional_resolver), Ok(answers)) => {
+                drop(inner);
+                additional_resolver
+                    .complete(self.origin(), answers, additionals, lookup_options)
+                    .await
+            }
+            _ => additionals,
+        };
+
         result.map(|answers| AuthLookup::answers(answers, additionals))
     }
 
@@ -1223,6 +1728,45 @@ impl Authority for InMemoryAuthority {
         }
     }
 
+    /// Looks up the answer of a simple query without awaiting, see `Authority::lookup_now`
+    ///
+    /// Signed answers, answers with CNAME, ANAME or DNAME records and answers which are not
+    ///  found are left to `search`, as is the lookup while the zone is being updated.
+    fn lookup_now(
+        &self,
+        name: &LowerName,
+        query_type: RecordType,
+        lookup_options: LookupOptions,
+    ) -> Option<Vec<Record>> {
+        // the SOA of a search is answered with the NS records of the zone
+        if lookup_options.is_dnssec()
+            || matches!(
+                query_type,
+                RecordType::SOA | RecordType::ANY | RecordType::AXFR | RecordType::IXFR
+            )
+        {
+            return None;
+        }
+
+        let inner = self.inner.try_read().ok()?;
+        if inner.dname_ancestor(self.origin(), name).is_some() {
+            return None;
+        }
+
+        let answer = inner.inner_lookup(name, query_type, lookup_options)?;
+        if answer.record_type() != query_type || maybe_next_name(&answer, query_type).is_some() {
+            return None;
+        }
+
+        let records = self
+            .rotation
+            .order(answer)
+            .records_without_rrsigs()
+            .cloned()
+            .collect::<Vec<_>>();
+        (!records.is_empty()).then_some(records)
+    }
+
     /// Return the NSEC records based on the given name
     ///
     /// # Arguments
@@ -1237,9 +1781,6 @@ impl Authority for InMemoryAuthority {
         lookup_options: LookupOptions,
     ) -> Result<Self::Lookup, LookupError> {
         let inner = self.inner.read().await;
-        fn is_nsec_rrset(rr_set: &RecordSet) -> bool {
-            rr_set.record_type() == RecordType::NSEC
-        }
 
         // TODO: need a BorrowdRrKey
         let rr_key = RrKey::new(name.clone(), RecordType::NSEC);
@@ -1252,35 +1793,13 @@ impl Authority for InMemoryAuthority {
             return Ok(no_data.into());
         }
 
-        let get_closest_nsec = |name: &LowerName| -> Option<Arc<RecordSet>> {
-            inner
-                .records
-                .values()
-                .rev()
-                .filter(|rr_set| is_nsec_rrset(rr_set))
-                // the name must be greater than the name in the nsec
-                .filter(|rr_set| *name >= rr_set.name().into())
-                // now find the next record where the covered name is greater
-                .find(|rr_set| {
-                    // there should only be one record
-                    rr_set
-                        .records(false, SupportedAlgorithms::default())
-                        .next()
-                        .and_then(Record::data)
-                        .and_then(RData::as_dnssec)
-                        .and_then(DNSSECRData::as_nsec)
-                        .map_or(false, |r| {
-                            // the search name is less than the next NSEC record
-                            *name < r.next_domain_name().into() ||
-                            // this is the last record, and wraps to the beginning of the zone
-                            r.next_domain_name() < rr_set.name()
-                        })
-                })
-                .cloned()
+        let minimally_covering = self.nx_proof_kind == NxProofKind::MinimallyCoveringNsec;
+        let closest_proof = if minimally_covering {
+            inner.minimally_covering_nsec(name, self.origin(), self.class)
+        } else {
+            inner.closest_nsec(name)
         };
 
-        let closest_proof = get_closest_nsec(name);
-
         // we need the wildcard proof, but make sure that it's still part of the zone.
         let wildcard = name.base_name();
         let origin = self.origin();
@@ -1291,10 +1810,12 @@ impl Authority for InMemoryAuthority {
         };
 
         // don't duplicate the record...
-        let wildcard_proof = if wildcard != *name {
-            get_closest_nsec(&wildcard)
-        } else {
+        let wildcard_proof = if wildcard == *name {
             None
+        } else if minimally_covering {
+            inner.minimally_covering_wildcard_nsec(&wildcard, self.origin(), self.class)
+        } else {
+            inner.closest_nsec(&wildcard)
         };
 
         let proofs = match (closest_proof, wildcard_proof) {
@@ -1321,6 +1842,34 @@ impl Authority for InMemoryAuthority {
     ) -> Result<Self::Lookup, LookupError> {
         Ok(AuthLookup::default())
     }
+
+    /// Return the NSEC record covering the next closer name of a wildcard expansion, i.e. the
+    ///  name with one more label than the wildcard, which proves that it does not exist
+    #[cfg(feature = "dnssec")]
+    async fn get_wildcard_proof(
+        &self,
+        next_closer: &LowerName,
+        lookup_options: LookupOptions,
+    ) -> Result<Self::Lookup, LookupError> {
+        let inner = self.inner.read().await;
+
+        let proof = if self.nx_proof_kind == NxProofKind::MinimallyCoveringNsec {
+            inner.minimally_covering_nsec(next_closer, self.origin(), self.class)
+        } else {
+            inner.closest_nsec(next_closer)
+        };
+
+        Ok(LookupRecords::many(lookup_options, proof.into_iter().collect()).into())
+    }
+
+    #[cfg(not(feature = "dnssec"))]
+    async fn get_wildcard_proof(
+        &self,
+        _next_closer: &LowerName,
+        _lookup_options: LookupOptions,
+    ) -> Result<Self::Lookup, LookupError> {
+        Ok(AuthLookup::default())
+    }
 }
 
 #[cfg(feature = "dnssec")]
@@ -7,6 +7,18 @@
 
 //! Zone file based serving with Dynamic DNS and journaling support
 
+#[cfg(feature = "hickory-resolver")]
+mod additionals;
+#[cfg(feature = "hickory-resolver")]
+mod aname;
 mod authority;
+mod rotation;
 
+#[cfg(feature = "hickory-resolver")]
+#[cfg_attr(docsrs, doc(cfg(feature = "resolver")))]
+pub use self::additionals::AdditionalResolver;
+#[cfg(feature = "hickory-resolver")]
+#[cfg_attr(docsrs, doc(cfg(feature = "resolver")))]
+pub use self::aname::AnameFlattener;
 pub use self::authority::InMemoryAuthority;
+pub use self::rotation::Rotation;
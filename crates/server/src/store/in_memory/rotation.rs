@@ -0,0 +1,325 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    hash::{BuildHasher, Hash, Hasher},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use crate::{
+    config::{RotationConfig, RotationPolicy},
+    proto::{
+        rr::{LowerName, Name, RData, Record, RecordSet, RecordType},
+        serialize::txt::RDataParser,
+    },
+};
+
+/// The order of the RRsets of a name, or of one of its RRsets
+#[derive(Debug)]
+struct RrsetRotation {
+    record_type: Option<RecordType>,
+    policy: RotationPolicy,
+    weights: Vec<(RData, u32)>,
+}
+
+/// The orders of the records of the RRsets of answers, see `RotationPolicy`
+///
+/// The order is applied to each answer of the authority, the RRsets of the zone keep the order of
+///  their records.
+#[derive(Debug, Default)]
+pub struct Rotation {
+    policy: RotationPolicy,
+    rrsets: HashMap<LowerName, Vec<RrsetRotation>>,
+    /// the number of ordered answers, which rotates the records
+    answers: AtomicUsize,
+}
+
+impl Rotation {
+    /// Creates the orders with the policy of the zone
+    pub fn new(policy: RotationPolicy) -> Self {
+        Self {
+            policy,
+            ..Self::default()
+        }
+    }
+
+    /// Reads the orders of the configuration of a zone
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - The name of the zone
+    /// * `policy` - The order of the RRsets without their own order
+    /// * `rotations` - The orders of the RRsets, with the weights of their records
+    pub fn try_from_config(
+        origin: &Name,
+        policy: RotationPolicy,
+        rotations: &[RotationConfig],
+    ) -> Result<Self, String> {
+        let mut rotation = Self::new(policy);
+        for config in rotations {
+            let name = Name::from_str(&config.name)
+                .and_then(|name| {
+                    if name.is_fqdn() {
+                        Ok(name)
+                    } else {
+                        name.append_domain(origin)
+                    }
+                })
+                .map_err(|e| format!("bad rotation name {}: {e}", config.name))?;
+            let record_type = config
+                .record_type
+                .as_deref()
+                .map(RecordType::from_str)
+                .transpose()
+                .map_err(|e| format!("bad rotation record type of {name}: {e}"))?;
+
+            let weights = match record_type {
+                Some(record_type) => config
+                    .weights
+                    .iter()
+                    .map(|(rdata, weight)| {
+                        RData::try_from_str(record_type, rdata)
+                            .map(|rdata| (rdata, *weight))
+                            .map_err(|e| format!("bad weighted record {rdata} of {name}: {e}"))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+                None if config.weights.is_empty() => Vec::new(),
+                None => return Err(format!("the weights of {name} need a record_type")),
+            };
+
+            rotation.insert(name, record_type, config.policy, weights);
+        }
+
+        Ok(rotation)
+    }
+
+    /// Sets the order of the RRsets of the name, or of its RRset of the type
+    ///
+    /// The records without weights have the weight 1.
+    pub fn insert(
+        &mut self,
+        name: Name,
+        record_type: Option<RecordType>,
+        policy: RotationPolicy,
+        weights: Vec<(RData, u32)>,
+    ) {
+        let rotations = self.rrsets.entry(LowerName::new(&name)).or_default();
+        rotations.retain(|rotation| rotation.record_type != record_type);
+        rotations.push(RrsetRotation {
+            record_type,
+            policy,
+            weights,
+        });
+    }
+
+    /// The RRset of an answer, with its records in their order
+    pub fn order(&self, rrset: Arc<RecordSet>) -> Arc<RecordSet> {
+        let rotation = self
+            .rrsets
+            .get(&LowerName::new(rrset.name()))
+            .and_then(|rotations| {
+                // the order of the type is more specific than the order of the name
+                rotations
+                    .iter()
+                    .find(|rotation| rotation.record_type == Some(rrset.record_type()))
+                    .or_else(|| {
+                        rotations
+                            .iter()
+                            .find(|rotation| rotation.record_type.is_none())
+                    })
+            });
+        let policy = rotation.map_or(self.policy, |rotation| rotation.policy);
+
+        let records = rrset.records_without_rrsigs().collect::<Vec<_>>();
+        if policy == RotationPolicy::Fixed || records.len() < 2 {
+            return rrset;
+        }
+
+        let answer = self.answers.fetch_add(1, Ordering::Relaxed);
+        let records = match policy {
+            RotationPolicy::RoundRobin => {
+                let mut records = records;
+                let len = records.len();
+                records.rotate_left(answer % len);
+                records
+            }
+            RotationPolicy::Weighted => {
+                let weights = rotation.map_or(&[][..], |rotation| &rotation.weights);
+                shuffle(records, weights, answer)
+            }
+            RotationPolicy::Fixed => return rrset,
+        };
+
+        let mut ordered = RecordSet::new(rrset.name(), rrset.record_type(), rrset.serial());
+        ordered.set_dns_class(rrset.dns_class());
+        for record in records {
+            ordered.insert(record.clone(), rrset.serial());
+        }
+        // the signatures are of the canonical order of the records
+        for rrsig in rrset.rrsigs() {
+            ordered.insert_rrsig(rrsig.clone());
+        }
+
+        Arc::new(ordered)
+    }
+}
+
+/// Shuffles the records, the first records are chosen by their weights
+///
+/// Each record gets the key `u^(1 / weight)` of a random `u` in `(0, 1]`, and the records are
+///  ordered by their keys, see "Weighted random sampling with a reservoir" by Efraimidis and
+///  Spirakis. The records with the weight 0 are last.
+fn shuffle<'r>(
+    records: Vec<&'r Record>,
+    weights: &[(RData, u32)],
+    answer: usize,
+) -> Vec<&'r Record> {
+    let random = RandomState::new();
+    let mut keyed = records
+        .into_iter()
+        .enumerate()
+        .map(|(index, record)| {
+            let weight = record
+                .data()
+                .and_then(|rdata| weights.iter().find(|(weighted, _)| weighted == rdata))
+                .map_or(1, |(_, weight)| *weight);
+
+            let mut hasher = random.build_hasher();
+            (answer, index).hash(&mut hasher);
+            let u = (hasher.finish() >> 11) as f64 / (1_u64 << 53) as f64;
+            // ln of the key, which keeps the order of the keys
+            let key = if weight == 0 {
+                f64::NEG_INFINITY
+            } else {
+                (1.0 - u).ln() / f64::from(weight)
+            };
+
+            (key, record)
+        })
+        .collect::<Vec<_>>();
+
+    keyed.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    keyed.into_iter().map(|(_, record)| record).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    fn rrset() -> Arc<RecordSet> {
+        let mut rrset = RecordSet::with_ttl(
+            Name::from_str("www.example.com.").unwrap(),
+            RecordType::A,
+            300,
+        );
+        for last in 1..=3 {
+            rrset.add_rdata(RData::A(Ipv4Addr::new(192, 0, 2, last).into()));
+        }
+        Arc::new(rrset)
+    }
+
+    fn first(rrset: &RecordSet) -> RData {
+        rrset
+            .records_without_rrsigs()
+            .next()
+            .and_then(Record::data)
+            .cloned()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let rotation = Rotation::new(RotationPolicy::RoundRobin);
+        let rrset = rrset();
+
+        let firsts = (0..4)
+            .map(|_| first(&rotation.order(Arc::clone(&rrset))))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            firsts,
+            [1, 2, 3, 1]
+                .into_iter()
+                .map(|last| RData::A(Ipv4Addr::new(192, 0, 2, last).into()))
+                .collect::<Vec<_>>()
+        );
+
+        // the records of the zone keep their order
+        assert_eq!(first(&rrset), RData::A(Ipv4Addr::new(192, 0, 2, 1).into()));
+        assert_eq!(rotation.order(Arc::clone(&rrset)).ttl(), 300);
+    }
+
+    #[test]
+    fn test_weighted() {
+        let origin = Name::from_str("example.com.").unwrap();
+        let config = RotationConfig {
+            name: "www".to_string(),
+            record_type: Some("A".to_string()),
+            policy: RotationPolicy::Weighted,
+            weights: HashMap::from([
+                ("192.0.2.1".to_string(), 0),
+                ("192.0.2.2".to_string(), 1),
+                ("192.0.2.3".to_string(), 1000),
+            ]),
+        };
+        let rotation =
+            Rotation::try_from_config(&origin, RotationPolicy::Fixed, &[config]).unwrap();
+
+        let heaviest = RData::A(Ipv4Addr::new(192, 0, 2, 3).into());
+        let mut heaviest_first = 0;
+        for _ in 0..100 {
+            let ordered = rotation.order(rrset());
+            assert_eq!(ordered.records_without_rrsigs().count(), 3);
+            // the record with the weight 0 is always last
+            assert_eq!(
+                ordered
+                    .records_without_rrsigs()
+                    .last()
+                    .and_then(Record::data),
+                Some(&RData::A(Ipv4Addr::new(192, 0, 2, 1).into()))
+            );
+            if first(&ordered) == heaviest {
+                heaviest_first += 1;
+            }
+        }
+        assert!(heaviest_first > 80);
+    }
+
+    #[test]
+    fn test_override() {
+        let origin = Name::from_str("example.com.").unwrap();
+        let config = RotationConfig {
+            name: "www.example.com.".to_string(),
+            record_type: None,
+            policy: RotationPolicy::Fixed,
+            weights: HashMap::new(),
+        };
+        let rotation =
+            Rotation::try_from_config(&origin, RotationPolicy::RoundRobin, &[config]).unwrap();
+
+        for _ in 0..3 {
+            assert_eq!(
+                first(&rotation.order(rrset())),
+                RData::A(Ipv4Addr::new(192, 0, 2, 1).into())
+            );
+        }
+
+        let config = RotationConfig {
+            name: "www".to_string(),
+            record_type: None,
+            policy: RotationPolicy::Weighted,
+            weights: HashMap::from([("192.0.2.1".to_string(), 2)]),
+        };
+        assert!(Rotation::try_from_config(&origin, RotationPolicy::Fixed, &[config]).is_err());
+    }
+}
@@ -7,9 +7,131 @@
 
 //! All persistent store implementations
 
+/// Implements `Authority` for a zone which answers the queries of some names from its pools, and
+///  everything else from its `zone: InMemoryAuthority`
+///
+/// `$pool_lookup` is a method of the type, with the signature `fn(&self, &LowerName, RecordType,
+///  Option<IpAddr>, LookupOptions) -> Option<Result<AuthLookup, LookupError>>`. It is passed the
+///  address of the client when searching for the query of a request, and returns `None` for the
+///  names without a pool. Zone transfers and SOA queries are always answered from the zone, and
+///  the records are only configured, updates are not implemented.
+macro_rules! pool_authority {
+    ($authority:ty, $pool_lookup:ident) => {
+        #[async_trait::async_trait]
+        impl $crate::authority::Authority for $authority {
+            type Lookup = $crate::authority::AuthLookup;
+
+            /// What type is this zone
+            fn zone_type(&self) -> $crate::authority::ZoneType {
+                self.zone.zone_type()
+            }
+
+            /// Return true if AXFR is allowed
+            fn is_axfr_allowed(&self) -> bool {
+                self.zone.is_axfr_allowed()
+            }
+
+            /// The records of the zone are only configured
+            async fn update(
+                &self,
+                _update: &$crate::authority::MessageRequest,
+            ) -> $crate::authority::UpdateResult<bool> {
+                Err($crate::proto::op::ResponseCode::NotImp)
+            }
+
+            /// Get the origin of this zone
+            fn origin(&self) -> &$crate::proto::rr::LowerName {
+                self.zone.origin()
+            }
+
+            /// Looks up the records of the name, from its pool if it has one
+            async fn lookup(
+                &self,
+                name: &$crate::proto::rr::LowerName,
+                rtype: $crate::proto::rr::RecordType,
+                lookup_options: $crate::authority::LookupOptions,
+            ) -> Result<Self::Lookup, $crate::authority::LookupError> {
+                match self.$pool_lookup(name, rtype, None, lookup_options) {
+                    Some(lookup) => lookup,
+                    None => self.zone.lookup(name, rtype, lookup_options).await,
+                }
+            }
+
+            /// Looks up the records of the query, from the pool of its name for the client
+            async fn search(
+                &self,
+                request_info: $crate::server::RequestInfo<'_>,
+                lookup_options: $crate::authority::LookupOptions,
+            ) -> Result<Self::Lookup, $crate::authority::LookupError> {
+                use $crate::proto::rr::RecordType;
+
+                let rtype = request_info.query.query_type();
+                if !matches!(rtype, RecordType::AXFR | RecordType::SOA) {
+                    let addr = request_info.client_addr();
+                    let name = request_info.query.name();
+                    if let Some(lookup) = self.$pool_lookup(name, rtype, Some(addr), lookup_options)
+                    {
+                        return lookup;
+                    }
+                }
+
+                self.zone.search(request_info, lookup_options).await
+            }
+
+            /// Get the NS, NameServer, record for the zone
+            async fn ns(
+                &self,
+                lookup_options: $crate::authority::LookupOptions,
+            ) -> Result<Self::Lookup, $crate::authority::LookupError> {
+                self.zone.ns(lookup_options).await
+            }
+
+            /// Return the NSEC records based on the given name
+            async fn get_nsec_records(
+                &self,
+                name: &$crate::proto::rr::LowerName,
+                lookup_options: $crate::authority::LookupOptions,
+            ) -> Result<Self::Lookup, $crate::authority::LookupError> {
+                self.zone.get_nsec_records(name, lookup_options).await
+            }
+
+            /// Return the NSEC records proving that there is no closer match for an answer
+            ///  synthesized from a wildcard
+            async fn get_wildcard_proof(
+                &self,
+                next_closer: &$crate::proto::rr::LowerName,
+                lookup_options: $crate::authority::LookupOptions,
+            ) -> Result<Self::Lookup, $crate::authority::LookupError> {
+                self.zone
+                    .get_wildcard_proof(next_closer, lookup_options)
+                    .await
+            }
+
+            /// Returns the SOA of the authority.
+            async fn soa(&self) -> Result<Self::Lookup, $crate::authority::LookupError> {
+                self.zone.soa().await
+            }
+
+            /// Returns the SOA record for the zone
+            async fn soa_secure(
+                &self,
+                lookup_options: $crate::authority::LookupOptions,
+            ) -> Result<Self::Lookup, $crate::authority::LookupError> {
+                self.zone.soa_secure(lookup_options).await
+            }
+        }
+    };
+}
+
 mod config;
+pub mod dhcp;
+pub mod discovery;
 pub mod file;
 pub mod forwarder;
+#[cfg(feature = "geoip")]
+#[cfg_attr(docsrs, doc(cfg(feature = "geoip")))]
+pub mod geo;
+pub mod health;
 pub mod in_memory;
 pub mod recursor;
 #[cfg(feature = "sqlite")]
@@ -7,7 +7,7 @@
 
 use std::{io, path::Path, time::Instant};
 
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 use crate::{
     authority::{
@@ -17,9 +17,9 @@ use crate::{
         op::{Query, ResponseCode},
         rr::{LowerName, Name, Record, RecordType},
     },
-    recursor::Recursor,
+    recursor::{root_hints, Recursor},
     resolver::{
-        config::{NameServerConfig, NameServerConfigGroup, Protocol},
+        config::{NameServerConfig, NameServerConfigGroup, PrivacyPolicy, Protocol},
         lookup::Lookup,
     },
     server::RequestInfo,
@@ -44,37 +44,84 @@ impl RecursiveAuthority {
     ) -> Result<Self, String> {
         info!("loading recursor config: {}", origin);
 
-        // read the roots
-        let root_addrs = config
-            .read_roots(root_dir)
-            .map_err(|e| format!("failed to read roots {}: {}", config.roots.display(), e))?;
-
-        // Configure all the name servers
-        let mut roots = NameServerConfigGroup::new();
-        for socket_addr in root_addrs {
-            roots.push(NameServerConfig {
-                socket_addr,
-                protocol: Protocol::Tcp,
-                tls_dns_name: None,
-                trust_negative_responses: false,
-                #[cfg(feature = "dns-over-rustls")]
-                tls_config: None,
-                bind_addr: None, // TODO: need to support bind addresses
-            });
-
-            roots.push(NameServerConfig {
-                socket_addr,
-                protocol: Protocol::Udp,
-                tls_dns_name: None,
-                trust_negative_responses: false,
-                #[cfg(feature = "dns-over-rustls")]
-                tls_config: None,
-                bind_addr: None,
-            });
+        // read the roots, or use the built-in root hints
+        let roots = if let Some(roots) = &config.roots {
+            let root_addrs = RecursiveConfig::read_roots(roots, root_dir)
+                .map_err(|e| format!("failed to read roots {}: {}", roots.display(), e))?;
+
+            // Configure all the name servers
+            let mut roots = NameServerConfigGroup::new();
+            for socket_addr in root_addrs {
+                roots.push(NameServerConfig {
+                    socket_addr,
+                    protocol: Protocol::Tcp,
+                    tls_dns_name: None,
+                    trust_negative_responses: false,
+                    privacy: PrivacyPolicy::default(),
+                    weight: 1,
+                    #[cfg(feature = "dns-over-rustls")]
+                    tls_config: None,
+                    bind_addr: None, // TODO: need to support bind addresses
+                    bind_device: None,
+                    http: None,
+                });
+
+                roots.push(NameServerConfig {
+                    socket_addr,
+                    protocol: Protocol::Udp,
+                    tls_dns_name: None,
+                    trust_negative_responses: false,
+                    privacy: PrivacyPolicy::default(),
+                    weight: 1,
+                    #[cfg(feature = "dns-over-rustls")]
+                    tls_config: None,
+                    bind_addr: None,
+                    bind_device: None,
+                    http: None,
+                });
+            }
+            roots
+        } else {
+            root_hints()
+        };
+
+        let builder = Recursor::builder()
+            .ns_cache_size(config.ns_cache_size)
+            .record_cache_size(config.record_cache_size);
+        #[cfg(feature = "dnssec")]
+        let builder = builder.dnssec_validation(config.dnssec_validation);
+        let mut recursor = builder
+            .build(roots)
+            .map_err(|e| format!("failed to initialize recursor: {e}"))?;
+
+        for zone in config.forward_zones.iter().chain(&config.stub_zones) {
+            if zone.name_servers.is_empty() {
+                return Err(format!("no name servers configured for {}", zone.zone));
+            }
         }
 
-        let recursor = Recursor::new(roots, config.ns_cache_size, config.record_cache_size)
-            .map_err(|e| format!("failed to initialize recursor: {e}"))?;
+        for forward in &config.forward_zones {
+            recursor =
+                recursor.with_forward_zone(forward.zone.clone(), forward.name_servers.clone());
+        }
+        for stub in &config.stub_zones {
+            recursor = recursor.with_stub_zone(stub.zone.clone(), stub.name_servers.clone());
+        }
+
+        #[cfg(feature = "root-zone-mirror")]
+        if config.root_zone_mirror {
+            let servers = if config.root_zone_servers.is_empty() {
+                crate::recursor::root_zone_servers()
+            } else {
+                config.root_zone_servers.clone()
+            };
+            recursor = recursor.with_root_zone_mirror(servers);
+        }
+
+        // the root hints are queried until the priming succeeds on a later query
+        if let Err(e) = recursor.prime().await {
+            warn!("failed to prime the root name servers: {e}");
+        }
 
         Ok(Self {
             origin: origin.into(),
@@ -152,6 +199,17 @@ impl Authority for RecursiveAuthority {
             "Getting NSEC records is unimplemented for the recursor",
         )))
     }
+
+    async fn get_wildcard_proof(
+        &self,
+        _next_closer: &LowerName,
+        _lookup_options: LookupOptions,
+    ) -> Result<Self::Lookup, LookupError> {
+        Err(LookupError::from(io::Error::new(
+            io::ErrorKind::Other,
+            "Getting wildcard proofs is unimplemented for the recursor",
+        )))
+    }
 }
 
 pub struct RecursiveLookup(Lookup);
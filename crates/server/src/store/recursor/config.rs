@@ -20,13 +20,15 @@ use crate::proto::{
     rr::{RData, Record, RecordSet},
     serialize::txt::Parser,
 };
-use crate::resolver::Name;
+use crate::resolver::{config::NameServerConfigGroup, Name};
 
 /// Configuration for file based zones
 #[derive(Clone, Deserialize, Eq, PartialEq, Debug)]
 pub struct RecursiveConfig {
-    /// File with roots, aka hints
-    pub roots: PathBuf,
+    /// File with roots, aka hints, e.g. the `named.root` file of IANA. The built-in root hints
+    ///  are used if this is not set, see `hickory_recursor::root_hints`.
+    #[serde(default)]
+    pub roots: Option<PathBuf>,
 
     /// Maximum nameserver cache size
     #[serde(default = "ns_cache_size_default")]
@@ -35,17 +37,56 @@ pub struct RecursiveConfig {
     /// Maximum DNS record cache size
     #[serde(default = "record_cache_size_default")]
     pub record_cache_size: usize,
+
+    /// Zones which are forwarded to recursive resolvers, instead of being resolved from the roots
+    #[serde(default)]
+    pub forward_zones: Vec<ZoneNameServersConfig>,
+
+    /// Zones with fixed authoritative name servers, instead of the ones delegated to by the
+    ///  parent zone
+    #[serde(default)]
+    pub stub_zones: Vec<ZoneNameServersConfig>,
+
+    /// Validate the priming response of the root hints with DNSSEC, see
+    ///  `RecursorBuilder::dnssec_validation`
+    #[cfg(feature = "dnssec")]
+    #[serde(default)]
+    pub dnssec_validation: bool,
+
+    /// Answer the queries to the root name servers from a local copy of the root zone, see
+    ///  `Recursor::with_root_zone_mirror`
+    #[cfg(feature = "root-zone-mirror")]
+    #[serde(default)]
+    pub root_zone_mirror: bool,
+
+    /// The servers the root zone is transferred from, defaults to the servers of ICANN, see
+    ///  `hickory_recursor::root_zone_servers`
+    #[cfg(feature = "root-zone-mirror")]
+    #[serde(default)]
+    pub root_zone_servers: Vec<SocketAddr>,
+}
+
+/// Name servers used for all names in a zone, see `RecursiveConfig::forward_zones` and
+///  `RecursiveConfig::stub_zones`
+#[derive(Clone, Deserialize, Eq, PartialEq, Debug)]
+pub struct ZoneNameServersConfig {
+    /// The name of the zone
+    pub zone: Name,
+
+    /// The name servers to use for the zone
+    pub name_servers: NameServerConfigGroup,
 }
 
 impl RecursiveConfig {
+    /// Reads the addresses of the root hints from the `roots` file
     pub(crate) fn read_roots(
-        &self,
+        roots: &Path,
         root_dir: Option<&Path>,
     ) -> Result<Vec<SocketAddr>, ConfigError> {
         let path = if let Some(root_dir) = root_dir {
-            Cow::Owned(root_dir.join(&self.roots))
+            Cow::Owned(root_dir.join(roots))
         } else {
-            Cow::Borrowed(&self.roots)
+            Cow::Borrowed(roots)
         };
 
         let mut roots = File::open(path.as_ref())?;
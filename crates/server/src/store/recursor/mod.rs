@@ -13,4 +13,4 @@ mod authority;
 mod config;
 
 pub use self::authority::RecursiveAuthority;
-pub use self::config::RecursiveConfig;
+pub use self::config::{RecursiveConfig, ZoneNameServersConfig};
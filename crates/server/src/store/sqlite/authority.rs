@@ -200,6 +200,18 @@ impl SqliteAuthority {
         Ok(())
     }
 
+    /// Verifies the backing Journal, if any, returns an error if the SOA serials recorded in the
+    ///  journal ever decrease.
+    pub async fn verify_journal(&self) -> PersistenceResult<()> {
+        if let Some(journal) = self.journal.lock().await.as_ref() {
+            if let Some(serial) = journal.verify_serials()? {
+                info!("verified journal up to SOA.serial: {}", serial);
+            }
+        }
+
+        Ok(())
+    }
+
     /// Associate a backing Journal with this Authority for Updatable zones
     pub async fn set_journal(&mut self, journal: Journal) {
         *self.journal.lock().await = Some(journal);
@@ -969,6 +981,16 @@ impl Authority for SqliteAuthority {
         self.in_memory.search(request_info, lookup_options).await
     }
 
+    /// Looks up the answer of a simple query without awaiting, see `Authority::lookup_now`
+    fn lookup_now(
+        &self,
+        name: &LowerName,
+        rtype: RecordType,
+        lookup_options: LookupOptions,
+    ) -> Option<Vec<Record>> {
+        self.in_memory.lookup_now(name, rtype, lookup_options)
+    }
+
     /// Return the NSEC records based on the given name
     ///
     /// # Arguments
@@ -983,6 +1005,18 @@ impl Authority for SqliteAuthority {
     ) -> Result<Self::Lookup, LookupError> {
         self.in_memory.get_nsec_records(name, lookup_options).await
     }
+
+    /// Return the NSEC records proving that there is no closer match for an answer synthesized
+    ///  from a wildcard
+    async fn get_wildcard_proof(
+        &self,
+        next_closer: &LowerName,
+        lookup_options: LookupOptions,
+    ) -> Result<Self::Lookup, LookupError> {
+        self.in_memory
+            .get_wildcard_proof(next_closer, lookup_options)
+            .await
+    }
 }
 
 #[cfg(feature = "dnssec")]
@@ -173,6 +173,47 @@ impl Journal {
         }
     }
 
+    /// Verifies that the SOA serials recorded in the journal never decrease, in insertion order.
+    ///
+    /// The serials are compared with the sequence space arithmetic of RFC 1982, so a serial may
+    ///  wrap around. Returns the last serial in the journal, or `None` if the journal is empty.
+    pub fn verify_serials(&self) -> PersistenceResult<Option<u32>> {
+        assert!(
+            self.version == CURRENT_VERSION,
+            "schema version mismatch, schema_up() resolves this"
+        );
+
+        let conn = self.conn.lock().expect("conn poisoned");
+        let mut stmt = conn.prepare(
+            "SELECT _rowid_, soa_serial
+                                            \
+                                               FROM records
+                                            \
+                                               ORDER BY _rowid_",
+        )?;
+
+        let mut previous: Option<u32> = None;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let row_id: i64 = row.get(0)?;
+            let serial: u32 = row.get(1)?;
+
+            match previous {
+                Some(previous) if (serial.wrapping_sub(previous) as i32) < 0 => {
+                    return Err(PersistenceErrorKind::SerialRegression {
+                        row_id,
+                        serial,
+                        previous,
+                    }
+                    .into())
+                }
+                _ => previous = Some(serial),
+            }
+        }
+
+        Ok(previous)
+    }
+
     /// selects the current schema version of the journal DB, returns -1 if there is no schema
     ///
     ///
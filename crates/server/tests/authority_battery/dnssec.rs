@@ -1,7 +1,11 @@
 #![cfg(feature = "dnssec")]
 
 use std::str::FromStr;
-use std::{future::Future, sync::Arc};
+use std::{
+    future::Future,
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
 
 use futures_executor::block_on;
 
@@ -18,7 +22,9 @@ use hickory_proto::{
 };
 use hickory_server::{
     authority::{AuthLookup, Authority, DnssecAuthority, LookupOptions},
+    config::{dnssec::NxProofKind, AnyQueryPolicy},
     server::{Protocol, RequestInfo},
+    store::in_memory::InMemoryAuthority,
 };
 
 const TEST_HEADER: &Header = &Header::new();
@@ -193,6 +199,61 @@ pub fn test_wildcard<A: Authority<Lookup = AuthLookup>>(authority: A, keys: &[DN
     verify(&cname_records, &rrsig_records, keys);
 }
 
+pub fn test_wildcard_proof<A: Authority<Lookup = AuthLookup>>(authority: A, keys: &[DNSKEY]) {
+    let name = Name::from_str("www.wildcard.example.com.").unwrap();
+    let query = Query::query(name.clone(), RecordType::CNAME).into();
+    let request_info = RequestInfo::new(
+        "127.0.0.1:53".parse().unwrap(),
+        Protocol::Udp,
+        TEST_HEADER,
+        &query,
+    );
+
+    let lookup = block_on(authority.search(
+        request_info,
+        LookupOptions::for_dnssec(true, SupportedAlgorithms::new()),
+    ))
+    .expect("lookup of www.wildcard.example.com. failed");
+
+    // the RRSIGs are owned by the query name, with the labels of the wildcard
+    let rrsigs: Vec<_> = lookup
+        .into_iter()
+        .cloned()
+        .filter_map(|r| Record::<RRSIG>::try_from(r).ok())
+        .collect();
+    assert!(!rrsigs.is_empty());
+    assert!(rrsigs.iter().all(|r| *r.name() == name
+        && r.data().unwrap().is_wildcard_expansion(&name)
+        && r.data().unwrap().num_labels() == 3));
+
+    // the next closer name is the query name itself
+    let lookup = block_on(authority.get_wildcard_proof(
+        &name.clone().into(),
+        LookupOptions::for_dnssec(true, SupportedAlgorithms::new()),
+    ))
+    .unwrap();
+
+    let (nsec_records, other_records): (Vec<_>, Vec<_>) = lookup
+        .into_iter()
+        .partition(|r| r.record_type() == RecordType::NSEC);
+
+    assert_eq!(nsec_records.len(), 1);
+    let nsec = nsec_records[0]
+        .data()
+        .and_then(RData::as_dnssec)
+        .and_then(|r| r.as_nsec())
+        .unwrap();
+    assert!(*nsec_records[0].name() < name);
+    assert!(name < *nsec.next_domain_name());
+
+    let rrsig_records: Vec<_> = other_records
+        .into_iter()
+        .cloned()
+        .filter_map(|r| Record::<RRSIG>::try_from(r).ok())
+        .collect();
+    verify(&nsec_records, &rrsig_records, keys);
+}
+
 pub fn test_nsec_nodata<A: Authority<Lookup = AuthLookup>>(authority: A, keys: &[DNSKEY]) {
     // this should have a single nsec record that covers the type
     let name = Name::from_str("www.example.com.").unwrap();
@@ -328,6 +389,105 @@ pub fn test_nsec_nxdomain_wraps_end<A: Authority<Lookup = AuthLookup>>(
     .is_secure());
 }
 
+pub fn test_nsec_minimally_covering<A>(mut authority: A, keys: &[DNSKEY])
+where
+    A: Authority<Lookup = AuthLookup> + DerefMut<Target = InMemoryAuthority>,
+{
+    authority.set_nx_proof_kind(NxProofKind::MinimallyCoveringNsec);
+
+    // ccc is between bbb and www, but the proof must not reveal either of them
+    let name = Name::from_str("ccc.example.com.").unwrap();
+    let lookup = block_on(authority.get_nsec_records(
+        &name.clone().into(),
+        LookupOptions::for_dnssec(true, SupportedAlgorithms::new()),
+    ))
+    .unwrap();
+
+    let (nsec_records, rrsig_records): (Vec<_>, Vec<_>) = lookup
+        .into_iter()
+        .cloned()
+        .partition(|r| r.record_type() == RecordType::NSEC);
+
+    println!("nsec_records: {nsec_records:?}");
+
+    // one record covers the name, the other is for the wildcard proof.
+    assert_eq!(nsec_records.len(), 2);
+
+    let covering = nsec_records
+        .iter()
+        .find(|r| *r.name() < name && r.name().base_name() == name.base_name())
+        .and_then(|r| r.data())
+        .and_then(RData::as_dnssec)
+        .and_then(|r| r.as_nsec())
+        .expect("covering NSEC missing");
+    assert_eq!(
+        covering.next_domain_name(),
+        &Name::from_labels(vec![&[0_u8][..], b"ccc", b"example", b"com"]).unwrap()
+    );
+    assert!(nsec_records.iter().all(|r| {
+        let next = r
+            .data()
+            .and_then(RData::as_dnssec)
+            .and_then(|r| r.as_nsec())
+            .unwrap()
+            .next_domain_name();
+        r.name().to_ascii() != "bbb.example.com." && next.to_ascii() != "www.example.com."
+    }));
+
+    // the generated records are signed
+    let rrsig_records: Vec<Record<RRSIG>> = rrsig_records
+        .into_iter()
+        .filter_map(|r| Record::<RRSIG>::try_from(r).ok())
+        .collect();
+    for nsec in &nsec_records {
+        let rrsigs: Vec<Record<RRSIG>> = rrsig_records
+            .iter()
+            .filter(|r| r.name() == nsec.name())
+            .cloned()
+            .collect();
+        verify(&[nsec], &rrsigs, keys);
+    }
+
+    let nsecs: Vec<&Record> = nsec_records.iter().collect();
+
+    let query = Query::query(name, RecordType::A);
+    let query = Arc::new(query);
+    assert!(xfer::dnssec_dns_handle::verify_nsec(
+        query,
+        &Name::from_str("example.com.").unwrap(),
+        &nsecs
+    )
+    .is_secure());
+}
+
+pub fn test_any_query_hinfo<A>(mut authority: A, keys: &[DNSKEY])
+where
+    A: Authority<Lookup = AuthLookup> + DerefMut<Target = InMemoryAuthority>,
+{
+    authority.set_any_query(AnyQueryPolicy::Hinfo);
+
+    let lookup = block_on(authority.lookup(
+        &Name::from_str("www.example.com.").unwrap().into(),
+        RecordType::ANY,
+        LookupOptions::for_dnssec(true, SupportedAlgorithms::new()),
+    ))
+    .unwrap();
+
+    let (hinfo_records, other_records): (Vec<_>, Vec<_>) = lookup
+        .into_iter()
+        .partition(|r| r.record_type() == RecordType::HINFO);
+    assert_eq!(hinfo_records.len(), 1);
+
+    // the synthesized record is signed
+    let rrsig_records: Vec<_> = other_records
+        .into_iter()
+        .cloned()
+        .filter_map(|r| Record::<RRSIG>::try_from(r).ok())
+        .collect();
+    assert_eq!(rrsig_records.len(), keys.len());
+    verify(&hinfo_records, &rrsig_records, keys);
+}
+
 pub fn test_rfc_6975_supported_algorithms<A: Authority<Lookup = AuthLookup>>(
     authority: A,
     keys: &[DNSKEY],
@@ -365,6 +525,14 @@ pub fn test_rfc_6975_supported_algorithms<A: Authority<Lookup = AuthLookup>>(
     }
 }
 
+pub fn test_verify_zone<A>(authority: A, keys: &[DNSKEY])
+where
+    A: Authority<Lookup = AuthLookup> + Deref<Target = InMemoryAuthority>,
+{
+    assert!(!keys.is_empty());
+    block_on(authority.verify_zone()).expect("zone signatures failed to verify");
+}
+
 pub fn verify(records: &[&Record], rrsig_records: &[Record<RRSIG>], keys: &[DNSKEY]) {
     let record_name = records.first().unwrap().name();
     let record_type = records.first().unwrap().record_type();
@@ -492,11 +660,15 @@ macro_rules! dnssec_battery {
                     test_ns,
                     test_aname_lookup,
                     test_wildcard,
+                    test_wildcard_proof,
                     test_nsec_nodata,
                     test_nsec_nxdomain_start,
                     test_nsec_nxdomain_middle,
                     test_nsec_nxdomain_wraps_end,
+                    test_nsec_minimally_covering,
+                    test_any_query_hinfo,
                     test_rfc_6975_supported_algorithms,
+                    test_verify_zone,
                 );
             }
         }
@@ -23,6 +23,10 @@ use std::time::Duration;
 
 use hickory_server::authority::ZoneType;
 use hickory_server::config::*;
+use hickory_server::server::{AcceptConfig, AdmissionConfig, Overflow};
+use hickory_server::store::dhcp::{LeaseFileFormat, LeaseFileSource};
+#[cfg(all(feature = "sqlite", feature = "resolver"))]
+use hickory_server::store::StoreConfig;
 
 #[test]
 fn test_read_config() {
@@ -41,6 +45,7 @@ fn test_read_config() {
     assert_eq!(config.get_listen_addrs_ipv4(), Ok(Vec::<Ipv4Addr>::new()));
     assert_eq!(config.get_listen_addrs_ipv6(), Ok(Vec::<Ipv6Addr>::new()));
     assert_eq!(config.get_tcp_request_timeout(), Duration::from_secs(5));
+    assert_eq!(config.get_workers(), None);
     assert_eq!(config.get_log_level(), tracing::Level::INFO);
     assert_eq!(config.get_directory(), Path::new("/var/named"));
     assert_eq!(
@@ -141,6 +146,55 @@ fn test_parse_toml() {
     let config = Config::from_toml("tcp_request_timeout = 25").unwrap();
     assert_eq!(config.get_tcp_request_timeout(), Duration::from_secs(25));
 
+    let config = Config::from_toml(
+        "max_tcp_connections = 150\nmax_tcp_connections_per_ip = 10\nmax_tcp_connection_lifetime = 60",
+    )
+    .unwrap();
+    let accept_config = config.get_accept_config();
+    assert_eq!(accept_config.max_connections.map(usize::from), Some(150));
+    assert_eq!(
+        accept_config.max_connections_per_ip.map(usize::from),
+        Some(10)
+    );
+    assert_eq!(
+        accept_config.max_connection_lifetime,
+        Some(Duration::from_secs(60))
+    );
+    assert_eq!(
+        Config::from_toml("").unwrap().get_accept_config(),
+        AcceptConfig::default()
+    );
+
+    let config = Config::from_toml("tcp_proxy_protocol = true").unwrap();
+    assert!(config.get_tcp_proxy_protocol());
+    assert!(!config.get_tls_proxy_protocol());
+
+    let config = Config::from_toml("request_timeout_ms = 1500").unwrap();
+    assert_eq!(
+        config.get_request_timeout(),
+        Some(Duration::from_millis(1500))
+    );
+    assert_eq!(config.get_slow_query_threshold(), None);
+
+    let config = Config::from_toml(
+        "max_requests_in_flight = 1000\nmax_requests_queued = 10\nrequest_overflow = \"servfail\"",
+    )
+    .unwrap();
+    let admission_config = config.get_admission_config();
+    assert_eq!(admission_config.max_in_flight.map(usize::from), Some(1000));
+    assert_eq!(admission_config.max_queued, 10);
+    assert_eq!(admission_config.max_in_flight_per_connection.get(), 1);
+    assert_eq!(admission_config.overflow, Overflow::ServFail);
+    assert_eq!(
+        Config::from_toml("").unwrap().get_admission_config(),
+        AdmissionConfig::default()
+    );
+    assert!(Config::from_toml("request_overflow = \"refuse\"").is_err());
+
+    let config = Config::from_toml("workers = 8").unwrap();
+    assert_eq!(config.get_workers().map(usize::from), Some(8));
+    assert!(Config::from_toml("workers = 0").is_err());
+
     let config = Config::from_toml("log_level = \"Debug\"").unwrap();
     assert_eq!(config.get_log_level(), tracing::Level::DEBUG);
 
@@ -236,6 +290,23 @@ tls_listen_port = 8853
         config.get_tls_cert().unwrap().get_path(),
         Path::new("path/to/some.pkcs12")
     );
+    assert!(config.get_tls_certs().is_empty());
+
+    let config = Config::from_toml(
+        "tls_cert = { path = \"dot.pem\", endpoint_name = \"dot.example.com\", private_key = \"dot.key\" }
+
+[[tls_certs]]
+path = \"doh.pem\"
+endpoint_name = \"doh.example.net\"
+private_key = \"doh.key\"
+",
+    )
+    .unwrap();
+
+    let tls_certs = config.get_tls_certs();
+    assert_eq!(tls_certs.len(), 1);
+    assert_eq!(tls_certs[0].get_endpoint_name(), Some("doh.example.net"));
+    assert_eq!(tls_certs[0].get_private_key(), Some(Path::new("doh.key")));
 }
 
 fn test_config(path: &str) {
@@ -273,3 +344,796 @@ define_test_config!(openssl_dnssec);
 define_test_config!(ring_dnssec);
 #[cfg(feature = "hickory-resolver")]
 define_test_config!(example_forwarder);
+#[cfg(feature = "hickory-recursor")]
+define_test_config!(example_recursor);
+
+#[cfg(feature = "hickory-recursor")]
+#[test]
+fn test_parse_recursor_zones() {
+    use std::str::FromStr;
+
+    use hickory_server::{proto::rr::Name, store::StoreConfig};
+
+    let config = Config::from_toml(
+        "[[zones]]
+zone = \".\"
+zone_type = \"Hint\"
+
+[zones.stores]
+type = \"recursor\"
+roots = \"default/root.zone\"
+
+[[zones.stores.forward_zones]]
+zone = \"corp.example.com.\"
+name_servers = [{ socket_addr = \"192.0.2.1:53\", protocol = \"udp\", trust_negative_responses = false }]
+
+[[zones.stores.stub_zones]]
+zone = \"lab.example.com.\"
+name_servers = [{ socket_addr = \"192.0.2.2:53\", protocol = \"udp\", trust_negative_responses = true },
+                { socket_addr = \"192.0.2.2:53\", protocol = \"tcp\", trust_negative_responses = true }]
+",
+    )
+    .unwrap();
+
+    let Some(StoreConfig::Recursor(recursor)) = &config.get_zones()[0].stores else {
+        panic!("expected recursor store");
+    };
+    assert_eq!(
+        recursor.roots.as_deref(),
+        Some(std::path::Path::new("default/root.zone"))
+    );
+    assert_eq!(recursor.ns_cache_size, 1024);
+    assert_eq!(recursor.forward_zones.len(), 1);
+    assert_eq!(
+        recursor.forward_zones[0].zone,
+        Name::from_str("corp.example.com.").unwrap()
+    );
+    assert_eq!(recursor.forward_zones[0].name_servers.len(), 1);
+    assert_eq!(recursor.stub_zones.len(), 1);
+    assert_eq!(recursor.stub_zones[0].name_servers.len(), 2);
+}
+
+#[cfg(feature = "hickory-recursor")]
+#[test]
+fn test_parse_recursor_default_roots() {
+    use hickory_server::store::StoreConfig;
+
+    let config = Config::from_toml(
+        "[[zones]]
+zone = \".\"
+zone_type = \"Hint\"
+
+[zones.stores]
+type = \"recursor\"
+",
+    )
+    .unwrap();
+
+    let Some(StoreConfig::Recursor(recursor)) = &config.get_zones()[0].stores else {
+        panic!("expected recursor store");
+    };
+    // the built-in root hints are used
+    assert_eq!(recursor.roots, None);
+}
+
+#[test]
+fn test_parse_https_auth() {
+    let config = Config::from_toml("").unwrap();
+    assert!(config.get_https_auth().is_none());
+
+    let config = Config::from_toml(
+        "[https_auth]
+allow_anonymous = true
+
+[[https_auth.profiles]]
+name = \"restricted\"
+queries_per_second = 10
+denied_query_types = [\"ANY\", \"AXFR\"]
+
+[[https_auth.principals]]
+name = \"alice\"
+bearer_tokens = [\"s3cr3t\"]
+profile = \"restricted\"
+",
+    )
+    .unwrap();
+
+    let https_auth = config.get_https_auth().unwrap();
+    assert!(https_auth.allow_anonymous());
+    assert_eq!(https_auth.get_client_ca_path(), None);
+    assert_eq!(https_auth.get_profiles()[0].name, "restricted");
+    assert_eq!(https_auth.get_profiles()[0].queries_per_second, Some(10));
+    assert_eq!(https_auth.get_profiles()[0].burst, None);
+    assert_eq!(
+        https_auth.get_profiles()[0].denied_query_types,
+        vec!["ANY".to_string(), "AXFR".to_string()]
+    );
+    assert_eq!(https_auth.get_principals()[0].name, "alice");
+    assert_eq!(
+        https_auth.get_principals()[0].bearer_tokens,
+        vec!["s3cr3t".to_string()]
+    );
+    assert_eq!(
+        https_auth.get_principals()[0].profile.as_deref(),
+        Some("restricted")
+    );
+}
+
+#[test]
+fn test_parse_dhcp() {
+    let config = Config::from_toml("").unwrap();
+    assert!(config.get_dhcp().is_none());
+
+    let config = Config::from_toml(
+        "[dhcp]
+socket_path = \"/run/hickory-dns/dhcp.sock\"
+zone = \"lan.example.com.\"
+reverse_zones = [\"1.168.192.in-addr.arpa.\"]
+",
+    )
+    .unwrap();
+
+    let dhcp = config.get_dhcp().unwrap();
+    assert_eq!(
+        dhcp.get_socket_path(),
+        Some(Path::new("/run/hickory-dns/dhcp.sock"))
+    );
+    assert_eq!(dhcp.get_zone(), "lan.example.com.");
+    assert_eq!(
+        dhcp.get_reverse_zones(),
+        &["1.168.192.in-addr.arpa.".to_string()]
+    );
+    assert_eq!(dhcp.get_ttl(), 300);
+    assert!(dhcp.get_lease_files().is_empty());
+    assert!(dhcp.lease_files(Path::new("/var/named")).is_none());
+
+    let config = Config::from_toml(
+        "[dhcp]
+lease_files = [
+    { path = \"/var/lib/dhcp/dhcpd.leases\", format = \"dhcpd\" },
+    { path = \"kea-leases4.csv\", format = \"kea\" },
+]
+zone = \"lan.example.com.\"
+reload_interval = 30
+",
+    )
+    .unwrap();
+
+    let dhcp = config.get_dhcp().unwrap();
+    assert_eq!(dhcp.get_socket_path(), None);
+    assert_eq!(dhcp.get_reload_interval(), Duration::from_secs(30));
+    assert_eq!(dhcp.get_lease_files()[0].format, LeaseFileFormat::Dhcpd);
+    let lease_files = dhcp.lease_files(Path::new("/var/named")).unwrap();
+    assert_eq!(
+        lease_files.sources()[1],
+        LeaseFileSource {
+            path: PathBuf::from("/var/named/kea-leases4.csv"),
+            format: LeaseFileFormat::Kea,
+        }
+    );
+}
+
+#[test]
+#[cfg(feature = "etcd")]
+fn test_parse_etcd() {
+    use hickory_server::store::StoreConfig;
+
+    let config = Config::from_toml(
+        "[[zones]]
+zone = \"svc.cluster.local\"
+zone_type = \"Primary\"
+stores = { type = \"etcd\", endpoint = \"127.0.0.1:2379\", prefix = \"/services/\" }
+",
+    )
+    .unwrap();
+
+    let Some(StoreConfig::Etcd(etcd)) = &config.get_zones()[0].stores else {
+        panic!("expected an etcd store");
+    };
+    assert_eq!(etcd.endpoint, "127.0.0.1:2379".parse().unwrap());
+    assert_eq!(etcd.prefix, "/services/");
+    assert_eq!(etcd.get_ttl(), 30);
+    assert_eq!(etcd.get_retry_interval(), Duration::from_secs(5));
+}
+
+#[test]
+#[cfg(feature = "geoip")]
+fn test_parse_geo() {
+    use hickory_server::store::StoreConfig;
+
+    let config = Config::from_toml(
+        "[[zones]]
+zone = \"example.com\"
+zone_type = \"Primary\"
+
+[zones.stores]
+type = \"geo\"
+zone_file_path = \"example.com.zone\"
+databases = [\"GeoLite2-Country.mmdb\"]
+
+[[zones.stores.names]]
+name = \"www\"
+pools = [
+    { name = \"europe\", continents = [\"EU\"], records = [\"A 192.0.2.1\"] },
+    { name = \"default\", weight = 2, records = [\"A 192.0.2.2\"] },
+]
+",
+    )
+    .unwrap();
+
+    let Some(StoreConfig::Geo(geo)) = &config.get_zones()[0].stores else {
+        panic!("expected a geo store");
+    };
+    assert_eq!(geo.zone_file_path, "example.com.zone");
+    assert_eq!(geo.databases, vec![PathBuf::from("GeoLite2-Country.mmdb")]);
+    assert_eq!(geo.names.len(), 1);
+    assert_eq!(geo.names[0].pools[0].continents, vec!["EU".to_string()]);
+    assert_eq!(geo.names[0].pools[1].weight, Some(2));
+}
+
+#[test]
+fn test_parse_rotation() {
+    let config = Config::from_toml(
+        "[[zones]]
+zone = \"example.com\"
+zone_type = \"Primary\"
+file = \"example.com.zone\"
+rotation = \"round_robin\"
+
+[[zones.rotations]]
+name = \"www\"
+record_type = \"A\"
+policy = \"weighted\"
+weights = { \"192.0.2.1\" = 3 }
+",
+    )
+    .unwrap();
+
+    let zone = &config.get_zones()[0];
+    assert_eq!(zone.get_rotation(), RotationPolicy::RoundRobin);
+    assert_eq!(zone.get_rotations()[0].policy, RotationPolicy::Weighted);
+    assert_eq!(zone.get_rotations()[0].weights["192.0.2.1"], 3);
+
+    let config = Config::from_toml(
+        "[[zones]]
+zone = \"example.com\"
+zone_type = \"Primary\"
+file = \"example.com.zone\"
+",
+    )
+    .unwrap();
+    assert_eq!(config.get_zones()[0].get_rotation(), RotationPolicy::Fixed);
+}
+
+#[test]
+fn test_parse_health() {
+    use hickory_server::store::{health::Probe, StoreConfig};
+
+    let config = Config::from_toml(
+        "[[zones]]
+zone = \"example.com\"
+zone_type = \"Primary\"
+
+[zones.stores]
+type = \"health\"
+zone_file_path = \"example.com.zone\"
+
+[[zones.stores.pools]]
+name = \"www\"
+members = [\"192.0.2.1\", \"2001:db8::1\"]
+probe = { type = \"http\", port = 8080, path = \"/health\" }
+unhealthy_threshold = 5
+",
+    )
+    .unwrap();
+
+    let Some(StoreConfig::Health(health)) = &config.get_zones()[0].stores else {
+        panic!("expected a health store");
+    };
+    let pool = &health.pools[0];
+    assert_eq!(pool.members.len(), 2);
+    assert_eq!(
+        pool.probe,
+        Probe::Http {
+            port: Some(8080),
+            path: Some("/health".to_string()),
+            host: None,
+        }
+    );
+    assert_eq!(pool.get_ttl(), 30);
+    assert_eq!(pool.get_interval(), Duration::from_secs(10));
+    assert_eq!(pool.get_healthy_threshold(), 2);
+    assert_eq!(pool.get_unhealthy_threshold(), 5);
+}
+
+#[test]
+#[cfg(feature = "resolver")]
+fn test_parse_blocklist() {
+    use std::net::IpAddr;
+
+    use hickory_server::resolver::blocklist::{BlockAction, ListFormat};
+
+    let config = Config::from_toml("").unwrap();
+    assert!(config.get_blocklist().is_none());
+
+    let config = Config::from_toml(
+        "[blocklist]
+lists = [
+    { path = \"blocklists/hosts\", format = \"hosts\" },
+    { path = \"blocklists/filters.txt\", format = \"adblock\" },
+]
+addresses = [\"192.0.2.1\", \"2001:db8::1\"]
+reload_interval = 0
+",
+    )
+    .unwrap();
+
+    let blocklist = config.get_blocklist().unwrap();
+    assert_eq!(blocklist.get_lists().len(), 2);
+    assert_eq!(
+        blocklist.get_lists()[1].path,
+        Path::new("blocklists/filters.txt")
+    );
+    assert_eq!(blocklist.get_lists()[1].format, ListFormat::AdBlock);
+    assert_eq!(
+        blocklist.get_action(),
+        BlockAction::Addresses(vec![
+            IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)),
+            IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)),
+        ])
+    );
+    assert_eq!(blocklist.get_ttl(), 60);
+    assert_eq!(blocklist.get_reload_interval(), None);
+
+    let config = Config::from_toml(
+        "[blocklist]
+lists = [{ path = \"blocklists/rpz.zone\", format = \"rpz\" }]
+action = \"null\"
+",
+    )
+    .unwrap();
+    let blocklist = config.get_blocklist().unwrap();
+    assert_eq!(blocklist.get_action(), BlockAction::Null);
+    assert_eq!(
+        blocklist.get_reload_interval(),
+        Some(Duration::from_secs(60))
+    );
+}
+
+#[test]
+fn test_parse_xdp() {
+    let config = Config::from_toml("").unwrap();
+    assert!(config.get_xdp().is_none());
+
+    let config = Config::from_toml(
+        "[xdp]
+interface = \"eth0\"
+xsk_map = \"/sys/fs/bpf/hickory_xsks\"
+",
+    )
+    .unwrap();
+
+    let xdp = config.get_xdp().unwrap();
+    assert_eq!(xdp.get_interface(), "eth0");
+    assert_eq!(xdp.get_xsk_map(), Path::new("/sys/fs/bpf/hickory_xsks"));
+    assert_eq!(xdp.get_queues(), &[0]);
+    assert_eq!(xdp.get_frames(), None);
+    assert!(!xdp.get_zero_copy());
+
+    let config = Config::from_toml(
+        "[xdp]
+interface = \"eth0\"
+xsk_map = \"/sys/fs/bpf/hickory_xsks\"
+queues = [0, 1]
+frames = 2048
+zero_copy = true
+",
+    )
+    .unwrap();
+
+    let xdp = config.get_xdp().unwrap();
+    assert_eq!(xdp.get_queues(), &[0, 1]);
+    assert_eq!(xdp.get_frames(), Some(2048));
+    assert!(xdp.get_zero_copy());
+
+    #[cfg(all(feature = "xdp", target_os = "linux"))]
+    {
+        let sockets = xdp.to_socket_configs(5353);
+        assert_eq!(sockets.len(), 2);
+        assert_eq!(sockets[1].queue_id, 1);
+        assert_eq!(sockets[1].frames, 2048);
+        assert!(sockets[1].zero_copy);
+        assert_eq!(sockets[1].port, 5353);
+    }
+}
+
+#[test]
+fn test_parse_server_id() {
+    let config = Config::from_toml("").unwrap();
+    assert_eq!(config.get_server_id(), None);
+
+    let config = Config::from_toml("server_id = \"anycast-fra-1\"").unwrap();
+    assert_eq!(config.get_server_id(), Some("anycast-fra-1"));
+}
+
+#[test]
+fn test_parse_acme() {
+    let config = Config::from_toml("").unwrap();
+    assert!(config.get_acme().is_none());
+
+    let config = Config::from_toml(
+        "[acme]
+contact = [\"mailto:hostmaster@example.com\"]
+challenge = \"dns_01\"
+",
+    )
+    .unwrap();
+
+    let acme = config.get_acme().unwrap();
+    assert_eq!(
+        acme.get_directory_url(),
+        "https://acme-v02.api.letsencrypt.org/directory"
+    );
+    assert_eq!(
+        acme.get_contact(),
+        &["mailto:hostmaster@example.com".to_string()]
+    );
+    assert_eq!(acme.get_account_path(), Path::new("acme_account.json"));
+    assert_eq!(acme.get_challenge(), acme::AcmeChallenge::Dns01);
+    assert_eq!(
+        acme.get_renew_before(),
+        Duration::from_secs(30 * 24 * 60 * 60)
+    );
+}
+
+#[cfg(feature = "hickory-resolver")]
+#[test]
+fn test_parse_aname() {
+    let config = Config::from_toml(
+        "[[zones]]
+zone = \"example.com\"
+zone_type = \"Primary\"
+file = \"example.com.zone\"
+
+[zones.aname]
+name_servers = [{ socket_addr = \"192.0.2.53:53\", protocol = \"udp\", trust_negative_responses = true }]
+min_refresh = 10
+",
+    )
+    .unwrap();
+
+    let aname = config.get_zones()[0].get_aname().unwrap();
+    assert_eq!(
+        aname.get_name_servers().unwrap()[0].socket_addr,
+        "192.0.2.53:53".parse().unwrap()
+    );
+    assert_eq!(aname.get_min_refresh(), Duration::from_secs(10));
+    assert_eq!(aname.get_max_refresh(), Duration::from_secs(3600));
+
+    let config = Config::from_toml(
+        "[[zones]]
+zone = \"example.com\"
+zone_type = \"Primary\"
+file = \"example.com.zone\"
+",
+    )
+    .unwrap();
+    assert!(config.get_zones()[0].get_aname().is_none());
+}
+
+#[cfg(feature = "hickory-resolver")]
+#[test]
+fn test_parse_additionals() {
+    let config = Config::from_toml(
+        "[[zones]]
+zone = \"example.com\"
+zone_type = \"Primary\"
+file = \"example.com.zone\"
+
+[zones.additionals]
+enabled = true
+timeout_ms = 200
+",
+    )
+    .unwrap();
+
+    let additionals = config.get_zones()[0].get_additionals().unwrap();
+    assert!(additionals.get_name_servers().is_none());
+    assert_eq!(additionals.get_timeout(), Duration::from_millis(200));
+
+    // the resolution is off unless enabled
+    let config = Config::from_toml(
+        "[[zones]]
+zone = \"example.com\"
+zone_type = \"Primary\"
+file = \"example.com.zone\"
+
+[zones.additionals]
+timeout_ms = 200
+",
+    )
+    .unwrap();
+    assert!(config.get_zones()[0].get_additionals().is_none());
+}
+
+#[test]
+fn test_validate() {
+    use hickory_server::config::validate::Problem;
+    use hickory_server::store::file::Severity;
+
+    let server_path = env::var("TDNS_WORKSPACE_ROOT").unwrap_or_else(|_| "../..".to_owned());
+    let zone_dir = PathBuf::from(server_path).join("tests/test-data/test_configs");
+    let errors = |problems: Vec<Problem>| {
+        problems
+            .into_iter()
+            .filter(|problem| problem.severity == Severity::Error)
+            .map(|problem| format!("{}: {}", problem.section, problem.message))
+            .collect::<Vec<_>>()
+    };
+
+    let config = Config::read_config(&zone_dir.join("example.toml")).unwrap();
+    assert_eq!(errors(config.validate(&zone_dir)), Vec::<String>::new());
+
+    let mut config = Config::from_toml(
+        "listen_addrs_ipv4 = [\"0.0.0.0\", \"300.1.1.1\"]
+
+[[zones]]
+zone = \"example.com\"
+zone_type = \"Primary\"
+file = \"missing.zone\"
+
+[[zones]]
+zone = \"example.com.\"
+zone_type = \"Primary\"
+file = \"example.com.zone\"
+
+[[zones]]
+zone = \"example.net\"
+zone_type = \"Primary\"
+",
+    )
+    .unwrap();
+    // the ports of the command line are validated as well
+    config.set_listen_port(5353);
+
+    let found = errors(config.validate(&zone_dir));
+    assert_eq!(found.len(), 4, "{found:#?}");
+    assert_eq!(
+        found[0],
+        "listen_addrs_ipv4: bad address 300.1.1.1: invalid IPv4 address syntax"
+    );
+    assert!(found[1].starts_with("zones[example.com.]: syntax: failed to read"));
+    assert_eq!(
+        found[2],
+        "zones[example.com.]: the zone is configured more than once"
+    );
+    assert_eq!(
+        found[3],
+        "zones[example.net.]: file is a necessary parameter of zone_config"
+    );
+    assert_eq!(config.get_listen_port(), 5353);
+
+    let config = Config::from_toml("directory = \"/nonexistent\"").unwrap();
+    assert_eq!(
+        errors(config.validate(config.get_directory())),
+        ["directory: /nonexistent is not a directory"]
+    );
+}
+
+#[cfg(feature = "dns-over-quic")]
+#[test]
+fn test_validate_listeners() {
+    let config = Config::from_toml(
+        "listen_port = 853
+quic_listen_port = 853
+
+[tls_cert]
+path = \"cert.pem\"
+private_key = \"cert.key\"
+",
+    )
+    .unwrap();
+
+    let problems = config.validate(Path::new("."));
+    assert!(problems
+        .iter()
+        .any(|problem| problem.message == "DNS and QUIC both listen on UDP port 853"));
+}
+
+#[cfg(feature = "yaml")]
+#[test]
+fn test_read_yaml_config() {
+    let server_path = env::var("TDNS_WORKSPACE_ROOT").unwrap_or_else(|_| "../..".to_owned());
+    let test_configs = PathBuf::from(server_path).join("tests/test-data/test_configs");
+
+    let toml = Config::read_config(&test_configs.join("example.toml")).unwrap();
+    let yaml = Config::read_config(&test_configs.join("example.yaml")).unwrap();
+    assert_eq!(yaml.get_listen_port(), toml.get_listen_port());
+    assert_eq!(yaml.get_zones(), toml.get_zones());
+
+    let config = Config::from_yaml(
+        "listen_addrs_ipv4: [0.0.0.0, 127.0.0.1]
+tcp_request_timeout: 25
+deny_networks:
+  - 10.0.0.0/8
+zones:
+  - zone: example.com
+    zone_type: Primary
+    stores:
+      type: file
+      zone_file_path: example.com.zone
+",
+    )
+    .unwrap();
+    assert_eq!(
+        config.get_listen_addrs_ipv4(),
+        Ok(vec![Ipv4Addr::UNSPECIFIED, Ipv4Addr::LOCALHOST])
+    );
+    assert_eq!(config.get_tcp_request_timeout(), Duration::from_secs(25));
+    assert_eq!(config.get_deny_networks().len(), 1);
+    assert_eq!(
+        config.get_zones()[0].get_zone().unwrap().to_string(),
+        "example.com."
+    );
+
+    let error = Config::from_yaml("listen_port: 53\nlisten_port: 54").unwrap_err();
+    assert!(error
+        .to_string()
+        .starts_with("yaml decode error: duplicate field `listen_port`"));
+}
+
+#[test]
+fn test_read_config_with_env() {
+    let server_path = env::var("TDNS_WORKSPACE_ROOT").unwrap_or_else(|_| "../..".to_owned());
+    let path = PathBuf::from(server_path).join("tests/test-data/test_configs/example.toml");
+    let vars = |vars: &[(&str, &str)]| {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<Vec<_>>()
+    };
+
+    let config = Config::read_config_with_env(
+        &path,
+        vars(&[
+            ("HICKORY_LISTEN_PORT", "5353"),
+            ("HICKORY_LISTEN_ADDRS_IPV4", "[\"127.0.0.1\"]"),
+            ("HICKORY_SERVER_ID", "\"1234\""),
+            ("HICKORY_ZONES__0__FILE", "localhost.zone"),
+            ("HICKORY_ZONES__6__ZONE", "example.net"),
+            ("HICKORY_ZONES__6__ZONE_TYPE", "Secondary"),
+            ("HICKORY_ZONES__6__FILE", "example.net.zone"),
+            ("PATH", "/usr/bin"),
+        ]),
+    )
+    .unwrap();
+
+    assert_eq!(config.get_listen_port(), 5353);
+    assert_eq!(
+        config.get_listen_addrs_ipv4(),
+        Ok(vec![Ipv4Addr::LOCALHOST])
+    );
+    assert_eq!(config.get_server_id(), Some("1234"));
+    assert_eq!(config.get_zones().len(), 7);
+    assert_eq!(
+        config.get_zones()[0].get_file(),
+        PathBuf::from("localhost.zone")
+    );
+    assert_eq!(config.get_zones()[6].get_zone_type(), ZoneType::Secondary);
+    assert_eq!(
+        config.get_zones()[6].get_file(),
+        PathBuf::from("example.net.zone")
+    );
+
+    // without any variables, the file is read as it is
+    let config = Config::read_config_with_env(&path, vars(&[("PATH", "/usr/bin")])).unwrap();
+    assert_eq!(config.get_listen_port(), 53);
+
+    // the prefix alone does not name a key
+    let config = Config::read_config_with_env(&path, vars(&[("HICKORY_", "5353")])).unwrap();
+    assert_eq!(config.get_listen_port(), 53);
+
+    let error = Config::read_config_with_env(&path, vars(&[("HICKORY_ZONES__8__FILE", "a.zone")]))
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "bad environment variable HICKORY_ZONES__8__FILE: index 8 is past the end of an array of 6 elements"
+    );
+
+    let error =
+        Config::read_config_with_env(&path, vars(&[("HICKORY_SERVER_ID", "1234")])).unwrap_err();
+    assert!(error
+        .to_string()
+        .starts_with("config decode error: invalid type: integer `1234`"));
+}
+
+#[cfg(all(feature = "sqlite", feature = "resolver"))]
+#[test]
+fn test_convert_named_conf() {
+    let server_path = env::var("TDNS_WORKSPACE_ROOT").unwrap_or_else(|_| "../..".to_owned());
+    let path = PathBuf::from(server_path).join("tests/test-data/test_configs/named/named.conf");
+
+    let conversion = named_conf::convert_file(&path).unwrap();
+    let config = Config::from_toml(&conversion.toml).unwrap();
+
+    assert_eq!(config.get_directory(), Path::new("/var/named"));
+    assert_eq!(config.get_listen_port(), 5353);
+    assert_eq!(
+        config.get_listen_addrs_ipv4(),
+        Ok(vec![Ipv4Addr::LOCALHOST])
+    );
+    assert_eq!(
+        config.get_listen_addrs_ipv6(),
+        Ok(vec![Ipv6Addr::LOCALHOST])
+    );
+    assert_eq!(config.get_server_id(), Some("ns1"));
+    assert_eq!(
+        config.get_allow_networks(),
+        &[
+            "10.0.0.0/8".parse().unwrap(),
+            "192.168.0.0/16".parse().unwrap(),
+            "127.0.0.1/32".parse().unwrap(),
+            "::1/128".parse().unwrap(),
+        ]
+    );
+    assert_eq!(
+        config.get_deny_networks(),
+        &["10.66.0.0/16".parse().unwrap()]
+    );
+
+    let zones = config
+        .get_zones()
+        .iter()
+        .map(|zone| {
+            (
+                zone.zone.as_str(),
+                zone.get_zone_type(),
+                zone.is_axfr_allowed(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        zones,
+        [
+            ("example.com", ZoneType::Primary, true),
+            ("example.net", ZoneType::Primary, false),
+            ("example.edu", ZoneType::Secondary, false),
+            ("internal", ZoneType::Forward, false),
+            (".", ZoneType::Forward, false),
+        ]
+    );
+    assert!(matches!(
+        &config.get_zones()[1].stores,
+        Some(StoreConfig::Sqlite(sqlite)) if sqlite.allow_update
+    ));
+
+    let warnings = conversion
+        .warnings
+        .iter()
+        .map(|warning| warning.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(warnings.len(), 8, "{warnings:#?}");
+    assert!(warnings[2].ends_with("named.conf:24: unsupported option querylog"));
+    assert!(warnings[6]
+        .ends_with("named.secondary.conf:13: zone stub.example of type stub is not converted"));
+    assert!(warnings[7].ends_with("named.conf:41: zone example.org of class CH is not converted"));
+}
+
+#[test]
+fn test_convert_named_conf_errors() {
+    let error = named_conf::convert("options { directory \"/var/named\" };", None).unwrap_err();
+    assert_eq!(error, "named.conf:1: expected ; before }");
+
+    let error = named_conf::convert("zone \"example.com\" { type primary;", None).unwrap_err();
+    assert_eq!(error, "named.conf: missing }");
+
+    let conversion = named_conf::convert(
+        "/* no access */ options { allow-query { none; }; recursion yes; };",
+        None,
+    )
+    .unwrap();
+    assert!(conversion.warnings.is_empty());
+    assert!(conversion
+        .toml
+        .contains("deny_networks = [\"0.0.0.0/0\", \"::/0\"]"));
+    assert!(conversion.toml.contains("type = \"recursor\""));
+}
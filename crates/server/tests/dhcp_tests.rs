@@ -0,0 +1,252 @@
+use std::net::{IpAddr, Ipv4Addr};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use hickory_proto::rr::{rdata::PTR, Name, RData, RecordType};
+use hickory_server::{
+    authority::{AuthorityObject, LookupOptions},
+    store::dhcp::{
+        DhcpLeases, FileLease, LeaseEvent, LeaseFileFormat, LeaseFileSource, LeaseFiles,
+    },
+};
+
+fn leases() -> DhcpLeases {
+    DhcpLeases::new(
+        Name::from_str("lan.example.com.").unwrap(),
+        vec![Name::from_str("1.168.192.in-addr.arpa.").unwrap()],
+        300,
+    )
+}
+
+async fn lookup(
+    authority: &dyn AuthorityObject,
+    name: &Name,
+    record_type: RecordType,
+) -> Vec<RData> {
+    authority
+        .lookup(&name.into(), record_type, LookupOptions::default())
+        .await
+        .map(|lookup| lookup.iter().filter_map(|r| r.data().cloned()).collect())
+        .unwrap_or_default()
+}
+
+#[test]
+fn test_parse_lease_event() {
+    assert_eq!(
+        LeaseEvent::from_str("add 192.168.1.23 laptop 3600").unwrap(),
+        LeaseEvent::Add {
+            addr: IpAddr::V4(Ipv4Addr::new(192, 168, 1, 23)),
+            hostname: Name::from_str("laptop").unwrap(),
+            lease_time: Some(Duration::from_secs(3600)),
+        }
+    );
+    assert!(matches!(
+        LeaseEvent::from_str("old 192.168.1.23 laptop infinite").unwrap(),
+        LeaseEvent::Add {
+            lease_time: None,
+            ..
+        }
+    ));
+    assert_eq!(
+        LeaseEvent::from_str("del 192.168.1.23 laptop").unwrap(),
+        LeaseEvent::Delete {
+            addr: IpAddr::V4(Ipv4Addr::new(192, 168, 1, 23)),
+        }
+    );
+
+    assert!(LeaseEvent::from_str("add 192.168.1.23 laptop").is_err());
+    assert!(LeaseEvent::from_str("add laptop 192.168.1.23 60").is_err());
+    assert!(LeaseEvent::from_str("renew 192.168.1.23").is_err());
+}
+
+#[tokio::test]
+async fn test_lease_records() {
+    let leases = leases();
+    let authorities = leases.authorities();
+    let (forward, reverse) = (&*authorities[0].1, &*authorities[1].1);
+
+    let name = Name::from_str("laptop.lan.example.com.").unwrap();
+    let reverse_name = Name::from(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 23)));
+
+    leases
+        .apply(LeaseEvent::from_str("add 192.168.1.23 Laptop 3600").unwrap())
+        .await
+        .unwrap();
+    leases
+        .apply(LeaseEvent::from_str("add 192.168.1.24 laptop 3600").unwrap())
+        .await
+        .unwrap();
+
+    let mut addrs = lookup(forward, &name, RecordType::A).await;
+    addrs.sort();
+    assert_eq!(
+        addrs,
+        vec![
+            RData::A(Ipv4Addr::new(192, 168, 1, 23).into()),
+            RData::A(Ipv4Addr::new(192, 168, 1, 24).into())
+        ]
+    );
+    assert_eq!(
+        lookup(reverse, &reverse_name, RecordType::PTR).await,
+        vec![RData::PTR(PTR(name.clone()))]
+    );
+
+    // the address moves to another host
+    leases
+        .apply(LeaseEvent::from_str("add 192.168.1.23 desktop 3600").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(
+        lookup(forward, &name, RecordType::A).await,
+        vec![RData::A(Ipv4Addr::new(192, 168, 1, 24).into())]
+    );
+    assert_eq!(
+        lookup(reverse, &reverse_name, RecordType::PTR).await,
+        vec![RData::PTR(PTR(
+            Name::from_str("desktop.lan.example.com.").unwrap()
+        ))]
+    );
+
+    leases
+        .apply(LeaseEvent::from_str("del 192.168.1.24").unwrap())
+        .await
+        .unwrap();
+    assert!(lookup(forward, &name, RecordType::A).await.is_empty());
+}
+
+#[tokio::test]
+async fn test_lease_expiry() {
+    let leases = leases();
+    let authorities = leases.authorities();
+    let forward = &*authorities[0].1;
+    let zone = Name::from_str("lan.example.com.").unwrap();
+    let soa = lookup(forward, &zone, RecordType::SOA).await;
+
+    leases
+        .apply(LeaseEvent::from_str("add 192.168.1.23 laptop 0").unwrap())
+        .await
+        .unwrap();
+    leases
+        .apply(LeaseEvent::from_str("add 192.168.1.24 printer infinite").unwrap())
+        .await
+        .unwrap();
+    leases.remove_expired().await;
+
+    let laptop = Name::from_str("laptop.lan.example.com.").unwrap();
+    let printer = Name::from_str("printer.lan.example.com.").unwrap();
+    assert!(lookup(forward, &laptop, RecordType::A).await.is_empty());
+    assert_eq!(
+        lookup(forward, &printer, RecordType::A).await,
+        vec![RData::A(Ipv4Addr::new(192, 168, 1, 24).into())]
+    );
+    assert_ne!(lookup(forward, &zone, RecordType::SOA).await, soa);
+}
+
+#[tokio::test]
+async fn test_lease_outside_zone() {
+    let leases = leases();
+
+    assert!(leases
+        .apply(LeaseEvent::from_str("add 192.168.1.23 laptop.example.org. 60").unwrap())
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_file_leases() {
+    let leases = leases();
+    let authorities = leases.authorities();
+    let (forward, reverse) = (&*authorities[0].1, &*authorities[1].1);
+
+    let laptop = Name::from_str("laptop.lan.example.com.").unwrap();
+    let printer = Name::from_str("printer.lan.example.com.").unwrap();
+    let file_lease = |addr: [u8; 4], hostname: &str, lease_time: Option<u64>| FileLease {
+        addr: IpAddr::V4(Ipv4Addr::from(addr)),
+        hostname: Name::from_str(hostname).unwrap(),
+        expires: lease_time.map(|secs| SystemTime::now() + Duration::from_secs(secs)),
+    };
+
+    leases
+        .apply(LeaseEvent::from_str("add 192.168.1.30 phone 3600").unwrap())
+        .await
+        .unwrap();
+    leases
+        .replace_file_leases(vec![
+            file_lease([192, 168, 1, 23], "laptop", Some(3600)),
+            file_lease([192, 168, 1, 24], "printer.lan.example.com.", None),
+            file_lease([192, 168, 1, 25], "expired", Some(0)),
+            file_lease([192, 168, 1, 26], "laptop.example.org.", None),
+        ])
+        .await;
+
+    assert_eq!(
+        lookup(forward, &laptop, RecordType::A).await,
+        vec![RData::A(Ipv4Addr::new(192, 168, 1, 23).into())]
+    );
+    assert_eq!(
+        lookup(forward, &printer, RecordType::A).await,
+        vec![RData::A(Ipv4Addr::new(192, 168, 1, 24).into())]
+    );
+    assert_eq!(
+        lookup(
+            reverse,
+            &Name::from(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 24))),
+            RecordType::PTR
+        )
+        .await,
+        vec![RData::PTR(PTR(printer.clone()))]
+    );
+    assert!(lookup(
+        forward,
+        &Name::from_str("expired.lan.example.com.").unwrap(),
+        RecordType::A
+    )
+    .await
+    .is_empty());
+
+    // the leases missing from the files are removed, the leases of events are kept
+    leases
+        .replace_file_leases(vec![file_lease([192, 168, 1, 23], "laptop", Some(3600))])
+        .await;
+    assert!(lookup(forward, &printer, RecordType::A).await.is_empty());
+    assert_eq!(
+        lookup(
+            forward,
+            &Name::from_str("phone.lan.example.com.").unwrap(),
+            RecordType::A
+        )
+        .await,
+        vec![RData::A(Ipv4Addr::new(192, 168, 1, 30).into())]
+    );
+}
+
+#[tokio::test]
+async fn test_lease_files() {
+    let dir = std::env::temp_dir().join(format!("hickory-dhcp-leases-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("kea-leases4.csv");
+    let _ = std::fs::remove_file(&path);
+
+    let files = LeaseFiles::new(vec![LeaseFileSource {
+        path: path.clone(),
+        format: LeaseFileFormat::Kea,
+    }]);
+
+    // a missing file has no leases, and is read again once it exists
+    assert_eq!(files.read_if_changed().unwrap(), Some(vec![]));
+    assert_eq!(files.read_if_changed().unwrap(), None);
+
+    std::fs::write(
+        &path,
+        "address,hwaddr,client_id,valid_lifetime,expire,subnet_id,fqdn_fwd,fqdn_rev,hostname,state
+192.168.1.23,00:11:22:33:44:55,,4294967295,0,1,0,0,laptop,0
+",
+    )
+    .unwrap();
+    let leases = files.read_if_changed().unwrap().unwrap();
+    assert_eq!(leases.len(), 1);
+    assert_eq!(leases[0].hostname, Name::from_str("laptop").unwrap());
+    assert_eq!(files.read_if_changed().unwrap(), None);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,171 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+use hickory_proto::rr::{rdata::SRV, LowerName, Name, RData, RecordType};
+use hickory_server::{
+    authority::{Authority, LookupOptions},
+    store::discovery::{DiscoveryAuthority, Service, ServiceEvent, ServicePort},
+};
+
+fn authority() -> DiscoveryAuthority {
+    DiscoveryAuthority::new(Name::from_str("svc.cluster.local.").unwrap(), 30)
+}
+
+async fn lookup(authority: &DiscoveryAuthority, name: &str, record_type: RecordType) -> Vec<RData> {
+    let name = LowerName::from(Name::from_str(name).unwrap());
+    let mut rdatas = authority
+        .lookup(&name, record_type, LookupOptions::default())
+        .await
+        .map(|lookup| {
+            lookup
+                .iter()
+                .filter_map(|r| r.data().cloned())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    rdatas.sort();
+    rdatas
+}
+
+fn web(addrs: Vec<IpAddr>) -> Service {
+    Service {
+        name: Name::from_str("web.default").unwrap(),
+        addrs,
+        ports: vec![ServicePort {
+            name: "http".to_string(),
+            protocol: "tcp".to_string(),
+            port: 8080,
+        }],
+    }
+}
+
+#[tokio::test]
+async fn test_service_records() {
+    let authority = authority();
+    let web_name = Name::from_str("web.default.svc.cluster.local.").unwrap();
+
+    authority
+        .apply(ServiceEvent::Reset(vec![web(vec![
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+            IpAddr::V6(Ipv6Addr::new(0xfd00, 0, 0, 0, 0, 0, 0, 1)),
+        ])]))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        lookup(&authority, "web.default.svc.cluster.local.", RecordType::A).await,
+        vec![
+            RData::A(Ipv4Addr::new(10, 0, 0, 1).into()),
+            RData::A(Ipv4Addr::new(10, 0, 0, 2).into()),
+        ]
+    );
+    assert_eq!(
+        lookup(
+            &authority,
+            "web.default.svc.cluster.local.",
+            RecordType::AAAA
+        )
+        .await
+        .len(),
+        1
+    );
+    assert_eq!(
+        lookup(
+            &authority,
+            "_http._tcp.web.default.svc.cluster.local.",
+            RecordType::SRV
+        )
+        .await,
+        vec![RData::SRV(SRV::new(0, 100, 8080, web_name.clone()))]
+    );
+
+    // the endpoints of the service change
+    authority
+        .apply(ServiceEvent::Update(web(vec![IpAddr::V4(Ipv4Addr::new(
+            10, 0, 0, 3,
+        ))])))
+        .await
+        .unwrap();
+    assert_eq!(
+        lookup(&authority, "web.default.svc.cluster.local.", RecordType::A).await,
+        vec![RData::A(Ipv4Addr::new(10, 0, 0, 3).into())]
+    );
+    assert!(lookup(
+        &authority,
+        "web.default.svc.cluster.local.",
+        RecordType::AAAA
+    )
+    .await
+    .is_empty());
+    assert_eq!(authority.services().await, vec![web_name]);
+
+    authority
+        .apply(ServiceEvent::Remove(Name::from_str("web.default").unwrap()))
+        .await
+        .unwrap();
+    assert!(
+        lookup(&authority, "web.default.svc.cluster.local.", RecordType::A)
+            .await
+            .is_empty()
+    );
+    assert!(lookup(
+        &authority,
+        "_http._tcp.web.default.svc.cluster.local.",
+        RecordType::SRV
+    )
+    .await
+    .is_empty());
+    assert!(authority.services().await.is_empty());
+}
+
+#[tokio::test]
+async fn test_service_reset() {
+    let authority = authority();
+
+    authority
+        .apply(ServiceEvent::Update(web(vec![IpAddr::V4(Ipv4Addr::new(
+            10, 0, 0, 1,
+        ))])))
+        .await
+        .unwrap();
+    let soa = lookup(&authority, "svc.cluster.local.", RecordType::SOA).await;
+
+    // the services missing from a reset are removed, services outside of the zone are skipped
+    authority
+        .apply(ServiceEvent::Reset(vec![
+            Service {
+                name: Name::from_str("db.default").unwrap(),
+                addrs: vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2))],
+                ports: vec![],
+            },
+            Service {
+                name: Name::from_str("db.example.com.").unwrap(),
+                addrs: vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3))],
+                ports: vec![],
+            },
+        ]))
+        .await
+        .unwrap();
+
+    assert!(
+        lookup(&authority, "web.default.svc.cluster.local.", RecordType::A)
+            .await
+            .is_empty()
+    );
+    assert_eq!(
+        lookup(&authority, "db.default.svc.cluster.local.", RecordType::A).await,
+        vec![RData::A(Ipv4Addr::new(10, 0, 0, 2).into())]
+    );
+    assert_ne!(
+        lookup(&authority, "svc.cluster.local.", RecordType::SOA).await,
+        soa
+    );
+
+    assert!(authority
+        .apply(ServiceEvent::Remove(
+            Name::from_str("db.example.com.").unwrap()
+        ))
+        .await
+        .is_err());
+}
@@ -0,0 +1,305 @@
+#![cfg(feature = "geoip")]
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use hickory_proto::{
+    op::{Header, Query},
+    rr::{
+        rdata::{opt::ClientSubnet, CNAME},
+        LowerName, Name, RData, Record, RecordType,
+    },
+};
+use hickory_server::{
+    authority::{Authority, LookupError, LookupOptions, ZoneType},
+    server::{Protocol, RequestInfo},
+    store::{
+        geo::{GeoAuthority, GeoNameConfig, GeoPool, GeoPoolConfig, Locate, Location},
+        in_memory::InMemoryAuthority,
+    },
+};
+
+const TEST_HEADER: &Header = &Header::new();
+
+/// The locations of the addresses of the tests
+struct TestLocator(HashMap<IpAddr, Location>);
+
+impl Locate for TestLocator {
+    fn locate(&self, addr: IpAddr) -> Location {
+        self.0.get(&addr).cloned().unwrap_or_default()
+    }
+}
+
+fn location(continent: &str, country: &str, asn: u32) -> Location {
+    Location {
+        continent: Some(continent.to_string()),
+        country: Some(country.to_string()),
+        asn: Some(asn),
+    }
+}
+
+fn pool(name: &str, records: Vec<RData>) -> GeoPool {
+    GeoPool {
+        name: name.to_string(),
+        weight: 1,
+        records,
+        ..GeoPool::default()
+    }
+}
+
+fn a(addr: [u8; 4]) -> RData {
+    RData::A(Ipv4Addr::from(addr).into())
+}
+
+fn authority() -> GeoAuthority {
+    let origin = Name::from_str("example.com.").unwrap();
+    let mut zone = InMemoryAuthority::empty(origin, ZoneType::Primary, false);
+    zone.upsert_mut(
+        Record::from_rdata(
+            Name::from_str("mail.example.com.").unwrap(),
+            300,
+            a([192, 0, 2, 25]),
+        ),
+        0,
+    );
+
+    let locator = TestLocator(HashMap::from([
+        ("198.51.100.1".parse().unwrap(), location("EU", "DE", 64500)),
+        ("198.51.100.2".parse().unwrap(), location("EU", "FR", 64501)),
+        ("198.51.100.3".parse().unwrap(), location("EU", "DE", 64502)),
+        ("198.51.100.4".parse().unwrap(), location("NA", "US", 64503)),
+    ]));
+
+    let mut authority = GeoAuthority::new(zone, Arc::new(locator));
+    authority.insert(
+        Name::from_str("www.example.com.").unwrap(),
+        60,
+        vec![
+            GeoPool {
+                continents: vec!["EU".to_string()],
+                ..pool("europe", vec![a([192, 0, 2, 1])])
+            },
+            GeoPool {
+                countries: vec!["de".to_string()],
+                ..pool("germany", vec![a([192, 0, 2, 2])])
+            },
+            GeoPool {
+                asns: vec![64502],
+                ..pool("isp", vec![a([192, 0, 2, 3])])
+            },
+        ],
+    );
+    authority.insert(
+        Name::from_str("cdn.example.com.").unwrap(),
+        60,
+        vec![pool(
+            "cdn",
+            vec![RData::CNAME(CNAME(
+                Name::from_str("edge.example.net.").unwrap(),
+            ))],
+        )],
+    );
+    authority
+}
+
+async fn search(
+    authority: &GeoAuthority,
+    name: &str,
+    record_type: RecordType,
+    src: &str,
+    client_subnet: Option<ClientSubnet>,
+) -> Result<Vec<RData>, LookupError> {
+    let query = Query::query(Name::from_str(name).unwrap(), record_type).into();
+    let mut request_info = RequestInfo::new(
+        format!("{src}:53").parse().unwrap(),
+        Protocol::Udp,
+        TEST_HEADER,
+        &query,
+    );
+    request_info.client_subnet = client_subnet;
+
+    let lookup = authority
+        .search(request_info, LookupOptions::default())
+        .await?;
+    Ok(lookup.iter().filter_map(|r| r.data().cloned()).collect())
+}
+
+#[tokio::test]
+async fn test_most_specific_pool() {
+    let authority = authority();
+    let www = "www.example.com.";
+
+    // France is only in the pool of its continent
+    assert_eq!(
+        search(&authority, www, RecordType::A, "198.51.100.2", None)
+            .await
+            .unwrap(),
+        vec![a([192, 0, 2, 1])]
+    );
+    // the country is more specific than the continent
+    assert_eq!(
+        search(&authority, www, RecordType::A, "198.51.100.1", None)
+            .await
+            .unwrap(),
+        vec![a([192, 0, 2, 2])]
+    );
+    // and the autonomous system than the country
+    assert_eq!(
+        search(&authority, www, RecordType::A, "198.51.100.3", None)
+            .await
+            .unwrap(),
+        vec![a([192, 0, 2, 3])]
+    );
+}
+
+#[tokio::test]
+async fn test_weighted_pool() {
+    let authority = authority();
+    let www = "www.example.com.";
+
+    // the clients at no location of any pool are answered by one of the pools
+    let records = search(&authority, www, RecordType::A, "198.51.100.4", None)
+        .await
+        .unwrap();
+    assert_eq!(records.len(), 1);
+    assert!([a([192, 0, 2, 1]), a([192, 0, 2, 2]), a([192, 0, 2, 3])].contains(&records[0]));
+
+    // and by the same pool for each query
+    for _ in 0..4 {
+        assert_eq!(
+            search(&authority, www, RecordType::A, "198.51.100.4", None)
+                .await
+                .unwrap(),
+            records
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_client_subnet() {
+    let authority = authority();
+
+    let subnet = ClientSubnet::new("198.51.100.3".parse().unwrap(), 24, 0);
+    assert_eq!(
+        search(
+            &authority,
+            "www.example.com.",
+            RecordType::A,
+            "198.51.100.2",
+            Some(subnet)
+        )
+        .await
+        .unwrap(),
+        vec![a([192, 0, 2, 3])]
+    );
+}
+
+#[tokio::test]
+async fn test_record_types() {
+    let authority = authority();
+    let cname = RData::CNAME(CNAME(Name::from_str("edge.example.net.").unwrap()));
+
+    // the CNAME answers the queries of all types
+    assert_eq!(
+        search(
+            &authority,
+            "cdn.example.com.",
+            RecordType::A,
+            "198.51.100.1",
+            None
+        )
+        .await
+        .unwrap(),
+        vec![cname.clone()]
+    );
+    assert_eq!(
+        search(
+            &authority,
+            "cdn.example.com.",
+            RecordType::CNAME,
+            "198.51.100.1",
+            None
+        )
+        .await
+        .unwrap(),
+        vec![cname]
+    );
+
+    assert!(matches!(
+        search(
+            &authority,
+            "www.example.com.",
+            RecordType::AAAA,
+            "198.51.100.1",
+            None
+        )
+        .await,
+        Err(LookupError::NameExists)
+    ));
+}
+
+#[tokio::test]
+async fn test_other_names() {
+    let authority = authority();
+
+    assert_eq!(
+        search(
+            &authority,
+            "mail.example.com.",
+            RecordType::A,
+            "198.51.100.1",
+            None
+        )
+        .await
+        .unwrap(),
+        vec![a([192, 0, 2, 25])]
+    );
+
+    let name = LowerName::from(Name::from_str("www.example.com.").unwrap());
+    let lookup = authority
+        .lookup(&name, RecordType::A, LookupOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(lookup.iter().count(), 1);
+}
+
+#[test]
+fn test_name_config() {
+    let origin = Name::from_str("example.com.").unwrap();
+    let config = GeoNameConfig {
+        name: "www".to_string(),
+        ttl: None,
+        pools: vec![GeoPoolConfig {
+            name: "europe".to_string(),
+            continents: vec!["EU".to_string()],
+            countries: vec![],
+            asns: vec![],
+            weight: None,
+            records: vec!["A 192.0.2.1".to_string(), "AAAA 2001:db8::1".to_string()],
+        }],
+    };
+
+    let (name, ttl, pools) = config.to_pools(&origin).unwrap();
+    assert_eq!(name, Name::from_str("www.example.com.").unwrap());
+    assert_eq!(ttl, 60);
+    assert_eq!(pools.len(), 1);
+    assert_eq!(pools[0].weight, 1);
+    assert_eq!(
+        pools[0].records,
+        vec![
+            a([192, 0, 2, 1]),
+            RData::AAAA("2001:db8::1".parse::<std::net::Ipv6Addr>().unwrap().into()),
+        ]
+    );
+
+    let bad = GeoNameConfig {
+        pools: vec![GeoPoolConfig {
+            records: vec!["A".to_string()],
+            ..config.pools[0].clone()
+        }],
+        ..config
+    };
+    assert!(bad.to_pools(&origin).is_err());
+}
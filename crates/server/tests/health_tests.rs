@@ -0,0 +1,128 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+use hickory_proto::rr::{LowerName, Name, RData, RecordType};
+use hickory_server::{
+    authority::{Authority, LookupError, LookupOptions, ZoneType},
+    store::{
+        health::{HealthAuthority, HealthPoolConfig, Probe},
+        in_memory::InMemoryAuthority,
+    },
+};
+use tokio::net::TcpListener;
+
+const FIRST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+const SECOND: IpAddr = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2));
+
+fn www() -> LowerName {
+    LowerName::from(Name::from_str("www.example.com.").unwrap())
+}
+
+fn pool(port: u16, members: Vec<IpAddr>, fallback: Vec<IpAddr>) -> HealthPoolConfig {
+    HealthPoolConfig {
+        name: "www".to_string(),
+        ttl: Some(5),
+        members,
+        fallback,
+        probe: Probe::Tcp { port },
+        interval: None,
+        timeout: Some(5),
+        healthy_threshold: Some(2),
+        unhealthy_threshold: Some(2),
+    }
+}
+
+fn authority(pool: HealthPoolConfig) -> HealthAuthority {
+    let origin = Name::from_str("example.com.").unwrap();
+    let zone = InMemoryAuthority::empty(origin.clone(), ZoneType::Primary, false);
+
+    let mut authority = HealthAuthority::new(zone);
+    authority.insert(pool.get_name(&origin).unwrap(), pool);
+    authority
+}
+
+#[tokio::test]
+async fn test_withdraw_and_restore() {
+    let first = TcpListener::bind((FIRST, 0)).await.unwrap();
+    let port = first.local_addr().unwrap().port();
+    let authority = authority(pool(port, vec![FIRST, SECOND], vec![]));
+
+    // the members are healthy until they fail enough checks
+    assert_eq!(authority.addrs(&www()), Some(vec![FIRST, SECOND]));
+    authority.check().await;
+    assert_eq!(authority.addrs(&www()), Some(vec![FIRST, SECOND]));
+    authority.check().await;
+    assert_eq!(authority.addrs(&www()), Some(vec![FIRST]));
+
+    let lookup = authority
+        .lookup(&www(), RecordType::A, LookupOptions::default())
+        .await
+        .unwrap();
+    let records = lookup.iter().collect::<Vec<_>>();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].ttl(), 5);
+    assert_eq!(
+        records[0].data(),
+        Some(&RData::A(Ipv4Addr::LOCALHOST.into()))
+    );
+
+    // and restored once they pass enough checks
+    let _second = TcpListener::bind((SECOND, port)).await.unwrap();
+    authority.check().await;
+    assert_eq!(authority.addrs(&www()), Some(vec![FIRST]));
+    authority.check().await;
+    assert_eq!(authority.addrs(&www()), Some(vec![FIRST, SECOND]));
+}
+
+#[tokio::test]
+async fn test_all_unhealthy() {
+    let listener = TcpListener::bind((FIRST, 0)).await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    drop(listener);
+
+    let authority = authority(pool(port, vec![FIRST, SECOND], vec![]));
+    authority.check().await;
+    authority.check().await;
+
+    // all of the members are answered while none is healthy
+    assert_eq!(authority.addrs(&www()), Some(vec![FIRST, SECOND]));
+}
+
+#[tokio::test]
+async fn test_fallback() {
+    let listener = TcpListener::bind((FIRST, 0)).await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    drop(listener);
+
+    let fallback = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+    let authority = authority(pool(port, vec![FIRST, SECOND], vec![fallback]));
+    authority.check().await;
+    authority.check().await;
+    assert_eq!(authority.addrs(&www()), Some(vec![fallback]));
+
+    // the fallback has no A records
+    assert!(matches!(
+        authority
+            .lookup(&www(), RecordType::A, LookupOptions::default())
+            .await,
+        Err(LookupError::NameExists)
+    ));
+    let lookup = authority
+        .lookup(&www(), RecordType::AAAA, LookupOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(
+        lookup.iter().next().unwrap().data(),
+        Some(&RData::AAAA(
+            Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1).into()
+        ))
+    );
+
+    // other types of the name have no records
+    assert!(matches!(
+        authority
+            .lookup(&www(), RecordType::TXT, LookupOptions::default())
+            .await,
+        Err(LookupError::NameExists)
+    ));
+}
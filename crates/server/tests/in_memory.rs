@@ -2,9 +2,16 @@ use std::str::FromStr;
 
 use tokio::runtime::Runtime;
 
-use hickory_proto::rr::{rdata::CNAME, Name, RData, Record, RecordType};
+use hickory_proto::{
+    op::ResponseCode,
+    rr::{
+        rdata::{A, AAAA, CNAME, DNAME, SOA, TXT},
+        Name, RData, Record, RecordType,
+    },
+};
 use hickory_server::{
-    authority::{Authority, ZoneType},
+    authority::{Authority, LookupError, ZoneType},
+    config::AnyQueryPolicy,
     store::in_memory::InMemoryAuthority,
 };
 
@@ -160,3 +167,172 @@ fn test_cname_loop() {
         )))
     );
 }
+
+#[test]
+fn test_dname_synthesis() {
+    let runtime = Runtime::new().expect("failed to create Tokio Runtime");
+    let mut auth = InMemoryAuthority::empty(
+        Name::from_str("example.com.").unwrap(),
+        ZoneType::Primary,
+        false,
+    );
+
+    auth.upsert_mut(
+        Record::from_rdata(
+            Name::from_str("old.example.com.").unwrap(),
+            300,
+            RData::DNAME(DNAME(Name::from_str("new.example.com.").unwrap())),
+        ),
+        0,
+    );
+    auth.upsert_mut(
+        Record::from_rdata(
+            Name::from_str("www.new.example.com.").unwrap(),
+            86400,
+            RData::A(A::new(127, 0, 0, 1)),
+        ),
+        0,
+    );
+
+    let mut lookup = runtime
+        .block_on(auth.lookup(
+            &Name::from_str("www.old.example.com.").unwrap().into(),
+            RecordType::A,
+            Default::default(),
+        ))
+        .unwrap();
+
+    // the DNAME, followed by the CNAME synthesized with its TTL
+    let records: Vec<&Record> = lookup.iter().collect();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].record_type(), RecordType::DNAME);
+    assert_eq!(
+        records[1].name(),
+        &Name::from_str("www.old.example.com.").unwrap()
+    );
+    assert_eq!(records[1].ttl(), 300);
+    assert_eq!(
+        records[1].data(),
+        Some(&RData::CNAME(CNAME(
+            Name::from_str("www.new.example.com.").unwrap()
+        )))
+    );
+
+    let additionals = lookup
+        .take_additionals()
+        .expect("Should be additional records");
+    let additionals: Vec<&Record> = additionals.iter().collect();
+    assert_eq!(additionals.len(), 1);
+    assert_eq!(additionals[0].data(), Some(&RData::A(A::new(127, 0, 0, 1))));
+
+    // the DNAME does not apply to its owner
+    let lookup = runtime
+        .block_on(auth.lookup(
+            &Name::from_str("old.example.com.").unwrap().into(),
+            RecordType::DNAME,
+            Default::default(),
+        ))
+        .unwrap();
+    let records: Vec<&Record> = lookup.iter().collect();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].record_type(), RecordType::DNAME);
+
+    // the substituted name would be too long
+    let label = "x".repeat(63);
+    auth.upsert_mut(
+        Record::from_rdata(
+            Name::from_str("long.example.com.").unwrap(),
+            300,
+            RData::DNAME(DNAME(
+                Name::from_str(&format!("{label}.{label}.{label}.com.")).unwrap(),
+            )),
+        ),
+        0,
+    );
+    let result = runtime.block_on(
+        auth.lookup(
+            &Name::from_str(&format!("{label}.long.example.com."))
+                .unwrap()
+                .into(),
+            RecordType::A,
+            Default::default(),
+        ),
+    );
+    assert!(matches!(
+        result,
+        Err(LookupError::ResponseCode(ResponseCode::YXDomain))
+    ));
+}
+
+#[test]
+fn test_any_query_minimization() {
+    let runtime = Runtime::new().expect("failed to create Tokio Runtime");
+    let origin = Name::from_str("example.com.").unwrap();
+    let www = Name::from_str("www.example.com.").unwrap();
+    let mut auth = InMemoryAuthority::empty(origin.clone(), ZoneType::Primary, false);
+
+    auth.upsert_mut(
+        Record::from_rdata(
+            origin.clone(),
+            3600,
+            RData::SOA(SOA::new(
+                Name::from_str("ns.example.com.").unwrap(),
+                Name::from_str("root.example.com.").unwrap(),
+                1,
+                3600,
+                600,
+                86400,
+                1800,
+            )),
+        ),
+        0,
+    );
+    auth.upsert_mut(
+        Record::from_rdata(www.clone(), 300, RData::A(A::new(192, 0, 2, 1))),
+        0,
+    );
+    auth.upsert_mut(
+        Record::from_rdata(
+            www.clone(),
+            300,
+            RData::AAAA(AAAA::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)),
+        ),
+        0,
+    );
+    auth.upsert_mut(
+        Record::from_rdata(
+            www.clone(),
+            300,
+            RData::TXT(TXT::new(vec!["www".to_string()])),
+        ),
+        0,
+    );
+
+    let lookup_any = |auth: &InMemoryAuthority, name: &Name| {
+        runtime.block_on(auth.lookup(&name.into(), RecordType::ANY, Default::default()))
+    };
+
+    // all the records by default
+    let lookup = lookup_any(&auth, &www).unwrap();
+    assert_eq!(lookup.iter().count(), 3);
+
+    auth.set_any_query(AnyQueryPolicy::OneRrset);
+    let lookup = lookup_any(&auth, &www).unwrap();
+    let records: Vec<&Record> = lookup.iter().collect();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].record_type(), RecordType::A);
+
+    auth.set_any_query(AnyQueryPolicy::Hinfo);
+    let lookup = lookup_any(&auth, &www).unwrap();
+    let records: Vec<&Record> = lookup.iter().collect();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].name(), &www);
+    assert_eq!(records[0].ttl(), 1800);
+    let hinfo = records[0].data().and_then(RData::as_hinfo).unwrap();
+    assert_eq!(hinfo.cpu(), b"RFC8482");
+    assert!(hinfo.os().is_empty());
+
+    // names which do not exist are still denied
+    let result = lookup_any(&auth, &Name::from_str("ftp.example.com.").unwrap());
+    assert!(result.unwrap_err().is_nx_domain());
+}
@@ -95,3 +95,43 @@ fn test_iterator() {
     );
     assert_eq!(None, iter.next());
 }
+
+#[test]
+fn test_verify_serials() {
+    let (mut record, journal) = create_test_journal();
+    assert_eq!(journal.verify_serials().unwrap(), Some(0));
+
+    record.set_data(Some(RData::A(A::from_str("127.0.2.1").unwrap())));
+    journal.insert_record(2, &record).unwrap();
+    assert_eq!(journal.verify_serials().unwrap(), Some(2));
+
+    record.set_data(Some(RData::A(A::from_str("127.0.3.1").unwrap())));
+    journal.insert_record(1, &record).unwrap();
+    assert!(journal.verify_serials().is_err());
+}
+
+#[test]
+fn test_verify_serials_wrap() {
+    let (mut record, journal) = create_test_journal();
+
+    // each step is less than half of the serial space
+    for (i, serial) in [0x4000_0000, 0x8000_0000, 0xC000_0000, u32::MAX]
+        .into_iter()
+        .enumerate()
+    {
+        record.set_data(Some(RData::A(
+            A::from_str(&format!("127.0.1.{i}")).unwrap(),
+        )));
+        journal.insert_record(serial, &record).unwrap();
+    }
+    assert_eq!(journal.verify_serials().unwrap(), Some(u32::MAX));
+
+    // the serial wraps around, RFC 1982
+    record.set_data(Some(RData::A(A::from_str("127.0.3.1").unwrap())));
+    journal.insert_record(1, &record).unwrap();
+    assert_eq!(journal.verify_serials().unwrap(), Some(1));
+
+    record.set_data(Some(RData::A(A::from_str("127.0.4.1").unwrap())));
+    journal.insert_record(u32::MAX - 1, &record).unwrap();
+    assert!(journal.verify_serials().is_err());
+}
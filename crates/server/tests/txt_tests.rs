@@ -496,3 +496,51 @@ fn test_named_root() {
     assert!(records.contains_key(&key));
     assert_eq!(records[&key].dns_class(), DNSClass::IN)
 }
+
+#[test]
+fn test_generic_rdata() {
+    const ZONE: &str = r"
+@   IN  SOA     venera  action\.domains (
+                            20     ; SERIAL
+                            7200   ; REFRESH
+                            600    ; RETRY
+                            3600000; EXPIRE
+                            60)    ; MINIMUM
+
+foo IN  TYPE65534   \# 3 abcdef
+foo IN  TYPE1       \# 4 0A000001
+bar IN  A           \# 4 0A000002
+";
+
+    let records = Parser::new(ZONE, None, Some(Name::from_str("isi.edu").unwrap())).parse();
+
+    if records.is_err() {
+        panic!("failed to parse: {:?}", records.err())
+    }
+
+    let (_, records) = records.unwrap();
+
+    let foo = Name::from_str("foo.isi.edu.").unwrap();
+    let unknown = &records[&RrKey::new(LowerName::from(&foo), RecordType::Unknown(65534))];
+    let record = unknown.records_without_rrsigs().next().unwrap();
+    assert_eq!(record.record_type(), RecordType::Unknown(65534));
+    assert_eq!(
+        record.data().unwrap().to_string(),
+        r"\# 3 abcdef",
+        "unknown types should display in the generic format"
+    );
+    assert!(record.to_string().contains(r"TYPE65534"));
+
+    let a = &records[&RrKey::new(LowerName::from(&foo), RecordType::A)];
+    assert_eq!(
+        a.records_without_rrsigs().next().unwrap().data(),
+        Some(&RData::A(A::new(10, 0, 0, 1)))
+    );
+
+    let bar = Name::from_str("bar.isi.edu.").unwrap();
+    let a = &records[&RrKey::new(LowerName::from(&bar), RecordType::A)];
+    assert_eq!(
+        a.records_without_rrsigs().next().unwrap().data(),
+        Some(&RData::A(A::new(10, 0, 0, 2)))
+    );
+}
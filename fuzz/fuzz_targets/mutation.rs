@@ -0,0 +1,29 @@
+#![no_main]
+use libfuzzer_sys::arbitrary::{Arbitrary, Unstructured};
+use libfuzzer_sys::fuzz_target;
+
+use hickory_proto::{
+    fuzzing::MessageMutator,
+    op::Message,
+    serialize::binary::{BinDecodable, BinEncodable},
+};
+
+fuzz_target!(|data: &[u8]| {
+    let mut u = Unstructured::new(data);
+    let Ok(message) = Message::arbitrary(&mut u) else {
+        return;
+    };
+
+    let mutator = MessageMutator::new(&message).expect("arbitrary messages are valid");
+    let Ok(mutation) = mutator.arbitrary_mutation(&mut u) else {
+        return;
+    };
+    let mutated = mutator
+        .mutate(mutation)
+        .expect("arbitrary mutations are in the message");
+
+    // the mutated message is either rejected, or decoded and encoded again
+    if let Ok(decoded) = Message::from_bytes(&mutated) {
+        let _ = decoded.to_bytes();
+    }
+});
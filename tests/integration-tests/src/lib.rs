@@ -37,6 +37,7 @@ use hickory_server::{
 };
 
 pub mod example_authority;
+pub mod local_server;
 pub mod mock_client;
 #[cfg(feature = "dns-over-rustls")]
 pub mod tls_client_connection;
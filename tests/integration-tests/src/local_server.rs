@@ -0,0 +1,59 @@
+//! A server of zones on a local UDP port, and a resolver of it, for the tests of the authorities
+//!  which resolve names outside of their zones
+
+use std::net::{Ipv4Addr, SocketAddr};
+use std::str::FromStr;
+
+use tokio::net::UdpSocket;
+
+use hickory_client::rr::rdata::SOA;
+use hickory_client::rr::{Name, RData, Record};
+use hickory_resolver::config::{NameServerConfigGroup, ResolverConfig, ResolverOpts};
+use hickory_resolver::TokioAsyncResolver;
+use hickory_server::authority::Catalog;
+use hickory_server::ServerFuture;
+
+/// Parses the name, panics if it is not valid
+pub fn name(name: &str) -> Name {
+    Name::from_str(name).unwrap()
+}
+
+/// The SOA record of a test zone
+pub fn soa(origin: &Name) -> Record {
+    Record::from_rdata(
+        origin.clone(),
+        3600,
+        RData::SOA(SOA::new(
+            name("ns.example.net."),
+            name("hostmaster.example.net."),
+            1,
+            7200,
+            3600,
+            1209600,
+            60,
+        )),
+    )
+}
+
+/// Serves the catalog on a UDP socket of the loopback interface, returns the server and its address
+pub async fn serve_catalog(catalog: Catalog) -> (ServerFuture<Catalog>, SocketAddr) {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = socket.local_addr().unwrap();
+
+    let mut server = ServerFuture::new(catalog);
+    server.register_socket(socket);
+
+    (server, addr)
+}
+
+/// A resolver querying only the server at `addr`
+pub fn resolver(addr: SocketAddr) -> TokioAsyncResolver {
+    TokioAsyncResolver::tokio(
+        ResolverConfig::from_parts(
+            None,
+            vec![],
+            NameServerConfigGroup::from_ips_clear(&[addr.ip()], addr.port(), true),
+        ),
+        ResolverOpts::default(),
+    )
+}
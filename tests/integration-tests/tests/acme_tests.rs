@@ -0,0 +1,116 @@
+use std::net::{Ipv4Addr, SocketAddr};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::net::UdpSocket;
+
+use hickory_acme::{Dns01Solver, ErrorKind, InProcessAuthority, Propagation};
+use hickory_client::client::{AsyncClient, ClientHandle};
+use hickory_client::rr::{rdata::TXT, DNSClass, Name, RData, RecordType};
+use hickory_client::udp::UdpClientStream;
+use hickory_server::authority::{Authority, Catalog};
+use hickory_server::store::in_memory::InMemoryAuthority;
+use hickory_server::ServerFuture;
+
+use hickory_integration::example_authority::create_example;
+
+async fn server(authority: Arc<InMemoryAuthority>) -> (ServerFuture<Catalog>, SocketAddr) {
+    let mut catalog = Catalog::new();
+    catalog.upsert(authority.origin().clone(), Box::new(authority));
+
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = socket.local_addr().unwrap();
+    let mut server = ServerFuture::new(catalog);
+    server.register_socket(socket);
+    (server, addr)
+}
+
+async fn challenge_values(server: SocketAddr, name: &Name) -> Vec<String> {
+    let stream = UdpClientStream::<UdpSocket>::new(server);
+    let (mut client, bg) = AsyncClient::connect(stream).await.unwrap();
+    tokio::spawn(bg);
+
+    let response = client
+        .query(name.clone(), DNSClass::IN, RecordType::TXT)
+        .await
+        .unwrap();
+    let mut values = response
+        .answers()
+        .iter()
+        .filter_map(|record| match record.data() {
+            Some(RData::TXT(txt)) => Some(txt.to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    values.sort();
+    values
+}
+
+fn propagation(timeout: Duration) -> Propagation {
+    let mut propagation = Propagation::default();
+    propagation.timeout = timeout;
+    propagation.interval = Duration::from_millis(100);
+    propagation.query_timeout = Duration::from_millis(500);
+    propagation
+}
+
+#[tokio::test]
+async fn test_present_and_cleanup() {
+    let authority = Arc::new(create_example());
+    let (mut server, addr) = server(authority.clone()).await;
+
+    let mut solver = Dns01Solver::new(InProcessAuthority::new(authority), vec![addr]);
+    solver.set_propagation(propagation(Duration::from_secs(5)));
+
+    let name = Name::from_str("_acme-challenge.example.com.").unwrap();
+    let domain = solver
+        .present(&Name::from_str("example.com.").unwrap(), "domain")
+        .await
+        .unwrap();
+    let wildcard = solver
+        .present(&Name::from_str("*.example.com.").unwrap(), "wildcard")
+        .await
+        .unwrap();
+
+    assert_eq!(domain.record().name(), &name);
+    assert_eq!(
+        wildcard.record().data(),
+        Some(&RData::TXT(TXT::new(vec!["wildcard".to_string()])))
+    );
+    assert_eq!(challenge_values(addr, &name).await, ["domain", "wildcard"]);
+
+    // the challenges are independent of each other
+    solver.cleanup(domain).await.unwrap();
+    assert_eq!(challenge_values(addr, &name).await, ["wildcard"]);
+    solver.cleanup(wildcard).await.unwrap();
+    assert!(challenge_values(addr, &name).await.is_empty());
+
+    server.shutdown_gracefully().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_not_propagated() {
+    let authority = Arc::new(create_example());
+    let (mut server, addr) = server(authority.clone()).await;
+
+    // nothing answers on this socket
+    let silent = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let silent_addr = silent.local_addr().unwrap();
+
+    let mut solver = Dns01Solver::new(InProcessAuthority::new(authority), vec![addr, silent_addr]);
+    solver.set_propagation(propagation(Duration::from_secs(1)));
+
+    let name = Name::from_str("www.example.com.").unwrap();
+    let error = solver.present(&name, "value").await.unwrap_err();
+    match error.kind() {
+        ErrorKind::NotPropagated { pending, .. } => assert_eq!(pending, &[silent_addr]),
+        kind => panic!("unexpected error: {kind}"),
+    }
+
+    // the record is removed again
+    let name = Name::from_str("_acme-challenge.www.example.com.").unwrap();
+    assert!(challenge_values(addr, &name).await.is_empty());
+
+    server.shutdown_gracefully().await.unwrap();
+}
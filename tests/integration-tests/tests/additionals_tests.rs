@@ -0,0 +1,154 @@
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use hickory_client::rr::rdata::{MX, SRV};
+use hickory_client::rr::{Name, RData, Record, RecordType};
+use hickory_integration::local_server::{name, resolver, serve_catalog, soa};
+use hickory_proto::rr::LowerName;
+use hickory_server::authority::{Authority, Catalog, LookupOptions, ZoneType};
+use hickory_server::store::in_memory::{AdditionalResolver, InMemoryAuthority};
+use hickory_server::ServerFuture;
+
+/// The zone of the mail and sip servers outside of `example.com.`
+fn create_provider() -> InMemoryAuthority {
+    let origin = name("example.net.");
+    let mut authority = InMemoryAuthority::empty(origin.clone(), ZoneType::Primary, false);
+    authority.upsert_mut(soa(&origin), 0);
+    authority.upsert_mut(
+        Record::from_rdata(
+            name("mx.example.net."),
+            300,
+            RData::A(Ipv4Addr::new(192, 0, 2, 25).into()),
+        ),
+        0,
+    );
+    authority.upsert_mut(
+        Record::from_rdata(
+            name("sip.example.net."),
+            300,
+            RData::A(Ipv4Addr::new(192, 0, 2, 60).into()),
+        ),
+        0,
+    );
+
+    authority
+}
+
+/// The zone with MX and SRV records of targets in and outside of it
+fn create_example() -> InMemoryAuthority {
+    let origin = name("example.com.");
+    let mut authority = InMemoryAuthority::empty(origin.clone(), ZoneType::Primary, false);
+    authority.upsert_mut(soa(&origin), 0);
+    for (preference, exchange) in [(10, "mx.example.net."), (20, "backup.example.com.")] {
+        authority.upsert_mut(
+            Record::from_rdata(
+                origin.clone(),
+                300,
+                RData::MX(MX::new(preference, name(exchange))),
+            ),
+            0,
+        );
+    }
+    authority.upsert_mut(
+        Record::from_rdata(
+            name("backup.example.com."),
+            300,
+            RData::A(Ipv4Addr::new(192, 0, 2, 26).into()),
+        ),
+        0,
+    );
+    authority.upsert_mut(
+        Record::from_rdata(
+            name("_sip._udp.example.com."),
+            300,
+            RData::SRV(SRV::new(0, 5, 5060, name("sip.example.net."))),
+        ),
+        0,
+    );
+
+    authority
+}
+
+async fn serve_provider() -> (ServerFuture<Catalog>, SocketAddr) {
+    let provider = create_provider();
+    let mut catalog = Catalog::new();
+    catalog.upsert(provider.origin().clone(), Box::new(Arc::new(provider)));
+
+    serve_catalog(catalog).await
+}
+
+fn additional_resolver(addr: SocketAddr) -> AdditionalResolver {
+    AdditionalResolver::new(resolver(addr), Duration::from_secs(5))
+}
+
+async fn additional_addrs(
+    authority: &InMemoryAuthority,
+    query_name: &str,
+    query_type: RecordType,
+) -> Vec<(Name, RData)> {
+    let mut lookup = authority
+        .lookup(
+            &LowerName::from(name(query_name)),
+            query_type,
+            LookupOptions::default(),
+        )
+        .await
+        .unwrap();
+    let mut addrs = lookup
+        .take_additionals()
+        .map(|additionals| {
+            additionals
+                .iter()
+                .filter(|record| record.record_type() == RecordType::A)
+                .filter_map(|record| Some((record.name().clone(), record.data()?.clone())))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    addrs.sort_by_key(|(name, _)| name.clone());
+    addrs
+}
+
+#[tokio::test]
+async fn test_mx_additionals() {
+    let (mut server, addr) = serve_provider().await;
+
+    let mut authority = create_example();
+    // only the targets in the zone are added by default
+    assert!(additional_addrs(&authority, "example.com.", RecordType::MX)
+        .await
+        .iter()
+        .all(|(name, _)| name != &self::name("mx.example.net.")));
+
+    authority.set_additional_resolver(additional_resolver(addr));
+    let addrs = additional_addrs(&authority, "example.com.", RecordType::MX).await;
+    assert!(addrs.contains(&(
+        name("mx.example.net."),
+        RData::A(Ipv4Addr::new(192, 0, 2, 25).into())
+    )));
+
+    server.shutdown_gracefully().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_srv_additionals() {
+    let (mut server, addr) = serve_provider().await;
+
+    let mut authority = create_example();
+    assert!(
+        additional_addrs(&authority, "_sip._udp.example.com.", RecordType::SRV)
+            .await
+            .is_empty()
+    );
+
+    authority.set_additional_resolver(additional_resolver(addr));
+    assert_eq!(
+        additional_addrs(&authority, "_sip._udp.example.com.", RecordType::SRV).await,
+        vec![(
+            name("sip.example.net."),
+            RData::A(Ipv4Addr::new(192, 0, 2, 60).into())
+        )]
+    );
+
+    server.shutdown_gracefully().await.unwrap();
+}
@@ -0,0 +1,102 @@
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use hickory_client::rr::rdata::ANAME;
+use hickory_client::rr::{RData, Record, RecordType};
+use hickory_integration::local_server::{name, resolver, serve_catalog, soa};
+use hickory_proto::rr::LowerName;
+use hickory_server::authority::{Authority, Catalog, LookupOptions, ZoneType};
+use hickory_server::store::in_memory::{AnameFlattener, InMemoryAuthority};
+
+/// The zone of the target, with two addresses of the edge
+fn create_cdn() -> InMemoryAuthority {
+    let origin = name("cdn.example.net.");
+    let mut authority = InMemoryAuthority::empty(origin.clone(), ZoneType::Primary, false);
+    authority.upsert_mut(soa(&origin), 0);
+    for last in [1, 2] {
+        authority.upsert_mut(
+            Record::from_rdata(
+                name("edge.cdn.example.net."),
+                120,
+                RData::A(Ipv4Addr::new(192, 0, 2, last).into()),
+            ),
+            0,
+        );
+    }
+
+    authority
+}
+
+/// The zone with an ANAME at its apex
+fn create_apex() -> InMemoryAuthority {
+    let origin = name("example.com.");
+    let mut authority = InMemoryAuthority::empty(origin.clone(), ZoneType::Primary, false);
+    authority.upsert_mut(soa(&origin), 0);
+    authority.upsert_mut(
+        Record::from_rdata(
+            origin,
+            300,
+            RData::ANAME(ANAME(name("edge.cdn.example.net."))),
+        ),
+        0,
+    );
+
+    authority
+}
+
+#[tokio::test]
+async fn test_flatten_apex() {
+    let cdn = create_cdn();
+    let mut catalog = Catalog::new();
+    catalog.upsert(cdn.origin().clone(), Box::new(Arc::new(cdn)));
+    let (mut server, addr) = serve_catalog(catalog).await;
+
+    let flattener = AnameFlattener::new(
+        resolver(addr),
+        Duration::from_secs(30),
+        Duration::from_secs(3600),
+    );
+
+    let apex = create_apex();
+    let refresh = flattener.flatten(&apex).await;
+    // the addresses of the target expire first, the missing AAAA records are not cached
+    assert!(refresh >= Duration::from_secs(30));
+    assert!(refresh <= Duration::from_secs(120));
+
+    let origin = LowerName::from(name("example.com."));
+    let lookup = apex
+        .lookup(&origin, RecordType::A, LookupOptions::default())
+        .await
+        .unwrap();
+    let records = lookup.iter().collect::<Vec<_>>();
+    assert_eq!(records.len(), 2);
+    for record in &records {
+        assert_eq!(record.name(), &name("example.com."));
+        assert!(record.ttl() <= 120);
+    }
+    let mut addrs = records
+        .iter()
+        .filter_map(|record| record.data())
+        .cloned()
+        .collect::<Vec<_>>();
+    addrs.sort_by_key(|rdata| rdata.to_string());
+    assert_eq!(
+        addrs,
+        vec![
+            RData::A(Ipv4Addr::new(192, 0, 2, 1).into()),
+            RData::A(Ipv4Addr::new(192, 0, 2, 2).into()),
+        ]
+    );
+
+    // the target has no AAAA records, only the ANAME is answered
+    let lookup = apex
+        .lookup(&origin, RecordType::AAAA, LookupOptions::default())
+        .await
+        .unwrap();
+    assert!(lookup
+        .iter()
+        .all(|record| record.record_type() == RecordType::ANAME));
+
+    server.shutdown_gracefully().await.unwrap();
+}
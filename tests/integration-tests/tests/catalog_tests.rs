@@ -1,18 +1,28 @@
-use std::{str::FromStr, sync::Arc};
+use std::{str::FromStr, sync::Arc, time::Duration};
 
 use hickory_client::{
     op::*,
-    rr::{rdata::*, *},
-    serialize::binary::{BinDecodable, BinEncodable},
+    rr::{
+        rdata::{
+            opt::{EdnsCode, EdnsOption},
+            *,
+        },
+        *,
+    },
+    serialize::binary::{BinDecodable, BinDecoder, BinEncodable},
 };
 
 use hickory_server::{
-    authority::{Authority, Catalog, MessageRequest, ZoneType},
-    server::{Protocol, Request},
+    authority::{
+        AuthLookup, Authority, Catalog, LookupError, LookupOptions, MessageRequest, UpdateResult,
+        ZoneType,
+    },
+    server::{Protocol, Request, RequestHandler, RequestInfo},
     store::in_memory::InMemoryAuthority,
 };
 
 use hickory_integration::{example_authority::create_example, *};
+use hickory_proto::{op::MessageRef, rr::LowerName};
 
 #[allow(clippy::unreadable_literal)]
 pub fn create_test() -> InMemoryAuthority {
@@ -639,3 +649,276 @@ async fn test_multiple_cname_additionals() {
         &RData::A(A::new(93, 184, 216, 34))
     );
 }
+
+#[tokio::test]
+async fn test_server_id() {
+    let mut catalog: Catalog = Catalog::new();
+    catalog.set_server_id(Some("anycast-1".to_string()));
+
+    let mut question: Message = Message::new();
+    let mut query: Query = Query::query(
+        Name::parse("hostname.bind.", None).unwrap(),
+        RecordType::TXT,
+    );
+    query.set_query_class(DNSClass::CH);
+    question.add_query(query);
+    let mut edns = Edns::new();
+    edns.options_mut()
+        .insert(EdnsOption::Unknown(EdnsCode::NSID.into(), vec![]));
+    question.set_edns(edns);
+
+    let question_bytes = question.to_bytes().unwrap();
+    let question_req = MessageRequest::from_bytes(&question_bytes).unwrap();
+    let question_req = Request::new(question_req, ([127, 0, 0, 1], 5553).into(), Protocol::Udp);
+
+    let response_handler = TestResponseHandler::new();
+    catalog
+        .handle_request(&question_req, response_handler.clone())
+        .await;
+    let result = response_handler.into_message().await;
+
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    assert_eq!(result.answers().len(), 1);
+    assert_eq!(result.answers()[0].dns_class(), DNSClass::CH);
+    assert_eq!(
+        result.answers()[0].data(),
+        Some(&RData::TXT(TXT::new(vec!["anycast-1".to_string()])))
+    );
+    assert_eq!(
+        result.extensions().as_ref().unwrap().option(EdnsCode::NSID),
+        Some(&EdnsOption::Unknown(
+            EdnsCode::NSID.into(),
+            b"anycast-1".to_vec()
+        ))
+    );
+}
+
+#[tokio::test]
+async fn test_blocklist() {
+    use hickory_resolver::blocklist::{BlockAction, Blocklist, ListFormat, SharedBlocklist};
+
+    let example = create_example();
+    let origin = example.origin().clone();
+
+    let mut blocklist = Blocklist::new(BlockAction::Addresses(vec![[192, 0, 2, 1].into()]));
+    blocklist
+        .read("ads.example.com\n".as_bytes(), ListFormat::Domains)
+        .unwrap();
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(origin, Box::new(Arc::new(example)));
+    catalog.set_blocklist(Some(Arc::new(SharedBlocklist::new(blocklist))));
+
+    let lookup = |name: &str, record_type| {
+        let mut question: Message = Message::new();
+        question.add_query(Query::query(Name::from_str(name).unwrap(), record_type));
+        let question_bytes = question.to_bytes().unwrap();
+        let question_req = MessageRequest::from_bytes(&question_bytes).unwrap();
+        let question_req = Request::new(question_req, ([127, 0, 0, 1], 5553).into(), Protocol::Udp);
+
+        let response_handler = TestResponseHandler::new();
+        let catalog = &catalog;
+        async move {
+            catalog
+                .handle_request(&question_req, response_handler.clone())
+                .await;
+            response_handler.into_message().await
+        }
+    };
+
+    let result = lookup("ads.example.com.", RecordType::A).await;
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    assert_eq!(
+        result.answers()[0].data(),
+        Some(&RData::A(A::new(192, 0, 2, 1)))
+    );
+    assert!(!result.authoritative());
+
+    let result = lookup("ads.example.com.", RecordType::AAAA).await;
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    assert!(result.answers().is_empty());
+
+    // the names which are not blocked are answered by the authorities
+    let result = lookup("www.example.com.", RecordType::A).await;
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    assert!(result.authoritative());
+}
+
+#[tokio::test]
+async fn test_sync_status() {
+    let primary = create_example();
+    let origin = primary.origin().clone();
+    let secondary_origin =
+        hickory_proto::rr::LowerName::from(Name::parse("secondary.com.", None).unwrap());
+    let secondary =
+        InMemoryAuthority::empty(secondary_origin.clone().into(), ZoneType::Secondary, false);
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(origin, Box::new(Arc::new(primary)));
+    catalog.upsert(secondary_origin.clone(), Box::new(Arc::new(secondary)));
+
+    let tracker = catalog.sync_tracker().clone();
+    let status = tracker.status();
+    assert_eq!(status.len(), 1);
+    assert_eq!(status[0].zone, secondary_origin);
+    assert_eq!(status[0].serial_lag(), None);
+
+    tracker.record_refresh(&secondary_origin, 2015082403, 2015082405);
+    assert_eq!(tracker.status()[0].serial_lag(), Some(2));
+
+    catalog.remove(&secondary_origin);
+    assert!(tracker.status().is_empty());
+}
+
+/// A forwarder whose upstream never answers
+struct HungAuthority(LowerName);
+
+#[async_trait::async_trait]
+impl Authority for HungAuthority {
+    type Lookup = AuthLookup;
+
+    fn zone_type(&self) -> ZoneType {
+        ZoneType::Forward
+    }
+
+    fn is_axfr_allowed(&self) -> bool {
+        false
+    }
+
+    async fn update(&self, _update: &MessageRequest) -> UpdateResult<bool> {
+        Err(ResponseCode::NotImp)
+    }
+
+    fn origin(&self) -> &LowerName {
+        &self.0
+    }
+
+    async fn lookup(
+        &self,
+        _name: &LowerName,
+        _rtype: RecordType,
+        _lookup_options: LookupOptions,
+    ) -> Result<Self::Lookup, LookupError> {
+        futures::future::pending().await
+    }
+
+    async fn search(
+        &self,
+        _request: RequestInfo<'_>,
+        _lookup_options: LookupOptions,
+    ) -> Result<Self::Lookup, LookupError> {
+        futures::future::pending().await
+    }
+
+    async fn get_nsec_records(
+        &self,
+        _name: &LowerName,
+        _lookup_options: LookupOptions,
+    ) -> Result<Self::Lookup, LookupError> {
+        Ok(AuthLookup::default())
+    }
+
+    async fn get_wildcard_proof(
+        &self,
+        _next_closer: &LowerName,
+        _lookup_options: LookupOptions,
+    ) -> Result<Self::Lookup, LookupError> {
+        Ok(AuthLookup::default())
+    }
+}
+
+#[tokio::test]
+async fn test_request_timeout() {
+    let origin = LowerName::from(Name::root());
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(origin.clone(), Box::new(Arc::new(HungAuthority(origin))));
+    catalog.set_request_timeout(Some(Duration::from_millis(100)));
+    catalog.set_slow_query_threshold(Some(Duration::from_millis(50)));
+
+    let mut question: Message = Message::new();
+    question
+        .add_query(Query::query(
+            Name::parse("www.example.com.", None).unwrap(),
+            RecordType::A,
+        ))
+        .set_recursion_desired(true);
+
+    let question_bytes = question.to_bytes().unwrap();
+    let question_req = MessageRequest::from_bytes(&question_bytes).unwrap();
+    let question_req = Request::new(question_req, ([127, 0, 0, 1], 5553).into(), Protocol::Udp);
+
+    // the lookup is aborted at the deadline, and answered with SERVFAIL
+    let response_handler = TestResponseHandler::new();
+    let info = tokio::time::timeout(
+        Duration::from_secs(5),
+        catalog.handle_request(&question_req, response_handler.clone()),
+    )
+    .await
+    .expect("the lookup should have been aborted");
+    assert_eq!(info.response_code(), ResponseCode::ServFail);
+
+    let result = response_handler.into_message().await;
+    assert_eq!(result.response_code(), ResponseCode::ServFail);
+    assert_eq!(result.id(), question.id());
+    assert!(result.answers().is_empty());
+}
+
+#[tokio::test]
+async fn test_answer_ref() {
+    let example = create_example();
+    let origin = example.origin().clone();
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(origin, Box::new(Arc::new(example)));
+
+    let src = ([127, 0, 0, 1], 5553).into();
+    let request_bytes = |name: &str, query_type: RecordType| {
+        let mut question: Message = Message::new();
+        question.add_query(Query::query(Name::parse(name, None).unwrap(), query_type));
+        let mut edns = Edns::new();
+        edns.set_max_payload(1232);
+        question.set_edns(edns);
+        question.to_bytes().unwrap()
+    };
+
+    // the answer from the borrowed request is the same as the one of handle_request
+    let question_bytes = request_bytes("www.example.com.", RecordType::A);
+    let question_ref = MessageRef::read(&mut BinDecoder::new(&question_bytes)).unwrap();
+    let answer = catalog
+        .answer_ref(&question_ref, src, Protocol::Udp)
+        .expect("the fast path should have answered");
+    let answer = Message::from_vec(&answer).unwrap();
+
+    let question_req = MessageRequest::from_bytes(&question_bytes).unwrap();
+    let question_req = Request::new(question_req, src, Protocol::Udp);
+    let response_handler = TestResponseHandler::new();
+    catalog
+        .handle_request(&question_req, response_handler.clone())
+        .await;
+    let result = response_handler.into_message().await;
+
+    assert!(answer.header().authoritative());
+    assert_eq!(answer.answers().len(), 1);
+    assert_eq!(answer.header(), result.header());
+    assert_eq!(answer.queries(), result.queries());
+    assert_eq!(answer.answers(), result.answers());
+    assert_eq!(answer.extensions(), result.extensions());
+
+    // the answers with more than a single RRset, or none, are left to handle_request
+    for (name, query_type) in [
+        ("example.com.", RecordType::SOA),
+        ("example.com.", RecordType::NS),
+        ("alias.example.com.", RecordType::A),
+        ("www.example.com.", RecordType::ANY),
+        ("nope.example.com.", RecordType::A),
+        ("www.example.org.", RecordType::A),
+    ] {
+        let question_bytes = request_bytes(name, query_type);
+        let question_ref = MessageRef::read(&mut BinDecoder::new(&question_bytes)).unwrap();
+        assert!(
+            catalog
+                .answer_ref(&question_ref, src, Protocol::Udp)
+                .is_none(),
+            "{name} {query_type}"
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use std::net::{IpAddr, Ipv4Addr};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use futures::FutureExt;
+use tokio::net::UdpSocket;
+
+use hickory_client::client::{DelegationChecker, DelegationReport, Finding};
+use hickory_client::rr::rdata::{NS, SOA};
+use hickory_client::rr::{Name, RData, Record};
+use hickory_server::authority::{Authority, Catalog, ZoneType};
+use hickory_server::store::in_memory::InMemoryAuthority;
+use hickory_server::ServerFuture;
+
+use hickory_integration::example_authority::create_example;
+
+const CHILD_A: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 1);
+const CHILD_B: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 2);
+
+fn name(name: &str) -> Name {
+    Name::from_str(name).unwrap()
+}
+
+/// The `com.` zone, delegating `example.com.` to the name servers of the example authority
+fn create_parent() -> InMemoryAuthority {
+    let origin = name("com.");
+    let mut authority = InMemoryAuthority::empty(origin.clone(), ZoneType::Primary, false);
+    authority.upsert_mut(
+        Record::from_rdata(
+            origin.clone(),
+            3600,
+            RData::SOA(SOA::new(
+                name("a.gtld-servers.net."),
+                name("nstld.verisign-grs.com."),
+                1,
+                1800,
+                900,
+                604800,
+                86400,
+            )),
+        ),
+        0,
+    );
+    for ns in ["a.iana-servers.net.", "b.iana-servers.net."] {
+        authority.upsert_mut(
+            Record::from_rdata(name("example.com."), 172800, RData::NS(NS(name(ns)))),
+            0,
+        );
+    }
+
+    authority
+}
+
+/// The example zone, with its serial incremented by `serial_offset`
+fn create_child(serial_offset: u32) -> InMemoryAuthority {
+    let mut authority = create_example();
+    let soa = SOA::new(
+        name("sns.dns.icann.org."),
+        name("noc.dns.icann.org."),
+        2015082403 + serial_offset,
+        7200,
+        3600,
+        1209600,
+        3600,
+    );
+    authority.upsert_mut(
+        Record::from_rdata(name("example.com."), 3600, RData::SOA(soa)),
+        0,
+    );
+
+    authority
+}
+
+fn serve(authority: InMemoryAuthority, socket: UdpSocket) -> ServerFuture<Catalog> {
+    let mut catalog = Catalog::new();
+    catalog.upsert(authority.origin().clone(), Box::new(Arc::new(authority)));
+
+    let mut server = ServerFuture::new(catalog);
+    server.register_socket(socket);
+    server
+}
+
+/// Checks the delegation, with the child name servers on the same port of two addresses
+async fn check(serial_offset_b: u32) -> (DelegationReport, u16) {
+    let parent_socket = UdpSocket::bind((CHILD_A, 0)).await.unwrap();
+    let parent_addr = parent_socket.local_addr().unwrap();
+    let parent = serve(create_parent(), parent_socket);
+
+    let child_a_socket = UdpSocket::bind((CHILD_A, 0)).await.unwrap();
+    let port = child_a_socket.local_addr().unwrap().port();
+    let child_a = serve(create_child(0), child_a_socket);
+    let child_b_socket = UdpSocket::bind((CHILD_B, port)).await.unwrap();
+    let child_b = serve(create_child(serial_offset_b), child_b_socket);
+
+    let mut checker = DelegationChecker::new();
+    checker.set_port(port);
+    checker.set_address_lookup(Arc::new(|name: Name| {
+        let ip = match name.to_ascii().as_str() {
+            "a.iana-servers.net." => Some(IpAddr::V4(CHILD_A)),
+            "b.iana-servers.net." => Some(IpAddr::V4(CHILD_B)),
+            _ => None,
+        };
+        async move { ip.into_iter().collect() }.boxed()
+    }));
+
+    let report = checker.check(&name("example.com."), &[parent_addr]).await;
+
+    for mut server in [parent, child_a, child_b] {
+        server.shutdown_gracefully().await.unwrap();
+    }
+
+    (report, port)
+}
+
+#[tokio::test]
+async fn test_healthy_delegation() {
+    let (report, _) = check(0).await;
+
+    assert!(report.is_healthy(), "{:?}", report.findings());
+    assert_eq!(
+        report.delegation,
+        vec![name("a.iana-servers.net."), name("b.iana-servers.net.")]
+    );
+    assert_eq!(report.name_servers.len(), 2);
+    assert!(report
+        .name_servers
+        .iter()
+        .all(|name_server| name_server.serial == 2015082403));
+}
+
+#[tokio::test]
+async fn test_serial_mismatch() {
+    let (report, port) = check(1).await;
+
+    assert_eq!(
+        report.findings(),
+        [Finding::SerialMismatch {
+            serials: vec![
+                ((CHILD_A, port).into(), 2015082403),
+                ((CHILD_B, port).into(), 2015082404),
+            ],
+        }]
+    );
+}
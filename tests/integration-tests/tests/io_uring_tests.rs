@@ -0,0 +1,54 @@
+#![cfg(all(feature = "io-uring", target_os = "linux"))]
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use hickory_proto::uring::{IoUringTcpListener, IoUringUdpSocket};
+use hickory_resolver::config::{NameServerConfig, Protocol, ResolverConfig, ResolverOpts};
+use hickory_resolver::name_server::IoUringConnectionProvider;
+use hickory_resolver::AsyncResolver;
+use hickory_server::authority::{Authority, Catalog};
+use hickory_server::ServerFuture;
+
+use hickory_integration::example_authority::create_example;
+
+async fn lookup(server: SocketAddr, protocol: Protocol) -> Vec<IpAddr> {
+    let mut config = ResolverConfig::new();
+    config.add_name_server(NameServerConfig::new(server, protocol));
+    let resolver = AsyncResolver::new(
+        config,
+        ResolverOpts::default(),
+        IoUringConnectionProvider::default(),
+    );
+
+    resolver
+        .lookup_ip("www.example.com.")
+        .await
+        .unwrap()
+        .iter()
+        .collect()
+}
+
+#[tokio::test]
+async fn test_server_and_resolver() {
+    let authority = create_example();
+    let mut catalog = Catalog::new();
+    catalog.upsert(authority.origin().clone(), Box::new(Arc::new(authority)));
+
+    let localhost = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+    let socket = IoUringUdpSocket::from_std(std::net::UdpSocket::bind(localhost).unwrap()).unwrap();
+    let listener = IoUringTcpListener::bind(localhost).unwrap();
+    let udp_addr = socket.local_addr().unwrap();
+    let tcp_addr = listener.local_addr().unwrap();
+
+    let mut server = ServerFuture::new(catalog);
+    server.register_io_uring_socket(socket);
+    server.register_io_uring_listener(listener, Duration::from_secs(5));
+
+    let www = IpAddr::V4(Ipv4Addr::new(93, 184, 216, 34));
+    assert!(lookup(udp_addr, Protocol::Udp).await.contains(&www));
+    assert!(lookup(tcp_addr, Protocol::Tcp).await.contains(&www));
+
+    server.shutdown_gracefully().await.unwrap();
+}
@@ -2,7 +2,7 @@ use std::net::*;
 use std::pin::Pin;
 use std::str::FromStr;
 use std::sync::{
-    atomic::{AtomicIsize, Ordering},
+    atomic::{AtomicBool, AtomicIsize, Ordering},
     Arc,
 };
 use std::task::Poll;
@@ -14,7 +14,9 @@ use hickory_client::op::{Query, ResponseCode};
 use hickory_client::rr::{Name, RecordType};
 use hickory_integration::mock_client::*;
 use hickory_proto::error::{ProtoError, ProtoErrorKind};
-use hickory_proto::xfer::{DnsHandle, DnsResponse, FirstAnswer};
+use hickory_proto::xfer::{
+    DnsHandle, DnsRequest, DnsRequestOptions, DnsResponse, FirstAnswer, Transport,
+};
 use hickory_resolver::config::*;
 use hickory_resolver::name_server::{NameServer, NameServerPool};
 
@@ -83,9 +85,13 @@ fn mock_nameserver_on_send_nx<O: OnSend + Unpin>(
             protocol: Protocol::Udp,
             tls_dns_name: None,
             trust_negative_responses,
+            privacy: PrivacyPolicy::default(),
+            weight: 1,
             #[cfg(any(feature = "dns-over-rustls", feature = "dns-over-https-rustls"))]
             tls_config: None,
             bind_addr: None,
+            bind_device: None,
+            http: None,
         },
         options,
         client,
@@ -93,6 +99,24 @@ fn mock_nameserver_on_send_nx<O: OnSend + Unpin>(
     )
 }
 
+#[cfg(test)]
+fn mock_tcp_nameserver(
+    messages: Vec<Result<DnsResponse, ProtoError>>,
+    options: ResolverOpts,
+) -> MockedNameServer<DefaultOnSend> {
+    let conn_provider = MockConnProvider {
+        on_send: DefaultOnSend,
+    };
+    let client = MockClientHandle::mock_on_send(messages, DefaultOnSend);
+
+    NameServer::from_conn(
+        NameServerConfig::new(SocketAddr::new(DEFAULT_SERVER_ADDR, 0), Protocol::Tcp),
+        options,
+        client,
+        conn_provider,
+    )
+}
+
 #[cfg(test)]
 fn mock_nameserver_pool(
     udp: Vec<MockedNameServer<DefaultOnSend>>,
@@ -238,6 +262,58 @@ fn test_datagram_stream_upgrade_on_truncation_despite_udp() {
     assert_eq!(response.answers(), &[tcp_record1, tcp_record2]);
 }
 
+#[test]
+fn test_truncation_retry_reuses_stream_connection() {
+    // Truncated responses over UDP are retried over the same TCP connection, and each response
+    // records the transport which produced it.
+
+    let query = Query::query(Name::from_str("www.example.com.").unwrap(), RecordType::A);
+
+    let udp_record = v4_record(query.name().clone(), Ipv4Addr::new(127, 0, 0, 1));
+    let tcp_record = v4_record(query.name().clone(), Ipv4Addr::new(127, 0, 0, 2));
+
+    let mut truncated = message(query.clone(), vec![], vec![], vec![]);
+    truncated.set_truncated(true);
+    let udp_message = message(query.clone(), vec![udp_record.clone()], vec![], vec![]);
+    let tcp_message = message(query.clone(), vec![tcp_record.clone()], vec![], vec![]);
+
+    // messages are popped off the back
+    let udp_nameserver = mock_nameserver(
+        vec![
+            Ok(DnsResponse::from_message(udp_message).unwrap()),
+            Ok(DnsResponse::from_message(truncated.clone()).unwrap()),
+            Ok(DnsResponse::from_message(truncated).unwrap()),
+        ],
+        Default::default(),
+    );
+    let tcp_nameserver = mock_tcp_nameserver(
+        vec![
+            Ok(DnsResponse::from_message(tcp_message.clone()).unwrap()),
+            Ok(DnsResponse::from_message(tcp_message).unwrap()),
+        ],
+        Default::default(),
+    );
+
+    let pool = mock_nameserver_pool(
+        vec![udp_nameserver],
+        vec![tcp_nameserver],
+        None,
+        Default::default(),
+    );
+
+    for _ in 0..2 {
+        let request = message(query.clone(), vec![], vec![], vec![]);
+        let response = block_on(pool.send(request).first_answer()).unwrap();
+        assert_eq!(response.answers(), &[tcp_record.clone()]);
+        assert_eq!(response.transport(), Some(Transport::Tcp));
+    }
+
+    let request = message(query, vec![], vec![], vec![]);
+    let response = block_on(pool.send(request).first_answer()).unwrap();
+    assert_eq!(response.answers(), &[udp_record]);
+    assert_eq!(response.transport(), Some(Transport::Udp));
+}
+
 #[test]
 fn test_datagram_fails_to_stream() {
     // Lookup to UDP should fail, and then the query should be retried on TCP because
@@ -266,6 +342,48 @@ fn test_datagram_fails_to_stream() {
     assert_eq!(response.answers()[0], tcp_record);
 }
 
+#[test]
+fn test_conflicting_datagram_confirmed_over_stream() {
+    // Conflicting responses over UDP are retried on TCP, even though `try_tcp_on_error` is not
+    // set, and the other UDP name servers are not tried.
+
+    let query = Query::query(Name::from_str("www.example.com.").unwrap(), RecordType::A);
+
+    let udp_record = v4_record(query.name().clone(), Ipv4Addr::new(127, 0, 0, 1));
+    let tcp_record = v4_record(query.name().clone(), Ipv4Addr::new(127, 0, 0, 2));
+    let udp_message = message(query.clone(), vec![udp_record], vec![], vec![]);
+    let tcp_message = message(query.clone(), vec![tcp_record.clone()], vec![], vec![]);
+
+    let conflicting = Err(ProtoError::from(ProtoErrorKind::ConflictingResponses(
+        SocketAddr::new(DEFAULT_SERVER_ADDR, 53),
+    )));
+    let udp_nameserver1 = mock_nameserver(vec![conflicting], Default::default());
+    let udp_nameserver2 = mock_nameserver_with_addr(
+        vec![Ok(DnsResponse::from_message(udp_message).unwrap())],
+        Ipv4Addr::new(128, 0, 0, 1).into(),
+        Default::default(),
+    );
+    let tcp_nameserver = mock_nameserver(
+        vec![Ok(DnsResponse::from_message(tcp_message).unwrap())],
+        Default::default(),
+    );
+
+    let mut options = ResolverOpts::default();
+    options.num_concurrent_reqs = 1;
+    options.server_ordering_strategy = ServerOrderingStrategy::UserProvidedOrder;
+    let pool = mock_nameserver_pool(
+        vec![udp_nameserver1, udp_nameserver2],
+        vec![tcp_nameserver],
+        None,
+        options,
+    );
+
+    let request = message(query, vec![], vec![], vec![]);
+    let future = pool.send(request).first_answer();
+    let response = block_on(future).unwrap();
+    assert_eq!(response.answers(), &[tcp_record]);
+}
+
 #[test]
 fn test_tcp_fallback_only_on_truncated() {
     // Lookup to UDP should fail with an error, and the resolver should not then try the query over
@@ -401,6 +519,83 @@ fn test_trust_nx_responses_fails() {
     }
 }
 
+#[test]
+fn test_confirm_nx_responses() {
+    let query = Query::query(Name::from_str("www.example.").unwrap(), RecordType::A);
+
+    let soa_record = soa_record(
+        query.name().clone(),
+        Name::from_str("example.com.").unwrap(),
+    );
+    let mut nx_message = message(query.clone(), vec![], vec![soa_record], vec![]);
+    nx_message.set_response_code(ResponseCode::NXDomain);
+    let nx = || Ok(DnsResponse::from_message(nx_message.clone()).unwrap());
+
+    let v4_record = v4_record(query.name().clone(), Ipv4Addr::new(127, 0, 0, 2));
+    let success = Ok(DnsResponse::from_message(message(
+        query.clone(),
+        vec![v4_record.clone()],
+        vec![],
+        vec![],
+    ))
+    .unwrap());
+
+    let mut options = ResolverOpts::default();
+    options.num_concurrent_reqs = 1;
+    options.server_ordering_strategy = ServerOrderingStrategy::UserProvidedOrder;
+
+    let mut request_options = DnsRequestOptions::default();
+    request_options.confirm_nxdomain = true;
+    let request = || {
+        DnsRequest::new(
+            message(query.clone(), vec![], vec![], vec![]),
+            request_options,
+        )
+    };
+    let nx_trusted = |result: Result<DnsResponse, ProtoError>| match result.unwrap_err().kind() {
+        ProtoErrorKind::NoRecordsFound {
+            response_code,
+            trusted,
+            ..
+        } if *response_code == ResponseCode::NXDomain => *trusted,
+        kind => panic!("expected NXDOMAIN, got {:?}", kind),
+    };
+
+    // a NXDOMAIN contradicted by the second name server
+    let pool = mock_nameserver_pool(
+        vec![
+            mock_nameserver_trust_nx(vec![nx()], options.clone(), true),
+            mock_nameserver_trust_nx(vec![success], options.clone(), true),
+        ],
+        vec![],
+        None,
+        options.clone(),
+    );
+    let response = block_on(pool.send(request()).first_answer()).unwrap();
+    assert_eq!(response.answers(), [v4_record]);
+
+    // a NXDOMAIN confirmed by the second name server
+    let pool = mock_nameserver_pool(
+        vec![
+            mock_nameserver_trust_nx(vec![nx()], options.clone(), true),
+            mock_nameserver_trust_nx(vec![nx()], options.clone(), true),
+        ],
+        vec![],
+        None,
+        options.clone(),
+    );
+    assert!(nx_trusted(block_on(pool.send(request()).first_answer())));
+
+    // a NXDOMAIN which can't be confirmed is not trusted
+    let pool = mock_nameserver_pool(
+        vec![mock_nameserver_trust_nx(vec![nx()], options.clone(), true)],
+        vec![],
+        None,
+        options,
+    );
+    assert!(!nx_trusted(block_on(pool.send(request()).first_answer())));
+}
+
 #[test]
 fn test_noerror_doesnt_leak() {
     let query = Query::query(Name::from_str("www.example.com.").unwrap(), RecordType::A);
@@ -584,6 +779,56 @@ fn test_user_provided_server_order() {
         });
 }
 
+#[test]
+fn test_skip_servers() {
+    let mut options = ResolverOpts::default();
+    options.num_concurrent_reqs = 1;
+    options.server_ordering_strategy = ServerOrderingStrategy::UserProvidedOrder;
+
+    let query = Query::query(Name::from_str("www.example.com.").unwrap(), RecordType::A);
+    let response = |ip: Ipv4Addr| -> Vec<Result<DnsResponse, ProtoError>> {
+        let record = v4_record(query.name().clone(), ip);
+        vec![Ok(DnsResponse::from_message(message(
+            query.clone(),
+            vec![record],
+            vec![],
+            vec![],
+        ))
+        .unwrap())]
+    };
+
+    let preferred_nameserver = mock_nameserver_with_addr(
+        response(Ipv4Addr::new(127, 0, 0, 1)),
+        Ipv4Addr::new(128, 0, 0, 1).into(),
+        Default::default(),
+    );
+    let secondary_nameserver = mock_nameserver_with_addr(
+        response(Ipv4Addr::new(127, 0, 0, 2)),
+        Ipv4Addr::new(129, 0, 0, 1).into(),
+        Default::default(),
+    );
+    let pool = mock_nameserver_pool(
+        vec![preferred_nameserver, secondary_nameserver],
+        vec![],
+        None,
+        options,
+    );
+
+    // a retry on a different server starts with the secondary name server
+    let mut request_options = DnsRequestOptions::default();
+    request_options.skip_servers = 1;
+    let request = DnsRequest::new(
+        message(query.clone(), vec![], vec![], vec![]),
+        request_options,
+    );
+
+    let response = block_on(pool.send(request).first_answer()).unwrap();
+    assert_eq!(
+        response.answers()[0],
+        v4_record(query.name().clone(), Ipv4Addr::new(127, 0, 0, 2))
+    );
+}
+
 #[test]
 fn test_return_error_from_highest_priority_nameserver() {
     let query = Query::query(Name::from_str("www.example.").unwrap(), RecordType::A);
@@ -849,3 +1094,140 @@ fn test_concurrent_requests_0_conn() {
     let response = block_on(future).unwrap();
     assert_eq!(response.answers()[0], udp_record);
 }
+
+/// Answers with the response once recovered, until then the messages of the mock are returned
+#[derive(Clone)]
+struct Recovering {
+    recovered: Arc<AtomicBool>,
+    response: DnsResponse,
+}
+
+impl OnSend for Recovering {
+    fn on_send<E>(
+        &self,
+        response: Result<DnsResponse, E>,
+    ) -> Pin<Box<dyn Future<Output = Result<DnsResponse, E>> + Send>>
+    where
+        E: From<ProtoError> + Send + 'static,
+    {
+        if self.recovered.load(Ordering::Relaxed) {
+            return Box::pin(future::ok(self.response.clone()));
+        }
+
+        Box::pin(future::ready(response))
+    }
+}
+
+#[test]
+fn test_demote_failing_server() {
+    let mut options = ResolverOpts::default();
+    options.num_concurrent_reqs = 1;
+    options.server_ordering_strategy = ServerOrderingStrategy::UserProvidedOrder;
+    options.health.failure_threshold = 2;
+    options.health.backoff = std::time::Duration::from_secs(60);
+
+    let query = Query::query(Name::from_str("www.example.com.").unwrap(), RecordType::A);
+    let preferred_record = v4_record(query.name().clone(), Ipv4Addr::new(127, 0, 0, 1));
+    let secondary_record = v4_record(query.name().clone(), Ipv4Addr::new(127, 0, 0, 2));
+    let response = |record: &hickory_proto::rr::Record| {
+        DnsResponse::from_message(message(query.clone(), vec![record.clone()], vec![], vec![]))
+            .unwrap()
+    };
+
+    // the preferred server fails until it recovers
+    let recovered = Arc::new(AtomicBool::new(false));
+    let preferred_nameserver = mock_nameserver_on_send_nx(
+        vec![],
+        options.clone(),
+        Recovering {
+            recovered: recovered.clone(),
+            response: response(&preferred_record),
+        },
+        Ipv4Addr::new(128, 0, 0, 1).into(),
+        false,
+    );
+    let secondary_nameserver = mock_nameserver_on_send_nx(
+        vec![],
+        options.clone(),
+        Recovering {
+            recovered: Arc::new(AtomicBool::new(true)),
+            response: response(&secondary_record),
+        },
+        Ipv4Addr::new(129, 0, 0, 1).into(),
+        false,
+    );
+    let pool = mock_nameserver_pool_on_send(
+        vec![preferred_nameserver, secondary_nameserver],
+        vec![],
+        None,
+        options,
+    );
+    let lookup = || {
+        let request = message(query.clone(), vec![], vec![], vec![]);
+        block_on(pool.send(request).first_answer())
+            .unwrap()
+            .answers()[0]
+            .clone()
+    };
+
+    // the preferred server is demoted after the second failure, and is then tried last
+    for _ in 0..3 {
+        assert_eq!(lookup(), secondary_record);
+    }
+
+    let health = pool.health_stats();
+    assert_eq!(health[0].consecutive_failures, 2);
+    assert!(health[0].demoted_for.is_some());
+    assert_eq!(health[1].successes, 3);
+    assert_eq!(health[1].demoted_for, None);
+
+    // a successful probe promotes it again
+    recovered.store(true, Ordering::Relaxed);
+    block_on(pool.probe());
+    assert_eq!(pool.health_stats()[0].demoted_for, None);
+    assert_eq!(lookup(), preferred_record);
+}
+
+#[test]
+fn test_round_robin() {
+    let mut options = ResolverOpts::default();
+    options.num_concurrent_reqs = 1;
+    options.server_ordering_strategy = ServerOrderingStrategy::RoundRobin;
+
+    let query = Query::query(Name::from_str("www.example.com.").unwrap(), RecordType::A);
+    let records = [
+        v4_record(query.name().clone(), Ipv4Addr::new(127, 0, 0, 1)),
+        v4_record(query.name().clone(), Ipv4Addr::new(127, 0, 0, 2)),
+        v4_record(query.name().clone(), Ipv4Addr::new(127, 0, 0, 3)),
+    ];
+    let nameservers = records
+        .iter()
+        .zip(128..)
+        .map(|(record, first_octet)| {
+            mock_nameserver_on_send_nx(
+                vec![],
+                options.clone(),
+                Recovering {
+                    recovered: Arc::new(AtomicBool::new(true)),
+                    response: DnsResponse::from_message(message(
+                        query.clone(),
+                        vec![record.clone()],
+                        vec![],
+                        vec![],
+                    ))
+                    .unwrap(),
+                },
+                Ipv4Addr::new(first_octet, 0, 0, 1).into(),
+                false,
+            )
+        })
+        .collect();
+    let pool = mock_nameserver_pool_on_send(nameservers, vec![], None, options);
+
+    // each request starts with the next name server
+    for record in records.iter().cycle().take(7) {
+        let request = message(query.clone(), vec![], vec![], vec![]);
+        let response = block_on(pool.send(request).first_answer()).unwrap();
+        assert_eq!(&response.answers()[0], record);
+    }
+}
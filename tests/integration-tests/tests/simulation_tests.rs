@@ -0,0 +1,132 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use hickory_client::rr::{Name, RData, RecordType};
+use hickory_integration::example_authority::create_example;
+use hickory_proto::error::ProtoErrorKind;
+use hickory_proto::sim::SimNetwork;
+use hickory_resolver::config::{
+    NameServerConfig, NameServerConfigGroup, Protocol, ResolverConfig, ResolverOpts,
+};
+use hickory_resolver::error::ResolveErrorKind;
+use hickory_resolver::name_server::{SimConnectionProvider, SimRuntimeProvider};
+use hickory_resolver::AsyncResolver;
+use hickory_server::authority::{Authority, Catalog};
+use hickory_server::ServerFuture;
+
+const CLIENT: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+const SERVER: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 53));
+
+fn serve(network: &SimNetwork) -> ServerFuture<Catalog> {
+    let authority = create_example();
+    let mut catalog = Catalog::new();
+    catalog.upsert(authority.origin().clone(), Box::new(Arc::new(authority)));
+
+    let mut server = ServerFuture::new(catalog);
+    server.register_sim_socket(network.bind_udp(SocketAddr::new(SERVER, 53)).unwrap());
+    server.register_sim_listener(
+        network.listen_tcp(SocketAddr::new(SERVER, 53)).unwrap(),
+        Duration::from_secs(5),
+    );
+    server
+}
+
+fn resolver(
+    network: &SimNetwork,
+    name_servers: NameServerConfigGroup,
+) -> AsyncResolver<SimConnectionProvider> {
+    let mut options = ResolverOpts::default();
+    options.timeout = Duration::from_secs(2);
+    options.attempts = 3;
+    options.cache_size = 0;
+
+    AsyncResolver::new(
+        ResolverConfig::from_parts(None, vec![], name_servers),
+        options,
+        SimConnectionProvider::new(SimRuntimeProvider::new(network.clone(), CLIENT)),
+    )
+}
+
+fn www() -> Name {
+    Name::parse("www.example.com.", None).unwrap()
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_udp_round_trip() {
+    let network = SimNetwork::new();
+    network.set_latency(CLIENT, SERVER, Duration::from_millis(40));
+    let mut server = serve(&network);
+    let resolver = resolver(
+        &network,
+        NameServerConfigGroup::from_ips_clear(&[SERVER], 53, true),
+    );
+
+    let started = Instant::now();
+    let lookup = resolver.lookup(www(), RecordType::A).await.unwrap();
+    assert_eq!(
+        lookup.iter().next(),
+        Some(&RData::A(Ipv4Addr::new(93, 184, 216, 34).into()))
+    );
+    // the query and the response each cross the link once
+    assert_eq!(started.elapsed(), Duration::from_millis(80));
+
+    server.shutdown_gracefully().await.unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_tcp_round_trip() {
+    let network = SimNetwork::new();
+    network.set_latency(CLIENT, SERVER, Duration::from_millis(40));
+    let mut server = serve(&network);
+    let resolver = resolver(
+        &network,
+        vec![NameServerConfig::new(
+            SocketAddr::new(SERVER, 53),
+            Protocol::Tcp,
+        )]
+        .into(),
+    );
+
+    let started = Instant::now();
+    let lookup = resolver.lookup(www(), RecordType::A).await.unwrap();
+    assert_eq!(
+        lookup.iter().next(),
+        Some(&RData::A(Ipv4Addr::new(93, 184, 216, 34).into()))
+    );
+    // the connection takes a round trip, and the streams of a connection have no latency
+    assert_eq!(started.elapsed(), Duration::from_millis(80));
+
+    server.shutdown_gracefully().await.unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_partition_timeout() {
+    let network = SimNetwork::new();
+    network.set_latency(CLIENT, SERVER, Duration::from_millis(40));
+    let mut server = serve(&network);
+    let resolver = resolver(
+        &network,
+        NameServerConfigGroup::from_ips_clear(&[SERVER], 53, true),
+    );
+
+    network.partition(CLIENT, SERVER);
+    let started = Instant::now();
+    let error = resolver.lookup(www(), RecordType::A).await.unwrap_err();
+    assert!(
+        matches!(error.kind(), ResolveErrorKind::Proto(e) if matches!(e.kind(), ProtoErrorKind::Timeout)),
+        "unexpected error: {error}"
+    );
+    // the query and its 3 reattempts each time out after 2 seconds
+    assert_eq!(started.elapsed(), Duration::from_secs(8));
+
+    // and succeeds once the link is restored
+    network.heal(CLIENT, SERVER);
+    let started = Instant::now();
+    resolver.lookup(www(), RecordType::A).await.unwrap();
+    assert_eq!(started.elapsed(), Duration::from_millis(80));
+
+    server.shutdown_gracefully().await.unwrap();
+}
@@ -0,0 +1,56 @@
+#![cfg(feature = "smol-runtime")]
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::executor::block_on;
+use tokio::net::{TcpListener, UdpSocket};
+use tokio::runtime::Runtime;
+
+use hickory_resolver::config::{NameServerConfig, Protocol, ResolverConfig, ResolverOpts};
+use hickory_resolver::SmolAsyncResolver;
+use hickory_server::authority::{Authority, Catalog};
+use hickory_server::ServerFuture;
+
+use hickory_integration::example_authority::create_example;
+
+fn lookup(server: SocketAddr, protocol: Protocol) -> Vec<IpAddr> {
+    let mut config = ResolverConfig::new();
+    config.add_name_server(NameServerConfig::new(server, protocol));
+    let resolver = SmolAsyncResolver::smol(config, ResolverOpts::default());
+
+    // the lookup is driven by the reactor of smol, on an executor which is neither smol nor Tokio
+    block_on(resolver.lookup_ip("www.example.com."))
+        .unwrap()
+        .iter()
+        .collect()
+}
+
+#[test]
+fn test_lookup_without_tokio() {
+    let authority = create_example();
+    let mut catalog = Catalog::new();
+    catalog.upsert(authority.origin().clone(), Box::new(Arc::new(authority)));
+
+    // the server still runs on Tokio
+    let runtime = Runtime::new().unwrap();
+    let localhost = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+    let (mut server, udp_addr, tcp_addr) = runtime.block_on(async {
+        let socket = UdpSocket::bind(localhost).await.unwrap();
+        let listener = TcpListener::bind(localhost).await.unwrap();
+        let udp_addr = socket.local_addr().unwrap();
+        let tcp_addr = listener.local_addr().unwrap();
+
+        let mut server = ServerFuture::new(catalog);
+        server.register_socket(socket);
+        server.register_listener(listener, Duration::from_secs(5));
+        (server, udp_addr, tcp_addr)
+    });
+
+    let www = IpAddr::V4(Ipv4Addr::new(93, 184, 216, 34));
+    assert!(lookup(udp_addr, Protocol::Udp).contains(&www));
+    assert!(lookup(tcp_addr, Protocol::Tcp).contains(&www));
+
+    runtime.block_on(server.shutdown_gracefully()).unwrap();
+}
@@ -0,0 +1,93 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! The check-zone program
+
+// BINARY WARNINGS
+#![warn(
+    clippy::default_trait_access,
+    clippy::dbg_macro,
+    clippy::unimplemented,
+    missing_copy_implementations,
+    missing_docs,
+    non_snake_case,
+    non_upper_case_globals,
+    rust_2018_idioms,
+    unreachable_pub
+)]
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Parser;
+
+use hickory_proto::rr::Name;
+use hickory_server::store::file::{check_zone, Severity};
+
+/// Checks a zone file, like BIND's named-checkzone
+///
+/// The zone is parsed, then checked for a SOA and NS records at its apex, CNAME records with
+/// other data, missing glue, dangling targets, occluded data and, if it is signed, for the
+/// signatures and the NSEC or NSEC3 chain. The exit status is 1 if any error is found.
+#[derive(Debug, Parser)]
+#[clap(name = "check-zone", version)]
+struct Opts {
+    /// The origin of the zone, e.g. example.com.
+    origin: Name,
+
+    /// The zone file
+    zone_file: PathBuf,
+
+    /// Print the diagnostics as a JSON array
+    #[clap(long)]
+    json: bool,
+
+    /// Only print the diagnostics of at least this severity: info, warning or error
+    #[clap(short = 's', long, default_value = "info")]
+    severity: Severity,
+}
+
+/// Run the check-zone program
+pub fn main() -> ExitCode {
+    let opts = Opts::parse();
+
+    let mut origin = opts.origin;
+    origin.set_fqdn(true);
+
+    let diagnostics = check_zone(&origin, &opts.zone_file);
+    let count = |severity| {
+        diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    };
+    let (errors, warnings) = (count(Severity::Error), count(Severity::Warning));
+    let shown = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity >= opts.severity)
+        .collect::<Vec<_>>();
+
+    if opts.json {
+        match serde_json::to_string_pretty(&shown) {
+            Ok(json) => println!("{json}"),
+            Err(e) => {
+                eprintln!("failed to serialize the diagnostics: {e}");
+                return ExitCode::FAILURE;
+            }
+        }
+    } else {
+        for diagnostic in shown {
+            println!("{}: {diagnostic}", opts.zone_file.display());
+        }
+        println!("zone {origin}: {errors} errors, {warnings} warnings");
+    }
+
+    match errors {
+        0 => ExitCode::SUCCESS,
+        _ => ExitCode::FAILURE,
+    }
+}
@@ -20,7 +20,11 @@
     unreachable_pub
 )]
 
-use std::net::SocketAddr;
+mod query;
+mod shell;
+
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
 #[cfg(feature = "dns-over-rustls")]
 use std::{sync::Arc, time::SystemTime};
 
@@ -107,7 +111,7 @@ struct Opts {
     command: Command,
 }
 
-#[derive(Clone, Debug, ValueEnum)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, ValueEnum)]
 enum Protocol {
     Udp,
     Tcp,
@@ -117,6 +121,42 @@ enum Protocol {
     H3,
 }
 
+impl Protocol {
+    /// The ALPN code used unless one is supplied, see `Opts::alpn`
+    fn default_alpn(self) -> Option<&'static str> {
+        match self {
+            Self::Udp | Self::Tcp | Self::Tls => None,
+            Self::Https => Some("h2"),
+            Self::Quic => Some("doq"),
+            Self::H3 => Some("h3"),
+        }
+    }
+}
+
+/// A name server and the protocol used to connect to it
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Endpoint {
+    protocol: Protocol,
+    nameserver: SocketAddr,
+    tls_dns_name: Option<String>,
+    alpn: Option<String>,
+}
+
+impl std::fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let protocol = self
+            .protocol
+            .to_possible_value()
+            .map(|value| value.get_name().to_string())
+            .unwrap_or_default();
+        write!(f, "{protocol}:{}", self.nameserver)?;
+        if let Some(dns_name) = &self.tls_dns_name {
+            write!(f, " dns_name:{dns_name}")?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Subcommand)]
 enum Command {
     Query(QueryOpt),
@@ -125,21 +165,32 @@ enum Command {
     Append(AppendOpt),
     // CompareAndSwap(),
     DeleteRecord(DeleteRecordOpt),
+    Shell(ShellOpt),
     // DeleteRecordSet,
     // DeleteAll,
     // ZoneTransfer,
     // Raw?
 }
 
-/// Query a name server for the record of the given type
+/// Query a name server for the record of the given type, with the options and output of dig
+///
+/// The query options follow the name and the type, e.g. `+short`, `+noall +answer`, `+multiline`,
+/// `+tcp`, `+dnssec`, `+bufsize=4096`, `+subnet=192.0.2.0/24`, `+cookie`, `+nsid` and
+/// `+norecurse`, options are disabled with the `no` prefix.
 #[derive(Debug, Args)]
 struct QueryOpt {
-    /// Name of the record to query
-    name: Name,
-
-    /// Type of DNS record to notify
-    #[clap(name = "TYPE")]
-    ty: RecordType,
+    /// Reverse lookup, query the PTR record of the address
+    #[clap(short = 'x', long)]
+    reverse: Option<IpAddr>,
+
+    /// Batch mode, query each line of the file (`-` for stdin), a name with an optional type and
+    /// query options, the query options of the command line apply to all of them
+    #[clap(short = 'f', long = "file")]
+    batch: Option<PathBuf>,
+
+    /// The name of the record, the type of the record (defaults to A) and the query options
+    #[clap(name = "NAME [TYPE] [+OPTION]")]
+    args: Vec<String>,
 }
 
 /// Notify a nameserver that a record has been updated
@@ -212,6 +263,17 @@ struct DeleteRecordOpt {
     rdata: Vec<String>,
 }
 
+/// Start an interactive shell, reading commands from stdin
+///
+/// The connections to the name servers are kept open across commands, type `help` for the
+/// commands of the shell.
+#[derive(Debug, Args)]
+struct ShellOpt {
+    /// Append the commands and responses of the session to this file
+    #[clap(long)]
+    transcript: Option<PathBuf>,
+}
+
 /// Run the resolve program
 #[tokio::main]
 pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -232,63 +294,89 @@ pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     hickory_util::logger(env!("CARGO_BIN_NAME"), log_level);
 
-    // TODO: need to cleanup all of ClientHandle and the Client in general to make it dynamically usable.
-    match opts.protocol {
-        Protocol::Udp => udp(opts).await?,
-        Protocol::Tcp => tcp(opts).await?,
-        Protocol::Tls => tls(opts).await?,
-        Protocol::Https => https(opts).await?,
-        Protocol::Quic => quic(opts).await?,
-        Protocol::H3 => h3(opts).await?,
+    let endpoint = Endpoint {
+        protocol: opts.protocol,
+        nameserver: opts.nameserver,
+        tls_dns_name: opts.tls_dns_name,
+        alpn: opts.alpn,
     };
+    let verify = !opts.do_not_verify_nameserver_cert;
+
+    // the queries connect as needed, for `+tcp` or to retry truncated responses
+    if let Command::Query(opt) = opts.command {
+        return query::run(endpoint, verify, opts.class, opt).await;
+    }
+
+    // TODO: need to cleanup all of ClientHandle and the Client in general to make it dynamically usable.
+    println!("; using {endpoint}");
+    let client = connect(&endpoint, verify).await?;
+    match opts.command {
+        Command::Shell(opt) => {
+            shell::Shell::new(endpoint, verify, opts.class, client, opt.transcript)?
+                .run()
+                .await?
+        }
+        command => handle_request(opts.class, opts.zone, command, client).await?,
+    }
 
     Ok(())
 }
 
-async fn udp(opts: Opts) -> Result<(), Box<dyn std::error::Error>> {
-    let nameserver = opts.nameserver;
+/// Connects to the name server, the background task of the connection is spawned
+async fn connect(
+    endpoint: &Endpoint,
+    verify: bool,
+) -> Result<AsyncClient, Box<dyn std::error::Error>> {
+    match endpoint.protocol {
+        Protocol::Udp => udp(endpoint).await,
+        Protocol::Tcp => tcp(endpoint).await,
+        Protocol::Tls => tls(endpoint, verify).await,
+        Protocol::Https => https(endpoint, verify).await,
+        Protocol::Quic => quic(endpoint, verify).await,
+        Protocol::H3 => h3(endpoint, verify).await,
+    }
+}
+
+async fn udp(endpoint: &Endpoint) -> Result<AsyncClient, Box<dyn std::error::Error>> {
+    let nameserver = endpoint.nameserver;
 
-    println!("; using udp:{nameserver}");
     let stream = UdpClientStream::<UdpSocket>::new(nameserver);
     let (client, bg) = AsyncClient::connect(stream).await?;
-    let handle = tokio::spawn(bg);
-    handle_request(opts.class, opts.zone, opts.command, client).await?;
-    drop(handle);
+    tokio::spawn(bg);
 
-    Ok(())
+    Ok(client)
 }
 
-async fn tcp(opts: Opts) -> Result<(), Box<dyn std::error::Error>> {
-    let nameserver = opts.nameserver;
+async fn tcp(endpoint: &Endpoint) -> Result<AsyncClient, Box<dyn std::error::Error>> {
+    let nameserver = endpoint.nameserver;
 
-    println!("; using tcp:{nameserver}");
     let (stream, sender) = TcpClientStream::<AsyncIoTokioAsStd<TokioTcpStream>>::new(nameserver);
     let client = AsyncClient::new(stream, sender, None);
     let (client, bg) = client.await?;
+    tokio::spawn(bg);
 
-    let handle = tokio::spawn(bg);
-    handle_request(opts.class, opts.zone, opts.command, client).await?;
-    drop(handle);
-
-    Ok(())
+    Ok(client)
 }
 
 #[cfg(not(feature = "dns-over-rustls"))]
-async fn tls(_opts: Opts) -> Result<(), Box<dyn std::error::Error>> {
-    panic!("`dns-over-rustls` feature is required during compilation");
+async fn tls(
+    _endpoint: &Endpoint,
+    _verify: bool,
+) -> Result<AsyncClient, Box<dyn std::error::Error>> {
+    Err("`dns-over-rustls` feature is required during compilation".into())
 }
 
 #[cfg(feature = "dns-over-rustls")]
-async fn tls(opts: Opts) -> Result<(), Box<dyn std::error::Error>> {
-    let nameserver = opts.nameserver;
-    let alpn = opts.alpn.map(String::into_bytes);
-    let dns_name = opts
+async fn tls(endpoint: &Endpoint, verify: bool) -> Result<AsyncClient, Box<dyn std::error::Error>> {
+    let nameserver = endpoint.nameserver;
+    let alpn = endpoint.alpn.clone().map(String::into_bytes);
+    let dns_name = endpoint
         .tls_dns_name
-        .expect("tls_dns_name is required tls connections");
-    println!("; using tls:{nameserver} dns_name:{dns_name}");
+        .clone()
+        .ok_or("tls_dns_name is required tls connections")?;
 
     let mut config = tls_config()?;
-    if opts.do_not_verify_nameserver_cert {
+    if !verify {
         self::do_not_verify_nameserver_cert(&mut config);
     }
     if let Some(alpn) = alpn {
@@ -299,35 +387,39 @@ async fn tls(opts: Opts) -> Result<(), Box<dyn std::error::Error>> {
     let (stream, sender) =
         tls_client_connect::<AsyncIoTokioAsStd<TokioTcpStream>>(nameserver, dns_name, config);
     let (client, bg) = AsyncClient::new(stream, sender, None).await?;
+    tokio::spawn(bg);
 
-    let handle = tokio::spawn(bg);
-    handle_request(opts.class, opts.zone, opts.command, client).await?;
-    drop(handle);
-
-    Ok(())
+    Ok(client)
 }
 
 #[cfg(not(feature = "dns-over-https"))]
-async fn https(_opts: Opts) -> Result<(), Box<dyn std::error::Error>> {
-    panic!("`dns-over-https` feature is required during compilation");
+async fn https(
+    _endpoint: &Endpoint,
+    _verify: bool,
+) -> Result<AsyncClient, Box<dyn std::error::Error>> {
+    Err("`dns-over-https` feature is required during compilation".into())
 }
 
 #[cfg(feature = "dns-over-https")]
-async fn https(opts: Opts) -> Result<(), Box<dyn std::error::Error>> {
+async fn https(
+    endpoint: &Endpoint,
+    verify: bool,
+) -> Result<AsyncClient, Box<dyn std::error::Error>> {
     use hickory_proto::h2::HttpsClientStreamBuilder;
 
-    let nameserver = opts.nameserver;
-    let alpn = opts
+    let nameserver = endpoint.nameserver;
+    let alpn = endpoint
         .alpn
+        .clone()
         .map(String::into_bytes)
-        .expect("ALPN is required for HTTPS");
-    let dns_name = opts
+        .ok_or("ALPN is required for HTTPS")?;
+    let dns_name = endpoint
         .tls_dns_name
-        .expect("tls_dns_name is required https connections");
-    println!("; using https:{nameserver} dns_name:{dns_name}");
+        .clone()
+        .ok_or("tls_dns_name is required https connections")?;
 
     let mut config = tls_config()?;
-    if opts.do_not_verify_nameserver_cert {
+    if !verify {
         self::do_not_verify_nameserver_cert(&mut config);
     }
     config.alpn_protocols.push(alpn);
@@ -338,35 +430,39 @@ async fn https(opts: Opts) -> Result<(), Box<dyn std::error::Error>> {
         https_builder.build::<AsyncIoTokioAsStd<TokioTcpStream>>(nameserver, dns_name),
     )
     .await?;
+    tokio::spawn(bg);
 
-    let handle = tokio::spawn(bg);
-    handle_request(opts.class, opts.zone, opts.command, client).await?;
-    drop(handle);
-
-    Ok(())
+    Ok(client)
 }
 
 #[cfg(not(feature = "dns-over-quic"))]
-async fn quic(_opts: Opts) -> Result<(), Box<dyn std::error::Error>> {
-    panic!("`dns-over-quic` feature is required during compilation");
+async fn quic(
+    _endpoint: &Endpoint,
+    _verify: bool,
+) -> Result<AsyncClient, Box<dyn std::error::Error>> {
+    Err("`dns-over-quic` feature is required during compilation".into())
 }
 
 #[cfg(feature = "dns-over-quic")]
-async fn quic(opts: Opts) -> Result<(), Box<dyn std::error::Error>> {
+async fn quic(
+    endpoint: &Endpoint,
+    verify: bool,
+) -> Result<AsyncClient, Box<dyn std::error::Error>> {
     use hickory_proto::quic::{self, QuicClientStream};
 
-    let nameserver = opts.nameserver;
-    let alpn = opts
+    let nameserver = endpoint.nameserver;
+    let alpn = endpoint
         .alpn
+        .clone()
         .map(String::into_bytes)
-        .expect("ALPN is required for QUIC");
-    let dns_name = opts
+        .ok_or("ALPN is required for QUIC")?;
+    let dns_name = endpoint
         .tls_dns_name
-        .expect("tls_dns_name is required quic connections");
-    println!("; using quic:{nameserver} dns_name:{dns_name}");
+        .clone()
+        .ok_or("tls_dns_name is required quic connections")?;
 
     let mut config = quic::client_config_tls13()?;
-    if opts.do_not_verify_nameserver_cert {
+    if !verify {
         self::do_not_verify_nameserver_cert(&mut config);
     }
     config.alpn_protocols.push(alpn);
@@ -374,35 +470,36 @@ async fn quic(opts: Opts) -> Result<(), Box<dyn std::error::Error>> {
     let mut quic_builder = QuicClientStream::builder();
     quic_builder.crypto_config(config);
     let (client, bg) = AsyncClient::connect(quic_builder.build(nameserver, dns_name)).await?;
+    tokio::spawn(bg);
 
-    let handle = tokio::spawn(bg);
-    handle_request(opts.class, opts.zone, opts.command, client).await?;
-    drop(handle);
-
-    Ok(())
+    Ok(client)
 }
 
 #[cfg(not(feature = "dns-over-h3"))]
-async fn h3(_opts: Opts) -> Result<(), Box<dyn std::error::Error>> {
-    panic!("`dns-over-h3` feature is required during compilation");
+async fn h3(
+    _endpoint: &Endpoint,
+    _verify: bool,
+) -> Result<AsyncClient, Box<dyn std::error::Error>> {
+    Err("`dns-over-h3` feature is required during compilation".into())
 }
 
 #[cfg(feature = "dns-over-h3")]
-async fn h3(opts: Opts) -> Result<(), Box<dyn std::error::Error>> {
+async fn h3(endpoint: &Endpoint, verify: bool) -> Result<AsyncClient, Box<dyn std::error::Error>> {
     use hickory_proto::h3::{self, H3ClientStream};
 
-    let nameserver = opts.nameserver;
-    let alpn = opts
+    let nameserver = endpoint.nameserver;
+    let alpn = endpoint
         .alpn
+        .clone()
         .map(String::into_bytes)
-        .expect("ALPN is required for H3");
-    let dns_name = opts
+        .ok_or("ALPN is required for H3")?;
+    let dns_name = endpoint
         .tls_dns_name
-        .expect("tls_dns_name is required H3 connections");
-    println!("; using h3:{nameserver} dns_name:{dns_name}");
+        .clone()
+        .ok_or("tls_dns_name is required H3 connections")?;
 
     let mut config = h3::client_config_tls13()?;
-    if opts.do_not_verify_nameserver_cert {
+    if !verify {
         self::do_not_verify_nameserver_cert(&mut config);
     }
     config.alpn_protocols.push(alpn);
@@ -410,12 +507,9 @@ async fn h3(opts: Opts) -> Result<(), Box<dyn std::error::Error>> {
     let mut h3_builder = H3ClientStream::builder();
     h3_builder.crypto_config(config);
     let (client, bg) = AsyncClient::connect(h3_builder.build(nameserver, dns_name)).await?;
+    tokio::spawn(bg);
 
-    let handle = tokio::spawn(bg);
-    handle_request(opts.class, opts.zone, opts.command, client).await?;
-    drop(handle);
-
-    Ok(())
+    Ok(client)
 }
 
 async fn handle_request(
@@ -425,12 +519,7 @@ async fn handle_request(
     mut client: impl ClientHandle,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let response = match command {
-        Command::Query(query) => {
-            let name = query.name;
-            let ty = query.ty;
-            println!("; sending query: {name} {class} {ty}");
-            client.query(name, class, ty).await?
-        }
+        Command::Query(_) => unreachable!("the queries are sent by the query module"),
         Command::Notify(opt) => {
             let name = opt.name;
             let ty = opt.ty;
@@ -485,6 +574,7 @@ async fn handle_request(
             println!("; sending delete-record: {name} {class} {ty} from {zone}");
             client.delete_by_rdata(rdata, zone).await?
         }
+        Command::Shell(_) => unreachable!("the shell does not send a single request"),
     };
 
     let response = response.into_message();
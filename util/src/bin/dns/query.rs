@@ -0,0 +1,902 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Queries with dig compatible options and output, see `QueryOpt`
+
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Write;
+use std::hash::{BuildHasher, Hasher};
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+use data_encoding::HEXLOWER_PERMISSIVE;
+
+use hickory_client::{
+    client::AsyncClient,
+    op::{Edns, Message, MessageType, OpCode, Query, ResponseCode},
+    rr::{DNSClass, Name, RData, Record, RecordType},
+};
+use hickory_proto::{
+    rr::rdata::opt::{ClientSubnet, EdnsCode, EdnsOption},
+    xfer::{DnsHandle, DnsRequest, DnsRequestOptions, FirstAnswer},
+};
+
+use super::{connect, Endpoint, Protocol, QueryOpt};
+
+/// The maximum UDP payload advertised in EDNS unless `+bufsize` is given
+const DEFAULT_BUFSIZE: u16 = 1232;
+
+/// The width of the base64 and hex fields of `+multiline` records
+const MULTILINE_WIDTH: usize = 44;
+
+/// The options of a query, set with `+option` and cleared with `+nooption` like in dig
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct QueryOptions {
+    tcp: bool,
+    recurse: bool,
+    dnssec: bool,
+    bufsize: u16,
+    subnet: Option<ClientSubnet>,
+    /// The client cookie, it is generated when empty
+    cookie: Option<Vec<u8>>,
+    nsid: bool,
+    short: bool,
+    multiline: bool,
+    cmd: bool,
+    comments: bool,
+    question: bool,
+    answer: bool,
+    authority: bool,
+    additional: bool,
+    stats: bool,
+}
+
+impl Default for QueryOptions {
+    fn default() -> Self {
+        Self {
+            tcp: false,
+            recurse: true,
+            dnssec: false,
+            bufsize: DEFAULT_BUFSIZE,
+            subnet: None,
+            cookie: None,
+            nsid: false,
+            short: false,
+            multiline: false,
+            cmd: true,
+            comments: true,
+            question: true,
+            answer: true,
+            authority: true,
+            additional: true,
+            stats: true,
+        }
+    }
+}
+
+impl QueryOptions {
+    /// Applies an option such as `+tcp`, `+nodnssec` or `+bufsize=4096`
+    fn set(&mut self, option: &str) -> Result<(), Box<dyn Error>> {
+        let option = option
+            .strip_prefix('+')
+            .ok_or_else(|| format!("query options start with `+`: {option}"))?;
+        let (option, value) = match option.split_once('=') {
+            Some((option, value)) => (option, Some(value)),
+            None => (option, None),
+        };
+        let (enable, name) = match option.strip_prefix("no") {
+            Some(name) => (false, name),
+            None => (true, option),
+        };
+
+        let flag = match name {
+            "tcp" | "vc" => &mut self.tcp,
+            "recurse" | "rec" => &mut self.recurse,
+            "dnssec" => &mut self.dnssec,
+            "nsid" => &mut self.nsid,
+            "short" => &mut self.short,
+            "multiline" | "multi" => &mut self.multiline,
+            "cmd" => &mut self.cmd,
+            "comments" => &mut self.comments,
+            "question" => &mut self.question,
+            "answer" => &mut self.answer,
+            "authority" => &mut self.authority,
+            "additional" => &mut self.additional,
+            "stats" => &mut self.stats,
+            "all" => {
+                no_value(option, value)?;
+                self.cmd = enable;
+                self.comments = enable;
+                self.question = enable;
+                self.answer = enable;
+                self.authority = enable;
+                self.additional = enable;
+                self.stats = enable;
+                return Ok(());
+            }
+            "bufsize" => {
+                self.bufsize = match (enable, value) {
+                    (true, Some(value)) => value.parse()?,
+                    (true, None) => {
+                        return Err("+bufsize requires a value, e.g. +bufsize=4096".into())
+                    }
+                    (false, _) => DEFAULT_BUFSIZE,
+                };
+                return Ok(());
+            }
+            "subnet" => {
+                self.subnet = match (enable, value) {
+                    (true, Some(value)) => Some(parse_subnet(value)?),
+                    (true, None) => {
+                        return Err("+subnet requires a value, e.g. +subnet=192.0.2.0/24".into())
+                    }
+                    (false, _) => None,
+                };
+                return Ok(());
+            }
+            "cookie" => {
+                self.cookie = match (enable, value) {
+                    (true, Some(value)) => Some(HEXLOWER_PERMISSIVE.decode(value.as_bytes())?),
+                    (true, None) => Some(Vec::new()),
+                    (false, _) => None,
+                };
+                return Ok(());
+            }
+            _ => return Err(format!("unknown query option: +{option}").into()),
+        };
+
+        no_value(option, value)?;
+        *flag = enable;
+        Ok(())
+    }
+}
+
+fn no_value(option: &str, value: Option<&str>) -> Result<(), Box<dyn Error>> {
+    match value {
+        Some(_) => Err(format!("+{option} does not take a value").into()),
+        None => Ok(()),
+    }
+}
+
+/// Parses a client subnet, the prefix defaults to the full address
+fn parse_subnet(subnet: &str) -> Result<ClientSubnet, Box<dyn Error>> {
+    if subnet.contains('/') {
+        return Ok(ClientSubnet::from_str(subnet)?);
+    }
+
+    let address = IpAddr::from_str(subnet)?;
+    let prefix = if address.is_ipv4() { 32 } else { 128 };
+    Ok(ClientSubnet::new(address, prefix, 0))
+}
+
+/// A query from the command line or from a line of the batch file
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Question {
+    name: Name,
+    ty: RecordType,
+    options: QueryOptions,
+}
+
+impl Question {
+    /// Parses the name, the type, `-x ADDR` and the query options
+    ///
+    /// Without a name the root name servers are queried, like dig does.
+    fn parse(
+        words: &[&str],
+        reverse: Option<IpAddr>,
+        options: &QueryOptions,
+    ) -> Result<(Option<Self>, QueryOptions), Box<dyn Error>> {
+        let mut options = options.clone();
+        let mut name = reverse.map(Name::from);
+        let mut default_ty = match reverse {
+            Some(_) => RecordType::PTR,
+            None => RecordType::A,
+        };
+        let mut ty = None;
+
+        let mut words = words.iter();
+        while let Some(&word) = words.next() {
+            if word.starts_with('+') {
+                options.set(word)?;
+            } else if word == "-x" {
+                let address = words.next().ok_or("-x requires an address")?;
+                name = Some(IpAddr::from_str(address)?.into());
+                default_ty = RecordType::PTR;
+            } else if name.is_none() {
+                name = Some(parse_name(word)?);
+            } else if ty.is_none() {
+                ty = Some(RecordType::from_str(&word.to_uppercase())?);
+            } else {
+                return Err(format!("unexpected argument: {word}").into());
+            }
+        }
+
+        let question = name.map(|name| Self {
+            name,
+            ty: ty.unwrap_or(default_ty),
+            options: options.clone(),
+        });
+        Ok((question, options))
+    }
+
+    /// The query of the root name servers
+    fn root(options: QueryOptions) -> Self {
+        Self {
+            name: Name::root(),
+            ty: RecordType::NS,
+            options,
+        }
+    }
+}
+
+/// Parses a name, which is always fully qualified like in dig
+fn parse_name(name: &str) -> Result<Name, Box<dyn Error>> {
+    let mut name = Name::from_utf8(name)?;
+    name.set_fqdn(true);
+    Ok(name)
+}
+
+/// Runs the query of the command line, or each query of the batch file
+pub(super) async fn run(
+    endpoint: Endpoint,
+    verify: bool,
+    class: DNSClass,
+    opt: QueryOpt,
+) -> Result<(), Box<dyn Error>> {
+    let words = opt.args.iter().map(String::as_str).collect::<Vec<_>>();
+    let (question, options) = Question::parse(&words, opt.reverse, &QueryOptions::default())?;
+
+    let mut questions = Vec::new();
+    match opt.batch {
+        None => questions.push(question.unwrap_or_else(|| Question::root(options))),
+        Some(path) => {
+            questions.extend(question);
+
+            let batch = if path.as_os_str() == "-" {
+                std::io::read_to_string(std::io::stdin())?
+            } else {
+                std::fs::read_to_string(&path)?
+            };
+            for (number, line) in batch.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+                    continue;
+                }
+
+                let words = line.split_whitespace().collect::<Vec<_>>();
+                let (question, options) = Question::parse(&words, None, &options)
+                    .map_err(|e| format!("{}:{}: {e}", path.display(), number + 1))?;
+                questions.push(question.unwrap_or_else(|| Question::root(options)));
+            }
+        }
+    }
+
+    let mut dig = Dig {
+        endpoint,
+        verify,
+        class,
+        clients: HashMap::new(),
+        cookie: RandomState::new().build_hasher().finish().to_be_bytes(),
+    };
+
+    let mut failures = 0;
+    for question in &questions {
+        if let Err(e) = dig.query(question).await {
+            eprintln!(";; {} {} failed: {e}", question.name, question.ty);
+            failures += 1;
+        }
+    }
+
+    match failures {
+        0 => Ok(()),
+        _ => Err(format!("{failures} of {} queries failed", questions.len()).into()),
+    }
+}
+
+/// The connections and the client cookie shared by the queries of a run
+struct Dig {
+    endpoint: Endpoint,
+    verify: bool,
+    class: DNSClass,
+    clients: HashMap<Protocol, AsyncClient>,
+    cookie: [u8; 8],
+}
+
+impl Dig {
+    /// Sends the query, over TCP again if the UDP response is truncated, and prints the response
+    async fn query(&mut self, question: &Question) -> Result<(), Box<dyn Error>> {
+        let options = &question.options;
+        let message = self.message(question);
+
+        let mut protocol = match self.endpoint.protocol {
+            Protocol::Udp if options.tcp => Protocol::Tcp,
+            protocol => protocol,
+        };
+        let started = Instant::now();
+        let (mut response, mut size) = self.send(protocol, message.clone()).await?;
+
+        if response.truncated() && protocol == Protocol::Udp {
+            if options.comments && !options.short {
+                println!(";; Truncated, retrying in TCP mode.");
+            }
+            protocol = Protocol::Tcp;
+            (response, size) = self.send(protocol, message).await?;
+        }
+
+        let server = Server {
+            protocol,
+            endpoint: &self.endpoint,
+            elapsed: started.elapsed(),
+            size,
+        };
+        print!("{}", format_response(question, &response, &server));
+        Ok(())
+    }
+
+    fn message(&self, question: &Question) -> Message {
+        let options = &question.options;
+        let mut query = Query::query(question.name.clone(), question.ty);
+        query.set_query_class(self.class);
+
+        let mut message = Message::new();
+        message
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Query)
+            .set_recursion_desired(options.recurse)
+            .add_query(query);
+
+        let mut edns = Edns::new();
+        edns.set_max_payload(options.bufsize);
+        edns.set_dnssec_ok(options.dnssec);
+        if let Some(subnet) = options.subnet {
+            edns.options_mut().insert(EdnsOption::Subnet(subnet));
+        }
+        if let Some(cookie) = &options.cookie {
+            let cookie = match cookie.is_empty() {
+                true => self.cookie.to_vec(),
+                false => cookie.clone(),
+            };
+            edns.options_mut()
+                .insert(EdnsOption::Unknown(EdnsCode::Cookie.into(), cookie));
+        }
+        if options.nsid {
+            edns.options_mut()
+                .insert(EdnsOption::Unknown(EdnsCode::NSID.into(), Vec::new()));
+        }
+        message.set_edns(edns);
+
+        message
+    }
+
+    /// Sends the message using the protocol, returns the response and its size on the wire
+    async fn send(
+        &mut self,
+        protocol: Protocol,
+        message: Message,
+    ) -> Result<(Message, usize), Box<dyn Error>> {
+        let client = match self.clients.get(&protocol) {
+            Some(client) => client.clone(),
+            None => {
+                let endpoint = Endpoint {
+                    protocol,
+                    ..self.endpoint.clone()
+                };
+                let client = connect(&endpoint, self.verify).await?;
+                self.clients.insert(protocol, client.clone());
+                client
+            }
+        };
+
+        let (response, buffer) = client
+            .send(DnsRequest::new(message, DnsRequestOptions::default()))
+            .first_answer()
+            .await?
+            .into_parts();
+        Ok((response, buffer.len()))
+    }
+}
+
+/// How a response was received, for the statistics of the output
+struct Server<'a> {
+    protocol: Protocol,
+    endpoint: &'a Endpoint,
+    elapsed: Duration,
+    size: usize,
+}
+
+/// Formats the response like dig, following the output options of the question
+fn format_response(question: &Question, response: &Message, server: &Server<'_>) -> String {
+    let options = &question.options;
+    let mut output = String::new();
+
+    if options.short {
+        for data in response.answers().iter().filter_map(Record::data) {
+            writeln!(output, "{data}").expect("writing to a String never fails");
+        }
+        return output;
+    }
+
+    // the sections are followed by a blank line, and titled unless the comments are disabled
+    let mut section = |title: Option<&str>, lines: &mut dyn Iterator<Item = String>| {
+        let mut lines = lines.peekable();
+        if lines.peek().is_none() {
+            return;
+        }
+        if let (true, Some(title)) = (options.comments, title) {
+            output.push_str(&format!(";; {title}:\n"));
+        }
+        for line in lines {
+            output.push_str(&line);
+            output.push('\n');
+        }
+        if options.comments {
+            output.push('\n');
+        }
+    };
+
+    if options.cmd {
+        section(
+            None,
+            &mut std::iter::once(format!(
+                "; <<>> {} {} <<>> {} {}",
+                env!("CARGO_BIN_NAME"),
+                env!("CARGO_PKG_VERSION"),
+                question.name,
+                question.ty,
+            )),
+        );
+    }
+    if options.comments {
+        section(None, &mut header(response).into_iter());
+        if let Some(edns) = response.extensions() {
+            section(
+                Some("OPT PSEUDOSECTION"),
+                &mut pseudo_section(edns).into_iter(),
+            );
+        }
+    }
+    if options.question {
+        section(
+            Some("QUESTION SECTION"),
+            &mut response.queries().iter().map(|query| {
+                format!(
+                    ";{}\t\t{}\t{}",
+                    query.name(),
+                    query.query_class(),
+                    query.query_type()
+                )
+            }),
+        );
+    }
+
+    let records = |records: &[Record]| {
+        records
+            .iter()
+            .map(|record| format_record(record, options.multiline))
+            .collect::<Vec<_>>()
+    };
+    if options.answer {
+        section(
+            Some("ANSWER SECTION"),
+            &mut records(response.answers()).into_iter(),
+        );
+    }
+    if options.authority {
+        section(
+            Some("AUTHORITY SECTION"),
+            &mut records(response.name_servers()).into_iter(),
+        );
+    }
+    if options.additional {
+        section(
+            Some("ADDITIONAL SECTION"),
+            &mut records(response.additionals()).into_iter(),
+        );
+    }
+
+    if options.stats {
+        let address = server.endpoint.nameserver;
+        let protocol = server
+            .protocol
+            .to_possible_value()
+            .map(|value| value.get_name().to_uppercase())
+            .unwrap_or_default();
+        section(
+            None,
+            &mut [
+                format!(";; Query time: {} msec", server.elapsed.as_millis()),
+                format!(
+                    ";; SERVER: {}#{}({}) ({protocol})",
+                    address.ip(),
+                    address.port(),
+                    address.ip()
+                ),
+                format!(";; MSG SIZE  rcvd: {}", server.size),
+            ]
+            .into_iter(),
+        );
+    }
+
+    output
+}
+
+/// The header comments, e.g. `;; ->>HEADER<<- opcode: QUERY, status: NOERROR, id: 1234`
+fn header(response: &Message) -> Vec<String> {
+    let header = response.header();
+    let flags = [
+        (header.message_type() == MessageType::Response, "qr"),
+        (header.authoritative(), "aa"),
+        (header.truncated(), "tc"),
+        (header.recursion_desired(), "rd"),
+        (header.recursion_available(), "ra"),
+        (header.authentic_data(), "ad"),
+        (header.checking_disabled(), "cd"),
+    ]
+    .iter()
+    .filter_map(|&(set, flag)| set.then_some(flag))
+    .collect::<Vec<_>>()
+    .join(" ");
+
+    vec![
+        ";; Got answer:".to_string(),
+        format!(
+            ";; ->>HEADER<<- opcode: {}, status: {}, id: {}",
+            header.op_code(),
+            response_code(response.response_code()),
+            header.id()
+        ),
+        format!(
+            ";; flags: {flags}; QUERY: {}, ANSWER: {}, AUTHORITY: {}, ADDITIONAL: {}",
+            header.query_count(),
+            header.answer_count(),
+            header.name_server_count(),
+            header.additional_count()
+        ),
+    ]
+}
+
+/// The mnemonic of the response code used by dig
+fn response_code(code: ResponseCode) -> String {
+    let mnemonic = match code {
+        ResponseCode::NoError => "NOERROR",
+        ResponseCode::FormErr => "FORMERR",
+        ResponseCode::ServFail => "SERVFAIL",
+        ResponseCode::NXDomain => "NXDOMAIN",
+        ResponseCode::NotImp => "NOTIMP",
+        ResponseCode::Refused => "REFUSED",
+        ResponseCode::YXDomain => "YXDOMAIN",
+        ResponseCode::YXRRSet => "YXRRSET",
+        ResponseCode::NXRRSet => "NXRRSET",
+        ResponseCode::NotAuth => "NOTAUTH",
+        ResponseCode::NotZone => "NOTZONE",
+        ResponseCode::BADVERS => "BADVERS",
+        ResponseCode::BADSIG => "BADSIG",
+        ResponseCode::BADKEY => "BADKEY",
+        ResponseCode::BADTIME => "BADTIME",
+        ResponseCode::BADMODE => "BADMODE",
+        ResponseCode::BADNAME => "BADNAME",
+        ResponseCode::BADALG => "BADALG",
+        ResponseCode::BADTRUNC => "BADTRUNC",
+        ResponseCode::BADCOOKIE => "BADCOOKIE",
+        ResponseCode::Unknown(code) => return format!("RESERVED{code}"),
+    };
+    mnemonic.to_string()
+}
+
+/// The EDNS comments, with the client subnet, cookie and NSID options
+fn pseudo_section(edns: &Edns) -> Vec<String> {
+    let flags = if edns.dnssec_ok() { " do" } else { "" };
+    let mut lines = vec![format!(
+        "; EDNS: version: {}, flags:{flags}; udp: {}",
+        edns.version(),
+        edns.max_payload()
+    )];
+
+    for (code, option) in edns.options().as_ref() {
+        let line = match (code, option) {
+            (_, EdnsOption::Subnet(subnet)) => format!(
+                "; CLIENT-SUBNET: {}/{}/{}",
+                subnet.addr(),
+                subnet.source_prefix(),
+                subnet.scope_prefix()
+            ),
+            (EdnsCode::Cookie, EdnsOption::Unknown(_, cookie)) => {
+                format!("; COOKIE: {}", HEXLOWER_PERMISSIVE.encode(cookie))
+            }
+            (EdnsCode::NSID, EdnsOption::Unknown(_, nsid)) => {
+                let hex = nsid
+                    .iter()
+                    .map(|byte| format!("{byte:02x}"))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let text = nsid
+                    .iter()
+                    .map(|&byte| match byte {
+                        0x20..=0x7e => byte as char,
+                        _ => '.',
+                    })
+                    .collect::<String>();
+                format!("; NSID: {hex} (\"{text}\")")
+            }
+            (code, EdnsOption::Unknown(_, data)) => format!(
+                "; OPT={}: {}",
+                u16::from(*code),
+                HEXLOWER_PERMISSIVE.encode(data)
+            ),
+            #[allow(unreachable_patterns)]
+            (code, _) => format!("; OPT={}", u16::from(*code)),
+        };
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// Formats a record like dig, with `+multiline` the long record data spans several lines
+fn format_record(record: &Record, multiline: bool) -> String {
+    let prefix = format!(
+        "{}\t{}\t{}\t{}",
+        record.name(),
+        record.ttl(),
+        record.dns_class(),
+        record.record_type()
+    );
+    let Some(data) = record.data() else {
+        return prefix;
+    };
+    if !multiline {
+        return format!("{prefix}\t{data}");
+    }
+
+    if let RData::SOA(soa) = data {
+        let interval = |seconds: i32| u32::try_from(seconds).unwrap_or_default();
+        return format!(
+            "{prefix}\t{} {} (\n\
+             \t\t\t\t{:<10} ; serial\n\
+             \t\t\t\t{:<10} ; refresh ({})\n\
+             \t\t\t\t{:<10} ; retry ({})\n\
+             \t\t\t\t{:<10} ; expire ({})\n\
+             \t\t\t\t{:<10} ; minimum ({})\n\
+             \t\t\t\t)",
+            soa.mname(),
+            soa.rname(),
+            soa.serial(),
+            soa.refresh(),
+            duration(interval(soa.refresh())),
+            soa.retry(),
+            duration(interval(soa.retry())),
+            soa.expire(),
+            duration(interval(soa.expire())),
+            soa.minimum(),
+            duration(soa.minimum()),
+        );
+    }
+
+    let text = data.to_string();
+    let wrapped = matches!(
+        record.record_type(),
+        RecordType::CDNSKEY
+            | RecordType::CDS
+            | RecordType::DNSKEY
+            | RecordType::DS
+            | RecordType::KEY
+            | RecordType::NSEC3
+            | RecordType::OPENPGPKEY
+            | RecordType::RRSIG
+            | RecordType::SIG
+            | RecordType::SSHFP
+            | RecordType::TLSA
+    );
+    if !wrapped || text.len() <= MULTILINE_WIDTH {
+        return format!("{prefix}\t{text}");
+    }
+
+    // the base64 and hex fields may be split, names and numbers are kept whole
+    let mut lines = vec![String::new()];
+    for word in text.split_whitespace() {
+        let chunks = match word.contains('.') {
+            true => vec![word],
+            false => word
+                .as_bytes()
+                .chunks(MULTILINE_WIDTH)
+                .map(|chunk| std::str::from_utf8(chunk).expect("base64 and hex are ascii"))
+                .collect(),
+        };
+        for chunk in chunks {
+            let line = lines.last_mut().expect("there is always a line");
+            if !line.is_empty() && line.len() + 1 + chunk.len() > MULTILINE_WIDTH {
+                lines.push(chunk.to_string());
+            } else {
+                if !line.is_empty() {
+                    line.push(' ');
+                }
+                line.push_str(chunk);
+            }
+        }
+    }
+
+    let mut output = format!("{prefix}\t(");
+    for line in lines {
+        write!(output, "\n\t\t\t\t{line}").expect("writing to a String never fails");
+    }
+    output.push_str(" )");
+    output
+}
+
+/// A duration in the format of dig, e.g. `1 week 3 days`
+fn duration(seconds: u32) -> String {
+    const UNITS: [(&str, u32); 5] = [
+        ("week", 7 * 24 * 3600),
+        ("day", 24 * 3600),
+        ("hour", 3600),
+        ("minute", 60),
+        ("second", 1),
+    ];
+
+    let mut remaining = seconds;
+    let mut parts = Vec::new();
+    for (unit, size) in UNITS {
+        let count = remaining / size;
+        remaining %= size;
+        match count {
+            0 => (),
+            1 => parts.push(format!("1 {unit}")),
+            _ => parts.push(format!("{count} {unit}s")),
+        }
+    }
+
+    match parts.is_empty() {
+        true => "0 seconds".to_string(),
+        false => parts.join(" "),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, SocketAddr};
+
+    use hickory_client::rr::rdata::{A, SOA};
+
+    use super::*;
+
+    fn parse(words: &[&str]) -> Question {
+        Question::parse(words, None, &QueryOptions::default())
+            .unwrap()
+            .0
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_question() {
+        let question = parse(&["www.example.com", "aaaa", "+short", "+tcp"]);
+        assert_eq!(question.name, Name::from_ascii("www.example.com.").unwrap());
+        assert_eq!(question.ty, RecordType::AAAA);
+        assert!(question.options.short);
+        assert!(question.options.tcp);
+
+        let question = parse(&["-x", "192.0.2.1"]);
+        assert_eq!(
+            question.name,
+            Name::from_ascii("1.2.0.192.in-addr.arpa.").unwrap()
+        );
+        assert_eq!(question.ty, RecordType::PTR);
+
+        let (question, options) =
+            Question::parse(&["+noall", "+answer"], None, &QueryOptions::default()).unwrap();
+        assert!(question.is_none());
+        assert!(options.answer);
+        assert!(!options.question && !options.comments && !options.stats);
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let mut options = QueryOptions::default();
+        options.set("+bufsize=4096").unwrap();
+        options.set("+subnet=192.0.2.0/24").unwrap();
+        options.set("+cookie=0102030405060708").unwrap();
+        options.set("+norec").unwrap();
+        assert_eq!(options.bufsize, 4096);
+        assert_eq!(
+            options.subnet,
+            Some(ClientSubnet::new(Ipv4Addr::new(192, 0, 2, 0).into(), 24, 0))
+        );
+        assert_eq!(options.cookie, Some(vec![1, 2, 3, 4, 5, 6, 7, 8]));
+        assert!(!options.recurse);
+
+        options.set("+subnet=192.0.2.1").unwrap();
+        assert_eq!(options.subnet.unwrap().source_prefix(), 32);
+
+        assert!(options.set("+bufsize").is_err());
+        assert!(options.set("+tcp=yes").is_err());
+        assert!(options.set("+unknown").is_err());
+    }
+
+    #[test]
+    fn test_format_response() {
+        let name = Name::from_ascii("www.example.com.").unwrap();
+        let mut response = Message::new();
+        response
+            .set_message_type(MessageType::Response)
+            .add_query(Query::query(name.clone(), RecordType::A))
+            .add_answer(Record::from_rdata(
+                name.clone(),
+                300,
+                RData::A(A::new(192, 0, 2, 1)),
+            ));
+        let endpoint = Endpoint {
+            protocol: Protocol::Udp,
+            nameserver: SocketAddr::from(([192, 0, 2, 53], 53)),
+            tls_dns_name: None,
+            alpn: None,
+        };
+        let server = Server {
+            protocol: Protocol::Udp,
+            endpoint: &endpoint,
+            elapsed: Duration::from_millis(12),
+            size: 49,
+        };
+
+        let question = parse(&["www.example.com", "+short"]);
+        assert_eq!(
+            format_response(&question, &response, &server),
+            "192.0.2.1\n"
+        );
+
+        let question = parse(&["www.example.com", "+noall", "+answer"]);
+        assert_eq!(
+            format_response(&question, &response, &server),
+            "www.example.com.\t300\tIN\tA\t192.0.2.1\n"
+        );
+
+        let question = parse(&["www.example.com", "+nocmd", "+nocomments"]);
+        assert_eq!(
+            format_response(&question, &response, &server),
+            ";www.example.com.\t\tIN\tA\n\
+             www.example.com.\t300\tIN\tA\t192.0.2.1\n\
+             ;; Query time: 12 msec\n\
+             ;; SERVER: 192.0.2.53#53(192.0.2.53) (UDP)\n\
+             ;; MSG SIZE  rcvd: 49\n"
+        );
+    }
+
+    #[test]
+    fn test_multiline_soa() {
+        let name = Name::from_ascii("example.com.").unwrap();
+        let soa = SOA::new(
+            Name::from_ascii("ns.example.com.").unwrap(),
+            Name::from_ascii("hostmaster.example.com.").unwrap(),
+            2023010101,
+            7200,
+            3600,
+            1209600,
+            3600,
+        );
+        let record = Record::from_rdata(name, 3600, RData::SOA(soa));
+
+        assert_eq!(
+            format_record(&record, true),
+            "example.com.\t3600\tIN\tSOA\tns.example.com. hostmaster.example.com. (\n\
+             \t\t\t\t2023010101 ; serial\n\
+             \t\t\t\t7200       ; refresh (2 hours)\n\
+             \t\t\t\t3600       ; retry (1 hour)\n\
+             \t\t\t\t1209600    ; expire (2 weeks)\n\
+             \t\t\t\t3600       ; minimum (1 hour)\n\
+             \t\t\t\t)"
+        );
+    }
+
+    #[test]
+    fn test_duration() {
+        assert_eq!(duration(0), "0 seconds");
+        assert_eq!(duration(90), "1 minute 30 seconds");
+        assert_eq!(duration(864000), "1 week 3 days");
+    }
+}
@@ -0,0 +1,350 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! The interactive shell of the dns client, see `ShellOpt`
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use clap::ValueEnum;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use hickory_client::{
+    client::AsyncClient,
+    op::{Edns, Message, MessageType, OpCode, Query},
+    rr::{DNSClass, Name, Record, RecordType},
+};
+use hickory_proto::xfer::{DnsHandle, DnsRequest, DnsRequestOptions, FirstAnswer};
+
+use super::{connect, Endpoint, Protocol};
+
+const HELP: &str = "\
+; query NAME [TYPE]              query the name server, TYPE defaults to A
+; dnssec NAME [TYPE]             query with DNSSEC OK, then show the DS, DNSKEY and RRSIG
+;                                  records of each zone from NAME up to the root
+; use PROTOCOL [ADDR [DNS_NAME]] switch the protocol or name server, connections are kept open
+; class CLASS                    set the class of the queries
+; set VAR VALUE...               set a variable, `$VAR` is replaced by its value in commands
+; unset VAR                      remove a variable
+; vars                           list the variables, `$_` is the first answer of the last query
+; transcript [FILE|off]          append the commands and responses to FILE, or stop
+; help                           show this help
+; quit                           leave the shell";
+
+/// The state of an interactive session
+pub(super) struct Shell {
+    endpoint: Endpoint,
+    verify: bool,
+    class: DNSClass,
+    clients: HashMap<Endpoint, AsyncClient>,
+    variables: BTreeMap<String, String>,
+    transcript: Option<File>,
+}
+
+impl Shell {
+    /// Creates a shell using the connection to the endpoint
+    pub(super) fn new(
+        endpoint: Endpoint,
+        verify: bool,
+        class: DNSClass,
+        client: AsyncClient,
+        transcript: Option<PathBuf>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut clients = HashMap::new();
+        clients.insert(endpoint.clone(), client);
+
+        let mut shell = Self {
+            endpoint,
+            verify,
+            class,
+            clients,
+            variables: BTreeMap::new(),
+            transcript: None,
+        };
+        if let Some(path) = transcript {
+            shell.set_transcript(&path.to_string_lossy())?;
+        }
+
+        Ok(shell)
+    }
+
+    /// Reads and executes commands from stdin, until `quit` or the end of the input
+    pub(super) async fn run(mut self) -> Result<(), Box<dyn Error>> {
+        println!("; type `help` for the list of commands");
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+
+        loop {
+            print!("{}> ", self.endpoint);
+            std::io::stdout().flush()?;
+
+            let Some(line) = lines.next_line().await? else {
+                println!();
+                return Ok(());
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+
+            self.record(&format!("; {}> {line}", self.endpoint));
+            match self.execute(line).await {
+                Ok(true) => continue,
+                Ok(false) => return Ok(()),
+                Err(e) => self.output(&format!("; error: {e}")),
+            }
+        }
+    }
+
+    /// Executes one command
+    ///
+    /// # Returns
+    ///
+    /// `false` if the shell must be left
+    async fn execute(&mut self, line: &str) -> Result<bool, Box<dyn Error>> {
+        let words = self.expand(line)?;
+        let (command, args) = words.split_first().ok_or("empty command")?;
+        let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+
+        match (command.as_str(), args.as_slice()) {
+            ("query" | "q", [name]) => self.query(parse_name(name)?, RecordType::A).await?,
+            ("query" | "q", [name, ty]) => {
+                self.query(parse_name(name)?, RecordType::from_str(ty)?)
+                    .await?
+            }
+            ("dnssec", [name]) => self.dnssec(parse_name(name)?, RecordType::A).await?,
+            ("dnssec", [name, ty]) => {
+                self.dnssec(parse_name(name)?, RecordType::from_str(ty)?)
+                    .await?
+            }
+            ("use", [protocol, rest @ ..]) if rest.len() <= 2 => {
+                self.use_endpoint(protocol, rest).await?
+            }
+            ("class", [class]) => self.class = DNSClass::from_str(class)?,
+            ("set", [var, value @ ..]) if !value.is_empty() => {
+                self.variables.insert(var.to_string(), value.join(" "));
+            }
+            ("unset", [var]) => {
+                self.variables.remove(*var);
+            }
+            ("vars", []) => {
+                let vars = self
+                    .variables
+                    .iter()
+                    .map(|(var, value)| format!("; {var} = {value}"))
+                    .collect::<Vec<_>>();
+                self.output(&vars.join("\n"));
+            }
+            ("transcript", []) => match &self.transcript {
+                Some(_) => self.output("; transcript enabled"),
+                None => self.output("; transcript disabled"),
+            },
+            ("transcript", ["off"]) => self.transcript = None,
+            ("transcript", [path]) => self.set_transcript(path)?,
+            ("help", []) => println!("{HELP}"),
+            ("quit" | "exit", []) => return Ok(false),
+            _ => return Err(format!("invalid command: {line}, see `help`").into()),
+        }
+
+        Ok(true)
+    }
+
+    /// Splits the line into words, replacing the variables by their value
+    fn expand(&self, line: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        line.split_whitespace()
+            .map(|word| match word.strip_prefix('$') {
+                Some(var) => self
+                    .variables
+                    .get(var)
+                    .cloned()
+                    .ok_or_else(|| format!("undefined variable: ${var}").into()),
+                None => Ok(word.to_string()),
+            })
+            .collect()
+    }
+
+    async fn query(&mut self, name: Name, ty: RecordType) -> Result<(), Box<dyn Error>> {
+        self.output(&format!("; sending query: {name} {} {ty}", self.class));
+        let response = self.send(name, ty, false).await?;
+        self.output(&format!("; received response\n{response}"));
+        Ok(())
+    }
+
+    /// Queries the record with its signatures, then the keys of each zone up to the root
+    async fn dnssec(&mut self, name: Name, ty: RecordType) -> Result<(), Box<dyn Error>> {
+        self.output(&format!(
+            "; sending dnssec query: {name} {} {ty}",
+            self.class
+        ));
+        let response = self.send(name.clone(), ty, true).await?;
+        self.output(&format!(
+            "; {} with {} answers",
+            response.response_code(),
+            response.answer_count()
+        ));
+        self.show_records(response.answers());
+
+        let mut zone = name;
+        loop {
+            let keys = self.send(zone.clone(), RecordType::DNSKEY, true).await?;
+            let is_apex = keys
+                .answers()
+                .iter()
+                .any(|record| record.name() == &zone && record.record_type() == RecordType::DNSKEY);
+
+            if is_apex {
+                self.output(&format!(";; zone {zone}"));
+                self.show_records(keys.answers());
+
+                if !zone.is_root() {
+                    let delegation = self.send(zone.clone(), RecordType::DS, true).await?;
+                    if delegation.answers().is_empty() {
+                        self.output("; no DS records, the zone is not signed by its parent");
+                    }
+                    self.show_records(delegation.answers());
+                }
+            }
+
+            if zone.is_root() {
+                return Ok(());
+            }
+            zone = zone.base_name();
+        }
+    }
+
+    /// Prints the records, with the key tags of the DNSKEY records
+    fn show_records(&mut self, records: &[Record]) {
+        for record in records {
+            #[cfg(any(feature = "dnssec-openssl", feature = "dnssec-ring"))]
+            {
+                use hickory_proto::rr::{dnssec::rdata::DNSSECRData, RData};
+
+                if let Some(RData::DNSSEC(DNSSECRData::DNSKEY(key))) = record.data() {
+                    if let Ok(tag) = key.calculate_key_tag() {
+                        let sep = if key.secure_entry_point() { " SEP" } else { "" };
+                        self.output(&format!("; key tag {tag}{sep}"));
+                    }
+                }
+            }
+            self.output(&record.to_string());
+        }
+    }
+
+    /// Sends a recursive query to the current name server
+    async fn send(
+        &mut self,
+        name: Name,
+        ty: RecordType,
+        dnssec_ok: bool,
+    ) -> Result<Message, Box<dyn Error>> {
+        let mut query = Query::query(name, ty);
+        query.set_query_class(self.class);
+
+        let mut message = Message::new();
+        message
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Query)
+            .set_recursion_desired(true)
+            .add_query(query);
+
+        let mut edns = Edns::new();
+        edns.set_max_payload(1232);
+        edns.set_dnssec_ok(dnssec_ok);
+        message.set_edns(edns);
+
+        let client = self.client().await?;
+        let response = client
+            .send(DnsRequest::new(message, DnsRequestOptions::default()))
+            .first_answer()
+            .await?
+            .into_message();
+
+        if let Some(data) = response.answers().first().and_then(Record::data) {
+            self.variables.insert("_".to_string(), data.to_string());
+        }
+        Ok(response)
+    }
+
+    /// Switches to another protocol or name server, the TLS name defaults to the current one
+    async fn use_endpoint(&mut self, protocol: &str, rest: &[&str]) -> Result<(), Box<dyn Error>> {
+        let protocol = Protocol::from_str(protocol, true)?;
+        let nameserver = match rest.first() {
+            Some(nameserver) => nameserver.parse()?,
+            None => self.endpoint.nameserver,
+        };
+        let tls_dns_name = rest
+            .get(1)
+            .map(ToString::to_string)
+            .or_else(|| self.endpoint.tls_dns_name.clone());
+        let alpn = if protocol == self.endpoint.protocol {
+            self.endpoint.alpn.clone()
+        } else {
+            protocol.default_alpn().map(str::to_string)
+        };
+
+        let endpoint = Endpoint {
+            protocol,
+            nameserver,
+            tls_dns_name,
+            alpn,
+        };
+        let previous = std::mem::replace(&mut self.endpoint, endpoint);
+        if let Err(e) = self.client().await {
+            self.endpoint = previous;
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    /// The connection to the current endpoint, which is established if necessary
+    async fn client(&mut self) -> Result<AsyncClient, Box<dyn Error>> {
+        if let Some(client) = self.clients.get(&self.endpoint) {
+            return Ok(client.clone());
+        }
+
+        println!("; using {}", self.endpoint);
+        let client = connect(&self.endpoint, self.verify).await?;
+        self.clients.insert(self.endpoint.clone(), client.clone());
+        Ok(client)
+    }
+
+    fn set_transcript(&mut self, path: &str) -> Result<(), Box<dyn Error>> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        self.transcript = Some(file);
+        println!("; appending the transcript to {path}");
+        Ok(())
+    }
+
+    /// Prints the text and appends it to the transcript
+    fn output(&mut self, text: &str) {
+        println!("{text}");
+        self.record(text);
+    }
+
+    /// Appends the text to the transcript, which is disabled if it can not be written
+    fn record(&mut self, text: &str) {
+        let Some(file) = &mut self.transcript else {
+            return;
+        };
+
+        if let Err(e) = writeln!(file, "{text}") {
+            println!("; error writing the transcript, disabling it: {e}");
+            self.transcript = None;
+        }
+    }
+}
+
+/// Parses a name, which is always fully qualified in the shell
+fn parse_name(name: &str) -> Result<Name, Box<dyn Error>> {
+    let mut name = Name::from_utf8(name)?;
+    name.set_fqdn(true);
+    Ok(name)
+}
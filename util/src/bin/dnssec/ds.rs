@@ -0,0 +1,115 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! The DS records of keys, see `DsOpt`
+
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use clap::{Args, ValueEnum};
+
+use hickory_proto::rr::{
+    dnssec::{
+        bind_key,
+        rdata::{DNSSECRData, DNSKEY},
+    },
+    Name, RData, Record,
+};
+
+use super::Digest;
+
+/// Prints the DS records of keys for the parent zone, like BIND's dnssec-dsfromkey
+///
+/// The keys are read from the `.key` files written by `keygen`, the records can also be printed
+/// as CDS and CDNSKEY records, which are published in the zone itself for the parent to pick up
+/// (RFC 7344).
+#[derive(Debug, Args)]
+pub(crate) struct DsOpt {
+    /// The `.key` files of the keys
+    #[clap(required = true)]
+    key_files: Vec<PathBuf>,
+
+    /// The digests of the DS and CDS records
+    #[clap(
+        short = 'd',
+        long = "digest",
+        value_enum,
+        value_delimiter = ',',
+        default_value = "sha256"
+    )]
+    digests: Vec<Digest>,
+
+    /// The types of the records: ds, cds or cdnskey
+    #[clap(
+        short = 'T',
+        long = "type",
+        value_enum,
+        value_delimiter = ',',
+        default_value = "ds"
+    )]
+    types: Vec<DsType>,
+
+    /// The TTL of the records
+    #[clap(long, default_value_t = 3600)]
+    ttl: u32,
+}
+
+/// The types of the printed records
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum DsType {
+    Ds,
+    Cds,
+    Cdnskey,
+}
+
+pub(crate) fn run(opt: DsOpt) -> Result<(), Box<dyn Error>> {
+    let mut keys = Vec::with_capacity(opt.key_files.len());
+    for path in &opt.key_files {
+        let key_file = fs::read_to_string(path)
+            .map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+        let key = bind_key::decode_public_key(&key_file)
+            .map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+        keys.push(key);
+    }
+
+    for record in records(&keys, &opt)? {
+        println!("{record}");
+    }
+    Ok(())
+}
+
+fn records(keys: &[(Name, DNSKEY)], opt: &DsOpt) -> Result<Vec<Record>, Box<dyn Error>> {
+    let mut records = Vec::new();
+    for ty in &opt.types {
+        for (name, dnskey) in keys {
+            if *ty == DsType::Cdnskey {
+                let rdata = DNSSECRData::CDNSKEY(dnskey.clone().into());
+                records.push(Record::from_rdata(
+                    name.clone(),
+                    opt.ttl,
+                    RData::DNSSEC(rdata),
+                ));
+                continue;
+            }
+
+            for digest in &opt.digests {
+                let ds = dnskey.to_ds(name, (*digest).into())?;
+                let rdata = match ty {
+                    DsType::Cds => DNSSECRData::CDS(ds.into()),
+                    _ => DNSSECRData::DS(ds),
+                };
+                records.push(Record::from_rdata(
+                    name.clone(),
+                    opt.ttl,
+                    RData::DNSSEC(rdata),
+                ));
+            }
+        }
+    }
+    Ok(records)
+}
@@ -0,0 +1,176 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Generation of keys, see `KeygenOpt`
+
+use std::error::Error;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use clap::{Args, ValueEnum};
+
+use hickory_proto::rr::{
+    dnssec::{
+        bind_key::{self, BindPrivateKey},
+        rdata::DNSKEY,
+        Algorithm, KeyFormat, KeyPair,
+    },
+    Name,
+};
+
+use super::KeyAlgorithm;
+
+/// Generates a key pair, like BIND's dnssec-keygen
+///
+/// The DNSKEY record is written to `K<origin>+<algorithm>+<key tag>.key` and the private key to
+/// `K<origin>+<algorithm>+<key tag>.private`, in the format of BIND, or to `.pk8`. The name of
+/// the private key file is printed, which is accepted by `sign-zone` and the server config.
+#[derive(Debug, Args)]
+pub(crate) struct KeygenOpt {
+    /// The origin of the zone, e.g. example.com.
+    origin: Name,
+
+    /// The algorithm of the key
+    #[clap(short = 'a', long, value_enum, default_value = "ecdsap256sha256")]
+    algorithm: KeyAlgorithm,
+
+    /// The size of the key in bits, only RSA keys have a choice, 2048 by default
+    #[clap(short = 'b', long)]
+    bits: Option<u32>,
+
+    /// Generate a key signing key, with the SEP flag
+    #[clap(short = 'f', long)]
+    ksk: bool,
+
+    /// The directory of the key files
+    #[clap(short = 'K', long, default_value = ".")]
+    key_directory: PathBuf,
+
+    /// The format of the private key file
+    #[clap(long, value_enum, default_value = "bind")]
+    format: PrivateKeyFormat,
+}
+
+/// The formats of the private key file
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum PrivateKeyFormat {
+    /// `.private`, the format of BIND
+    Bind,
+    /// `.pk8`, PKCS#8, not for RSA keys
+    Pkcs8,
+}
+
+pub(crate) fn run(opt: KeygenOpt) -> Result<(), Box<dyn Error>> {
+    let mut origin = opt.origin;
+    origin.set_fqdn(true);
+
+    let path = generate(
+        &origin,
+        opt.algorithm.into(),
+        opt.bits,
+        opt.ksk,
+        &opt.key_directory,
+        opt.format,
+    )?;
+    println!("{}", path.display());
+    Ok(())
+}
+
+/// Generates the key and writes the key files, returns the path of the private key file
+pub(crate) fn generate(
+    origin: &Name,
+    algorithm: Algorithm,
+    bits: Option<u32>,
+    is_key_signing_key: bool,
+    key_directory: &Path,
+    format: PrivateKeyFormat,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let size = match algorithm {
+        Algorithm::ECDSAP256SHA256 | Algorithm::ED25519 => Some(256),
+        Algorithm::ECDSAP384SHA384 => Some(384),
+        _ => None,
+    };
+    match (size, bits) {
+        (Some(size), Some(bits)) if size != bits => {
+            return Err(format!("{} keys have {size} bits", algorithm.as_str()).into())
+        }
+        (None, Some(bits)) if !(1024..=4096).contains(&bits) => {
+            return Err("RSA keys have 1024 to 4096 bits".into())
+        }
+        _ => (),
+    }
+
+    let (key, private_key) = match algorithm {
+        #[cfg(feature = "dnssec-openssl")]
+        Algorithm::RSASHA256 | Algorithm::RSASHA512 => {
+            if format == PrivateKeyFormat::Pkcs8 {
+                return Err("RSA keys are only written in the BIND format".into());
+            }
+            let key = KeyPair::generate_rsa(bits.unwrap_or(2048))?;
+            let private_key = BindPrivateKey::from_key_pair(algorithm, &key)?.to_string();
+            (key, private_key.into_bytes())
+        }
+        _ => {
+            let pkcs8 = KeyPair::generate_pkcs8(algorithm)?;
+            let key = KeyFormat::Pkcs8.decode_key(&pkcs8, None, algorithm)?;
+            let private_key = match format {
+                PrivateKeyFormat::Bind => BindPrivateKey::from_pkcs8(algorithm, &pkcs8)?
+                    .to_string()
+                    .into_bytes(),
+                PrivateKeyFormat::Pkcs8 => pkcs8,
+            };
+            (key, private_key)
+        }
+    };
+
+    let dnskey = DNSKEY::new(
+        true,
+        is_key_signing_key,
+        false,
+        algorithm,
+        key.to_public_bytes()?,
+    );
+    // the name ends with the key tag, which is not an extension
+    let name = format!(
+        "K{origin}+{:03}+{:05}",
+        u8::from(algorithm),
+        dnskey.calculate_key_tag()?
+    );
+    let extension = match format {
+        PrivateKeyFormat::Bind => "private",
+        PrivateKeyFormat::Pkcs8 => "pk8",
+    };
+
+    let private_key_path = key_directory.join(format!("{name}.{extension}"));
+    write_new(&private_key_path, &private_key, true)?;
+    write_new(
+        &key_directory.join(format!("{name}.key")),
+        bind_key::encode_public_key(origin, &dnskey)?.as_bytes(),
+        false,
+    )?;
+
+    Ok(private_key_path)
+}
+
+/// Writes a new file, only readable by the owner if it is private
+fn write_new(path: &Path, contents: &[u8], private: bool) -> Result<(), Box<dyn Error>> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    #[cfg(not(unix))]
+    let _ = private;
+
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(contents))
+        .map_err(|e| format!("failed to write {}: {e}", path.display()).into())
+}
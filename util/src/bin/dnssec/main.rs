@@ -0,0 +1,112 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! The dnssec program
+
+// BINARY WARNINGS
+#![warn(
+    clippy::default_trait_access,
+    clippy::dbg_macro,
+    clippy::unimplemented,
+    missing_copy_implementations,
+    missing_docs,
+    non_snake_case,
+    non_upper_case_globals,
+    rust_2018_idioms,
+    unreachable_pub
+)]
+
+mod ds;
+mod keygen;
+mod sign_zone;
+
+use std::error::Error;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use tracing::Level;
+
+use hickory_proto::rr::dnssec::{Algorithm, DigestType};
+
+use ds::DsOpt;
+use keygen::KeygenOpt;
+use sign_zone::SignZoneOpt;
+
+/// Manages the DNSSEC keys and signatures of zones
+#[derive(Debug, Parser)]
+#[clap(name = "dnssec", version)]
+struct Opts {
+    /// Enable debug and all logging
+    #[clap(long, global = true)]
+    debug: bool,
+
+    /// Command to execute
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    Keygen(KeygenOpt),
+    Ds(DsOpt),
+    SignZone(SignZoneOpt),
+}
+
+/// The algorithms of the keys
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum KeyAlgorithm {
+    #[cfg(feature = "dnssec-openssl")]
+    Rsasha256,
+    #[cfg(feature = "dnssec-openssl")]
+    Rsasha512,
+    Ecdsap256sha256,
+    Ecdsap384sha384,
+    Ed25519,
+}
+
+impl From<KeyAlgorithm> for Algorithm {
+    fn from(algorithm: KeyAlgorithm) -> Self {
+        match algorithm {
+            #[cfg(feature = "dnssec-openssl")]
+            KeyAlgorithm::Rsasha256 => Self::RSASHA256,
+            #[cfg(feature = "dnssec-openssl")]
+            KeyAlgorithm::Rsasha512 => Self::RSASHA512,
+            KeyAlgorithm::Ecdsap256sha256 => Self::ECDSAP256SHA256,
+            KeyAlgorithm::Ecdsap384sha384 => Self::ECDSAP384SHA384,
+            KeyAlgorithm::Ed25519 => Self::ED25519,
+        }
+    }
+}
+
+/// The digests of the DS records
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Digest {
+    Sha256,
+    Sha384,
+}
+
+impl From<Digest> for DigestType {
+    fn from(digest: Digest) -> Self {
+        match digest {
+            Digest::Sha256 => Self::SHA256,
+            Digest::Sha384 => Self::SHA384,
+        }
+    }
+}
+
+/// Run the dnssec program
+pub fn main() -> Result<(), Box<dyn Error>> {
+    let opts = Opts::parse();
+
+    let log_level = if opts.debug { Some(Level::DEBUG) } else { None };
+    hickory_util::logger(env!("CARGO_BIN_NAME"), log_level);
+
+    match opts.command {
+        Command::Keygen(opt) => keygen::run(opt),
+        Command::Ds(opt) => ds::run(opt),
+        Command::SignZone(opt) => sign_zone::run(opt),
+    }
+}
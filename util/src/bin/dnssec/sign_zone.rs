@@ -0,0 +1,270 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Signing of zone files, see `SignZoneOpt`
+
+use std::error::Error;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use data_encoding::HEXLOWER_PERMISSIVE;
+use tracing::info;
+
+use hickory_proto::rr::{
+    dnssec::{bind_key::BindPrivateKey, rdata::DNSSECRData, Algorithm},
+    Name, RData, Record, RecordType, RrKey,
+};
+use hickory_server::{
+    config::dnssec::KeyConfig,
+    store::file::{sign_zone, Denial, SignOptions, ZoneKey},
+};
+
+use super::keygen::{self, PrivateKeyFormat};
+use super::{Digest, KeyAlgorithm};
+
+/// Signs a zone file, like BIND's dnssec-signzone
+///
+/// The DNSKEY records of the keys are added to the zone, the NSEC or NSEC3 chain is generated
+/// and the RRsets are signed. The signatures of a zone which is already signed are kept unless
+/// they are about to expire, so only the changed RRsets are signed again. The DS records of the
+/// key signing keys are written for the parent zone.
+#[derive(Debug, Args)]
+pub(crate) struct SignZoneOpt {
+    /// The origin of the zone, e.g. example.com.
+    origin: Name,
+
+    /// The zone file
+    zone_file: PathBuf,
+
+    /// A private key file of a zone signing key, the format is given by the extension: .private
+    /// (BIND, with the .key file next to it), .pk8, .der or .pem
+    #[clap(short = 'k', long = "key")]
+    keys: Vec<PathBuf>,
+
+    /// A private key file of a key signing key, see `--key`
+    #[clap(long = "ksk")]
+    key_signing_keys: Vec<PathBuf>,
+
+    /// The algorithm of the keys, the .private files include their algorithm
+    #[clap(short = 'a', long, value_enum, default_value = "ecdsap256sha256")]
+    algorithm: KeyAlgorithm,
+
+    /// The password of the private key files
+    #[clap(long)]
+    password: Option<String>,
+
+    /// Generate a key signing key and a zone signing key, written to the key directory like
+    /// `keygen` does
+    #[clap(long)]
+    generate: bool,
+
+    /// The directory of the generated keys
+    #[clap(short = 'K', long, default_value = ".")]
+    key_directory: PathBuf,
+
+    /// Use NSEC3 instead of NSEC records
+    #[clap(long)]
+    nsec3: bool,
+
+    /// The additional iterations of the NSEC3 hash
+    #[clap(long, default_value_t = 0, requires = "nsec3")]
+    iterations: u16,
+
+    /// The salt of the NSEC3 hash, in hex, or `-` for none
+    #[clap(long, default_value = "-", requires = "nsec3")]
+    salt: String,
+
+    /// Leave the insecure delegations out of the NSEC3 chain
+    #[clap(long, requires = "nsec3")]
+    opt_out: bool,
+
+    /// The validity of new signatures, in days
+    #[clap(long, default_value_t = 30)]
+    validity: u32,
+
+    /// Replace the existing signatures which expire within this many days, defaults to a quarter
+    /// of the validity
+    #[clap(long)]
+    refresh: Option<u32>,
+
+    /// Replace all existing signatures
+    #[clap(long)]
+    resign_all: bool,
+
+    /// Increment the serial of the SOA record
+    #[clap(long)]
+    increment_serial: bool,
+
+    /// The signed zone file, defaults to the zone file with the `.signed` extension
+    #[clap(short = 'o', long)]
+    output: Option<PathBuf>,
+
+    /// The file of the DS records, defaults to `dsset-<origin>` in the directory of the output
+    #[clap(long)]
+    ds_file: Option<PathBuf>,
+
+    /// The digest of the DS records
+    #[clap(long, value_enum, default_value = "sha256")]
+    digest: Digest,
+}
+
+pub(crate) fn run(opt: SignZoneOpt) -> Result<(), Box<dyn Error>> {
+    let mut origin = opt.origin.clone();
+    origin.set_fqdn(true);
+    let algorithm = Algorithm::from(opt.algorithm);
+
+    let mut keys = Vec::new();
+    for (paths, is_key_signing_key) in [(&opt.key_signing_keys, true), (&opt.keys, false)] {
+        for path in paths {
+            keys.push(read_key(
+                &opt,
+                &origin,
+                path,
+                algorithm,
+                is_key_signing_key,
+            )?);
+        }
+    }
+    if opt.generate {
+        for is_key_signing_key in [true, false] {
+            let path = keygen::generate(
+                &origin,
+                algorithm,
+                None,
+                is_key_signing_key,
+                &opt.key_directory,
+                PrivateKeyFormat::Bind,
+            )?;
+            println!("{}", path.display());
+            keys.push(read_key(
+                &opt,
+                &origin,
+                &path,
+                algorithm,
+                is_key_signing_key,
+            )?);
+        }
+    }
+    if keys.is_empty() {
+        return Err("no keys, use --key, --ksk or --generate".into());
+    }
+
+    let options = sign_options(&opt)?;
+    let signed = sign_zone(&origin, &opt.zone_file, &keys, &options)?;
+
+    let output = opt
+        .output
+        .clone()
+        .unwrap_or_else(|| opt.zone_file.with_extension("signed"));
+    fs::write(&output, signed.to_zone_file())
+        .map_err(|e| format!("failed to write {}: {e}", output.display()))?;
+
+    // the DS records of the key signing keys, or of all keys if there are none
+    let ttl = signed
+        .records
+        .get(&RrKey::new(origin.clone().into(), RecordType::DNSKEY))
+        .map_or(3600, |set| set.ttl());
+    let parent_keys = match keys.iter().any(ZoneKey::is_key_signing_key) {
+        true => keys.iter().filter(|key| key.is_key_signing_key()).collect(),
+        false => keys.iter().collect::<Vec<_>>(),
+    };
+    let mut ds_set = String::new();
+    for key in parent_keys {
+        let ds = key.to_ds(&origin, opt.digest.into())?;
+        let record = Record::from_rdata(origin.clone(), ttl, RData::DNSSEC(DNSSECRData::DS(ds)));
+        writeln!(ds_set, "{record}")?;
+    }
+    let ds_file = opt.ds_file.clone().unwrap_or_else(|| {
+        output
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(format!("dsset-{origin}"))
+    });
+    fs::write(&ds_file, ds_set)
+        .map_err(|e| format!("failed to write {}: {e}", ds_file.display()))?;
+
+    println!("{}", output.display());
+    println!("{}", ds_file.display());
+    println!(
+        "zone {origin}: {} new signatures, {} kept, {} dropped",
+        signed.signed, signed.kept, signed.dropped
+    );
+    Ok(())
+}
+
+fn sign_options(opt: &SignZoneOpt) -> Result<SignOptions, Box<dyn Error>> {
+    let denial = if opt.nsec3 {
+        let salt = match opt.salt.as_str() {
+            "-" => Vec::new(),
+            salt => HEXLOWER_PERMISSIVE
+                .decode(salt.as_bytes())
+                .map_err(|e| format!("bad salt {salt}: {e}"))?,
+        };
+        Denial::Nsec3 {
+            iterations: opt.iterations,
+            salt,
+            opt_out: opt.opt_out,
+        }
+    } else {
+        Denial::Nsec
+    };
+
+    let validity = opt.validity.saturating_mul(86_400);
+    let refresh = opt
+        .refresh
+        .map_or(validity / 4, |refresh| refresh.saturating_mul(86_400));
+    if refresh >= validity {
+        return Err("the refresh period must be shorter than the validity".into());
+    }
+
+    Ok(SignOptions {
+        denial,
+        validity,
+        refresh,
+        resign_all: opt.resign_all,
+        increment_serial: opt.increment_serial,
+        ..SignOptions::default()
+    })
+}
+
+fn read_key(
+    opt: &SignZoneOpt,
+    origin: &Name,
+    path: &Path,
+    algorithm: Algorithm,
+    is_key_signing_key: bool,
+) -> Result<ZoneKey, Box<dyn Error>> {
+    let algorithm = match path.extension() {
+        Some(extension) if extension == "private" => fs::read_to_string(path)
+            .map_err(|e| format!("failed to read {}: {e}", path.display()))?
+            .parse::<BindPrivateKey>()
+            .map_err(|e| format!("bad key {}: {e}", path.display()))?
+            .algorithm(),
+        _ => algorithm,
+    };
+    let config = KeyConfig::new(
+        path.to_string_lossy().into_owned(),
+        opt.password.clone(),
+        algorithm,
+        origin.to_string(),
+        true,
+        false,
+    );
+    let key = config.read_key()?;
+    let key = ZoneKey::new(key, algorithm, origin.clone(), is_key_signing_key)
+        .map_err(|e| format!("bad key {}: {e}", path.display()))?;
+
+    info!(
+        "{} {} with the key tag {}",
+        if is_key_signing_key { "KSK" } else { "ZSK" },
+        path.display(),
+        key.key_tag()
+    );
+    Ok(key)
+}
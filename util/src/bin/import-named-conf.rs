@@ -0,0 +1,80 @@
+// Copyright 2015-2023 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// https://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// https://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! The import-named-conf program
+
+// BINARY WARNINGS
+#![warn(
+    clippy::default_trait_access,
+    clippy::dbg_macro,
+    clippy::unimplemented,
+    missing_copy_implementations,
+    missing_docs,
+    non_snake_case,
+    non_upper_case_globals,
+    rust_2018_idioms,
+    unreachable_pub
+)]
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Parser;
+
+use hickory_server::config::named_conf;
+
+/// Converts a BIND named.conf to the configuration of the Hickory DNS server
+///
+/// The options, ACLs and zones which can be converted are written as TOML, the directives
+/// which are not converted, or only in part, are printed as warnings.
+#[derive(Debug, Parser)]
+#[clap(name = "import-named-conf", version)]
+struct Opts {
+    /// The named.conf, includes are relative to its directory
+    named_conf: PathBuf,
+
+    /// Write the configuration to this file instead of stdout
+    #[clap(short = 'o', long)]
+    output: Option<PathBuf>,
+
+    /// Fail if any directive is not converted
+    #[clap(long)]
+    strict: bool,
+}
+
+/// Run the import-named-conf program
+pub fn main() -> ExitCode {
+    let opts = Opts::parse();
+
+    let conversion = match named_conf::convert_file(&opts.named_conf) {
+        Ok(conversion) => conversion,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    for warning in &conversion.warnings {
+        eprintln!("warning: {warning}");
+    }
+
+    match &opts.output {
+        Some(output) => {
+            if let Err(e) = fs::write(output, &conversion.toml) {
+                eprintln!("error: failed to write {}: {e}", output.display());
+                return ExitCode::FAILURE;
+            }
+        }
+        None => print!("{}", conversion.toml),
+    }
+
+    match (opts.strict, conversion.warnings.len()) {
+        (true, 1..) => ExitCode::FAILURE,
+        _ => ExitCode::SUCCESS,
+    }
+}
@@ -32,7 +32,7 @@ use console::style;
 use hickory_client::op::Query;
 use hickory_recursor::Recursor;
 use hickory_resolver::{
-    config::{NameServerConfig, NameServerConfigGroup, Protocol},
+    config::{NameServerConfig, NameServerConfigGroup, PrivacyPolicy, Protocol},
     proto::rr::RecordType,
     Name,
 };
@@ -126,9 +126,13 @@ pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
             protocol: Protocol::Tcp,
             tls_dns_name: None,
             trust_negative_responses: false,
+            privacy: PrivacyPolicy::default(),
+            weight: 1,
             #[cfg(feature = "dns-over-rustls")]
             tls_config: None,
             bind_addr: opts.bind.map(|ip| SocketAddr::new(ip, 0)),
+            bind_device: None,
+            http: None,
         });
 
         roots.push(NameServerConfig {
@@ -136,9 +140,13 @@ pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
             protocol: Protocol::Udp,
             tls_dns_name: None,
             trust_negative_responses: false,
+            privacy: PrivacyPolicy::default(),
+            weight: 1,
             #[cfg(feature = "dns-over-rustls")]
             tls_config: None,
             bind_addr: opts.bind.map(|ip| SocketAddr::new(ip, 0)),
+            bind_device: None,
+            http: None,
         });
     }
 
@@ -37,7 +37,10 @@ use tokio::task::JoinSet;
 
 use hickory_client::rr::{Record, RecordData};
 use hickory_resolver::{
-    config::{NameServerConfig, NameServerConfigGroup, Protocol, ResolverConfig, ResolverOpts},
+    config::{
+        NameServerConfig, NameServerConfigGroup, PrivacyPolicy, Protocol, ResolverConfig,
+        ResolverOpts,
+    },
     error::ResolveError,
     lookup::Lookup,
     proto::rr::RecordType,
@@ -280,9 +283,13 @@ pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
             protocol: Protocol::Tcp,
             tls_dns_name: None,
             trust_negative_responses: false,
+            privacy: PrivacyPolicy::default(),
+            weight: 1,
             #[cfg(feature = "dns-over-rustls")]
             tls_config: None,
             bind_addr: opts.bind.map(|ip| SocketAddr::new(ip, 0)),
+            bind_device: None,
+            http: None,
         });
 
         name_servers.push(NameServerConfig {
@@ -290,9 +297,13 @@ pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
             protocol: Protocol::Udp,
             tls_dns_name: None,
             trust_negative_responses: false,
+            privacy: PrivacyPolicy::default(),
+            weight: 1,
             #[cfg(feature = "dns-over-rustls")]
             tls_config: None,
             bind_addr: opts.bind.map(|ip| SocketAddr::new(ip, 0)),
+            bind_device: None,
+            http: None,
         });
     }
 